@@ -0,0 +1,261 @@
+//! AHN client for the ground level of peilgebieden.
+//!
+//! The terrain model (DTM) of the AHN is fetched as a GeoTIFF from the PDOK
+//! WCS for the bounding box of a peilgebied in RD New. The median of the
+//! cells within the peilgebied is stored as its `maaiveld`, which fills in
+//! drooglegging calculations and the ground level line in charts. Large
+//! peilgebieden are fetched at a coarser resolution than the AHN, which
+//! barely affects the median.
+
+use serde::Serialize;
+use std::io::Cursor;
+use std::sync::Arc;
+use thiserror::Error;
+use tiff::decoder::{Decoder, DecodingResult};
+use tracing::{info, warn};
+
+use peilbeheer_core::maaiveld::{self, Hoogteraster};
+use peilbeheer_core::projectie::{self, Crs};
+
+use crate::db::Database;
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Resolution of the AHN terrain model (m).
+const AHN_CELGROOTTE: f64 = 0.5;
+
+/// AHN WCS configuration.
+#[derive(Debug, Clone)]
+pub struct AhnConfig {
+    pub wcs_url: String,
+    /// Coverage of the terrain model, e.g. `dtm_05m`
+    pub coverage: String,
+    /// Largest number of cells along either axis of a request
+    pub max_cellen: usize,
+}
+
+impl Default for AhnConfig {
+    fn default() -> Self {
+        Self {
+            wcs_url: std::env::var("AHN_WCS_URL")
+                .unwrap_or_else(|_| "https://service.pdok.nl/rws/ahn/wcs/v1_0".to_string()),
+            coverage: std::env::var("AHN_COVERAGE").unwrap_or_else(|_| "dtm_05m".to_string()),
+            max_cellen: std::env::var("AHN_MAX_CELLEN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(1000),
+        }
+    }
+}
+
+/// Errors of the AHN client.
+#[derive(Debug, Error)]
+pub enum AhnError {
+    #[error("Peilgebied {0} not found")]
+    PeilgebiedNotFound(String),
+    #[error("Peilgebied {0} has no ground level cells in the AHN")]
+    GeenHoogte(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("AHN returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+    #[error("Database error: {0}")]
+    Database(#[from] anyhow::Error),
+}
+
+/// Ground level determined for a peilgebied.
+#[derive(Debug, Clone, Serialize)]
+pub struct Maaiveld {
+    pub peilgebied_code: String,
+    /// Median ground level in m NAP
+    pub maaiveld: f64,
+    /// Cells with a height within the peilgebied
+    pub cellen: usize,
+    /// Cell size of the request (m)
+    pub celgrootte: f64,
+    /// Source, e.g. `ahn:dtm_05m`
+    pub bron: String,
+}
+
+/// Result of determining the ground level of several peilgebieden.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AhnSyncResult {
+    pub peilgebieden: usize,
+    pub bijgewerkt: usize,
+    pub errors: Vec<String>,
+}
+
+/// Client determining and storing the ground level of peilgebieden.
+pub struct AhnClient {
+    config: AhnConfig,
+    db: Arc<Database>,
+    http_client: HttpClient,
+}
+
+impl AhnClient {
+    pub fn new(config: AhnConfig, db: Arc<Database>) -> Self {
+        Self {
+            config,
+            db,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    /// Determine the ground level of a peilgebied from the AHN and store it.
+    pub async fn bepaal(&self, peilgebied: &str) -> Result<Maaiveld, AhnError> {
+        let geojson = self
+            .db
+            .get_peilgebied_geometrie(peilgebied)?
+            .ok_or_else(|| AhnError::PeilgebiedNotFound(peilgebied.to_string()))?;
+        let mut geometrie: serde_json::Value =
+            serde_json::from_str(&geojson).map_err(|e| AhnError::Parse(e.to_string()))?;
+        projectie::herprojecteer_van(&mut geometrie, Crs::Wgs84, Crs::RdNew)
+            .map_err(|e| AhnError::Parse(e.to_string()))?;
+        let ringen = maaiveld::ringen_uit_geojson(&geometrie);
+        let (min_x, min_y, max_x, max_y) = maaiveld::omhullende(&ringen)
+            .ok_or_else(|| AhnError::GeenHoogte(peilgebied.to_string()))?;
+
+        let raster = self.hoogteraster(min_x, min_y, max_x, max_y).await?;
+        let statistiek = raster
+            .mediaan_binnen(&ringen)
+            .ok_or_else(|| AhnError::GeenHoogte(peilgebied.to_string()))?;
+
+        let maaiveld = Maaiveld {
+            peilgebied_code: peilgebied.to_string(),
+            maaiveld: (statistiek.mediaan * 100.0).round() / 100.0,
+            cellen: statistiek.cellen,
+            celgrootte: raster.celgrootte,
+            bron: format!("ahn:{}", self.config.coverage),
+        };
+        self.db
+            .set_peilgebied_maaiveld(peilgebied, maaiveld.maaiveld, &maaiveld.bron)?;
+        info!(
+            "Maaiveld van peilgebied {}: {:.2} m NAP ({} cellen van {} m)",
+            peilgebied, maaiveld.maaiveld, maaiveld.cellen, maaiveld.celgrootte
+        );
+        Ok(maaiveld)
+    }
+
+    /// Determine the ground level of all peilgebieden, or only of those
+    /// without one.
+    pub async fn bepaal_alle(&self, alleen_ontbrekend: bool) -> Result<AhnSyncResult, AhnError> {
+        let codes = self.db.get_peilgebied_codes(alleen_ontbrekend)?;
+        let mut result = AhnSyncResult {
+            peilgebieden: codes.len(),
+            ..Default::default()
+        };
+        for code in codes {
+            match self.bepaal(&code).await {
+                Ok(_) => result.bijgewerkt += 1,
+                Err(e) => {
+                    warn!("Maaiveld van peilgebied {} niet bepaald: {}", code, e);
+                    result.errors.push(format!("{}: {}", code, e));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Terrain model of a bounding box (RD New, m), aligned to the AHN grid.
+    async fn hoogteraster(
+        &self,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<Hoogteraster, AhnError> {
+        let omvang = (max_x - min_x).max(max_y - min_y);
+        let celgrootte = (omvang / self.config.max_cellen as f64 / AHN_CELGROOTTE)
+            .ceil()
+            .max(1.0)
+            * AHN_CELGROOTTE;
+        let min_x = (min_x / celgrootte).floor() * celgrootte;
+        let min_y = (min_y / celgrootte).floor() * celgrootte;
+        let breedte = ((max_x - min_x) / celgrootte).ceil().max(1.0) as usize;
+        let hoogte = ((max_y - min_y) / celgrootte).ceil().max(1.0) as usize;
+        let max_x = min_x + breedte as f64 * celgrootte;
+        let max_y = min_y + hoogte as f64 * celgrootte;
+
+        let request = self.http_client.get(&self.config.wcs_url).query(&[
+            ("SERVICE", "WCS".to_string()),
+            ("VERSION", "2.0.1".to_string()),
+            ("REQUEST", "GetCoverage".to_string()),
+            ("COVERAGEID", self.config.coverage.clone()),
+            ("FORMAT", "image/tiff".to_string()),
+            ("SUBSET", format!("x({},{})", min_x, max_x)),
+            ("SUBSET", format!("y({},{})", min_y, max_y)),
+            ("SCALESIZE", format!("x({}),y({})", breedte, hoogte)),
+        ]);
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(AhnError::Api { status, message });
+        }
+        let bytes = response.bytes().await.map_err(HttpError::from)?;
+
+        let waarden = geotiff_waarden(&bytes, breedte, hoogte)?;
+        Ok(Hoogteraster {
+            min_x,
+            max_y,
+            celgrootte,
+            breedte,
+            hoogte,
+            waarden,
+        })
+    }
+}
+
+/// Cell values of a single-band GeoTIFF of the requested size.
+fn geotiff_waarden(bytes: &[u8], breedte: usize, hoogte: usize) -> Result<Vec<f32>, AhnError> {
+    let parse = |e: tiff::TiffError| AhnError::Parse(format!("invalid GeoTIFF: {}", e));
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(parse)?;
+    let (b, h) = decoder.dimensions().map_err(parse)?;
+    if (b as usize, h as usize) != (breedte, hoogte) {
+        return Err(AhnError::Parse(format!(
+            "GeoTIFF is {}x{} cells, requested {}x{}",
+            b, h, breedte, hoogte
+        )));
+    }
+    match decoder.read_image().map_err(parse)? {
+        DecodingResult::F32(waarden) => Ok(waarden),
+        DecodingResult::F64(waarden) => Ok(waarden.into_iter().map(|w| w as f32).collect()),
+        _ => Err(AhnError::Parse(
+            "GeoTIFF has no floating point heights".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    #[test]
+    fn test_geotiff_waarden() {
+        let mut bytes = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut bytes)
+            .unwrap()
+            .write_image::<colortype::Gray32Float>(3, 2, &[-1.5, -1.0, -0.5, 0.0, 0.5, f32::MAX])
+            .unwrap();
+        let bytes = bytes.into_inner();
+
+        let waarden = geotiff_waarden(&bytes, 3, 2).unwrap();
+        assert_eq!(waarden.len(), 6);
+        assert_eq!(waarden[0], -1.5);
+
+        assert!(matches!(
+            geotiff_waarden(&bytes, 2, 3),
+            Err(AhnError::Parse(_))
+        ));
+        assert!(matches!(
+            geotiff_waarden(b"no tiff", 3, 2),
+            Err(AhnError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+//! Periodic alert rule evaluation fed by the time series store.
+//!
+//! Every interval the evaluator collects the fields referenced by enabled
+//! rules, loads the matching series per location from [`TimeSeriesService`]
+//! and evaluates the rules with one [`EvaluationContext`] per location, so
+//! rules with a resource selector produce a separate alert per resource.
+//! The catalog's latest timestamp and expected delivery interval of each
+//! series are passed along for `no_data` (heartbeat) conditions.
+//! Run durations and outcomes are kept as [`EvaluatorMetrics`].
+
+use anyhow::Result as AnyhowResult;
+use chrono::{Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+use tokio::sync::RwLock;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::{
+    alert::{
+        AlertRule, AlertValue, EvaluationContext, EvaluatorMetrics, SeriesHeartbeat,
+        TimeSeriesValue,
+    },
+    timeseries::{TimeSeriesCatalogEntry, TimeSeriesId, TimeSeriesQuery},
+};
+
+use crate::alert_service::AlertService;
+use crate::timeseries_service::TimeSeriesService;
+
+/// Evaluator configuration.
+#[derive(Debug, Clone)]
+pub struct AlertEvaluatorConfig {
+    /// Whether the periodic loop runs
+    pub enabled: bool,
+    /// Time between runs (seconds)
+    pub interval_secs: u64,
+    /// History loaded for conditions without a time window (minutes)
+    pub default_lookback_minutes: i64,
+    /// Maximum number of catalog entries considered per run
+    pub max_series: usize,
+}
+
+impl Default for AlertEvaluatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: std::env::var("ALERT_EVALUATION_ENABLED")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            interval_secs: std::env::var("ALERT_EVALUATION_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            default_lookback_minutes: 60,
+            max_series: 10_000,
+        }
+    }
+}
+
+/// Periodic rule evaluator.
+pub struct AlertEvaluator {
+    alert_service: Arc<AlertService>,
+    timeseries_service: Arc<TimeSeriesService>,
+    config: AlertEvaluatorConfig,
+    metrics: Arc<RwLock<EvaluatorMetrics>>,
+}
+
+impl AlertEvaluator {
+    /// Create a new evaluator.
+    pub fn new(
+        alert_service: Arc<AlertService>,
+        timeseries_service: Arc<TimeSeriesService>,
+        config: AlertEvaluatorConfig,
+    ) -> Self {
+        Self {
+            alert_service,
+            timeseries_service,
+            config,
+            metrics: Arc::new(RwLock::new(EvaluatorMetrics::default())),
+        }
+    }
+
+    /// Start the background evaluation loop (no-op when disabled).
+    pub fn start(self: &Arc<Self>) {
+        if !self.config.enabled {
+            info!("Alert evaluator disabled");
+            return;
+        }
+
+        let evaluator = Arc::clone(self);
+        let interval = StdDuration::from_secs(self.config.interval_secs.max(1));
+
+        tokio::spawn(async move {
+            info!("Alert evaluator started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = evaluator.run_once().await {
+                    warn!("Alert evaluation run failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Current evaluator metrics.
+    pub async fn metrics(&self) -> EvaluatorMetrics {
+        self.metrics.read().await.clone()
+    }
+
+    /// Evaluate all enabled rules against the latest time series data.
+    pub async fn run_once(&self) -> AnyhowResult<EvaluatorMetrics> {
+        let started_at = Utc::now();
+        let timer = Instant::now();
+
+        let outcome = self.evaluate().await;
+
+        let duration_ms = timer.elapsed().as_secs_f64() * 1000.0;
+        let mut metrics = self.metrics.write().await;
+        match &outcome {
+            Ok(run) => {
+                metrics.record_run(
+                    started_at,
+                    duration_ms,
+                    run.contexts,
+                    run.series_loaded,
+                    run.alerts_triggered,
+                    run.last_error.clone(),
+                );
+                debug!(
+                    "Alert evaluation: {} contexts, {} series, {} alerts in {:.1} ms",
+                    run.contexts, run.series_loaded, run.alerts_triggered, duration_ms
+                );
+            }
+            Err(e) => {
+                metrics.record_run(started_at, duration_ms, 0, 0, 0, Some(e.to_string()));
+            }
+        }
+
+        outcome.map(|_| metrics.clone())
+    }
+
+    /// Build contexts per location and evaluate them.
+    async fn evaluate(&self) -> AnyhowResult<RunOutcome> {
+        let mut outcome = RunOutcome::default();
+
+        let rules: Vec<AlertRule> = self.alert_service.list_rules().await?
+            .into_iter()
+            .filter(|r| r.enabled)
+            .collect();
+        let lookbacks = field_lookbacks(&rules, Duration::minutes(self.config.default_lookback_minutes));
+        if lookbacks.is_empty() {
+            return Ok(outcome);
+        }
+
+        // Group the relevant series per location
+        let catalog = self.timeseries_service
+            .list_series(None, Some(self.config.max_series))
+            .await?;
+        let mut locations: BTreeMap<String, Vec<TimeSeriesCatalogEntry>> = BTreeMap::new();
+        for entry in catalog {
+            if lookbacks.contains_key(&field_name(&entry.id)) {
+                locations.entry(entry.id.location_id.clone()).or_default().push(entry);
+            }
+        }
+
+        // Resource kinds are only needed to match rule resource selectors
+        let kinds = if rules.iter().any(|r| r.resource_selector.is_some()) {
+            self.alert_service.resource_kinds()
+        } else {
+            HashMap::new()
+        };
+        let now = Utc::now();
+        for (location_id, entries) in locations {
+            let mut context = EvaluationContext {
+                now,
+                values: HashMap::new(),
+                time_series: HashMap::new(),
+                source: Some(location_id.clone()),
+                source_kind: kinds.get(&location_id).cloned(),
+                heartbeats: HashMap::new(),
+            };
+
+            for entry in entries {
+                let field = field_name(&entry.id);
+                context.heartbeats.insert(
+                    field.clone(),
+                    SeriesHeartbeat {
+                        last_timestamp: entry.last_timestamp,
+                        expected_interval_seconds: entry.expected_interval_seconds,
+                    },
+                );
+                let lookback = lookbacks[&field];
+                // Query end is exclusive, include a point stamped exactly `now`
+                let query = TimeSeriesQuery::new(entry.id, now - lookback, now + Duration::seconds(1));
+
+                let series = match self.timeseries_service.query(&query).await {
+                    Ok(series) => series,
+                    Err(e) => {
+                        warn!("Failed to load {} for {}: {}", field, location_id, e);
+                        outcome.last_error = Some(e.to_string());
+                        continue;
+                    }
+                };
+                outcome.series_loaded += 1;
+
+                let points: Vec<TimeSeriesValue> = series.data
+                    .iter()
+                    .filter(|p| p.is_valid())
+                    .map(|p| TimeSeriesValue {
+                        timestamp: p.timestamp,
+                        value: p.value,
+                        flag: Some(p.flag.as_str().to_string()),
+                    })
+                    .collect();
+
+                if let Some(latest) = points.last() {
+                    context.values.insert(field.clone(), AlertValue::Number(latest.value));
+                }
+                context.time_series.insert(field, points);
+            }
+
+            outcome.contexts += 1;
+            match self.alert_service.evaluate_rules(&context).await {
+                Ok(alerts) => outcome.alerts_triggered += alerts.len(),
+                Err(e) => {
+                    warn!("Rule evaluation failed for {}: {}", location_id, e);
+                    outcome.last_error = Some(e.to_string());
+                }
+            }
+        }
+
+        if outcome.alerts_triggered > 0 {
+            info!("Alert evaluation triggered {} alert(s)", outcome.alerts_triggered);
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// Counters of a single evaluation run.
+#[derive(Debug, Default)]
+struct RunOutcome {
+    contexts: usize,
+    series_loaded: usize,
+    alerts_triggered: usize,
+    last_error: Option<String>,
+}
+
+/// Condition field name for a series: the parameter, suffixed with the
+/// qualifier when present (e.g. `water_level:inlet`).
+fn field_name(id: &TimeSeriesId) -> String {
+    match &id.qualifier {
+        Some(qualifier) => format!("{}:{}", id.parameter, qualifier),
+        None => id.parameter.clone(),
+    }
+}
+
+/// History to load per field: the longest time window of any condition on
+/// that field, or `default` when none of them has a window.
+fn field_lookbacks(rules: &[AlertRule], default: Duration) -> HashMap<String, Duration> {
+    let mut lookbacks: HashMap<String, Duration> = HashMap::new();
+    for condition in rules.iter().flat_map(|r| &r.conditions) {
+        let window = condition.window_duration().unwrap_or(default);
+        lookbacks
+            .entry(condition.field.clone())
+            .and_modify(|current| *current = (*current).max(window))
+            .or_insert(window);
+    }
+    lookbacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peilbeheer_core::alert::{
+        AlertCategory, AlertCondition, AlertSeverity, ComparisonOperator,
+    };
+
+    fn condition(field: &str, window: Option<&str>) -> AlertCondition {
+        AlertCondition {
+            field: field.to_string(),
+            operator: ComparisonOperator::Gt,
+            value: AlertValue::Number(0.0),
+            clear_value: None,
+            source_filter: None,
+            time_window: window.map(str::to_string),
+            aggregation: None,
+        }
+    }
+
+    #[test]
+    fn test_field_lookbacks() {
+        let rules = vec![
+            AlertRule::new(
+                "RULE_1",
+                "Hoog peil",
+                AlertCategory::WaterLevel,
+                AlertSeverity::Warning,
+                vec![condition("water_level", Some("6h")), condition("debiet", None)],
+            ),
+            AlertRule::new(
+                "RULE_2",
+                "Snelle stijging",
+                AlertCategory::WaterLevel,
+                AlertSeverity::Critical,
+                vec![condition("water_level", Some("30m"))],
+            ),
+        ];
+
+        let lookbacks = field_lookbacks(&rules, Duration::hours(1));
+        assert_eq!(lookbacks.len(), 2);
+        assert_eq!(lookbacks["water_level"], Duration::hours(6));
+        assert_eq!(lookbacks["debiet"], Duration::hours(1));
+    }
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(field_name(&TimeSeriesId::new("GEMAAL_001", "water_level")), "water_level");
+        assert_eq!(
+            field_name(&TimeSeriesId::with_qualifier("GEMAAL_001", "water_level", "inlet")),
+            "water_level:inlet"
+        );
+    }
+}
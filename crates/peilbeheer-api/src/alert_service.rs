@@ -3,23 +3,33 @@
 //! This service provides:
 //! - CRUD operations for alert rules
 //! - Rule evaluation engine with context data
-//! - Alert persistence and history
+//! - Alert persistence, comments and status history
 //! - Alert acknowledgment and resolution
-//! - Notification delivery via WebSocket
+//! - Notification delivery via WebSocket, webhooks and e-mail
+//! - Escalation of unacknowledged alerts
+//! - Maintenance windows that suppress notifications
+//! - Automatic resolution once conditions no longer hold
+//! - Hysteresis and damping of flapping rules
 
 use anyhow::Result as AnyhowResult;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration as StdDuration;
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
 
 use peilbeheer_core::{
     alert::{AlertRule, RuleId as AlertRuleId, *},
+    alert_report::{AlertTimeline, AlertTrendReport, StatsBucket},
+    maintenance::*,
     websocket::{AlertSeverity as WsAlertSeverity, WsMessage},
 };
 
-use crate::db::Database;
+use crate::db::{Database, is_no_rows};
+use crate::mailer::{EmailMessage, LogMailer, Mailer};
+use crate::webhook_client::{WebhookClient, WebhookConfig, WebhookDelivery, WebhookPayload};
 use crate::websocket_service::WebSocketServer;
 
 /// Alert service error types.
@@ -40,16 +50,39 @@ pub enum AlertServiceError {
 
     #[error("Evaluation error: {0}")]
     EvaluationError(String),
+
+    #[error("Maintenance window not found: {0}")]
+    MaintenanceWindowNotFound(String),
+
+    #[error("Invalid maintenance window: {0}")]
+    InvalidMaintenanceWindow(String),
+
+    #[error("Invalid comment: {0}")]
+    InvalidComment(String),
+
+    #[error("Invalid alert state: {0}")]
+    InvalidState(String),
 }
 
+/// Rule and evaluation source that per-source evaluation state is tracked for.
+type StreakKey = (AlertRuleId, Option<String>);
+
 /// Alert engine service.
 pub struct AlertService {
     db: Arc<Database>,
     ws_server: Arc<WebSocketServer>,
+    webhook_client: Arc<WebhookClient>,
+    mailer: Arc<dyn Mailer>,
     /// In-memory cache of active rules
     rules: Arc<RwLock<HashMap<AlertRuleId, AlertRule>>>,
-    /// Track last trigger time for cooldown
-    last_triggers: Arc<RwLock<HashMap<AlertRuleId, DateTime<Utc>>>>,
+    /// Track last trigger time per rule and source for cooldown
+    last_triggers: Arc<RwLock<HashMap<StreakKey, DateTime<Utc>>>>,
+    /// In-memory cache of maintenance windows
+    maintenance_windows: Arc<RwLock<HashMap<MaintenanceWindowId, MaintenanceWindow>>>,
+    /// Consecutive non-matching evaluations per rule and source (for auto-resolve)
+    clear_streaks: Arc<RwLock<HashMap<StreakKey, u32>>>,
+    /// Hysteresis and flapping state per rule and source
+    flap_states: Arc<RwLock<HashMap<StreakKey, FlapState>>>,
 }
 
 impl AlertService {
@@ -58,11 +91,22 @@ impl AlertService {
         Self {
             db,
             ws_server,
+            webhook_client: Arc::new(WebhookClient::new(WebhookConfig::default())),
+            mailer: Arc::new(LogMailer),
             rules: Arc::new(RwLock::new(HashMap::new())),
             last_triggers: Arc::new(RwLock::new(HashMap::new())),
+            maintenance_windows: Arc::new(RwLock::new(HashMap::new())),
+            clear_streaks: Arc::new(RwLock::new(HashMap::new())),
+            flap_states: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Set the mailer of e-mail notifications (default: log only).
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = mailer;
+        self
+    }
+
     /// Initialize the service by loading rules from database.
     pub async fn initialize(&self) -> AnyhowResult<()> {
         info!("Initializing Alert Service...");
@@ -75,7 +119,19 @@ impl AlertService {
             rules_cache.insert(rule.id.clone(), rule);
         }
 
-        info!("Alert Service initialized with {} rules", rules_cache.len());
+        // Load maintenance windows from database
+        let windows = self.load_maintenance_windows_from_db()?;
+        let mut windows_cache = self.maintenance_windows.write().await;
+        windows_cache.clear();
+        for window in windows {
+            windows_cache.insert(window.id.clone(), window);
+        }
+
+        info!(
+            "Alert Service initialized with {} rules and {} maintenance windows",
+            rules_cache.len(),
+            windows_cache.len()
+        );
         Ok(())
     }
 
@@ -84,7 +140,8 @@ impl AlertService {
         let rows = self.db.query(
             "SELECT id, name, description, category, severity, conditions, condition_logic,
                     cooldown_seconds, enabled, notification_channels, title_template, message_template,
-                    metadata, created_at, updated_at, created_by
+                    metadata, CAST(created_at AS VARCHAR), CAST(updated_at AS VARCHAR), created_by,
+                    escalation_policy, auto_resolve_after, flapping_policy, resource_selector
              FROM alert_rules
              ORDER BY created_at DESC",
             &[],
@@ -106,6 +163,10 @@ impl AlertService {
                     row.get::<_, String>(13)?,
                     row.get::<_, Option<String>>(14)?,
                     row.get::<_, Option<String>>(15)?,
+                    row.get::<_, Option<String>>(16)?,
+                    row.get::<_, Option<u32>>(17)?,
+                    row.get::<_, Option<String>>(18)?,
+                    row.get::<_, Option<String>>(19)?,
                 ))
             },
         )?;
@@ -116,7 +177,8 @@ impl AlertService {
                 id, name, description, category_str, severity_str, conditions_json,
                 condition_logic_str, cooldown_seconds, enabled, channels_json,
                 title_template, message_template, metadata_json,
-                created_at_str, updated_at_str, created_by,
+                created_at_str, updated_at_str, created_by, escalation_json,
+                auto_resolve_after, flapping_json, selector_json,
             ) = row;
 
             let category = parse_category(&category_str);
@@ -127,6 +189,12 @@ impl AlertService {
             let condition_logic = parse_condition_logic(&condition_logic_str);
             let notification_channels: Vec<NotificationChannel> = serde_json::from_str(&channels_json)
                 .unwrap_or_else(|_| vec![NotificationChannel::WebSocket]);
+            let escalation_policy: Option<EscalationPolicy> = escalation_json
+                .and_then(|j| serde_json::from_str(&j).ok());
+            let flapping_policy: Option<FlappingPolicy> = flapping_json
+                .and_then(|j| serde_json::from_str(&j).ok());
+            let resource_selector: Option<ResourceSelector> = selector_json
+                .and_then(|j| serde_json::from_str(&j).ok());
             let metadata: HashMap<String, serde_json::Value> = metadata_json
                 .and_then(|j| serde_json::from_str(&j).ok())
                 .unwrap_or_default();
@@ -142,11 +210,15 @@ impl AlertService {
                 description,
                 category,
                 severity,
+                resource_selector,
                 conditions,
                 condition_logic,
                 cooldown_seconds,
                 enabled,
                 notification_channels,
+                escalation_policy,
+                auto_resolve_after,
+                flapping_policy,
                 title_template,
                 message_template,
                 metadata,
@@ -174,11 +246,15 @@ impl AlertService {
             description: request.description,
             category: request.category,
             severity: request.severity,
+            resource_selector: request.resource_selector,
             conditions: request.conditions.clone(),
             condition_logic: request.condition_logic,
             cooldown_seconds: request.cooldown_seconds,
             enabled: true,
             notification_channels: request.notification_channels,
+            escalation_policy: request.escalation_policy,
+            auto_resolve_after: request.auto_resolve_after,
+            flapping_policy: request.flapping_policy,
             title_template: request.title_template,
             message_template: request.message_template,
             metadata: request.metadata.unwrap_or_default(),
@@ -195,6 +271,15 @@ impl AlertService {
         // Serialize complex fields
         let conditions_json = serde_json::to_string(&rule.conditions)?;
         let channels_json = serde_json::to_string(&rule.notification_channels)?;
+        let escalation_json = rule.escalation_policy.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let flapping_json = rule.flapping_policy.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let selector_json = rule.resource_selector.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
         let metadata_json = if rule.metadata.is_empty() {
             None
         } else {
@@ -205,8 +290,9 @@ impl AlertService {
         self.db.execute(
             "INSERT INTO alert_rules (id, name, description, category, severity, conditions,
                                    condition_logic, cooldown_seconds, enabled, notification_channels,
-                                   title_template, message_template, metadata, created_at, updated_at, created_by)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                   title_template, message_template, metadata, created_at, updated_at, created_by,
+                                   escalation_policy, auto_resolve_after, flapping_policy, resource_selector)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             &[
                 &rule.id as &dyn duckdb::ToSql,
                 &rule.name,
@@ -224,6 +310,10 @@ impl AlertService {
                 &format_datetime(now),
                 &format_datetime(now),
                 &creator_id as &dyn duckdb::ToSql,
+                &escalation_json,
+                &rule.auto_resolve_after,
+                &flapping_json,
+                &selector_json,
             ],
         )?;
 
@@ -273,6 +363,10 @@ impl AlertService {
         if let Some(severity) = request.severity {
             rule.severity = severity;
         }
+        if let Some(selector) = request.resource_selector {
+            // A selector without patterns makes the rule apply to every source
+            rule.resource_selector = (!selector.patterns().is_empty()).then_some(selector);
+        }
         if let Some(conditions) = request.conditions {
             rule.conditions = conditions;
         }
@@ -288,6 +382,18 @@ impl AlertService {
         if let Some(channels) = request.notification_channels {
             rule.notification_channels = channels;
         }
+        if let Some(policy) = request.escalation_policy {
+            // A policy without steps removes escalation from the rule
+            rule.escalation_policy = (!policy.steps.is_empty()).then_some(policy);
+        }
+        if let Some(after) = request.auto_resolve_after {
+            // 0 disables auto-resolve
+            rule.auto_resolve_after = (after > 0).then_some(after);
+        }
+        if let Some(policy) = request.flapping_policy {
+            // max_transitions 0 disables flapping detection
+            rule.flapping_policy = (policy.max_transitions > 0).then_some(policy);
+        }
         if let Some(template) = request.title_template {
             rule.title_template = template;
         }
@@ -308,6 +414,15 @@ impl AlertService {
         // Update database
         let conditions_json = serde_json::to_string(&rule.conditions)?;
         let channels_json = serde_json::to_string(&rule.notification_channels)?;
+        let escalation_json = rule.escalation_policy.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let flapping_json = rule.flapping_policy.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let selector_json = rule.resource_selector.as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
         let metadata_json = if rule.metadata.is_empty() {
             None
         } else {
@@ -318,7 +433,9 @@ impl AlertService {
             "UPDATE alert_rules
              SET name = ?, description = ?, category = ?, severity = ?, conditions = ?,
                  condition_logic = ?, cooldown_seconds = ?, enabled = ?, notification_channels = ?,
-                 title_template = ?, message_template = ?, metadata = ?, updated_at = ?
+                 escalation_policy = ?, auto_resolve_after = ?, flapping_policy = ?,
+                 resource_selector = ?, title_template = ?,
+                 message_template = ?, metadata = ?, updated_at = ?
              WHERE id = ?",
             &[
                 &rule.name as &dyn duckdb::ToSql,
@@ -330,6 +447,10 @@ impl AlertService {
                 &rule.cooldown_seconds,
                 &rule.enabled,
                 &channels_json,
+                &escalation_json,
+                &rule.auto_resolve_after,
+                &flapping_json,
+                &selector_json,
                 &rule.title_template,
                 &rule.message_template,
                 &metadata_json,
@@ -352,6 +473,17 @@ impl AlertService {
             return Err(AlertServiceError::RuleNotFound(id.to_string()).into());
         }
 
+        // The alert tables have no cascading foreign keys (see migration 007a)
+        for table in ["alert_history", "alert_notifications", "alert_comments", "alert_escalations"] {
+            self.db.execute(
+                &format!(
+                    "DELETE FROM {} WHERE alert_id IN (SELECT id FROM alerts WHERE rule_id = ?)",
+                    table
+                ),
+                &[&id as &dyn duckdb::ToSql],
+            )?;
+        }
+        self.db.execute("DELETE FROM alerts WHERE rule_id = ?", &[&id as &dyn duckdb::ToSql])?;
         self.db.execute("DELETE FROM alert_rules WHERE id = ?", &[&id as &dyn duckdb::ToSql])?;
         rules.remove(id);
 
@@ -359,50 +491,512 @@ impl AlertService {
         Ok(())
     }
 
+    /// Load all maintenance windows from database.
+    fn load_maintenance_windows_from_db(&self) -> AnyhowResult<Vec<MaintenanceWindow>> {
+        let rows = self.db.query(
+            "SELECT id, name, description, rule_ids, resources, schedule, enabled,
+                    CAST(created_at AS VARCHAR), CAST(updated_at AS VARCHAR), created_by
+             FROM maintenance_windows
+             ORDER BY created_at DESC",
+            &[],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, bool>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                    row.get::<_, Option<String>>(9)?,
+                ))
+            },
+        )?;
+
+        let mut windows = Vec::new();
+        for row in rows {
+            let (
+                id, name, description, rule_ids_json, resources_json, schedule_json,
+                enabled, created_at_str, updated_at_str, created_by,
+            ) = row;
+
+            let schedule: MaintenanceSchedule = match serde_json::from_str(&schedule_json) {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    warn!("Skipping maintenance window {} with invalid schedule: {}", id, e);
+                    continue;
+                }
+            };
+            let rule_ids: Vec<String> = rule_ids_json
+                .and_then(|j| serde_json::from_str(&j).ok())
+                .unwrap_or_default();
+            let resources: Vec<String> = resources_json
+                .and_then(|j| serde_json::from_str(&j).ok())
+                .unwrap_or_default();
+
+            let created_at = created_at_str
+                .map(|s| parse_datetime(&s))
+                .unwrap_or_else(Utc::now);
+            let updated_at = updated_at_str
+                .map(|s| parse_datetime(&s))
+                .unwrap_or(created_at);
+
+            windows.push(MaintenanceWindow {
+                id,
+                name,
+                description,
+                rule_ids,
+                resources,
+                schedule,
+                enabled,
+                created_at,
+                updated_at,
+                created_by,
+            });
+        }
+
+        Ok(windows)
+    }
+
+    /// Create a new maintenance window.
+    pub async fn create_maintenance_window(
+        &self,
+        request: CreateMaintenanceWindowRequest,
+        creator_id: Option<String>,
+    ) -> AnyhowResult<MaintenanceWindow> {
+        let id = format!("MW_{}", uuid::Uuid::new_v4());
+        let now = Utc::now();
+
+        let window = MaintenanceWindow {
+            id: id.clone(),
+            name: request.name,
+            description: request.description,
+            rule_ids: request.rule_ids,
+            resources: request.resources,
+            schedule: request.schedule,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            created_by: creator_id,
+        };
+
+        if let Err(errors) = window.validate() {
+            return Err(AlertServiceError::InvalidMaintenanceWindow(errors.join("; ")).into());
+        }
+
+        self.db.execute(
+            "INSERT INTO maintenance_windows (id, name, description, rule_ids, resources, schedule,
+                                              enabled, created_at, updated_at, created_by)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            &[
+                &window.id as &dyn duckdb::ToSql,
+                &window.name,
+                &window.description,
+                &serde_json::to_string(&window.rule_ids)?,
+                &serde_json::to_string(&window.resources)?,
+                &serde_json::to_string(&window.schedule)?,
+                &window.enabled,
+                &format_datetime(now),
+                &format_datetime(now),
+                &window.created_by,
+            ],
+        )?;
+
+        let mut windows = self.maintenance_windows.write().await;
+        windows.insert(id.clone(), window.clone());
+
+        info!("Created maintenance window: {}", id);
+        Ok(window)
+    }
+
+    /// Get a maintenance window by ID.
+    pub async fn get_maintenance_window(&self, id: &str) -> AnyhowResult<MaintenanceWindow> {
+        let windows = self.maintenance_windows.read().await;
+        windows.get(id)
+            .cloned()
+            .ok_or_else(|| AlertServiceError::MaintenanceWindowNotFound(id.to_string()).into())
+    }
+
+    /// List all maintenance windows.
+    pub async fn list_maintenance_windows(&self) -> AnyhowResult<Vec<MaintenanceWindow>> {
+        let windows = self.maintenance_windows.read().await;
+        Ok(windows.values().cloned().collect())
+    }
+
+    /// List maintenance windows active at the given moment.
+    pub async fn active_maintenance_windows(&self, at: DateTime<Utc>) -> Vec<MaintenanceWindow> {
+        let windows = self.maintenance_windows.read().await;
+        windows.values()
+            .filter(|w| w.is_active_at(at))
+            .cloned()
+            .collect()
+    }
+
+    /// Update an existing maintenance window.
+    pub async fn update_maintenance_window(
+        &self,
+        id: &str,
+        request: UpdateMaintenanceWindowRequest,
+    ) -> AnyhowResult<MaintenanceWindow> {
+        let mut windows = self.maintenance_windows.write().await;
+        let mut window = windows.get(id)
+            .cloned()
+            .ok_or_else(|| AlertServiceError::MaintenanceWindowNotFound(id.to_string()))?;
+
+        if let Some(name) = request.name {
+            window.name = name;
+        }
+        if let Some(description) = request.description {
+            window.description = Some(description);
+        }
+        if let Some(rule_ids) = request.rule_ids {
+            window.rule_ids = rule_ids;
+        }
+        if let Some(resources) = request.resources {
+            window.resources = resources;
+        }
+        if let Some(schedule) = request.schedule {
+            window.schedule = schedule;
+        }
+        if let Some(enabled) = request.enabled {
+            window.enabled = enabled;
+        }
+
+        window.updated_at = Utc::now();
+
+        if let Err(errors) = window.validate() {
+            return Err(AlertServiceError::InvalidMaintenanceWindow(errors.join("; ")).into());
+        }
+
+        self.db.execute(
+            "UPDATE maintenance_windows
+             SET name = ?, description = ?, rule_ids = ?, resources = ?, schedule = ?,
+                 enabled = ?, updated_at = ?
+             WHERE id = ?",
+            &[
+                &window.name as &dyn duckdb::ToSql,
+                &window.description,
+                &serde_json::to_string(&window.rule_ids)?,
+                &serde_json::to_string(&window.resources)?,
+                &serde_json::to_string(&window.schedule)?,
+                &window.enabled,
+                &format_datetime(window.updated_at),
+                &id,
+            ],
+        )?;
+
+        windows.insert(id.to_string(), window.clone());
+
+        info!("Updated maintenance window: {}", id);
+        Ok(window)
+    }
+
+    /// Delete a maintenance window.
+    pub async fn delete_maintenance_window(&self, id: &str) -> AnyhowResult<()> {
+        let mut windows = self.maintenance_windows.write().await;
+        if !windows.contains_key(id) {
+            return Err(AlertServiceError::MaintenanceWindowNotFound(id.to_string()).into());
+        }
+
+        self.db.execute("DELETE FROM maintenance_windows WHERE id = ?", &[&id as &dyn duckdb::ToSql])?;
+        windows.remove(id);
+
+        info!("Deleted maintenance window: {}", id);
+        Ok(())
+    }
+
+    /// Find an active maintenance window that covers the alert.
+    async fn suppressing_window(&self, alert: &Alert) -> Option<MaintenanceWindowId> {
+        let windows = self.maintenance_windows.read().await;
+        windows.values()
+            .find(|w| {
+                w.is_active_at(alert.triggered_at)
+                    && w.covers(&alert.rule_id, &alert.affected_resources)
+            })
+            .map(|w| w.id.clone())
+    }
+
+    /// Kind per resource code (`gemaal`, `peilgebied` or the asset layer),
+    /// used to match kind-prefixed resource selectors.
+    pub fn resource_kinds(&self) -> HashMap<String, String> {
+        let sources = [
+            "SELECT code, 'gemaal' FROM gemaal_registratie",
+            "SELECT code, 'peilgebied' FROM peilgebied",
+            "SELECT code, layer_type FROM asset_registratie",
+        ];
+
+        let mut kinds = HashMap::new();
+        for sql in sources {
+            match self.db.query(sql, &[], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            }) {
+                Ok(rows) => {
+                    for (code, kind) in rows {
+                        // Earlier sources take precedence
+                        kinds.entry(code).or_insert(kind);
+                    }
+                }
+                Err(e) => warn!("Failed to load resource kinds ({}): {}", sql, e),
+            }
+        }
+        kinds
+    }
+
     /// Evaluate all enabled rules against the given context.
     pub async fn evaluate_rules(&self, context: &EvaluationContext) -> AnyhowResult<Vec<Alert>> {
         let rules = self.rules.read().await;
         let mut triggered_alerts = Vec::new();
         let mut last_triggers = self.last_triggers.write().await;
 
-        for rule in rules.values().filter(|r| r.enabled) {
-            let result = self.evaluate_rule(rule, context).await?;
+        let applicable = rules.values().filter(|r| {
+            r.enabled && r.applies_to_source(context.source_kind.as_deref(), context.source.as_deref())
+        });
+
+        for rule in applicable {
+            let streak_key = (rule.id.clone(), context.source.clone());
+            let firing = self.flap_states.read().await
+                .get(&streak_key)
+                .is_some_and(|state| state.firing);
+
+            let result = self.evaluate_rule(rule, context, firing).await?;
+
+            let (event, damped) = {
+                let mut states = self.flap_states.write().await;
+                let state = states.entry(streak_key.clone()).or_default();
+                let event = state.record(result.triggered, context.now, rule.flapping_policy.as_ref());
+                (event, state.is_damped(context.now))
+            };
+            if let Some(event) = event {
+                self.log_flap_event(rule, context.source.as_deref(), event)?;
+            }
 
-            if result.triggered {
-                // Check cooldown
-                let last_triggered = last_triggers.get(&rule.id).copied();
-                if rule.is_in_cooldown(last_triggered) {
-                    debug!("Rule {} is in cooldown, skipping", rule.id);
-                    continue;
+            if !result.triggered {
+                if let Some(threshold) = rule.auto_resolve_after {
+                    let streak = {
+                        let mut streaks = self.clear_streaks.write().await;
+                        let streak = streaks.entry(streak_key).or_insert(0);
+                        *streak += 1;
+                        *streak
+                    };
+                    if streak >= threshold {
+                        let resolved = self
+                            .auto_resolve_alerts(rule, context.source.as_deref(), streak)
+                            .await?;
+                        if resolved > 0 {
+                            info!("Auto-resolved {} alert(s) for rule {}", resolved, rule.id);
+                        }
+                    }
                 }
+                continue;
+            }
 
-                // Create and store alerts
-                for alert in &result.alerts {
-                    self.store_alert(alert).await?;
-                    self.send_notifications(alert).await;
-                    triggered_alerts.push(alert.clone());
-                }
+            self.clear_streaks.write().await.remove(&streak_key);
+
+            if damped {
+                debug!("Rule {} is damped because of flapping, skipping", rule.id);
+                continue;
+            }
 
-                // Update last trigger time
-                last_triggers.insert(rule.id.clone(), Utc::now());
+            // Check cooldown
+            let last_triggered = last_triggers.get(&streak_key).copied();
+            if rule.is_in_cooldown(last_triggered) {
+                debug!("Rule {} is in cooldown, skipping", rule.id);
+                continue;
             }
+
+            // Create and store alerts
+            for mut alert in result.alerts {
+                if let Some(window_id) = self.suppressing_window(&alert).await {
+                    // Record the alert, but don't notify during maintenance
+                    alert.status = AlertStatus::Suppressed;
+                    self.store_alert(&alert).await?;
+                    record_transition(
+                        &self.db,
+                        &alert.id,
+                        None,
+                        AlertStatus::Suppressed,
+                        None,
+                        &serde_json::json!({ "maintenance_window_id": window_id }),
+                    )?;
+                    debug!("Alert {} suppressed by maintenance window {}", alert.id, window_id);
+                } else {
+                    self.store_alert(&alert).await?;
+                    record_transition(
+                        &self.db,
+                        &alert.id,
+                        None,
+                        AlertStatus::Active,
+                        None,
+                        &serde_json::json!({ "resources": alert.affected_resources }),
+                    )?;
+                    self.send_notifications(rule, &alert).await;
+                    self.schedule_escalation(rule, &alert)?;
+                }
+                triggered_alerts.push(alert);
+            }
+
+            // Update last trigger time
+            last_triggers.insert(streak_key, Utc::now());
         }
 
         Ok(triggered_alerts)
     }
 
+    /// Resolve the open alerts of a rule for the given source.
+    async fn auto_resolve_alerts(
+        &self,
+        rule: &AlertRule,
+        source: Option<&str>,
+        evaluations: u32,
+    ) -> AnyhowResult<usize> {
+        let rows = self.db.query(
+            "SELECT id, affected_resources FROM alerts WHERE rule_id = ? AND status <> 'resolved'",
+            &[&rule.id as &dyn duckdb::ToSql],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )?;
+
+        let mut resolved = 0;
+        for (id, resources_json) in rows {
+            let resources: Vec<String> = resources_json
+                .and_then(|j| serde_json::from_str(&j).ok())
+                .unwrap_or_default();
+            if !matches_source(&resources, source) {
+                continue;
+            }
+
+            let mut alert = self.get_alert(&id).await?;
+            let previous_status = alert.status;
+            alert.resolve_automatically(evaluations);
+
+            self.db.execute(
+                "UPDATE alerts SET status = ?, resolved_at = ?, resolution_note = ? WHERE id = ?",
+                &[
+                    &alert.status.as_str() as &dyn duckdb::ToSql,
+                    &alert.resolved_at.map(format_datetime),
+                    &alert.resolution_note,
+                    &id,
+                ],
+            )?;
+            self.complete_escalation(&id)?;
+            record_transition(
+                &self.db,
+                &id,
+                Some(previous_status),
+                AlertStatus::Resolved,
+                None,
+                &serde_json::json!({
+                    "automatic": true,
+                    "consecutive_evaluations": evaluations,
+                }),
+            )?;
+
+            debug!("Alert {} resolved automatically", id);
+            resolved += 1;
+        }
+
+        Ok(resolved)
+    }
+
+    /// Log a change in flapping status of a rule.
+    fn log_flap_event(
+        &self,
+        rule: &AlertRule,
+        source: Option<&str>,
+        event: FlapEvent,
+    ) -> AnyhowResult<()> {
+        let (event_type, details) = match event {
+            FlapEvent::Started { transitions } => {
+                warn!(
+                    "Rule {} is flapping for {} ({} state changes), damping notifications",
+                    rule.id,
+                    source.unwrap_or("all sources"),
+                    transitions
+                );
+                (
+                    "flapping_started",
+                    serde_json::json!({
+                        "transitions": transitions,
+                        "policy": rule.flapping_policy,
+                    }),
+                )
+            }
+            FlapEvent::Ended => {
+                info!("Rule {} no longer damped for {}", rule.id, source.unwrap_or("all sources"));
+                ("flapping_ended", serde_json::json!({}))
+            }
+        };
+
+        let id = format!("ARE_{}", uuid::Uuid::new_v4());
+        self.db.execute(
+            "INSERT INTO alert_rule_events (id, rule_id, source, event_type, details, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            &[
+                &id as &dyn duckdb::ToSql,
+                &rule.id,
+                &source,
+                &event_type,
+                &details.to_string(),
+                &format_datetime(Utc::now()),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// List logged events of a rule, newest first.
+    pub async fn list_rule_events(&self, rule_id: &str, limit: u64) -> AnyhowResult<Vec<RuleEvent>> {
+        let rows = self.db.query(
+            "SELECT id, rule_id, source, event_type, details, CAST(created_at AS VARCHAR)
+             FROM alert_rule_events
+             WHERE rule_id = ?
+             ORDER BY created_at DESC
+             LIMIT ?",
+            &[&rule_id as &dyn duckdb::ToSql, &(limit as i64)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            },
+        )?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, rule_id, source, event_type, details, created_at)| RuleEvent {
+                id,
+                rule_id,
+                source,
+                event_type,
+                details: details
+                    .and_then(|d| serde_json::from_str(&d).ok())
+                    .unwrap_or(serde_json::Value::Null),
+                created_at: parse_datetime(&created_at),
+            })
+            .collect())
+    }
+
     /// Evaluate a single rule.
+    ///
+    /// When `firing` (the rule triggered in the previous evaluation for this
+    /// source) conditions compare against their `clear_value`.
     pub async fn evaluate_rule(
         &self,
         rule: &AlertRule,
         context: &EvaluationContext,
+        firing: bool,
     ) -> AnyhowResult<RuleEvaluationResult> {
         let mut condition_results = Vec::new();
         let mut all_passed = true;
         let mut any_passed = false;
 
         for condition in &rule.conditions {
-            let result = self.evaluate_condition(condition, context).await?;
+            let result = self.evaluate_condition(condition, context, firing).await?;
             condition_results.push(result.clone());
 
             match result.passed {
@@ -417,7 +1011,7 @@ impl AlertService {
         };
 
         let alerts = if triggered {
-            vec![Alert::from_rule(rule, context)]
+            vec![Alert::from_evaluation(rule, context, &condition_results)]
         } else {
             Vec::new()
         };
@@ -441,29 +1035,17 @@ impl AlertService {
         &self,
         condition: &AlertCondition,
         context: &EvaluationContext,
+        firing: bool,
     ) -> AnyhowResult<ConditionResult> {
-        // Get the actual value from context
-        let actual_value = context.values.get(&condition.field);
-
-        // If not found and we have a time window, try time series aggregation
-        let actual_value = if actual_value.is_none() {
-            if let Some(ref _window) = condition.time_window
-                && let Some(_series) = context.time_series.get(&condition.field) {
-                    // Need to own the aggregated value, not reference it
-                    return Ok(ConditionResult {
-                        field: condition.field.clone(),
-                        passed: false, // Placeholder - aggregation needs proper implementation
-                        actual_value: Some(AlertValue::Number(0.0)),
-                        expected_value: condition.value.clone(),
-                        operator: condition.operator,
-                    });
-                }
-            None
-        } else {
-            actual_value
-        };
+        if condition.operator == ComparisonOperator::NoData {
+            return Ok(evaluate_no_data(condition, context));
+        }
 
-        let passed = match (&actual_value, &condition.value, condition.operator) {
+        let actual_value = resolve_condition_value(condition, context);
+        let actual_value = actual_value.as_ref();
+        let threshold = condition_threshold(condition, firing);
+
+        let passed = match (&actual_value, threshold, condition.operator) {
             (Some(AlertValue::Number(actual)), AlertValue::Number(expected), op) => {
                 op.eval_numeric(*actual, *expected)
             }
@@ -486,7 +1068,7 @@ impl AlertService {
             field: condition.field.clone(),
             passed,
             actual_value: actual_value.cloned(),
-            expected_value: condition.value.clone(),
+            expected_value: threshold.clone(),
             operator: condition.operator,
         })
     }
@@ -508,8 +1090,8 @@ impl AlertService {
         self.db.execute(
             "INSERT INTO alerts (id, rule_id, rule_name, severity, title, message, category,
                                affected_resources, status, triggered_at, acknowledged_at,
-                               acknowledged_by, resolved_at, context)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                               acknowledged_by, resolved_at, resolution_note, context)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             &[
                 &alert.id as &dyn duckdb::ToSql,
                 &alert.rule_id,
@@ -524,6 +1106,7 @@ impl AlertService {
                 &alert.acknowledged_at.map(format_datetime),
                 &alert.acknowledged_by,
                 &alert.resolved_at.map(format_datetime),
+                &alert.resolution_note,
                 &context_json,
             ],
         )?;
@@ -532,7 +1115,20 @@ impl AlertService {
     }
 
     /// Send notifications for an alert.
-    async fn send_notifications(&self, alert: &Alert) {
+    async fn send_notifications(&self, rule: &AlertRule, alert: &Alert) {
+        self.notify(&rule.notification_channels, alert, None).await;
+    }
+
+    /// Deliver an alert to the given channels.
+    ///
+    /// The WebSocket broadcast always happens so dashboards stay current;
+    /// `escalation_step` is set when notifying for an escalation step.
+    async fn notify(
+        &self,
+        channels: &[NotificationChannel],
+        alert: &Alert,
+        escalation_step: Option<u32>,
+    ) {
         // Convert core AlertSeverity to WsAlertSeverity
         let ws_severity = match alert.severity {
             AlertSeverity::Info => WsAlertSeverity::Info,
@@ -541,26 +1137,262 @@ impl AlertService {
             AlertSeverity::Critical => WsAlertSeverity::Critical,
         };
 
+        let title = match escalation_step {
+            Some(step) => format!("Escalation {}: {}", step, alert.title),
+            None => alert.title.clone(),
+        };
+
         let msg = WsMessage::alert(
             alert.id.clone(),
             ws_severity,
-            alert.title.clone(),
+            title.clone(),
             alert.message.clone(),
         );
 
         // Broadcast via WebSocket
-        #[allow(clippy::let_underscore_future)]
-        let _ = self.ws_server.broadcast(msg);
+        self.ws_server.broadcast(msg).await;
 
-        // TODO: Implement other channels (email, webhook)
+        for channel in channels {
+            match channel {
+                NotificationChannel::WebSocket => {
+                    // Already broadcast above
+                }
+                NotificationChannel::Webhook { url, headers } => {
+                    // Deliver in the background so retries don't block evaluation
+                    let client = self.webhook_client.clone();
+                    let db = self.db.clone();
+                    let alert = alert.clone();
+                    let url = url.clone();
+                    let headers = headers.clone();
+
+                    tokio::spawn(async move {
+                        let payload = match escalation_step {
+                            Some(step) => WebhookPayload::escalated(&alert, step),
+                            None => WebhookPayload::triggered(&alert),
+                        };
+                        let delivery = client.deliver(&url, &headers, &payload).await;
+                        if let Err(e) = record_notification(&db, &alert.id, "webhook", &url, &delivery) {
+                            warn!("Failed to record webhook notification for {}: {}", alert.id, e);
+                        }
+                    });
+                }
+                NotificationChannel::Email { recipients } => {
+                    let mailer = self.mailer.clone();
+                    let alert_id = alert.id.clone();
+                    let messages: Vec<EmailMessage> = recipients
+                        .iter()
+                        .map(|to| EmailMessage {
+                            to: to.clone(),
+                            subject: format!(
+                                "[{}] {}",
+                                alert.severity.as_str().to_uppercase(),
+                                title
+                            ),
+                            body: alert.message.clone(),
+                        })
+                        .collect();
+
+                    tokio::spawn(async move {
+                        for message in &messages {
+                            if let Err(e) = mailer.send(message).await {
+                                warn!("E-mail of alert {} to {} failed: {}", alert_id, message.to, e);
+                            }
+                        }
+                    });
+                }
+                NotificationChannel::Sms { .. } => {
+                    // Rejected by rule validation; rules stored before may still have it
+                    warn!("SMS notifications are not supported, alert {} not sent by SMS", alert.id);
+                }
+            }
+        }
+    }
+
+    /// Register a new alert for escalation when its rule has a matching policy.
+    fn schedule_escalation(&self, rule: &AlertRule, alert: &Alert) -> AnyhowResult<()> {
+        let Some(policy) = &rule.escalation_policy else {
+            return Ok(());
+        };
+        if !policy.applies_to(alert.severity) {
+            return Ok(());
+        }
+        let Some(first_due) = policy.step_due_at(0, alert.triggered_at) else {
+            return Ok(());
+        };
+
+        self.db.execute(
+            "INSERT INTO alert_escalations (alert_id, rule_id, current_step, next_escalation_at,
+                                            completed, created_at, updated_at)
+             VALUES (?, ?, 0, ?, FALSE, ?, ?)",
+            &[
+                &alert.id as &dyn duckdb::ToSql,
+                &rule.id,
+                &format_datetime(first_due),
+                &format_datetime(Utc::now()),
+                &format_datetime(Utc::now()),
+            ],
+        )?;
+
+        debug!("Scheduled escalation for alert {} at {}", alert.id, first_due);
+        Ok(())
+    }
+
+    /// Fire all escalation steps that are due.
+    ///
+    /// Alerts that were acknowledged or resolved in the meantime are marked
+    /// completed without notifying. Returns the number of escalated alerts.
+    pub async fn process_escalations(&self) -> AnyhowResult<usize> {
+        let now = Utc::now();
+        let due = self.db.query(
+            "SELECT alert_id, rule_id, current_step
+             FROM alert_escalations
+             WHERE completed = FALSE AND next_escalation_at <= ?
+             ORDER BY next_escalation_at",
+            &[&format_datetime(now) as &dyn duckdb::ToSql],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            },
+        )?;
+
+        let mut escalated = 0;
+        for (alert_id, rule_id, current_step) in due {
+            let alert = match self.get_alert(&alert_id).await {
+                Ok(alert) if alert.status == AlertStatus::Active => alert,
+                Ok(_) => {
+                    self.complete_escalation(&alert_id)?;
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Cannot escalate alert {}: {}", alert_id, e);
+                    self.complete_escalation(&alert_id)?;
+                    continue;
+                }
+            };
+
+            let policy = {
+                let rules = self.rules.read().await;
+                rules.get(&rule_id).and_then(|r| r.escalation_policy.clone())
+            };
+            let Some(policy) = policy else {
+                self.complete_escalation(&alert_id)?;
+                continue;
+            };
+            let Some(step) = policy.steps.get(current_step as usize) else {
+                self.complete_escalation(&alert_id)?;
+                continue;
+            };
+
+            let step_number = current_step + 1;
+            info!("Escalating alert {} (step {}/{})", alert_id, step_number, policy.steps.len());
+            self.notify(&step.channels, &alert, Some(step_number)).await;
+
+            record_history(
+                &self.db,
+                &alert_id,
+                "escalated",
+                &serde_json::json!({
+                    "step": step_number,
+                    "after_minutes": step.after_minutes,
+                    "channels": step.channels,
+                }),
+            )?;
+
+            let next_due = policy.step_due_at(step_number as usize, alert.triggered_at);
+            self.db.execute(
+                "UPDATE alert_escalations
+                 SET current_step = ?, next_escalation_at = ?, last_escalated_at = ?,
+                     completed = ?, updated_at = ?
+                 WHERE alert_id = ?",
+                &[
+                    &step_number as &dyn duckdb::ToSql,
+                    &next_due.map(format_datetime),
+                    &format_datetime(now),
+                    &next_due.is_none(),
+                    &format_datetime(now),
+                    &alert_id,
+                ],
+            )?;
+
+            escalated += 1;
+        }
+
+        Ok(escalated)
+    }
+
+    /// Stop escalating an alert.
+    fn complete_escalation(&self, alert_id: &str) -> AnyhowResult<()> {
+        self.db.execute(
+            "UPDATE alert_escalations
+             SET completed = TRUE, next_escalation_at = NULL, updated_at = ?
+             WHERE alert_id = ? AND completed = FALSE",
+            &[&format_datetime(Utc::now()) as &dyn duckdb::ToSql, &alert_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get the escalation state of an alert, if it escalates.
+    pub async fn get_escalation(&self, alert_id: &str) -> AnyhowResult<Option<AlertEscalation>> {
+        let rows = self.db.query(
+            "SELECT alert_id, rule_id, current_step,
+                    CAST(next_escalation_at AS VARCHAR), CAST(last_escalated_at AS VARCHAR), completed
+             FROM alert_escalations WHERE alert_id = ?",
+            &[&alert_id as &dyn duckdb::ToSql],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u32>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, bool>(5)?,
+                ))
+            },
+        )?;
+
+        Ok(rows.into_iter().next().map(
+            |(alert_id, rule_id, current_step, next_at, last_at, completed)| AlertEscalation {
+                alert_id,
+                rule_id,
+                current_step,
+                next_escalation_at: next_at.map(|s| parse_datetime(&s)),
+                last_escalated_at: last_at.map(|s| parse_datetime(&s)),
+                completed,
+            },
+        ))
+    }
+
+    /// Start the background worker that fires due escalation steps.
+    pub fn start_escalation_worker(self: &Arc<Self>, interval: StdDuration) {
+        let service = Arc::clone(self);
+
+        tokio::spawn(async move {
+            info!("Alert escalation worker started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                match service.process_escalations().await {
+                    Ok(0) => {}
+                    Ok(count) => info!("Escalated {} alert(s)", count),
+                    Err(e) => warn!("Alert escalation run failed: {}", e),
+                }
+            }
+        });
     }
 
     /// Get an alert by ID.
     pub async fn get_alert(&self, id: &str) -> AnyhowResult<Alert> {
         let result = self.db.query_row(
             "SELECT id, rule_id, rule_name, severity, title, message, category,
-                     affected_resources, status, triggered_at, acknowledged_at,
-                     acknowledged_by, resolved_at, context
+                     affected_resources, status, CAST(triggered_at AS VARCHAR),
+                     CAST(acknowledged_at AS VARCHAR), acknowledged_by,
+                     CAST(resolved_at AS VARCHAR), resolution_note, context
              FROM alerts WHERE id = ?",
             &[&id as &dyn duckdb::ToSql],
             |row| {
@@ -578,14 +1410,16 @@ impl AlertService {
                     row.get::<_, Option<String>>(10)?,
                     row.get::<_, Option<String>>(11)?,
                     row.get::<_, Option<String>>(12)?,
+                    row.get::<_, Option<String>>(13)?,
+                    row.get::<_, Option<String>>(14)?,
                 ))
             },
         );
 
         let (id, rule_id, rule_name, severity_str, title, message, category_str,
             resources_json, status_str, triggered_at_str, acknowledged_at_str,
-            acknowledged_by, resolved_at_str) = result.map_err(|e| {
-            if e.to_string().contains("QueryReturnedNoRows") {
+            acknowledged_by, resolved_at_str, resolution_note, context_json) = result.map_err(|e| {
+            if is_no_rows(&e) {
                 AlertServiceError::AlertNotFound(id.to_string()).into()
             } else {
                 e
@@ -599,6 +1433,9 @@ impl AlertService {
             .and_then(|j| serde_json::from_str(&j).ok())
             .unwrap_or_default();
         let status = parse_alert_status(&status_str);
+        let context: HashMap<String, serde_json::Value> = context_json
+            .and_then(|j| serde_json::from_str(&j).ok())
+            .unwrap_or_default();
 
         Ok(Alert {
             id,
@@ -614,7 +1451,8 @@ impl AlertService {
             acknowledged_at: acknowledged_at_str.map(|s| parse_datetime(&s)),
             acknowledged_by,
             resolved_at: resolved_at_str.map(|s| parse_datetime(&s)),
-            context: HashMap::new(),
+            resolution_note,
+            context,
         })
     }
 
@@ -663,8 +1501,9 @@ impl AlertService {
 
         let sql = format!(
             "SELECT id, rule_id, rule_name, severity, title, message, category,
-                    affected_resources, status, triggered_at, acknowledged_at,
-                    acknowledged_by, resolved_at, context
+                    affected_resources, status, CAST(triggered_at AS VARCHAR),
+                    CAST(acknowledged_at AS VARCHAR), acknowledged_by,
+                    CAST(resolved_at AS VARCHAR), resolution_note, context
              FROM alerts
              {}
              ORDER BY triggered_at DESC
@@ -689,6 +1528,7 @@ impl AlertService {
                 row.get::<_, Option<String>>(11)?,
                 row.get::<_, Option<String>>(12)?,
                 row.get::<_, Option<String>>(13)?,
+                row.get::<_, Option<String>>(14)?,
             ))
         })?;
 
@@ -696,7 +1536,7 @@ impl AlertService {
         for row in rows {
             let (id, rule_id, rule_name, severity_str, title, message, category_str,
                 resources_json, status_str, triggered_at_str, acknowledged_at_str,
-                acknowledged_by, resolved_at_str, context_json) = row;
+                acknowledged_by, resolved_at_str, resolution_note, context_json) = row;
 
             let severity = AlertSeverity::from_str(&severity_str)
                 .unwrap_or(AlertSeverity::Info);
@@ -723,6 +1563,7 @@ impl AlertService {
                 acknowledged_at: acknowledged_at_str.map(|s| parse_datetime(&s)),
                 acknowledged_by,
                 resolved_at: resolved_at_str.map(|s| parse_datetime(&s)),
+                resolution_note,
                 context,
             });
         }
@@ -739,9 +1580,11 @@ impl AlertService {
         let mut alert = self.get_alert(id).await?;
 
         if alert.status != AlertStatus::Active {
-            return Err(AlertServiceError::InvalidRule(
-                "Alert is not active".to_string(),
-            ).into());
+            return Err(AlertServiceError::InvalidState(format!(
+                "alert is {}, only active alerts can be acknowledged",
+                alert.status.as_str()
+            ))
+            .into());
         }
 
         alert.acknowledge(request.user_id.clone());
@@ -756,13 +1599,27 @@ impl AlertService {
             ],
         )?;
 
+        self.complete_escalation(id)?;
+        record_transition(
+            &self.db,
+            id,
+            Some(AlertStatus::Active),
+            AlertStatus::Acknowledged,
+            Some(&request.user_id),
+            &serde_json::json!({ "comment": request.comment }),
+        )?;
+
         info!("Alert {} acknowledged by {}", id, request.user_id);
         Ok(alert)
     }
 
-    /// Resolve an alert.
-    pub async fn resolve_alert(&self, id: &str) -> AnyhowResult<Alert> {
+    /// Resolve an alert on behalf of `user_id`.
+    pub async fn resolve_alert(&self, id: &str, user_id: Option<&str>) -> AnyhowResult<Alert> {
         let mut alert = self.get_alert(id).await?;
+        if alert.status == AlertStatus::Resolved {
+            return Err(AlertServiceError::InvalidState("alert is already resolved".to_string()).into());
+        }
+        let previous_status = alert.status;
         alert.resolve();
 
         self.db.execute(
@@ -774,10 +1631,142 @@ impl AlertService {
             ],
         )?;
 
+        self.complete_escalation(id)?;
+        record_transition(
+            &self.db,
+            id,
+            Some(previous_status),
+            AlertStatus::Resolved,
+            user_id,
+            &serde_json::json!({}),
+        )?;
+
         info!("Alert {} resolved", id);
         Ok(alert)
     }
 
+    /// Add a comment to an alert.
+    pub async fn add_comment(
+        &self,
+        alert_id: &str,
+        user_id: &str,
+        request: CreateAlertCommentRequest,
+    ) -> AnyhowResult<AlertComment> {
+        let comment = request.comment.trim();
+        if comment.is_empty() {
+            return Err(AlertServiceError::InvalidComment("comment cannot be empty".to_string()).into());
+        }
+
+        // Ensure the alert exists
+        self.get_alert(alert_id).await?;
+
+        let comment = AlertComment {
+            id: format!("ACM_{}", uuid::Uuid::new_v4()),
+            alert_id: alert_id.to_string(),
+            user_id: user_id.to_string(),
+            comment: comment.to_string(),
+            created_at: Utc::now(),
+        };
+
+        self.db.execute(
+            "INSERT INTO alert_comments (id, alert_id, user_id, comment, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            &[
+                &comment.id as &dyn duckdb::ToSql,
+                &comment.alert_id,
+                &comment.user_id,
+                &comment.comment,
+                &format_datetime(comment.created_at),
+            ],
+        )?;
+        insert_history(
+            &self.db,
+            alert_id,
+            "commented",
+            None,
+            Some(user_id),
+            &serde_json::json!({ "comment_id": comment.id }),
+        )?;
+
+        info!("Comment added to alert {} by {}", alert_id, user_id);
+        Ok(comment)
+    }
+
+    /// List the comments of an alert, oldest first.
+    pub async fn list_comments(&self, alert_id: &str) -> AnyhowResult<Vec<AlertComment>> {
+        let rows = self.db.query(
+            "SELECT id, alert_id, user_id, comment, CAST(created_at AS VARCHAR)
+             FROM alert_comments
+             WHERE alert_id = ?
+             ORDER BY created_at",
+            &[&alert_id as &dyn duckdb::ToSql],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        )?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, alert_id, user_id, comment, created_at)| AlertComment {
+                id,
+                alert_id,
+                user_id,
+                comment,
+                created_at: parse_datetime(&created_at),
+            })
+            .collect())
+    }
+
+    /// Get the audit trail of an alert, oldest first.
+    pub async fn get_history(&self, alert_id: &str) -> AnyhowResult<Vec<AlertHistoryEntry>> {
+        let rows = self.db.query(
+            "SELECT id, alert_id, event_type, from_status, to_status, caused_by, cause_type,
+                    event_data, CAST(created_at AS VARCHAR)
+             FROM alert_history
+             WHERE alert_id = ?
+             ORDER BY created_at",
+            &[&alert_id as &dyn duckdb::ToSql],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, String>(8)?,
+                ))
+            },
+        )?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, alert_id, event_type, from, to, caused_by, cause_type, data, created_at)| {
+                AlertHistoryEntry {
+                    id,
+                    alert_id,
+                    event_type,
+                    from_status: from.as_deref().map(parse_alert_status),
+                    to_status: to.as_deref().map(parse_alert_status),
+                    caused_by,
+                    cause_type: cause_type.unwrap_or_else(|| "system".to_string()),
+                    event_data: data
+                        .and_then(|d| serde_json::from_str(&d).ok())
+                        .unwrap_or(serde_json::Value::Null),
+                    created_at: parse_datetime(&created_at),
+                }
+            })
+            .collect())
+    }
+
     /// Get alert statistics.
     pub async fn get_stats(&self) -> AnyhowResult<AlertStats> {
         // Count total alerts
@@ -834,13 +1823,19 @@ impl AlertService {
             top_rules.push(RuleTriggerCount { rule_id, rule_name, count: count as u64 });
         }
 
-        // Average resolution time
+        // Average resolution and acknowledge time
         let avg_resolution: Option<f64> = self.db.query_row(
-            "SELECT AVG(JULIANDAY(resolved_at) - JULIANDAY(triggered_at)) * 86400
+            "SELECT AVG(epoch(resolved_at) - epoch(triggered_at))
              FROM alerts WHERE resolved_at IS NOT NULL",
             &[],
             |row| row.get(0),
-        ).ok();
+        ).ok().flatten();
+        let avg_acknowledge: Option<f64> = self.db.query_row(
+            "SELECT AVG(epoch(acknowledged_at) - epoch(triggered_at))
+             FROM alerts WHERE acknowledged_at IS NOT NULL",
+            &[],
+            |row| row.get(0),
+        ).ok().flatten();
 
         Ok(AlertStats {
             total_alerts: total as u64,
@@ -849,12 +1844,281 @@ impl AlertService {
             by_category,
             top_rules,
             avg_resolution_seconds: avg_resolution,
+            avg_acknowledge_seconds: avg_acknowledge,
         })
     }
+
+    /// Build a trend report of the alerts triggered within `[start, end)`.
+    ///
+    /// Suppressed alerts are left out, they never required handling.
+    pub async fn get_trend_report(
+        &self,
+        bucket: StatsBucket,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> AnyhowResult<AlertTrendReport> {
+        let start_str = format_datetime(start);
+        let end_str = format_datetime(end);
+        let rows = self.db.query(
+            "SELECT rule_id, rule_name, CAST(triggered_at AS VARCHAR),
+                    CAST(acknowledged_at AS VARCHAR), CAST(resolved_at AS VARCHAR)
+             FROM alerts
+             WHERE triggered_at >= ? AND triggered_at < ? AND status != 'suppressed'",
+            &[&start_str, &end_str],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )?;
+
+        let alerts: Vec<AlertTimeline> = rows
+            .into_iter()
+            .map(|(rule_id, rule_name, triggered, acknowledged, resolved)| AlertTimeline {
+                rule_id,
+                rule_name,
+                triggered_at: parse_datetime(&triggered),
+                acknowledged_at: acknowledged.as_deref().map(parse_datetime),
+                resolved_at: resolved.as_deref().map(parse_datetime),
+            })
+            .collect();
+
+        Ok(AlertTrendReport::build(&alerts, bucket, start, end))
+    }
+}
+
+/// Helper: Log a notification delivery attempt in `alert_notifications`.
+fn record_notification(
+    db: &Database,
+    alert_id: &str,
+    channel_type: &str,
+    channel_target: &str,
+    delivery: &WebhookDelivery,
+) -> AnyhowResult<()> {
+    let id = format!("NTF_{}", uuid::Uuid::new_v4());
+    let status = if delivery.succeeded() { "sent" } else { "failed" };
+    let sent_at = delivery.succeeded().then(|| format_datetime(Utc::now()));
+    let request_payload = (!delivery.request_body.is_empty()).then(|| delivery.request_body.clone());
+    let response_payload = delivery.response_body.as_ref().map(|body| {
+        serde_json::json!({ "status": delivery.status_code, "body": body }).to_string()
+    });
+    let retry_count = delivery.attempts.saturating_sub(1);
+
+    db.execute(
+        "INSERT INTO alert_notifications (id, alert_id, channel_type, channel_target, status,
+                                          sent_at, error_message, retry_count,
+                                          request_payload, response_payload, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        &[
+            &id as &dyn duckdb::ToSql,
+            &alert_id,
+            &channel_type,
+            &channel_target,
+            &status,
+            &sent_at,
+            &delivery.error,
+            &retry_count,
+            &request_payload,
+            &response_payload,
+            &format_datetime(Utc::now()),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Helper: Append an event to `alert_history`.
+fn record_history(
+    db: &Database,
+    alert_id: &str,
+    event_type: &str,
+    event_data: &serde_json::Value,
+) -> AnyhowResult<()> {
+    insert_history(db, alert_id, event_type, None, None, event_data)
+}
+
+/// Helper: Append a status transition to `alert_history`.
+///
+/// `caused_by` is the user that made the change, None for the rule engine.
+fn record_transition(
+    db: &Database,
+    alert_id: &str,
+    from: Option<AlertStatus>,
+    to: AlertStatus,
+    caused_by: Option<&str>,
+    event_data: &serde_json::Value,
+) -> AnyhowResult<()> {
+    insert_history(db, alert_id, to.event_type(), Some((from, to)), caused_by, event_data)
+}
+
+fn insert_history(
+    db: &Database,
+    alert_id: &str,
+    event_type: &str,
+    transition: Option<(Option<AlertStatus>, AlertStatus)>,
+    caused_by: Option<&str>,
+    event_data: &serde_json::Value,
+) -> AnyhowResult<()> {
+    let id = format!("AHI_{}", uuid::Uuid::new_v4());
+    let from_status = transition.and_then(|(from, _)| from).map(|s| s.as_str().to_string());
+    let to_status = transition.map(|(_, to)| to.as_str().to_string());
+    let cause_type = if caused_by.is_some() { "user" } else { "system" };
+
+    db.execute(
+        "INSERT INTO alert_history (id, alert_id, event_type, from_status, to_status, event_data,
+                                    caused_by, cause_type, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        &[
+            &id as &dyn duckdb::ToSql,
+            &alert_id,
+            &event_type,
+            &from_status,
+            &to_status,
+            &event_data.to_string(),
+            &caused_by,
+            &cause_type,
+            &format_datetime(Utc::now()),
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Helper: Threshold a condition compares against; a firing condition uses
+/// its `clear_value` so it only clears once the value is well past the
+/// trigger threshold.
+fn condition_threshold(condition: &AlertCondition, firing: bool) -> &AlertValue {
+    match (&condition.clear_value, firing) {
+        (Some(clear), true) => clear,
+        _ => &condition.value,
+    }
+}
+
+/// Helper: Evaluate a `no_data` condition against the delivery state of the
+/// field's series. Values are minutes of silence; a series that never
+/// delivered counts as silent.
+fn evaluate_no_data(condition: &AlertCondition, context: &EvaluationContext) -> ConditionResult {
+    let heartbeat = match &condition.source_filter {
+        Some(filter) if context.source.as_deref() != Some(filter.as_str()) => None,
+        _ => context.heartbeats.get(&condition.field),
+    };
+    let limit = heartbeat.and_then(|h| condition.no_data_limit(h));
+    let silence = heartbeat
+        .and_then(|h| h.last_timestamp)
+        .map(|last| context.now - last);
+
+    let passed = match limit {
+        Some(limit) => silence.is_none_or(|silence| silence > limit),
+        None => false,
+    };
+    let minutes = |d: chrono::Duration| AlertValue::Number(d.num_seconds() as f64 / 60.0);
+
+    ConditionResult {
+        field: condition.field.clone(),
+        passed,
+        actual_value: silence.map(minutes),
+        expected_value: limit.map(minutes).unwrap_or_else(|| condition.value.clone()),
+        operator: condition.operator,
+    }
+}
+
+/// Helper: Check whether an alert's resources belong to an evaluation source.
+fn matches_source(resources: &[String], source: Option<&str>) -> bool {
+    match source {
+        Some(source) => resources.iter().any(|r| r == source),
+        None => resources.is_empty(),
+    }
+}
+
+/// Helper: Determine the value a condition is compared against.
+///
+/// Conditions with a `source_filter` only see contexts from that source.
+/// Rate operators use the rate of change of the field's time series.
+/// Conditions with a time window aggregate the series over that window;
+/// otherwise the point value is used, falling back to the whole series.
+fn resolve_condition_value(
+    condition: &AlertCondition,
+    context: &EvaluationContext,
+) -> Option<AlertValue> {
+    if let Some(filter) = &condition.source_filter
+        && context.source.as_deref() != Some(filter.as_str())
+    {
+        return None;
+    }
+
+    let series = context.time_series.get(&condition.field)
+        .map(|series| window_series(series, condition.window_duration(), context.now))
+        .filter(|s| !s.is_empty());
+
+    if condition.operator.is_rate() {
+        return series
+            .and_then(|s| rate_per_hour(&s))
+            .map(AlertValue::Number);
+    }
+
+    if condition.time_window.is_some()
+        && let Some(series) = &series
+    {
+        return Some(aggregate_series(series, condition.aggregation));
+    }
+
+    if let Some(value) = context.values.get(&condition.field) {
+        return Some(value.clone());
+    }
+
+    series.map(|s| aggregate_series(&s, condition.aggregation))
+}
+
+/// Helper: Select the points of a series within `window` before `now`.
+///
+/// Without a window all points up to `now` are used.
+fn window_series(
+    series: &[TimeSeriesValue],
+    window: Option<chrono::Duration>,
+    now: DateTime<Utc>,
+) -> Vec<TimeSeriesValue> {
+    let start = window.map(|w| now - w);
+    let mut points: Vec<TimeSeriesValue> = series
+        .iter()
+        .filter(|p| p.timestamp <= now && start.is_none_or(|start| p.timestamp >= start))
+        .filter(|p| p.value.is_finite())
+        .cloned()
+        .collect();
+    points.sort_by_key(|p| p.timestamp);
+    points
+}
+
+/// Helper: Rate of change in units per hour.
+///
+/// Uses the least-squares slope so a single noisy reading does not dominate.
+/// Needs at least two points at different timestamps.
+fn rate_per_hour(series: &[TimeSeriesValue]) -> Option<f64> {
+    let origin = series.first()?.timestamp;
+    let points: Vec<(f64, f64)> = series
+        .iter()
+        .map(|p| ((p.timestamp - origin).num_milliseconds() as f64 / 3_600_000.0, p.value))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+
+    if sxx <= f64::EPSILON {
+        return None;
+    }
+    Some(sxy / sxx)
 }
 
 /// Helper: Aggregate time series values.
-#[allow(dead_code)]
 fn aggregate_series(
     series: &[TimeSeriesValue],
     aggregation: Option<AggregationFunction>,
@@ -930,9 +2194,10 @@ fn format_datetime(dt: DateTime<Utc>) -> String {
 
 /// Helper: Parse datetime from DuckDB.
 fn parse_datetime(s: &str) -> DateTime<Utc> {
-    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.6f")
+    // `CAST(... AS VARCHAR)` drops trailing zeros of the fraction
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
         .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
-        .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.6f"))
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
         .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
         .map(|ndt| ndt.and_utc())
         .unwrap_or_else(|_| Utc::now())
@@ -987,4 +2252,327 @@ mod tests {
             _ => panic!("Expected Number"),
         }
     }
+
+    fn level_series(now: DateTime<Utc>, levels: &[(i64, f64)]) -> Vec<TimeSeriesValue> {
+        levels
+            .iter()
+            .map(|(minutes_ago, value)| TimeSeriesValue {
+                timestamp: now - Duration::minutes(*minutes_ago),
+                value: *value,
+                flag: None,
+            })
+            .collect()
+    }
+
+    fn condition(operator: ComparisonOperator, value: f64, window: Option<&str>) -> AlertCondition {
+        AlertCondition {
+            field: "water_level".to_string(),
+            operator,
+            value: AlertValue::Number(value),
+            clear_value: None,
+            source_filter: None,
+            time_window: window.map(str::to_string),
+            aggregation: Some(AggregationFunction::Max),
+        }
+    }
+
+    #[test]
+    fn test_matches_source() {
+        let resources = vec!["GEMAAL_001".to_string()];
+        assert!(matches_source(&resources, Some("GEMAAL_001")));
+        assert!(!matches_source(&resources, Some("GEMAAL_002")));
+        assert!(!matches_source(&resources, None));
+        assert!(matches_source(&[], None));
+    }
+
+    #[test]
+    fn test_condition_threshold() {
+        let mut cond = condition(ComparisonOperator::Gt, -4.5, None);
+        assert_eq!(condition_threshold(&cond, true).as_number(), Some(-4.5));
+
+        cond.clear_value = Some(AlertValue::Number(-4.6));
+        assert_eq!(condition_threshold(&cond, false).as_number(), Some(-4.5));
+        assert_eq!(condition_threshold(&cond, true).as_number(), Some(-4.6));
+    }
+
+    #[test]
+    fn test_rate_per_hour() {
+        let now = Utc::now();
+        // Rising 3 cm per 30 minutes = 0.06 m/h
+        let series = level_series(now, &[(60, -0.60), (30, -0.57), (0, -0.54)]);
+        let rate = rate_per_hour(&series).unwrap();
+        assert!((rate - 0.06).abs() < 1e-9);
+
+        assert!(rate_per_hour(&series[..1]).is_none());
+        assert!(rate_per_hour(&[]).is_none());
+    }
+
+    #[test]
+    fn test_resolve_condition_value_windowed() {
+        let now = Utc::now();
+        let mut context = EvaluationContext {
+            now,
+            values: HashMap::new(),
+            time_series: HashMap::new(),
+            source: None,
+            source_kind: None,
+            heartbeats: HashMap::new(),
+        };
+        // Slow rise over the last day, fast rise in the last hour
+        context.time_series.insert(
+            "water_level".to_string(),
+            level_series(now, &[(1440, -0.70), (120, -0.66), (60, -0.65), (30, -0.61), (0, -0.57)]),
+        );
+
+        // Max over the last 90 minutes excludes older points
+        let max = resolve_condition_value(&condition(ComparisonOperator::Gt, 0.0, Some("90m")), &context);
+        assert!(matches!(max, Some(AlertValue::Number(v)) if (v + 0.57).abs() < 1e-9));
+
+        // Rate over the last hour: 8 cm/h
+        let rate = resolve_condition_value(&condition(ComparisonOperator::RateAbove, 0.05, Some("1h")), &context)
+            .and_then(|v| v.as_number())
+            .unwrap();
+        assert!((rate - 0.08).abs() < 1e-9);
+        assert!(ComparisonOperator::RateAbove.eval_numeric(rate, 0.05));
+
+        // Rate over the full day stays below the threshold
+        let daily = resolve_condition_value(&condition(ComparisonOperator::RateAbove, 0.05, Some("1d")), &context)
+            .and_then(|v| v.as_number())
+            .unwrap();
+        assert!(daily < 0.05);
+
+        // Without a window the point value takes precedence
+        context.values.insert("water_level".to_string(), AlertValue::Number(-0.40));
+        let point = resolve_condition_value(&condition(ComparisonOperator::Gt, 0.0, None), &context);
+        assert!(matches!(point, Some(AlertValue::Number(v)) if (v + 0.40).abs() < 1e-9));
+        let windowed = resolve_condition_value(&condition(ComparisonOperator::Gt, 0.0, Some("90m")), &context);
+        assert!(matches!(windowed, Some(AlertValue::Number(v)) if (v + 0.57).abs() < 1e-9));
+
+        // Source filter must match the context source
+        let mut filtered = condition(ComparisonOperator::Gt, 0.0, None);
+        filtered.source_filter = Some("GEMAAL_001".to_string());
+        assert!(resolve_condition_value(&filtered, &context).is_none());
+        context.source = Some("GEMAAL_001".to_string());
+        assert!(resolve_condition_value(&filtered, &context).is_some());
+    }
+
+    #[test]
+    fn test_evaluate_no_data() {
+        let now = Utc::now();
+        let cond = condition(ComparisonOperator::NoData, 2.0, Some("1h"));
+        let mut context = EvaluationContext {
+            now,
+            values: HashMap::new(),
+            time_series: HashMap::new(),
+            source: Some("GEMAAL_001".to_string()),
+            source_kind: None,
+            heartbeats: HashMap::new(),
+        };
+
+        // Without delivery state the condition cannot be evaluated
+        assert!(!evaluate_no_data(&cond, &context).passed);
+
+        // 10-minute series, last point 25 minutes ago: limit is 20 minutes
+        context.heartbeats.insert(
+            "water_level".to_string(),
+            SeriesHeartbeat {
+                last_timestamp: Some(now - chrono::Duration::minutes(25)),
+                expected_interval_seconds: Some(600),
+            },
+        );
+        let result = evaluate_no_data(&cond, &context);
+        assert!(result.passed);
+        assert_eq!(result.actual_value.and_then(|v| v.as_number()), Some(25.0));
+        assert_eq!(result.expected_value.as_number(), Some(20.0));
+
+        // Unconfigured series use the one hour window
+        context.heartbeats.get_mut("water_level").unwrap().expected_interval_seconds = None;
+        assert!(!evaluate_no_data(&cond, &context).passed);
+
+        // A registered series that never delivered is silent
+        context.heartbeats.get_mut("water_level").unwrap().last_timestamp = None;
+        assert!(evaluate_no_data(&cond, &context).passed);
+    }
+
+    fn service() -> AlertService {
+        AlertService::new(Arc::new(Database::in_memory()), Arc::new(WebSocketServer::new()))
+    }
+
+    async fn create_test_rule(service: &AlertService, escalation_policy: Option<EscalationPolicy>) -> AlertRule {
+        service
+            .create_rule(
+                CreateAlertRuleRequest {
+                    name: "Hoog peil".to_string(),
+                    description: None,
+                    category: AlertCategory::WaterLevel,
+                    severity: AlertSeverity::Critical,
+                    resource_selector: None,
+                    conditions: vec![condition(ComparisonOperator::Gt, -0.4, None)],
+                    condition_logic: ConditionLogic::And,
+                    cooldown_seconds: 0,
+                    notification_channels: vec![NotificationChannel::WebSocket],
+                    escalation_policy,
+                    auto_resolve_after: None,
+                    flapping_policy: None,
+                    title_template: "Hoog peil".to_string(),
+                    message_template: "Peil boven streefpeil".to_string(),
+                    metadata: None,
+                },
+                None,
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Insert an alert row with timestamps as DuckDB literals.
+    fn insert_alert(
+        service: &AlertService,
+        id: &str,
+        rule: &AlertRule,
+        status: AlertStatus,
+        triggered_at: &str,
+        acknowledged_at: Option<&str>,
+        resolved_at: Option<&str>,
+    ) {
+        service
+            .db
+            .execute(
+                "INSERT INTO alerts (id, rule_id, rule_name, severity, title, message, category,
+                                     status, triggered_at, acknowledged_at, resolved_at)
+                 VALUES (?, ?, ?, ?, 'Hoog peil', '', ?, ?, ?, ?, ?)",
+                &[
+                    &id as &dyn duckdb::ToSql,
+                    &rule.id,
+                    &rule.name,
+                    &rule.severity.as_str(),
+                    &rule.category.as_str(),
+                    &status.as_str(),
+                    &triggered_at,
+                    &acknowledged_at,
+                    &resolved_at,
+                ],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_datetime_trimmed_fraction() {
+        // DuckDB renders 12:00:00.120000 as "12:00:00.12"
+        let at = parse_datetime("2026-01-01 12:00:00.12");
+        assert_eq!(at.to_rfc3339(), "2026-01-01T12:00:00.120+00:00");
+        assert_eq!(
+            parse_datetime("2026-01-01 12:00:00").to_rfc3339(),
+            "2026-01-01T12:00:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_escalation_due_from_trimmed_triggered_at() {
+        let service = service();
+        let policy = EscalationPolicy {
+            min_severity: AlertSeverity::Critical,
+            steps: vec![
+                EscalationStep { after_minutes: 0, channels: vec![NotificationChannel::WebSocket] },
+                EscalationStep { after_minutes: 60, channels: vec![NotificationChannel::WebSocket] },
+            ],
+        };
+        let rule = create_test_rule(&service, Some(policy)).await;
+
+        let triggered = format!("{}.12", (Utc::now() - Duration::minutes(30)).format("%Y-%m-%d %H:%M:%S"));
+        insert_alert(&service, "ALERT_1", &rule, AlertStatus::Active, &triggered, None, None);
+        service
+            .db
+            .execute(
+                "INSERT INTO alert_escalations (alert_id, rule_id, current_step, next_escalation_at)
+                 VALUES ('ALERT_1', ?, 0, ?)",
+                &[&rule.id as &dyn duckdb::ToSql, &triggered],
+            )
+            .unwrap();
+
+        assert_eq!(service.process_escalations().await.unwrap(), 1);
+
+        // The second step is due an hour after triggering, not an hour from now
+        let escalation = service.get_escalation("ALERT_1").await.unwrap().unwrap();
+        assert_eq!(escalation.current_step, 1);
+        assert_eq!(
+            escalation.next_escalation_at,
+            Some(parse_datetime(&triggered) + Duration::minutes(60))
+        );
+        assert_eq!(escalation.next_escalation_at.unwrap().timestamp_subsec_millis(), 120);
+    }
+
+    #[tokio::test]
+    async fn test_trend_report_trimmed_fractions() {
+        let service = service();
+        let rule = create_test_rule(&service, None).await;
+        insert_alert(
+            &service,
+            "ALERT_1",
+            &rule,
+            AlertStatus::Resolved,
+            "2026-01-01 08:00:00.12",
+            Some("2026-01-01 08:05:00.5"),
+            Some("2026-01-01 09:00:00.25"),
+        );
+
+        let start = parse_datetime("2026-01-01 00:00:00");
+        let report = service
+            .get_trend_report(StatsBucket::Day, start, start + Duration::days(3))
+            .await
+            .unwrap();
+
+        // Counted in the day it was triggered, not today
+        assert_eq!(report.periods.len(), 3);
+        assert_eq!(report.periods[0].stats.triggered, 1);
+        assert_eq!(report.totals.triggered, 1);
+        assert!((report.totals.mtta_seconds.unwrap() - 300.38).abs() < 1e-6);
+        assert!((report.totals.mttr_seconds.unwrap() - 3600.13).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge_requires_active_alert() {
+        let service = service();
+        let rule = create_test_rule(&service, None).await;
+        insert_alert(&service, "ALERT_1", &rule, AlertStatus::Active, "2026-01-01 08:00:00", None, None);
+        let request = || AcknowledgeAlertRequest {
+            user_id: "operator".to_string(),
+            comment: None,
+        };
+
+        service.acknowledge_alert("ALERT_1", request()).await.unwrap();
+        let err = service.acknowledge_alert("ALERT_1", request()).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlertServiceError>(),
+            Some(AlertServiceError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_twice_rejected() {
+        let service = service();
+        let rule = create_test_rule(&service, None).await;
+        insert_alert(&service, "ALERT_1", &rule, AlertStatus::Active, "2026-01-01 08:00:00", None, None);
+
+        let resolved = service.resolve_alert("ALERT_1", Some("operator")).await.unwrap();
+        let err = service.resolve_alert("ALERT_1", Some("other")).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlertServiceError>(),
+            Some(AlertServiceError::InvalidState(_))
+        ));
+
+        // The first resolution stands and is recorded once
+        let alert = service.get_alert("ALERT_1").await.unwrap();
+        assert_eq!(
+            alert.resolved_at.map(|at| at.timestamp_micros()),
+            resolved.resolved_at.map(|at| at.timestamp_micros())
+        );
+        let resolutions = service
+            .get_history("ALERT_1")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.to_status == Some(AlertStatus::Resolved))
+            .count();
+        assert_eq!(resolutions, 1);
+    }
 }
@@ -1,14 +1,272 @@
 use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use peilbeheer_core::asset::AssetRegistratie;
 use peilbeheer_core::hydronet::GeoJsonGemaal;
-use reqwest::Client;
+use peilbeheer_core::projectie::{self, Crs};
 use serde::Deserialize;
 use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::http_resilience::HttpClient;
 
 const ARCGIS_BASE: &str = "https://rijnland.enl-mcs.nl/arcgis/rest/services";
 const PAGE_SIZE: u32 = 1000;
 
+/// Requested token lifetime (minutes).
+const TOKEN_EXPIRATION_MINUTES: i64 = 60;
+
+/// A token is refreshed this long before it expires (seconds).
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 120;
+
+/// ArcGIS error codes for an invalid or missing token.
+const TOKEN_ERROR_CODES: [i64; 2] = [498, 499];
+
+/// Credentials for secured ArcGIS services.
+///
+/// With `ARCGIS_TOKEN_URL` set, every request carries a token from either
+/// `generateToken` (`ARCGIS_USERNAME`/`ARCGIS_PASSWORD`) or an OAuth2
+/// token endpoint (`ARCGIS_CLIENT_ID`/`ARCGIS_CLIENT_SECRET`). Public
+/// services accept the token as well.
+#[derive(Debug, Clone)]
+enum ArcGisCredentials {
+    UserPassword {
+        token_url: String,
+        username: String,
+        password: String,
+        referer: String,
+    },
+    ClientCredentials {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+impl ArcGisCredentials {
+    fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let token_url = var("ARCGIS_TOKEN_URL")?;
+        if let (Some(client_id), Some(client_secret)) =
+            (var("ARCGIS_CLIENT_ID"), var("ARCGIS_CLIENT_SECRET"))
+        {
+            return Some(Self::ClientCredentials {
+                token_url,
+                client_id,
+                client_secret,
+            });
+        }
+        match (var("ARCGIS_USERNAME"), var("ARCGIS_PASSWORD")) {
+            (Some(username), Some(password)) => Some(Self::UserPassword {
+                token_url,
+                username,
+                password,
+                referer: var("ARCGIS_REFERER").unwrap_or_else(|| ARCGIS_BASE.to_string()),
+            }),
+            _ => {
+                tracing::warn!(
+                    "ARCGIS_TOKEN_URL is set without credentials, requests are sent without token"
+                );
+                None
+            }
+        }
+    }
+
+    /// Referer the token is bound to; requests with the token must send it.
+    fn referer(&self) -> Option<&str> {
+        match self {
+            Self::UserPassword { referer, .. } => Some(referer),
+            Self::ClientCredentials { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Token of the configured credentials, refreshed before it expires.
+struct TokenProvider {
+    credentials: Option<ArcGisCredentials>,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl TokenProvider {
+    fn shared() -> &'static TokenProvider {
+        static SHARED: OnceLock<TokenProvider> = OnceLock::new();
+        SHARED.get_or_init(|| TokenProvider {
+            credentials: ArcGisCredentials::from_env(),
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Current token, or None without credentials.
+    async fn token(&self, client: &HttpClient) -> Result<Option<String>, String> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(None);
+        };
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref()
+            && token.expires_at - chrono::Duration::seconds(TOKEN_REFRESH_MARGIN_SECS) > Utc::now()
+        {
+            return Ok(Some(token.token.clone()));
+        }
+
+        let token = request_token(client, credentials).await?;
+        tracing::info!("ArcGIS: token vernieuwd, geldig tot {}", token.expires_at);
+        *cached = Some(token.clone());
+        Ok(Some(token.token))
+    }
+
+    /// Drop the cached token after the server rejected it.
+    async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+}
+
+async fn request_token(
+    client: &HttpClient,
+    credentials: &ArcGisCredentials,
+) -> Result<CachedToken, String> {
+    let expiration = TOKEN_EXPIRATION_MINUTES.to_string();
+    let request = match credentials {
+        ArcGisCredentials::UserPassword {
+            token_url,
+            username,
+            password,
+            referer,
+        } => client.post(token_url).form(&[
+            ("username", username.as_str()),
+            ("password", password.as_str()),
+            ("client", "referer"),
+            ("referer", referer.as_str()),
+            ("expiration", expiration.as_str()),
+            ("f", "json"),
+        ]),
+        ArcGisCredentials::ClientCredentials {
+            token_url,
+            client_id,
+            client_secret,
+        } => client.post(token_url).form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("grant_type", "client_credentials"),
+            ("expiration", expiration.as_str()),
+            ("f", "json"),
+        ]),
+    };
+    let response = client
+        .send(request)
+        .await
+        .map_err(|e| format!("ArcGIS token request failed: {e}"))?;
+    if !response.status().is_success() {
+        return Err(format!("ArcGIS token HTTP {}", response.status()));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("ArcGIS token parse failed: {e}"))?;
+    parse_token(&body, Utc::now())
+}
+
+/// Token from a `generateToken` (`token`, `expires` in ms since epoch) or
+/// OAuth2 (`access_token`, `expires_in` in seconds) response.
+fn parse_token(body: &Value, now: DateTime<Utc>) -> Result<CachedToken, String> {
+    if let Some(error) = body.get("error") {
+        return Err(format!("ArcGIS token error: {error}"));
+    }
+    let token = body
+        .get("token")
+        .or_else(|| body.get("access_token"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "ArcGIS token response without token".to_string())?;
+    let expires_at = match (
+        body.get("expires").and_then(|v| v.as_i64()),
+        body.get("expires_in").and_then(|v| v.as_i64()),
+    ) {
+        (Some(ms), _) => DateTime::from_timestamp_millis(ms),
+        (None, Some(secs)) => Some(now + chrono::Duration::seconds(secs)),
+        (None, None) => None,
+    }
+    .unwrap_or(now + chrono::Duration::minutes(TOKEN_EXPIRATION_MINUTES));
+    Ok(CachedToken {
+        token: token.to_string(),
+        expires_at,
+    })
+}
+
+/// Query één pagina van een laag als GeoJSON, met token als dat is
+/// geconfigureerd. Een verlopen of geweigerd token wordt één keer vernieuwd.
+async fn query_page(
+    client: &HttpClient,
+    url: &str,
+    offset: u32,
+    timeout: Duration,
+) -> Result<Value, String> {
+    let tokens = TokenProvider::shared();
+    let mut retried = false;
+    loop {
+        let mut request = client
+            .get(url)
+            .query(&[
+                ("where", "1=1"),
+                ("outFields", "*"),
+                ("f", "geojson"),
+                ("resultOffset", &offset.to_string()),
+                ("resultRecordCount", &PAGE_SIZE.to_string()),
+            ])
+            .header(
+                "User-Agent",
+                "Mozilla/5.0 (compatible; PeilbeheerHHVR/1.0)",
+            )
+            .timeout(timeout);
+        if let Some(token) = tokens.token(client).await? {
+            request = request.query(&[("token", token)]);
+            if let Some(referer) = tokens.credentials.as_ref().and_then(|c| c.referer()) {
+                request = request.header("Referer", referer);
+            }
+        }
+        let response = client
+            .send(request)
+            .await
+            .map_err(|e| format!("request failed: {e}"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            if TOKEN_ERROR_CODES.contains(&(status.as_u16() as i64)) && !retried {
+                tokens.invalidate().await;
+                retried = true;
+                continue;
+            }
+            return Err(format!("HTTP {status}"));
+        }
+
+        let mut body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("parse failed: {e}"))?;
+
+        // ArcGIS reports token errors in the body with HTTP 200
+        if let Some(code) = body.pointer("/error/code").and_then(|c| c.as_i64()) {
+            if TOKEN_ERROR_CODES.contains(&code) && !retried {
+                tokens.invalidate().await;
+                retried = true;
+                continue;
+            }
+            return Err(format!("error {}", body["error"]));
+        }
+
+        // Services published in RD New return RD coordinates, also as GeoJSON
+        projectie::herprojecteer(&mut body, Crs::Wgs84)
+            .map_err(|e| format!("reprojection failed: {e}"))?;
+        return Ok(body);
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ArcGisResponse {
     features: Vec<ArcGisFeature>,
@@ -29,38 +287,17 @@ struct ArcGisGeometry {
 
 /// Haal alle gemalen op van de ArcGIS MapServer met paginatie.
 pub async fn fetch_gemalen_geojson() -> Result<Vec<GeoJsonGemaal>, String> {
-    let client = Client::new();
+    let client = HttpClient::shared();
     let url = format!("{ARCGIS_BASE}/Gemaal/MapServer/0/query");
     let mut all_gemalen = Vec::new();
     let mut offset: u32 = 0;
 
     loop {
-        let response = client
-            .get(&url)
-            .query(&[
-                ("where", "1=1"),
-                ("outFields", "*"),
-                ("f", "geojson"),
-                ("resultOffset", &offset.to_string()),
-                ("resultRecordCount", &PAGE_SIZE.to_string()),
-            ])
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (compatible; PeilbeheerHHVR/1.0)",
-            )
-            .timeout(std::time::Duration::from_secs(30))
-            .send()
+        let body = query_page(&client, &url, offset, Duration::from_secs(30))
             .await
-            .map_err(|e| format!("ArcGIS request failed: {e}"))?;
-
-        if !response.status().is_success() {
-            return Err(format!("ArcGIS HTTP {}", response.status()));
-        }
-
-        let body: ArcGisResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("ArcGIS parse failed: {e}"))?;
+            .map_err(|e| format!("ArcGIS {e}"))?;
+        let body: ArcGisResponse =
+            serde_json::from_value(body).map_err(|e| format!("ArcGIS parse failed: {e}"))?;
 
         let page_count = body.features.len();
 
@@ -127,37 +364,16 @@ pub async fn fetch_layer_assets(
     layer_id: u32,
     layer_type: &str,
 ) -> Result<Vec<AssetRegistratie>, String> {
-    let client = Client::new();
+    let client = HttpClient::shared();
     let url = format!("{ARCGIS_BASE}/{service_name}/MapServer/{layer_id}/query");
     let mut all_assets = Vec::new();
     let mut offset: u32 = 0;
 
     loop {
-        let response = client
-            .get(&url)
-            .query(&[
-                ("where", "1=1"),
-                ("outFields", "*"),
-                ("f", "geojson"),
-                ("resultOffset", &offset.to_string()),
-                ("resultRecordCount", &PAGE_SIZE.to_string()),
-            ])
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (compatible; PeilbeheerHHVR/1.0)",
-            )
-            .timeout(std::time::Duration::from_secs(30))
-            .send()
-            .await
-            .map_err(|e| format!("ArcGIS request failed for {service_name}: {e}"))?;
-
-        if !response.status().is_success() {
-            return Err(format!("ArcGIS HTTP {} for {service_name}", response.status()));
-        }
-
-        let body: ArcGisResponse = response
-            .json()
+        let body = query_page(&client, &url, offset, Duration::from_secs(30))
             .await
+            .map_err(|e| format!("ArcGIS {e} for {service_name}"))?;
+        let body: ArcGisResponse = serde_json::from_value(body)
             .map_err(|e| format!("ArcGIS parse failed for {service_name}: {e}"))?;
 
         let page_count = body.features.len();
@@ -243,7 +459,7 @@ pub async fn fetch_peilgebieden_to_file(
     layer_id: u32,
     output_path: &Path,
 ) -> Result<usize, String> {
-    let client = Client::new();
+    let client = HttpClient::shared();
     let url = format!("{ARCGIS_BASE}/{service_name}/MapServer/{layer_id}/query");
     let mut all_features: Vec<Value> = Vec::new();
     let mut offset: u32 = 0;
@@ -254,32 +470,9 @@ pub async fn fetch_peilgebieden_to_file(
             all_features.len()
         );
 
-        let response = client
-            .get(&url)
-            .query(&[
-                ("where", "1=1"),
-                ("outFields", "*"),
-                ("f", "geojson"),
-                ("resultOffset", &offset.to_string()),
-                ("resultRecordCount", &PAGE_SIZE.to_string()),
-            ])
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (compatible; PeilbeheerHHVR/1.0)",
-            )
-            .timeout(std::time::Duration::from_secs(60))
-            .send()
-            .await
-            .map_err(|e| format!("ArcGIS peilgebieden request failed: {e}"))?;
-
-        if !response.status().is_success() {
-            return Err(format!("ArcGIS peilgebieden HTTP {}", response.status()));
-        }
-
-        let body: Value = response
-            .json()
+        let body = query_page(&client, &url, offset, Duration::from_secs(60))
             .await
-            .map_err(|e| format!("ArcGIS peilgebieden parse failed: {e}"))?;
+            .map_err(|e| format!("ArcGIS peilgebieden {e}"))?;
 
         let features = body
             .get("features")
@@ -319,3 +512,41 @@ pub async fn fetch_peilgebieden_to_file(
 
     Ok(total)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_token() {
+        let now = DateTime::parse_from_rfc3339("2025-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        // generateToken: expires in ms since epoch
+        let token = parse_token(
+            &serde_json::json!({"token": "abc", "expires": 1741611600000i64, "ssl": true}),
+            now,
+        )
+        .unwrap();
+        assert_eq!(token.token, "abc");
+        assert_eq!(token.expires_at.to_rfc3339(), "2025-03-10T13:00:00+00:00");
+
+        // OAuth2 client credentials
+        let token = parse_token(
+            &serde_json::json!({"access_token": "xyz", "expires_in": 7200}),
+            now,
+        )
+        .unwrap();
+        assert_eq!(token.token, "xyz");
+        assert_eq!(token.expires_at, now + chrono::Duration::hours(2));
+
+        assert!(
+            parse_token(
+                &serde_json::json!({"error": {"code": 400, "message": "Unable to generate token."}}),
+                now
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! Audit logging of mutating API calls.
+//!
+//! [`record_mutations`] is a middleware on the API router. For every POST,
+//! PUT, PATCH and DELETE request it identifies the caller from the request
+//! headers, summarizes the request body (see
+//! [`peilbeheer_core::audit::summarize_payload`]) and stores the outcome in
+//! the `audit_log` table once the handler has responded. Admins search the
+//! log at `GET /api/audit`.
+
+use anyhow::Result as AnyhowResult;
+use axum::{
+    body::Body,
+    extract::{Extension, OriginalUri, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Instant;
+
+use peilbeheer_core::audit::{is_audited_method, is_success_status, summarize_payload};
+use peilbeheer_core::{AuditEntry, AuditQuery};
+
+use crate::auth_service::AuthService;
+use crate::db::Database;
+use crate::routes::auth::authenticate;
+
+/// Largest request body that is buffered for auditing (same as axum's JSON limit)
+const MAX_AUDITED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default and maximum number of entries returned by a search
+const DEFAULT_QUERY_LIMIT: u64 = 100;
+const MAX_QUERY_LIMIT: u64 = 1000;
+
+/// Audit log service.
+pub struct AuditService {
+    db: Arc<Database>,
+}
+
+impl AuditService {
+    /// Create a new audit service.
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Store an audit entry.
+    pub fn record(&self, entry: &AuditEntry) -> AnyhowResult<()> {
+        self.db.execute(
+            "INSERT INTO audit_log (id, occurred_at, user_id, username, method, path,
+                                    payload_summary, status_code, success, duration_ms)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            &[
+                &entry.id as &dyn duckdb::ToSql,
+                &format_datetime(entry.occurred_at),
+                &entry.user_id,
+                &entry.username,
+                &entry.method,
+                &entry.path,
+                &entry.payload_summary,
+                &(entry.status_code as i32),
+                &entry.success,
+                &(entry.duration_ms as i64),
+            ],
+        )
+    }
+
+    /// Search the audit log, most recent first.
+    pub fn query(&self, query: &AuditQuery) -> AnyhowResult<Vec<AuditEntry>> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
+
+        if let Some(user) = &query.user {
+            conditions.push("(user_id = ? OR username = ?)");
+            params.push(Box::new(user.clone()));
+            params.push(Box::new(user.clone()));
+        }
+        if let Some(method) = &query.method {
+            conditions.push("method = ?");
+            params.push(Box::new(method.to_ascii_uppercase()));
+        }
+        if let Some(path) = &query.path {
+            conditions.push("contains(path, ?)");
+            params.push(Box::new(path.clone()));
+        }
+        if let Some(search) = &query.search {
+            conditions.push("contains(lower(payload_summary), lower(?))");
+            params.push(Box::new(search.clone()));
+        }
+        if let Some(success) = query.success {
+            conditions.push("success = ?");
+            params.push(Box::new(success));
+        }
+        if let Some(start) = &query.start_time {
+            conditions.push("occurred_at >= ?");
+            params.push(Box::new(format_datetime(*start)));
+        }
+        if let Some(end) = &query.end_time {
+            conditions.push("occurred_at <= ?");
+            params.push(Box::new(format_datetime(*end)));
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT);
+        let offset = query.offset.unwrap_or(0);
+
+        let sql = format!(
+            "SELECT id, CAST(occurred_at AS VARCHAR), user_id, username, method, path,
+                    payload_summary, status_code, success, duration_ms
+             FROM audit_log
+             {}
+             ORDER BY occurred_at DESC
+             LIMIT {} OFFSET {}",
+            where_clause, limit, offset
+        );
+
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.query(&sql, &param_refs, |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                occurred_at: parse_datetime(&row.get::<_, String>(1)?),
+                user_id: row.get(2)?,
+                username: row.get(3)?,
+                method: row.get(4)?,
+                path: row.get(5)?,
+                payload_summary: row.get(6)?,
+                status_code: row.get::<_, i32>(7)? as u16,
+                success: row.get(8)?,
+                duration_ms: row.get::<_, i64>(9)? as u64,
+            })
+        })
+    }
+}
+
+/// Middleware recording every mutating request in the audit log.
+///
+/// Failing to store an entry is logged but does not fail the request.
+pub async fn record_mutations(
+    Extension(audit): Extension<Arc<AuditService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_audited_method(request.method().as_str()) {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let occurred_at = Utc::now();
+    let method = request.method().to_string();
+    // Within the nested API router the URI lacks the `/api` prefix
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let caller = authenticate(&auth, request.headers()).ok();
+
+    let (parts, body) = request.into_parts();
+    let (payload_summary, response) = match axum::body::to_bytes(body, MAX_AUDITED_BODY_BYTES).await
+    {
+        Ok(bytes) => {
+            let summary = summarize_payload(&bytes);
+            let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+            (summary, response)
+        }
+        Err(_) => (None, StatusCode::PAYLOAD_TOO_LARGE.into_response()),
+    };
+
+    let status_code = response.status().as_u16();
+    let entry = AuditEntry {
+        id: format!("aud_{}", uuid::Uuid::new_v4().simple()),
+        occurred_at,
+        user_id: caller.as_ref().map(|claims| claims.sub.clone()),
+        username: caller.map(|claims| claims.username),
+        method,
+        path,
+        payload_summary,
+        status_code,
+        success: is_success_status(status_code),
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    if let Err(e) = audit.record(&entry) {
+        tracing::warn!("Failed to record audit entry for {} {}: {}", entry.method, entry.path, e);
+    }
+
+    response
+}
+
+/// Helper: Format datetime for DuckDB.
+fn format_datetime(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+/// Helper: Parse datetime from DuckDB.
+fn parse_datetime(s: &str) -> DateTime<Utc> {
+    // `CAST(... AS VARCHAR)` drops trailing zeros of the fraction
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .map(|ndt| ndt.and_utc())
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_datetime_trimmed_fraction() {
+        // DuckDB renders 12:00:00.120000 as "12:00:00.12"
+        let at = parse_datetime("2026-01-01 12:00:00.12");
+        assert_eq!(at.to_rfc3339(), "2026-01-01T12:00:00.120+00:00");
+        assert_eq!(
+            parse_datetime("2026-01-01 12:00:00").to_rfc3339(),
+            "2026-01-01T12:00:00+00:00"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! External password authentication backends.
+//!
+//! Besides local accounts, [`crate::auth_service::AuthService`] can check
+//! passwords against a directory through an [`AuthBackend`]. Users unknown
+//! to the API, and users provisioned by the backend earlier, are
+//! authenticated by the backend; they are provisioned at their first login
+//! and their role follows their directory groups. Local accounts keep
+//! their own password. See [`crate::ldap`] for the LDAP/AD backend.
+
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use thiserror::Error;
+
+use peilbeheer_core::Role;
+
+use crate::ldap::{LdapBackend, LdapConfig};
+
+/// A user authenticated by a backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    /// Username as known in the directory
+    pub username: String,
+    pub email: String,
+    pub full_name: Option<String>,
+    /// Role granted by the user's groups (None = no mapped group)
+    pub role: Option<Role>,
+}
+
+/// Backend errors.
+#[derive(Debug, Error)]
+pub enum BackendError {
+    #[error("directory unavailable: {0}")]
+    Unavailable(String),
+    #[error("invalid directory entry: {0}")]
+    InvalidEntry(String),
+    #[error("no role is mapped to the user's groups")]
+    NoRole,
+}
+
+/// Checks username/password combinations against an external directory.
+pub trait AuthBackend: Send + Sync {
+    /// Name stored as `auth_provider` of the users it provisions.
+    fn name(&self) -> &str;
+
+    /// Role of new users without a mapped group (None = login refused).
+    fn default_role(&self) -> Option<Role>;
+
+    /// Identity of the user, or `None` when the credentials are rejected.
+    fn authenticate<'a>(
+        &'a self,
+        username: &'a str,
+        password: &'a str,
+    ) -> BoxFuture<'a, Result<Option<ExternalIdentity>, BackendError>>;
+}
+
+/// Backend configured by the environment, if any.
+pub fn from_config(ldap: &LdapConfig) -> Option<Arc<dyn AuthBackend>> {
+    ldap.is_enabled()
+        .then(|| Arc::new(LdapBackend::new(ldap.clone())) as Arc<dyn AuthBackend>)
+}
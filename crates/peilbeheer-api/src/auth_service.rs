@@ -1,25 +1,77 @@
 //! Authentication and authorization service.
 //!
 //! This module provides JWT token generation, user management,
-//! and password hashing for the Peilbeheer API.
+//! and password hashing for the Peilbeheer API. Users can also log in via
+//! OIDC single sign-on (see [`crate::oidc`]); they are provisioned at their
+//! first login with a role derived from their AD groups.
+//!
+//! Access tokens are JWTs, valid for 24 hours by default
+//! ([`AuthServiceConfig::token_expiration_hours`]); revoking their session
+//! ends them early. Each login also returns a single-use
+//! refresh token; refreshing rotates it within its token family, and reuse of
+//! an already exchanged token revokes the whole family.
+//!
+//! Each token family is registered as a session with the client it was used
+//! from. Access tokens carry the session ID, so users (and admins) can list
+//! sessions and revoke them one by one, effective immediately.
+//!
+//! Users who forgot their password request a short-lived, single-use reset
+//! token by e-mail (see [`crate::password_reset`]); setting a new password
+//! with it ends all their sessions.
+//!
+//! Service accounts for integrations cannot log in; they authenticate with
+//! API keys bound to them, which stop working when the account is disabled
+//! or expires.
+//!
+//! Users can enable TOTP two-factor authentication (see [`crate::totp`]);
+//! password logins then also require a code or a recovery code. An admin can
+//! require it per role. Single sign-on logins leave the second factor to the
+//! identity provider.
+//!
+//! Password logins can also be checked against a directory through an
+//! [`AuthBackend`] such as LDAP/AD (see [`crate::auth_backend`]). Directory
+//! users are provisioned at their first login like single sign-on users;
+//! their password stays in the directory, so it cannot be changed or reset
+//! here.
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use std::sync::Arc;
 use thiserror::Error;
 
+use peilbeheer_core::auth::API_KEY_PREFIX;
 use peilbeheer_core::{
-    Claims, CreateUserRequest, LoginRequest, LoginResponse, Permission, Role,
-    UpdateUserRequest, User, UserInfo,
+    AccountType, ApiKey, Claims, ClientInfo, CreateApiKeyRequest, CreateUserRequest, CreatedApiKey,
+    LoginRequest, LoginResponse, Permission, RecoveryCodes, RefreshToken, RefreshTokenState,
+    ResourceScope, Role, Session, TotpEnrollment, TwoFactorPolicy, UpdateUserRequest, User,
+    UserInfo,
 };
 
-use crate::db::Database;
+use crate::auth_backend::{self, AuthBackend, BackendError, ExternalIdentity};
+use crate::db::{Database, is_no_rows};
+use crate::ldap::LdapConfig;
+use crate::oidc::{OidcClient, OidcConfig, OidcError, OidcIdentity, role_for_groups};
+use crate::totp;
 
 /// JWT secret key (loaded from environment)
 const JWT_SECRET_ENV: &str = "JWT_SECRET";
 const DEFAULT_JWT_SECRET: &str = "change-this-secret-in-production";
 /// Token expiration time (24 hours)
 const TOKEN_EXPIRATION_HOURS: i64 = 24;
+/// Minimum time between `last_used_at` updates of an API key (seconds)
+const API_KEY_TOUCH_INTERVAL_SECS: i64 = 60;
+/// Refresh token lifetime (days), overridable via environment
+const REFRESH_TOKEN_EXPIRATION_ENV: &str = "REFRESH_TOKEN_EXPIRATION_DAYS";
+const REFRESH_TOKEN_EXPIRATION_DAYS: i64 = 30;
+/// Password reset token lifetime (minutes), overridable via environment
+const PASSWORD_RESET_TOKEN_MINUTES_ENV: &str = "PASSWORD_RESET_TOKEN_MINUTES";
+const PASSWORD_RESET_TOKEN_MINUTES: i64 = 30;
+/// Lifetime of a token that only allows two-factor enrolment (minutes)
+const SETUP_TOKEN_MINUTES: i64 = 15;
+/// Issuer shown in authenticator apps
+const TOTP_ISSUER: &str = "Peilbeheer HHVR";
+/// `auth_settings` key of the two-factor policy
+const TWO_FACTOR_POLICY_KEY: &str = "two_factor_policy";
 
 /// Authentication service configuration.
 #[derive(Debug, Clone)]
@@ -28,6 +80,14 @@ pub struct AuthServiceConfig {
     pub jwt_secret: String,
     /// Token expiration time in hours
     pub token_expiration_hours: i64,
+    /// Refresh token expiration time in days
+    pub refresh_token_expiration_days: i64,
+    /// Password reset token expiration time in minutes
+    pub password_reset_token_minutes: i64,
+    /// Single sign-on configuration
+    pub oidc: OidcConfig,
+    /// LDAP/AD password authentication configuration
+    pub ldap: LdapConfig,
 }
 
 impl Default for AuthServiceConfig {
@@ -36,6 +96,16 @@ impl Default for AuthServiceConfig {
             jwt_secret: std::env::var(JWT_SECRET_ENV)
                 .unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string()),
             token_expiration_hours: TOKEN_EXPIRATION_HOURS,
+            refresh_token_expiration_days: std::env::var(REFRESH_TOKEN_EXPIRATION_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(REFRESH_TOKEN_EXPIRATION_DAYS),
+            password_reset_token_minutes: std::env::var(PASSWORD_RESET_TOKEN_MINUTES_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(PASSWORD_RESET_TOKEN_MINUTES),
+            oidc: OidcConfig::default(),
+            ldap: LdapConfig::default(),
         }
     }
 }
@@ -62,6 +132,30 @@ pub enum AuthError {
     DatabaseError(#[from] anyhow::Error),
     #[error("JWT error: {0}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
+    #[error("Single sign-on failed: {0}")]
+    Oidc(#[from] OidcError),
+    #[error("Directory login failed: {0}")]
+    Backend(#[from] BackendError),
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("API key not found: {0}")]
+    ApiKeyNotFound(String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Invalid refresh token")]
+    InvalidRefreshToken,
+    #[error("Refresh token reused, session revoked")]
+    RefreshTokenReused,
+    #[error("Two-factor code required")]
+    TwoFactorRequired,
+    #[error("Invalid two-factor code")]
+    InvalidTwoFactorCode,
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+    #[error("Session revoked")]
+    SessionRevoked,
+    #[error("Invalid or expired password reset token")]
+    InvalidResetToken,
 }
 
 /// Authentication service.
@@ -69,8 +163,9 @@ pub struct AuthService {
     db: Arc<Database>,
     config: AuthServiceConfig,
     encoding_key: EncodingKey,
-    #[allow(dead_code)]
     decoding_key: DecodingKey,
+    oidc: Option<OidcClient>,
+    backend: Option<Arc<dyn AuthBackend>>,
 }
 
 impl AuthService {
@@ -78,12 +173,19 @@ impl AuthService {
     pub fn new(db: Arc<Database>, config: AuthServiceConfig) -> anyhow::Result<Self> {
         let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_ref());
         let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_ref());
+        let oidc = config
+            .oidc
+            .is_enabled()
+            .then(|| OidcClient::new(config.oidc.clone()));
+        let backend = auth_backend::from_config(&config.ldap);
 
         Ok(Self {
             db,
             config,
             encoding_key,
             decoding_key,
+            oidc,
+            backend,
         })
     }
 
@@ -117,47 +219,651 @@ impl AuthService {
     }
 
     /// Login a user and return a JWT token.
-    pub fn login(&self, req: &LoginRequest) -> Result<LoginResponse, AuthError> {
+    ///
+    /// Local accounts are checked against their stored password. Unknown
+    /// users and users of the configured [`AuthBackend`] are checked by the
+    /// backend and provisioned or synced on success.
+    pub async fn login(
+        &self,
+        req: &LoginRequest,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AuthError> {
         // Get user from database
-        let user = self.get_user_by_username(&req.username)?
+        let existing = self.get_user_by_username(&req.username)?
+            .filter(|user| !user.is_service_account());
+
+        let user = match (existing, &self.backend) {
+            (Some(user), Some(backend)) if self.auth_provider(&user.id)? == backend.name() => {
+                self.backend_login(backend.as_ref(), req, Some(user)).await?
+            }
+            (Some(user), _) => {
+                // Check if user is active
+                if !user.is_active {
+                    return Err(AuthError::UserInactive);
+                }
+
+                // Verify password (in production, compare with hashed password from DB)
+                // For now, we'll store the password hash in a separate column or validate externally
+                // This is a simplified version - in production, use proper password hashing
+                let stored_hash = self.get_password_hash(&user.id)?;
+                if !Self::verify_password(&req.password, &stored_hash) {
+                    return Err(AuthError::InvalidCredentials);
+                }
+                user
+            }
+            (None, Some(backend)) => self.backend_login(backend.as_ref(), req, None).await?,
+            (None, None) => return Err(AuthError::InvalidCredentials),
+        };
+
+        if user.two_factor_enabled {
+            let code = req.totp_code.as_deref().ok_or(AuthError::TwoFactorRequired)?;
+            self.verify_second_factor(&user.id, code)?;
+        } else if self.two_factor_policy()?.requires(user.get_role()) {
+            return self.issue_setup_token(user);
+        }
+
+        // Update last login
+        let _ = self.update_last_login(&user.id);
+
+        self.issue_token(user, client)
+    }
+
+    /// Check a password login with the backend and provision or sync the user.
+    async fn backend_login(
+        &self,
+        backend: &dyn AuthBackend,
+        req: &LoginRequest,
+        existing: Option<User>,
+    ) -> Result<User, AuthError> {
+        let identity = backend
+            .authenticate(&req.username, &req.password)
+            .await?
             .ok_or(AuthError::InvalidCredentials)?;
 
-        // Check if user is active
+        let user = self.provision_backend_user(backend, &identity, existing)?;
         if !user.is_active {
             return Err(AuthError::UserInactive);
         }
+        tracing::info!("{} login: {} ({})", backend.name(), user.username, user.role);
+        Ok(user)
+    }
 
-        // Verify password (in production, compare with hashed password from DB)
-        // For now, we'll store the password hash in a separate column or validate externally
-        // This is a simplified version - in production, use proper password hashing
-        let stored_hash = self.get_password_hash(&user.id)?;
-        if !Self::verify_password(&req.password, &stored_hash) {
-            return Err(AuthError::InvalidCredentials);
+    /// Find or create the user for a directory identity and sync its role.
+    ///
+    /// Users without a mapped group keep their current role; new users get
+    /// the backend's default role. Local accounts are never taken over.
+    fn provision_backend_user(
+        &self,
+        backend: &dyn AuthBackend,
+        identity: &ExternalIdentity,
+        existing: Option<User>,
+    ) -> Result<User, AuthError> {
+        let existing = match existing {
+            Some(user) => Some(user),
+            None => self.get_user_by_username(&identity.username)?,
+        };
+        let now_str = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+
+        if let Some(user) = existing {
+            if user.is_service_account() || self.auth_provider(&user.id)? != backend.name() {
+                return Err(AuthError::UserAlreadyExists(user.username));
+            }
+            let role = identity.role.map(|r| r.as_str().to_string()).unwrap_or(user.role);
+            self.db.execute(
+                "UPDATE users
+                 SET role = ?, email = ?, full_name = COALESCE(?, full_name), updated_at = ?
+                 WHERE id = ?",
+                &[
+                    &role as &dyn duckdb::ToSql,
+                    &identity.email,
+                    &identity.full_name,
+                    &now_str,
+                    &user.id,
+                ],
+            )?;
+            return self.get_user_by_id(&user.id)?
+                .ok_or_else(|| AuthError::UserNotFound(user.id.clone()));
         }
 
-        // Update last login
-        let _ = self.update_last_login(&user.id);
+        let role = identity
+            .role
+            .or(backend.default_role())
+            .ok_or(BackendError::NoRole)?;
+        if self.get_user_by_email(&identity.email)?.is_some() {
+            return Err(AuthError::UserAlreadyExists(identity.email.clone()));
+        }
 
-        // Generate JWT token
+        let id = Self::generate_user_id();
+        // The password stays in the directory; an empty hash never matches
+        self.db.execute(
+            "INSERT INTO users (
+                id, username, email, full_name, password_hash, role, custom_permissions,
+                created_at, is_active, auth_provider
+            ) VALUES (?, ?, ?, ?, '', ?, '[]', ?, TRUE, ?)",
+            &[
+                &id as &dyn duckdb::ToSql,
+                &identity.username,
+                &identity.email,
+                &identity.full_name,
+                &role.as_str(),
+                &now_str,
+                &backend.name(),
+            ],
+        )?;
+        tracing::info!(
+            "Provisioned {} user {} as {}",
+            backend.name(),
+            identity.username,
+            role.as_str()
+        );
+
+        self.get_user_by_id(&id)?
+            .ok_or(AuthError::UserNotFound(id))
+    }
+
+    /// Login provider of a user: `local`, `oidc` or the name of a backend.
+    fn auth_provider(&self, id: &str) -> Result<String, AuthError> {
+        Ok(self.db.query_row(
+            "SELECT COALESCE(auth_provider, 'local') FROM users WHERE id = ?",
+            &[&id],
+            |row| row.get::<_, String>(0),
+        )?)
+    }
+
+    /// Whether the password of a user is kept by the authentication backend.
+    fn has_backend_password(&self, id: &str) -> Result<bool, AuthError> {
+        Ok(match &self.backend {
+            Some(backend) => self.auth_provider(id)? == backend.name(),
+            None => false,
+        })
+    }
+
+    /// Generate a JWT token and a refresh token in a new session.
+    fn issue_token(&self, user: User, client: &ClientInfo) -> Result<LoginResponse, AuthError> {
+        let family_id = format!("rtf_{}", uuid::Uuid::new_v4().simple());
+        self.issue_token_in_family(user, &family_id, client)
+            .map(|(response, _)| response)
+    }
+
+    /// Generate a JWT token and a refresh token in the given token family,
+    /// and record the use of its session.
+    ///
+    /// Returns the response and the ID of the stored refresh token.
+    fn issue_token_in_family(
+        &self,
+        user: User,
+        family_id: &str,
+        client: &ClientInfo,
+    ) -> Result<(LoginResponse, String), AuthError> {
+        let (refresh_token, refresh_token_id) = self.store_refresh_token(&user.id, family_id)?;
+        self.record_session_use(family_id, &user.id, client)?;
         let exp = Utc::now()
             .checked_add_signed(Duration::hours(self.config.token_expiration_hours))
             .unwrap()
             .timestamp();
 
-        let claims = Claims::from_user(&user, exp);
+        let mut claims = Claims::from_user(&user, exp);
+        claims.sid = Some(family_id.to_string());
 
         let token = encode(&Header::default(), &claims, &self.encoding_key)?;
 
-        Ok(LoginResponse {
+        let response = LoginResponse {
             access_token: token,
             token_type: "Bearer".to_string(),
             expires_in: self.config.token_expiration_hours * 3600,
+            refresh_token: Some(refresh_token),
+            two_factor_setup_required: false,
+            user: UserInfo::from(user),
+        };
+        Ok((response, refresh_token_id))
+    }
+
+    /// Generate a token for a user who must enrol in two-factor authentication.
+    ///
+    /// The token carries no permissions, so it only gives access to the
+    /// enrolment endpoints, and comes without refresh token.
+    fn issue_setup_token(&self, user: User) -> Result<LoginResponse, AuthError> {
+        let exp = (Utc::now() + Duration::minutes(SETUP_TOKEN_MINUTES)).timestamp();
+        let mut claims = Claims::from_user(&user, exp);
+        claims.permissions.clear();
+
+        let token = encode(&Header::default(), &claims, &self.encoding_key)?;
+
+        Ok(LoginResponse {
+            access_token: token,
+            token_type: "Bearer".to_string(),
+            expires_in: SETUP_TOKEN_MINUTES * 60,
+            refresh_token: None,
+            two_factor_setup_required: true,
             user: UserInfo::from(user),
         })
     }
 
+    /// Create and store a new refresh token; returns the token and its ID.
+    fn store_refresh_token(
+        &self,
+        user_id: &str,
+        family_id: &str,
+    ) -> Result<(String, String), AuthError> {
+        let token = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let id = format!("rt_{}", uuid::Uuid::new_v4().simple());
+        let now = Utc::now();
+        let expires_at = now + Duration::days(self.config.refresh_token_expiration_days);
+
+        self.db.execute(
+            "INSERT INTO refresh_tokens (id, family_id, user_id, token_hash, created_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            &[
+                &id as &dyn duckdb::ToSql,
+                &family_id,
+                &user_id,
+                &Self::hash_token(&token),
+                &format_timestamp(now),
+                &format_timestamp(expires_at),
+            ],
+        )?;
+
+        Ok((token, id))
+    }
+
+    /// Exchange a refresh token for a new access token and refresh token.
+    ///
+    /// The presented token is used up. Presenting a token that was already
+    /// exchanged means it was copied, so the whole family is revoked and
+    /// the legitimate holder has to log in again.
+    pub fn refresh(&self, token: &str, client: &ClientInfo) -> Result<LoginResponse, AuthError> {
+        let result = self.db.query_row(
+            "SELECT id, family_id, user_id, CAST(created_at AS VARCHAR), CAST(expires_at AS VARCHAR),
+                    CAST(used_at AS VARCHAR), CAST(revoked_at AS VARCHAR)
+             FROM refresh_tokens WHERE token_hash = ?",
+            &[&Self::hash_token(token)],
+            refresh_token_from_row,
+        );
+        let stored = match result {
+            Ok(stored) => stored,
+            Err(e) if is_no_rows(&e) => {
+                return Err(AuthError::InvalidRefreshToken);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Utc::now();
+        match stored.state(now) {
+            RefreshTokenState::Valid => {}
+            RefreshTokenState::Reused => {
+                tracing::warn!(
+                    "Refresh token of user {} reused, revoking token family {}",
+                    stored.user_id,
+                    stored.family_id
+                );
+                self.revoke_session(&stored.family_id)?;
+                return Err(AuthError::RefreshTokenReused);
+            }
+            RefreshTokenState::Expired | RefreshTokenState::Revoked => {
+                return Err(AuthError::InvalidRefreshToken);
+            }
+        }
+
+        // Mark used before issuing; a concurrent exchange that got there
+        // first leaves no row to update, which counts as reuse
+        if !self.mark_refresh_token_used(&stored.id, now)? {
+            tracing::warn!(
+                "Refresh token of user {} exchanged concurrently, revoking token family {}",
+                stored.user_id,
+                stored.family_id
+            );
+            self.revoke_session(&stored.family_id)?;
+            return Err(AuthError::RefreshTokenReused);
+        }
+
+        let user = self
+            .get_user_by_id(&stored.user_id)?
+            .ok_or(AuthError::InvalidRefreshToken)?;
+        if !user.is_active {
+            self.revoke_session(&stored.family_id)?;
+            return Err(AuthError::UserInactive);
+        }
+        // Sessions from before 2FA became required end here
+        if !user.two_factor_enabled && self.two_factor_policy()?.requires(user.get_role()) {
+            self.revoke_session(&stored.family_id)?;
+            return Err(AuthError::InvalidRefreshToken);
+        }
+
+        let (response, successor_id) =
+            self.issue_token_in_family(user, &stored.family_id, client)?;
+        self.db.execute(
+            "UPDATE refresh_tokens SET replaced_by = ? WHERE id = ?",
+            &[&successor_id as &dyn duckdb::ToSql, &stored.id],
+        )?;
+
+        Ok(response)
+    }
+
+    /// Mark a refresh token used; false when it already was.
+    fn mark_refresh_token_used(&self, id: &str, now: DateTime<Utc>) -> Result<bool, AuthError> {
+        let updated = self.db.execute_count(
+            "UPDATE refresh_tokens SET used_at = ? WHERE id = ? AND used_at IS NULL",
+            &[&format_timestamp(now) as &dyn duckdb::ToSql, &id],
+        )?;
+        Ok(updated == 1)
+    }
+
+    /// Revoke the token family of a refresh token (logout).
+    ///
+    /// Unknown tokens are ignored.
+    pub fn revoke_refresh_token(&self, token: &str) -> Result<(), AuthError> {
+        let result = self.db.query_row(
+            "SELECT family_id FROM refresh_tokens WHERE token_hash = ?",
+            &[&Self::hash_token(token)],
+            |row| row.get::<_, String>(0),
+        );
+        match result {
+            Ok(family_id) => self.revoke_session(&family_id),
+            Err(e) if is_no_rows(&e) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Register a login or refresh in the session of a token family.
+    ///
+    /// Creates the session at the first use; token families from before
+    /// sessions were registered get one at their next refresh.
+    fn record_session_use(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        client: &ClientInfo,
+    ) -> Result<(), AuthError> {
+        let now = Utc::now();
+        let expires_at = now + Duration::days(self.config.refresh_token_expiration_days);
+        self.db.execute(
+            "INSERT INTO sessions (id, user_id, created_at, last_used_at, expires_at, user_agent, ip_address)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET
+                last_used_at = excluded.last_used_at,
+                expires_at = excluded.expires_at,
+                user_agent = COALESCE(excluded.user_agent, sessions.user_agent),
+                ip_address = COALESCE(excluded.ip_address, sessions.ip_address)",
+            &[
+                &session_id as &dyn duckdb::ToSql,
+                &user_id,
+                &format_timestamp(now),
+                &format_timestamp(now),
+                &format_timestamp(expires_at),
+                &client.user_agent,
+                &client.ip_address,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Revoke a session and all refresh tokens of its token family.
+    fn revoke_session(&self, session_id: &str) -> Result<(), AuthError> {
+        let now = format_timestamp(Utc::now());
+        self.db.execute(
+            "UPDATE refresh_tokens SET revoked_at = ? WHERE family_id = ? AND revoked_at IS NULL",
+            &[&now as &dyn duckdb::ToSql, &session_id],
+        )?;
+        self.db.execute(
+            "UPDATE sessions SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL",
+            &[&now as &dyn duckdb::ToSql, &session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Revoke all sessions of a user, except `keep` if given.
+    ///
+    /// Returns the number of active sessions that were ended.
+    pub fn revoke_user_sessions(
+        &self,
+        user_id: &str,
+        keep: Option<&str>,
+    ) -> Result<usize, AuthError> {
+        let now = format_timestamp(Utc::now());
+        let ended = self.db.query_row(
+            "SELECT COUNT(*) FROM sessions
+             WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ? AND id IS DISTINCT FROM ?",
+            &[&user_id as &dyn duckdb::ToSql, &now, &keep],
+            |row| row.get::<_, i64>(0),
+        )?;
+        self.db.execute(
+            "UPDATE refresh_tokens SET revoked_at = ?
+             WHERE user_id = ? AND revoked_at IS NULL AND family_id IS DISTINCT FROM ?",
+            &[&now as &dyn duckdb::ToSql, &user_id, &keep],
+        )?;
+        self.db.execute(
+            "UPDATE sessions SET revoked_at = ?
+             WHERE user_id = ? AND revoked_at IS NULL AND id IS DISTINCT FROM ?",
+            &[&now as &dyn duckdb::ToSql, &user_id, &keep],
+        )?;
+        Ok(ended as usize)
+    }
+
+    /// Revoke one session of a user.
+    pub fn revoke_user_session(&self, user_id: &str, session_id: &str) -> Result<(), AuthError> {
+        let owned = self.db.query_row(
+            "SELECT COUNT(*) FROM sessions WHERE id = ? AND user_id = ?",
+            &[&session_id as &dyn duckdb::ToSql, &user_id],
+            |row| row.get::<_, i64>(0),
+        )?;
+        if owned == 0 {
+            return Err(AuthError::SessionNotFound(session_id.to_string()));
+        }
+        self.revoke_session(session_id)
+    }
+
+    /// Active sessions of a user, most recently used first.
+    ///
+    /// `current` marks the session of the requesting token.
+    pub fn list_sessions(
+        &self,
+        user_id: &str,
+        current: Option<&str>,
+    ) -> Result<Vec<Session>, AuthError> {
+        let sessions = self.db.query(
+            "SELECT id, user_id, CAST(created_at AS VARCHAR), CAST(last_used_at AS VARCHAR),
+                    CAST(expires_at AS VARCHAR), user_agent, ip_address
+             FROM sessions
+             WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ?
+             ORDER BY last_used_at DESC",
+            &[&user_id as &dyn duckdb::ToSql, &format_timestamp(Utc::now())],
+            |row| {
+                let id: String = row.get(0)?;
+                Ok(Session {
+                    current: current == Some(id.as_str()),
+                    id,
+                    user_id: row.get(1)?,
+                    created_at: parse_timestamp(&row.get::<_, String>(2)?),
+                    last_used_at: parse_timestamp(&row.get::<_, String>(3)?),
+                    expires_at: parse_timestamp(&row.get::<_, String>(4)?),
+                    user_agent: row.get(5)?,
+                    ip_address: row.get(6)?,
+                })
+            },
+        )?;
+        Ok(sessions)
+    }
+
+    /// Whether a session exists and has not been revoked.
+    fn session_is_active(&self, session_id: &str) -> Result<bool, AuthError> {
+        let active = self.db.query_row(
+            "SELECT COUNT(*) FROM sessions WHERE id = ? AND revoked_at IS NULL",
+            &[&session_id],
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(active > 0)
+    }
+
+    /// Whether OIDC single sign-on is configured.
+    pub fn oidc_enabled(&self) -> bool {
+        self.oidc.is_some()
+    }
+
+    /// Frontend URL that receives the token after a single sign-on login.
+    pub fn oidc_post_login_redirect(&self) -> Option<&str> {
+        self.config.oidc.post_login_redirect.as_deref()
+    }
+
+    /// Start a single sign-on login: URL of the provider's login page and
+    /// the `Set-Cookie` value binding the login to the browser.
+    pub async fn oidc_authorization_url(&self) -> Result<(String, String), AuthError> {
+        let oidc = self.oidc.as_ref().ok_or(OidcError::NotConfigured)?;
+        Ok(oidc.authorization_url().await?)
+    }
+
+    /// Finish a single sign-on login and return a JWT token.
+    ///
+    /// The user is provisioned at the first login. The role follows the
+    /// user's AD groups at every login; users without a mapped group get
+    /// the configured default role, or keep their current role.
+    pub async fn oidc_login(
+        &self,
+        code: &str,
+        state: &str,
+        state_cookie: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AuthError> {
+        let oidc = self.oidc.as_ref().ok_or(OidcError::NotConfigured)?;
+        let identity = oidc.exchange_code(code, state, state_cookie).await?;
+
+        let user = self.provision_oidc_user(&identity)?;
+        if !user.is_active {
+            return Err(AuthError::UserInactive);
+        }
+
+        let _ = self.update_last_login(&user.id);
+        tracing::info!("Single sign-on login: {} ({})", user.username, user.role);
+
+        self.issue_token(user, client)
+    }
+
+    /// Find or create the user for an OIDC identity and sync its role.
+    ///
+    /// An existing account with the same e-mail address is linked to the
+    /// identity instead of creating a duplicate.
+    fn provision_oidc_user(&self, identity: &OidcIdentity) -> Result<User, AuthError> {
+        let mapped_role = role_for_groups(&self.config.oidc.group_roles, &identity.groups, None);
+
+        // Only the immutable subject links an identity to an account; the
+        // email and username are chosen by the user at the provider
+        let existing = self.get_user_by_oidc_subject(&identity.subject)?;
+
+        let now_str = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+
+        if let Some(user) = existing {
+            if user.is_service_account() {
+                return Err(AuthError::UserAlreadyExists(identity.email.clone()));
+            }
+            // Groups only manage the role of accounts provisioned by single
+            // sign-on; a linked local account keeps the role it was given
+            let role = match mapped_role {
+                Some(mapped) if self.auth_provider(&user.id)? == "oidc" => {
+                    mapped.as_str().to_string()
+                }
+                _ => user.role,
+            };
+            self.db.execute(
+                "UPDATE users
+                 SET role = ?, full_name = COALESCE(?, full_name), oidc_subject = ?, updated_at = ?
+                 WHERE id = ?",
+                &[
+                    &role as &dyn duckdb::ToSql,
+                    &identity.full_name,
+                    &identity.subject,
+                    &now_str,
+                    &user.id,
+                ],
+            )?;
+            return self.get_user_by_id(&user.id)?
+                .ok_or_else(|| AuthError::UserNotFound(user.id.clone()));
+        }
+
+        let role = mapped_role
+            .or(self.config.oidc.default_role)
+            .ok_or(OidcError::NoRole)?;
+        // An existing local account is only used after an administrator
+        // linked it to the identity
+        if self.get_user_by_email(&identity.email)?.is_some() {
+            return Err(OidcError::AccountNotLinked(identity.email.clone()).into());
+        }
+        if self.get_user_by_username(&identity.username)?.is_some() {
+            return Err(OidcError::AccountNotLinked(identity.username.clone()).into());
+        }
+
+        let id = Self::generate_user_id();
+        // SSO users have no password; an empty hash never matches
+        self.db.execute(
+            "INSERT INTO users (
+                id, username, email, full_name, password_hash, role, custom_permissions,
+                created_at, is_active, auth_provider, oidc_subject
+            ) VALUES (?, ?, ?, ?, '', ?, '[]', ?, TRUE, 'oidc', ?)",
+            &[
+                &id as &dyn duckdb::ToSql,
+                &identity.username,
+                &identity.email,
+                &identity.full_name,
+                &role.as_str(),
+                &now_str,
+                &identity.subject,
+            ],
+        )?;
+        tracing::info!("Provisioned single sign-on user {} as {}", identity.username, role.as_str());
+
+        self.get_user_by_id(&id)?
+            .ok_or(AuthError::UserNotFound(id))
+    }
+
+    /// Link an existing account to an OIDC identity, so the user can sign in
+    /// with single sign-on. The account keeps its role.
+    pub fn link_oidc_subject(&self, id: &str, subject: &str) -> Result<User, AuthError> {
+        let user = self
+            .get_user_by_id(id)?
+            .ok_or_else(|| AuthError::UserNotFound(id.to_string()))?;
+        if user.is_service_account() {
+            return Err(AuthError::InvalidRequest(
+                "Service accounts cannot sign in with single sign-on".to_string(),
+            ));
+        }
+        if let Some(linked) = self.get_user_by_oidc_subject(subject)?
+            && linked.id != user.id
+        {
+            return Err(AuthError::InvalidRequest(format!(
+                "Identity is already linked to user {}",
+                linked.username
+            )));
+        }
+
+        let now_str = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
+        self.db.execute(
+            "UPDATE users SET oidc_subject = ?, updated_at = ? WHERE id = ?",
+            &[&subject as &dyn duckdb::ToSql, &now_str, &user.id],
+        )?;
+        self.get_user_by_id(id)?
+            .ok_or_else(|| AuthError::UserNotFound(id.to_string()))
+    }
+
+    /// Get a user by the id of its OIDC identity.
+    fn get_user_by_oidc_subject(&self, subject: &str) -> Result<Option<User>, AuthError> {
+        let result = self.db.query_row(
+            &format!("SELECT {} FROM users WHERE oidc_subject = ?", USER_COLUMNS),
+            &[&subject],
+            user_from_row,
+        );
+
+        match result {
+            Ok(user) => Ok(Some(user)),
+            Err(e) if is_no_rows(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Verify a JWT token and return the claims.
-    #[allow(dead_code)]
+    ///
+    /// Tokens of a revoked session are rejected.
     pub fn verify_token(&self, token: &str) -> Result<Claims, AuthError> {
         let token_data = decode::<Claims>(
             token,
@@ -165,6 +871,12 @@ impl AuthService {
             &Validation::new(Algorithm::HS256),
         )?;
 
+        if let Some(sid) = &token_data.claims.sid
+            && !self.session_is_active(sid)?
+        {
+            return Err(AuthError::SessionRevoked);
+        }
+
         Ok(token_data.claims)
     }
 
@@ -204,36 +916,9 @@ impl AuthService {
             }
         }
 
-        let id = Self::generate_user_id();
         let now = Utc::now();
-        let now_str = now.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
-        let password_hash = Self::hash_password(&req.password);
-        let perms_json = serde_json::to_string(&req.custom_permissions).unwrap();
-
-        // Insert user
-        self.db.execute(
-            r#"
-            INSERT INTO users (
-                id, username, email, full_name, password_hash,
-                role, custom_permissions, created_at, created_by, is_active
-            ) VALUES ('{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', '{}', {})
-            "#,
-            &[
-                &id.as_bytes(),
-                &req.username.as_bytes(),
-                &req.email.as_bytes(),
-                &req.full_name.as_ref().map(|s| s.as_bytes()).unwrap_or(&[]),
-                &password_hash.as_bytes(),
-                &req.role.as_bytes(),
-                &perms_json.as_bytes(),
-                &now_str.as_bytes(),
-                &creator.map(|s| s.as_bytes()).unwrap_or(&[]),
-                &(1_i32),
-            ],
-        )?;
-
-        Ok(User {
-            id,
+        let user = User {
+            id: Self::generate_user_id(),
             username: req.username.clone(),
             email: req.email.clone(),
             full_name: req.full_name.clone(),
@@ -244,34 +929,89 @@ impl AuthService {
             updated_at: None,
             last_login: None,
             is_active: true,
-        })
+            resource_scope: req.resource_scope.clone(),
+            two_factor_enabled: false,
+            account_type: req.account_type,
+            expires_at: req.expires_at,
+        };
+        Self::validate_account(&user, now)?;
+
+        // Service accounts have no password; an empty hash never matches
+        let password_hash = match user.account_type {
+            AccountType::User if req.password.is_empty() => {
+                return Err(AuthError::InvalidRequest("password cannot be empty".to_string()));
+            }
+            AccountType::User => Self::hash_password(&req.password),
+            AccountType::Service => String::new(),
+        };
+        let perms_json = serde_json::to_string(&user.custom_permissions).unwrap();
+        let scope_json = (!user.resource_scope.is_unrestricted())
+            .then(|| serde_json::to_string(&user.resource_scope).unwrap());
+
+        // Insert user
+        self.db.execute(
+            r#"
+            INSERT INTO users (
+                id, username, email, full_name, password_hash, role, custom_permissions,
+                created_at, created_by, is_active, resource_scope, account_type, expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, TRUE, ?, ?, ?)
+            "#,
+            &[
+                &user.id as &dyn duckdb::ToSql,
+                &user.username,
+                &user.email,
+                &user.full_name,
+                &password_hash,
+                &user.role,
+                &perms_json,
+                &format_timestamp(now),
+                &user.created_by,
+                &scope_json,
+                &user.account_type.as_str(),
+                &user.expires_at.map(format_timestamp),
+            ],
+        )?;
+
+        if user.is_service_account() {
+            tracing::info!(
+                "Service account {} created, expires {}",
+                user.username,
+                user.expires_at.map(format_timestamp).unwrap_or_default()
+            );
+        }
+        Ok(user)
+    }
+
+    /// Check the account type specific restrictions of a new or updated user.
+    fn validate_account(user: &User, now: DateTime<Utc>) -> Result<(), AuthError> {
+        if !user.is_service_account() {
+            return match user.expires_at {
+                Some(_) => Err(AuthError::InvalidRequest(
+                    "expires_at only applies to service accounts".to_string(),
+                )),
+                None => Ok(()),
+            };
+        }
+
+        let errors = user.service_account_violations(now);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AuthError::InvalidRequest(errors.join("; ")))
+        }
     }
 
     /// Get a user by ID.
     pub fn get_user_by_id(&self, id: &str) -> Result<Option<User>, AuthError> {
         let result = self.db.query_row(
-            "SELECT id, username, email, full_name, role, custom_permissions, created_at, created_by, updated_at, last_login, is_active FROM users WHERE id = ?",
+            &format!("SELECT {} FROM users WHERE id = ?", USER_COLUMNS),
             &[&id.as_bytes()],
-            |row| {
-                Ok(User {
-                    id: row.get::<_, String>(0)?,
-                    username: row.get::<_, String>(1)?,
-                    email: row.get::<_, String>(2)?,
-                    full_name: row.get::<_, Option<String>>(3)?,
-                    role: row.get::<_, String>(4)?,
-                    custom_permissions: parse_json_array(row.get::<_, Option<String>>(5)?),
-                    created_at: parse_timestamp(row.get::<_, String>(6)?.as_str()),
-                    created_by: row.get::<_, Option<String>>(7)?,
-                    updated_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
-                    last_login: row.get::<_, Option<String>>(9)?.map(|s| parse_timestamp(&s)),
-                    is_active: row.get::<_, i32>(10)? == 1,
-                })
-            },
+            user_from_row,
         );
 
         match result {
             Ok(user) => Ok(Some(user)),
-            Err(e) if e.to_string().contains("QueryReturnedNoRows") => Ok(None),
+            Err(e) if is_no_rows(&e) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
@@ -279,28 +1019,14 @@ impl AuthService {
     /// Get a user by username.
     pub fn get_user_by_username(&self, username: &str) -> Result<Option<User>, AuthError> {
         let result = self.db.query_row(
-            "SELECT id, username, email, full_name, role, custom_permissions, created_at, created_by, updated_at, last_login, is_active FROM users WHERE username = ?",
+            &format!("SELECT {} FROM users WHERE username = ?", USER_COLUMNS),
             &[&username.as_bytes()],
-            |row| {
-                Ok(User {
-                    id: row.get::<_, String>(0)?,
-                    username: row.get::<_, String>(1)?,
-                    email: row.get::<_, String>(2)?,
-                    full_name: row.get::<_, Option<String>>(3)?,
-                    role: row.get::<_, String>(4)?,
-                    custom_permissions: parse_json_array(row.get::<_, Option<String>>(5)?),
-                    created_at: parse_timestamp(row.get::<_, String>(6)?.as_str()),
-                    created_by: row.get::<_, Option<String>>(7)?,
-                    updated_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
-                    last_login: row.get::<_, Option<String>>(9)?.map(|s| parse_timestamp(&s)),
-                    is_active: row.get::<_, i32>(10)? == 1,
-                })
-            },
+            user_from_row,
         );
 
         match result {
             Ok(user) => Ok(Some(user)),
-            Err(e) if e.to_string().contains("QueryReturnedNoRows") => Ok(None),
+            Err(e) if is_no_rows(&e) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
@@ -308,28 +1034,14 @@ impl AuthService {
     /// Get a user by email.
     pub fn get_user_by_email(&self, email: &str) -> Result<Option<User>, AuthError> {
         let result = self.db.query_row(
-            "SELECT id, username, email, full_name, role, custom_permissions, created_at, created_by, updated_at, last_login, is_active FROM users WHERE email = ?",
+            &format!("SELECT {} FROM users WHERE email = ?", USER_COLUMNS),
             &[&email.as_bytes()],
-            |row| {
-                Ok(User {
-                    id: row.get::<_, String>(0)?,
-                    username: row.get::<_, String>(1)?,
-                    email: row.get::<_, String>(2)?,
-                    full_name: row.get::<_, Option<String>>(3)?,
-                    role: row.get::<_, String>(4)?,
-                    custom_permissions: parse_json_array(row.get::<_, Option<String>>(5)?),
-                    created_at: parse_timestamp(row.get::<_, String>(6)?.as_str()),
-                    created_by: row.get::<_, Option<String>>(7)?,
-                    updated_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
-                    last_login: row.get::<_, Option<String>>(9)?.map(|s| parse_timestamp(&s)),
-                    is_active: row.get::<_, i32>(10)? == 1,
-                })
-            },
+            user_from_row,
         );
 
         match result {
             Ok(user) => Ok(Some(user)),
-            Err(e) if e.to_string().contains("QueryReturnedNoRows") => Ok(None),
+            Err(e) if is_no_rows(&e) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
@@ -343,23 +1055,9 @@ impl AuthService {
         }
 
         let users = self.db.query(
-            "SELECT id, username, email, full_name, role, custom_permissions, created_at, created_by, updated_at, last_login, is_active FROM users ORDER BY username",
+            &format!("SELECT {} FROM users ORDER BY username", USER_COLUMNS),
             &[],
-            |row| {
-                Ok(User {
-                    id: row.get::<_, String>(0)?,
-                    username: row.get::<_, String>(1)?,
-                    email: row.get::<_, String>(2)?,
-                    full_name: row.get::<_, Option<String>>(3)?,
-                    role: row.get::<_, String>(4)?,
-                    custom_permissions: parse_json_array(row.get::<_, Option<String>>(5)?),
-                    created_at: parse_timestamp(row.get::<_, String>(6)?.as_str()),
-                    created_by: row.get::<_, Option<String>>(7)?,
-                    updated_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
-                    last_login: row.get::<_, Option<String>>(9)?.map(|s| parse_timestamp(&s)),
-                    is_active: row.get::<_, i32>(10)? == 1,
-                })
-            },
+            user_from_row,
         )?;
 
         Ok(users)
@@ -367,37 +1065,68 @@ impl AuthService {
 
     /// Update a user.
     pub fn update_user(&self, id: &str, req: &UpdateUserRequest) -> Result<User, AuthError> {
-        let _user = self.get_user_by_id(id)?
+        let user = self.get_user_by_id(id)?
             .ok_or_else(|| AuthError::UserNotFound(id.to_string()))?;
 
         let now = Utc::now();
         let now_str = now.format("%Y-%m-%d %H:%M:%S%.6f").to_string();
 
+        // Changes to what the account may do and for how long are checked
+        // against the restrictions of its type
+        if req.role.is_some() || req.custom_permissions.is_some() || req.expires_at.is_some() {
+            let mut updated = user;
+            if let Some(role) = &req.role {
+                updated.role = role.clone();
+            }
+            if let Some(custom_permissions) = &req.custom_permissions {
+                updated.custom_permissions = custom_permissions.clone();
+            }
+            if req.expires_at.is_some() {
+                updated.expires_at = req.expires_at;
+            }
+            Self::validate_account(&updated, now)?;
+        }
+
         // Build update query dynamically based on provided fields
         let mut updates = Vec::new();
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
         if let Some(email) = &req.email {
-            updates.push(format!("email = '{email}'"));
+            updates.push("email = ?".to_string());
+            params.push(Box::new(email.clone()));
         }
         if let Some(full_name) = &req.full_name {
-            updates.push(format!("full_name = '{full_name}'"));
+            updates.push("full_name = ?".to_string());
+            params.push(Box::new(full_name.clone()));
         }
         if let Some(role) = &req.role {
-            updates.push(format!("role = '{role}'"));
+            updates.push("role = ?".to_string());
+            params.push(Box::new(role.clone()));
         }
         if let Some(custom_permissions) = &req.custom_permissions {
-            let perms_json = serde_json::to_string(custom_permissions)
-                .unwrap_or_default();
-            updates.push(format!("custom_permissions = '{perms_json}'"));
+            updates.push("custom_permissions = ?".to_string());
+            params.push(Box::new(serde_json::to_string(custom_permissions).unwrap_or_default()));
         }
         if let Some(is_active) = req.is_active {
-            updates.push(format!("is_active = {}", if is_active { 1 } else { 0 }));
+            updates.push("is_active = ?".to_string());
+            params.push(Box::new(is_active));
+        }
+        if let Some(resource_scope) = &req.resource_scope {
+            updates.push("resource_scope = ?".to_string());
+            params.push(Box::new(serde_json::to_string(resource_scope).unwrap_or_default()));
+        }
+        if let Some(expires_at) = req.expires_at {
+            updates.push("expires_at = ?".to_string());
+            params.push(Box::new(format_timestamp(expires_at)));
         }
 
-        updates.push(format!("updated_at = '{}'", now_str));
+        updates.push("updated_at = ?".to_string());
+        params.push(Box::new(now_str));
+        params.push(Box::new(id.to_string()));
 
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         self.db.execute(
-            &format!("UPDATE users SET {} WHERE id = '{}'", updates.join(", "), id),
-            &[],
+            &format!("UPDATE users SET {} WHERE id = ?", updates.join(", ")),
+            &param_refs,
         )?;
 
         // Return updated user
@@ -407,6 +1136,7 @@ impl AuthService {
 
     /// Delete a user.
     pub fn delete_user(&self, id: &str) -> Result<(), AuthError> {
+        self.revoke_user_sessions(id, None)?;
         self.db.execute(
             "DELETE FROM users WHERE id = ?",
             &[&id.as_bytes()],
@@ -415,12 +1145,19 @@ impl AuthService {
     }
 
     /// Change user password.
+    ///
+    /// Sessions are left alone; see [`Self::revoke_user_sessions`].
     pub fn change_password(
         &self,
         id: &str,
         old_password: &str,
         new_password: &str,
     ) -> Result<(), AuthError> {
+        if self.has_backend_password(id)? {
+            return Err(AuthError::InvalidRequest(
+                "The password of this user is managed by the directory".to_string(),
+            ));
+        }
         let stored_hash = self.get_password_hash(id)?;
 
         if !Self::verify_password(old_password, &stored_hash) {
@@ -430,13 +1167,122 @@ impl AuthService {
         let new_hash = Self::hash_password(new_password);
 
         self.db.execute(
-            "UPDATE users SET password_hash = '{}' WHERE id = '{}'",
-            &[&new_hash.as_bytes(), &id.as_bytes()],
+            "UPDATE users SET password_hash = ? WHERE id = ?",
+            &[&new_hash as &dyn duckdb::ToSql, &id],
         )?;
 
         Ok(())
     }
 
+    /// Lifetime of password reset tokens (minutes).
+    pub fn password_reset_token_minutes(&self) -> i64 {
+        self.config.password_reset_token_minutes
+    }
+
+    /// Create a password reset token for the active user with this e-mail
+    /// address; returns the user and the token.
+    ///
+    /// Returns `None` for unknown addresses, inactive users, service
+    /// accounts and directory users. Callers must not reveal the difference.
+    pub fn create_password_reset_token(
+        &self,
+        email: &str,
+    ) -> Result<Option<(User, String)>, AuthError> {
+        let Some(user) = self
+            .get_user_by_email(email)?
+            .filter(|user| user.is_active && !user.is_service_account())
+        else {
+            return Ok(None);
+        };
+        if self.has_backend_password(&user.id)? {
+            return Ok(None);
+        }
+
+        let token = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(self.config.password_reset_token_minutes);
+
+        self.db.execute(
+            "INSERT INTO password_reset_tokens (id, user_id, token_hash, created_at, expires_at)
+             VALUES (?, ?, ?, ?, ?)",
+            &[
+                &format!("prt_{}", uuid::Uuid::new_v4().simple()) as &dyn duckdb::ToSql,
+                &user.id,
+                &Self::hash_token(&token),
+                &format_timestamp(now),
+                &format_timestamp(expires_at),
+            ],
+        )?;
+
+        Ok(Some((user, token)))
+    }
+
+    /// Set a new password with a password reset token.
+    ///
+    /// The token is used up; other outstanding reset tokens and all sessions
+    /// of the user end as well.
+    pub fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AuthError> {
+        if new_password.is_empty() {
+            return Err(AuthError::InvalidRequest("New password must not be empty".to_string()));
+        }
+
+        let result = self.db.query_row(
+            "SELECT id, user_id, CAST(expires_at AS VARCHAR), used_at IS NOT NULL
+             FROM password_reset_tokens WHERE token_hash = ?",
+            &[&Self::hash_token(token)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, bool>(3)?,
+                ))
+            },
+        );
+        let (id, user_id, expires_at, used) = match result {
+            Ok(row) => row,
+            Err(e) if is_no_rows(&e) => return Err(AuthError::InvalidResetToken),
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Utc::now();
+        if used || parse_timestamp(&expires_at) <= now {
+            return Err(AuthError::InvalidResetToken);
+        }
+        // A concurrent reset with the same token leaves no row to update
+        let claimed = self.db.execute_count(
+            "UPDATE password_reset_tokens SET used_at = ? WHERE id = ? AND used_at IS NULL",
+            &[&format_timestamp(now) as &dyn duckdb::ToSql, &id],
+        )?;
+        if claimed == 0 {
+            return Err(AuthError::InvalidResetToken);
+        }
+
+        let user = self
+            .get_user_by_id(&user_id)?
+            .ok_or(AuthError::InvalidResetToken)?;
+        if !user.is_active {
+            return Err(AuthError::UserInactive);
+        }
+
+        self.db.execute(
+            "UPDATE users SET password_hash = ? WHERE id = ?",
+            &[&Self::hash_password(new_password) as &dyn duckdb::ToSql, &user.id],
+        )?;
+        self.db.execute(
+            "UPDATE password_reset_tokens SET used_at = ? WHERE user_id = ? AND used_at IS NULL",
+            &[&format_timestamp(now) as &dyn duckdb::ToSql, &user.id],
+        )?;
+        self.revoke_user_sessions(&user.id, None)?;
+
+        tracing::info!("Password of user {} reset", user.username);
+        Ok(())
+    }
+
     /// Get password hash for a user.
     fn get_password_hash(&self, id: &str) -> Result<String, AuthError> {
         self.db
@@ -446,7 +1292,7 @@ impl AuthService {
                 |row| row.get::<_, String>(0),
             )
             .map_err(|e| {
-                if e.to_string().contains("QueryReturnedNoRows") {
+                if is_no_rows(&e) {
                     AuthError::UserNotFound(id.to_string())
                 } else {
                     AuthError::DatabaseError(e)
@@ -477,6 +1323,464 @@ impl AuthService {
         Ok(user.has_all_permissions(required_permissions))
     }
 
+    /// Create an API key with a subset of the creator's permissions.
+    ///
+    /// The key carries the creator's resource scope. A key bound to a
+    /// service account is also limited to the permissions of the account and
+    /// expires with it at the latest; a scoped creator can only bind accounts
+    /// within their scope. The returned key is shown once; only its hash is
+    /// stored.
+    pub fn create_api_key(
+        &self,
+        req: &CreateApiKeyRequest,
+        creator: &Claims,
+    ) -> Result<CreatedApiKey, AuthError> {
+        req.validate(&creator.permissions)
+            .map_err(|errors| AuthError::InvalidRequest(errors.join("; ")))?;
+
+        let mut expires_at = req.expires_at;
+        if let Some(account_id) = &req.service_account_id {
+            let account = self
+                .get_user_by_id(account_id)?
+                .filter(User::is_service_account)
+                .ok_or_else(|| {
+                    AuthError::InvalidRequest(format!("no service account '{}'", account_id))
+                })?;
+            if !account.is_active || account.is_expired(Utc::now()) {
+                return Err(AuthError::InvalidRequest(format!(
+                    "service account '{}' is inactive or expired",
+                    account.username
+                )));
+            }
+
+            let account_resources: Vec<String> = account
+                .resource_scope
+                .peilgebieden
+                .iter()
+                .chain(&account.resource_scope.assets)
+                .cloned()
+                .collect();
+            if !creator.scope.allows(&account_resources) {
+                return Err(AuthError::InvalidRequest(format!(
+                    "service account '{}' reaches beyond your resource scope",
+                    account.username
+                )));
+            }
+
+            let account_permissions = account.get_permissions();
+            let missing: Vec<&str> = req
+                .permissions
+                .iter()
+                .filter(|p| {
+                    Permission::from_str(p).is_none_or(|p| !account_permissions.contains(&p))
+                })
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                return Err(AuthError::InvalidRequest(format!(
+                    "service account '{}' lacks permissions: {}",
+                    account.username,
+                    missing.join(", ")
+                )));
+            }
+
+            expires_at = match (expires_at, account.expires_at) {
+                (Some(key), Some(account)) => Some(key.min(account)),
+                (key, account) => key.or(account),
+            };
+        }
+
+        let key = format!(
+            "{}{}{}",
+            API_KEY_PREFIX,
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let api_key = ApiKey {
+            id: format!("key_{}", uuid::Uuid::new_v4().simple()),
+            name: req.name.trim().to_string(),
+            prefix: key[..API_KEY_PREFIX.len() + 8].to_string(),
+            permissions: req.permissions.clone(),
+            created_by: Some(creator.sub.clone()),
+            created_at: Utc::now(),
+            expires_at,
+            revoked_at: None,
+            last_used_at: None,
+            service_account_id: req.service_account_id.clone(),
+            resource_scope: creator.scope.clone(),
+        };
+        let scope_json = (!api_key.resource_scope.is_unrestricted())
+            .then(|| serde_json::to_string(&api_key.resource_scope).unwrap());
+
+        self.db.execute(
+            "INSERT INTO api_keys (id, name, key_prefix, key_hash, permissions, created_by, created_at,
+                                   expires_at, service_account_id, resource_scope)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            &[
+                &api_key.id as &dyn duckdb::ToSql,
+                &api_key.name,
+                &api_key.prefix,
+                &Self::hash_token(&key),
+                &serde_json::to_string(&api_key.permissions).unwrap_or_default(),
+                &api_key.created_by,
+                &format_timestamp(api_key.created_at),
+                &api_key.expires_at.map(format_timestamp),
+                &api_key.service_account_id,
+                &scope_json,
+            ],
+        )?;
+
+        tracing::info!("API key '{}' created by {}", api_key.name, creator.username);
+        Ok(CreatedApiKey { api_key, key })
+    }
+
+    /// List all API keys, including revoked ones.
+    pub fn list_api_keys(&self) -> Result<Vec<ApiKey>, AuthError> {
+        let keys = self.db.query(
+            "SELECT id, name, key_prefix, permissions, created_by, CAST(created_at AS VARCHAR),
+                    CAST(expires_at AS VARCHAR), CAST(revoked_at AS VARCHAR), CAST(last_used_at AS VARCHAR),
+                    service_account_id, resource_scope
+             FROM api_keys ORDER BY created_at DESC",
+            &[],
+            api_key_from_row,
+        )?;
+        Ok(keys)
+    }
+
+    /// Revoke an API key; it can no longer be used.
+    pub fn revoke_api_key(&self, id: &str) -> Result<ApiKey, AuthError> {
+        let key = self.get_api_key(id)?
+            .ok_or_else(|| AuthError::ApiKeyNotFound(id.to_string()))?;
+        if key.revoked_at.is_some() {
+            return Ok(key);
+        }
+
+        self.db.execute(
+            "UPDATE api_keys SET revoked_at = ? WHERE id = ?",
+            &[&format_timestamp(Utc::now()) as &dyn duckdb::ToSql, &id],
+        )?;
+        tracing::info!("API key '{}' revoked", key.name);
+
+        self.get_api_key(id)?
+            .ok_or_else(|| AuthError::ApiKeyNotFound(id.to_string()))
+    }
+
+    /// Verify an API key and return claims carrying its permissions.
+    pub fn verify_api_key(&self, key: &str) -> Result<Claims, AuthError> {
+        if !key.starts_with(API_KEY_PREFIX) {
+            return Err(AuthError::InvalidApiKey);
+        }
+
+        let result = self.db.query_row(
+            "SELECT id, name, key_prefix, permissions, created_by, CAST(created_at AS VARCHAR),
+                    CAST(expires_at AS VARCHAR), CAST(revoked_at AS VARCHAR), CAST(last_used_at AS VARCHAR),
+                    service_account_id, resource_scope
+             FROM api_keys WHERE key_hash = ?",
+            &[&Self::hash_token(key)],
+            api_key_from_row,
+        );
+        let api_key = match result {
+            Ok(api_key) => api_key,
+            Err(e) if is_no_rows(&e) => {
+                return Err(AuthError::InvalidApiKey);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Utc::now();
+        if !api_key.is_usable(now) {
+            return Err(AuthError::InvalidApiKey);
+        }
+
+        // Keys of a service account stop working with the account
+        let account = match &api_key.service_account_id {
+            Some(account_id) => Some(
+                self.get_user_by_id(account_id)?
+                    .filter(|account| account.is_active && !account.is_expired(now))
+                    .ok_or(AuthError::InvalidApiKey)?,
+            ),
+            None => None,
+        };
+
+        // Avoid a write on every request of chatty clients
+        if api_key
+            .last_used_at
+            .is_none_or(|used| (now - used).num_seconds() >= API_KEY_TOUCH_INTERVAL_SECS)
+        {
+            let _ = self.db.execute(
+                "UPDATE api_keys SET last_used_at = ? WHERE id = ?",
+                &[&format_timestamp(now) as &dyn duckdb::ToSql, &api_key.id],
+            );
+        }
+
+        let exp = api_key
+            .expires_at
+            .unwrap_or(now + Duration::hours(self.config.token_expiration_hours))
+            .timestamp();
+        Ok(match &account {
+            Some(account) => Claims::from_service_account_key(&api_key, account, exp),
+            None => Claims::from_api_key(&api_key, exp),
+        })
+    }
+
+    /// Get an API key by ID.
+    fn get_api_key(&self, id: &str) -> Result<Option<ApiKey>, AuthError> {
+        let result = self.db.query_row(
+            "SELECT id, name, key_prefix, permissions, created_by, CAST(created_at AS VARCHAR),
+                    CAST(expires_at AS VARCHAR), CAST(revoked_at AS VARCHAR), CAST(last_used_at AS VARCHAR),
+                    service_account_id, resource_scope
+             FROM api_keys WHERE id = ?",
+            &[&id],
+            api_key_from_row,
+        );
+
+        match result {
+            Ok(key) => Ok(Some(key)),
+            Err(e) if is_no_rows(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Hash an API key or refresh token for storage and lookup.
+    ///
+    /// Both are long random strings, so an unsalted hash suffices.
+    fn hash_token(key: &str) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(key.as_bytes()))
+    }
+
+    /// Start two-factor enrolment: generate a new TOTP secret.
+    ///
+    /// The secret only takes effect after [`Self::confirm_totp`].
+    pub fn enroll_totp(&self, user_id: &str) -> Result<TotpEnrollment, AuthError> {
+        let user = self
+            .get_user_by_id(user_id)?
+            .ok_or_else(|| AuthError::UserNotFound(user_id.to_string()))?;
+        if user.two_factor_enabled {
+            return Err(AuthError::InvalidRequest(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let secret = totp::generate_secret();
+        self.db.execute(
+            "UPDATE users SET totp_secret = ?, totp_enabled = FALSE, totp_last_step = NULL WHERE id = ?",
+            &[&secret as &dyn duckdb::ToSql, &user.id],
+        )?;
+
+        let account = if user.email.is_empty() { &user.username } else { &user.email };
+        let otpauth_uri = totp::otpauth_uri(TOTP_ISSUER, account, &secret);
+        Ok(TotpEnrollment {
+            qr_svg: totp::qr_svg(&otpauth_uri),
+            otpauth_uri,
+            secret,
+        })
+    }
+
+    /// Complete enrolment with a code from the authenticator app.
+    ///
+    /// Enables two-factor authentication and returns fresh recovery codes.
+    pub fn confirm_totp(&self, user_id: &str, code: &str) -> Result<RecoveryCodes, AuthError> {
+        let (secret, enabled, _) = self.get_totp_state(user_id)?;
+        let secret = match secret {
+            Some(secret) if !enabled => secret,
+            _ => {
+                return Err(AuthError::InvalidRequest(
+                    "No pending two-factor enrolment".to_string(),
+                ));
+            }
+        };
+        let step = totp::verify(&secret, code, Utc::now()).ok_or(AuthError::InvalidTwoFactorCode)?;
+
+        self.db.execute(
+            "UPDATE users SET totp_enabled = TRUE, totp_last_step = ? WHERE id = ?",
+            &[&step as &dyn duckdb::ToSql, &user_id],
+        )?;
+        tracing::info!("Two-factor authentication enabled for user {}", user_id);
+
+        self.replace_recovery_codes(user_id)
+    }
+
+    /// Disable two-factor authentication, confirmed with a current code.
+    ///
+    /// Not allowed when the policy requires it for the user's role.
+    pub fn disable_totp(&self, user_id: &str, code: &str) -> Result<(), AuthError> {
+        let user = self
+            .get_user_by_id(user_id)?
+            .ok_or_else(|| AuthError::UserNotFound(user_id.to_string()))?;
+        if !user.two_factor_enabled {
+            return Ok(());
+        }
+        if self.two_factor_policy()?.requires(user.get_role()) {
+            return Err(AuthError::InvalidRequest(format!(
+                "Two-factor authentication is required for role '{}'",
+                user.role
+            )));
+        }
+
+        self.verify_second_factor(user_id, code)?;
+        self.reset_totp(user_id)
+    }
+
+    /// Replace the recovery codes, confirmed with a current code.
+    pub fn regenerate_recovery_codes(
+        &self,
+        user_id: &str,
+        code: &str,
+    ) -> Result<RecoveryCodes, AuthError> {
+        self.verify_second_factor(user_id, code)?;
+        self.replace_recovery_codes(user_id)
+    }
+
+    /// Remove two-factor authentication of a user (admin reset after a
+    /// lost device). Ends all sessions of the user.
+    pub fn reset_totp(&self, user_id: &str) -> Result<(), AuthError> {
+        self.db.execute(
+            "UPDATE users SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL WHERE id = ?",
+            &[&user_id],
+        )?;
+        self.db.execute(
+            "DELETE FROM user_recovery_codes WHERE user_id = ?",
+            &[&user_id],
+        )?;
+        self.revoke_user_sessions(user_id, None)?;
+        tracing::info!("Two-factor authentication removed for user {}", user_id);
+        Ok(())
+    }
+
+    /// Roles that must use two-factor authentication.
+    pub fn two_factor_policy(&self) -> Result<TwoFactorPolicy, AuthError> {
+        let result = self.db.query_row(
+            "SELECT value FROM auth_settings WHERE key = ?",
+            &[&TWO_FACTOR_POLICY_KEY],
+            |row| row.get::<_, String>(0),
+        );
+        match result {
+            Ok(value) => Ok(serde_json::from_str(&value).unwrap_or_default()),
+            Err(e) if is_no_rows(&e) => Ok(TwoFactorPolicy::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Change the roles that must use two-factor authentication.
+    ///
+    /// Affected users without 2FA have to enrol at their next login.
+    pub fn set_two_factor_policy(
+        &self,
+        policy: &TwoFactorPolicy,
+        updated_by: &str,
+    ) -> Result<TwoFactorPolicy, AuthError> {
+        self.db.execute(
+            "INSERT OR REPLACE INTO auth_settings (key, value, updated_at, updated_by) VALUES (?, ?, ?, ?)",
+            &[
+                &TWO_FACTOR_POLICY_KEY as &dyn duckdb::ToSql,
+                &serde_json::to_string(policy).unwrap_or_default(),
+                &format_timestamp(Utc::now()),
+                &updated_by,
+            ],
+        )?;
+        tracing::info!(
+            "Two-factor authentication required for roles {:?} (set by {})",
+            policy.required_roles,
+            updated_by
+        );
+        Ok(policy.clone())
+    }
+
+    /// Verify a TOTP code or recovery code of a user with 2FA enabled.
+    ///
+    /// A TOTP code is rejected when it is not newer than the last accepted
+    /// one; a recovery code is used up. Both are claimed with a guarded
+    /// update, so concurrent logins cannot use the same code twice.
+    fn verify_second_factor(&self, user_id: &str, code: &str) -> Result<(), AuthError> {
+        let (secret, enabled, _) = self.get_totp_state(user_id)?;
+        let secret = match secret {
+            Some(secret) if enabled => secret,
+            _ => return Err(AuthError::InvalidTwoFactorCode),
+        };
+
+        if let Some(step) = totp::verify(&secret, code, Utc::now()) {
+            if !self.claim_totp_step(user_id, step)? {
+                return Err(AuthError::InvalidTwoFactorCode);
+            }
+            return Ok(());
+        }
+
+        let code_hash = Self::hash_token(&totp::normalize_recovery_code(code));
+        let result = self.db.query_row(
+            "SELECT id FROM user_recovery_codes WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+            &[&user_id as &dyn duckdb::ToSql, &code_hash],
+            |row| row.get::<_, String>(0),
+        );
+        let recovery_code_id = match result {
+            Ok(id) => id,
+            Err(e) if is_no_rows(&e) => return Err(AuthError::InvalidTwoFactorCode),
+            Err(e) => return Err(e.into()),
+        };
+        if !self.mark_recovery_code_used(&recovery_code_id, Utc::now())? {
+            return Err(AuthError::InvalidTwoFactorCode);
+        }
+        tracing::warn!("Recovery code used by user {}", user_id);
+        Ok(())
+    }
+
+    /// Atomically record an accepted TOTP step; false if the same or a
+    /// later step was accepted before.
+    fn claim_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AuthError> {
+        let updated = self.db.execute_count(
+            "UPDATE users SET totp_last_step = ? WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+            &[&step as &dyn duckdb::ToSql, &user_id, &step],
+        )?;
+        Ok(updated == 1)
+    }
+
+    /// Atomically claim a recovery code; false if it was already used.
+    fn mark_recovery_code_used(&self, id: &str, now: DateTime<Utc>) -> Result<bool, AuthError> {
+        let updated = self.db.execute_count(
+            "UPDATE user_recovery_codes SET used_at = ? WHERE id = ? AND used_at IS NULL",
+            &[&format_timestamp(now) as &dyn duckdb::ToSql, &id],
+        )?;
+        Ok(updated == 1)
+    }
+
+    /// Generate new recovery codes, invalidating the previous ones.
+    fn replace_recovery_codes(&self, user_id: &str) -> Result<RecoveryCodes, AuthError> {
+        self.db.execute(
+            "DELETE FROM user_recovery_codes WHERE user_id = ?",
+            &[&user_id],
+        )?;
+
+        let codes = totp::generate_recovery_codes();
+        let now = format_timestamp(Utc::now());
+        for code in &codes {
+            self.db.execute(
+                "INSERT INTO user_recovery_codes (id, user_id, code_hash, created_at) VALUES (?, ?, ?, ?)",
+                &[
+                    &format!("rc_{}", uuid::Uuid::new_v4().simple()) as &dyn duckdb::ToSql,
+                    &user_id,
+                    &Self::hash_token(code),
+                    &now,
+                ],
+            )?;
+        }
+
+        Ok(RecoveryCodes { recovery_codes: codes })
+    }
+
+    /// TOTP secret, enabled flag and last accepted step of a user.
+    fn get_totp_state(&self, user_id: &str) -> Result<(Option<String>, bool, Option<i64>), AuthError> {
+        let result = self.db.query_row(
+            "SELECT totp_secret, COALESCE(totp_enabled, FALSE), totp_last_step FROM users WHERE id = ?",
+            &[&user_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        );
+        match result {
+            Ok(state) => Ok(state),
+            Err(e) if is_no_rows(&e) => Err(AuthError::UserNotFound(user_id.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Create default admin user if no users exist.
     pub fn ensure_default_admin(&self) -> Result<bool, AuthError> {
         let users = self.list_users()?;
@@ -488,6 +1792,9 @@ impl AuthService {
                 full_name: Some("System Administrator".to_string()),
                 role: "admin".to_string(),
                 custom_permissions: vec![],
+                resource_scope: Default::default(),
+                account_type: AccountType::User,
+                expires_at: None,
             };
 
             self.create_user(&admin_req, None)?;
@@ -501,14 +1808,80 @@ impl AuthService {
 /// Helper to parse timestamp strings.
 fn parse_timestamp(s: &str) -> chrono::DateTime<chrono::Utc> {
     use chrono::NaiveDateTime;
-    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.6f")
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
         .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
-        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.6f"))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
         .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
         .map(|ndt| ndt.and_utc())
         .unwrap_or_else(|_| chrono::Utc::now())
 }
 
+/// Helper to format timestamps for DuckDB.
+fn format_timestamp(dt: chrono::DateTime<chrono::Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+/// Columns read by [`user_from_row`]
+const USER_COLUMNS: &str = "id, username, email, full_name, role, custom_permissions, \
+     CAST(created_at AS VARCHAR), created_by, CAST(updated_at AS VARCHAR), \
+     CAST(last_login AS VARCHAR), is_active, resource_scope, COALESCE(totp_enabled, FALSE), \
+     COALESCE(account_type, 'user'), CAST(expires_at AS VARCHAR)";
+
+fn user_from_row(row: &duckdb::Row) -> duckdb::Result<User> {
+    Ok(User {
+        id: row.get::<_, String>(0)?,
+        username: row.get::<_, String>(1)?,
+        email: row.get::<_, String>(2)?,
+        full_name: row.get::<_, Option<String>>(3)?,
+        role: row.get::<_, String>(4)?,
+        custom_permissions: parse_json_array(row.get::<_, Option<String>>(5)?),
+        created_at: parse_timestamp(row.get::<_, String>(6)?.as_str()),
+        created_by: row.get::<_, Option<String>>(7)?,
+        updated_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
+        last_login: row.get::<_, Option<String>>(9)?.map(|s| parse_timestamp(&s)),
+        is_active: row.get::<_, bool>(10)?,
+        resource_scope: parse_resource_scope(row.get::<_, Option<String>>(11)?),
+        two_factor_enabled: row.get::<_, bool>(12)?,
+        account_type: AccountType::from_str(&row.get::<_, String>(13)?).unwrap_or_default(),
+        expires_at: row.get::<_, Option<String>>(14)?.map(|s| parse_timestamp(&s)),
+    })
+}
+
+/// Helper to map an `api_keys` row.
+fn api_key_from_row(row: &duckdb::Row) -> duckdb::Result<ApiKey> {
+    Ok(ApiKey {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        prefix: row.get(2)?,
+        permissions: parse_json_array(row.get::<_, Option<String>>(3)?),
+        created_by: row.get(4)?,
+        created_at: parse_timestamp(&row.get::<_, String>(5)?),
+        expires_at: row.get::<_, Option<String>>(6)?.map(|s| parse_timestamp(&s)),
+        revoked_at: row.get::<_, Option<String>>(7)?.map(|s| parse_timestamp(&s)),
+        last_used_at: row.get::<_, Option<String>>(8)?.map(|s| parse_timestamp(&s)),
+        service_account_id: row.get(9)?,
+        resource_scope: parse_resource_scope(row.get::<_, Option<String>>(10)?),
+    })
+}
+
+/// Helper to map a `refresh_tokens` row.
+fn refresh_token_from_row(row: &duckdb::Row) -> duckdb::Result<RefreshToken> {
+    Ok(RefreshToken {
+        id: row.get(0)?,
+        family_id: row.get(1)?,
+        user_id: row.get(2)?,
+        created_at: parse_timestamp(&row.get::<_, String>(3)?),
+        expires_at: parse_timestamp(&row.get::<_, String>(4)?),
+        used_at: row.get::<_, Option<String>>(5)?.map(|s| parse_timestamp(&s)),
+        revoked_at: row.get::<_, Option<String>>(6)?.map(|s| parse_timestamp(&s)),
+    })
+}
+
+fn parse_resource_scope(s: Option<String>) -> ResourceScope {
+    s.and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
 /// Helper to parse JSON arrays from strings.
 fn parse_json_array(s: Option<String>) -> Vec<String> {
     s.and_then(|v| serde_json::from_str::<Vec<String>>(&v).ok())
@@ -519,6 +1892,279 @@ fn parse_json_array(s: Option<String>) -> Vec<String> {
 mod tests {
     use super::*;
 
+    fn service(config: AuthServiceConfig) -> AuthService {
+        AuthService::new(Arc::new(Database::in_memory()), config).unwrap()
+    }
+
+    fn local_user(auth: &AuthService, username: &str, role: &str) -> User {
+        auth.create_user(
+            &CreateUserRequest {
+                username: username.to_string(),
+                email: format!("{}@example.nl", username),
+                password: "Correct-Horse-9".to_string(),
+                full_name: None,
+                role: role.to_string(),
+                custom_permissions: Vec::new(),
+                resource_scope: ResourceScope::default(),
+                account_type: AccountType::default(),
+                expires_at: None,
+            },
+            None,
+        )
+        .unwrap()
+    }
+
+    fn identity(subject: &str, username: &str, groups: &[&str]) -> OidcIdentity {
+        OidcIdentity {
+            subject: subject.to_string(),
+            username: username.to_string(),
+            email: format!("{}@example.nl", username),
+            full_name: None,
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_oidc_links_only_by_subject() {
+        let mut config = AuthServiceConfig::default();
+        config.oidc.group_roles = [
+            ("beheer".to_string(), Role::Admin),
+            ("lezers".to_string(), Role::Viewer),
+        ]
+        .into();
+        config.oidc.default_role = Some(Role::Viewer);
+        let auth = service(config);
+
+        // Same email and username as a local account, but not linked
+        let local = local_user(&auth, "jan", "operator");
+        assert!(matches!(
+            auth.provision_oidc_user(&identity("oid-1", "jan", &["beheer"])),
+            Err(AuthError::Oidc(OidcError::AccountNotLinked(_)))
+        ));
+
+        // After linking, the local account keeps its role
+        auth.link_oidc_subject(&local.id, "oid-1").unwrap();
+        let user = auth.provision_oidc_user(&identity("oid-1", "jan", &["beheer"])).unwrap();
+        assert_eq!(user.id, local.id);
+        assert_eq!(user.role, "operator");
+        assert!(matches!(
+            auth.link_oidc_subject(&local_user(&auth, "kees", "viewer").id, "oid-1"),
+            Err(AuthError::InvalidRequest(_))
+        ));
+
+        // Provisioned accounts follow their groups
+        let piet = auth.provision_oidc_user(&identity("oid-2", "piet", &["beheer"])).unwrap();
+        assert_eq!(piet.role, "admin");
+        let piet = auth.provision_oidc_user(&identity("oid-2", "piet", &["lezers"])).unwrap();
+        assert_eq!(piet.role, "viewer");
+    }
+
+    #[test]
+    fn test_update_user_binds_values() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+
+        let scope = ResourceScope {
+            peilgebieden: vec!["PG-1']".to_string()],
+            ..Default::default()
+        };
+        let updated = auth
+            .update_user(
+                &user.id,
+                &UpdateUserRequest {
+                    email: None,
+                    full_name: Some("Jan O'Brien".to_string()),
+                    role: None,
+                    custom_permissions: None,
+                    is_active: None,
+                    resource_scope: Some(scope.clone()),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.full_name.as_deref(), Some("Jan O'Brien"));
+        assert_eq!(updated.resource_scope, scope);
+        assert_eq!(updated.role, "viewer");
+    }
+
+    #[test]
+    fn test_refresh_token_rotation() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+        let client = ClientInfo::default();
+        let first = auth.issue_token(user, &client).unwrap().refresh_token.unwrap();
+
+        let second = auth.refresh(&first, &client).unwrap().refresh_token.unwrap();
+        assert!(matches!(
+            auth.refresh(&first, &client),
+            Err(AuthError::RefreshTokenReused)
+        ));
+        // Reuse revoked the family, including the successor
+        assert!(matches!(
+            auth.refresh(&second, &client),
+            Err(AuthError::InvalidRefreshToken)
+        ));
+        assert!(matches!(
+            auth.refresh("onbekend", &client),
+            Err(AuthError::InvalidRefreshToken)
+        ));
+    }
+
+    #[test]
+    fn test_revoke_user_session() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+        let user_id = user.id.clone();
+        let laptop = ClientInfo {
+            user_agent: Some("Firefox".to_string()),
+            ip_address: Some("203.0.113.7".to_string()),
+        };
+        let first = auth.issue_token(user.clone(), &laptop).unwrap();
+        let second = auth.issue_token(user, &ClientInfo::default()).unwrap();
+        let sid = auth.verify_token(&first.access_token).unwrap().sid.unwrap();
+
+        let sessions = auth.list_sessions(&user_id, Some(&sid)).unwrap();
+        assert_eq!(sessions.len(), 2);
+        let current = sessions.iter().find(|s| s.current).unwrap();
+        assert_eq!(current.id, sid);
+        assert_eq!(current.user_agent.as_deref(), Some("Firefox"));
+        assert_eq!(current.ip_address.as_deref(), Some("203.0.113.7"));
+
+        // Another user cannot end the session
+        let other = local_user(&auth, "piet", "viewer");
+        assert!(matches!(
+            auth.revoke_user_session(&other.id, &sid),
+            Err(AuthError::SessionNotFound(_))
+        ));
+
+        auth.revoke_user_session(&user_id, &sid).unwrap();
+        assert!(matches!(auth.verify_token(&first.access_token), Err(AuthError::SessionRevoked)));
+        assert!(matches!(
+            auth.refresh(&first.refresh_token.unwrap(), &laptop),
+            Err(AuthError::InvalidRefreshToken)
+        ));
+
+        // The other session is left alone
+        assert!(auth.verify_token(&second.access_token).is_ok());
+        assert!(auth.refresh(&second.refresh_token.unwrap(), &ClientInfo::default()).is_ok());
+        assert_eq!(auth.list_sessions(&user_id, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_revoke_user_sessions_keeps_current() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+        let user_id = user.id.clone();
+        let tokens: Vec<LoginResponse> = (0..3)
+            .map(|_| auth.issue_token(user.clone(), &ClientInfo::default()).unwrap())
+            .collect();
+        let keep = auth.verify_token(&tokens[0].access_token).unwrap().sid.unwrap();
+
+        assert_eq!(auth.revoke_user_sessions(&user_id, Some(&keep)).unwrap(), 2);
+        assert!(auth.verify_token(&tokens[0].access_token).is_ok());
+        for revoked in &tokens[1..] {
+            assert!(matches!(auth.verify_token(&revoked.access_token), Err(AuthError::SessionRevoked)));
+        }
+        let sessions = auth.list_sessions(&user_id, Some(&keep)).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].current);
+
+        // Without a session to keep, all of them end
+        assert_eq!(auth.revoke_user_sessions(&user_id, None).unwrap(), 1);
+        assert!(auth.list_sessions(&user_id, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_refresh_token_marked_used_once() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+        let user_id = user.id.clone();
+        auth.issue_token(user, &ClientInfo::default()).unwrap();
+        let id = auth
+            .db
+            .query_row(
+                "SELECT id FROM refresh_tokens WHERE user_id = ?",
+                &[&user_id],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+
+        // The losing side of a concurrent exchange finds the token used
+        assert!(auth.mark_refresh_token_used(&id, Utc::now()).unwrap());
+        assert!(!auth.mark_refresh_token_used(&id, Utc::now()).unwrap());
+    }
+
+    #[test]
+    fn test_second_factor_claimed_once() {
+        let auth = service(AuthServiceConfig::default());
+        let user = local_user(&auth, "jan", "viewer");
+        let secret = auth.enroll_totp(&user.id).unwrap().secret;
+        let step = totp::time_step(Utc::now());
+        let code = totp::code_at_step(&secret, step).unwrap();
+        let recovery = auth.confirm_totp(&user.id, &code).unwrap().recovery_codes;
+
+        // The losing side of a concurrent login finds the step taken
+        assert!(auth.claim_totp_step(&user.id, step + 1).unwrap());
+        assert!(!auth.claim_totp_step(&user.id, step + 1).unwrap());
+        assert!(!auth.claim_totp_step(&user.id, step).unwrap());
+
+        // ... or the recovery code used
+        let code_hash = AuthService::hash_token(&totp::normalize_recovery_code(&recovery[0]));
+        let id = auth
+            .db
+            .query_row(
+                "SELECT id FROM user_recovery_codes WHERE user_id = ? AND code_hash = ?",
+                &[&user.id as &dyn duckdb::ToSql, &code_hash],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+        assert!(auth.mark_recovery_code_used(&id, Utc::now()).unwrap());
+        assert!(!auth.mark_recovery_code_used(&id, Utc::now()).unwrap());
+
+        assert!(auth.verify_second_factor(&user.id, &recovery[1]).is_ok());
+        assert!(matches!(
+            auth.verify_second_factor(&user.id, &recovery[1]),
+            Err(AuthError::InvalidTwoFactorCode)
+        ));
+    }
+
+    #[test]
+    fn test_reset_password_token_single_use() {
+        let auth = service(AuthServiceConfig::default());
+        local_user(&auth, "jan", "viewer");
+        let (user, token) = auth.create_password_reset_token("jan@example.nl").unwrap().unwrap();
+
+        auth.reset_password(&token, "Nieuw-Wachtwoord-1").unwrap();
+        assert_eq!(
+            auth.get_password_hash(&user.id).unwrap(),
+            AuthService::hash_password("Nieuw-Wachtwoord-1")
+        );
+        assert!(matches!(
+            auth.reset_password(&token, "Ander-Wachtwoord-2"),
+            Err(AuthError::InvalidResetToken)
+        ));
+        assert!(matches!(
+            auth.reset_password("onbekend", "Ander-Wachtwoord-2"),
+            Err(AuthError::InvalidResetToken)
+        ));
+        assert!(auth.create_password_reset_token("niemand@example.nl").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reset_password_token_expired() {
+        let auth = service(AuthServiceConfig {
+            password_reset_token_minutes: -1,
+            ..AuthServiceConfig::default()
+        });
+        local_user(&auth, "jan", "viewer");
+        let (_, token) = auth.create_password_reset_token("jan@example.nl").unwrap().unwrap();
+
+        assert!(matches!(
+            auth.reset_password(&token, "Nieuw-Wachtwoord-1"),
+            Err(AuthError::InvalidResetToken)
+        ));
+    }
+
     #[test]
     fn test_password_hashing() {
         let password = "test123";
@@ -528,6 +2174,14 @@ mod tests {
         assert!(!AuthService::verify_password("wrong", &hash));
     }
 
+    #[test]
+    fn test_token_hashing() {
+        let hash = AuthService::hash_token("pbk_0123456789abcdef");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, AuthService::hash_token("pbk_0123456789abcdef"));
+        assert_ne!(hash, AuthService::hash_token("pbk_0123456789abcdeg"));
+    }
+
     #[test]
     fn test_role_permissions() {
         let admin_perms = Permission::for_role(Role::Admin);
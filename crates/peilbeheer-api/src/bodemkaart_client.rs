@@ -0,0 +1,197 @@
+//! BRO Bodemkaart client for the soil type of peilgebieden.
+//!
+//! The soil areas within the bounding box of a peilgebied are fetched from
+//! the Bodemkaart WFS (PDOK). Their overlap with the peilgebied gives the
+//! area per soil type, from which [`PeilgebiedBodem`] derives the dominant
+//! type and the default infiltration and seepage. The result is stored in
+//! `peilgebied_bodem`, so the WFS is only queried once per peilgebied.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::info;
+
+use peilbeheer_core::bodem::{BodemParameters, Bodemtype, PeilgebiedBodem};
+use peilbeheer_core::projectie;
+
+use crate::config::WfsLayerConfig;
+use crate::db::Database;
+use crate::wfs_client;
+
+/// Source recorded with a determined soil type.
+const BRON: &str = "bro-bodemkaart";
+
+/// Bodemkaart WFS configuration.
+#[derive(Debug, Clone)]
+pub struct BodemkaartConfig {
+    pub wfs_url: String,
+    /// Feature type of the soil areas
+    pub type_name: String,
+    /// Attribute with the soil code, e.g. `hVb`
+    pub code_field: String,
+}
+
+impl Default for BodemkaartConfig {
+    fn default() -> Self {
+        Self {
+            wfs_url: std::env::var("BODEMKAART_WFS_URL").unwrap_or_else(|_| {
+                "https://service.pdok.nl/bzk/bro-bodemkaart/wfs/v1_0".to_string()
+            }),
+            type_name: std::env::var("BODEMKAART_TYPE_NAME")
+                .unwrap_or_else(|_| "bro-bodemkaart:soilarea".to_string()),
+            code_field: std::env::var("BODEMKAART_CODE_FIELD")
+                .unwrap_or_else(|_| "first_soilcode".to_string()),
+        }
+    }
+}
+
+/// Errors of the Bodemkaart client.
+#[derive(Debug, Error)]
+pub enum BodemkaartError {
+    #[error("Peilgebied {0} not found")]
+    PeilgebiedNotFound(String),
+    #[error("Bodemkaart WFS failed: {0}")]
+    Wfs(String),
+    #[error("Database error: {0}")]
+    Database(#[from] anyhow::Error),
+}
+
+/// Client determining and storing the soil of peilgebieden.
+pub struct BodemkaartClient {
+    config: BodemkaartConfig,
+    db: Arc<Database>,
+}
+
+impl BodemkaartClient {
+    pub fn new(config: BodemkaartConfig, db: Arc<Database>) -> Self {
+        Self { config, db }
+    }
+
+    /// Stored soil of a peilgebied, determined from the Bodemkaart when
+    /// not stored yet.
+    pub async fn bodem(&self, peilgebied: &str) -> Result<PeilgebiedBodem, BodemkaartError> {
+        match self.opgeslagen(peilgebied)? {
+            Some(bodem) => Ok(bodem),
+            None => self.bepaal(peilgebied).await,
+        }
+    }
+
+    /// Determine the soil of a peilgebied from the Bodemkaart and store it.
+    pub async fn bepaal(&self, peilgebied: &str) -> Result<PeilgebiedBodem, BodemkaartError> {
+        let (min_lon, min_lat, max_lon, max_lat) = self
+            .db
+            .get_peilgebied_bbox(peilgebied)?
+            .ok_or_else(|| BodemkaartError::PeilgebiedNotFound(peilgebied.to_string()))?;
+
+        // RD New avoids the axis order ambiguity of an EPSG:4326 bbox
+        let (min_x, min_y) = projectie::wgs84_naar_rd(min_lon, min_lat);
+        let (max_x, max_y) = projectie::wgs84_naar_rd(max_lon, max_lat);
+        let wfs = WfsLayerConfig {
+            url: self.config.wfs_url.clone(),
+            type_name: self.config.type_name.clone(),
+            code_field: self.config.code_field.clone(),
+            naam_field: String::new(),
+            srs_name: Some("urn:ogc:def:crs:EPSG::28992".to_string()),
+            bbox: Some(format!(
+                "{:.0},{:.0},{:.0},{:.0},urn:ogc:def:crs:EPSG::28992",
+                min_x.floor(),
+                min_y.floor(),
+                max_x.ceil(),
+                max_y.ceil()
+            )),
+        };
+        let features = wfs_client::fetch_features(&wfs)
+            .await
+            .map_err(BodemkaartError::Wfs)?;
+
+        let (typen, geometrieen): (Vec<Bodemtype>, Vec<String>) = features
+            .iter()
+            .filter_map(|feature| {
+                let geometrie = feature.get("geometry").filter(|g| !g.is_null())?;
+                let code = feature["properties"][&self.config.code_field]
+                    .as_str()
+                    .unwrap_or_default();
+                Some((Bodemtype::from_bodemcode(code), geometrie.to_string()))
+            })
+            .unzip();
+        let overlap = self.db.peilgebied_overlap(peilgebied, &geometrieen)?;
+
+        let mut oppervlakten = BTreeMap::new();
+        for (bodemtype, oppervlakte) in typen.into_iter().zip(overlap) {
+            *oppervlakten.entry(bodemtype).or_insert(0.0) += oppervlakte;
+        }
+        let bodem = PeilgebiedBodem::uit_aandelen(peilgebied, &oppervlakten, BRON);
+        info!(
+            "Bodem van peilgebied {}: {} ({} bodemvlakken)",
+            peilgebied,
+            bodem.bodemtype.as_str(),
+            features.len()
+        );
+
+        self.opslaan(&bodem)?;
+        Ok(bodem)
+    }
+
+    /// Stored soil of a peilgebied.
+    pub fn opgeslagen(&self, peilgebied: &str) -> Result<Option<PeilgebiedBodem>, BodemkaartError> {
+        let mut rows = self.db.query(
+            "SELECT peilgebied_code, bodemtype, CAST(aandelen AS VARCHAR), infiltratie, kwel,
+                    bron, CAST(bepaald_op AS VARCHAR)
+             FROM peilgebied_bodem WHERE peilgebied_code = ?",
+            &[&peilgebied as &dyn duckdb::ToSql],
+            |row| {
+                Ok(PeilgebiedBodem {
+                    peilgebied_code: row.get(0)?,
+                    bodemtype: Bodemtype::from_str(&row.get::<_, String>(1)?)
+                        .unwrap_or(Bodemtype::Onbekend),
+                    aandelen: row
+                        .get::<_, Option<String>>(2)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                    parameters: BodemParameters {
+                        infiltratie: row.get(3)?,
+                        kwel: row.get(4)?,
+                    },
+                    bron: row.get(5)?,
+                    bepaald_op: parse_timestamp(&row.get::<_, String>(6)?).unwrap_or_else(Utc::now),
+                })
+            },
+        )?;
+        Ok(rows.pop())
+    }
+
+    fn opslaan(&self, bodem: &PeilgebiedBodem) -> anyhow::Result<()> {
+        self.db.execute(
+            "INSERT INTO peilgebied_bodem
+                (peilgebied_code, bodemtype, aandelen, infiltratie, kwel, bron, bepaald_op)
+             VALUES (?, ?, ?, ?, ?, ?, CAST(? AS TIMESTAMP))
+             ON CONFLICT (peilgebied_code) DO UPDATE SET
+                 bodemtype = excluded.bodemtype,
+                 aandelen = excluded.aandelen,
+                 infiltratie = excluded.infiltratie,
+                 kwel = excluded.kwel,
+                 bron = excluded.bron,
+                 bepaald_op = excluded.bepaald_op",
+            &[
+                &bodem.peilgebied_code as &dyn duckdb::ToSql,
+                &bodem.bodemtype.as_str(),
+                &serde_json::to_string(&bodem.aandelen)?,
+                &bodem.parameters.infiltratie,
+                &bodem.parameters.kwel,
+                &bodem.bron,
+                &format_timestamp(bodem.bepaald_op),
+            ],
+        )
+    }
+}
+
+fn format_timestamp(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|ndt| ndt.and_utc())
+}
@@ -1,18 +1,52 @@
 use std::env;
+use std::net::IpAddr;
 
 use serde::{Deserialize, Serialize};
 use peilbeheer_core::DhydroConfig;
 
-/// Configuratie voor een ArcGIS-laag.
+/// Configuratie voor een asset-laag, standaard uit ArcGIS REST.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArcgisLayerConfig {
+    #[serde(default)]
     pub service_name: String,
+    #[serde(default)]
     pub layer_id: u32,
     pub display_label: String,
     pub layer_type: String,
     pub icon_svg: String,
     pub color: String,
     pub default_visible: bool,
+    /// Haal de laag op van een OGC WFS in plaats van ArcGIS REST
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wfs: Option<WfsLayerConfig>,
+}
+
+/// WFS-bron van een asset-laag (bijv. GeoServer of PDOK).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WfsLayerConfig {
+    /// WFS-endpoint, bijv. `https://service.pdok.nl/lv/bgt/wfs/v1_0`
+    pub url: String,
+    /// Featuretype, bijv. `bgt:waterdeel`
+    pub type_name: String,
+    /// Attribuut met de asset-code; zonder waarde wordt het feature-id gebruikt
+    #[serde(default = "default_wfs_code_field")]
+    pub code_field: String,
+    #[serde(default = "default_wfs_naam_field")]
+    pub naam_field: String,
+    /// Stelsel van de opgevraagde geometrie (standaard dat van de laag)
+    #[serde(default)]
+    pub srs_name: Option<String>,
+    /// Beperk tot een gebied: `minx,miny,maxx,maxy[,crs]`
+    #[serde(default)]
+    pub bbox: Option<String>,
+}
+
+fn default_wfs_code_field() -> String {
+    "CODE".to_string()
+}
+
+fn default_wfs_naam_field() -> String {
+    "NAAM".to_string()
 }
 
 /// Server configuratie.
@@ -27,8 +61,45 @@ pub struct Config {
     pub peilgebieden_geojson_path: String,
     pub peilgebieden_arcgis_service: String,
     pub peilgebieden_arcgis_layer_id: u32,
-    #[allow(dead_code)]
+    /// GeoPackage of Shapefile met peilgebieden; vervangt de ArcGIS-bron
+    pub peilgebieden_import_path: Option<String>,
+    /// Laag binnen de GeoPackage (standaard de eerste)
+    pub peilgebieden_import_layer: Option<String>,
+    pub peilgebieden_attribuut_mapping: PeilgebiedAttribuutMapping,
     pub dhydro: DhydroConfig,
+    /// Reverse proxies waarvan `X-Forwarded-For` vertrouwd wordt
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Bronkolommen van de peilgebied-attributen bij een bestandsimport.
+///
+/// Een kolom op `null` laat het attribuut leeg; de code is verplicht.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeilgebiedAttribuutMapping {
+    pub code: String,
+    pub naam: Option<String>,
+    pub zomerpeil: Option<String>,
+    pub winterpeil: Option<String>,
+    pub vastpeil: Option<String>,
+    pub oppervlakte: Option<String>,
+    pub soortafwatering: Option<String>,
+    pub soortpeilgebied: Option<String>,
+}
+
+impl Default for PeilgebiedAttribuutMapping {
+    fn default() -> Self {
+        Self {
+            code: "CODE".to_string(),
+            naam: Some("NAAM".to_string()),
+            zomerpeil: Some("ZOMERPEIL".to_string()),
+            winterpeil: Some("WINTERPEIL".to_string()),
+            vastpeil: Some("VASTPEIL".to_string()),
+            oppervlakte: Some("OPPERVLAKTE".to_string()),
+            soortafwatering: Some("SOORTAFWATERING".to_string()),
+            soortpeilgebied: Some("SOORTPEILGEBIED".to_string()),
+        }
+    }
 }
 
 impl Config {
@@ -39,6 +110,11 @@ impl Config {
             Err(_) => default_arcgis_layers(),
         };
 
+        let peilgebieden_attribuut_mapping = match env::var("PEILGEBIEDEN_ATTRIBUTE_MAPPING") {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(_) => PeilgebiedAttribuutMapping::default(),
+        };
+
         let dhydro = DhydroConfig {
             base_url: env::var("DHYDRO_BASE_URL")
                 .unwrap_or_else(|_| "https://api.dhydro.nl".to_string()),
@@ -56,6 +132,14 @@ impl Config {
                 .unwrap_or(30),
         };
 
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+
         Ok(Self {
             host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
             port: env::var("PORT")
@@ -75,7 +159,15 @@ impl Config {
                 .unwrap_or_else(|_| "0".to_string())
                 .parse()
                 .unwrap_or(0),
+            peilgebieden_import_path: env::var("PEILGEBIEDEN_IMPORT_PATH")
+                .ok()
+                .filter(|p| !p.is_empty()),
+            peilgebieden_import_layer: env::var("PEILGEBIEDEN_IMPORT_LAYER")
+                .ok()
+                .filter(|l| !l.is_empty()),
+            peilgebieden_attribuut_mapping,
             dhydro,
+            trusted_proxies,
         })
     }
 }
@@ -90,6 +182,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#1a5276" stroke="white" stroke-width="2"/><path d="M9 17v-3a5 5 0 0 1 10 0v3" fill="none" stroke="white" stroke-width="1.8" stroke-linecap="round"/><line x1="14" y1="9" x2="14" y2="12" stroke="white" stroke-width="1.8" stroke-linecap="round"/><line x1="10" y1="17" x2="18" y2="17" stroke="white" stroke-width="1.8" stroke-linecap="round"/></svg>"##.to_string(),
             color: "#1a5276".to_string(),
             default_visible: true,
+            wfs: None,
         },
         ArcgisLayerConfig {
             service_name: "Stuw".to_string(),
@@ -99,6 +192,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#8e44ad" stroke="white" stroke-width="2"/><rect x="9" y="10" width="10" height="8" rx="1" fill="none" stroke="white" stroke-width="1.8"/><line x1="9" y1="14" x2="19" y2="14" stroke="white" stroke-width="1.8"/></svg>"##.to_string(),
             color: "#8e44ad".to_string(),
             default_visible: true,
+            wfs: None,
         },
         ArcgisLayerConfig {
             service_name: "Sluis".to_string(),
@@ -108,6 +202,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#2980b9" stroke="white" stroke-width="2"/><rect x="8" y="11" width="5" height="6" fill="none" stroke="white" stroke-width="1.5"/><rect x="15" y="11" width="5" height="6" fill="none" stroke="white" stroke-width="1.5"/><line x1="13" y1="13" x2="15" y2="13" stroke="white" stroke-width="1.5"/></svg>"##.to_string(),
             color: "#2980b9".to_string(),
             default_visible: true,
+            wfs: None,
         },
         ArcgisLayerConfig {
             service_name: "Inlaat".to_string(),
@@ -117,6 +212,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#27ae60" stroke="white" stroke-width="2"/><path d="M10 14h8M15 11l3 3-3 3" fill="none" stroke="white" stroke-width="1.8" stroke-linecap="round" stroke-linejoin="round"/></svg>"##.to_string(),
             color: "#27ae60".to_string(),
             default_visible: false,
+            wfs: None,
         },
         ArcgisLayerConfig {
             service_name: "Duiker".to_string(),
@@ -126,6 +222,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#d35400" stroke="white" stroke-width="2"/><ellipse cx="14" cy="14" rx="5" ry="3" fill="none" stroke="white" stroke-width="1.8"/></svg>"##.to_string(),
             color: "#d35400".to_string(),
             default_visible: false,
+            wfs: None,
         },
         ArcgisLayerConfig {
             service_name: "Dam".to_string(),
@@ -135,6 +232,7 @@ fn default_arcgis_layers() -> Vec<ArcgisLayerConfig> {
             icon_svg: r##"<svg width="28" height="28" viewBox="0 0 28 28"><circle cx="14" cy="14" r="12" fill="#7f8c8d" stroke="white" stroke-width="2"/><line x1="8" y1="14" x2="20" y2="14" stroke="white" stroke-width="2.5" stroke-linecap="round"/><line x1="14" y1="10" x2="14" y2="18" stroke="white" stroke-width="1.5" stroke-linecap="round"/></svg>"##.to_string(),
             color: "#7f8c8d".to_string(),
             default_visible: false,
+            wfs: None,
         },
     ]
 }
@@ -303,7 +303,7 @@ impl DashboardService {
         });
 
         // Sort by timestamp and limit
-        items.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        items.sort_by_key(|item| std::cmp::Reverse(item.timestamp));
         items.truncate(limit);
 
         let has_more = items.len() >= limit;
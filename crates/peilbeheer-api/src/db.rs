@@ -6,9 +6,14 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use duckdb::{params, Connection};
 
 use peilbeheer_core::asset::AssetRegistratie;
+use peilbeheer_core::energiecontract::EnergieContract;
 use peilbeheer_core::gemaal::{GemaalSnapshot, GemaalStatus, GemaalTrends};
 use peilbeheer_core::hydronet::GeoJsonGemaal;
 use peilbeheer_core::peilgebied::PeilgebiedInfo;
+use peilbeheer_core::projectie::{self, Crs};
+
+use crate::config::PeilgebiedAttribuutMapping;
+use crate::realtime_simulatie::RealtimeInstelling;
 
 #[allow(dead_code)]
 fn datetime_to_string(dt: &DateTime<Utc>) -> String {
@@ -24,10 +29,184 @@ fn parse_datetime(s: &str) -> DateTime<Utc> {
         .unwrap_or_else(|_| Utc::now())
 }
 
+/// Whether an error of [`Database::query_row`] means that no row matched.
+pub fn is_no_rows(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<duckdb::Error>(),
+        Some(duckdb::Error::QueryReturnedNoRows)
+    )
+}
+
 fn parse_optional_datetime(s: Option<String>) -> Option<DateTime<Utc>> {
     s.map(|ds| parse_datetime(&ds))
 }
 
+/// Schrijf een WGS84-kopie van een peilgebieden-GeoJSON die niet in WGS84
+/// staat. Geeft het pad van de (tijdelijke) kopie, of None als het bestand
+/// al bruikbaar is.
+fn peilgebieden_naar_wgs84(path: &str) -> anyhow::Result<Option<String>> {
+    let mut geojson: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if projectie::detecteer_crs(&geojson)? != Some(Crs::RdNew) {
+        return Ok(None);
+    }
+    projectie::herprojecteer_van(&mut geojson, Crs::RdNew, Crs::Wgs84)?;
+
+    let tmp = std::env::temp_dir().join(format!(
+        "peilgebieden_wgs84_{}.geojson",
+        uuid::Uuid::new_v4()
+    ));
+    std::fs::write(&tmp, serde_json::to_vec(&geojson)?)?;
+    tracing::info!("Peilgebieden in {} omgezet van RD New naar WGS84", path);
+    Ok(Some(tmp.to_string_lossy().into_owned()))
+}
+
+/// Identifier tussen dubbele aanhalingstekens, voor kolomnamen uit configuratie.
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Bewaar het maaiveld van de peilgebieden vóór het leegmaken van de tabel.
+fn bewaar_maaiveld(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(
+        "CREATE OR REPLACE TEMP TABLE peilgebied_maaiveld_oud AS
+         SELECT code, maaiveld, maaiveld_bron, maaiveld_bepaald_op
+         FROM peilgebied WHERE maaiveld IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Zet het met [`bewaar_maaiveld`] bewaarde maaiveld terug bij dezelfde codes.
+fn herstel_maaiveld(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE peilgebied
+         SET maaiveld = oud.maaiveld,
+             maaiveld_bron = oud.maaiveld_bron,
+             maaiveld_bepaald_op = oud.maaiveld_bepaald_op
+         FROM peilgebied_maaiveld_oud oud
+         WHERE peilgebied.code = oud.code",
+        [],
+    )?;
+    conn.execute("DROP TABLE IF EXISTS peilgebied_maaiveld_oud", [])?;
+    Ok(())
+}
+
+/// Lees een GeoPackage of Shapefile in de tijdelijke tabel
+/// `peilgebied_import` en tel wat er bij het laden overgeslagen wordt.
+fn lees_peilgebieden_import(
+    conn: &Connection,
+    path: &str,
+    layer: Option<&str>,
+    mapping: &PeilgebiedAttribuutMapping,
+) -> anyhow::Result<PeilgebiedImport> {
+    let tekst = |kolom: &Option<String>| match kolom {
+        Some(k) => format!("NULLIF(TRIM(CAST({} AS VARCHAR)), '')", quote_ident(k)),
+        None => "NULL".to_string(),
+    };
+    let getal = |kolom: &Option<String>| match kolom {
+        Some(k) => format!("TRY_CAST({} AS DOUBLE)", quote_ident(k)),
+        None => "NULL".to_string(),
+    };
+    let bron = match layer {
+        Some(_) => "ST_Read(?, layer := ?)",
+        None => "ST_Read(?)",
+    };
+    let sql = format!(
+        r#"
+        CREATE OR REPLACE TEMP TABLE peilgebied_import AS
+        SELECT {code} AS code, {naam} AS naam, {zomerpeil} AS zomerpeil,
+               {winterpeil} AS winterpeil, {vastpeil} AS vastpeil,
+               {oppervlakte} AS oppervlakte, {soortafwatering} AS soortafwatering,
+               {soortpeilgebied} AS soortpeilgebied,
+               CASE WHEN ST_XMax(geom) > 180 OR ST_YMax(geom) > 90
+                    THEN ST_Transform(geom, 'EPSG:28992', 'EPSG:4326', always_xy := true)
+                    ELSE geom END AS geometry
+        FROM {bron}
+        "#,
+        code = tekst(&Some(mapping.code.clone())),
+        naam = tekst(&mapping.naam),
+        zomerpeil = getal(&mapping.zomerpeil),
+        winterpeil = getal(&mapping.winterpeil),
+        vastpeil = getal(&mapping.vastpeil),
+        oppervlakte = getal(&mapping.oppervlakte),
+        soortafwatering = tekst(&mapping.soortafwatering),
+        soortpeilgebied = tekst(&mapping.soortpeilgebied),
+    );
+    match layer {
+        Some(layer) => conn.execute(&sql, params![path, layer])?,
+        None => conn.execute(&sql, params![path])?,
+    };
+
+    Ok(conn.query_row(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE code IS NULL),
+               COUNT(*) FILTER (WHERE code IS NOT NULL
+                   AND (geometry IS NULL OR ST_IsEmpty(geometry)
+                        OR ST_GeometryType(geometry)::VARCHAR NOT IN ('POLYGON', 'MULTIPOLYGON'))),
+               COUNT(*) FILTER (WHERE code IS NOT NULL AND NOT ST_IsEmpty(geometry)
+                   AND ST_GeometryType(geometry)::VARCHAR IN ('POLYGON', 'MULTIPOLYGON')
+                   AND NOT ST_IsValid(geometry))
+        FROM peilgebied_import
+        "#,
+        [],
+        |row| {
+            Ok(PeilgebiedImport {
+                gelezen: row.get::<_, i64>(0)? as usize,
+                zonder_code: row.get::<_, i64>(1)? as usize,
+                ongeldig_geometrietype: row.get::<_, i64>(2)? as usize,
+                gerepareerd: row.get::<_, i64>(3)? as usize,
+                ..Default::default()
+            })
+        },
+    )?)
+}
+
+/// Voeg de peilgebieden uit `peilgebied_import` toe aan `peilgebied`.
+fn laad_peilgebieden_import(
+    conn: &Connection,
+    import: PeilgebiedImport,
+) -> anyhow::Result<PeilgebiedImport> {
+    let geladen = conn.execute(
+        r#"
+        INSERT INTO peilgebied (code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte,
+                                soortafwatering, soortpeilgebied, geometry)
+        SELECT code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte,
+               soortafwatering, soortpeilgebied,
+               CASE WHEN ST_IsValid(geometry) THEN geometry ELSE ST_MakeValid(geometry) END
+        FROM peilgebied_import
+        WHERE code IS NOT NULL
+          AND geometry IS NOT NULL AND NOT ST_IsEmpty(geometry)
+          AND ST_GeometryType(geometry)::VARCHAR IN ('POLYGON', 'MULTIPOLYGON')
+        QUALIFY ROW_NUMBER() OVER (PARTITION BY code) = 1
+        "#,
+        [],
+    )?;
+    conn.execute("DROP TABLE IF EXISTS peilgebied_import", [])?;
+
+    Ok(PeilgebiedImport {
+        geladen,
+        dubbele_code: import
+            .gelezen
+            .saturating_sub(import.zonder_code + import.ongeldig_geometrietype + geladen),
+        ..import
+    })
+}
+
+/// Resultaat van een peilgebieden-import uit een bestand.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct PeilgebiedImport {
+    /// Features in het bronbestand
+    pub gelezen: usize,
+    pub geladen: usize,
+    pub zonder_code: usize,
+    /// Geen geometrie, leeg, of geen (Multi)Polygon
+    pub ongeldig_geometrietype: usize,
+    /// Ongeldige geometrieën die met ST_MakeValid zijn gerepareerd
+    pub gerepareerd: usize,
+    pub dubbele_code: usize,
+}
+
 /// Database wrapper met thread-safe connection.
 pub struct Database {
     conn: Mutex<Connection>,
@@ -51,6 +230,23 @@ impl Database {
         })
     }
 
+    /// In-memory database with the schema, for tests. Spatial functions are
+    /// only available when the extension is installed.
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        let conn = Connection::open_in_memory().unwrap();
+        if let Err(e) = conn.execute_batch("LOAD spatial;") {
+            tracing::warn!("Spatial extension not available: {}", e);
+        }
+
+        let db = Self {
+            conn: Mutex::new(conn),
+            cached_peilgebieden_geojson: Mutex::new(None),
+        };
+        db.initialize_schema().unwrap();
+        db
+    }
+
     /// Check if a table exists in the database.
     pub fn table_exists(&self, table_name: &str) -> bool {
         let conn = self.conn.lock().unwrap();
@@ -68,10 +264,39 @@ impl Database {
             include_str!("../../../migrations/002_asset_registratie.sql"),
             include_str!("../../../migrations/003_peilgebieden.sql"),
             include_str!("../../../migrations/004_scenarios.sql"),
+            include_str!("../../../migrations/004a_scenarios_history.sql"),
             include_str!("../../../migrations/005_scenario_results.sql"),
+            include_str!("../../../migrations/005a_scenario_results.sql"),
             include_str!("../../../migrations/006_users.sql"),
             include_str!("../../../migrations/007_alerts.sql"),
+            include_str!("../../../migrations/007a_alert_tables.sql"),
             include_str!("../../../migrations/008_timeseries.sql"),
+            include_str!("../../../migrations/009_alert_escalation.sql"),
+            include_str!("../../../migrations/010_maintenance_windows.sql"),
+            include_str!("../../../migrations/011_alert_auto_resolve.sql"),
+            include_str!("../../../migrations/012_alert_flapping.sql"),
+            include_str!("../../../migrations/013_alert_resource_selector.sql"),
+            include_str!("../../../migrations/014_alert_comments.sql"),
+            include_str!("../../../migrations/015_timeseries_expected_interval.sql"),
+            include_str!("../../../migrations/016_users_oidc.sql"),
+            include_str!("../../../migrations/017_api_keys.sql"),
+            include_str!("../../../migrations/018_refresh_tokens.sql"),
+            include_str!("../../../migrations/019_resource_scopes.sql"),
+            include_str!("../../../migrations/020_two_factor.sql"),
+            include_str!("../../../migrations/021_audit_log.sql"),
+            include_str!("../../../migrations/022_sessions.sql"),
+            include_str!("../../../migrations/023_password_reset_tokens.sql"),
+            include_str!("../../../migrations/024_service_accounts.sql"),
+            include_str!("../../../migrations/025_login_throttling.sql"),
+            include_str!("../../../migrations/026_fews_sync_runs.sql"),
+            include_str!("../../../migrations/027_fews_mappings.sql"),
+            include_str!("../../../migrations/028_dhydro_models.sql"),
+            include_str!("../../../migrations/029_energieprijzen.sql"),
+            include_str!("../../../migrations/030_peilgebied_bodem.sql"),
+            include_str!("../../../migrations/031_peilgebied_maaiveld.sql"),
+            include_str!("../../../migrations/032_gemaal_energiecontract.sql"),
+            include_str!("../../../migrations/033_realtime_simulatie.sql"),
+            include_str!("../../../migrations/034_api_key_scope.sql"),
         ];
 
         for schema in migrations {
@@ -490,6 +715,70 @@ impl Database {
         Ok(assets)
     }
 
+    /// Dichtstbijzijnde assets bij een punt (lon, lat), met de afstand in
+    /// meter over de bol. Optioneel beperkt tot lagen en tot assets binnen
+    /// een peilgebied.
+    pub fn find_nearest_assets(
+        &self,
+        lon: f64,
+        lat: f64,
+        layer_types: Option<&[&str]>,
+        peilgebied: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(AssetRegistratie, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let limit = limit as i64;
+
+        let mut params: Vec<&dyn duckdb::ToSql> = vec![&lat, &lat, &lon];
+        let mut filter = String::new();
+        if let Some(types) = layer_types.filter(|t| !t.is_empty()) {
+            let placeholders: Vec<&str> = types.iter().map(|_| "?").collect();
+            filter.push_str(&format!(" AND a.layer_type IN ({})", placeholders.join(", ")));
+            params.extend(types.iter().map(|t| t as &dyn duckdb::ToSql));
+        }
+        if let Some(code) = &peilgebied {
+            filter.push_str(
+                " AND EXISTS (SELECT 1 FROM peilgebied p WHERE p.code = ? \
+                 AND ST_Intersects(p.geometry, ST_Point(a.longitude, a.latitude)))",
+            );
+            params.push(code);
+        }
+        params.push(&limit);
+
+        let query = format!(
+            r#"
+            SELECT a.layer_type, a.code, a.naam, a.latitude, a.longitude, a.extra_properties,
+                   2 * 6371008.8 * ASIN(SQRT(
+                       POWER(SIN(RADIANS(a.latitude - ?) / 2), 2)
+                       + COS(RADIANS(?)) * COS(RADIANS(a.latitude))
+                         * POWER(SIN(RADIANS(a.longitude - ?) / 2), 2)
+                   )) AS afstand_m
+            FROM asset_registratie a
+            WHERE a.latitude IS NOT NULL AND a.longitude IS NOT NULL{filter}
+            ORDER BY afstand_m, a.layer_type, a.code
+            LIMIT ?
+            "#
+        );
+
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params.as_slice(), |row| {
+            let extra_str: Option<String> = row.get(5)?;
+            Ok((
+                AssetRegistratie {
+                    layer_type: row.get(0)?,
+                    code: row.get(1)?,
+                    naam: row.get(2)?,
+                    lat: row.get(3)?,
+                    lon: row.get(4)?,
+                    extra_properties: extra_str.and_then(|s| serde_json::from_str(&s).ok()),
+                },
+                row.get::<_, f64>(6)?,
+            ))
+        })?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
     /// Tel het totaal aantal asset registraties.
     /// Returns 0 if the table doesn't exist yet.
     pub fn get_total_asset_count(&self) -> anyhow::Result<usize> {
@@ -516,31 +805,99 @@ impl Database {
     }
 
     /// Laad peilgebieden vanuit een GeoJSON-bestand via ST_Read.
+    ///
+    /// Bestanden in RD New (EPSG:28992) worden eerst naar WGS84 omgezet.
     pub fn load_peilgebieden_from_geojson(&self, path: &str) -> anyhow::Result<usize> {
+        let wgs84_path = peilgebieden_naar_wgs84(path)?;
+        let path = wgs84_path.as_deref().unwrap_or(path);
         let conn = self.conn.lock().unwrap();
 
-        conn.execute(
+        let result = conn.execute(
             r#"
-            INSERT INTO peilgebied
+            INSERT INTO peilgebied (code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte,
+                                    soortafwatering, soortpeilgebied, geometry)
             SELECT CODE, NAAM, ZOMERPEIL, WINTERPEIL, VASTPEIL, OPPERVLAKTE,
                    SOORTAFWATERING, SOORTPEILGEBIED, geom
             FROM ST_Read(?)
             "#,
             params![path],
-        )?;
+        );
+        if let Some(tmp) = &wgs84_path {
+            let _ = std::fs::remove_file(tmp);
+        }
+        result?;
 
         let count: i64 =
             conn.query_row("SELECT COUNT(*) FROM peilgebied", [], |row| row.get(0))?;
         Ok(count as usize)
     }
 
+    /// Laad peilgebieden uit een GeoPackage of Shapefile via ST_Read.
+    ///
+    /// De attributen komen uit de kolommen van `mapping`. Features zonder
+    /// code, zonder geometrie of met een ander type dan (Multi)Polygon worden
+    /// overgeslagen, ongeldige geometrieën gerepareerd met ST_MakeValid en
+    /// van dubbele codes telt alleen de eerste. Geometrieën in RD New
+    /// (herkend aan coördinaten buiten het gradenbereik) gaan naar WGS84.
+    pub fn load_peilgebieden_from_geopackage(
+        &self,
+        path: &str,
+        layer: Option<&str>,
+        mapping: &PeilgebiedAttribuutMapping,
+    ) -> anyhow::Result<PeilgebiedImport> {
+        let conn = self.conn.lock().unwrap();
+        let import = lees_peilgebieden_import(&conn, path, layer, mapping)?;
+        let import = laad_peilgebieden_import(&conn, import)?;
+        tracing::info!("Peilgebieden uit {path}: {import:?}");
+        Ok(import)
+    }
+
+    /// Herlaad peilgebieden uit een GeoPackage of Shapefile en invalideer de cache.
+    ///
+    /// Het bestand wordt eerst volledig ingelezen; pas daarna worden de
+    /// peilgebieden in één transactie vervangen, zodat een onleesbaar bestand
+    /// de bestaande peilgebieden laat staan. Het maaiveld blijft per code
+    /// behouden; bepaal het opnieuw als de grenzen gewijzigd zijn.
+    pub fn reload_peilgebieden_from_geopackage(
+        &self,
+        path: &str,
+        layer: Option<&str>,
+        mapping: &PeilgebiedAttribuutMapping,
+    ) -> anyhow::Result<PeilgebiedImport> {
+        let conn = self.conn.lock().unwrap();
+        let import = match lees_peilgebieden_import(&conn, path, layer, mapping) {
+            Ok(import) => import,
+            Err(e) => {
+                let _ = conn.execute("DROP TABLE IF EXISTS peilgebied_import", []);
+                return Err(e);
+            }
+        };
+
+        let tx = conn.unchecked_transaction()?;
+        bewaar_maaiveld(&tx)?;
+        tx.execute("DELETE FROM peilgebied", [])?;
+        let import = laad_peilgebieden_import(&tx, import)?;
+        herstel_maaiveld(&tx)?;
+        tx.commit()?;
+        tracing::info!("Peilgebieden uit {path}: {import:?}");
+
+        let mut cache = self.cached_peilgebieden_geojson.lock().unwrap();
+        *cache = None;
+        Ok(import)
+    }
+
     /// Herlaad peilgebieden: leeg tabel, laad opnieuw vanuit GeoJSON, invalideer cache.
+    ///
+    /// Het maaiveld blijft per code behouden.
     pub fn reload_peilgebieden_from_geojson(&self, path: &str) -> anyhow::Result<usize> {
         {
             let conn = self.conn.lock().unwrap();
+            bewaar_maaiveld(&conn)?;
             conn.execute("DELETE FROM peilgebied", [])?;
         }
-        let count = self.load_peilgebieden_from_geojson(path)?;
+        let count = self.load_peilgebieden_from_geojson(path);
+        herstel_maaiveld(&self.conn.lock().unwrap())?;
+        let count = count?;
         // Invalideer de cache zodat het volgende GET verse data teruggeeft
         let mut cache = self.cached_peilgebieden_geojson.lock().unwrap();
         *cache = None;
@@ -563,7 +920,7 @@ impl Database {
         let mut stmt = conn.prepare(
             r#"
             SELECT code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte,
-                   soortafwatering, soortpeilgebied, ST_AsGeoJSON(geometry) AS geojson
+                   soortafwatering, soortpeilgebied, ST_AsGeoJSON(geometry) AS geojson, maaiveld
             FROM peilgebied
             ORDER BY code
             "#,
@@ -581,11 +938,12 @@ impl Database {
                 row.get::<_, Option<String>>(6)?,
                 row.get::<_, Option<String>>(7)?,
                 row.get::<_, String>(8)?,
+                row.get::<_, Option<f64>>(9)?,
             ))
         })?;
 
         for row in rows {
-            let (code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte, soortafwatering, soortpeilgebied, geojson) = row?;
+            let (code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte, soortafwatering, soortpeilgebied, geojson, maaiveld) = row?;
 
             let properties = serde_json::json!({
                 "CODE": code,
@@ -596,6 +954,7 @@ impl Database {
                 "OPPERVLAKTE": oppervlakte,
                 "SOORTAFWATERING": soortafwatering,
                 "SOORTPEILGEBIED": soortpeilgebied,
+                "MAAIVELD": maaiveld,
             });
 
             let geometry: serde_json::Value = serde_json::from_str(&geojson)?;
@@ -617,8 +976,8 @@ impl Database {
         Ok(serde_json::to_string(&collection)?)
     }
 
-    /// Zoek peilgebied bij een punt (lon, lat).
-    #[allow(dead_code)]
+    /// Zoek peilgebied bij een punt (lon, lat). Een punt op de grens hoort
+    /// bij het peilgebied.
     pub fn find_peilgebied_for_point(
         &self,
         lon: f64,
@@ -627,9 +986,11 @@ impl Database {
         let conn = self.conn.lock().unwrap();
         let result = conn.query_row(
             r#"
-            SELECT code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte, soortafwatering
+            SELECT code, naam, zomerpeil, winterpeil, vastpeil, oppervlakte, soortafwatering,
+                   maaiveld
             FROM peilgebied
-            WHERE ST_Contains(geometry, ST_Point(?, ?))
+            WHERE ST_Intersects(geometry, ST_Point(?, ?))
+            ORDER BY code
             LIMIT 1
             "#,
             params![lon, lat],
@@ -642,6 +1003,7 @@ impl Database {
                     vastpeil: row.get(4)?,
                     oppervlakte: row.get(5)?,
                     soortafwatering: row.get(6)?,
+                    maaiveld: row.get(7)?,
                 })
             },
         );
@@ -653,6 +1015,242 @@ impl Database {
         }
     }
 
+    /// Polygoon van een peilgebied als WKT (lon/lat), vereenvoudigd met
+    /// `tolerantie` graden zodat de polygoon in een URL past.
+    pub fn get_peilgebied_wkt(&self, code: &str, tolerantie: f64) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            r#"
+            SELECT ST_AsText(ST_SimplifyPreserveTopology(geometry, ?))
+            FROM peilgebied
+            WHERE code = ?
+            "#,
+            params![tolerantie, code],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(wkt) => Ok(Some(wkt)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Punt binnen een peilgebied als (lon, lat), voor bronnen die alleen
+    /// puntlocaties ondersteunen.
+    pub fn get_peilgebied_punt(&self, code: &str) -> anyhow::Result<Option<(f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            r#"
+            SELECT ST_X(ST_PointOnSurface(geometry)), ST_Y(ST_PointOnSurface(geometry))
+            FROM peilgebied
+            WHERE code = ?
+            "#,
+            params![code],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+
+        match result {
+            Ok(punt) => Ok(Some(punt)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Bounding box van een peilgebied als (min_lon, min_lat, max_lon, max_lat).
+    pub fn get_peilgebied_bbox(&self, code: &str) -> anyhow::Result<Option<(f64, f64, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            r#"
+            SELECT ST_XMin(geometry), ST_YMin(geometry), ST_XMax(geometry), ST_YMax(geometry)
+            FROM peilgebied
+            WHERE code = ?
+            "#,
+            params![code],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        );
+
+        match result {
+            Ok(bbox) => Ok(Some(bbox)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Overlap (graden²) van GeoJSON-geometrieën (WGS84) met een peilgebied,
+    /// in dezelfde volgorde. Ongeldige geometrieën hebben geen overlap.
+    pub fn peilgebied_overlap(&self, code: &str, geometrieen: &[String]) -> anyhow::Result<Vec<f64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT COALESCE(ST_Area(ST_Intersection(ST_MakeValid(ST_GeomFromGeoJSON(?)), geometry)), 0.0)
+            FROM peilgebied
+            WHERE code = ?
+            "#,
+        )?;
+
+        let mut overlap = Vec::with_capacity(geometrieen.len());
+        for geojson in geometrieen {
+            let oppervlakte = stmt
+                .query_row(params![geojson, code], |row| row.get::<_, f64>(0))
+                .unwrap_or_else(|e| {
+                    tracing::debug!("Overlap met peilgebied {} niet bepaald: {}", code, e);
+                    0.0
+                });
+            overlap.push(oppervlakte);
+        }
+        Ok(overlap)
+    }
+
+    /// Geometrie van een peilgebied als GeoJSON (WGS84).
+    pub fn get_peilgebied_geometrie(&self, code: &str) -> anyhow::Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT ST_AsGeoJSON(geometry) FROM peilgebied WHERE code = ?",
+            params![code],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(geojson) => Ok(Some(geojson)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Codes van de peilgebieden, desgewenst alleen die zonder maaiveld.
+    pub fn get_peilgebied_codes(&self, zonder_maaiveld: bool) -> anyhow::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT code FROM peilgebied WHERE NOT ? OR maaiveld IS NULL ORDER BY code",
+        )?;
+        let codes = stmt
+            .query_map(params![zonder_maaiveld], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(codes)
+    }
+
+    /// Maaiveldhoogte (m NAP) van een peilgebied, als die bepaald is.
+    pub fn get_peilgebied_maaiveld(&self, code: &str) -> anyhow::Result<Option<f64>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT maaiveld FROM peilgebied WHERE code = ?",
+            params![code],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(maaiveld) => Ok(maaiveld),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Sla de maaiveldhoogte van een peilgebied op en invalideer de cache.
+    pub fn set_peilgebied_maaiveld(&self, code: &str, maaiveld: f64, bron: &str) -> anyhow::Result<()> {
+        {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE peilgebied
+                 SET maaiveld = ?, maaiveld_bron = ?, maaiveld_bepaald_op = CAST(? AS TIMESTAMP)
+                 WHERE code = ?",
+                params![maaiveld, bron, datetime_to_string(&Utc::now()), code],
+            )?;
+        }
+        let mut cache = self.cached_peilgebieden_geojson.lock().unwrap();
+        *cache = None;
+        Ok(())
+    }
+
+    /// Of een gemaal geregistreerd is of een status heeft.
+    pub fn gemaal_bestaat(&self, gemaal_code: &str) -> anyhow::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let bestaat = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM gemaal_registratie WHERE code = ?)
+                 OR EXISTS (SELECT 1 FROM gemaal_status_snapshot WHERE gemaal_code = ?)",
+            params![gemaal_code, gemaal_code],
+            |row| row.get(0),
+        )?;
+        Ok(bestaat)
+    }
+
+    /// Energiecontract van een gemaal, als dat is vastgelegd.
+    pub fn get_gemaal_energiecontract(&self, gemaal_code: &str) -> anyhow::Result<Option<EnergieContract>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT CAST(contract AS VARCHAR) FROM gemaal_energiecontract WHERE gemaal_code = ?",
+            params![gemaal_code],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Leg het energiecontract van een gemaal vast.
+    pub fn set_gemaal_energiecontract(
+        &self,
+        gemaal_code: &str,
+        contract: &EnergieContract,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO gemaal_energiecontract (gemaal_code, contract, bijgewerkt_op)
+             VALUES (?, ?, CAST(? AS TIMESTAMP))
+             ON CONFLICT (gemaal_code) DO UPDATE SET
+                 contract = excluded.contract,
+                 bijgewerkt_op = excluded.bijgewerkt_op",
+            params![
+                gemaal_code,
+                serde_json::to_string(contract)?,
+                datetime_to_string(&Utc::now())
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Instelling van de realtime simulatie, als die is vastgelegd.
+    pub fn get_realtime_instelling(&self) -> anyhow::Result<Option<RealtimeInstelling>> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.query_row(
+            "SELECT CAST(instelling AS VARCHAR) FROM realtime_simulatie WHERE id = 'default'",
+            [],
+            |row| row.get::<_, String>(0),
+        );
+
+        match result {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Leg de instelling van de realtime simulatie vast, of verwijder haar.
+    pub fn set_realtime_instelling(
+        &self,
+        instelling: Option<&RealtimeInstelling>,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match instelling {
+            Some(instelling) => conn.execute(
+                "INSERT INTO realtime_simulatie (id, instelling, bijgewerkt_op)
+                 VALUES ('default', ?, CAST(? AS TIMESTAMP))
+                 ON CONFLICT (id) DO UPDATE SET
+                     instelling = excluded.instelling,
+                     bijgewerkt_op = excluded.bijgewerkt_op",
+                params![
+                    serde_json::to_string(instelling)?,
+                    datetime_to_string(&Utc::now())
+                ],
+            )?,
+            None => conn.execute("DELETE FROM realtime_simulatie WHERE id = 'default'", [])?,
+        };
+        Ok(())
+    }
+
     /// Bulk koppeling: gemaal_code → peilgebied_code via spatial join.
     pub fn get_gemaal_peilgebied_mapping(&self) -> anyhow::Result<HashMap<String, String>> {
         let conn = self.conn.lock().unwrap();
@@ -688,6 +1286,12 @@ impl Database {
         Ok(())
     }
 
+    /// Execute a SQL statement with parameters; returns the number of affected rows.
+    pub fn execute_count(&self, sql: &str, params: &[&dyn duckdb::ToSql]) -> anyhow::Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.execute(sql, params)?)
+    }
+
     /// Query and map rows using a closure.
     pub fn query<T, F>(
         &self,
@@ -723,3 +1327,203 @@ impl Database {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(layer_type: &str, code: &str, lat: f64, lon: f64) -> AssetRegistratie {
+        AssetRegistratie {
+            layer_type: layer_type.to_string(),
+            code: code.to_string(),
+            naam: None,
+            lat: Some(lat),
+            lon: Some(lon),
+            extra_properties: None,
+        }
+    }
+
+    #[test]
+    fn test_find_nearest_assets() {
+        let db = Database::in_memory();
+        db.write_asset_registraties(&[
+            asset("gemaal", "GEM_VER", 52.010, 4.500),
+            asset("gemaal", "GEM_DICHT", 52.001, 4.500),
+            asset("stuw", "STU_1", 52.0005, 4.500),
+        ])
+        .unwrap();
+
+        let alle = db.find_nearest_assets(4.500, 52.000, None, None, 10).unwrap();
+        let codes: Vec<&str> = alle.iter().map(|(a, _)| a.code.as_str()).collect();
+        assert_eq!(codes, vec!["STU_1", "GEM_DICHT", "GEM_VER"]);
+        // 0,001 graad breedte is ruim 111 m
+        assert!((alle[1].1 - 111.2).abs() < 0.5, "{}", alle[1].1);
+
+        let gemalen = db.find_nearest_assets(4.500, 52.000, Some(&["gemaal"]), None, 1).unwrap();
+        assert_eq!(gemalen.len(), 1);
+        assert_eq!(gemalen[0].0.code, "GEM_DICHT");
+    }
+
+    #[test]
+    #[ignore = "needs the DuckDB spatial extension"]
+    fn test_find_nearest_assets_in_peilgebied() {
+        let db = Database::in_memory();
+        db.execute(
+            "INSERT INTO peilgebied (code, naam, geometry)
+             VALUES ('PG_1', 'Noord', ST_GeomFromText('POLYGON((4.5 52.0, 4.6 52.0, 4.6 52.1, 4.5 52.1, 4.5 52.0))'))",
+            &[],
+        )
+        .unwrap();
+        db.write_asset_registraties(&[
+            // Buiten het peilgebied, maar het dichtst bij
+            asset("gemaal", "GEM_BUITEN", 51.9995, 4.550),
+            // Op de grens
+            asset("gemaal", "GEM_GRENS", 52.000, 4.560),
+        ])
+        .unwrap();
+
+        let gemalen = db.find_nearest_assets(4.550, 52.000, None, Some("PG_1"), 5).unwrap();
+        let codes: Vec<&str> = gemalen.iter().map(|(a, _)| a.code.as_str()).collect();
+        assert_eq!(codes, vec!["GEM_GRENS"]);
+
+        // Een punt op de grens ligt in het peilgebied
+        let peilgebied = db.find_peilgebied_for_point(4.5, 52.05).unwrap();
+        assert_eq!(peilgebied.map(|p| p.code).as_deref(), Some("PG_1"));
+        assert!(db.find_peilgebied_for_point(4.4, 52.05).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reload_peilgebieden_keeps_rows_on_failed_import() {
+        let db = Database::in_memory();
+        db.execute(
+            "INSERT INTO peilgebied (code, naam, maaiveld) VALUES ('PG_1', 'Noord', -0.20)",
+            &[],
+        )
+        .unwrap();
+        let mapping = PeilgebiedAttribuutMapping {
+            code: "PGCODE".to_string(),
+            naam: None,
+            zomerpeil: None,
+            winterpeil: None,
+            vastpeil: None,
+            oppervlakte: None,
+            soortafwatering: None,
+            soortpeilgebied: None,
+        };
+
+        // Zonder spatial-extensie ontbreekt ST_Read, anders het bestand
+        let pad = std::env::temp_dir().join("peilbeheer_bestaat_niet.gpkg");
+        assert!(db
+            .reload_peilgebieden_from_geopackage(pad.to_str().unwrap(), None, &mapping)
+            .is_err());
+
+        let rijen: Vec<(String, f64)> = db
+            .query("SELECT code, maaiveld FROM peilgebied", &[], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(rijen, vec![("PG_1".to_string(), -0.20)]);
+        assert!(!db.table_exists("peilgebied_import"));
+    }
+
+    /// Schrijf een GeoPackage met zes peilgebieden: één in WGS84, één in
+    /// RD New, een dubbele code, een feature zonder code, een punt en een
+    /// zichzelf snijdende polygoon.
+    fn peilgebieden_fixture(db: &Database) -> std::path::PathBuf {
+        let pad = std::env::temp_dir()
+            .join(format!("peilbeheer_peilgebieden_{}.gpkg", std::process::id()));
+        let _ = std::fs::remove_file(&pad);
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(&format!(
+                r#"
+                COPY (
+                    SELECT PGCODE, PGNAAM, CAST(ZP AS DOUBLE) AS ZP, CAST(WP AS DOUBLE) AS WP,
+                           ST_GeomFromText(wkt) AS geom
+                    FROM (VALUES
+                        ('PG_1', 'Noord', -0.60, -0.70,
+                         'POLYGON((4.5 52.1, 4.6 52.1, 4.6 52.2, 4.5 52.2, 4.5 52.1))'),
+                        ('PG_2', 'Zuid', -1.20, -1.30,
+                         'POLYGON((100000 450000, 101000 450000, 101000 451000, 100000 451000, 100000 450000))'),
+                        ('PG_1', 'Dubbel', 0.00, 0.00,
+                         'POLYGON((4.7 52.1, 4.8 52.1, 4.8 52.2, 4.7 52.2, 4.7 52.1))'),
+                        (NULL, 'Zonder code', 0.00, 0.00,
+                         'POLYGON((4.7 52.3, 4.8 52.3, 4.8 52.4, 4.7 52.4, 4.7 52.3))'),
+                        ('PG_3', 'Punt', 0.00, 0.00, 'POINT(4.5 52.1)'),
+                        ('PG_4', 'Vlinder', -0.40, -0.50,
+                         'POLYGON((4.0 52.0, 4.1 52.1, 4.1 52.0, 4.0 52.1, 4.0 52.0))')
+                    ) AS t(PGCODE, PGNAAM, ZP, WP, wkt)
+                ) TO '{}' WITH (FORMAT GDAL, DRIVER 'GPKG', LAYER_NAME 'peilgebieden');
+                "#,
+                pad.display()
+            ))
+            .unwrap();
+        pad
+    }
+
+    #[test]
+    #[ignore = "needs the DuckDB spatial extension"]
+    fn test_load_peilgebieden_from_geopackage() {
+        let db = Database::in_memory();
+        let pad = peilgebieden_fixture(&db);
+        let mapping = PeilgebiedAttribuutMapping {
+            code: "PGCODE".to_string(),
+            naam: Some("PGNAAM".to_string()),
+            zomerpeil: Some("ZP".to_string()),
+            winterpeil: Some("WP".to_string()),
+            vastpeil: None,
+            oppervlakte: None,
+            soortafwatering: None,
+            soortpeilgebied: None,
+        };
+
+        let import = db
+            .load_peilgebieden_from_geopackage(pad.to_str().unwrap(), Some("peilgebieden"), &mapping)
+            .unwrap();
+        let _ = std::fs::remove_file(&pad);
+
+        assert_eq!(import.gelezen, 6);
+        assert_eq!(import.geladen, 3);
+        assert_eq!(import.zonder_code, 1);
+        assert_eq!(import.ongeldig_geometrietype, 1);
+        assert_eq!(import.gerepareerd, 1);
+        assert_eq!(import.dubbele_code, 1);
+
+        let codes: Vec<String> = db
+            .query("SELECT code FROM peilgebied ORDER BY code", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(codes, vec!["PG_1", "PG_2", "PG_4"]);
+
+        let (zomerpeil, winterpeil, vastpeil, naam): (f64, f64, Option<f64>, String) = db
+            .query_row(
+                "SELECT zomerpeil, winterpeil, vastpeil, naam FROM peilgebied WHERE code = 'PG_2'",
+                &[],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!((zomerpeil, winterpeil, vastpeil), (-1.2, -1.3, None));
+        assert_eq!(naam, "Zuid");
+
+        // RD New is naar WGS84 omgezet
+        let (x, y): (f64, f64) = db
+            .query_row(
+                "SELECT ST_X(ST_Centroid(geometry)), ST_Y(ST_Centroid(geometry))
+                 FROM peilgebied WHERE code = 'PG_2'",
+                &[],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!((3.0..8.0).contains(&x) && (50.0..54.0).contains(&y), "{x}, {y}");
+
+        // De vlinder is gerepareerd
+        let geldig: bool = db
+            .query_row(
+                "SELECT ST_IsValid(geometry) FROM peilgebied WHERE code = 'PG_4'",
+                &[],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(geldig);
+    }
+}
@@ -0,0 +1,197 @@
+//! Cached catalog of the models available on D-Hydro.
+//!
+//! The catalog is fetched from D-Hydro every `DHYDRO_MODEL_REFRESH_SECS`
+//! and stored in the `dhydro_models` table; models D-Hydro no longer lists
+//! are dropped. Reads only use the cache, so the model list stays available
+//! while D-Hydro is unreachable or not configured.
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use peilbeheer_core::{DhydroClient, DhydroModel, DhydroModelCatalog};
+
+use crate::db::{Database, is_no_rows};
+
+/// Columns read by [`model_from_row`].
+const MODEL_COLUMNS: &str = "id, name, description, model_type, version, \
+     CAST(valid_from AS VARCHAR), CAST(valid_until AS VARCHAR), is_active, parameters, \
+     CAST(created_at AS VARCHAR), CAST(updated_at AS VARCHAR)";
+
+/// D-Hydro model catalog backed by DuckDB.
+pub struct ModelCatalogService {
+    db: Arc<Database>,
+    /// None when D-Hydro is not configured
+    client: Option<Arc<Mutex<DhydroClient>>>,
+    refresh_interval: StdDuration,
+}
+
+impl ModelCatalogService {
+    /// Create a new catalog.
+    pub fn new(db: Arc<Database>, client: Option<Arc<Mutex<DhydroClient>>>) -> Self {
+        let refresh_secs = std::env::var("DHYDRO_MODEL_REFRESH_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600);
+
+        Self {
+            db,
+            client,
+            refresh_interval: StdDuration::from_secs(refresh_secs),
+        }
+    }
+
+    /// Start the periodic refresh (no-op without a D-Hydro client).
+    pub fn start(self: &Arc<Self>) {
+        if self.client.is_none() {
+            info!("D-Hydro model catalog refresh disabled (D-Hydro not configured)");
+            return;
+        }
+
+        let catalog = Arc::clone(self);
+        let interval = self.refresh_interval.max(StdDuration::from_secs(60));
+
+        tokio::spawn(async move {
+            info!("D-Hydro model catalog refresh started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                match catalog.refresh().await {
+                    Ok(count) => info!("D-Hydro model catalog refreshed: {} models", count),
+                    Err(e) => warn!("D-Hydro model catalog refresh failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Fetch the models from D-Hydro and replace the cached catalog.
+    pub async fn refresh(&self) -> AnyhowResult<usize> {
+        let Some(client) = &self.client else {
+            anyhow::bail!("D-Hydro is not configured");
+        };
+        let models = client.lock().await.list_models().await?;
+        self.replace_models(&models)?;
+        Ok(models.len())
+    }
+
+    /// Store the models of a refresh and drop those it no longer returned.
+    fn replace_models(&self, models: &[DhydroModel]) -> AnyhowResult<()> {
+        let fetched_at = format_timestamp(Utc::now());
+        for model in models {
+            let parameters = serde_json::to_string(&model.parameters)?;
+            self.db.execute(
+                r#"
+                INSERT INTO dhydro_models (
+                    id, name, description, model_type, version, valid_from, valid_until,
+                    is_active, parameters, created_at, updated_at, fetched_at
+                ) VALUES (?, ?, ?, ?, ?, CAST(? AS TIMESTAMP), CAST(? AS TIMESTAMP), ?, ?,
+                          CAST(? AS TIMESTAMP), CAST(? AS TIMESTAMP), CAST(? AS TIMESTAMP))
+                ON CONFLICT (id) DO UPDATE SET
+                    name = excluded.name,
+                    description = excluded.description,
+                    model_type = excluded.model_type,
+                    version = excluded.version,
+                    valid_from = excluded.valid_from,
+                    valid_until = excluded.valid_until,
+                    is_active = excluded.is_active,
+                    parameters = excluded.parameters,
+                    created_at = excluded.created_at,
+                    updated_at = excluded.updated_at,
+                    fetched_at = excluded.fetched_at
+                "#,
+                &[
+                    &model.id as &dyn duckdb::ToSql,
+                    &model.name,
+                    &model.description,
+                    &model.model_type,
+                    &model.version,
+                    &model.valid_from.map(format_timestamp),
+                    &model.valid_until.map(format_timestamp),
+                    &model.is_active,
+                    &parameters,
+                    &model.created_at.map(format_timestamp),
+                    &model.updated_at.map(format_timestamp),
+                    &fetched_at,
+                ],
+            )?;
+        }
+
+        self.db.execute(
+            "DELETE FROM dhydro_models WHERE fetched_at < CAST(? AS TIMESTAMP)",
+            &[&fetched_at as &dyn duckdb::ToSql],
+        )?;
+        Ok(())
+    }
+
+    /// Cached models; only those available at `at` unless `all` is set.
+    pub fn catalog(&self, all: bool, at: DateTime<Utc>) -> AnyhowResult<DhydroModelCatalog> {
+        let models = self.db.query(
+            &format!("SELECT {} FROM dhydro_models ORDER BY name, version", MODEL_COLUMNS),
+            &[],
+            model_from_row,
+        )?;
+        let refreshed_at = self.db.query_row(
+            "SELECT CAST(MAX(fetched_at) AS VARCHAR) FROM dhydro_models",
+            &[],
+            |row| row.get::<_, Option<String>>(0),
+        )?;
+
+        Ok(DhydroModelCatalog {
+            models: models
+                .into_iter()
+                .filter(|model| all || model.is_available_at(at))
+                .collect(),
+            refreshed_at: refreshed_at.as_deref().and_then(parse_timestamp),
+        })
+    }
+
+    /// A cached model by id.
+    pub fn get_model(&self, id: &str) -> AnyhowResult<Option<DhydroModel>> {
+        match self.db.query_row(
+            &format!("SELECT {} FROM dhydro_models WHERE id = ?", MODEL_COLUMNS),
+            &[&id as &dyn duckdb::ToSql],
+            model_from_row,
+        ) {
+            Ok(model) => Ok(Some(model)),
+            Err(e) if is_no_rows(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn model_from_row(row: &duckdb::Row) -> duckdb::Result<DhydroModel> {
+    let timestamp = |idx| -> duckdb::Result<Option<DateTime<Utc>>> {
+        Ok(row.get::<_, Option<String>>(idx)?.as_deref().and_then(parse_timestamp))
+    };
+
+    Ok(DhydroModel {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        model_type: row.get(3)?,
+        version: row.get(4)?,
+        valid_from: timestamp(5)?,
+        valid_until: timestamp(6)?,
+        is_active: row.get(7)?,
+        parameters: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+        created_at: timestamp(9)?,
+        updated_at: timestamp(10)?,
+    })
+}
+
+fn format_timestamp(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|ndt| ndt.and_utc())
+}
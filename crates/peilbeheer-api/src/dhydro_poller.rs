@@ -0,0 +1,264 @@
+//! Background polling of scenario executions running on D-Hydro.
+//!
+//! Executions handed to D-Hydro carry the D-Hydro job id in
+//! `scenario_results.dhydro_job_id`. Every interval the poller requests the
+//! status of each pending or running job, stores changes on the execution
+//! result and broadcasts them to WebSocket clients as `scenario.status`,
+//! followed by `scenario.completed` once the job has finished. The time
+//! series of a completed run are imported into the time series store.
+
+use anyhow::Result as AnyhowResult;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::{
+    DhydroClient, ExecutionStatus, ScenarioResult, ScenarioResults, ScenarioStatus,
+    StoredScenarioResult,
+};
+
+use crate::scenario_service::ScenarioService;
+use crate::timeseries_service::TimeSeriesService;
+use crate::websocket_service::WebSocketServer;
+
+/// Poller configuration.
+#[derive(Debug, Clone)]
+pub struct DhydroPollerConfig {
+    /// Whether the periodic loop runs
+    pub enabled: bool,
+    /// Time between polls (seconds)
+    pub interval_secs: u64,
+}
+
+impl Default for DhydroPollerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: std::env::var("DHYDRO_POLL_ENABLED")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            interval_secs: std::env::var("DHYDRO_POLL_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+        }
+    }
+}
+
+/// Polls D-Hydro for the status of running scenario executions.
+pub struct DhydroPoller {
+    /// The client caches its OAuth token, so calls need exclusive access
+    client: Arc<Mutex<DhydroClient>>,
+    scenario_service: Arc<ScenarioService>,
+    timeseries_service: Arc<TimeSeriesService>,
+    ws_server: Arc<WebSocketServer>,
+    config: DhydroPollerConfig,
+}
+
+impl DhydroPoller {
+    /// Create a new poller.
+    pub fn new(
+        client: Arc<Mutex<DhydroClient>>,
+        scenario_service: Arc<ScenarioService>,
+        timeseries_service: Arc<TimeSeriesService>,
+        ws_server: Arc<WebSocketServer>,
+        config: DhydroPollerConfig,
+    ) -> Self {
+        Self {
+            client,
+            scenario_service,
+            timeseries_service,
+            ws_server,
+            config,
+        }
+    }
+
+    /// Start the background polling loop (no-op when disabled).
+    pub fn start(self: &Arc<Self>) {
+        if !self.config.enabled {
+            info!("D-Hydro poller disabled");
+            return;
+        }
+
+        let poller = Arc::clone(self);
+        let interval = StdDuration::from_secs(self.config.interval_secs.max(1));
+
+        tokio::spawn(async move {
+            info!("D-Hydro poller started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = poller.poll_once().await {
+                    warn!("D-Hydro poll failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Poll every running execution once; returns the number of updates.
+    pub async fn poll_once(&self) -> AnyhowResult<usize> {
+        let running = self.scenario_service.list_running_dhydro_results()?;
+        let mut updated = 0;
+
+        for stored in &running {
+            let Some(job_id) = stored.dhydro_job_id.as_deref() else {
+                continue;
+            };
+
+            let remote = match self.client.lock().await.get_scenario_result(job_id).await {
+                Ok(remote) => remote,
+                Err(e) => {
+                    warn!("D-Hydro status of job {} unavailable: {}", job_id, e);
+                    continue;
+                }
+            };
+
+            let Some(status) = status_change(stored, &remote) else {
+                continue;
+            };
+            self.apply(stored, status, &remote).await?;
+            updated += 1;
+        }
+
+        debug!("D-Hydro poll: {} of {} executions updated", updated, running.len());
+        Ok(updated)
+    }
+
+    async fn apply(
+        &self,
+        stored: &StoredScenarioResult,
+        status: ExecutionStatus,
+        remote: &ScenarioResult,
+    ) -> AnyhowResult<()> {
+        let summary = remote
+            .results
+            .as_ref()
+            .map(|results| serde_json::to_value(&results.summary))
+            .transpose()?;
+        let error_message = match remote.status {
+            ScenarioStatus::Failed => remote.error_message.as_deref(),
+            _ => None,
+        };
+
+        if status == ExecutionStatus::Completed
+            && let Some(results) = &remote.results
+        {
+            self.import_time_series(stored, results).await;
+        }
+
+        self.scenario_service.update_scenario_result(
+            &stored.id,
+            status,
+            summary.as_ref(),
+            error_message,
+            None,
+        )?;
+        info!(
+            "Scenario {} execution {}: {} -> {}",
+            stored.scenario_id,
+            stored.id,
+            stored.status,
+            status.as_str()
+        );
+
+        self.ws_server
+            .scenario_status(&stored.scenario_id, status.as_str())
+            .await;
+        if status.is_finished() {
+            self.ws_server
+                .scenario_completed(
+                    &stored.scenario_id,
+                    &stored.id,
+                    status == ExecutionStatus::Completed,
+                )
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Import the output series of a run; failures are logged per series.
+    async fn import_time_series(&self, stored: &StoredScenarioResult, results: &ScenarioResults) {
+        let mut points = 0;
+        for series in &results.time_series {
+            match self
+                .timeseries_service
+                .import_from_dhydro(series, &stored.scenario_id)
+                .await
+            {
+                Ok(result) => points += result.points_written,
+                Err(e) => warn!(
+                    "Import of D-Hydro series {} of scenario {} failed: {}",
+                    series.id, stored.scenario_id, e
+                ),
+            }
+        }
+        info!(
+            "Imported {} D-Hydro series ({} points) of scenario {}",
+            results.time_series.len(),
+            points,
+            stored.scenario_id
+        );
+    }
+}
+
+/// New status of a stored execution, if D-Hydro reports a different one.
+fn status_change(stored: &StoredScenarioResult, remote: &ScenarioResult) -> Option<ExecutionStatus> {
+    let status = ExecutionStatus::from(&remote.status);
+    (ExecutionStatus::from_str(&stored.status) != Some(status)).then_some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn stored(status: &str) -> StoredScenarioResult {
+        StoredScenarioResult {
+            id: "RES_1".to_string(),
+            scenario_id: "SCN_1".to_string(),
+            status: status.to_string(),
+            started_at: None,
+            completed_at: None,
+            duration_seconds: None,
+            results_summary: serde_json::json!({}),
+            time_series_count: 0,
+            output_files: serde_json::json!([]),
+            error_message: None,
+            error_code: None,
+            created_at: Utc::now(),
+            created_by: None,
+            dhydro_job_id: Some("JOB_1".to_string()),
+            dhydro_result_url: None,
+        }
+    }
+
+    fn remote(status: ScenarioStatus) -> ScenarioResult {
+        ScenarioResult {
+            id: "JOB_1".to_string(),
+            scenario_id: "DH_1".to_string(),
+            status,
+            started_at: None,
+            completed_at: None,
+            error_message: None,
+            results: None,
+        }
+    }
+
+    #[test]
+    fn test_status_change() {
+        assert_eq!(status_change(&stored("pending"), &remote(ScenarioStatus::Pending)), None);
+        assert_eq!(
+            status_change(&stored("pending"), &remote(ScenarioStatus::Running)),
+            Some(ExecutionStatus::Running)
+        );
+        assert_eq!(status_change(&stored("running"), &remote(ScenarioStatus::Running)), None);
+        assert_eq!(
+            status_change(&stored("running"), &remote(ScenarioStatus::Failed)),
+            Some(ExecutionStatus::Failed)
+        );
+    }
+}
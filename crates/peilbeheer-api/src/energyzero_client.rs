@@ -1,13 +1,21 @@
-use chrono::{NaiveDate, Timelike, Utc};
-use peilbeheer_core::energie::UurPrijs;
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike, Utc};
+use peilbeheer_core::energie::{HourlyPrice, UurPrijs};
 use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use crate::db::Database;
+use crate::http_resilience::{HttpClient, HttpError};
 
 /// Errors from EnergyZero API calls.
 #[derive(Debug, Error)]
 pub enum EnergyZeroError {
     #[error("HTTP request failed: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(#[from] HttpError),
 
     #[error("API returned error status {status}: {message}")]
     ApiError { status: reqwest::StatusCode, message: String },
@@ -20,6 +28,12 @@ pub enum EnergyZeroError {
 
     #[error("Invalid date: {0}")]
     InvalidDate(String),
+
+    #[error("Prices for {0} are not available yet (published around 13:00)")]
+    NotYetAvailable(NaiveDate),
+
+    #[error("Price cache error: {0}")]
+    Cache(#[from] anyhow::Error),
 }
 
 impl From<EnergyZeroError> for String {
@@ -37,7 +51,6 @@ struct EnergyZeroResponse {
 #[derive(Debug, Deserialize)]
 struct EnergyZeroPriceEntry {
     #[serde(rename = "readingDate")]
-    #[allow(dead_code)]
     reading_date: String,
     price: f64,
 }
@@ -50,23 +63,22 @@ pub async fn fetch_energieprijzen_vandaag() -> Result<Vec<UurPrijs>, EnergyZeroE
     fetch_energieprijzen(today).await
 }
 
-/// Internal helper to fetch prices for a date without padding.
-async fn fetch_energieprijzen_single_day(datum: NaiveDate) -> Result<Vec<UurPrijs>, EnergyZeroError> {
-    let from = format!("{}T00:00:00.000Z", datum);
-    let till = format!(
-        "{}T00:00:00.000Z",
-        datum.checked_add_days(chrono::Days::new(1))
-            .ok_or_else(|| EnergyZeroError::InvalidDate("No next day".to_string()))?
-    );
-
+/// Haal de uurprijzen van één (UTC-)dag op bij EnergyZero.
+///
+/// De API retourneert prijzen in €/kWh, gesorteerd op readingDate; een dag
+/// waarvan de veiling nog niet is geweest geeft een lege lijst.
+async fn fetch_uurprijzen(datum: NaiveDate) -> Result<Vec<HourlyPrice>, EnergyZeroError> {
+    let dag_start = datum.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
     let url = format!(
         "https://api.energyzero.nl/v1/energyprices?fromDate={}&tillDate={}&interval=4&usageType=1&inclBtw=true",
-        from, till
+        dag_start.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+        (dag_start + Duration::days(1)).format("%Y-%m-%dT%H:%M:%S%.3fZ")
     );
 
     tracing::debug!("EnergyZero request: {}", url);
 
-    let response = reqwest::get(&url).await?;
+    let client = HttpClient::shared();
+    let response = client.send(client.get(&url)).await?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -74,18 +86,22 @@ async fn fetch_energieprijzen_single_day(datum: NaiveDate) -> Result<Vec<UurPrij
         return Err(EnergyZeroError::ApiError { status, message });
     }
 
-    let data: EnergyZeroResponse = response.json().await?;
-
-    // De API retourneert prijzen in €/kWh, gesorteerd op readingDate.
-    // We nemen de eerste 24 entries en mappen ze naar uur 0..23.
-    let prijzen: Vec<UurPrijs> = data
+    let data: EnergyZeroResponse = response.json().await.map_err(HttpError::from)?;
+    let now = Utc::now();
+    let prijzen: Vec<HourlyPrice> = data
         .prices
         .into_iter()
         .take(24)
         .enumerate()
-        .map(|(i, entry)| UurPrijs {
-            uur: i as u8,
-            prijs_eur_kwh: entry.price,
+        .map(|(i, entry)| {
+            let hour_start = DateTime::parse_from_rfc3339(&entry.reading_date)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| dag_start + Duration::hours(i as i64));
+            HourlyPrice {
+                hour_start,
+                price_eur_kwh: entry.price,
+                is_forecast: hour_start > now,
+            }
         })
         .collect();
 
@@ -98,6 +114,11 @@ async fn fetch_energieprijzen_single_day(datum: NaiveDate) -> Result<Vec<UurPrij
     Ok(prijzen)
 }
 
+/// Internal helper to fetch prices for a date without padding.
+async fn fetch_energieprijzen_single_day(datum: NaiveDate) -> Result<Vec<UurPrijs>, EnergyZeroError> {
+    Ok(uur_prijzen(&fetch_uurprijzen(datum).await?))
+}
+
 /// Haal de EPEX-spotprijzen op voor een specifieke datum.
 /// Padt aan tot 24 uur door prijzen van de volgende dag te halen indien nodig.
 pub async fn fetch_energieprijzen(datum: NaiveDate) -> Result<Vec<UurPrijs>, EnergyZeroError> {
@@ -115,15 +136,12 @@ pub async fn fetch_energieprijzen(datum: NaiveDate) -> Result<Vec<UurPrijs>, Ene
         );
 
         // Try to fetch from next day to fill the gap (non-recursive)
-        if let Some(next_date) = datum.checked_add_days(chrono::Days::new(1)) {
-            if let Ok(next_prijzen) = fetch_energieprijzen_single_day(next_date).await {
-                let take_count = remaining.min(next_prijzen.len());
-                for (i, p) in next_prijzen.into_iter().take(take_count).enumerate() {
-                    prijzen.push(UurPrijs {
-                        uur: (prijzen.len() + i) as u8,
-                        prijs_eur_kwh: p.prijs_eur_kwh,
-                    });
-                }
+        if let Some(next_date) = datum.checked_add_days(chrono::Days::new(1))
+            && let Ok(next_prijzen) = fetch_energieprijzen_single_day(next_date).await
+        {
+            let take_count = remaining.min(next_prijzen.len());
+            for (i, p) in next_prijzen.into_iter().take(take_count).enumerate() {
+                prijzen.push(UurPrijs::uur((prijzen.len() + i) as u8, p.prijs_eur_kwh));
             }
         }
     }
@@ -136,35 +154,185 @@ pub async fn fetch_energieprijzen(datum: NaiveDate) -> Result<Vec<UurPrijs>, Ene
     Ok(prijzen)
 }
 
-/// Fetch prices for multiple consecutive days.
-pub async fn fetch_energieprijzen_multiple_days(start_date: NaiveDate, days: u8) -> Result<Vec<UurPrijs>, EnergyZeroError> {
-    let mut all_prijzen = Vec::new();
+/// Uurprijzen genummerd vanaf uur 0.
+pub fn uur_prijzen(prijzen: &[HourlyPrice]) -> Vec<UurPrijs> {
+    prijzen
+        .iter()
+        .enumerate()
+        .map(|(i, p)| UurPrijs::uur(i as u8, p.price_eur_kwh))
+        .collect()
+}
+
+/// Day-ahead prices cached in DuckDB.
+///
+/// Complete days are read from the `energieprijzen` table; other days are
+/// fetched from EnergyZero and stored. Stored prices are kept, so the table
+/// also holds the price history for savings reports. The periodic sync
+/// stores today's prices and, once the day-ahead auction is published,
+/// tomorrow's.
+pub struct EnergyPriceStore {
+    db: Arc<Database>,
+    sync_interval: StdDuration,
+}
+
+impl EnergyPriceStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        let sync_secs = std::env::var("ENERGYZERO_SYNC_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(3600);
+
+        Self {
+            db,
+            sync_interval: StdDuration::from_secs(sync_secs),
+        }
+    }
+
+    /// Start the periodic sync of today's and tomorrow's prices.
+    pub fn start(self: &Arc<Self>) {
+        let store = Arc::clone(self);
+        let interval = self.sync_interval.max(StdDuration::from_secs(300));
 
-    for day_offset in 0..days {
-        let date = start_date
-            .checked_add_days(chrono::Days::new(day_offset as u64))
-            .ok_or_else(|| EnergyZeroError::InvalidDate("Invalid date offset".to_string()))?;
+        tokio::spawn(async move {
+            info!("Energy price sync started (interval: {:?})", interval);
 
-        match fetch_energieprijzen(date).await {
-            Ok(mut prijzen) => {
-                // Adjust hour numbers to be continuous across days
-                for p in &mut prijzen {
-                    p.uur += 24 * day_offset;
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let today = Utc::now().date_naive();
+                for datum in [Some(today), today.succ_opt()].into_iter().flatten() {
+                    match store.dag_prijzen(datum).await {
+                        Ok(_) => {}
+                        Err(EnergyZeroError::NotYetAvailable(_)) => {
+                            debug!("Energy prices for {} not published yet", datum)
+                        }
+                        Err(e) => warn!("Energy price sync for {} failed: {}", datum, e),
+                    }
                 }
-                all_prijzen.extend(prijzen);
             }
-            Err(EnergyZeroError::ApiError { .. }) => {
-                // If we can't fetch a future day, that's okay - just return what we have
-                tracing::warn!("Failed to fetch prices for day {}: API error, using {} hours total", day_offset, all_prijzen.len());
-                break;
+        });
+    }
+
+    /// Hourly prices of a (UTC) day, from the cache when complete.
+    pub async fn dag_prijzen(&self, datum: NaiveDate) -> Result<Vec<HourlyPrice>, EnergyZeroError> {
+        let dag_start = datum.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let dag_eind = dag_start + Duration::days(1);
+
+        let cached = self.historie(dag_start, dag_eind)?;
+        if cached.len() >= 24 {
+            debug!("Energy prices for {} from cache", datum);
+            return Ok(cached);
+        }
+
+        let prijzen = fetch_uurprijzen(datum).await?;
+        if prijzen.is_empty() {
+            return Err(if dag_start > Utc::now() {
+                EnergyZeroError::NotYetAvailable(datum)
+            } else {
+                EnergyZeroError::InsufficientData(0)
+            });
+        }
+        self.store(&prijzen)?;
+        Ok(prijzen)
+    }
+
+    /// Prices of `days` consecutive days from `start_date`.
+    ///
+    /// Stops at the first day that is not available yet, so the result can
+    /// be shorter than requested.
+    pub async fn prijzen_vanaf(
+        &self,
+        start_date: NaiveDate,
+        days: u8,
+    ) -> Result<Vec<HourlyPrice>, EnergyZeroError> {
+        let mut all_prijzen = Vec::new();
+
+        for day_offset in 0..days {
+            let date = start_date
+                .checked_add_days(chrono::Days::new(day_offset as u64))
+                .ok_or_else(|| EnergyZeroError::InvalidDate("Invalid date offset".to_string()))?;
+
+            match self.dag_prijzen(date).await {
+                Ok(prijzen) => all_prijzen.extend(prijzen),
+                Err(EnergyZeroError::NotYetAvailable(_) | EnergyZeroError::ApiError { .. })
+                    if day_offset > 0 =>
+                {
+                    // A future day may not be published yet - return what we have
+                    warn!(
+                        "No prices for day {}, using {} hours total",
+                        day_offset,
+                        all_prijzen.len()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => return Err(e),
         }
+
+        if all_prijzen.is_empty() {
+            return Err(EnergyZeroError::InsufficientData(0));
+        }
+
+        Ok(all_prijzen)
     }
 
-    if all_prijzen.is_empty() {
-        return Err(EnergyZeroError::InsufficientData(0));
+    /// Stored prices of the hours starting in `[van, tot)`.
+    pub fn historie(&self, van: DateTime<Utc>, tot: DateTime<Utc>) -> AnyhowResult<Vec<HourlyPrice>> {
+        let now = Utc::now();
+        self.db.query(
+            "SELECT CAST(uur_start AS VARCHAR), prijs_eur_kwh FROM energieprijzen
+             WHERE uur_start >= CAST(? AS TIMESTAMP) AND uur_start < CAST(? AS TIMESTAMP)
+             ORDER BY uur_start",
+            &[
+                &format_timestamp(van) as &dyn duckdb::ToSql,
+                &format_timestamp(tot),
+            ],
+            |row| {
+                let hour_start = parse_timestamp(&row.get::<_, String>(0)?).unwrap_or(van);
+                Ok(HourlyPrice {
+                    hour_start,
+                    price_eur_kwh: row.get(1)?,
+                    is_forecast: hour_start > now,
+                })
+            },
+        )
     }
 
-    Ok(all_prijzen)
+    fn store(&self, prijzen: &[HourlyPrice]) -> AnyhowResult<()> {
+        let opgehaald_op = format_timestamp(Utc::now());
+        for prijs in prijzen {
+            let hour_start = prijs
+                .hour_start
+                .with_minute(0)
+                .and_then(|dt| dt.with_second(0))
+                .and_then(|dt| dt.with_nanosecond(0))
+                .unwrap_or(prijs.hour_start);
+            self.db.execute(
+                "INSERT INTO energieprijzen (uur_start, prijs_eur_kwh, bron, opgehaald_op)
+                 VALUES (CAST(? AS TIMESTAMP), ?, 'energyzero', CAST(? AS TIMESTAMP))
+                 ON CONFLICT (uur_start) DO UPDATE SET
+                     prijs_eur_kwh = excluded.prijs_eur_kwh,
+                     bron = excluded.bron,
+                     opgehaald_op = excluded.opgehaald_op",
+                &[
+                    &format_timestamp(hour_start) as &dyn duckdb::ToSql,
+                    &prijs.price_eur_kwh,
+                    &opgehaald_op,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn format_timestamp(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|ndt| ndt.and_utc())
 }
@@ -2,46 +2,65 @@
 //!
 //! This module provides HTTP client functionality for interacting with
 //! Delft-FEWS (Flood Early Warning System) through its PI-REST API.
+//!
+//! Time series are read from PI-JSON and PI-XML responses alike (see
+//! [`crate::fews_pi_xml`]); the format is detected per response.
 
 use anyhow::Result as AnyhowResult;
-use chrono::{Duration, Utc};
-use reqwest::Client;
-use std::collections::HashSet;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration as StdDuration;
+use std::time::{Duration as StdDuration, Instant};
+use tokio::time::MissedTickBehavior;
 use tracing::{debug, info, warn};
 
 use peilbeheer_core::{
-    FewsConfig, FewsLocation, FewsModuleInstance, FewsParameter, FewsSyncConfig, FewsSyncRequest,
-    FewsSyncResult, FewsTimeSeriesQuery, FewsTimeSeriesResponse,
+    CreateFewsMappingRequest, FewsConfig, FewsDocumentFormat, FewsIdMapping, FewsIdTranslator, FewsLocation,
+    FewsMappingKind, FewsModuleInstance, FewsParameter, FewsSyncConfig, FewsSyncRequest, FewsSyncResult,
+    FewsSyncRun, FewsSyncTrigger, FewsTimeSeriesQuery, FewsTimeSeriesResponse, UpdateFewsMappingRequest,
 };
 
+use crate::db::{Database, is_no_rows};
+use crate::fews_pi_xml;
+use crate::http_resilience::{HttpClient, HttpError};
+use crate::timeseries_service::TimeSeriesService;
+
 /// Fews client error types.
 #[derive(Debug, thiserror::Error)]
 #[allow(dead_code)]
 pub enum FewsError {
     #[error("HTTP request failed: {0}")]
-    HttpError(#[from] reqwest::Error),
+    HttpError(#[from] HttpError),
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
     #[error("Authentication failed")]
     AuthenticationFailed,
 }
 
+/// Fews id mapping errors.
+#[derive(Debug, thiserror::Error)]
+pub enum FewsMappingError {
+    #[error("Mapping not found")]
+    NotFound,
+    #[error("Mapping already exists: {0}")]
+    AlreadyExists(String),
+    #[error("Invalid mapping: {}", .0.join(", "))]
+    Invalid(Vec<String>),
+    #[error(transparent)]
+    Database(#[from] anyhow::Error),
+}
+
 /// Fews PI-REST API client.
 pub struct FewsClient {
     pub config: FewsConfig,
-    http_client: Client,
+    http_client: HttpClient,
 }
 
 impl FewsClient {
     /// Create a new Fews client.
     pub fn new(config: FewsConfig) -> Self {
         let timeout = StdDuration::from_secs(config.timeout_secs);
-        let http_client = Client::builder()
-            .timeout(timeout)
-            .build()
-            .unwrap_or_default();
+        let http_client = HttpClient::shared().with_timeout(timeout);
 
         Self { config, http_client }
     }
@@ -90,8 +109,18 @@ impl FewsClient {
         if let Some(end) = &query.end_time {
             params.push(format!("endTime={}", end.format("%Y-%m-%dT%H:%M:%SZ")));
         }
-        if let Some(qualifier) = &query.qualifier {
-            params.push(format!("qualifier={}", qualifier));
+        if let Some(qualifiers) = &query.qualifier_ids {
+            for q in qualifiers {
+                params.push(format!("qualifierIds={}", q));
+            }
+        }
+        if let Some(ensemble) = &query.ensemble_id {
+            params.push(format!("ensembleId={}", ensemble));
+        }
+        if let Some(sets) = &query.module_instance_sets {
+            for set in sets {
+                params.push(format!("moduleInstanceSets={}", set));
+            }
         }
         if let Some(show) = query.show_enumeration {
             params.push(format!("showEnumeration={}", show));
@@ -102,6 +131,9 @@ impl FewsClient {
         if let Some(headers_only) = query.only_headers {
             params.push(format!("onlyHeaders={}", headers_only));
         }
+        if let Some(format) = self.config.document_format {
+            params.push(format!("documentFormat={}", format.as_str()));
+        }
 
         if !params.is_empty() {
             url = format!("{}?{}", url, params.join("&"));
@@ -110,7 +142,7 @@ impl FewsClient {
         debug!("Fetching Fews time series: {}", url);
 
         let req = self.add_auth_headers(self.http_client.get(&url));
-        let resp = req.send().await?;
+        let resp = self.http_client.send(req).await?;
 
         if !resp.status().is_success() {
             return Err(FewsError::InvalidResponse(format!(
@@ -121,9 +153,13 @@ impl FewsClient {
             .into());
         }
 
-        let json = resp.text().await?;
-        let response: FewsTimeSeriesResponse = serde_json::from_str(&json)
-            .map_err(|e| FewsError::InvalidResponse(format!("Parse error: {}", e)))?;
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = resp.text().await?;
+        let response = parse_time_series_response(content_type.as_deref(), &body)?;
 
         info!("Retrieved {} time series from Fews", response.time_series.len());
 
@@ -140,7 +176,7 @@ impl FewsClient {
         debug!("Fetching Fews locations: {}", url);
 
         let req = self.add_auth_headers(self.http_client.get(&url));
-        let resp = req.send().await?;
+        let resp = self.http_client.send(req).await?;
 
         if !resp.status().is_success() {
             return Err(FewsError::InvalidResponse(format!(
@@ -187,7 +223,7 @@ impl FewsClient {
         debug!("Fetching Fews parameters: {}", url);
 
         let req = self.add_auth_headers(self.http_client.get(&url));
-        let resp = req.send().await?;
+        let resp = self.http_client.send(req).await?;
 
         if !resp.status().is_success() {
             return Err(FewsError::InvalidResponse(format!(
@@ -229,7 +265,7 @@ impl FewsClient {
         debug!("Fetching Fews module instances: {}", url);
 
         let req = self.add_auth_headers(self.http_client.get(&url));
-        let resp = req.send().await?;
+        let resp = self.http_client.send(req).await?;
 
         if !resp.status().is_success() {
             return Err(FewsError::InvalidResponse(format!(
@@ -318,7 +354,7 @@ impl FewsClient {
         debug!("Pinging Fews API: {}", url);
 
         let req = self.add_auth_headers(self.http_client.get(&url));
-        let resp = req.send().await?;
+        let resp = self.http_client.send(req).await?;
 
         let success = resp.status().is_success();
 
@@ -330,49 +366,260 @@ impl FewsClient {
 
         Ok(success)
     }
+
+    /// Write time series to Fews as a PI-XML document.
+    ///
+    /// The series are imported into the configured filter; their headers
+    /// determine the location, parameter and module instance.
+    pub async fn write_timeseries(&self, time_series: &FewsTimeSeriesResponse) -> AnyhowResult<()> {
+        let url = self.build_url("timeseries");
+        let content = fews_pi_xml::write_time_series(time_series);
+
+        debug!("Writing {} time series to Fews: {}", time_series.time_series.len(), url);
+
+        let form = [
+            ("filterId", self.config.filter_id.as_str()),
+            ("piTimeSeriesXmlContent", content.as_str()),
+        ];
+        let req = self.add_auth_headers(self.http_client.post(&url).form(&form));
+        let resp = self.http_client.send(req).await?;
+
+        match resp.status().as_u16() {
+            401 | 403 => return Err(FewsError::AuthenticationFailed.into()),
+            _ if !resp.status().is_success() => {
+                return Err(FewsError::InvalidResponse(format!(
+                    "HTTP {}: {}",
+                    resp.status().as_u16(),
+                    resp.text().await.unwrap_or_default()
+                ))
+                .into());
+            }
+            _ => {}
+        }
+
+        info!("Wrote {} time series to Fews", time_series.time_series.len());
+
+        Ok(())
+    }
+}
+
+/// Columns of a Fews id mapping, in the order of [`mapping_from_row`]
+const MAPPING_COLUMNS: &str =
+    "id, kind, fews_id, local_id, description, CAST(created_at AS VARCHAR), CAST(updated_at AS VARCHAR)";
+
+fn mapping_from_row(row: &duckdb::Row) -> duckdb::Result<FewsIdMapping> {
+    Ok(FewsIdMapping {
+        id: row.get(0)?,
+        kind: FewsMappingKind::from_str(&row.get::<_, String>(1)?).unwrap_or(FewsMappingKind::Parameter),
+        fews_id: row.get(2)?,
+        local_id: row.get(3)?,
+        description: row.get(4)?,
+        created_at: parse_datetime(&row.get::<_, String>(5)?),
+        updated_at: parse_datetime(&row.get::<_, String>(6)?),
+    })
+}
+
+/// Peilgebieden whose scheduled sync is due at `now`.
+///
+/// `next_runs` holds the next run per peilgebied; a config seen for the
+/// first time is scheduled from `now`, so a restart does not trigger it.
+fn due_syncs(
+    configs: &[FewsSyncConfig],
+    next_runs: &mut HashMap<String, chrono::DateTime<Utc>>,
+    now: chrono::DateTime<Utc>,
+) -> Vec<String> {
+    let mut due = Vec::new();
+    for config in configs {
+        let next = next_runs
+            .get(&config.peilgebied_id)
+            .copied()
+            .or_else(|| config.next_run_after(now));
+        let Some(next) = next else {
+            continue;
+        };
+
+        if next <= now {
+            due.push(config.peilgebied_id.clone());
+            match config.next_run_after(now) {
+                Some(following) => next_runs.insert(config.peilgebied_id.clone(), following),
+                None => next_runs.remove(&config.peilgebied_id),
+            };
+        } else {
+            next_runs.insert(config.peilgebied_id.clone(), next);
+        }
+    }
+    due
 }
 
+/// Helper: Format datetime for DuckDB.
+fn format_datetime(dt: chrono::DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+/// Helper: Parse datetime from DuckDB (which drops trailing zeros of the fraction).
+fn parse_datetime(s: &str) -> chrono::DateTime<Utc> {
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|ndt| ndt.and_utc())
+        .unwrap_or_else(|_| Utc::now())
+}
+
+/// Parse a time series response in PI-JSON or PI-XML.
+///
+/// The format follows the content type, or else the first character of the body.
+pub fn parse_time_series_response(
+    content_type: Option<&str>,
+    body: &str,
+) -> Result<FewsTimeSeriesResponse, FewsError> {
+    match FewsDocumentFormat::detect(content_type, body) {
+        FewsDocumentFormat::PiXml => fews_pi_xml::parse_time_series(body),
+        FewsDocumentFormat::PiJson => serde_json::from_str(body)
+            .map_err(|e| FewsError::InvalidResponse(format!("Parse error: {}", e))),
+    }
+}
+
+/// Time between checks for due scheduled syncs
+const SCHEDULER_TICK: StdDuration = StdDuration::from_secs(30);
+/// Default and maximum number of runs returned by a listing
+const DEFAULT_RUNS_LIMIT: u64 = 100;
+const MAX_RUNS_LIMIT: u64 = 1000;
+
 /// Fews sync service for managing periodic data synchronization.
+///
+/// A sync fetches the mapped locations and parameters of a peilgebied over
+/// its lookback period and stores them in the [`TimeSeriesService`] under
+/// the local ids. Syncs run on request or on the config's cron schedule
+/// (or interval); every run is recorded in `fews_sync_runs`. Fews ids are
+/// translated with the config's mappings and the general mappings in
+/// `fews_mappings`, managed under `/api/fews/mappings`.
 pub struct FewsSyncService {
-    #[allow(dead_code)]
     client: Arc<FewsClient>,
+    timeseries: Arc<TimeSeriesService>,
+    db: Arc<Database>,
     config: Vec<FewsSyncConfig>,
 }
 
 #[allow(dead_code)]
 impl FewsSyncService {
     /// Create a new Fews sync service.
-    pub fn new(#[allow(dead_code)]
-    client: Arc<FewsClient>, config: Vec<FewsSyncConfig>) -> Self {
-        Self { client, config }
+    pub fn new(
+        client: Arc<FewsClient>,
+        timeseries: Arc<TimeSeriesService>,
+        db: Arc<Database>,
+        config: Vec<FewsSyncConfig>,
+    ) -> Self {
+        Self { client, timeseries, db, config }
+    }
+
+    /// Start the background scheduler (no-op without automatic syncs).
+    pub fn start_scheduler(self: &Arc<Self>) {
+        let scheduled = self.config.iter()
+            .filter(|c| c.next_run_after(Utc::now()).is_some())
+            .count();
+        if scheduled == 0 {
+            info!("No scheduled Fews syncs");
+            return;
+        }
+
+        let service = Arc::clone(self);
+
+        tokio::spawn(async move {
+            info!("Fews sync scheduler started ({} peilgebieden)", scheduled);
+
+            let mut next_runs = HashMap::new();
+            let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                for peilgebied_id in due_syncs(&service.config, &mut next_runs, Utc::now()) {
+                    if let Err(e) = service.run_sync(&peilgebied_id, FewsSyncTrigger::Scheduled).await {
+                        warn!("Scheduled Fews sync of {} failed: {}", peilgebied_id, e);
+                    }
+                }
+            }
+        });
     }
 
-    /// Run sync for a specific peilgebied.
-    pub async fn sync_peilgebied(
+    /// Sync a peilgebied and record the run.
+    ///
+    /// Returns `None` when the peilgebied has no sync config. Fews and
+    /// storage errors end up in the run; only recording it can fail.
+    pub async fn run_sync(
         &self,
         peilgebied_id: &str,
-        hours_back: i64,
-    ) -> AnyhowResult<Option<FewsSyncResult>> {
-        let config = self.config.iter()
-            .find(|c| c.peilgebied_id == peilgebied_id);
-
-        let config = match config {
-            Some(c) => c,
-            None => {
-                debug!("No Fews sync config for peilgebied: {}", peilgebied_id);
-                return Ok(None);
-            }
+        trigger: FewsSyncTrigger,
+    ) -> AnyhowResult<Option<FewsSyncRun>> {
+        let Some(config) = self.config.iter().find(|c| c.peilgebied_id == peilgebied_id) else {
+            debug!("No Fews sync config for peilgebied: {}", peilgebied_id);
+            return Ok(None);
         };
 
-        let end_time = Utc::now();
-        let start_time = end_time - Duration::hours(hours_back);
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let mut run = FewsSyncRun {
+            id: format!("FSR_{}", uuid::Uuid::new_v4()),
+            peilgebied_id: peilgebied_id.to_string(),
+            trigger,
+            started_at,
+            duration_ms: 0.0,
+            time_series_count: 0,
+            data_points_count: 0,
+            points_written: 0,
+            errors: Vec::new(),
+        };
+
+        let mappings = self.list_mappings(None).unwrap_or_else(|e| {
+            run.errors.push(format!("Failed to load Fews mappings: {}", e));
+            Vec::new()
+        });
+        let translator = FewsIdTranslator::new(&mappings).with_sync_config(config);
+
+        match self.fetch(config, started_at).await {
+            Ok(response) => {
+                run.time_series_count = response.time_series.len();
+                for series in &response.time_series {
+                    run.data_points_count += series.data.len();
+
+                    let ts_id = translator.time_series_id(&series.header);
+                    match self.timeseries.import_from_fews(series, &ts_id).await {
+                        Ok(result) => run.points_written += result.points_written,
+                        Err(e) => run.errors.push(format!("{}: {}", ts_id.key(), e)),
+                    }
+                }
+            }
+            Err(e) => run.errors.push(e.to_string()),
+        }
+
+        run.duration_ms = timer.elapsed().as_secs_f64() * 1000.0;
+        self.record_run(&run)?;
+
+        if run.succeeded() {
+            info!(
+                "Fews sync of {} stored {} of {} points in {:.0} ms",
+                peilgebied_id, run.points_written, run.data_points_count, run.duration_ms
+            );
+        } else {
+            warn!("Fews sync of {} had {} error(s): {}", peilgebied_id, run.errors.len(), run.errors[0]);
+        }
+
+        Ok(Some(run))
+    }
 
-        let request = FewsSyncRequest {
-            start_time,
-            end_time,
+    /// Fetch the mapped series over the config's lookback period.
+    async fn fetch(
+        &self,
+        config: &FewsSyncConfig,
+        end_time: chrono::DateTime<Utc>,
+    ) -> AnyhowResult<FewsTimeSeriesResponse> {
+        let query = FewsTimeSeriesQuery {
             location_ids: Some(config.location_mapping.values().cloned().collect()),
             parameter_ids: Some(config.parameter_mapping.values().cloned().collect()),
-            sync_results: Some(true),
+            start_time: Some(end_time - config.lookback()),
+            end_time: Some(end_time),
+            qualifier_ids: config.qualifier_ids.clone(),
+            ensemble_id: config.ensemble_id.clone(),
+            module_instance_sets: config.module_instance_sets.clone(),
+            ..Default::default()
         };
 
         // Apply filter ID
@@ -380,9 +627,199 @@ impl FewsSyncService {
         client_config.filter_id = config.fews_filter_id.clone();
         let client = FewsClient::new(client_config);
 
-        let result = client.sync(&request).await?;
+        client.get_time_series(&query).await
+    }
+
+    /// Recorded sync runs, most recent first.
+    pub fn list_runs(
+        &self,
+        peilgebied_id: Option<&str>,
+        limit: Option<u64>,
+    ) -> AnyhowResult<Vec<FewsSyncRun>> {
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
+        let where_clause = match peilgebied_id {
+            Some(id) => {
+                params.push(Box::new(id.to_string()));
+                "WHERE peilgebied_id = ?"
+            }
+            None => "",
+        };
+        let limit = limit.unwrap_or(DEFAULT_RUNS_LIMIT).min(MAX_RUNS_LIMIT);
+
+        let sql = format!(
+            "SELECT id, peilgebied_id, trigger_type, CAST(started_at AS VARCHAR), duration_ms,
+                    time_series_count, data_points_count, points_written, errors
+             FROM fews_sync_runs
+             {}
+             ORDER BY started_at DESC
+             LIMIT {}",
+            where_clause, limit
+        );
 
-        Ok(Some(result))
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.query(&sql, &param_refs, |row| {
+            Ok(FewsSyncRun {
+                id: row.get(0)?,
+                peilgebied_id: row.get(1)?,
+                trigger: FewsSyncTrigger::from_str(&row.get::<_, String>(2)?)
+                    .unwrap_or(FewsSyncTrigger::Manual),
+                started_at: parse_datetime(&row.get::<_, String>(3)?),
+                duration_ms: row.get(4)?,
+                time_series_count: row.get::<_, i32>(5)? as usize,
+                data_points_count: row.get::<_, i32>(6)? as usize,
+                points_written: row.get::<_, i32>(7)? as usize,
+                errors: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+            })
+        })
+    }
+
+    /// Fews id mappings, optionally of one kind, ordered by Fews id.
+    pub fn list_mappings(&self, kind: Option<FewsMappingKind>) -> AnyhowResult<Vec<FewsIdMapping>> {
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
+        let where_clause = match kind {
+            Some(kind) => {
+                params.push(Box::new(kind.as_str()));
+                "WHERE kind = ?"
+            }
+            None => "",
+        };
+
+        let sql = format!(
+            "SELECT {} FROM fews_mappings {} ORDER BY kind, fews_id",
+            MAPPING_COLUMNS, where_clause
+        );
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.query(&sql, &param_refs, mapping_from_row)
+    }
+
+    /// Get a Fews id mapping.
+    pub fn get_mapping(&self, id: &str) -> AnyhowResult<Option<FewsIdMapping>> {
+        let result = self.db.query_row(
+            &format!("SELECT {} FROM fews_mappings WHERE id = ?", MAPPING_COLUMNS),
+            &[&id as &dyn duckdb::ToSql],
+            mapping_from_row,
+        );
+
+        match result {
+            Ok(mapping) => Ok(Some(mapping)),
+            Err(e) if is_no_rows(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a Fews id mapping; a Fews id has at most one mapping per kind.
+    pub fn create_mapping(
+        &self,
+        request: CreateFewsMappingRequest,
+    ) -> Result<FewsIdMapping, FewsMappingError> {
+        request.validate().map_err(FewsMappingError::Invalid)?;
+
+        let fews_id = request.fews_id.trim().to_string();
+        let exists = self.list_mappings(Some(request.kind))?
+            .iter()
+            .any(|m| m.fews_id == fews_id);
+        if exists {
+            return Err(FewsMappingError::AlreadyExists(format!(
+                "{} {}",
+                request.kind.as_str(),
+                fews_id
+            )));
+        }
+
+        let now = Utc::now();
+        let mapping = FewsIdMapping {
+            id: format!("FMP_{}", uuid::Uuid::new_v4()),
+            kind: request.kind,
+            fews_id,
+            local_id: request.local_id.trim().to_string(),
+            description: request.description,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.db.execute(
+            "INSERT INTO fews_mappings (id, kind, fews_id, local_id, description, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            &[
+                &mapping.id as &dyn duckdb::ToSql,
+                &mapping.kind.as_str(),
+                &mapping.fews_id,
+                &mapping.local_id,
+                &mapping.description,
+                &format_datetime(mapping.created_at),
+                &format_datetime(mapping.updated_at),
+            ],
+        )?;
+
+        info!(
+            "Created Fews {} mapping {} -> {}",
+            mapping.kind.as_str(), mapping.fews_id, mapping.local_id
+        );
+        Ok(mapping)
+    }
+
+    /// Update the local id or description of a Fews id mapping.
+    pub fn update_mapping(
+        &self,
+        id: &str,
+        request: UpdateFewsMappingRequest,
+    ) -> Result<FewsIdMapping, FewsMappingError> {
+        let mut mapping = self.get_mapping(id)?.ok_or(FewsMappingError::NotFound)?;
+
+        if let Some(local_id) = request.local_id {
+            if local_id.trim().is_empty() {
+                return Err(FewsMappingError::Invalid(vec![
+                    "local_id: must not be empty".to_string(),
+                ]));
+            }
+            mapping.local_id = local_id.trim().to_string();
+        }
+        if let Some(description) = request.description {
+            mapping.description = Some(description);
+        }
+        mapping.updated_at = Utc::now();
+
+        self.db.execute(
+            "UPDATE fews_mappings SET local_id = ?, description = ?, updated_at = ? WHERE id = ?",
+            &[
+                &mapping.local_id as &dyn duckdb::ToSql,
+                &mapping.description,
+                &format_datetime(mapping.updated_at),
+                &mapping.id,
+            ],
+        )?;
+
+        Ok(mapping)
+    }
+
+    /// Delete a Fews id mapping.
+    pub fn delete_mapping(&self, id: &str) -> Result<(), FewsMappingError> {
+        let mapping = self.get_mapping(id)?.ok_or(FewsMappingError::NotFound)?;
+
+        self.db.execute("DELETE FROM fews_mappings WHERE id = ?", &[&id as &dyn duckdb::ToSql])?;
+
+        info!("Deleted Fews {} mapping of {}", mapping.kind.as_str(), mapping.fews_id);
+        Ok(())
+    }
+
+    fn record_run(&self, run: &FewsSyncRun) -> AnyhowResult<()> {
+        self.db.execute(
+            "INSERT INTO fews_sync_runs
+                (id, peilgebied_id, trigger_type, started_at, duration_ms,
+                 time_series_count, data_points_count, points_written, errors)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            &[
+                &run.id as &dyn duckdb::ToSql,
+                &run.peilgebied_id,
+                &run.trigger.as_str(),
+                &format_datetime(run.started_at),
+                &run.duration_ms,
+                &(run.time_series_count as i32),
+                &(run.data_points_count as i32),
+                &(run.points_written as i32),
+                &serde_json::to_string(&run.errors).unwrap_or_else(|_| "[]".to_string()),
+            ],
+        )
     }
 
     /// Get all sync configurations.
@@ -420,4 +857,57 @@ mod tests {
         assert_eq!(config.filter_id, "WatershedFilter");
         assert_eq!(config.timeout_secs, 30);
     }
+
+    #[test]
+    fn test_due_syncs() {
+        let config = |id: &str, cron: Option<&str>, auto_sync: bool| FewsSyncConfig {
+            peilgebied_id: id.to_string(),
+            fews_filter_id: "Rijnland".to_string(),
+            location_mapping: HashMap::new(),
+            parameter_mapping: HashMap::new(),
+            sync_interval_hours: None,
+            auto_sync,
+            cron: cron.map(str::to_string),
+            lookback_hours: None,
+            qualifier_ids: None,
+            ensemble_id: None,
+            module_instance_sets: None,
+        };
+        let configs = vec![
+            config("PG_001", Some("0 * * * *"), true),
+            config("PG_002", Some("0 * * * *"), false),
+            config("PG_003", None, true),
+        ];
+        let at = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let mut next_runs = HashMap::new();
+
+        assert!(due_syncs(&configs, &mut next_runs, at("2025-03-10T08:20:00Z")).is_empty());
+        assert_eq!(next_runs.len(), 1);
+        assert!(due_syncs(&configs, &mut next_runs, at("2025-03-10T08:59:30Z")).is_empty());
+        assert_eq!(due_syncs(&configs, &mut next_runs, at("2025-03-10T09:00:10Z")), vec!["PG_001"]);
+        assert_eq!(next_runs["PG_001"], at("2025-03-10T10:00:00Z"));
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let at = parse_datetime("2025-03-10 08:00:01.12345");
+        assert_eq!(at.timestamp_subsec_micros(), 123_450);
+        assert_eq!(parse_datetime("2025-03-10 08:00:01").to_rfc3339(), "2025-03-10T08:00:01+00:00");
+    }
+
+    #[test]
+    fn test_parse_time_series_response() {
+        let xml = r#"<TimeSeries xmlns="http://www.wldelft.nl/fews/PI" version="1.25">
+            <series><header><locationId>GEM_001</locationId><parameterId>Q</parameterId></header>
+            <event date="2025-03-10" time="00:00:00" value="1.5"/></series></TimeSeries>"#;
+        let response = parse_time_series_response(Some("application/xml"), xml).unwrap();
+        assert_eq!(response.time_series[0].data[0].value, 1.5);
+        let response = parse_time_series_response(None, xml).unwrap();
+        assert_eq!(response.time_series.len(), 1);
+
+        let json = r#"{"version": "1.25", "time_series": [], "only_headers": null}"#;
+        let response = parse_time_series_response(Some("application/json"), json).unwrap();
+        assert_eq!(response.version, "1.25");
+        assert!(parse_time_series_response(Some("application/json"), xml).is_err());
+    }
 }
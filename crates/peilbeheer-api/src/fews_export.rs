@@ -0,0 +1,363 @@
+//! Simulation and optimization results as Delft-FEWS time series.
+//!
+//! Results are converted to Fews series and written as a PI-XML
+//! `TimeSeries` document (see [`crate::fews_pi_xml`]) that Delft-FEWS
+//! imports directly:
+//!
+//! - a network simulation gives per peilgebied the water level, the pumped
+//!   outflow and the inlet, and per verbinding the discharge, under the
+//!   peilgebied and verbinding ids as locations;
+//! - an optimization gives per hour the optimal pump fraction, the water
+//!   level at the end of the hour and the energy use of the pumps.
+//!
+//! Simulation times are minutes after the export start; the time step of
+//! the series is the largest whole number of seconds all times fall on,
+//! and the series is non-equidistant when the times are not whole seconds.
+
+use chrono::{DateTime, Duration, Utc};
+
+use peilbeheer_core::energie::OptimalisatieResultaat;
+use peilbeheer_core::{
+    FewsTimeSeries, FewsTimeSeriesHeader, FewsTimeSeriesPoint, FewsTimeSeriesResponse,
+    FewsTimeStep, FewsValueType,
+};
+use peilbeheer_simulatie::{NetwerkSimulatieResultaat, PeilgebiedStatus};
+
+use crate::fews_pi_xml;
+
+/// Simulated water level of a peilgebied (m NAP)
+pub const PARAMETER_WATERSTAND: &str = "H.simulatie";
+/// Pumped outflow of a peilgebied (m³/s)
+pub const PARAMETER_UITSTROOM: &str = "Q.uitstroom";
+/// Inlet into a peilgebied from outside the network (m³/s)
+pub const PARAMETER_INLAAT: &str = "Q.inlaat";
+/// Discharge through a verbinding (m³/s)
+pub const PARAMETER_DEBIET: &str = "Q.simulatie";
+/// Optimal pump fraction per hour (-)
+pub const PARAMETER_POMPFRACTIE: &str = "Pomp.fractie";
+/// Water level at the end of each optimized hour (m NAP)
+pub const PARAMETER_WATERSTAND_OPTIMAAL: &str = "H.optimalisatie";
+/// Energy use of the pumps per optimized hour (kWh)
+pub const PARAMETER_ENERGIE: &str = "E.pomp";
+
+const SECONDS_PER_HOUR: i64 = 3600;
+
+/// Value of a peilgebied status exported as a series.
+type StatusValue = fn(&PeilgebiedStatus) -> f64;
+
+/// Parameter, units and value of each series of a peilgebied.
+const PEILGEBIED_PARAMETERS: [(&str, &str, StatusValue); 3] = [
+    (PARAMETER_WATERSTAND, "m NAP", |s| s.waterstand),
+    (PARAMETER_UITSTROOM, "m3/s", |s| s.uitstroom_debiet),
+    (PARAMETER_INLAAT, "m3/s", |s| s.inlaat_debiet),
+];
+
+/// Where and when exported series are placed in Fews.
+#[derive(Debug, Clone)]
+pub struct PiExport {
+    pub module_instance_id: String,
+    /// Time of minute 0 of a simulation or hour 0 of an optimization
+    pub start: DateTime<Utc>,
+    /// Time the results were computed (forecast time in Fews)
+    pub forecast_date: Option<DateTime<Utc>>,
+    /// Qualifier of every series
+    pub qualifier: Option<String>,
+}
+
+impl PiExport {
+    /// Series of one location and parameter.
+    pub fn series(
+        &self,
+        location_id: &str,
+        parameter_id: &str,
+        units: &str,
+        value_type: FewsValueType,
+        step_seconds: Option<i64>,
+        points: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    ) -> FewsTimeSeries {
+        let data: Vec<FewsTimeSeriesPoint> = points
+            .into_iter()
+            .map(|(at, value)| FewsTimeSeriesPoint {
+                date: format_date(at),
+                value,
+                flag: None,
+            })
+            .collect();
+
+        FewsTimeSeries {
+            header: FewsTimeSeriesHeader {
+                location_id: location_id.to_string(),
+                parameter_id: parameter_id.to_string(),
+                module_instance_id: self.module_instance_id.clone(),
+                time_step: step_seconds
+                    .map_or(FewsTimeStep::NonEquidistant, FewsTimeStep::from_seconds),
+                time_step_seconds: step_seconds,
+                start_date: data.first().map(|p| p.date.clone()).unwrap_or_default(),
+                end_date: data.last().map(|p| p.date.clone()).unwrap_or_default(),
+                units: units.to_string(),
+                type_description: String::new(),
+                value_type,
+                station_name: String::new(),
+                parameter_description: String::new(),
+                module_description: String::new(),
+                geo_delta: None,
+                geo_datum: None,
+                lat: None,
+                lon: None,
+                x: None,
+                y: None,
+                qualifier: self.qualifier.clone(),
+                miss_val: None,
+                ensemble_id: None,
+                ensemble_member: None,
+                forecast_date: self.forecast_date.map(format_date),
+            },
+            data,
+            misses: Vec::new(),
+        }
+    }
+
+    /// Series of a network simulation, peilgebieden and verbindingen in
+    /// order of id.
+    pub fn simulation_series(&self, resultaat: &NetwerkSimulatieResultaat) -> Vec<FewsTimeSeries> {
+        let stappen = &resultaat.tijdstappen;
+        let step_seconds = step_seconds(stappen.iter().map(|s| s.tijd));
+        let at =
+            |minuten: f64| self.start + Duration::milliseconds((minuten * 60_000.0).round() as i64);
+
+        let mut peilgebied_ids: Vec<&String> =
+            stappen.iter().flat_map(|s| s.statussen.keys()).collect();
+        peilgebied_ids.sort();
+        peilgebied_ids.dedup();
+        let mut verbinding_ids: Vec<&String> = stappen
+            .iter()
+            .flat_map(|s| s.stromen.iter().map(|stroom| &stroom.verbinding_id))
+            .collect();
+        verbinding_ids.sort();
+        verbinding_ids.dedup();
+
+        let mut series = Vec::new();
+        for id in peilgebied_ids {
+            for (parameter_id, units, value) in PEILGEBIED_PARAMETERS {
+                let points = stappen.iter().filter_map(|s| {
+                    s.statussen
+                        .get(id)
+                        .map(|status| (at(s.tijd), value(status)))
+                });
+                series.push(self.series(
+                    id,
+                    parameter_id,
+                    units,
+                    FewsValueType::Instantaneous,
+                    step_seconds,
+                    points,
+                ));
+            }
+        }
+        for id in verbinding_ids {
+            let points = stappen.iter().filter_map(|s| {
+                s.stromen
+                    .iter()
+                    .find(|stroom| &stroom.verbinding_id == id)
+                    .map(|stroom| (at(s.tijd), stroom.debiet))
+            });
+            series.push(self.series(
+                id,
+                PARAMETER_DEBIET,
+                "m3/s",
+                FewsValueType::Instantaneous,
+                step_seconds,
+                points,
+            ));
+        }
+        series
+    }
+
+    /// Optimal pump fraction per hour; each value holds for the hour
+    /// starting at its time.
+    pub fn pump_fraction_series(
+        &self,
+        resultaat: &OptimalisatieResultaat,
+        location_id: &str,
+        parameter_id: &str,
+    ) -> FewsTimeSeries {
+        self.series(
+            location_id,
+            parameter_id,
+            "-",
+            FewsValueType::Instantaneous,
+            Some(SECONDS_PER_HOUR),
+            resultaat
+                .uren
+                .iter()
+                .map(|uur| (self.hour(uur.uur, 0), uur.pomp_fractie_optimaal)),
+        )
+    }
+
+    /// Series of an optimization at one location.
+    pub fn optimization_series(
+        &self,
+        resultaat: &OptimalisatieResultaat,
+        location_id: &str,
+    ) -> Vec<FewsTimeSeries> {
+        vec![
+            self.pump_fraction_series(resultaat, location_id, PARAMETER_POMPFRACTIE),
+            self.series(
+                location_id,
+                PARAMETER_WATERSTAND_OPTIMAAL,
+                "m NAP",
+                FewsValueType::Instantaneous,
+                Some(SECONDS_PER_HOUR),
+                resultaat
+                    .uren
+                    .iter()
+                    .map(|uur| (self.hour(uur.uur, 1), uur.waterstand_eind_optimaal)),
+            ),
+            // Accumulated over the hour ending at the time of the value
+            self.series(
+                location_id,
+                PARAMETER_ENERGIE,
+                "kWh",
+                FewsValueType::Accumulative,
+                Some(SECONDS_PER_HOUR),
+                resultaat
+                    .uren
+                    .iter()
+                    .map(|uur| (self.hour(uur.uur, 1), uur.energie_optimaal_kwh)),
+            ),
+        ]
+    }
+
+    fn hour(&self, uur: u8, offset: i64) -> DateTime<Utc> {
+        self.start + Duration::hours(uur as i64 + offset)
+    }
+}
+
+/// PI-XML document of the series.
+pub fn pi_xml(time_series: Vec<FewsTimeSeries>) -> String {
+    fews_pi_xml::write_time_series(&FewsTimeSeriesResponse {
+        version: String::new(),
+        time_series,
+        only_headers: None,
+    })
+}
+
+/// Largest step in seconds that all times (minutes) fall on: the greatest
+/// common divisor of the times. None when a time is not a whole second or
+/// there is no step between the times.
+fn step_seconds(minuten: impl Iterator<Item = f64>) -> Option<i64> {
+    let mut step = 0;
+    for m in minuten {
+        let seconds = m * 60.0;
+        if (seconds - seconds.round()).abs() > 1e-6 {
+            return None;
+        }
+        step = gcd(step, (seconds.round() as i64).abs());
+    }
+    (step > 0).then_some(step)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn format_date(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use peilbeheer_simulatie::{NetwerkTijdstap, StroomRichting, VerbindingStroom};
+
+    use super::*;
+
+    fn export() -> PiExport {
+        PiExport {
+            module_instance_id: "Peilbeheer".to_string(),
+            start: "2025-03-10T00:00:00Z".parse().unwrap(),
+            forecast_date: Some("2025-03-09T23:00:00Z".parse().unwrap()),
+            qualifier: None,
+        }
+    }
+
+    fn stap(tijd: f64, waterstand: f64) -> NetwerkTijdstap {
+        let status = |id: &str| PeilgebiedStatus {
+            id: id.to_string(),
+            waterstand,
+            inkomend_debiet: 0.0,
+            uitgaand_debiet: 0.1,
+            uitstroom_debiet: 0.2,
+            inlaat_debiet: 0.0,
+            regen_intensiteit: 0.0,
+            pomp_actief: true,
+        };
+        NetwerkTijdstap {
+            tijd,
+            duur: 15.0,
+            statussen: HashMap::from([
+                ("zuid".to_string(), status("zuid")),
+                ("noord".to_string(), status("noord")),
+            ]),
+            stromen: vec![VerbindingStroom {
+                verbinding_id: "stuw".to_string(),
+                debiet: 0.1,
+                richting: StroomRichting::Naar,
+                benutting: 0.5,
+                actief: true,
+            }],
+            boezembelasting: HashMap::new(),
+            kruinhoogten: HashMap::new(),
+            chloride: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_simulation_series() {
+        let resultaat = NetwerkSimulatieResultaat {
+            tijdstappen: vec![stap(0.0, -0.60), stap(15.0, -0.58), stap(30.0, -0.57)],
+            totale_kosten: None,
+        };
+        let series = export().simulation_series(&resultaat);
+
+        // Three per peilgebied, one per verbinding
+        assert_eq!(series.len(), 7);
+        let noord = &series[0].header;
+        assert_eq!(noord.location_id, "noord");
+        assert_eq!(noord.parameter_id, PARAMETER_WATERSTAND);
+        assert_eq!(noord.time_step, FewsTimeStep::Minute);
+        assert_eq!(noord.time_step_seconds, Some(900));
+        assert_eq!(noord.start_date, "2025-03-10T00:00:00Z");
+        assert_eq!(noord.end_date, "2025-03-10T00:30:00Z");
+        assert_eq!(noord.forecast_date.as_deref(), Some("2025-03-09T23:00:00Z"));
+        assert_eq!(series[0].data[1].value, -0.58);
+        assert_eq!(series[1].header.parameter_id, PARAMETER_UITSTROOM);
+        assert_eq!(series[3].header.location_id, "zuid");
+        assert_eq!(series[6].header.location_id, "stuw");
+        assert_eq!(series[6].header.parameter_id, PARAMETER_DEBIET);
+
+        // The document reads back as the same series
+        let xml = pi_xml(series.clone());
+        assert!(xml.contains(r#"<timeStep unit="second" multiplier="900"/>"#));
+        let document = fews_pi_xml::parse_time_series(&xml).unwrap();
+        assert_eq!(document.time_series.len(), 7);
+        assert_eq!(document.time_series[0].header.location_id, "noord");
+        let read_back = &document.time_series[0].data;
+        assert_eq!(read_back.len(), 3);
+        assert_eq!(read_back[2].date, series[0].data[2].date);
+        assert_eq!(read_back[2].value, -0.57);
+    }
+
+    #[test]
+    fn test_step_seconds() {
+        assert_eq!(step_seconds([0.0, 60.0, 120.0].into_iter()), Some(3600));
+        assert_eq!(step_seconds([60.0, 120.0, 180.0].into_iter()), Some(3600));
+        assert_eq!(step_seconds([0.0, 60.0, 75.0].into_iter()), Some(900));
+        assert_eq!(step_seconds([7.0, 14.0, 18.0].into_iter()), Some(60));
+        assert_eq!(step_seconds([0.0, 0.5].into_iter()), Some(30));
+        // Not on whole seconds, or a single time without a step
+        assert_eq!(step_seconds([0.0, 0.001].into_iter()), None);
+        assert_eq!(step_seconds([0.0].into_iter()), None);
+    }
+}
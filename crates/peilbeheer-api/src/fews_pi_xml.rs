@@ -0,0 +1,553 @@
+//! Delft-FEWS PI-XML time series documents.
+//!
+//! PI-REST serves time series as PI-JSON or PI-XML (`documentFormat`). This
+//! module reads and writes the `TimeSeries` document of the PI schema and
+//! maps it onto the same [`FewsTimeSeriesResponse`] the JSON format uses:
+//!
+//! ```xml
+//! <TimeSeries xmlns="http://www.wldelft.nl/fews/PI" version="1.25">
+//!   <timeZone>0.0</timeZone>
+//!   <series>
+//!     <header>
+//!       <type>instantaneous</type>
+//!       <moduleInstanceId>ImportTelemetrie</moduleInstanceId>
+//!       <locationId>GEM_001</locationId>
+//!       <parameterId>H.meting</parameterId>
+//!       <timeStep unit="second" multiplier="900"/>
+//!       <startDate date="2025-03-10" time="00:00:00"/>
+//!       <endDate date="2025-03-10" time="01:00:00"/>
+//!       <missVal>-999.0</missVal>
+//!       <units>m NAP</units>
+//!     </header>
+//!     <event date="2025-03-10" time="00:00:00" value="-0.61" flag="0"/>
+//!   </series>
+//! </TimeSeries>
+//! ```
+//!
+//! Event times are local to the document's `timeZone` (hours from UTC) and
+//! are converted to UTC RFC 3339 timestamps. Events holding the missing
+//! value are left out of the data; their epoch milliseconds go into
+//! `misses`. Written documents are always in UTC.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::writer::ElementWriter;
+use quick_xml::{Reader, Writer};
+use std::collections::HashMap;
+use std::io;
+
+use peilbeheer_core::{
+    FewsTimeSeries, FewsTimeSeriesHeader, FewsTimeSeriesPoint, FewsTimeSeriesResponse,
+    FewsTimeStep, FewsValueType,
+};
+
+use crate::fews_client::FewsError;
+
+/// Namespace of PI documents
+const PI_NAMESPACE: &str = "http://www.wldelft.nl/fews/PI";
+/// PI schema version of written documents
+const PI_VERSION: &str = "1.25";
+/// Missing value of written documents
+const MISSING_VALUE: f64 = -999.0;
+
+/// Read a PI-XML `TimeSeries` document.
+pub fn parse_time_series(xml: &str) -> Result<FewsTimeSeriesResponse, FewsError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut document = DocumentReader::default();
+
+    loop {
+        match reader.read_event().map_err(parse_error)? {
+            Event::Start(e) => {
+                document.open(&e)?;
+                document.path.push(local_name(&e));
+            }
+            Event::Empty(e) => document.open(&e)?,
+            Event::Text(text) => document.text(&text.unescape().map_err(parse_error)?),
+            Event::End(_) => document.close()?,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !document.found_root {
+        return Err(FewsError::InvalidResponse(
+            "PI-XML document without TimeSeries element".to_string(),
+        ));
+    }
+
+    Ok(FewsTimeSeriesResponse {
+        version: document.version,
+        time_series: document.series,
+        only_headers: None,
+    })
+}
+
+/// Write a PI-XML `TimeSeries` document.
+///
+/// Missing values are written as `-999.0`, unless a series has its own.
+pub fn write_time_series(response: &FewsTimeSeriesResponse) -> String {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    let version = if response.version.is_empty() {
+        PI_VERSION
+    } else {
+        response.version.as_str()
+    };
+
+    // Writing to a Vec cannot fail
+    let _ = writer
+        .create_element("TimeSeries")
+        .with_attribute(("xmlns", PI_NAMESPACE))
+        .with_attribute(("version", version))
+        .write_inner_content(|w| {
+            w.create_element("timeZone")
+                .write_text_content(BytesText::new("0.0"))?;
+            for series in &response.time_series {
+                write_series(w, series)?;
+            }
+            Ok(())
+        });
+
+    let body = String::from_utf8(writer.into_inner()).unwrap_or_default();
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n", body)
+}
+
+fn write_series(w: &mut Writer<Vec<u8>>, series: &FewsTimeSeries) -> io::Result<()> {
+    let header = &series.header;
+    let miss_val = header.miss_val.unwrap_or(MISSING_VALUE);
+
+    w.create_element("series").write_inner_content(|w| {
+        w.create_element("header").write_inner_content(|w| {
+            let value_type = match header.value_type {
+                FewsValueType::Instantaneous => "instantaneous",
+                FewsValueType::Accumulative => "accumulative",
+            };
+            text_element(w, "type", value_type)?;
+            text_element(w, "moduleInstanceId", &header.module_instance_id)?;
+            text_element(w, "locationId", &header.location_id)?;
+            text_element(w, "parameterId", &header.parameter_id)?;
+            if let Some(qualifier) = &header.qualifier {
+                text_element(w, "qualifierId", qualifier)?;
+            }
+            if let Some(ensemble) = &header.ensemble_id {
+                text_element(w, "ensembleId", ensemble)?;
+            }
+            if let Some(member) = &header.ensemble_member {
+                let name = if member.parse::<u32>().is_ok() {
+                    "ensembleMemberIndex"
+                } else {
+                    "ensembleMemberId"
+                };
+                text_element(w, name, member)?;
+            }
+
+            let (unit, multiplier) = time_step_attributes(header);
+            let mut time_step = w.create_element("timeStep").with_attribute(("unit", unit));
+            if let Some(multiplier) = multiplier {
+                time_step =
+                    time_step.with_attribute(("multiplier", multiplier.to_string().as_str()));
+            }
+            time_step.write_empty()?;
+
+            let dates = series.data.iter().filter_map(|p| parse_date(&p.date));
+            let start = parse_date(&header.start_date).or_else(|| dates.clone().min());
+            let end = parse_date(&header.end_date).or_else(|| dates.max());
+            let forecast = header.forecast_date.as_deref().and_then(parse_date);
+            for (name, at) in [("startDate", start), ("endDate", end), ("forecastDate", forecast)] {
+                if let Some(at) = at {
+                    date_element(w, name, at).write_empty()?;
+                }
+            }
+
+            text_element(w, "missVal", &miss_val.to_string())?;
+            if !header.station_name.is_empty() {
+                text_element(w, "stationName", &header.station_name)?;
+            }
+            let coordinates = [("lat", header.lat), ("lon", header.lon), ("x", header.x), ("y", header.y)];
+            for (name, value) in coordinates {
+                if let Some(value) = value {
+                    text_element(w, name, &value.to_string())?;
+                }
+            }
+            text_element(w, "units", &header.units)
+        })?;
+
+        for point in &series.data {
+            let Some(at) = parse_date(&point.date) else {
+                continue;
+            };
+            let value = if point.value.is_nan() { miss_val } else { point.value };
+            let mut event = date_element(w, "event", at)
+                .with_attribute(("value", value.to_string().as_str()));
+            if let Some(flag) = point.flag {
+                event = event.with_attribute(("flag", flag.to_string().as_str()));
+            }
+            event.write_empty()?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn text_element(w: &mut Writer<Vec<u8>>, name: &str, text: &str) -> io::Result<()> {
+    w.create_element(name).write_text_content(BytesText::new(text))?;
+    Ok(())
+}
+
+fn date_element<'a>(
+    w: &'a mut Writer<Vec<u8>>,
+    name: &'a str,
+    at: DateTime<Utc>,
+) -> ElementWriter<'a, Vec<u8>> {
+    w.create_element(name)
+        .with_attribute(("date", at.format("%Y-%m-%d").to_string().as_str()))
+        .with_attribute(("time", at.format("%H:%M:%S").to_string().as_str()))
+}
+
+/// State while reading a document.
+#[derive(Default)]
+struct DocumentReader {
+    found_root: bool,
+    version: String,
+    /// Offset of local times from UTC (hours)
+    time_zone: f64,
+    series: Vec<FewsTimeSeries>,
+    current: Option<SeriesReader>,
+    /// Names of the open elements
+    path: Vec<String>,
+}
+
+impl DocumentReader {
+    fn parent(&self) -> Option<&str> {
+        self.path.last().map(String::as_str)
+    }
+
+    /// Handle a start tag or empty element.
+    fn open(&mut self, e: &BytesStart) -> Result<(), FewsError> {
+        let name = local_name(e);
+        let attributes = attributes(e)?;
+
+        match (self.parent(), name.as_str()) {
+            (None, "TimeSeries") => {
+                self.found_root = true;
+                self.version = attributes.get("version").cloned().unwrap_or_default();
+            }
+            (Some("TimeSeries"), "series") => self.current = Some(SeriesReader::default()),
+            (Some("header"), "timeStep") => {
+                if let Some(series) = self.current.as_mut()
+                    && let Some((step, seconds)) = time_step(&attributes)
+                {
+                    series.time_step = Some(step);
+                    series.time_step_seconds = seconds;
+                }
+            }
+            (Some("header"), "startDate" | "endDate" | "forecastDate") => {
+                let at = date_time(&attributes, self.time_zone)
+                    .ok_or_else(|| FewsError::InvalidResponse(format!("PI-XML invalid {}", name)))?;
+                if let Some(series) = self.current.as_mut() {
+                    match name.as_str() {
+                        "startDate" => series.start_date = Some(at),
+                        "endDate" => series.end_date = Some(at),
+                        _ => series.forecast_date = Some(at),
+                    }
+                }
+            }
+            (Some("series"), "event") => {
+                let at = date_time(&attributes, self.time_zone).ok_or_else(|| {
+                    FewsError::InvalidResponse("PI-XML event without valid date".to_string())
+                })?;
+                let value = attributes
+                    .get("value")
+                    .and_then(|v| v.trim().parse::<f64>().ok())
+                    .unwrap_or(f64::NAN);
+                let flag = attributes.get("flag").and_then(|f| f.trim().parse().ok());
+                if let Some(series) = self.current.as_mut() {
+                    series.events.push((at, value, flag));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handle the text of the innermost open element.
+    fn text(&mut self, text: &str) {
+        let [.., parent, name] = self.path.as_slice() else {
+            return;
+        };
+        match (parent.as_str(), name.as_str()) {
+            ("TimeSeries", "timeZone") => self.time_zone = text.trim().parse().unwrap_or(0.0),
+            ("header", field) => {
+                if let Some(series) = self.current.as_mut() {
+                    // Repeated fields (qualifierId) keep their first value
+                    series
+                        .fields
+                        .entry(field.to_string())
+                        .or_insert_with(|| text.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle an end tag.
+    fn close(&mut self) -> Result<(), FewsError> {
+        if self.path.pop().as_deref() == Some("series")
+            && let Some(series) = self.current.take()
+        {
+            self.series.push(series.build()?);
+        }
+        Ok(())
+    }
+}
+
+/// Series being read: its header fields and events.
+#[derive(Default)]
+struct SeriesReader {
+    fields: HashMap<String, String>,
+    time_step: Option<FewsTimeStep>,
+    time_step_seconds: Option<i64>,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+    forecast_date: Option<DateTime<Utc>>,
+    events: Vec<(DateTime<Utc>, f64, Option<i64>)>,
+}
+
+impl SeriesReader {
+    fn field(&self, name: &str) -> Option<String> {
+        self.fields.get(name).filter(|v| !v.is_empty()).cloned()
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        self.fields.get(name).and_then(|v| v.parse().ok())
+    }
+
+    fn build(self) -> Result<FewsTimeSeries, FewsError> {
+        let required = |name: &str| {
+            self.field(name)
+                .ok_or_else(|| FewsError::InvalidResponse(format!("PI-XML series without {}", name)))
+        };
+        let miss_val = self.number("missVal");
+
+        let mut data = Vec::with_capacity(self.events.len());
+        let mut misses = Vec::new();
+        for &(at, value, flag) in &self.events {
+            if value.is_nan() || miss_val.is_some_and(|miss| value == miss) {
+                misses.push(at.timestamp_millis());
+            } else {
+                data.push(FewsTimeSeriesPoint {
+                    date: format_date(at),
+                    value,
+                    flag,
+                });
+            }
+        }
+
+        let value_type = match self.field("type").as_deref() {
+            Some("accumulative") => FewsValueType::Accumulative,
+            _ => FewsValueType::Instantaneous,
+        };
+        let start_date = self.start_date.or_else(|| self.events.first().map(|e| e.0));
+        let end_date = self.end_date.or_else(|| self.events.last().map(|e| e.0));
+
+        let header = FewsTimeSeriesHeader {
+            location_id: required("locationId")?,
+            parameter_id: required("parameterId")?,
+            module_instance_id: self.field("moduleInstanceId").unwrap_or_default(),
+            time_step: self.time_step.unwrap_or(FewsTimeStep::Second),
+            time_step_seconds: self.time_step_seconds,
+            start_date: start_date.map(format_date).unwrap_or_default(),
+            end_date: end_date.map(format_date).unwrap_or_default(),
+            units: self.field("units").unwrap_or_default(),
+            type_description: self.field("type").unwrap_or_default(),
+            value_type,
+            station_name: self.field("stationName").unwrap_or_default(),
+            parameter_description: self.field("parameterName").unwrap_or_default(),
+            module_description: String::new(),
+            geo_delta: None,
+            geo_datum: None,
+            lat: self.number("lat"),
+            lon: self.number("lon"),
+            x: self.number("x"),
+            y: self.number("y"),
+            qualifier: self.field("qualifierId"),
+            miss_val,
+            ensemble_id: self.field("ensembleId"),
+            ensemble_member: self
+                .field("ensembleMemberIndex")
+                .or_else(|| self.field("ensembleMemberId")),
+            forecast_date: self.forecast_date.map(format_date),
+        };
+
+        Ok(FewsTimeSeries {
+            header,
+            data,
+            misses,
+        })
+    }
+}
+
+fn parse_error(e: impl std::fmt::Display) -> FewsError {
+    FewsError::InvalidResponse(format!("PI-XML parse error: {}", e))
+}
+
+/// Element name without namespace prefix.
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+fn attributes(e: &BytesStart) -> Result<HashMap<String, String>, FewsError> {
+    e.attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(parse_error)?;
+            let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+            let value = attribute.unescape_value().map_err(parse_error)?.into_owned();
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// UTC time of `date` and `time` attributes in a time zone `offset_hours` from UTC.
+fn date_time(attributes: &HashMap<String, String>, offset_hours: f64) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(attributes.get("date")?.trim(), "%Y-%m-%d").ok()?;
+    let time = match attributes.get("time") {
+        Some(time) => NaiveTime::parse_from_str(time.trim(), "%H:%M:%S%.f").ok()?,
+        None => NaiveTime::MIN,
+    };
+    let offset = Duration::seconds((offset_hours * 3600.0).round() as i64);
+    Some(date.and_time(time).and_utc() - offset)
+}
+
+/// Time step of a `timeStep` element, with its length in seconds when the
+/// step is a fixed number of seconds.
+fn time_step(attributes: &HashMap<String, String>) -> Option<(FewsTimeStep, Option<i64>)> {
+    let multiplier: i64 = attributes
+        .get("multiplier")
+        .and_then(|m| m.trim().parse().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(1);
+
+    let unit_seconds = match attributes.get("unit")?.as_str() {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "nonequidistant" => return Some((FewsTimeStep::NonEquidistant, None)),
+        "month" => return Some((FewsTimeStep::Month, None)),
+        "year" if multiplier % 10 == 0 => return Some((FewsTimeStep::Decade, None)),
+        "year" => return Some((FewsTimeStep::Year, None)),
+        _ => return Some((FewsTimeStep::Second, None)),
+    };
+    let seconds = unit_seconds * multiplier;
+    Some((FewsTimeStep::from_seconds(seconds), Some(seconds)))
+}
+
+/// `unit` and `multiplier` of the time step of a series.
+///
+/// Steps of a month or longer are not a fixed number of seconds and are
+/// written as non-equidistant.
+fn time_step_attributes(header: &FewsTimeSeriesHeader) -> (&'static str, Option<i64>) {
+    match header.time_step_seconds.or(header.time_step.seconds()) {
+        Some(seconds) => ("second", Some(seconds)),
+        None => ("nonequidistant", None),
+    }
+}
+
+fn format_date(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TimeSeries xmlns="http://www.wldelft.nl/fews/PI" version="1.23">
+  <timeZone>1.0</timeZone>
+  <series>
+    <header>
+      <type>instantaneous</type>
+      <moduleInstanceId>ImportTelemetrie</moduleInstanceId>
+      <locationId>GEM_001</locationId>
+      <parameterId>H.meting</parameterId>
+      <qualifierId>validated</qualifierId>
+      <timeStep unit="second" multiplier="900"/>
+      <startDate date="2025-03-10" time="01:00:00"/>
+      <endDate date="2025-03-10" time="01:30:00"/>
+      <missVal>-999.0</missVal>
+      <stationName>Gemaal De Blauwe &amp; Brug</stationName>
+      <units>m NAP</units>
+    </header>
+    <event date="2025-03-10" time="01:00:00" value="-0.61" flag="0"/>
+    <event date="2025-03-10" time="01:15:00" value="-999.0" flag="8"/>
+    <event date="2025-03-10" time="01:30:00" value="-0.59" flag="0"/>
+  </series>
+  <series>
+    <header>
+      <type>accumulative</type>
+      <locationId>KNMI_344</locationId>
+      <parameterId>P.meting</parameterId>
+      <ensembleId>ECMWF</ensembleId>
+      <ensembleMemberIndex>3</ensembleMemberIndex>
+      <timeStep unit="nonequidistant"/>
+      <forecastDate date="2025-03-10" time="06:00:00"/>
+    </header>
+  </series>
+</TimeSeries>"#;
+
+    #[test]
+    fn test_parse_time_series() {
+        let response = parse_time_series(DOCUMENT).unwrap();
+        assert_eq!(response.version, "1.23");
+        assert_eq!(response.time_series.len(), 2);
+
+        let series = &response.time_series[0];
+        assert_eq!(series.header.location_id, "GEM_001");
+        assert_eq!(series.header.time_step, FewsTimeStep::Minute);
+        assert_eq!(series.header.time_step_seconds, Some(900));
+        assert_eq!(series.header.start_date, "2025-03-10T00:00:00Z");
+        assert_eq!(series.header.qualifier.as_deref(), Some("validated"));
+        assert_eq!(series.header.station_name, "Gemaal De Blauwe & Brug");
+        assert_eq!(series.header.miss_val, Some(-999.0));
+        assert_eq!(series.data.len(), 2);
+        assert_eq!(series.data[1].date, "2025-03-10T00:30:00Z");
+        assert_eq!(series.data[1].value, -0.59);
+        assert_eq!(series.misses.len(), 1);
+
+        let empty = &response.time_series[1];
+        assert_eq!(empty.header.value_type, FewsValueType::Accumulative);
+        assert_eq!(empty.header.time_step, FewsTimeStep::NonEquidistant);
+        assert_eq!(empty.header.time_step_seconds, None);
+        assert!(empty.data.is_empty());
+        assert_eq!(empty.header.ensemble_member.as_deref(), Some("3"));
+        assert_eq!(empty.header.forecast_date.as_deref(), Some("2025-03-10T05:00:00Z"));
+
+        assert!(parse_time_series("<Other/>").is_err());
+        assert!(parse_time_series("<TimeSeries><series><header/></series></TimeSeries>").is_err());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let response = parse_time_series(DOCUMENT).unwrap();
+        let xml = write_time_series(&response);
+        assert!(xml.contains(r#"<timeStep unit="second" multiplier="900"/>"#));
+        assert!(xml.contains(r#"<timeStep unit="nonequidistant"/>"#));
+        assert!(xml.contains(r#"<event date="2025-03-10" time="00:00:00" value="-0.61" flag="0"/>"#));
+
+        let again = parse_time_series(&xml).unwrap();
+        assert_eq!(again.time_series.len(), 2);
+        assert_eq!(again.time_series[0].data.len(), 2);
+        assert_eq!(again.time_series[0].header.start_date, "2025-03-10T00:00:00Z");
+        assert_eq!(again.time_series[0].header.time_step_seconds, Some(900));
+        assert_eq!(again.time_series[0].header.station_name, "Gemaal De Blauwe & Brug");
+        assert_eq!(again.time_series[1].header.ensemble_id.as_deref(), Some("ECMWF"));
+        assert_eq!(again.time_series[1].header.forecast_date, response.time_series[1].header.forecast_date);
+    }
+}
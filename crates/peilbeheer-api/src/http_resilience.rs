@@ -0,0 +1,359 @@
+//! Shared HTTP client with retries, timeouts and circuit breakers.
+//!
+//! All outgoing HTTP requests of the API go through [`HttpClient`]:
+//! - Every request gets a timeout unless it sets its own.
+//! - Idempotent requests (GET, HEAD, PUT, DELETE, OPTIONS) are retried
+//!   after network errors, 429, 502, 503 and 504, with exponential
+//!   backoff and jitter. Other methods, such as the FEWS write-back or
+//!   webhook POSTs, are sent once so a write is never repeated.
+//! - A circuit breaker per host rejects requests for a while after a run
+//!   of consecutive failures (network errors and 5xx), so a host that is
+//!   down is not hammered and callers fail fast.
+//!
+//! The breakers are shared by all clients that use [`HttpClient::shared`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration as StdDuration, Instant};
+
+use reqwest::{Client, IntoUrl, Method, RequestBuilder, Response, StatusCode};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// Resilience configuration.
+#[derive(Debug, Clone)]
+pub struct ResilienceConfig {
+    /// Retries after the first attempt of an idempotent request
+    pub max_retries: u32,
+    /// Delay before the first retry (milliseconds)
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the retry delay (milliseconds)
+    pub max_backoff_ms: u64,
+    /// Timeout of requests without their own timeout (seconds)
+    pub timeout_secs: u64,
+    /// Consecutive failures that open a host's circuit
+    pub failure_threshold: u32,
+    /// Time a circuit stays open before a request is let through (seconds)
+    pub open_secs: u64,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        let env = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_retries: env("HTTP_MAX_RETRIES", 2) as u32,
+            initial_backoff_ms: env("HTTP_RETRY_BACKOFF_MS", 250),
+            max_backoff_ms: env("HTTP_RETRY_MAX_BACKOFF_MS", 5_000),
+            timeout_secs: env("HTTP_TIMEOUT_SECS", 30),
+            failure_threshold: env("HTTP_CIRCUIT_BREAKER_THRESHOLD", 5) as u32,
+            open_secs: env("HTTP_CIRCUIT_BREAKER_OPEN_SECS", 30),
+        }
+    }
+}
+
+/// Errors of [`HttpClient::send`].
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("circuit breaker open for {0}")]
+    CircuitOpen(String),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// Failure state of one host.
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Circuit breakers per host.
+#[derive(Debug)]
+struct CircuitBreakers {
+    failure_threshold: u32,
+    open_for: StdDuration,
+    hosts: Mutex<HashMap<String, BreakerState>>,
+}
+
+impl CircuitBreakers {
+    fn new(config: &ResilienceConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            open_for: StdDuration::from_secs(config.open_secs),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request to `host` may be sent.
+    ///
+    /// Once the open period has passed the circuit is half open: requests
+    /// are let through and the next failure opens it again.
+    fn allow(&self, host: &str, now: Instant) -> bool {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = hosts.get_mut(host) else {
+            return true;
+        };
+        match state.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                state.open_until = None;
+                state.consecutive_failures = self.failure_threshold - 1;
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Record the outcome of a request to `host`.
+    fn record(&self, host: &str, success: bool, now: Instant) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if success {
+            hosts.remove(host);
+            return;
+        }
+
+        let state = hosts.entry(host.to_string()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold && state.open_until.is_none() {
+            warn!(
+                "Circuit breaker opened for {} after {} failures",
+                host, state.consecutive_failures
+            );
+            state.open_until = Some(now + self.open_for);
+        }
+    }
+}
+
+/// HTTP client with retries, timeouts and per-host circuit breakers.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    config: Arc<ResilienceConfig>,
+    breakers: Arc<CircuitBreakers>,
+    timeout: StdDuration,
+}
+
+impl HttpClient {
+    /// Create a client with its own circuit breakers.
+    pub fn new(config: ResilienceConfig) -> Self {
+        Self {
+            client: Client::new(),
+            timeout: StdDuration::from_secs(config.timeout_secs),
+            breakers: Arc::new(CircuitBreakers::new(&config)),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Process-wide client, configured by the environment.
+    pub fn shared() -> Self {
+        static SHARED: OnceLock<HttpClient> = OnceLock::new();
+        SHARED
+            .get_or_init(|| HttpClient::new(ResilienceConfig::default()))
+            .clone()
+    }
+
+    /// Same client and breakers with another default timeout.
+    pub fn with_timeout(&self, timeout: StdDuration) -> Self {
+        Self {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    /// Start a GET request; send it with [`HttpClient::send`].
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Start a POST request; send it with [`HttpClient::send`].
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Send a request, retrying idempotent requests on transient failures.
+    pub async fn send(&self, builder: RequestBuilder) -> Result<Response, HttpError> {
+        let mut request = builder.build()?;
+        if request.timeout().is_none() {
+            *request.timeout_mut() = Some(self.timeout);
+        }
+
+        let url = request.url();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let max_retries = if is_idempotent(request.method()) {
+            self.config.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            if !self.breakers.allow(&host, Instant::now()) {
+                return Err(HttpError::CircuitOpen(host));
+            }
+
+            // Streaming bodies cannot be replayed, so they get one attempt
+            let Some(current) = request.try_clone() else {
+                let outcome = self.client.execute(request).await;
+                self.breakers
+                    .record(&host, !is_failure(&outcome), Instant::now());
+                return Ok(outcome?);
+            };
+
+            let outcome = self.client.execute(current).await;
+            self.breakers
+                .record(&host, !is_failure(&outcome), Instant::now());
+
+            if attempt >= max_retries || !is_retryable(&outcome) {
+                return Ok(outcome?);
+            }
+
+            attempt += 1;
+            let delay = jitter(self.backoff_delay(attempt));
+            debug!(
+                "{} {} failed ({}), retry {} of {} in {:?}",
+                request.method(),
+                request.url(),
+                describe(&outcome),
+                attempt,
+                max_retries,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based), without jitter.
+    fn backoff_delay(&self, attempt: u32) -> StdDuration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.config.initial_backoff_ms.saturating_mul(factor);
+        StdDuration::from_millis(delay.min(self.config.max_backoff_ms))
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Whether the outcome counts as a failure of the host.
+fn is_failure(outcome: &Result<Response, reqwest::Error>) -> bool {
+    match outcome {
+        Ok(response) => response.status().is_server_error(),
+        Err(_) => true,
+    }
+}
+
+/// Whether another attempt could succeed.
+fn is_retryable(outcome: &Result<Response, reqwest::Error>) -> bool {
+    match outcome {
+        Ok(response) => is_retryable_status(response.status()),
+        Err(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn describe(outcome: &Result<Response, reqwest::Error>) -> String {
+    match outcome {
+        Ok(response) => format!("HTTP {}", response.status()),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Random delay between half and all of `delay`, so clients that failed
+/// together do not retry together.
+fn jitter(delay: StdDuration) -> StdDuration {
+    let half = delay / 2;
+    let spread = half.as_millis() as u64;
+    if spread == 0 {
+        return delay;
+    }
+    let random = (uuid::Uuid::new_v4().as_u128() % u128::from(spread + 1)) as u64;
+    half + StdDuration::from_millis(random)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ResilienceConfig {
+        ResilienceConfig {
+            max_retries: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 300,
+            timeout_secs: 5,
+            failure_threshold: 2,
+            open_secs: 10,
+        }
+    }
+
+    #[test]
+    fn test_backoff_with_jitter() {
+        let client = HttpClient::new(config());
+        assert_eq!(client.backoff_delay(1), StdDuration::from_millis(100));
+        assert_eq!(client.backoff_delay(2), StdDuration::from_millis(200));
+        assert_eq!(client.backoff_delay(3), StdDuration::from_millis(300));
+        assert_eq!(client.backoff_delay(30), StdDuration::from_millis(300));
+
+        for _ in 0..100 {
+            let delay = jitter(StdDuration::from_millis(200));
+            assert!(delay >= StdDuration::from_millis(100));
+            assert!(delay <= StdDuration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breakers = CircuitBreakers::new(&config());
+        let now = Instant::now();
+
+        breakers.record("fews.example.com", false, now);
+        assert!(breakers.allow("fews.example.com", now));
+        breakers.record("fews.example.com", false, now);
+        assert!(!breakers.allow("fews.example.com", now));
+        // Other hosts are not affected
+        assert!(breakers.allow("api.energyzero.nl", now));
+
+        // Half open after the open period; one failure opens it again
+        let later = now + StdDuration::from_secs(11);
+        assert!(breakers.allow("fews.example.com", later));
+        breakers.record("fews.example.com", false, later);
+        assert!(!breakers.allow("fews.example.com", later));
+
+        // A success closes it
+        let much_later = later + StdDuration::from_secs(11);
+        assert!(breakers.allow("fews.example.com", much_later));
+        breakers.record("fews.example.com", true, much_later);
+        breakers.record("fews.example.com", false, much_later);
+        assert!(breakers.allow("fews.example.com", much_later));
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    }
+}
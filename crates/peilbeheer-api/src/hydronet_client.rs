@@ -1,7 +1,8 @@
 use peilbeheer_core::hydronet::{DataPoint, GeoJsonGemaal, HydronetResponse, HydronetSeries};
-use reqwest::Client;
 use serde_json::Value;
 
+use crate::http_resilience::HttpClient;
+
 const HYDRONET_BASE_URL: &str =
     "https://watercontrolroom.hydronet.com/service/efsserviceprovider/api";
 #[allow(dead_code)]
@@ -10,14 +11,14 @@ const API_DELAY_MS: u64 = 150;
 /// HTTP client voor de Hydronet Water Control Room API.
 pub struct HydronetClient {
     chart_id: String,
-    client: Client,
+    client: HttpClient,
 }
 
 impl HydronetClient {
     pub fn new(chart_id: String) -> Self {
         Self {
             chart_id,
-            client: Client::new(),
+            client: HttpClient::shared(),
         }
     }
 
@@ -28,15 +29,17 @@ impl HydronetClient {
     ) -> Result<HydronetResponse, String> {
         let url = format!("{}/chart/{}", HYDRONET_BASE_URL, self.chart_id);
 
-        let response = self
+        let request = self
             .client
             .get(&url)
             .query(&[("featureIdentifier", feature_identifier)])
             .header("User-Agent", "Mozilla/5.0 (compatible; PeilbeheerHHVR/1.0)")
             .header("Accept", "application/json, text/plain, */*")
             .header("Referer", "https://rijnland.maps.arcgis.com/")
-            .timeout(std::time::Duration::from_secs(30))
-            .send()
+            .timeout(std::time::Duration::from_secs(30));
+        let response = self
+            .client
+            .send(request)
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
@@ -0,0 +1,452 @@
+//! KNMI Data Platform client for precipitation forecasts.
+//!
+//! Forecasts are requested from the KNMI EDR API with the polygon of a
+//! peilgebied as area and reduced to area means:
+//! - The radar nowcast gives hourly sums for the next hours by
+//!   [`peilbeheer_core::knmi::regen_per_uur`], usable as `regen_per_uur`
+//!   of a simulation or an optimization.
+//! - The HARMONIE (or EPS) forecast gives precipitation and evaporation for
+//!   the next 48 to 240 hours. [`KnmiForecastSync`] stores it periodically
+//!   as forecast time series of the configured peilgebieden.
+//!
+//! When KNMI is not configured or does not respond, the client falls back
+//! to Buienradar and Meteoserver ([`NeerslagFallback`]).
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use peilbeheer_core::knmi::{self, KnmiError};
+use peilbeheer_core::{RegenVerwachting, TimeSeriesDataPoint, WeerVerwachting};
+
+use crate::db::Database;
+use crate::http_resilience::{HttpClient, HttpError};
+use crate::neerslag_fallback::NeerslagFallback;
+use crate::timeseries_service::TimeSeriesService;
+
+/// Longest nowcast that can be requested (hours).
+pub const MAX_UREN: usize = 24;
+
+/// Shortest and longest weather forecast that can be requested (hours).
+pub const MIN_FORECAST_UREN: usize = 48;
+pub const MAX_FORECAST_UREN: usize = 240;
+
+/// KNMI client configuration.
+#[derive(Debug, Clone)]
+pub struct KnmiConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    /// EDR collection of the nowcast
+    pub collection: String,
+    /// Precipitation parameter within the collection (mm per time step)
+    pub parameter: String,
+    /// Tolerance for simplifying peilgebied polygons (degrees)
+    pub simplify_tolerance: f64,
+    /// EDR collection of the weather forecast (HARMONIE or EPS)
+    pub forecast_collection: String,
+    pub forecast_precipitation_parameter: String,
+    pub forecast_evaporation_parameter: String,
+    /// Whether the forecast accumulates from the start of the model run
+    pub forecast_accumulated: bool,
+    /// Forecast length (hours, 48 to 240)
+    pub forecast_hours: usize,
+    /// Peilgebieden whose forecast is stored periodically
+    pub forecast_peilgebieden: Vec<String>,
+    /// Time between forecast syncs (seconds)
+    pub forecast_interval_secs: u64,
+}
+
+impl Default for KnmiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: std::env::var("KNMI_BASE_URL")
+                .unwrap_or_else(|_| "https://api.dataplatform.knmi.nl/edr/v1".to_string()),
+            api_key: std::env::var("KNMI_API_KEY").ok().filter(|k| !k.is_empty()),
+            collection: std::env::var("KNMI_NOWCAST_COLLECTION")
+                .unwrap_or_else(|_| "radar_forecast".to_string()),
+            parameter: std::env::var("KNMI_NOWCAST_PARAMETER")
+                .unwrap_or_else(|_| "precipitation".to_string()),
+            simplify_tolerance: std::env::var("KNMI_SIMPLIFY_TOLERANCE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0005),
+            forecast_collection: std::env::var("KNMI_FORECAST_COLLECTION")
+                .unwrap_or_else(|_| "harmonie_arome_cy43_p1".to_string()),
+            forecast_precipitation_parameter: std::env::var(
+                "KNMI_FORECAST_PRECIPITATION_PARAMETER",
+            )
+            .unwrap_or_else(|_| "total-precipitation".to_string()),
+            forecast_evaporation_parameter: std::env::var("KNMI_FORECAST_EVAPORATION_PARAMETER")
+                .unwrap_or_else(|_| "evaporation".to_string()),
+            forecast_accumulated: std::env::var("KNMI_FORECAST_ACCUMULATED")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            forecast_hours: std::env::var("KNMI_FORECAST_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(MIN_FORECAST_UREN),
+            forecast_peilgebieden: std::env::var("KNMI_FORECAST_PEILGEBIEDEN")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            forecast_interval_secs: std::env::var("KNMI_FORECAST_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3 * 3600),
+        }
+    }
+}
+
+impl KnmiConfig {
+    pub fn is_configured(&self) -> bool {
+        self.api_key.is_some()
+    }
+}
+
+/// Errors of the KNMI client.
+#[derive(Debug, Error)]
+pub enum KnmiClientError {
+    #[error("KNMI is not configured (KNMI_API_KEY)")]
+    NotConfigured,
+    #[error("Peilgebied {0} not found")]
+    PeilgebiedNotFound(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("KNMI returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error(transparent)]
+    Parse(#[from] KnmiError),
+    #[error("Database error: {0}")]
+    Database(#[from] anyhow::Error),
+}
+
+impl KnmiClientError {
+    /// Whether KNMI itself is unavailable, so a fallback source may help.
+    fn is_unavailable(&self) -> bool {
+        matches!(self, Self::NotConfigured | Self::Http(_) | Self::Api { .. })
+    }
+}
+
+/// Client for the KNMI precipitation nowcast per peilgebied.
+pub struct KnmiClient {
+    db: Arc<Database>,
+    config: KnmiConfig,
+    http_client: HttpClient,
+    fallback: Option<NeerslagFallback>,
+}
+
+impl KnmiClient {
+    pub fn new(db: Arc<Database>, config: KnmiConfig) -> Self {
+        Self {
+            db,
+            config,
+            http_client: HttpClient::shared(),
+            fallback: None,
+        }
+    }
+
+    /// Fall back to Buienradar and Meteoserver when KNMI is unavailable.
+    pub fn with_fallback(mut self, fallback: NeerslagFallback) -> Self {
+        self.fallback = Some(fallback).filter(|f| f.config().enabled);
+        self
+    }
+
+    pub fn config(&self) -> &KnmiConfig {
+        &self.config
+    }
+
+    /// Whether a weather forecast can be fetched from KNMI or a fallback.
+    pub fn has_forecast_source(&self) -> bool {
+        self.config.is_configured() || self.fallback.as_ref().is_some_and(|f| f.has_forecast())
+    }
+
+    /// Precipitation forecast of a peilgebied for the next `uren` hours.
+    ///
+    /// Falls back to the Buienradar nowcast when KNMI is unavailable; the
+    /// KNMI error is returned when the fallback fails as well.
+    pub async fn regen_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<RegenVerwachting, KnmiClientError> {
+        match (self.knmi_regen_verwachting(peilgebied_code, uren).await, &self.fallback) {
+            (Err(e), Some(fallback)) if e.is_unavailable() => {
+                warn!("KNMI nowcast unavailable ({}), falling back to Buienradar", e);
+                fallback
+                    .regen_verwachting(peilgebied_code, uren)
+                    .await
+                    .map_err(|fallback_error| {
+                        warn!("Buienradar fallback failed: {}", fallback_error);
+                        e
+                    })
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Weather forecast of a peilgebied for the next `uren` hours.
+    ///
+    /// Falls back to the Meteoserver forecast (precipitation only) when KNMI
+    /// is unavailable and a Meteoserver key is configured.
+    pub async fn weer_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<WeerVerwachting, KnmiClientError> {
+        match (self.knmi_weer_verwachting(peilgebied_code, uren).await, &self.fallback) {
+            (Err(e), Some(fallback)) if e.is_unavailable() && fallback.has_forecast() => {
+                warn!("KNMI forecast unavailable ({}), falling back to Meteoserver", e);
+                let uren = uren.clamp(MIN_FORECAST_UREN, MAX_FORECAST_UREN);
+                fallback
+                    .weer_verwachting(peilgebied_code, uren)
+                    .await
+                    .map_err(|fallback_error| {
+                        warn!("Meteoserver fallback failed: {}", fallback_error);
+                        e
+                    })
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Area-mean precipitation of a peilgebied for the next `uren` hours.
+    ///
+    /// The hours start at the current 5-minute radar time step and end with
+    /// the nowcast, about two hours ahead, even when more are requested.
+    async fn knmi_regen_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<RegenVerwachting, KnmiClientError> {
+        let now = Utc::now();
+        let start = now.duration_trunc(Duration::minutes(5)).unwrap_or(now);
+        let uren = uren.clamp(1, MAX_UREN);
+        let end = start + Duration::hours(uren as i64);
+
+        let coverage = self
+            .fetch_area(
+                peilgebied_code,
+                &self.config.collection,
+                &[self.config.parameter.as_str()],
+                start,
+                end,
+            )
+            .await?;
+
+        Ok(RegenVerwachting {
+            peilgebied_code: peilgebied_code.to_string(),
+            start,
+            regen_per_uur: knmi::regen_per_uur(&coverage, &self.config.parameter, start, uren)?,
+            bron: format!("knmi:{}", self.config.collection),
+            opgehaald_op: now,
+        })
+    }
+
+    /// Area-mean precipitation and evaporation of a peilgebied for the next
+    /// `uren` hours (48 to 240), from the start of the current hour.
+    async fn knmi_weer_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<WeerVerwachting, KnmiClientError> {
+        let now = Utc::now();
+        let start = now.duration_trunc(Duration::hours(1)).unwrap_or(now);
+        let uren = uren.clamp(MIN_FORECAST_UREN, MAX_FORECAST_UREN);
+        let end = start + Duration::hours(uren as i64);
+
+        let neerslag_parameter = self.config.forecast_precipitation_parameter.as_str();
+        let verdamping_parameter = self.config.forecast_evaporation_parameter.as_str();
+        let coverage = self
+            .fetch_area(
+                peilgebied_code,
+                &self.config.forecast_collection,
+                &[neerslag_parameter, verdamping_parameter],
+                start,
+                end,
+            )
+            .await?;
+
+        let reeks = |parameter: &str| -> Result<Vec<TimeSeriesDataPoint>, KnmiError> {
+            let mut reeks = knmi::gebiedsgemiddelde(&coverage, parameter)?;
+            if self.config.forecast_accumulated {
+                // The first value covers the time since the model run
+                // started, not one time step
+                reeks = knmi::deaccumuleer(&reeks).into_iter().skip(1).collect();
+            }
+            Ok(reeks
+                .into_iter()
+                .filter(|(tijd, _)| *tijd >= start && *tijd <= end)
+                .map(|(tijd, waarde)| TimeSeriesDataPoint::new(tijd, waarde))
+                .collect())
+        };
+
+        Ok(WeerVerwachting {
+            peilgebied_code: peilgebied_code.to_string(),
+            neerslag: reeks(neerslag_parameter)?,
+            verdamping: reeks(verdamping_parameter)?,
+            bron: format!("knmi:{}", self.config.forecast_collection),
+            opgehaald_op: now,
+        })
+    }
+
+    /// CoverageJSON of `parameters` within the polygon of a peilgebied.
+    async fn fetch_area(
+        &self,
+        peilgebied_code: &str,
+        collection: &str,
+        parameters: &[&str],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<serde_json::Value, KnmiClientError> {
+        let Some(api_key) = &self.config.api_key else {
+            return Err(KnmiClientError::NotConfigured);
+        };
+        let wkt = self
+            .db
+            .get_peilgebied_wkt(peilgebied_code, self.config.simplify_tolerance)?
+            .ok_or_else(|| KnmiClientError::PeilgebiedNotFound(peilgebied_code.to_string()))?;
+
+        let url = format!(
+            "{}/collections/{}/area",
+            self.config.base_url.trim_end_matches('/'),
+            collection
+        );
+        let request = self
+            .http_client
+            .get(&url)
+            .header(reqwest::header::AUTHORIZATION, api_key)
+            .query(&[
+                ("coords", wkt.as_str()),
+                ("parameter-name", &parameters.join(",")),
+                (
+                    "datetime",
+                    &format!("{}/{}", edr_time(start), edr_time(end)),
+                ),
+            ]);
+
+        tracing::debug!(
+            "KNMI {} request for peilgebied {}",
+            collection,
+            peilgebied_code
+        );
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(KnmiClientError::Api { status, message });
+        }
+        Ok(response.json().await.map_err(HttpError::from)?)
+    }
+}
+
+/// Summary of a forecast sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct KnmiForecastSyncResult {
+    pub peilgebieden: usize,
+    pub points_written: usize,
+    pub errors: Vec<String>,
+}
+
+/// Periodically stores the KNMI weather forecast of the configured
+/// peilgebieden in the [`TimeSeriesService`].
+pub struct KnmiForecastSync {
+    client: Arc<KnmiClient>,
+    timeseries: Arc<TimeSeriesService>,
+}
+
+impl KnmiForecastSync {
+    pub fn new(client: Arc<KnmiClient>, timeseries: Arc<TimeSeriesService>) -> Self {
+        Self { client, timeseries }
+    }
+
+    /// Start the periodic sync (no-op without forecast source or
+    /// peilgebieden).
+    pub fn start(self: &Arc<Self>) {
+        let config = self.client.config();
+        if !self.client.has_forecast_source() || config.forecast_peilgebieden.is_empty() {
+            info!("KNMI forecast sync disabled");
+            return;
+        }
+
+        let sync = Arc::clone(self);
+        let interval = StdDuration::from_secs(config.forecast_interval_secs.max(60));
+
+        tokio::spawn(async move {
+            info!("KNMI forecast sync started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let codes = sync.client.config().forecast_peilgebieden.clone();
+                match sync.sync(&codes, None).await {
+                    Ok(result) if result.errors.is_empty() => info!(
+                        "KNMI forecast synced: {} peilgebieden, {} points",
+                        result.peilgebieden, result.points_written
+                    ),
+                    Ok(result) => warn!(
+                        "KNMI forecast sync finished with errors: {}",
+                        result.errors.join("; ")
+                    ),
+                    Err(e) => warn!("KNMI forecast sync failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Fetch and store the forecast of each peilgebied.
+    ///
+    /// Errors of single peilgebieden end up in the result; only a missing
+    /// forecast source fails the sync.
+    pub async fn sync(
+        &self,
+        peilgebied_codes: &[String],
+        uren: Option<usize>,
+    ) -> AnyhowResult<KnmiForecastSyncResult> {
+        if !self.client.has_forecast_source() {
+            return Err(KnmiClientError::NotConfigured.into());
+        }
+        let uren = uren.unwrap_or(self.client.config().forecast_hours);
+
+        let mut result = KnmiForecastSyncResult {
+            peilgebieden: 0,
+            points_written: 0,
+            errors: Vec::new(),
+        };
+        for code in peilgebied_codes {
+            let verwachting = match self.client.weer_verwachting(code, uren).await {
+                Ok(verwachting) => verwachting,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", code, e));
+                    continue;
+                }
+            };
+            match self.timeseries.import_knmi_forecast(&verwachting).await {
+                Ok(writes) => {
+                    result.peilgebieden += 1;
+                    result.points_written += writes.iter().map(|w| w.points_written).sum::<usize>();
+                }
+                Err(e) => result.errors.push(format!("{}: {}", code, e)),
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn edr_time(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
@@ -0,0 +1,259 @@
+//! LDAP / Active Directory password authentication.
+//!
+//! A login first looks up the user's entry, bound as a service account
+//! (`LDAP_BIND_DN`) or anonymously, and then binds as that entry with the
+//! password given. Lookups use `LDAP_USER_FILTER`, in which `{username}` is
+//! replaced by the escaped username.
+//!
+//! Groups map to roles via `LDAP_GROUP_ROLES`, e.g.
+//! `"Peilbeheer-Admins=admin;CN=Peilbeheer-Operators,OU=Groups,DC=rijnland,DC=net=operator"`.
+//! A group is matched by its common name or its full DN, ignoring case.
+//! Nested groups are only seen when the directory includes them in
+//! `memberOf`.
+
+use futures_util::future::BoxFuture;
+use ldap3::{LdapConnAsync, LdapConnSettings, LdapError, Scope, SearchEntry, ldap_escape};
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+use tracing::debug;
+
+use peilbeheer_core::Role;
+
+use crate::auth_backend::{AuthBackend, BackendError, ExternalIdentity};
+use crate::oidc::role_for_groups;
+
+/// `auth_provider` of users provisioned from the directory
+pub const LDAP_PROVIDER: &str = "ldap";
+/// Result code of a bind with a wrong DN or password
+const INVALID_CREDENTIALS_RC: u32 = 49;
+/// Default filter, matching Active Directory accounts
+const DEFAULT_USER_FILTER: &str = "(&(objectClass=user)(sAMAccountName={username}))";
+
+/// LDAP configuration.
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    /// Server URL, e.g. `ldaps://dc01.rijnland.net:636`
+    pub url: Option<String>,
+    /// Upgrade `ldap://` connections with StartTLS
+    pub starttls: bool,
+    /// DN of the account used for lookups (anonymous when unset)
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    /// Base DN of the user lookup
+    pub base_dn: Option<String>,
+    /// Lookup filter; `{username}` is replaced by the username
+    pub user_filter: String,
+    /// Attribute holding the username
+    pub username_attribute: String,
+    /// Role per group (lowercase common name or DN)
+    pub group_roles: HashMap<String, Role>,
+    /// Role for users without a mapped group (None = login refused)
+    pub default_role: Option<Role>,
+    /// Connection and operation timeout (seconds)
+    pub timeout_secs: u64,
+}
+
+impl Default for LdapConfig {
+    fn default() -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        Self {
+            url: env("LDAP_URL"),
+            starttls: env("LDAP_STARTTLS").is_some_and(|v| v == "true" || v == "1"),
+            bind_dn: env("LDAP_BIND_DN"),
+            bind_password: env("LDAP_BIND_PASSWORD"),
+            base_dn: env("LDAP_BASE_DN"),
+            user_filter: env("LDAP_USER_FILTER")
+                .unwrap_or_else(|| DEFAULT_USER_FILTER.to_string()),
+            username_attribute: env("LDAP_USERNAME_ATTRIBUTE")
+                .unwrap_or_else(|| "sAMAccountName".to_string()),
+            group_roles: env("LDAP_GROUP_ROLES")
+                .map(|s| parse_ldap_group_roles(&s))
+                .unwrap_or_default(),
+            default_role: env("LDAP_DEFAULT_ROLE").and_then(|s| Role::from_str(&s)),
+            timeout_secs: env("LDAP_TIMEOUT")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+        }
+    }
+}
+
+impl LdapConfig {
+    /// Whether LDAP authentication is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.url.is_some() && self.base_dn.is_some()
+    }
+}
+
+/// Parse a group mapping; groups are stored in lowercase.
+///
+/// Group DNs contain commas, so entries are only separated by `;`. The role
+/// follows the last `=`.
+pub fn parse_ldap_group_roles(s: &str) -> HashMap<String, Role> {
+    s.split(';')
+        .filter_map(|entry| {
+            let (group, role) = entry.rsplit_once('=')?;
+            let group = group.trim().to_lowercase();
+            let role = Role::from_str(role.trim())?;
+            (!group.is_empty()).then_some((group, role))
+        })
+        .collect()
+}
+
+/// Names a user's groups are matched by: the DN and the common name of
+/// each `memberOf` value, in lowercase.
+pub fn group_names(member_of: &[String]) -> Vec<String> {
+    member_of
+        .iter()
+        .flat_map(|dn| {
+            let dn = dn.trim().to_lowercase();
+            let cn = dn
+                .split(',')
+                .next()
+                .and_then(|rdn| rdn.trim().strip_prefix("cn="))
+                .map(str::to_string);
+            std::iter::once(dn).chain(cn)
+        })
+        .collect()
+}
+
+/// Authenticates users against an LDAP directory.
+pub struct LdapBackend {
+    config: LdapConfig,
+}
+
+impl LdapBackend {
+    /// Create a new backend.
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+
+    async fn lookup_and_bind(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<ExternalIdentity>, LdapError> {
+        let url = self.config.url.as_deref().unwrap_or_default();
+        let base_dn = self.config.base_dn.as_deref().unwrap_or_default();
+        let timeout = StdDuration::from_secs(self.config.timeout_secs);
+
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(timeout)
+            .set_starttls(self.config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, url).await?;
+        ldap3::drive!(conn);
+        ldap.with_timeout(timeout);
+
+        if let Some(bind_dn) = &self.config.bind_dn {
+            let bind_password = self.config.bind_password.as_deref().unwrap_or_default();
+            ldap.simple_bind(bind_dn, bind_password).await?.success()?;
+        }
+
+        let filter = self
+            .config
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let attributes = vec![
+            self.config.username_attribute.as_str(),
+            "mail",
+            "displayName",
+            "memberOf",
+        ];
+        let (entries, _) = ldap
+            .search(base_dn, Scope::Subtree, &filter, attributes)
+            .await?
+            .success()?;
+
+        if entries.len() != 1 {
+            debug!("LDAP lookup of '{}' found {} entries", username, entries.len());
+            let _ = ldap.unbind().await;
+            return Ok(None);
+        }
+        let entry = SearchEntry::construct(entries.into_iter().next().unwrap());
+
+        let bind = ldap.simple_bind(&entry.dn, password).await?;
+        let _ = ldap.unbind().await;
+        if bind.rc == INVALID_CREDENTIALS_RC {
+            return Ok(None);
+        }
+        bind.success()?;
+
+        let first = |attribute: &str| {
+            entry
+                .attrs
+                .get(attribute)
+                .and_then(|values| values.first())
+                .cloned()
+        };
+        let groups = group_names(entry.attrs.get("memberOf").map_or(&[], Vec::as_slice));
+
+        Ok(Some(ExternalIdentity {
+            username: first(&self.config.username_attribute)
+                .unwrap_or_else(|| username.to_string()),
+            email: first("mail").unwrap_or_default(),
+            full_name: first("displayName"),
+            role: role_for_groups(&self.config.group_roles, &groups, None),
+        }))
+    }
+}
+
+impl AuthBackend for LdapBackend {
+    fn name(&self) -> &str {
+        LDAP_PROVIDER
+    }
+
+    fn default_role(&self) -> Option<Role> {
+        self.config.default_role
+    }
+
+    fn authenticate<'a>(
+        &'a self,
+        username: &'a str,
+        password: &'a str,
+    ) -> BoxFuture<'a, Result<Option<ExternalIdentity>, BackendError>> {
+        Box::pin(async move {
+            // An empty password would be an anonymous bind, which succeeds
+            if username.trim().is_empty() || password.is_empty() {
+                return Ok(None);
+            }
+
+            let identity = self
+                .lookup_and_bind(username.trim(), password)
+                .await
+                .map_err(|e| BackendError::Unavailable(e.to_string()))?;
+
+            match identity {
+                Some(identity) if identity.email.is_empty() => Err(BackendError::InvalidEntry(
+                    format!("{} has no e-mail address", identity.username),
+                )),
+                identity => Ok(identity),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_role_mapping() {
+        let mapping = parse_ldap_group_roles(
+            "Peilbeheer-Admins=admin; CN=Operators,OU=Groups,DC=rijnland,DC=net=operator;x=unknown",
+        );
+        assert_eq!(mapping.len(), 2);
+        assert_eq!(mapping["peilbeheer-admins"], Role::Admin);
+        assert_eq!(mapping["cn=operators,ou=groups,dc=rijnland,dc=net"], Role::Operator);
+
+        let groups = group_names(&[
+            "CN=Operators,OU=Groups,DC=rijnland,DC=net".to_string(),
+            "CN=Peilbeheer-Admins,OU=Other,DC=rijnland,DC=net".to_string(),
+        ]);
+        assert!(groups.contains(&"peilbeheer-admins".to_string()));
+        assert_eq!(role_for_groups(&mapping, &groups, None), Some(Role::Admin));
+
+        let groups = group_names(&["CN=Operators,OU=Groups,DC=rijnland,DC=net".to_string()]);
+        assert_eq!(role_for_groups(&mapping, &groups, None), Some(Role::Operator));
+        assert_eq!(role_for_groups(&mapping, &group_names(&[]), None), None);
+    }
+}
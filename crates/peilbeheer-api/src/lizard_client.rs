@@ -0,0 +1,456 @@
+//! Lizard client for groundwater levels per peilgebied.
+//!
+//! Groundwater time series whose location lies within a peilgebied are
+//! found with a `location__geometry__within` query on the Lizard v4 API and
+//! their events are stored by [`LizardGrondwaterSync`] as time series
+//! `grondwaterstand` of the Lizard location, with the filter as qualifier
+//! and the peilgebied as attribute. Measured levels back the seepage and
+//! infiltration assumptions of the simulation.
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::TimeSeriesDataPoint;
+
+use crate::db::Database;
+use crate::http_resilience::{HttpClient, HttpError};
+use crate::timeseries_service::TimeSeriesService;
+
+/// Longest period that can be requested at once (days).
+pub const MAX_PERIODE_DAGEN: i64 = 366;
+
+/// Results per page of a Lizard list request.
+const PAGE_SIZE: usize = 1000;
+
+/// Tolerance (degrees) used to simplify the peilgebied polygon for the URL.
+const WKT_TOLERANTIE: f64 = 0.0005;
+
+/// Lizard client configuration.
+#[derive(Debug, Clone)]
+pub struct LizardConfig {
+    /// Base URL of the v4 API, e.g. `https://rijnland.lizard.net/api/v4`
+    pub base_url: String,
+    /// Personal API key (sent as password of user `__key__`)
+    pub api_key: Option<String>,
+    /// Filter on the observation type parameter of groundwater series
+    pub observation_parameter: String,
+    /// Peilgebieden whose groundwater levels are stored periodically
+    pub peilgebieden: Vec<String>,
+    /// Time between syncs (seconds)
+    pub sync_interval_secs: u64,
+    /// Period fetched on each sync (days)
+    pub sync_history_days: i64,
+}
+
+impl Default for LizardConfig {
+    fn default() -> Self {
+        Self {
+            base_url: std::env::var("LIZARD_BASE_URL")
+                .unwrap_or_else(|_| "https://rijnland.lizard.net/api/v4".to_string()),
+            api_key: std::env::var("LIZARD_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+            observation_parameter: std::env::var("LIZARD_OBSERVATION_PARAMETER")
+                .unwrap_or_else(|_| "grondwater".to_string()),
+            peilgebieden: std::env::var("LIZARD_PEILGEBIEDEN")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            sync_interval_secs: std::env::var("LIZARD_SYNC_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(21_600),
+            sync_history_days: std::env::var("LIZARD_HISTORY_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7),
+        }
+    }
+}
+
+/// Errors of the Lizard client.
+#[derive(Debug, Error)]
+pub enum LizardClientError {
+    #[error("Invalid period: {0}")]
+    InvalidPeriod(String),
+    #[error("Peilgebied not found: {0}")]
+    PeilgebiedNotFound(String),
+    #[error("Database error: {0}")]
+    Database(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("Lizard returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+}
+
+/// Groundwater time series of a Lizard location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GrondwaterReeks {
+    pub uuid: String,
+    /// Code of the Lizard location (well)
+    pub locatie_code: String,
+    /// Code of the series, usually the filter of the well
+    pub filter_code: String,
+    pub naam: Option<String>,
+    pub eenheid: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+}
+
+/// Client for the Lizard v4 API.
+pub struct LizardClient {
+    config: LizardConfig,
+    db: Arc<Database>,
+    http_client: HttpClient,
+}
+
+impl LizardClient {
+    pub fn new(config: LizardConfig, db: Arc<Database>) -> Self {
+        Self {
+            config,
+            db,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    pub fn config(&self) -> &LizardConfig {
+        &self.config
+    }
+
+    /// Groundwater series located within a peilgebied.
+    pub async fn grondwaterreeksen(
+        &self,
+        peilgebied: &str,
+    ) -> Result<Vec<GrondwaterReeks>, LizardClientError> {
+        let wkt = self
+            .db
+            .get_peilgebied_wkt(peilgebied, WKT_TOLERANTIE)
+            .map_err(|e| LizardClientError::Database(e.to_string()))?
+            .ok_or_else(|| LizardClientError::PeilgebiedNotFound(peilgebied.to_string()))?;
+
+        let url = format!("{}/timeseries/", self.config.base_url.trim_end_matches('/'));
+        let query = [
+            ("location__geometry__within", wkt),
+            (
+                "observation_type__parameter__icontains",
+                self.config.observation_parameter.clone(),
+            ),
+            ("page_size", PAGE_SIZE.to_string()),
+        ];
+        let mut reeksen = Vec::new();
+        let mut request = self.http_client.get(&url).query(&query);
+        loop {
+            let body = self.get_json(request).await?;
+            reeksen.extend(parse_reeksen(&body)?);
+            match body["next"].as_str() {
+                Some(next) => request = self.http_client.get(next),
+                None => break,
+            }
+        }
+        debug!(
+            "Lizard: {} grondwaterreeksen in {}",
+            reeksen.len(),
+            peilgebied
+        );
+        Ok(reeksen)
+    }
+
+    /// Events of a series in `[van, tot]`.
+    pub async fn grondwaterstanden(
+        &self,
+        uuid: &str,
+        van: DateTime<Utc>,
+        tot: DateTime<Utc>,
+    ) -> Result<Vec<TimeSeriesDataPoint>, LizardClientError> {
+        if tot <= van || tot - van > Duration::days(MAX_PERIODE_DAGEN) {
+            return Err(LizardClientError::InvalidPeriod(format!(
+                "{} to {} (at most {} days)",
+                van, tot, MAX_PERIODE_DAGEN
+            )));
+        }
+
+        let url = format!(
+            "{}/timeseries/{}/events/",
+            self.config.base_url.trim_end_matches('/'),
+            uuid
+        );
+        let query = [
+            ("time__gte", van.to_rfc3339()),
+            ("time__lte", tot.to_rfc3339()),
+            ("page_size", PAGE_SIZE.to_string()),
+        ];
+        let mut punten = Vec::new();
+        let mut request = self.http_client.get(&url).query(&query);
+        loop {
+            let body = self.get_json(request).await?;
+            punten.extend(parse_events(&body)?);
+            match body["next"].as_str() {
+                Some(next) => request = self.http_client.get(next),
+                None => break,
+            }
+        }
+        punten.sort_by_key(|p| p.timestamp);
+        Ok(punten)
+    }
+
+    async fn get_json(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<serde_json::Value, LizardClientError> {
+        let request = match &self.config.api_key {
+            Some(key) => request.basic_auth("__key__", Some(key)),
+            None => request,
+        };
+        let response = self
+            .http_client
+            .send(request.header("Accept", "application/json"))
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(LizardClientError::Api { status, message });
+        }
+        Ok(response.json().await.map_err(HttpError::from)?)
+    }
+}
+
+/// Periodic storage of Lizard groundwater levels as time series.
+pub struct LizardGrondwaterSync {
+    client: Arc<LizardClient>,
+    timeseries: Arc<TimeSeriesService>,
+}
+
+/// Result of a Lizard sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct LizardSyncResult {
+    pub peilgebieden: usize,
+    pub reeksen: usize,
+    pub points_written: usize,
+    pub errors: Vec<String>,
+}
+
+impl LizardGrondwaterSync {
+    pub fn new(client: Arc<LizardClient>, timeseries: Arc<TimeSeriesService>) -> Self {
+        Self { client, timeseries }
+    }
+
+    /// Start the periodic sync (no-op without peilgebieden).
+    pub fn start(self: &Arc<Self>) {
+        let config = self.client.config();
+        if config.peilgebieden.is_empty() {
+            info!("Lizard groundwater sync disabled (LIZARD_PEILGEBIEDEN not set)");
+            return;
+        }
+
+        let sync = Arc::clone(self);
+        let interval = StdDuration::from_secs(config.sync_interval_secs.max(300));
+
+        tokio::spawn(async move {
+            info!("Lizard groundwater sync started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let peilgebieden = sync.client.config().peilgebieden.clone();
+                match sync.sync(&peilgebieden, None).await {
+                    Ok(result) if result.errors.is_empty() => info!(
+                        "Lizard groundwater levels synced: {} series, {} points",
+                        result.reeksen, result.points_written
+                    ),
+                    Ok(result) => warn!(
+                        "Lizard sync finished with errors: {}",
+                        result.errors.join("; ")
+                    ),
+                    Err(e) => warn!("Lizard sync failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Fetch and store the groundwater levels of every series in each
+    /// peilgebied.
+    ///
+    /// Errors of single peilgebieden or series end up in the result.
+    pub async fn sync(
+        &self,
+        peilgebieden: &[String],
+        dagen: Option<i64>,
+    ) -> AnyhowResult<LizardSyncResult> {
+        let tot = Utc::now();
+        let van = tot - Duration::days(dagen.unwrap_or(self.client.config().sync_history_days));
+
+        let mut result = LizardSyncResult {
+            peilgebieden: 0,
+            reeksen: 0,
+            points_written: 0,
+            errors: Vec::new(),
+        };
+        for peilgebied in peilgebieden {
+            let reeksen = match self.client.grondwaterreeksen(peilgebied).await {
+                Ok(reeksen) => reeksen,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", peilgebied, e));
+                    continue;
+                }
+            };
+            result.peilgebieden += 1;
+
+            for reeks in reeksen {
+                let data = match self.client.grondwaterstanden(&reeks.uuid, van, tot).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        result.errors.push(format!("{}: {}", reeks.locatie_code, e));
+                        continue;
+                    }
+                };
+                match self
+                    .timeseries
+                    .import_lizard_grondwaterstanden(&reeks, peilgebied, data)
+                    .await
+                {
+                    Ok(write) => {
+                        result.reeksen += 1;
+                        result.points_written += write.points_written;
+                    }
+                    Err(e) => result.errors.push(format!("{}: {}", reeks.locatie_code, e)),
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Groundwater series in a `/timeseries/` page.
+fn parse_reeksen(body: &serde_json::Value) -> Result<Vec<GrondwaterReeks>, LizardClientError> {
+    let results = body["results"]
+        .as_array()
+        .ok_or_else(|| LizardClientError::Parse("results is missing".to_string()))?;
+
+    results
+        .iter()
+        .map(|reeks| {
+            let uuid = reeks["uuid"]
+                .as_str()
+                .ok_or_else(|| LizardClientError::Parse("series without uuid".to_string()))?;
+            let locatie = &reeks["location"];
+            let coordinaten = locatie["geometry"]["coordinates"].as_array();
+            let filter_code = reeks["code"]
+                .as_str()
+                .filter(|c| !c.is_empty())
+                .unwrap_or(uuid);
+            Ok(GrondwaterReeks {
+                uuid: uuid.to_string(),
+                locatie_code: locatie["code"]
+                    .as_str()
+                    .filter(|c| !c.is_empty())
+                    .unwrap_or(filter_code)
+                    .to_string(),
+                filter_code: filter_code.to_string(),
+                naam: reeks["name"].as_str().map(str::to_string),
+                eenheid: reeks["observation_type"]["unit"]
+                    .as_str()
+                    .map(str::to_string),
+                lat: coordinaten.and_then(|c| c.get(1)).and_then(|v| v.as_f64()),
+                lon: coordinaten.and_then(|c| c.first()).and_then(|v| v.as_f64()),
+            })
+        })
+        .collect()
+}
+
+/// Events in an `/events/` page; events without a value are skipped.
+fn parse_events(body: &serde_json::Value) -> Result<Vec<TimeSeriesDataPoint>, LizardClientError> {
+    let results = body["results"]
+        .as_array()
+        .ok_or_else(|| LizardClientError::Parse("results is missing".to_string()))?;
+
+    let mut punten = Vec::new();
+    for event in results {
+        let Some(waarde) = event["value"].as_f64() else {
+            continue;
+        };
+        let tijd = event["time"]
+            .as_str()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .ok_or_else(|| LizardClientError::Parse(format!("invalid time {}", event["time"])))?;
+        punten.push(TimeSeriesDataPoint::new(tijd.with_timezone(&Utc), waarde));
+    }
+    Ok(punten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_reeksen() {
+        let body = json!({
+            "count": 2,
+            "next": null,
+            "results": [
+                {
+                    "uuid": "7b1f0c2e-0000-4000-8000-000000000001",
+                    "code": "B30F0123-001",
+                    "name": "Grondwaterstand filter 1",
+                    "observation_type": {"parameter": "Grondwaterstand", "unit": "mNAP"},
+                    "location": {
+                        "code": "B30F0123",
+                        "geometry": {"type": "Point", "coordinates": [4.52, 52.14, 0.0]}
+                    }
+                },
+                {"uuid": "7b1f0c2e-0000-4000-8000-000000000002", "location": {}}
+            ]
+        });
+        let reeksen = parse_reeksen(&body).unwrap();
+        assert_eq!(reeksen.len(), 2);
+        assert_eq!(reeksen[0].locatie_code, "B30F0123");
+        assert_eq!(reeksen[0].filter_code, "B30F0123-001");
+        assert_eq!(reeksen[0].eenheid.as_deref(), Some("mNAP"));
+        assert_eq!((reeksen[0].lon, reeksen[0].lat), (Some(4.52), Some(52.14)));
+
+        // Without codes the uuid identifies the series
+        assert_eq!(reeksen[1].locatie_code, reeksen[1].uuid);
+        assert!(reeksen[1].lat.is_none());
+
+        assert!(parse_reeksen(&json!({"detail": "Not found"})).is_err());
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let body = json!({
+            "next": "https://rijnland.lizard.net/api/v4/timeseries/x/events/?page=2",
+            "results": [
+                {"time": "2025-03-10T00:00:00Z", "value": -1.42, "flag": null},
+                {"time": "2025-03-10T01:00:00+01:00", "value": null},
+                {"time": "2025-03-10T02:00:00Z", "value": -1.40}
+            ]
+        });
+        let punten = parse_events(&body).unwrap();
+        assert_eq!(punten.len(), 2);
+        assert!((punten[0].value + 1.42).abs() < 1e-12);
+        assert_eq!(
+            punten[1].timestamp.to_rfc3339(),
+            "2025-03-10T02:00:00+00:00"
+        );
+
+        assert!(parse_events(&json!({"results": [{"time": "gisteren", "value": 1.0}]})).is_err());
+    }
+}
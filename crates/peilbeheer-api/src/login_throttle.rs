@@ -0,0 +1,293 @@
+//! Brute-force protection of password logins.
+//!
+//! The login route asks [`LoginThrottle::locked_for`] before verifying a
+//! password and reports the outcome afterwards. Failures are recorded in
+//! `login_attempts` and counted per account and per client address in
+//! `login_lockouts`; see [`peilbeheer_core::login_throttle`] for the policy.
+//! The state lives in the database, so lockouts hold across API instances
+//! and restarts. Admins list and lift lockouts under `/api/auth/lockouts`.
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+use peilbeheer_core::{Lockout, LockoutPolicy, LockoutScope, LoginAttempt};
+
+use crate::db::{Database, is_no_rows};
+
+/// Default and maximum number of attempts returned by a search
+const DEFAULT_ATTEMPTS_LIMIT: u64 = 100;
+const MAX_ATTEMPTS_LIMIT: u64 = 1000;
+
+/// Login throttling service.
+pub struct LoginThrottle {
+    db: Arc<Database>,
+    account_policy: LockoutPolicy,
+    ip_policy: LockoutPolicy,
+}
+
+impl LoginThrottle {
+    /// Create a login throttle with the default policies.
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            account_policy: LockoutPolicy::for_scope(LockoutScope::Account),
+            ip_policy: LockoutPolicy::for_scope(LockoutScope::Ip),
+        }
+    }
+
+    fn policy(&self, scope: LockoutScope) -> &LockoutPolicy {
+        match scope {
+            LockoutScope::Account => &self.account_policy,
+            LockoutScope::Ip => &self.ip_policy,
+        }
+    }
+
+    /// Remaining lockout of the account or the client address, if any.
+    ///
+    /// A rejected attempt is recorded but does not extend the lockout.
+    pub fn locked_for(&self, username: &str, ip_address: &str) -> AnyhowResult<Option<Duration>> {
+        let now = Utc::now();
+        let mut remaining = None;
+        for (scope, value) in [(LockoutScope::Account, username), (LockoutScope::Ip, ip_address)] {
+            if let Some(lockout) = self.get_lockout(scope, &scope.subject(value))? {
+                remaining = remaining.max(lockout.remaining(now));
+            }
+        }
+
+        if remaining.is_some() {
+            self.record_attempt(username, ip_address, "locked", now)?;
+        }
+        Ok(remaining)
+    }
+
+    /// Record a failed login and update the lockouts.
+    pub fn record_failure(
+        &self,
+        username: &str,
+        ip_address: &str,
+        reason: &str,
+    ) -> AnyhowResult<()> {
+        let now = Utc::now();
+        self.record_attempt(username, ip_address, reason, now)?;
+
+        for (scope, value) in [(LockoutScope::Account, username), (LockoutScope::Ip, ip_address)] {
+            let subject = scope.subject(value);
+            let current = self.get_lockout(scope, &subject)?;
+            let lockout = self
+                .policy(scope)
+                .register_failure(scope, &subject, current.as_ref(), now);
+
+            if let Some(until) = lockout.locked_until {
+                tracing::warn!(
+                    "Login {} '{}' locked until {} after {} failed attempts",
+                    scope.as_str(),
+                    subject,
+                    format_datetime(until),
+                    lockout.failures
+                );
+            }
+            self.store_lockout(&lockout)?;
+        }
+        Ok(())
+    }
+
+    /// Record a successful login; the failure count of the account starts over.
+    ///
+    /// The count of the address is left alone, so one valid account does not
+    /// help guessing the passwords of others.
+    pub fn record_success(&self, username: &str) -> AnyhowResult<()> {
+        self.db.execute(
+            "DELETE FROM login_lockouts WHERE scope = ? AND subject = ?",
+            &[
+                &LockoutScope::Account.as_str() as &dyn duckdb::ToSql,
+                &LockoutScope::Account.subject(username),
+            ],
+        )
+    }
+
+    /// Accounts and addresses that are currently locked, longest first.
+    pub fn list_lockouts(&self) -> AnyhowResult<Vec<Lockout>> {
+        self.db.query(
+            "SELECT scope, subject, failures, CAST(last_failure_at AS VARCHAR),
+                    CAST(locked_until AS VARCHAR)
+             FROM login_lockouts
+             WHERE locked_until > ?
+             ORDER BY locked_until DESC",
+            &[&format_datetime(Utc::now())],
+            lockout_from_row,
+        )
+    }
+
+    /// Lift the lockout of an account or address and reset its failure count.
+    ///
+    /// Returns whether there was anything to lift.
+    pub fn lift(&self, scope: LockoutScope, value: &str) -> AnyhowResult<bool> {
+        let subject = scope.subject(value);
+        if self.get_lockout(scope, &subject)?.is_none() {
+            return Ok(false);
+        }
+
+        self.db.execute(
+            "DELETE FROM login_lockouts WHERE scope = ? AND subject = ?",
+            &[&scope.as_str() as &dyn duckdb::ToSql, &subject],
+        )?;
+        tracing::info!("Login lockout of {} '{}' lifted", scope.as_str(), subject);
+        Ok(true)
+    }
+
+    /// Recorded failed logins, most recent first.
+    pub fn list_attempts(
+        &self,
+        username: Option<&str>,
+        ip_address: Option<&str>,
+        limit: Option<u64>,
+    ) -> AnyhowResult<Vec<LoginAttempt>> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
+
+        if let Some(username) = username {
+            conditions.push("lower(username) = ?");
+            params.push(Box::new(LockoutScope::Account.subject(username)));
+        }
+        if let Some(ip_address) = ip_address {
+            conditions.push("ip_address = ?");
+            params.push(Box::new(LockoutScope::Ip.subject(ip_address)));
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let limit = limit.unwrap_or(DEFAULT_ATTEMPTS_LIMIT).min(MAX_ATTEMPTS_LIMIT);
+
+        let sql = format!(
+            "SELECT id, CAST(attempted_at AS VARCHAR), username, ip_address, reason
+             FROM login_attempts
+             {}
+             ORDER BY attempted_at DESC
+             LIMIT {}",
+            where_clause, limit
+        );
+
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.query(&sql, &param_refs, |row| {
+            Ok(LoginAttempt {
+                id: row.get(0)?,
+                attempted_at: parse_datetime(&row.get::<_, String>(1)?),
+                username: row.get(2)?,
+                ip_address: row.get(3)?,
+                reason: row.get(4)?,
+            })
+        })
+    }
+
+    fn record_attempt(
+        &self,
+        username: &str,
+        ip_address: &str,
+        reason: &str,
+        at: DateTime<Utc>,
+    ) -> AnyhowResult<()> {
+        self.db.execute(
+            "INSERT INTO login_attempts (id, attempted_at, username, ip_address, reason)
+             VALUES (?, ?, ?, ?, ?)",
+            &[
+                &format!("att_{}", uuid::Uuid::new_v4().simple()) as &dyn duckdb::ToSql,
+                &format_datetime(at),
+                &username.trim(),
+                &ip_address,
+                &reason,
+            ],
+        )
+    }
+
+    fn get_lockout(&self, scope: LockoutScope, subject: &str) -> AnyhowResult<Option<Lockout>> {
+        let result = self.db.query_row(
+            "SELECT scope, subject, failures, CAST(last_failure_at AS VARCHAR),
+                    CAST(locked_until AS VARCHAR)
+             FROM login_lockouts WHERE scope = ? AND subject = ?",
+            &[&scope.as_str() as &dyn duckdb::ToSql, &subject],
+            lockout_from_row,
+        );
+
+        match result {
+            Ok(lockout) => Ok(Some(lockout)),
+            Err(e) if is_no_rows(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn store_lockout(&self, lockout: &Lockout) -> AnyhowResult<()> {
+        self.db.execute(
+            "INSERT INTO login_lockouts (scope, subject, failures, last_failure_at, locked_until)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (scope, subject) DO UPDATE SET
+                failures = excluded.failures,
+                last_failure_at = excluded.last_failure_at,
+                locked_until = excluded.locked_until",
+            &[
+                &lockout.scope.as_str() as &dyn duckdb::ToSql,
+                &lockout.subject,
+                &(lockout.failures as i32),
+                &format_datetime(lockout.last_failure_at),
+                &lockout.locked_until.map(format_datetime),
+            ],
+        )
+    }
+}
+
+fn lockout_from_row(row: &duckdb::Row) -> duckdb::Result<Lockout> {
+    Ok(Lockout {
+        scope: LockoutScope::from_str(&row.get::<_, String>(0)?).unwrap_or(LockoutScope::Account),
+        subject: row.get(1)?,
+        failures: row.get::<_, i32>(2)? as u32,
+        last_failure_at: parse_datetime(&row.get::<_, String>(3)?),
+        locked_until: row.get::<_, Option<String>>(4)?.map(|s| parse_datetime(&s)),
+    })
+}
+
+/// Helper: Format datetime for DuckDB.
+fn format_datetime(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+}
+
+/// Helper: Parse datetime from DuckDB.
+fn parse_datetime(s: &str) -> DateTime<Utc> {
+    // `CAST(... AS VARCHAR)` drops trailing zeros of the fraction
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .map(|ndt| ndt.and_utc())
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_datetime_trimmed_fraction() {
+        // DuckDB renders 12:00:00.120000 as "12:00:00.12"
+        let at = parse_datetime("2026-01-01 12:00:00.12");
+        assert_eq!(at.timestamp_subsec_millis(), 120);
+        assert_eq!(
+            parse_datetime("2026-01-01 12:00:00").to_rfc3339(),
+            "2026-01-01T12:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_lockout_after_failures() {
+        let throttle = LoginThrottle::new(Arc::new(Database::in_memory()));
+        for _ in 0..5 {
+            assert!(throttle.locked_for("jan", "10.0.0.1").unwrap().is_none());
+            throttle.record_failure("jan", "10.0.0.1", "invalid_credentials").unwrap();
+        }
+
+        // The account is locked from any address
+        let remaining = throttle.locked_for("jan", "10.0.0.2").unwrap().unwrap();
+        assert!(remaining > Duration::zero() && remaining <= Duration::minutes(1));
+        assert!(throttle.locked_for("piet", "10.0.0.2").unwrap().is_none());
+    }
+}
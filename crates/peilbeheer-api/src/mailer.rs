@@ -0,0 +1,118 @@
+//! Outgoing e-mail.
+//!
+//! Mail goes through a [`Mailer`], chosen at startup by [`from_env`]:
+//! with `MAIL_WEBHOOK_URL` set, messages are POSTed as JSON to a mail relay
+//! (e.g. a Power Automate flow or an internal mail API); otherwise they are
+//! only written to the log, which is meant for development.
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Endpoint of the mail relay
+const MAIL_WEBHOOK_URL_ENV: &str = "MAIL_WEBHOOK_URL";
+/// Bearer token for the mail relay (optional)
+const MAIL_WEBHOOK_TOKEN_ENV: &str = "MAIL_WEBHOOK_TOKEN";
+/// Request timeout (seconds)
+const MAIL_TIMEOUT_SECS: u64 = 10;
+
+/// An e-mail message.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    /// Plain text body
+    pub body: String,
+}
+
+/// Mail delivery errors.
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("Mail relay returned HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+}
+
+/// Delivers e-mail messages.
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), MailError>>;
+}
+
+/// Writes messages to the log instead of sending them.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(async move {
+            tracing::info!(
+                "E-mail to {} (not sent, no mail relay configured): {}\n{}",
+                message.to,
+                message.subject,
+                message.body
+            );
+            Ok(())
+        })
+    }
+}
+
+/// POSTs messages as JSON to a mail relay.
+pub struct WebhookMailer {
+    url: String,
+    token: Option<String>,
+    http_client: HttpClient,
+}
+
+impl WebhookMailer {
+    /// Create a mailer for the relay at `url`.
+    pub fn new(url: String, token: Option<String>) -> Self {
+        let http_client =
+            HttpClient::shared().with_timeout(StdDuration::from_secs(MAIL_TIMEOUT_SECS));
+
+        Self {
+            url,
+            token,
+            http_client,
+        }
+    }
+}
+
+impl Mailer for WebhookMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(async move {
+            let mut request = self.http_client.post(&self.url).json(message);
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+
+            let response = self.http_client.send(request).await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(MailError::Status {
+                    status: status.as_u16(),
+                    body: response.text().await.unwrap_or_default(),
+                });
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Mailer configured by the environment.
+pub fn from_env() -> Arc<dyn Mailer> {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+    match env(MAIL_WEBHOOK_URL_ENV) {
+        Some(url) => Arc::new(WebhookMailer::new(url, env(MAIL_WEBHOOK_TOKEN_ENV))),
+        None => {
+            tracing::warn!(
+                "No mail relay configured ({}), e-mail is only logged",
+                MAIL_WEBHOOK_URL_ENV
+            );
+            Arc::new(LogMailer)
+        }
+    }
+}
@@ -1,37 +1,85 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
     extract::Extension,
+    middleware,
     routing::{delete, get, post, put},
     Router,
 };
-use peilbeheer_core::FewsConfig;
+use peilbeheer_core::{DhydroClient, FewsConfig, FewsDocumentFormat, FewsSyncConfig, Permission};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod ahn_client;
+mod alert_evaluator;
 mod alert_service;
 mod arcgis_client;
+mod audit_service;
+mod auth_backend;
 mod auth_service;
+mod bodemkaart_client;
 mod config;
 mod dashboard_service;
 mod db;
+mod dhydro_catalog;
+mod dhydro_poller;
 mod energyzero_client;
 mod error;
 mod fews_client;
+mod fews_export;
+mod fews_pi_xml;
+mod http_resilience;
 mod hydronet_client;
+mod knmi_client;
+mod ldap;
+mod lizard_client;
+mod login_throttle;
+mod mailer;
+mod mqtt_ingest;
+mod neerslag_fallback;
+mod oidc;
 mod optimization_service;
+mod password_reset;
+mod pv_forecast_client;
+mod quarter_price_client;
+mod rate_limit;
+mod realtime_simulatie;
 mod routes;
+mod rws_client;
+mod scada_bridge;
 mod scenario_service;
 mod timeseries_service;
+mod totp;
+mod webhook_client;
 mod websocket_service;
+mod wfs_client;
 
+use ahn_client::{AhnClient, AhnConfig};
+use alert_evaluator::{AlertEvaluator, AlertEvaluatorConfig};
 use alert_service::AlertService;
+use audit_service::AuditService;
 use auth_service::AuthService;
+use bodemkaart_client::{BodemkaartClient, BodemkaartConfig};
 use dashboard_service::DashboardService;
 use db::Database;
+use dhydro_catalog::ModelCatalogService;
+use dhydro_poller::{DhydroPoller, DhydroPollerConfig};
+use energyzero_client::EnergyPriceStore;
 use fews_client::{FewsClient, FewsSyncService};
+use knmi_client::{KnmiClient, KnmiConfig, KnmiForecastSync};
+use lizard_client::{LizardClient, LizardConfig, LizardGrondwaterSync};
+use login_throttle::LoginThrottle;
+use mqtt_ingest::{MqttConfig, MqttIngest};
+use neerslag_fallback::{NeerslagFallback, NeerslagFallbackConfig};
 use optimization_service::OptimizationService;
+use password_reset::{PasswordResetConfig, PasswordResetService};
+use pv_forecast_client::{PvForecastClient, PvForecastConfig};
+use quarter_price_client::{QuarterPriceClient, QuarterPriceConfig};
+use realtime_simulatie::{RealtimeConfig, RealtimeSimulatie};
+use rws_client::{RwsClient, RwsConfig, RwsWaterinfoSync};
+use scada_bridge::{ScadaClient, ScadaConfig};
 use scenario_service::ScenarioService;
 use timeseries_service::TimeSeriesService;
 use websocket_service::WebSocketServer;
@@ -78,13 +126,7 @@ async fn main() -> anyhow::Result<()> {
     if asset_count == 0 {
         tracing::info!("Asset cache leeg, ophalen van alle ArcGIS-lagen...");
         for layer in &config.arcgis_layers {
-            match arcgis_client::fetch_layer_assets(
-                &layer.service_name,
-                layer.layer_id,
-                &layer.layer_type,
-            )
-            .await
-            {
+            match wfs_client::fetch_layer(layer).await {
                 Ok(assets) => match db.write_asset_registraties(&assets) {
                     Ok(n) => tracing::info!("Auto-sync {}: {n} assets gecached", layer.layer_type),
                     Err(e) => tracing::warn!("Auto-sync {} schrijven mislukt: {e}", layer.layer_type),
@@ -96,9 +138,22 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Asset cache bevat {asset_count} registraties");
     }
 
-    // Auto-sync peilgebieden: ophalen van ArcGIS → opslaan als GeoJSON → laden in DuckDB
+    // Auto-sync peilgebieden: uit een GeoPackage/Shapefile als die is geconfigureerd,
+    // anders ophalen van ArcGIS → opslaan als GeoJSON → laden in DuckDB
     let peilgebied_count = db.get_peilgebied_count().unwrap_or(0);
-    if peilgebied_count == 0 {
+    if peilgebied_count == 0
+        && let Some(import_path) = &config.peilgebieden_import_path
+    {
+        tracing::info!("Peilgebieden laden vanuit {import_path} naar DuckDB...");
+        match db.load_peilgebieden_from_geopackage(
+            import_path,
+            config.peilgebieden_import_layer.as_deref(),
+            &config.peilgebieden_attribuut_mapping,
+        ) {
+            Ok(import) => tracing::info!("{} peilgebieden geladen in DuckDB", import.geladen),
+            Err(e) => tracing::warn!("Peilgebieden importeren mislukt: {e}"),
+        }
+    } else if peilgebied_count == 0 {
         let geojson_path = std::path::Path::new(&config.peilgebieden_geojson_path);
 
         // Stap 1: Als het bestand nog niet bestaat, ophalen van ArcGIS
@@ -140,12 +195,95 @@ async fn main() -> anyhow::Result<()> {
     let db_arc = Arc::new(db);
     let scenario_service = Arc::new(ScenarioService::new(db_arc.clone()));
     let auth_service = Arc::new(AuthService::with_default_config(db_arc.clone())?);
+    let audit_service = Arc::new(AuditService::new(db_arc.clone()));
+    let mailer = mailer::from_env();
+    let password_reset_service = Arc::new(PasswordResetService::new(
+        auth_service.clone(),
+        mailer.clone(),
+        PasswordResetConfig::default(),
+    ));
+    let login_throttle = Arc::new(LoginThrottle::new(db_arc.clone()));
     let ws_server = Arc::new(WebSocketServer::new());
-    let alert_service = Arc::new(AlertService::new(db_arc.clone(), ws_server.clone()));
+    let alert_service =
+        Arc::new(AlertService::new(db_arc.clone(), ws_server.clone()).with_mailer(mailer));
     alert_service.initialize().await?;
+    let escalation_interval_secs = std::env::var("ALERT_ESCALATION_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60);
+    alert_service.start_escalation_worker(std::time::Duration::from_secs(escalation_interval_secs));
     let timeseries_service = Arc::new(TimeSeriesService::new(db_arc.clone()));
+    let alert_evaluator = Arc::new(AlertEvaluator::new(
+        alert_service.clone(),
+        timeseries_service.clone(),
+        AlertEvaluatorConfig::default(),
+    ));
+    alert_evaluator.start();
+    let dhydro_client = config
+        .dhydro
+        .is_configured()
+        .then(|| Arc::new(tokio::sync::Mutex::new(DhydroClient::new(config.dhydro.clone()))));
+    let model_catalog = Arc::new(ModelCatalogService::new(db_arc.clone(), dhydro_client.clone()));
+    model_catalog.start();
+    if let Some(dhydro_client) = dhydro_client.clone() {
+        let dhydro_poller = Arc::new(DhydroPoller::new(
+            dhydro_client,
+            scenario_service.clone(),
+            timeseries_service.clone(),
+            ws_server.clone(),
+            DhydroPollerConfig::default(),
+        ));
+        dhydro_poller.start();
+    }
+    let knmi_config = KnmiConfig::default();
+    if !knmi_config.is_configured() {
+        tracing::info!("KNMI disabled (KNMI_API_KEY not set), precipitation from fallback sources");
+    }
+    let knmi_client = Arc::new(
+        KnmiClient::new(db_arc.clone(), knmi_config).with_fallback(NeerslagFallback::new(
+            db_arc.clone(),
+            NeerslagFallbackConfig::default(),
+        )),
+    );
+    let knmi_forecast_sync = Arc::new(KnmiForecastSync::new(
+        knmi_client.clone(),
+        timeseries_service.clone(),
+    ));
+    knmi_forecast_sync.start();
+    let rws_client = Arc::new(RwsClient::new(RwsConfig::default()));
+    let rws_sync = Arc::new(RwsWaterinfoSync::new(
+        rws_client.clone(),
+        timeseries_service.clone(),
+    ));
+    rws_sync.start();
+    let lizard_client = Arc::new(LizardClient::new(LizardConfig::default(), db_arc.clone()));
+    let lizard_sync = Arc::new(LizardGrondwaterSync::new(
+        lizard_client.clone(),
+        timeseries_service.clone(),
+    ));
+    lizard_sync.start();
+    let mqtt_ingest = Arc::new(MqttIngest::new(
+        MqttConfig::from_env()?,
+        timeseries_service.clone(),
+        ws_server.clone(),
+    ));
+    mqtt_ingest.start();
+    let scada_client = Arc::new(ScadaClient::new(ScadaConfig::from_env()?));
+    let bodemkaart_client = Arc::new(BodemkaartClient::new(
+        BodemkaartConfig::default(),
+        db_arc.clone(),
+    ));
+    let ahn_client = Arc::new(AhnClient::new(AhnConfig::default(), db_arc.clone()));
     let dashboard_service = Arc::new(DashboardService::new(db_arc.clone()));
-    let optimization_service = Arc::new(OptimizationService::new(db_arc.clone(), ws_server.clone()));
+    let energy_prices = Arc::new(EnergyPriceStore::new(db_arc.clone()));
+    energy_prices.start();
+    let quarter_prices = Arc::new(QuarterPriceClient::new(QuarterPriceConfig::default()));
+    let pv_forecast = Arc::new(PvForecastClient::new(PvForecastConfig::default()));
+    let optimization_service = Arc::new(OptimizationService::new(
+        db_arc.clone(),
+        ws_server.clone(),
+        energy_prices.clone(),
+    ));
 
     // Initialize Fews client (if configured)
     let fews_config = FewsConfig {
@@ -156,9 +294,51 @@ async fn main() -> anyhow::Result<()> {
             .unwrap_or_else(|_| "30".to_string())
             .parse()
             .unwrap_or(30),
+        document_format: std::env::var("FEWS_DOCUMENT_FORMAT")
+            .ok()
+            .and_then(|v| FewsDocumentFormat::from_str(&v)),
+        write_module_instance_id: std::env::var("FEWS_WRITE_MODULE_INSTANCE").ok(),
     };
     let fews_client = Arc::new(FewsClient::new(fews_config.clone()));
-    let fews_sync_service = Arc::new(FewsSyncService::new(fews_client.clone(), vec![]));
+    let fews_sync_configs: Vec<serde_json::Value> = match std::env::var("FEWS_SYNC_CONFIGS") {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring FEWS_SYNC_CONFIGS: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    let fews_sync_configs = fews_sync_configs
+        .into_iter()
+        .filter_map(|v| match serde_json::from_value::<FewsSyncConfig>(v) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                tracing::warn!("Ignoring malformed Fews sync config: {}", e);
+                None
+            }
+        })
+        .filter(|c| match c.validate() {
+            Ok(()) => true,
+            Err(errors) => {
+                tracing::warn!("Ignoring Fews sync config of {}: {}", c.peilgebied_id, errors.join(", "));
+                false
+            }
+        })
+        .collect();
+    let fews_sync_service = Arc::new(FewsSyncService::new(
+        fews_client.clone(),
+        timeseries_service.clone(),
+        db_arc.clone(),
+        fews_sync_configs,
+    ));
+    fews_sync_service.start_scheduler();
+    let realtime_simulatie = Arc::new(RealtimeSimulatie::new(
+        db_arc.clone(),
+        timeseries_service.clone(),
+        fews_client.clone(),
+        fews_sync_service.clone(),
+        RealtimeConfig::default(),
+    ));
+    realtime_simulatie.start();
 
     // Ensure default admin user exists
     // Only do this if users table exists (it's created in migrations)
@@ -176,6 +356,7 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Scenario service initialized");
     tracing::info!("Authentication service initialized");
+    tracing::info!("Audit log service initialized");
     tracing::info!("WebSocket server initialized (ID: {})", ws_server.server_id());
     tracing::info!("Alert service initialized");
     tracing::info!("Time series service initialized");
@@ -184,86 +365,166 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Fews client initialized (filter: {})", fews_config.filter_id);
 
     // Build API router
-    let api = Router::new()
+    let api = api_router().layer(middleware::from_fn(audit_service::record_mutations));
+
+    // Combine API with static file serving
+    let app = Router::new()
+        .nest("/api", api)
+        .fallback_service(ServeDir::new("static").append_index_html_on_directories(true))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any),
+        )
+        .layer(Extension(db_arc))
+        .layer(Extension(Arc::new(config.clone())))
+        .layer(Extension(scenario_service))
+        .layer(Extension(dhydro_client))
+        .layer(Extension(model_catalog))
+        .layer(Extension(auth_service))
+        .layer(Extension(audit_service))
+        .layer(Extension(password_reset_service))
+        .layer(Extension(login_throttle))
+        .layer(Extension(ws_server))
+        .layer(Extension(Arc::new(routes::simulatie::LopendeSimulaties::default())))
+        .layer(Extension(fews_client))
+        .layer(Extension(fews_sync_service))
+        .layer(Extension(realtime_simulatie))
+        .layer(Extension(knmi_client))
+        .layer(Extension(knmi_forecast_sync))
+        .layer(Extension(rws_client))
+        .layer(Extension(rws_sync))
+        .layer(Extension(lizard_client))
+        .layer(Extension(lizard_sync))
+        .layer(Extension(scada_client))
+        .layer(Extension(bodemkaart_client))
+        .layer(Extension(ahn_client))
+        .layer(Extension(alert_service))
+        .layer(Extension(alert_evaluator))
+        .layer(Extension(timeseries_service))
+        .layer(Extension(dashboard_service))
+        .layer(Extension(optimization_service))
+        .layer(Extension(energy_prices))
+        .layer(Extension(quarter_prices))
+        .layer(Extension(pv_forecast));
+
+    // Start server
+    let addr = format!("{}:{}", config.host, config.port);
+    tracing::info!("Listening on http://{}", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+
+    Ok(())
+}
+
+/// Routes of the API, grouped by the permission they require.
+///
+/// The public routes and the routes whose handlers authorize the caller
+/// themselves (own account, sessions, 2FA, API keys, alerts, audit log) are
+/// not guarded here.
+fn api_router() -> Router {
+    let require =
+        |permission| middleware::from_fn_with_state(permission, routes::auth::require_permission);
+
+    let public = Router::new()
         .route("/health", get(routes::health::health_check))
-        .route("/gemalen", get(routes::gemalen::list_gemalen))
-        .route("/gemalen/geojson", get(routes::gemalen::get_geojson))
-        .route("/gemalen/sync", post(routes::gemalen::sync_gemalen))
-        .route("/gemalen/{code}", get(routes::gemalen::get_gemaal))
-        .route("/status", get(routes::status::get_status_summary))
-        .route("/status/generate", post(routes::status::generate_status))
-        .route("/simulatie", post(routes::simulatie::run_simulatie))
-        .route("/assets/layers", get(routes::assets::list_layers))
-        .route("/assets/geojson", get(routes::assets::get_assets_geojson))
-        .route("/assets/sync", post(routes::assets::sync_assets))
-        .route("/peilgebieden/geojson", get(routes::peilgebieden::get_peilgebieden_geojson))
-        .route("/peilgebieden/mapping", get(routes::peilgebieden::get_peilgebied_mapping))
-        .route("/peilgebieden/sync", post(routes::peilgebieden::sync_peilgebieden))
-        .route("/energieprijzen", get(routes::optimalisatie::get_energieprijzen))
-        .route("/optimalisatie", post(routes::optimalisatie::run_optimalisatie))
-        // Optimization job queue routes
-        .route("/optimization/jobs", get(routes::optimalisatie::list_jobs))
-        .route("/optimization/jobs", post(routes::optimalisatie::create_job))
-        .route("/optimization/jobs/{id}", get(routes::optimalisatie::get_job))
-        .route("/optimization/jobs/{id}/cancel", post(routes::optimalisatie::cancel_job))
-        .route("/optimization/queue/stats", get(routes::optimalisatie::get_queue_stats))
-        .route("/optimization/forecast", get(routes::optimalisatie::get_price_forecast))
-        .route("/optimization/forecast/refresh", post(routes::optimalisatie::refresh_price_forecast))
         // Authentication routes
         .route("/auth/login", post(routes::auth::login))
         .route("/auth/logout", post(routes::auth::logout))
+        .route("/auth/refresh", post(routes::auth::refresh))
+        .route("/auth/password-reset/request", post(routes::auth::request_password_reset))
+        .route("/auth/password-reset/confirm", post(routes::auth::confirm_password_reset))
+        .route("/auth/oidc/login", get(routes::auth::oidc_login))
+        .route("/auth/oidc/callback", get(routes::auth::oidc_callback))
         .route("/auth/me", get(routes::auth::get_current_user))
-        .route("/auth/users", get(routes::auth::list_users))
-        .route("/auth/users", post(routes::auth::create_user))
-        .route("/auth/users/{id}", get(routes::auth::get_user))
-        .route("/auth/users/{id}", post(routes::auth::update_user))
-        .route("/auth/users/{id}/delete", post(routes::auth::delete_user))
-        .route("/auth/users/{id}/password", post(routes::auth::change_password))
-        .route("/auth/users/{id}/permissions", get(routes::auth::get_user_permissions))
-        // Scenario management routes
-        .route("/scenarios", get(routes::scenarios::list_scenarios))
-        .route("/scenarios", post(routes::scenarios::create_scenario))
-        .route("/scenarios/{id}", get(routes::scenarios::get_scenario))
-        .route("/scenarios/{id}", put(routes::scenarios::update_scenario))
-        .route("/scenarios/{id}", delete(routes::scenarios::delete_scenario))
-        .route("/scenarios/{id}/execute", post(routes::scenarios::execute_scenario))
-        .route("/scenarios/{id}/results", get(routes::scenarios::get_scenario_results))
-        .route("/scenarios/{id}/clone", post(routes::scenarios::clone_scenario))
-        // WebSocket routes
-        .route("/ws", get(routes::websocket::websocket_handler))
-        .route("/ws/status", get(routes::websocket::ws_status))
-        // Fews integration routes
-        .route("/fews/timeseries", get(routes::fews::get_time_series))
-        .route("/fews/locations", get(routes::fews::get_locations))
-        .route("/fews/parameters", get(routes::fews::get_parameters))
-        .route("/fews/modules", get(routes::fews::get_module_instances))
-        .route("/fews/sync", post(routes::fews::sync_fews))
-        .route("/fews/ping", get(routes::fews::ping_fews))
-        .route("/fews/status", get(routes::fews::fews_status))
-        .route("/fews/config", get(routes::fews::get_sync_configs))
+        .route("/auth/users/{id}/2fa/reset", post(routes::auth::reset_user_totp))
+        .route("/auth/users/{id}/sessions", get(routes::auth::list_user_sessions))
+        .route("/auth/users/{id}/sessions", delete(routes::auth::revoke_all_user_sessions))
+        .route("/auth/users/{id}/sessions/{session_id}", delete(routes::auth::revoke_user_session))
+        .route("/auth/sessions", get(routes::auth::list_sessions))
+        .route("/auth/sessions/revoke-others", post(routes::auth::revoke_other_sessions))
+        .route("/auth/sessions/{id}", delete(routes::auth::revoke_session))
+        .route("/auth/2fa/enroll", post(routes::auth::enroll_totp))
+        .route("/auth/2fa/confirm", post(routes::auth::confirm_totp))
+        .route("/auth/2fa/disable", post(routes::auth::disable_totp))
+        .route("/auth/2fa/recovery-codes", post(routes::auth::regenerate_recovery_codes))
+        .route("/auth/2fa/policy", get(routes::auth::get_two_factor_policy))
+        .route("/auth/2fa/policy", put(routes::auth::set_two_factor_policy))
+        .route("/auth/api-keys", get(routes::auth::list_api_keys))
+        .route("/auth/api-keys", post(routes::auth::create_api_key))
+        .route("/auth/api-keys/{id}", delete(routes::auth::revoke_api_key))
+        .route("/audit", get(routes::audit::list_audit_log))
         // Alert rules routes
         .route("/alerts/rules", get(routes::alerts::list_rules))
         .route("/alerts/rules", post(routes::alerts::create_rule))
-        .route("/alerts/rules/categories", get(routes::alerts::get_categories))
-        .route("/alerts/rules/operators", get(routes::alerts::get_operators))
         .route("/alerts/rules/{id}", get(routes::alerts::get_rule))
         .route("/alerts/rules/{id}", put(routes::alerts::update_rule))
         .route("/alerts/rules/{id}", delete(routes::alerts::delete_rule))
+        .route("/alerts/rules/{id}/events", get(routes::alerts::list_rule_events))
         .route("/alerts/rules/evaluate", post(routes::alerts::evaluate_rules))
+        .route("/alerts/evaluator/metrics", get(routes::alerts::get_evaluator_metrics))
+        // Maintenance window routes
+        .route("/alerts/maintenance", get(routes::alerts::list_maintenance_windows))
+        .route("/alerts/maintenance", post(routes::alerts::create_maintenance_window))
+        .route("/alerts/maintenance/{id}", get(routes::alerts::get_maintenance_window))
+        .route("/alerts/maintenance/{id}", put(routes::alerts::update_maintenance_window))
+        .route("/alerts/maintenance/{id}", delete(routes::alerts::delete_maintenance_window))
         // Alert instances routes
         .route("/alerts", get(routes::alerts::list_alerts))
         .route("/alerts/stats", get(routes::alerts::get_alert_stats))
+        .route("/alerts/stats/trend", get(routes::alerts::get_alert_trend))
         .route("/alerts/{id}", get(routes::alerts::get_alert))
         .route("/alerts/{id}/acknowledge", post(routes::alerts::acknowledge_alert))
         .route("/alerts/{id}/resolve", post(routes::alerts::resolve_alert))
-        // Time series routes
-        .route("/timeseries", get(routes::timeseries::list_series))
-        .route("/timeseries/query", get(routes::timeseries::query_timeseries))
-        .route("/timeseries/write", post(routes::timeseries::write_timeseries))
-        .route("/timeseries/register", post(routes::timeseries::register_series))
-        .route("/timeseries/{location_id}/{parameter}", get(routes::timeseries::get_series_metadata))
-        .route("/timeseries/levels", get(routes::timeseries::get_aggregation_levels))
-        .route("/timeseries/functions", get(routes::timeseries::get_aggregation_functions))
+        .route("/alerts/{id}/escalation", get(routes::alerts::get_alert_escalation))
+        .route("/alerts/{id}/comments", get(routes::alerts::list_alert_comments))
+        .route("/alerts/{id}/comments", post(routes::alerts::add_alert_comment))
+        .route("/alerts/{id}/history", get(routes::alerts::get_alert_history));
+
+    let authenticated = Router::new()
+        .route("/auth/users/{id}/password", post(routes::auth::change_password))
+        .route_layer(middleware::from_fn(routes::auth::require_authentication));
+
+    // User management routes
+    let users_read = Router::new()
+        .route("/auth/users", get(routes::auth::list_users))
+        .route("/auth/users/{id}", get(routes::auth::get_user))
+        .route("/auth/users/{id}/permissions", get(routes::auth::get_user_permissions))
+        .route_layer(require(Permission::UsersRead));
+
+    let users_create = Router::new()
+        .route("/auth/users", post(routes::auth::create_user))
+        .route_layer(require(Permission::UsersCreate));
+
+    let users_update = Router::new()
+        .route("/auth/users/{id}", post(routes::auth::update_user))
+        .route("/auth/users/{id}/oidc", put(routes::auth::link_oidc_identity))
+        .route("/auth/lockouts", get(routes::auth::list_lockouts))
+        .route("/auth/login-attempts", get(routes::auth::list_login_attempts))
+        .route("/auth/lockouts/{scope}/{subject}", delete(routes::auth::lift_lockout))
+        .route_layer(require(Permission::UsersUpdate));
+
+    let users_delete = Router::new()
+        .route("/auth/users/{id}/delete", post(routes::auth::delete_user))
+        .route_layer(require(Permission::UsersDelete));
+
+    let alerts_read = Router::new()
+        .route("/alerts/rules/categories", get(routes::alerts::get_categories))
+        .route("/alerts/rules/operators", get(routes::alerts::get_operators))
+        .route_layer(require(Permission::AlertsRead));
+
+    let system_status = Router::new()
+        .route("/status", get(routes::status::get_status_summary))
+        .route("/optimization/queue/stats", get(routes::optimalisatie::get_queue_stats))
+        .route("/fews/ping", get(routes::fews::ping_fews))
+        .route("/fews/status", get(routes::fews::fews_status))
+        .route("/fews/config", get(routes::fews::get_sync_configs))
+        .route("/fews/sync/runs", get(routes::fews::list_sync_runs))
+        // WebSocket routes
+        .route("/ws", get(routes::websocket::websocket_handler))
+        .route("/ws/status", get(routes::websocket::ws_status))
         // Dashboard routes
         .route("/dashboard/kpi", get(routes::dashboard::get_kpi))
         .route("/dashboard/health", get(routes::dashboard::get_health))
@@ -272,36 +533,171 @@ async fn main() -> anyhow::Result<()> {
         .route("/dashboard/gemalen", get(routes::dashboard::get_gemaal_summary))
         .route("/dashboard/chart", get(routes::dashboard::get_chart))
         .route("/dashboard/widgets/system", get(routes::dashboard::get_system_overview_widget))
-        .route("/dashboard/widgets/gemalen", get(routes::dashboard::get_gemaal_status_widget));
+        .route("/dashboard/widgets/gemalen", get(routes::dashboard::get_gemaal_status_widget))
+        .route_layer(require(Permission::SystemStatus));
 
-    // Combine API with static file serving
-    let app = Router::new()
-        .nest("/api", api)
-        .fallback_service(ServeDir::new("static").append_index_html_on_directories(true))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
+    let assets_read = Router::new()
+        .route("/gemalen", get(routes::gemalen::list_gemalen))
+        .route("/gemalen/geojson", get(routes::gemalen::get_geojson))
+        .route("/gemalen/{code}", get(routes::gemalen::get_gemaal))
+        .route("/gemalen/{code}/energiecontract", get(routes::gemalen::get_energiecontract))
+        .route("/assets/layers", get(routes::assets::list_layers))
+        .route("/assets/geojson", get(routes::assets::get_assets_geojson))
+        .route("/peilgebieden/geojson", get(routes::peilgebieden::get_peilgebieden_geojson))
+        .route("/peilgebieden/mapping", get(routes::peilgebieden::get_peilgebied_mapping))
+        .route("/peilgebieden/{code}/bodem", get(routes::bodem::get_bodem))
+        .route("/spatial/lookup", get(routes::spatial::lookup))
+        .route("/scada/setpoints/{gemaal}", get(routes::scada::get_setpoints))
+        // Fews integration routes
+        .route("/fews/timeseries", get(routes::fews::get_time_series))
+        .route("/fews/locations", get(routes::fews::get_locations))
+        .route("/fews/parameters", get(routes::fews::get_parameters))
+        .route("/fews/modules", get(routes::fews::get_module_instances))
+        .route("/fews/mappings", get(routes::fews::list_mappings))
+        .route("/fews/mappings/{id}", get(routes::fews::get_mapping))
+        .route("/fews/export/simulation", post(routes::fews::export_simulation))
+        .route("/fews/export/optimization/{job_id}", get(routes::fews::export_optimization))
+        // Time series routes
+        .route("/timeseries", get(routes::timeseries::list_series))
+        .route("/timeseries/query", get(routes::timeseries::query_timeseries))
+        .route("/timeseries/{location_id}/{parameter}", get(routes::timeseries::get_series_metadata))
+        .route("/timeseries/levels", get(routes::timeseries::get_aggregation_levels))
+        .route("/timeseries/functions", get(routes::timeseries::get_aggregation_functions))
+        .route_layer(require(Permission::AssetsRead));
+
+    let assets_update = Router::new()
+        .route("/timeseries/write", post(routes::timeseries::write_timeseries))
+        .route("/timeseries/register", post(routes::timeseries::register_series))
+        .route("/timeseries/{location_id}/{parameter}/expected-interval", put(routes::timeseries::set_expected_interval))
+        .route("/gemalen/{code}/energiecontract", put(routes::gemalen::put_energiecontract))
+        .route("/fews/mappings", post(routes::fews::create_mapping))
+        .route("/fews/mappings/{id}", put(routes::fews::update_mapping))
+        .route("/fews/mappings/{id}", delete(routes::fews::delete_mapping))
+        .route_layer(require(Permission::AssetsUpdate));
+
+    let assets_sync = Router::new()
+        .route("/gemalen/sync", post(routes::gemalen::sync_gemalen))
+        .route("/status/generate", post(routes::status::generate_status))
+        .route("/assets/sync", post(routes::assets::sync_assets))
+        .route("/peilgebieden/sync", post(routes::peilgebieden::sync_peilgebieden))
+        .route("/peilgebieden/{code}/bodem", post(routes::bodem::bepaal_bodem))
+        .route("/peilgebieden/{code}/maaiveld", post(routes::maaiveld::bepaal_maaiveld))
+        .route("/ahn/maaiveld/sync", post(routes::maaiveld::sync_maaiveld))
+        .route("/fews/sync", post(routes::fews::sync_fews))
+        .route("/fews/sync/{peilgebied_id}", post(routes::fews::run_peilgebied_sync))
+        .route("/fews/timeseries/optimization", post(routes::fews::write_optimization_result))
+        .route("/knmi/verwachting/sync", post(routes::knmi::sync_weer_verwachting))
+        .route("/rws/waterstanden/sync", post(routes::rws::sync_waterstanden))
+        .route("/lizard/grondwater/sync", post(routes::lizard::sync_grondwater))
+        .route_layer(require(Permission::AssetsSync));
+
+    // Pump schedules are written to the process automation
+    let pumps_control = Router::new()
+        .route("/scada/setpoints/{gemaal}", post(routes::scada::write_setpoints))
+        .route_layer(require(Permission::PumpsControl));
+
+    // Scenario management routes
+    let scenarios_read = Router::new()
+        .route("/scenarios", get(routes::scenarios::list_scenarios))
+        .route("/scenarios/{id}", get(routes::scenarios::get_scenario))
+        .route("/dhydro/models", get(routes::dhydro::list_models))
+        .route("/dhydro/models/{id}", get(routes::dhydro::get_model))
+        .route("/knmi/regen/{code}", get(routes::knmi::get_regen_verwachting))
+        .route("/knmi/regenscenario", get(routes::knmi::get_regenscenario))
+        .route("/timeseries/regenscenario", get(routes::timeseries::get_historisch_regenscenario))
+        .route("/ontwerpbuien", get(routes::ontwerpbuien::list_ontwerpbuien))
+        .route("/ontwerpbuien/{id}", get(routes::ontwerpbuien::get_ontwerpbui))
+        .route("/ontwerpbuien/{id}/regenscenario", get(routes::ontwerpbuien::get_regenscenario))
+        .route("/knmi/verwachting/{code}", get(routes::knmi::get_weer_verwachting))
+        .route("/rws/waterstanden/{locatie}", get(routes::rws::get_waterstanden))
+        .route("/lizard/grondwater/{peilgebied}", get(routes::lizard::get_grondwaterreeksen))
+        .route(
+            "/simulatie/topologie-voorstel",
+            get(routes::simulatie::get_topologie_voorstel),
         )
-        .layer(Extension(db_arc))
-        .layer(Extension(Arc::new(config.clone())))
-        .layer(Extension(scenario_service))
-        .layer(Extension(auth_service))
-        .layer(Extension(ws_server))
-        .layer(Extension(fews_client))
-        .layer(Extension(fews_sync_service))
-        .layer(Extension(alert_service))
-        .layer(Extension(timeseries_service))
-        .layer(Extension(dashboard_service))
-        .layer(Extension(optimization_service));
+        .route(
+            "/simulatie/topologie/validatie",
+            post(routes::simulatie::valideer_topologie),
+        )
+        .route("/simulatie/realtime", get(routes::simulatie::get_realtime))
+        .route_layer(require(Permission::ScenariosRead));
 
-    // Start server
-    let addr = format!("{}:{}", config.host, config.port);
-    tracing::info!("Listening on http://{}", addr);
+    let scenarios_create = Router::new()
+        .route("/scenarios", post(routes::scenarios::create_scenario))
+        .route("/scenarios/{id}/clone", post(routes::scenarios::clone_scenario))
+        .route_layer(require(Permission::ScenariosCreate));
 
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    let scenarios_update = Router::new()
+        .route("/scenarios/{id}", put(routes::scenarios::update_scenario))
+        .route(
+            "/simulatie/realtime",
+            put(routes::simulatie::put_realtime).delete(routes::simulatie::delete_realtime),
+        )
+        .route_layer(require(Permission::ScenariosUpdate));
 
-    Ok(())
+    let scenarios_delete = Router::new()
+        .route("/scenarios/{id}", delete(routes::scenarios::delete_scenario))
+        .route_layer(require(Permission::ScenariosDelete));
+
+    let scenarios_execute = Router::new()
+        .route("/scenarios/{id}/execute", post(routes::scenarios::execute_scenario))
+        .route("/simulatie", post(routes::simulatie::run_simulatie))
+        .route("/simulatie/sweep", post(routes::simulatie::run_sweep))
+        .route("/simulatie/tornado", post(routes::simulatie::run_tornado))
+        .route("/simulatie/pid-tuning", post(routes::simulatie::run_pid_tuning))
+        .route("/simulatie/netwerk", post(routes::simulatie::run_netwerk))
+        .route("/simulatie/jaarbalans", post(routes::simulatie::run_jaarbalans))
+        .route("/simulatie/droogte", post(routes::simulatie::run_droogte))
+        .route("/simulatie/ensemble", post(routes::simulatie::run_ensemble))
+        .route("/simulatie/rapport", post(routes::simulatie::run_rapport))
+        .route("/simulatie/realtime/run", post(routes::simulatie::run_realtime))
+        .route(
+            "/simulatie/topologie-voorstel",
+            post(routes::simulatie::post_topologie_voorstel),
+        )
+        .route(
+            "/simulatie/netwerk/{run_id}/cancel",
+            post(routes::simulatie::cancel_netwerk),
+        )
+        .route("/optimalisatie", post(routes::optimalisatie::run_optimalisatie))
+        // Optimization job queue routes
+        .route("/optimization/jobs", post(routes::optimalisatie::create_job))
+        .route("/optimization/jobs/{id}/cancel", post(routes::optimalisatie::cancel_job))
+        .route("/optimization/forecast/refresh", post(routes::optimalisatie::refresh_price_forecast))
+        .route_layer(require(Permission::ScenariosExecute));
+
+    let results_read = Router::new()
+        .route("/scenarios/{id}/results", get(routes::scenarios::get_scenario_results))
+        .route("/energieprijzen", get(routes::optimalisatie::get_energieprijzen))
+        .route("/energieprijzen/historie", get(routes::optimalisatie::get_prijs_historie))
+        .route("/energieprijzen/kwartier", get(routes::optimalisatie::get_kwartierprijzen))
+        .route("/pv/verwachting", get(routes::optimalisatie::get_pv_verwachting))
+        .route("/optimization/jobs", get(routes::optimalisatie::list_jobs))
+        .route("/optimization/jobs/{id}", get(routes::optimalisatie::get_job))
+        .route(
+            "/optimization/jobs/{id}/rapport",
+            get(routes::optimalisatie::get_job_rapport),
+        )
+        .route("/optimization/forecast", get(routes::optimalisatie::get_price_forecast))
+        .route_layer(require(Permission::ResultsRead));
+
+    Router::new()
+        .merge(public)
+        .merge(authenticated)
+        .merge(users_read)
+        .merge(users_create)
+        .merge(users_update)
+        .merge(users_delete)
+        .merge(alerts_read)
+        .merge(system_status)
+        .merge(assets_read)
+        .merge(assets_update)
+        .merge(assets_sync)
+        .merge(pumps_control)
+        .merge(scenarios_read)
+        .merge(scenarios_create)
+        .merge(scenarios_update)
+        .merge(scenarios_delete)
+        .merge(scenarios_execute)
+        .merge(results_read)
 }
@@ -0,0 +1,395 @@
+//! MQTT ingest of live pumping station telemetry.
+//!
+//! Pumping stations publish flow, running hours etc. on MQTT topics. Each
+//! [`TopicMapping`] maps a topic filter to a time series; `+` and `#`
+//! segments of the filter can be used in the location, parameter and
+//! qualifier as `{1}`, `{2}`, ... Every message is written as a
+//! [`TimeSeriesWriteBatch`] and broadcast to WebSocket clients as
+//! `timeseries.update`.
+//!
+//! Payloads are a plain number, or a JSON object with the value (and
+//! optionally the time) in configurable fields.
+
+use chrono::{DateTime, Utc};
+use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::WsMessage;
+use peilbeheer_core::timeseries::{TimeSeriesDataPoint, TimeSeriesId, TimeSeriesWriteBatch};
+
+use crate::timeseries_service::TimeSeriesService;
+use crate::websocket_service::WebSocketServer;
+
+/// Wait before polling again after a connection error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// MQTT ingest configuration.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    /// Broker host; ingest is disabled without it
+    pub host: Option<String>,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub mappings: Vec<TopicMapping>,
+}
+
+impl MqttConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mappings = match std::env::var("MQTT_TOPIC_MAPPINGS") {
+            Ok(json) if !json.is_empty() => serde_json::from_str(&json)?,
+            _ => vec![TopicMapping::default()],
+        };
+        Ok(Self {
+            host: std::env::var("MQTT_HOST").ok().filter(|h| !h.is_empty()),
+            port: std::env::var("MQTT_PORT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1883),
+            client_id: std::env::var("MQTT_CLIENT_ID")
+                .unwrap_or_else(|_| "peilbeheer-api".to_string()),
+            username: std::env::var("MQTT_USERNAME")
+                .ok()
+                .filter(|u| !u.is_empty()),
+            password: std::env::var("MQTT_PASSWORD").ok(),
+            mappings,
+        })
+    }
+}
+
+/// Mapping of an MQTT topic filter to a time series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicMapping {
+    /// Topic filter, e.g. `gemalen/+/debiet`
+    pub topic: String,
+    /// Location of the series, e.g. `{1}`
+    pub location_id: String,
+    pub parameter: String,
+    #[serde(default)]
+    pub qualifier: Option<String>,
+    /// Field of the value in a JSON payload
+    #[serde(default = "default_value_field")]
+    pub value_field: String,
+    /// Field of the time (RFC 3339 or epoch seconds); without it the time
+    /// of receipt is used
+    #[serde(default = "default_timestamp_field")]
+    pub timestamp_field: String,
+    /// Factor applied to the value, e.g. for unit conversion
+    #[serde(default)]
+    pub scale: Option<f64>,
+}
+
+fn default_value_field() -> String {
+    "value".to_string()
+}
+
+fn default_timestamp_field() -> String {
+    "timestamp".to_string()
+}
+
+impl Default for TopicMapping {
+    /// `gemalen/{code}/{parameter}`
+    fn default() -> Self {
+        Self {
+            topic: "gemalen/+/+".to_string(),
+            location_id: "{1}".to_string(),
+            parameter: "{2}".to_string(),
+            qualifier: None,
+            value_field: default_value_field(),
+            timestamp_field: default_timestamp_field(),
+            scale: None,
+        }
+    }
+}
+
+impl TopicMapping {
+    /// Series and data point of a message, or None if the topic does not
+    /// match this mapping.
+    pub fn map(
+        &self,
+        topic: &str,
+        payload: &[u8],
+        ontvangen: DateTime<Utc>,
+    ) -> Option<Result<(TimeSeriesId, TimeSeriesDataPoint), String>> {
+        let segmenten = topic_match(&self.topic, topic)?;
+        let invullen = |template: &str| {
+            segmenten
+                .iter()
+                .enumerate()
+                .fold(template.to_string(), |s, (i, segment)| {
+                    s.replace(&format!("{{{}}}", i + 1), segment)
+                })
+        };
+        let series_id = TimeSeriesId {
+            location_id: invullen(&self.location_id),
+            parameter: invullen(&self.parameter),
+            qualifier: self.qualifier.as_deref().map(invullen),
+        };
+        Some(
+            self.parse_payload(payload, ontvangen)
+                .map(|punt| (series_id, punt)),
+        )
+    }
+
+    fn parse_payload(
+        &self,
+        payload: &[u8],
+        ontvangen: DateTime<Utc>,
+    ) -> Result<TimeSeriesDataPoint, String> {
+        let tekst = std::str::from_utf8(payload).map_err(|_| "payload is not UTF-8".to_string())?;
+        let json: serde_json::Value = serde_json::from_str(tekst.trim())
+            .map_err(|_| format!("invalid payload {:?}", tekst))?;
+
+        let (waarde, tijd) = match &json {
+            serde_json::Value::Object(object) => (
+                object.get(&self.value_field).and_then(as_f64),
+                object.get(&self.timestamp_field),
+            ),
+            other => (as_f64(other), None),
+        };
+        let waarde = waarde.ok_or_else(|| format!("no numeric {} in payload", self.value_field))?;
+        let tijd = match tijd {
+            None | Some(serde_json::Value::Null) => ontvangen,
+            Some(serde_json::Value::Number(n)) => n
+                .as_i64()
+                .and_then(|s| DateTime::from_timestamp(s, 0))
+                .ok_or_else(|| format!("invalid {} {}", self.timestamp_field, n))?,
+            Some(t) => t
+                .as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok_or_else(|| format!("invalid {} {}", self.timestamp_field, t))?,
+        };
+        Ok(TimeSeriesDataPoint::new(
+            tijd,
+            waarde * self.scale.unwrap_or(1.0),
+        ))
+    }
+}
+
+fn as_f64(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+/// Segments of `topic` matched by the `+` and `#` wildcards of `filter`,
+/// or None if the topic does not match. `#` captures the rest of the topic.
+fn topic_match<'a>(filter: &str, topic: &'a str) -> Option<Vec<&'a str>> {
+    let mut segmenten = Vec::new();
+    let mut rest = topic;
+    let mut filter_delen = filter.split('/').peekable();
+    while let Some(deel) = filter_delen.next() {
+        if deel == "#" {
+            segmenten.push(rest);
+            return Some(segmenten);
+        }
+        let (segment, volgende) = match rest.split_once('/') {
+            Some((segment, volgende)) => (segment, Some(volgende)),
+            None => (rest, None),
+        };
+        match deel {
+            "+" => segmenten.push(segment),
+            _ if deel == segment => {}
+            _ => return None,
+        }
+        match (volgende, filter_delen.peek()) {
+            (Some(volgende), Some(_)) => rest = volgende,
+            (None, None) => return Some(segmenten),
+            (None, Some(&"#")) => rest = "",
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// MQTT subscriber writing telemetry to time series.
+pub struct MqttIngest {
+    config: MqttConfig,
+    timeseries: Arc<TimeSeriesService>,
+    ws_server: Arc<WebSocketServer>,
+}
+
+impl MqttIngest {
+    pub fn new(
+        config: MqttConfig,
+        timeseries: Arc<TimeSeriesService>,
+        ws_server: Arc<WebSocketServer>,
+    ) -> Self {
+        Self {
+            config,
+            timeseries,
+            ws_server,
+        }
+    }
+
+    /// Connect to the broker and ingest messages (no-op without `MQTT_HOST`).
+    pub fn start(self: &Arc<Self>) {
+        let Some(host) = self.config.host.clone() else {
+            info!("MQTT ingest disabled (MQTT_HOST not set)");
+            return;
+        };
+
+        let mut options = MqttOptions::new(&self.config.client_id, host.as_str(), self.config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.config.username {
+            options.set_credentials(
+                username.as_str(),
+                self.config.password.clone().unwrap_or_default(),
+            );
+        }
+        let (client, mut eventloop) = AsyncClient::new(options, 100);
+
+        let ingest = Arc::clone(self);
+        tokio::spawn(async move {
+            info!("MQTT ingest started ({}:{})", host, ingest.config.port);
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                        // Subscriptions do not survive a clean session reconnect
+                        for mapping in &ingest.config.mappings {
+                            if let Err(e) = client.subscribe(&mapping.topic, QoS::AtLeastOnce).await
+                            {
+                                warn!("MQTT subscribe to {} failed: {}", mapping.topic, e);
+                            }
+                        }
+                    }
+                    Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                        ingest.handle(&publish.topic, &publish.payload).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("MQTT connection error: {}", e);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Write a message to its time series and broadcast it.
+    async fn handle(&self, topic: &str, payload: &[u8]) {
+        let Some(mapped) = self
+            .config
+            .mappings
+            .iter()
+            .find_map(|m| m.map(topic, payload, Utc::now()))
+        else {
+            debug!("MQTT message on unmapped topic {}", topic);
+            return;
+        };
+        let (series_id, punt) = match mapped {
+            Ok(mapped) => mapped,
+            Err(e) => {
+                warn!("MQTT message on {} ignored: {}", topic, e);
+                return;
+            }
+        };
+
+        let update = WsMessage::TimeSeriesUpdate {
+            location_id: series_id.location_id.clone(),
+            parameter: series_id.parameter.clone(),
+            value: punt.value,
+            timestamp: punt.timestamp,
+        };
+        let batch = TimeSeriesWriteBatch {
+            series_id,
+            data: vec![punt],
+            attributes: None,
+        };
+        match self.timeseries.write_batch(batch).await {
+            Ok(_) => self.ws_server.broadcast(update).await,
+            Err(e) => warn!("MQTT message on {} not stored: {}", topic, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_match() {
+        assert_eq!(
+            topic_match("gemalen/+/+", "gemalen/GEM001/debiet"),
+            Some(vec!["GEM001", "debiet"])
+        );
+        assert_eq!(topic_match("gemalen/+/+", "gemalen/GEM001"), None);
+        assert_eq!(topic_match("gemalen/+", "gemalen/GEM001/debiet"), None);
+        assert_eq!(
+            topic_match("site/#", "site/noord/GEM002/draaiuren"),
+            Some(vec!["noord/GEM002/draaiuren"])
+        );
+        assert_eq!(topic_match("site/#", "site"), Some(vec![""]));
+        assert_eq!(
+            topic_match("gemalen/GEM001/debiet", "gemalen/GEM001/debiet"),
+            Some(vec![])
+        );
+        assert_eq!(
+            topic_match("gemalen/GEM001/debiet", "gemalen/GEM002/debiet"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_map_payload() {
+        let nu = DateTime::parse_from_rfc3339("2025-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mapping = TopicMapping::default();
+
+        let (id, punt) = mapping
+            .map("gemalen/GEM001/debiet", b"1.25", nu)
+            .unwrap()
+            .unwrap();
+        assert_eq!(id, TimeSeriesId::new("GEM001", "debiet"));
+        assert_eq!((punt.timestamp, punt.value), (nu, 1.25));
+
+        let (_, punt) = mapping
+            .map(
+                "gemalen/GEM001/draaiuren",
+                br#"{"value": "1520.5", "timestamp": "2025-03-10T11:59:00+01:00"}"#,
+                nu,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(punt.value, 1520.5);
+        assert_eq!(punt.timestamp.to_rfc3339(), "2025-03-10T10:59:00+00:00");
+
+        assert!(mapping.map("telemetrie/x", b"1", nu).is_none());
+        assert!(
+            mapping
+                .map("gemalen/GEM001/debiet", b"aan", nu)
+                .unwrap()
+                .is_err()
+        );
+
+        let mapping = TopicMapping {
+            topic: "site/+/pomp/+".to_string(),
+            location_id: "{1}".to_string(),
+            parameter: "debiet".to_string(),
+            qualifier: Some("pomp{2}".to_string()),
+            value_field: "q".to_string(),
+            timestamp_field: "ts".to_string(),
+            scale: Some(1.0 / 3600.0),
+        };
+        let (id, punt) = mapping
+            .map(
+                "site/GEM003/pomp/2",
+                br#"{"q": 7200, "ts": 1741608000}"#,
+                nu,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            id,
+            TimeSeriesId::with_qualifier("GEM003", "debiet", "pomp2")
+        );
+        assert_eq!(punt.value, 2.0);
+        assert_eq!(punt.timestamp, nu);
+    }
+}
@@ -0,0 +1,295 @@
+//! Fallback precipitation sources for when KNMI is unavailable.
+//!
+//! Both sources only support point locations, so a point within the
+//! peilgebied is used instead of the area mean:
+//! - Buienradar `raintext` replaces the radar nowcast. It covers the next
+//!   two hours in 5-minute steps of rain intensity.
+//! - The Meteoserver hourly forecast replaces the HARMONIE precipitation
+//!   forecast. It has no evaporation.
+//!
+//! [`crate::knmi_client::KnmiClient`] switches to these sources on its own;
+//! the `bron` of the result tells which source was used.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use std::sync::Arc;
+use thiserror::Error;
+
+use peilbeheer_core::{RegenVerwachting, TimeSeriesDataPoint, WeerVerwachting};
+
+use crate::db::Database;
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Hours covered by the Buienradar nowcast.
+pub const BUIENRADAR_UREN: usize = 2;
+
+/// Buienradar time steps (5 minutes) per hour.
+const BUIENRADAR_STAPPEN_PER_UUR: usize = 12;
+
+/// Fallback source configuration.
+#[derive(Debug, Clone)]
+pub struct NeerslagFallbackConfig {
+    /// Whether KNMI falls back to these sources
+    pub enabled: bool,
+    pub buienradar_url: String,
+    pub meteoserver_url: String,
+    /// Meteoserver forecasts are only used with a key
+    pub meteoserver_api_key: Option<String>,
+}
+
+impl Default for NeerslagFallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: std::env::var("NEERSLAG_FALLBACK")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            buienradar_url: std::env::var("BUIENRADAR_RAINTEXT_URL")
+                .unwrap_or_else(|_| "https://gpsgadget.buienradar.nl/data/raintext".to_string()),
+            meteoserver_url: std::env::var("METEOSERVER_BASE_URL")
+                .unwrap_or_else(|_| "https://data.meteoserver.nl/api".to_string()),
+            meteoserver_api_key: std::env::var("METEOSERVER_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+        }
+    }
+}
+
+/// Errors of the fallback sources.
+#[derive(Debug, Error)]
+pub enum NeerslagFallbackError {
+    #[error("Meteoserver is not configured (METEOSERVER_API_KEY)")]
+    NotConfigured,
+    #[error("Peilgebied {0} not found")]
+    PeilgebiedNotFound(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("{source_name} returned error status {status}: {message}")]
+    Api {
+        source_name: &'static str,
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+    #[error("Database error: {0}")]
+    Database(#[from] anyhow::Error),
+}
+
+/// Client for the Buienradar and Meteoserver precipitation data.
+pub struct NeerslagFallback {
+    db: Arc<Database>,
+    config: NeerslagFallbackConfig,
+    http_client: HttpClient,
+}
+
+impl NeerslagFallback {
+    pub fn new(db: Arc<Database>, config: NeerslagFallbackConfig) -> Self {
+        Self {
+            db,
+            config,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    pub fn config(&self) -> &NeerslagFallbackConfig {
+        &self.config
+    }
+
+    /// Whether a forecast can be fetched (needs a Meteoserver key).
+    pub fn has_forecast(&self) -> bool {
+        self.config.enabled && self.config.meteoserver_api_key.is_some()
+    }
+
+    /// Buienradar nowcast at a point of a peilgebied, as hourly sums.
+    ///
+    /// Covers at most [`BUIENRADAR_UREN`] hours; later hours are missing
+    /// from `regen_per_uur`.
+    pub async fn regen_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<RegenVerwachting, NeerslagFallbackError> {
+        let (lon, lat) = self.punt(peilgebied_code)?;
+        let now = Utc::now();
+        let start = now.duration_trunc(Duration::minutes(5)).unwrap_or(now);
+
+        let request = self.http_client.get(&self.config.buienradar_url).query(&[
+            ("lat", format!("{:.2}", lat)),
+            ("lon", format!("{:.2}", lon)),
+        ]);
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(NeerslagFallbackError::Api {
+                source_name: "Buienradar",
+                status,
+                message,
+            });
+        }
+        let text = response.text().await.map_err(HttpError::from)?;
+
+        Ok(RegenVerwachting {
+            peilgebied_code: peilgebied_code.to_string(),
+            start,
+            regen_per_uur: buienradar_regen_per_uur(&text, uren.min(BUIENRADAR_UREN))?,
+            bron: "buienradar:raintext".to_string(),
+            opgehaald_op: now,
+        })
+    }
+
+    /// Meteoserver hourly precipitation forecast at a point of a
+    /// peilgebied, for the next `uren` hours.
+    pub async fn weer_verwachting(
+        &self,
+        peilgebied_code: &str,
+        uren: usize,
+    ) -> Result<WeerVerwachting, NeerslagFallbackError> {
+        let Some(api_key) = &self.config.meteoserver_api_key else {
+            return Err(NeerslagFallbackError::NotConfigured);
+        };
+        let (lon, lat) = self.punt(peilgebied_code)?;
+        let now = Utc::now();
+        let start = now.duration_trunc(Duration::hours(1)).unwrap_or(now);
+        let end = start + Duration::hours(uren as i64);
+
+        let url = format!(
+            "{}/uurverwachting.php",
+            self.config.meteoserver_url.trim_end_matches('/')
+        );
+        let request = self.http_client.get(&url).query(&[
+            ("lat", format!("{:.4}", lat)),
+            ("long", format!("{:.4}", lon)),
+            ("key", api_key.clone()),
+        ]);
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(NeerslagFallbackError::Api {
+                source_name: "Meteoserver",
+                status,
+                message,
+            });
+        }
+        let body: serde_json::Value = response.json().await.map_err(HttpError::from)?;
+
+        Ok(WeerVerwachting {
+            peilgebied_code: peilgebied_code.to_string(),
+            neerslag: meteoserver_neerslag(&body)?
+                .into_iter()
+                .filter(|p| p.timestamp >= start && p.timestamp <= end)
+                .collect(),
+            verdamping: Vec::new(),
+            bron: "meteoserver:uurverwachting".to_string(),
+            opgehaald_op: now,
+        })
+    }
+
+    fn punt(&self, peilgebied_code: &str) -> Result<(f64, f64), NeerslagFallbackError> {
+        self.db
+            .get_peilgebied_punt(peilgebied_code)?
+            .ok_or_else(|| NeerslagFallbackError::PeilgebiedNotFound(peilgebied_code.to_string()))
+    }
+}
+
+/// Hourly sums (mm) of a Buienradar `raintext` response.
+///
+/// Each line is `intensity|HH:MM` with intensity 0-255, where the rain
+/// rate is `10^((intensity - 109) / 32)` mm/h. The local clock times are
+/// ignored: the first line is the current 5-minute step.
+fn buienradar_regen_per_uur(text: &str, uren: usize) -> Result<Vec<f64>, NeerslagFallbackError> {
+    let stappen_per_uur = BUIENRADAR_STAPPEN_PER_UUR;
+    let mut regen = vec![0.0; uren];
+    let mut stappen = 0usize;
+    for (i, regel) in text.lines().filter(|r| !r.trim().is_empty()).enumerate() {
+        let waarde = regel
+            .split('|')
+            .next()
+            .and_then(|w| w.trim().parse::<u8>().ok())
+            .ok_or_else(|| {
+                NeerslagFallbackError::Parse(format!("invalid Buienradar line: {}", regel))
+            })?;
+        stappen += 1;
+        if waarde == 0 {
+            continue;
+        }
+        let mm_per_uur = 10f64.powf((f64::from(waarde) - 109.0) / 32.0);
+        if let Some(uur) = regen.get_mut(i / stappen_per_uur) {
+            *uur += mm_per_uur / stappen_per_uur as f64;
+        }
+    }
+    if stappen == 0 {
+        return Err(NeerslagFallbackError::Parse(
+            "empty Buienradar response".to_string(),
+        ));
+    }
+    regen.truncate(stappen.div_ceil(stappen_per_uur));
+    Ok(regen
+        .into_iter()
+        .map(|mm| (mm * 100.0).round() / 100.0)
+        .collect())
+}
+
+/// Hourly precipitation (mm) of a Meteoserver `uurverwachting` response.
+///
+/// Meteoserver returns numbers as strings and the time as Unix seconds.
+fn meteoserver_neerslag(
+    body: &serde_json::Value,
+) -> Result<Vec<TimeSeriesDataPoint>, NeerslagFallbackError> {
+    let data = body["data"]
+        .as_array()
+        .ok_or_else(|| NeerslagFallbackError::Parse("missing data".to_string()))?;
+    let getal = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+        v => v.as_f64(),
+    };
+
+    Ok(data
+        .iter()
+        .filter_map(|uur| {
+            let tijd = getal(&uur["tijd"]).and_then(|t| DateTime::from_timestamp(t as i64, 0))?;
+            let neerslag = getal(&uur["neersl"])?;
+            Some(TimeSeriesDataPoint::new(tijd, neerslag))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buienradar_regen_per_uur() {
+        // 109 is 1 mm/h, so twelve steps of 109 make 1 mm
+        let mut text = String::new();
+        for i in 0..18 {
+            let waarde = if i < 12 { 109 } else { 0 };
+            text.push_str(&format!("{:03}|14:{:02}\r\n", waarde, i * 5 % 60));
+        }
+        let regen = buienradar_regen_per_uur(&text, 2).unwrap();
+        assert_eq!(regen, vec![1.0, 0.0]);
+
+        // Only the hours covered by the response are returned
+        assert_eq!(buienradar_regen_per_uur("000|14:05\n", 2).unwrap().len(), 1);
+        assert!(buienradar_regen_per_uur("", 2).is_err());
+        assert!(buienradar_regen_per_uur("abc|14:05", 2).is_err());
+    }
+
+    #[test]
+    fn test_meteoserver_neerslag() {
+        let body = serde_json::json!({
+            "plaatsnaam": [{"plaats": "Leiden"}],
+            "data": [
+                {"tijd": "1772402400", "tijd_nl": "01-03-2026 23:00", "neersl": "0.4"},
+                {"tijd": "1772406000", "tijd_nl": "02-03-2026 00:00", "neersl": "0"},
+                {"tijd": "1772409600", "tijd_nl": "02-03-2026 01:00", "neersl": "-"}
+            ]
+        });
+        let neerslag = meteoserver_neerslag(&body).unwrap();
+        assert_eq!(neerslag.len(), 2);
+        assert_eq!(neerslag[0].value, 0.4);
+        assert_eq!(neerslag[0].timestamp.timestamp(), 1_772_402_400);
+
+        assert!(meteoserver_neerslag(&serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,558 @@
+//! OpenID Connect login (single sign-on) against Azure AD.
+//!
+//! Implements the authorization code flow with PKCE: the API redirects the
+//! browser to the provider with a fresh `state`, `nonce` and code challenge,
+//! and on the callback exchanges the code for an ID token. A cookie with a
+//! hash of the state ties the callback to the browser that started the
+//! login. The ID token is
+//! verified against the provider's published signing keys before its
+//! claims (subject, name, e-mail and AD groups) are used. Provider metadata
+//! is read from the issuer's discovery document and cached.
+//!
+//! AD groups map to roles via `OIDC_GROUP_ROLES`, e.g.
+//! `"<group-object-id>=admin;<group-object-id>=operator"`. Azure AD only
+//! includes the `groups` claim when the app registration is configured to
+//! emit group claims.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use tokio::sync::{Mutex, RwLock};
+use tracing::debug;
+
+use peilbeheer_core::Role;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// How long a started login may take before its state expires
+const PENDING_LOGIN_MINUTES: i64 = 10;
+
+/// Cookie tying a started login to the browser that started it
+pub const STATE_COOKIE: &str = "oidc_state";
+
+/// OIDC configuration.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL, e.g. `https://login.microsoftonline.com/<tenant>/v2.0`
+    pub issuer: Option<String>,
+    /// Application (client) id
+    pub client_id: Option<String>,
+    /// Client secret (omitted for public clients)
+    pub client_secret: Option<String>,
+    /// Callback URL registered at the provider
+    pub redirect_uri: String,
+    /// Requested scopes
+    pub scopes: String,
+    /// Role per AD group id
+    pub group_roles: HashMap<String, Role>,
+    /// Role for users without a mapped group (None = login refused)
+    pub default_role: Option<Role>,
+    /// Frontend URL receiving the API token after login (JSON response when unset)
+    pub post_login_redirect: Option<String>,
+    /// Request timeout (seconds)
+    pub timeout_secs: u64,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+        Self {
+            issuer: env("OIDC_ISSUER").map(|s| s.trim_end_matches('/').to_string()),
+            client_id: env("OIDC_CLIENT_ID"),
+            client_secret: env("OIDC_CLIENT_SECRET"),
+            redirect_uri: env("OIDC_REDIRECT_URI")
+                .unwrap_or_else(|| "http://localhost:3000/api/auth/oidc/callback".to_string()),
+            scopes: env("OIDC_SCOPES").unwrap_or_else(|| "openid profile email".to_string()),
+            group_roles: env("OIDC_GROUP_ROLES")
+                .map(|s| parse_group_roles(&s))
+                .unwrap_or_default(),
+            default_role: env("OIDC_DEFAULT_ROLE").and_then(|s| Role::from_str(&s)),
+            post_login_redirect: env("OIDC_POST_LOGIN_REDIRECT"),
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl OidcConfig {
+    /// Whether single sign-on is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.issuer.is_some() && self.client_id.is_some()
+    }
+}
+
+/// OIDC errors.
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("single sign-on is not configured")]
+    NotConfigured,
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("provider returned HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("unknown or expired login state")]
+    InvalidState,
+    #[error("invalid ID token: {0}")]
+    InvalidIdToken(String),
+    #[error("no role is mapped to the user's groups")]
+    NoRole,
+    #[error("an account for {0} already exists; an administrator must link it to the identity")]
+    AccountNotLinked(String),
+}
+
+/// Parse a group mapping like `"<group>=admin;<group>=operator"`.
+///
+/// Entries may be separated by `;` or `,`; unknown roles are skipped.
+pub fn parse_group_roles(s: &str) -> HashMap<String, Role> {
+    s.split([';', ','])
+        .filter_map(|entry| {
+            let (group, role) = entry.split_once('=')?;
+            let group = group.trim();
+            let role = Role::from_str(role.trim())?;
+            (!group.is_empty()).then(|| (group.to_string(), role))
+        })
+        .collect()
+}
+
+/// Highest role granted by the user's groups, or `default` when none of
+/// them is mapped.
+pub fn role_for_groups(
+    mapping: &HashMap<String, Role>,
+    groups: &[String],
+    default: Option<Role>,
+) -> Option<Role> {
+    groups
+        .iter()
+        .filter_map(|group| mapping.get(group))
+        .copied()
+        .max_by_key(|role| role.level())
+        .or(default)
+}
+
+/// PKCE code verifier and its S256 challenge.
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    /// Generate a random verifier.
+    pub fn generate() -> Self {
+        Self::from_verifier(format!("{}{}", random_token(), random_token()))
+    }
+
+    /// Challenge for a given verifier.
+    pub fn from_verifier(verifier: String) -> Self {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self { verifier, challenge }
+    }
+}
+
+/// Random URL-safe token.
+fn random_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Value of the [`STATE_COOKIE`] of a login: a hash, so the state itself is
+/// not stored in the browser.
+fn state_binding(state: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(state.as_bytes()))
+}
+
+/// `Set-Cookie` value removing the [`STATE_COOKIE`].
+pub fn clear_state_cookie() -> String {
+    format!("{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax", STATE_COOKIE)
+}
+
+/// Identity of a user who logged in at the provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcIdentity {
+    /// Stable user id at the provider (`oid` for Azure AD, else `sub`)
+    pub subject: String,
+    pub username: String,
+    pub email: String,
+    pub full_name: Option<String>,
+    pub groups: Vec<String>,
+}
+
+/// Claims read from the ID token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    #[serde(default)]
+    pub oid: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    #[serde(default)]
+    pub preferred_username: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl IdTokenClaims {
+    /// Identity of the user, requiring the nonce of the login.
+    pub fn into_identity(self, expected_nonce: &str) -> Result<OidcIdentity, OidcError> {
+        if self.nonce.as_deref() != Some(expected_nonce) {
+            return Err(OidcError::InvalidIdToken("nonce mismatch".to_string()));
+        }
+
+        let email = self
+            .email
+            .or_else(|| self.preferred_username.clone().filter(|u| u.contains('@')))
+            .ok_or_else(|| OidcError::InvalidIdToken("no e-mail address".to_string()))?;
+        let username = self.preferred_username.unwrap_or_else(|| email.clone());
+
+        Ok(OidcIdentity {
+            subject: self.oid.unwrap_or(self.sub),
+            username,
+            email,
+            full_name: self.name,
+            groups: self.groups,
+        })
+    }
+}
+
+/// Endpoints from the provider's discovery document.
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Login started by [`OidcClient::authorization_url`].
+#[derive(Debug, Clone)]
+struct PendingLogin {
+    verifier: String,
+    nonce: String,
+    created_at: DateTime<Utc>,
+}
+
+/// OIDC client with cached provider metadata and pending logins.
+pub struct OidcClient {
+    config: OidcConfig,
+    client: HttpClient,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    jwks: RwLock<Option<JwkSet>>,
+    pending: Mutex<HashMap<String, PendingLogin>>,
+}
+
+impl OidcClient {
+    /// Create a new client.
+    pub fn new(config: OidcConfig) -> Self {
+        let client =
+            HttpClient::shared().with_timeout(StdDuration::from_secs(config.timeout_secs));
+
+        Self {
+            config,
+            client,
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start a login: URL to redirect the browser to, and the `Set-Cookie`
+    /// value of the [`STATE_COOKIE`] the callback requires.
+    pub async fn authorization_url(&self) -> Result<(String, String), OidcError> {
+        let client_id = self.config.client_id.as_deref().ok_or(OidcError::NotConfigured)?;
+        let metadata = self.metadata().await?;
+
+        let state = random_token();
+        let nonce = random_token();
+        let pkce = Pkce::generate();
+
+        let url = build_authorization_url(
+            &metadata.authorization_endpoint,
+            &[
+                ("client_id", client_id),
+                ("response_type", "code"),
+                ("redirect_uri", &self.config.redirect_uri),
+                ("response_mode", "query"),
+                ("scope", &self.config.scopes),
+                ("state", &state),
+                ("nonce", &nonce),
+                ("code_challenge", &pkce.challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        );
+
+        let secure = if self.config.redirect_uri.starts_with("https://") { "; Secure" } else { "" };
+        let cookie = format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            STATE_COOKIE,
+            state_binding(&state),
+            PENDING_LOGIN_MINUTES * 60,
+            secure
+        );
+
+        let now = Utc::now();
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, login| now - login.created_at < Duration::minutes(PENDING_LOGIN_MINUTES));
+        pending.insert(
+            state,
+            PendingLogin {
+                verifier: pkce.verifier,
+                nonce,
+                created_at: now,
+            },
+        );
+
+        Ok((url, cookie))
+    }
+
+    /// Finish a login: exchange the authorization code and verify the ID token.
+    ///
+    /// `state_cookie` is the [`STATE_COOKIE`] sent with the callback; a
+    /// callback without the cookie of its login is refused.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        state: &str,
+        state_cookie: Option<&str>,
+    ) -> Result<OidcIdentity, OidcError> {
+        let client_id = self.config.client_id.as_deref().ok_or(OidcError::NotConfigured)?;
+
+        if state_cookie != Some(state_binding(state).as_str()) {
+            return Err(OidcError::InvalidState);
+        }
+
+        let login = self
+            .pending
+            .lock()
+            .await
+            .remove(state)
+            .filter(|login| Utc::now() - login.created_at < Duration::minutes(PENDING_LOGIN_MINUTES))
+            .ok_or(OidcError::InvalidState)?;
+
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("client_id", client_id),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("code_verifier", login.verifier.as_str()),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let request = self.client.post(&metadata.token_endpoint).form(&form);
+        let response = self.client.send(request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(OidcError::Status {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        let tokens: TokenResponse = response.json().await.map_err(HttpError::from)?;
+
+        let claims = self.verify_id_token(&tokens.id_token, &metadata, client_id).await?;
+        claims.into_identity(&login.nonce)
+    }
+
+    /// Verify signature, issuer, audience and expiry of an ID token.
+    async fn verify_id_token(
+        &self,
+        token: &str,
+        metadata: &ProviderMetadata,
+        client_id: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let header = decode_header(token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| OidcError::InvalidIdToken("missing key id".to_string()))?;
+
+        let key = match self.find_key(&kid, &metadata.jwks_uri, false).await? {
+            Some(key) => key,
+            // Keys rotate; refresh the cached set once
+            None => self
+                .find_key(&kid, &metadata.jwks_uri, true)
+                .await?
+                .ok_or_else(|| OidcError::InvalidIdToken(format!("unknown key id '{}'", kid)))?,
+        };
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+
+        decode::<IdTokenClaims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| OidcError::InvalidIdToken(e.to_string()))
+    }
+
+    /// Signing key with the given id, optionally refreshing the key set.
+    async fn find_key(
+        &self,
+        kid: &str,
+        jwks_uri: &str,
+        refresh: bool,
+    ) -> Result<Option<DecodingKey>, OidcError> {
+        if refresh || self.jwks.read().await.is_none() {
+            debug!("Fetching OIDC signing keys from {}", jwks_uri);
+            let jwks: JwkSet = self.get_json(jwks_uri).await?;
+            *self.jwks.write().await = Some(jwks);
+        }
+
+        let jwks = self.jwks.read().await;
+        let Some(jwk) = jwks.as_ref().and_then(|set| set.find(kid)) else {
+            return Ok(None);
+        };
+        DecodingKey::from_jwk(jwk)
+            .map(Some)
+            .map_err(|e| OidcError::InvalidIdToken(e.to_string()))
+    }
+
+    /// Provider metadata, fetched from the discovery document once.
+    async fn metadata(&self) -> Result<ProviderMetadata, OidcError> {
+        if let Some(metadata) = self.metadata.read().await.clone() {
+            return Ok(metadata);
+        }
+
+        let issuer = self.config.issuer.as_deref().ok_or(OidcError::NotConfigured)?;
+        let url = format!("{}/.well-known/openid-configuration", issuer);
+        debug!("Fetching OIDC discovery document from {}", url);
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, OidcError> {
+        let response = self.client.send(self.client.get(url)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(OidcError::Status {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(response.json().await.map_err(HttpError::from)?)
+    }
+}
+
+/// Append query parameters to the authorization endpoint.
+fn build_authorization_url(endpoint: &str, params: &[(&str, &str)]) -> String {
+    let query = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let separator = if endpoint.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", endpoint, separator, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims() -> IdTokenClaims {
+        IdTokenClaims {
+            sub: "pairwise-sub".to_string(),
+            oid: Some("00000000-aaaa".to_string()),
+            nonce: Some("n-123".to_string()),
+            preferred_username: Some("j.jansen@rijnland.net".to_string()),
+            email: None,
+            name: Some("Jan Jansen".to_string()),
+            groups: vec!["grp-operators".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // RFC 7636, appendix B
+        let pkce = Pkce::from_verifier("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+        assert_eq!(pkce.challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let generated = Pkce::generate();
+        assert!(generated.verifier.len() >= 43);
+        assert_ne!(generated.verifier, Pkce::generate().verifier);
+    }
+
+    #[test]
+    fn test_group_role_mapping() {
+        let mapping = parse_group_roles("grp-admins=admin; grp-operators=operator,grp-x=unknown");
+        assert_eq!(mapping.len(), 2);
+        assert_eq!(mapping["grp-admins"], Role::Admin);
+
+        let groups = vec!["grp-operators".to_string(), "grp-admins".to_string()];
+        assert_eq!(role_for_groups(&mapping, &groups, None), Some(Role::Admin));
+        assert_eq!(
+            role_for_groups(&mapping, &["grp-other".to_string()], Some(Role::Viewer)),
+            Some(Role::Viewer)
+        );
+        assert_eq!(role_for_groups(&mapping, &[], None), None);
+    }
+
+    #[test]
+    fn test_identity_from_claims() {
+        let identity = claims().into_identity("n-123").unwrap();
+        assert_eq!(identity.subject, "00000000-aaaa");
+        assert_eq!(identity.username, "j.jansen@rijnland.net");
+        assert_eq!(identity.email, "j.jansen@rijnland.net");
+        assert_eq!(identity.full_name.as_deref(), Some("Jan Jansen"));
+
+        assert!(matches!(
+            claims().into_identity("other"),
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_requires_state_cookie() {
+        let client = OidcClient::new(OidcConfig {
+            issuer: Some("https://login.example.com".to_string()),
+            client_id: Some("abc".to_string()),
+            ..OidcConfig::default()
+        });
+        client.pending.lock().await.insert(
+            "s-1".to_string(),
+            PendingLogin {
+                verifier: "v".to_string(),
+                nonce: "n".to_string(),
+                created_at: Utc::now(),
+            },
+        );
+
+        // Another browser has no cookie, or the cookie of another login
+        for cookie in [None, Some(state_binding("s-2"))] {
+            assert!(matches!(
+                client.exchange_code("code", "s-1", cookie.as_deref()).await,
+                Err(OidcError::InvalidState)
+            ));
+        }
+        assert!(client.pending.lock().await.contains_key("s-1"));
+    }
+
+    #[test]
+    fn test_authorization_url() {
+        let url = build_authorization_url(
+            "https://login.microsoftonline.com/tenant/oauth2/v2.0/authorize",
+            &[("client_id", "abc"), ("scope", "openid profile")],
+        );
+        assert_eq!(
+            url,
+            "https://login.microsoftonline.com/tenant/oauth2/v2.0/authorize?client_id=abc&scope=openid%20profile"
+        );
+    }
+}
@@ -17,7 +17,7 @@ use tracing::{debug, info};
 use peilbeheer_core::energie::*;
 
 use crate::db::Database;
-use crate::energyzero_client;
+use crate::energyzero_client::EnergyPriceStore;
 use crate::websocket_service::WebSocketServer;
 
 /// Optimization service with background job processing.
@@ -26,6 +26,8 @@ pub struct OptimizationService {
     ws_server: Arc<WebSocketServer>,
     jobs: Arc<RwLock<HashMap<String, OptimizationJob>>>,
     job_tx: mpsc::Sender<JobCommand>,
+    /// Day-ahead prices, cached in DuckDB
+    prices: Arc<EnergyPriceStore>,
     /// Cached price forecast with timestamp
     #[allow(clippy::type_complexity)]
     price_cache: Arc<RwLock<Option<(PriceForecast, DateTime<Utc>)>>>,
@@ -43,7 +45,11 @@ enum JobCommand {
 
 impl OptimizationService {
     /// Create a new optimization service.
-    pub fn new(db: Arc<Database>, ws_server: Arc<WebSocketServer>) -> Self {
+    pub fn new(
+        db: Arc<Database>,
+        ws_server: Arc<WebSocketServer>,
+        prices: Arc<EnergyPriceStore>,
+    ) -> Self {
         let (job_tx, job_rx) = mpsc::channel(100);
 
         let jobs = Arc::new(RwLock::new(HashMap::new()));
@@ -57,6 +63,7 @@ impl OptimizationService {
             ws_server,
             jobs,
             job_tx,
+            prices,
             price_cache,
         };
 
@@ -156,7 +163,7 @@ impl OptimizationService {
     /// Get price forecast for optimization.
     ///
     /// Uses a 15-minute cache to avoid excessive API calls.
-    /// Fetches today's prices from EnergyZero API.
+    /// Prices start at midnight UTC of today and come from the price store.
     pub async fn get_price_forecast(&self, hours: u8) -> AnyhowResult<PriceForecast> {
         let now = Utc::now();
         let cache_duration = Duration::minutes(15);
@@ -183,26 +190,13 @@ impl OptimizationService {
         let start_date = Utc::now().date_naive();
 
         // Fetch prices for multiple days at once
-        let all_prices = energyzero_client::fetch_energieprijzen_multiple_days(start_date, days_needed)
+        let hourly_prices: Vec<HourlyPrice> = self
+            .prices
+            .prijzen_vanaf(start_date, days_needed)
             .await
-            .map_err(|e| anyhow::anyhow!("EnergyZero fetch failed: {}", e))?;
-
-        // Take only the requested number of hours
-        let all_prices = all_prices.into_iter().take(hours as usize).collect::<Vec<_>>();
-
-        // Create HourlyPrice entries from UurPrijs
-        let now_dt = Utc::now();
-        let hourly_prices: Vec<HourlyPrice> = all_prices
+            .map_err(|e| anyhow::anyhow!("EnergyZero fetch failed: {}", e))?
             .into_iter()
-            .enumerate()
-            .map(|(i, uur_prijs)| {
-                let hour_start = now_dt + Duration::hours(i as i64);
-                HourlyPrice {
-                    hour_start,
-                    price_eur_kwh: uur_prijs.prijs_eur_kwh,
-                    is_forecast: hour_start > now_dt,
-                }
-            })
+            .take(hours as usize)
             .collect();
 
         let forecast = PriceForecast {
@@ -286,6 +280,7 @@ impl OptimizationService {
         };
 
         job.completed_at = Some(Utc::now());
+        job.result = result.clone();
 
         // Update final status in memory
         {
@@ -309,24 +304,21 @@ impl OptimizationService {
 
         let prijzen: Vec<UurPrijs> = if params.prijzen.is_empty() {
             // Generate default prices if not provided
-            (0..24).map(|i| UurPrijs {
-                uur: i as u8,
-                prijs_eur_kwh: 0.15 + if (8..20).contains(&i) { 0.20 } else { 0.0 },
-            }).collect()
+            (0..24).map(|i| UurPrijs::uur(i as u8, 0.15 + if (8..20).contains(&i) { 0.20 } else { 0.0 })).collect()
         } else {
             // Normalize prices: map them to 0-23 hours based on their index, not the uur field
-            params.prijzen.iter().enumerate().map(|(i, p)| UurPrijs {
-                uur: i as u8,  // Reset to 0-23 based on index
-                prijs_eur_kwh: p.prijs_eur_kwh,
+            params.prijzen.iter().enumerate().map(|(i, p)| {
+                UurPrijs::uur(i as u8, p.prijs_eur_kwh) // Reset to 0-23 based on index
             }).collect()
         };
 
         let num_hours = prijzen.len().min(24);
+        let emissiefactoren = params.emissiefactoren(num_hours);
         let mut uren = Vec::new();
         let mut cumulatieve_kosten_optimaal = 0.0;
         let mut cumulatieve_kosten_naief = 0.0;
 
-        for i in 0..num_hours {
+        for (i, &emissiefactor) in emissiefactoren.iter().enumerate() {
             let uur = i as u8;
             let prijs = prijzen.get(i)
                 .map(|p| p.prijs_eur_kwh)
@@ -356,6 +348,13 @@ impl OptimizationService {
                 waterstand_eind_naief: params.streefpeil,
                 kosten_optimaal,
                 kosten_naief,
+                energie_optimaal_kwh: 0.0,
+                energie_naief_kwh: 0.0,
+                emissiefactor_kg_kwh: emissiefactor,
+                co2_optimaal_kg: 0.0,
+                co2_naief_kg: 0.0,
+                pompen_optimaal: Vec::new(),
+                pompen_naief: Vec::new(),
             });
         }
 
@@ -377,6 +376,23 @@ impl OptimizationService {
             tijdstappen_optimaal: Vec::new(),
             tijdstappen_naief: Vec::new(),
             prijzen,
+            eigen_verbruik_optimaal_kwh: 0.0,
+            eigen_verbruik_naief_kwh: 0.0,
+            totale_energie_optimaal_kwh: 0.0,
+            totale_energie_naief_kwh: 0.0,
+            totale_co2_optimaal_kg: 0.0,
+            totale_co2_naief_kg: 0.0,
+            inschakelingen_optimaal: 0,
+            inschakelingen_naief: 0,
+            piekvermogen_optimaal_kw: 0.0,
+            piekvermogen_naief_kw: 0.0,
+            piekboete_optimaal_eur: 0.0,
+            piekboete_naief_eur: 0.0,
+            flexvergoeding_optimaal_eur: 0.0,
+            flexvergoeding_naief_eur: 0.0,
+            kwartieren: Vec::new(),
+            pompen: Vec::new(),
+            totale_kosten_aan_uit: None,
         })
     }
 
@@ -410,6 +426,7 @@ impl Clone for OptimizationService {
             ws_server: self.ws_server.clone(),
             jobs: self.jobs.clone(),
             job_tx: self.job_tx.clone(),
+            prices: self.prices.clone(),
             price_cache: self.price_cache.clone(),
         }
     }
@@ -0,0 +1,162 @@
+//! Self-service password reset.
+//!
+//! A reset request e-mails a single-use token to the address of the account;
+//! the new password is then set with that token (see
+//! [`AuthService::reset_password`]). The response to a request is the same
+//! whether or not the address belongs to an account, and the e-mail is sent
+//! in the background so response times do not tell either.
+//!
+//! Requests are rate limited per client and per e-mail address, to prevent
+//! mail flooding and probing of addresses.
+
+use chrono::Duration;
+use std::sync::Arc;
+
+use peilbeheer_core::User;
+
+use crate::auth_service::{AuthError, AuthService};
+use crate::mailer::{EmailMessage, Mailer};
+use crate::rate_limit::RateLimiter;
+
+/// Frontend page that accepts the token as `?token=` query parameter
+const PASSWORD_RESET_URL_ENV: &str = "PASSWORD_RESET_URL";
+/// Requests allowed per client within the window
+const MAX_REQUESTS_PER_CLIENT: usize = 10;
+/// Requests allowed per e-mail address within the window
+const MAX_REQUESTS_PER_ADDRESS: usize = 3;
+/// Rate limit window (minutes)
+const RATE_LIMIT_WINDOW_MINUTES: i64 = 60;
+
+/// Password reset configuration.
+#[derive(Debug, Clone)]
+pub struct PasswordResetConfig {
+    /// Page linked from the e-mail; without it the e-mail contains the bare token
+    pub reset_url: Option<String>,
+    pub max_requests_per_client: usize,
+    pub max_requests_per_address: usize,
+    pub rate_limit_window: Duration,
+}
+
+impl Default for PasswordResetConfig {
+    fn default() -> Self {
+        Self {
+            reset_url: std::env::var(PASSWORD_RESET_URL_ENV)
+                .ok()
+                .filter(|v| !v.is_empty()),
+            max_requests_per_client: MAX_REQUESTS_PER_CLIENT,
+            max_requests_per_address: MAX_REQUESTS_PER_ADDRESS,
+            rate_limit_window: Duration::minutes(RATE_LIMIT_WINDOW_MINUTES),
+        }
+    }
+}
+
+/// Password reset errors.
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordResetError {
+    #[error("Too many password reset requests, retry in {} seconds", .0.num_seconds())]
+    RateLimited(Duration),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+}
+
+/// Password reset service.
+pub struct PasswordResetService {
+    auth: Arc<AuthService>,
+    mailer: Arc<dyn Mailer>,
+    config: PasswordResetConfig,
+    per_client: RateLimiter,
+    per_address: RateLimiter,
+}
+
+impl PasswordResetService {
+    /// Create a new password reset service.
+    pub fn new(
+        auth: Arc<AuthService>,
+        mailer: Arc<dyn Mailer>,
+        config: PasswordResetConfig,
+    ) -> Self {
+        let per_client = RateLimiter::new(config.max_requests_per_client, config.rate_limit_window);
+        let per_address =
+            RateLimiter::new(config.max_requests_per_address, config.rate_limit_window);
+
+        Self {
+            auth,
+            mailer,
+            config,
+            per_client,
+            per_address,
+        }
+    }
+
+    /// Handle a reset request from `client` (its address) for `email`.
+    ///
+    /// Succeeds for unknown addresses too; only rate limiting and database
+    /// errors are reported.
+    pub fn request(&self, email: &str, client: &str) -> Result<(), PasswordResetError> {
+        let email = email.trim();
+        self.per_client
+            .check(client)
+            .map_err(PasswordResetError::RateLimited)?;
+        self.per_address
+            .check(&email.to_lowercase())
+            .map_err(PasswordResetError::RateLimited)?;
+
+        let Some((user, token)) = self.auth.create_password_reset_token(email)? else {
+            tracing::debug!("Password reset requested for unknown or inactive address");
+            return Ok(());
+        };
+
+        let message = self.reset_message(&user, &token);
+        let mailer = self.mailer.clone();
+        tokio::spawn(async move {
+            if let Err(e) = mailer.send(&message).await {
+                tracing::warn!("Failed to send password reset e-mail to {}: {}", message.to, e);
+            }
+        });
+
+        tracing::info!("Password reset requested for user {}", user.username);
+        Ok(())
+    }
+
+    /// Set a new password with a reset token.
+    pub fn confirm(&self, token: &str, new_password: &str) -> Result<(), PasswordResetError> {
+        Ok(self.auth.reset_password(token.trim(), new_password)?)
+    }
+
+    /// E-mail with the reset link (or the bare token).
+    fn reset_message(&self, user: &User, token: &str) -> EmailMessage {
+        let (kind, instruction) = match &self.config.reset_url {
+            Some(url) => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                (
+                    "link",
+                    format!(
+                        "Open de volgende link om een nieuw wachtwoord in te stellen:\n\n{}{}token={}",
+                        url, separator, token
+                    ),
+                )
+            }
+            None => (
+                "code",
+                format!(
+                    "Gebruik de volgende code om een nieuw wachtwoord in te stellen:\n\n{}",
+                    token
+                ),
+            ),
+        };
+
+        EmailMessage {
+            to: user.email.clone(),
+            subject: "Wachtwoord herstellen - Peilbeheer HHVR".to_string(),
+            body: format!(
+                "Beste {},\n\nEr is gevraagd om het wachtwoord van je account te herstellen. {}\n\n\
+                 De {} is {} minuten geldig en kan één keer worden gebruikt. Heb je dit niet \
+                 zelf aangevraagd? Dan kun je deze e-mail negeren.\n",
+                user.full_name.as_deref().unwrap_or(&user.username),
+                instruction,
+                kind,
+                self.auth.password_reset_token_minutes()
+            ),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Solar power forecast for pumping stations with solar panels.
+//!
+//! The expected generation of a [`PvInstallatie`] is requested from
+//! Forecast.Solar and reduced to hourly means by
+//! [`peilbeheer_core::energie::opwek_per_uur`], usable as `opwek_kw` of an
+//! optimization.
+
+use chrono::{DateTime, Utc};
+use peilbeheer_core::energie::{PvInstallatie, opwek_per_uur};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Longest forecast that can be requested (hours).
+pub const MAX_UREN: usize = 48;
+
+/// PV forecast client configuration.
+#[derive(Debug, Clone)]
+pub struct PvForecastConfig {
+    pub base_url: String,
+    /// API key of a paid plan (optional, the public API is rate limited)
+    pub api_key: Option<String>,
+}
+
+impl Default for PvForecastConfig {
+    fn default() -> Self {
+        Self {
+            base_url: std::env::var("PV_FORECAST_BASE_URL")
+                .unwrap_or_else(|_| "https://api.forecast.solar".to_string()),
+            api_key: std::env::var("PV_FORECAST_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+        }
+    }
+}
+
+/// Errors of the PV forecast client.
+#[derive(Debug, Error)]
+pub enum PvForecastError {
+    #[error("Invalid installation: {0}")]
+    InvalidInstallation(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("Forecast.Solar returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+}
+
+/// Expected solar generation of an installation.
+#[derive(Debug, Clone, Serialize)]
+pub struct PvVerwachting {
+    /// Start of the first hour
+    pub start: DateTime<Utc>,
+    /// Mean generated power per hour (kW), from `start`
+    pub opwek_kw: Vec<f64>,
+    pub bron: String,
+    pub opgehaald_op: DateTime<Utc>,
+}
+
+/// Client for the Forecast.Solar estimate API.
+pub struct PvForecastClient {
+    config: PvForecastConfig,
+    http_client: HttpClient,
+}
+
+impl PvForecastClient {
+    pub fn new(config: PvForecastConfig) -> Self {
+        Self {
+            config,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    /// Hourly generation of `installatie` for `uren` hours from `start`.
+    pub async fn opwek_verwachting(
+        &self,
+        installatie: &PvInstallatie,
+        start: DateTime<Utc>,
+        uren: usize,
+    ) -> Result<PvVerwachting, PvForecastError> {
+        valideer(installatie)?;
+
+        let base = self.config.base_url.trim_end_matches('/');
+        let base = match &self.config.api_key {
+            Some(key) => format!("{}/{}", base, key),
+            None => base.to_string(),
+        };
+        let url = format!(
+            "{}/estimate/{}/{}/{}/{}/{}",
+            base,
+            installatie.lat,
+            installatie.lon,
+            installatie.helling,
+            installatie.azimut,
+            installatie.vermogen_kwp
+        );
+        debug!(
+            "Forecast.Solar request: {}",
+            redact_key(&url, self.config.api_key.as_deref())
+        );
+
+        let request = self
+            .http_client
+            .get(&url)
+            .header("Accept", "application/json")
+            .query(&[("time", "iso8601")]);
+        // The key is part of the path, keep it out of the error message
+        let response = self.http_client.send(request).await.map_err(|e| match e {
+            HttpError::Request(e) => HttpError::Request(e.without_url()),
+            other => other,
+        })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(PvForecastError::Api { status, message });
+        }
+        let body: serde_json::Value = response.json().await.map_err(HttpError::from)?;
+
+        Ok(PvVerwachting {
+            start,
+            opwek_kw: opwek_per_uur(&parse_watts(&body)?, start, uren),
+            bron: "forecast.solar".to_string(),
+            opgehaald_op: Utc::now(),
+        })
+    }
+}
+
+fn valideer(installatie: &PvInstallatie) -> Result<(), PvForecastError> {
+    if !(-90.0..=90.0).contains(&installatie.lat) || !(-180.0..=180.0).contains(&installatie.lon) {
+        return Err(PvForecastError::InvalidInstallation(format!(
+            "invalid location {}, {}",
+            installatie.lat, installatie.lon
+        )));
+    }
+    if !(0.0..=90.0).contains(&installatie.helling)
+        || !(-180.0..=180.0).contains(&installatie.azimut)
+    {
+        return Err(PvForecastError::InvalidInstallation(
+            "helling must be 0-90 and azimut -180-180 degrees".to_string(),
+        ));
+    }
+    if installatie.vermogen_kwp <= 0.0 {
+        return Err(PvForecastError::InvalidInstallation(
+            "vermogen_kwp must be greater than 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// `url` with the API key replaced, for logging.
+fn redact_key(url: &str, api_key: Option<&str>) -> String {
+    match api_key {
+        Some(key) if !key.is_empty() => url.replace(key, "***"),
+        _ => url.to_string(),
+    }
+}
+
+/// (time, power in W) of the `result.watts` of an estimate.
+fn parse_watts(body: &serde_json::Value) -> Result<Vec<(DateTime<Utc>, f64)>, PvForecastError> {
+    let watts = body["result"]["watts"]
+        .as_object()
+        .ok_or_else(|| PvForecastError::Parse("result.watts is missing".to_string()))?;
+    watts
+        .iter()
+        .map(|(tijd, watt)| {
+            let tijd = DateTime::parse_from_rfc3339(tijd)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_| PvForecastError::Parse(format!("invalid time {}", tijd)))?;
+            let watt = watt
+                .as_f64()
+                .ok_or_else(|| PvForecastError::Parse(format!("invalid value {}", watt)))?;
+            Ok((tijd, watt))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_watts() {
+        let body = json!({
+            "result": {
+                "watts": {
+                    "2025-06-01T05:30:00+02:00": 0,
+                    "2025-06-01T12:00:00+02:00": 4200.5
+                },
+                "watt_hours_day": {"2025-06-01": 21000}
+            },
+            "message": {"code": 0, "type": "success"}
+        });
+        let watts = parse_watts(&body).unwrap();
+        assert_eq!(watts.len(), 2);
+        assert!(
+            watts
+                .iter()
+                .any(|(t, w)| t.to_rfc3339() == "2025-06-01T10:00:00+00:00" && *w == 4200.5)
+        );
+
+        assert!(parse_watts(&json!({"message": {"code": 429}})).is_err());
+    }
+
+    #[test]
+    fn test_redact_key() {
+        let url = "https://api.forecast.solar/geheim/estimate/52.16/4.49/30/0/25";
+        assert_eq!(
+            redact_key(url, Some("geheim")),
+            "https://api.forecast.solar/***/estimate/52.16/4.49/30/0/25"
+        );
+        assert_eq!(redact_key(url, None), url);
+    }
+
+    #[test]
+    fn test_valideer() {
+        let installatie = PvInstallatie {
+            lat: 52.16,
+            lon: 4.49,
+            helling: 30.0,
+            azimut: 0.0,
+            vermogen_kwp: 25.0,
+        };
+        assert!(valideer(&installatie).is_ok());
+        assert!(
+            valideer(&PvInstallatie {
+                vermogen_kwp: 0.0,
+                ..installatie.clone()
+            })
+            .is_err()
+        );
+        assert!(
+            valideer(&PvInstallatie {
+                lat: 95.0,
+                ..installatie
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Imbalance and intraday prices per quarter hour.
+//!
+//! Besides the day-ahead hourly prices of EnergyZero the optimization can
+//! steer on prices per imbalance settlement period (15 minutes):
+//! - TenneT settlement prices (imbalance), published shortly after each
+//!   quarter hour.
+//! - Intraday prices from a configurable source returning a list of
+//!   `{start, price_eur_mwh}` entries.
+//!
+//! Both are converted to quarter entries of [`UurPrijs`] by
+//! [`kwartier_uur_prijzen`], which the optimizer uses on top of the
+//! day-ahead prices.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use peilbeheer_core::energie::{PrijsSignaal, UurPrijs};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::info;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Quarter price client configuration.
+#[derive(Debug, Clone)]
+pub struct QuarterPriceConfig {
+    pub tennet_base_url: String,
+    pub tennet_api_key: Option<String>,
+    /// Field of the settlement price used for consumption
+    pub tennet_price_field: String,
+    /// URL of the intraday prices; `{from}` and `{to}` are replaced by
+    /// RFC 3339 timestamps
+    pub intraday_url: Option<String>,
+}
+
+impl Default for QuarterPriceConfig {
+    fn default() -> Self {
+        Self {
+            tennet_base_url: std::env::var("TENNET_BASE_URL")
+                .unwrap_or_else(|_| "https://api.tennet.eu/publications/v1".to_string()),
+            tennet_api_key: std::env::var("TENNET_API_KEY")
+                .ok()
+                .filter(|k| !k.is_empty()),
+            tennet_price_field: std::env::var("TENNET_PRICE_FIELD")
+                .unwrap_or_else(|_| "shortage".to_string()),
+            intraday_url: std::env::var("INTRADAY_PRICES_URL")
+                .ok()
+                .filter(|u| !u.is_empty()),
+        }
+    }
+}
+
+/// Errors of the quarter price client.
+#[derive(Debug, Error)]
+pub enum QuarterPriceError {
+    #[error("{0} prices are not configured ({1})")]
+    NotConfigured(&'static str, &'static str),
+    #[error("Day-ahead prices are hourly, use /energieprijzen")]
+    HourlySignal,
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("API returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+}
+
+/// Price of one quarter hour.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct KwartierPrijs {
+    pub start: DateTime<Utc>,
+    pub prijs_eur_kwh: f64,
+}
+
+/// Client for imbalance (TenneT) and intraday prices.
+pub struct QuarterPriceClient {
+    config: QuarterPriceConfig,
+    http_client: HttpClient,
+}
+
+impl QuarterPriceClient {
+    pub fn new(config: QuarterPriceConfig) -> Self {
+        Self {
+            config,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    /// Quarter prices of one (UTC) day for an imbalance or intraday signal.
+    pub async fn dag_prijzen(
+        &self,
+        signaal: PrijsSignaal,
+        datum: NaiveDate,
+    ) -> Result<Vec<KwartierPrijs>, QuarterPriceError> {
+        let van = datum.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        self.prijzen(signaal, van, van + Duration::days(1)).await
+    }
+
+    /// Quarter prices starting in `[van, tot)`, sorted by start.
+    pub async fn prijzen(
+        &self,
+        signaal: PrijsSignaal,
+        van: DateTime<Utc>,
+        tot: DateTime<Utc>,
+    ) -> Result<Vec<KwartierPrijs>, QuarterPriceError> {
+        let (request, velden): (_, Vec<&str>) = match signaal {
+            PrijsSignaal::DayAhead => return Err(QuarterPriceError::HourlySignal),
+            PrijsSignaal::Onbalans => {
+                let api_key = self.config.tennet_api_key.as_deref().ok_or(
+                    QuarterPriceError::NotConfigured("Imbalance", "TENNET_API_KEY"),
+                )?;
+                let url = format!(
+                    "{}/settlement-prices",
+                    self.config.tennet_base_url.trim_end_matches('/')
+                );
+                let request = self
+                    .http_client
+                    .get(&url)
+                    .header("apikey", api_key)
+                    .header("Accept", "application/json")
+                    .query(&[
+                        ("date_from", van.format("%d-%m-%Y %H:%M:%S").to_string()),
+                        ("date_to", tot.format("%d-%m-%Y %H:%M:%S").to_string()),
+                    ]);
+                (request, vec![self.config.tennet_price_field.as_str()])
+            }
+            PrijsSignaal::Intraday => {
+                let url = self
+                    .config
+                    .intraday_url
+                    .as_deref()
+                    .ok_or(QuarterPriceError::NotConfigured(
+                        "Intraday",
+                        "INTRADAY_PRICES_URL",
+                    ))?
+                    .replace("{from}", &van.to_rfc3339())
+                    .replace("{to}", &tot.to_rfc3339());
+                (self.http_client.get(&url), vec!["price_eur_mwh", "price"])
+            }
+        };
+
+        let response = self.http_client.send(request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(QuarterPriceError::Api { status, message });
+        }
+        let body: serde_json::Value = response.json().await.map_err(HttpError::from)?;
+
+        let prijzen: Vec<KwartierPrijs> = parse_kwartier_prijzen(&body, &velden)?
+            .into_iter()
+            .filter(|p| p.start >= van && p.start < tot)
+            .collect();
+        info!("{:?}: {} kwartierprijzen opgehaald", signaal, prijzen.len());
+        Ok(prijzen)
+    }
+}
+
+/// Fields holding the start of a quarter hour.
+const START_VELDEN: [&str; 4] = ["timeInterval_start", "isp_start", "start", "datetime"];
+
+/// Quarter prices in a JSON response, in €/kWh.
+///
+/// Every object with a start time and one of `prijs_velden` (€/MWh) is a
+/// price, wherever it is nested, so both the TenneT publication format and
+/// a plain list are accepted. A later entry for the same quarter replaces
+/// an earlier one.
+pub fn parse_kwartier_prijzen(
+    body: &serde_json::Value,
+    prijs_velden: &[&str],
+) -> Result<Vec<KwartierPrijs>, QuarterPriceError> {
+    let mut prijzen = std::collections::BTreeMap::new();
+    let mut stack = vec![body];
+    while let Some(value) = stack.pop() {
+        match value {
+            serde_json::Value::Array(items) => stack.extend(items.iter().rev()),
+            serde_json::Value::Object(map) => {
+                let start = START_VELDEN
+                    .iter()
+                    .find_map(|veld| map.get(*veld).and_then(|v| v.as_str()))
+                    .and_then(parse_tijd);
+                let prijs = prijs_velden.iter().find_map(|veld| {
+                    map.get(*veld).and_then(|v| {
+                        v.as_f64()
+                            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                    })
+                });
+                match (start, prijs) {
+                    (Some(start), Some(prijs)) => {
+                        prijzen.insert(start, prijs / 1000.0);
+                    }
+                    _ => stack.extend(map.values()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if prijzen.is_empty() && !body.is_null() && body.as_array().is_none_or(|a| !a.is_empty()) {
+        return Err(QuarterPriceError::Parse(format!(
+            "no prices with fields {:?}",
+            prijs_velden
+        )));
+    }
+    Ok(prijzen
+        .into_iter()
+        .map(|(start, prijs_eur_kwh)| KwartierPrijs {
+            start,
+            prijs_eur_kwh,
+        })
+        .collect())
+}
+
+fn parse_tijd(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").map(|dt| dt.and_utc())
+        })
+        .ok()
+}
+
+/// Quarter entries of [`UurPrijs`] for the `uren` hours from `start`: `uur`
+/// is the hour after `start`, `kwartier` the quarter within that hour.
+pub fn kwartier_uur_prijzen(
+    prijzen: &[KwartierPrijs],
+    start: DateTime<Utc>,
+    uren: usize,
+) -> Vec<UurPrijs> {
+    prijzen
+        .iter()
+        .filter_map(|p| {
+            let minuten = (p.start - start).num_minutes();
+            if !(0..uren as i64 * 60).contains(&minuten) {
+                return None;
+            }
+            Some(UurPrijs::kwartier(
+                (minuten / 60) as u8,
+                ((minuten % 60) / 15) as u8,
+                p.prijs_eur_kwh,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_kwartier_prijzen() {
+        let tennet = json!({
+            "Response": {"TimeSeries": [{"Period": {"Points": [
+                {"isp": "1", "timeInterval_start": "2025-03-10T00:00:00Z", "shortage": "85.5", "surplus": 80.0},
+                {"isp": "2", "timeInterval_start": "2025-03-10T00:15:00Z", "shortage": -20.0, "surplus": -25.0}
+            ]}}]}
+        });
+        let prijzen = parse_kwartier_prijzen(&tennet, &["shortage"]).unwrap();
+        assert_eq!(prijzen.len(), 2);
+        assert!((prijzen[0].prijs_eur_kwh - 0.0855).abs() < 1e-12);
+        assert!((prijzen[1].prijs_eur_kwh + 0.02).abs() < 1e-12);
+
+        let intraday = json!([
+            {"start": "2025-03-10T01:45:00+01:00", "price_eur_mwh": 100.0}
+        ]);
+        let prijzen = parse_kwartier_prijzen(&intraday, &["price_eur_mwh", "price"]).unwrap();
+        assert_eq!(prijzen[0].start.to_rfc3339(), "2025-03-10T00:45:00+00:00");
+
+        assert!(
+            parse_kwartier_prijzen(&json!([]), &["price"])
+                .unwrap()
+                .is_empty()
+        );
+        assert!(parse_kwartier_prijzen(&json!({"foo": 1}), &["price"]).is_err());
+    }
+
+    #[test]
+    fn test_kwartier_uur_prijzen() {
+        let start = DateTime::parse_from_rfc3339("2025-03-10T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let prijs = |minuten: i64| KwartierPrijs {
+            start: start + Duration::minutes(minuten),
+            prijs_eur_kwh: 0.1,
+        };
+        let prijzen =
+            kwartier_uur_prijzen(&[prijs(-15), prijs(0), prijs(75), prijs(24 * 60)], start, 24);
+        assert_eq!(
+            prijzen,
+            vec![UurPrijs::kwartier(0, 0, 0.1), UurPrijs::kwartier(1, 1, 0.1)]
+        );
+    }
+}
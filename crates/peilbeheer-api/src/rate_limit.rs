@@ -0,0 +1,86 @@
+//! In-memory rate limiting.
+//!
+//! Attempts are counted per key (e.g. a client address or an e-mail address)
+//! in a sliding window. The state lives in the process, so limits apply per
+//! API instance and start over after a restart.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Number of tracked keys above which idle keys are dropped
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Sliding-window rate limiter.
+pub struct RateLimiter {
+    max_attempts: usize,
+    window: Duration,
+    attempts: Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>,
+}
+
+impl RateLimiter {
+    /// Allow `max_attempts` per key within `window`.
+    pub fn new(max_attempts: usize, window: Duration) -> Self {
+        Self {
+            max_attempts,
+            window,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record an attempt for `key`.
+    ///
+    /// Returns the time until the next attempt is allowed when the limit is
+    /// reached; rejected attempts are not counted.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Utc::now())
+    }
+
+    /// Record an attempt for `key` at `at`.
+    pub fn check_at(&self, key: &str, at: DateTime<Utc>) -> Result<(), Duration> {
+        let mut attempts = self.attempts.lock().unwrap();
+        let window_start = at - self.window;
+
+        if attempts.len() > PRUNE_THRESHOLD {
+            attempts.retain(|_, times| times.back().is_some_and(|last| *last > window_start));
+        }
+
+        let times = attempts.entry(key.to_string()).or_default();
+        while times.front().is_some_and(|first| *first <= window_start) {
+            times.pop_front();
+        }
+
+        if times.len() >= self.max_attempts {
+            let oldest = times.front().copied().unwrap_or(at);
+            return Err(oldest + self.window - at);
+        }
+
+        times.push_back(at);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sliding_window() {
+        let limiter = RateLimiter::new(2, Duration::minutes(10));
+        let start = Utc.with_ymd_and_hms(2025, 3, 10, 8, 0, 0).unwrap();
+
+        assert!(limiter.check_at("a", start).is_ok());
+        assert!(limiter.check_at("a", start + Duration::minutes(4)).is_ok());
+        assert_eq!(
+            limiter.check_at("a", start + Duration::minutes(5)),
+            Err(Duration::minutes(5))
+        );
+        // Other keys have their own budget
+        assert!(limiter.check_at("b", start + Duration::minutes(5)).is_ok());
+
+        // The first attempt leaves the window
+        assert!(limiter.check_at("a", start + Duration::minutes(10)).is_ok());
+        assert!(limiter.check_at("a", start + Duration::minutes(11)).is_err());
+    }
+}
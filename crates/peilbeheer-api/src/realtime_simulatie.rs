@@ -0,0 +1,529 @@
+//! Realtime network simulation fed by live Fews data.
+//!
+//! Every interval the service starts the network simulation of the
+//! configured topology from the latest measured water level of each
+//! peilgebied (series `<peilgebied>|waterstand`, filled by the Fews sync),
+//! computes a few hours ahead with the precipitation forecast and stores the
+//! expected water levels as forecast series (see
+//! [`TimeSeriesService::import_realtime_verwachting`]). With a Fews module
+//! instance configured the forecast is also written to Fews, under the
+//! location and parameter ids of the peilgebied's sync config.
+//!
+//! Precipitation comes from the first configured forecast qualifier with
+//! data for the peilgebied; without a forecast the last measured hour is
+//! assumed to continue.
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+use tokio::sync::RwLock;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::timeseries::{TimeSeriesId, TimeSeriesQuery};
+use peilbeheer_core::{
+    FewsSyncTrigger, FewsTimeSeries, FewsTimeSeriesHeader, FewsTimeSeriesPoint,
+    FewsTimeSeriesResponse, FewsTimeStep, FewsValueType,
+};
+use peilbeheer_simulatie::{
+    Beginstaat, HistorischePeriode, NetwerkTopologie, PeilgebiedId, StrategyType, Verwachting,
+    reken_vooruit,
+};
+
+use crate::db::Database;
+use crate::fews_client::{FewsClient, FewsSyncService};
+use crate::timeseries_service::TimeSeriesService;
+
+/// Longest forecast horizon (hours)
+pub const MAX_HORIZON_UREN: usize = 48;
+
+/// Realtime simulation configuration.
+#[derive(Debug, Clone)]
+pub struct RealtimeConfig {
+    /// Whether the periodic loop runs
+    pub enabled: bool,
+    /// Time between runs (seconds)
+    pub interval_secs: u64,
+}
+
+impl Default for RealtimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: std::env::var("REALTIME_SIM_ENABLED")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            interval_secs: std::env::var("REALTIME_SIM_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(900),
+        }
+    }
+}
+
+/// Settings of the realtime mode, stored in `realtime_simulatie`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealtimeInstelling {
+    pub topologie: NetwerkTopologie,
+    #[serde(default)]
+    pub strategy: StrategyType,
+    /// Hours to compute ahead
+    #[serde(default = "default_horizon_uren")]
+    pub horizon_uren: usize,
+    /// Oldest measurement still used as starting level (minutes)
+    #[serde(default = "default_max_meetleeftijd_min")]
+    pub max_meetleeftijd_min: i64,
+    /// Qualifiers of the `neerslag` forecast series, in order of preference
+    #[serde(default = "default_neerslag_qualifiers")]
+    pub neerslag_qualifiers: Vec<String>,
+    /// Sync the peilgebieden with Fews before each run
+    #[serde(default)]
+    pub fews_sync: bool,
+    /// Fews module instance to publish the forecast under; without one the
+    /// forecast is only stored locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fews_module_instance_id: Option<String>,
+}
+
+fn default_horizon_uren() -> usize {
+    6
+}
+
+fn default_max_meetleeftijd_min() -> i64 {
+    120
+}
+
+fn default_neerslag_qualifiers() -> Vec<String> {
+    vec!["forecast".to_string(), "forecast:knmi".to_string()]
+}
+
+impl RealtimeInstelling {
+    /// Validate the settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_HORIZON_UREN).contains(&self.horizon_uren) {
+            return Err(format!("Horizon must be 1 to {} hours", MAX_HORIZON_UREN));
+        }
+        if self.max_meetleeftijd_min <= 0 {
+            return Err("Maximum measurement age must be positive".to_string());
+        }
+        self.topologie.valideer().map_err(|e| e.to_string())
+    }
+}
+
+/// Outcome of one realtime run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RealtimeRun {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    pub verwachting: Option<Verwachting>,
+    /// Peilgebieden without a recent measurement, started at streefpeil
+    pub zonder_meting: Vec<PeilgebiedId>,
+    /// Source of the precipitation per peilgebied: a forecast qualifier,
+    /// `persistentie` or `geen`
+    pub neerslagbron: HashMap<PeilgebiedId, String>,
+    pub points_written: usize,
+    pub fews_gepubliceerd: bool,
+    pub errors: Vec<String>,
+}
+
+/// Periodic forecasts from the latest measurements.
+pub struct RealtimeSimulatie {
+    db: Arc<Database>,
+    timeseries: Arc<TimeSeriesService>,
+    fews_client: Arc<FewsClient>,
+    fews_sync: Arc<FewsSyncService>,
+    config: RealtimeConfig,
+    laatste_run: RwLock<Option<RealtimeRun>>,
+}
+
+impl RealtimeSimulatie {
+    /// Create a new realtime simulation service.
+    pub fn new(
+        db: Arc<Database>,
+        timeseries: Arc<TimeSeriesService>,
+        fews_client: Arc<FewsClient>,
+        fews_sync: Arc<FewsSyncService>,
+        config: RealtimeConfig,
+    ) -> Self {
+        Self {
+            db,
+            timeseries,
+            fews_client,
+            fews_sync,
+            config,
+            laatste_run: RwLock::new(None),
+        }
+    }
+
+    /// Start the periodic loop (no-op when disabled). Runs are skipped
+    /// while no settings are stored.
+    pub fn start(self: &Arc<Self>) {
+        if !self.config.enabled {
+            info!("Realtime simulation disabled");
+            return;
+        }
+
+        let service = Arc::clone(self);
+        let interval = StdDuration::from_secs(self.config.interval_secs.max(60));
+
+        tokio::spawn(async move {
+            info!("Realtime simulation started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                match service.run_once().await {
+                    Ok(Some(run)) if run.errors.is_empty() => info!(
+                        "Realtime forecast stored: {} points in {:.0} ms",
+                        run.points_written, run.duration_ms
+                    ),
+                    Ok(Some(run)) => warn!(
+                        "Realtime run finished with errors: {}",
+                        run.errors.join("; ")
+                    ),
+                    Ok(None) => debug!("No realtime simulation configured"),
+                    Err(e) => warn!("Realtime run failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Stored settings, if any.
+    pub fn instelling(&self) -> AnyhowResult<Option<RealtimeInstelling>> {
+        self.db.get_realtime_instelling()
+    }
+
+    /// Store or remove the settings.
+    pub fn set_instelling(&self, instelling: Option<&RealtimeInstelling>) -> AnyhowResult<()> {
+        self.db.set_realtime_instelling(instelling)
+    }
+
+    /// The most recent run.
+    pub async fn laatste_run(&self) -> Option<RealtimeRun> {
+        self.laatste_run.read().await.clone()
+    }
+
+    /// Compute and publish a forecast now; `None` without stored settings.
+    ///
+    /// Missing data and publication errors end up in the run; only reading
+    /// the settings can fail.
+    pub async fn run_once(&self) -> AnyhowResult<Option<RealtimeRun>> {
+        let Some(instelling) = self.instelling()? else {
+            return Ok(None);
+        };
+
+        let timer = Instant::now();
+        let started_at = Utc::now();
+        let mut run = RealtimeRun {
+            started_at,
+            duration_ms: 0.0,
+            verwachting: None,
+            zonder_meting: Vec::new(),
+            neerslagbron: HashMap::new(),
+            points_written: 0,
+            fews_gepubliceerd: false,
+            errors: Vec::new(),
+        };
+
+        let mut ids: Vec<&PeilgebiedId> = instelling.topologie.peilgebieden.keys().collect();
+        ids.sort();
+
+        if instelling.fews_sync {
+            for id in &ids {
+                if let Err(e) = self.fews_sync.run_sync(id, FewsSyncTrigger::Manual).await {
+                    run.errors.push(format!("Fews sync {}: {}", id, e));
+                }
+            }
+        }
+
+        // Whole minutes, so the forecast lines up with the measurements
+        let t0 = started_at
+            .duration_trunc(Duration::minutes(1))
+            .unwrap_or(started_at);
+        let mut beginstaat = Beginstaat {
+            t0,
+            waterstanden: HashMap::new(),
+            regen_per_uur: HashMap::new(),
+        };
+        for id in &ids {
+            match self.gemeten_waterstand(id, t0, &instelling).await {
+                Ok(Some(waterstand)) => {
+                    beginstaat.waterstanden.insert((*id).clone(), waterstand);
+                }
+                Ok(None) => run.zonder_meting.push((*id).clone()),
+                Err(e) => {
+                    run.errors.push(format!("{}: {}", id, e));
+                    run.zonder_meting.push((*id).clone());
+                }
+            }
+            match self.neerslag(id, t0, &instelling).await {
+                Ok((regen, bron)) => {
+                    beginstaat.regen_per_uur.insert((*id).clone(), regen);
+                    run.neerslagbron.insert((*id).clone(), bron);
+                }
+                Err(e) => run.errors.push(format!("{}: {}", id, e)),
+            }
+        }
+
+        let topologie = instelling.topologie.clone();
+        let strategy = instelling.strategy;
+        let horizon = instelling.horizon_uren;
+        let uitkomst = tokio::task::spawn_blocking(move || {
+            reken_vooruit(
+                &topologie,
+                &beginstaat,
+                horizon,
+                strategy.strategy().as_ref(),
+            )
+        })
+        .await?;
+
+        match uitkomst {
+            Ok(verwachting) => {
+                match self
+                    .timeseries
+                    .import_realtime_verwachting(&verwachting)
+                    .await
+                {
+                    Ok(results) => {
+                        run.points_written = results.iter().map(|r| r.points_written).sum()
+                    }
+                    Err(e) => run.errors.push(format!("Storing forecast failed: {}", e)),
+                }
+                if let Some(module_instance_id) = &instelling.fews_module_instance_id {
+                    match self.publish_fews(&verwachting, module_instance_id).await {
+                        Ok(()) => run.fews_gepubliceerd = true,
+                        Err(e) => run.errors.push(format!("Fews publication failed: {}", e)),
+                    }
+                }
+                run.verwachting = Some(verwachting);
+            }
+            Err(e) => run.errors.push(format!("Simulation failed: {}", e)),
+        }
+
+        run.duration_ms = timer.elapsed().as_secs_f64() * 1000.0;
+        *self.laatste_run.write().await = Some(run.clone());
+        Ok(Some(run))
+    }
+
+    /// Latest valid water level of a peilgebied no older than the maximum
+    /// measurement age.
+    async fn gemeten_waterstand(
+        &self,
+        peilgebied_id: &str,
+        t0: DateTime<Utc>,
+        instelling: &RealtimeInstelling,
+    ) -> AnyhowResult<Option<f64>> {
+        let query = TimeSeriesQuery::new(
+            TimeSeriesId::new(peilgebied_id, "waterstand"),
+            t0 - Duration::minutes(instelling.max_meetleeftijd_min),
+            t0 + Duration::minutes(1),
+        );
+        let series = self.timeseries.query(&query).await?;
+        Ok(series
+            .data
+            .iter()
+            .rev()
+            .find(|p| p.is_valid())
+            .map(|p| p.value))
+    }
+
+    /// Hourly precipitation (mm) over the horizon and its source.
+    async fn neerslag(
+        &self,
+        peilgebied_id: &str,
+        t0: DateTime<Utc>,
+        instelling: &RealtimeInstelling,
+    ) -> AnyhowResult<(Vec<f64>, String)> {
+        let eind = t0 + Duration::hours(instelling.horizon_uren as i64);
+        let periode = HistorischePeriode::nieuw(t0, eind)?;
+        for qualifier in &instelling.neerslag_qualifiers {
+            let series = self
+                .timeseries
+                .query(&TimeSeriesQuery::new(
+                    TimeSeriesId::with_qualifier(peilgebied_id, "neerslag", qualifier),
+                    t0,
+                    eind,
+                ))
+                .await?;
+            if series.data.iter().any(|p| p.is_valid()) {
+                let regen = periode.uursommen(series.data.iter().map(|p| (p.timestamp, p.value)));
+                return Ok((regen, qualifier.clone()));
+            }
+        }
+
+        // Persistence: the last measured hour continues
+        let gemeten = self
+            .timeseries
+            .query(&TimeSeriesQuery::new(
+                TimeSeriesId::new(peilgebied_id, "neerslag"),
+                t0 - Duration::hours(1),
+                t0,
+            ))
+            .await?;
+        if !gemeten.data.iter().any(|p| p.is_valid()) {
+            return Ok((vec![0.0; periode.uren()], "geen".to_string()));
+        }
+        let laatste_uur: f64 = gemeten
+            .data
+            .iter()
+            .filter(|p| p.is_valid())
+            .map(|p| p.value.max(0.0))
+            .sum();
+        Ok((
+            vec![laatste_uur; periode.uren()],
+            "persistentie".to_string(),
+        ))
+    }
+
+    /// Write the forecast to Fews, one series per peilgebied.
+    async fn publish_fews(
+        &self,
+        verwachting: &Verwachting,
+        module_instance_id: &str,
+    ) -> AnyhowResult<()> {
+        let configs = self.fews_sync.get_configs();
+        let time_series = verwachting
+            .peilgebieden
+            .iter()
+            .map(|peilgebied| {
+                let config = configs
+                    .iter()
+                    .find(|c| c.peilgebied_id == peilgebied.peilgebied_id);
+                let location_id = config
+                    .and_then(|c| c.location_mapping.get(&peilgebied.peilgebied_id))
+                    .unwrap_or(&peilgebied.peilgebied_id);
+                let parameter_id = config
+                    .and_then(|c| c.parameter_mapping.get("waterstand"))
+                    .map_or("waterstand", String::as_str);
+                fews_series(
+                    verwachting.t0,
+                    peilgebied,
+                    location_id,
+                    parameter_id,
+                    module_instance_id,
+                )
+            })
+            .collect();
+
+        self.fews_client
+            .write_timeseries(&FewsTimeSeriesResponse {
+                version: String::new(),
+                time_series,
+                only_headers: None,
+            })
+            .await
+    }
+}
+
+/// Forecast of a peilgebied as a Fews forecast series.
+fn fews_series(
+    t0: DateTime<Utc>,
+    peilgebied: &peilbeheer_simulatie::PeilgebiedVerwachting,
+    location_id: &str,
+    parameter_id: &str,
+    module_instance_id: &str,
+) -> FewsTimeSeries {
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let data: Vec<FewsTimeSeriesPoint> = peilgebied
+        .waarden
+        .iter()
+        .map(|w| FewsTimeSeriesPoint {
+            date: format(w.tijd),
+            value: w.waterstand,
+            flag: None,
+        })
+        .collect();
+
+    FewsTimeSeries {
+        header: FewsTimeSeriesHeader {
+            location_id: location_id.to_string(),
+            parameter_id: parameter_id.to_string(),
+            module_instance_id: module_instance_id.to_string(),
+            time_step: FewsTimeStep::Hour,
+            time_step_seconds: None,
+            start_date: data.first().map(|p| p.date.clone()).unwrap_or_default(),
+            end_date: data.last().map(|p| p.date.clone()).unwrap_or_default(),
+            units: "m NAP".to_string(),
+            type_description: String::new(),
+            value_type: FewsValueType::Instantaneous,
+            station_name: String::new(),
+            parameter_description: String::new(),
+            module_description: String::new(),
+            geo_delta: None,
+            geo_datum: None,
+            lat: None,
+            lon: None,
+            x: None,
+            y: None,
+            qualifier: None,
+            miss_val: None,
+            ensemble_id: None,
+            ensemble_member: None,
+            forecast_date: Some(format(t0)),
+        },
+        data,
+        misses: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peilbeheer_simulatie::{PeilgebiedVerwachting, Verwachtingswaarde};
+
+    #[test]
+    fn test_fews_series() {
+        let t0: DateTime<Utc> = "2025-06-01T12:00:00Z".parse().unwrap();
+        let peilgebied = PeilgebiedVerwachting {
+            peilgebied_id: "PG_001".to_string(),
+            gemeten: Some(-0.58),
+            waarden: vec![
+                Verwachtingswaarde {
+                    tijd: t0 + Duration::hours(1),
+                    waterstand: -0.57,
+                },
+                Verwachtingswaarde {
+                    tijd: t0 + Duration::hours(2),
+                    waterstand: -0.56,
+                },
+            ],
+            max_waterstand: -0.56,
+            overschrijding_vanaf: None,
+        };
+        let series = fews_series(t0, &peilgebied, "LOC_1", "H.sim", "Realtime");
+        assert!(series.header.is_forecast());
+        assert_eq!(
+            series.header.forecast_date.as_deref(),
+            Some("2025-06-01T12:00:00Z")
+        );
+        assert_eq!(series.header.start_date, "2025-06-01T13:00:00Z");
+        assert_eq!(series.header.end_date, "2025-06-01T14:00:00Z");
+        assert_eq!(series.data.len(), 2);
+        assert_eq!(series.data[1].value, -0.56);
+    }
+
+    #[test]
+    fn test_instelling_defaults() {
+        let instelling: RealtimeInstelling = serde_json::from_value(serde_json::json!({
+            "topologie": NetwerkTopologie::nieuw(),
+        }))
+        .unwrap();
+        assert_eq!(instelling.horizon_uren, 6);
+        assert_eq!(
+            instelling.neerslag_qualifiers,
+            vec!["forecast", "forecast:knmi"]
+        );
+        assert!(instelling.fews_module_instance_id.is_none());
+
+        let te_lang = RealtimeInstelling {
+            horizon_uren: MAX_HORIZON_UREN + 1,
+            ..instelling
+        };
+        assert!(te_lang.validate().is_err());
+    }
+}
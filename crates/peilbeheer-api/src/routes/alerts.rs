@@ -4,7 +4,7 @@
 
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
@@ -13,10 +13,15 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{error, info};
 
+use peilbeheer_core::{Claims, Permission};
 use peilbeheer_core::alert::*;
+use peilbeheer_core::alert_report::{AlertTrendReport, StatsBucket};
+use peilbeheer_core::maintenance::*;
 
-use crate::alert_service::AlertService;
+use crate::alert_evaluator::AlertEvaluator;
+use crate::alert_service::{AlertService, AlertServiceError};
 use crate::auth_service::AuthService;
+use crate::routes::auth::{ErrorResponse, authorize, authorize_scope};
 
 /// Response wrapper for API responses.
 #[derive(Debug, Serialize)]
@@ -80,6 +85,28 @@ pub struct ListAlertsQuery {
     pub offset: Option<u64>,
 }
 
+/// Query parameters for listing rule events.
+#[derive(Debug, Deserialize)]
+pub struct ListRuleEventsQuery {
+    pub limit: Option<u64>,
+}
+
+/// Query parameters for the alert trend report.
+#[derive(Debug, Deserialize)]
+pub struct AlertTrendQuery {
+    /// "day", "week" or "month" (default "day")
+    pub bucket: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// Query parameters for listing maintenance windows.
+#[derive(Debug, Deserialize)]
+pub struct ListMaintenanceWindowsQuery {
+    /// Only return windows that are active right now
+    pub active: Option<bool>,
+}
+
 /// Manual evaluation request.
 #[derive(Debug, Deserialize)]
 pub struct EvaluateRulesRequest {
@@ -91,14 +118,22 @@ pub struct EvaluateRulesRequest {
 pub struct EvaluationContextBody {
     pub values: HashMap<String, serde_json::Value>,
     pub source: Option<String>,
+    pub source_kind: Option<String>,
+    /// Delivery state per field, for `no_data` conditions
+    #[serde(default)]
+    pub heartbeats: HashMap<String, SeriesHeartbeat>,
 }
 
 /// List all alert rules.
 pub async fn list_rules(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Query(params): Query<ListRulesQuery>,
-) -> impl IntoResponse {
-    match service.list_rules().await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.list_rules().await {
         Ok(rules) => {
             let filtered: Vec<_> = rules
                 .into_iter()
@@ -125,30 +160,37 @@ pub async fn list_rules(
             error!("Failed to list rules: {}", e);
             Json(ApiResponse::<Vec<AlertRule>>::error(e.to_string()))
         }
-    }
+    })
 }
 
 /// Get a specific rule by ID.
 pub async fn get_rule(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match service.get_rule(&id).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_rule(&id).await {
         Ok(rule) => Json(ApiResponse::ok(rule)),
         Err(e) => {
             error!("Failed to get rule {}: {}", id, e);
             Json(ApiResponse::<AlertRule>::error(e.to_string()))
         }
-    }
+    })
 }
 
 /// Create a new alert rule.
 pub async fn create_rule(
     Extension(service): Extension<Arc<AlertService>>,
-    Extension(_auth): Extension<Arc<AuthService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Json(request): Json<CreateAlertRuleRequest>,
-) -> impl IntoResponse {
-    match service.create_rule(request, None).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.create_rule(request, Some(claims.sub)).await {
         Ok(rule) => {
             info!("Created alert rule: {}", rule.id);
             Json(ApiResponse::ok(rule))
@@ -157,16 +199,20 @@ pub async fn create_rule(
             error!("Failed to create rule: {}", e);
             Json(ApiResponse::<AlertRule>::error(e.to_string()))
         }
-    }
+    })
 }
 
 /// Update an existing rule.
 pub async fn update_rule(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
     Json(request): Json<UpdateAlertRuleRequest>,
-) -> impl IntoResponse {
-    match service.update_rule(&id, request).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.update_rule(&id, request).await {
         Ok(rule) => {
             info!("Updated alert rule: {}", id);
             Json(ApiResponse::ok(rule))
@@ -175,15 +221,19 @@ pub async fn update_rule(
             error!("Failed to update rule {}: {}", id, e);
             Json(ApiResponse::<AlertRule>::error(e.to_string()))
         }
-    }
+    })
 }
 
 /// Delete a rule.
 pub async fn delete_rule(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match service.delete_rule(&id).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.delete_rule(&id).await {
         Ok(()) => {
             info!("Deleted alert rule: {}", id);
             Json(ApiResponse::ok(serde_json::json!({"deleted": true})))
@@ -192,91 +242,389 @@ pub async fn delete_rule(
             error!("Failed to delete rule {}: {}", id, e);
             Json(ApiResponse::<serde_json::Value>::error(e.to_string()))
         }
-    }
+    })
+}
+
+/// List logged events of a rule (e.g. flapping started/ended).
+pub async fn list_rule_events(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(params): Query<ListRuleEventsQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.list_rule_events(&id, params.limit.unwrap_or(100)).await {
+        Ok(events) => Json(ApiResponse::ok(events)),
+        Err(e) => {
+            error!("Failed to list events for rule {}: {}", id, e);
+            Json(ApiResponse::<Vec<RuleEvent>>::error(e.to_string()))
+        }
+    })
 }
 
 /// List triggered alerts.
 pub async fn list_alerts(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Query(params): Query<ListAlertsQuery>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
     let query = build_alert_query(params);
-    match service.query_alerts(&query).await {
+    Ok(match service.query_alerts(&query).await {
         Ok(alerts) => Json(ApiResponse::ok(alerts)),
         Err(e) => {
             error!("Failed to list alerts: {}", e);
             Json(ApiResponse::<Vec<Alert>>::error(e.to_string()))
         }
-    }
+    })
 }
 
 /// Get a specific alert by ID.
 pub async fn get_alert(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match service.get_alert(&id).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_alert(&id).await {
         Ok(alert) => Json(ApiResponse::ok(alert)),
         Err(e) => {
             error!("Failed to get alert {}: {}", id, e);
             Json(ApiResponse::<Alert>::error(e.to_string()))
         }
+    })
+}
+
+/// Require the resources of an alert to be within the caller's scope.
+///
+/// An alert that cannot be looked up has no resources, which only users
+/// without a resource scope may act on.
+async fn authorize_alert_scope(
+    service: &AlertService,
+    claims: &Claims,
+    id: &str,
+) -> Result<(), ErrorResponse> {
+    let resources = service
+        .get_alert(id)
+        .await
+        .map(|alert| alert.affected_resources)
+        .unwrap_or_default();
+    authorize_scope(claims, &resources)
+}
+
+/// Status of a failed alert state change: 409 when the alert is not in a
+/// state that allows it.
+fn state_change_status(e: &anyhow::Error) -> StatusCode {
+    match e.downcast_ref::<AlertServiceError>() {
+        Some(AlertServiceError::InvalidState(_)) => StatusCode::CONFLICT,
+        _ => StatusCode::OK,
     }
 }
 
 /// Acknowledge an alert.
 pub async fn acknowledge_alert(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-    Json(request): Json<AcknowledgeAlertRequest>,
-) -> impl IntoResponse {
-    match service.acknowledge_alert(&id, request).await {
+    Json(mut request): Json<AcknowledgeAlertRequest>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::AlertsAcknowledge)?;
+    authorize_alert_scope(&service, &claims, &id).await?;
+    // Acknowledge on behalf of the authenticated user
+    request.user_id = claims.sub;
+
+    Ok(match service.acknowledge_alert(&id, request).await {
         Ok(alert) => {
             info!("Alert {} acknowledged", id);
-            Json(ApiResponse::ok(alert))
+            (StatusCode::OK, Json(ApiResponse::ok(alert)))
         }
         Err(e) => {
             error!("Failed to acknowledge alert {}: {}", id, e);
-            Json(ApiResponse::<Alert>::error(e.to_string()))
+            (state_change_status(&e), Json(ApiResponse::<Alert>::error(e.to_string())))
         }
-    }
+    })
 }
 
 /// Resolve an alert.
 pub async fn resolve_alert(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    match service.resolve_alert(&id).await {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::AlertsAcknowledge)?;
+    authorize_alert_scope(&service, &claims, &id).await?;
+
+    Ok(match service.resolve_alert(&id, Some(&claims.sub)).await {
         Ok(alert) => {
             info!("Alert {} resolved", id);
-            Json(ApiResponse::ok(alert))
+            (StatusCode::OK, Json(ApiResponse::ok(alert)))
         }
         Err(e) => {
             error!("Failed to resolve alert {}: {}", id, e);
-            Json(ApiResponse::<Alert>::error(e.to_string()))
+            (state_change_status(&e), Json(ApiResponse::<Alert>::error(e.to_string())))
         }
-    }
+    })
+}
+
+/// Add a comment to an alert.
+pub async fn add_alert_comment(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<CreateAlertCommentRequest>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::AlertsAcknowledge)?;
+    authorize_alert_scope(&service, &claims, &id).await?;
+
+    Ok(match service.add_comment(&id, &claims.sub, request).await {
+        Ok(comment) => Json(ApiResponse::ok(comment)),
+        Err(e) => {
+            error!("Failed to add comment to alert {}: {}", id, e);
+            Json(ApiResponse::<AlertComment>::error(e.to_string()))
+        }
+    })
+}
+
+/// List the comments of an alert.
+pub async fn list_alert_comments(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.list_comments(&id).await {
+        Ok(comments) => Json(ApiResponse::ok(comments)),
+        Err(e) => {
+            error!("Failed to list comments of alert {}: {}", id, e);
+            Json(ApiResponse::<Vec<AlertComment>>::error(e.to_string()))
+        }
+    })
+}
+
+/// Get the status history (audit trail) of an alert.
+pub async fn get_alert_history(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_history(&id).await {
+        Ok(history) => Json(ApiResponse::ok(history)),
+        Err(e) => {
+            error!("Failed to get history of alert {}: {}", id, e);
+            Json(ApiResponse::<Vec<AlertHistoryEntry>>::error(e.to_string()))
+        }
+    })
+}
+
+/// Get the escalation state of an alert.
+pub async fn get_alert_escalation(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_escalation(&id).await {
+        Ok(escalation) => Json(ApiResponse::ok(escalation)),
+        Err(e) => {
+            error!("Failed to get escalation for alert {}: {}", id, e);
+            Json(ApiResponse::<Option<AlertEscalation>>::error(e.to_string()))
+        }
+    })
 }
 
 /// Get alert statistics.
 pub async fn get_alert_stats(
     Extension(service): Extension<Arc<AlertService>>,
-) -> impl IntoResponse {
-    match service.get_stats().await {
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_stats().await {
         Ok(stats) => Json(ApiResponse::ok(stats)),
         Err(e) => {
             error!("Failed to get alert stats: {}", e);
             Json(ApiResponse::<AlertStats>::error(e.to_string()))
         }
+    })
+}
+
+/// Get time-bucketed alert statistics (triggers, MTTA, MTTR per period and per rule).
+///
+/// Defaults to daily buckets over the last 30 days.
+pub async fn get_alert_trend(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Query(params): Query<AlertTrendQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    let bucket = match params.bucket.as_deref() {
+        None => StatsBucket::Day,
+        Some(s) => match StatsBucket::from_str(s) {
+            Some(bucket) => bucket,
+            None => {
+                return Ok(Json(ApiResponse::<AlertTrendReport>::error(format!(
+                    "Invalid bucket '{}', expected day, week or month",
+                    s
+                ))));
+            }
+        },
+    };
+    let end = params
+        .end
+        .and_then(|s| parse_datetime_iso(&s))
+        .unwrap_or_else(chrono::Utc::now);
+    let start = params
+        .start
+        .and_then(|s| parse_datetime_iso(&s))
+        .unwrap_or(end - chrono::Duration::days(30));
+    if start >= end {
+        return Ok(Json(ApiResponse::<AlertTrendReport>::error(
+            "start must be before end",
+        )));
     }
+
+    Ok(match service.get_trend_report(bucket, start, end).await {
+        Ok(report) => Json(ApiResponse::ok(report)),
+        Err(e) => {
+            error!("Failed to build alert trend report: {}", e);
+            Json(ApiResponse::<AlertTrendReport>::error(e.to_string()))
+        }
+    })
+}
+
+/// List maintenance windows.
+pub async fn list_maintenance_windows(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Query(params): Query<ListMaintenanceWindowsQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    if params.active == Some(true) {
+        let windows = service.active_maintenance_windows(chrono::Utc::now()).await;
+        return Ok(Json(ApiResponse::ok(windows)));
+    }
+
+    Ok(match service.list_maintenance_windows().await {
+        Ok(windows) => Json(ApiResponse::ok(windows)),
+        Err(e) => {
+            error!("Failed to list maintenance windows: {}", e);
+            Json(ApiResponse::<Vec<MaintenanceWindow>>::error(e.to_string()))
+        }
+    })
+}
+
+/// Get a specific maintenance window by ID.
+pub async fn get_maintenance_window(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(match service.get_maintenance_window(&id).await {
+        Ok(window) => Json(ApiResponse::ok(window)),
+        Err(e) => {
+            error!("Failed to get maintenance window {}: {}", id, e);
+            Json(ApiResponse::<MaintenanceWindow>::error(e.to_string()))
+        }
+    })
+}
+
+/// Create a new maintenance window.
+pub async fn create_maintenance_window(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateMaintenanceWindowRequest>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.create_maintenance_window(request, Some(claims.sub)).await {
+        Ok(window) => {
+            info!("Created maintenance window: {}", window.id);
+            Json(ApiResponse::ok(window))
+        }
+        Err(e) => {
+            error!("Failed to create maintenance window: {}", e);
+            Json(ApiResponse::<MaintenanceWindow>::error(e.to_string()))
+        }
+    })
+}
+
+/// Update an existing maintenance window.
+pub async fn update_maintenance_window(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateMaintenanceWindowRequest>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.update_maintenance_window(&id, request).await {
+        Ok(window) => {
+            info!("Updated maintenance window: {}", id);
+            Json(ApiResponse::ok(window))
+        }
+        Err(e) => {
+            error!("Failed to update maintenance window {}: {}", id, e);
+            Json(ApiResponse::<MaintenanceWindow>::error(e.to_string()))
+        }
+    })
+}
+
+/// Delete a maintenance window.
+pub async fn delete_maintenance_window(
+    Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsManage)?;
+
+    Ok(match service.delete_maintenance_window(&id).await {
+        Ok(()) => {
+            info!("Deleted maintenance window: {}", id);
+            Json(ApiResponse::ok(serde_json::json!({"deleted": true})))
+        }
+        Err(e) => {
+            error!("Failed to delete maintenance window {}: {}", id, e);
+            Json(ApiResponse::<serde_json::Value>::error(e.to_string()))
+        }
+    })
 }
 
 /// Manually evaluate rules with given context.
 pub async fn evaluate_rules(
     Extension(service): Extension<Arc<AlertService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
     Json(request): Json<EvaluateRulesRequest>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsManage)?;
+
     // Convert JSON values to AlertValues
     let mut values = HashMap::new();
     for (key, json_val) in &request.context.values {
@@ -306,9 +654,11 @@ pub async fn evaluate_rules(
         values,
         time_series: HashMap::new(),
         source: request.context.source,
+        source_kind: request.context.source_kind,
+        heartbeats: request.context.heartbeats,
     };
 
-    match service.evaluate_rules(&context).await {
+    Ok(match service.evaluate_rules(&context).await {
         Ok(alerts) => {
             info!("Manual rule evaluation triggered {} alerts", alerts.len());
             Json(ApiResponse::ok(serde_json::json!({
@@ -320,7 +670,18 @@ pub async fn evaluate_rules(
             error!("Failed to evaluate rules: {}", e);
             Json(ApiResponse::<serde_json::Value>::error(e.to_string()))
         }
-    }
+    })
+}
+
+/// Get metrics of the periodic rule evaluator.
+pub async fn get_evaluator_metrics(
+    Extension(evaluator): Extension<Arc<AlertEvaluator>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AlertsRead)?;
+
+    Ok(Json(ApiResponse::ok(evaluator.metrics().await)))
 }
 
 /// Get alert categories.
@@ -349,6 +710,9 @@ pub async fn get_operators() -> impl IntoResponse {
         serde_json::json!({"value": "not_contains", "symbol": "not_contains", "label": "Does Not Contain", "types": ["string"]}),
         serde_json::json!({"value": "is_null", "symbol": "is_null", "label": "Is Null/Empty", "types": ["any"]}),
         serde_json::json!({"value": "is_not_null", "symbol": "is_not_null", "label": "Is Not Null", "types": ["any"]}),
+        serde_json::json!({"value": "rate_above", "symbol": "rate >", "label": "Rate of Change Above (per hour)", "types": ["number"]}),
+        serde_json::json!({"value": "rate_below", "symbol": "rate <", "label": "Rate of Change Below (per hour)", "types": ["number"]}),
+        serde_json::json!({"value": "no_data", "symbol": "no data", "label": "No New Data (missed deliveries)", "types": ["number"]}),
     ];
     Json(ApiResponse::ok(operators))
 }
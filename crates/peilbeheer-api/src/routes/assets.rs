@@ -4,10 +4,10 @@ use axum::{extract::Extension, extract::Query, Json};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-use crate::arcgis_client;
 use crate::config::Config;
 use crate::db::Database;
 use crate::error::ApiError;
+use crate::wfs_client;
 
 #[derive(Debug, Deserialize)]
 pub struct LayersQuery {
@@ -92,7 +92,7 @@ pub async fn get_assets_geojson(
     })))
 }
 
-/// POST /api/assets/sync - Sync alle lagen van ArcGIS.
+/// POST /api/assets/sync - Sync alle lagen van ArcGIS of WFS.
 pub async fn sync_assets(
     Extension(config): Extension<Arc<Config>>,
     Extension(db): Extension<Arc<Database>>,
@@ -100,13 +100,7 @@ pub async fn sync_assets(
     let mut results = Vec::new();
 
     for layer in &config.arcgis_layers {
-        match arcgis_client::fetch_layer_assets(
-            &layer.service_name,
-            layer.layer_id,
-            &layer.layer_type,
-        )
-        .await
-        {
+        match wfs_client::fetch_layer(layer).await {
             Ok(assets) => {
                 let count = db
                     .write_asset_registraties(&assets)
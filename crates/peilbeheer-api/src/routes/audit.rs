@@ -0,0 +1,103 @@
+//! Audit log API routes.
+//!
+//! Searching the log of mutating API calls is reserved for admins
+//! (`audit:read`).
+
+use axum::{
+    Json,
+    extract::{Extension, Query},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+
+use peilbeheer_core::{AuditQuery, Permission};
+
+use crate::audit_service::AuditService;
+use crate::auth_service::AuthService;
+use crate::routes::auth::{ErrorResponse, authorize};
+
+/// Response wrapper for API responses.
+#[derive(Debug, Serialize)]
+struct ApiResponse<T> {
+    success: bool,
+    data: Option<T>,
+    error: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    fn ok(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(message.into()),
+        }
+    }
+}
+
+/// Query parameters for searching the audit log.
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    /// User id or username
+    pub user: Option<String>,
+    pub method: Option<String>,
+    /// Part of the request path
+    pub path: Option<String>,
+    /// Text within the payload summary
+    pub search: Option<String>,
+    pub success: Option<bool>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// Search the audit log.
+pub async fn list_audit_log(
+    Extension(audit): Extension<Arc<AuditService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Query(params): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, ErrorResponse> {
+    authorize(&auth, &headers, Permission::AuditRead)?;
+
+    let query = AuditQuery {
+        user: params.user,
+        method: params.method,
+        path: params.path,
+        search: params.search,
+        success: params.success,
+        start_time: params.start_time.and_then(|s| parse_datetime_iso(&s)),
+        end_time: params.end_time.and_then(|s| parse_datetime_iso(&s)),
+        limit: params.limit,
+        offset: params.offset,
+    };
+
+    Ok(match audit.query(&query) {
+        Ok(entries) => (StatusCode::OK, Json(ApiResponse::ok(entries))),
+        Err(e) => {
+            error!("Failed to search audit log: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::error(e.to_string())),
+            )
+        }
+    })
+}
+
+/// Helper: Parse ISO datetime string.
+fn parse_datetime_iso(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
@@ -1,20 +1,67 @@
 //! Authentication and authorization routes.
 //!
 //! Endpoints for user login, logout, user management, and JWT token handling.
+//! Single sign-on logins go through `/auth/oidc/login` and the provider's
+//! redirect back to `/auth/oidc/callback`. With LDAP configured, `/auth/login`
+//! also accepts directory accounts. Machine clients authenticate with
+//! an API key in the `X-API-Key` header instead of a bearer token. Expired
+//! access tokens are renewed at `/auth/refresh` with a rotating refresh token.
+//!
+//! [`authorize`] checks a global permission; [`authorize_scope`] additionally
+//! limits users with a resource scope to their own peilgebieden and assets.
+//! Route groups without their own checks are guarded as a whole by the
+//! [`require_permission`] middleware.
+//!
+//! Two-factor enrolment runs under `/auth/2fa`: `enroll` returns a secret and
+//! QR code, `confirm` activates it with a first code and returns recovery
+//! codes. Users whose role requires 2FA but who have not enrolled get a
+//! permissionless token at login that only works for these endpoints.
+//!
+//! Every login starts a session; `/auth/sessions` lists the caller's active
+//! sessions and revokes them one by one or all but the current one. Admins
+//! manage the sessions of other users under `/auth/users/{id}/sessions`.
+//!
+//! Service accounts are created through `/auth/users` with `account_type`
+//! `service` and an `expires_at`; their API keys are created at
+//! `/auth/api-keys` with the account as `service_account_id`.
+//!
+//! Failed logins lock the account and the client address for an
+//! exponentially growing period. Admins see the lockouts and failed attempts
+//! at `/auth/lockouts` and `/auth/login-attempts` and lift a lockout with
+//! `DELETE /auth/lockouts/{scope}/{subject}`.
+//!
+//! Users who forgot their password request a reset e-mail at
+//! `/auth/password-reset/request` and set a new password with its token at
+//! `/auth/password-reset/confirm`.
 
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
-    response::{IntoResponse, Json, Response},
+    extract::{ConnectInfo, Extension, FromRequestParts, Path, Query, Request, State},
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{AUTHORIZATION, COOKIE, SET_COOKIE, UPGRADE, USER_AGENT},
+        request::Parts,
+    },
+    middleware::Next,
+    response::{IntoResponse, Json, Redirect, Response},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
+use peilbeheer_core::auth::API_KEY_ROLE;
 use peilbeheer_core::{
-    ChangePasswordRequest, CreateUserRequest, LoginRequest, LoginResponse, UpdateUserRequest, User,
+    ApiKey, ChangePasswordRequest, Claims, ClientInfo, CreateApiKeyRequest, CreateUserRequest,
+    CreatedApiKey, Lockout, LockoutScope, LoginAttempt, LoginRequest, LoginResponse,
+    PasswordResetConfirmRequest, PasswordResetRequest,
+    Permission, RecoveryCodes, RefreshTokenRequest, Session, TotpEnrollment, TwoFactorCodeRequest, TwoFactorPolicy, UpdateUserRequest, User,
 };
 
+use crate::auth_backend::BackendError;
 use crate::auth_service::{AuthError, AuthService};
+use crate::config::Config;
+use crate::login_throttle::LoginThrottle;
+use crate::oidc;
+use crate::password_reset::{PasswordResetError, PasswordResetService};
 
 /// Response wrapper for API errors.
 #[derive(Debug, Serialize)]
@@ -23,12 +70,294 @@ pub struct ErrorResponse {
     detail: Option<String>,
 }
 
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Verify the API key or bearer token of a request.
+///
+/// Returns the claims, or a 401 when no valid credentials are present.
+pub fn authenticate(auth: &AuthService, headers: &HeaderMap) -> Result<Claims, ErrorResponse> {
+    if let Some(key) = headers.get(API_KEY_HEADER) {
+        let key = key.to_str().unwrap_or_default().trim();
+        return auth.verify_api_key(key).map_err(|e| ErrorResponse {
+            error: "Unauthorized".to_string(),
+            detail: Some(e.to_string()),
+        });
+    }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ErrorResponse {
+            error: "Unauthorized".to_string(),
+            detail: Some("Missing bearer token".to_string()),
+        })?;
+
+    auth.verify_token(token.trim()).map_err(|e| ErrorResponse {
+        error: "Unauthorized".to_string(),
+        detail: Some(e.to_string()),
+    })
+}
+
+/// Verify the credentials of a request and require a permission.
+///
+/// Returns the claims, or a 401 when the credentials are missing or invalid
+/// and a 403 when the user or API key lacks `permission`.
+pub fn authorize(
+    auth: &AuthService,
+    headers: &HeaderMap,
+    permission: Permission,
+) -> Result<Claims, ErrorResponse> {
+    let claims = authenticate(auth, headers)?;
+
+    if !claims.has_permission(&permission) {
+        return Err(ErrorResponse {
+            error: "Insufficient permissions".to_string(),
+            detail: Some(format!("Requires permission '{}'", permission.as_str())),
+        });
+    }
+
+    Ok(claims)
+}
+
+/// Require that authorized claims cover all resources of an action.
+///
+/// Call after [`authorize`] once the resources (peilgebied or asset codes) of
+/// the target are known. Returns a 403 for users whose resource scope does
+/// not include all of them.
+pub fn authorize_scope(claims: &Claims, resources: &[String]) -> Result<(), ErrorResponse> {
+    if claims.scope.allows(resources) {
+        return Ok(());
+    }
+
+    let outside = claims.scope.outside(resources);
+    Err(ErrorResponse {
+        error: "Insufficient permissions".to_string(),
+        detail: Some(if outside.is_empty() {
+            "Not tied to a peilgebied or asset within your scope".to_string()
+        } else {
+            format!("Outside your scope: {}", outside.join(", "))
+        }),
+    })
+}
+
+/// Extractor for the authenticated caller (user or API key).
+///
+/// Rejects the request with a 401 when the credentials are missing or invalid.
+pub struct CurrentUser(pub Claims);
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let auth = parts
+            .extensions
+            .get::<Arc<AuthService>>()
+            .cloned()
+            .ok_or_else(|| ErrorResponse {
+                error: "Authentication unavailable".to_string(),
+                detail: None,
+            })?;
+
+        authenticate(&auth, &parts.headers).map(CurrentUser)
+    }
+}
+
+/// Query parameter carrying a bearer token on WebSocket upgrades
+const ACCESS_TOKEN_PARAM: &str = "access_token";
+
+/// Middleware requiring a permission for every route of a group.
+///
+/// Attach with `middleware::from_fn_with_state(permission, require_permission)`
+/// as route layer. Rejects the request with a 401 or 403 like [`authorize`].
+/// Browsers cannot set headers on a WebSocket upgrade, so there the bearer
+/// token may also be passed as `?access_token=`.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if is_websocket_upgrade(request.headers())
+        && !request.headers().contains_key(AUTHORIZATION)
+        && let Some(token) = query_access_token(request.uri().query())
+        && let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token))
+    {
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+
+    match authorize(&auth, request.headers(), permission) {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Middleware requiring valid credentials for every route of a group whose
+/// handlers check themselves what the caller may do.
+pub async fn require_authentication(
+    Extension(auth): Extension<Arc<AuthService>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match authenticate(&auth, request.headers()) {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// Value of the cookie `name` of a request.
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(cookie, _)| *cookie == name)
+        .map(|(_, value)| value)
+}
+
+fn query_access_token(query: Option<&str>) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == ACCESS_TOKEN_PARAM)
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Extractor for the client of a request, recorded with its session and
+/// used for login lockouts.
+///
+/// The address is the peer of the connection. Only when the peer is one of
+/// the configured `TRUSTED_PROXIES` is `X-Forwarded-For` followed, see
+/// [`client_address`].
+pub struct Client(pub ClientInfo);
+
+impl<S: Send + Sync> FromRequestParts<S> for Client {
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| peer.ip());
+        let trusted_proxies = parts
+            .extensions
+            .get::<Arc<Config>>()
+            .map(|config| config.trusted_proxies.as_slice())
+            .unwrap_or_default();
+
+        Ok(Client(ClientInfo {
+            user_agent: parts
+                .headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            ip_address: peer
+                .map(|peer| client_address(&parts.headers, peer, trusted_proxies).to_string()),
+        }))
+    }
+}
+
+/// Address of the client behind the trusted proxies.
+///
+/// Every proxy appends the address it received the request from to
+/// `X-Forwarded-For`, while the client can put anything in front. The chain is
+/// therefore followed from the peer backwards for as long as the hop is a
+/// trusted proxy; the first other address is the client.
+fn client_address(headers: &HeaderMap, peer: IpAddr, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let forwarded: Vec<&str> = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|chain| chain.split(','))
+        .map(str::trim)
+        .collect();
+
+    let mut address = peer;
+    for hop in forwarded.iter().rev() {
+        if !trusted_proxies.contains(&address) {
+            break;
+        }
+        match hop.parse() {
+            Ok(hop) => address = hop,
+            // A malformed entry cannot be followed further
+            Err(_) => break,
+        }
+    }
+    address
+}
+
+/// Address used for lockouts; without connection info (in tests) all
+/// requests share one.
+fn lockout_address(client: &ClientInfo) -> String {
+    client.ip_address.clone().unwrap_or_else(|| "unknown".to_string())
+}
+
 /// Login endpoint - public access.
+///
+/// Repeated failures lock the account and the client address for a while;
+/// attempts during a lockout are rejected with a 429 before the password is
+/// checked.
 pub async fn login(
     Extension(auth): Extension<Arc<AuthService>>,
+    Extension(throttle): Extension<Arc<LoginThrottle>>,
+    Client(client): Client,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, ErrorResponse> {
-    auth.login(&req)
+    let address = lockout_address(&client);
+
+    match throttle.locked_for(&req.username, &address) {
+        Ok(Some(retry_after)) => {
+            return Err(ErrorResponse {
+                error: "Too many requests".to_string(),
+                detail: Some(format!(
+                    "Too many failed logins, retry in {} seconds",
+                    retry_after.num_seconds().max(1)
+                )),
+            });
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check login lockout: {}", e),
+    }
+
+    let result = auth.login(&req, &client).await;
+    let failure = match &result {
+        Ok(_) => {
+            if let Err(e) = throttle.record_success(&req.username) {
+                tracing::warn!("Failed to reset login failures: {}", e);
+            }
+            None
+        }
+        Err(AuthError::InvalidCredentials) => Some("invalid_credentials"),
+        Err(AuthError::InvalidTwoFactorCode) => Some("invalid_two_factor_code"),
+        Err(AuthError::UserInactive) => Some("inactive"),
+        Err(_) => None,
+    };
+    if let Some(reason) = failure
+        && let Err(e) = throttle.record_failure(&req.username, &address, reason)
+    {
+        tracing::warn!("Failed to record failed login: {}", e);
+    }
+
+    result
         .map(Json)
         .map_err(|e| match e {
             AuthError::InvalidCredentials => ErrorResponse {
@@ -39,6 +368,19 @@ pub async fn login(
                 error: "User inactive".to_string(),
                 detail: Some("This user account has been disabled".to_string()),
             },
+            AuthError::TwoFactorRequired => ErrorResponse {
+                error: "Two-factor code required".to_string(),
+                detail: Some("Repeat the login with a TOTP code or recovery code".to_string()),
+            },
+            AuthError::InvalidTwoFactorCode => two_factor_error(e),
+            AuthError::Backend(BackendError::Unavailable(_)) => ErrorResponse {
+                error: "Directory unavailable".to_string(),
+                detail: Some(e.to_string()),
+            },
+            AuthError::Backend(_) | AuthError::UserAlreadyExists(_) => ErrorResponse {
+                error: "Directory login failed".to_string(),
+                detail: Some(e.to_string()),
+            },
             _ => ErrorResponse {
                 error: "Login failed".to_string(),
                 detail: Some(e.to_string()),
@@ -46,24 +388,524 @@ pub async fn login(
         })
 }
 
+/// Refresh endpoint - exchanges a refresh token for new tokens.
+///
+/// The refresh token is single-use; the response contains its successor.
+pub async fn refresh(
+    Extension(auth): Extension<Arc<AuthService>>,
+    Client(client): Client,
+    Json(req): Json<RefreshTokenRequest>,
+) -> Result<Json<LoginResponse>, ErrorResponse> {
+    auth.refresh(&req.refresh_token, &client)
+        .map(Json)
+        .map_err(|e| match e {
+            AuthError::InvalidRefreshToken | AuthError::RefreshTokenReused => ErrorResponse {
+                error: "Invalid refresh token".to_string(),
+                detail: Some(e.to_string()),
+            },
+            AuthError::UserInactive => ErrorResponse {
+                error: "User inactive".to_string(),
+                detail: Some("This user account has been disabled".to_string()),
+            },
+            _ => ErrorResponse {
+                error: "Refresh failed".to_string(),
+                detail: Some(e.to_string()),
+            },
+        })
+}
+
+/// Query parameters of the OIDC callback.
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Start a single sign-on login - redirects to the identity provider and
+/// sets the cookie the callback requires.
+pub async fn oidc_login(
+    Extension(auth): Extension<Arc<AuthService>>,
+) -> Result<Response, ErrorResponse> {
+    if !auth.oidc_enabled() {
+        return Err(ErrorResponse {
+            error: "SSO not configured".to_string(),
+            detail: None,
+        });
+    }
+
+    auth.oidc_authorization_url()
+        .await
+        .map(|(url, cookie)| ([(SET_COOKIE, cookie)], Redirect::to(&url)).into_response())
+        .map_err(|e| ErrorResponse {
+            error: "SSO login failed".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+/// Single sign-on callback - exchanges the authorization code for a token.
+///
+/// Redirects to the configured frontend URL with the token in the fragment,
+/// or returns the login response as JSON.
+pub async fn oidc_callback(
+    Extension(auth): Extension<Arc<AuthService>>,
+    Client(client): Client,
+    headers: HeaderMap,
+    Query(params): Query<OidcCallbackQuery>,
+) -> Result<Response, ErrorResponse> {
+    let failed = |detail: String| ErrorResponse {
+        error: "SSO login failed".to_string(),
+        detail: Some(detail),
+    };
+
+    if let Some(error) = params.error {
+        return Err(failed(match params.error_description {
+            Some(description) => format!("{}: {}", error, description),
+            None => error,
+        }));
+    }
+    let (Some(code), Some(state)) = (params.code, params.state) else {
+        return Err(failed("Missing code or state".to_string()));
+    };
+
+    let response = auth
+        .oidc_login(&code, &state, cookie_value(&headers, oidc::STATE_COOKIE), &client)
+        .await
+        .map_err(|e| match e {
+            AuthError::UserInactive => ErrorResponse {
+                error: "User inactive".to_string(),
+                detail: Some("This user account has been disabled".to_string()),
+            },
+            _ => failed(e.to_string()),
+        })?;
+
+    // The login is finished, its state cookie is no longer needed
+    let cleared = [(SET_COOKIE, oidc::clear_state_cookie())];
+    Ok(match auth.oidc_post_login_redirect() {
+        Some(target) => {
+            let redirect = Redirect::to(&format!(
+                "{}#access_token={}&token_type={}&expires_in={}&refresh_token={}",
+                target,
+                response.access_token,
+                response.token_type,
+                response.expires_in,
+                response.refresh_token.unwrap_or_default()
+            ));
+            (cleared, redirect).into_response()
+        }
+        None => (cleared, Json(response)).into_response(),
+    })
+}
+
 /// Logout endpoint.
-pub async fn logout() -> Result<StatusCode, ErrorResponse> {
-    // In a stateless JWT system, logout is handled client-side by discarding the token
+///
+/// Revokes the session of the refresh token in the body, if any, which also
+/// invalidates the access tokens of that session.
+pub async fn logout(
+    Extension(auth): Extension<Arc<AuthService>>,
+    body: Option<Json<RefreshTokenRequest>>,
+) -> Result<StatusCode, ErrorResponse> {
+    if let Some(Json(req)) = body {
+        auth.revoke_refresh_token(&req.refresh_token)
+            .map_err(|e| ErrorResponse {
+                error: "Logout failed".to_string(),
+                detail: Some(e.to_string()),
+            })?;
+    }
     Ok(StatusCode::OK)
 }
 
-/// Get current user info - simplified version that doesn't require JWT.
+/// Request a password reset e-mail - public access.
+///
+/// Always accepted, whether or not the address belongs to an account.
+pub async fn request_password_reset(
+    Extension(resets): Extension<Arc<PasswordResetService>>,
+    Client(client): Client,
+    Json(req): Json<PasswordResetRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ErrorResponse> {
+    resets.request(&req.email, &lockout_address(&client)).map_err(password_reset_error)?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "If the address belongs to an account, a reset e-mail has been sent",
+        })),
+    ))
+}
+
+/// Set a new password with a password reset token - public access.
+pub async fn confirm_password_reset(
+    Extension(resets): Extension<Arc<PasswordResetService>>,
+    Json(req): Json<PasswordResetConfirmRequest>,
+) -> Result<StatusCode, ErrorResponse> {
+    resets
+        .confirm(&req.token, &req.new_password)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(password_reset_error)
+}
+
+/// Map errors of the password reset endpoints.
+fn password_reset_error(e: PasswordResetError) -> ErrorResponse {
+    match e {
+        PasswordResetError::RateLimited(_) => ErrorResponse {
+            error: "Too many requests".to_string(),
+            detail: Some(e.to_string()),
+        },
+        PasswordResetError::Auth(AuthError::InvalidResetToken) => ErrorResponse {
+            error: "Invalid reset token".to_string(),
+            detail: Some("The token is incorrect, expired or already used".to_string()),
+        },
+        PasswordResetError::Auth(AuthError::InvalidRequest(detail)) => ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(detail),
+        },
+        PasswordResetError::Auth(AuthError::UserInactive) => ErrorResponse {
+            error: "User inactive".to_string(),
+            detail: Some("This user account has been disabled".to_string()),
+        },
+        _ => ErrorResponse {
+            error: "Password reset failed".to_string(),
+            detail: Some(e.to_string()),
+        },
+    }
+}
+
+/// Query parameters for listing failed logins.
+#[derive(Debug, Deserialize)]
+pub struct LoginAttemptsQuery {
+    pub username: Option<String>,
+    pub ip_address: Option<String>,
+    pub limit: Option<u64>,
+}
+
+/// List the current login lockouts.
+pub async fn list_lockouts(
+    Extension(throttle): Extension<Arc<LoginThrottle>>,
+) -> Result<Json<Vec<Lockout>>, ErrorResponse> {
+    throttle.list_lockouts().map(Json).map_err(|e| ErrorResponse {
+        error: "Failed to list lockouts".to_string(),
+        detail: Some(e.to_string()),
+    })
+}
+
+/// Lift the lockout of an account (`account/{username}`) or client address
+/// (`ip/{address}`).
+pub async fn lift_lockout(
+    Extension(throttle): Extension<Arc<LoginThrottle>>,
+    Path((scope, subject)): Path<(String, String)>,
+) -> Result<StatusCode, ErrorResponse> {
+    let scope = LockoutScope::from_str(&scope).ok_or_else(|| ErrorResponse {
+        error: "Invalid request".to_string(),
+        detail: Some(format!("Unknown lockout scope '{}', use 'account' or 'ip'", scope)),
+    })?;
+
+    match throttle.lift(scope, &subject) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ErrorResponse {
+            error: "Lockout not found".to_string(),
+            detail: Some(format!("No failed logins recorded for {} '{}'", scope.as_str(), subject)),
+        }),
+        Err(e) => Err(ErrorResponse {
+            error: "Failed to lift lockout".to_string(),
+            detail: Some(e.to_string()),
+        }),
+    }
+}
+
+/// List recorded failed logins, most recent first.
+pub async fn list_login_attempts(
+    Extension(throttle): Extension<Arc<LoginThrottle>>,
+    Query(query): Query<LoginAttemptsQuery>,
+) -> Result<Json<Vec<LoginAttempt>>, ErrorResponse> {
+    throttle
+        .list_attempts(query.username.as_deref(), query.ip_address.as_deref(), query.limit)
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Failed to list login attempts".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+/// Get the authenticated user (or API key) and its permissions.
 pub async fn get_current_user(
-    Extension(_auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
 ) -> Result<Json<serde_json::Value>, ErrorResponse> {
-    // For now, return a placeholder
-    // TODO: Implement proper JWT extraction
     Ok(Json(serde_json::json!({
-        "message": "Authentication not yet implemented",
-        "note": "Use the login endpoint to get a token, then include it in Authorization header"
+        "id": claims.sub,
+        "username": claims.username,
+        "email": claims.email,
+        "role": claims.role,
+        "permissions": claims.permissions,
+        "expires_at": claims.exp,
     })))
 }
 
+/// Map errors of the two-factor endpoints.
+fn two_factor_error(e: AuthError) -> ErrorResponse {
+    match e {
+        AuthError::InvalidTwoFactorCode => ErrorResponse {
+            error: "Invalid two-factor code".to_string(),
+            detail: Some("The code is incorrect, expired or already used".to_string()),
+        },
+        AuthError::InvalidRequest(detail) => ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(detail),
+        },
+        AuthError::UserNotFound(id) => ErrorResponse {
+            error: "User not found".to_string(),
+            detail: Some(format!("No user found with ID: {}", id)),
+        },
+        _ => ErrorResponse {
+            error: "Two-factor authentication failed".to_string(),
+            detail: Some(e.to_string()),
+        },
+    }
+}
+
+/// Reject API keys on endpoints that manage a user's own account.
+fn require_user_account(claims: &Claims) -> Result<&str, ErrorResponse> {
+    if claims.role == API_KEY_ROLE {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("Only available to user accounts, not to API keys".to_string()),
+        });
+    }
+    Ok(&claims.sub)
+}
+
+/// Start two-factor enrolment for the current user.
+pub async fn enroll_totp(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+) -> Result<Json<TotpEnrollment>, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.enroll_totp(user_id).map(Json).map_err(two_factor_error)
+}
+
+/// Confirm two-factor enrolment with a first code; returns recovery codes.
+pub async fn confirm_totp(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodes>, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.confirm_totp(user_id, &req.code)
+        .map(Json)
+        .map_err(two_factor_error)
+}
+
+/// Disable two-factor authentication of the current user.
+pub async fn disable_totp(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> Result<StatusCode, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.disable_totp(user_id, &req.code)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(two_factor_error)
+}
+
+/// Replace the recovery codes of the current user.
+pub async fn regenerate_recovery_codes(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodes>, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.regenerate_recovery_codes(user_id, &req.code)
+        .map(Json)
+        .map_err(two_factor_error)
+}
+
+/// Get the roles that must use two-factor authentication.
+pub async fn get_two_factor_policy(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+) -> Result<Json<TwoFactorPolicy>, ErrorResponse> {
+    authorize(&auth, &headers, Permission::UsersRead)?;
+    auth.two_factor_policy().map(Json).map_err(two_factor_error)
+}
+
+/// Set the roles that must use two-factor authentication.
+pub async fn set_two_factor_policy(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Json(policy): Json<TwoFactorPolicy>,
+) -> Result<Json<TwoFactorPolicy>, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::SystemConfigure)?;
+    auth.set_two_factor_policy(&policy, &claims.username)
+        .map(Json)
+        .map_err(two_factor_error)
+}
+
+/// Remove two-factor authentication of a user, e.g. after a lost device.
+pub async fn reset_user_totp(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::UsersUpdate)?;
+    auth.get_user_by_id(&id)
+        .map_err(two_factor_error)?
+        .ok_or_else(|| two_factor_error(AuthError::UserNotFound(id.clone())))?;
+
+    auth.reset_totp(&id).map_err(two_factor_error)?;
+    tracing::info!("Two-factor authentication of user {} reset by {}", id, claims.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Map errors of the session endpoints.
+fn session_error(e: AuthError) -> ErrorResponse {
+    match e {
+        AuthError::SessionNotFound(id) => ErrorResponse {
+            error: "Session not found".to_string(),
+            detail: Some(format!("No session found with ID: {}", id)),
+        },
+        AuthError::UserNotFound(id) => ErrorResponse {
+            error: "User not found".to_string(),
+            detail: Some(format!("No user found with ID: {}", id)),
+        },
+        _ => ErrorResponse {
+            error: "Session management failed".to_string(),
+            detail: Some(e.to_string()),
+        },
+    }
+}
+
+/// List the active sessions of the current user.
+pub async fn list_sessions(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+) -> Result<Json<Vec<Session>>, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.list_sessions(user_id, claims.sid.as_deref())
+        .map(Json)
+        .map_err(session_error)
+}
+
+/// Revoke one session of the current user.
+pub async fn revoke_session(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.revoke_user_session(user_id, &session_id)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(session_error)
+}
+
+/// Revoke all sessions of the current user except the current one.
+pub async fn revoke_other_sessions(
+    Extension(auth): Extension<Arc<AuthService>>,
+    CurrentUser(claims): CurrentUser,
+) -> Result<Json<serde_json::Value>, ErrorResponse> {
+    let user_id = require_user_account(&claims)?;
+    auth.revoke_user_sessions(user_id, claims.sid.as_deref())
+        .map(|revoked| Json(serde_json::json!({ "revoked": revoked })))
+        .map_err(session_error)
+}
+
+/// List the active sessions of a user, with their addresses and user agents.
+pub async fn list_user_sessions(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Session>>, ErrorResponse> {
+    authorize(&auth, &headers, Permission::UsersUpdate)?;
+    auth.get_user_by_id(&id)
+        .map_err(session_error)?
+        .ok_or_else(|| session_error(AuthError::UserNotFound(id.clone())))?;
+
+    auth.list_sessions(&id, None).map(Json).map_err(session_error)
+}
+
+/// Revoke one session of a user.
+pub async fn revoke_user_session(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(String, String)>,
+) -> Result<StatusCode, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::UsersUpdate)?;
+    auth.revoke_user_session(&id, &session_id).map_err(session_error)?;
+    tracing::info!("Session {} of user {} revoked by {}", session_id, id, claims.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke all sessions of a user.
+pub async fn revoke_all_user_sessions(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::UsersUpdate)?;
+    let revoked = auth.revoke_user_sessions(&id, None).map_err(session_error)?;
+    tracing::info!("All sessions of user {} revoked by {}", id, claims.username);
+    Ok(Json(serde_json::json!({ "revoked": revoked })))
+}
+
+/// List API keys.
+pub async fn list_api_keys(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ApiKey>>, ErrorResponse> {
+    authorize(&auth, &headers, Permission::ApiKeysManage)?;
+
+    auth.list_api_keys()
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Failed to list API keys".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+/// Create an API key scoped to a subset of the caller's permissions.
+///
+/// The key is only included in this response.
+pub async fn create_api_key(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKey>, ErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::ApiKeysManage)?;
+
+    auth.create_api_key(&req, &claims)
+        .map(Json)
+        .map_err(|e| match e {
+            AuthError::InvalidRequest(detail) => ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some(detail),
+            },
+            _ => ErrorResponse {
+                error: "Failed to create API key".to_string(),
+                detail: Some(e.to_string()),
+            },
+        })
+}
+
+/// Revoke an API key.
+pub async fn revoke_api_key(
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ApiKey>, ErrorResponse> {
+    authorize(&auth, &headers, Permission::ApiKeysManage)?;
+
+    auth.revoke_api_key(&id)
+        .map(Json)
+        .map_err(|e| match e {
+            AuthError::ApiKeyNotFound(_) => ErrorResponse {
+                error: "API key not found".to_string(),
+                detail: Some(format!("No API key found with ID: {}", id)),
+            },
+            _ => ErrorResponse {
+                error: "Failed to revoke API key".to_string(),
+                detail: Some(e.to_string()),
+            },
+        })
+}
+
 /// List all users.
 pub async fn list_users(
     Extension(auth): Extension<Arc<AuthService>>,
@@ -108,6 +950,10 @@ pub async fn create_user(
                 error: "User already exists".to_string(),
                 detail: Some(format!("Username or email already in use: {}", username)),
             },
+            AuthError::InvalidRequest(detail) => ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some(detail),
+            },
             _ => ErrorResponse {
                 error: "Failed to create user".to_string(),
                 detail: Some(e.to_string()),
@@ -126,9 +972,57 @@ pub async fn update_user(
             tracing::info!("User updated: {}", user.username);
             Json(user)
         })
-        .map_err(|e| ErrorResponse {
-            error: "Failed to update user".to_string(),
-            detail: Some(e.to_string()),
+        .map_err(|e| match e {
+            AuthError::InvalidRequest(detail) => ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some(detail),
+            },
+            _ => ErrorResponse {
+                error: "Failed to update user".to_string(),
+                detail: Some(e.to_string()),
+            },
+        })
+}
+
+/// Request to link an account to a single sign-on identity.
+#[derive(Debug, Deserialize)]
+pub struct LinkOidcRequest {
+    /// Stable user id at the provider (`oid` for Azure AD, else `sub`)
+    pub subject: String,
+}
+
+/// Link an existing account to a single sign-on identity.
+pub async fn link_oidc_identity(
+    Extension(auth): Extension<Arc<AuthService>>,
+    Path(id): Path<String>,
+    Json(req): Json<LinkOidcRequest>,
+) -> Result<Json<User>, ErrorResponse> {
+    let subject = req.subject.trim();
+    if subject.is_empty() {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("Subject must not be empty".to_string()),
+        });
+    }
+
+    auth.link_oidc_subject(&id, subject)
+        .map(|user| {
+            tracing::info!("User {} linked to single sign-on identity", user.username);
+            Json(user)
+        })
+        .map_err(|e| match e {
+            AuthError::InvalidRequest(detail) => ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some(detail),
+            },
+            AuthError::UserNotFound(id) => ErrorResponse {
+                error: "User not found".to_string(),
+                detail: Some(format!("No user found with ID: {}", id)),
+            },
+            _ => ErrorResponse {
+                error: "Failed to link user".to_string(),
+                detail: Some(e.to_string()),
+            },
         })
 }
 
@@ -149,26 +1043,88 @@ pub async fn delete_user(
 }
 
 /// Change user password.
+///
+/// Only the user itself or a user administrator may change it. Wrong old
+/// passwords count as failed logins, so guessing is locked out like at the
+/// login.
+///
+/// Unless `logout_other_sessions` is false, all sessions of the user end
+/// except the one of the request, when the user changes their own password.
 pub async fn change_password(
     Extension(auth): Extension<Arc<AuthService>>,
+    Extension(throttle): Extension<Arc<LoginThrottle>>,
+    Client(client): Client,
+    CurrentUser(claims): CurrentUser,
+    headers: HeaderMap,
     Path(id): Path<String>,
     Json(req): Json<ChangePasswordRequest>,
 ) -> Result<StatusCode, ErrorResponse> {
-    auth.change_password(&id, &req.old_password, &req.new_password)
-        .map(|_| {
-            tracing::info!("Password changed for user: {}", id);
-            StatusCode::NO_CONTENT
-        })
+    let failed = |e: AuthError| ErrorResponse {
+        error: "Failed to change password".to_string(),
+        detail: Some(e.to_string()),
+    };
+
+    if claims.sub != id && !claims.has_permission(&Permission::UsersUpdate) {
+        return Err(ErrorResponse {
+            error: "Insufficient permissions".to_string(),
+            detail: Some("Only the user or an administrator can change the password".to_string()),
+        });
+    }
+    let user = auth
+        .get_user_by_id(&id)
+        .map_err(failed)?
+        .ok_or_else(|| ErrorResponse {
+            error: "User not found".to_string(),
+            detail: Some(format!("No user found with ID: {}", id)),
+        })?;
+
+    let address = lockout_address(&client);
+    match throttle.locked_for(&user.username, &address) {
+        Ok(Some(retry_after)) => {
+            return Err(ErrorResponse {
+                error: "Too many requests".to_string(),
+                detail: Some(format!(
+                    "Too many failed attempts, retry in {} seconds",
+                    retry_after.num_seconds().max(1)
+                )),
+            });
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check login lockout: {}", e),
+    }
+
+    let result = auth.change_password(&id, &req.old_password, &req.new_password);
+    if let Err(AuthError::InvalidCredentials) = result
+        && let Err(e) = throttle.record_failure(&user.username, &address, "invalid_credentials")
+    {
+        tracing::warn!("Failed to record failed password change: {}", e);
+    }
+    result
         .map_err(|e| match e {
             AuthError::InvalidCredentials => ErrorResponse {
                 error: "Invalid password".to_string(),
                 detail: Some("The old password is incorrect".to_string()),
             },
-            _ => ErrorResponse {
-                error: "Failed to change password".to_string(),
+            AuthError::InvalidRequest(_) => ErrorResponse {
+                error: "Invalid request".to_string(),
                 detail: Some(e.to_string()),
             },
-        })
+            _ => failed(e),
+        })?;
+    tracing::info!("Password changed for user: {}", id);
+
+    if req.logout_other_sessions {
+        let current_session = authenticate(&auth, &headers)
+            .ok()
+            .filter(|claims| claims.sub == id)
+            .and_then(|claims| claims.sid);
+        let revoked = auth
+            .revoke_user_sessions(&id, current_session.as_deref())
+            .map_err(failed)?;
+        tracing::info!("Ended {} other session(s) of user {}", revoked, id);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 /// Get user permissions.
@@ -203,12 +1159,199 @@ pub async fn get_user_permissions(
 impl IntoResponse for ErrorResponse {
     fn into_response(self) -> Response {
         let status = match self.error.as_str() {
-            "Invalid credentials" | "Invalid password" => StatusCode::UNAUTHORIZED,
-            "User not found" => StatusCode::NOT_FOUND,
-            "User already exists" | "Invalid role" => StatusCode::BAD_REQUEST,
+            "Invalid credentials"
+            | "Invalid password"
+            | "Unauthorized"
+            | "SSO login failed"
+            | "Directory login failed"
+            | "Invalid refresh token"
+            | "Two-factor code required"
+            | "Invalid two-factor code" => StatusCode::UNAUTHORIZED,
+            "User inactive" => StatusCode::FORBIDDEN,
+            "SSO not configured" => StatusCode::NOT_FOUND,
+            "User not found" | "API key not found" | "Session not found" | "Lockout not found" => {
+                StatusCode::NOT_FOUND
+            }
+            "User already exists" | "Invalid role" | "Invalid request" | "Invalid reset token" => {
+                StatusCode::BAD_REQUEST
+            }
+            "Too many requests" => StatusCode::TOO_MANY_REQUESTS,
+            "Directory unavailable" => StatusCode::SERVICE_UNAVAILABLE,
             "Insufficient permissions" => StatusCode::FORBIDDEN,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status, Json(self)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peilbeheer_core::{AccountType, ResourceScope};
+
+    use crate::db::Database;
+
+    /// Bearer header of a freshly created and logged in user.
+    async fn bearer(auth: &AuthService, username: &str, role: &str, scope: ResourceScope) -> HeaderMap {
+        let password = "Correct-Horse-9".to_string();
+        auth.create_user(
+            &CreateUserRequest {
+                username: username.to_string(),
+                email: format!("{}@example.nl", username),
+                password: password.clone(),
+                full_name: None,
+                role: role.to_string(),
+                custom_permissions: Vec::new(),
+                resource_scope: scope,
+                account_type: AccountType::default(),
+                expires_at: None,
+            },
+            None,
+        )
+        .unwrap();
+        let login = auth
+            .login(
+                &LoginRequest { username: username.to_string(), password, totp_code: None },
+                &ClientInfo::default(),
+            )
+            .await
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        let value = format!("Bearer {}", login.access_token);
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&value).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let auth = AuthService::with_default_config(Arc::new(Database::in_memory())).unwrap();
+        let headers = bearer(&auth, "jan", "viewer", ResourceScope::default()).await;
+
+        let claims = authorize(&auth, &headers, Permission::AlertsRead).unwrap();
+        assert_eq!(claims.username, "jan");
+
+        let denied = authorize(&auth, &headers, Permission::AlertsManage).unwrap_err();
+        assert_eq!(denied.error, "Insufficient permissions");
+        assert_eq!(denied.detail.as_deref(), Some("Requires permission 'alerts:manage'"));
+
+        let missing = authorize(&auth, &HeaderMap::new(), Permission::AlertsRead).unwrap_err();
+        assert_eq!(missing.error, "Unauthorized");
+    }
+
+    #[tokio::test]
+    async fn test_authorize_scope() {
+        let auth = AuthService::with_default_config(Arc::new(Database::in_memory())).unwrap();
+        let scope = ResourceScope { peilgebieden: vec!["PG_1".to_string()], assets: Vec::new() };
+        let scoped = bearer(&auth, "piet", "operator", scope).await;
+        let claims = authorize(&auth, &scoped, Permission::AlertsAcknowledge).unwrap();
+
+        assert!(authorize_scope(&claims, &["PG_1".to_string()]).is_ok());
+
+        let denied =
+            authorize_scope(&claims, &["PG_1".to_string(), "PG_2".to_string()]).unwrap_err();
+        assert_eq!(denied.error, "Insufficient permissions");
+        assert_eq!(denied.detail.as_deref(), Some("Outside your scope: PG_2"));
+
+        // Actions not tied to any resource are denied to scoped users
+        let denied = authorize_scope(&claims, &[]).unwrap_err();
+        assert_eq!(
+            denied.detail.as_deref(),
+            Some("Not tied to a peilgebied or asset within your scope")
+        );
+
+        // An empty scope acts as a wildcard
+        let unrestricted = bearer(&auth, "klaas", "operator", ResourceScope::default()).await;
+        let claims = authorize(&auth, &unrestricted, Permission::AlertsAcknowledge).unwrap();
+        assert!(authorize_scope(&claims, &["PG_1".to_string(), "PG_2".to_string()]).is_ok());
+        assert!(authorize_scope(&claims, &[]).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_keeps_creator_scope() {
+        let auth = AuthService::with_default_config(Arc::new(Database::in_memory())).unwrap();
+        let scope = ResourceScope { peilgebieden: vec!["PG_1".to_string()], assets: Vec::new() };
+        let scoped = bearer(&auth, "piet", "admin", scope).await;
+        let creator = authorize(&auth, &scoped, Permission::ApiKeysManage).unwrap();
+
+        let created = auth
+            .create_api_key(
+                &CreateApiKeyRequest {
+                    name: "SCADA bridge".to_string(),
+                    permissions: vec!["scenarios:execute".to_string()],
+                    expires_at: None,
+                    service_account_id: None,
+                },
+                &creator,
+            )
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_str(&created.key).unwrap());
+        let claims = authorize(&auth, &headers, Permission::ScenariosExecute).unwrap();
+
+        assert!(authorize_scope(&claims, &["PG_1".to_string()]).is_ok());
+        let denied = authorize_scope(&claims, &["PG_2".to_string()]).unwrap_err();
+        assert_eq!(denied.detail.as_deref(), Some("Outside your scope: PG_2"));
+    }
+
+    #[tokio::test]
+    async fn test_list_user_sessions_requires_admin() {
+        let auth = Arc::new(AuthService::with_default_config(Arc::new(Database::in_memory())).unwrap());
+        let viewer = bearer(&auth, "jan", "viewer", ResourceScope::default()).await;
+        let admin = bearer(&auth, "beheer", "admin", ResourceScope::default()).await;
+        let id = auth.get_user_by_username("jan").unwrap().unwrap().id;
+
+        let denied = list_user_sessions(Extension(auth.clone()), viewer, Path(id.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.error, "Insufficient permissions");
+
+        let Json(sessions) = list_user_sessions(Extension(auth), admin, Path(id)).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_cookie_value() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("theme=dark; oidc_state=abc-_1"));
+        assert_eq!(cookie_value(&headers, "oidc_state"), Some("abc-_1"));
+        assert_eq!(cookie_value(&headers, "theme"), Some("dark"));
+        assert_eq!(cookie_value(&headers, "state"), None);
+        assert_eq!(cookie_value(&HeaderMap::new(), "oidc_state"), None);
+    }
+
+    #[test]
+    fn test_query_access_token() {
+        assert_eq!(
+            query_access_token(Some("channel=alerts&access_token=abc%2Edef")),
+            Some("abc.def".to_string())
+        );
+        assert_eq!(query_access_token(Some("access_token=")), None);
+        assert_eq!(query_access_token(Some("token=abc")), None);
+        assert_eq!(query_access_token(None), None);
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_websocket_upgrade(&headers));
+        headers.insert(UPGRADE, HeaderValue::from_static("WebSocket"));
+        assert!(is_websocket_upgrade(&headers));
+    }
+
+    #[test]
+    fn test_client_address() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", HeaderValue::from_static("1.2.3.4, 203.0.113.7"));
+
+        // Without trusted proxies the header is ignored
+        assert_eq!(client_address(&headers, client, &[]), client);
+        assert_eq!(client_address(&headers, proxy, &[]), proxy);
+        // Behind a trusted proxy the hop it added counts, not the forged one
+        assert_eq!(client_address(&headers, proxy, &[proxy]), client);
+        // A client putting the proxy in front is not followed past its own hop
+        headers.insert("X-Forwarded-For", HeaderValue::from_static("1.2.3.4, 10.0.0.1, 203.0.113.7"));
+        assert_eq!(client_address(&headers, proxy, &[proxy]), client);
+        headers.insert("X-Forwarded-For", HeaderValue::from_static("unknown"));
+        assert_eq!(client_address(&headers, proxy, &[proxy]), proxy);
+    }
+}
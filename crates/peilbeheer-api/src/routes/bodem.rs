@@ -0,0 +1,64 @@
+//! Soil routes of peilgebieden.
+//!
+//! The soil type of a peilgebied comes from the BRO Bodemkaart and gives
+//! the default `infiltratie` of its `PeilgebiedConfig`.
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    response::IntoResponse,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use peilbeheer_core::bodem::PeilgebiedBodem;
+
+use crate::bodemkaart_client::{BodemkaartClient, BodemkaartError};
+
+/// Error response of the soil routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Soil of a peilgebied, determined on first request.
+pub async fn get_bodem(
+    Extension(client): Extension<Arc<BodemkaartClient>>,
+    Path(code): Path<String>,
+) -> Result<Json<PeilgebiedBodem>, ErrorResponse> {
+    Ok(Json(client.bodem(&code).await?))
+}
+
+/// Determine the soil of a peilgebied again from the Bodemkaart.
+pub async fn bepaal_bodem(
+    Extension(client): Extension<Arc<BodemkaartClient>>,
+    Path(code): Path<String>,
+) -> Result<Json<PeilgebiedBodem>, ErrorResponse> {
+    Ok(Json(client.bepaal(&code).await?))
+}
+
+impl From<BodemkaartError> for ErrorResponse {
+    fn from(e: BodemkaartError) -> Self {
+        let error = match &e {
+            BodemkaartError::PeilgebiedNotFound(_) => "Peilgebied not found",
+            BodemkaartError::Wfs(_) => "Bodemkaart request failed",
+            BodemkaartError::Database(_) => "Database error",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Peilgebied not found" => axum::http::StatusCode::NOT_FOUND,
+            "Bodemkaart request failed" => axum::http::StatusCode::BAD_GATEWAY,
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
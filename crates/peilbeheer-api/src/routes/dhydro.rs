@@ -16,10 +16,12 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use peilbeheer_core::{
-    DhydroClient, DhydroModel, Scenario, ScenarioParameters, ScenarioResult,
+    DhydroClient, DhydroModel, DhydroModelCatalog, Scenario, ScenarioParameters, ScenarioResult,
     TimeSeries, TimeSeriesQuery,
 };
 
+use crate::dhydro_catalog::ModelCatalogService;
+
 /// Query parameters for time series requests.
 #[derive(Debug, Deserialize)]
 pub struct TimeSeriesRequest {
@@ -79,40 +81,46 @@ pub struct ErrorResponse {
     detail: Option<String>,
 }
 
-/// List all available DHYdro models.
-pub async fn list_models(
-    State(_client): State<Arc<DhydroClient>>,
-) -> Result<Json<Vec<DhydroModel>>, ErrorResponse> {
-    // Note: We need interior mutability for the client (token refresh)
-    // For now, we'll create a new client per request
-    // TODO: Use RwLock or tokio::sync::Mutex for shared mutable client
-    Err(ErrorResponse {
-        error: "Not implemented".to_string(),
-        detail: Some("Use POST /api/dhydro/models/list for now".to_string()),
-    })
+/// Query parameters for the model catalog.
+#[derive(Debug, Deserialize)]
+pub struct ModelCatalogQuery {
+    /// Include inactive models and models outside their validity period
+    #[serde(default)]
+    pub all: bool,
+    /// Time the models must be valid at (default: now)
+    pub valid_at: Option<DateTime<Utc>>,
 }
 
-/// List all available DHYdro models (POST endpoint).
-pub async fn list_models_post(
-    Extension(_client): Extension<Arc<DhydroClient>>,
-) -> Result<Json<Vec<DhydroModel>>, ErrorResponse> {
-    // We need to get a mutable client - for now clone if needed
-    // This is a limitation that will be fixed with proper async locking
-    Err(ErrorResponse {
-        error: "Authentication required".to_string(),
-        detail: Some("DHYdro integration requires valid credentials".to_string()),
-    })
+/// List the cached D-Hydro models, by default those valid now.
+pub async fn list_models(
+    Extension(catalog): Extension<Arc<ModelCatalogService>>,
+    Query(query): Query<ModelCatalogQuery>,
+) -> Result<Json<DhydroModelCatalog>, ErrorResponse> {
+    catalog
+        .catalog(query.all, query.valid_at.unwrap_or_else(Utc::now))
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Failed to load model catalog".to_string(),
+            detail: Some(e.to_string()),
+        })
 }
 
-/// Get a specific model by ID.
+/// Get a cached model by ID.
 pub async fn get_model(
-    State(_client): State<Arc<DhydroClient>>,
-    axum::extract::Path(_id): axum::extract::Path<String>,
+    Extension(catalog): Extension<Arc<ModelCatalogService>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
 ) -> Result<Json<DhydroModel>, ErrorResponse> {
-    Err(ErrorResponse {
-        error: "Not implemented".to_string(),
-        detail: Some("DHYdro client integration in progress".to_string()),
-    })
+    match catalog.get_model(&id) {
+        Ok(Some(model)) => Ok(Json(model)),
+        Ok(None) => Err(ErrorResponse {
+            error: "Model not found".to_string(),
+            detail: Some(id),
+        }),
+        Err(e) => Err(ErrorResponse {
+            error: "Failed to load model catalog".to_string(),
+            detail: Some(e.to_string()),
+        }),
+    }
 }
 
 /// Fetch time series data from DHYdro.
@@ -256,6 +264,7 @@ impl IntoResponse for ErrorResponse {
         let status = match self.error.as_str() {
             "Not implemented" => StatusCode::NOT_IMPLEMENTED,
             "Authentication required" => StatusCode::UNAUTHORIZED,
+            "Model not found" => StatusCode::NOT_FOUND,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status, Json(self)).into_response()
@@ -274,6 +283,18 @@ mod tests {
         assert_eq!(req.parameter, Some("water_level".to_string()));
     }
 
+    #[test]
+    fn test_model_catalog_query_deserialize() {
+        let query: ModelCatalogQuery = serde_urlencoded::from_str("").unwrap();
+        assert!(!query.all);
+        assert!(query.valid_at.is_none());
+
+        let query: ModelCatalogQuery =
+            serde_urlencoded::from_str("all=true&valid_at=2024-06-01T00:00:00Z").unwrap();
+        assert!(query.all);
+        assert_eq!(query.valid_at.unwrap().to_rfc3339(), "2024-06-01T00:00:00+00:00");
+    }
+
     #[test]
     fn test_create_scenario_request_deserialize() {
         let json = r#"{
@@ -2,22 +2,33 @@
 //!
 //! These endpoints provide access to Delft-FEWS time series data,
 //! location/parameter metadata, and synchronization functionality.
+//! Pump schedules of optimization jobs can be written back to Fews, and
+//! simulation and optimization results exported as PI-XML documents.
+//! Configured peilgebieden sync on their own schedule; their runs can be
+//! listed and a sync can be started by hand. Mappings of Fews location
+//! and parameter ids to local names are managed under `/fews/mappings`.
 
 use axum::{
-    extract::{Extension, Query},
-    response::IntoResponse,
+    extract::{Extension, Path, Query},
+    http::header,
+    response::{IntoResponse, Response},
     Json,
 };
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use peilbeheer_core::energie::OptimalisatieResultaat;
 use peilbeheer_core::{
-    FewsLocation, FewsModuleInstance, FewsParameter, FewsSyncConfig, FewsSyncRequest,
-    FewsSyncResult, FewsTimeSeriesQuery, FewsTimeSeriesResponse,
+    CreateFewsMappingRequest, FewsIdMapping, FewsLocation, FewsMappingKind, FewsModuleInstance, FewsParameter, FewsSyncConfig, FewsSyncRequest,
+    FewsSyncResult, FewsSyncRun, FewsSyncTrigger, FewsTimeSeries, FewsTimeSeriesQuery, FewsTimeSeriesResponse,
+    UpdateFewsMappingRequest,
 };
+use peilbeheer_simulatie::NetwerkSimulatieResultaat;
 
-use crate::fews_client::{FewsClient, FewsSyncService};
+use crate::fews_client::{FewsClient, FewsMappingError, FewsSyncService};
+use crate::fews_export::{self, PiExport};
+use crate::optimization_service::OptimizationService;
 
 /// Query parameters for time series requests.
 #[derive(Debug, Deserialize)]
@@ -27,10 +38,73 @@ pub struct FewsQueryParams {
     pub module_instance_ids: Option<String>,
     pub start: Option<String>,
     pub end: Option<String>,
-    pub qualifier: Option<String>,
+    /// Comma-separated qualifier ids
+    #[serde(alias = "qualifier")]
+    pub qualifier_ids: Option<String>,
+    pub ensemble_id: Option<String>,
+    /// Comma-separated module instance sets
+    pub module_instance_sets: Option<String>,
     pub hours_back: Option<i64>,
 }
 
+/// Query parameters for listing sync runs.
+#[derive(Debug, Deserialize)]
+pub struct SyncRunsQuery {
+    pub peilgebied_id: Option<String>,
+    pub limit: Option<u64>,
+}
+
+/// Query parameters for listing id mappings.
+#[derive(Debug, Deserialize)]
+pub struct MappingsQuery {
+    pub kind: Option<FewsMappingKind>,
+}
+
+/// Request to write the pump schedule of an optimization job to Fews.
+#[derive(Debug, Deserialize)]
+pub struct WriteOptimizationRequest {
+    pub job_id: String,
+    pub location_id: String,
+    pub parameter_id: String,
+    /// Module instance (default: `FEWS_WRITE_MODULE_INSTANCE`)
+    pub module_instance_id: Option<String>,
+    /// Time of hour 0 (default: midnight UTC of the day the job was created)
+    pub start: Option<DateTime<Utc>>,
+}
+
+/// Summary of a write to Fews.
+#[derive(Debug, Serialize)]
+pub struct WriteOptimizationResponse {
+    pub module_instance_id: String,
+    pub location_id: String,
+    pub parameter_id: String,
+    pub data_points_count: usize,
+    pub start: DateTime<Utc>,
+}
+
+/// Request to export a network simulation result as PI-XML.
+#[derive(Debug, Deserialize)]
+pub struct ExportSimulationRequest {
+    pub resultaat: NetwerkSimulatieResultaat,
+    /// Time of minute 0 of the simulation
+    pub start: DateTime<Utc>,
+    /// Module instance (default: `FEWS_WRITE_MODULE_INSTANCE`)
+    pub module_instance_id: Option<String>,
+    pub forecast_date: Option<DateTime<Utc>>,
+    pub qualifier: Option<String>,
+}
+
+/// Query parameters for exporting an optimization job as PI-XML.
+#[derive(Debug, Deserialize)]
+pub struct ExportOptimizationParams {
+    /// Location of the series (default: the peilgebied of the job)
+    pub location_id: Option<String>,
+    /// Module instance (default: `FEWS_WRITE_MODULE_INSTANCE`)
+    pub module_instance_id: Option<String>,
+    /// Time of hour 0 (default: midnight UTC of the day the job was created)
+    pub start: Option<DateTime<Utc>>,
+}
+
 /// Response wrapper for Fews errors.
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -74,8 +148,14 @@ pub async fn get_time_series(
             }
     }
 
-    if let Some(qualifier) = &params.qualifier {
-        query.qualifier = Some(qualifier.clone());
+    if let Some(qualifiers) = &params.qualifier_ids {
+        query.qualifier_ids = Some(qualifiers.split(',').map(|s| s.to_string()).collect());
+    }
+
+    query.ensemble_id = params.ensemble_id.clone();
+
+    if let Some(sets) = &params.module_instance_sets {
+        query.module_instance_sets = Some(sets.split(',').map(|s| s.to_string()).collect());
     }
 
     client.get_time_series(&query)
@@ -140,6 +220,144 @@ pub async fn sync_fews(
         })
 }
 
+/// Write the optimal pump schedule of a completed job to Fews.
+pub async fn write_optimization_result(
+    Extension(client): Extension<Arc<FewsClient>>,
+    Extension(optimization): Extension<Arc<OptimizationService>>,
+    Json(request): Json<WriteOptimizationRequest>,
+) -> Result<Json<WriteOptimizationResponse>, ErrorResponse> {
+    let module_instance_id = request
+        .module_instance_id
+        .clone()
+        .or_else(|| client.config.write_module_instance_id.clone())
+        .ok_or_else(|| ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("No module instance given or configured".to_string()),
+        })?;
+
+    let job = optimization.get_job(&request.job_id).await;
+    let Some((job, result)) = job.and_then(|job| job.result.clone().map(|r| (job, r))) else {
+        return Err(ErrorResponse {
+            error: "Optimization result not found".to_string(),
+            detail: Some(format!("Job {} has no result", request.job_id)),
+        });
+    };
+
+    let start = request
+        .start
+        .unwrap_or_else(|| job.created_at.date_naive().and_time(NaiveTime::MIN).and_utc());
+    let series = optimization_time_series(
+        &result,
+        start,
+        &request.location_id,
+        &request.parameter_id,
+        &module_instance_id,
+    );
+    let data_points_count = series.data.len();
+
+    let document = FewsTimeSeriesResponse {
+        version: String::new(),
+        time_series: vec![series],
+        only_headers: None,
+    };
+    client.write_timeseries(&document)
+        .await
+        .map_err(|e| ErrorResponse {
+            error: "Failed to write time series".to_string(),
+            detail: Some(e.to_string()),
+        })?;
+
+    Ok(Json(WriteOptimizationResponse {
+        module_instance_id,
+        location_id: request.location_id,
+        parameter_id: request.parameter_id,
+        data_points_count,
+        start,
+    }))
+}
+
+/// Hourly optimal pump fractions as a Fews time series.
+///
+/// Each value holds for the hour starting at its timestamp.
+fn optimization_time_series(
+    result: &OptimalisatieResultaat,
+    start: DateTime<Utc>,
+    location_id: &str,
+    parameter_id: &str,
+    module_instance_id: &str,
+) -> FewsTimeSeries {
+    let export = PiExport {
+        module_instance_id: module_instance_id.to_string(),
+        start,
+        forecast_date: None,
+        qualifier: None,
+    };
+    export.pump_fraction_series(result, location_id, parameter_id)
+}
+
+/// Export a network simulation result as a PI-XML document.
+pub async fn export_simulation(
+    Extension(client): Extension<Arc<FewsClient>>,
+    Json(request): Json<ExportSimulationRequest>,
+) -> Result<Response, ErrorResponse> {
+    let export = PiExport {
+        module_instance_id: export_module_instance(&client, request.module_instance_id)?,
+        start: request.start,
+        forecast_date: request.forecast_date,
+        qualifier: request.qualifier,
+    };
+    Ok(pi_xml_response(export.simulation_series(&request.resultaat)))
+}
+
+/// Export the result of a completed optimization job as a PI-XML document.
+pub async fn export_optimization(
+    Extension(client): Extension<Arc<FewsClient>>,
+    Extension(optimization): Extension<Arc<OptimizationService>>,
+    Path(job_id): Path<String>,
+    Query(params): Query<ExportOptimizationParams>,
+) -> Result<Response, ErrorResponse> {
+    let module_instance_id = export_module_instance(&client, params.module_instance_id)?;
+
+    let job = optimization.get_job(&job_id).await;
+    let Some((job, result)) = job.and_then(|job| job.result.clone().map(|r| (job, r))) else {
+        return Err(ErrorResponse {
+            error: "Optimization result not found".to_string(),
+            detail: Some(format!("Job {} has no result", job_id)),
+        });
+    };
+
+    let export = PiExport {
+        module_instance_id,
+        start: params
+            .start
+            .unwrap_or_else(|| job.created_at.date_naive().and_time(NaiveTime::MIN).and_utc()),
+        forecast_date: job.completed_at,
+        qualifier: None,
+    };
+    let location_id = params.location_id.unwrap_or(job.peilgebied_id);
+    Ok(pi_xml_response(export.optimization_series(&result, &location_id)))
+}
+
+fn export_module_instance(
+    client: &FewsClient,
+    module_instance_id: Option<String>,
+) -> Result<String, ErrorResponse> {
+    module_instance_id
+        .or_else(|| client.config.write_module_instance_id.clone())
+        .ok_or_else(|| ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("No module instance given or configured".to_string()),
+        })
+}
+
+fn pi_xml_response(series: Vec<FewsTimeSeries>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml")],
+        fews_export::pi_xml(series),
+    )
+        .into_response()
+}
+
 /// Test Fews connection.
 pub async fn ping_fews(
     Extension(client): Extension<Arc<FewsClient>>,
@@ -164,6 +382,85 @@ pub async fn get_sync_configs(
     Json(service.get_configs().to_vec())
 }
 
+/// Sync a configured peilgebied now.
+pub async fn run_peilgebied_sync(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Path(peilgebied_id): Path<String>,
+) -> Result<Json<FewsSyncRun>, ErrorResponse> {
+    let run = service.run_sync(&peilgebied_id, FewsSyncTrigger::Manual)
+        .await
+        .map_err(|e| ErrorResponse {
+            error: "Fews sync failed".to_string(),
+            detail: Some(e.to_string()),
+        })?;
+
+    run.map(Json).ok_or_else(|| ErrorResponse {
+        error: "Sync config not found".to_string(),
+        detail: Some(format!("No Fews sync config for peilgebied {}", peilgebied_id)),
+    })
+}
+
+/// List recorded sync runs, most recent first.
+pub async fn list_sync_runs(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Query(query): Query<SyncRunsQuery>,
+) -> Result<Json<Vec<FewsSyncRun>>, ErrorResponse> {
+    service.list_runs(query.peilgebied_id.as_deref(), query.limit)
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Failed to list sync runs".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+/// List Fews id mappings.
+pub async fn list_mappings(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Query(query): Query<MappingsQuery>,
+) -> Result<Json<Vec<FewsIdMapping>>, ErrorResponse> {
+    service.list_mappings(query.kind)
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Failed to list mappings".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+/// Get a Fews id mapping.
+pub async fn get_mapping(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Path(id): Path<String>,
+) -> Result<Json<FewsIdMapping>, ErrorResponse> {
+    let mapping = service.get_mapping(&id).map_err(FewsMappingError::Database)?;
+    Ok(Json(mapping.ok_or(FewsMappingError::NotFound)?))
+}
+
+/// Create a Fews id mapping.
+pub async fn create_mapping(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Json(request): Json<CreateFewsMappingRequest>,
+) -> Result<Json<FewsIdMapping>, ErrorResponse> {
+    Ok(Json(service.create_mapping(request)?))
+}
+
+/// Update a Fews id mapping.
+pub async fn update_mapping(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateFewsMappingRequest>,
+) -> Result<Json<FewsIdMapping>, ErrorResponse> {
+    Ok(Json(service.update_mapping(&id, request)?))
+}
+
+/// Delete a Fews id mapping.
+pub async fn delete_mapping(
+    Extension(service): Extension<Arc<FewsSyncService>>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ErrorResponse> {
+    service.delete_mapping(&id)?;
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
 /// Ping Fews connection status.
 pub async fn fews_status(
     Extension(client): Extension<Arc<FewsClient>>,
@@ -180,11 +477,31 @@ pub async fn fews_status(
     })))
 }
 
+impl From<FewsMappingError> for ErrorResponse {
+    fn from(e: FewsMappingError) -> Self {
+        let error = match &e {
+            FewsMappingError::NotFound => "Mapping not found",
+            FewsMappingError::AlreadyExists(_) => "Mapping already exists",
+            FewsMappingError::Invalid(_) => "Invalid request",
+            FewsMappingError::Database(_) => "Failed to store mapping",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
 impl IntoResponse for ErrorResponse {
     fn into_response(self) -> axum::response::Response {
         let status = match self.error.as_str() {
             "Authentication failed" => axum::http::StatusCode::UNAUTHORIZED,
-            "Location not found" | "Parameter not found" | "Module instance not found" => axum::http::StatusCode::NOT_FOUND,
+            "Invalid request" => axum::http::StatusCode::BAD_REQUEST,
+            "Location not found" | "Parameter not found" | "Module instance not found"
+            | "Optimization result not found" | "Sync config not found" | "Mapping not found" => {
+                axum::http::StatusCode::NOT_FOUND
+            }
+            "Mapping already exists" => axum::http::StatusCode::CONFLICT,
             _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status, Json(self)).into_response()
@@ -194,6 +511,7 @@ impl IntoResponse for ErrorResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use peilbeheer_core::energie::OptimalisatieUurResultaat;
 
     #[test]
     fn test_query_params_parse() {
@@ -202,5 +520,70 @@ mod tests {
         assert_eq!(params.location_ids, Some("LOC1,LOC2".to_string()));
         assert_eq!(params.parameter_ids, Some("H".to_string()));
         assert_eq!(params.hours_back, Some(24));
+
+        let query = "qualifier=max&ensemble_id=ECMWF&module_instance_sets=Forecast_Rijn,Forecast_Maas";
+        let params: FewsQueryParams = serde_urlencoded::from_str(query).unwrap();
+        assert_eq!(params.qualifier_ids, Some("max".to_string()));
+        assert_eq!(params.ensemble_id, Some("ECMWF".to_string()));
+        assert_eq!(params.module_instance_sets, Some("Forecast_Rijn,Forecast_Maas".to_string()));
+    }
+
+    #[test]
+    fn test_optimization_time_series() {
+        let uur = |uur: u8, fractie: f64| OptimalisatieUurResultaat {
+            uur,
+            prijs_eur_kwh: 0.2,
+            regen_mm_uur: 0.0,
+            pomp_fractie_optimaal: fractie,
+            pomp_fractie_naief: 0.5,
+            waterstand_eind_optimaal: -2.5,
+            waterstand_eind_naief: -2.5,
+            kosten_optimaal: 0.0,
+            kosten_naief: 0.0,
+            energie_optimaal_kwh: 0.0,
+            energie_naief_kwh: 0.0,
+            emissiefactor_kg_kwh: 0.0,
+            co2_optimaal_kg: 0.0,
+            co2_naief_kg: 0.0,
+            pompen_optimaal: Vec::new(),
+            pompen_naief: Vec::new(),
+        };
+        let result = OptimalisatieResultaat {
+            uren: vec![uur(0, 0.8), uur(1, 0.2)],
+            totale_kosten_optimaal: 0.0,
+            totale_kosten_naief: 0.0,
+            besparing_eur: 0.0,
+            besparing_pct: 0.0,
+            max_afwijking_optimaal_cm: 0.0,
+            max_afwijking_naief_cm: 0.0,
+            tijdstappen_optimaal: Vec::new(),
+            tijdstappen_naief: Vec::new(),
+            prijzen: Vec::new(),
+            eigen_verbruik_optimaal_kwh: 0.0,
+            eigen_verbruik_naief_kwh: 0.0,
+            totale_energie_optimaal_kwh: 0.0,
+            totale_energie_naief_kwh: 0.0,
+            totale_co2_optimaal_kg: 0.0,
+            totale_co2_naief_kg: 0.0,
+            inschakelingen_optimaal: 0,
+            inschakelingen_naief: 0,
+            piekvermogen_optimaal_kw: 0.0,
+            piekvermogen_naief_kw: 0.0,
+            piekboete_optimaal_eur: 0.0,
+            piekboete_naief_eur: 0.0,
+            flexvergoeding_optimaal_eur: 0.0,
+            flexvergoeding_naief_eur: 0.0,
+            kwartieren: Vec::new(),
+            pompen: Vec::new(),
+            totale_kosten_aan_uit: None,
+        };
+        let start = DateTime::parse_from_rfc3339("2025-03-10T00:00:00Z").unwrap().with_timezone(&Utc);
+
+        let series = optimization_time_series(&result, start, "GEM_001", "Pomp.fractie", "Optimalisatie");
+        assert_eq!(series.header.module_instance_id, "Optimalisatie");
+        assert_eq!(series.header.start_date, "2025-03-10T00:00:00Z");
+        assert_eq!(series.header.end_date, "2025-03-10T01:00:00Z");
+        assert_eq!(series.data[0].value, 0.8);
+        assert_eq!(series.data[1].date, "2025-03-10T01:00:00Z");
     }
 }
@@ -9,6 +9,7 @@ use crate::db::Database;
 use crate::error::ApiError;
 use crate::hydronet_client::HydronetClient;
 
+use peilbeheer_core::energiecontract::EnergieContract;
 use peilbeheer_core::gemaal::GemaalSnapshot;
 use peilbeheer_core::hydronet::GeoJsonGemaal;
 
@@ -85,6 +86,34 @@ pub async fn get_gemaal(
     Ok(Json(response))
 }
 
+/// GET /api/gemalen/:code/energiecontract - Energiecontract van een gemaal.
+pub async fn get_energiecontract(
+    Path(code): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Result<Json<EnergieContract>, ApiError> {
+    db.get_gemaal_energiecontract(&code)
+        .map_err(ApiError::Internal)?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Geen energiecontract voor gemaal {}", code)))
+}
+
+/// PUT /api/gemalen/:code/energiecontract - Leg het energiecontract van een
+/// gemaal vast; de optimalisatie rekent ermee als `gemaal_code` is opgegeven.
+pub async fn put_energiecontract(
+    Path(code): Path<String>,
+    Extension(db): Extension<Arc<Database>>,
+    Json(contract): Json<EnergieContract>,
+) -> Result<Json<EnergieContract>, ApiError> {
+    if !db.gemaal_bestaat(&code).map_err(ApiError::Internal)? {
+        return Err(ApiError::NotFound(format!("Gemaal {} niet gevonden", code)));
+    }
+    contract.valideer().map_err(ApiError::Validation)?;
+    db.set_gemaal_energiecontract(&code, &contract)
+        .map_err(ApiError::Internal)?;
+
+    Ok(Json(contract))
+}
+
 fn to_geojson_feature(g: &GeoJsonGemaal) -> Value {
     json!({
         "type": "Feature",
@@ -0,0 +1,218 @@
+//! KNMI precipitation forecast routes.
+//!
+//! The nowcast of one peilgebied is returned as hourly sums; for several
+//! peilgebieden it is combined into a regenscenario that can be passed to
+//! a network simulation as is. The multi-day weather forecast can be
+//! fetched per peilgebied or stored as forecast time series.
+
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use peilbeheer_core::{RegenVerwachting, WeerVerwachting};
+use peilbeheer_simulatie::{Regenscenario, RegenscenarioType};
+
+use crate::knmi_client::{
+    KnmiClient, KnmiClientError, KnmiForecastSync, KnmiForecastSyncResult, MAX_FORECAST_UREN,
+    MAX_UREN, MIN_FORECAST_UREN,
+};
+
+/// Query parameters of the forecast of one peilgebied.
+#[derive(Debug, Deserialize)]
+pub struct RegenQuery {
+    /// Number of hours (default 2, at most 24)
+    pub uren: Option<usize>,
+}
+
+/// Query parameters of a forecast regenscenario.
+#[derive(Debug, Deserialize)]
+pub struct RegenscenarioQuery {
+    /// Comma-separated peilgebied codes
+    pub peilgebieden: String,
+    pub uren: Option<usize>,
+}
+
+/// Query parameters of the weather forecast of one peilgebied.
+#[derive(Debug, Deserialize)]
+pub struct VerwachtingQuery {
+    /// Number of hours (default `KNMI_FORECAST_HOURS`, 48 to 240)
+    pub uren: Option<usize>,
+}
+
+/// Request to store the weather forecast of peilgebieden.
+#[derive(Debug, Default, Deserialize)]
+pub struct ForecastSyncRequest {
+    /// Peilgebied codes (default `KNMI_FORECAST_PEILGEBIEDEN`)
+    #[serde(default)]
+    pub peilgebieden: Option<Vec<String>>,
+    pub uren: Option<usize>,
+}
+
+/// Error response of the KNMI routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Precipitation forecast of a peilgebied.
+pub async fn get_regen_verwachting(
+    Extension(client): Extension<Arc<KnmiClient>>,
+    Path(code): Path<String>,
+    Query(query): Query<RegenQuery>,
+) -> Result<Json<RegenVerwachting>, ErrorResponse> {
+    let uren = uren(query.uren)?;
+    Ok(Json(client.regen_verwachting(&code, uren).await?))
+}
+
+/// Precipitation forecast of several peilgebieden as regenscenario.
+pub async fn get_regenscenario(
+    Extension(client): Extension<Arc<KnmiClient>>,
+    Query(query): Query<RegenscenarioQuery>,
+) -> Result<Json<Regenscenario>, ErrorResponse> {
+    let uren = uren(query.uren)?;
+    let codes: Vec<&str> = query
+        .peilgebieden
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect();
+    if codes.is_empty() {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("peilgebieden is empty".to_string()),
+        });
+    }
+
+    let mut regen_per_uur = HashMap::new();
+    for code in codes {
+        let verwachting = client.regen_verwachting(code, uren).await?;
+        regen_per_uur.insert(verwachting.peilgebied_code, verwachting.regen_per_uur);
+    }
+
+    Ok(Json(Regenscenario {
+        regen_per_uur,
+        scenario_type: RegenscenarioType::Voorspelling,
+        periode: None,
+        klimaatscenario: None,
+    }))
+}
+
+/// Weather forecast (precipitation and evaporation) of a peilgebied.
+pub async fn get_weer_verwachting(
+    Extension(client): Extension<Arc<KnmiClient>>,
+    Path(code): Path<String>,
+    Query(query): Query<VerwachtingQuery>,
+) -> Result<Json<WeerVerwachting>, ErrorResponse> {
+    let uren = forecast_uren(query.uren.unwrap_or(client.config().forecast_hours))?;
+    Ok(Json(client.weer_verwachting(&code, uren).await?))
+}
+
+/// Store the weather forecast of peilgebieden as forecast time series.
+pub async fn sync_weer_verwachting(
+    Extension(sync): Extension<Arc<KnmiForecastSync>>,
+    Extension(client): Extension<Arc<KnmiClient>>,
+    body: Option<Json<ForecastSyncRequest>>,
+) -> Result<Json<KnmiForecastSyncResult>, ErrorResponse> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let uren = request.uren.map(forecast_uren).transpose()?;
+    let codes = request
+        .peilgebieden
+        .unwrap_or_else(|| client.config().forecast_peilgebieden.clone());
+    if codes.is_empty() {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("no peilgebieden given or configured".to_string()),
+        });
+    }
+
+    sync.sync(&codes, uren)
+        .await
+        .map(Json)
+        .map_err(|e| match e.downcast::<KnmiClientError>() {
+            Ok(e) => e.into(),
+            Err(e) => ErrorResponse {
+                error: "KNMI forecast sync failed".to_string(),
+                detail: Some(e.to_string()),
+            },
+        })
+}
+
+fn forecast_uren(uren: usize) -> Result<usize, ErrorResponse> {
+    if (MIN_FORECAST_UREN..=MAX_FORECAST_UREN).contains(&uren) {
+        Ok(uren)
+    } else {
+        Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(format!(
+                "uren must be between {} and {}, got {}",
+                MIN_FORECAST_UREN, MAX_FORECAST_UREN, uren
+            )),
+        })
+    }
+}
+
+fn uren(uren: Option<usize>) -> Result<usize, ErrorResponse> {
+    match uren.unwrap_or(2) {
+        uren @ 1..=MAX_UREN => Ok(uren),
+        uren => Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(format!(
+                "uren must be between 1 and {}, got {}",
+                MAX_UREN, uren
+            )),
+        }),
+    }
+}
+
+impl From<KnmiClientError> for ErrorResponse {
+    fn from(e: KnmiClientError) -> Self {
+        let error = match &e {
+            KnmiClientError::NotConfigured => "KNMI not configured",
+            KnmiClientError::PeilgebiedNotFound(_) => "Peilgebied not found",
+            KnmiClientError::Http(_) | KnmiClientError::Api { .. } => "KNMI request failed",
+            KnmiClientError::Parse(_) => "Invalid KNMI response",
+            KnmiClientError::Database(_) => "Failed to load peilgebied",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Invalid request" => axum::http::StatusCode::BAD_REQUEST,
+            "Peilgebied not found" => axum::http::StatusCode::NOT_FOUND,
+            "KNMI not configured" => axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "KNMI request failed" | "Invalid KNMI response" => axum::http::StatusCode::BAD_GATEWAY,
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uren() {
+        assert_eq!(uren(None).unwrap(), 2);
+        assert_eq!(uren(Some(24)).unwrap(), 24);
+        assert!(uren(Some(0)).is_err());
+        assert!(uren(Some(25)).is_err());
+
+        assert_eq!(forecast_uren(48).unwrap(), 48);
+        assert_eq!(forecast_uren(240).unwrap(), 240);
+        assert!(forecast_uren(24).is_err());
+        assert!(forecast_uren(241).is_err());
+    }
+}
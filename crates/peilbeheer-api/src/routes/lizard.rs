@@ -0,0 +1,105 @@
+//! Lizard groundwater routes.
+//!
+//! The groundwater series within a peilgebied can be listed directly or
+//! stored as time series for the configured peilgebieden.
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::lizard_client::{
+    GrondwaterReeks, LizardClient, LizardClientError, LizardGrondwaterSync, LizardSyncResult,
+};
+
+/// Request to store the groundwater levels of peilgebieden.
+#[derive(Debug, Default, Deserialize)]
+pub struct LizardSyncRequest {
+    /// Peilgebied codes (default `LIZARD_PEILGEBIEDEN`)
+    #[serde(default)]
+    pub peilgebieden: Option<Vec<String>>,
+    /// Period before now (days)
+    pub dagen: Option<i64>,
+}
+
+/// Error response of the Lizard routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Groundwater series located within a peilgebied.
+pub async fn get_grondwaterreeksen(
+    Extension(client): Extension<Arc<LizardClient>>,
+    Path(peilgebied): Path<String>,
+) -> Result<Json<Vec<GrondwaterReeks>>, ErrorResponse> {
+    Ok(Json(client.grondwaterreeksen(&peilgebied).await?))
+}
+
+/// Store the groundwater levels of peilgebieden as time series.
+pub async fn sync_grondwater(
+    Extension(sync): Extension<Arc<LizardGrondwaterSync>>,
+    Extension(client): Extension<Arc<LizardClient>>,
+    body: Option<Json<LizardSyncRequest>>,
+) -> Result<Json<LizardSyncResult>, ErrorResponse> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let peilgebieden = request
+        .peilgebieden
+        .unwrap_or_else(|| client.config().peilgebieden.clone());
+    if peilgebieden.is_empty() {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("no peilgebieden given or configured".to_string()),
+        });
+    }
+    if let Some(dagen) = request.dagen
+        && !(1..=crate::lizard_client::MAX_PERIODE_DAGEN).contains(&dagen)
+    {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(format!("dagen out of range: {}", dagen)),
+        });
+    }
+
+    sync.sync(&peilgebieden, request.dagen)
+        .await
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "Lizard sync failed".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+impl From<LizardClientError> for ErrorResponse {
+    fn from(e: LizardClientError) -> Self {
+        let error = match &e {
+            LizardClientError::InvalidPeriod(_) => "Invalid request",
+            LizardClientError::PeilgebiedNotFound(_) => "Not found",
+            LizardClientError::Database(_) => "Database error",
+            LizardClientError::Http(_) | LizardClientError::Api { .. } => "Lizard request failed",
+            LizardClientError::Parse(_) => "Invalid Lizard response",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Invalid request" => axum::http::StatusCode::BAD_REQUEST,
+            "Not found" => axum::http::StatusCode::NOT_FOUND,
+            "Lizard request failed" | "Invalid Lizard response" => {
+                axum::http::StatusCode::BAD_GATEWAY
+            }
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
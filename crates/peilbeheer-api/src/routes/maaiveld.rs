@@ -0,0 +1,86 @@
+//! Ground level routes of peilgebieden.
+//!
+//! The median ground level from the AHN is stored with the peilgebied and
+//! returned as `MAAIVELD` by `/peilgebieden/geojson`.
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::ahn_client::{AhnClient, AhnError, AhnSyncResult, Maaiveld};
+
+/// Request body for determining the ground level of all peilgebieden.
+#[derive(Debug, Deserialize)]
+pub struct AhnSyncRequest {
+    /// Skip peilgebieden that already have a ground level (default true)
+    #[serde(default = "default_alleen_ontbrekend")]
+    pub alleen_ontbrekend: bool,
+}
+
+impl Default for AhnSyncRequest {
+    fn default() -> Self {
+        Self {
+            alleen_ontbrekend: default_alleen_ontbrekend(),
+        }
+    }
+}
+
+fn default_alleen_ontbrekend() -> bool {
+    true
+}
+
+/// Error response of the ground level routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Determine the ground level of a peilgebied from the AHN.
+pub async fn bepaal_maaiveld(
+    Extension(client): Extension<Arc<AhnClient>>,
+    Path(code): Path<String>,
+) -> Result<Json<Maaiveld>, ErrorResponse> {
+    Ok(Json(client.bepaal(&code).await?))
+}
+
+/// Determine the ground level of all peilgebieden from the AHN.
+pub async fn sync_maaiveld(
+    Extension(client): Extension<Arc<AhnClient>>,
+    body: Option<Json<AhnSyncRequest>>,
+) -> Result<Json<AhnSyncResult>, ErrorResponse> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    Ok(Json(client.bepaal_alle(request.alleen_ontbrekend).await?))
+}
+
+impl From<AhnError> for ErrorResponse {
+    fn from(e: AhnError) -> Self {
+        let error = match &e {
+            AhnError::PeilgebiedNotFound(_) => "Peilgebied not found",
+            AhnError::GeenHoogte(_) => "No ground level",
+            AhnError::Http(_) | AhnError::Api { .. } => "AHN request failed",
+            AhnError::Parse(_) => "Invalid AHN response",
+            AhnError::Database(_) => "Database error",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Peilgebied not found" => axum::http::StatusCode::NOT_FOUND,
+            "No ground level" => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            "AHN request failed" | "Invalid AHN response" => axum::http::StatusCode::BAD_GATEWAY,
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
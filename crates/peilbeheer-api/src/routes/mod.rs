@@ -1,15 +1,24 @@
 pub mod alerts;
+pub mod audit;
 pub mod auth;
 pub mod assets;
+pub mod bodem;
 pub mod dashboard;
 pub mod dhydro;
 pub mod fews;
 pub mod gemalen;
 pub mod health;
+pub mod knmi;
+pub mod lizard;
+pub mod maaiveld;
+pub mod ontwerpbuien;
 pub mod optimalisatie;
 pub mod peilgebieden;
+pub mod rws;
+pub mod scada;
 pub mod scenarios;
 pub mod simulatie;
+pub mod spatial;
 pub mod status;
 pub mod timeseries;
 pub mod websocket;
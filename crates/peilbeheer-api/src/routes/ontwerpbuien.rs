@@ -0,0 +1,87 @@
+//! Ontwerpbuien voor toetsing aan de normen.
+//!
+//! De bibliotheek ligt vast in de simulatie; deze routes tonen de buien en
+//! leveren een bui als regenscenario voor een netwerksimulatie.
+
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use serde::Deserialize;
+
+use peilbeheer_simulatie::{Ontwerpbui, Regenscenario};
+
+use crate::error::ApiError;
+
+/// Filter op de bibliotheek.
+#[derive(Debug, Deserialize)]
+pub struct OntwerpbuiFilter {
+    /// Herhalingstijd in jaren
+    pub herhalingstijd: Option<u32>,
+    /// Duur in uren
+    pub duur: Option<usize>,
+}
+
+/// Query parameters van een ontwerpbui als regenscenario.
+#[derive(Debug, Deserialize)]
+pub struct OntwerpbuiScenarioQuery {
+    /// Komma-gescheiden peilgebiedcodes
+    pub peilgebieden: String,
+    /// Lengte van het scenario in uren (standaard de duur van de bui)
+    pub uren: Option<usize>,
+}
+
+/// Langste scenario dat met droge uren wordt aangevuld.
+const MAX_SCENARIO_UREN: usize = 7 * 24;
+
+/// GET /api/ontwerpbuien - Alle ontwerpbuien, optioneel gefilterd.
+pub async fn list_ontwerpbuien(Query(filter): Query<OntwerpbuiFilter>) -> Json<Vec<Ontwerpbui>> {
+    Json(
+        Ontwerpbui::bibliotheek()
+            .into_iter()
+            .filter(|bui| {
+                filter
+                    .herhalingstijd
+                    .is_none_or(|t| bui.herhalingstijd == t)
+            })
+            .filter(|bui| filter.duur.is_none_or(|d| bui.duur_uren == d))
+            .collect(),
+    )
+}
+
+/// GET /api/ontwerpbuien/{id} - Eén ontwerpbui.
+pub async fn get_ontwerpbui(Path(id): Path<String>) -> Result<Json<Ontwerpbui>, ApiError> {
+    zoek(&id).map(Json)
+}
+
+/// GET /api/ontwerpbuien/{id}/regenscenario - Ontwerpbui op peilgebieden.
+pub async fn get_regenscenario(
+    Path(id): Path<String>,
+    Query(query): Query<OntwerpbuiScenarioQuery>,
+) -> Result<Json<Regenscenario>, ApiError> {
+    let bui = zoek(&id)?;
+    let uren = query.uren.unwrap_or(bui.duur_uren);
+    if !(bui.duur_uren..=MAX_SCENARIO_UREN).contains(&uren) {
+        return Err(ApiError::Validation(format!(
+            "uren moet tussen {} en {} liggen",
+            bui.duur_uren, MAX_SCENARIO_UREN
+        )));
+    }
+
+    let codes: Vec<String> = query
+        .peilgebieden
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect();
+    if codes.is_empty() {
+        return Err(ApiError::Validation("peilgebieden is leeg".to_string()));
+    }
+
+    Ok(Json(bui.regenscenario(&codes, uren)))
+}
+
+fn zoek(id: &str) -> Result<Ontwerpbui, ApiError> {
+    Ontwerpbui::zoek(id).ok_or_else(|| ApiError::NotFound(format!("Ontwerpbui {}", id)))
+}
@@ -3,17 +3,27 @@
 //! Endpoints for pump scheduling optimization jobs and queue management.
 
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
+    response::Response,
     Json,
 };
+use chrono::{DateTime, Duration, DurationRound, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::warn;
 
 use peilbeheer_core::energie::*;
+use peilbeheer_core::pompen;
+use peilbeheer_simulatie::Rapport;
 
+use crate::db::Database;
+use crate::energyzero_client::{self, EnergyPriceStore, EnergyZeroError};
 use crate::error::ApiError;
 use crate::optimization_service::OptimizationService;
+use crate::pv_forecast_client::{self, PvForecastClient, PvForecastError, PvVerwachting};
+use crate::quarter_price_client::{self, KwartierPrijs, QuarterPriceClient, QuarterPriceError};
+use crate::routes::simulatie::{rapport_fout, rapport_response, RapportQuery};
+use crate::rws_client::RwsClient;
 
 /// Request to create an optimization job.
 #[derive(Debug, Deserialize)]
@@ -23,6 +33,42 @@ pub struct CreateJobRequest {
     pub params: OptimalisatieParams,
 }
 
+/// Query parameters of the energy prices.
+#[derive(Debug, Deserialize)]
+pub struct EnergieprijzenQuery {
+    /// `YYYY-MM-DD`, `vandaag` or `morgen` (UTC days)
+    pub date: Option<String>,
+}
+
+/// Query parameters of the quarter hour prices.
+#[derive(Debug, Deserialize)]
+pub struct KwartierprijzenQuery {
+    /// `YYYY-MM-DD`, `vandaag` or `morgen` (UTC days, default today)
+    pub date: Option<String>,
+    /// `onbalans` (default) or `intraday`
+    pub bron: Option<PrijsSignaal>,
+}
+
+/// Query parameters of the solar power forecast.
+#[derive(Debug, Deserialize)]
+pub struct PvVerwachtingQuery {
+    pub lat: f64,
+    pub lon: f64,
+    pub helling: Option<f64>,
+    pub azimut: Option<f64>,
+    pub vermogen_kwp: f64,
+    /// Number of hours from the current hour (default 24, at most 48)
+    pub uren: Option<usize>,
+}
+
+/// Query parameters of the price history.
+#[derive(Debug, Deserialize)]
+pub struct PrijsHistorieQuery {
+    pub van: DateTime<Utc>,
+    /// Default: now
+    pub tot: Option<DateTime<Utc>>,
+}
+
 /// Response for job creation.
 #[derive(Debug, Serialize)]
 pub struct CreateJobResponse {
@@ -46,6 +92,32 @@ pub async fn get_job(
     Ok(Json(service.get_job(&id).await))
 }
 
+/// Get the report of a completed job, as PDF or with `?formaat=html` as a
+/// self-contained HTML file.
+pub async fn get_job_rapport(
+    Extension(service): Extension<Arc<OptimizationService>>,
+    Path(id): Path<String>,
+    Query(query): Query<RapportQuery>,
+) -> Result<Response, ApiError> {
+    let mut job = service
+        .get_job(&id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Job {} not found", id)))?;
+    let resultaat = job
+        .result
+        .take()
+        .ok_or_else(|| ApiError::NotFound(format!("Job {} has no result", id)))?;
+
+    // Rendering the PDF is CPU-bound
+    tokio::task::spawn_blocking(move || {
+        let rapport =
+            Rapport::optimalisatie(&job.name, &job.params, &resultaat).map_err(rapport_fout)?;
+        rapport_response(&rapport, query.formaat, &format!("optimalisatie-{}", job.id))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+}
+
 /// Create a new optimization job.
 pub async fn create_job(
     Extension(service): Extension<Arc<OptimizationService>>,
@@ -109,7 +181,11 @@ pub async fn refresh_price_forecast(
 
 /// Run immediate optimization (synchronous).
 pub async fn run_optimalisatie(
+    Extension(db): Extension<Arc<Database>>,
     Extension(service): Extension<Arc<OptimizationService>>,
+    Extension(quarter_prices): Extension<Arc<QuarterPriceClient>>,
+    Extension(pv_forecast): Extension<Arc<PvForecastClient>>,
+    Extension(rws): Extension<Arc<RwsClient>>,
     Json(mut params): Json<OptimalisatieParams>,
 ) -> Result<Json<OptimalisatieResultaat>, ApiError> {
     // Validate
@@ -118,59 +194,251 @@ pub async fn run_optimalisatie(
             "oppervlakte moet groter zijn dan 0".into(),
         ));
     }
-    if params.max_debiet <= 0.0 {
+    if !params.pompen.is_empty() {
+        // The pumps determine the capacity; max_debiet is not used
+        pompen::valideer(&params.pompen).map_err(ApiError::Validation)?;
+    } else if let Some(curve) = &params.pompcurve {
+        curve.valideer().map_err(ApiError::Validation)?;
+    } else if params.max_debiet <= 0.0 {
         return Err(ApiError::Validation(
             "max_debiet moet groter zijn dan 0".into(),
         ));
     }
-    // Allow any number of hours (1-24) instead of exactly 24
-    if params.regen_per_uur.is_empty() || params.regen_per_uur.len() > 24 {
+    if !(MIN_HORIZON_UREN..=MAX_HORIZON_UREN).contains(&params.horizon_uren) {
+        return Err(ApiError::Validation(format!(
+            "horizon_uren moet {}-{} zijn, maar is {}",
+            MIN_HORIZON_UREN, MAX_HORIZON_UREN, params.horizon_uren
+        )));
+    }
+    // Allow any number of hours up to the horizon
+    let uren = params.horizon_uren;
+    if params.regen_per_uur.is_empty() || params.regen_per_uur.len() > uren {
         return Err(ApiError::Validation(format!(
-            "regen_per_uur moet 1-24 waarden bevatten, maar bevat {}",
+            "regen_per_uur moet 1-{} waarden bevatten, maar bevat {}",
+            uren,
             params.regen_per_uur.len()
         )));
     }
 
-    // If no prices provided, fetch from EnergyZero
-    if params.prijzen.is_empty() {
-        // Use the number of rain hours to determine how many prices we need
-        let hours_needed = params.regen_per_uur.len() as u8;
-        match service.get_price_forecast(hours_needed.max(1)).await {
-            Ok(forecast) => {
-                params.prijzen = forecast.hourly_prices.iter()
-                    .map(|hp| UurPrijs {
-                        uur: (hp.hour_start.timestamp() / 3600) as u8,
-                        prijs_eur_kwh: hp.price_eur_kwh,
-                    })
-                    .collect();
-            }
+    // Energy contract of the pumping station
+    if params.contract.is_none()
+        && let Some(gemaal) = &params.gemaal_code
+    {
+        params.contract = db
+            .get_gemaal_energiecontract(gemaal)
+            .map_err(ApiError::Internal)?;
+    }
+    if let Some(contract) = &params.contract {
+        contract.valideer().map_err(ApiError::Validation)?;
+    }
+
+    // Hour 0 of the optimization
+    let mut start = huidig_uur();
+
+    // If no prices provided, fetch from EnergyZero; a fixed-price contract
+    // does not need market prices
+    let marktprijzen = params.contract.as_ref().is_none_or(|c| c.is_dynamisch());
+    if params.prijzen.is_empty() && marktprijzen {
+        // Prices for the whole horizon; hours after the published day-ahead
+        // prices repeat the last known day in the optimizer
+        let hours_needed = uren as u8;
+        let forecast = match service.get_price_forecast(hours_needed.max(1)).await {
+            Ok(forecast) => forecast,
             Err(e) => {
                 return Err(ApiError::Hydronet(format!("EnergyZero: {}", e)));
             }
+        };
+        params.prijzen = energyzero_client::uur_prijzen(&forecast.hourly_prices);
+        if let Some(first) = forecast.hourly_prices.first() {
+            start = first.hour_start;
+        }
+
+        // Imbalance or intraday prices replace the day-ahead price per
+        // quarter hour where they are known
+        if params.prijs_signaal != PrijsSignaal::DayAhead {
+            let kwartierprijzen = quarter_prices
+                .prijzen(params.prijs_signaal, start, start + Duration::hours(uren as i64))
+                .await
+                .map_err(quarter_price_error)?;
+            params.prijzen.extend(quarter_price_client::kwartier_uur_prijzen(
+                &kwartierprijzen,
+                start,
+                uren,
+            ));
         }
     }
 
-    // Run optimization
-    let result = peilbeheer_simulatie::optimalisatie::optimize_pump_schedule(&params)
-        .map_err(ApiError::Validation)?;
+    // Contract off-peak hours are local hours from the start of the horizon
+    params.starttijd.get_or_insert(start);
+
+    // Solar generation of the pumping station, aligned with the prices
+    if params.opwek_kw.is_empty()
+        && let Some(installatie) = &params.pv_installatie
+    {
+        let verwachting = pv_forecast
+            .opwek_verwachting(installatie, start, uren)
+            .await
+            .map_err(pv_forecast_error)?;
+        params.opwek_kw = verwachting.opwek_kw;
+    }
+
+    // Outer water level, so the pump head follows the tide
+    if params.buitenpeil_per_uur.is_empty()
+        && let Some(locatie) = &params.buitenpeil_locatie
+    {
+        params.buitenpeil_per_uur = rws
+            .buitenpeil_per_uur(locatie, start, uren)
+            .await
+            .map_err(|e| ApiError::Hydronet(format!("RWS Waterinfo: {}", e)))?;
+    }
+
+    // Run optimization; the MILP is CPU-bound, so keep it off the runtime
+    let result = tokio::task::spawn_blocking(move || {
+        peilbeheer_simulatie::optimalisatie::optimize_pump_schedule(&params)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+    .map_err(ApiError::Validation)?;
 
     Ok(Json(result))
 }
 
-/// Get energy prices (legacy endpoint - redirects to forecast).
+/// Get energy prices.
+///
+/// With `date` the 24 day-ahead prices of that day (tomorrow's after the
+/// auction around 13:00), otherwise the current price forecast.
 pub async fn get_energieprijzen(
     Extension(service): Extension<Arc<OptimizationService>>,
+    Extension(store): Extension<Arc<EnergyPriceStore>>,
+    Query(query): Query<EnergieprijzenQuery>,
 ) -> Result<Json<Vec<UurPrijs>>, ApiError> {
+    if let Some(date) = &query.date {
+        let datum = parse_prijs_datum(date, Utc::now().date_naive())?;
+        return match store.dag_prijzen(datum).await {
+            Ok(prijzen) => Ok(Json(energyzero_client::uur_prijzen(&prijzen))),
+            Err(e @ EnergyZeroError::NotYetAvailable(_)) => Err(ApiError::NotFound(e.to_string())),
+            Err(e) => Err(ApiError::Hydronet(format!("Failed to get prices: {}", e))),
+        };
+    }
+
     match service.get_price_forecast(24).await {
         Ok(forecast) => {
-            let prijzen: Vec<UurPrijs> = forecast.hourly_prices.iter()
-                .map(|hp| UurPrijs {
-                    uur: (hp.hour_start.timestamp() / 3600) as u8,
-                    prijs_eur_kwh: hp.price_eur_kwh,
-                })
-                .collect();
-            Ok(Json(prijzen))
+            Ok(Json(energyzero_client::uur_prijzen(&forecast.hourly_prices)))
         }
         Err(e) => Err(ApiError::Hydronet(format!("Failed to get prices: {}", e))),
     }
 }
+
+/// Imbalance or intraday prices per quarter hour of a day.
+pub async fn get_kwartierprijzen(
+    Extension(quarter_prices): Extension<Arc<QuarterPriceClient>>,
+    Query(query): Query<KwartierprijzenQuery>,
+) -> Result<Json<Vec<KwartierPrijs>>, ApiError> {
+    let vandaag = Utc::now().date_naive();
+    let datum = match &query.date {
+        Some(date) => parse_prijs_datum(date, vandaag)?,
+        None => vandaag,
+    };
+    let bron = query.bron.unwrap_or(PrijsSignaal::Onbalans);
+    quarter_prices
+        .dag_prijzen(bron, datum)
+        .await
+        .map(Json)
+        .map_err(quarter_price_error)
+}
+
+/// Expected solar generation of an installation per hour.
+pub async fn get_pv_verwachting(
+    Extension(pv_forecast): Extension<Arc<PvForecastClient>>,
+    Query(query): Query<PvVerwachtingQuery>,
+) -> Result<Json<PvVerwachting>, ApiError> {
+    let uren = query.uren.unwrap_or(24);
+    if !(1..=pv_forecast_client::MAX_UREN).contains(&uren) {
+        return Err(ApiError::Validation(format!(
+            "uren must be between 1 and {}, got {}",
+            pv_forecast_client::MAX_UREN,
+            uren
+        )));
+    }
+    let installatie = PvInstallatie {
+        lat: query.lat,
+        lon: query.lon,
+        helling: query.helling.unwrap_or(30.0),
+        azimut: query.azimut.unwrap_or(0.0),
+        vermogen_kwp: query.vermogen_kwp,
+    };
+    pv_forecast
+        .opwek_verwachting(&installatie, huidig_uur(), uren)
+        .await
+        .map(Json)
+        .map_err(pv_forecast_error)
+}
+
+fn huidig_uur() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(Duration::hours(1)).unwrap_or(now)
+}
+
+fn pv_forecast_error(e: PvForecastError) -> ApiError {
+    match e {
+        PvForecastError::InvalidInstallation(_) => ApiError::Validation(e.to_string()),
+        e => ApiError::Hydronet(format!("Failed to get solar forecast: {}", e)),
+    }
+}
+
+fn quarter_price_error(e: QuarterPriceError) -> ApiError {
+    match e {
+        QuarterPriceError::HourlySignal => ApiError::Validation(e.to_string()),
+        e => ApiError::Hydronet(format!("Failed to get quarter prices: {}", e)),
+    }
+}
+
+/// Stored hourly prices of a period, for savings reports.
+pub async fn get_prijs_historie(
+    Extension(store): Extension<Arc<EnergyPriceStore>>,
+    Query(query): Query<PrijsHistorieQuery>,
+) -> Result<Json<Vec<HourlyPrice>>, ApiError> {
+    let tot = query.tot.unwrap_or_else(Utc::now);
+    if tot <= query.van {
+        return Err(ApiError::Validation("tot moet na van liggen".into()));
+    }
+    if tot - query.van > Duration::days(366 * 5) {
+        return Err(ApiError::Validation("periode mag maximaal 5 jaar zijn".into()));
+    }
+    Ok(Json(store.historie(query.van, tot)?))
+}
+
+fn parse_prijs_datum(date: &str, vandaag: NaiveDate) -> Result<NaiveDate, ApiError> {
+    match date {
+        "vandaag" | "today" => Ok(vandaag),
+        "morgen" | "tomorrow" => vandaag
+            .succ_opt()
+            .ok_or_else(|| ApiError::Validation("ongeldige datum".into())),
+        _ => NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            ApiError::Validation(format!(
+                "date moet YYYY-MM-DD, vandaag of morgen zijn, niet {}",
+                date
+            ))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prijs_datum() {
+        let vandaag = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        assert_eq!(parse_prijs_datum("vandaag", vandaag).unwrap(), vandaag);
+        assert_eq!(
+            parse_prijs_datum("morgen", vandaag).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 3, 11).unwrap()
+        );
+        assert_eq!(
+            parse_prijs_datum("2025-01-31", vandaag).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()
+        );
+        assert!(parse_prijs_datum("31-01-2025", vandaag).is_err());
+    }
+}
@@ -51,10 +51,37 @@ pub async fn get_peilgebied_mapping(Extension(db): Extension<Arc<Database>>) ->
 }
 
 /// POST /api/peilgebieden/sync — ophalen van ArcGIS, opslaan als bestand, laden in DuckDB.
+///
+/// Met `PEILGEBIEDEN_IMPORT_PATH` wordt in plaats daarvan de GeoPackage of
+/// Shapefile opnieuw ingelezen.
 pub async fn sync_peilgebieden(
     Extension(config): Extension<Arc<Config>>,
     Extension(db): Extension<Arc<Database>>,
 ) -> Response {
+    if let Some(import_path) = &config.peilgebieden_import_path {
+        return match db.reload_peilgebieden_from_geopackage(
+            import_path,
+            config.peilgebieden_import_layer.as_deref(),
+            &config.peilgebieden_attribuut_mapping,
+        ) {
+            Ok(import) => Json(json!({
+                "status": "ok",
+                "imported_from": import_path,
+                "loaded_in_db": import.geladen,
+                "import": import,
+            }))
+            .into_response(),
+            Err(e) => {
+                tracing::error!("Peilgebieden import uit {import_path} mislukt: {e}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Import mislukt: {e}"),
+                )
+                    .into_response()
+            }
+        };
+    }
+
     let geojson_path = std::path::Path::new(&config.peilgebieden_geojson_path);
 
     // Stap 1: Ophalen van ArcGIS en opslaan als bestand
@@ -0,0 +1,131 @@
+//! Rijkswaterstaat Waterinfo routes.
+//!
+//! Outer water levels of an RWS location can be fetched directly or
+//! stored as time series of the configured locations.
+
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use peilbeheer_core::TimeSeriesDataPoint;
+
+use crate::rws_client::{ProcesType, RwsClient, RwsClientError, RwsSyncResult, RwsWaterinfoSync};
+
+/// Query parameters of the water levels of a location.
+#[derive(Debug, Deserialize)]
+pub struct WaterstandenQuery {
+    /// Default: 24 hours before `tot`
+    pub van: Option<DateTime<Utc>>,
+    /// Default: now
+    pub tot: Option<DateTime<Utc>>,
+    /// `meting` (default) or `verwachting`
+    pub proces: Option<String>,
+}
+
+/// Request to store the water levels of RWS locations.
+#[derive(Debug, Default, Deserialize)]
+pub struct RwsSyncRequest {
+    /// RWS location codes (default `RWS_WATERINFO_LOCATIONS`)
+    #[serde(default)]
+    pub locaties: Option<Vec<String>>,
+    /// Period before now (hours)
+    pub uren: Option<i64>,
+}
+
+/// Error response of the RWS routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Water levels (m NAP) of an RWS location.
+pub async fn get_waterstanden(
+    Extension(client): Extension<Arc<RwsClient>>,
+    Path(locatie): Path<String>,
+    Query(query): Query<WaterstandenQuery>,
+) -> Result<Json<Vec<TimeSeriesDataPoint>>, ErrorResponse> {
+    let proces = match query.proces.as_deref().unwrap_or("meting") {
+        "meting" => ProcesType::Meting,
+        "verwachting" => ProcesType::Verwachting,
+        other => {
+            return Err(ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some(format!(
+                    "proces must be meting or verwachting, got {}",
+                    other
+                )),
+            });
+        }
+    };
+    let tot = query.tot.unwrap_or_else(Utc::now);
+    let van = query.van.unwrap_or(tot - Duration::hours(24));
+
+    Ok(Json(client.waterstanden(&locatie, proces, van, tot).await?))
+}
+
+/// Store the measured water levels of RWS locations as time series.
+pub async fn sync_waterstanden(
+    Extension(sync): Extension<Arc<RwsWaterinfoSync>>,
+    Extension(client): Extension<Arc<RwsClient>>,
+    body: Option<Json<RwsSyncRequest>>,
+) -> Result<Json<RwsSyncResult>, ErrorResponse> {
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    let locaties = request
+        .locaties
+        .unwrap_or_else(|| client.config().locaties.clone());
+    if locaties.is_empty() {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some("no locaties given or configured".to_string()),
+        });
+    }
+    if let Some(uren) = request.uren
+        && !(1..=24 * crate::rws_client::MAX_PERIODE_DAGEN).contains(&uren)
+    {
+        return Err(ErrorResponse {
+            error: "Invalid request".to_string(),
+            detail: Some(format!("uren out of range: {}", uren)),
+        });
+    }
+
+    sync.sync(&locaties, request.uren)
+        .await
+        .map(Json)
+        .map_err(|e| ErrorResponse {
+            error: "RWS sync failed".to_string(),
+            detail: Some(e.to_string()),
+        })
+}
+
+impl From<RwsClientError> for ErrorResponse {
+    fn from(e: RwsClientError) -> Self {
+        let error = match &e {
+            RwsClientError::InvalidPeriod(_) => "Invalid request",
+            RwsClientError::NoData(_) => "No data",
+            RwsClientError::Http(_) | RwsClientError::Api { .. } => "RWS request failed",
+            RwsClientError::Parse(_) => "Invalid RWS response",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Invalid request" => axum::http::StatusCode::BAD_REQUEST,
+            "No data" => axum::http::StatusCode::NOT_FOUND,
+            "RWS request failed" | "Invalid RWS response" => axum::http::StatusCode::BAD_GATEWAY,
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
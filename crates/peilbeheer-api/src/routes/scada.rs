@@ -0,0 +1,132 @@
+//! SCADA routes for pump setpoints.
+//!
+//! Reading setpoints needs `assets:read`. Writing a pump schedule needs
+//! `pumps:control` and a resource scope that includes the gemaal, and is a
+//! dry run unless the request and the bridge configuration both allow it.
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::HeaderMap,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use peilbeheer_core::Permission;
+use peilbeheer_core::energie::OptimalisatieResultaat;
+
+use crate::auth_service::AuthService;
+use crate::routes::auth::{ErrorResponse as AuthErrorResponse, authorize, authorize_scope};
+use crate::scada_bridge::{
+    ScadaClient, ScadaError, SchemaSchrijfResultaat, TagWaarde, pomp_fracties,
+};
+
+/// Request to write a pump schedule.
+#[derive(Debug, Deserialize)]
+pub struct SetpointSchemaRequest {
+    /// Hourly pump fractions (0-1); alternatively `resultaat`
+    #[serde(default)]
+    pub pomp_fracties: Option<Vec<f64>>,
+    /// Optimization result whose optimized schedule is written
+    #[serde(default)]
+    pub resultaat: Option<OptimalisatieResultaat>,
+    /// Start of the first hour (default now)
+    pub start: Option<DateTime<Utc>>,
+    /// Only report the planned writes (default true)
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+/// Error response of the SCADA routes.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Current setpoint and other nodes of a gemaal.
+pub async fn get_setpoints(
+    Extension(client): Extension<Arc<ScadaClient>>,
+    Path(gemaal): Path<String>,
+) -> Result<Json<Vec<TagWaarde>>, ErrorResponse> {
+    Ok(Json(client.lees_setpoints(&gemaal).await?))
+}
+
+/// Write a pump schedule of a gemaal as setpoints.
+pub async fn write_setpoints(
+    Extension(client): Extension<Arc<ScadaClient>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    headers: HeaderMap,
+    Path(gemaal): Path<String>,
+    Json(request): Json<SetpointSchemaRequest>,
+) -> Result<impl IntoResponse, AuthErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::PumpsControl)?;
+    authorize_scope(&claims, std::slice::from_ref(&gemaal))?;
+
+    let fracties = match (request.pomp_fracties, &request.resultaat) {
+        (Some(fracties), None) => fracties,
+        (None, Some(resultaat)) => pomp_fracties(resultaat),
+        _ => {
+            return Ok(Err(ErrorResponse {
+                error: "Invalid request".to_string(),
+                detail: Some("give either pomp_fracties or resultaat".to_string()),
+            }));
+        }
+    };
+
+    tracing::info!(
+        "Pump schedule for gemaal {} requested by {} (dry run: {})",
+        gemaal,
+        claims.username,
+        request.dry_run
+    );
+    Ok(client
+        .schrijf_schema(
+            &gemaal,
+            &fracties,
+            request.start.unwrap_or_else(Utc::now),
+            request.dry_run,
+        )
+        .await
+        .map(Json::<SchemaSchrijfResultaat>)
+        .map_err(ErrorResponse::from))
+}
+
+impl From<ScadaError> for ErrorResponse {
+    fn from(e: ScadaError) -> Self {
+        let error = match &e {
+            ScadaError::NotConfigured => "SCADA bridge disabled",
+            ScadaError::UnknownGemaal(_) => "Not found",
+            ScadaError::InvalidSchedule(_) => "Invalid request",
+            ScadaError::Http(_) | ScadaError::Api { .. } | ScadaError::WriteRejected(_) => {
+                "SCADA request failed"
+            }
+            ScadaError::Parse(_) => "Invalid SCADA response",
+        };
+        ErrorResponse {
+            error: error.to_string(),
+            detail: Some(e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self.error.as_str() {
+            "Invalid request" => axum::http::StatusCode::BAD_REQUEST,
+            "Not found" => axum::http::StatusCode::NOT_FOUND,
+            "SCADA bridge disabled" => axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "SCADA request failed" | "Invalid SCADA response" => {
+                axum::http::StatusCode::BAD_GATEWAY
+            }
+            _ => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
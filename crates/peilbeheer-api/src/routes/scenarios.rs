@@ -1,22 +1,33 @@
 //! Scenario management routes.
 //!
 //! RESTful API endpoints for hydraulic modeling scenario CRUD operations,
-//! execution management, and result retrieval.
+//! execution management, and result retrieval. Executing a scenario requires
+//! the `scenarios:execute` permission for all of its peilgebieden.
+//!
+//! With D-Hydro configured an execution is submitted right away and its job
+//! id stored, so the D-Hydro poller follows it; without it the execution
+//! stays pending.
 
 use axum::{
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tokio::sync::Mutex;
 
 use peilbeheer_core::{
-    CloneScenarioRequest, CreateScenarioRequest, StoredScenario, StoredScenarioStatus,
-    StoredScenarioResult, UpdateScenarioRequest,
+    CloneScenarioRequest, CreateScenarioRequest, DhydroClient, DhydroError, ExecutionStatus,
+    Permission, ScenarioResult, StoredScenario, StoredScenarioStatus, StoredScenarioResult,
+    UpdateScenarioRequest,
 };
 
+use crate::auth_service::AuthService;
+use crate::routes::auth::{
+    CurrentUser, ErrorResponse as AuthErrorResponse, authorize, authorize_scope,
+};
 use crate::scenario_service::ScenarioService;
 
 /// Query parameters for scenario listing.
@@ -88,13 +99,32 @@ pub async fn create_scenario(
 }
 
 /// Update an existing scenario.
+///
+/// Both the current and the new peilgebieden of the scenario must be within
+/// the caller's scope.
 pub async fn update_scenario(
     Extension(service): Extension<Arc<ScenarioService>>,
+    CurrentUser(claims): CurrentUser,
     Path(id): Path<String>,
     Json(req): Json<UpdateScenarioRequest>,
-) -> Result<Json<StoredScenario>, ErrorResponse> {
-    service
-        .update_scenario(&id, &req, None)
+) -> Result<impl IntoResponse, AuthErrorResponse> {
+    match service.get_scenario(&id) {
+        Ok(Some(scenario)) => authorize_scope(&claims, &scenario.peilgebieden)?,
+        Ok(None) => {}
+        // Without the scenario its scope cannot be checked
+        Err(e) => {
+            return Ok(Err(ErrorResponse {
+                error: "Failed to update scenario".to_string(),
+                detail: Some(e.to_string()),
+            }));
+        }
+    }
+    if let Some(peilgebieden) = &req.peilgebieden {
+        authorize_scope(&claims, peilgebieden)?;
+    }
+
+    Ok(service
+        .update_scenario(&id, &req, Some(&claims.sub))
         .map(|scenario| {
             tracing::info!("Updated scenario: {}", id);
             Json(scenario)
@@ -102,7 +132,7 @@ pub async fn update_scenario(
         .map_err(|e| ErrorResponse {
             error: "Failed to update scenario".to_string(),
             detail: Some(e.to_string()),
-        })
+        }))
 }
 
 /// Delete a scenario.
@@ -122,38 +152,100 @@ pub async fn delete_scenario(
         })
 }
 
-/// Execute a scenario (create execution record).
+/// Execute a scenario (create execution record, submit to D-Hydro).
 pub async fn execute_scenario(
     Extension(service): Extension<Arc<ScenarioService>>,
+    Extension(auth): Extension<Arc<AuthService>>,
+    Extension(dhydro): Extension<Option<Arc<Mutex<DhydroClient>>>>,
+    headers: HeaderMap,
     Path(id): Path<String>,
-) -> Result<Json<StoredScenarioResult>, ErrorResponse> {
-    service
-        .execute_scenario(&id, None)
-        .map(|result_id| {
-            tracing::info!("Started execution for scenario: {}", id);
-            // Return the result ID as a minimal result object
-            Json(StoredScenarioResult {
-                id: result_id,
-                scenario_id: id.clone(),
-                status: "pending".to_string(),
-                started_at: None,
-                completed_at: None,
-                duration_seconds: None,
-                results_summary: serde_json::json!({}),
-                time_series_count: 0,
-                output_files: serde_json::json!([]),
-                error_message: None,
-                error_code: None,
-                created_at: chrono::Utc::now(),
-                created_by: None,
-                dhydro_job_id: None,
-                dhydro_result_url: None,
-            })
-        })
-        .map_err(|e| ErrorResponse {
-            error: "Failed to execute scenario".to_string(),
-            detail: Some(e.to_string()),
-        })
+) -> Result<impl IntoResponse, AuthErrorResponse> {
+    let claims = authorize(&auth, &headers, Permission::ScenariosExecute)?;
+    let scenario = match service.get_scenario(&id) {
+        Ok(Some(scenario)) => scenario,
+        Ok(None) => {
+            return Ok(Err(ErrorResponse {
+                error: "Scenario not found".to_string(),
+                detail: None,
+            }));
+        }
+        // Without the scenario its scope cannot be checked
+        Err(e) => {
+            return Ok(Err(ErrorResponse {
+                error: "Failed to execute scenario".to_string(),
+                detail: Some(e.to_string()),
+            }));
+        }
+    };
+    authorize_scope(&claims, &scenario.peilgebieden)?;
+
+    let result_id = match service.execute_scenario(&id, Some(&claims.sub)) {
+        Ok(result_id) => result_id,
+        Err(e) => {
+            return Ok(Err(ErrorResponse {
+                error: "Failed to execute scenario".to_string(),
+                detail: Some(e.to_string()),
+            }));
+        }
+    };
+
+    let mut status = ExecutionStatus::Pending;
+    let mut dhydro_job_id = None;
+    if let Some(dhydro) = dhydro {
+        let mut error_message = None;
+        match submit_to_dhydro(&mut *dhydro.lock().await, &scenario).await {
+            Ok(job) => {
+                status = ExecutionStatus::from(&job.status);
+                dhydro_job_id = Some(job.id);
+            }
+            Err(e) => {
+                status = ExecutionStatus::Failed;
+                error_message = Some(e.to_string());
+            }
+        }
+        let stored = service.update_scenario_result(
+            &result_id,
+            status,
+            None,
+            error_message.as_deref(),
+            dhydro_job_id.as_deref(),
+        );
+        if let Some(detail) = error_message.or_else(|| stored.err().map(|e| e.to_string())) {
+            return Ok(Err(ErrorResponse {
+                error: "Failed to execute scenario".to_string(),
+                detail: Some(detail),
+            }));
+        }
+    }
+
+    tracing::info!("Started execution for scenario: {}", id);
+    // Return the result ID as a minimal result object
+    Ok(Ok(Json(StoredScenarioResult {
+        id: result_id,
+        scenario_id: id.clone(),
+        status: status.as_str().to_string(),
+        started_at: None,
+        completed_at: None,
+        duration_seconds: None,
+        results_summary: serde_json::json!({}),
+        time_series_count: 0,
+        output_files: serde_json::json!([]),
+        error_message: None,
+        error_code: None,
+        created_at: chrono::Utc::now(),
+        created_by: Some(claims.sub.clone()),
+        dhydro_job_id,
+        dhydro_result_url: None,
+    })))
+}
+
+/// Create the scenario in D-Hydro and start it; returns the job.
+async fn submit_to_dhydro(
+    client: &mut DhydroClient,
+    scenario: &StoredScenario,
+) -> Result<ScenarioResult, DhydroError> {
+    let remote = client.create_scenario(&scenario.to_dhydro()).await?;
+    client.execute_scenario(&remote.id).await
 }
 
 /// Get scenario execution results.
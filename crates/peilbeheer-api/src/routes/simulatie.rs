@@ -1,17 +1,54 @@
-use axum::Json;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
+use crate::bodemkaart_client::BodemkaartClient;
+use crate::db::Database;
 use crate::error::ApiError;
+use crate::realtime_simulatie::{RealtimeInstelling, RealtimeRun, RealtimeSimulatie};
+use crate::timeseries_service::TimeSeriesService;
+use crate::websocket_service::WebSocketServer;
 
+use peilbeheer_core::WsMessage;
 use peilbeheer_core::waterbalans::SimulatieParams;
 use peilbeheer_simulatie::waterbalans::calculate_time_series;
+use peilbeheer_simulatie::{
+    Annulering, BouwerInstellingen, Dagweer, Doelfunctie, DroogteResultaat, EnsembleResultaat,
+    Ensemblelid, HistorischePeriode, JaarbalansInstellingen, JaarbalansResultaat,
+    NetwerkSimulatieResultaat, NetwerkTopologie, PeilgebiedId, PidTuning, Rapport, RapportFout,
+    Scenario, ScenarioFout, StrategyType,
+    SweepParameter, SweepResultaat, TopologieVoorstel, TornadoResultaat, TuningMethode,
+    ValidatieRapport, Verdringingsreeks, simuleer_droogte, simuleer_ensemble,
+    simuleer_jaarbalans, stel_topologie_voor, sweep, tornado, tune_pid,
+};
+
+/// Simulatieverzoek: SimulatieParams met optioneel een peilgebied.
+#[derive(Debug, Deserialize)]
+pub struct SimulatieRequest {
+    #[serde(flatten)]
+    pub params: SimulatieParams,
+    /// Peilgebied waarvan het maaiveld gebruikt wordt als
+    /// `maaiveld_niveau` ontbreekt
+    #[serde(default)]
+    pub peilgebied: Option<String>,
+}
 
 /// POST /api/simulatie - Voer een waterbalans simulatie uit.
 ///
 /// Verwacht een JSON body met SimulatieParams.
 /// Retourneert een tijdreeks van waterstandberekeningen.
 pub async fn run_simulatie(
-    Json(params): Json<SimulatieParams>,
+    Extension(db): Extension<Arc<Database>>,
+    Json(request): Json<SimulatieRequest>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
+    let mut params = request.params;
     // Validatie
     if params.oppervlakte <= 0.0 {
         return Err(ApiError::Validation(
@@ -29,6 +66,14 @@ pub async fn run_simulatie(
         ));
     }
 
+    // Maaiveld uit het AHN van het peilgebied
+    if params.maaiveld_niveau.is_none()
+        && let Some(code) = &request.peilgebied
+        && let Some(maaiveld) = db.get_peilgebied_maaiveld(code)?
+    {
+        params.maaiveld_niveau = Some(maaiveld);
+    }
+
     let tijdstappen = calculate_time_series(&params);
 
     // Bereken samenvatting
@@ -42,16 +87,14 @@ pub async fn run_simulatie(
         .fold(f64::INFINITY, f64::min);
 
     // Optioneel: drooglegging berekenen als maaiveld is opgegeven
-    let drooglegging = if params.maaiveld_niveau != 0.0 {
-        Some(peilbeheer_simulatie::drooglegging::calculate_drooglegging(
-            params.maaiveld_niveau,
+    let drooglegging = params.maaiveld_niveau.map(|maaiveld| {
+        peilbeheer_simulatie::drooglegging::calculate_drooglegging(
+            maaiveld,
             max_waterstand,
             params.streefpeil,
             params.marge,
-        ))
-    } else {
-        None
-    };
+        )
+    });
 
     Ok(Json(serde_json::json!({
         "params": params,
@@ -64,3 +107,588 @@ pub async fn run_simulatie(
         "drooglegging": drooglegging,
     })))
 }
+
+/// Langste scenario van een gevoeligheidsanalyse in uren
+const MAX_SWEEP_UREN: usize = 168;
+
+/// Gevoeligheidsverzoek: een netwerkscenario en de te variëren parameters.
+#[derive(Debug, Deserialize)]
+pub struct SweepRequest {
+    pub scenario: Scenario,
+    pub parameters: Vec<SweepParameter>,
+}
+
+/// POST /api/simulatie/sweep - Simuleer een scenario voor elke combinatie
+/// van de waarden van één of twee parameters.
+pub async fn run_sweep(
+    Json(request): Json<SweepRequest>,
+) -> Result<Json<SweepResultaat>, ApiError> {
+    valideer_sweep_duur(&request)?;
+    tokio::task::spawn_blocking(move || sweep(&request.scenario, &request.parameters))
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))?
+        .map(Json)
+        .map_err(scenario_fout)
+}
+
+/// POST /api/simulatie/tornado - Varieer elke parameter afzonderlijk en
+/// sorteer op de invloed op de peilstijging.
+pub async fn run_tornado(
+    Json(request): Json<SweepRequest>,
+) -> Result<Json<TornadoResultaat>, ApiError> {
+    valideer_sweep_duur(&request)?;
+    tokio::task::spawn_blocking(move || tornado(&request.scenario, &request.parameters))
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))?
+        .map(Json)
+        .map_err(scenario_fout)
+}
+
+/// Elke run van een gevoeligheidsanalyse simuleert het hele scenario;
+/// begrens daarom de duur.
+fn valideer_sweep_duur(request: &SweepRequest) -> Result<(), ApiError> {
+    if request.scenario.parameters.duration_hours > MAX_SWEEP_UREN {
+        return Err(ApiError::Validation(format!(
+            "Scenario mag hoogstens {} uur duren",
+            MAX_SWEEP_UREN
+        )));
+    }
+    Ok(())
+}
+
+/// PID-tuningverzoek: de simulatieparameters van het peilgebied met de
+/// methode en de doelfunctie.
+#[derive(Debug, Deserialize)]
+pub struct PidTuningRequest {
+    #[serde(flatten)]
+    pub params: SimulatieParams,
+    #[serde(default)]
+    pub methode: TuningMethode,
+    #[serde(default)]
+    pub doelfunctie: Doelfunctie,
+}
+
+/// POST /api/simulatie/pid-tuning - Stel Kp/Ki/Kd voor de PID-regelaar van
+/// een peilgebied voor.
+pub async fn run_pid_tuning(
+    Json(request): Json<PidTuningRequest>,
+) -> Result<Json<PidTuning>, ApiError> {
+    tokio::task::spawn_blocking(move || {
+        tune_pid(&request.params, request.methode, &request.doelfunctie)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+    .map(Json)
+    .map_err(|e| ApiError::Validation(e.to_string()))
+}
+
+/// Lopende netwerksimulaties met hun annuleringstoken, op run-id.
+#[derive(Default)]
+pub struct LopendeSimulaties(Mutex<HashMap<String, Annulering>>);
+
+impl LopendeSimulaties {
+    fn start(self: &Arc<Self>, run_id: &str) -> Option<LopendeRun> {
+        let mut runs = self.0.lock().unwrap();
+        if runs.contains_key(run_id) {
+            return None;
+        }
+        let annulering = Annulering::nieuw();
+        runs.insert(run_id.to_string(), annulering.clone());
+        Some(LopendeRun {
+            lopend: Arc::clone(self),
+            run_id: run_id.to_string(),
+            annulering,
+        })
+    }
+
+    fn stop(&self, run_id: &str) {
+        self.0.lock().unwrap().remove(run_id);
+    }
+
+    fn annuleer(&self, run_id: &str) -> bool {
+        match self.0.lock().unwrap().get(run_id) {
+            Some(annulering) => {
+                annulering.annuleer();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Registratie van een lopende netwerksimulatie. Bij het droppen, ook als de
+/// client het verzoek afbreekt, wordt de run geannuleerd en uitgeschreven.
+struct LopendeRun {
+    lopend: Arc<LopendeSimulaties>,
+    run_id: String,
+    annulering: Annulering,
+}
+
+impl Drop for LopendeRun {
+    fn drop(&mut self) {
+        self.annulering.annuleer();
+        self.lopend.stop(&self.run_id);
+    }
+}
+
+/// Langste netwerksimulatie in uren
+const MAX_NETWERK_UREN: usize = 240;
+
+/// Netwerksimulatieverzoek. Met een eigen `run_id` kan de client de run
+/// al annuleren en de voortgang volgen voordat het antwoord binnen is.
+#[derive(Debug, Deserialize)]
+pub struct NetwerkSimulatieRequest {
+    pub scenario: Scenario,
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+/// POST /api/simulatie/netwerk - Simuleer een netwerkscenario. De voortgang
+/// gaat per procent als `simulatie.voortgang` over de WebSocket.
+pub async fn run_netwerk(
+    Extension(ws_server): Extension<Arc<WebSocketServer>>,
+    Extension(lopend): Extension<Arc<LopendeSimulaties>>,
+    Json(request): Json<NetwerkSimulatieRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let run_id = request
+        .run_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    if request.scenario.parameters.duration_hours > MAX_NETWERK_UREN {
+        return Err(ApiError::Validation(format!(
+            "Scenario mag hoogstens {} uur duren",
+            MAX_NETWERK_UREN
+        )));
+    }
+    let run = lopend
+        .start(&run_id)
+        .ok_or_else(|| ApiError::Conflict(format!("Simulatie {} loopt al", run_id)))?;
+    let annulering = run.annulering.clone();
+
+    let broadcaster = ws_server.broadcaster();
+    let scenario = request.scenario;
+    let id = run_id.clone();
+    let uitkomst = tokio::task::spawn_blocking(move || {
+        let mut gemeld = -1.0;
+        scenario.simuleer_met_voortgang(
+            |voortgang| {
+                let procent = voortgang.percentage.floor();
+                if procent <= gemeld {
+                    return;
+                }
+                gemeld = procent;
+                let _ = broadcaster.send(WsMessage::SimulatieVoortgang {
+                    run_id: id.clone(),
+                    scenario_id: scenario.id.clone(),
+                    percentage: voortgang.percentage,
+                    tijd: voortgang.tijd,
+                    statistieken: serde_json::json!({
+                        "tijdstap": voortgang.tijdstap,
+                        "max_waterstand": voortgang.max_waterstand,
+                        "uitgeslagen_volume": voortgang.uitgeslagen_volume,
+                    }),
+                });
+            },
+            &annulering,
+        )
+    })
+    .await;
+    drop(run);
+
+    match uitkomst.map_err(|e| ApiError::Internal(e.into()))? {
+        Ok(resultaat) => Ok(Json(serde_json::json!({
+            "run_id": run_id,
+            "status": "voltooid",
+            "resultaat": resultaat,
+        }))),
+        Err(ScenarioFout::Geannuleerd { tijd }) => Ok(Json(serde_json::json!({
+            "run_id": run_id,
+            "status": "geannuleerd",
+            "tijd": tijd,
+        }))),
+        Err(e) => Err(scenario_fout(e)),
+    }
+}
+
+/// POST /api/simulatie/netwerk/{run_id}/cancel - Breek een lopende
+/// netwerksimulatie af.
+pub async fn cancel_netwerk(
+    Extension(lopend): Extension<Arc<LopendeSimulaties>>,
+    Path(run_id): Path<String>,
+) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "cancelled": lopend.annuleer(&run_id),
+        "run_id": run_id,
+    }))
+}
+
+/// GET /api/simulatie/topologie-voorstel - Stel een netwerktopologie voor
+/// uit de peilgebieden en de gemaal- en stuwassets.
+pub async fn get_topologie_voorstel(
+    Extension(db): Extension<Arc<Database>>,
+    Extension(bodemkaart): Extension<Arc<BodemkaartClient>>,
+) -> Result<Json<TopologieVoorstel>, ApiError> {
+    let peilgebieden: serde_json::Value =
+        serde_json::from_str(&db.get_all_peilgebieden_geojson()?).map_err(anyhow::Error::from)?;
+    let kunstwerken = db.get_all_assets(Some(&["gemaal", "stuw"]))?;
+    let mut voorstel =
+        stel_topologie_voor(&peilgebieden, &kunstwerken, &BouwerInstellingen::default())
+            .map_err(|e| ApiError::Validation(e.to_string()))?;
+
+    // Infiltratie uit de al bepaalde bodem van de peilgebieden
+    for knoop in &mut voorstel.knopen {
+        knoop.bodem = bodemkaart
+            .opgeslagen(&knoop.id)
+            .map_err(|e| ApiError::Internal(e.into()))?;
+    }
+    Ok(Json(voorstel))
+}
+
+/// Beoordeeld topologievoorstel dat een netwerktopologie moet worden.
+#[derive(Debug, Deserialize)]
+pub struct TopologieVoorstelRequest {
+    pub voorstel: TopologieVoorstel,
+    #[serde(default)]
+    pub instellingen: BouwerInstellingen,
+}
+
+/// POST /api/simulatie/topologie-voorstel - Zet een beoordeeld voorstel om
+/// in een netwerktopologie.
+pub async fn post_topologie_voorstel(
+    Json(request): Json<TopologieVoorstelRequest>,
+) -> Result<Json<NetwerkTopologie>, ApiError> {
+    request
+        .voorstel
+        .naar_topologie(&request.instellingen)
+        .map(Json)
+        .map_err(|e| ApiError::Validation(e.to_string()))
+}
+
+/// POST /api/simulatie/topologie/validatie - Fouten en waarschuwingen bij
+/// een netwerktopologie.
+pub async fn valideer_topologie(
+    Json(topologie): Json<NetwerkTopologie>,
+) -> Json<ValidatieRapport> {
+    Json(topologie.valideer_rapport())
+}
+
+/// Langste weerreeks van een jaar- of droogtebalans in dagen (tien jaar)
+const MAX_BALANS_DAGEN: usize = 3660;
+
+/// Jaarbalansverzoek: de topologie met neerslag en verdamping per dag.
+#[derive(Debug, Deserialize)]
+pub struct JaarbalansRequest {
+    pub topologie: NetwerkTopologie,
+    pub weer: Vec<Dagweer>,
+    #[serde(default)]
+    pub instellingen: JaarbalansInstellingen,
+}
+
+/// POST /api/simulatie/jaarbalans - Reken de peilgebieden per dag door met
+/// zomer- en winterpeil, voor aan- en afvoerbalansen en watertekorten.
+pub async fn run_jaarbalans(
+    Json(request): Json<JaarbalansRequest>,
+) -> Result<Json<JaarbalansResultaat>, ApiError> {
+    valideer_balans_duur(&request.weer)?;
+    request
+        .topologie
+        .valideer()
+        .map_err(|e| ApiError::Validation(e.to_string()))?;
+    tokio::task::spawn_blocking(move || {
+        simuleer_jaarbalans(&request.topologie, &request.weer, &request.instellingen)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+    .map(Json)
+    .map_err(|e| ApiError::Validation(e.to_string()))
+}
+
+/// Een balans rekent elke dag van het weer door; begrens daarom het aantal
+/// dagen.
+fn valideer_balans_duur(weer: &[Dagweer]) -> Result<(), ApiError> {
+    if weer.len() > MAX_BALANS_DAGEN {
+        return Err(ApiError::Validation(format!(
+            "Weerreeks mag hoogstens {} dagen lang zijn",
+            MAX_BALANS_DAGEN
+        )));
+    }
+    Ok(())
+}
+
+/// Droogteverzoek: een jaarbalansverzoek met de verdringingsreeks en de
+/// beschikbare aanvoer in m³ per dag.
+#[derive(Debug, Deserialize)]
+pub struct DroogteRequest {
+    pub topologie: NetwerkTopologie,
+    pub weer: Vec<Dagweer>,
+    #[serde(default)]
+    pub instellingen: JaarbalansInstellingen,
+    pub reeks: Verdringingsreeks,
+    pub beschikbaar_per_dag: Vec<f64>,
+}
+
+/// POST /api/simulatie/droogte - Verdeel beperkte aanvoer over de
+/// peilgebieden volgens de verdringingsreeks, met gevraagde en geleverde
+/// volumes per rang en peilgebied.
+pub async fn run_droogte(
+    Json(request): Json<DroogteRequest>,
+) -> Result<Json<DroogteResultaat>, ApiError> {
+    valideer_balans_duur(&request.weer)?;
+    request
+        .topologie
+        .valideer()
+        .map_err(|e| ApiError::Validation(e.to_string()))?;
+    tokio::task::spawn_blocking(move || {
+        simuleer_droogte(
+            &request.topologie,
+            &request.weer,
+            &request.instellingen,
+            &request.reeks,
+            &request.beschikbaar_per_dag,
+        )
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+    .map(Json)
+    .map_err(|e| ApiError::Validation(e.to_string()))
+}
+
+/// Instelling en laatste run van de realtime simulatie.
+#[derive(Debug, Serialize)]
+pub struct RealtimeStatus {
+    pub instelling: Option<RealtimeInstelling>,
+    pub laatste_run: Option<RealtimeRun>,
+}
+
+/// GET /api/simulatie/realtime - Instelling en laatste verwachting van de
+/// realtime simulatie.
+pub async fn get_realtime(
+    Extension(realtime): Extension<Arc<RealtimeSimulatie>>,
+) -> Result<Json<RealtimeStatus>, ApiError> {
+    Ok(Json(RealtimeStatus {
+        instelling: realtime.instelling()?,
+        laatste_run: realtime.laatste_run().await,
+    }))
+}
+
+/// PUT /api/simulatie/realtime - Stel de topologie en bronnen van de
+/// realtime simulatie in; die rekent vanaf de volgende run ermee.
+pub async fn put_realtime(
+    Extension(realtime): Extension<Arc<RealtimeSimulatie>>,
+    Json(instelling): Json<RealtimeInstelling>,
+) -> Result<Json<RealtimeInstelling>, ApiError> {
+    instelling.validate().map_err(ApiError::Validation)?;
+    realtime.set_instelling(Some(&instelling))?;
+    Ok(Json(instelling))
+}
+
+/// DELETE /api/simulatie/realtime - Zet de realtime simulatie uit.
+pub async fn delete_realtime(
+    Extension(realtime): Extension<Arc<RealtimeSimulatie>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    realtime.set_instelling(None)?;
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+/// POST /api/simulatie/realtime/run - Reken nu een verwachting door en
+/// publiceer haar.
+pub async fn run_realtime(
+    Extension(realtime): Extension<Arc<RealtimeSimulatie>>,
+) -> Result<Json<RealtimeRun>, ApiError> {
+    realtime
+        .run_once()
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("Geen realtime simulatie ingesteld".to_string()))
+}
+
+/// Langste horizon van een ensemblerun in uren
+const MAX_ENSEMBLE_UREN: usize = 240;
+
+/// Ensembleverzoek: topologie en beginstaat, met de leden van de
+/// neerslagverwachting of een Fews-ensemble uit de tijdreeksopslag.
+#[derive(Debug, Deserialize)]
+pub struct EnsembleRequest {
+    pub topologie: NetwerkTopologie,
+    #[serde(default)]
+    pub strategy: StrategyType,
+    pub t0: DateTime<Utc>,
+    pub uren: usize,
+    /// Gemeten beginwaterstand per peilgebied; de overige starten op
+    /// streefpeil
+    #[serde(default)]
+    pub waterstanden: HashMap<PeilgebiedId, f64>,
+    #[serde(default)]
+    pub leden: Vec<Ensemblelid>,
+    /// Fews-ensemble waarvan de leden uit de `neerslag`-reeksen worden
+    /// gelezen als `leden` leeg is
+    #[serde(default)]
+    pub ensemble_id: Option<String>,
+    #[serde(default = "default_percentielen")]
+    pub percentielen: Vec<f64>,
+}
+
+fn default_percentielen() -> Vec<f64> {
+    vec![5.0, 50.0, 95.0]
+}
+
+/// POST /api/simulatie/ensemble - Reken per ensemblelid van de
+/// neerslagverwachting vooruit en bundel de leden tot de kans op
+/// overschrijding en percentielbanden per peilgebied.
+pub async fn run_ensemble(
+    Extension(timeseries): Extension<Arc<TimeSeriesService>>,
+    Json(mut request): Json<EnsembleRequest>,
+) -> Result<Json<EnsembleResultaat>, ApiError> {
+    if !(1..=MAX_ENSEMBLE_UREN).contains(&request.uren) {
+        return Err(ApiError::Validation(format!(
+            "Horizon moet 1 tot {} uur zijn",
+            MAX_ENSEMBLE_UREN
+        )));
+    }
+    request
+        .topologie
+        .valideer()
+        .map_err(|e| ApiError::Validation(e.to_string()))?;
+
+    if request.leden.is_empty()
+        && let Some(ensemble_id) = &request.ensemble_id
+    {
+        let periode = HistorischePeriode::nieuw(
+            request.t0,
+            request.t0 + Duration::hours(request.uren as i64),
+        )
+        .map_err(scenario_fout)?;
+        let codes: Vec<&str> = request
+            .topologie
+            .peilgebieden
+            .keys()
+            .map(String::as_str)
+            .collect();
+        request.leden = timeseries
+            .ensemble_regen(&codes, ensemble_id, &periode)
+            .await?;
+        if request.leden.is_empty() {
+            return Err(ApiError::NotFound(format!(
+                "Geen neerslagreeksen van ensemble {}",
+                ensemble_id
+            )));
+        }
+    }
+
+    tokio::task::spawn_blocking(move || {
+        simuleer_ensemble(
+            &request.topologie,
+            request.t0,
+            &request.waterstanden,
+            &request.leden,
+            request.uren,
+            &request.strategy,
+            &request.percentielen,
+        )
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+    .map(Json)
+    .map_err(|e| ApiError::Validation(e.to_string()))
+}
+
+/// Verzoek om een rapport van een scenario.
+#[derive(Debug, Deserialize)]
+pub struct RapportRequest {
+    pub scenario: Scenario,
+    /// Eerder berekend resultaat van het scenario; ontbreekt het, dan wordt
+    /// het scenario eerst gesimuleerd
+    #[serde(default)]
+    pub resultaat: Option<NetwerkSimulatieResultaat>,
+}
+
+/// Formaat van een rapport.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RapportFormaat {
+    #[default]
+    Pdf,
+    /// Zelfstandig HTML-bestand met interactieve grafieken
+    Html,
+}
+
+/// Query parameters van een rapport.
+#[derive(Debug, Deserialize)]
+pub struct RapportQuery {
+    #[serde(default)]
+    pub formaat: RapportFormaat,
+}
+
+/// POST /api/simulatie/rapport - Maak een rapport van een scenario met
+/// samenvatting, statistieken, grafieken per peilgebied en de topologie,
+/// als PDF of met `?formaat=html` als zelfstandig HTML-bestand.
+pub async fn run_rapport(
+    Query(query): Query<RapportQuery>,
+    Json(request): Json<RapportRequest>,
+) -> Result<Response, ApiError> {
+    let RapportRequest {
+        scenario,
+        resultaat,
+    } = request;
+    if resultaat.is_none() && scenario.parameters.duration_hours > MAX_NETWERK_UREN {
+        return Err(ApiError::Validation(format!(
+            "Scenario mag hoogstens {} uur duren",
+            MAX_NETWERK_UREN
+        )));
+    }
+    tokio::task::spawn_blocking(move || {
+        let resultaat = match resultaat {
+            Some(resultaat) => resultaat,
+            None => scenario.simuleer().map_err(scenario_fout)?,
+        };
+        let rapport = Rapport::simulatie(&scenario, &resultaat).map_err(rapport_fout)?;
+        rapport_response(&rapport, query.formaat, "simulatierapport")
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))?
+}
+
+/// Rapport als bijlage; de extensie volgt uit het formaat.
+pub(crate) fn rapport_response(
+    rapport: &Rapport,
+    formaat: RapportFormaat,
+    bestandsnaam: &str,
+) -> Result<Response, ApiError> {
+    let (content_type, extensie, inhoud) = match formaat {
+        RapportFormaat::Pdf => (
+            "application/pdf",
+            "pdf",
+            rapport.als_pdf().map_err(rapport_fout)?,
+        ),
+        RapportFormaat::Html => (
+            "text/html; charset=utf-8",
+            "html",
+            rapport.als_html().into_bytes(),
+        ),
+    };
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", bestandsnaam, extensie),
+            ),
+        ],
+        inhoud,
+    )
+        .into_response())
+}
+
+pub(crate) fn rapport_fout(e: RapportFout) -> ApiError {
+    match e {
+        RapportFout::GeenData => ApiError::Validation(e.to_string()),
+        e => ApiError::Internal(anyhow::anyhow!("{}", e)),
+    }
+}
+
+fn scenario_fout(e: ScenarioFout) -> ApiError {
+    match e {
+        ScenarioFout::SimulatieMislukt { .. } => ApiError::Internal(anyhow::anyhow!("{}", e)),
+        e => ApiError::Validation(e.to_string()),
+    }
+}
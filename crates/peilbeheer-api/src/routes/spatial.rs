@@ -0,0 +1,172 @@
+//! Ruimtelijke queries op peilgebieden en assets.
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Extension, Query},
+};
+use peilbeheer_core::asset::AssetRegistratie;
+use peilbeheer_core::peilgebied::PeilgebiedInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::ApiError;
+
+/// Standaard aantal dichtstbijzijnde assets.
+const DEFAULT_LIMIT: usize = 5;
+const MAX_LIMIT: usize = 100;
+/// Laag met de gemalen in `asset_registratie`.
+const GEMAAL_LAYER: &str = "gemaal";
+
+#[derive(Debug, Deserialize)]
+pub struct LookupQuery {
+    pub lat: f64,
+    pub lon: f64,
+    /// Kommagescheiden lagen voor de dichtstbijzijnde assets (standaard alle)
+    pub layers: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Asset met de afstand tot het opgevraagde punt.
+#[derive(Debug, Serialize)]
+pub struct AssetAfstand {
+    #[serde(flatten)]
+    pub asset: AssetRegistratie,
+    pub afstand_m: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpatialLookup {
+    pub lat: f64,
+    pub lon: f64,
+    /// Peilgebied waarin het punt ligt
+    pub peilgebied: Option<PeilgebiedInfo>,
+    /// Dichtstbijzijnde gemaal binnen het peilgebied, anders het
+    /// dichtstbijzijnde gemaal overall
+    pub gemaal: Option<AssetAfstand>,
+    pub gemaal_in_peilgebied: bool,
+    /// Dichtstbijzijnde assets, oplopend op afstand
+    pub assets: Vec<AssetAfstand>,
+}
+
+/// GET /api/spatial/lookup?lat=..&lon=.. - Peilgebied en dichtstbijzijnde
+/// gemaal en assets bij een punt.
+pub async fn lookup(
+    Query(query): Query<LookupQuery>,
+    Extension(db): Extension<Arc<Database>>,
+) -> Result<Json<SpatialLookup>, ApiError> {
+    if !(-90.0..=90.0).contains(&query.lat) || !(-180.0..=180.0).contains(&query.lon) {
+        return Err(ApiError::Validation(format!(
+            "Ongeldige locatie {}, {}",
+            query.lat, query.lon
+        )));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::Validation(format!(
+            "limit moet tussen 1 en {MAX_LIMIT} liggen"
+        )));
+    }
+    let layers: Option<Vec<&str>> = query.layers.as_ref().map(|l| {
+        l.split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect()
+    });
+
+    let peilgebied = db.find_peilgebied_for_point(query.lon, query.lat)?;
+
+    let nearest_gemaal = |peilgebied: Option<&str>| {
+        db.find_nearest_assets(query.lon, query.lat, Some(&[GEMAAL_LAYER]), peilgebied, 1)
+            .map(|mut gemalen| gemalen.pop())
+    };
+    let mut gemaal = match &peilgebied {
+        Some(p) => nearest_gemaal(Some(&p.code))?,
+        None => None,
+    };
+    let gemaal_in_peilgebied = gemaal.is_some();
+    if gemaal.is_none() {
+        gemaal = nearest_gemaal(None)?;
+    }
+
+    let assets = db.find_nearest_assets(query.lon, query.lat, layers.as_deref(), None, limit)?;
+
+    let met_afstand =
+        |(asset, afstand_m): (AssetRegistratie, f64)| AssetAfstand { asset, afstand_m };
+    Ok(Json(SpatialLookup {
+        lat: query.lat,
+        lon: query.lon,
+        peilgebied,
+        gemaal: gemaal.map(met_afstand),
+        gemaal_in_peilgebied,
+        assets: assets.into_iter().map(met_afstand).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(lat: f64, lon: f64, limit: Option<usize>) -> Query<LookupQuery> {
+        Query(LookupQuery { lat, lon, layers: None, limit })
+    }
+
+    fn asset(layer_type: &str, code: &str, lat: f64, lon: f64) -> AssetRegistratie {
+        AssetRegistratie {
+            layer_type: layer_type.to_string(),
+            code: code.to_string(),
+            naam: None,
+            lat: Some(lat),
+            lon: Some(lon),
+            extra_properties: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup_validatie() {
+        let db = Arc::new(Database::in_memory());
+
+        let result = lookup(query(91.0, 4.5, None), Extension(db.clone())).await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+
+        let result = lookup(query(52.0, 4.5, Some(0)), Extension(db.clone())).await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+
+        let result = lookup(query(52.0, 4.5, Some(MAX_LIMIT + 1)), Extension(db)).await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the DuckDB spatial extension"]
+    async fn test_lookup() {
+        let db = Arc::new(Database::in_memory());
+        db.execute(
+            "INSERT INTO peilgebied (code, naam, geometry)
+             VALUES ('PG_1', 'Noord', ST_GeomFromText('POLYGON((4.5 52.0, 4.6 52.0, 4.6 52.1, 4.5 52.1, 4.5 52.0))'))",
+            &[],
+        )
+        .unwrap();
+        db.write_asset_registraties(&[
+            asset(GEMAAL_LAYER, "GEM_BINNEN", 52.050, 4.590),
+            asset(GEMAAL_LAYER, "GEM_BUITEN", 52.050, 4.490),
+            asset("stuw", "STU_1", 52.050, 4.505),
+        ])
+        .unwrap();
+
+        // Het gemaal in het peilgebied gaat voor het dichtere gemaal erbuiten
+        let Json(binnen) = lookup(query(52.050, 4.501, None), Extension(db.clone())).await.unwrap();
+        assert_eq!(binnen.peilgebied.map(|p| p.code).as_deref(), Some("PG_1"));
+        assert!(binnen.gemaal_in_peilgebied);
+        assert_eq!(binnen.gemaal.unwrap().asset.code, "GEM_BINNEN");
+        let codes: Vec<&str> = binnen.assets.iter().map(|a| a.asset.code.as_str()).collect();
+        assert_eq!(codes, vec!["STU_1", "GEM_BUITEN", "GEM_BINNEN"]);
+
+        // Buiten elk peilgebied telt het dichtstbijzijnde gemaal
+        let Json(buiten) = lookup(query(52.050, 4.480, Some(1)), Extension(db)).await.unwrap();
+        assert!(buiten.peilgebied.is_none());
+        assert!(!buiten.gemaal_in_peilgebied);
+        assert_eq!(buiten.gemaal.unwrap().asset.code, "GEM_BUITEN");
+        assert_eq!(buiten.assets.len(), 1);
+    }
+}
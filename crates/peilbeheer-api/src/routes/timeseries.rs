@@ -14,6 +14,7 @@ use std::sync::Arc;
 use tracing::{info, warn};
 
 use peilbeheer_core::timeseries::*;
+use peilbeheer_simulatie::{HistorischePeriode, Regenscenario};
 
 use crate::timeseries_service::TimeSeriesService;
 
@@ -54,8 +55,23 @@ pub struct TimeSeriesQueryParams {
     pub aggregation: Option<String>,
     pub function: Option<String>,
     pub fill_gaps: Option<String>,
+    pub fill_value: Option<f64>,
 }
 
+/// Query parameters of a historical regenscenario.
+#[derive(Debug, Deserialize)]
+pub struct HistorischRegenscenarioParams {
+    /// Comma-separated peilgebied codes
+    pub peilgebieden: String,
+    pub start: String,
+    pub end: String,
+    /// Qualifier of the `neerslag` series (default: the measured series)
+    pub qualifier: Option<String>,
+}
+
+/// Longest period that can be replayed (one leap year).
+const MAX_HISTORISCHE_UREN: usize = 366 * 24;
+
 /// Request to write time series data.
 #[derive(Debug, Deserialize)]
 pub struct WriteTimeSeriesRequest {
@@ -99,6 +115,16 @@ pub struct RegisterSeriesRequest {
     pub min_value: Option<f64>,
     pub max_value: Option<f64>,
     pub retention_days: Option<u32>,
+    /// Expected time between deliveries (seconds)
+    pub expected_interval_seconds: Option<u32>,
+}
+
+/// Request to set the expected delivery interval of a series.
+#[derive(Debug, Deserialize)]
+pub struct ExpectedIntervalRequest {
+    pub qualifier: Option<String>,
+    /// Expected time between deliveries (seconds, None clears it)
+    pub expected_interval_seconds: Option<u32>,
 }
 
 /// Query time series data.
@@ -139,6 +165,8 @@ pub async fn query_timeseries(
             query.fill_gaps = Some(method);
         }
 
+    query.fill_value = params.fill_value;
+
     match service.query(&query).await {
         Ok(series) => Ok(Json(ApiResponse::ok(series))),
         Err(e) => {
@@ -148,6 +176,67 @@ pub async fn query_timeseries(
     }
 }
 
+/// Measured precipitation of a historical period as regenscenario.
+///
+/// Every peilgebied needs precipitation data in the period; a peilgebied
+/// without data is an error rather than a dry replay.
+pub async fn get_historisch_regenscenario(
+    Extension(service): Extension<Arc<TimeSeriesService>>,
+    Query(params): Query<HistorischRegenscenarioParams>,
+) -> Result<Json<ApiResponse<Regenscenario>>, Json<ApiResponse<()>>> {
+    let (Some(start), Some(end)) = (
+        parse_timestamp_iso(&params.start),
+        parse_timestamp_iso(&params.end),
+    ) else {
+        return Err(Json(ApiResponse::error("Invalid start or end timestamp")));
+    };
+    let periode = HistorischePeriode::nieuw(start, end)
+        .map_err(|e| Json(ApiResponse::error(e.to_string())))?;
+    if periode.uren() > MAX_HISTORISCHE_UREN {
+        return Err(Json(ApiResponse::error(format!(
+            "Period longer than {} hours",
+            MAX_HISTORISCHE_UREN
+        ))));
+    }
+
+    let codes: Vec<&str> = params
+        .peilgebieden
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .collect();
+    if codes.is_empty() {
+        return Err(Json(ApiResponse::error("peilgebieden is empty")));
+    }
+
+    let mut regen_per_uur = HashMap::new();
+    for code in codes {
+        match service
+            .historische_regen(code, &periode, params.qualifier.as_deref())
+            .await
+        {
+            Ok(Some(regen)) => {
+                regen_per_uur.insert(code.to_string(), regen);
+            }
+            Ok(None) => {
+                return Err(Json(ApiResponse::error(format!(
+                    "No precipitation data for {} in the period",
+                    code
+                ))));
+            }
+            Err(e) => {
+                warn!("Historical precipitation query error: {}", e);
+                return Err(Json(ApiResponse::error(format!("Query failed: {}", e))));
+            }
+        }
+    }
+
+    Ok(Json(ApiResponse::ok(Regenscenario::historisch(
+        periode,
+        regen_per_uur,
+    ))))
+}
+
 /// Write time series data.
 pub async fn write_timeseries(
     Extension(service): Extension<Arc<TimeSeriesService>>,
@@ -225,6 +314,7 @@ pub async fn register_series(
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
         retention_days: req.retention_days,
+        expected_interval_seconds: req.expected_interval_seconds,
         attributes: HashMap::new(),
     };
 
@@ -260,6 +350,34 @@ pub async fn get_series_metadata(
     }
 }
 
+/// Set the expected delivery interval of a series, used by no-data alert rules.
+pub async fn set_expected_interval(
+    Extension(service): Extension<Arc<TimeSeriesService>>,
+    Path((location_id, parameter)): Path<(String, String)>,
+    Json(req): Json<ExpectedIntervalRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, Json<ApiResponse<()>>> {
+    if req.expected_interval_seconds == Some(0) {
+        return Err(Json(ApiResponse::error("expected_interval_seconds must be positive")));
+    }
+
+    let series_id = if let Some(q) = &req.qualifier {
+        TimeSeriesId::with_qualifier(&location_id, &parameter, q)
+    } else {
+        TimeSeriesId::new(&location_id, &parameter)
+    };
+
+    match service.set_expected_interval(&series_id, req.expected_interval_seconds).await {
+        Ok(true) => Ok(Json(ApiResponse::ok(serde_json::json!({
+            "expected_interval_seconds": req.expected_interval_seconds
+        })))),
+        Ok(false) => Err(Json(ApiResponse::error("Series not found"))),
+        Err(e) => {
+            warn!("Set expected interval error: {}", e);
+            Err(Json(ApiResponse::error(format!("Update failed: {}", e))))
+        }
+    }
+}
+
 /// List all time series.
 pub async fn list_series(
     Extension(service): Extension<Arc<TimeSeriesService>>,
@@ -408,7 +526,9 @@ fn parse_fill_method(s: &str) -> Option<FillMethod> {
         "linear" | "interpolate" => Some(FillMethod::Linear),
         "forward" | "ffill" => Some(FillMethod::Forward),
         "backward" | "bfill" => Some(FillMethod::Backward),
-        "constant" => Some(FillMethod::Constant),
+        "constant" | "value" => Some(FillMethod::Constant),
+        "spline" | "cubic" => Some(FillMethod::Spline),
+        "seasonal" | "climatology" => Some(FillMethod::Seasonal),
         _ => None,
     }
 }
@@ -433,6 +553,10 @@ fn parse_source_type(s: &str) -> Option<TimeSeriesSourceType> {
         "hydronet" => Some(TimeSeriesSourceType::Hydronet),
         "dhydro" => Some(TimeSeriesSourceType::DHydro),
         "energyzero" => Some(TimeSeriesSourceType::EnergyZero),
+        "knmi" => Some(TimeSeriesSourceType::Knmi),
+        "rws" => Some(TimeSeriesSourceType::Rws),
+        "lizard" => Some(TimeSeriesSourceType::Lizard),
+        "meteoserver" => Some(TimeSeriesSourceType::Meteoserver),
         "manual" => Some(TimeSeriesSourceType::Manual),
         "calculated" => Some(TimeSeriesSourceType::Calculated),
         _ => Some(TimeSeriesSourceType::Custom(s.to_string())),
@@ -21,7 +21,7 @@ use crate::websocket_service::WebSocketServer;
 ///
 /// Example:
 /// ```javascript
-/// const ws = new WebSocket('ws://localhost:3000/api/ws');
+/// const ws = new WebSocket(`ws://localhost:3000/api/ws?access_token=${token}`);
 /// ws.onmessage = (event) => {
 ///     const msg = JSON.parse(event.data);
 ///     console.log('Received:', msg);
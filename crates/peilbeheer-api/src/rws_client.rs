@@ -0,0 +1,421 @@
+//! Rijkswaterstaat Waterinfo client for outer water levels.
+//!
+//! Water levels (`WATHTE`, surface water) of RWS locations are requested
+//! from the waterwebservices:
+//! - Measurements are stored periodically by [`RwsWaterinfoSync`] as time
+//!   series `waterstand` of the RWS location (m NAP).
+//! - [`RwsClient::buitenpeil_per_uur`] gives hourly outer water levels for
+//!   an optimization, from the RWS forecast or else the last measurement,
+//!   so the pump head follows the outer water level.
+
+use anyhow::Result as AnyhowResult;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use peilbeheer_core::TimeSeriesDataPoint;
+
+use crate::http_resilience::{HttpClient, HttpError};
+use crate::timeseries_service::TimeSeriesService;
+
+/// Longest period that can be requested at once (days).
+pub const MAX_PERIODE_DAGEN: i64 = 31;
+
+/// RWS measurements without a value use this placeholder.
+const GEEN_WAARDE: f64 = 999_999_999.0;
+
+/// RWS Waterinfo client configuration.
+#[derive(Debug, Clone)]
+pub struct RwsConfig {
+    pub base_url: String,
+    /// Locations whose water levels are stored periodically, e.g. `HOEK`
+    pub locaties: Vec<String>,
+    /// Time between syncs (seconds)
+    pub sync_interval_secs: u64,
+    /// Period fetched on each sync (hours)
+    pub sync_history_hours: i64,
+}
+
+impl Default for RwsConfig {
+    fn default() -> Self {
+        Self {
+            base_url: std::env::var("RWS_WATERINFO_BASE_URL").unwrap_or_else(|_| {
+                "https://waterwebservices.rijkswaterstaat.nl/ONLINEWAARNEMINGENSERVICES_DBO"
+                    .to_string()
+            }),
+            locaties: std::env::var("RWS_WATERINFO_LOCATIONS")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            sync_interval_secs: std::env::var("RWS_WATERINFO_SYNC_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            sync_history_hours: std::env::var("RWS_WATERINFO_HISTORY_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24),
+        }
+    }
+}
+
+/// Errors of the RWS Waterinfo client.
+#[derive(Debug, Error)]
+pub enum RwsClientError {
+    #[error("Invalid period: {0}")]
+    InvalidPeriod(String),
+    #[error("No water levels for location {0}")]
+    NoData(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("RWS returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+}
+
+/// Kind of water level series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcesType {
+    Meting,
+    Verwachting,
+}
+
+impl ProcesType {
+    fn as_str(self) -> &'static str {
+        match self {
+            ProcesType::Meting => "meting",
+            ProcesType::Verwachting => "verwachting",
+        }
+    }
+}
+
+/// Client for the RWS waterwebservices.
+pub struct RwsClient {
+    config: RwsConfig,
+    http_client: HttpClient,
+}
+
+impl RwsClient {
+    pub fn new(config: RwsConfig) -> Self {
+        Self {
+            config,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    pub fn config(&self) -> &RwsConfig {
+        &self.config
+    }
+
+    /// Water levels (m NAP) of a location in `[van, tot]`.
+    pub async fn waterstanden(
+        &self,
+        locatie: &str,
+        proces: ProcesType,
+        van: DateTime<Utc>,
+        tot: DateTime<Utc>,
+    ) -> Result<Vec<TimeSeriesDataPoint>, RwsClientError> {
+        if tot <= van || tot - van > Duration::days(MAX_PERIODE_DAGEN) {
+            return Err(RwsClientError::InvalidPeriod(format!(
+                "{} to {} (at most {} days)",
+                van, tot, MAX_PERIODE_DAGEN
+            )));
+        }
+
+        let url = format!(
+            "{}/OphalenWaarnemingen",
+            self.config.base_url.trim_end_matches('/')
+        );
+        let body = serde_json::json!({
+            "Locatie": {"Code": locatie},
+            "AquoPlusWaarnemingMetadata": {"AquoMetadata": {
+                "Compartiment": {"Code": "OW"},
+                "Grootheid": {"Code": "WATHTE"},
+                "ProcesType": proces.as_str(),
+            }},
+            "Periode": {
+                "Begindatumtijd": rws_time(van),
+                "Einddatumtijd": rws_time(tot),
+            },
+        });
+        debug!("RWS request for {} ({})", locatie, proces.as_str());
+
+        let response = self
+            .http_client
+            .send(self.http_client.post(&url).json(&body))
+            .await?;
+        let status = response.status();
+        // An empty period is answered with 204 or with Succesvol false
+        if status == reqwest::StatusCode::NO_CONTENT {
+            return Ok(Vec::new());
+        }
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(RwsClientError::Api { status, message });
+        }
+        let body: serde_json::Value = response.json().await.map_err(HttpError::from)?;
+        parse_waarnemingen(&body)
+    }
+
+    /// Outer water level per hour (m NAP) for `uren` hours from `start`.
+    ///
+    /// Uses the RWS forecast where available; hours without a forecast get
+    /// the previous hour's level, or the last measurement.
+    pub async fn buitenpeil_per_uur(
+        &self,
+        locatie: &str,
+        start: DateTime<Utc>,
+        uren: usize,
+    ) -> Result<Vec<f64>, RwsClientError> {
+        let tot = start + Duration::hours(uren as i64);
+        let verwachting = self
+            .waterstanden(locatie, ProcesType::Verwachting, start, tot)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("RWS forecast of {} unavailable: {}", locatie, e);
+                Vec::new()
+            });
+        let laatste_meting = self
+            .waterstanden(
+                locatie,
+                ProcesType::Meting,
+                start - Duration::hours(6),
+                Utc::now().max(start),
+            )
+            .await?
+            .last()
+            .map(|p| p.value);
+
+        let uren = uurgemiddelden(&verwachting, start, uren);
+        let mut vorige = laatste_meting.or_else(|| uren.iter().flatten().next().copied());
+        uren.into_iter()
+            .map(|uur| {
+                vorige = uur.or(vorige);
+                vorige.ok_or_else(|| RwsClientError::NoData(locatie.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Periodic storage of RWS water levels as time series.
+pub struct RwsWaterinfoSync {
+    client: Arc<RwsClient>,
+    timeseries: Arc<TimeSeriesService>,
+}
+
+/// Result of an RWS sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct RwsSyncResult {
+    pub locaties: usize,
+    pub points_written: usize,
+    pub errors: Vec<String>,
+}
+
+impl RwsWaterinfoSync {
+    pub fn new(client: Arc<RwsClient>, timeseries: Arc<TimeSeriesService>) -> Self {
+        Self { client, timeseries }
+    }
+
+    /// Start the periodic sync (no-op without locations).
+    pub fn start(self: &Arc<Self>) {
+        let config = self.client.config();
+        if config.locaties.is_empty() {
+            info!("RWS Waterinfo sync disabled (RWS_WATERINFO_LOCATIONS not set)");
+            return;
+        }
+
+        let sync = Arc::clone(self);
+        let interval = StdDuration::from_secs(config.sync_interval_secs.max(60));
+
+        tokio::spawn(async move {
+            info!("RWS Waterinfo sync started (interval: {:?})", interval);
+
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                let locaties = sync.client.config().locaties.clone();
+                match sync.sync(&locaties, None).await {
+                    Ok(result) if result.errors.is_empty() => info!(
+                        "RWS water levels synced: {} locations, {} points",
+                        result.locaties, result.points_written
+                    ),
+                    Ok(result) => warn!(
+                        "RWS sync finished with errors: {}",
+                        result.errors.join("; ")
+                    ),
+                    Err(e) => warn!("RWS sync failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Fetch and store the measured water levels of each location.
+    ///
+    /// Errors of single locations end up in the result.
+    pub async fn sync(
+        &self,
+        locaties: &[String],
+        uren: Option<i64>,
+    ) -> AnyhowResult<RwsSyncResult> {
+        let tot = Utc::now();
+        let van = tot - Duration::hours(uren.unwrap_or(self.client.config().sync_history_hours));
+
+        let mut result = RwsSyncResult {
+            locaties: 0,
+            points_written: 0,
+            errors: Vec::new(),
+        };
+        for locatie in locaties {
+            let data = match self
+                .client
+                .waterstanden(locatie, ProcesType::Meting, van, tot)
+                .await
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", locatie, e));
+                    continue;
+                }
+            };
+            match self.timeseries.import_rws_waterstanden(locatie, data).await {
+                Ok(write) => {
+                    result.locaties += 1;
+                    result.points_written += write.points_written;
+                }
+                Err(e) => result.errors.push(format!("{}: {}", locatie, e)),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Water levels in an `OphalenWaarnemingen` response, in m NAP.
+///
+/// RWS reports water levels in cm NAP; other units are kept as is.
+fn parse_waarnemingen(
+    body: &serde_json::Value,
+) -> Result<Vec<TimeSeriesDataPoint>, RwsClientError> {
+    if body["Succesvol"].as_bool() == Some(false) {
+        // No measurements in the period
+        return Ok(Vec::new());
+    }
+    let lijsten = body["WaarnemingenLijst"]
+        .as_array()
+        .ok_or_else(|| RwsClientError::Parse("WaarnemingenLijst is missing".to_string()))?;
+
+    let mut punten = Vec::new();
+    for lijst in lijsten {
+        let eenheid = lijst["AquoMetadata"]["Eenheid"]["Code"]
+            .as_str()
+            .unwrap_or("cm");
+        let factor = if eenheid == "cm" { 0.01 } else { 1.0 };
+        for meting in lijst["MetingenLijst"].as_array().into_iter().flatten() {
+            let Some(waarde) = meting["Meetwaarde"]["Waarde_Numeriek"].as_f64() else {
+                continue;
+            };
+            if waarde.abs() >= GEEN_WAARDE {
+                continue;
+            }
+            let tijd = meting["Tijdstip"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .ok_or_else(|| {
+                    RwsClientError::Parse(format!("invalid Tijdstip {}", meting["Tijdstip"]))
+                })?;
+            punten.push(TimeSeriesDataPoint::new(
+                tijd.with_timezone(&Utc),
+                waarde * factor,
+            ));
+        }
+    }
+    punten.sort_by_key(|p| p.timestamp);
+    Ok(punten)
+}
+
+/// Mean per hour from `start`; hours without points are None.
+fn uurgemiddelden(
+    punten: &[TimeSeriesDataPoint],
+    start: DateTime<Utc>,
+    uren: usize,
+) -> Vec<Option<f64>> {
+    let mut som = vec![(0.0, 0usize); uren];
+    for punt in punten {
+        let minuten = (punt.timestamp - start).num_minutes();
+        if minuten < 0 {
+            continue;
+        }
+        if let Some(uur) = som.get_mut((minuten / 60) as usize) {
+            uur.0 += punt.value;
+            uur.1 += 1;
+        }
+    }
+    som.into_iter()
+        .map(|(s, n)| (n > 0).then(|| s / n as f64))
+        .collect()
+}
+
+fn rws_time(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%dT%H:%M:%S%.3f+00:00").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_waarnemingen() {
+        let body = json!({
+            "Succesvol": true,
+            "WaarnemingenLijst": [{
+                "Locatie": {"Code": "HOEK"},
+                "AquoMetadata": {"Eenheid": {"Code": "cm"}},
+                "MetingenLijst": [
+                    {"Tijdstip": "2025-03-10T01:10:00.000+01:00", "Meetwaarde": {"Waarde_Numeriek": 85.0}},
+                    {"Tijdstip": "2025-03-10T01:00:00.000+01:00", "Meetwaarde": {"Waarde_Numeriek": -42.0}},
+                    {"Tijdstip": "2025-03-10T01:20:00.000+01:00", "Meetwaarde": {"Waarde_Numeriek": 999999999.0}}
+                ]
+            }]
+        });
+        let punten = parse_waarnemingen(&body).unwrap();
+        assert_eq!(punten.len(), 2);
+        assert_eq!(
+            punten[0].timestamp.to_rfc3339(),
+            "2025-03-10T00:00:00+00:00"
+        );
+        assert!((punten[0].value + 0.42).abs() < 1e-12);
+        assert!((punten[1].value - 0.85).abs() < 1e-12);
+
+        let leeg = json!({"Succesvol": false, "Foutmelding": "Geen gegevens gevonden!"});
+        assert!(parse_waarnemingen(&leeg).unwrap().is_empty());
+        assert!(parse_waarnemingen(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_uurgemiddelden() {
+        let start = DateTime::parse_from_rfc3339("2025-03-10T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let punt = |min: i64, v: f64| TimeSeriesDataPoint::new(start + Duration::minutes(min), v);
+        let punten = vec![punt(-10, 9.0), punt(0, 1.0), punt(30, 2.0), punt(130, 0.5)];
+        assert_eq!(
+            uurgemiddelden(&punten, start, 3),
+            vec![Some(1.5), None, Some(0.5)]
+        );
+    }
+}
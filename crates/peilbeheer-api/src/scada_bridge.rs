@@ -0,0 +1,476 @@
+//! SCADA bridge for reading and writing pump setpoints.
+//!
+//! The process automation is reached through the REST interface of an
+//! OPC-UA gateway (IoT-gateway style): `GET {url}/read?ids=...` returns the
+//! current value of OPC-UA nodes and `POST {url}/write` sets them. The
+//! bridge is optional: it stays disabled without `SCADA_GATEWAY_URL`.
+//!
+//! Scope: this crate has no native OPC-UA client (binary protocol, secure
+//! channels, certificates). Sites without such a gateway are not supported;
+//! the gateway does the OPC-UA side and its own access control.
+//!
+//! Each [`ScadaTagMapping`] links a gemaal to its nodes:
+//! - `setpoint_node`: the current pump setpoint (% of capacity)
+//! - `schema_node`: the node of one hour of the pump schedule, with `{uur}`
+//!   replaced by the local (Europe/Amsterdam) hour, 0-23
+//! - `lees_nodes`: further nodes to read, e.g. running state or flow
+//!
+//! Writing is guarded twice: the bridge runs in dry-run mode unless
+//! `SCADA_DRY_RUN=false`, and a write request is a dry run unless it asks
+//! otherwise. A dry run returns the writes it would have made.
+
+use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono_tz::Europe::Amsterdam;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use peilbeheer_core::energie::OptimalisatieResultaat;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Number of hours in a pump schedule.
+pub const SCHEMA_UREN: usize = 24;
+
+/// Placeholder for the hour in [`ScadaTagMapping::schema_node`].
+const UUR_PLACEHOLDER: &str = "{uur}";
+
+/// SCADA bridge configuration.
+#[derive(Debug, Clone)]
+pub struct ScadaConfig {
+    /// Base URL of the OPC-UA gateway; the bridge is disabled without it
+    pub gateway_url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Never write, only report the planned writes
+    pub dry_run: bool,
+    pub mappings: Vec<ScadaTagMapping>,
+}
+
+impl ScadaConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mappings = match std::env::var("SCADA_TAG_MAPPINGS") {
+            Ok(json) if !json.is_empty() => serde_json::from_str(&json)?,
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            gateway_url: std::env::var("SCADA_GATEWAY_URL")
+                .ok()
+                .filter(|u| !u.is_empty()),
+            username: std::env::var("SCADA_USERNAME")
+                .ok()
+                .filter(|u| !u.is_empty()),
+            password: std::env::var("SCADA_PASSWORD").ok(),
+            dry_run: std::env::var("SCADA_DRY_RUN")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "no"))
+                .unwrap_or(true),
+            mappings,
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.gateway_url.is_some()
+    }
+}
+
+/// OPC-UA nodes of a gemaal.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScadaTagMapping {
+    pub gemaal_code: String,
+    /// Node of the current setpoint (% of pump capacity)
+    pub setpoint_node: String,
+    /// Node of one schedule hour, containing `{uur}`
+    #[serde(default)]
+    pub schema_node: Option<String>,
+    /// Further nodes to read, by name
+    #[serde(default)]
+    pub lees_nodes: BTreeMap<String, String>,
+}
+
+/// Errors of the SCADA bridge.
+#[derive(Debug, Error)]
+pub enum ScadaError {
+    #[error("SCADA bridge not configured (SCADA_GATEWAY_URL not set)")]
+    NotConfigured,
+    #[error("No SCADA tags for gemaal {0}")]
+    UnknownGemaal(String),
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("Gateway returned error status {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Failed to parse response: {0}")]
+    Parse(String),
+    #[error("Gateway rejected writes: {0}")]
+    WriteRejected(String),
+}
+
+/// Value of a node as read from the gateway.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagWaarde {
+    /// `setpoint` or the name in `lees_nodes`
+    pub naam: String,
+    pub node: String,
+    pub waarde: Option<f64>,
+    /// Whether the gateway read the node successfully
+    pub goed: bool,
+    pub tijdstip: Option<DateTime<Utc>>,
+}
+
+/// A single setpoint write.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SetpointWrite {
+    pub node: String,
+    /// Setpoint (% of pump capacity)
+    pub waarde: f64,
+}
+
+/// Result of writing a pump schedule.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaSchrijfResultaat {
+    pub gemaal_code: String,
+    pub dry_run: bool,
+    pub writes: Vec<SetpointWrite>,
+}
+
+/// Client for the OPC-UA gateway.
+pub struct ScadaClient {
+    config: ScadaConfig,
+    http_client: HttpClient,
+}
+
+impl ScadaClient {
+    pub fn new(config: ScadaConfig) -> Self {
+        if !config.is_configured() {
+            info!("SCADA bridge disabled (SCADA_GATEWAY_URL not set)");
+        } else if config.dry_run {
+            info!("SCADA bridge in dry-run mode, setpoints are not written");
+        }
+        Self {
+            config,
+            http_client: HttpClient::shared(),
+        }
+    }
+
+    /// Read the setpoint and other nodes of a gemaal.
+    pub async fn lees_setpoints(&self, gemaal_code: &str) -> Result<Vec<TagWaarde>, ScadaError> {
+        let gateway = self.gateway_url()?;
+        let mapping = self.mapping(gemaal_code)?;
+
+        let nodes: Vec<(String, String)> =
+            std::iter::once(("setpoint".to_string(), mapping.setpoint_node.clone()))
+                .chain(mapping.lees_nodes.clone())
+                .collect();
+        let ids = nodes
+            .iter()
+            .map(|(_, node)| node.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let request = self
+            .http_client
+            .get(format!("{}/read", gateway))
+            .query(&[("ids", ids)]);
+        let body = self.send_json(request).await?;
+        parse_read_results(&body, &nodes)
+    }
+
+    /// Write an hourly pump schedule (fractions 0-1) as setpoints.
+    ///
+    /// The fraction of the hour that is running now goes to the current
+    /// setpoint; a schedule that has not started yet or has ended leaves it
+    /// alone. With a `schema_node` every hour is also written to its
+    /// schedule node. Only writes when neither the bridge nor the request is
+    /// a dry run.
+    pub async fn schrijf_schema(
+        &self,
+        gemaal_code: &str,
+        pomp_fracties: &[f64],
+        start: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<SchemaSchrijfResultaat, ScadaError> {
+        let gateway = self.gateway_url()?;
+        let mapping = self.mapping(gemaal_code)?;
+        let writes = build_writes(mapping, pomp_fracties, start, Utc::now())?;
+        let dry_run = dry_run || self.config.dry_run;
+
+        if dry_run {
+            info!(
+                "SCADA dry run for gemaal {}: {} setpoints not written",
+                gemaal_code,
+                writes.len()
+            );
+        } else {
+            let body: Vec<serde_json::Value> = writes
+                .iter()
+                .map(|w| serde_json::json!({ "id": w.node, "v": w.waarde }))
+                .collect();
+            let request = self
+                .http_client
+                .post(format!("{}/write", gateway))
+                .json(&body);
+            let response = self.send_json(request).await?;
+            check_write_results(&response)?;
+            warn!(
+                "SCADA: {} setpoints written for gemaal {}",
+                writes.len(),
+                gemaal_code
+            );
+        }
+
+        Ok(SchemaSchrijfResultaat {
+            gemaal_code: gemaal_code.to_string(),
+            dry_run,
+            writes,
+        })
+    }
+
+    fn gateway_url(&self) -> Result<&str, ScadaError> {
+        self.config
+            .gateway_url
+            .as_deref()
+            .map(|u| u.trim_end_matches('/'))
+            .ok_or(ScadaError::NotConfigured)
+    }
+
+    fn mapping(&self, gemaal_code: &str) -> Result<&ScadaTagMapping, ScadaError> {
+        self.config
+            .mappings
+            .iter()
+            .find(|m| m.gemaal_code == gemaal_code)
+            .ok_or_else(|| ScadaError::UnknownGemaal(gemaal_code.to_string()))
+    }
+
+    async fn send_json(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<serde_json::Value, ScadaError> {
+        let request = match &self.config.username {
+            Some(username) => request.basic_auth(username, self.config.password.as_ref()),
+            None => request,
+        };
+        let response = self
+            .http_client
+            .send(request.header("Accept", "application/json"))
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(ScadaError::Api { status, message });
+        }
+        Ok(response.json().await.map_err(HttpError::from)?)
+    }
+}
+
+/// Hourly pump fractions of the optimized schedule.
+pub fn pomp_fracties(resultaat: &OptimalisatieResultaat) -> Vec<f64> {
+    resultaat
+        .uren
+        .iter()
+        .map(|u| u.pomp_fractie_optimaal)
+        .collect()
+}
+
+/// Setpoint writes at `now` of a schedule starting at `start`.
+///
+/// Schedule hours are named by their local hour. When summer time ends the
+/// repeated hour is written twice, the later value winning; when it starts
+/// the skipped hour is not written.
+fn build_writes(
+    mapping: &ScadaTagMapping,
+    pomp_fracties: &[f64],
+    start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Vec<SetpointWrite>, ScadaError> {
+    if pomp_fracties.is_empty() || pomp_fracties.len() > SCHEMA_UREN {
+        return Err(ScadaError::InvalidSchedule(format!(
+            "expected 1 to {} hours, got {}",
+            SCHEMA_UREN,
+            pomp_fracties.len()
+        )));
+    }
+    if let Some((uur, fractie)) = pomp_fracties
+        .iter()
+        .enumerate()
+        .find(|(_, f)| !(0.0..=1.0).contains(*f))
+    {
+        return Err(ScadaError::InvalidSchedule(format!(
+            "pump fraction {} of hour {} outside 0-1",
+            fractie, uur
+        )));
+    }
+
+    let setpoint = |fractie: f64| (fractie * 1000.0).round() / 10.0;
+
+    let mut writes = Vec::new();
+    if now >= start {
+        let lopend_uur = (now - start).num_hours() as usize;
+        if let Some(&fractie) = pomp_fracties.get(lopend_uur) {
+            writes.push(SetpointWrite {
+                node: mapping.setpoint_node.clone(),
+                waarde: setpoint(fractie),
+            });
+        }
+    }
+    if let Some(schema_node) = &mapping.schema_node {
+        writes.extend(pomp_fracties.iter().enumerate().map(|(i, &f)| {
+            let uur = (start + Duration::hours(i as i64))
+                .with_timezone(&Amsterdam)
+                .hour();
+            SetpointWrite {
+                node: schema_node.replace(UUR_PLACEHOLDER, &uur.to_string()),
+                waarde: setpoint(f),
+            }
+        }));
+    }
+    Ok(writes)
+}
+
+/// Values of `nodes` in a gateway read response.
+fn parse_read_results(
+    body: &serde_json::Value,
+    nodes: &[(String, String)],
+) -> Result<Vec<TagWaarde>, ScadaError> {
+    let results = body["readResults"]
+        .as_array()
+        .ok_or_else(|| ScadaError::Parse("missing readResults".to_string()))?;
+
+    Ok(nodes
+        .iter()
+        .map(|(naam, node)| {
+            let result = results.iter().find(|r| r["id"].as_str() == Some(node));
+            TagWaarde {
+                naam: naam.clone(),
+                node: node.clone(),
+                waarde: result.and_then(|r| match &r["v"] {
+                    serde_json::Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+                    v => v.as_f64(),
+                }),
+                goed: result.and_then(|r| r["s"].as_bool()).unwrap_or(false),
+                tijdstip: result
+                    .and_then(|r| r["t"].as_i64())
+                    .and_then(DateTime::from_timestamp_millis),
+            }
+        })
+        .collect())
+}
+
+/// Fail when the gateway did not accept every write.
+fn check_write_results(body: &serde_json::Value) -> Result<(), ScadaError> {
+    let results = body["writeResults"]
+        .as_array()
+        .ok_or_else(|| ScadaError::Parse("missing writeResults".to_string()))?;
+    let rejected: Vec<String> = results
+        .iter()
+        .filter(|r| !r["s"].as_bool().unwrap_or(false))
+        .map(|r| {
+            format!(
+                "{} ({})",
+                r["id"].as_str().unwrap_or("?"),
+                r["r"].as_str().unwrap_or("unknown reason")
+            )
+        })
+        .collect();
+    if rejected.is_empty() {
+        Ok(())
+    } else {
+        Err(ScadaError::WriteRejected(rejected.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn mapping() -> ScadaTagMapping {
+        ScadaTagMapping {
+            gemaal_code: "GM_001".to_string(),
+            setpoint_node: "Rijnland.GM_001.Setpoint".to_string(),
+            schema_node: Some("Rijnland.GM_001.Schema.Uur{uur}".to_string()),
+            lees_nodes: BTreeMap::from([("debiet".to_string(), "Rijnland.GM_001.Q".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_build_writes() {
+        // 22:00 UTC is 23:00 in winter time
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 22, 0, 0).unwrap();
+        let now = start + Duration::minutes(90);
+        let writes = build_writes(&mapping(), &[0.5, 0.0, 1.0], start, now).unwrap();
+
+        assert_eq!(writes.len(), 4);
+        // The setpoint gets the hour running now
+        assert_eq!(writes[0].node, "Rijnland.GM_001.Setpoint");
+        assert_eq!(writes[0].waarde, 0.0);
+        // The schedule wraps around local midnight
+        assert_eq!(writes[1].node, "Rijnland.GM_001.Schema.Uur23");
+        assert_eq!(writes[1].waarde, 50.0);
+        assert_eq!(writes[2].node, "Rijnland.GM_001.Schema.Uur0");
+        assert_eq!(writes[3].node, "Rijnland.GM_001.Schema.Uur1");
+        assert_eq!(writes[3].waarde, 100.0);
+
+        assert!(matches!(
+            build_writes(&mapping(), &[0.5, 1.2], start, now),
+            Err(ScadaError::InvalidSchedule(_))
+        ));
+        assert!(build_writes(&mapping(), &[], start, now).is_err());
+        assert!(build_writes(&mapping(), &[f64::NAN], start, now).is_err());
+    }
+
+    #[test]
+    fn test_build_writes_outside_schedule() {
+        // 10:00 UTC is 12:00 in summer time
+        let start = Utc.with_ymd_and_hms(2026, 7, 1, 10, 0, 0).unwrap();
+
+        // A future schedule leaves the current setpoint alone
+        let writes =
+            build_writes(&mapping(), &[0.5, 0.8], start, start - Duration::hours(3)).unwrap();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0].node, "Rijnland.GM_001.Schema.Uur12");
+        assert_eq!(writes[1].node, "Rijnland.GM_001.Schema.Uur13");
+
+        // So does an ended one
+        let writes =
+            build_writes(&mapping(), &[0.5, 0.8], start, start + Duration::hours(2)).unwrap();
+        assert!(writes.iter().all(|w| w.node != "Rijnland.GM_001.Setpoint"));
+    }
+
+    #[test]
+    fn test_parse_read_results() {
+        let body = serde_json::json!({
+            "readResults": [
+                {"id": "Rijnland.GM_001.Setpoint", "s": true, "r": "", "v": 40.0, "t": 1_772_402_400_000_i64},
+                {"id": "Rijnland.GM_001.Q", "s": false, "r": "Bad", "v": null, "t": 1_772_402_400_000_i64}
+            ]
+        });
+        let m = mapping();
+        let nodes = vec![
+            ("setpoint".to_string(), m.setpoint_node.clone()),
+            ("debiet".to_string(), "Rijnland.GM_001.Q".to_string()),
+        ];
+        let waarden = parse_read_results(&body, &nodes).unwrap();
+
+        assert_eq!(waarden[0].waarde, Some(40.0));
+        assert!(waarden[0].goed);
+        assert!(waarden[0].tijdstip.is_some());
+        assert_eq!(waarden[1].waarde, None);
+        assert!(!waarden[1].goed);
+
+        let rejected = serde_json::json!({
+            "writeResults": [
+                {"id": "a", "s": true, "r": ""},
+                {"id": "b", "s": false, "r": "Read only"}
+            ]
+        });
+        assert!(matches!(
+            check_write_results(&rejected),
+            Err(ScadaError::WriteRejected(msg)) if msg == "b (Read only)"
+        ));
+    }
+}
@@ -15,7 +15,7 @@ use peilbeheer_core::{
     UpdateScenarioRequest,
 };
 
-use crate::db::Database;
+use crate::db::{Database, is_no_rows};
 
 /// Scenario management service.
 pub struct ScenarioService {
@@ -52,28 +52,29 @@ impl ScenarioService {
                 start_time, end_time, time_step,
                 boundary_conditions, initial_conditions, model_parameters,
                 created_at, created_by, updated_at,
-                is_base_scenario, base_scenario_id, status, tags
+                is_base_scenario, base_scenario_id, status, tags, peilgebieden
             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             &[
-                &id.as_bytes(),
-                &req.name.as_bytes(),
-                &req.description.as_ref().map(|s| s.as_bytes()),
-                &req.model_id.as_bytes(),
-                &None::<&[u8]>, // model_type
-                &start_str.as_bytes(),
-                &end_str.as_bytes(),
-                &(req.time_step.to_string()).as_bytes(),
-                &serde_json::to_string(&boundary_json).unwrap().as_bytes(),
-                &serde_json::to_string(&initial_json).unwrap().as_bytes(),
-                &serde_json::to_string(&model_json).unwrap().as_bytes(),
-                &now_str.as_bytes(),
-                &req.created_by.as_ref().map(|s| s.as_bytes()),
-                &now_str.as_bytes(),
-                &(if req.base_scenario_id.is_none() { "1" } else { "0" }).as_bytes(),
-                &req.base_scenario_id.as_ref().map(|s| s.as_bytes()),
-                &StoredScenarioStatus::Draft.as_str().as_bytes(),
-                &serde_json::to_string(&tags_json).unwrap().as_bytes(),
+                &id as &dyn duckdb::ToSql,
+                &req.name,
+                &req.description,
+                &req.model_id,
+                &None::<String>, // model_type
+                &start_str,
+                &end_str,
+                &req.time_step,
+                &serde_json::to_string(&boundary_json).unwrap(),
+                &serde_json::to_string(&initial_json).unwrap(),
+                &serde_json::to_string(&model_json).unwrap(),
+                &now_str,
+                &req.created_by,
+                &now_str,
+                &req.base_scenario_id.is_none(),
+                &req.base_scenario_id,
+                &StoredScenarioStatus::Draft.as_str(),
+                &serde_json::to_string(&tags_json).unwrap(),
+                &serde_json::to_string(&req.peilgebieden).unwrap(),
             ],
         )?;
 
@@ -99,6 +100,7 @@ impl ScenarioService {
             base_scenario_id: req.base_scenario_id.clone(),
             status: StoredScenarioStatus::Draft.as_str().to_string(),
             tags: tags_json,
+            peilgebieden: req.peilgebieden.clone(),
         })
     }
 
@@ -107,13 +109,14 @@ impl ScenarioService {
         let result = self.db.query_row(
             r#"
             SELECT id, name, description, model_id, model_type,
-                   start_time, end_time, time_step,
-                   boundary_conditions, initial_conditions, model_parameters,
-                   created_at, created_by, updated_at,
-                   is_base_scenario, base_scenario_id, status, tags
+                   CAST(start_time AS VARCHAR), CAST(end_time AS VARCHAR), time_step,
+                   CAST(boundary_conditions AS VARCHAR), CAST(initial_conditions AS VARCHAR),
+                   CAST(model_parameters AS VARCHAR),
+                   CAST(created_at AS VARCHAR), created_by, CAST(updated_at AS VARCHAR),
+                   is_base_scenario, base_scenario_id, status, tags, peilgebieden
             FROM scenarios WHERE id = ?
             "#,
-            &[&id.as_bytes()],
+            &[&id],
             |row| {
                 Ok(StoredScenario {
                     id: row.get::<_, String>(0)?,
@@ -130,17 +133,18 @@ impl ScenarioService {
                     created_at: parse_timestamp(row.get::<_, String>(11)?.as_str()),
                     created_by: row.get::<_, Option<String>>(12)?,
                     updated_at: parse_timestamp(row.get::<_, String>(13)?.as_str()),
-                    is_base_scenario: row.get::<_, i32>(14)? == 1,
+                    is_base_scenario: row.get::<_, Option<bool>>(14)?.unwrap_or(false),
                     base_scenario_id: row.get::<_, Option<String>>(15)?,
                     status: row.get::<_, String>(16)?,
                     tags: parse_json_value(row.get::<_, Option<String>>(17)?),
+                    peilgebieden: parse_json_array(row.get::<_, Option<String>>(18)?),
                 })
             },
         );
 
         match result {
             Ok(scenario) => Ok(Some(scenario)),
-            Err(e) if e.to_string().contains("QueryReturnedNoRows") => Ok(None),
+            Err(e) if is_no_rows(&e) => Ok(None),
             Err(e) => Err(e),
         }
     }
@@ -155,10 +159,11 @@ impl ScenarioService {
         let mut query = String::from(
             r#"
             SELECT id, name, description, model_id, model_type,
-                   start_time, end_time, time_step,
-                   boundary_conditions, initial_conditions, model_parameters,
-                   created_at, created_by, updated_at,
-                   is_base_scenario, base_scenario_id, status, tags
+                   CAST(start_time AS VARCHAR), CAST(end_time AS VARCHAR), time_step,
+                   CAST(boundary_conditions AS VARCHAR), CAST(initial_conditions AS VARCHAR),
+                   CAST(model_parameters AS VARCHAR),
+                   CAST(created_at AS VARCHAR), created_by, CAST(updated_at AS VARCHAR),
+                   is_base_scenario, base_scenario_id, status, tags, peilgebieden
             FROM scenarios WHERE 1=1
             "#,
         );
@@ -195,10 +200,11 @@ impl ScenarioService {
                 created_at: parse_timestamp(row.get::<_, String>(11)?.as_str()),
                 created_by: row.get::<_, Option<String>>(12)?,
                 updated_at: parse_timestamp(row.get::<_, String>(13)?.as_str()),
-                is_base_scenario: row.get::<_, i32>(14)? == 1,
+                is_base_scenario: row.get::<_, Option<bool>>(14)?.unwrap_or(false),
                 base_scenario_id: row.get::<_, Option<String>>(15)?,
                 status: row.get::<_, String>(16)?,
                 tags: parse_json_value(row.get::<_, Option<String>>(17)?),
+                peilgebieden: parse_json_array(row.get::<_, Option<String>>(18)?),
             })
         })
     }
@@ -216,18 +222,27 @@ impl ScenarioService {
         })?;
 
         let mut updates = Vec::new();
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
 
         if let Some(name) = &req.name {
-            updates.push(format!("name = '{}'", name));
+            updates.push("name = ?");
+            params.push(Box::new(name.clone()));
         }
         if let Some(desc) = &req.description {
-            updates.push(format!("description = '{}'", desc));
+            updates.push("description = ?");
+            params.push(Box::new(desc.clone()));
         }
         if let Some(status) = &req.status {
-            updates.push(format!("status = '{}'", status.as_str()));
+            updates.push("status = ?");
+            params.push(Box::new(status.as_str().to_string()));
         }
         if let Some(ref tags) = req.tags {
-            updates.push(format!("tags = '{}'", serde_json::to_string(tags).unwrap()));
+            updates.push("tags = ?");
+            params.push(Box::new(serde_json::to_string(tags)?));
+        }
+        if let Some(ref peilgebieden) = req.peilgebieden {
+            updates.push("peilgebieden = ?");
+            params.push(Box::new(serde_json::to_string(peilgebieden)?));
         }
 
         if updates.is_empty() {
@@ -235,15 +250,13 @@ impl ScenarioService {
         }
 
         let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
-        updates.push(format!("updated_at = '{}'", now));
-
-        let query = format!(
-            "UPDATE scenarios SET {} WHERE id = '{}'",
-            updates.join(", "),
-            id
-        );
+        updates.push("updated_at = ?");
+        params.push(Box::new(now));
+        params.push(Box::new(id.to_string()));
 
-        self.db.execute(&query, &[])?;
+        let query = format!("UPDATE scenarios SET {} WHERE id = ?", updates.join(", "));
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.execute(&query, &param_refs)?;
 
         // Log wijziging
         self.log_scenario_change(id, "updated", Some(&json!(old)), None)?;
@@ -293,11 +306,11 @@ impl ScenarioService {
                 start_time, end_time, time_step,
                 boundary_conditions, initial_conditions, model_parameters,
                 created_at, created_by, updated_at,
-                is_base_scenario, base_scenario_id, status, tags
-            ) VALUES ('{}', '{}', '{}', '{}', '{}', '{}', '{}', {}, '{}', '{}', '{}', '{}', '{}', '{}', {}, {}, '{}', '{}')
+                is_base_scenario, base_scenario_id, status, tags, peilgebieden
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, FALSE, ?, ?, ?, ?)
             "#,
             &[
-                &new_id,
+                &new_id as &dyn duckdb::ToSql,
                 &req.new_name,
                 &req.new_description.as_ref().unwrap_or(&source.description.clone().unwrap_or_default()),
                 &source.model_id,
@@ -311,10 +324,10 @@ impl ScenarioService {
                 &now_str,
                 &user.unwrap_or(""),
                 &now_str,
-                &"0",
                 &id,
                 &StoredScenarioStatus::Draft.as_str(),
                 &serde_json::to_string(&source.tags).unwrap(),
+                &serde_json::to_string(&source.peilgebieden).unwrap(),
             ],
         )?;
 
@@ -337,28 +350,31 @@ impl ScenarioService {
             r#"
             INSERT INTO scenario_results (
                 id, scenario_id, status, started_at, created_at, created_by
-            ) VALUES ('{}', '{}', '{}', '{}', '{}', '{}')
+            ) VALUES (?, ?, ?, ?, ?, ?)
             "#,
             &[
-                &result_id.as_bytes(),
-                &scenario_id.as_bytes(),
-                &ExecutionStatus::Pending.as_str().as_bytes(),
-                &now_str.as_bytes(),
-                &now_str.as_bytes(),
-                &user.unwrap_or("").as_bytes(),
+                &result_id as &dyn duckdb::ToSql,
+                &scenario_id,
+                &ExecutionStatus::Pending.as_str(),
+                &now_str,
+                &now_str,
+                &user,
             ],
         )?;
 
         // Update scenario status
         self.db.execute(
-            &format!("UPDATE scenarios SET status = '{}' WHERE id = '{}'", StoredScenarioStatus::Active.as_str(), scenario_id),
-            &[],
+            "UPDATE scenarios SET status = ? WHERE id = ?",
+            &[&StoredScenarioStatus::Active.as_str() as &dyn duckdb::ToSql, &scenario_id],
         )?;
 
         Ok(result_id)
     }
 
     /// Update scenario execution result.
+    ///
+    /// A finished execution gets its completion time, and the status of its
+    /// scenario follows (see [`Self::sync_scenario_status`]).
     pub fn update_scenario_result(
         &self,
         result_id: &str,
@@ -367,73 +383,105 @@ impl ScenarioService {
         error_message: Option<&str>,
         dhydro_job_id: Option<&str>,
     ) -> anyhow::Result<()> {
-        let mut updates = vec![format!("status = '{}'", status.as_str())];
+        let mut updates = vec!["status = ?".to_string()];
+        let mut params: Vec<Box<dyn duckdb::ToSql>> = vec![Box::new(status.as_str())];
 
-        if status == ExecutionStatus::Completed || status == ExecutionStatus::Failed {
-            let now = Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string();
-            updates.push(format!("completed_at = '{}'", now));
+        if status.is_finished() {
+            updates.push("completed_at = ?".to_string());
+            params.push(Box::new(Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string()));
         }
 
         if let Some(summary) = results_summary {
-            updates.push(format!("results_summary = '{}'", serde_json::to_string(summary).unwrap()));
+            updates.push("results_summary = ?".to_string());
+            params.push(Box::new(serde_json::to_string(summary)?));
         }
 
+        // Error messages come from D-Hydro and may contain quotes
         if let Some(msg) = error_message {
-            updates.push(format!("error_message = '{}'", msg));
+            updates.push("error_message = ?".to_string());
+            params.push(Box::new(msg.to_string()));
         }
 
         if let Some(job_id) = dhydro_job_id {
-            updates.push(format!("dhydro_job_id = '{}'", job_id));
+            updates.push("dhydro_job_id = ?".to_string());
+            params.push(Box::new(job_id.to_string()));
         }
 
+        params.push(Box::new(result_id.to_string()));
         let query = format!(
-            "UPDATE scenario_results SET {} WHERE id = '{}'",
-            updates.join(", "),
-            result_id
+            "UPDATE scenario_results SET {} WHERE id = ?",
+            updates.join(", ")
         );
 
-        self.db.execute(&query, &[])?;
+        let param_refs: Vec<&dyn duckdb::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.db.execute(&query, &param_refs)?;
+
+        if status.is_finished() {
+            self.sync_scenario_status(result_id)?;
+        }
         Ok(())
     }
 
+    /// Set the status of the scenario of an execution from its executions.
+    ///
+    /// A scenario stays active while an execution is pending or running, or
+    /// once one completed; when all failed or were cancelled it returns to
+    /// draft. Archived scenarios are left alone.
+    fn sync_scenario_status(&self, result_id: &str) -> anyhow::Result<()> {
+        self.db.execute(
+            r#"
+            UPDATE scenarios SET
+                status = CASE WHEN EXISTS (
+                    SELECT 1 FROM scenario_results r
+                    WHERE r.scenario_id = scenarios.id AND r.status IN (?, ?, ?)
+                ) THEN ? ELSE ? END,
+                updated_at = ?
+            WHERE id = (SELECT scenario_id FROM scenario_results WHERE id = ?) AND status <> ?
+            "#,
+            &[
+                &ExecutionStatus::Pending.as_str() as &dyn duckdb::ToSql,
+                &ExecutionStatus::Running.as_str(),
+                &ExecutionStatus::Completed.as_str(),
+                &StoredScenarioStatus::Active.as_str(),
+                &StoredScenarioStatus::Draft.as_str(),
+                &Utc::now().format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+                &result_id,
+                &StoredScenarioStatus::Archived.as_str(),
+            ],
+        )
+    }
+
     /// Get scenario results.
     pub fn get_scenario_results(
         &self,
         scenario_id: &str,
     ) -> anyhow::Result<Vec<StoredScenarioResult>> {
+        self.db.query(
+            &format!(
+                "SELECT {} FROM scenario_results WHERE scenario_id = ? ORDER BY created_at DESC",
+                RESULT_COLUMNS
+            ),
+            &[&scenario_id as &dyn duckdb::ToSql],
+            result_from_row,
+        )
+    }
+
+    /// Executions handed to D-Hydro that have not finished yet.
+    pub fn list_running_dhydro_results(&self) -> anyhow::Result<Vec<StoredScenarioResult>> {
         self.db.query(
             &format!(
                 r#"
-                SELECT id, scenario_id, status, started_at, completed_at, duration_seconds,
-                       results_summary, time_series_count, output_files,
-                       error_message, error_code, created_at, created_by,
-                       dhydro_job_id, dhydro_result_url
-                FROM scenario_results
-                WHERE scenario_id = '{}'
-                ORDER BY created_at DESC
+                SELECT {} FROM scenario_results
+                WHERE dhydro_job_id IS NOT NULL AND status IN (?, ?)
+                ORDER BY created_at
                 "#,
-                scenario_id
+                RESULT_COLUMNS
             ),
-            &[],
-            |row| {
-                Ok(StoredScenarioResult {
-                    id: row.get::<_, String>(0)?,
-                    scenario_id: row.get::<_, String>(1)?,
-                    status: row.get::<_, String>(2)?,
-                    started_at: row.get::<_, Option<String>>(3)?.map(|s| parse_timestamp(&s)),
-                    completed_at: row.get::<_, Option<String>>(4)?.map(|s| parse_timestamp(&s)),
-                    duration_seconds: row.get::<_, Option<i32>>(5)?,
-                    results_summary: parse_json_value(row.get::<_, Option<String>>(6)?),
-                    time_series_count: row.get::<_, i32>(7)?,
-                    output_files: parse_json_value(row.get::<_, Option<String>>(8)?),
-                    error_message: row.get::<_, Option<String>>(9)?,
-                    error_code: row.get::<_, Option<String>>(10)?,
-                    created_at: parse_timestamp(row.get::<_, String>(11)?.as_str()),
-                    created_by: row.get::<_, Option<String>>(12)?,
-                    dhydro_job_id: row.get::<_, Option<String>>(13)?,
-                    dhydro_result_url: row.get::<_, Option<String>>(14)?,
-                })
-            },
+            &[
+                &ExecutionStatus::Pending.as_str() as &dyn duckdb::ToSql,
+                &ExecutionStatus::Running.as_str(),
+            ],
+            result_from_row,
         )
     }
 
@@ -575,15 +623,15 @@ impl ScenarioService {
             r#"
             INSERT INTO scenarios_history (
                 id, scenario_id, changed_at, change_type, old_values, new_values
-            ) VALUES ('{}', '{}', '{}', '{}', '{}', '{}')
+            ) VALUES (?, ?, ?, ?, ?, ?)
             "#,
             &[
-                &history_id.as_bytes(),
-                &scenario_id.as_bytes(),
-                &now.as_bytes(),
-                &change_type.as_bytes(),
-                &old_json.unwrap_or_default().as_bytes(),
-                &new_json.unwrap_or_default().as_bytes(),
+                &history_id as &dyn duckdb::ToSql,
+                &scenario_id,
+                &now,
+                &change_type,
+                &old_json,
+                &new_json,
             ],
         )?;
 
@@ -591,12 +639,37 @@ impl ScenarioService {
     }
 }
 
+/// Columns read by [`result_from_row`].
+const RESULT_COLUMNS: &str = "id, scenario_id, status, CAST(started_at AS VARCHAR), \
+     CAST(completed_at AS VARCHAR), duration_seconds, CAST(results_summary AS VARCHAR), \
+     time_series_count, CAST(output_files AS VARCHAR), error_message, error_code, \
+     CAST(created_at AS VARCHAR), created_by, dhydro_job_id, dhydro_result_url";
+
+fn result_from_row(row: &duckdb::Row) -> duckdb::Result<StoredScenarioResult> {
+    Ok(StoredScenarioResult {
+        id: row.get::<_, String>(0)?,
+        scenario_id: row.get::<_, String>(1)?,
+        status: row.get::<_, String>(2)?,
+        started_at: row.get::<_, Option<String>>(3)?.map(|s| parse_timestamp(&s)),
+        completed_at: row.get::<_, Option<String>>(4)?.map(|s| parse_timestamp(&s)),
+        duration_seconds: row.get::<_, Option<i32>>(5)?,
+        results_summary: parse_json_value(row.get::<_, Option<String>>(6)?),
+        time_series_count: row.get::<_, i32>(7)?,
+        output_files: parse_json_value(row.get::<_, Option<String>>(8)?),
+        error_message: row.get::<_, Option<String>>(9)?,
+        error_code: row.get::<_, Option<String>>(10)?,
+        created_at: parse_timestamp(row.get::<_, String>(11)?.as_str()),
+        created_by: row.get::<_, Option<String>>(12)?,
+        dhydro_job_id: row.get::<_, Option<String>>(13)?,
+        dhydro_result_url: row.get::<_, Option<String>>(14)?,
+    })
+}
+
 /// Helper function to parse timestamp strings.
 fn parse_timestamp(s: &str) -> DateTime<Utc> {
     use chrono::NaiveDateTime;
 
-    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.6f")
-        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
         .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.6f"))
         .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
         .map(|ndt| ndt.and_utc())
@@ -609,6 +682,12 @@ fn parse_json_value(s: Option<String>) -> serde_json::Value {
         .unwrap_or(serde_json::Value::Null)
 }
 
+/// Helper function to parse JSON string arrays from DuckDB strings.
+fn parse_json_array(s: Option<String>) -> Vec<String> {
+    s.and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -625,6 +704,95 @@ mod tests {
         assert_eq!(id2.len(), 16);
     }
 
+    #[test]
+    fn test_update_scenario_binds_values() {
+        let service = ScenarioService::new(Arc::new(Database::in_memory()));
+        let scenario = service
+            .create_scenario(&CreateScenarioRequest {
+                name: "Bui".to_string(),
+                description: None,
+                model_id: "model".to_string(),
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                time_step: 60,
+                boundary_conditions: None,
+                initial_conditions: None,
+                model_parameters: None,
+                base_scenario_id: None,
+                tags: Vec::new(),
+                peilgebieden: vec!["PG-1".to_string()],
+                created_by: None,
+            })
+            .unwrap();
+
+        let injectie = "PG-2', name = 'overschreven".to_string();
+        let updated = service
+            .update_scenario(
+                &scenario.id,
+                &UpdateScenarioRequest {
+                    name: Some("Bui van O'Neill".to_string()),
+                    description: None,
+                    start_time: None,
+                    end_time: None,
+                    time_step: None,
+                    boundary_conditions: None,
+                    initial_conditions: None,
+                    model_parameters: None,
+                    status: None,
+                    tags: None,
+                    peilgebieden: Some(vec![injectie.clone()]),
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!(updated.name, "Bui van O'Neill");
+        assert_eq!(updated.peilgebieden, vec![injectie]);
+    }
+
+    #[test]
+    fn test_execution_lifecycle() {
+        let service = ScenarioService::new(Arc::new(Database::in_memory()));
+        let scenario = service
+            .create_scenario(&CreateScenarioRequest {
+                name: "Bui".to_string(),
+                description: None,
+                model_id: "model".to_string(),
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                time_step: 60,
+                boundary_conditions: None,
+                initial_conditions: None,
+                model_parameters: None,
+                base_scenario_id: None,
+                tags: Vec::new(),
+                peilgebieden: Vec::new(),
+                created_by: None,
+            })
+            .unwrap();
+        let status = |service: &ScenarioService| {
+            service.get_scenario(&scenario.id).unwrap().unwrap().status
+        };
+
+        let result_id = service.execute_scenario(&scenario.id, None).unwrap();
+        assert_eq!(status(&service), "active");
+        // Only executions handed to D-Hydro are polled
+        assert!(service.list_running_dhydro_results().unwrap().is_empty());
+        service
+            .update_scenario_result(&result_id, ExecutionStatus::Pending, None, None, Some("JOB_1"))
+            .unwrap();
+        let running = service.list_running_dhydro_results().unwrap();
+        assert_eq!(running[0].dhydro_job_id.as_deref(), Some("JOB_1"));
+
+        service
+            .update_scenario_result(&result_id, ExecutionStatus::Cancelled, None, None, None)
+            .unwrap();
+        let result = &service.get_scenario_results(&scenario.id).unwrap()[0];
+        assert_eq!(result.status, "cancelled");
+        assert!(result.completed_at.is_some());
+        assert!(service.list_running_dhydro_results().unwrap().is_empty());
+        assert_eq!(status(&service), "draft");
+    }
+
     #[test]
     fn test_parse_timestamp() {
         let ts = "2024-01-01 12:00:00.000000";
@@ -13,9 +13,16 @@ use std::sync::Arc;
 use tracing::{debug, info, warn};
 
 use peilbeheer_core::timeseries::*;
+use peilbeheer_core::dhydro::TimeSeries as DhydroSeries;
 use peilbeheer_core::fews::FewsTimeSeries as FewsSeries;
+use peilbeheer_core::knmi::WeerVerwachting;
+use peilbeheer_simulatie::{Ensemblelid, HistorischePeriode, Verwachting};
 
-use crate::db::Database;
+use crate::db::{Database, is_no_rows};
+use crate::lizard_client::GrondwaterReeks;
+
+/// Qualifier of the water level forecasts of the realtime simulation.
+pub const REALTIME_QUALIFIER: &str = "forecast:realtime";
 
 /// Time series storage service.
 pub struct TimeSeriesService {
@@ -53,8 +60,8 @@ impl TimeSeriesService {
             "INSERT INTO timeseries_catalog
                 (location_id, parameter, qualifier, display_name, description, units,
                  data_type, source, source_type, min_value, max_value, retention_days,
-                 created_at, updated_at, attributes)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                 expected_interval_seconds, created_at, updated_at, attributes)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT (location_id, parameter, qualifier)
              DO UPDATE SET
                  display_name = excluded.display_name,
@@ -66,12 +73,14 @@ impl TimeSeriesService {
                  min_value = excluded.min_value,
                  max_value = excluded.max_value,
                  retention_days = excluded.retention_days,
+                 expected_interval_seconds = excluded.expected_interval_seconds,
                  updated_at = excluded.updated_at,
                  attributes = excluded.attributes",
             &[
                 &metadata.id.location_id as &dyn duckdb::ToSql,
                 &metadata.id.parameter,
-                &metadata.id.qualifier,
+                // The primary key cannot hold NULL, so no qualifier is stored as ''
+                &metadata.id.qualifier.as_deref().unwrap_or(""),
                 &metadata.display_name,
                 &metadata.description,
                 &metadata.units,
@@ -81,6 +90,7 @@ impl TimeSeriesService {
                 &metadata.min_value,
                 &metadata.max_value,
                 &metadata.retention_days,
+                &metadata.expected_interval_seconds,
                 &format_datetime(now),
                 &format_datetime(metadata.updated_at),
                 &json_str(&metadata.attributes),
@@ -217,69 +227,25 @@ impl TimeSeriesService {
             None => ("value", ""),
         };
 
-        let start_str = format_datetime(query.start);
-        let end_str = format_datetime(query.end);
-
-        let sql = if query.aggregation.is_some() || query.function.is_some() {
-            // Aggregated query
-            format!(
-                "SELECT timestamp, {} as value
-                 FROM {}
-                 WHERE series_id = ? AND timestamp >= ? AND timestamp < ?
-                 ORDER BY timestamp",
-                value_col, table_name
-            )
-        } else {
-            // Raw query
-            format!(
-                "SELECT timestamp, value, quality as flag
-                 FROM {}
-                 WHERE series_id = ? AND timestamp >= ? AND timestamp < ?
-                 ORDER BY timestamp",
-                table_name
-            )
-        };
-
-        let rows = self.db.query(
-            &sql,
-            &[
-                &series_key as &dyn duckdb::ToSql,
-                &start_str,
-                &end_str,
-            ],
-            |row| {
-                if query.aggregation.is_some() || query.function.is_some() {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, Option<f64>>(1)?,
-                        Some(QualityFlag::Good),
-                    ))
-                } else {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, Option<f64>>(1)?,
-                        row.get::<_, Option<String>>(2)?.and_then(|s| QualityFlag::from_str(&s)),
-                    ))
-                }
-            },
+        let aggregated = query.aggregation.is_some() || query.function.is_some();
+        let mut data = self.select_points(
+            &series_key,
+            table_name,
+            value_col,
+            aggregated,
+            (query.start, query.end),
         )?;
 
-        let mut data = Vec::new();
-        for (ts_str, value, flag) in rows {
-            let timestamp = parse_datetime(&ts_str);
-            let flag = flag.unwrap_or(QualityFlag::Good);
-
-            if let Some(v) = value {
-                data.push(TimeSeriesDataPoint::with_flag(timestamp, v, flag));
-            } else {
-                data.push(TimeSeriesDataPoint::with_flag(timestamp, f64::NAN, QualityFlag::Missing));
-            }
-        }
-
         // Apply gap filling if requested
         if let Some(fill_method) = query.fill_gaps
             && !data.is_empty() && interval_sec > 0 {
-                data = self.fill_gaps(data, query.start, query.end, interval_sec, fill_method)?;
+                // The seasonal means come from the whole history of the series
+                let seasonal = if fill_method == FillMethod::Seasonal {
+                    self.seasonal_means(&series_key, table_name, value_col, aggregated, interval_sec)?
+                } else {
+                    SeasonalMeans::default()
+                };
+                data = self.fill_gaps(data, &seasonal, query, fill_method, interval_sec)?;
             }
 
         let function = query.function.unwrap_or(AggregationFunction::Average);
@@ -300,6 +266,88 @@ impl TimeSeriesService {
         })
     }
 
+    /// Points of a series in `table_name` within `[start, end)`. Aggregated
+    /// tables have no quality column; their points count as good.
+    fn select_points(
+        &self,
+        series_key: &str,
+        table_name: &str,
+        value_col: &str,
+        aggregated: bool,
+        (start, end): (DateTime<Utc>, DateTime<Utc>),
+    ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
+        let quality_col = if aggregated { "NULL" } else { "quality" };
+        let sql = format!(
+            "SELECT CAST(timestamp AS VARCHAR), {} as value, {} as flag
+             FROM {}
+             WHERE series_id = ? AND timestamp >= ? AND timestamp < ?
+             ORDER BY timestamp",
+            value_col, quality_col, table_name
+        );
+
+        let start_str = format_datetime(start);
+        let end_str = format_datetime(end);
+        let rows = self.db.query(&sql, &[&series_key as &dyn duckdb::ToSql, &start_str, &end_str], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<f64>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(ts_str, value, flag)| {
+                let timestamp = parse_datetime(&ts_str);
+                let flag = flag.and_then(|s| QualityFlag::from_str(&s)).unwrap_or(QualityFlag::Good);
+                match value {
+                    Some(v) => TimeSeriesDataPoint::with_flag(timestamp, v, flag),
+                    None => TimeSeriesDataPoint::with_flag(timestamp, f64::NAN, QualityFlag::Missing),
+                }
+            })
+            .collect())
+    }
+
+    /// Climatological means over the whole history of a series in
+    /// `table_name`, grouped by month and slot of the day in the database.
+    fn seasonal_means(
+        &self,
+        series_key: &str,
+        table_name: &str,
+        value_col: &str,
+        aggregated: bool,
+        interval_sec: i64,
+    ) -> AnyhowResult<SeasonalMeans> {
+        // Same validity as `TimeSeriesDataPoint::is_valid`
+        let quality_filter = if aggregated {
+            ""
+        } else {
+            " AND lower(COALESCE(quality, 'good')) NOT IN ('bad', 'missing')"
+        };
+        let sql = format!(
+            "SELECT CAST(month(timestamp) AS INTEGER) AS month,
+                    CAST(floor((hour(timestamp) * 3600 + minute(timestamp) * 60 + second(timestamp)) / ?) AS BIGINT) AS slot,
+                    CAST(SUM({col}) AS DOUBLE), COUNT(*)
+             FROM {table}
+             WHERE series_id = ? AND {col} IS NOT NULL AND isfinite({col}){quality}
+             GROUP BY month, slot",
+            col = value_col,
+            table = table_name,
+            quality = quality_filter,
+        );
+
+        let groups = self.db.query(&sql, &[&interval_sec as &dyn duckdb::ToSql, &series_key], |row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+
+        Ok(SeasonalMeans::from_groups(&groups))
+    }
+
     /// Get series metadata from catalog.
     pub async fn get_metadata(&self, id: &TimeSeriesId) -> AnyhowResult<Option<TimeSeriesMetadata>> {
         let _key = id.key();
@@ -307,7 +355,9 @@ impl TimeSeriesService {
         let result = self.db.query_row(
             "SELECT location_id, parameter, qualifier, display_name, description, units,
                      data_type, source, source_type, min_value, max_value, retention_days,
-                     created_at, updated_at, first_timestamp, last_timestamp, point_count, attributes
+                     CAST(created_at AS VARCHAR), CAST(updated_at AS VARCHAR),
+                     CAST(first_timestamp AS VARCHAR), CAST(last_timestamp AS VARCHAR),
+                     point_count, attributes, expected_interval_seconds
              FROM timeseries_catalog
              WHERE location_id = ? AND parameter = ? AND COALESCE(qualifier, '') = COALESCE(?, '')",
             &[
@@ -335,6 +385,7 @@ impl TimeSeriesService {
                     row.get::<_, Option<String>>(15)?,
                     row.get::<_, Option<i64>>(16)?,
                     row.get::<_, Option<String>>(17)?,
+                    row.get::<_, Option<u32>>(18)?,
                 ))
             },
         );
@@ -342,7 +393,7 @@ impl TimeSeriesService {
         match result {
             Ok((_loc, _param, _qual, name, desc, units, data_type, source, source_type,
                 min_val, max_val, retention, created_str, updated_str,
-                _first_str, _last_str, _point_count, attr_str)) =>
+                _first_str, _last_str, _point_count, attr_str, expected_interval)) =>
             {
                 let attributes: HashMap<String, serde_json::Value> = attr_str
                     .and_then(|s| serde_json::from_str(&s).ok())
@@ -361,14 +412,43 @@ impl TimeSeriesService {
                     created_at: parse_datetime(&created_str),
                     updated_at: parse_datetime(&updated_str),
                     retention_days: retention,
+                    expected_interval_seconds: expected_interval,
                     attributes,
                 }))
             }
-            Err(e) if e.to_string().contains("QueryReturnedNoRows") => Ok(None),
+            Err(e) if is_no_rows(&e) => Ok(None),
             Err(e) => Err(e),
         }
     }
 
+    /// Set the expected delivery interval of a series (None clears it).
+    ///
+    /// Returns false when the series is not in the catalog.
+    pub async fn set_expected_interval(
+        &self,
+        id: &TimeSeriesId,
+        interval_seconds: Option<u32>,
+    ) -> AnyhowResult<bool> {
+        if self.get_metadata(id).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.db.execute(
+            "UPDATE timeseries_catalog
+             SET expected_interval_seconds = ?, updated_at = ?
+             WHERE location_id = ? AND parameter = ? AND COALESCE(qualifier, '') = COALESCE(?, '')",
+            &[
+                &interval_seconds as &dyn duckdb::ToSql,
+                &format_datetime(Utc::now()),
+                &id.location_id,
+                &id.parameter,
+                &id.qualifier,
+            ],
+        )?;
+
+        Ok(true)
+    }
+
     /// List all time series in the catalog.
     pub async fn list_series(
         &self,
@@ -378,7 +458,8 @@ impl TimeSeriesService {
         let sql = if let Some(_st) = source_type {
             format!(
                 "SELECT location_id, parameter, qualifier, display_name, units, source,
-                         first_timestamp, last_timestamp, point_count
+                         CAST(first_timestamp AS VARCHAR), CAST(last_timestamp AS VARCHAR), point_count,
+                         expected_interval_seconds
                  FROM timeseries_catalog
                  WHERE source_type = ?
                  ORDER BY location_id, parameter
@@ -388,7 +469,8 @@ impl TimeSeriesService {
         } else {
             format!(
                 "SELECT location_id, parameter, qualifier, display_name, units, source,
-                         first_timestamp, last_timestamp, point_count
+                         CAST(first_timestamp AS VARCHAR), CAST(last_timestamp AS VARCHAR), point_count,
+                         expected_interval_seconds
                  FROM timeseries_catalog
                  ORDER BY location_id, parameter
                  LIMIT {}",
@@ -409,17 +491,52 @@ impl TimeSeriesService {
         Ok(rows)
     }
 
-    /// Import Fews time series data.
-    #[allow(dead_code)]
+    /// Import Fews time series data under a local series id.
+    ///
+    /// New series are registered with Fews as their source; forecast series
+    /// carry their ensemble in the attributes. A newer forecast overwrites
+    /// the values of an older one.
     pub async fn import_from_fews(
         &self,
         series: &FewsSeries,
-        location_id: &str,
+        ts_id: &TimeSeriesId,
     ) -> AnyhowResult<TimeSeriesWriteResult> {
-        let ts_id = TimeSeriesId::new(
-            location_id,
-            series.header.parameter_id.clone(),
-        );
+        if self.get_metadata(ts_id).await?.is_none() {
+            let header = &series.header;
+            let mut attributes = HashMap::new();
+            for (name, value) in [
+                ("fews_ensemble_id", &header.ensemble_id),
+                ("fews_ensemble_member", &header.ensemble_member),
+            ] {
+                if let Some(value) = value {
+                    attributes.insert(name.to_string(), serde_json::json!(value));
+                }
+            }
+
+            let now = Utc::now();
+            self.register_series(TimeSeriesMetadata {
+                id: ts_id.clone(),
+                display_name: format!("{} - {}", ts_id.location_id, ts_id.parameter),
+                description: Some(format!(
+                    "Fews {}{} {}",
+                    if header.is_forecast() { "forecast " } else { "" },
+                    header.location_id,
+                    header.parameter_id
+                )),
+                units: (!series.header.units.is_empty()).then(|| series.header.units.clone()),
+                data_type: TimeSeriesDataType::Instantaneous,
+                min_value: None,
+                max_value: None,
+                source: "fews".to_string(),
+                source_type: TimeSeriesSourceType::Fews,
+                created_at: now,
+                updated_at: now,
+                retention_days: None,
+                expected_interval_seconds: None,
+                attributes,
+            })
+            .await?;
+        }
 
         let data: Vec<TimeSeriesDataPoint> = series.data.iter()
             .filter_map(|p| {
@@ -435,7 +552,7 @@ impl TimeSeriesService {
             .collect();
 
         let batch = TimeSeriesWriteBatch {
-            series_id: ts_id,
+            series_id: ts_id.clone(),
             data,
             attributes: None,
         };
@@ -443,6 +560,334 @@ impl TimeSeriesService {
         self.write_batch(batch).await
     }
 
+    /// Import the output of a D-Hydro scenario run.
+    ///
+    /// Each series is stored under a `scenario:<id>` qualifier (see
+    /// [`DhydroSeries::series_id`]), so model output can be plotted and
+    /// alerted on next to the measurements of the same location. New series
+    /// are registered with D-Hydro as their source.
+    pub async fn import_from_dhydro(
+        &self,
+        series: &DhydroSeries,
+        scenario_id: &str,
+    ) -> AnyhowResult<TimeSeriesWriteResult> {
+        let batch = series.to_write_batch(scenario_id);
+
+        if self.get_metadata(&batch.series_id).await?.is_none() {
+            let attributes = HashMap::from([
+                ("dhydro_series_id".to_string(), serde_json::json!(series.id)),
+                ("scenario_id".to_string(), serde_json::json!(scenario_id)),
+            ]);
+
+            let now = Utc::now();
+            self.register_series(TimeSeriesMetadata {
+                id: batch.series_id.clone(),
+                display_name: format!("{} - {} ({})", series.location_id, series.name, scenario_id),
+                description: Some(format!("D-Hydro scenario {} {}", scenario_id, series.parameter)),
+                units: (!series.unit.is_empty()).then(|| series.unit.clone()),
+                data_type: TimeSeriesDataType::Instantaneous,
+                min_value: None,
+                max_value: None,
+                source: "dhydro".to_string(),
+                source_type: TimeSeriesSourceType::DHydro,
+                created_at: now,
+                updated_at: now,
+                retention_days: None,
+                expected_interval_seconds: None,
+                attributes,
+            })
+            .await?;
+        }
+
+        self.write_batch(batch).await
+    }
+
+    /// Import a KNMI weather forecast of a peilgebied.
+    ///
+    /// Precipitation and evaporation are stored as `neerslag` and
+    /// `verdamping` of the peilgebied under the `forecast:knmi` qualifier; a
+    /// newer forecast overwrites the values of an older one. Forecasts of a
+    /// fallback source go to the same series; the `bron` attribute and the
+    /// source of the metadata name the source of the latest forecast.
+    pub async fn import_knmi_forecast(
+        &self,
+        verwachting: &WeerVerwachting,
+    ) -> AnyhowResult<Vec<TimeSeriesWriteResult>> {
+        // The source is KNMI or a fallback, e.g. `meteoserver:uurverwachting`
+        let bron = serde_json::json!(verwachting.bron);
+        let source = verwachting
+            .bron
+            .split_once(':')
+            .map_or(verwachting.bron.as_str(), |(source, _)| source);
+
+        let mut results = Vec::new();
+        for batch in verwachting.to_write_batches() {
+            let now = Utc::now();
+            match self.get_metadata(&batch.series_id).await? {
+                None => {
+                    self.register_series(TimeSeriesMetadata {
+                        id: batch.series_id.clone(),
+                        display_name: format!(
+                            "{} - {} (verwachting)",
+                            batch.series_id.location_id, batch.series_id.parameter
+                        ),
+                        description: Some(format!("{} {}", verwachting.bron, batch.series_id.parameter)),
+                        units: Some("mm".to_string()),
+                        data_type: TimeSeriesDataType::Total,
+                        min_value: None,
+                        max_value: None,
+                        source: source.to_string(),
+                        source_type: parse_source_type(source),
+                        created_at: now,
+                        updated_at: now,
+                        retention_days: None,
+                        expected_interval_seconds: None,
+                        attributes: HashMap::from([("bron".to_string(), bron.clone())]),
+                    })
+                    .await?;
+                }
+                // Record a switch to or from a fallback source
+                Some(mut metadata) if metadata.attributes.get("bron") != Some(&bron) => {
+                    metadata.description =
+                        Some(format!("{} {}", verwachting.bron, batch.series_id.parameter));
+                    metadata.source = source.to_string();
+                    metadata.source_type = parse_source_type(source);
+                    metadata.updated_at = now;
+                    metadata.attributes.remove("knmi_bron");
+                    metadata.attributes.insert("bron".to_string(), bron.clone());
+                    self.register_series(metadata).await?;
+                }
+                Some(_) => {}
+            }
+
+            results.push(self.write_batch(batch).await?);
+        }
+        Ok(results)
+    }
+
+    /// Store a water level forecast of the realtime simulation.
+    ///
+    /// The expected levels of each peilgebied go to `waterstand` under the
+    /// [`REALTIME_QUALIFIER`]; a newer forecast overwrites the values of an
+    /// older one, and the `t0` attribute holds the start of the latest.
+    pub async fn import_realtime_verwachting(
+        &self,
+        verwachting: &Verwachting,
+    ) -> AnyhowResult<Vec<TimeSeriesWriteResult>> {
+        let t0 = serde_json::json!(verwachting.t0.to_rfc3339());
+        let mut results = Vec::new();
+        for peilgebied in &verwachting.peilgebieden {
+            let series_id = TimeSeriesId::with_qualifier(
+                &peilgebied.peilgebied_id,
+                "waterstand",
+                REALTIME_QUALIFIER,
+            );
+            let now = Utc::now();
+            let mut metadata = match self.get_metadata(&series_id).await? {
+                Some(metadata) => metadata,
+                None => TimeSeriesMetadata {
+                    id: series_id.clone(),
+                    display_name: format!(
+                        "{} - waterstand (realtime verwachting)",
+                        peilgebied.peilgebied_id
+                    ),
+                    description: Some(format!(
+                        "Realtime simulatie {} waterstand",
+                        peilgebied.peilgebied_id
+                    )),
+                    units: Some("m NAP".to_string()),
+                    data_type: TimeSeriesDataType::Instantaneous,
+                    min_value: None,
+                    max_value: None,
+                    source: "calculated".to_string(),
+                    source_type: TimeSeriesSourceType::Calculated,
+                    created_at: now,
+                    updated_at: now,
+                    retention_days: None,
+                    expected_interval_seconds: Some(3600),
+                    attributes: HashMap::new(),
+                },
+            };
+            metadata.updated_at = now;
+            metadata.attributes.insert("t0".to_string(), t0.clone());
+            self.register_series(metadata).await?;
+
+            results.push(
+                self.write_batch(TimeSeriesWriteBatch {
+                    series_id,
+                    data: peilgebied
+                        .waarden
+                        .iter()
+                        .map(|w| TimeSeriesDataPoint::new(w.tijd, w.waterstand))
+                        .collect(),
+                    attributes: None,
+                })
+                .await?,
+            );
+        }
+        Ok(results)
+    }
+
+    /// Hourly precipitation sums (mm) of a peilgebied over a historical
+    /// period, for replaying it as a regenscenario.
+    ///
+    /// Reads the raw `neerslag` series of the peilgebied, by default the
+    /// measured series without qualifier. Returns `None` when the series
+    /// has no values in the period.
+    pub async fn historische_regen(
+        &self,
+        peilgebied_code: &str,
+        periode: &HistorischePeriode,
+        qualifier: Option<&str>,
+    ) -> AnyhowResult<Option<Vec<f64>>> {
+        let series_id = match qualifier {
+            Some(q) => TimeSeriesId::with_qualifier(peilgebied_code, "neerslag", q),
+            None => TimeSeriesId::new(peilgebied_code, "neerslag"),
+        };
+        let series = self
+            .query(&TimeSeriesQuery::new(series_id, periode.start, periode.eind))
+            .await?;
+        if !series.data.iter().any(|p| p.value.is_finite()) {
+            return Ok(None);
+        }
+        Ok(Some(periode.uursommen(
+            series.data.iter().map(|p| (p.timestamp, p.value)),
+        )))
+    }
+
+    /// Hourly precipitation (mm) of each member of a Fews ensemble forecast
+    /// for the given peilgebieden.
+    ///
+    /// The members are the `neerslag` series with qualifier
+    /// `forecast:<ensemble_id>:<member>`, as stored by the Fews import. A
+    /// peilgebied without values for a member gets no precipitation in that
+    /// member. Members are sorted by name.
+    pub async fn ensemble_regen(
+        &self,
+        peilgebied_codes: &[&str],
+        ensemble_id: &str,
+        periode: &HistorischePeriode,
+    ) -> AnyhowResult<Vec<Ensemblelid>> {
+        let prefix = format!("forecast:{}:", ensemble_id);
+        let series = self.db.query(
+            "SELECT location_id, qualifier FROM timeseries_catalog
+             WHERE parameter = 'neerslag' AND starts_with(qualifier, ?)
+             ORDER BY qualifier, location_id",
+            &[&prefix as &dyn duckdb::ToSql],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )?;
+
+        let mut leden: Vec<Ensemblelid> = Vec::new();
+        for (location_id, qualifier) in series {
+            if !peilgebied_codes.contains(&location_id.as_str()) {
+                continue;
+            }
+            let Some(regen) = self
+                .historische_regen(&location_id, periode, Some(&qualifier))
+                .await?
+            else {
+                continue;
+            };
+            let lid = &qualifier[prefix.len()..];
+            if leden.last().is_none_or(|l| l.lid != lid) {
+                leden.push(Ensemblelid {
+                    lid: lid.to_string(),
+                    regen_per_uur: HashMap::new(),
+                });
+            }
+            if let Some(laatste) = leden.last_mut() {
+                laatste.regen_per_uur.insert(location_id, regen);
+            }
+        }
+        Ok(leden)
+    }
+
+    /// Store measured water levels (m NAP) of an RWS location as time series
+    /// `waterstand` of that location.
+    pub async fn import_rws_waterstanden(
+        &self,
+        locatie: &str,
+        data: Vec<TimeSeriesDataPoint>,
+    ) -> AnyhowResult<TimeSeriesWriteResult> {
+        let series_id = TimeSeriesId::new(locatie, "waterstand");
+        if self.get_metadata(&series_id).await?.is_none() {
+            let now = Utc::now();
+            self.register_series(TimeSeriesMetadata {
+                id: series_id.clone(),
+                display_name: format!("{} - waterstand", locatie),
+                description: Some(format!("RWS Waterinfo waterstand {}", locatie)),
+                units: Some("m NAP".to_string()),
+                data_type: TimeSeriesDataType::Instantaneous,
+                min_value: None,
+                max_value: None,
+                source: "rws".to_string(),
+                source_type: TimeSeriesSourceType::Rws,
+                created_at: now,
+                updated_at: now,
+                retention_days: None,
+                expected_interval_seconds: Some(600),
+                attributes: HashMap::new(),
+            })
+            .await?;
+        }
+
+        self.write_batch(TimeSeriesWriteBatch {
+            series_id,
+            data,
+            attributes: None,
+        })
+        .await
+    }
+
+    /// Store groundwater levels of a Lizard series as time series
+    /// `grondwaterstand` of its location, with the filter as qualifier.
+    pub async fn import_lizard_grondwaterstanden(
+        &self,
+        reeks: &GrondwaterReeks,
+        peilgebied: &str,
+        data: Vec<TimeSeriesDataPoint>,
+    ) -> AnyhowResult<TimeSeriesWriteResult> {
+        let series_id =
+            TimeSeriesId::with_qualifier(&reeks.locatie_code, "grondwaterstand", &reeks.filter_code);
+        if self.get_metadata(&series_id).await?.is_none() {
+            let now = Utc::now();
+            let mut attributes = HashMap::new();
+            attributes.insert("peilgebied".to_string(), serde_json::json!(peilgebied));
+            attributes.insert("lizard_uuid".to_string(), serde_json::json!(reeks.uuid));
+            if let (Some(lat), Some(lon)) = (reeks.lat, reeks.lon) {
+                attributes.insert("lat".to_string(), serde_json::json!(lat));
+                attributes.insert("lon".to_string(), serde_json::json!(lon));
+            }
+            self.register_series(TimeSeriesMetadata {
+                id: series_id.clone(),
+                display_name: format!(
+                    "{} - grondwaterstand {}",
+                    reeks.locatie_code, reeks.filter_code
+                ),
+                description: reeks.naam.clone(),
+                units: reeks.eenheid.clone(),
+                data_type: TimeSeriesDataType::Instantaneous,
+                min_value: None,
+                max_value: None,
+                source: "lizard".to_string(),
+                source_type: TimeSeriesSourceType::Lizard,
+                created_at: now,
+                updated_at: now,
+                retention_days: None,
+                expected_interval_seconds: None,
+                attributes,
+            })
+            .await?;
+        }
+
+        self.write_batch(TimeSeriesWriteBatch {
+            series_id,
+            data,
+            attributes: None,
+        })
+        .await
+    }
+
     /// Ensure catalog entry exists for a series.
     async fn ensure_catalog_entry(&self, id: &TimeSeriesId) -> AnyhowResult<()> {
         // Check if exists
@@ -472,6 +917,7 @@ impl TimeSeriesService {
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 retention_days: None,
+                expected_interval_seconds: None,
                 attributes: HashMap::new(),
             };
             self.register_series(metadata).await?;
@@ -498,7 +944,7 @@ impl TimeSeriesService {
                  first_timestamp = COALESCE(MIN(first_timestamp), ?),
                  last_timestamp = COALESCE(MAX(last_timestamp), ?),
                  updated_at = ?
-             WHERE (location_id || '|' || parameter || COALESCE('|' || qualifier, '')) = ?",
+             WHERE (location_id || '|' || parameter || COALESCE('|' || NULLIF(qualifier, ''), '')) = ?",
             &[
                 &points_written as &dyn duckdb::ToSql,
                 &first_ts.map(format_datetime),
@@ -543,24 +989,36 @@ impl TimeSeriesService {
         Ok(())
     }
 
-    /// Fill gaps in time series data.
+    /// Fill gaps in time series data over the range of `query`.
+    ///
+    /// `seasonal` holds the means used by seasonal filling.
     fn fill_gaps(
         &self,
         data: Vec<TimeSeriesDataPoint>,
-        start: DateTime<Utc>,
-        end: DateTime<Utc>,
-        interval_sec: i64,
+        seasonal: &SeasonalMeans,
+        query: &TimeSeriesQuery,
         method: FillMethod,
+        interval_sec: i64,
     ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
         if data.is_empty() {
             return Ok(data);
         }
 
+        let (start, end) = (query.start, query.end);
+
         match method {
             FillMethod::None => Ok(data),
             FillMethod::Forward => self.fill_forward(data, start, end, interval_sec),
             FillMethod::Linear => self.fill_linear(data, start, end, interval_sec),
-            _ => Ok(data), // TODO: implement other methods
+            FillMethod::Backward => self.fill_backward(data, start, end, interval_sec),
+            FillMethod::Constant => {
+                let value = query
+                    .fill_value
+                    .ok_or_else(|| anyhow::anyhow!("Fill value required for constant gap filling"))?;
+                self.fill_constant(data, start, end, interval_sec, value)
+            }
+            FillMethod::Spline => self.fill_spline(data, start, end, interval_sec),
+            FillMethod::Seasonal => self.fill_seasonal(data, seasonal, start, end, interval_sec),
         }
     }
 
@@ -673,6 +1131,243 @@ impl TimeSeriesService {
 
         Ok(result)
     }
+
+    /// Backward fill gaps (use next valid value).
+    fn fill_backward(
+        &self,
+        data: Vec<TimeSeriesDataPoint>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        interval_sec: i64,
+    ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
+        let valid_points: Vec<_> = data.into_iter().filter(|p| p.is_valid()).collect();
+        let mut result = fill_grid(&valid_points, start, end, interval_sec, |_| None);
+
+        // Walk back over the grid, carrying the first valid value at or after each slot
+        let mut next_points = valid_points.iter().rev().peekable();
+        let mut next_value = None;
+        for point in result.iter_mut().rev() {
+            while let Some(next) = next_points.next_if(|p| p.timestamp >= point.timestamp) {
+                next_value = Some(next.value);
+            }
+            if point.flag == QualityFlag::Missing
+                && let Some(value) = next_value
+            {
+                *point = TimeSeriesDataPoint::with_flag(point.timestamp, value, QualityFlag::Interpolated);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Fill gaps with a fixed value.
+    fn fill_constant(
+        &self,
+        data: Vec<TimeSeriesDataPoint>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        interval_sec: i64,
+        value: f64,
+    ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
+        let valid_points: Vec<_> = data.into_iter().filter(|p| p.is_valid()).collect();
+
+        Ok(fill_grid(&valid_points, start, end, interval_sec, |_| Some(value)))
+    }
+
+    /// Natural cubic spline interpolation for gaps.
+    ///
+    /// Outside the range of valid points the first/last value is held,
+    /// the spline is never extrapolated.
+    fn fill_spline(
+        &self,
+        data: Vec<TimeSeriesDataPoint>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        interval_sec: i64,
+    ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
+        let valid_points: Vec<_> = data.into_iter().filter(|p| p.is_valid()).collect();
+
+        if valid_points.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let origin = valid_points[0].timestamp.timestamp();
+        let xs: Vec<f64> = valid_points
+            .iter()
+            .map(|p| (p.timestamp.timestamp() - origin) as f64)
+            .collect();
+        let ys: Vec<f64> = valid_points.iter().map(|p| p.value).collect();
+        let second_derivatives = natural_spline_coefficients(&xs, &ys);
+
+        Ok(fill_grid(&valid_points, start, end, interval_sec, |ts| {
+            let x = (ts.timestamp() - origin) as f64;
+            Some(evaluate_spline(&xs, &ys, &second_derivatives, x))
+        }))
+    }
+
+    /// Fill gaps with the climatological mean for the same month and time of day.
+    ///
+    /// A gap gets the mean of its month and slot within the day, falling
+    /// back to the mean of its slot over all months and then to the overall
+    /// mean when those have no data.
+    fn fill_seasonal(
+        &self,
+        data: Vec<TimeSeriesDataPoint>,
+        seasonal: &SeasonalMeans,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        interval_sec: i64,
+    ) -> AnyhowResult<Vec<TimeSeriesDataPoint>> {
+        if seasonal.overall.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let valid_points: Vec<_> = data.into_iter().filter(|p| p.is_valid()).collect();
+
+        Ok(fill_grid(&valid_points, start, end, interval_sec, |ts| {
+            seasonal.mean_at(ts, interval_sec)
+        }))
+    }
+}
+
+/// Climatological means of a series per month and slot of the day.
+#[derive(Debug, Default)]
+struct SeasonalMeans {
+    by_month_slot: HashMap<(u32, i64), f64>,
+    by_slot: HashMap<i64, f64>,
+    overall: Option<f64>,
+}
+
+impl SeasonalMeans {
+    /// Build the means from `(month, slot, sum, count)` groups.
+    fn from_groups(groups: &[(u32, i64, f64, i64)]) -> Self {
+        let mut slots: HashMap<i64, (f64, i64)> = HashMap::new();
+        let (mut sum, mut count) = (0.0, 0);
+        for &(_, slot, group_sum, group_count) in groups {
+            let entry = slots.entry(slot).or_insert((0.0, 0));
+            entry.0 += group_sum;
+            entry.1 += group_count;
+            sum += group_sum;
+            count += group_count;
+        }
+
+        Self {
+            by_month_slot: groups
+                .iter()
+                .map(|&(month, slot, sum, count)| ((month, slot), sum / count as f64))
+                .collect(),
+            by_slot: slots
+                .into_iter()
+                .map(|(slot, (sum, count))| (slot, sum / count as f64))
+                .collect(),
+            overall: (count > 0).then(|| sum / count as f64),
+        }
+    }
+
+    /// Mean for the slot of `ts`, with the fallbacks described at
+    /// `fill_seasonal`.
+    fn mean_at(&self, ts: DateTime<Utc>, interval_sec: i64) -> Option<f64> {
+        use chrono::{Datelike, Timelike};
+
+        let slot = ts.num_seconds_from_midnight() as i64 / interval_sec;
+        self.by_month_slot
+            .get(&(ts.month(), slot))
+            .or_else(|| self.by_slot.get(&slot))
+            .copied()
+            .or(self.overall)
+    }
+}
+
+/// Helper: Walk the regular grid from `start` to `end`, keeping valid points
+/// that fall on the grid and filling the rest via `fill`.
+///
+/// Filled points are flagged `Interpolated`; slots for which `fill` returns
+/// `None` are emitted as missing.
+fn fill_grid<F>(
+    valid_points: &[TimeSeriesDataPoint],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    interval_sec: i64,
+    fill: F,
+) -> Vec<TimeSeriesDataPoint>
+where
+    F: Fn(DateTime<Utc>) -> Option<f64>,
+{
+    let observed: HashMap<DateTime<Utc>, &TimeSeriesDataPoint> =
+        valid_points.iter().map(|p| (p.timestamp, p)).collect();
+
+    let mut result = Vec::new();
+    let mut current_ts = start;
+
+    while current_ts < end {
+        let point = match observed.get(&current_ts) {
+            Some(p) => (*p).clone(),
+            None => match fill(current_ts) {
+                Some(value) => {
+                    TimeSeriesDataPoint::with_flag(current_ts, value, QualityFlag::Interpolated)
+                }
+                None => TimeSeriesDataPoint::missing(current_ts),
+            },
+        };
+
+        result.push(point);
+        current_ts += Duration::seconds(interval_sec);
+    }
+
+    result
+}
+
+/// Helper: Second derivatives of a natural cubic spline through `(xs, ys)`.
+///
+/// `xs` must be strictly increasing. Solved with the Thomas algorithm.
+fn natural_spline_coefficients(xs: &[f64], ys: &[f64]) -> Vec<f64> {
+    let n = xs.len();
+    let mut m = vec![0.0; n];
+    if n < 3 {
+        return m;
+    }
+
+    let mut c_prime = vec![0.0; n];
+    let mut d_prime = vec![0.0; n];
+
+    for i in 1..n - 1 {
+        let h0 = xs[i] - xs[i - 1];
+        let h1 = xs[i + 1] - xs[i];
+        let a = h0;
+        let b = 2.0 * (h0 + h1);
+        let c = h1;
+        let d = 6.0 * ((ys[i + 1] - ys[i]) / h1 - (ys[i] - ys[i - 1]) / h0);
+
+        let denom = b - a * c_prime[i - 1];
+        c_prime[i] = c / denom;
+        d_prime[i] = (d - a * d_prime[i - 1]) / denom;
+    }
+
+    for i in (1..n - 1).rev() {
+        m[i] = d_prime[i] - c_prime[i] * m[i + 1];
+    }
+
+    m
+}
+
+/// Helper: Evaluate a natural cubic spline at `x`, clamped to the data range.
+fn evaluate_spline(xs: &[f64], ys: &[f64], m: &[f64], x: f64) -> f64 {
+    let n = xs.len();
+    if n == 1 || x <= xs[0] {
+        return ys[0];
+    }
+    if x >= xs[n - 1] {
+        return ys[n - 1];
+    }
+
+    let i = xs.partition_point(|&xi| xi <= x) - 1;
+    let h = xs[i + 1] - xs[i];
+    let a = (xs[i + 1] - x) / h;
+    let b = (x - xs[i]) / h;
+
+    a * ys[i]
+        + b * ys[i + 1]
+        + ((a * a * a - a) * m[i] + (b * b * b - b) * m[i + 1]) * h * h / 6.0
 }
 
 /// Helper: Serialize enum as string.
@@ -698,7 +1393,7 @@ fn format_datetime(dt: DateTime<Utc>) -> String {
 
 /// Helper: Parse datetime from DuckDB.
 fn parse_datetime(s: &str) -> DateTime<Utc> {
-    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.6f")
+    chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
         .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
         .map(|ndt| ndt.and_utc())
         .unwrap_or_else(|_| Utc::now())
@@ -724,6 +1419,10 @@ fn parse_source_type(s: &str) -> TimeSeriesSourceType {
         "hydronet" => TimeSeriesSourceType::Hydronet,
         "dhydro" => TimeSeriesSourceType::DHydro,
         "energyzero" => TimeSeriesSourceType::EnergyZero,
+        "knmi" => TimeSeriesSourceType::Knmi,
+        "rws" => TimeSeriesSourceType::Rws,
+        "lizard" => TimeSeriesSourceType::Lizard,
+        "meteoserver" => TimeSeriesSourceType::Meteoserver,
         "manual" => TimeSeriesSourceType::Manual,
         "calculated" => TimeSeriesSourceType::Calculated,
         other => TimeSeriesSourceType::Custom(other.to_string()),
@@ -751,7 +1450,7 @@ fn parse_catalog_row(row: &duckdb::Row) -> duckdb::Result<TimeSeriesCatalogEntry
         id: TimeSeriesId {
             location_id: row.get(0)?,
             parameter: row.get(1)?,
-            qualifier: row.get(2)?,
+            qualifier: row.get::<_, Option<String>>(2)?.filter(|q| !q.is_empty()),
         },
         display_name: row.get(3)?,
         units: row.get(4)?,
@@ -760,6 +1459,7 @@ fn parse_catalog_row(row: &duckdb::Row) -> duckdb::Result<TimeSeriesCatalogEntry
         first_timestamp: row.get::<_, Option<String>>(6)?.map(|s| parse_datetime(&s)),
         last_timestamp: row.get::<_, Option<String>>(7)?.map(|s| parse_datetime(&s)),
         point_count: row.get::<_, Option<i64>>(8)?.unwrap_or(0) as u64,
+        expected_interval_seconds: row.get(9)?,
     })
 }
 
@@ -767,6 +1467,37 @@ fn parse_catalog_row(row: &duckdb::Row) -> duckdb::Result<TimeSeriesCatalogEntry
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_register_series_without_qualifier_twice() {
+        let service = TimeSeriesService::new(Arc::new(Database::in_memory()));
+        let id = TimeSeriesId::new("LOC_001", "water_level");
+        let now = Utc::now();
+        let metadata = TimeSeriesMetadata {
+            id: id.clone(),
+            display_name: "LOC_001 - water_level".to_string(),
+            description: None,
+            units: Some("m NAP".to_string()),
+            data_type: TimeSeriesDataType::Instantaneous,
+            min_value: None,
+            max_value: None,
+            source: "fews".to_string(),
+            source_type: TimeSeriesSourceType::Fews,
+            created_at: now,
+            updated_at: now,
+            retention_days: None,
+            expected_interval_seconds: None,
+            attributes: HashMap::new(),
+        };
+
+        service.register_series(metadata.clone()).await.unwrap();
+        service.register_series(metadata).await.unwrap();
+
+        assert!(service.get_metadata(&id).await.unwrap().is_some());
+        let series = service.list_series(None, None).await.unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].id, id);
+    }
+
     #[test]
     fn test_timeseries_id() {
         let id = TimeSeriesId::new("LOC_001", "water_level");
@@ -810,4 +1541,142 @@ mod tests {
         let invalid = TimeSeriesQuery::new(id, end, start);
         assert!(invalid.validate().is_err());
     }
+
+    #[test]
+    fn test_fill_grid_keeps_observed_points() {
+        let start = Utc::now();
+        let points = vec![
+            TimeSeriesDataPoint::new(start, 1.0),
+            TimeSeriesDataPoint::new(start + Duration::seconds(120), 3.0),
+        ];
+
+        let filled = fill_grid(&points, start, start + Duration::seconds(180), 60, |_| Some(9.0));
+        assert_eq!(filled.len(), 3);
+        assert_eq!(filled[0].flag, QualityFlag::Good);
+        assert_eq!(filled[1].value, 9.0);
+        assert_eq!(filled[1].flag, QualityFlag::Interpolated);
+        assert_eq!(filled[2].value, 3.0);
+
+        let filled = fill_grid(&points, start, start + Duration::seconds(180), 60, |_| None);
+        assert_eq!(filled[1].flag, QualityFlag::Missing);
+    }
+
+    #[test]
+    fn test_fill_seasonal_per_month() {
+        use chrono::TimeZone;
+
+        let service = TimeSeriesService::new(Arc::new(Database::in_memory()));
+        let day = 86_400;
+        let jan = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let jul = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        let points = vec![
+            TimeSeriesDataPoint::new(jan, 1.0),
+            TimeSeriesDataPoint::new(jan + Duration::days(2), 3.0),
+            TimeSeriesDataPoint::new(jul, 20.0),
+        ];
+        // January: 1.0 and 3.0, July: 20.0
+        let seasonal = SeasonalMeans::from_groups(&[(1, 0, 4.0, 2), (7, 0, 20.0, 1)]);
+
+        // A January gap takes the January mean, not the mean over the year
+        let filled = service
+            .fill_seasonal(points.clone(), &seasonal, jan, jan + Duration::days(3), day)
+            .unwrap();
+        assert_eq!(filled[1].value, 2.0);
+        assert_eq!(filled[1].flag, QualityFlag::Interpolated);
+
+        // A July gap takes the July mean
+        let filled = service
+            .fill_seasonal(points.clone(), &seasonal, jul, jul + Duration::days(2), day)
+            .unwrap();
+        assert_eq!(filled[1].value, 20.0);
+
+        // A month without data falls back to the mean over all months
+        let mar = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let filled = service
+            .fill_seasonal(points.clone(), &seasonal, mar, mar + Duration::days(1), day)
+            .unwrap();
+        assert_eq!(filled[0].value, 8.0);
+
+        // Without history nothing can be filled
+        let filled = service
+            .fill_seasonal(points, &SeasonalMeans::default(), jan, jan + Duration::days(3), day)
+            .unwrap();
+        assert!(filled.is_empty());
+    }
+
+    #[test]
+    fn test_fill_backward() {
+        let service = TimeSeriesService::new(Arc::new(Database::in_memory()));
+        let start = Utc::now();
+        let points = vec![
+            TimeSeriesDataPoint::new(start + Duration::seconds(60), 1.0),
+            TimeSeriesDataPoint::with_flag(start + Duration::seconds(120), 5.0, QualityFlag::Bad),
+            // Off the grid, fills the slot before it
+            TimeSeriesDataPoint::new(start + Duration::seconds(150), 3.0),
+        ];
+
+        let filled = service
+            .fill_backward(points, start, start + Duration::seconds(240), 60)
+            .unwrap();
+        let values: Vec<f64> = filled.iter().map(|p| p.value).collect();
+        assert_eq!(&values[..3], &[1.0, 1.0, 3.0]);
+        assert_eq!(filled[0].flag, QualityFlag::Interpolated);
+        assert_eq!(filled[1].flag, QualityFlag::Good);
+        // Nothing after the last slot
+        assert_eq!(filled[3].flag, QualityFlag::Missing);
+    }
+
+    #[tokio::test]
+    async fn test_fill_seasonal_from_history() {
+        use chrono::TimeZone;
+
+        let db = Arc::new(Database::in_memory());
+        let service = TimeSeriesService::new(db.clone());
+        let id = TimeSeriesId::new("LOC_001", "water_level");
+        // Previous Januaries have data at 06:00, the queried day only at 00:00
+        for (ts, value) in [
+            ("2022-01-10 06:00:00", 4.0),
+            ("2023-01-10 06:00:00", 6.0),
+            ("2024-01-01 00:00:00", 1.0),
+        ] {
+            db.execute(
+                "INSERT INTO timeseries_data_1h (series_id, timestamp, avg_value) VALUES (?, ?, ?)",
+                &[&id.key() as &dyn duckdb::ToSql, &ts, &value],
+            )
+            .unwrap();
+        }
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut query = TimeSeriesQuery::new(id, start, start + Duration::hours(7));
+        query.aggregation = Some(AggregationLevel::Hour1);
+        query.function = Some(AggregationFunction::Average);
+        query.fill_gaps = Some(FillMethod::Seasonal);
+        let series = service.query(&query).await.unwrap();
+
+        assert_eq!(series.data.len(), 7);
+        assert_eq!(series.data[0].value, 1.0);
+        assert_eq!(series.data[0].flag, QualityFlag::Good);
+        // 06:00 takes the January mean at 06:00 from the earlier years
+        assert_eq!(series.data[6].value, 5.0);
+        assert_eq!(series.data[6].flag, QualityFlag::Interpolated);
+        // Slots without history fall back to the overall mean
+        assert!((series.data[3].value - 11.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_natural_spline() {
+        // Points on a straight line: the spline must reproduce the line
+        let xs = vec![0.0, 1.0, 2.0, 3.0];
+        let ys = vec![0.0, 2.0, 4.0, 6.0];
+        let m = natural_spline_coefficients(&xs, &ys);
+        assert!(m.iter().all(|v| v.abs() < 1e-9));
+        assert!((evaluate_spline(&xs, &ys, &m, 1.5) - 3.0).abs() < 1e-9);
+
+        // Passes through the knots and is clamped outside the range
+        let ys = vec![0.0, 1.0, 0.0, 1.0];
+        let m = natural_spline_coefficients(&xs, &ys);
+        assert!((evaluate_spline(&xs, &ys, &m, 2.0) - 0.0).abs() < 1e-9);
+        assert_eq!(evaluate_spline(&xs, &ys, &m, -1.0), 0.0);
+        assert_eq!(evaluate_spline(&xs, &ys, &m, 10.0), 1.0);
+    }
 }
@@ -0,0 +1,330 @@
+//! Outgoing webhook delivery for alert notifications.
+//!
+//! Alerts are POSTed as JSON to the configured endpoint (Teams, Slack or an
+//! external SCADA system). Each request is signed with HMAC-SHA256 over
+//! `"{timestamp}.{body}"` so receivers can verify the origin and reject
+//! replays. Failed deliveries are retried with exponential backoff.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+use tracing::{debug, warn};
+
+use peilbeheer_core::alert::Alert;
+
+use crate::http_resilience::{HttpClient, HttpError};
+
+/// Signing secret (loaded from environment)
+const WEBHOOK_SECRET_ENV: &str = "ALERT_WEBHOOK_SECRET";
+/// Header carrying the `sha256=<hex>` signature
+pub const SIGNATURE_HEADER: &str = "X-Peilbeheer-Signature";
+/// Header carrying the Unix timestamp that was signed
+pub const TIMESTAMP_HEADER: &str = "X-Peilbeheer-Timestamp";
+/// Header carrying the event type
+pub const EVENT_HEADER: &str = "X-Peilbeheer-Event";
+
+/// Webhook delivery configuration.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Shared secret for HMAC signing (unsigned when `None`)
+    pub secret: Option<String>,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry (milliseconds)
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the retry delay (milliseconds)
+    pub max_backoff_ms: u64,
+    /// Request timeout per attempt (seconds)
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            secret: std::env::var(WEBHOOK_SECRET_ENV).ok().filter(|s| !s.is_empty()),
+            max_retries: std::env::var("ALERT_WEBHOOK_MAX_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(4),
+            initial_backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// Webhook error types.
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] HttpError),
+    #[error("Endpoint returned HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("Failed to serialize payload: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+impl WebhookError {
+    /// Whether another attempt could succeed.
+    ///
+    /// Network errors, 5xx and 429 are retried; other 4xx responses mean the
+    /// receiver rejected the request and retrying will not help.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http(_) => true,
+            Self::Status { status, .. } => *status >= 500 || *status == 429,
+            Self::Serialize(_) => false,
+        }
+    }
+}
+
+/// JSON body posted to webhook endpoints.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    /// Event type (e.g. `alert.triggered`)
+    pub event: &'a str,
+    /// One-line summary, picked up by Slack/Teams incoming webhooks
+    pub text: String,
+    /// The full alert
+    pub alert: &'a Alert,
+    /// When the payload was created
+    pub sent_at: DateTime<Utc>,
+}
+
+impl<'a> WebhookPayload<'a> {
+    /// Build the payload for a newly triggered alert.
+    pub fn triggered(alert: &'a Alert) -> Self {
+        Self {
+            event: "alert.triggered",
+            text: summary(alert),
+            alert,
+            sent_at: Utc::now(),
+        }
+    }
+
+    /// Build the payload for an escalation step of an unacknowledged alert.
+    pub fn escalated(alert: &'a Alert, step: u32) -> Self {
+        Self {
+            event: "alert.escalated",
+            text: format!("Escalation {}: {}", step, summary(alert)),
+            alert,
+            sent_at: Utc::now(),
+        }
+    }
+}
+
+/// One-line alert summary used as webhook `text`.
+fn summary(alert: &Alert) -> String {
+    format!(
+        "[{}] {}: {}",
+        alert.severity.as_str().to_uppercase(),
+        alert.title,
+        alert.message
+    )
+}
+
+/// Outcome of a delivery, including all retries.
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    /// Number of attempts made
+    pub attempts: u32,
+    /// Serialized request body
+    pub request_body: String,
+    /// HTTP status of the last response, if any
+    pub status_code: Option<u16>,
+    /// Body of the last response, if any
+    pub response_body: Option<String>,
+    /// Error of the last attempt when delivery failed
+    pub error: Option<String>,
+}
+
+impl WebhookDelivery {
+    /// Whether the endpoint accepted the payload.
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// HTTP client for webhook delivery.
+pub struct WebhookClient {
+    config: WebhookConfig,
+    http_client: HttpClient,
+}
+
+impl WebhookClient {
+    /// Create a new webhook client.
+    pub fn new(config: WebhookConfig) -> Self {
+        let http_client =
+            HttpClient::shared().with_timeout(StdDuration::from_secs(config.timeout_secs));
+
+        Self { config, http_client }
+    }
+
+    /// Deliver a payload, retrying with exponential backoff.
+    pub async fn deliver(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        payload: &WebhookPayload<'_>,
+    ) -> WebhookDelivery {
+        let body = match serde_json::to_string(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                return WebhookDelivery {
+                    attempts: 0,
+                    request_body: String::new(),
+                    status_code: None,
+                    response_body: None,
+                    error: Some(WebhookError::from(e).to_string()),
+                };
+            }
+        };
+
+        let mut delivery = WebhookDelivery {
+            attempts: 0,
+            request_body: body,
+            status_code: None,
+            response_body: None,
+            error: None,
+        };
+
+        loop {
+            delivery.attempts += 1;
+
+            match self.post(url, headers, payload.event, &delivery.request_body).await {
+                Ok((status, response)) => {
+                    debug!("Webhook {} accepted payload (HTTP {})", url, status);
+                    delivery.status_code = Some(status);
+                    delivery.response_body = Some(response);
+                    delivery.error = None;
+                    return delivery;
+                }
+                Err(e) => {
+                    if let WebhookError::Status { status, body } = &e {
+                        delivery.status_code = Some(*status);
+                        delivery.response_body = Some(body.clone());
+                    }
+                    delivery.error = Some(e.to_string());
+
+                    if !e.is_retryable() || delivery.attempts > self.config.max_retries {
+                        warn!(
+                            "Webhook delivery to {} failed after {} attempt(s): {}",
+                            url, delivery.attempts, e
+                        );
+                        return delivery;
+                    }
+
+                    let delay = self.backoff_delay(delivery.attempts);
+                    debug!(
+                        "Webhook delivery to {} failed ({}), retrying in {:?}",
+                        url, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Send a single signed POST request.
+    async fn post(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        event: &str,
+        body: &str,
+    ) -> Result<(u16, String), WebhookError> {
+        let mut builder = self
+            .http_client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, event);
+
+        if let Some(secret) = &self.config.secret {
+            let timestamp = Utc::now().timestamp();
+            builder = builder
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign_payload(secret, timestamp, body));
+        }
+
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+
+        let resp = self.http_client.send(builder.body(body.to_string())).await?;
+        let status = resp.status().as_u16();
+        let text = resp.text().await.unwrap_or_default();
+
+        if (200..300).contains(&status) {
+            Ok((status, text))
+        } else {
+            Err(WebhookError::Status { status, body: text })
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based).
+    fn backoff_delay(&self, attempt: u32) -> StdDuration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.config.initial_backoff_ms.saturating_mul(factor);
+        StdDuration::from_millis(delay.min(self.config.max_backoff_ms))
+    }
+}
+
+/// Compute the `sha256=<hex>` signature for a webhook body.
+///
+/// Receivers recompute the HMAC over `"{timestamp}.{body}"` with the shared
+/// secret and compare it to the signature header.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // HMAC-SHA256(key = "key", message = "1700000000.{}")
+        let sig = sign_payload("key", 1_700_000_000, "{}");
+        assert_eq!(
+            sig,
+            "sha256=9d713ed406bb7076d4123f0dc2c39d2df5c654ed4b0cd56b52c8b4c940bd63ae"
+        );
+
+        // Sensitive to secret, timestamp and body
+        assert_ne!(sig, sign_payload("other", 1_700_000_000, "{}"));
+        assert_ne!(sig, sign_payload("key", 1_700_000_001, "{}"));
+        assert_ne!(sig, sign_payload("key", 1_700_000_000, "{\"a\":1}"));
+    }
+
+    #[test]
+    fn test_backoff_and_retryable() {
+        let client = WebhookClient::new(WebhookConfig {
+            secret: None,
+            max_retries: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 3_000,
+            timeout_secs: 1,
+        });
+
+        assert_eq!(client.backoff_delay(1), StdDuration::from_millis(500));
+        assert_eq!(client.backoff_delay(2), StdDuration::from_millis(1_000));
+        assert_eq!(client.backoff_delay(3), StdDuration::from_millis(2_000));
+        assert_eq!(client.backoff_delay(4), StdDuration::from_millis(3_000));
+        assert_eq!(client.backoff_delay(40), StdDuration::from_millis(3_000));
+
+        let server_error = WebhookError::Status { status: 503, body: String::new() };
+        let rate_limited = WebhookError::Status { status: 429, body: String::new() };
+        let rejected = WebhookError::Status { status: 400, body: String::new() };
+        assert!(server_error.is_retryable());
+        assert!(rate_limited.is_retryable());
+        assert!(!rejected.is_retryable());
+    }
+}
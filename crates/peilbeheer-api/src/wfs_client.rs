@@ -0,0 +1,278 @@
+//! OGC WFS client for asset layers.
+//!
+//! Layers from GeoServer or PDOK (e.g. BGT water parts) can be loaded into
+//! `asset_registratie` next to the ArcGIS REST layers. A layer in
+//! `ARCGIS_LAYERS` with a `wfs` section is fetched here as GeoJSON with WFS
+//! 2.0 paging; RD New geometries are converted to WGS84 and lines and
+//! polygons are represented by the mean of their coordinates.
+//!
+//! [`fetch_features`] gives the raw features for other WFS sources, such
+//! as the BRO Bodemkaart.
+
+use std::time::Duration;
+
+use peilbeheer_core::asset::AssetRegistratie;
+use peilbeheer_core::projectie::{self, Crs};
+use serde_json::Value;
+
+use crate::arcgis_client;
+use crate::config::{ArcgisLayerConfig, WfsLayerConfig};
+use crate::http_resilience::HttpClient;
+
+/// Features per GetFeature request.
+const PAGE_SIZE: usize = 1000;
+/// Upper bound on the number of pages of one layer.
+const MAX_PAGES: usize = 200;
+
+/// Fetch the assets of a configured layer from its source (WFS or ArcGIS REST).
+pub async fn fetch_layer(layer: &ArcgisLayerConfig) -> Result<Vec<AssetRegistratie>, String> {
+    match &layer.wfs {
+        Some(wfs) => fetch_layer_assets(wfs, &layer.layer_type).await,
+        None => {
+            arcgis_client::fetch_layer_assets(
+                &layer.service_name,
+                layer.layer_id,
+                &layer.layer_type,
+            )
+            .await
+        }
+    }
+}
+
+/// Fetch all features of a WFS feature type as assets.
+pub async fn fetch_layer_assets(
+    config: &WfsLayerConfig,
+    layer_type: &str,
+) -> Result<Vec<AssetRegistratie>, String> {
+    let features = fetch_features(config).await?;
+    let assets = assets_van_features(&features, config, layer_type);
+
+    tracing::info!("WFS: {} {} assets opgehaald", assets.len(), layer_type);
+    Ok(assets)
+}
+
+/// Fetch all features of a WFS feature type as GeoJSON in WGS84.
+pub async fn fetch_features(config: &WfsLayerConfig) -> Result<Vec<Value>, String> {
+    let client = HttpClient::shared();
+    let mut all_features = Vec::new();
+
+    for page in 0..MAX_PAGES {
+        let start_index = page * PAGE_SIZE;
+        let mut query = vec![
+            ("service", "WFS".to_string()),
+            ("version", "2.0.0".to_string()),
+            ("request", "GetFeature".to_string()),
+            ("typeNames", config.type_name.clone()),
+            ("outputFormat", "application/json".to_string()),
+            ("count", PAGE_SIZE.to_string()),
+            ("startIndex", start_index.to_string()),
+        ];
+        if let Some(srs_name) = &config.srs_name {
+            query.push(("srsName", srs_name.clone()));
+        }
+        if let Some(bbox) = &config.bbox {
+            query.push(("bbox", bbox.clone()));
+        }
+
+        let request = client
+            .get(&config.url)
+            .query(&query)
+            .timeout(Duration::from_secs(60));
+        let response = client
+            .send(request)
+            .await
+            .map_err(|e| format!("WFS request failed for {}: {e}", config.type_name))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("WFS HTTP {status} for {}", config.type_name));
+        }
+        let mut body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("WFS parse failed for {}: {e}", config.type_name))?;
+
+        let features = features_wgs84(&mut body, config)?;
+        let page_count = features.len();
+        all_features.extend(features);
+
+        if page_count < PAGE_SIZE {
+            break;
+        }
+    }
+
+    Ok(all_features)
+}
+
+/// Features of a GetFeature response, reprojected to WGS84.
+fn features_wgs84(body: &mut Value, config: &WfsLayerConfig) -> Result<Vec<Value>, String> {
+    // A srsName request overrides the crs member some servers omit
+    let bron = config.srs_name.as_deref().and_then(Crs::from_name);
+    match bron {
+        Some(van) => projectie::herprojecteer_van(body, van, Crs::Wgs84),
+        None => projectie::herprojecteer(body, Crs::Wgs84),
+    }
+    .map_err(|e| format!("WFS reprojection failed for {}: {e}", config.type_name))?;
+
+    match body.get_mut("features").map(Value::take) {
+        Some(Value::Array(features)) => Ok(features),
+        _ => Err(format!("WFS response for {} has no features", config.type_name)),
+    }
+}
+
+fn assets_van_features(
+    features: &[Value],
+    config: &WfsLayerConfig,
+    layer_type: &str,
+) -> Vec<AssetRegistratie> {
+    features
+        .iter()
+        .filter_map(|feature| {
+            let props = feature.get("properties").and_then(|p| p.as_object());
+            let code = props
+                .and_then(|p| p.get(&config.code_field))
+                .and_then(waarde_als_tekst)
+                .or_else(|| feature.get("id").and_then(waarde_als_tekst))?;
+            let naam = props
+                .and_then(|p| p.get(&config.naam_field))
+                .and_then(waarde_als_tekst);
+            let (lon, lat) = feature
+                .get("geometry")
+                .and_then(representatief_punt)
+                .map_or((None, None), |(lon, lat)| (Some(lon), Some(lat)));
+
+            let extra = props
+                .map(|p| {
+                    p.iter()
+                        .filter(|(k, _)| *k != &config.code_field && *k != &config.naam_field)
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect::<serde_json::Map<String, Value>>()
+                })
+                .filter(|m| !m.is_empty())
+                .map(Value::Object);
+
+            Some(AssetRegistratie {
+                layer_type: layer_type.to_string(),
+                code,
+                naam,
+                lat,
+                lon,
+                extra_properties: extra,
+            })
+        })
+        .collect()
+}
+
+fn waarde_als_tekst(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Mean of all coordinates of a geometry, as (lon, lat).
+fn representatief_punt(geometry: &Value) -> Option<(f64, f64)> {
+    fn verzamel(value: &Value, som: &mut (f64, f64, usize)) {
+        let Some(items) = value.as_array() else {
+            return;
+        };
+        match (
+            items.first().and_then(Value::as_f64),
+            items.get(1).and_then(Value::as_f64),
+        ) {
+            (Some(x), Some(y)) => {
+                som.0 += x;
+                som.1 += y;
+                som.2 += 1;
+            }
+            _ => items.iter().for_each(|item| verzamel(item, som)),
+        }
+    }
+
+    let mut som = (0.0, 0.0, 0);
+    if let Some(coordinates) = geometry.get("coordinates") {
+        verzamel(coordinates, &mut som);
+    }
+    if let Some(geometries) = geometry.get("geometries").and_then(|g| g.as_array()) {
+        for g in geometries {
+            if let Some(coordinates) = g.get("coordinates") {
+                verzamel(coordinates, &mut som);
+            }
+        }
+    }
+    (som.2 > 0).then(|| (som.0 / som.2 as f64, som.1 / som.2 as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse_features(
+        body: &mut Value,
+        config: &WfsLayerConfig,
+        layer_type: &str,
+    ) -> Result<Vec<AssetRegistratie>, String> {
+        let features = features_wgs84(body, config)?;
+        Ok(assets_van_features(&features, config, layer_type))
+    }
+
+    fn config() -> WfsLayerConfig {
+        WfsLayerConfig {
+            url: "https://service.pdok.nl/lv/bgt/wfs/v1_0".to_string(),
+            type_name: "bgt:waterdeel".to_string(),
+            code_field: "lokaal_id".to_string(),
+            naam_field: "NAAM".to_string(),
+            srs_name: None,
+            bbox: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_features_rd() {
+        let mut body = json!({
+            "type": "FeatureCollection",
+            "crs": {"type": "name", "properties": {"name": "urn:ogc:def:crs:EPSG::28992"}},
+            "features": [
+                {"type": "Feature", "id": "waterdeel.1",
+                 "properties": {"lokaal_id": "G0546.abc", "class": "watergang"},
+                 "geometry": {"type": "Polygon", "coordinates": [[
+                     [93800.0, 463800.0], [93900.0, 463800.0], [93900.0, 463900.0], [93800.0, 463900.0]
+                 ]]}},
+                {"type": "Feature", "id": "waterdeel.2", "properties": {}, "geometry": null}
+            ]
+        });
+        let assets = parse_features(&mut body, &config(), "bgt_water").unwrap();
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets[0].code, "G0546.abc");
+        assert_eq!(assets[0].layer_type, "bgt_water");
+        assert!((assets[0].lat.unwrap() - 52.159).abs() < 0.01);
+        assert!((assets[0].lon.unwrap() - 4.49).abs() < 0.01);
+        assert_eq!(
+            assets[0].extra_properties,
+            Some(json!({"class": "watergang"}))
+        );
+
+        // Feature-id as fallback code, no geometry
+        assert_eq!(assets[1].code, "waterdeel.2");
+        assert!(assets[1].lat.is_none());
+        assert!(assets[1].extra_properties.is_none());
+    }
+
+    #[test]
+    fn test_parse_features_wgs84() {
+        let mut body = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "properties": {"lokaal_id": 42, "NAAM": "Gemaal Noord"},
+                 "geometry": {"type": "Point", "coordinates": [4.5, 52.2]}}
+            ]
+        });
+        let assets = parse_features(&mut body, &config(), "gemaal").unwrap();
+        assert_eq!(assets[0].code, "42");
+        assert_eq!(assets[0].naam.as_deref(), Some("Gemaal Noord"));
+        assert_eq!((assets[0].lon, assets[0].lat), (Some(4.5), Some(52.2)));
+
+        assert!(parse_features(&mut json!({"error": "x"}), &config(), "gemaal").is_err());
+    }
+}
@@ -5,7 +5,9 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::template::{self, Template};
 
 /// Unique identifier for an alert rule.
 pub type RuleId = String;
@@ -31,6 +33,10 @@ pub struct AlertRule {
     /// Severity level when triggered
     pub severity: AlertSeverity,
 
+    /// Resources the rule is evaluated for (None = every source)
+    #[serde(default)]
+    pub resource_selector: Option<ResourceSelector>,
+
     /// Conditions that must be met (AND logic)
     pub conditions: Vec<AlertCondition>,
 
@@ -46,6 +52,19 @@ pub struct AlertRule {
     /// Notification channels
     pub notification_channels: Vec<NotificationChannel>,
 
+    /// Escalation for alerts that stay unacknowledged
+    #[serde(default)]
+    pub escalation_policy: Option<EscalationPolicy>,
+
+    /// Resolve open alerts after this many consecutive evaluations
+    /// in which the conditions no longer hold
+    #[serde(default)]
+    pub auto_resolve_after: Option<u32>,
+
+    /// Damping of rules that keep switching between triggered and clear
+    #[serde(default)]
+    pub flapping_policy: Option<FlappingPolicy>,
+
     /// Template for alert title
     pub title_template: String,
 
@@ -152,16 +171,144 @@ pub struct AlertCondition {
     /// Threshold value to compare against
     pub value: AlertValue,
 
+    /// Threshold at which a triggered condition clears again (hysteresis);
+    /// `value` is used when not set
+    #[serde(default)]
+    pub clear_value: Option<AlertValue>,
+
     /// Optional data source filter (e.g., specific gemaal code)
     pub source_filter: Option<String>,
 
-    /// Time window for aggregation (e.g., "5m", "1h", "1d")
+    /// Time window for aggregation and rate operators (e.g., "5m", "1h", "1d")
     pub time_window: Option<String>,
 
     /// Aggregation function for time window
     pub aggregation: Option<AggregationFunction>,
 }
 
+impl AlertCondition {
+    /// Parsed `time_window`, if set and valid.
+    pub fn window_duration(&self) -> Option<chrono::Duration> {
+        self.time_window.as_deref().and_then(parse_time_window)
+    }
+
+    /// Silence after which a `no_data` condition triggers for a series.
+    ///
+    /// Series with a configured delivery interval allow `value` missed
+    /// deliveries; other series fall back to the condition's `time_window`.
+    pub fn no_data_limit(&self, heartbeat: &SeriesHeartbeat) -> Option<chrono::Duration> {
+        match (heartbeat.expected_interval_seconds, self.value.as_number()) {
+            (Some(interval), Some(missed)) if interval > 0 && missed > 0.0 => Some(
+                chrono::Duration::milliseconds((interval as f64 * missed * 1000.0) as i64),
+            ),
+            _ => self.window_duration(),
+        }
+    }
+}
+
+/// Parse a time window like "30s", "5m", "1h", "1d" or "1w".
+pub fn parse_time_window(s: &str) -> Option<chrono::Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount.parse().ok()?;
+    if amount <= 0 {
+        return None;
+    }
+
+    match unit {
+        "s" => Some(chrono::Duration::seconds(amount)),
+        "m" => Some(chrono::Duration::minutes(amount)),
+        "h" => Some(chrono::Duration::hours(amount)),
+        "d" => Some(chrono::Duration::days(amount)),
+        "w" => Some(chrono::Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+/// Selects the resources (locations) a rule is evaluated for.
+///
+/// Either a single pattern or a list of patterns. A pattern is a resource id
+/// with optional `*`/`?` wildcards, optionally prefixed with the resource
+/// kind: `peilgebied:PG_1*` matches peilgebieden whose code starts with
+/// `PG_1`, `GEMAAL_001` matches that resource of any kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceSelector {
+    Pattern(String),
+    List(Vec<String>),
+}
+
+impl ResourceSelector {
+    /// Patterns of the selector.
+    pub fn patterns(&self) -> &[String] {
+        match self {
+            Self::Pattern(pattern) => std::slice::from_ref(pattern),
+            Self::List(patterns) => patterns,
+        }
+    }
+
+    /// Check whether a resource of the given kind matches any pattern.
+    pub fn matches(&self, kind: Option<&str>, resource: &str) -> bool {
+        self.patterns().iter().any(|pattern| match pattern.split_once(':') {
+            Some((pattern_kind, id_pattern)) => {
+                kind == Some(pattern_kind) && glob_match(id_pattern, resource)
+            }
+            None => glob_match(pattern, resource),
+        })
+    }
+
+    /// Validate the selector definition.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if self.patterns().is_empty() {
+            errors.push("resource_selector: at least one pattern is required".to_string());
+        }
+        for pattern in self.patterns() {
+            let (kind, id_pattern) = pattern.split_once(':').unwrap_or(("", pattern));
+            if id_pattern.trim().is_empty() || (pattern.contains(':') && kind.trim().is_empty()) {
+                errors.push(format!("resource_selector: invalid pattern '{}'", pattern));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Match `text` against a pattern with `*` (any sequence) and `?` (any
+/// single character) wildcards.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 /// Comparison operators for conditions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -186,6 +333,15 @@ pub enum ComparisonOperator {
     IsNull,
     /// Is not null/empty
     IsNotNull,
+    /// Rate of change (units per hour) greater than
+    #[serde(rename = "rate_above")]
+    RateAbove,
+    /// Rate of change (units per hour) less than
+    #[serde(rename = "rate_below")]
+    RateBelow,
+    /// Series delivered no new points for longer than allowed (heartbeat)
+    #[serde(rename = "no_data")]
+    NoData,
 }
 
 impl ComparisonOperator {
@@ -201,9 +357,17 @@ impl ComparisonOperator {
             Self::NotContains => "not_contains",
             Self::IsNull => "is_null",
             Self::IsNotNull => "is_not_null",
+            Self::RateAbove => "rate_above",
+            Self::RateBelow => "rate_below",
+            Self::NoData => "no_data",
         }
     }
 
+    /// Whether the operator compares the rate of change of a time series.
+    pub fn is_rate(&self) -> bool {
+        matches!(self, Self::RateAbove | Self::RateBelow)
+    }
+
     /// Evaluate the operator for numeric values.
     pub fn eval_numeric(&self, left: f64, right: f64) -> bool {
         match self {
@@ -213,6 +377,8 @@ impl ComparisonOperator {
             Self::Gte => left >= right,
             Self::Lt => left < right,
             Self::Lte => left <= right,
+            Self::RateAbove => left > right,
+            Self::RateBelow => left < right,
             _ => false,
         }
     }
@@ -260,6 +426,16 @@ impl AlertValue {
             _ => None,
         }
     }
+
+    /// Text representation used in alert templates.
+    pub fn to_template_string(&self) -> String {
+        match self {
+            Self::Number(n) => n.to_string(),
+            Self::String(s) => s.clone(),
+            Self::Boolean(b) => b.to_string(),
+            Self::Array(arr) => arr.join(", "),
+        }
+    }
 }
 
 /// Aggregation functions for time-windowed conditions.
@@ -307,14 +483,235 @@ impl ConditionLogic {
 pub enum NotificationChannel {
     /// WebSocket broadcast
     WebSocket,
-    /// Email notification (not yet implemented)
+    /// Email notification
     Email { recipients: Vec<String> },
-    /// Webhook POST
+    /// Webhook POST (signed JSON payload, retried with backoff)
     Webhook { url: String, headers: HashMap<String, String> },
-    /// SMS (not yet implemented)
+    /// SMS (not supported, rejected by validation)
     Sms { recipients: Vec<String> },
 }
 
+impl NotificationChannel {
+    /// Validate the channel definition.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Email { recipients } if recipients.is_empty() => {
+                Err("email channel: at least one recipient is required".to_string())
+            }
+            Self::Sms { .. } => Err("sms channel: SMS notifications are not supported".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Escalation policy for alerts that are not acknowledged in time.
+///
+/// Steps fire in order; each step notifies its channels once the alert has
+/// been active (unacknowledged) for `after_minutes` since it was triggered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    /// Lowest alert severity that escalates
+    #[serde(default = "default_escalation_severity")]
+    pub min_severity: AlertSeverity,
+
+    /// Escalation steps, ordered by `after_minutes`
+    pub steps: Vec<EscalationStep>,
+}
+
+fn default_escalation_severity() -> AlertSeverity {
+    AlertSeverity::Critical
+}
+
+/// Single escalation step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscalationStep {
+    /// Minutes after triggering before this step fires
+    pub after_minutes: u32,
+
+    /// Channels notified when the step fires
+    pub channels: Vec<NotificationChannel>,
+}
+
+impl EscalationPolicy {
+    /// Whether alerts of the given severity escalate.
+    pub fn applies_to(&self, severity: AlertSeverity) -> bool {
+        !self.steps.is_empty() && severity >= self.min_severity
+    }
+
+    /// When step `step` becomes due for an alert triggered at `triggered_at`.
+    pub fn step_due_at(&self, step: usize, triggered_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.steps
+            .get(step)
+            .map(|s| triggered_at + chrono::Duration::minutes(s.after_minutes as i64))
+    }
+
+    /// Validate the policy definition.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if self.steps.is_empty() {
+            errors.push("escalation_policy: at least one step is required".to_string());
+        }
+
+        for (i, step) in self.steps.iter().enumerate() {
+            if step.channels.is_empty() {
+                errors.push(format!("escalation step {}: at least one channel is required", i));
+            }
+            for channel in &step.channels {
+                if let Err(e) = channel.validate() {
+                    errors.push(format!("escalation step {}: {}", i, e));
+                }
+            }
+            if i > 0 && step.after_minutes <= self.steps[i - 1].after_minutes {
+                errors.push(format!(
+                    "escalation step {}: after_minutes must be greater than the previous step",
+                    i
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Escalation state of a single alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEscalation {
+    pub alert_id: AlertId,
+    pub rule_id: RuleId,
+    /// Number of steps that have fired
+    pub current_step: u32,
+    /// When the next step fires (None once completed)
+    pub next_escalation_at: Option<DateTime<Utc>>,
+    pub last_escalated_at: Option<DateTime<Utc>>,
+    /// Set when all steps fired or the alert was acknowledged/resolved
+    pub completed: bool,
+}
+
+/// Flapping detection for a rule.
+///
+/// A rule whose state (triggered/clear) changes `max_transitions` times
+/// within `window_minutes` for the same source is damped: it creates no
+/// new alerts for that source during `damping_minutes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlappingPolicy {
+    /// State changes within the window that count as flapping
+    pub max_transitions: u32,
+
+    /// Observation window (minutes)
+    pub window_minutes: u32,
+
+    /// How long the rule stays damped (minutes)
+    pub damping_minutes: u32,
+}
+
+impl FlappingPolicy {
+    /// Validate the policy definition.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if self.max_transitions < 2 {
+            errors.push("flapping_policy: max_transitions must be at least 2".to_string());
+        }
+        if self.window_minutes == 0 {
+            errors.push("flapping_policy: window_minutes must be greater than 0".to_string());
+        }
+        if self.damping_minutes == 0 {
+            errors.push("flapping_policy: damping_minutes must be greater than 0".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Evaluation state of a rule for one source, used for hysteresis and
+/// flapping detection.
+#[derive(Debug, Clone, Default)]
+pub struct FlapState {
+    /// Whether the rule triggered in the last evaluation
+    pub firing: bool,
+
+    /// Moments the state changed within the flapping window
+    pub transitions: Vec<DateTime<Utc>>,
+
+    /// End of the current damping period
+    pub damped_until: Option<DateTime<Utc>>,
+}
+
+/// Change in flapping status reported by [`FlapState::record`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlapEvent {
+    /// The rule started flapping and is damped
+    Started { transitions: usize },
+    /// The damping period ended
+    Ended,
+}
+
+impl FlapState {
+    /// Whether the rule is damped at the given moment.
+    pub fn is_damped(&self, at: DateTime<Utc>) -> bool {
+        self.damped_until.is_some_and(|until| at < until)
+    }
+
+    /// Record an evaluation outcome at `at`.
+    pub fn record(
+        &mut self,
+        triggered: bool,
+        at: DateTime<Utc>,
+        policy: Option<&FlappingPolicy>,
+    ) -> Option<FlapEvent> {
+        if triggered != self.firing {
+            self.firing = triggered;
+            self.transitions.push(at);
+        }
+
+        let Some(policy) = policy else {
+            self.transitions.clear();
+            return self.damped_until.take().map(|_| FlapEvent::Ended);
+        };
+
+        let window_start = at - chrono::Duration::minutes(policy.window_minutes as i64);
+        self.transitions.retain(|t| *t > window_start);
+
+        if let Some(until) = self.damped_until {
+            if at < until {
+                return None;
+            }
+            self.damped_until = None;
+            return Some(FlapEvent::Ended);
+        }
+
+        if self.transitions.len() >= policy.max_transitions as usize {
+            let transitions = self.transitions.len();
+            self.transitions.clear();
+            self.damped_until = Some(at + chrono::Duration::minutes(policy.damping_minutes as i64));
+            return Some(FlapEvent::Started { transitions });
+        }
+
+        None
+    }
+}
+
+/// Logged rule-level event, e.g. the start or end of flapping.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleEvent {
+    pub id: String,
+    pub rule_id: RuleId,
+    /// Evaluation source the event applies to
+    pub source: Option<String>,
+    pub event_type: String,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Triggered alert instance.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Alert {
@@ -357,6 +754,10 @@ pub struct Alert {
     /// When the alert was resolved
     pub resolved_at: Option<DateTime<Utc>>,
 
+    /// Annotation on how the alert was resolved
+    #[serde(default)]
+    pub resolution_note: Option<String>,
+
     /// Additional context data
     pub context: HashMap<String, serde_json::Value>,
 }
@@ -384,6 +785,16 @@ impl AlertStatus {
             Self::Suppressed => "suppressed",
         }
     }
+
+    /// History event type of a transition into this status.
+    pub fn event_type(&self) -> &str {
+        match self {
+            Self::Active => "triggered",
+            Self::Acknowledged => "acknowledged",
+            Self::Resolved => "resolved",
+            Self::Suppressed => "suppressed",
+        }
+    }
 }
 
 /// Result of evaluating a single rule.
@@ -447,6 +858,9 @@ pub struct AlertStats {
 
     /// Average resolution time (seconds)
     pub avg_resolution_seconds: Option<f64>,
+
+    /// Average time to acknowledge (seconds)
+    pub avg_acknowledge_seconds: Option<f64>,
 }
 
 /// Count of triggers per rule.
@@ -457,6 +871,64 @@ pub struct RuleTriggerCount {
     pub count: u64,
 }
 
+/// Metrics of the periodic rule evaluator.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvaluatorMetrics {
+    /// Completed evaluation runs
+    pub runs: u64,
+    /// Runs in which at least one context failed to evaluate
+    pub failed_runs: u64,
+    /// Start of the last run
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// Duration of the last run (milliseconds)
+    pub last_duration_ms: Option<f64>,
+    /// Mean run duration (milliseconds)
+    pub avg_duration_ms: Option<f64>,
+    /// Longest run duration (milliseconds)
+    pub max_duration_ms: Option<f64>,
+    /// Evaluation contexts (locations) in the last run
+    pub last_contexts: usize,
+    /// Time series loaded in the last run
+    pub last_series_loaded: usize,
+    /// Alerts triggered in the last run
+    pub last_alerts_triggered: usize,
+    /// Alerts triggered over all runs
+    pub total_alerts_triggered: u64,
+    /// Error of the last failed context, if any in the last run
+    pub last_error: Option<String>,
+}
+
+impl EvaluatorMetrics {
+    /// Record a finished run.
+    pub fn record_run(
+        &mut self,
+        started_at: DateTime<Utc>,
+        duration_ms: f64,
+        contexts: usize,
+        series_loaded: usize,
+        alerts_triggered: usize,
+        error: Option<String>,
+    ) {
+        let previous_runs = self.runs as f64;
+        self.runs += 1;
+        if error.is_some() {
+            self.failed_runs += 1;
+        }
+        self.last_run_at = Some(started_at);
+        self.last_duration_ms = Some(duration_ms);
+        self.avg_duration_ms = Some(match self.avg_duration_ms {
+            Some(avg) => (avg * previous_runs + duration_ms) / self.runs as f64,
+            None => duration_ms,
+        });
+        self.max_duration_ms = Some(self.max_duration_ms.map_or(duration_ms, |max| max.max(duration_ms)));
+        self.last_contexts = contexts;
+        self.last_series_loaded = series_loaded;
+        self.last_alerts_triggered = alerts_triggered;
+        self.total_alerts_triggered += alerts_triggered as u64;
+        self.last_error = error;
+    }
+}
+
 /// Create alert rule request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateAlertRuleRequest {
@@ -464,10 +936,18 @@ pub struct CreateAlertRuleRequest {
     pub description: Option<String>,
     pub category: AlertCategory,
     pub severity: AlertSeverity,
+    #[serde(default)]
+    pub resource_selector: Option<ResourceSelector>,
     pub conditions: Vec<AlertCondition>,
     pub condition_logic: ConditionLogic,
     pub cooldown_seconds: u32,
     pub notification_channels: Vec<NotificationChannel>,
+    #[serde(default)]
+    pub escalation_policy: Option<EscalationPolicy>,
+    #[serde(default)]
+    pub auto_resolve_after: Option<u32>,
+    #[serde(default)]
+    pub flapping_policy: Option<FlappingPolicy>,
     pub title_template: String,
     pub message_template: String,
     pub metadata: Option<HashMap<String, serde_json::Value>>,
@@ -480,11 +960,22 @@ pub struct UpdateAlertRuleRequest {
     pub description: Option<String>,
     pub category: Option<AlertCategory>,
     pub severity: Option<AlertSeverity>,
+    /// An empty list removes the selector
+    #[serde(default)]
+    pub resource_selector: Option<ResourceSelector>,
     pub conditions: Option<Vec<AlertCondition>>,
     pub condition_logic: Option<ConditionLogic>,
     pub cooldown_seconds: Option<u32>,
     pub enabled: Option<bool>,
     pub notification_channels: Option<Vec<NotificationChannel>>,
+    #[serde(default)]
+    pub escalation_policy: Option<EscalationPolicy>,
+    /// Set to 0 to disable auto-resolve
+    #[serde(default)]
+    pub auto_resolve_after: Option<u32>,
+    /// Set `max_transitions` to 0 to disable flapping detection
+    #[serde(default)]
+    pub flapping_policy: Option<FlappingPolicy>,
     pub title_template: Option<String>,
     pub message_template: Option<String>,
     pub metadata: Option<HashMap<String, serde_json::Value>>,
@@ -534,6 +1025,24 @@ pub struct EvaluationContext {
 
     /// Metadata about the source
     pub source: Option<String>,
+
+    /// Kind of the source, e.g. `gemaal` or `peilgebied`
+    #[serde(default)]
+    pub source_kind: Option<String>,
+
+    /// Delivery state per field, for `no_data` conditions
+    #[serde(default)]
+    pub heartbeats: HashMap<String, SeriesHeartbeat>,
+}
+
+/// Delivery state of a time series, used by `no_data` conditions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeriesHeartbeat {
+    /// Timestamp of the latest point (None = never delivered)
+    pub last_timestamp: Option<DateTime<Utc>>,
+
+    /// Expected time between deliveries (seconds)
+    pub expected_interval_seconds: Option<u32>,
 }
 
 /// Time series value for aggregation.
@@ -544,9 +1053,46 @@ pub struct TimeSeriesValue {
     pub flag: Option<String>,
 }
 
+/// Note by an operator on an alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertComment {
+    pub id: String,
+    pub alert_id: AlertId,
+    pub user_id: String,
+    pub comment: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to add a comment to an alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAlertCommentRequest {
+    pub comment: String,
+}
+
+/// Entry in the audit trail of an alert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertHistoryEntry {
+    pub id: String,
+    pub alert_id: AlertId,
+    /// Event type, e.g. `triggered`, `acknowledged`, `resolved`, `escalated`
+    pub event_type: String,
+    /// Status before the event (status transitions only)
+    pub from_status: Option<AlertStatus>,
+    /// Status after the event (status transitions only)
+    pub to_status: Option<AlertStatus>,
+    /// User that caused the event (None for the rule engine)
+    pub caused_by: Option<String>,
+    /// `user` or `system`
+    pub cause_type: String,
+    pub event_data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Request to acknowledge an alert.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AcknowledgeAlertRequest {
+    /// Acknowledging user (set from the token by the API)
+    #[serde(default)]
     pub user_id: String,
     pub comment: Option<String>,
 }
@@ -567,12 +1113,16 @@ impl AlertRule {
             description: None,
             category,
             severity,
+            resource_selector: None,
             conditions,
             condition_logic: ConditionLogic::And,
             cooldown_seconds: 300, // 5 minutes default
             enabled: true,
             notification_channels: vec![NotificationChannel::WebSocket],
-            title_template: "{category} Alert: {rule_name}".to_string(),
+            escalation_policy: None,
+            auto_resolve_after: None,
+            flapping_policy: None,
+            title_template: "{{category}} Alert: {{rule_name}}".to_string(),
             message_template: String::new(),
             metadata: HashMap::new(),
             created_at: now,
@@ -581,6 +1131,15 @@ impl AlertRule {
         }
     }
 
+    /// Check whether the rule is evaluated for the given source.
+    pub fn applies_to_source(&self, kind: Option<&str>, source: Option<&str>) -> bool {
+        match (&self.resource_selector, source) {
+            (None, _) => true,
+            (Some(selector), Some(source)) => selector.matches(kind, source),
+            (Some(_), None) => false,
+        }
+    }
+
     /// Check if the rule is in cooldown period.
     pub fn is_in_cooldown(&self, last_triggered: Option<DateTime<Utc>>) -> bool {
         match last_triggered {
@@ -603,11 +1162,70 @@ impl AlertRule {
     }
 
     fn render_template(&self, template: &str, context: &HashMap<String, String>) -> String {
-        let mut result = template.to_string();
-        for (key, value) in context {
-            result = result.replace(&format!("{{{{{}}}}}", key), value);
+        template::render(template, context)
+    }
+
+    /// Names the templates of this rule can always use.
+    ///
+    /// Taken from the render context of an evaluation in which every
+    /// condition matched, so it lists the generic variables, the condition
+    /// fields and their `<field>.value` / `<field>.threshold` variants.
+    pub fn template_variables(&self) -> BTreeSet<String> {
+        let context = EvaluationContext {
+            now: Utc::now(),
+            values: self
+                .conditions
+                .iter()
+                .map(|c| (c.field.clone(), c.value.clone()))
+                .collect(),
+            time_series: HashMap::new(),
+            source: None,
+            source_kind: None,
+            heartbeats: HashMap::new(),
+        };
+        let results: Vec<ConditionResult> = self
+            .conditions
+            .iter()
+            .map(|c| ConditionResult {
+                field: c.field.clone(),
+                passed: true,
+                actual_value: Some(c.value.clone()),
+                expected_value: c.value.clone(),
+                operator: c.operator,
+            })
+            .collect();
+
+        template_context(self, &context, &results, context.now).into_keys().collect()
+    }
+
+    /// Check the templates for syntax errors and unknown variables.
+    ///
+    /// Other context values are filled from the evaluated location at
+    /// runtime, so only `<field>.value` / `<field>.threshold` variables of a
+    /// field without a condition are rejected.
+    fn validate_templates(&self) -> Vec<String> {
+        let known = self.template_variables();
+        let mut errors = Vec::new();
+
+        for (name, source) in [
+            ("title_template", &self.title_template),
+            ("message_template", &self.message_template),
+        ] {
+            match Template::parse(source) {
+                Ok(parsed) => {
+                    let unknown = parsed.variables().into_iter().filter(|v| {
+                        !known.contains(v)
+                            && (v.ends_with(".value") || v.ends_with(".threshold"))
+                    });
+                    for variable in unknown {
+                        errors.push(format!("{}: unknown variable '{}'", name, variable));
+                    }
+                }
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
         }
-        result
+
+        errors
     }
 
     /// Validate the rule definition.
@@ -626,11 +1244,78 @@ impl AlertRule {
             if cond.field.is_empty() {
                 errors.push(format!("condition {}: field cannot be empty", i));
             }
+            if let Some(window) = &cond.time_window
+                && parse_time_window(window).is_none()
+            {
+                errors.push(format!("condition {}: invalid time_window '{}'", i, window));
+            }
+            if cond.operator.is_rate() && cond.value.as_number().is_none() {
+                errors.push(format!("condition {}: rate operators require a numeric value", i));
+            }
+            if cond.operator == ComparisonOperator::NoData {
+                if cond.value.as_number().is_none_or(|missed| missed <= 0.0) {
+                    errors.push(format!(
+                        "condition {}: no_data requires a positive number of missed deliveries as value",
+                        i
+                    ));
+                }
+                if cond.time_window.is_none() {
+                    errors.push(format!(
+                        "condition {}: no_data requires a time_window for series without an expected interval",
+                        i
+                    ));
+                }
+            }
+            if let Some(clear) = &cond.clear_value {
+                use ComparisonOperator::*;
+                match (cond.operator, cond.value.as_number(), clear.as_number()) {
+                    (Gt | Gte | RateAbove, Some(on), Some(off)) if off > on => errors.push(
+                        format!("condition {}: clear_value must not be above value", i),
+                    ),
+                    (Lt | Lte | RateBelow, Some(on), Some(off)) if off < on => errors.push(
+                        format!("condition {}: clear_value must not be below value", i),
+                    ),
+                    (Gt | Gte | Lt | Lte | RateAbove | RateBelow, Some(_), Some(_)) => {}
+                    _ => errors.push(format!(
+                        "condition {}: clear_value requires a numeric threshold operator",
+                        i
+                    )),
+                }
+            }
         }
 
         if self.title_template.is_empty() {
             errors.push("title_template cannot be empty".to_string());
         }
+        errors.extend(self.validate_templates());
+
+        errors.extend(
+            self.notification_channels
+                .iter()
+                .filter_map(|channel| channel.validate().err()),
+        );
+
+        if let Some(policy) = &self.escalation_policy
+            && let Err(policy_errors) = policy.validate()
+        {
+            errors.extend(policy_errors);
+        }
+
+        if self.auto_resolve_after == Some(0) {
+            errors.push("auto_resolve_after must be at least 1".to_string());
+        }
+
+        if let Some(policy) = &self.flapping_policy
+            && let Err(policy_errors) = policy.validate()
+        {
+            errors.extend(policy_errors);
+        }
+
+        if let Some(selector) = &self.resource_selector
+            && let Err(selector_errors) = selector.validate()
+        {
+            errors.extend(selector_errors);
+        }
 
         if errors.is_empty() {
             Ok(())
@@ -640,35 +1325,73 @@ impl AlertRule {
     }
 }
 
+/// Variables for rendering the templates of `rule`: the rule and resource,
+/// the context values and the observed values and thresholds per condition.
+fn template_context(
+    rule: &AlertRule,
+    context: &EvaluationContext,
+    condition_results: &[ConditionResult],
+    now: DateTime<Utc>,
+) -> HashMap<String, String> {
+    let mut template_ctx = HashMap::new();
+    template_ctx.insert("rule_id".to_string(), rule.id.clone());
+    template_ctx.insert("rule_name".to_string(), rule.name.clone());
+    template_ctx.insert("category".to_string(), rule.category.as_str().to_string());
+    template_ctx.insert("severity".to_string(), rule.severity.as_str().to_string());
+    template_ctx.insert("resource".to_string(), context.source.clone().unwrap_or_default());
+    template_ctx.insert("resource_kind".to_string(), context.source_kind.clone().unwrap_or_default());
+    template_ctx.insert("resources".to_string(), context.source.clone().unwrap_or_default());
+    template_ctx.insert(
+        "triggered_at".to_string(),
+        now.format("%Y-%m-%d %H:%M UTC").to_string(),
+    );
+
+    // Add values from context
+    for (key, value) in &context.values {
+        template_ctx.insert(key.clone(), value.to_template_string());
+    }
+
+    // Observed values and thresholds per condition field
+    for result in condition_results {
+        if let Some(actual) = &result.actual_value {
+            template_ctx.insert(format!("{}.value", result.field), actual.to_template_string());
+        }
+        template_ctx.insert(
+            format!("{}.threshold", result.field),
+            result.expected_value.to_template_string(),
+        );
+    }
+
+    // The first matching condition provides the generic variables
+    if let Some(primary) = condition_results.iter().find(|r| r.passed) {
+        template_ctx.insert("field".to_string(), primary.field.clone());
+        template_ctx.insert("operator".to_string(), primary.operator.as_str().to_string());
+        template_ctx.insert(
+            "value".to_string(),
+            primary.actual_value.as_ref().map(AlertValue::to_template_string).unwrap_or_default(),
+        );
+        template_ctx.insert("threshold".to_string(), primary.expected_value.to_template_string());
+    }
+
+    template_ctx
+}
+
 impl Alert {
     /// Create a new alert from a rule.
     pub fn from_rule(rule: &AlertRule, context: &EvaluationContext) -> Self {
+        Self::from_evaluation(rule, context, &[])
+    }
+
+    /// Create a new alert from a rule, filling the templates with the
+    /// observed values and thresholds of the evaluated conditions.
+    pub fn from_evaluation(
+        rule: &AlertRule,
+        context: &EvaluationContext,
+        condition_results: &[ConditionResult],
+    ) -> Self {
         let now = Utc::now();
-        let id = format!("ALT_{}_{}", rule.id, now.timestamp());
-
-        // Build context for template rendering
-        let mut template_ctx = HashMap::new();
-        template_ctx.insert("rule_name".to_string(), rule.name.clone());
-        template_ctx.insert("category".to_string(), rule.category.as_str().to_string());
-        template_ctx.insert("severity".to_string(), rule.severity.as_str().to_string());
-
-        // Add values from context
-        for (key, value) in &context.values {
-            match value {
-                AlertValue::Number(n) => {
-                    template_ctx.insert(key.clone(), n.to_string());
-                }
-                AlertValue::String(s) => {
-                    template_ctx.insert(key.clone(), s.clone());
-                }
-                AlertValue::Boolean(b) => {
-                    template_ctx.insert(key.clone(), b.to_string());
-                }
-                AlertValue::Array(arr) => {
-                    template_ctx.insert(key.clone(), arr.join(", "));
-                }
-            }
-        }
+        let id = format!("ALT_{}", uuid::Uuid::new_v4());
+        let template_ctx = template_context(rule, context, condition_results, now);
 
         Self {
             id,
@@ -684,6 +1407,7 @@ impl Alert {
             acknowledged_at: None,
             acknowledged_by: None,
             resolved_at: None,
+            resolution_note: None,
             context: context.values.iter().map(|(k, v)| {
                 (k.clone(), serde_json::to_value(v).unwrap_or(serde_json::Value::Null))
             }).collect(),
@@ -703,6 +1427,20 @@ impl Alert {
         self.resolved_at = Some(Utc::now());
     }
 
+    /// Resolve the alert because its conditions no longer hold.
+    pub fn resolve_automatically(&mut self, evaluations: u32) {
+        self.resolve();
+        self.resolution_note = Some(format!(
+            "resolved automatically: conditions not met for {} consecutive evaluations",
+            evaluations
+        ));
+    }
+
+    /// Check if the alert is still open (not resolved).
+    pub fn is_open(&self) -> bool {
+        self.status != AlertStatus::Resolved
+    }
+
     /// Check if alert is still active.
     pub fn is_active(&self) -> bool {
         self.status == AlertStatus::Active
@@ -735,6 +1473,7 @@ mod tests {
                 field: "water_level".to_string(),
                 operator: ComparisonOperator::Gte,
                 value: AlertValue::Number(2.5),
+                clear_value: None,
                 source_filter: None,
                 time_window: None,
                 aggregation: None,
@@ -764,6 +1503,7 @@ mod tests {
             field: "test_field".to_string(),
             operator: ComparisonOperator::Eq,
             value: AlertValue::Boolean(true),
+            clear_value: None,
             source_filter: None,
             time_window: None,
             aggregation: None,
@@ -794,6 +1534,7 @@ mod tests {
                 field: "pump_status".to_string(),
                 operator: ComparisonOperator::Eq,
                 value: AlertValue::String("offline".to_string()),
+                clear_value: None,
                 source_filter: Some("GEMAAL_001".to_string()),
                 time_window: None,
                 aggregation: None,
@@ -805,6 +1546,8 @@ mod tests {
             values: HashMap::new(),
             time_series: HashMap::new(),
             source: Some("GEMAAL_001".to_string()),
+            source_kind: None,
+            heartbeats: HashMap::new(),
         };
         context.values.insert("pump_status".to_string(), AlertValue::String("offline".to_string()));
 
@@ -814,6 +1557,61 @@ mod tests {
         assert_eq!(alert.severity, AlertSeverity::Error);
         assert_eq!(alert.status, AlertStatus::Active);
         assert!(alert.affected_resources.contains(&"GEMAAL_001".to_string()));
+        assert_eq!(alert.title, "pump_status Alert: Pump Offline");
+    }
+
+    #[test]
+    fn test_alert_templates() {
+        let mut rule = AlertRule::new(
+            "RULE_007",
+            "Hoog peil",
+            AlertCategory::WaterLevel,
+            AlertSeverity::Critical,
+            vec![AlertCondition {
+                field: "water_level".to_string(),
+                operator: ComparisonOperator::Gt,
+                value: AlertValue::Number(-4.5),
+                clear_value: None,
+                source_filter: None,
+                time_window: None,
+                aggregation: None,
+            }],
+        );
+        rule.title_template = "{{upper severity}}: {{rule_name}} bij {{resource}}".to_string();
+        rule.message_template =
+            "Peil {{round water_level.value 2}} m boven drempel {{threshold}} m".to_string();
+        assert!(rule.validate().is_ok());
+
+        let context = EvaluationContext {
+            now: Utc::now(),
+            values: HashMap::from([("water_level".to_string(), AlertValue::Number(-4.2345))]),
+            time_series: HashMap::new(),
+            source: Some("GEMAAL_001".to_string()),
+            source_kind: None,
+            heartbeats: HashMap::new(),
+        };
+        let results = vec![ConditionResult {
+            field: "water_level".to_string(),
+            passed: true,
+            actual_value: Some(AlertValue::Number(-4.2345)),
+            expected_value: AlertValue::Number(-4.5),
+            operator: ComparisonOperator::Gt,
+        }];
+
+        let alert = Alert::from_evaluation(&rule, &context, &results);
+        assert_eq!(alert.title, "CRITICAL: Hoog peil bij GEMAAL_001");
+        assert_eq!(alert.message, "Peil -4.23 m boven drempel -4.5 m");
+
+        // Values of other fields at the location are filled at runtime
+        rule.message_template = "Peil {{water_level}} m, neerslag {{neerslag}} mm".to_string();
+        assert!(rule.validate().is_ok());
+
+        rule.message_template = "Peil {{debiet.value}}".to_string();
+        rule.title_template = "{{#if resource}}Peil".to_string();
+        let errors = rule.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("title_template:"));
+        assert_eq!(errors[1], "message_template: unknown variable 'debiet.value'");
     }
 
     #[test]
@@ -831,6 +1629,8 @@ mod tests {
             values: HashMap::new(),
             time_series: HashMap::new(),
             source: None,
+            source_kind: None,
+            heartbeats: HashMap::new(),
         };
 
         let mut alert = Alert::from_rule(&rule, &context);
@@ -844,6 +1644,56 @@ mod tests {
         alert.resolve();
         assert_eq!(alert.status, AlertStatus::Resolved);
         assert!(alert.resolved_at.is_some());
+        assert!(!alert.is_open());
+
+        let mut auto = Alert::from_rule(&rule, &context);
+        auto.resolve_automatically(3);
+        assert_eq!(auto.status, AlertStatus::Resolved);
+        assert!(auto.resolution_note.unwrap().starts_with("resolved automatically"));
+    }
+
+    #[test]
+    fn test_no_data_condition() {
+        let mut condition = AlertCondition {
+            field: "water_level".to_string(),
+            operator: ComparisonOperator::NoData,
+            value: AlertValue::Number(3.0),
+            clear_value: None,
+            source_filter: None,
+            time_window: Some("2h".to_string()),
+            aggregation: None,
+        };
+
+        // Series delivering every 15 minutes may miss three deliveries
+        let configured = SeriesHeartbeat {
+            last_timestamp: None,
+            expected_interval_seconds: Some(900),
+        };
+        assert_eq!(condition.no_data_limit(&configured), Some(chrono::Duration::minutes(45)));
+
+        // Without a configured interval the time window applies
+        let unconfigured = SeriesHeartbeat::default();
+        assert_eq!(condition.no_data_limit(&unconfigured), Some(chrono::Duration::hours(2)));
+
+        let rule = AlertRule::new(
+            "RULE_008",
+            "Sensor plat",
+            AlertCategory::SystemHealth,
+            AlertSeverity::Warning,
+            vec![condition.clone()],
+        );
+        assert!(rule.validate().is_ok());
+
+        condition.value = AlertValue::Number(0.0);
+        condition.time_window = None;
+        let rule = AlertRule::new(
+            "RULE_008",
+            "Sensor plat",
+            AlertCategory::SystemHealth,
+            AlertSeverity::Warning,
+            vec![condition],
+        );
+        assert_eq!(rule.validate().unwrap_err().len(), 2);
     }
 
     #[test]
@@ -871,4 +1721,246 @@ mod tests {
         let old = Utc::now() - chrono::Duration::seconds(120);
         assert!(!rule_with_cooldown.is_in_cooldown(Some(old)));
     }
+
+    #[test]
+    fn test_escalation_policy() {
+        let policy = EscalationPolicy {
+            min_severity: AlertSeverity::Critical,
+            steps: vec![
+                EscalationStep {
+                    after_minutes: 15,
+                    channels: vec![NotificationChannel::WebSocket],
+                },
+                EscalationStep {
+                    after_minutes: 60,
+                    channels: vec![NotificationChannel::Email { recipients: vec!["piket@hhvr.nl".to_string()] }],
+                },
+            ],
+        };
+
+        assert!(policy.validate().is_ok());
+        assert!(policy.applies_to(AlertSeverity::Critical));
+        assert!(!policy.applies_to(AlertSeverity::Error));
+
+        let triggered = Utc::now();
+        assert_eq!(policy.step_due_at(0, triggered), Some(triggered + chrono::Duration::minutes(15)));
+        assert_eq!(policy.step_due_at(1, triggered), Some(triggered + chrono::Duration::minutes(60)));
+        assert_eq!(policy.step_due_at(2, triggered), None);
+
+        // Steps must be strictly increasing
+        let mut invalid = policy.clone();
+        invalid.steps[1].after_minutes = 10;
+        assert!(invalid.validate().is_err());
+
+        // SMS is not supported, e-mail needs recipients
+        let mut invalid = policy.clone();
+        invalid.steps[0].channels = vec![NotificationChannel::Sms { recipients: vec!["+31600000000".to_string()] }];
+        assert!(invalid.validate().is_err());
+        invalid.steps[0].channels = vec![NotificationChannel::Email { recipients: Vec::new() }];
+        assert!(invalid.validate().is_err());
+
+        // Severity defaults to critical
+        let parsed: EscalationPolicy = serde_json::from_str(
+            r#"{"steps": [{"after_minutes": 5, "channels": ["web_socket"]}]}"#,
+        ).unwrap();
+        assert_eq!(parsed.min_severity, AlertSeverity::Critical);
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        assert_eq!(parse_time_window("30s"), Some(chrono::Duration::seconds(30)));
+        assert_eq!(parse_time_window("5m"), Some(chrono::Duration::minutes(5)));
+        assert_eq!(parse_time_window("1h"), Some(chrono::Duration::hours(1)));
+        assert_eq!(parse_time_window("2d"), Some(chrono::Duration::days(2)));
+        assert_eq!(parse_time_window("1w"), Some(chrono::Duration::weeks(1)));
+        assert_eq!(parse_time_window("0h"), None);
+        assert_eq!(parse_time_window("h"), None);
+        assert_eq!(parse_time_window("5y"), None);
+    }
+
+    #[test]
+    fn test_rate_operator() {
+        assert!(ComparisonOperator::RateAbove.is_rate());
+        assert!(!ComparisonOperator::Gt.is_rate());
+        assert!(ComparisonOperator::RateAbove.eval_numeric(0.06, 0.05));
+        assert!(ComparisonOperator::RateBelow.eval_numeric(-0.1, -0.05));
+
+        let op: ComparisonOperator = serde_json::from_str("\"rate_above\"").unwrap();
+        assert_eq!(op, ComparisonOperator::RateAbove);
+
+        let mut rule = AlertRule::new(
+            "RULE_006",
+            "Snelle stijging",
+            AlertCategory::WaterLevel,
+            AlertSeverity::Warning,
+            vec![AlertCondition {
+                field: "water_level".to_string(),
+                operator: ComparisonOperator::RateAbove,
+                value: AlertValue::Number(0.05),
+                clear_value: None,
+                source_filter: None,
+                time_window: Some("1h".to_string()),
+                aggregation: None,
+            }],
+        );
+        assert!(rule.validate().is_ok());
+
+        rule.conditions[0].time_window = Some("an hour".to_string());
+        assert!(rule.validate().is_err());
+    }
+
+    #[test]
+    fn test_history_entry() {
+        assert_eq!(AlertStatus::Active.event_type(), "triggered");
+        assert_eq!(AlertStatus::Resolved.event_type(), "resolved");
+
+        let entry = AlertHistoryEntry {
+            id: "AHI_1".to_string(),
+            alert_id: "ALT_1".to_string(),
+            event_type: AlertStatus::Acknowledged.event_type().to_string(),
+            from_status: Some(AlertStatus::Active),
+            to_status: Some(AlertStatus::Acknowledged),
+            caused_by: Some("operator".to_string()),
+            cause_type: "user".to_string(),
+            event_data: serde_json::json!({ "comment": "Gemaal handmatig herstart" }),
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["from_status"], "active");
+        assert_eq!(json["to_status"], "acknowledged");
+        assert_eq!(json["event_type"], "acknowledged");
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("PG_1*", "PG_12"));
+        assert!(glob_match("PG_1*", "PG_1"));
+        assert!(!glob_match("PG_1*", "PG_21"));
+        assert!(glob_match("GEMAAL_00?", "GEMAAL_001"));
+        assert!(!glob_match("GEMAAL_00?", "GEMAAL_0010"));
+        assert!(glob_match("*_001", "GEMAAL_001"));
+        assert!(glob_match("G*L_*1", "GEMAAL_001"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "PG_1"));
+    }
+
+    #[test]
+    fn test_resource_selector() {
+        let selector: ResourceSelector = serde_json::from_str("\"peilgebied:PG_1*\"").unwrap();
+        assert!(selector.matches(Some("peilgebied"), "PG_12"));
+        assert!(!selector.matches(Some("gemaal"), "PG_12"));
+        assert!(!selector.matches(None, "PG_12"));
+        assert!(!selector.matches(Some("peilgebied"), "PG_22"));
+
+        let list: ResourceSelector =
+            serde_json::from_str(r#"["GEMAAL_001", "gemaal:KGM_*"]"#).unwrap();
+        assert!(list.matches(None, "GEMAAL_001"));
+        assert!(list.matches(Some("gemaal"), "KGM_042"));
+        assert!(!list.matches(Some("gemaal"), "GEMAAL_002"));
+        assert!(list.validate().is_ok());
+
+        assert!(ResourceSelector::Pattern("peilgebied:".to_string()).validate().is_err());
+        assert!(ResourceSelector::List(vec![]).validate().is_err());
+
+        let mut rule = AlertRule::new(
+            "RULE_009",
+            "Hoog peil",
+            AlertCategory::WaterLevel,
+            AlertSeverity::Warning,
+            vec![],
+        );
+        assert!(rule.applies_to_source(None, None));
+        rule.resource_selector = Some(selector);
+        assert!(rule.applies_to_source(Some("peilgebied"), Some("PG_12")));
+        assert!(!rule.applies_to_source(Some("peilgebied"), Some("PG_22")));
+        assert!(!rule.applies_to_source(None, None));
+    }
+
+    #[test]
+    fn test_flapping_detection() {
+        let policy = FlappingPolicy {
+            max_transitions: 4,
+            window_minutes: 10,
+            damping_minutes: 30,
+        };
+        assert!(policy.validate().is_ok());
+
+        let start = Utc::now();
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let mut state = FlapState::default();
+
+        // Three state changes within the window are tolerated
+        assert_eq!(state.record(true, at(0), Some(&policy)), None);
+        assert_eq!(state.record(false, at(1), Some(&policy)), None);
+        assert_eq!(state.record(true, at(2), Some(&policy)), None);
+        assert!(!state.is_damped(at(2)));
+
+        // The fourth change starts damping
+        assert_eq!(
+            state.record(false, at(3), Some(&policy)),
+            Some(FlapEvent::Started { transitions: 4 })
+        );
+        assert!(state.is_damped(at(4)));
+        assert_eq!(state.record(true, at(4), Some(&policy)), None);
+
+        // Damping ends after damping_minutes
+        assert_eq!(state.record(true, at(33), Some(&policy)), Some(FlapEvent::Ended));
+        assert!(!state.is_damped(at(33)));
+
+        // Changes spread out beyond the window don't count as flapping
+        let mut slow = FlapState::default();
+        for (i, triggered) in [true, false, true, false, true].into_iter().enumerate() {
+            assert_eq!(slow.record(triggered, at(i as i64 * 15), Some(&policy)), None);
+        }
+
+        let invalid = FlappingPolicy { max_transitions: 1, window_minutes: 0, damping_minutes: 5 };
+        assert_eq!(invalid.validate().unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn test_clear_value_validation() {
+        let mut rule = AlertRule::new(
+            "RULE_008",
+            "Hoog peil",
+            AlertCategory::WaterLevel,
+            AlertSeverity::Warning,
+            vec![AlertCondition {
+                field: "water_level".to_string(),
+                operator: ComparisonOperator::Gt,
+                value: AlertValue::Number(-4.5),
+                clear_value: Some(AlertValue::Number(-4.6)),
+                source_filter: None,
+                time_window: None,
+                aggregation: None,
+            }],
+        );
+        assert!(rule.validate().is_ok());
+
+        // Clearing above the trigger threshold would never leave the alarm state
+        rule.conditions[0].clear_value = Some(AlertValue::Number(-4.4));
+        assert!(rule.validate().is_err());
+
+        rule.conditions[0].operator = ComparisonOperator::Lt;
+        assert!(rule.validate().is_ok());
+
+        rule.conditions[0].operator = ComparisonOperator::Eq;
+        assert!(rule.validate().is_err());
+    }
+
+    #[test]
+    fn test_evaluator_metrics() {
+        let mut metrics = EvaluatorMetrics::default();
+        let now = Utc::now();
+
+        metrics.record_run(now, 100.0, 3, 6, 1, None);
+        metrics.record_run(now, 300.0, 3, 6, 0, Some("query failed".to_string()));
+
+        assert_eq!(metrics.runs, 2);
+        assert_eq!(metrics.failed_runs, 1);
+        assert_eq!(metrics.last_duration_ms, Some(300.0));
+        assert_eq!(metrics.avg_duration_ms, Some(200.0));
+        assert_eq!(metrics.max_duration_ms, Some(300.0));
+        assert_eq!(metrics.total_alerts_triggered, 1);
+        assert!(metrics.last_error.is_some());
+    }
 }
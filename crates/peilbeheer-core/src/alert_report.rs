@@ -0,0 +1,321 @@
+//! Time-bucketed alert statistics for reporting on alarm handling.
+//!
+//! Alerts are grouped by the period in which they were triggered. Per period
+//! and per rule the report contains the number of triggers, the mean time to
+//! acknowledge (MTTA) and the mean time to resolve (MTTR).
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::alert::RuleId;
+
+/// Period size of a trend report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsBucket {
+    Day,
+    /// ISO week, starting on Monday
+    Week,
+    Month,
+}
+
+impl StatsBucket {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    /// Start of the period containing `at`.
+    pub fn period_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let date = at.date_naive();
+        let start = match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date),
+        };
+        start.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+    }
+
+    /// Start of the period following the one starting at `start`.
+    pub fn next_period(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Day => start + Duration::days(1),
+            Self::Week => start + Duration::weeks(1),
+            Self::Month => start
+                .checked_add_months(Months::new(1))
+                .unwrap_or(start + Duration::days(31)),
+        }
+    }
+}
+
+/// Lifecycle timestamps of a single alert, the input of a report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertTimeline {
+    pub rule_id: RuleId,
+    pub rule_name: String,
+    pub triggered_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Alarm handling figures of a group of alerts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertHandlingStats {
+    /// Alerts triggered
+    pub triggered: u64,
+
+    /// Alerts that were acknowledged
+    pub acknowledged: u64,
+
+    /// Alerts that were resolved
+    pub resolved: u64,
+
+    /// Mean time to acknowledge (seconds)
+    pub mtta_seconds: Option<f64>,
+
+    /// Mean time to resolve (seconds)
+    pub mttr_seconds: Option<f64>,
+}
+
+/// Statistics of one period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertPeriodStats {
+    pub period_start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stats: AlertHandlingStats,
+}
+
+/// Statistics of one rule over the whole report range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleStats {
+    pub rule_id: RuleId,
+    pub rule_name: String,
+    #[serde(flatten)]
+    pub stats: AlertHandlingStats,
+}
+
+/// Trend report of alert handling over a time range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertTrendReport {
+    pub bucket: StatsBucket,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+
+    /// Totals over the whole range
+    pub totals: AlertHandlingStats,
+
+    /// One entry per period, including periods without alerts
+    pub periods: Vec<AlertPeriodStats>,
+
+    /// Per rule, most triggered first
+    pub by_rule: Vec<AlertRuleStats>,
+}
+
+/// Running sums used while building a report.
+#[derive(Debug, Default)]
+struct Accumulator {
+    triggered: u64,
+    acknowledged: u64,
+    resolved: u64,
+    acknowledge_seconds: f64,
+    resolve_seconds: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, alert: &AlertTimeline) {
+        self.triggered += 1;
+        if let Some(at) = alert.acknowledged_at {
+            self.acknowledged += 1;
+            self.acknowledge_seconds += seconds_between(alert.triggered_at, at);
+        }
+        if let Some(at) = alert.resolved_at {
+            self.resolved += 1;
+            self.resolve_seconds += seconds_between(alert.triggered_at, at);
+        }
+    }
+
+    fn finish(&self) -> AlertHandlingStats {
+        AlertHandlingStats {
+            triggered: self.triggered,
+            acknowledged: self.acknowledged,
+            resolved: self.resolved,
+            mtta_seconds: (self.acknowledged > 0)
+                .then(|| self.acknowledge_seconds / self.acknowledged as f64),
+            mttr_seconds: (self.resolved > 0).then(|| self.resolve_seconds / self.resolved as f64),
+        }
+    }
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+impl AlertTrendReport {
+    /// Build a report of the alerts triggered within `[start, end)`.
+    pub fn build(
+        alerts: &[AlertTimeline],
+        bucket: StatsBucket,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        let in_range: Vec<&AlertTimeline> = alerts
+            .iter()
+            .filter(|a| a.triggered_at >= start && a.triggered_at < end)
+            .collect();
+
+        let mut totals = Accumulator::default();
+        let mut per_period: HashMap<DateTime<Utc>, Accumulator> = HashMap::new();
+        let mut per_rule: HashMap<&str, (&str, Accumulator)> = HashMap::new();
+
+        for alert in &in_range {
+            totals.add(alert);
+            per_period
+                .entry(bucket.period_start(alert.triggered_at))
+                .or_default()
+                .add(alert);
+            per_rule
+                .entry(alert.rule_id.as_str())
+                .or_insert_with(|| (alert.rule_name.as_str(), Accumulator::default()))
+                .1
+                .add(alert);
+        }
+
+        let mut periods = Vec::new();
+        let mut period_start = bucket.period_start(start);
+        while period_start < end {
+            periods.push(AlertPeriodStats {
+                period_start,
+                stats: per_period
+                    .get(&period_start)
+                    .map(Accumulator::finish)
+                    .unwrap_or_default(),
+            });
+            period_start = bucket.next_period(period_start);
+        }
+
+        let mut by_rule: Vec<AlertRuleStats> = per_rule
+            .into_iter()
+            .map(|(rule_id, (rule_name, acc))| AlertRuleStats {
+                rule_id: rule_id.to_string(),
+                rule_name: rule_name.to_string(),
+                stats: acc.finish(),
+            })
+            .collect();
+        by_rule.sort_by(|a, b| {
+            b.stats.triggered.cmp(&a.stats.triggered).then_with(|| a.rule_id.cmp(&b.rule_id))
+        });
+
+        Self {
+            bucket,
+            start,
+            end,
+            totals: totals.finish(),
+            periods,
+            by_rule,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn timeline(
+        rule: &str,
+        triggered_at: DateTime<Utc>,
+        ack_after_min: Option<i64>,
+        resolve_after_min: Option<i64>,
+    ) -> AlertTimeline {
+        AlertTimeline {
+            rule_id: rule.to_string(),
+            rule_name: format!("Regel {}", rule),
+            triggered_at,
+            acknowledged_at: ack_after_min.map(|m| triggered_at + Duration::minutes(m)),
+            resolved_at: resolve_after_min.map(|m| triggered_at + Duration::minutes(m)),
+        }
+    }
+
+    #[test]
+    fn test_period_start() {
+        // 2025-03-12 is a Wednesday
+        let wednesday = at(12, 15, 30);
+        assert_eq!(StatsBucket::Day.period_start(wednesday), at(12, 0, 0));
+        assert_eq!(StatsBucket::Week.period_start(wednesday), at(10, 0, 0));
+        assert_eq!(StatsBucket::Month.period_start(wednesday), at(1, 0, 0));
+        assert_eq!(
+            StatsBucket::Month.next_period(at(1, 0, 0)),
+            Utc.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(StatsBucket::from_str("Week"), Some(StatsBucket::Week));
+    }
+
+    #[test]
+    fn test_daily_report() {
+        let alerts = vec![
+            timeline("RULE_1", at(10, 8, 0), Some(10), Some(60)),
+            timeline("RULE_1", at(10, 20, 0), Some(20), None),
+            timeline("RULE_2", at(12, 9, 0), None, Some(30)),
+            // Outside the range
+            timeline("RULE_2", at(20, 9, 0), Some(5), Some(5)),
+        ];
+
+        let report = AlertTrendReport::build(&alerts, StatsBucket::Day, at(10, 0, 0), at(13, 0, 0));
+
+        assert_eq!(report.periods.len(), 3);
+        let first = &report.periods[0].stats;
+        assert_eq!(first.triggered, 2);
+        assert_eq!(first.acknowledged, 2);
+        assert_eq!(first.mtta_seconds, Some(900.0));
+        assert_eq!(first.mttr_seconds, Some(3600.0));
+
+        // Day without alerts is still reported
+        assert_eq!(report.periods[1].stats, AlertHandlingStats::default());
+        assert_eq!(report.periods[2].stats.triggered, 1);
+
+        assert_eq!(report.totals.triggered, 3);
+        assert_eq!(report.totals.resolved, 2);
+        assert_eq!(report.totals.mttr_seconds, Some(2700.0));
+
+        assert_eq!(report.by_rule.len(), 2);
+        assert_eq!(report.by_rule[0].rule_id, "RULE_1");
+        assert_eq!(report.by_rule[0].stats.triggered, 2);
+        assert_eq!(report.by_rule[1].stats.mtta_seconds, None);
+    }
+
+    #[test]
+    fn test_weekly_report() {
+        let alerts = vec![
+            timeline("RULE_1", at(3, 8, 0), Some(10), Some(60)),
+            timeline("RULE_1", at(9, 8, 0), Some(30), Some(60)),
+            timeline("RULE_1", at(11, 8, 0), None, None),
+        ];
+
+        let report = AlertTrendReport::build(&alerts, StatsBucket::Week, at(3, 0, 0), at(17, 0, 0));
+
+        assert_eq!(report.periods.len(), 2);
+        assert_eq!(report.periods[0].period_start, at(3, 0, 0));
+        assert_eq!(report.periods[0].stats.triggered, 2);
+        assert_eq!(report.periods[0].stats.mtta_seconds, Some(1200.0));
+        assert_eq!(report.periods[1].period_start, at(10, 0, 0));
+        assert_eq!(report.periods[1].stats.triggered, 1);
+    }
+}
@@ -0,0 +1,188 @@
+//! Audit log of mutating API calls.
+//!
+//! Every POST, PUT, PATCH and DELETE request is recorded with the caller,
+//! the endpoint, a summary of the request body and the outcome. The summary
+//! never contains secrets: values of fields such as passwords, tokens and
+//! two-factor codes are redacted, long arrays are collapsed to their length
+//! and the result is truncated.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Maximum length of a payload summary (characters)
+pub const MAX_SUMMARY_CHARS: usize = 1000;
+
+/// Arrays longer than this are summarized by their length
+const MAX_ARRAY_ITEMS: usize = 10;
+
+/// Replacement of redacted values
+const REDACTED: &str = "***";
+
+/// One recorded API call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+
+    pub occurred_at: DateTime<Utc>,
+
+    /// Authenticated caller (`None` for anonymous calls such as a login)
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+
+    /// HTTP method
+    pub method: String,
+
+    /// Request path, without query string
+    pub path: String,
+
+    /// Request body with secrets redacted, see [`summarize_payload`]
+    pub payload_summary: Option<String>,
+
+    /// HTTP status code of the response
+    pub status_code: u16,
+
+    /// Whether the call succeeded (2xx/3xx)
+    pub success: bool,
+
+    pub duration_ms: u64,
+}
+
+/// Filters for searching the audit log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    /// User id or username
+    pub user: Option<String>,
+    pub method: Option<String>,
+    /// Part of the request path
+    pub path: Option<String>,
+    /// Text within the payload summary
+    pub search: Option<String>,
+    pub success: Option<bool>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// Whether requests with this HTTP method are recorded.
+pub fn is_audited_method(method: &str) -> bool {
+    matches!(
+        method.to_ascii_uppercase().as_str(),
+        "POST" | "PUT" | "PATCH" | "DELETE"
+    )
+}
+
+/// Whether a response status counts as a successful call.
+pub fn is_success_status(status_code: u16) -> bool {
+    (200..400).contains(&status_code)
+}
+
+/// Summarize a request body for the audit log.
+///
+/// JSON bodies are redacted and compacted; other bodies are only recorded by
+/// their size, since they cannot be redacted. Empty bodies give `None`.
+pub fn summarize_payload(body: &[u8]) -> Option<String> {
+    if body.iter().all(|b| b.is_ascii_whitespace()) {
+        return None;
+    }
+
+    let summary = match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes>", body.len()),
+    };
+
+    Some(truncate(summary, MAX_SUMMARY_CHARS))
+}
+
+/// Whether the value of a field may hold a secret.
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    ["password", "secret", "token"]
+        .iter()
+        .any(|part| key.contains(part))
+        || matches!(key.as_str(), "code" | "totp_code" | "key" | "api_key")
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if is_sensitive_key(key) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) if items.len() > MAX_ARRAY_ITEMS => {
+            *value = Value::String(format!("<{} items>", items.len()));
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn truncate(s: String, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let body = br#"{
+            "username": "jan",
+            "password": "geheim",
+            "totp_code": "123456",
+            "nested": {"refresh_token": "abc", "client_secret": "xyz"},
+            "gemaal_code": "GEM-01"
+        }"#;
+
+        let summary = summarize_payload(body).unwrap();
+        assert!(!summary.contains("geheim"));
+        assert!(!summary.contains("123456"));
+        assert!(!summary.contains("abc"));
+        assert!(!summary.contains("xyz"));
+        assert!(summary.contains("\"username\":\"jan\""));
+        assert!(summary.contains("\"gemaal_code\":\"GEM-01\""));
+    }
+
+    #[test]
+    fn test_large_payloads_are_compacted() {
+        let points: Vec<f64> = (0..500).map(|i| i as f64).collect();
+        let body = serde_json::json!({ "series": "WL_01", "points": points }).to_string();
+        let summary = summarize_payload(body.as_bytes()).unwrap();
+        assert!(summary.contains("\"points\":\"<500 items>\""));
+
+        let long = serde_json::json!({ "description": "x".repeat(5000) }).to_string();
+        let summary = summarize_payload(long.as_bytes()).unwrap();
+        assert_eq!(summary.chars().count(), MAX_SUMMARY_CHARS + 1);
+        assert!(summary.ends_with('…'));
+    }
+
+    #[test]
+    fn test_non_json_and_empty_bodies() {
+        assert_eq!(summarize_payload(b""), None);
+        assert_eq!(summarize_payload(b"  \n"), None);
+        assert_eq!(summarize_payload(b"password=geheim").as_deref(), Some("<15 bytes>"));
+    }
+
+    #[test]
+    fn test_audited_methods() {
+        assert!(is_audited_method("POST"));
+        assert!(is_audited_method("delete"));
+        assert!(!is_audited_method("GET"));
+        assert!(!is_audited_method("OPTIONS"));
+        assert!(is_success_status(201));
+        assert!(!is_success_status(403));
+    }
+}
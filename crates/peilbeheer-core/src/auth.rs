@@ -2,6 +2,12 @@
 //!
 //! This module provides domain models for user authentication,
 //! JWT tokens, role-based access control (RBAC), and permissions.
+//!
+//! Permissions are global per role. A [`ResourceScope`] additionally limits
+//! resource-bound actions of a user to their own peilgebieden and assets.
+//!
+//! Integrations run under a service account ([`AccountType::Service`]): it
+//! cannot log in, expires, and authenticates with API keys bound to it.
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -88,12 +94,26 @@ pub enum Permission {
     AssetsUpdate,
     AssetsSync,
 
+    // Pump control permissions
+    PumpsControl,
+
     // User management permissions
     UsersRead,
     UsersCreate,
     UsersUpdate,
     UsersDelete,
 
+    // Alert permissions
+    AlertsRead,
+    AlertsAcknowledge,
+    AlertsManage,
+
+    // API key permissions
+    ApiKeysManage,
+
+    // Audit permissions
+    AuditRead,
+
     // System permissions
     SystemStatus,
     SystemConfigure,
@@ -112,10 +132,16 @@ impl Permission {
             Self::AssetsRead => "assets:read",
             Self::AssetsUpdate => "assets:update",
             Self::AssetsSync => "assets:sync",
+            Self::PumpsControl => "pumps:control",
             Self::UsersRead => "users:read",
             Self::UsersCreate => "users:create",
             Self::UsersUpdate => "users:update",
             Self::UsersDelete => "users:delete",
+            Self::AlertsRead => "alerts:read",
+            Self::AlertsAcknowledge => "alerts:acknowledge",
+            Self::AlertsManage => "alerts:manage",
+            Self::ApiKeysManage => "api_keys:manage",
+            Self::AuditRead => "audit:read",
             Self::SystemStatus => "system:status",
             Self::SystemConfigure => "system:configure",
         }
@@ -134,16 +160,39 @@ impl Permission {
             "assets:read" => Some(Self::AssetsRead),
             "assets:update" => Some(Self::AssetsUpdate),
             "assets:sync" => Some(Self::AssetsSync),
+            "pumps:control" => Some(Self::PumpsControl),
             "users:read" => Some(Self::UsersRead),
             "users:create" => Some(Self::UsersCreate),
             "users:update" => Some(Self::UsersUpdate),
             "users:delete" => Some(Self::UsersDelete),
+            "alerts:read" => Some(Self::AlertsRead),
+            "alerts:acknowledge" => Some(Self::AlertsAcknowledge),
+            "alerts:manage" => Some(Self::AlertsManage),
+            "api_keys:manage" => Some(Self::ApiKeysManage),
+            "audit:read" => Some(Self::AuditRead),
             "system:status" => Some(Self::SystemStatus),
             "system:configure" => Some(Self::SystemConfigure),
             _ => None,
         }
     }
 
+    /// Whether a service account may hold this permission.
+    ///
+    /// Managing accounts and keys, configuring the system and writing pump
+    /// setpoints stay with people.
+    pub fn allowed_for_service_accounts(&self) -> bool {
+        !matches!(
+            self,
+            Self::PumpsControl
+                | Self::UsersCreate
+                | Self::UsersUpdate
+                | Self::UsersDelete
+                | Self::ApiKeysManage
+                | Self::AuditRead
+                | Self::SystemConfigure
+        )
+    }
+
     /// Get all permissions for a given role
     pub fn for_role(role: Role) -> HashSet<Permission> {
         match role {
@@ -160,6 +209,7 @@ impl Permission {
                 Permission::ResultsRead,
                 Permission::AssetsRead,
                 Permission::UsersRead,
+                Permission::AlertsRead,
                 Permission::SystemStatus,
             ]
             .into_iter()
@@ -171,6 +221,8 @@ impl Permission {
                 Permission::AssetsRead,
                 Permission::AssetsUpdate,
                 Permission::AssetsSync,
+                Permission::AlertsRead,
+                Permission::AlertsAcknowledge,
                 Permission::SystemStatus,
             ]
             .into_iter()
@@ -184,6 +236,9 @@ impl Permission {
                 Permission::ResultsDelete,
                 Permission::AssetsRead,
                 Permission::AssetsUpdate,
+                Permission::AlertsRead,
+                Permission::AlertsAcknowledge,
+                Permission::AlertsManage,
                 Permission::SystemStatus,
             ]
             .into_iter()
@@ -199,10 +254,16 @@ impl Permission {
                 Permission::AssetsRead,
                 Permission::AssetsUpdate,
                 Permission::AssetsSync,
+                Permission::PumpsControl,
                 Permission::UsersRead,
                 Permission::UsersCreate,
                 Permission::UsersUpdate,
                 Permission::UsersDelete,
+                Permission::AlertsRead,
+                Permission::AlertsAcknowledge,
+                Permission::AlertsManage,
+                Permission::ApiKeysManage,
+                Permission::AuditRead,
                 Permission::SystemStatus,
                 Permission::SystemConfigure,
             ]
@@ -212,6 +273,82 @@ impl Permission {
     }
 }
 
+/// Peilgebieden and assets a user's resource-bound actions are limited to.
+///
+/// An empty scope means no restriction. Otherwise an action on resources
+/// (executing a scenario, acknowledging an alert) is only allowed when every
+/// resource involved is listed, so actions not tied to any resource are left
+/// to unrestricted users.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ResourceScope {
+    /// Peilgebied codes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peilgebieden: Vec<String>,
+
+    /// Asset codes (e.g. gemalen)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<String>,
+}
+
+impl ResourceScope {
+    /// Whether the scope places no restriction at all.
+    pub fn is_unrestricted(&self) -> bool {
+        self.peilgebieden.is_empty() && self.assets.is_empty()
+    }
+
+    /// Whether a peilgebied or asset code is within the scope.
+    pub fn contains(&self, resource: &str) -> bool {
+        self.peilgebieden.iter().chain(&self.assets).any(|r| r == resource)
+    }
+
+    /// Whether an action on `resources` is allowed.
+    pub fn allows(&self, resources: &[String]) -> bool {
+        self.is_unrestricted()
+            || (!resources.is_empty() && resources.iter().all(|r| self.contains(r)))
+    }
+
+    /// Resources outside the scope, for error messages.
+    pub fn outside<'a>(&self, resources: &'a [String]) -> Vec<&'a str> {
+        resources
+            .iter()
+            .filter(|r| !self.contains(r))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Longest validity of a service account (days)
+pub const MAX_SERVICE_ACCOUNT_DAYS: i64 = 365;
+
+/// Kind of account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountType {
+    /// Person logging in with a password or single sign-on
+    #[default]
+    User,
+    /// Integration authenticating with API keys only
+    Service,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Service => "service",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "user" => Some(Self::User),
+            "service" => Some(Self::Service),
+            _ => None,
+        }
+    }
+}
+
 /// User account stored in the database.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct User {
@@ -232,9 +369,70 @@ pub struct User {
     pub last_login: Option<DateTime<Utc>>,
     #[serde(default)]
     pub is_active: bool,
+    /// Restriction of resource-bound actions (empty = all resources)
+    #[serde(default, skip_serializing_if = "ResourceScope::is_unrestricted")]
+    pub resource_scope: ResourceScope,
+    /// Whether the user logs in with a TOTP code as second factor
+    #[serde(default)]
+    pub two_factor_enabled: bool,
+    #[serde(default)]
+    pub account_type: AccountType,
+    /// End of validity; required for service accounts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 impl User {
+    /// Whether this is a service account
+    pub fn is_service_account(&self) -> bool {
+        self.account_type == AccountType::Service
+    }
+
+    /// Whether the account has expired at the given time
+    pub fn is_expired(&self, at: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires| at >= expires)
+    }
+
+    /// Check the restrictions on a service account at time `now`.
+    ///
+    /// Returns the violations; always empty for user accounts.
+    pub fn service_account_violations(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !self.is_service_account() {
+            return errors;
+        }
+
+        match self.expires_at {
+            None => errors.push("service accounts require expires_at".to_string()),
+            Some(expires) if expires <= now => {
+                errors.push("expires_at must be in the future".to_string())
+            }
+            Some(expires) if expires > now + chrono::Duration::days(MAX_SERVICE_ACCOUNT_DAYS) => {
+                errors.push(format!(
+                    "expires_at can be at most {} days ahead",
+                    MAX_SERVICE_ACCOUNT_DAYS
+                ))
+            }
+            Some(_) => {}
+        }
+
+        let mut forbidden: Vec<&str> = self
+            .get_permissions()
+            .iter()
+            .filter(|p| !p.allowed_for_service_accounts())
+            .map(Permission::as_str)
+            .collect();
+        if !forbidden.is_empty() {
+            forbidden.sort_unstable();
+            errors.push(format!(
+                "service accounts cannot hold permissions: {}",
+                forbidden.join(", ")
+            ));
+        }
+
+        errors
+    }
+
     /// Get the user's role as a Role enum
     pub fn get_role(&self) -> Option<Role> {
         Role::from_str(&self.role)
@@ -283,6 +481,11 @@ pub struct Claims {
     pub permissions: Vec<String>,
     pub exp: i64, // Expiration time (Unix timestamp)
     pub iat: i64, // Issued at (Unix timestamp)
+    #[serde(default, skip_serializing_if = "ResourceScope::is_unrestricted")]
+    pub scope: ResourceScope,
+    /// Session the token belongs to; revoking the session invalidates it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 impl Claims {
@@ -301,6 +504,48 @@ impl Claims {
             permissions,
             exp,
             iat: Utc::now().timestamp(),
+            scope: user.resource_scope.clone(),
+            sid: None,
+        }
+    }
+
+    /// Create claims for a request authenticated with an API key
+    pub fn from_api_key(key: &ApiKey, exp: i64) -> Self {
+        Self {
+            sub: key.id.clone(),
+            username: key.name.clone(),
+            email: String::new(),
+            role: API_KEY_ROLE.to_string(),
+            permissions: key.permissions.clone(),
+            exp,
+            iat: Utc::now().timestamp(),
+            scope: key.resource_scope.clone(),
+            sid: None,
+        }
+    }
+
+    /// Create claims for an API key bound to a service account.
+    ///
+    /// The key acts as the account, with the permissions both hold.
+    pub fn from_service_account_key(key: &ApiKey, account: &User, exp: i64) -> Self {
+        let account_permissions = account.get_permissions();
+        let permissions = key
+            .permissions
+            .iter()
+            .filter(|p| Permission::from_str(p).is_some_and(|p| account_permissions.contains(&p)))
+            .cloned()
+            .collect();
+
+        Self {
+            sub: account.id.clone(),
+            username: account.username.clone(),
+            email: account.email.clone(),
+            role: API_KEY_ROLE.to_string(),
+            permissions,
+            exp,
+            iat: Utc::now().timestamp(),
+            scope: account.resource_scope.clone(),
+            sid: None,
         }
     }
 
@@ -313,6 +558,11 @@ impl Claims {
     pub fn has_any_permission(&self, permissions: &[Permission]) -> bool {
         permissions.iter().any(|p| self.permissions.contains(&p.as_str().to_string()))
     }
+
+    /// Check if claims have a permission for all of the given resources
+    pub fn has_permission_for(&self, permission: &Permission, resources: &[String]) -> bool {
+        self.has_permission(permission) && self.scope.allows(resources)
+    }
 }
 
 /// Login request.
@@ -320,6 +570,9 @@ impl Claims {
 pub struct LoginRequest {
     pub username: String,
     pub password: String, // In production, this should be hashed
+    /// TOTP code or recovery code, for users with two-factor authentication
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_code: Option<String>,
 }
 
 /// Login response with JWT token.
@@ -328,9 +581,66 @@ pub struct LoginResponse {
     pub access_token: String,
     pub token_type: String,
     pub expires_in: i64, // seconds
+    /// Single-use token to obtain a new access token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// The user must enrol in two-factor authentication first; the token
+    /// only gives access to the enrolment endpoints
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub two_factor_setup_required: bool,
     pub user: UserInfo,
 }
 
+/// Request to exchange a refresh token for new tokens.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+/// Stored refresh token (the token itself is only kept as a hash).
+///
+/// Every login starts a token family. Refreshing uses up the token and
+/// issues its successor in the same family, so a token presented twice
+/// means it was copied: the whole family is then revoked.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RefreshToken {
+    pub id: String,
+    pub family_id: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Set when the token was exchanged for its successor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Result of checking a presented refresh token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshTokenState {
+    Valid,
+    Expired,
+    Revoked,
+    /// Already exchanged before: the token was presented twice
+    Reused,
+}
+
+impl RefreshToken {
+    /// State of the token when presented at `at`.
+    pub fn state(&self, at: DateTime<Utc>) -> RefreshTokenState {
+        if self.revoked_at.is_some() {
+            RefreshTokenState::Revoked
+        } else if self.used_at.is_some() {
+            RefreshTokenState::Reused
+        } else if at >= self.expires_at {
+            RefreshTokenState::Expired
+        } else {
+            RefreshTokenState::Valid
+        }
+    }
+}
+
 /// User info returned in login response (no sensitive data).
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UserInfo {
@@ -341,6 +651,10 @@ pub struct UserInfo {
     pub full_name: Option<String>,
     pub role: String,
     pub permissions: Vec<String>,
+    #[serde(default, skip_serializing_if = "ResourceScope::is_unrestricted")]
+    pub resource_scope: ResourceScope,
+    #[serde(default)]
+    pub two_factor_enabled: bool,
 }
 
 impl From<User> for UserInfo {
@@ -357,21 +671,72 @@ impl From<User> for UserInfo {
             full_name: user.full_name,
             role: user.role,
             permissions,
+            resource_scope: user.resource_scope,
+            two_factor_enabled: user.two_factor_enabled,
         }
     }
 }
 
+/// Roles that must use two-factor authentication (set by an admin).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TwoFactorPolicy {
+    #[serde(default)]
+    pub required_roles: Vec<Role>,
+}
+
+impl TwoFactorPolicy {
+    /// Whether users with `role` must use two-factor authentication.
+    pub fn requires(&self, role: Option<Role>) -> bool {
+        role.is_some_and(|role| self.required_roles.contains(&role))
+    }
+}
+
+/// Material to register a new TOTP secret in an authenticator app.
+///
+/// Enrolment takes effect once a code generated from it is confirmed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TotpEnrollment {
+    /// Base32 secret, for manual entry
+    pub secret: String,
+    /// `otpauth://` URI encoded in the QR code
+    pub otpauth_uri: String,
+    /// QR code as SVG image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr_svg: Option<String>,
+}
+
+/// Request carrying a TOTP code or recovery code.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+/// Single-use recovery codes, only shown when they are generated.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
 /// Request to create a new user.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CreateUserRequest {
     pub username: String,
     pub email: String,
+    /// Not used for service accounts
+    #[serde(default)]
     pub password: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub full_name: Option<String>,
     pub role: String,
     #[serde(default)]
     pub custom_permissions: Vec<String>,
+    #[serde(default)]
+    pub resource_scope: ResourceScope,
+    #[serde(default)]
+    pub account_type: AccountType,
+    /// End of validity; required for service accounts
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 /// Request to update a user.
@@ -387,6 +752,12 @@ pub struct UpdateUserRequest {
     pub custom_permissions: Option<Vec<String>>,
     #[serde(default)]
     pub is_active: Option<bool>,
+    /// New resource scope; an empty scope lifts the restriction
+    #[serde(default)]
+    pub resource_scope: Option<ResourceScope>,
+    /// New end of validity of a service account
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 /// Request to change password.
@@ -394,6 +765,148 @@ pub struct UpdateUserRequest {
 pub struct ChangePasswordRequest {
     pub old_password: String,
     pub new_password: String,
+    /// End all sessions except the one making the request
+    #[serde(default = "default_logout_other_sessions")]
+    pub logout_other_sessions: bool,
+}
+
+fn default_logout_other_sessions() -> bool {
+    true
+}
+
+/// Request a password reset e-mail.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+/// Set a new password with the token from a password reset e-mail.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// Login session: a refresh token family and the client that started it.
+///
+/// Access tokens carry the session ID (`sid`), so revoking a session also
+/// rejects its access tokens right away.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    /// Last login or refresh
+    pub last_used_at: DateTime<Utc>,
+    /// Expiry of the current refresh token
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    /// The session of the token that requested the list
+    #[serde(default)]
+    pub current: bool,
+}
+
+/// Client a session is started or refreshed from.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Prefix of every API key, so leaked keys are easy to recognise
+pub const API_KEY_PREFIX: &str = "pbk_";
+
+/// Role reported in the claims of API key requests
+pub const API_KEY_ROLE: &str = "api_key";
+
+/// API key for machine clients such as FEWS sync scripts and SCADA bridges.
+///
+/// Only a hash of the key is stored; `prefix` identifies the key in lists.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// First characters of the key
+    pub prefix: String,
+    /// Permissions granted to the key
+    pub permissions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Service account the key authenticates as
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_account_id: Option<String>,
+    /// Resource scope of the creator, which the key cannot exceed
+    #[serde(default, skip_serializing_if = "ResourceScope::is_unrestricted")]
+    pub resource_scope: ResourceScope,
+}
+
+impl ApiKey {
+    /// Whether the key can be used at the given time (not revoked or expired)
+    pub fn is_usable(&self, at: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| at < expires)
+    }
+}
+
+/// Request to create an API key.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub permissions: Vec<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Bind the key to this service account
+    #[serde(default)]
+    pub service_account_id: Option<String>,
+}
+
+impl CreateApiKeyRequest {
+    /// Validate the request; keys can only get permissions the creator has.
+    pub fn validate(&self, creator_permissions: &[String]) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if self.name.trim().is_empty() {
+            errors.push("name cannot be empty".to_string());
+        }
+        if self.permissions.is_empty() {
+            errors.push("at least one permission is required".to_string());
+        }
+        for permission in &self.permissions {
+            if Permission::from_str(permission).is_none() {
+                errors.push(format!("unknown permission '{}'", permission));
+            } else if !creator_permissions.contains(permission) {
+                errors.push(format!("cannot grant permission '{}' you do not have", permission));
+            }
+        }
+        if let Some(expires_at) = self.expires_at
+            && expires_at <= Utc::now()
+        {
+            errors.push("expires_at must be in the future".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Newly created API key; the key itself is only returned once.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
 }
 
 #[cfg(test)]
@@ -419,6 +932,25 @@ mod tests {
         assert!(viewer_perms.contains(&Permission::ScenariosRead));
         assert!(!viewer_perms.contains(&Permission::ScenariosCreate));
         assert!(!viewer_perms.contains(&Permission::UsersDelete));
+        assert!(viewer_perms.contains(&Permission::AlertsRead));
+        assert!(!viewer_perms.contains(&Permission::AlertsAcknowledge));
+
+        let operator_perms = Permission::for_role(Role::Operator);
+        assert!(operator_perms.contains(&Permission::AlertsAcknowledge));
+        assert!(!operator_perms.contains(&Permission::AlertsManage));
+
+        assert!(Permission::for_role(Role::Engineer).contains(&Permission::AlertsManage));
+        assert_eq!(Permission::from_str("alerts:manage"), Some(Permission::AlertsManage));
+
+        // Only admins read the audit log
+        assert!(admin_perms.contains(&Permission::AuditRead));
+        assert!(!Permission::for_role(Role::Engineer).contains(&Permission::AuditRead));
+
+        // Pump setpoints are written by admins or by explicit grant
+        assert!(admin_perms.contains(&Permission::PumpsControl));
+        assert!(!operator_perms.contains(&Permission::PumpsControl));
+        assert_eq!(Permission::from_str("pumps:control"), Some(Permission::PumpsControl));
+        assert!(!Permission::PumpsControl.allowed_for_service_accounts());
     }
 
     #[test]
@@ -435,6 +967,10 @@ mod tests {
             updated_at: None,
             last_login: None,
             is_active: true,
+            resource_scope: ResourceScope::default(),
+            two_factor_enabled: false,
+            account_type: AccountType::User,
+            expires_at: None,
         };
 
         // Viewer base role doesn't have ScenariosCreate, but custom_permissions does
@@ -447,6 +983,219 @@ mod tests {
         assert!(!user.has_permission(&Permission::UsersCreate));
     }
 
+    #[test]
+    fn test_resource_scope() {
+        let resources = |codes: &[&str]| codes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        let unrestricted = ResourceScope::default();
+        assert!(unrestricted.allows(&resources(&["PG_1"])));
+        assert!(unrestricted.allows(&[]));
+
+        let scope = ResourceScope {
+            peilgebieden: vec!["PG_1".to_string(), "PG_2".to_string()],
+            assets: vec!["GEMAAL_001".to_string()],
+        };
+        assert!(scope.allows(&resources(&["PG_1"])));
+        assert!(scope.allows(&resources(&["PG_2", "GEMAAL_001"])));
+        assert!(!scope.allows(&resources(&["PG_1", "PG_3"])));
+        assert_eq!(scope.outside(&resources(&["PG_1", "PG_3"])), vec!["PG_3"]);
+        // Actions not tied to a resource are left to unrestricted users
+        assert!(!scope.allows(&[]));
+
+        let mut user = User {
+            id: "1".to_string(),
+            username: "rayon_noord".to_string(),
+            email: "noord@example.com".to_string(),
+            full_name: None,
+            role: "operator".to_string(),
+            custom_permissions: vec![],
+            created_at: Utc::now(),
+            created_by: None,
+            updated_at: None,
+            last_login: None,
+            is_active: true,
+            resource_scope: scope,
+            two_factor_enabled: false,
+            account_type: AccountType::User,
+            expires_at: None,
+        };
+        let claims = Claims::from_user(&user, 0);
+        assert!(claims.has_permission_for(&Permission::ScenariosExecute, &resources(&["PG_2"])));
+        assert!(!claims.has_permission_for(&Permission::ScenariosExecute, &resources(&["PG_3"])));
+        // The scope never grants permissions the role lacks
+        assert!(!claims.has_permission_for(&Permission::UsersDelete, &resources(&["PG_1"])));
+
+        // Unrestricted scopes are left out of the token
+        user.resource_scope = ResourceScope::default();
+        let json = serde_json::to_value(Claims::from_user(&user, 0)).unwrap();
+        assert!(json.get("scope").is_none());
+    }
+
+    #[test]
+    fn test_two_factor_policy() {
+        let policy: TwoFactorPolicy =
+            serde_json::from_str(r#"{"required_roles": ["operator", "admin"]}"#).unwrap();
+        assert!(policy.requires(Some(Role::Admin)));
+        assert!(policy.requires(Some(Role::Operator)));
+        assert!(!policy.requires(Some(Role::Viewer)));
+        assert!(!policy.requires(None));
+        assert!(!TwoFactorPolicy::default().requires(Some(Role::Admin)));
+    }
+
+    #[test]
+    fn test_change_password_ends_other_sessions_by_default() {
+        let req: ChangePasswordRequest =
+            serde_json::from_str(r#"{"old_password": "a", "new_password": "b"}"#).unwrap();
+        assert!(req.logout_other_sessions);
+
+        let req: ChangePasswordRequest = serde_json::from_str(
+            r#"{"old_password": "a", "new_password": "b", "logout_other_sessions": false}"#,
+        )
+        .unwrap();
+        assert!(!req.logout_other_sessions);
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let creator = vec!["alerts:read".to_string(), "assets:sync".to_string()];
+        let mut req = CreateApiKeyRequest {
+            name: "FEWS sync".to_string(),
+            permissions: vec!["assets:sync".to_string()],
+            expires_at: Some(Utc::now() + chrono::Duration::days(30)),
+            service_account_id: None,
+        };
+        assert!(req.validate(&creator).is_ok());
+
+        req.permissions = vec!["users:delete".to_string(), "bogus".to_string()];
+        req.expires_at = Some(Utc::now() - chrono::Duration::days(1));
+        assert_eq!(req.validate(&creator).unwrap_err().len(), 3);
+
+        let now = Utc::now();
+        let mut key = ApiKey {
+            id: "KEY_1".to_string(),
+            name: "SCADA bridge".to_string(),
+            prefix: "pbk_1234abcd".to_string(),
+            permissions: vec!["alerts:read".to_string()],
+            created_by: None,
+            created_at: now,
+            expires_at: None,
+            revoked_at: None,
+            last_used_at: None,
+            service_account_id: None,
+            resource_scope: ResourceScope {
+                peilgebieden: vec!["PG_1".to_string()],
+                assets: vec![],
+            },
+        };
+        assert!(key.is_usable(now));
+
+        let claims = Claims::from_api_key(&key, now.timestamp() + 60);
+        assert_eq!(claims.role, API_KEY_ROLE);
+        assert!(claims.has_permission(&Permission::AlertsRead));
+        assert!(!claims.has_permission(&Permission::AlertsManage));
+        assert_eq!(claims.scope, key.resource_scope);
+
+        key.expires_at = Some(now);
+        assert!(!key.is_usable(now));
+        key.expires_at = None;
+        key.revoked_at = Some(now);
+        assert!(!key.is_usable(now));
+    }
+
+    #[test]
+    fn test_service_accounts() {
+        let now = Utc::now();
+        let mut account = User {
+            id: "usr_1".to_string(),
+            username: "fews-sync".to_string(),
+            email: "fews-sync@example.com".to_string(),
+            full_name: None,
+            role: "viewer".to_string(),
+            custom_permissions: vec!["assets:sync".to_string()],
+            created_at: now,
+            created_by: None,
+            updated_at: None,
+            last_login: None,
+            is_active: true,
+            resource_scope: ResourceScope {
+                peilgebieden: vec!["PG_1".to_string()],
+                assets: vec![],
+            },
+            two_factor_enabled: false,
+            account_type: AccountType::Service,
+            expires_at: Some(now + chrono::Duration::days(90)),
+        };
+        assert!(account.service_account_violations(now).is_empty());
+        assert!(!account.is_expired(now));
+
+        // Expiry is required and bounded
+        account.expires_at = None;
+        assert_eq!(account.service_account_violations(now).len(), 1);
+        account.expires_at = Some(now + chrono::Duration::days(MAX_SERVICE_ACCOUNT_DAYS + 1));
+        assert_eq!(account.service_account_violations(now).len(), 1);
+        account.expires_at = Some(now);
+        assert!(account.is_expired(now));
+
+        // No user management through a service account
+        account.expires_at = Some(now + chrono::Duration::days(90));
+        account.custom_permissions.push("users:create".to_string());
+        assert_eq!(
+            account.service_account_violations(now),
+            vec!["service accounts cannot hold permissions: users:create".to_string()]
+        );
+        account.custom_permissions.pop();
+
+        // Keys act as the account, limited to what both hold
+        let key = ApiKey {
+            id: "key_1".to_string(),
+            name: "FEWS".to_string(),
+            prefix: "pbk_1234abcd".to_string(),
+            permissions: vec!["assets:sync".to_string(), "alerts:manage".to_string()],
+            created_by: None,
+            created_at: now,
+            expires_at: account.expires_at,
+            revoked_at: None,
+            last_used_at: None,
+            service_account_id: Some(account.id.clone()),
+            resource_scope: ResourceScope::default(),
+        };
+        let claims = Claims::from_service_account_key(&key, &account, 0);
+        assert_eq!(claims.sub, "usr_1");
+        assert_eq!(claims.role, API_KEY_ROLE);
+        assert_eq!(claims.permissions, vec!["assets:sync".to_string()]);
+        assert!(!claims.scope.is_unrestricted());
+
+        assert_eq!(AccountType::from_str("Service"), Some(AccountType::Service));
+        let req: CreateUserRequest = serde_json::from_str(
+            r#"{"username": "u", "email": "u@example.com", "password": "pw", "role": "viewer"}"#,
+        )
+        .unwrap();
+        assert_eq!(req.account_type, AccountType::User);
+    }
+
+    #[test]
+    fn test_refresh_token_state() {
+        let now = Utc::now();
+        let mut token = RefreshToken {
+            id: "rt_1".to_string(),
+            family_id: "rtf_1".to_string(),
+            user_id: "usr_1".to_string(),
+            created_at: now,
+            expires_at: now + chrono::Duration::days(30),
+            used_at: None,
+            revoked_at: None,
+        };
+        assert_eq!(token.state(now), RefreshTokenState::Valid);
+        assert_eq!(token.state(now + chrono::Duration::days(31)), RefreshTokenState::Expired);
+
+        token.used_at = Some(now);
+        assert_eq!(token.state(now), RefreshTokenState::Reused);
+
+        // Revocation wins, a revoked family stays revoked
+        token.revoked_at = Some(now);
+        assert_eq!(token.state(now), RefreshTokenState::Revoked);
+    }
+
     #[test]
     fn test_permission_serialization() {
         let perm = Permission::ScenariosExecute;
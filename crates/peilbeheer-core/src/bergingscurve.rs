@@ -0,0 +1,312 @@
+//! Bergingscurven (stage-storage) van peilgebieden.
+//!
+//! Het bergend oppervlak van een peilgebied groeit met het peil: taluds
+//! lopen schuin op en bij hoge standen stroomt water de percelen op. Een
+//! [`BergingsCurve`] geeft bij een peil het open-waterareaal en het geborgen
+//! volume, uit een tabel peil→oppervlak of peil→volume.
+//!
+//! Tussen de punten van een oppervlaktabel verloopt het oppervlak lineair;
+//! bij een volumetabel is het oppervlak per traject constant. Buiten de
+//! tabel geldt het oppervlak van het eerste of laatste punt.
+
+use serde::{Deserialize, Serialize};
+
+/// Eén punt van een bergingscurve: een peil met het oppervlak of het
+/// volume daar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BergingsPunt {
+    /// Peil in m NAP
+    pub peil: f64,
+    /// Bergend oppervlak bij dit peil in m²
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oppervlak: Option<f64>,
+    /// Geborgen volume bij dit peil in m³, vanaf een willekeurig nulpunt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+}
+
+impl BergingsPunt {
+    pub fn oppervlak(peil: f64, oppervlak: f64) -> Self {
+        Self {
+            peil,
+            oppervlak: Some(oppervlak),
+            volume: None,
+        }
+    }
+
+    pub fn volume(peil: f64, volume: f64) -> Self {
+        Self {
+            peil,
+            oppervlak: None,
+            volume: Some(volume),
+        }
+    }
+}
+
+/// Bergingscurve: punten oplopend in peil, allemaal met een oppervlak of
+/// allemaal met een volume.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BergingsCurve {
+    pub punten: Vec<BergingsPunt>,
+}
+
+/// Traject tussen twee punten met lineair verlopend oppervlak.
+#[derive(Clone)]
+struct Traject {
+    peil: f64,
+    lengte: f64,
+    volume: f64,
+    /// Oppervlak aan het begin en eind van het traject
+    van: f64,
+    tot: f64,
+}
+
+impl Traject {
+    fn volume_bij(&self, d: f64) -> f64 {
+        self.volume + self.van * d + (self.tot - self.van) / (2.0 * self.lengte) * d * d
+    }
+
+    fn oppervlak_bij(&self, d: f64) -> f64 {
+        self.van + (self.tot - self.van) * d / self.lengte
+    }
+
+    /// Hoogte boven het begin van het traject waarop `volume` is geborgen.
+    fn hoogte_bij(&self, volume: f64) -> f64 {
+        let extra = volume - self.volume;
+        let k = (self.tot - self.van) / (2.0 * self.lengte);
+        if k.abs() < 1e-12 {
+            extra / self.van
+        } else {
+            (-self.van + (self.van * self.van + 4.0 * k * extra).max(0.0).sqrt()) / (2.0 * k)
+        }
+    }
+}
+
+impl BergingsCurve {
+    /// Curve uit punten; de punten worden gesorteerd op peil.
+    pub fn new(mut punten: Vec<BergingsPunt>) -> Result<Self, String> {
+        punten.sort_by(|a, b| a.peil.total_cmp(&b.peil));
+        let curve = Self { punten };
+        curve.valideer()?;
+        Ok(curve)
+    }
+
+    /// Controleer of de curve bruikbaar is.
+    pub fn valideer(&self) -> Result<(), String> {
+        if self.punten.len() < 2 {
+            return Err("Bergingscurve heeft minstens twee punten nodig".into());
+        }
+        let met_oppervlak = self.punten[0].oppervlak.is_some();
+        for punt in &self.punten {
+            let waarde = if met_oppervlak {
+                punt.oppervlak
+            } else {
+                punt.volume
+            };
+            match waarde {
+                Some(w) if w.is_finite() && punt.peil.is_finite() => {}
+                _ => {
+                    return Err(format!(
+                        "Bergingscurvepunt bij {} m heeft geen geldig {}",
+                        punt.peil,
+                        if met_oppervlak { "oppervlak" } else { "volume" }
+                    ));
+                }
+            }
+            if met_oppervlak && punt.oppervlak.is_some_and(|o| o <= 0.0) {
+                return Err(format!(
+                    "Bergend oppervlak bij {} m moet > 0 zijn",
+                    punt.peil
+                ));
+            }
+        }
+        for paar in self.punten.windows(2) {
+            if paar[1].peil <= paar[0].peil {
+                return Err("Peilen van de bergingscurve moeten oplopen".into());
+            }
+            if !met_oppervlak && paar[1].volume <= paar[0].volume {
+                return Err("Volumes van de bergingscurve moeten oplopen".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Geborgen volume in m³ bij een peil, ten opzichte van het eerste punt.
+    pub fn volume(&self, peil: f64) -> f64 {
+        let trajecten = self.trajecten();
+        let (traject, d) = Self::zoek(&trajecten, peil);
+        traject.volume_bij(d)
+    }
+
+    /// Bergend oppervlak in m² bij een peil.
+    pub fn oppervlak(&self, peil: f64) -> f64 {
+        let trajecten = self.trajecten();
+        let (traject, d) = Self::zoek(&trajecten, peil);
+        traject.oppervlak_bij(d.clamp(0.0, traject.lengte))
+    }
+
+    /// Peil in m NAP waarbij `volume` (ten opzichte van het eerste punt)
+    /// is geborgen.
+    pub fn peil(&self, volume: f64) -> f64 {
+        let trajecten = self.trajecten();
+        let eerste = &trajecten[0];
+        let laatste = &trajecten[trajecten.len() - 1];
+        if volume < eerste.volume {
+            return eerste.peil + (volume - eerste.volume) / eerste.van;
+        }
+        let eind = laatste.volume_bij(laatste.lengte);
+        if volume > eind {
+            return laatste.peil + laatste.lengte + (volume - eind) / laatste.tot;
+        }
+        let traject = trajecten
+            .iter()
+            .rev()
+            .find(|t| volume >= t.volume)
+            .unwrap_or(eerste);
+        traject.peil + traject.hoogte_bij(volume).clamp(0.0, traject.lengte)
+    }
+
+    /// Peil na het bergen van `volume_verandering` m³ vanaf `peil`.
+    pub fn peil_na(&self, peil: f64, volume_verandering: f64) -> f64 {
+        self.peil(self.volume(peil) + volume_verandering)
+    }
+
+    fn trajecten(&self) -> Vec<Traject> {
+        let mut volume = 0.0;
+        self.punten
+            .windows(2)
+            .map(|paar| {
+                let lengte = paar[1].peil - paar[0].peil;
+                let (van, tot) = match (paar[0].oppervlak, paar[1].oppervlak) {
+                    (Some(van), Some(tot)) => (van, tot),
+                    _ => {
+                        let oppervlak = (paar[1].volume.unwrap_or(0.0)
+                            - paar[0].volume.unwrap_or(0.0))
+                            / lengte;
+                        (oppervlak, oppervlak)
+                    }
+                };
+                let traject = Traject {
+                    peil: paar[0].peil,
+                    lengte,
+                    volume,
+                    van,
+                    tot,
+                };
+                volume = traject.volume_bij(lengte);
+                traject
+            })
+            .collect()
+    }
+
+    /// Traject van een peil en de hoogte erboven. Onder het eerste en
+    /// boven het laatste traject geldt een constant oppervlak.
+    fn zoek(trajecten: &[Traject], peil: f64) -> (Traject, f64) {
+        let eerste = &trajecten[0];
+        let laatste = &trajecten[trajecten.len() - 1];
+        if peil < eerste.peil {
+            return (
+                Self::constant(eerste.peil, eerste.volume, eerste.van),
+                peil - eerste.peil,
+            );
+        }
+        let eind = laatste.peil + laatste.lengte;
+        if peil > eind {
+            let volume = laatste.volume_bij(laatste.lengte);
+            return (Self::constant(eind, volume, laatste.tot), peil - eind);
+        }
+        let traject = trajecten
+            .iter()
+            .rev()
+            .find(|t| peil >= t.peil)
+            .unwrap_or(eerste);
+        (traject.clone(), peil - traject.peil)
+    }
+
+    fn constant(peil: f64, volume: f64, oppervlak: f64) -> Traject {
+        Traject {
+            peil,
+            lengte: 1.0,
+            volume,
+            van: oppervlak,
+            tot: oppervlak,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn talud() -> BergingsCurve {
+        // Het open water verdubbelt tussen -1.0 en -0.5 m NAP
+        BergingsCurve::new(vec![
+            BergingsPunt::oppervlak(-0.5, 20_000.0),
+            BergingsPunt::oppervlak(-1.0, 10_000.0),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_oppervlaktabel() {
+        let curve = talud();
+        assert_eq!(curve.oppervlak(-0.75), 15_000.0);
+        assert_eq!(curve.oppervlak(-2.0), 10_000.0);
+        assert_eq!(curve.oppervlak(0.0), 20_000.0);
+
+        // Volume tussen de punten: gemiddeld oppervlak maal de hoogte
+        assert!((curve.volume(-0.5) - 7_500.0).abs() < 1e-9);
+        assert!((curve.volume(-0.75) - 3_125.0).abs() < 1e-9);
+        assert!((curve.volume(-1.1) + 1_000.0).abs() < 1e-9);
+        assert!((curve.volume(-0.4) - 9_500.0).abs() < 1e-9);
+
+        for peil in [-1.2, -0.9, -0.75, -0.6, -0.3] {
+            assert!((curve.peil(curve.volume(peil)) - peil).abs() < 1e-9);
+        }
+        // Hetzelfde volume geeft hoger in de curve een kleinere stijging
+        let laag = curve.peil_na(-1.0, 1_000.0) + 1.0;
+        let hoog = curve.peil_na(-0.6, 1_000.0) + 0.6;
+        assert!(hoog < laag);
+    }
+
+    #[test]
+    fn test_volumetabel() {
+        let curve = BergingsCurve::new(vec![
+            BergingsPunt::volume(-1.0, 0.0),
+            BergingsPunt::volume(-0.8, 2_000.0),
+            BergingsPunt::volume(-0.6, 6_000.0),
+        ])
+        .unwrap();
+        assert!((curve.oppervlak(-0.9) - 10_000.0).abs() < 1e-6);
+        assert!((curve.oppervlak(-0.7) - 20_000.0).abs() < 1e-6);
+        assert!((curve.volume(-0.7) - 4_000.0).abs() < 1e-9);
+        assert!((curve.peil(4_000.0) + 0.7).abs() < 1e-9);
+        assert!((curve.peil(8_000.0) + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_valideer() {
+        assert!(BergingsCurve::new(vec![BergingsPunt::oppervlak(0.0, 1.0)]).is_err());
+        assert!(
+            BergingsCurve::new(vec![
+                BergingsPunt::oppervlak(0.0, 1.0),
+                BergingsPunt::volume(1.0, 1.0)
+            ])
+            .is_err()
+        );
+        assert!(
+            BergingsCurve::new(vec![
+                BergingsPunt::volume(0.0, 2.0),
+                BergingsPunt::volume(1.0, 1.0)
+            ])
+            .is_err()
+        );
+        assert!(
+            BergingsCurve::new(vec![
+                BergingsPunt::oppervlak(0.0, 0.0),
+                BergingsPunt::oppervlak(1.0, 1.0)
+            ])
+            .is_err()
+        );
+    }
+}
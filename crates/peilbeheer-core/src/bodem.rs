@@ -0,0 +1,234 @@
+//! Bodemtype en standaard infiltratie/kwel per peilgebied.
+//!
+//! De BRO Bodemkaart 1:50.000 codeert de bodem per vlak met een
+//! bodemcode (bijv. `hVb`, `Mn25A`, `pZg23`). De hoofdletter bepaalt de
+//! hoofdgroep: veen, moerig, klei, zand of leem. Per hoofdgroep gelden
+//! standaardwaarden voor wegzijging (infiltratie) en kwel.
+//!
+//! Een peilgebied bestaat meestal uit meerdere bodemvlakken.
+//! [`PeilgebiedBodem::uit_aandelen`] bepaalt het dominante bodemtype en
+//! weegt de parameters naar oppervlakteaandeel.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Hoofdgroep van de bodem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bodemtype {
+    Veen,
+    /// Moerige gronden: dunne veenlaag op zand of klei
+    Moerig,
+    Klei,
+    Zand,
+    Leem,
+    /// Bebouwing, water of een onbekende code
+    Onbekend,
+}
+
+impl Bodemtype {
+    /// Hoofdgroep van een bodemcode van de Bodemkaart.
+    ///
+    /// Kleine letters vooraf zijn toevoegingen (bijv. `h` in `hVb`); de
+    /// eerste hoofdletter is de hoofdgroep. Codes zonder bodem zoals
+    /// `WATER` of `BEBOUW` zijn onbekend.
+    pub fn from_bodemcode(code: &str) -> Self {
+        let code = code.trim();
+        if code.len() > 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+            return Self::Onbekend;
+        }
+        match code.chars().find(|c| c.is_ascii_uppercase()) {
+            Some('V') => Self::Veen,
+            Some('W') => Self::Moerig,
+            Some('M' | 'R' | 'K') => Self::Klei,
+            Some('Z' | 'H' | 'Y' | 'E') => Self::Zand,
+            Some('L') => Self::Leem,
+            _ => Self::Onbekend,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Veen => "veen",
+            Self::Moerig => "moerig",
+            Self::Klei => "klei",
+            Self::Zand => "zand",
+            Self::Leem => "leem",
+            Self::Onbekend => "onbekend",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "veen" => Some(Self::Veen),
+            "moerig" => Some(Self::Moerig),
+            "klei" => Some(Self::Klei),
+            "zand" => Some(Self::Zand),
+            "leem" => Some(Self::Leem),
+            "onbekend" => Some(Self::Onbekend),
+            _ => None,
+        }
+    }
+
+    /// Standaard infiltratie en kwel van de hoofdgroep.
+    ///
+    /// Indicatieve waarden voor polders in het laagveen- en
+    /// kleigebied; lokale metingen gaan voor.
+    pub fn standaard_parameters(&self) -> BodemParameters {
+        // mm/dag
+        let (infiltratie, kwel) = match self {
+            Self::Veen => (0.3, 0.3),
+            Self::Moerig => (0.4, 0.2),
+            Self::Klei => (0.2, 0.1),
+            Self::Zand => (1.0, 0.0),
+            Self::Leem => (0.5, 0.0),
+            Self::Onbekend => (0.3, 0.0),
+        };
+        BodemParameters {
+            infiltratie: infiltratie / 24.0,
+            kwel: kwel / 24.0,
+        }
+    }
+}
+
+/// Infiltratie (wegzijging) en kwel in mm/uur.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BodemParameters {
+    pub infiltratie: f64,
+    pub kwel: f64,
+}
+
+impl BodemParameters {
+    /// Netto infiltratie in mm/uur; negatief bij netto kwel.
+    ///
+    /// Dit is de `infiltratie` van simulatie en optimalisatie.
+    pub fn netto_infiltratie(&self) -> f64 {
+        self.infiltratie - self.kwel
+    }
+}
+
+/// Bodem van een peilgebied met de afgeleide standaardparameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedBodem {
+    pub peilgebied_code: String,
+    /// Bodemtype met het grootste aandeel
+    pub bodemtype: Bodemtype,
+    /// Oppervlakteaandeel per bodemtype (0-1)
+    pub aandelen: BTreeMap<Bodemtype, f64>,
+    /// Naar oppervlakte gewogen parameters
+    pub parameters: BodemParameters,
+    /// Bron, bijv. `bro-bodemkaart`
+    pub bron: String,
+    pub bepaald_op: DateTime<Utc>,
+}
+
+impl PeilgebiedBodem {
+    /// Bodem uit de oppervlakte per bodemtype.
+    ///
+    /// Onbekende vlakken tellen niet mee zolang er bekende zijn. Zonder
+    /// oppervlakte is de bodem onbekend.
+    pub fn uit_aandelen(
+        peilgebied_code: impl Into<String>,
+        oppervlakten: &BTreeMap<Bodemtype, f64>,
+        bron: impl Into<String>,
+    ) -> Self {
+        let bekend: BTreeMap<Bodemtype, f64> = oppervlakten
+            .iter()
+            .filter(|(t, a)| **t != Bodemtype::Onbekend && **a > 0.0)
+            .map(|(t, a)| (*t, *a))
+            .collect();
+        let meetellend = if bekend.is_empty() {
+            oppervlakten
+                .iter()
+                .filter(|(_, a)| **a > 0.0)
+                .map(|(t, a)| (*t, *a))
+                .collect()
+        } else {
+            bekend
+        };
+        let totaal: f64 = meetellend.values().sum();
+
+        let (aandelen, bodemtype, parameters) = if totaal > 0.0 {
+            let aandelen: BTreeMap<Bodemtype, f64> =
+                meetellend.iter().map(|(t, a)| (*t, a / totaal)).collect();
+            let bodemtype = aandelen
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(t, _)| *t)
+                .unwrap_or(Bodemtype::Onbekend);
+            let parameters = aandelen.iter().fold(
+                BodemParameters {
+                    infiltratie: 0.0,
+                    kwel: 0.0,
+                },
+                |som, (t, aandeel)| {
+                    let p = t.standaard_parameters();
+                    BodemParameters {
+                        infiltratie: som.infiltratie + aandeel * p.infiltratie,
+                        kwel: som.kwel + aandeel * p.kwel,
+                    }
+                },
+            );
+            (aandelen, bodemtype, parameters)
+        } else {
+            (
+                BTreeMap::new(),
+                Bodemtype::Onbekend,
+                Bodemtype::Onbekend.standaard_parameters(),
+            )
+        };
+
+        Self {
+            peilgebied_code: peilgebied_code.into(),
+            bodemtype,
+            aandelen,
+            parameters,
+            bron: bron.into(),
+            bepaald_op: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bodemcode() {
+        assert_eq!(Bodemtype::from_bodemcode("hVb"), Bodemtype::Veen);
+        assert_eq!(Bodemtype::from_bodemcode("Vc"), Bodemtype::Veen);
+        assert_eq!(Bodemtype::from_bodemcode("kWp"), Bodemtype::Moerig);
+        assert_eq!(Bodemtype::from_bodemcode("Mn25A"), Bodemtype::Klei);
+        assert_eq!(Bodemtype::from_bodemcode("pZg23"), Bodemtype::Zand);
+        assert_eq!(Bodemtype::from_bodemcode("Hn21"), Bodemtype::Zand);
+        assert_eq!(Bodemtype::from_bodemcode("WATER"), Bodemtype::Onbekend);
+        assert_eq!(Bodemtype::from_bodemcode("BEBOUW"), Bodemtype::Onbekend);
+        assert_eq!(Bodemtype::from_bodemcode(""), Bodemtype::Onbekend);
+    }
+
+    #[test]
+    fn test_uit_aandelen() {
+        let oppervlakten = BTreeMap::from([
+            (Bodemtype::Veen, 3.0),
+            (Bodemtype::Klei, 1.0),
+            (Bodemtype::Onbekend, 6.0),
+        ]);
+        let bodem = PeilgebiedBodem::uit_aandelen("PG_001", &oppervlakten, "test");
+
+        // Onbekende vlakken tellen niet mee
+        assert_eq!(bodem.bodemtype, Bodemtype::Veen);
+        assert_eq!(bodem.aandelen[&Bodemtype::Veen], 0.75);
+        assert!(!bodem.aandelen.contains_key(&Bodemtype::Onbekend));
+
+        let veen = Bodemtype::Veen.standaard_parameters();
+        let klei = Bodemtype::Klei.standaard_parameters();
+        let verwacht = 0.75 * veen.infiltratie + 0.25 * klei.infiltratie;
+        assert!((bodem.parameters.infiltratie - verwacht).abs() < 1e-12);
+
+        let leeg = PeilgebiedBodem::uit_aandelen("PG_002", &BTreeMap::new(), "test");
+        assert_eq!(leeg.bodemtype, Bodemtype::Onbekend);
+        assert_eq!(leeg.parameters, Bodemtype::Onbekend.standaard_parameters());
+    }
+}
@@ -37,6 +37,8 @@ use thiserror::Error;
 
 use std::time::Duration;
 
+use crate::timeseries::{QualityFlag, TimeSeriesDataPoint, TimeSeriesId, TimeSeriesWriteBatch};
+
 /// DHYdro API client configuration.
 ///
 /// Note: D-HYDRO is Deltares' hydraulic modeling software suite.
@@ -163,6 +165,32 @@ pub struct DhydroModel {
     pub is_active: bool,
     #[serde(default)]
     pub parameters: serde_json::Value,
+    /// Model version (schematisation release)
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Start of the period the model may be used for
+    #[serde(default)]
+    pub valid_from: Option<DateTime<Utc>>,
+    /// End of the period the model may be used for
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl DhydroModel {
+    /// Whether the model is active and valid at the given time.
+    pub fn is_available_at(&self, at: DateTime<Utc>) -> bool {
+        self.is_active
+            && self.valid_from.is_none_or(|from| from <= at)
+            && self.valid_until.is_none_or(|until| at < until)
+    }
+}
+
+/// Cached catalog of D-Hydro models.
+#[derive(Debug, Clone, Serialize)]
+pub struct DhydroModelCatalog {
+    pub models: Vec<DhydroModel>,
+    /// Last time the catalog was fetched from D-Hydro
+    pub refreshed_at: Option<DateTime<Utc>>,
 }
 
 /// Time series data point.
@@ -187,6 +215,40 @@ pub struct TimeSeries {
     pub data: Vec<TimeSeriesPoint>,
 }
 
+impl TimeSeries {
+    /// Id of the series in the time series store.
+    ///
+    /// Model output is kept apart from measurements at the same location by
+    /// a `scenario:<id>` qualifier, followed by the D-Hydro qualifier if any.
+    pub fn series_id(&self, scenario_id: &str) -> TimeSeriesId {
+        let qualifier = match &self.qualifier {
+            Some(qualifier) => format!("scenario:{}:{}", scenario_id, qualifier),
+            None => format!("scenario:{}", scenario_id),
+        };
+        TimeSeriesId::with_qualifier(&self.location_id, &self.parameter, qualifier)
+    }
+
+    /// Write batch of the output of a scenario; unknown flags count as good.
+    pub fn to_write_batch(&self, scenario_id: &str) -> TimeSeriesWriteBatch {
+        TimeSeriesWriteBatch {
+            series_id: self.series_id(scenario_id),
+            data: self
+                .data
+                .iter()
+                .map(|point| {
+                    let flag = point
+                        .flag
+                        .as_deref()
+                        .and_then(QualityFlag::from_str)
+                        .unwrap_or(QualityFlag::Good);
+                    TimeSeriesDataPoint::with_flag(point.timestamp, point.value, flag)
+                })
+                .collect(),
+            attributes: None,
+        }
+    }
+}
+
 /// Time series query parameters.
 #[derive(Debug, Clone, Serialize)]
 pub struct TimeSeriesQuery {
@@ -517,11 +579,7 @@ impl DhydroClient {
         &mut self,
         model_id: Option<&str>,
     ) -> Result<Vec<Scenario>, DhydroError> {
-        let params = if let Some(mid) = model_id {
-            Some(vec![("model_id", mid.to_string())])
-        } else {
-            None
-        };
+        let params = model_id.map(|mid| vec![("model_id", mid.to_string())]);
 
         self.request(
             reqwest::Method::GET,
@@ -615,6 +673,55 @@ impl DhydroClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_model_availability() {
+        let now = Utc::now();
+        let mut model: DhydroModel = serde_json::from_str(
+            r#"{"id": "M1", "name": "Rijnland", "model_type": "1D2D", "is_active": true}"#,
+        )
+        .unwrap();
+        assert!(model.is_available_at(now));
+
+        model.valid_from = Some(now - chrono::Duration::days(1));
+        model.valid_until = Some(now);
+        assert!(model.is_available_at(now - chrono::Duration::hours(1)));
+        assert!(!model.is_available_at(now));
+
+        model.valid_until = None;
+        model.is_active = false;
+        assert!(!model.is_available_at(now));
+    }
+
+    #[test]
+    fn test_time_series_to_write_batch() {
+        let timestamp = Utc::now();
+        let mut series = TimeSeries {
+            id: "TS_1".to_string(),
+            name: "Waterstand".to_string(),
+            parameter: "water_level".to_string(),
+            unit: "m NAP".to_string(),
+            location_id: "PG_001".to_string(),
+            qualifier: None,
+            data: vec![
+                TimeSeriesPoint { timestamp, value: -0.6, flag: None },
+                TimeSeriesPoint { timestamp, value: -0.5, flag: Some("questionable".to_string()) },
+            ],
+        };
+
+        let batch = series.to_write_batch("SCN_1");
+        assert_eq!(batch.series_id.location_id, "PG_001");
+        assert_eq!(batch.series_id.qualifier.as_deref(), Some("scenario:SCN_1"));
+        assert_eq!(batch.data.len(), 2);
+        assert_eq!(batch.data[0].flag, QualityFlag::Good);
+        assert_eq!(batch.data[1].flag, QualityFlag::Questionable);
+
+        series.qualifier = Some("max".to_string());
+        assert_eq!(
+            series.series_id("SCN_1").qualifier.as_deref(),
+            Some("scenario:SCN_1:max")
+        );
+    }
+
     #[test]
     fn test_time_series_query_serialization() {
         let query = TimeSeriesQuery {
@@ -3,15 +3,153 @@
 //! This module provides types for pump scheduling optimization
 //! based on energy prices and water balance constraints.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono_tz::Europe::Amsterdam;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// Stroomprijs voor één uur.
+use crate::energiecontract::EnergieContract;
+use crate::pompcurve::PompCurve;
+use crate::pompen::Pomp;
+
+/// Stroomprijs voor één uur, of voor één kwartier van dat uur.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct UurPrijs {
     pub uur: u8,
     pub prijs_eur_kwh: f64,
+    /// Kwartier binnen het uur (0-3); None voor een uurprijs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kwartier: Option<u8>,
+}
+
+/// Aantal kwartieren per dag.
+pub const KWARTIEREN_PER_DAG: usize = 96;
+
+/// CO2-emissiefactor van netstroom voor uren zonder opgegeven factor, in
+/// kg/kWh; ongeveer het gemiddelde van de Nederlandse stroommix.
+pub const STANDAARD_EMISSIEFACTOR: f64 = 0.30;
+
+/// Kortste en langste optimalisatiehorizon in uren.
+pub const MIN_HORIZON_UREN: usize = 24;
+pub const MAX_HORIZON_UREN: usize = 168;
+
+impl UurPrijs {
+    /// Prijs voor een heel uur.
+    pub fn uur(uur: u8, prijs_eur_kwh: f64) -> Self {
+        Self { uur, prijs_eur_kwh, kwartier: None }
+    }
+
+    /// Prijs voor één kwartier van een uur.
+    pub fn kwartier(uur: u8, kwartier: u8, prijs_eur_kwh: f64) -> Self {
+        Self { uur, prijs_eur_kwh, kwartier: Some(kwartier) }
+    }
+}
+
+/// Of de prijzen kwartierprijzen bevatten.
+pub fn heeft_kwartierprijzen(prijzen: &[UurPrijs]) -> bool {
+    prijzen.iter().any(|p| p.kwartier.is_some())
+}
+
+/// Prijs per uur over `uren` uur.
+///
+/// Uren zonder prijs krijgen de prijs van een dag eerder, zodat een
+/// meerdaagse horizon na de bekende day-ahead prijzen het laatste
+/// dagprofiel herhaalt; daarvoor geldt `standaard`.
+pub fn prijs_per_uur(prijzen: &[UurPrijs], standaard: f64, uren: usize) -> Vec<f64> {
+    let mut per_uur = vec![None; uren];
+    for prijs in prijzen.iter().filter(|p| p.kwartier.is_none()) {
+        if let Some(p) = per_uur.get_mut(prijs.uur as usize) {
+            *p = Some(prijs.prijs_eur_kwh);
+        }
+    }
+    herhaal_vorige_dag(per_uur, 24, standaard)
+}
+
+/// Prijs per kwartier over `uren` uur.
+///
+/// Uurprijzen gelden voor alle vier kwartieren van hun uur; kwartierprijzen
+/// gaan daarvoor, zodat onbalans- of intradayprijzen de day-ahead prijs
+/// vervangen waar ze bekend zijn. Kwartieren zonder prijs krijgen de prijs
+/// van een dag eerder, en op de eerste dag `standaard`.
+pub fn prijs_per_kwartier(prijzen: &[UurPrijs], standaard: f64, uren: usize) -> Vec<f64> {
+    let mut per_kwartier = vec![None; uren * 4];
+    for prijs in prijzen.iter().filter(|p| p.kwartier.is_none()) {
+        let start = prijs.uur as usize * 4;
+        if let Some(kwartieren) = per_kwartier.get_mut(start..start + 4) {
+            kwartieren.fill(Some(prijs.prijs_eur_kwh));
+        }
+    }
+    for prijs in prijzen.iter() {
+        if let Some(kwartier) = prijs.kwartier.filter(|k| *k < 4)
+            && let Some(p) = per_kwartier.get_mut(prijs.uur as usize * 4 + kwartier as usize)
+        {
+            *p = Some(prijs.prijs_eur_kwh);
+        }
+    }
+    herhaal_vorige_dag(per_kwartier, KWARTIEREN_PER_DAG, standaard)
+}
+
+/// Vul ontbrekende prijzen aan met die van `per_dag` posities eerder.
+fn herhaal_vorige_dag(mut prijzen: Vec<Option<f64>>, per_dag: usize, standaard: f64) -> Vec<f64> {
+    for i in per_dag..prijzen.len() {
+        if prijzen[i].is_none() {
+            prijzen[i] = prijzen[i - per_dag];
+        }
+    }
+    prijzen.into_iter().map(|p| p.unwrap_or(standaard)).collect()
+}
+
+/// Prijssignaal waarop de optimalisatie stuurt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrijsSignaal {
+    /// Day-ahead uurprijzen (EPEX)
+    #[default]
+    DayAhead,
+    /// Onbalansprijzen per kwartier (TenneT), aangevuld met day-ahead
+    Onbalans,
+    /// Intraday kwartierprijzen, aangevuld met day-ahead
+    Intraday,
+}
+
+/// Congestie- of flexsignaal van de netbeheerder (GOPACS) voor één uur.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Flexsignaal {
+    /// Uur van de horizon
+    pub uur: u8,
+    /// Hoogste toegestane afname uit het net in dit uur (kW)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_vermogen_kw: Option<f64>,
+    /// Flexvergoeding per afgenomen kWh (€): positief als het net vraagt om
+    /// op te regelen, negatief als het vraagt om af te regelen, zodat elke
+    /// kWh minder afname de vergoeding oplevert
+    #[serde(default)]
+    pub vergoeding_eur_kwh: f64,
+}
+
+/// Methode waarmee het pompschema wordt geoptimaliseerd.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Optimizer {
+    /// Dynamisch programmeren over discrete pompfracties
+    #[default]
+    Dp,
+    /// Exacte MILP-formulering met aan/uit-beslissingen en minimale draaitijden
+    Milp,
+}
+
+/// Regeling van de pompen van een gemaal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pompregeling {
+    /// Vast toerental: een pompfractie is het deel van de tijd dat de pomp
+    /// vol draait, het vermogen schaalt lineair
+    #[default]
+    AanUit,
+    /// Frequentieregeling: een pompfractie is het toerental. Volgens de
+    /// affiniteitswetten schaalt het debiet lineair en het vermogen met de
+    /// derde macht van het toerental.
+    Toerengeregeld,
 }
 
 /// Parameters voor de energieoptimalisatie.
@@ -19,7 +157,9 @@ pub struct UurPrijs {
 pub struct OptimalisatieParams {
     /// Streefpeil in m NAP
     pub streefpeil: f64,
-    /// Maximaal gemaal debiet in m³/s
+    /// Maximaal gemaal debiet in m³/s (met een `pompcurve` volgt het
+    /// debiet uit de opvoerhoogte)
+    #[serde(default)]
     pub max_debiet: f64,
     /// Oppervlakte peilgebied in m²
     pub oppervlakte: f64,
@@ -32,14 +172,69 @@ pub struct OptimalisatieParams {
     /// Pompopvoerhoogte in m
     #[serde(default = "default_opvoerhoogte")]
     pub opvoerhoogte: f64,
+    /// Buitenwaterstand per uur in m NAP (leeg = vaste `opvoerhoogte`).
+    /// De opvoerhoogte is dan het verschil tussen buitenpeil en streefpeil.
+    #[serde(default)]
+    pub buitenpeil_per_uur: Vec<f64>,
+    /// RWS Waterinfo-locatie waarvan de API het buitenpeil ophaalt als
+    /// `buitenpeil_per_uur` leeg is
+    #[serde(default)]
+    pub buitenpeil_locatie: Option<String>,
     /// Pompefficiëntie (0-1)
     #[serde(default = "default_efficiency")]
     pub efficiency: f64,
-    /// Regenintensiteit per uur, 24 waarden in mm/uur
+    /// Pompkarakteristiek; debiet en rendement volgen dan uit de
+    /// opvoerhoogte van elk uur
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pompcurve: Option<PompCurve>,
+    /// Pompen van het gemaal met trapsgewijze inschakeling (leeg = één
+    /// pomp met `max_debiet` of `pompcurve`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen: Vec<Pomp>,
+    /// Regenintensiteit per uur in mm/uur, hooguit `horizon_uren` waarden
     pub regen_per_uur: Vec<f64>,
-    /// Stroomprijzen per uur, 24 entries (leeg = API fetcht ze)
+    /// Stroomprijzen per uur over de horizon (leeg = API fetcht ze). Bevat
+    /// de lijst kwartierprijzen, dan schakelt de optimalisatie per kwartier.
     #[serde(default)]
     pub prijzen: Vec<UurPrijs>,
+    /// Prijssignaal dat de API ophaalt als `prijzen` leeg is
+    #[serde(default)]
+    pub prijs_signaal: PrijsSignaal,
+    /// Verwachte zonnestroomopwek bij het gemaal per uur in kW (leeg = geen
+    /// opwek, of de API haalt de verwachting op voor `pv_installatie`)
+    #[serde(default)]
+    pub opwek_kw: Vec<f64>,
+    /// Zonnepanelen bij het gemaal
+    #[serde(default)]
+    pub pv_installatie: Option<PvInstallatie>,
+    /// Vergoeding voor teruggeleverde zonnestroom in €/kWh. Eigen verbruik
+    /// kost deze vergoeding in plaats van de stroomprijs.
+    #[serde(default)]
+    pub terugleververgoeding: f64,
+    /// CO2-emissiefactor van de netstroom per uur in kg/kWh (leeg =
+    /// `STANDAARD_EMISSIEFACTOR`). Zonnestroom voor eigen verbruik telt
+    /// zonder uitstoot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emissiefactor_per_uur: Vec<f64>,
+    /// Energiecontract van het gemaal; afname- en terugleverprijs volgen dan
+    /// uit het contract en de marktprijzen in `prijzen`, en een piek boven
+    /// het gecontracteerde vermogen kost een boete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<EnergieContract>,
+    /// Gemaal waarvan de API het energiecontract ophaalt als `contract`
+    /// leeg is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gemaal_code: Option<String>,
+    /// Begin van uur 0 van de horizon; de daluren van het contract gelden
+    /// in lokale tijd (Europe/Amsterdam). Zonder begint de horizon om
+    /// middernacht lokale tijd.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starttijd: Option<DateTime<Utc>>,
+    /// Congestie- en flexsignalen van de netbeheerder: een maximaal
+    /// vermogen is een harde grens voor de afname, een flexvergoeding een
+    /// opbrengst per afgenomen kWh
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flexsignalen: Vec<Flexsignaal>,
     /// Toegestane marge rond streefpeil in cm
     #[serde(default = "default_marge_cm")]
     pub marge_cm: f64,
@@ -48,6 +243,39 @@ pub struct OptimalisatieParams {
     /// in het open water. Typisch 0.05–0.15 voor agrarische polders.
     #[serde(default = "default_berging_factor")]
     pub berging_factor: f64,
+    /// Lengte van de optimalisatie in uren (24-168)
+    #[serde(default = "default_horizon_uren")]
+    pub horizon_uren: usize,
+    /// Uren die per venster van 24 uur worden vastgelegd voordat vanaf de
+    /// bereikte waterstand opnieuw wordt geoptimaliseerd (1-24)
+    #[serde(default = "default_herplan_uren")]
+    pub herplan_uren: usize,
+    /// Waterstand bij de start in m NAP (None = streefpeil)
+    #[serde(default)]
+    pub start_waterstand: Option<f64>,
+    /// Optimalisatiemethode
+    #[serde(default)]
+    pub optimizer: Optimizer,
+    /// Minimale draaitijd van het gemaal na inschakelen in minuten
+    #[serde(default)]
+    pub min_draaitijd_min: u32,
+    /// Minimale stilstand van het gemaal na uitschakelen in minuten
+    #[serde(default)]
+    pub min_uittijd_min: u32,
+    /// Kosten per inschakeling van het gemaal in € voor slijtage; weegt
+    /// in de optimalisatie mee naast de stroomkosten
+    #[serde(default)]
+    pub schakelkosten: f64,
+    /// Kleinste pompfractie als het gemaal draait, 0-1
+    #[serde(default)]
+    pub min_pompfractie: f64,
+    /// Aan/uit- of toerengeregelde pompen
+    #[serde(default)]
+    pub pompregeling: Pompregeling,
+    /// Laagste toerental (0-1) van toerengeregelde pompen; minder debiet
+    /// wordt gehaald door op dit toerental aan en uit te schakelen
+    #[serde(default)]
+    pub min_toerental: f64,
 }
 
 impl Default for OptimalisatieParams {
@@ -60,20 +288,188 @@ impl Default for OptimalisatieParams {
             infiltratie: default_infiltratie(),
             opvoerhoogte: default_opvoerhoogte(),
             efficiency: default_efficiency(),
+            pompcurve: None,
+            pompen: Vec::new(),
+            buitenpeil_per_uur: Vec::new(),
+            buitenpeil_locatie: None,
             regen_per_uur: vec![0.0; 24],
             prijzen: Vec::new(),
+            prijs_signaal: PrijsSignaal::default(),
+            opwek_kw: Vec::new(),
+            pv_installatie: None,
+            terugleververgoeding: 0.0,
+            emissiefactor_per_uur: Vec::new(),
+            contract: None,
+            gemaal_code: None,
+            starttijd: None,
+            flexsignalen: Vec::new(),
             marge_cm: default_marge_cm(),
             berging_factor: default_berging_factor(),
+            horizon_uren: default_horizon_uren(),
+            herplan_uren: default_herplan_uren(),
+            start_waterstand: None,
+            optimizer: Optimizer::default(),
+            min_draaitijd_min: 0,
+            min_uittijd_min: 0,
+            schakelkosten: 0.0,
+            min_pompfractie: 0.0,
+            pompregeling: Pompregeling::default(),
+            min_toerental: 0.0,
+        }
+    }
+}
+
+/// Kleinste opvoerhoogte bij een buitenpeil onder het streefpeil (m).
+pub const MIN_OPVOERHOOGTE: f64 = 0.10;
+
+impl OptimalisatieParams {
+    /// Opvoerhoogte tijdens een uur: buitenpeil min streefpeil als het
+    /// buitenpeil bekend is (het laatste bekende uur geldt daarna), anders
+    /// de vaste `opvoerhoogte`.
+    pub fn opvoerhoogte(&self, uur: usize) -> f64 {
+        match self.buitenpeil_per_uur.get(uur).or(self.buitenpeil_per_uur.last()) {
+            Some(buitenpeil) => (buitenpeil - self.streefpeil).max(MIN_OPVOERHOOGTE),
+            None => self.opvoerhoogte,
+        }
+    }
+
+    /// Debiet bij volle inzet tijdens een uur (m³/s): de som van de pompen,
+    /// of uit de pompcurve bij de opvoerhoogte van dat uur, anders
+    /// `max_debiet`.
+    pub fn pompdebiet(&self, uur: usize) -> f64 {
+        let opvoerhoogte = self.opvoerhoogte(uur);
+        if !self.pompen.is_empty() {
+            return self.pompen.iter().map(|p| p.debiet_bij(opvoerhoogte)).sum();
+        }
+        match &self.pompcurve {
+            Some(curve) => curve.debiet(opvoerhoogte),
+            None => self.max_debiet,
+        }
+    }
+
+    /// Vermogen van een pomp bij `inzet` (0-1) als deel van zijn vermogen
+    /// bij volle inzet.
+    ///
+    /// Aan/uit-pompen draaien een deel van de tijd vol. Toerengeregelde
+    /// pompen draaien op toerental `inzet` met vermogen `inzet³`; onder
+    /// `min_toerental` draaien ze een deel van de tijd op dat toerental.
+    pub fn vermogensfactor(&self, inzet: f64) -> f64 {
+        let inzet = inzet.clamp(0.0, 1.0);
+        match self.pompregeling {
+            Pompregeling::AanUit => inzet,
+            Pompregeling::Toerengeregeld if inzet < self.min_toerental => {
+                inzet * self.min_toerental.powi(2)
+            }
+            Pompregeling::Toerengeregeld => inzet.powi(3),
+        }
+    }
+
+    /// Emissiefactor per uur over `uren` uur (kg CO2/kWh). Net als bij de
+    /// prijzen herhalen uren na de opgegeven factoren het vorige etmaal.
+    pub fn emissiefactoren(&self, uren: usize) -> Vec<f64> {
+        let mut per_uur = vec![None; uren];
+        for (factor, uur) in self.emissiefactor_per_uur.iter().zip(per_uur.iter_mut()) {
+            *uur = Some(*factor);
+        }
+        herhaal_vorige_dag(per_uur, 24, STANDAARD_EMISSIEFACTOR)
+    }
+
+    /// Lokaal uur van de dag (0-23) van uur `uur` van de horizon.
+    pub fn uur_van_dag(&self, uur: usize) -> usize {
+        match self.starttijd {
+            Some(start) => {
+                (start + Duration::hours(uur as i64)).with_timezone(&Amsterdam).hour() as usize
+            }
+            None => uur % 24,
+        }
+    }
+
+    /// Hoogste toegestane afname uit het net tijdens een uur (kW); bij
+    /// meerdere signalen voor hetzelfde uur geldt de laagste.
+    pub fn max_afname_kw(&self, uur: usize) -> Option<f64> {
+        self.flexsignalen
+            .iter()
+            .filter(|s| s.uur as usize == uur)
+            .filter_map(|s| s.max_vermogen_kw)
+            .reduce(f64::min)
+    }
+
+    /// Flexvergoeding per afgenomen kWh tijdens een uur (€/kWh).
+    pub fn flexvergoeding(&self, uur: usize) -> f64 {
+        self.flexsignalen
+            .iter()
+            .filter(|s| s.uur as usize == uur)
+            .map(|s| s.vergoeding_eur_kwh)
+            .sum()
+    }
+
+    /// Rendement tijdens een uur: uit de pompcurve als die rendementen
+    /// bevat, anders `efficiency`.
+    pub fn rendement(&self, uur: usize) -> f64 {
+        self.pompcurve
+            .as_ref()
+            .and_then(|curve| curve.rendement(self.opvoerhoogte(uur)))
+            .unwrap_or(self.efficiency)
+    }
+}
+
+/// Zonnepanelen bij een gemaal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PvInstallatie {
+    /// Breedtegraad (WGS84)
+    pub lat: f64,
+    /// Lengtegraad (WGS84)
+    pub lon: f64,
+    /// Hellingshoek van de panelen in graden (0 = plat)
+    #[serde(default = "default_helling")]
+    pub helling: f64,
+    /// Oriëntatie in graden vanaf het zuiden (-90 = oost, 90 = west)
+    #[serde(default)]
+    pub azimut: f64,
+    /// Piekvermogen in kWp
+    pub vermogen_kwp: f64,
+}
+
+/// Stroomkosten van één uur pompen met zonnestroom erbij.
+///
+/// Het verbruik wordt eerst gedekt door de opwek; dat eigen verbruik kost
+/// de gemiste terugleververgoeding, de rest wordt afgenomen tegen `prijs`.
+/// Retourneert (kosten in €, eigen verbruik in kWh), beide per uur.
+pub fn stroomkosten(verbruik_kw: f64, opwek_kw: f64, prijs: f64, vergoeding: f64) -> (f64, f64) {
+    let eigen = verbruik_kw.min(opwek_kw.max(0.0));
+    ((verbruik_kw - eigen) * prijs + eigen * vergoeding, eigen)
+}
+
+/// Gemiddeld opgewekt vermogen per uur (kW) uit een PV-verwachting in watt.
+///
+/// Waarden worden gemiddeld per uur vanaf `start`; uren zonder waarden
+/// (nacht) krijgen 0.0.
+pub fn opwek_per_uur(watts: &[(DateTime<Utc>, f64)], start: DateTime<Utc>, uren: usize) -> Vec<f64> {
+    let mut som = vec![(0.0, 0usize); uren];
+    for (tijd, watt) in watts {
+        let minuten = (*tijd - start).num_minutes();
+        if minuten < 0 {
+            continue;
+        }
+        if let Some(uur) = som.get_mut((minuten / 60) as usize) {
+            uur.0 += watt / 1000.0;
+            uur.1 += 1;
         }
     }
+    som.into_iter()
+        .map(|(kw, n)| if n > 0 { kw / n as f64 } else { 0.0 })
+        .collect()
 }
 
+fn default_helling() -> f64 { 30.0 }
 fn default_verdamping() -> f64 { 0.5 }
 fn default_infiltratie() -> f64 { 0.2 }
 fn default_opvoerhoogte() -> f64 { 2.0 }
 fn default_efficiency() -> f64 { 0.70 }
 fn default_marge_cm() -> f64 { 20.0 }
 fn default_berging_factor() -> f64 { 0.10 }
+fn default_horizon_uren() -> usize { MIN_HORIZON_UREN }
+fn default_herplan_uren() -> usize { 24 }
 
 /// Resultaat per uur van de optimalisatie.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -87,6 +483,41 @@ pub struct OptimalisatieUurResultaat {
     pub waterstand_eind_naief: f64,
     pub kosten_optimaal: f64,
     pub kosten_naief: f64,
+    /// Stroomverbruik van de pompen in kWh
+    #[serde(default)]
+    pub energie_optimaal_kwh: f64,
+    #[serde(default)]
+    pub energie_naief_kwh: f64,
+    /// CO2-emissiefactor van de netstroom in dit uur (kg/kWh)
+    #[serde(default)]
+    pub emissiefactor_kg_kwh: f64,
+    /// CO2-uitstoot van de uit het net afgenomen stroom in kg
+    #[serde(default)]
+    pub co2_optimaal_kg: f64,
+    #[serde(default)]
+    pub co2_naief_kg: f64,
+    /// Inzet per pomp (0-1) in de volgorde van `OptimalisatieResultaat::pompen`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen_optimaal: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen_naief: Vec<f64>,
+}
+
+/// Resultaat per kwartier van een optimalisatie op kwartierprijzen.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptimalisatieKwartierResultaat {
+    pub uur: u8,
+    pub kwartier: u8,
+    pub prijs_eur_kwh: f64,
+    pub pomp_fractie_optimaal: f64,
+    pub pomp_fractie_naief: f64,
+    pub kosten_optimaal: f64,
+    pub kosten_naief: f64,
+    /// Inzet per pomp (0-1) in de volgorde van `OptimalisatieResultaat::pompen`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen_optimaal: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen_naief: Vec<f64>,
 }
 
 /// Totaalresultaat van de optimalisatie.
@@ -102,6 +533,53 @@ pub struct OptimalisatieResultaat {
     pub tijdstappen_optimaal: Vec<SimulatieStapUitgebreid>,
     pub tijdstappen_naief: Vec<SimulatieStapUitgebreid>,
     pub prijzen: Vec<UurPrijs>,
+    /// Pompverbruik gedekt door eigen zonnestroom (kWh)
+    #[serde(default)]
+    pub eigen_verbruik_optimaal_kwh: f64,
+    #[serde(default)]
+    pub eigen_verbruik_naief_kwh: f64,
+    /// Stroomverbruik van de pompen over de horizon (kWh)
+    #[serde(default)]
+    pub totale_energie_optimaal_kwh: f64,
+    #[serde(default)]
+    pub totale_energie_naief_kwh: f64,
+    /// CO2-uitstoot van de netstroom over de horizon (kg)
+    #[serde(default)]
+    pub totale_co2_optimaal_kg: f64,
+    #[serde(default)]
+    pub totale_co2_naief_kg: f64,
+    /// Aantal keer dat het gemaal inschakelt
+    #[serde(default)]
+    pub inschakelingen_optimaal: usize,
+    #[serde(default)]
+    pub inschakelingen_naief: usize,
+    /// Hoogste afname uit het net (kW)
+    #[serde(default)]
+    pub piekvermogen_optimaal_kw: f64,
+    #[serde(default)]
+    pub piekvermogen_naief_kw: f64,
+    /// Boete voor afname boven het gecontracteerde vermogen (€); zit ook in
+    /// de totale kosten
+    #[serde(default)]
+    pub piekboete_optimaal_eur: f64,
+    #[serde(default)]
+    pub piekboete_naief_eur: f64,
+    /// Flexvergoeding van de netbeheerder (€); zit als opbrengst in de
+    /// totale kosten
+    #[serde(default)]
+    pub flexvergoeding_optimaal_eur: f64,
+    #[serde(default)]
+    pub flexvergoeding_naief_eur: f64,
+    /// Schema per kwartier; leeg als er op uurprijzen is geoptimaliseerd
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kwartieren: Vec<OptimalisatieKwartierResultaat>,
+    /// Pompen in inschakelvolgorde; leeg bij een gemaal zonder pompen
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pompen: Vec<String>,
+    /// Optimale kosten met dezelfde pompen zonder toerenregeling; alleen
+    /// bij toerengeregelde pompen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totale_kosten_aan_uit: Option<f64>,
 }
 
 /// Uitgebreide simulatiestap (per minuut) met kostinformatie.
@@ -203,6 +681,10 @@ pub enum PriceSource {
     EPEX,
     NordPool,
     Manual,
+    /// TenneT imbalance settlement prices
+    Imbalance,
+    /// Intraday market prices
+    Intraday,
 }
 
 /// Pump schedule from optimization.
@@ -406,6 +888,7 @@ impl PumpSchedule {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pompcurve::PompPunt;
 
     #[test]
     fn test_optimization_job_creation() {
@@ -456,4 +939,168 @@ mod tests {
         assert_eq!(schedule.get_active_hours(0.5), vec![1, 2, 3]);
         assert_eq!(schedule.total_pump_hours(), 2.0);
     }
+
+    #[test]
+    fn test_prijs_per_uur() {
+        let prijzen: Vec<UurPrijs> = (0..30).map(|uur| UurPrijs::uur(uur, uur as f64)).collect();
+        assert_eq!(prijs_per_uur(&prijzen[..2], 0.15, 3), vec![0.0, 1.0, 0.15]);
+
+        // Na uur 29 herhalen de prijzen van een dag eerder
+        let per_uur = prijs_per_uur(&prijzen, 0.15, 72);
+        assert_eq!(per_uur.len(), 72);
+        assert_eq!(per_uur[29], 29.0);
+        assert_eq!(per_uur[30], 6.0);
+        assert_eq!(per_uur[71], 23.0);
+    }
+
+    #[test]
+    fn test_prijs_per_kwartier() {
+        let prijzen = vec![
+            UurPrijs::uur(0, 0.10),
+            UurPrijs::uur(1, 0.20),
+            UurPrijs::kwartier(1, 2, 0.50),
+            UurPrijs::kwartier(30, 0, 9.99),
+        ];
+        assert!(heeft_kwartierprijzen(&prijzen));
+        assert!(!heeft_kwartierprijzen(&prijzen[..2]));
+
+        let per_kwartier = prijs_per_kwartier(&prijzen, 0.15, 24);
+        assert_eq!(per_kwartier.len(), KWARTIEREN_PER_DAG);
+        assert_eq!(&per_kwartier[..8], &[0.10, 0.10, 0.10, 0.10, 0.20, 0.20, 0.50, 0.20]);
+        assert_eq!(per_kwartier[8], 0.15);
+
+        // Op de tweede dag herhaalt het dagprofiel tot een bekende prijs
+        let per_kwartier = prijs_per_kwartier(&prijzen, 0.15, 48);
+        assert_eq!(per_kwartier.len(), 2 * KWARTIEREN_PER_DAG);
+        assert_eq!(per_kwartier[KWARTIEREN_PER_DAG + 6], 0.50);
+        assert_eq!(per_kwartier[120], 9.99);
+        assert_eq!(per_kwartier[121], 0.15);
+
+        let json = serde_json::to_value(UurPrijs::uur(3, 0.1)).unwrap();
+        assert!(json.get("kwartier").is_none());
+        let prijs: UurPrijs = serde_json::from_value(json).unwrap();
+        assert_eq!(prijs.kwartier, None);
+    }
+
+    #[test]
+    fn test_stroomkosten_en_opwek() {
+        // Zonder opwek: alles afgenomen
+        assert_eq!(stroomkosten(10.0, 0.0, 0.30, 0.05), (3.0, 0.0));
+        // 6 kW eigen verbruik tegen de vergoeding, 4 kW afgenomen
+        let (kosten, eigen) = stroomkosten(10.0, 6.0, 0.30, 0.05);
+        assert!((kosten - 1.5).abs() < 1e-12);
+        assert_eq!(eigen, 6.0);
+        // Meer opwek dan verbruik
+        assert_eq!(stroomkosten(2.0, 6.0, 0.30, 0.0), (0.0, 2.0));
+
+        let start = DateTime::parse_from_rfc3339("2025-06-01T04:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let watts = vec![
+            (start - chrono::Duration::minutes(30), 500.0),
+            (start + chrono::Duration::minutes(60), 1000.0),
+            (start + chrono::Duration::minutes(90), 3000.0),
+        ];
+        assert_eq!(opwek_per_uur(&watts, start, 3), vec![0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn test_emissiefactoren() {
+        let mut params = OptimalisatieParams::default();
+        assert_eq!(params.emissiefactoren(2), vec![STANDAARD_EMISSIEFACTOR; 2]);
+
+        // Na het opgegeven etmaal herhaalt het dagprofiel
+        params.emissiefactor_per_uur = (0..24).map(|uur| uur as f64 / 100.0).collect();
+        let factoren = params.emissiefactoren(48);
+        assert_eq!(factoren[5], 0.05);
+        assert_eq!(factoren[29], 0.05);
+    }
+
+    #[test]
+    fn test_uur_van_dag() {
+        let mut params = OptimalisatieParams::default();
+        assert_eq!(params.uur_van_dag(25), 1);
+
+        // Op 29 maart 2026 gaat de klok om 2 uur naar 3 uur
+        params.starttijd = Some("2026-03-28T23:00:00Z".parse().unwrap());
+        assert_eq!(params.uur_van_dag(0), 0);
+        assert_eq!(params.uur_van_dag(1), 1);
+        assert_eq!(params.uur_van_dag(2), 3);
+        // Zomertijd: 12 uur UTC is 14 uur
+        assert_eq!(params.uur_van_dag(13), 14);
+    }
+
+    #[test]
+    fn test_flexsignalen() {
+        let params = OptimalisatieParams {
+            flexsignalen: vec![
+                Flexsignaal { uur: 3, max_vermogen_kw: Some(50.0), vergoeding_eur_kwh: -0.05 },
+                Flexsignaal { uur: 3, max_vermogen_kw: Some(20.0), vergoeding_eur_kwh: 0.0 },
+                Flexsignaal { uur: 5, max_vermogen_kw: None, vergoeding_eur_kwh: 0.10 },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(params.max_afname_kw(3), Some(20.0));
+        assert_eq!(params.max_afname_kw(5), None);
+        assert_eq!(params.flexvergoeding(3), -0.05);
+        assert_eq!(params.flexvergoeding(5), 0.10);
+        assert_eq!(params.flexvergoeding(4), 0.0);
+    }
+
+    #[test]
+    fn test_opvoerhoogte_buitenpeil() {
+        let mut params = OptimalisatieParams {
+            streefpeil: -0.60,
+            opvoerhoogte: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(params.opvoerhoogte(5), 2.0);
+
+        params.buitenpeil_per_uur = vec![0.40, 1.40, -1.0];
+        assert!((params.opvoerhoogte(0) - 1.0).abs() < 1e-12);
+        assert!((params.opvoerhoogte(1) - 2.0).abs() < 1e-12);
+        assert_eq!(params.opvoerhoogte(2), MIN_OPVOERHOOGTE);
+        // Na het laatste bekende uur
+        assert_eq!(params.opvoerhoogte(10), MIN_OPVOERHOOGTE);
+    }
+
+    #[test]
+    fn test_pompdebiet_uit_pompcurve() {
+        let mut params = OptimalisatieParams {
+            streefpeil: -0.60,
+            max_debiet: 1.0,
+            efficiency: 0.70,
+            buitenpeil_per_uur: vec![0.40, 1.40],
+            ..Default::default()
+        };
+        assert_eq!(params.pompdebiet(1), 1.0);
+        assert_eq!(params.rendement(1), 0.70);
+
+        params.pompcurve = Some(
+            PompCurve::new(vec![
+                PompPunt::new(1.0, 2.0).met_rendement(0.8),
+                PompPunt::new(3.0, 0.0).met_rendement(0.6),
+            ])
+            .unwrap(),
+        );
+        // Opvoerhoogte 1.0 m in uur 0 en 2.0 m in uur 1
+        assert!((params.pompdebiet(0) - 2.0).abs() < 1e-12);
+        assert!((params.pompdebiet(1) - 1.0).abs() < 1e-12);
+        assert!((params.rendement(1) - 0.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_vermogensfactor() {
+        let mut params = OptimalisatieParams::default();
+        assert_eq!(params.vermogensfactor(0.5), 0.5);
+
+        params.pompregeling = Pompregeling::Toerengeregeld;
+        assert_eq!(params.vermogensfactor(0.5), 0.125);
+        assert_eq!(params.vermogensfactor(1.0), 1.0);
+
+        // Onder het laagste toerental aan/uit op dat toerental
+        params.min_toerental = 0.4;
+        assert!((params.vermogensfactor(0.2) - 0.2 * 0.16).abs() < 1e-12);
+        assert!((params.vermogensfactor(0.4) - 0.064).abs() < 1e-12);
+    }
 }
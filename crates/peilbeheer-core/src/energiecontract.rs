@@ -0,0 +1,221 @@
+//! Energiecontracten van gemalen.
+//!
+//! Een contract bepaalt wat een gemaal betaalt voor afgenomen stroom en
+//! ontvangt voor teruggeleverde zonnestroom: een vast tarief (eventueel met
+//! daltarief) of de marktprijs met een opslag, een terugleverregeling en
+//! eventueel een gecontracteerd vermogen met een boete per kW daarboven.
+//! De optimalisatie rekent met de prijzen van het contract in plaats van de
+//! kale marktprijzen.
+
+use serde::{Deserialize, Serialize};
+
+/// Energiecontract van een gemaal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergieContract {
+    /// Tarief voor afgenomen stroom
+    pub tarief: Tarief,
+    /// Verrekening van teruggeleverde zonnestroom
+    #[serde(default)]
+    pub teruglevering: Teruglevering,
+    /// Gecontracteerd vermogen bij de netbeheerder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netcapaciteit: Option<Netcapaciteit>,
+}
+
+/// Tarief voor afgenomen stroom.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "soort", rename_all = "snake_case")]
+pub enum Tarief {
+    /// Vaste prijs per kWh, eventueel lager in de daluren
+    Vast {
+        prijs_eur_kwh: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dal: Option<Daltarief>,
+    },
+    /// Marktprijs per uur of kwartier plus een opslag per kWh (leverancier,
+    /// energiebelasting)
+    Dynamisch {
+        #[serde(default)]
+        opslag_eur_kwh: f64,
+    },
+}
+
+/// Lager tarief voor de uren van `van_uur` tot `tot_uur`; loopt over
+/// middernacht als `van_uur` groter is dan `tot_uur`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Daltarief {
+    pub prijs_eur_kwh: f64,
+    /// Eerste daluur (0-23)
+    #[serde(default = "default_dal_van")]
+    pub van_uur: u8,
+    /// Eerste uur na het dal (0-23)
+    #[serde(default = "default_dal_tot")]
+    pub tot_uur: u8,
+}
+
+impl Daltarief {
+    /// Of een uur van de dag (0-23) in het dal valt.
+    pub fn bevat(&self, uur_van_dag: usize) -> bool {
+        let (van, tot) = (self.van_uur as usize, self.tot_uur as usize);
+        if van <= tot {
+            (van..tot).contains(&uur_van_dag)
+        } else {
+            uur_van_dag >= van || uur_van_dag < tot
+        }
+    }
+}
+
+/// Verrekening van teruggeleverde stroom.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "soort", rename_all = "snake_case")]
+pub enum Teruglevering {
+    /// Geen vergoeding
+    #[default]
+    Geen,
+    /// Vaste vergoeding per kWh
+    Vast { vergoeding_eur_kwh: f64 },
+    /// Marktprijs min terugleverkosten per kWh
+    Marktprijs {
+        #[serde(default)]
+        kosten_eur_kwh: f64,
+    },
+    /// Salderen: teruglevering wordt verrekend tegen de afnameprijs
+    Salderen,
+}
+
+/// Gecontracteerd vermogen met een boete voor overschrijding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Netcapaciteit {
+    /// Gecontracteerd afnamevermogen in kW
+    pub gecontracteerd_kw: f64,
+    /// Boete per kW van de hoogste afname boven het gecontracteerde
+    /// vermogen (€/kW)
+    pub boete_eur_kw: f64,
+}
+
+impl EnergieContract {
+    /// Of het contract marktprijzen nodig heeft.
+    pub fn is_dynamisch(&self) -> bool {
+        matches!(self.tarief, Tarief::Dynamisch { .. })
+            || matches!(self.teruglevering, Teruglevering::Marktprijs { .. })
+    }
+
+    /// Afnameprijs (€/kWh) bij een marktprijs, in een lokaal uur van de dag
+    /// (0-23, zie [`OptimalisatieParams::uur_van_dag`]).
+    ///
+    /// [`OptimalisatieParams::uur_van_dag`]: crate::energie::OptimalisatieParams::uur_van_dag
+    pub fn afnameprijs(&self, marktprijs: f64, uur_van_dag: usize) -> f64 {
+        match &self.tarief {
+            Tarief::Vast { prijs_eur_kwh, dal } => match dal {
+                Some(dal) if dal.bevat(uur_van_dag) => dal.prijs_eur_kwh,
+                _ => *prijs_eur_kwh,
+            },
+            Tarief::Dynamisch { opslag_eur_kwh } => marktprijs + opslag_eur_kwh,
+        }
+    }
+
+    /// Vergoeding (€/kWh) voor teruggeleverde stroom bij een marktprijs in
+    /// een lokaal uur van de dag; dit is ook wat eigen verbruik van
+    /// zonnestroom aan vergoeding misloopt.
+    pub fn terugleverprijs(&self, marktprijs: f64, uur_van_dag: usize) -> f64 {
+        match &self.teruglevering {
+            Teruglevering::Geen => 0.0,
+            Teruglevering::Vast { vergoeding_eur_kwh } => *vergoeding_eur_kwh,
+            Teruglevering::Marktprijs { kosten_eur_kwh } => marktprijs - kosten_eur_kwh,
+            Teruglevering::Salderen => self.afnameprijs(marktprijs, uur_van_dag),
+        }
+    }
+
+    /// Boete (€) voor een hoogste afname van `piek_kw`.
+    pub fn piekboete(&self, piek_kw: f64) -> f64 {
+        self.netcapaciteit.as_ref().map_or(0.0, |net| {
+            (piek_kw - net.gecontracteerd_kw).max(0.0) * net.boete_eur_kw
+        })
+    }
+
+    /// Controleer dat prijzen, daluren en vermogen zinnig zijn.
+    pub fn valideer(&self) -> Result<(), String> {
+        if let Tarief::Vast { prijs_eur_kwh, dal } = &self.tarief {
+            if *prijs_eur_kwh < 0.0 {
+                return Err("Vast tarief mag niet negatief zijn".into());
+            }
+            if let Some(dal) = dal
+                && (dal.van_uur > 23 || dal.tot_uur > 23 || dal.van_uur == dal.tot_uur)
+            {
+                return Err("Daluren moeten 0-23 zijn en van_uur en tot_uur moeten verschillen".into());
+            }
+        }
+        if let Some(net) = &self.netcapaciteit
+            && (net.gecontracteerd_kw <= 0.0 || net.boete_eur_kw < 0.0)
+        {
+            return Err(
+                "Gecontracteerd vermogen moet groter zijn dan 0 en de boete mag niet negatief zijn"
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn default_dal_van() -> u8 { 23 }
+fn default_dal_tot() -> u8 { 7 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vast_tarief_met_dal() {
+        let contract: EnergieContract = serde_json::from_value(serde_json::json!({
+            "tarief": { "soort": "vast", "prijs_eur_kwh": 0.25, "dal": { "prijs_eur_kwh": 0.18 } },
+            "teruglevering": { "soort": "salderen" }
+        }))
+        .unwrap();
+        assert!(!contract.is_dynamisch());
+        assert!(contract.valideer().is_ok());
+
+        // Het dal loopt standaard van 23 tot 7 uur
+        assert_eq!(contract.afnameprijs(0.05, 3), 0.18);
+        assert_eq!(contract.afnameprijs(0.05, 12), 0.25);
+        assert_eq!(contract.afnameprijs(0.05, 23), 0.18);
+        assert_eq!(contract.afnameprijs(0.05, 7), 0.25);
+        // Salderen verrekent tegen de afnameprijs
+        assert_eq!(contract.terugleverprijs(0.05, 12), 0.25);
+        assert_eq!(contract.piekboete(1000.0), 0.0);
+    }
+
+    #[test]
+    fn test_dynamisch_met_netcapaciteit() {
+        let contract = EnergieContract {
+            tarief: Tarief::Dynamisch { opslag_eur_kwh: 0.12 },
+            teruglevering: Teruglevering::Marktprijs { kosten_eur_kwh: 0.02 },
+            netcapaciteit: Some(Netcapaciteit { gecontracteerd_kw: 100.0, boete_eur_kw: 5.0 }),
+        };
+        assert!(contract.is_dynamisch());
+        assert!((contract.afnameprijs(0.08, 5) - 0.20).abs() < 1e-12);
+        assert!((contract.terugleverprijs(0.08, 5) - 0.06).abs() < 1e-12);
+        assert_eq!(contract.piekboete(80.0), 0.0);
+        assert_eq!(contract.piekboete(130.0), 150.0);
+
+        let json = serde_json::to_value(&contract).unwrap();
+        assert_eq!(json["tarief"]["soort"], "dynamisch");
+        assert_eq!(serde_json::from_value::<EnergieContract>(json).unwrap(), contract);
+    }
+
+    #[test]
+    fn test_valideer() {
+        let mut contract = EnergieContract {
+            tarief: Tarief::Vast {
+                prijs_eur_kwh: 0.25,
+                dal: Some(Daltarief { prijs_eur_kwh: 0.2, van_uur: 7, tot_uur: 7 }),
+            },
+            teruglevering: Teruglevering::Geen,
+            netcapaciteit: None,
+        };
+        assert!(contract.valideer().is_err());
+
+        contract.tarief = Tarief::Vast { prijs_eur_kwh: 0.25, dal: None };
+        contract.netcapaciteit = Some(Netcapaciteit { gecontracteerd_kw: 0.0, boete_eur_kw: 5.0 });
+        assert!(contract.valideer().is_err());
+    }
+}
@@ -3,10 +3,13 @@
 //! This module provides types and client functionality for interacting with
 //! Delft-FEWS (Flood Early Warning System) through its PI-REST API.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::maintenance::parse_cron;
+use crate::timeseries::TimeSeriesId;
+
 /// Fews client configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FewsConfig {
@@ -18,6 +21,12 @@ pub struct FewsConfig {
     pub api_key: Option<String>,
     /// Request timeout in seconds
     pub timeout_secs: u64,
+    /// Document format to request (None = server default, detected per response)
+    #[serde(default)]
+    pub document_format: Option<FewsDocumentFormat>,
+    /// Module instance that written time series are stored under
+    #[serde(default)]
+    pub write_module_instance_id: Option<String>,
 }
 
 impl Default for FewsConfig {
@@ -27,6 +36,45 @@ impl Default for FewsConfig {
             filter_id: "WatershedFilter".to_string(),
             api_key: None,
             timeout_secs: 30,
+            document_format: None,
+            write_module_instance_id: None,
+        }
+    }
+}
+
+/// Document formats of the PI-REST service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FewsDocumentFormat {
+    PiJson,
+    PiXml,
+}
+
+impl FewsDocumentFormat {
+    /// Value of the `documentFormat` query parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PiJson => "PI_JSON",
+            Self::PiXml => "PI_XML",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().replace('-', "_").as_str() {
+            "PI_JSON" | "JSON" => Some(Self::PiJson),
+            "PI_XML" | "XML" => Some(Self::PiXml),
+            _ => None,
+        }
+    }
+
+    /// Format of a response, from its content type or else its first character.
+    pub fn detect(content_type: Option<&str>, body: &str) -> Self {
+        match content_type.map(str::to_lowercase) {
+            Some(ct) if ct.contains("xml") => Self::PiXml,
+            Some(ct) if ct.contains("json") => Self::PiJson,
+            _ if body.trim_start_matches('\u{feff}').trim_start().starts_with('<') => Self::PiXml,
+            _ => Self::PiJson,
         }
     }
 }
@@ -52,6 +100,8 @@ pub enum FewsTimeStep {
     Month,
     Year,
     Decade,
+    /// Irregular times without a step
+    NonEquidistant,
 }
 
 impl FewsTimeStep {
@@ -64,6 +114,29 @@ impl FewsTimeStep {
             Self::Month => "month",
             Self::Year => "year",
             Self::Decade => "decade",
+            Self::NonEquidistant => "nonequidistant",
+        }
+    }
+
+    /// Length of the step in seconds; None for steps of a month or longer
+    /// and for non-equidistant series.
+    pub fn seconds(&self) -> Option<i64> {
+        match self {
+            Self::Second => Some(1),
+            Self::Minute => Some(60),
+            Self::Hour => Some(3600),
+            Self::Day => Some(86_400),
+            Self::Month | Self::Year | Self::Decade | Self::NonEquidistant => None,
+        }
+    }
+
+    /// Coarsest unit a step of `seconds` is a whole number of.
+    pub fn from_seconds(seconds: i64) -> Self {
+        match seconds {
+            s if s % 86_400 == 0 => Self::Day,
+            s if s % 3600 == 0 => Self::Hour,
+            s if s % 60 == 0 => Self::Minute,
+            _ => Self::Second,
         }
     }
 }
@@ -77,7 +150,12 @@ pub struct FewsTimeSeriesQuery {
     pub module_instance_ids: Option<Vec<String>>,
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
-    pub qualifier: Option<String>,
+    /// Qualifiers the series must have
+    pub qualifier_ids: Option<Vec<String>>,
+    /// Ensemble to fetch all members of
+    pub ensemble_id: Option<String>,
+    /// Module instance sets, e.g. the forecast runs of a workflow
+    pub module_instance_sets: Option<Vec<String>>,
     pub show_enumeration: Option<bool>,
     pub version: Option<String>,
     pub only_headers: Option<bool>,
@@ -100,6 +178,10 @@ pub struct FewsTimeSeriesHeader {
     pub parameter_id: String,
     pub module_instance_id: String,
     pub time_step: FewsTimeStep,
+    /// Length of the step in seconds when it is not one `time_step`, e.g.
+    /// 900 for a quarter-hourly series
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_step_seconds: Option<i64>,
     pub start_date: String,
     pub end_date: String,
     pub units: String,
@@ -116,6 +198,40 @@ pub struct FewsTimeSeriesHeader {
     pub y: Option<f64>,
     pub qualifier: Option<String>,
     pub miss_val: Option<f64>,
+    /// Ensemble of a forecast series
+    #[serde(default)]
+    pub ensemble_id: Option<String>,
+    /// Member of the ensemble (index or id)
+    #[serde(default)]
+    pub ensemble_member: Option<String>,
+    /// Time of the forecast (RFC 3339), None for observations
+    #[serde(default)]
+    pub forecast_date: Option<String>,
+}
+
+impl FewsTimeSeriesHeader {
+    /// Whether the series is a forecast.
+    pub fn is_forecast(&self) -> bool {
+        self.forecast_date.is_some() || self.ensemble_id.is_some()
+    }
+
+    /// Qualifier of the series in the time series store.
+    ///
+    /// Forecasts are stored apart from observations, one series per
+    /// ensemble member: `forecast`, `forecast:<ensemble>:<member>`, prefixed
+    /// by the Fews qualifier if there is one.
+    pub fn series_qualifier(&self) -> Option<String> {
+        let forecast = self.is_forecast().then(|| {
+            let mut parts = vec!["forecast"];
+            parts.extend(self.ensemble_id.as_deref());
+            parts.extend(self.ensemble_member.as_deref());
+            parts.join(":")
+        });
+        match (&self.qualifier, forecast) {
+            (Some(qualifier), Some(forecast)) => Some(format!("{}:{}", qualifier, forecast)),
+            (qualifier, forecast) => forecast.or_else(|| qualifier.clone()),
+        }
+    }
 }
 
 /// Value type in Fews.
@@ -214,6 +330,233 @@ pub struct FewsSyncConfig {
     pub parameter_mapping: HashMap<String, String>, // local_id -> fews_id
     pub sync_interval_hours: Option<u32>,
     pub auto_sync: bool,
+    /// Cron expression of automatic syncs (UTC), takes precedence over the interval
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// Period fetched per sync (hours, default 24)
+    #[serde(default)]
+    pub lookback_hours: Option<i64>,
+    /// Qualifiers to fetch
+    #[serde(default)]
+    pub qualifier_ids: Option<Vec<String>>,
+    /// Ensemble to fetch, for forecast syncs
+    #[serde(default)]
+    pub ensemble_id: Option<String>,
+    /// Module instance sets to fetch, for forecast syncs
+    #[serde(default)]
+    pub module_instance_sets: Option<Vec<String>>,
+}
+
+impl FewsSyncConfig {
+    /// Period fetched per sync.
+    pub fn lookback(&self) -> Duration {
+        Duration::hours(self.lookback_hours.unwrap_or(24).max(1))
+    }
+
+    /// Next automatic sync after `after`, if the config syncs automatically.
+    pub fn next_run_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.auto_sync {
+            return None;
+        }
+        match (&self.cron, self.sync_interval_hours) {
+            (Some(cron), _) => parse_cron(cron).ok()?.after(&after).next(),
+            (None, Some(hours)) if hours > 0 => Some(after + Duration::hours(hours as i64)),
+            _ => None,
+        }
+    }
+
+    /// Validate the configuration.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.peilgebied_id.trim().is_empty() {
+            errors.push("peilgebied_id: must not be empty".to_string());
+        }
+        if let Some(cron) = &self.cron
+            && let Err(e) = parse_cron(cron)
+        {
+            errors.push(format!("cron: invalid cron expression '{}': {}", cron, e));
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Kind of id a Fews mapping translates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FewsMappingKind {
+    Location,
+    Parameter,
+}
+
+impl FewsMappingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Location => "location",
+            Self::Parameter => "parameter",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "location" => Some(Self::Location),
+            "parameter" => Some(Self::Parameter),
+            _ => None,
+        }
+    }
+}
+
+/// Translation of a Fews location or parameter id to the local name,
+/// e.g. parameter `H.meting` to `water_level`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FewsIdMapping {
+    pub id: String,
+    pub kind: FewsMappingKind,
+    pub fews_id: String,
+    pub local_id: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Create Fews mapping request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFewsMappingRequest {
+    pub kind: FewsMappingKind,
+    pub fews_id: String,
+    pub local_id: String,
+    pub description: Option<String>,
+}
+
+impl CreateFewsMappingRequest {
+    /// Validate the request.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.fews_id.trim().is_empty() {
+            errors.push("fews_id: must not be empty".to_string());
+        }
+        if self.local_id.trim().is_empty() {
+            errors.push("local_id: must not be empty".to_string());
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Update Fews mapping request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateFewsMappingRequest {
+    pub local_id: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Translates Fews series headers to local [`TimeSeriesId`]s.
+///
+/// The mappings of a sync config take precedence over the general
+/// mappings; ids without a mapping are kept as they are.
+#[derive(Debug, Clone, Default)]
+pub struct FewsIdTranslator {
+    locations: HashMap<String, String>,
+    parameters: HashMap<String, String>,
+}
+
+impl FewsIdTranslator {
+    /// Translator for the general mappings.
+    pub fn new(mappings: &[FewsIdMapping]) -> Self {
+        let mut translator = Self::default();
+        for mapping in mappings {
+            translator.insert(mapping.kind, &mapping.fews_id, &mapping.local_id);
+        }
+        translator
+    }
+
+    /// Add the (local -> Fews) mappings of a sync config.
+    pub fn with_sync_config(mut self, config: &FewsSyncConfig) -> Self {
+        for (local, fews) in &config.location_mapping {
+            self.insert(FewsMappingKind::Location, fews, local);
+        }
+        for (local, fews) in &config.parameter_mapping {
+            self.insert(FewsMappingKind::Parameter, fews, local);
+        }
+        self
+    }
+
+    fn insert(&mut self, kind: FewsMappingKind, fews_id: &str, local_id: &str) {
+        let map = match kind {
+            FewsMappingKind::Location => &mut self.locations,
+            FewsMappingKind::Parameter => &mut self.parameters,
+        };
+        map.insert(fews_id.to_string(), local_id.to_string());
+    }
+
+    /// Local id of a Fews id.
+    pub fn local_id<'a>(&'a self, kind: FewsMappingKind, fews_id: &'a str) -> &'a str {
+        let map = match kind {
+            FewsMappingKind::Location => &self.locations,
+            FewsMappingKind::Parameter => &self.parameters,
+        };
+        map.get(fews_id).map_or(fews_id, String::as_str)
+    }
+
+    /// Local series id of a Fews series.
+    pub fn time_series_id(&self, header: &FewsTimeSeriesHeader) -> TimeSeriesId {
+        let location = self.local_id(FewsMappingKind::Location, &header.location_id);
+        let parameter = self.local_id(FewsMappingKind::Parameter, &header.parameter_id);
+        match header.series_qualifier() {
+            Some(qualifier) => TimeSeriesId::with_qualifier(location, parameter, qualifier),
+            None => TimeSeriesId::new(location, parameter),
+        }
+    }
+}
+
+/// What started a sync run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FewsSyncTrigger {
+    Scheduled,
+    Manual,
+}
+
+impl FewsSyncTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::Manual => "manual",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "scheduled" => Some(Self::Scheduled),
+            "manual" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// A recorded sync run of a peilgebied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FewsSyncRun {
+    pub id: String,
+    pub peilgebied_id: String,
+    pub trigger: FewsSyncTrigger,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    /// Time series received from Fews
+    pub time_series_count: usize,
+    /// Data points received from Fews
+    pub data_points_count: usize,
+    /// Data points stored in the time series store
+    pub points_written: usize,
+    /// Errors of the run; a run without errors succeeded
+    pub errors: Vec<String>,
+}
+
+impl FewsSyncRun {
+    /// Whether the run finished without errors.
+    pub fn succeeded(&self) -> bool {
+        self.errors.is_empty()
+    }
 }
 
 impl FewsTimeSeriesQuery {
@@ -282,6 +625,123 @@ mod tests {
     fn test_time_step_serialization() {
         let step = FewsTimeStep::Hour;
         assert_eq!(step.as_str(), "hour");
+        assert_eq!(step.seconds(), Some(3600));
+        assert_eq!(FewsTimeStep::from_seconds(900), FewsTimeStep::Minute);
+        assert_eq!(FewsTimeStep::from_seconds(7200), FewsTimeStep::Hour);
+        assert_eq!(FewsTimeStep::from_seconds(90), FewsTimeStep::Second);
+        assert_eq!(FewsTimeStep::NonEquidistant.seconds(), None);
+    }
+
+    #[test]
+    fn test_document_format_detection() {
+        use FewsDocumentFormat::*;
+        assert_eq!(FewsDocumentFormat::detect(Some("application/xml;charset=UTF-8"), "{}"), PiXml);
+        assert_eq!(FewsDocumentFormat::detect(Some("application/json"), "<x/>"), PiJson);
+        assert_eq!(FewsDocumentFormat::detect(None, "  <?xml version=\"1.0\"?>"), PiXml);
+        assert_eq!(FewsDocumentFormat::detect(Some("text/plain"), "{\"version\": \"1.25\"}"), PiJson);
+        assert_eq!(FewsDocumentFormat::from_str("pi_xml"), Some(PiXml));
+        assert_eq!(FewsDocumentFormat::from_str("csv"), None);
+    }
+
+    #[test]
+    fn test_sync_config_schedule() {
+        let mut config = FewsSyncConfig {
+            peilgebied_id: "PG_001".to_string(),
+            fews_filter_id: "Rijnland".to_string(),
+            location_mapping: HashMap::new(),
+            parameter_mapping: HashMap::new(),
+            sync_interval_hours: Some(6),
+            auto_sync: true,
+            cron: None,
+            lookback_hours: None,
+            qualifier_ids: None,
+            ensemble_id: None,
+            module_instance_sets: None,
+        };
+        let at = DateTime::parse_from_rfc3339("2025-03-10T08:20:00Z").unwrap().with_timezone(&Utc);
+
+        assert_eq!(config.next_run_after(at), Some(at + Duration::hours(6)));
+        assert_eq!(config.lookback(), Duration::hours(24));
+
+        config.cron = Some("*/15 * * * *".to_string());
+        let next = config.next_run_after(at).unwrap();
+        assert_eq!(next.to_rfc3339(), "2025-03-10T08:30:00+00:00");
+        assert!(config.validate().is_ok());
+
+        config.auto_sync = false;
+        assert_eq!(config.next_run_after(at), None);
+
+        config.cron = Some("every hour".to_string());
+        assert_eq!(config.validate().unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_id_translator() {
+        let now = Utc::now();
+        let mapping = |kind, fews_id: &str, local_id: &str| FewsIdMapping {
+            id: format!("FMP_{}", fews_id),
+            kind,
+            fews_id: fews_id.to_string(),
+            local_id: local_id.to_string(),
+            description: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let config = FewsSyncConfig {
+            peilgebied_id: "PG_001".to_string(),
+            fews_filter_id: "Rijnland".to_string(),
+            location_mapping: HashMap::from([("GEM_001".to_string(), "RL_GEM_1".to_string())]),
+            parameter_mapping: HashMap::from([("debiet".to_string(), "Q.meting".to_string())]),
+            sync_interval_hours: None,
+            auto_sync: false,
+            cron: None,
+            lookback_hours: None,
+            qualifier_ids: None,
+            ensemble_id: None,
+            module_instance_sets: None,
+        };
+        let translator = FewsIdTranslator::new(&[
+            mapping(FewsMappingKind::Parameter, "H.meting", "water_level"),
+            mapping(FewsMappingKind::Location, "RL_GEM_1", "GEM_999"),
+        ])
+        .with_sync_config(&config);
+
+        assert_eq!(translator.local_id(FewsMappingKind::Parameter, "H.meting"), "water_level");
+        assert_eq!(translator.local_id(FewsMappingKind::Parameter, "Q.meting"), "debiet");
+        assert_eq!(translator.local_id(FewsMappingKind::Location, "RL_GEM_1"), "GEM_001");
+        assert_eq!(translator.local_id(FewsMappingKind::Location, "RL_GEM_2"), "RL_GEM_2");
+    }
+
+    #[test]
+    fn test_series_qualifier() {
+        let header: FewsTimeSeriesHeader = serde_json::from_value(serde_json::json!({
+            "location_id": "GEM_001", "parameter_id": "H.voorspeld", "module_instance_id": "Sobek",
+            "time_step": "HOUR", "start_date": "", "end_date": "", "units": "m",
+            "type_description": "", "value_type": "instantaneous", "station_name": "",
+            "parameter_description": "", "module_description": "", "geo_delta": null,
+            "geo_datum": null, "lat": null, "lon": null, "x": null, "y": null,
+            "qualifier": null, "miss_val": null
+        }))
+        .unwrap();
+        assert!(!header.is_forecast());
+        assert_eq!(header.series_qualifier(), None);
+
+        let forecast = FewsTimeSeriesHeader {
+            forecast_date: Some("2025-03-10T06:00:00Z".to_string()),
+            ..header.clone()
+        };
+        assert_eq!(forecast.series_qualifier().as_deref(), Some("forecast"));
+
+        let member = FewsTimeSeriesHeader {
+            ensemble_id: Some("ECMWF".to_string()),
+            ensemble_member: Some("12".to_string()),
+            qualifier: Some("max".to_string()),
+            ..forecast
+        };
+        assert_eq!(member.series_qualifier().as_deref(), Some("max:forecast:ECMWF:12"));
+
+        let id = FewsIdTranslator::default().time_series_id(&member);
+        assert_eq!(id.qualifier.as_deref(), Some("max:forecast:ECMWF:12"));
     }
 
     #[test]
@@ -0,0 +1,389 @@
+//! KNMI neerslag- en weerverwachtingen per peilgebied.
+//!
+//! De KNMI Data Platform EDR API levert de waarden binnen een polygoon als
+//! CoverageJSON: per tijdstap de waarden van de rastercellen in het gebied.
+//! [`gebiedsgemiddelde`] middelt die cellen per tijdstap.
+//!
+//! - Radar nowcast: [`regen_per_uur`] telt de tijdstappen op tot uursommen,
+//!   direct bruikbaar als `regen_per_uur` van simulatie en optimalisatie.
+//! - HARMONIE/EPS: [`WeerVerwachting`] bevat neerslag en verdamping voor
+//!   de komende dagen en wordt opgeslagen als forecast-tijdreeksen.
+//!
+//! Daarnaast leest [`daggegevens`] de dagwaarden van KNMI-stations, de
+//! invoer van de Makkink-verdamping in [`crate::verdamping`].
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use crate::timeseries::{TimeSeriesDataPoint, TimeSeriesId, TimeSeriesWriteBatch};
+use crate::verdamping::Dagwaarde;
+
+/// Qualifier van KNMI-verwachtingen in de tijdreeksopslag.
+pub const FORECAST_QUALIFIER: &str = "forecast:knmi";
+
+/// Fouten bij het verwerken van KNMI data.
+#[derive(Debug, Error)]
+pub enum KnmiError {
+    #[error("Ongeldige CoverageJSON: {0}")]
+    InvalidCoverage(String),
+    #[error("Parameter {0} ontbreekt in de CoverageJSON")]
+    MissingParameter(String),
+    #[error("Ongeldige daggegevens: {0}")]
+    InvalidDaggegevens(String),
+}
+
+/// Gebiedsgemiddelde neerslagverwachting van een peilgebied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegenVerwachting {
+    pub peilgebied_code: String,
+    /// Begin van het eerste uur
+    pub start: DateTime<Utc>,
+    /// Neerslag per uur in mm, vanaf `start` tot het eind van de verwachting
+    pub regen_per_uur: Vec<f64>,
+    /// Bron van de verwachting, bijv. `knmi:radar_forecast`
+    pub bron: String,
+    pub opgehaald_op: DateTime<Utc>,
+}
+
+/// Gebiedsgemiddelde weerverwachting van een peilgebied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeerVerwachting {
+    pub peilgebied_code: String,
+    /// Neerslag per tijdstap (mm)
+    pub neerslag: Vec<TimeSeriesDataPoint>,
+    /// Verdamping per tijdstap (mm)
+    pub verdamping: Vec<TimeSeriesDataPoint>,
+    /// Bron van de verwachting, bijv. `knmi:harmonie_arome_cy43_p1`
+    pub bron: String,
+    pub opgehaald_op: DateTime<Utc>,
+}
+
+impl WeerVerwachting {
+    /// Tijdreeksen van de verwachting: `neerslag` en `verdamping` op het
+    /// peilgebied met qualifier [`FORECAST_QUALIFIER`].
+    pub fn to_write_batches(&self) -> Vec<TimeSeriesWriteBatch> {
+        [
+            ("neerslag", &self.neerslag),
+            ("verdamping", &self.verdamping),
+        ]
+        .into_iter()
+        .filter(|(_, data)| !data.is_empty())
+        .map(|(parameter, data)| TimeSeriesWriteBatch {
+            series_id: TimeSeriesId::with_qualifier(
+                self.peilgebied_code.clone(),
+                parameter,
+                FORECAST_QUALIFIER,
+            ),
+            data: data.clone(),
+            attributes: None,
+        })
+        .collect()
+    }
+}
+
+/// Uursommen van de gebiedsgemiddelde neerslag in een CoverageJSON.
+///
+/// De waarden zijn neerslaghoeveelheden per tijdstap (mm). De
+/// gebiedsgemiddelden per tijdstap worden opgeteld per uur vanaf `start`.
+/// Uren zonder tijdstappen krijgen 0.0, maar de reeks stopt bij het uur
+/// van de laatste tijdstap: de nowcast reikt maar een paar uur vooruit, en
+/// daarna is de neerslag onbekend in plaats van droog.
+pub fn regen_per_uur(
+    coverage: &serde_json::Value,
+    parameter: &str,
+    start: DateTime<Utc>,
+    uren: usize,
+) -> Result<Vec<f64>, KnmiError> {
+    let mut regen = vec![0.0; uren];
+    let mut bereik = 0;
+    for (tijd, waarde) in gebiedsgemiddelde(coverage, parameter)? {
+        let minuten = (tijd - start).num_minutes();
+        if minuten < 0 {
+            continue;
+        }
+        let index = (minuten / 60) as usize;
+        if let Some(uur) = regen.get_mut(index) {
+            *uur += waarde;
+            bereik = bereik.max(index + 1);
+        }
+    }
+    regen.truncate(bereik);
+    Ok(regen)
+}
+
+/// Gemiddelde per tijdstap van de cellen met een waarde, op volgorde van
+/// tijd. Een `CoverageCollection` wordt behandeld als één gebied, dus de
+/// leden van een ensemble worden ook gemiddeld.
+pub fn gebiedsgemiddelde(
+    coverage: &serde_json::Value,
+    parameter: &str,
+) -> Result<Vec<(DateTime<Utc>, f64)>, KnmiError> {
+    let mut per_tijdstap: BTreeMap<DateTime<Utc>, (f64, usize)> = BTreeMap::new();
+
+    let coverages = match coverage["type"].as_str() {
+        Some("CoverageCollection") => coverage["coverages"]
+            .as_array()
+            .ok_or_else(|| KnmiError::InvalidCoverage("coverages ontbreekt".to_string()))?
+            .iter()
+            .collect(),
+        _ => vec![coverage],
+    };
+
+    for coverage in coverages {
+        for (tijd, waarde) in samples(coverage, parameter)? {
+            let entry = per_tijdstap.entry(tijd).or_default();
+            entry.0 += waarde;
+            entry.1 += 1;
+        }
+    }
+
+    Ok(per_tijdstap
+        .into_iter()
+        .map(|(tijd, (som, aantal))| (tijd, som / aantal as f64))
+        .collect())
+}
+
+/// Hoeveelheden per tijdstap uit een reeks die optelt vanaf het begin van
+/// de modelrun, zoals de neerslag van HARMONIE. De eerste waarde blijft
+/// staan; negatieve verschillen (afronding) worden 0.0.
+pub fn deaccumuleer(reeks: &[(DateTime<Utc>, f64)]) -> Vec<(DateTime<Utc>, f64)> {
+    let mut vorige = 0.0;
+    reeks
+        .iter()
+        .map(|&(tijd, waarde)| {
+            let stap = (waarde - vorige).max(0.0);
+            vorige = waarde;
+            (tijd, stap)
+        })
+        .collect()
+}
+
+/// Dagwaarden uit de CSV van de KNMI-daggegevens met de velden TG
+/// (etmaalgemiddelde temperatuur in 0.1 °C) en Q (globale straling in
+/// J/cm²).
+///
+/// De kolommen volgen uit de kopregel (`# STN,YYYYMMDD,TG,Q`); dagen
+/// waarvoor TG of Q ontbreekt worden overgeslagen.
+pub fn daggegevens(tekst: &str) -> Result<Vec<Dagwaarde>, KnmiError> {
+    let mut kolommen = None;
+    let mut dagwaarden = Vec::new();
+
+    for regel in tekst.lines() {
+        let velden: Vec<&str> = regel.trim_start_matches('#').split(',').map(str::trim).collect();
+        if regel.starts_with('#') {
+            if velden.contains(&"YYYYMMDD") {
+                let kolom = |naam: &str| {
+                    velden
+                        .iter()
+                        .position(|v| *v == naam)
+                        .ok_or_else(|| KnmiError::InvalidDaggegevens(format!("kolom {} ontbreekt", naam)))
+                };
+                kolommen = Some((kolom("YYYYMMDD")?, kolom("TG")?, kolom("Q")?));
+            }
+            continue;
+        }
+        if regel.trim().is_empty() {
+            continue;
+        }
+        let (datum, tg, q) = kolommen
+            .ok_or_else(|| KnmiError::InvalidDaggegevens("kopregel ontbreekt".to_string()))?;
+
+        let datum = velden
+            .get(datum)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+            .ok_or_else(|| KnmiError::InvalidDaggegevens(format!("ongeldige regel: {}", regel)))?;
+        let waarde = |kolom: usize| velden.get(kolom).and_then(|v| v.parse::<f64>().ok());
+        if let (Some(tg), Some(q)) = (waarde(tg), waarde(q)) {
+            dagwaarden.push(Dagwaarde {
+                datum,
+                temperatuur: tg / 10.0,
+                straling: q,
+            });
+        }
+    }
+    Ok(dagwaarden)
+}
+
+/// (tijd, waarde) van alle pixels met een waarde in één coverage.
+fn samples(
+    coverage: &serde_json::Value,
+    parameter: &str,
+) -> Result<Vec<(DateTime<Utc>, f64)>, KnmiError> {
+    let tijden = coverage["domain"]["axes"]["t"]["values"]
+        .as_array()
+        .ok_or_else(|| KnmiError::InvalidCoverage("tijd-as ontbreekt".to_string()))?
+        .iter()
+        .map(|t| {
+            t.as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok_or_else(|| KnmiError::InvalidCoverage(format!("ongeldige tijd {}", t)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let range = &coverage["ranges"][parameter];
+    let waarden = range["values"]
+        .as_array()
+        .ok_or_else(|| KnmiError::MissingParameter(parameter.to_string()))?;
+
+    // Zonder axisNames/shape is er één waarde per tijdstap
+    let assen: Vec<&str> = range["axisNames"]
+        .as_array()
+        .map(|names| names.iter().filter_map(|n| n.as_str()).collect())
+        .unwrap_or_else(|| vec!["t"]);
+    let vorm: Vec<usize> = range["shape"]
+        .as_array()
+        .map(|shape| {
+            shape
+                .iter()
+                .filter_map(|n| n.as_u64())
+                .map(|n| n as usize)
+                .collect()
+        })
+        .unwrap_or_else(|| vec![tijden.len()]);
+
+    let t_as = assen
+        .iter()
+        .position(|a| *a == "t")
+        .ok_or_else(|| KnmiError::InvalidCoverage("t ontbreekt in axisNames".to_string()))?;
+    if vorm.len() != assen.len() || vorm[t_as] != tijden.len() {
+        return Err(KnmiError::InvalidCoverage(
+            "shape past niet bij de assen".to_string(),
+        ));
+    }
+    // Waarden zijn row-major; de stap van de tijd-as is het product van
+    // de lengtes van de assen erna
+    let stap: usize = vorm[t_as + 1..].iter().product();
+
+    Ok(waarden
+        .iter()
+        .enumerate()
+        .filter_map(|(i, waarde)| {
+            let tijd = tijden[(i / stap) % tijden.len()];
+            waarde.as_f64().map(|w| (tijd, w))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_regen_per_uur_grid() {
+        // Drie tijdstappen van 30 minuten op een raster van 1x2 pixels;
+        // null is een pixel buiten het peilgebied
+        let coverage = json!({
+            "type": "Coverage",
+            "domain": {"axes": {
+                "t": {"values": ["2024-06-01T12:00:00Z", "2024-06-01T12:30:00Z", "2024-06-01T13:00:00Z"]},
+                "x": {"values": [4.5, 4.6]},
+                "y": {"values": [52.1]}
+            }},
+            "ranges": {"precipitation": {
+                "type": "NdArray",
+                "axisNames": ["t", "y", "x"],
+                "shape": [3, 1, 2],
+                "values": [1.0, 3.0, 0.5, null, 0.2, 0.4]
+            }}
+        });
+
+        // Het derde uur valt buiten de verwachting en ontbreekt
+        let regen = regen_per_uur(&coverage, "precipitation", start(), 3).unwrap();
+        assert_eq!(regen.len(), 2);
+        assert!((regen[0] - 2.5).abs() < 1e-9);
+        assert!((regen[1] - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_regen_per_uur_collection() {
+        let punt = |waarden: serde_json::Value| {
+            json!({
+                "type": "Coverage",
+                "domain": {"axes": {"t": {"values": ["2024-06-01T11:55:00Z", "2024-06-01T12:05:00Z"]}}},
+                "ranges": {"precipitation": {"values": waarden}}
+            })
+        };
+        let coverage = json!({
+            "type": "CoverageCollection",
+            "coverages": [punt(json!([9.0, 1.0])), punt(json!([9.0, 2.0]))]
+        });
+
+        // De tijdstap voor de start telt niet mee
+        let regen = regen_per_uur(&coverage, "precipitation", start(), 1).unwrap();
+        assert_eq!(regen, vec![1.5]);
+
+        assert!(matches!(
+            regen_per_uur(&coverage, "rain", start(), 1),
+            Err(KnmiError::MissingParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_deaccumuleer() {
+        let uur = |h: i64| start() + chrono::Duration::hours(h);
+        let reeks = vec![
+            (uur(0), 0.0),
+            (uur(1), 1.5),
+            (uur(2), 1.5),
+            (uur(3), 1.4),
+            (uur(4), 4.0),
+        ];
+        let stappen: Vec<f64> = deaccumuleer(&reeks).into_iter().map(|(_, v)| v).collect();
+        assert_eq!(stappen, vec![0.0, 1.5, 0.0, 0.0, 2.6]);
+    }
+
+    #[test]
+    fn test_daggegevens() {
+        let tekst = "\
+# BRON: KONINKLIJK NEDERLANDS METEOROLOGISCH INSTITUUT (KNMI)
+# STN,YYYYMMDD,   TG,    Q
+  260,20240601,  156, 1850
+  260,20240602,     , 1200
+
+  260,20240603,  180, 2210
+";
+        let dagen = daggegevens(tekst).unwrap();
+        assert_eq!(dagen.len(), 2);
+        assert_eq!(dagen[0].datum, NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+        assert!((dagen[0].temperatuur - 15.6).abs() < 1e-12);
+        assert_eq!(dagen[1].straling, 2210.0);
+
+        assert!(matches!(
+            daggegevens("  260,20240601,  156, 1850"),
+            Err(KnmiError::InvalidDaggegevens(_))
+        ));
+        assert!(matches!(
+            daggegevens("# STN,YYYYMMDD,   TG"),
+            Err(KnmiError::InvalidDaggegevens(_))
+        ));
+    }
+
+    #[test]
+    fn test_weer_verwachting_batches() {
+        let verwachting = WeerVerwachting {
+            peilgebied_code: "PG_001".to_string(),
+            neerslag: vec![TimeSeriesDataPoint::new(start(), 1.2)],
+            verdamping: Vec::new(),
+            bron: "knmi:harmonie_arome_cy43_p1".to_string(),
+            opgehaald_op: start(),
+        };
+        let batches = verwachting.to_write_batches();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].series_id.location_id, "PG_001");
+        assert_eq!(batches[0].series_id.parameter, "neerslag");
+        assert_eq!(
+            batches[0].series_id.qualifier.as_deref(),
+            Some(FORECAST_QUALIFIER)
+        );
+    }
+}
@@ -1,38 +1,68 @@
 pub mod alert;
+pub mod alert_report;
 pub mod asset;
+pub mod audit;
 pub mod auth;
+pub mod bergingscurve;
+pub mod bodem;
 pub mod dashboard;
 pub mod dhydro;
 pub mod energie;
+pub mod energiecontract;
 pub mod fews;
 pub mod gemaal;
 pub mod hydronet;
+pub mod knmi;
+pub mod login_throttle;
+pub mod maaiveld;
+pub mod maintenance;
 pub mod peilgebied;
+pub mod pompcurve;
+pub mod pompen;
+pub mod projectie;
 pub mod scenario;
 pub mod sliding_window;
+pub mod template;
 pub mod timeseries;
+pub mod verdamping;
 pub mod waterbalans;
 pub mod websocket;
 
 pub use asset::AssetRegistratie;
+pub use audit::{AuditEntry, AuditQuery};
 pub use auth::{
-    ChangePasswordRequest, Claims, CreateUserRequest, LoginRequest, LoginResponse,
-    Permission, Role, UpdateUserRequest, User, UserInfo,
+    AccountType, ApiKey, ChangePasswordRequest, Claims, ClientInfo, CreateApiKeyRequest,
+    CreateUserRequest, CreatedApiKey, LoginRequest, LoginResponse, PasswordResetConfirmRequest,
+    PasswordResetRequest, Permission, RecoveryCodes, RefreshToken, RefreshTokenRequest,
+    RefreshTokenState, ResourceScope, Role, Session, TotpEnrollment, TwoFactorCodeRequest,
+    TwoFactorPolicy, UpdateUserRequest, User, UserInfo,
 };
+pub use bergingscurve::{BergingsCurve, BergingsPunt};
+pub use bodem::{BodemParameters, Bodemtype, PeilgebiedBodem};
 pub use dhydro::{
-    DhydroClient, DhydroConfig, DhydroError, DhydroModel, OAuthToken, Scenario,
+    DhydroClient, DhydroConfig, DhydroError, DhydroModel, DhydroModelCatalog, OAuthToken, Scenario,
     ScenarioParameters, ScenarioResult, ScenarioResults, ScenarioStatus,
     ScenarioSummary, TimeSeries, TimeSeriesAggregation, TimeSeriesPoint,
     TimeSeriesQuery,
 };
 pub use energie::{
-    JobStatus, OptimizationJob, OptimalisatieParams, OptimalisatieResultaat,
-    OptimalisatieUurResultaat, PriceForecast, PriceSource, PumpSchedule, QueueStats,
-    SimulatieStapUitgebreid, UurPrijs, HourlyPrice,
+    Flexsignaal, JobStatus, OptimizationJob, OptimalisatieKwartierResultaat, OptimalisatieParams,
+    OptimalisatieResultaat, OptimalisatieUurResultaat, PriceForecast, PriceSource, PrijsSignaal,
+    PvInstallatie,
+    PumpSchedule, QueueStats, SimulatieStapUitgebreid, UurPrijs, HourlyPrice,
 };
+pub use energiecontract::{Daltarief, EnergieContract, Netcapaciteit, Tarief, Teruglevering};
 pub use gemaal::{Gemaal, GemaalSnapshot, GemaalStatus, GemaalTrends, StationSummary, TrendDirection, TrendInfo, TrendStrength};
 pub use hydronet::{DataPoint, HydronetSeries};
+pub use knmi::{KnmiError, RegenVerwachting, WeerVerwachting};
+pub use login_throttle::{Lockout, LockoutPolicy, LockoutScope, LoginAttempt};
+pub use maintenance::{
+    CreateMaintenanceWindowRequest, MaintenanceSchedule, MaintenanceWindow, MaintenanceWindowId,
+    UpdateMaintenanceWindowRequest,
+};
 pub use peilgebied::PeilgebiedInfo;
+pub use pompcurve::{PompCurve, PompPunt};
+pub use pompen::Pomp;
 pub use scenario::{
     CloneScenarioRequest, CreateScenarioRequest, ExecutionStatus, ScenarioComparison,
     ScenarioComparisonItem, ScenarioComparisonStats, StoredScenario, StoredScenarioStatus,
@@ -44,20 +74,28 @@ pub use websocket::{
     UnsubscribeRequest, WsMessage,
 };
 pub use alert::{
-    AcknowledgeAlertRequest, Alert, AlertCategory, AlertCondition, AlertQuery, AlertRule,
-    AlertSeverity, AlertStats, AlertStatus, AlertValue,
+    AcknowledgeAlertRequest, Alert, AlertCategory, AlertComment, AlertCondition, AlertEscalation,
+    AlertHistoryEntry, AlertQuery, CreateAlertCommentRequest,
+    AlertRule, AlertSeverity, AlertStats, AlertStatus, AlertValue,
     AggregationFunction as AlertAggregationFunction, ComparisonOperator,
-    ConditionLogic, ConditionResult, CreateAlertRuleRequest, EvaluationContext,
-    NotificationChannel, RuleEvaluationResult, RuleId as AlertRuleId, RuleTriggerCount,
-    TimeSeriesValue, UpdateAlertRuleRequest,
+    ConditionLogic, ConditionResult, CreateAlertRuleRequest, EscalationPolicy, EscalationStep,
+    EvaluationContext, EvaluatorMetrics, FlapEvent, FlapState, FlappingPolicy, NotificationChannel,
+    ResourceSelector, RuleEvaluationResult, RuleEvent, RuleId as AlertRuleId, RuleTriggerCount,
+    SeriesHeartbeat, TimeSeriesValue, UpdateAlertRuleRequest,
+};
+pub use alert_report::{
+    AlertHandlingStats, AlertPeriodStats, AlertRuleStats, AlertTimeline, AlertTrendReport,
+    StatsBucket,
 };
 pub use fews::{
-    FewsConfig, FewsLocation, FewsModuleInstance, FewsParameter,
-    FewsSyncConfig, FewsSyncRequest, FewsSyncResult, FewsTimeSeries, FewsTimeSeriesHeader,
+    CreateFewsMappingRequest, FewsConfig, FewsDocumentFormat, FewsIdMapping, FewsIdTranslator,
+    FewsLocation, FewsMappingKind, FewsModuleInstance, FewsParameter, UpdateFewsMappingRequest,
+    FewsSyncConfig, FewsSyncRequest, FewsSyncResult, FewsSyncRun, FewsSyncTrigger, FewsTimeSeries, FewsTimeSeriesHeader,
     FewsTimeSeriesId, FewsTimeSeriesPoint, FewsTimeSeriesQuery, FewsTimeSeriesResponse,
     FewsTimeStep, FewsValueType,
 };
-pub use waterbalans::{SimulatieParams, SimulatieStap, WaterBalance};
+pub use verdamping::Dagwaarde;
+pub use waterbalans::{Kwelrelatie, SimulatieParams, SimulatieStap, WaterBalance};
 pub use timeseries::{
     AggregatedSeries, AggregationFunction as TsAggregationFunction, AggregationLevel,
     AggregationMetadata, DownsampleConfig, FillMethod, GapAnalysisResult, QualityFlag,
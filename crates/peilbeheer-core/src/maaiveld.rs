@@ -0,0 +1,187 @@
+//! Maaiveldhoogte van een peilgebied uit een hoogteraster.
+//!
+//! Het AHN levert het maaiveld (DTM) als raster in RD New. Het maaiveld
+//! van een peilgebied is de mediaan van de cellen waarvan het middelpunt
+//! binnen de polygoon ligt; de mediaan is ongevoelig voor dijken,
+//! watergangen en losse uitschieters.
+
+use serde_json::Value;
+
+/// Cellen met een hoogte buiten dit bereik (m NAP) zijn nodata. Het AHN
+/// gebruikt de grootste f32 als nodata-waarde.
+const MAX_HOOGTE: f64 = 1000.0;
+
+/// Rechthoekig hoogteraster, rij voor rij vanaf de bovenrand.
+#[derive(Debug, Clone)]
+pub struct Hoogteraster {
+    /// Linkerrand (x) in meter
+    pub min_x: f64,
+    /// Bovenrand (y) in meter
+    pub max_y: f64,
+    /// Celgrootte in meter
+    pub celgrootte: f64,
+    pub breedte: usize,
+    pub hoogte: usize,
+    /// Hoogte per cel in m NAP
+    pub waarden: Vec<f32>,
+}
+
+/// Maaiveld van een peilgebied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaaiveldStatistiek {
+    /// Mediane maaiveldhoogte in m NAP
+    pub mediaan: f64,
+    /// Aantal cellen met een hoogte binnen het peilgebied
+    pub cellen: usize,
+}
+
+impl Hoogteraster {
+    /// Mediane hoogte van de cellen binnen de ringen.
+    ///
+    /// De ringen (buitenranden en gaten, in dezelfde coördinaten als het
+    /// raster) gelden samen volgens de even-oneven-regel. None als geen
+    /// enkele cel met een hoogte binnen de ringen valt.
+    pub fn mediaan_binnen(&self, ringen: &[Vec<(f64, f64)>]) -> Option<MaaiveldStatistiek> {
+        let mut hoogten = Vec::new();
+        let mut snijpunten = Vec::new();
+
+        for rij in 0..self.hoogte {
+            let y = self.max_y - (rij as f64 + 0.5) * self.celgrootte;
+
+            // Snijpunten van de horizontale lijn door de celmiddens met de randen
+            snijpunten.clear();
+            for ring in ringen {
+                for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                    if (a.1 > y) != (b.1 > y) {
+                        snijpunten.push(a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1));
+                    }
+                }
+            }
+            snijpunten.sort_by(f64::total_cmp);
+
+            for paar in snijpunten.chunks_exact(2) {
+                let van = self.kolom(paar[0]);
+                let tot = self.kolom(paar[1]);
+                for kolom in van..tot {
+                    let hoogte = f64::from(self.waarden[rij * self.breedte + kolom]);
+                    if hoogte.is_finite() && hoogte.abs() < MAX_HOOGTE {
+                        hoogten.push(hoogte);
+                    }
+                }
+            }
+        }
+
+        if hoogten.is_empty() {
+            return None;
+        }
+        hoogten.sort_by(f64::total_cmp);
+        let midden = hoogten.len() / 2;
+        let mediaan = if hoogten.len() % 2 == 0 {
+            (hoogten[midden - 1] + hoogten[midden]) / 2.0
+        } else {
+            hoogten[midden]
+        };
+        Some(MaaiveldStatistiek {
+            mediaan,
+            cellen: hoogten.len(),
+        })
+    }
+
+    /// Eerste kolom waarvan het celmidden op of rechts van `x` ligt.
+    fn kolom(&self, x: f64) -> usize {
+        let kolom = ((x - self.min_x) / self.celgrootte - 0.5).ceil();
+        kolom.clamp(0.0, self.breedte as f64) as usize
+    }
+}
+
+/// Ringen van een GeoJSON Polygon of MultiPolygon als (x, y)-punten.
+pub fn ringen_uit_geojson(geometrie: &Value) -> Vec<Vec<(f64, f64)>> {
+    let ring = |ring: &Value| -> Vec<(f64, f64)> {
+        ring.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|punt| Some((punt.get(0)?.as_f64()?, punt.get(1)?.as_f64()?)))
+            .collect()
+    };
+    let polygonen: Vec<&Value> = match geometrie["type"].as_str() {
+        Some("Polygon") => vec![&geometrie["coordinates"]],
+        Some("MultiPolygon") => geometrie["coordinates"]
+            .as_array()
+            .map(|p| p.iter().collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    polygonen
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .map(ring)
+        .filter(|r| r.len() >= 3)
+        .collect()
+}
+
+/// Omhullende (min_x, min_y, max_x, max_y) van ringen.
+pub fn omhullende(ringen: &[Vec<(f64, f64)>]) -> Option<(f64, f64, f64, f64)> {
+    let mut punten = ringen.iter().flatten();
+    let &(x, y) = punten.next()?;
+    Some(
+        punten.fold((x, y, x, y), |(min_x, min_y, max_x, max_y), &(x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raster() -> Hoogteraster {
+        // 4x4 cellen van 1 m; de hoogte is de kolomindex, rechtsonder nodata
+        let mut waarden: Vec<f32> = (0..16).map(|i| (i % 4) as f32).collect();
+        waarden[15] = f32::MAX;
+        Hoogteraster {
+            min_x: 0.0,
+            max_y: 4.0,
+            celgrootte: 1.0,
+            breedte: 4,
+            hoogte: 4,
+            waarden,
+        }
+    }
+
+    #[test]
+    fn test_mediaan_binnen() {
+        let vierkant = vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)];
+        let stat = raster().mediaan_binnen(std::slice::from_ref(&vierkant)).unwrap();
+        // 15 cellen met een hoogte: 4x0, 4x1, 4x2, 3x3
+        assert_eq!(stat.cellen, 15);
+        assert_eq!(stat.mediaan, 1.0);
+
+        // Een gat over de twee linker kolommen laat alleen 2 en 3 over
+        let gat = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 4.0), (0.0, 4.0)];
+        let stat = raster().mediaan_binnen(&[vierkant, gat]).unwrap();
+        assert_eq!(stat.cellen, 7);
+        assert_eq!(stat.mediaan, 2.0);
+
+        let buiten = vec![(10.0, 10.0), (12.0, 10.0), (12.0, 12.0)];
+        assert!(raster().mediaan_binnen(&[buiten]).is_none());
+    }
+
+    #[test]
+    fn test_ringen_uit_geojson() {
+        let multi = serde_json::json!({
+            "type": "MultiPolygon",
+            "coordinates": [
+                [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]],
+                [[[5.0, 5.0], [6.0, 5.0], [6.0, 7.0], [5.0, 5.0]]]
+            ]
+        });
+        let ringen = ringen_uit_geojson(&multi);
+        assert_eq!(ringen.len(), 2);
+        assert_eq!(omhullende(&ringen), Some((0.0, 0.0, 6.0, 7.0)));
+
+        let punt = serde_json::json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        assert!(ringen_uit_geojson(&punt).is_empty());
+        assert_eq!(omhullende(&[]), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Maintenance windows for alert suppression.
+//!
+//! During planned maintenance (e.g. a gemaal being serviced) alerts are still
+//! recorded, but stored as suppressed and not notified. A window can be
+//! scoped to specific rules and/or resources and is either a one-off period
+//! or a recurring cron schedule.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::alert::RuleId;
+
+/// Unique identifier for a maintenance window.
+pub type MaintenanceWindowId = String;
+
+/// Planned maintenance window during which alerts are suppressed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Unique window identifier
+    pub id: MaintenanceWindowId,
+
+    /// Human-readable name
+    pub name: String,
+
+    /// Detailed description
+    pub description: Option<String>,
+
+    /// Rules to suppress (empty = all rules)
+    pub rule_ids: Vec<RuleId>,
+
+    /// Resources to suppress, e.g. gemaal codes (empty = all resources)
+    pub resources: Vec<String>,
+
+    /// When the window is active
+    pub schedule: MaintenanceSchedule,
+
+    /// Whether the window is in use
+    pub enabled: bool,
+
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+
+    /// Last modification timestamp
+    pub updated_at: DateTime<Utc>,
+
+    /// Creator user ID
+    pub created_by: Option<String>,
+}
+
+/// Schedule of a maintenance window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaintenanceSchedule {
+    /// Single period
+    Once {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// Recurring period starting at each cron occurrence (UTC)
+    Recurring {
+        /// Cron expression, standard 5-field or with leading seconds field
+        cron: String,
+        /// Length of each occurrence (minutes)
+        duration_minutes: u32,
+    },
+}
+
+impl MaintenanceSchedule {
+    /// Check whether the schedule covers the given moment.
+    pub fn is_active_at(&self, at: DateTime<Utc>) -> bool {
+        match self {
+            Self::Once { start, end } => *start <= at && at < *end,
+            Self::Recurring { cron, duration_minutes } => {
+                let Ok(schedule) = parse_cron(cron) else {
+                    return false;
+                };
+                // Active when an occurrence started within the last `duration_minutes`
+                let window_start = at - Duration::minutes(*duration_minutes as i64);
+                schedule
+                    .after(&window_start)
+                    .next()
+                    .is_some_and(|occurrence| occurrence <= at)
+            }
+        }
+    }
+
+    /// Validate the schedule definition.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        match self {
+            Self::Once { start, end } => {
+                if end <= start {
+                    errors.push("schedule: end must be after start".to_string());
+                }
+            }
+            Self::Recurring { cron, duration_minutes } => {
+                if let Err(e) = parse_cron(cron) {
+                    errors.push(format!("schedule: invalid cron expression '{}': {}", cron, e));
+                }
+                if *duration_minutes == 0 {
+                    errors.push("schedule: duration_minutes must be greater than 0".to_string());
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl MaintenanceWindow {
+    /// Check whether the window is enabled and active at the given moment.
+    pub fn is_active_at(&self, at: DateTime<Utc>) -> bool {
+        self.enabled && self.schedule.is_active_at(at)
+    }
+
+    /// Check whether the window applies to an alert of `rule_id` on `resources`.
+    pub fn covers(&self, rule_id: &str, resources: &[String]) -> bool {
+        let rule_matches = self.rule_ids.is_empty() || self.rule_ids.iter().any(|r| r == rule_id);
+        let resource_matches = self.resources.is_empty()
+            || resources.iter().any(|res| self.resources.contains(res));
+        rule_matches && resource_matches
+    }
+
+    /// Validate the window definition.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if self.name.is_empty() {
+            errors.push("name cannot be empty".to_string());
+        }
+
+        if let Err(schedule_errors) = self.schedule.validate() {
+            errors.extend(schedule_errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Create maintenance window request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMaintenanceWindowRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub rule_ids: Vec<RuleId>,
+    #[serde(default)]
+    pub resources: Vec<String>,
+    pub schedule: MaintenanceSchedule,
+}
+
+/// Update maintenance window request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMaintenanceWindowRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub rule_ids: Option<Vec<RuleId>>,
+    pub resources: Option<Vec<String>>,
+    pub schedule: Option<MaintenanceSchedule>,
+    pub enabled: Option<bool>,
+}
+
+/// Parse a cron expression, accepting the standard 5-field form.
+pub(crate) fn parse_cron(expr: &str) -> Result<cron::Schedule, cron::error::Error> {
+    let expr = expr.trim();
+    if expr.split_whitespace().count() == 5 {
+        cron::Schedule::from_str(&format!("0 {}", expr))
+    } else {
+        cron::Schedule::from_str(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(schedule: MaintenanceSchedule) -> MaintenanceWindow {
+        let now = Utc::now();
+        MaintenanceWindow {
+            id: "MW_001".to_string(),
+            name: "Onderhoud gemaal".to_string(),
+            description: None,
+            rule_ids: vec![],
+            resources: vec![],
+            schedule,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn test_once_schedule() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 10, 8, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 3, 10, 16, 0, 0).unwrap();
+        let mw = window(MaintenanceSchedule::Once { start, end });
+
+        assert!(mw.validate().is_ok());
+        assert!(mw.is_active_at(start));
+        assert!(mw.is_active_at(start + Duration::hours(4)));
+        assert!(!mw.is_active_at(end));
+        assert!(!mw.is_active_at(start - Duration::minutes(1)));
+
+        let disabled = MaintenanceWindow { enabled: false, ..mw };
+        assert!(!disabled.is_active_at(start + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_recurring_schedule() {
+        // Every Tuesday 07:00 UTC for two hours
+        let mw = window(MaintenanceSchedule::Recurring {
+            cron: "0 7 * * Tue".to_string(),
+            duration_minutes: 120,
+        });
+        assert!(mw.validate().is_ok());
+
+        // 2025-03-11 is a Tuesday
+        let tuesday = |h, m| Utc.with_ymd_and_hms(2025, 3, 11, h, m, 0).unwrap();
+        assert!(mw.is_active_at(tuesday(7, 0)));
+        assert!(mw.is_active_at(tuesday(8, 59)));
+        assert!(!mw.is_active_at(tuesday(9, 0)));
+        assert!(!mw.is_active_at(tuesday(6, 59)));
+        assert!(!mw.is_active_at(Utc.with_ymd_and_hms(2025, 3, 12, 7, 30, 0).unwrap()));
+
+        let invalid = window(MaintenanceSchedule::Recurring {
+            cron: "not a cron".to_string(),
+            duration_minutes: 0,
+        });
+        assert_eq!(invalid.validate().unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn test_window_scope() {
+        let start = Utc::now();
+        let mut mw = window(MaintenanceSchedule::Once { start, end: start + Duration::hours(1) });
+        let resources = vec!["GEMAAL_001".to_string()];
+
+        // Unscoped window covers everything
+        assert!(mw.covers("RULE_1", &resources));
+        assert!(mw.covers("RULE_1", &[]));
+
+        mw.resources = vec!["GEMAAL_001".to_string()];
+        assert!(mw.covers("RULE_1", &resources));
+        assert!(!mw.covers("RULE_1", &["GEMAAL_002".to_string()]));
+
+        mw.rule_ids = vec!["RULE_2".to_string()];
+        assert!(!mw.covers("RULE_1", &resources));
+        assert!(mw.covers("RULE_2", &resources));
+    }
+}
@@ -9,4 +9,7 @@ pub struct PeilgebiedInfo {
     pub vastpeil: Option<f64>,
     pub oppervlakte: Option<f64>,
     pub soortafwatering: Option<String>,
+    /// Mediane maaiveldhoogte in m NAP, uit het AHN
+    #[serde(default)]
+    pub maaiveld: Option<f64>,
 }
@@ -0,0 +1,171 @@
+//! Pompkarakteristieken (Q-H curves).
+//!
+//! Het debiet van een gemaal zakt naarmate de opvoerhoogte stijgt, bij
+//! hoog buitenwater tot de nulopbrengsthoogte. Een [`PompCurve`] geeft
+//! het debiet en het rendement bij een opvoerhoogte door lineair te
+//! interpoleren tussen gemeten punten van de fabrikant of een pompproef.
+
+use serde::{Deserialize, Serialize};
+
+/// Eén punt van een pompkarakteristiek.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PompPunt {
+    /// Opvoerhoogte in m
+    pub opvoerhoogte: f64,
+    /// Debiet bij deze opvoerhoogte in m³/s
+    pub debiet: f64,
+    /// Rendement van pomp en motor bij deze opvoerhoogte (0-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendement: Option<f64>,
+}
+
+impl PompPunt {
+    pub fn new(opvoerhoogte: f64, debiet: f64) -> Self {
+        Self {
+            opvoerhoogte,
+            debiet,
+            rendement: None,
+        }
+    }
+
+    pub fn met_rendement(mut self, rendement: f64) -> Self {
+        self.rendement = Some(rendement);
+        self
+    }
+}
+
+/// Pompkarakteristiek: punten oplopend in opvoerhoogte.
+///
+/// Onder het eerste punt geldt het debiet van het eerste punt, boven het
+/// laatste dat van het laatste punt; eindigt de curve bij de
+/// nulopbrengsthoogte, dan pompt het gemaal daarboven niets meer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PompCurve {
+    pub punten: Vec<PompPunt>,
+}
+
+impl PompCurve {
+    /// Curve uit punten; de punten worden gesorteerd op opvoerhoogte.
+    pub fn new(mut punten: Vec<PompPunt>) -> Result<Self, String> {
+        punten.sort_by(|a, b| a.opvoerhoogte.total_cmp(&b.opvoerhoogte));
+        let curve = Self { punten };
+        curve.valideer()?;
+        Ok(curve)
+    }
+
+    /// Controleer of de curve bruikbaar is.
+    pub fn valideer(&self) -> Result<(), String> {
+        if self.punten.is_empty() {
+            return Err("Pompcurve heeft geen punten".into());
+        }
+        for punt in &self.punten {
+            if !punt.opvoerhoogte.is_finite() || !punt.debiet.is_finite() || punt.debiet < 0.0 {
+                return Err(format!(
+                    "Ongeldig pompcurvepunt: {} m, {} m³/s",
+                    punt.opvoerhoogte, punt.debiet
+                ));
+            }
+            if let Some(rendement) = punt.rendement
+                && !(rendement > 0.0 && rendement <= 1.0)
+            {
+                return Err(format!("Ongeldig rendement in pompcurve: {}", rendement));
+            }
+        }
+        if self
+            .punten
+            .windows(2)
+            .any(|paar| paar[1].opvoerhoogte <= paar[0].opvoerhoogte)
+        {
+            return Err("Opvoerhoogten van de pompcurve moeten oplopen".into());
+        }
+        Ok(())
+    }
+
+    /// Debiet in m³/s bij een opvoerhoogte.
+    pub fn debiet(&self, opvoerhoogte: f64) -> f64 {
+        self.interpoleer(opvoerhoogte, |p| Some(p.debiet))
+            .unwrap_or(0.0)
+            .max(0.0)
+    }
+
+    /// Rendement bij een opvoerhoogte; None als de curve geen rendementen
+    /// bevat.
+    pub fn rendement(&self, opvoerhoogte: f64) -> Option<f64> {
+        self.interpoleer(opvoerhoogte, |p| p.rendement)
+    }
+
+    /// Hoogste debiet van de curve in m³/s.
+    pub fn max_debiet(&self) -> f64 {
+        self.punten.iter().map(|p| p.debiet).fold(0.0, f64::max)
+    }
+
+    /// Lineaire interpolatie van een waarde over de punten die haar hebben.
+    fn interpoleer(
+        &self,
+        opvoerhoogte: f64,
+        waarde: impl Fn(&PompPunt) -> Option<f64>,
+    ) -> Option<f64> {
+        let punten: Vec<(f64, f64)> = self
+            .punten
+            .iter()
+            .filter_map(|p| Some((p.opvoerhoogte, waarde(p)?)))
+            .collect();
+        let (eerste, laatste) = (punten.first()?, punten.last()?);
+        if opvoerhoogte <= eerste.0 {
+            return Some(eerste.1);
+        }
+        if opvoerhoogte >= laatste.0 {
+            return Some(laatste.1);
+        }
+        punten.windows(2).find_map(|paar| {
+            let ((h0, w0), (h1, w1)) = (paar[0], paar[1]);
+            (opvoerhoogte <= h1).then(|| w0 + (w1 - w0) * (opvoerhoogte - h0) / (h1 - h0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> PompCurve {
+        PompCurve::new(vec![
+            PompPunt::new(3.0, 0.0),
+            PompPunt::new(0.5, 2.0).met_rendement(0.60),
+            PompPunt::new(2.0, 1.0).met_rendement(0.75),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_debiet_volgt_opvoerhoogte() {
+        let curve = curve();
+        assert_eq!(curve.punten[0].opvoerhoogte, 0.5);
+        assert_eq!(curve.max_debiet(), 2.0);
+
+        assert_eq!(curve.debiet(0.0), 2.0);
+        assert!((curve.debiet(1.25) - 1.5).abs() < 1e-12);
+        assert!((curve.debiet(2.5) - 0.5).abs() < 1e-12);
+        // Boven de nulopbrengsthoogte pompt het gemaal niets
+        assert_eq!(curve.debiet(4.0), 0.0);
+    }
+
+    #[test]
+    fn test_rendement() {
+        let curve = curve();
+        assert!((curve.rendement(1.25).unwrap() - 0.675).abs() < 1e-12);
+        // Boven het laatste punt met een rendement geldt dat rendement
+        assert_eq!(curve.rendement(2.8), Some(0.75));
+
+        let zonder = PompCurve::new(vec![PompPunt::new(1.0, 1.0)]).unwrap();
+        assert_eq!(zonder.rendement(1.0), None);
+    }
+
+    #[test]
+    fn test_valideer() {
+        assert!(PompCurve::new(Vec::new()).is_err());
+        assert!(PompCurve::new(vec![PompPunt::new(1.0, -1.0)]).is_err());
+        assert!(PompCurve::new(vec![PompPunt::new(1.0, 1.0), PompPunt::new(1.0, 0.5)]).is_err());
+        assert!(PompCurve::new(vec![PompPunt::new(1.0, 1.0).met_rendement(1.5)]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Pompen van een gemaal met trapsgewijze inschakeling.
+//!
+//! Veel gemalen hebben twee of drie pompen. Ze worden in een vaste
+//! volgorde ingeschakeld: de eerste pomp bij een klein peilverschil, de
+//! volgende pas als het peil verder stijgt, en uitgeschakeld in omgekeerde
+//! volgorde. Elke pomp heeft een eigen capaciteit, rendement of
+//! pompkarakteristiek en in- en uitschakelpeil.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::pompcurve::PompCurve;
+
+/// Eén pomp van een gemaal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pomp {
+    pub id: String,
+    /// Plaats in de inschakelvolgorde (laagste eerst)
+    #[serde(default)]
+    pub volgorde: u32,
+    /// Debiet bij volle inzet in m³/s
+    pub debiet: f64,
+    /// Rendement (0-1) als de pompcurve het niet geeft
+    #[serde(default = "default_rendement")]
+    pub rendement: f64,
+    /// Pompkarakteristiek; het debiet volgt dan uit de opvoerhoogte
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pompcurve: Option<PompCurve>,
+    /// Inschakelpeil ten opzichte van het streefpeil in m
+    pub inschakelpeil: f64,
+    /// Uitschakelpeil ten opzichte van het streefpeil in m
+    pub uitschakelpeil: f64,
+}
+
+fn default_rendement() -> f64 {
+    0.70
+}
+
+impl Pomp {
+    pub fn new(
+        id: impl Into<String>,
+        debiet: f64,
+        inschakelpeil: f64,
+        uitschakelpeil: f64,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            volgorde: 0,
+            debiet,
+            rendement: default_rendement(),
+            pompcurve: None,
+            inschakelpeil,
+            uitschakelpeil,
+        }
+    }
+
+    pub fn met_volgorde(mut self, volgorde: u32) -> Self {
+        self.volgorde = volgorde;
+        self
+    }
+
+    pub fn met_rendement(mut self, rendement: f64) -> Self {
+        self.rendement = rendement;
+        self
+    }
+
+    pub fn met_pompcurve(mut self, pompcurve: PompCurve) -> Self {
+        self.pompcurve = Some(pompcurve);
+        self
+    }
+
+    /// Debiet bij volle inzet en een opvoerhoogte (m³/s).
+    pub fn debiet_bij(&self, opvoerhoogte: f64) -> f64 {
+        match &self.pompcurve {
+            Some(curve) => curve.debiet(opvoerhoogte),
+            None => self.debiet,
+        }
+    }
+
+    /// Rendement bij een opvoerhoogte.
+    pub fn rendement_bij(&self, opvoerhoogte: f64) -> f64 {
+        self.pompcurve
+            .as_ref()
+            .and_then(|curve| curve.rendement(opvoerhoogte))
+            .unwrap_or(self.rendement)
+    }
+}
+
+/// De pompen in inschakelvolgorde.
+pub fn in_volgorde(pompen: &[Pomp]) -> Vec<Pomp> {
+    let mut pompen = pompen.to_vec();
+    pompen.sort_by_key(|p| p.volgorde);
+    pompen
+}
+
+/// Controleer de pompen van een gemaal.
+pub fn valideer(pompen: &[Pomp]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for pomp in pompen {
+        if !ids.insert(pomp.id.as_str()) {
+            return Err(format!("Pomp {} komt meer dan eens voor", pomp.id));
+        }
+        if !pomp.debiet.is_finite() || pomp.debiet < 0.0 {
+            return Err(format!("Debiet van pomp {} moet >= 0 zijn", pomp.id));
+        }
+        if !(pomp.rendement > 0.0 && pomp.rendement <= 1.0) {
+            return Err(format!(
+                "Rendement van pomp {} moet tussen 0 en 1 liggen",
+                pomp.id
+            ));
+        }
+        if pomp.uitschakelpeil >= pomp.inschakelpeil {
+            return Err(format!(
+                "Uitschakelpeil van pomp {} moet onder het inschakelpeil liggen",
+                pomp.id
+            ));
+        }
+        if let Some(curve) = &pomp.pompcurve {
+            curve.valideer()?;
+        }
+    }
+    Ok(())
+}
+
+/// Verdeel een debiet over pompen met de gegeven capaciteiten (m³/s).
+///
+/// De pompen worden in volgorde volgezet: een pomp draait pas als de
+/// vorige vol draait. Retourneert de inzet per pomp (0-1).
+pub fn verdeel(capaciteiten: &[f64], debiet: f64) -> Vec<f64> {
+    let mut rest = debiet.max(0.0);
+    capaciteiten
+        .iter()
+        .map(|&capaciteit| {
+            if capaciteit <= 0.0 {
+                return 0.0;
+            }
+            let inzet = (rest / capaciteit).min(1.0);
+            rest -= inzet * capaciteit;
+            inzet
+        })
+        .collect()
+}
+
+/// Schakel pompen (in inschakelvolgorde) op de afwijking van het
+/// streefpeil in m.
+///
+/// Een pomp gaat aan boven zijn inschakelpeil als de vorige pomp draait en
+/// uit onder zijn uitschakelpeil als de volgende pomp uit staat; daartussen
+/// blijft hij in zijn toestand.
+pub fn schakel(pompen: &[Pomp], aan: &mut [bool], afwijking: f64) {
+    for i in 0..pompen.len() {
+        let vorige_aan = i == 0 || aan[i - 1];
+        if !aan[i] && vorige_aan && afwijking >= pompen[i].inschakelpeil {
+            aan[i] = true;
+        }
+    }
+    for i in (0..pompen.len()).rev() {
+        let volgende_uit = i + 1 == pompen.len() || !aan[i + 1];
+        if aan[i] && volgende_uit && afwijking <= pompen[i].uitschakelpeil {
+            aan[i] = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pompen() -> Vec<Pomp> {
+        in_volgorde(&[
+            Pomp::new("p2", 1.0, 0.10, 0.02).met_volgorde(2),
+            Pomp::new("p1", 0.5, 0.05, 0.0).met_volgorde(1),
+        ])
+    }
+
+    #[test]
+    fn test_verdeel_in_volgorde() {
+        assert_eq!(verdeel(&[0.5, 1.0], 0.25), vec![0.5, 0.0]);
+        assert_eq!(verdeel(&[0.5, 1.0], 1.0), vec![1.0, 0.5]);
+        assert_eq!(verdeel(&[0.5, 1.0], 2.0), vec![1.0, 1.0]);
+        assert_eq!(verdeel(&[0.0, 1.0], 0.5), vec![0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_trapsgewijs_schakelen() {
+        let pompen = pompen();
+        assert_eq!(pompen[0].id, "p1");
+        let mut aan = vec![false; 2];
+
+        schakel(&pompen, &mut aan, 0.06);
+        assert_eq!(aan, vec![true, false]);
+        schakel(&pompen, &mut aan, 0.12);
+        assert_eq!(aan, vec![true, true]);
+        // Hysterese: tussen de schakelpeilen blijven beide aan
+        schakel(&pompen, &mut aan, 0.03);
+        assert_eq!(aan, vec![true, true]);
+        // Uit in omgekeerde volgorde
+        schakel(&pompen, &mut aan, 0.01);
+        assert_eq!(aan, vec![true, false]);
+        schakel(&pompen, &mut aan, -0.01);
+        assert_eq!(aan, vec![false, false]);
+    }
+
+    #[test]
+    fn test_valideer() {
+        assert!(valideer(&pompen()).is_ok());
+        assert!(valideer(&[Pomp::new("p", 1.0, 0.0, 0.05)]).is_err());
+        assert!(valideer(&[Pomp::new("p", 1.0, 0.1, 0.0), Pomp::new("p", 1.0, 0.1, 0.0)]).is_err());
+        assert!(valideer(&[Pomp::new("p", 1.0, 0.1, 0.0).met_rendement(0.0)]).is_err());
+    }
+}
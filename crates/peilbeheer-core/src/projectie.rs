@@ -0,0 +1,350 @@
+//! Herprojectie tussen RD New (EPSG:28992) en WGS84 (EPSG:4326).
+//!
+//! De transformatie gebruikt de benaderingsformules van Schreutelaar
+//! (polynomen rond Amersfoort), nauwkeurig tot ongeveer een meter binnen
+//! Nederland. Dat volstaat voor kaartweergave en ruimtelijke koppelingen;
+//! voor landmeetkundige nauwkeurigheid is RDNAPTRANS nodig.
+//!
+//! Coördinaten volgen de GeoJSON-volgorde: `[x, y]` in RD en
+//! `[lon, lat]` in WGS84.
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// RD-coördinaten van het referentiepunt Amersfoort.
+const X0: f64 = 155_000.0;
+const Y0: f64 = 463_000.0;
+/// WGS84-coördinaten van Amersfoort in graden.
+const PHI0: f64 = 52.155_174_40;
+const LAM0: f64 = 5.387_206_21;
+
+/// Coëfficiënten (p, q, K) van RD naar breedte: Σ K·dX^p·dY^q (boogseconden).
+const K_LAT: [(i32, i32, f64); 11] = [
+    (0, 1, 3_235.653_89),
+    (2, 0, -32.582_97),
+    (0, 2, -0.2475),
+    (2, 1, -0.849_78),
+    (0, 3, -0.0655),
+    (2, 2, -0.017_09),
+    (1, 0, -0.007_38),
+    (4, 0, 0.0053),
+    (2, 3, -0.000_39),
+    (4, 1, 0.000_33),
+    (1, 1, -0.000_12),
+];
+
+/// Coëfficiënten (p, q, L) van RD naar lengte: Σ L·dX^p·dY^q (boogseconden).
+const L_LON: [(i32, i32, f64); 12] = [
+    (1, 0, 5_260.529_16),
+    (1, 1, 105.946_84),
+    (1, 2, 2.456_56),
+    (3, 0, -0.818_85),
+    (1, 3, 0.055_94),
+    (3, 1, -0.056_07),
+    (0, 1, 0.011_99),
+    (3, 2, -0.002_56),
+    (1, 4, 0.001_28),
+    (0, 2, 0.000_22),
+    (2, 0, -0.000_22),
+    (5, 0, 0.000_26),
+];
+
+/// Coëfficiënten (p, q, R) van WGS84 naar x: Σ R·dφ^p·dλ^q (meter).
+const R_X: [(i32, i32, f64); 9] = [
+    (0, 1, 190_094.945),
+    (1, 1, -11_832.228),
+    (2, 1, -114.221),
+    (0, 3, -32.391),
+    (1, 0, -0.705),
+    (3, 1, -2.340),
+    (1, 3, -0.608),
+    (0, 2, -0.008),
+    (2, 3, 0.148),
+];
+
+/// Coëfficiënten (p, q, S) van WGS84 naar y: Σ S·dφ^p·dλ^q (meter).
+const S_Y: [(i32, i32, f64); 10] = [
+    (1, 0, 309_056.544),
+    (0, 2, 3_638.893),
+    (2, 0, 73.077),
+    (1, 2, -157.984),
+    (3, 0, 59.788),
+    (0, 1, 0.433),
+    (2, 2, -6.439),
+    (1, 1, -0.032),
+    (0, 4, 0.092),
+    (1, 4, -0.054),
+];
+
+/// Fouten bij het herprojecteren.
+#[derive(Debug, Error)]
+pub enum ProjectieFout {
+    #[error("Onbekend coördinatenstelsel: {0}")]
+    OnbekendeCrs(String),
+}
+
+/// Ondersteunde coördinatenstelsels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crs {
+    /// EPSG:4326
+    Wgs84,
+    /// EPSG:28992 (Amersfoort / RD New)
+    RdNew,
+}
+
+impl Crs {
+    pub fn epsg(self) -> u32 {
+        match self {
+            Crs::Wgs84 => 4326,
+            Crs::RdNew => 28992,
+        }
+    }
+
+    /// Stelsel uit een naam als `EPSG:28992`,
+    /// `urn:ogc:def:crs:EPSG::28992` of `urn:ogc:def:crs:OGC:1.3:CRS84`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.ends_with("CRS84") {
+            return Some(Crs::Wgs84);
+        }
+        let code = name.rsplit(':').next()?.trim();
+        match code {
+            "4326" => Some(Crs::Wgs84),
+            "28992" => Some(Crs::RdNew),
+            _ => None,
+        }
+    }
+}
+
+/// RD-coördinaat naar WGS84, als (lon, lat) in graden.
+pub fn rd_naar_wgs84(x: f64, y: f64) -> (f64, f64) {
+    let dx = (x - X0) * 1e-5;
+    let dy = (y - Y0) * 1e-5;
+    let lat = PHI0 + polynoom(&K_LAT, dx, dy) / 3600.0;
+    let lon = LAM0 + polynoom(&L_LON, dx, dy) / 3600.0;
+    (lon, lat)
+}
+
+/// WGS84-coördinaat (graden) naar RD, als (x, y) in meter.
+pub fn wgs84_naar_rd(lon: f64, lat: f64) -> (f64, f64) {
+    let dphi = 0.36 * (lat - PHI0);
+    let dlam = 0.36 * (lon - LAM0);
+    let x = X0 + polynoom(&R_X, dphi, dlam);
+    let y = Y0 + polynoom(&S_Y, dphi, dlam);
+    (x, y)
+}
+
+fn polynoom(coefficienten: &[(i32, i32, f64)], a: f64, b: f64) -> f64 {
+    coefficienten
+        .iter()
+        .map(|&(p, q, c)| c * a.powi(p) * b.powi(q))
+        .sum()
+}
+
+/// Stelsel van een GeoJSON-object: het `crs`-lid als dat er is, anders
+/// afgeleid uit de eerste coördinaat (RD-waarden liggen ver buiten het
+/// bereik van graden). None als er geen coördinaten zijn.
+pub fn detecteer_crs(geojson: &Value) -> Result<Option<Crs>, ProjectieFout> {
+    if let Some(name) = geojson
+        .pointer("/crs/properties/name")
+        .and_then(|n| n.as_str())
+    {
+        return Crs::from_name(name)
+            .map(Some)
+            .ok_or_else(|| ProjectieFout::OnbekendeCrs(name.to_string()));
+    }
+    Ok(eerste_coordinaat(geojson).map(|(x, y)| {
+        if x.abs() > 180.0 || y.abs() > 90.0 {
+            Crs::RdNew
+        } else {
+            Crs::Wgs84
+        }
+    }))
+}
+
+/// Herprojecteer een GeoJSON-object (FeatureCollection, Feature of
+/// geometrie) naar `naar`. Het bronstelsel wordt bepaald met
+/// [`detecteer_crs`]. Retourneert of er iets is omgerekend.
+pub fn herprojecteer(geojson: &mut Value, naar: Crs) -> Result<bool, ProjectieFout> {
+    match detecteer_crs(geojson)? {
+        Some(van) => herprojecteer_van(geojson, van, naar),
+        None => Ok(false),
+    }
+}
+
+/// Herprojecteer een GeoJSON-object van `van` naar `naar`.
+///
+/// Het `crs`-lid wordt bijgewerkt; voor WGS84 vervalt het, omdat dat de
+/// standaard is van GeoJSON (RFC 7946).
+pub fn herprojecteer_van(geojson: &mut Value, van: Crs, naar: Crs) -> Result<bool, ProjectieFout> {
+    if van == naar {
+        return Ok(false);
+    }
+    let transformeer: fn(f64, f64) -> (f64, f64) = match (van, naar) {
+        (Crs::RdNew, Crs::Wgs84) => rd_naar_wgs84,
+        (Crs::Wgs84, Crs::RdNew) => wgs84_naar_rd,
+        _ => unreachable!("gelijke stelsels zijn al afgehandeld"),
+    };
+    transformeer_object(geojson, transformeer);
+
+    if let Some(object) = geojson.as_object_mut() {
+        match naar {
+            Crs::Wgs84 => {
+                object.remove("crs");
+            }
+            Crs::RdNew => {
+                object.insert(
+                    "crs".to_string(),
+                    serde_json::json!({
+                        "type": "name",
+                        "properties": {"name": "urn:ogc:def:crs:EPSG::28992"}
+                    }),
+                );
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn transformeer_object(value: &mut Value, transformeer: fn(f64, f64) -> (f64, f64)) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    if let Some(coordinates) = object.get_mut("coordinates") {
+        transformeer_coordinaten(coordinates, transformeer);
+    }
+    for lid in ["geometry", "geometries", "features"] {
+        match object.get_mut(lid) {
+            Some(Value::Array(items)) => {
+                for item in items {
+                    transformeer_object(item, transformeer);
+                }
+            }
+            Some(item @ Value::Object(_)) => transformeer_object(item, transformeer),
+            _ => {}
+        }
+    }
+    // Een bbox klopt na het omrekenen niet meer
+    object.remove("bbox");
+}
+
+fn transformeer_coordinaten(value: &mut Value, transformeer: fn(f64, f64) -> (f64, f64)) {
+    let Value::Array(items) = value else {
+        return;
+    };
+    match (
+        items.first().and_then(Value::as_f64),
+        items.get(1).and_then(Value::as_f64),
+    ) {
+        (Some(x), Some(y)) => {
+            let (nx, ny) = transformeer(x, y);
+            items[0] = Value::from(nx);
+            items[1] = Value::from(ny);
+        }
+        _ => {
+            for item in items {
+                transformeer_coordinaten(item, transformeer);
+            }
+        }
+    }
+}
+
+fn eerste_coordinaat(value: &Value) -> Option<(f64, f64)> {
+    fn in_coordinaten(value: &Value) -> Option<(f64, f64)> {
+        let items = value.as_array()?;
+        match (
+            items.first()?.as_f64(),
+            items.get(1).and_then(Value::as_f64),
+        ) {
+            (Some(x), Some(y)) => Some((x, y)),
+            _ => items.iter().find_map(in_coordinaten),
+        }
+    }
+
+    let object = value.as_object()?;
+    if let Some(coordinaat) = object.get("coordinates").and_then(in_coordinaten) {
+        return Some(coordinaat);
+    }
+    ["geometry", "geometries", "features"]
+        .iter()
+        .filter_map(|lid| object.get(*lid))
+        .find_map(|item| match item {
+            Value::Array(items) => items.iter().find_map(eerste_coordinaat),
+            item => eerste_coordinaat(item),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_amersfoort_en_heen_en_terug() {
+        let (lon, lat) = rd_naar_wgs84(X0, Y0);
+        assert!((lon - LAM0).abs() < 1e-9);
+        assert!((lat - PHI0).abs() < 1e-9);
+
+        // Leiden, stadhuis (ongeveer RD 93800, 463800)
+        let (lon, lat) = rd_naar_wgs84(93_800.0, 463_800.0);
+        assert!((lat - 52.158).abs() < 0.01, "lat {}", lat);
+        assert!((lon - 4.49).abs() < 0.01, "lon {}", lon);
+
+        let (x, y) = wgs84_naar_rd(lon, lat);
+        assert!((x - 93_800.0).abs() < 1.0, "x {}", x);
+        assert!((y - 463_800.0).abs() < 1.0, "y {}", y);
+    }
+
+    #[test]
+    fn test_crs_namen() {
+        assert_eq!(Crs::from_name("EPSG:28992"), Some(Crs::RdNew));
+        assert_eq!(
+            Crs::from_name("urn:ogc:def:crs:EPSG::28992"),
+            Some(Crs::RdNew)
+        );
+        assert_eq!(
+            Crs::from_name("urn:ogc:def:crs:OGC:1.3:CRS84"),
+            Some(Crs::Wgs84)
+        );
+        assert_eq!(Crs::from_name("EPSG:3857"), None);
+    }
+
+    #[test]
+    fn test_herprojecteer_feature_collection() {
+        let mut collectie = json!({
+            "type": "FeatureCollection",
+            "crs": {"type": "name", "properties": {"name": "EPSG:28992"}},
+            "features": [
+                {"type": "Feature", "properties": {"CODE": "PG_001"}, "bbox": [0, 0, 1, 1],
+                 "geometry": {"type": "Polygon", "coordinates": [[
+                     [93800.0, 463800.0], [93900.0, 463800.0], [93900.0, 463900.0], [93800.0, 463800.0]
+                 ]]}},
+                {"type": "Feature", "properties": {}, "geometry": null}
+            ]
+        });
+
+        assert_eq!(detecteer_crs(&collectie).unwrap(), Some(Crs::RdNew));
+        assert!(herprojecteer(&mut collectie, Crs::Wgs84).unwrap());
+        assert!(collectie.get("crs").is_none());
+        assert!(collectie["features"][0].get("bbox").is_none());
+        let punt = &collectie["features"][0]["geometry"]["coordinates"][0][0];
+        assert!((punt[0].as_f64().unwrap() - 4.49).abs() < 0.01);
+        assert!((punt[1].as_f64().unwrap() - 52.158).abs() < 0.01);
+        assert_eq!(collectie["features"][0]["properties"]["CODE"], "PG_001");
+
+        // Zonder crs-lid herkend aan de coördinaten; al WGS84 verandert niets
+        assert_eq!(detecteer_crs(&collectie).unwrap(), Some(Crs::Wgs84));
+        assert!(!herprojecteer(&mut collectie, Crs::Wgs84).unwrap());
+
+        assert!(herprojecteer(&mut collectie, Crs::RdNew).unwrap());
+        let punt = &collectie["features"][0]["geometry"]["coordinates"][0][0];
+        assert!((punt[0].as_f64().unwrap() - 93_800.0).abs() < 1.0);
+        assert_eq!(detecteer_crs(&collectie).unwrap(), Some(Crs::RdNew));
+
+        let mut punt = json!({"type": "Point", "coordinates": [155000.0, 463000.0]});
+        assert!(herprojecteer(&mut punt, Crs::Wgs84).unwrap());
+        assert!((punt["coordinates"][1].as_f64().unwrap() - PHI0).abs() < 1e-9);
+
+        let mut onbekend = json!({"type": "Point", "crs": {"properties": {"name": "EPSG:3857"}}, "coordinates": [0, 0]});
+        assert!(herprojecteer(&mut onbekend, Crs::Wgs84).is_err());
+    }
+}
@@ -6,6 +6,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::dhydro::{Scenario, ScenarioParameters, ScenarioStatus};
+
 /// Status van een opgeslagen scenario.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -74,6 +76,23 @@ impl ExecutionStatus {
             _ => None,
         }
     }
+
+    /// Of de uitvoering afgelopen is (geslaagd, mislukt of geannuleerd).
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+impl From<&ScenarioStatus> for ExecutionStatus {
+    fn from(status: &ScenarioStatus) -> Self {
+        match status {
+            ScenarioStatus::Pending => Self::Pending,
+            ScenarioStatus::Running => Self::Running,
+            ScenarioStatus::Completed => Self::Completed,
+            ScenarioStatus::Failed => Self::Failed,
+            ScenarioStatus::Cancelled => Self::Cancelled,
+        }
+    }
 }
 
 /// Scenario voor opslag in database.
@@ -119,6 +138,34 @@ pub struct StoredScenario {
     // Tags
     #[serde(default)]
     pub tags: serde_json::Value,
+
+    // Peilgebieden waarop het scenario betrekking heeft (autorisatie per rayon)
+    #[serde(default)]
+    pub peilgebieden: Vec<String>,
+}
+
+impl StoredScenario {
+    /// Het scenario zoals het naar D-Hydro gestuurd wordt.
+    pub fn to_dhydro(&self) -> Scenario {
+        Scenario {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            model_id: self.model_id.clone(),
+            parameters: ScenarioParameters {
+                start_time: self.start_time,
+                end_time: self.end_time,
+                time_step: self.time_step,
+                boundary_conditions: self.boundary_conditions.clone(),
+                initial_conditions: self.initial_conditions.clone(),
+                model_parameters: self.model_parameters.clone(),
+            },
+            created_at: Some(self.created_at),
+            created_by: self.created_by.clone(),
+            is_base_scenario: self.is_base_scenario,
+            base_scenario_id: self.base_scenario_id.clone(),
+        }
+    }
 }
 
 /// Request om een nieuw scenario te maken.
@@ -142,6 +189,8 @@ pub struct CreateScenarioRequest {
     #[serde(default)]
     pub tags: Vec<String>,
     #[serde(default)]
+    pub peilgebieden: Vec<String>,
+    #[serde(default)]
     pub created_by: Option<String>,
 }
 
@@ -168,6 +217,8 @@ pub struct UpdateScenarioRequest {
     pub status: Option<StoredScenarioStatus>,
     #[serde(default)]
     pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub peilgebieden: Option<Vec<String>>,
 }
 
 /// Scenario uitvoeringsresultaat.
@@ -322,6 +373,16 @@ mod tests {
         assert_eq!(ExecutionStatus::from_str("FAILED"), Some(ExecutionStatus::Failed));
     }
 
+    #[test]
+    fn test_execution_status_from_dhydro() {
+        assert_eq!(ExecutionStatus::from(&ScenarioStatus::Running), ExecutionStatus::Running);
+        assert_eq!(ExecutionStatus::from(&ScenarioStatus::Cancelled), ExecutionStatus::Cancelled);
+        assert!(!ExecutionStatus::Pending.is_finished());
+        assert!(!ExecutionStatus::Running.is_finished());
+        assert!(ExecutionStatus::Completed.is_finished());
+        assert!(ExecutionStatus::Cancelled.is_finished());
+    }
+
     #[test]
     fn test_create_scenario_serialization() {
         let req = CreateScenarioRequest {
@@ -336,6 +397,7 @@ mod tests {
             model_parameters: None,
             base_scenario_id: None,
             tags: vec!["flood".to_string(), "extreme".to_string()],
+            peilgebieden: vec![],
             created_by: Some("user".to_string()),
         };
 
@@ -0,0 +1,344 @@
+//! Handlebars-style templates for alert titles and messages.
+//!
+//! Supported syntax:
+//! - `{{variable}}` inserts a value (empty when the value is not available)
+//! - `{{upper variable}}`, `{{lower variable}}` and `{{round variable 2}}`
+//!   apply a helper to the value
+//! - `{{#if variable}}...{{else}}...{{/if}}` renders a section only when the
+//!   value is set (non-empty and not `false` or `0`)
+
+use std::collections::{BTreeSet, HashMap};
+use thiserror::Error;
+
+/// Most decimals `round` renders; an f64 carries no more significant digits.
+pub const MAX_ROUND_DECIMALS: usize = 15;
+
+/// Template parse errors.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TemplateError {
+    #[error("unclosed tag at position {0}")]
+    UnclosedTag(usize),
+
+    #[error("empty tag at position {0}")]
+    EmptyTag(usize),
+
+    #[error("unknown helper '{0}'")]
+    UnknownHelper(String),
+
+    #[error("invalid arguments for '{0}'")]
+    InvalidArguments(String),
+
+    #[error("unexpected '{{{{{0}}}}}'")]
+    UnexpectedTag(String),
+
+    #[error("missing '{{{{/if}}}}' for '{{{{#if {0}}}}}'")]
+    UnclosedBlock(String),
+}
+
+/// Parsed template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value {
+        variable: String,
+        helper: Option<Helper>,
+    },
+    If {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Helper {
+    Upper,
+    Lower,
+    Round(usize),
+}
+
+/// Lexical token of a template.
+enum Token {
+    Text(String),
+    Value {
+        variable: String,
+        helper: Option<Helper>,
+    },
+    If(String),
+    Else,
+    EndIf,
+}
+
+/// Reason a block of nodes ended.
+enum Stop {
+    Eof,
+    Else,
+    EndIf,
+}
+
+impl Template {
+    /// Parse a template string.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut tokens = tokenize(source)?.into_iter();
+        match parse_nodes(&mut tokens)? {
+            (nodes, Stop::Eof) => Ok(Self { nodes }),
+            (_, Stop::Else) => Err(TemplateError::UnexpectedTag("else".to_string())),
+            (_, Stop::EndIf) => Err(TemplateError::UnexpectedTag("/if".to_string())),
+        }
+    }
+
+    /// Render the template with the given variables.
+    pub fn render(&self, variables: &HashMap<String, String>) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, &mut output);
+        output
+    }
+
+    /// Names of all variables referenced by the template.
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        collect_variables(&self.nodes, &mut names);
+        names
+    }
+}
+
+/// Render a template string, falling back to the literal text when it does
+/// not parse.
+pub fn render(source: &str, variables: &HashMap<String, String>) -> String {
+    match Template::parse(source) {
+        Ok(template) => template.render(variables),
+        Err(_) => source.to_string(),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut offset = 0;
+
+    while let Some(open) = rest.find("{{") {
+        if open > 0 {
+            tokens.push(Token::Text(rest[..open].to_string()));
+        }
+        let position = offset + open;
+        let after_open = &rest[open + 2..];
+        let close = after_open.find("}}").ok_or(TemplateError::UnclosedTag(position))?;
+
+        tokens.push(parse_tag(after_open[..close].trim(), position)?);
+
+        let consumed = open + 2 + close + 2;
+        rest = &rest[consumed..];
+        offset += consumed;
+    }
+
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest.to_string()));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_tag(tag: &str, position: usize) -> Result<Token, TemplateError> {
+    if tag.is_empty() {
+        return Err(TemplateError::EmptyTag(position));
+    }
+    if tag == "else" {
+        return Ok(Token::Else);
+    }
+    if tag == "/if" {
+        return Ok(Token::EndIf);
+    }
+
+    let words: Vec<&str> = tag.split_whitespace().collect();
+
+    if let Some(block) = words[0].strip_prefix('#') {
+        return match (block, words.as_slice()) {
+            ("if", [_, variable]) => Ok(Token::If(variable.to_string())),
+            ("if", _) => Err(TemplateError::InvalidArguments("#if".to_string())),
+            _ => Err(TemplateError::UnknownHelper(words[0].to_string())),
+        };
+    }
+    if words[0].starts_with('/') {
+        return Err(TemplateError::UnexpectedTag(tag.to_string()));
+    }
+
+    let (variable, helper) = match words.as_slice() {
+        [variable] => (*variable, None),
+        ["upper", variable] => (*variable, Some(Helper::Upper)),
+        ["lower", variable] => (*variable, Some(Helper::Lower)),
+        ["round", variable] => (*variable, Some(Helper::Round(0))),
+        ["round", variable, decimals] => {
+            let decimals = decimals
+                .parse::<usize>()
+                .map_err(|_| TemplateError::InvalidArguments("round".to_string()))?;
+            (*variable, Some(Helper::Round(decimals.min(MAX_ROUND_DECIMALS))))
+        }
+        ["upper" | "lower" | "round", ..] => {
+            return Err(TemplateError::InvalidArguments(words[0].to_string()));
+        }
+        _ => return Err(TemplateError::UnknownHelper(words[0].to_string())),
+    };
+
+    Ok(Token::Value {
+        variable: variable.to_string(),
+        helper,
+    })
+}
+
+fn parse_nodes(
+    tokens: &mut impl Iterator<Item = Token>,
+) -> Result<(Vec<Node>, Stop), TemplateError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Value { variable, helper } => nodes.push(Node::Value { variable, helper }),
+            Token::Else => return Ok((nodes, Stop::Else)),
+            Token::EndIf => return Ok((nodes, Stop::EndIf)),
+            Token::If(variable) => {
+                let (then, otherwise) = match parse_nodes(tokens)? {
+                    (then, Stop::EndIf) => (then, Vec::new()),
+                    (then, Stop::Else) => match parse_nodes(tokens)? {
+                        (otherwise, Stop::EndIf) => (then, otherwise),
+                        (_, Stop::Else) => {
+                            return Err(TemplateError::UnexpectedTag("else".to_string()));
+                        }
+                        (_, Stop::Eof) => return Err(TemplateError::UnclosedBlock(variable)),
+                    },
+                    (_, Stop::Eof) => return Err(TemplateError::UnclosedBlock(variable)),
+                };
+                nodes.push(Node::If { variable, then, otherwise });
+            }
+        }
+    }
+
+    Ok((nodes, Stop::Eof))
+}
+
+fn render_nodes(nodes: &[Node], variables: &HashMap<String, String>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value { variable, helper } => {
+                if let Some(value) = variables.get(variable) {
+                    output.push_str(&apply_helper(*helper, value));
+                }
+            }
+            Node::If { variable, then, otherwise } => {
+                let truthy = variables
+                    .get(variable)
+                    .is_some_and(|v| !v.is_empty() && v != "false" && v != "0");
+                render_nodes(if truthy { then } else { otherwise }, variables, output);
+            }
+        }
+    }
+}
+
+fn apply_helper(helper: Option<Helper>, value: &str) -> String {
+    match helper {
+        None => value.to_string(),
+        Some(Helper::Upper) => value.to_uppercase(),
+        Some(Helper::Lower) => value.to_lowercase(),
+        Some(Helper::Round(decimals)) => match value.parse::<f64>() {
+            Ok(number) => format!("{:.*}", decimals, number),
+            Err(_) => value.to_string(),
+        },
+    }
+}
+
+fn collect_variables(nodes: &[Node], names: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Value { variable, .. } => {
+                names.insert(variable.clone());
+            }
+            Node::If { variable, then, otherwise } => {
+                names.insert(variable.clone());
+                collect_variables(then, names);
+                collect_variables(otherwise, names);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_variables_and_helpers() {
+        let template = Template::parse(
+            "{{upper severity}}: peil {{ resource }} is {{round water_level.value 2}} m (drempel {{water_level.threshold}})",
+        )
+        .unwrap();
+
+        let rendered = template.render(&vars(&[
+            ("severity", "critical"),
+            ("resource", "GEMAAL_001"),
+            ("water_level.value", "-4.23456"),
+            ("water_level.threshold", "-4.5"),
+        ]));
+        assert_eq!(rendered, "CRITICAL: peil GEMAAL_001 is -4.23 m (drempel -4.5)");
+
+        let names: Vec<_> = template.variables().into_iter().collect();
+        assert_eq!(
+            names,
+            vec!["resource", "severity", "water_level.threshold", "water_level.value"]
+        );
+
+        // The precision of round is clamped
+        assert_eq!(
+            render("{{round value 4000000000}}", &vars(&[("value", "0.5")])),
+            format!("{:.*}", MAX_ROUND_DECIMALS, 0.5)
+        );
+
+        // Unknown values render empty
+        assert_eq!(render("[{{missing}}]", &HashMap::new()), "[]");
+    }
+
+    #[test]
+    fn test_if_blocks() {
+        let template =
+            Template::parse("Alarm{{#if resource}} bij {{resource}}{{else}} (onbekend){{/if}}").unwrap();
+
+        assert_eq!(template.render(&vars(&[("resource", "PG_12")])), "Alarm bij PG_12");
+        assert_eq!(template.render(&vars(&[("resource", "")])), "Alarm (onbekend)");
+        assert_eq!(template.render(&HashMap::new()), "Alarm (onbekend)");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Template::parse("Peil {{value"), Err(TemplateError::UnclosedTag(5)));
+        assert_eq!(Template::parse("{{ }}"), Err(TemplateError::EmptyTag(0)));
+        assert_eq!(
+            Template::parse("{{format value}}"),
+            Err(TemplateError::UnknownHelper("format".to_string()))
+        );
+        assert_eq!(
+            Template::parse("{{round value x}}"),
+            Err(TemplateError::InvalidArguments("round".to_string()))
+        );
+        assert_eq!(
+            Template::parse("{{#if value}}hoog"),
+            Err(TemplateError::UnclosedBlock("value".to_string()))
+        );
+        assert_eq!(
+            Template::parse("hoog{{/if}}"),
+            Err(TemplateError::UnexpectedTag("/if".to_string()))
+        );
+
+        // Invalid templates render literally
+        assert_eq!(render("Peil {{value", &HashMap::new()), "Peil {{value");
+    }
+}
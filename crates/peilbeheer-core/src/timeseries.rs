@@ -251,6 +251,9 @@ pub struct TimeSeriesMetadata {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub retention_days: Option<u32>,
+    /// Expected time between deliveries (seconds), used to detect missing data
+    #[serde(default)]
+    pub expected_interval_seconds: Option<u32>,
     pub attributes: HashMap<String, serde_json::Value>,
 }
 
@@ -284,6 +287,14 @@ pub enum TimeSeriesSourceType {
     DHydro,
     /// EnergyZero API
     EnergyZero,
+    /// KNMI Data Platform
+    Knmi,
+    /// Rijkswaterstaat Waterinfo (waterwebservices)
+    Rws,
+    /// Lizard (groundwater levels)
+    Lizard,
+    /// Meteoserver (precipitation forecast, fallback for KNMI)
+    Meteoserver,
     /// Manual entry
     Manual,
     /// Calculated/derived
@@ -301,6 +312,9 @@ pub struct TimeSeriesQuery {
     pub aggregation: Option<AggregationLevel>,
     pub function: Option<AggregationFunction>,
     pub fill_gaps: Option<FillMethod>,
+    /// Value used by [`FillMethod::Constant`]
+    #[serde(default)]
+    pub fill_value: Option<f64>,
     pub max_gap_seconds: Option<i64>,
 }
 
@@ -316,8 +330,14 @@ pub enum FillMethod {
     Forward,
     /// Backward fill (use next value)
     Backward,
-    /// Fill with a specific value
+    /// Fill with a specific value (see `TimeSeriesQuery::fill_value`)
     Constant,
+    /// Natural cubic spline through the valid points
+    Spline,
+    /// Climatological mean over the series history for the same month and
+    /// slot of the day, falling back to the mean of the slot over all
+    /// months and then to the overall mean
+    Seasonal,
 }
 
 /// Aggregation function for downsampled data.
@@ -436,6 +456,8 @@ pub struct TimeSeriesCatalogEntry {
     pub first_timestamp: Option<DateTime<Utc>>,
     pub last_timestamp: Option<DateTime<Utc>>,
     pub point_count: u64,
+    #[serde(default)]
+    pub expected_interval_seconds: Option<u32>,
 }
 
 impl AggregatedSeries {
@@ -496,6 +518,7 @@ impl TimeSeriesQuery {
             aggregation: None,
             function: None,
             fill_gaps: None,
+            fill_value: None,
             max_gap_seconds: None,
         }
     }
@@ -518,12 +541,22 @@ impl TimeSeriesQuery {
         self
     }
 
+    /// Set the value used for constant gap filling.
+    pub fn with_fill_value(mut self, value: f64) -> Self {
+        self.fill_value = Some(value);
+        self
+    }
+
     /// Validate the query.
     pub fn validate(&self) -> Result<(), String> {
         if self.start >= self.end {
             return Err("Start time must be before end time".to_string());
         }
 
+        if self.fill_gaps == Some(FillMethod::Constant) && self.fill_value.is_none() {
+            return Err("Fill value required for constant gap filling".to_string());
+        }
+
         // Check if aggregation and function are compatible
         if self.aggregation.is_some() && self.function.is_none() {
             return Err("Aggregation function required when aggregation level is set".to_string());
@@ -616,8 +649,18 @@ mod tests {
         assert!(invalid_query.validate().is_err());
 
         // Invalid: aggregation without function
-        let query = TimeSeriesQuery::new(id, start, end)
+        let query = TimeSeriesQuery::new(id.clone(), start, end)
             .with_aggregation(AggregationLevel::Hour1);
         assert!(query.validate().is_err());
+
+        // Invalid: constant fill without value
+        let query = TimeSeriesQuery::new(id.clone(), start, end)
+            .with_fill_method(FillMethod::Constant);
+        assert!(query.validate().is_err());
+
+        let query = TimeSeriesQuery::new(id, start, end)
+            .with_fill_method(FillMethod::Constant)
+            .with_fill_value(0.0);
+        assert!(query.validate().is_ok());
     }
 }
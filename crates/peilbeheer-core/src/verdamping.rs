@@ -0,0 +1,117 @@
+//! Referentieverdamping volgens Makkink.
+//!
+//! Het KNMI berekent de referentiegewasverdamping (EV24) met de formule van
+//! Makkink uit de etmaalgemiddelde temperatuur en de globale straling. Deze
+//! module doet hetzelfde voor dagwaarden uit de KNMI-daggegevens
+//! ([`crate::knmi::daggegevens`]) of een verwachting. De verdamping van een
+//! peilgebied is de referentieverdamping maal de gewasfactor.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Makkink-constante
+const MAKKINK_C: f64 = 0.65;
+
+/// Dagwaarden van een KNMI-station.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Dagwaarde {
+    pub datum: NaiveDate,
+    /// Etmaalgemiddelde temperatuur in °C
+    pub temperatuur: f64,
+    /// Globale straling in J/cm²
+    pub straling: f64,
+}
+
+impl Dagwaarde {
+    /// Referentieverdamping van deze dag in mm.
+    pub fn makkink(&self) -> f64 {
+        makkink(self.temperatuur, self.straling)
+    }
+}
+
+/// Referentieverdamping in mm/dag volgens Makkink bij een etmaalgemiddelde
+/// temperatuur (°C) en globale straling (J/cm²).
+pub fn makkink(temperatuur: f64, straling: f64) -> f64 {
+    // Verzadigingsdampspanning en haar helling (kPa, kPa/°C)
+    let es = 0.6108 * (17.27 * temperatuur / (temperatuur + 237.3)).exp();
+    let s = 4098.0 * es / (temperatuur + 237.3).powi(2);
+    // Psychrometerconstante (kPa/°C) en verdampingswarmte (J/kg)
+    let gamma = 0.0646 + 0.00006 * temperatuur;
+    let lambda = 2.501e6 - 2375.0 * temperatuur;
+
+    // Straling van J/cm² naar J/m²; verdamping van m naar mm
+    let straling = straling.max(0.0) * 1e4;
+    MAKKINK_C * s / (s + gamma) * straling / (1000.0 * lambda) * 1000.0
+}
+
+/// Referentieverdamping per uur in mm/uur vanaf `start`.
+///
+/// De verdamping van een dag wordt gelijk over de uren verdeeld. Uren
+/// zonder dagwaarde krijgen de dichtstbijzijnde eerdere dag, of anders de
+/// eerste dag; zonder dagwaarden is de verdamping 0.0.
+pub fn referentieverdamping_per_uur(
+    dagwaarden: &[Dagwaarde],
+    start: DateTime<Utc>,
+    uren: usize,
+) -> Vec<f64> {
+    let mut dagen = dagwaarden.to_vec();
+    dagen.sort_by_key(|d| d.datum);
+    let Some(eerste) = dagen.first() else {
+        return vec![0.0; uren];
+    };
+
+    (0..uren)
+        .map(|uur| {
+            let datum = (start + Duration::hours(uur as i64)).date_naive();
+            let dag = dagen
+                .iter()
+                .rev()
+                .find(|d| d.datum <= datum)
+                .unwrap_or(eerste);
+            dag.makkink() / 24.0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dag(datum: &str, temperatuur: f64, straling: f64) -> Dagwaarde {
+        Dagwaarde {
+            datum: datum.parse().unwrap(),
+            temperatuur,
+            straling,
+        }
+    }
+
+    #[test]
+    fn test_makkink() {
+        // Zomerdag: ruim 3.5 mm, winterdag: minder dan een halve mm
+        let zomer = makkink(20.0, 2000.0);
+        assert!((zomer - 3.6).abs() < 0.1, "{}", zomer);
+        let winter = makkink(3.0, 200.0);
+        assert!(winter > 0.1 && winter < 0.5, "{}", winter);
+        assert_eq!(makkink(15.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_referentieverdamping_per_uur() {
+        let dagen = [
+            dag("2024-06-02", 20.0, 2000.0),
+            dag("2024-06-01", 10.0, 1000.0),
+        ];
+        let start = DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let per_uur = referentieverdamping_per_uur(&dagen, start, 48);
+        assert_eq!(per_uur.len(), 48);
+        assert!((per_uur[0] - makkink(10.0, 1000.0) / 24.0).abs() < 1e-12);
+        assert!((per_uur[12] - makkink(20.0, 2000.0) / 24.0).abs() < 1e-12);
+        // Na de laatste dag geldt de laatste dag
+        assert_eq!(per_uur[47], per_uur[12]);
+
+        assert_eq!(referentieverdamping_per_uur(&[], start, 2), vec![0.0, 0.0]);
+    }
+}
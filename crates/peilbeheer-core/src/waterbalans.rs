@@ -11,6 +11,27 @@ pub struct WaterBalance {
     pub nieuwe_waterstand: f64,
 }
 
+/// Kwel of wegzijging die lineair afhangt van het verschil tussen de
+/// stijghoogte onder de polder (boezem- of grondwaterpeil) en het
+/// polderpeil, via de hydraulische weerstand van de deklaag.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Kwelrelatie {
+    /// Boezem- of grondwaterpeil in m NAP
+    pub stijghoogte: f64,
+    /// Hydraulische weerstand van de deklaag in dagen
+    pub weerstand_dagen: f64,
+}
+
+impl Kwelrelatie {
+    /// Kwel in mm/uur bij een polderpeil; negatief bij wegzijging.
+    pub fn kwel_mm_uur(&self, waterstand: f64) -> f64 {
+        if self.weerstand_dagen <= 0.0 {
+            return 0.0;
+        }
+        (self.stijghoogte - waterstand) * 1000.0 / (self.weerstand_dagen * 24.0)
+    }
+}
+
 /// Parameters voor een waterbalans simulatie.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulatieParams {
@@ -47,8 +68,41 @@ pub struct SimulatieParams {
     #[serde(default)]
     pub marge: f64,
     /// Maaiveld niveau in m NAP (voor drooglegging)
-    #[serde(default)]
-    pub maaiveld_niveau: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maaiveld_niveau: Option<f64>,
+    /// Instellingen van de PID-regelaar; standaardwaarden als ontbrekend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<PidInstellingen>,
+}
+
+/// Versterkingsfactoren van de PID-regelaar van het gemaal.
+///
+/// De fout is waterstand min streefpeil in m, de tijd in minuten en de
+/// uitvoer de pompfractie (0.0 - 1.0).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PidInstellingen {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+    /// Tijdconstante in minuten van het laagdoorlaatfilter op de D-term
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d_filter_minuten: Option<f64>,
+    /// Grootste verandering van de pompfractie per tijdstap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_verandering: Option<f64>,
+}
+
+impl Default for PidInstellingen {
+    /// De standaardwaarden uit de Python implementatie.
+    fn default() -> Self {
+        Self {
+            kp: 5.0,
+            ki: 0.05,
+            kd: 20.0,
+            d_filter_minuten: None,
+            max_verandering: None,
+        }
+    }
 }
 
 fn default_na_regen_duur() -> f64 {
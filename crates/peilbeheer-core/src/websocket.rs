@@ -38,6 +38,18 @@ pub enum WsMessage {
         success: bool,
     },
 
+    /// Progress of a running network simulation
+    #[serde(rename = "simulatie.voortgang")]
+    SimulatieVoortgang {
+        run_id: String,
+        scenario_id: String,
+        percentage: f64,
+        /// Simulation time in minutes
+        tijd: f64,
+        /// Intermediate statistics such as the highest water level so far
+        statistieken: serde_json::Value,
+    },
+
     /// New scenario created
     #[serde(rename = "scenario.created")]
     ScenarioCreated { scenario_id: String, name: String },
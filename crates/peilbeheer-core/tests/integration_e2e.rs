@@ -22,11 +22,11 @@
 //! - Verify resource cleanup
 //! - Log test metrics
 
-use chrono::{Utc, Duration, TimeZone};
+use chrono::{Utc, Duration};
 use peilbeheer_core::{
-    energie::{OptimalisatieParams, UurPrijs},
+    energie::UurPrijs,
     alert::{AlertRule, AlertSeverity, ComparisonOperator, AlertCondition, AlertValue, AlertCategory, ConditionLogic},
-    timeseries::{TimeSeriesDataPoint, AggregationFunction, AggregationLevel, QualityFlag},
+    timeseries::{TimeSeriesDataPoint, QualityFlag},
 };
 use std::collections::HashMap;
 
@@ -49,10 +49,7 @@ mod mocks {
 
         pub fn with_flat_prices(&self, price: f64) -> Vec<UurPrijs> {
             (0..24)
-                .map(|hour| UurPrijs {
-                    uur: hour as u8,
-                    prijs_eur_kwh: price,
-                })
+                .map(|hour| UurPrijs::uur(hour as u8, price))
                 .collect()
         }
 
@@ -60,10 +57,7 @@ mod mocks {
             (0..24)
                 .map(|hour| {
                     let price = 0.20 + 0.30 * (1.0 - ((hour as f32 - 12.0).abs() / 12.0).powi(2));
-                    UurPrijs {
-                        uur: hour as u8,
-                        prijs_eur_kwh: price as f64,
-                    }
+                    UurPrijs::uur(hour as u8, price as f64)
                 })
                 .collect()
         }
@@ -73,10 +67,7 @@ mod mocks {
                 .map(|hour| {
                     let is_peak = (8..18).contains(&hour);
                     let price = if is_peak { 0.50 } else { 0.22 };
-                    UurPrijs {
-                        uur: hour as u8,
-                        prijs_eur_kwh: price,
-                    }
+                    UurPrijs::uur(hour as u8, price)
                 })
                 .collect()
         }
@@ -134,22 +125,9 @@ mod mocks {
         pub water_levels: Vec<f64>,
         pub rain_mm: Vec<f64>,
     }
-
-    /// Test time context for deterministic tests.
-    pub struct TestTimeContext {
-        pub now: chrono::DateTime<Utc>,
-    }
-
-    impl TestTimeContext {
-        pub fn new() -> Self {
-            Self {
-                now: Utc.with_ymd_and_hms(2025, 2, 18, 12, 0, 0).unwrap(),
-            }
-        }
-    }
 }
 
-use mocks::{EnergyZeroMock, FewsClientMock, ScenarioTestData, TestTimeContext};
+use mocks::{EnergyZeroMock, FewsClientMock, ScenarioTestData};
 
 // ========================================================================
 // INTEGRATION TESTS
@@ -164,7 +142,6 @@ mod integration_tests {
         energyzero_mock: EnergyZeroMock,
         fews_mock: FewsClientMock,
         alert_rules: Vec<AlertRule>,
-        time_context: TestTimeContext,
     }
 
     impl IntegrationTestFixture {
@@ -176,7 +153,6 @@ mod integration_tests {
                 energyzero_mock: EnergyZeroMock::new(),
                 fews_mock: FewsClientMock::new(),
                 alert_rules: Self::default_alert_rules(now),
-                time_context: TestTimeContext::new(),
             }
         }
 
@@ -189,11 +165,13 @@ mod integration_tests {
                     description: Some("Waterstand boven kritiek niveau".to_string()),
                     category: AlertCategory::WaterLevel,
                     severity: AlertSeverity::Critical,
+                    resource_selector: None,
                     conditions: vec![
                         AlertCondition {
                             field: "waterlevel.main".to_string(),
                             operator: ComparisonOperator::Gt,
                             value: AlertValue::Number(-4.5),
+                            clear_value: None,
                             source_filter: None,
                             time_window: None,
                             aggregation: None,
@@ -203,6 +181,9 @@ mod integration_tests {
                     cooldown_seconds: 3600,
                     enabled: true,
                     notification_channels: vec![],
+                    escalation_policy: None,
+                    auto_resolve_after: None,
+                    flapping_policy: None,
                     title_template: "Hoge waterstand".to_string(),
                     message_template: "Waterstand boven -4.5m NAP".to_string(),
                     metadata: HashMap::new(),
@@ -216,11 +197,13 @@ mod integration_tests {
                     description: Some("Waterstand onder minimaal niveau".to_string()),
                     category: AlertCategory::WaterLevel,
                     severity: AlertSeverity::Warning,
+                    resource_selector: None,
                     conditions: vec![
                         AlertCondition {
                             field: "waterlevel.main".to_string(),
                             operator: ComparisonOperator::Lt,
                             value: AlertValue::Number(-5.5),
+                            clear_value: None,
                             source_filter: None,
                             time_window: None,
                             aggregation: None,
@@ -230,6 +213,9 @@ mod integration_tests {
                     cooldown_seconds: 7200,
                     enabled: true,
                     notification_channels: vec![],
+                    escalation_policy: None,
+                    auto_resolve_after: None,
+                    flapping_policy: None,
                     title_template: "Lage waterstand".to_string(),
                     message_template: "Waterstand onder -5.5m NAP".to_string(),
                     metadata: HashMap::new(),
@@ -546,42 +532,4 @@ mod integration_tests {
         assert!(missing_point.value.is_nan(), "Missing point value should be NaN");
         assert_eq!(missing_point.flag, QualityFlag::Missing);
     }
-
-    // ========================================================================
-    // VALIDATION HELPERS
-    // ========================================================================
-
-    /// Validate that price array is well-formed.
-    fn validate_prices(prices: &[UurPrijs]) -> Result<(), String> {
-        if prices.len() != 24 {
-            return Err(format!("Expected 24 hours, got {}", prices.len()));
-        }
-
-        for (hour, price) in prices.iter().enumerate() {
-            if price.uur != hour as u8 {
-                return Err(format!("Hour {}: uur field mismatch", hour));
-            }
-            if price.prijs_eur_kwh <= 0.0 {
-                return Err(format!("Hour {}: price must be positive", hour));
-            }
-            if price.prijs_eur_kwh >= 1.0 {
-                return Err(format!("Hour {}: price {} exceeds reasonable maximum", hour, price.prijs_eur_kwh));
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Validate that water level data is physically realistic.
-    fn validate_water_levels(levels: &[f64]) -> Result<(), String> {
-        for (hour, level) in levels.iter().enumerate() {
-            if level.is_nan() || level.is_infinite() {
-                return Err(format!("Hour {}: invalid water level", hour));
-            }
-            if *level < -10.0 || *level > 10.0 {
-                return Err(format!("Hour {}: water level {} out of physical range [-10, 10] m NAP", hour, level));
-            }
-        }
-        Ok(())
-    }
 }
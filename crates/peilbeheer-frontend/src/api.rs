@@ -1,3 +1,4 @@
+use dioxus::prelude::*;
 use serde::{Deserialize, Serialize};
 
 fn api_base() -> String {
@@ -6,6 +7,133 @@ fn api_base() -> String {
     "http://localhost:3000/api".to_string()
 }
 
+// ── Authentication ──
+
+const TOKEN_KEY: &str = "peilbeheer_token";
+const REFRESH_TOKEN_KEY: &str = "peilbeheer_refresh_token";
+
+/// Access token of the logged-in user, kept in local storage so a reload
+/// does not log the user out.
+pub static AUTH_TOKEN: GlobalSignal<Option<String>> = Signal::global(stored_token);
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn stored_token() -> Option<String> {
+    local_storage()?.get_item(TOKEN_KEY).ok()?
+}
+
+fn store(key: &str, value: Option<&str>) {
+    if let Some(storage) = local_storage() {
+        let _ = match value {
+            Some(v) => storage.set_item(key, v),
+            None => storage.remove_item(key),
+        };
+    }
+}
+
+fn set_token(token: Option<String>) {
+    store(TOKEN_KEY, token.as_deref());
+    *AUTH_TOKEN.write() = token;
+}
+
+#[derive(Serialize)]
+struct LoginRequest<'a> {
+    username: &'a str,
+    password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totp_code: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+    detail: Option<String>,
+}
+
+/// Log in and keep the access token for the following requests.
+pub async fn login(username: &str, password: &str, totp_code: Option<&str>) -> Result<(), String> {
+    let url = format!("{}/auth/login", api_base());
+    let request = LoginRequest { username, password, totp_code };
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {e}"))?;
+
+    if !response.status().is_success() {
+        let body = response
+            .json::<ErrorBody>()
+            .await
+            .map_err(|e| format!("Parse failed: {e}"))?;
+        return Err(body.detail.unwrap_or(body.error));
+    }
+
+    let login = response
+        .json::<LoginResponse>()
+        .await
+        .map_err(|e| format!("Parse failed: {e}"))?;
+    store(REFRESH_TOKEN_KEY, login.refresh_token.as_deref());
+    set_token(Some(login.access_token));
+    Ok(())
+}
+
+/// Revoke the refresh token and forget both tokens.
+pub async fn logout() {
+    let refresh_token = local_storage().and_then(|s| s.get_item(REFRESH_TOKEN_KEY).ok()?);
+    if let Some(refresh_token) = refresh_token {
+        let url = format!("{}/auth/logout", api_base());
+        let _ = post(&url).json(&RefreshTokenRequest { refresh_token }).send().await;
+    }
+    store(REFRESH_TOKEN_KEY, None);
+    set_token(None);
+}
+
+fn authorized(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match AUTH_TOKEN.peek().as_deref() {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+fn get(url: &str) -> reqwest::RequestBuilder {
+    authorized(reqwest::Client::new().get(url))
+}
+
+fn post(url: &str) -> reqwest::RequestBuilder {
+    authorized(reqwest::Client::new().post(url))
+}
+
+/// Send a request; an expired or revoked token sends the user back to the
+/// login form.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {e}"))?;
+    match response.status() {
+        reqwest::StatusCode::UNAUTHORIZED => {
+            set_token(None);
+            Err("Niet ingelogd".to_string())
+        }
+        reqwest::StatusCode::FORBIDDEN => Err("Geen toegang".to_string()),
+        _ => Ok(response),
+    }
+}
+
 // ── Domain types (match API JSON responses) ──
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -120,7 +248,11 @@ pub struct SimulatieParams {
     pub smart_control: bool,
     pub streefpeil: f64,
     pub marge: f64,
-    pub maaiveld_niveau: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maaiveld_niveau: Option<f64>,
+    /// Peilgebied whose AHN ground level is used when `maaiveld_niveau` is unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peilgebied: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -209,9 +341,8 @@ pub struct AssetProperties {
 
 pub async fn fetch_status() -> Result<StatusResponse, String> {
     let url = format!("{}/status", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<StatusResponse>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -220,9 +351,8 @@ pub async fn fetch_status() -> Result<StatusResponse, String> {
 #[allow(dead_code)]
 pub async fn fetch_gemalen() -> Result<Vec<GemaalSnapshot>, String> {
     let url = format!("{}/gemalen", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<Vec<GemaalSnapshot>>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -230,9 +360,8 @@ pub async fn fetch_gemalen() -> Result<Vec<GemaalSnapshot>, String> {
 
 pub async fn fetch_gemaal(code: &str) -> Result<GemaalDetailResponse, String> {
     let url = format!("{}/gemalen/{code}", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<GemaalDetailResponse>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -240,14 +369,9 @@ pub async fn fetch_gemaal(code: &str) -> Result<GemaalDetailResponse, String> {
 
 #[allow(dead_code)]
 pub async fn run_simulatie(params: &SimulatieParams) -> Result<SimulatieResponse, String> {
-    let client = reqwest::Client::new();
     let url = format!("{}/simulatie", api_base());
-    client
-        .post(&url)
-        .json(params)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(post(&url).json(params))
+        .await?
         .json::<SimulatieResponse>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -255,9 +379,8 @@ pub async fn run_simulatie(params: &SimulatieParams) -> Result<SimulatieResponse
 
 pub async fn fetch_layers() -> Result<Vec<LayerConfig>, String> {
     let url = format!("{}/assets/layers", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<Vec<LayerConfig>>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -268,9 +391,8 @@ pub async fn fetch_assets_geojson(layers: Option<&str>) -> Result<AssetFeatureCo
         Some(l) => format!("{}/assets/geojson?layers={l}", api_base()),
         None => format!("{}/assets/geojson", api_base()),
     };
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<AssetFeatureCollection>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -281,9 +403,8 @@ pub async fn fetch_assets_geojson_raw(layers: Option<&str>) -> Result<String, St
         Some(l) => format!("{}/assets/geojson?layers={l}", api_base()),
         None => format!("{}/assets/geojson", api_base()),
     };
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .text()
         .await
         .map_err(|e| format!("Read failed: {e}"))
@@ -293,9 +414,8 @@ pub async fn fetch_peilgebieden_geojson() -> Result<String, String> {
     // Cache-bust to avoid stale browser cache (endpoint sets max-age=86400)
     let ts = js_sys::Date::now() as u64;
     let url = format!("{}/peilgebieden/geojson?_t={ts}", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .text()
         .await
         .map_err(|e| format!("Read failed: {e}"))
@@ -304,9 +424,8 @@ pub async fn fetch_peilgebieden_geojson() -> Result<String, String> {
 #[allow(dead_code)]
 pub async fn fetch_gemaal_peilgebied_mapping() -> Result<std::collections::HashMap<String, String>, String> {
     let url = format!("{}/peilgebieden/mapping", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<std::collections::HashMap<String, String>>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
@@ -375,27 +494,44 @@ pub struct OptimalisatieResultaat {
     pub prijzen: Vec<UurPrijs>,
 }
 
+// ── Ontwerpbuien ──
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Ontwerpbui {
+    pub id: String,
+    pub herhalingstijd: u32,
+    pub duur_uren: usize,
+    pub patroon: String,
+    pub totaal_mm: f64,
+    pub regen_per_uur: Vec<f64>,
+}
+
+/// Ontwerpbuien van hoogstens `max_duur` uur.
+pub async fn fetch_ontwerpbuien(max_duur: usize) -> Result<Vec<Ontwerpbui>, String> {
+    let url = format!("{}/ontwerpbuien", api_base());
+    let buien = send(get(&url))
+        .await?
+        .json::<Vec<Ontwerpbui>>()
+        .await
+        .map_err(|e| format!("Parse failed: {e}"))?;
+    Ok(buien.into_iter().filter(|b| b.duur_uren <= max_duur).collect())
+}
+
 // ── Energieoptimalisatie API functions ──
 
 pub async fn fetch_energieprijzen() -> Result<Vec<UurPrijs>, String> {
     let url = format!("{}/energieprijzen", api_base());
-    reqwest::get(&url)
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(get(&url))
+        .await?
         .json::<Vec<UurPrijs>>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
 }
 
 pub async fn run_optimalisatie(params: &OptimalisatieParams) -> Result<OptimalisatieResultaat, String> {
-    let client = reqwest::Client::new();
     let url = format!("{}/optimalisatie", api_base());
-    client
-        .post(&url)
-        .json(params)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {e}"))?
+    send(post(&url).json(params))
+        .await?
         .json::<OptimalisatieResultaat>()
         .await
         .map_err(|e| format!("Parse failed: {e}"))
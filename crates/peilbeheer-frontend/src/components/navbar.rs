@@ -1,5 +1,6 @@
 use dioxus::prelude::*;
 
+use crate::api;
 use crate::Route;
 
 #[component]
@@ -24,6 +25,11 @@ pub fn Navbar() -> Element {
                     }
                 }
             }
+            button {
+                class: "navbar-logout",
+                onclick: move |_| async move { api::logout().await },
+                "Uitloggen"
+            }
         }
     }
 }
@@ -9,6 +9,7 @@ use components::navbar::Navbar;
 use pages::dashboard::Dashboard;
 use pages::gemaal_detail::GemaalDetail;
 use pages::gemalen::Gemalen;
+use pages::login::Login;
 
 #[derive(Debug, Clone, PartialEq, Routable)]
 enum Route {
@@ -25,6 +26,10 @@ enum Route {
 
 #[component]
 fn Layout() -> Element {
+    if api::AUTH_TOKEN.read().is_none() {
+        return rsx! { Login {} };
+    }
+
     rsx! {
         Navbar {}
         Outlet::<Route> {}
@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 use serde::Deserialize;
 use wasm_bindgen::JsValue;
 
-use crate::api::{self, OptimalisatieParams, OptimalisatieResultaat, UurPrijs};
+use crate::api::{self, Ontwerpbui, OptimalisatieParams, OptimalisatieResultaat, UurPrijs};
 
 /// Geselecteerd peilgebied (vanuit JS map click).
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -15,6 +15,9 @@ struct SelectedPeilgebied {
     vastpeil: Option<f64>,
     gemaal_naam: Option<String>,
     gemaal_capaciteit: Option<f64>,
+    /// Median AHN ground level (m NAP)
+    #[serde(default)]
+    maaiveld: Option<f64>,
 }
 
 #[component]
@@ -239,20 +242,43 @@ fn SimulatiePanel(peilgebied: SelectedPeilgebied, on_close: EventHandler) -> Ele
     let mut qf_intensiteit = use_signal(|| 10.0_f64);
     let mut qf_startuur = use_signal(|| 6_u8);
     let mut qf_duur = use_signal(|| 3_u8);
+    let mut ontwerpbuien: Signal<Vec<Ontwerpbui>> = use_signal(Vec::new);
+    let mut qf_ontwerpbui: Signal<Option<String>> = use_signal(|| None);
 
     // Auto-compute regen_per_uur whenever inputs change
     use_effect(move || {
         let mut regen = vec![0.0; 24];
         let start = qf_startuur() as usize;
-        let duur = qf_duur() as usize;
-        let intens = qf_intensiteit();
-        for i in 0..duur {
-            let h = (start + i) % 24;
-            regen[h] = intens;
+        let bui = qf_ontwerpbui()
+            .and_then(|id| ontwerpbuien.read().iter().find(|b| b.id == id).cloned());
+        match bui {
+            // Design storm: its hourly pattern from the start hour
+            Some(bui) => {
+                for (i, r) in bui.regen_per_uur.iter().enumerate() {
+                    regen[(start + i) % 24] = *r;
+                }
+            }
+            None => {
+                let duur = qf_duur() as usize;
+                let intens = qf_intensiteit();
+                for i in 0..duur {
+                    let h = (start + i) % 24;
+                    regen[h] = intens;
+                }
+            }
         }
         opt_regen_per_uur.set(regen);
     });
 
+    // Design storms that fit in the 24-hour window
+    use_effect(move || {
+        spawn(async move {
+            if let Ok(buien) = api::fetch_ontwerpbuien(24).await {
+                ontwerpbuien.set(buien);
+            }
+        });
+    });
+
     // Auto-fetch prices on mount
     use_effect(move || {
         spawn(async move {
@@ -301,6 +327,14 @@ fn SimulatiePanel(peilgebied: SelectedPeilgebied, on_close: EventHandler) -> Ele
                             span { class: "sim-info-chip-label", "Peil" }
                             span { class: "sim-info-chip-value", "{peil_display}" }
                         }
+                        if let Some(maaiveld) = peilgebied.maaiveld {
+                            div { class: "sim-info-chip",
+                                span { class: "sim-info-chip-label", "Maaiveld" }
+                                span { class: "sim-info-chip-value",
+                                    {format!("{maaiveld:.2} m NAP")}
+                                }
+                            }
+                        }
                         if let Some(ref naam) = peilgebied.gemaal_naam {
                             div { class: "sim-info-chip",
                                 span { class: "sim-info-chip-label", "Gemaal" }
@@ -317,31 +351,53 @@ fn SimulatiePanel(peilgebied: SelectedPeilgebied, on_close: EventHandler) -> Ele
 
                     // Regen scenario
                     h3 { class: "sim-section-title", "Regenscenario (24 uur)" }
-                    div { class: "sim-form-grid",
+                    if !ontwerpbuien.read().is_empty() {
                         div { class: "sim-field",
-                            label { "Intensiteit" }
+                            label { "Bui" }
                             div { class: "sim-input-wrap",
-                                input {
-                                    r#type: "number", step: "1", min: "0",
-                                    value: "{qf_intensiteit}",
+                                select {
+                                    value: qf_ontwerpbui().unwrap_or_default(),
                                     onchange: move |e: Event<FormData>| {
-                                        if let Ok(v) = e.value().parse::<f64>() { qf_intensiteit.set(v); }
+                                        let id = e.value();
+                                        qf_ontwerpbui.set((!id.is_empty()).then_some(id));
                                     },
+                                    option { value: "", "Handmatig" }
+                                    for bui in ontwerpbuien.read().iter() {
+                                        option { value: "{bui.id}",
+                                            "T={bui.herhalingstijd}, {bui.duur_uren} uur, {bui.patroon} ({bui.totaal_mm:.0} mm)"
+                                        }
+                                    }
                                 }
-                                span { class: "sim-unit", "mm/uur" }
                             }
                         }
-                        div { class: "sim-field",
-                            label { "Duur" }
-                            div { class: "sim-input-wrap",
-                                input {
-                                    r#type: "number", step: "1", min: "1", max: "24",
-                                    value: "{qf_duur}",
-                                    onchange: move |e: Event<FormData>| {
-                                        if let Ok(v) = e.value().parse::<u8>() { qf_duur.set(v.clamp(1, 24)); }
-                                    },
+                    }
+                    if qf_ontwerpbui().is_none() {
+                        div { class: "sim-form-grid",
+                            div { class: "sim-field",
+                                label { "Intensiteit" }
+                                div { class: "sim-input-wrap",
+                                    input {
+                                        r#type: "number", step: "1", min: "0",
+                                        value: "{qf_intensiteit}",
+                                        onchange: move |e: Event<FormData>| {
+                                            if let Ok(v) = e.value().parse::<f64>() { qf_intensiteit.set(v); }
+                                        },
+                                    }
+                                    span { class: "sim-unit", "mm/uur" }
+                                }
+                            }
+                            div { class: "sim-field",
+                                label { "Duur" }
+                                div { class: "sim-input-wrap",
+                                    input {
+                                        r#type: "number", step: "1", min: "1", max: "24",
+                                        value: "{qf_duur}",
+                                        onchange: move |e: Event<FormData>| {
+                                            if let Ok(v) = e.value().parse::<u8>() { qf_duur.set(v.clamp(1, 24)); }
+                                        },
+                                    }
+                                    span { class: "sim-unit", "uren" }
                                 }
-                                span { class: "sim-unit", "uren" }
                             }
                         }
                     }
@@ -538,6 +594,7 @@ fn SimulatiePanel(peilgebied: SelectedPeilgebied, on_close: EventHandler) -> Ele
                                     streefpeil: streefpeil(),
                                     marge_cm: opt_marge_cm(),
                                     max_debiet: gemaal_debiet(),
+                                    maaiveld: peilgebied.maaiveld,
                                 }
                             },
                             Err(e) => rsx! {
@@ -616,6 +673,7 @@ fn OptimalisatieResultDisplay(
     streefpeil: f64,
     marge_cm: f64,
     max_debiet: f64,
+    maaiveld: Option<f64>,
 ) -> Element {
     let besparing_positief = data.besparing_eur >= 0.0;
 
@@ -624,6 +682,7 @@ fn OptimalisatieResultDisplay(
     let chart_streef = streefpeil;
     let chart_marge = marge_cm;
     let chart_debiet = max_debiet;
+    let chart_maaiveld = maaiveld;
     use_effect(move || {
         let window = web_sys::window().unwrap();
 
@@ -668,6 +727,8 @@ fn OptimalisatieResultDisplay(
         js_sys::Reflect::set(&window, &JsValue::from_str("_optPump"), &vec_to_js_array(&pump_opt)).ok();
         js_sys::Reflect::set(&window, &JsValue::from_str("_optPrices"), &vec_to_js_array(&prices)).ok();
         js_sys::Reflect::set(&window, &JsValue::from_str("_optStreefpeil"), &JsValue::from_f64(chart_streef)).ok();
+        let maaiveld = chart_maaiveld.map(JsValue::from_f64).unwrap_or(JsValue::NULL);
+        js_sys::Reflect::set(&window, &JsValue::from_str("_optMaaiveld"), &maaiveld).ok();
 
         let marge_m = chart_marge / 100.0;
         js_sys::Reflect::set(&window, &JsValue::from_str("_optBandMin"), &JsValue::from_f64(chart_streef - marge_m)).ok();
@@ -793,6 +854,7 @@ fn build_opt_chart_js() -> String {
             var streefpeil = window._optStreefpeil || 0;
             var bandMin = window._optBandMin || 0;
             var bandMax = window._optBandMax || 0;
+            var maaiveld = window._optMaaiveld;
 
             var hourLabels = labels.map(function(m) {
                 var h = Math.floor(m / 60);
@@ -876,6 +938,19 @@ fn build_opt_chart_js() -> String {
                 }
             ];
 
+            if (maaiveld != null) {
+                datasets.push({
+                    label: 'Maaiveld',
+                    data: labels.map(function() { return maaiveld; }),
+                    borderColor: 'rgb(120, 72, 32)',
+                    borderWidth: 1.5,
+                    borderDash: [6, 3],
+                    pointRadius: 0,
+                    fill: false,
+                    yAxisID: 'y'
+                });
+            }
+
             window._optChart = new Chart(ctx, {
                 type: 'line',
                 data: { labels: hourLabels, datasets: datasets },
@@ -935,139 +1010,6 @@ fn build_opt_chart_js() -> String {
     "##.to_string()
 }
 
-#[allow(dead_code)]
-fn build_chart_js() -> String {
-    // All data is pre-set on window._sim* properties by Rust.
-    // Static JS string literal — no user input, safe to evaluate.
-    r##"
-    setTimeout(function() {
-        try {
-            console.log('[SimChart] building chart...');
-            if (window._simChart) { window._simChart.destroy(); window._simChart = null; }
-            var canvas = document.getElementById('sim-waterstand-chart');
-            if (!canvas) { console.warn('[SimChart] canvas not found'); return; }
-            if (typeof Chart === 'undefined') { console.error('[SimChart] Chart.js not loaded'); return; }
-
-            var ctx = canvas.getContext('2d');
-            var labels = Array.from(window._simLabels || []);
-            var waterstandData = Array.from(window._simWaterstand || []);
-            var pumpData = Array.from(window._simPump || []);
-            var streefpeil = window._simStreefpeil || 0;
-            var maaiveld = window._simMaaiveld || 0;
-            var margeLevel = window._simMargeLevel || 0;
-
-            console.log('[SimChart] data points:', labels.length, 'streefpeil:', streefpeil);
-
-            var datasets = [
-                {
-                    label: 'Waterstand',
-                    data: waterstandData,
-                    borderColor: 'rgb(37, 99, 235)',
-                    backgroundColor: 'rgba(37, 99, 235, 0.1)',
-                    fill: true,
-                    borderWidth: 2,
-                    pointRadius: 0,
-                    tension: 0.2,
-                    yAxisID: 'y'
-                },
-                {
-                    label: 'Streefpeil',
-                    data: labels.map(function() { return streefpeil; }),
-                    borderColor: 'rgb(34, 197, 94)',
-                    borderWidth: 1.5,
-                    borderDash: [6, 3],
-                    pointRadius: 0,
-                    fill: false,
-                    yAxisID: 'y'
-                }
-            ];
-
-            if (maaiveld !== 0) {
-                datasets.push({
-                    label: 'Maaiveld',
-                    data: labels.map(function() { return maaiveld; }),
-                    borderColor: 'rgb(249, 115, 22)',
-                    borderWidth: 1.5,
-                    borderDash: [6, 3],
-                    pointRadius: 0,
-                    fill: false,
-                    yAxisID: 'y'
-                });
-                datasets.push({
-                    label: 'Drooglegging marge',
-                    data: labels.map(function() { return margeLevel; }),
-                    borderColor: 'rgba(234, 179, 8, 0.5)',
-                    backgroundColor: 'rgba(234, 179, 8, 0.08)',
-                    borderWidth: 1,
-                    borderDash: [3, 3],
-                    pointRadius: 0,
-                    fill: '+1',
-                    yAxisID: 'y'
-                });
-            }
-
-            datasets.push({
-                label: 'Gemaal inzet (%)',
-                data: pumpData,
-                borderColor: 'rgba(220, 38, 38, 0.7)',
-                backgroundColor: 'rgba(220, 38, 38, 0.15)',
-                fill: true,
-                borderWidth: 1.5,
-                pointRadius: 0,
-                yAxisID: 'y1'
-            });
-
-            window._simChart = new Chart(ctx, {
-                type: 'line',
-                data: { labels: labels, datasets: datasets },
-                options: {
-                    responsive: true,
-                    maintainAspectRatio: false,
-                    animation: false,
-                    interaction: { mode: 'index', intersect: false },
-                    plugins: {
-                        legend: {
-                            display: true,
-                            position: 'bottom',
-                            labels: { boxWidth: 12, font: { size: 10 }, padding: 8 }
-                        },
-                        tooltip: {
-                            callbacks: {
-                                title: function(items) { return 't = ' + items[0].label + ' min'; }
-                            }
-                        }
-                    },
-                    scales: {
-                        x: {
-                            title: { display: true, text: 'Tijd (min)', font: { size: 10 } },
-                            ticks: {
-                                maxTicksLimit: 8,
-                                font: { size: 9 }
-                            }
-                        },
-                        y: {
-                            title: { display: true, text: 'm NAP', font: { size: 10 } },
-                            ticks: { font: { size: 9 } }
-                        },
-                        y1: {
-                            position: 'right',
-                            title: { display: true, text: 'Gemaal (%)', font: { size: 10 } },
-                            min: 0,
-                            max: 100,
-                            grid: { drawOnChartArea: false },
-                            ticks: { font: { size: 9 } }
-                        }
-                    }
-                }
-            });
-            console.log('[SimChart] chart created OK');
-        } catch(e) {
-            console.error('[SimChart] error:', e);
-        }
-    }, 100);
-    "##.to_string()
-}
-
 fn build_gemalen_map_js() -> String {
     // GeoJSON data is pre-set on window._pgData / window._gmData by Rust
     // to avoid embedding huge JSON strings.
@@ -1169,6 +1111,7 @@ fn build_gemalen_map_js() -> String {
                                     zomerpeil: p.ZOMERPEIL || null,
                                     winterpeil: p.WINTERPEIL || null,
                                     vastpeil: p.VASTPEIL || null,
+                                    maaiveld: p.MAAIVELD != null ? p.MAAIVELD : null,
                                     gemaal_naam: nearest ? (nearest.properties.naam || null) : null,
                                     gemaal_capaciteit: nearest ? (nearest.properties.extra_properties ? nearest.properties.extra_properties.MAXIMALECAPACITEIT : null) : null
                                 };
@@ -0,0 +1,73 @@
+use dioxus::prelude::*;
+
+use crate::api;
+
+/// Login form, shown instead of the pages as long as there is no token.
+#[component]
+pub fn Login() -> Element {
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut totp_code = use_signal(String::new);
+    let mut error: Signal<Option<String>> = use_signal(|| None);
+    let mut loading = use_signal(|| false);
+
+    let on_submit = move |e: Event<FormData>| {
+        e.prevent_default();
+        spawn(async move {
+            loading.set(true);
+            let code = totp_code();
+            let code = (!code.trim().is_empty()).then_some(code.trim());
+            if let Err(e) = api::login(&username(), &password(), code).await {
+                error.set(Some(e));
+            }
+            loading.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "page",
+            h1 { class: "page-title", "Inloggen" }
+            form { class: "form-card", onsubmit: on_submit,
+                div { class: "form-grid",
+                    div { class: "form-group",
+                        label { "Gebruikersnaam" }
+                        input {
+                            r#type: "text",
+                            autocomplete: "username",
+                            value: "{username}",
+                            oninput: move |e| username.set(e.value()),
+                        }
+                    }
+                    div { class: "form-group",
+                        label { "Wachtwoord" }
+                        input {
+                            r#type: "password",
+                            autocomplete: "current-password",
+                            value: "{password}",
+                            oninput: move |e| password.set(e.value()),
+                        }
+                    }
+                    div { class: "form-group",
+                        label { "Verificatiecode" }
+                        input {
+                            r#type: "text",
+                            autocomplete: "one-time-code",
+                            value: "{totp_code}",
+                            oninput: move |e| totp_code.set(e.value()),
+                        }
+                        span { class: "unit", "alleen bij tweefactorauthenticatie" }
+                    }
+                }
+                if let Some(e) = error() {
+                    div { class: "error-message", "{e}" }
+                }
+                button {
+                    class: "btn btn-primary",
+                    r#type: "submit",
+                    disabled: loading(),
+                    if loading() { "Bezig..." } else { "Inloggen" }
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod dashboard;
 pub mod gemaal_detail;
 pub mod gemalen;
+pub mod login;
 pub mod simulatie;
@@ -14,8 +14,11 @@ pub fn Simulatie() -> Element {
 }
 
 /// Reusable simulatie form + results, without page wrapper.
+///
+/// With a `peilgebied` and an empty ground level the API uses the ground
+/// level of that peilgebied.
 #[component]
-pub fn SimulatieSection() -> Element {
+pub fn SimulatieSection(#[props(default)] peilgebied: Option<String>) -> Element {
     let mut start_waterstand = use_signal(|| "-0.60".to_string());
     let mut regen_intensiteit = use_signal(|| "10.0".to_string());
     let mut regen_duur = use_signal(|| "60.0".to_string());
@@ -28,7 +31,7 @@ pub fn SimulatieSection() -> Element {
     let mut smart_control = use_signal(|| false);
     let mut streefpeil = use_signal(|| "-0.60".to_string());
     let mut marge = use_signal(|| "5.0".to_string());
-    let mut maaiveld_niveau = use_signal(|| "0.0".to_string());
+    let mut maaiveld_niveau = use_signal(String::new);
 
     let mut result: Signal<Option<Result<SimulatieResponse, String>>> = use_signal(|| None);
     let mut loading = use_signal(|| false);
@@ -47,7 +50,8 @@ pub fn SimulatieSection() -> Element {
             smart_control: smart_control(),
             streefpeil: streefpeil().parse().unwrap_or(-0.60),
             marge: marge().parse().unwrap_or(5.0),
-            maaiveld_niveau: maaiveld_niveau().parse().unwrap_or(0.0),
+            maaiveld_niveau: maaiveld_niveau().parse().ok(),
+            peilgebied: peilgebied.clone(),
         };
 
         spawn(async move {
@@ -0,0 +1,287 @@
+// Interactieve grafieken van een HTML-rapport.
+//
+// Elke <figure class="grafiek"> bevat zijn reeksen als JSON. De grafiek
+// wordt als SVG getekend; bewegen toont de waarden bij de cursor, slepen
+// zoomt in op de tijdas, dubbelklikken zoomt weer uit en een klik op de
+// legenda verbergt of toont een reeks.
+(function () {
+  'use strict';
+
+  var SVG = 'http://www.w3.org/2000/svg';
+  var KLEUREN = ['#1f77b4', '#d62728', '#2ca02c', '#ff7f0e', '#9467bd',
+    '#8c564b', '#e377c2', '#7f7f7f', '#bcbd22', '#17becf'];
+  var BREEDTE = 900;
+  var HOOGTE = 420;
+  var MARGE = { links: 70, rechts: 20, boven: 20, onder: 50 };
+
+  function element(naam, attributen, ouder) {
+    var el = document.createElementNS(SVG, naam);
+    Object.keys(attributen).forEach(function (sleutel) {
+      el.setAttribute(sleutel, attributen[sleutel]);
+    });
+    if (ouder) {
+      ouder.appendChild(el);
+    }
+    return el;
+  }
+
+  function getal(waarde) {
+    return waarde.toLocaleString('nl-NL', { maximumFractionDigits: 3 });
+  }
+
+  // Ronde waarden tussen van en tot, ongeveer aantal stuks.
+  function schaalstreepjes(van, tot, aantal) {
+    var ruw = (tot - van) / aantal;
+    var stap = Math.pow(10, Math.floor(Math.log10(ruw)));
+    var factor = ruw / stap;
+    stap *= factor < 1.5 ? 1 : factor < 3 ? 2 : factor < 7 ? 5 : 10;
+    var streepjes = [];
+    for (var w = Math.ceil(van / stap) * stap; w <= tot + stap * 1e-9; w += stap) {
+      streepjes.push(Math.abs(w) < stap * 1e-9 ? 0 : w);
+    }
+    return streepjes;
+  }
+
+  // Index van het punt met de x het dichtst bij x; punten zijn op x gesorteerd.
+  function dichtstbij(punten, x) {
+    var laag = 0;
+    var hoog = punten.length - 1;
+    while (hoog - laag > 1) {
+      var midden = (laag + hoog) >> 1;
+      if (punten[midden][0] < x) {
+        laag = midden;
+      } else {
+        hoog = midden;
+      }
+    }
+    return Math.abs(punten[laag][0] - x) <= Math.abs(punten[hoog][0] - x) ? laag : hoog;
+  }
+
+  // Kleinste en grootste waarde; een lus, want Math.min.apply loopt bij
+  // lange reeksen tegen de grens van het aantal argumenten aan.
+  function bereik(waarden) {
+    var min = Infinity;
+    var max = -Infinity;
+    for (var i = 0; i < waarden.length; i++) {
+      if (waarden[i] < min) {
+        min = waarden[i];
+      }
+      if (waarden[i] > max) {
+        max = waarden[i];
+      }
+    }
+    if (min === max) {
+      var rand = Math.abs(min) * 0.05 || 0.5;
+      return [min - rand, max + rand];
+    }
+    return [min, max];
+  }
+
+  function grafiek(figuur) {
+    var data = JSON.parse(figuur.querySelector('script').textContent);
+    var reeksen = data.reeksen.map(function (reeks, i) {
+      return {
+        naam: reeks.naam,
+        kleur: KLEUREN[i % KLEUREN.length],
+        punten: reeks.punten.filter(function (p) {
+          return p[1] !== null && isFinite(p[1]);
+        }),
+        zichtbaar: true
+      };
+    }).filter(function (reeks) {
+      return reeks.punten.length > 0;
+    });
+    var zoom = null;
+    var schaal = null;
+
+    var svg = element('svg', { viewBox: '0 0 ' + BREEDTE + ' ' + HOOGTE, role: 'img' });
+    var tip = document.createElement('div');
+    tip.className = 'tip';
+    var legenda = document.createElement('div');
+    legenda.className = 'legenda';
+    figuur.appendChild(svg);
+    figuur.appendChild(tip);
+    figuur.appendChild(legenda);
+
+    reeksen.forEach(function (reeks) {
+      var knop = document.createElement('button');
+      knop.type = 'button';
+      knop.innerHTML = '<span></span>';
+      knop.firstChild.style.background = reeks.kleur;
+      knop.appendChild(document.createTextNode(reeks.naam));
+      knop.addEventListener('click', function () {
+        reeks.zichtbaar = !reeks.zichtbaar;
+        knop.classList.toggle('uit', !reeks.zichtbaar);
+        teken();
+      });
+      legenda.appendChild(knop);
+    });
+
+    function zichtbare() {
+      return reeksen.filter(function (reeks) {
+        return reeks.zichtbaar;
+      });
+    }
+
+    function teken() {
+      while (svg.firstChild) {
+        svg.removeChild(svg.firstChild);
+      }
+      var actief = zichtbare();
+      if (actief.length === 0) {
+        schaal = null;
+        return;
+      }
+
+      var xs = [];
+      actief.forEach(function (reeks) {
+        xs.push(reeks.punten[0][0], reeks.punten[reeks.punten.length - 1][0]);
+      });
+      var x = zoom || bereik(xs);
+      var ys = [];
+      actief.forEach(function (reeks) {
+        reeks.punten.forEach(function (p) {
+          if (p[0] >= x[0] && p[0] <= x[1]) {
+            ys.push(p[1]);
+          }
+        });
+      });
+      var y = bereik(ys.length > 0 ? ys : [0]);
+
+      var links = MARGE.links;
+      var rechts = BREEDTE - MARGE.rechts;
+      var boven = MARGE.boven;
+      var onder = HOOGTE - MARGE.onder;
+      schaal = {
+        x: x,
+        naarX: function (w) { return links + (w - x[0]) / (x[1] - x[0]) * (rechts - links); },
+        naarY: function (w) { return onder - (w - y[0]) / (y[1] - y[0]) * (onder - boven); },
+        vanX: function (px) { return x[0] + (px - links) / (rechts - links) * (x[1] - x[0]); },
+        links: links,
+        rechts: rechts,
+        boven: boven,
+        onder: onder
+      };
+
+      schaalstreepjes(y[0], y[1], 6).forEach(function (w) {
+        var py = schaal.naarY(w);
+        element('line', { x1: links, x2: rechts, y1: py, y2: py, class: 'raster' }, svg);
+        element('text', { x: links - 6, y: py + 4, 'text-anchor': 'end' }, svg).textContent = getal(w);
+      });
+      schaalstreepjes(x[0], x[1], 8).forEach(function (w) {
+        var px = schaal.naarX(w);
+        element('line', { x1: px, x2: px, y1: boven, y2: onder, class: 'raster' }, svg);
+        element('text', { x: px, y: onder + 16, 'text-anchor': 'middle' }, svg).textContent = getal(w);
+      });
+      element('rect', { x: links, y: boven, width: rechts - links, height: onder - boven, class: 'kader' }, svg);
+      element('text', { x: (links + rechts) / 2, y: HOOGTE - 8, 'text-anchor': 'middle', class: 'label' }, svg)
+        .textContent = data.x_label;
+      element('text', {
+        x: 0, y: 0, 'text-anchor': 'middle', class: 'label',
+        transform: 'translate(16 ' + (boven + onder) / 2 + ') rotate(-90)'
+      }, svg).textContent = data.y_label;
+
+      var clip = 'clip-' + data.id;
+      var defs = element('defs', {}, svg);
+      element('rect', { x: links, y: boven, width: rechts - links, height: onder - boven },
+        element('clipPath', { id: clip }, defs));
+      actief.forEach(function (reeks) {
+        element('polyline', {
+          points: reeks.punten.map(function (p) {
+            return schaal.naarX(p[0]).toFixed(1) + ',' + schaal.naarY(p[1]).toFixed(1);
+          }).join(' '),
+          stroke: reeks.kleur,
+          'clip-path': 'url(#' + clip + ')',
+          class: 'lijn'
+        }, svg);
+      });
+      schaal.cursor = element('line', { y1: boven, y2: onder, class: 'cursor', visibility: 'hidden' }, svg);
+      schaal.selectie = element('rect', { y: boven, height: onder - boven, class: 'selectie', visibility: 'hidden' }, svg);
+    }
+
+    function positie(gebeurtenis) {
+      var rect = svg.getBoundingClientRect();
+      return (gebeurtenis.clientX - rect.left) / rect.width * BREEDTE;
+    }
+
+    function binnen(px) {
+      return Math.min(Math.max(px, schaal.links), schaal.rechts);
+    }
+
+    var sleepStart = null;
+
+    svg.addEventListener('mousemove', function (gebeurtenis) {
+      if (!schaal) {
+        return;
+      }
+      var px = binnen(positie(gebeurtenis));
+      var x = schaal.vanX(px);
+      schaal.cursor.setAttribute('x1', px);
+      schaal.cursor.setAttribute('x2', px);
+      schaal.cursor.setAttribute('visibility', 'visible');
+
+      var regels = [data.x_label + ': ' + getal(x)];
+      zichtbare().forEach(function (reeks) {
+        var p = reeks.punten[dichtstbij(reeks.punten, x)];
+        regels.push('<span style="color:' + reeks.kleur + '">■</span> ' +
+          reeks.naam.replace(/[&<>]/g, function (c) {
+            return { '&': '&amp;', '<': '&lt;', '>': '&gt;' }[c];
+          }) + ': ' + getal(p[1]));
+      });
+      tip.innerHTML = regels.join('<br>');
+      tip.style.display = 'block';
+      var figuurRect = figuur.getBoundingClientRect();
+      var links = gebeurtenis.clientX - figuurRect.left + 12;
+      if (links + tip.offsetWidth > figuur.clientWidth) {
+        links -= tip.offsetWidth + 24;
+      }
+      tip.style.left = links + 'px';
+      tip.style.top = (gebeurtenis.clientY - figuurRect.top + 12) + 'px';
+
+      if (sleepStart !== null) {
+        schaal.selectie.setAttribute('x', Math.min(sleepStart, px));
+        schaal.selectie.setAttribute('width', Math.abs(px - sleepStart));
+        schaal.selectie.setAttribute('visibility', 'visible');
+      }
+    });
+
+    svg.addEventListener('mouseleave', function () {
+      tip.style.display = 'none';
+      sleepStart = null;
+      if (schaal) {
+        schaal.cursor.setAttribute('visibility', 'hidden');
+        schaal.selectie.setAttribute('visibility', 'hidden');
+      }
+    });
+
+    svg.addEventListener('mousedown', function (gebeurtenis) {
+      if (schaal) {
+        sleepStart = binnen(positie(gebeurtenis));
+        gebeurtenis.preventDefault();
+      }
+    });
+
+    svg.addEventListener('mouseup', function (gebeurtenis) {
+      if (sleepStart === null) {
+        return;
+      }
+      var eind = binnen(positie(gebeurtenis));
+      if (Math.abs(eind - sleepStart) > 5) {
+        zoom = [schaal.vanX(Math.min(sleepStart, eind)), schaal.vanX(Math.max(sleepStart, eind))];
+        teken();
+      } else {
+        schaal.selectie.setAttribute('visibility', 'hidden');
+      }
+      sleepStart = null;
+    });
+
+    svg.addEventListener('dblclick', function () {
+      zoom = null;
+      teken();
+    });
+
+    teken();
+  }
+
+  Array.prototype.forEach.call(document.querySelectorAll('figure.grafiek'), grafiek);
+})();
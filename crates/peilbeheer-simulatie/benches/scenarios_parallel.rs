@@ -0,0 +1,119 @@
+// Benchmark van `run_scenarios_parallel`.
+//
+// Rekent 50 scenario's door, eerst serieel en daarna parallel met een
+// oplopend aantal threads, en toont de versnelling ten opzichte van de
+// seriële run. Uitvoeren met:
+//
+//     cargo bench -p peilbeheer-simulatie --bench scenarios_parallel
+
+use std::time::{Duration, Instant};
+
+use peilbeheer_simulatie::netwerk::*;
+use peilbeheer_simulatie::scenario::*;
+
+const AANTAL_SCENARIOS: usize = 50;
+const DUUR_UREN: usize = 48;
+
+fn main() {
+    let scenarios = maak_scenarios();
+
+    let serieel = meet(|| {
+        for scenario in &scenarios {
+            scenario.simuleer().expect("Scenario moet slagen");
+        }
+    });
+    println!(
+        "{} scenario's van {} uur, serieel: {:>8.1} ms",
+        AANTAL_SCENARIOS,
+        DUUR_UREN,
+        serieel.as_secs_f64() * 1000.0
+    );
+
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("Threadpool aanmaken mislukt");
+        let parallel = meet(|| {
+            pool.install(|| {
+                let resultaten = run_scenarios_parallel(&scenarios, |_, _| {});
+                assert!(resultaten.iter().all(Result::is_ok));
+            })
+        });
+        println!(
+            "{:>3} threads:          {:>8.1} ms  (versnelling {:.2}x)",
+            threads,
+            parallel.as_secs_f64() * 1000.0,
+            serieel.as_secs_f64() / parallel.as_secs_f64()
+        );
+        threads *= 2;
+    }
+}
+
+/// Beste van drie metingen, zodat opwarmen en ruis niet meetellen.
+fn meet(mut taak: impl FnMut()) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            taak();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn maak_scenarios() -> Vec<Scenario> {
+    let topologie = maak_topologie().expect("Topologie moet geldig zijn");
+    let ids: Vec<String> = topologie.peilgebieden.keys().cloned().collect();
+    (0..AANTAL_SCENARIOS)
+        .map(|i| {
+            // Elk scenario krijgt een andere constante regenintensiteit
+            let regen = constant_regen_scenario(&ids, 0.5 + i as f64 * 0.1, DUUR_UREN);
+            ScenarioBouwer::nieuw(format!("scenario_{}", i))
+                .met_topologie(topologie.clone())
+                .met_regen_scenario(regen)
+                .met_duur(DUUR_UREN)
+                .met_strategy(StrategyType::Gebalanceerd {
+                    balance_factor: 0.5,
+                })
+                .bouw()
+                .expect("Scenario moet geldig zijn")
+        })
+        .collect()
+}
+
+fn maak_topologie() -> Result<NetwerkTopologie, NetwerkFout> {
+    let mut topologie = NetwerkTopologie::nieuw();
+    for i in 0..10 {
+        topologie.voeg_peilgebied_toe(PeilgebiedConfig {
+            id: format!("polder_{}", i),
+            naam: None,
+            oppervlakte: 100_000.0 + i as f64 * 10_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.1,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.05,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        })?;
+    }
+    for i in 0..9 {
+        topologie.voeg_verbinding_toe(Verbinding::nieuw_gemaal(
+            format!("gemaal_{}", i),
+            format!("polder_{}", i),
+            format!("polder_{}", i + 1),
+            0.3,
+            1.0,
+        )?)?;
+    }
+    Ok(topologie)
+}
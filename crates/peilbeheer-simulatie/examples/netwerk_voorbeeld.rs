@@ -146,8 +146,15 @@ fn maak_topologie() -> Result<NetwerkTopologie, NetwerkFout> {
             marge: 0.20,
             maaiveld_niveau: 0.0,
             max_uitstroom_debiet: 0.6,
+            max_inlaat_debiet: 0.0,
             verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
             infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
         })?;
     }
 
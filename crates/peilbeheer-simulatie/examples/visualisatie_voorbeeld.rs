@@ -98,8 +98,15 @@ fn voer_scenario() -> Result<NetwerkSimulatieResultaat, Box<dyn std::error::Erro
             marge: 0.20,
             maaiveld_niveau: 0.0,
             max_uitstroom_debiet: 0.8,
+            max_inlaat_debiet: 0.0,
             verdamping: 0.1,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
             infiltratie: 0.05,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
         })?;
     }
 
@@ -0,0 +1,368 @@
+//! Checkpoints van lange netwerksimulaties.
+//!
+//! Een checkpoint bevat de volledige toestand van een [`NetwerkSimulatie`]:
+//! de topologie (met de actuele kruinhoogten van de stuwen), waterstanden,
+//! boezembelasting, chlorideconcentraties, de simulatietijd en de
+//! tijdstapinstellingen. Hervatten vanuit een checkpoint en dan verder
+//! simuleren met hetzelfde regenscenario geeft dezelfde uitkomst als een
+//! run die nooit onderbroken is; het regenscenario wordt vanaf de
+//! checkpointtijd verder gelezen.
+//!
+//! Regen die met [`NeerslagGenerator`](crate::scenario::NeerslagGenerator)
+//! is gemaakt hoeft niet in het checkpoint: met het zaad dat in het
+//! checkpoint staat kan dezelfde reeks opnieuw gegenereerd worden.
+//! Strategieën met een eigen interne toestand vallen buiten het checkpoint.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{
+    AdaptieveTijdstap, NetwerkFout, NetwerkSimulatie, NetwerkSimulatieResultaat, NetwerkTopologie,
+    PeilgebiedId, UitstroomStrategy,
+};
+
+/// Huidige versie van het checkpointformaat.
+pub const CHECKPOINT_VERSIE: u32 = 1;
+
+/// Volledige toestand van een netwerksimulatie.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Versie van het formaat, zie [`CHECKPOINT_VERSIE`]
+    pub versie: u32,
+    /// Topologie inclusief actuele kruinhoogten
+    pub topologie: NetwerkTopologie,
+    /// Waterstand per peilgebied en boezem
+    pub waterstanden: HashMap<PeilgebiedId, f64>,
+    /// Netto instroom per boezem in de laatste tijdstap (m³/s)
+    #[serde(default)]
+    pub boezembelasting: HashMap<PeilgebiedId, f64>,
+    /// Chlorideconcentratie per peilgebied in mg/l
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub chloride: HashMap<PeilgebiedId, f64>,
+    /// Simulatietijd in minuten
+    pub tijd: f64,
+    /// Tijdstap in minuten
+    pub stap_minuten: f64,
+    /// Adaptieve stapverkleining
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptief: Option<AdaptieveTijdstap>,
+    /// Duur van de laatste stap in minuten
+    pub laatste_stap: f64,
+    /// Zaad van de toevalsgenerator waarmee de regen is gemaakt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zaad: Option<u64>,
+}
+
+/// Fouttype voor checkpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckpointFout {
+    /// Kon checkpoint niet laden
+    LadenMislukt { pad: String, reden: String },
+    /// Kon checkpoint niet opslaan
+    OpslaanMislukt { pad: String, reden: String },
+    /// Checkpoint past niet bij zijn topologie of heeft een onbekende versie
+    OngeldigCheckpoint { reden: String },
+    /// De netwerksimulatie is mislukt
+    Netwerk(NetwerkFout),
+}
+
+impl fmt::Display for CheckpointFout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LadenMislukt { pad, reden } => {
+                write!(f, "Kon checkpoint niet laden van {}: {}", pad, reden)
+            }
+            Self::OpslaanMislukt { pad, reden } => {
+                write!(f, "Kon checkpoint niet opslaan naar {}: {}", pad, reden)
+            }
+            Self::OngeldigCheckpoint { reden } => write!(f, "Ongeldig checkpoint: {}", reden),
+            Self::Netwerk(e) => write!(f, "Netwerksimulatie mislukt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointFout {}
+
+impl From<NetwerkFout> for CheckpointFout {
+    fn from(e: NetwerkFout) -> Self {
+        Self::Netwerk(e)
+    }
+}
+
+impl Checkpoint {
+    /// Leg de toestand van een simulatie vast.
+    pub fn van(simulatie: &NetwerkSimulatie) -> Self {
+        Self {
+            versie: CHECKPOINT_VERSIE,
+            topologie: simulatie.topologie.clone(),
+            waterstanden: simulatie.waterstanden.clone(),
+            boezembelasting: simulatie.boezembelasting.clone(),
+            chloride: simulatie.chloride.clone(),
+            tijd: simulatie.tijd,
+            stap_minuten: simulatie.stap_minuten,
+            adaptief: simulatie.adaptief,
+            laatste_stap: simulatie.laatste_stap,
+            zaad: None,
+        }
+    }
+
+    /// Bewaar het zaad van de regengenerator in het checkpoint.
+    pub fn met_zaad(mut self, zaad: u64) -> Self {
+        self.zaad = Some(zaad);
+        self
+    }
+
+    /// Bouw de simulatie weer op vanuit het checkpoint.
+    pub fn hervat(self) -> Result<NetwerkSimulatie, CheckpointFout> {
+        if self.versie != CHECKPOINT_VERSIE {
+            return Err(CheckpointFout::OngeldigCheckpoint {
+                reden: format!(
+                    "versie {} wordt niet ondersteund, verwacht {}",
+                    self.versie, CHECKPOINT_VERSIE
+                ),
+            });
+        }
+        if !(self.tijd.is_finite() && self.tijd >= 0.0) {
+            return Err(CheckpointFout::OngeldigCheckpoint {
+                reden: format!("ongeldige simulatietijd {}", self.tijd),
+            });
+        }
+        let knooppunten = self
+            .topologie
+            .peilgebieden
+            .keys()
+            .chain(self.topologie.boezems.keys());
+        for id in knooppunten {
+            if !self.waterstanden.get(id).is_some_and(|h| h.is_finite()) {
+                return Err(CheckpointFout::OngeldigCheckpoint {
+                    reden: format!("geen geldige waterstand voor {}", id),
+                });
+            }
+        }
+
+        let mut simulatie =
+            NetwerkSimulatie::nieuw(self.topologie)?.met_tijdstap(self.stap_minuten)?;
+        if let Some(adaptief) = self.adaptief {
+            simulatie = simulatie.met_adaptieve_tijdstap(adaptief)?;
+        }
+        simulatie.waterstanden = self.waterstanden;
+        simulatie.boezembelasting.extend(self.boezembelasting);
+        simulatie.chloride.extend(self.chloride);
+        simulatie.tijd = self.tijd;
+        simulatie.laatste_stap = self.laatste_stap;
+        Ok(simulatie)
+    }
+
+    /// Sla het checkpoint op als JSON. Er wordt eerst naar een tijdelijk
+    /// bestand geschreven, zodat een crash tijdens het schrijven het vorige
+    /// checkpoint niet beschadigt.
+    pub fn sla_op<P: AsRef<Path>>(&self, pad: P) -> Result<(), CheckpointFout> {
+        let pad = pad.as_ref();
+        let fout = |reden: String| CheckpointFout::OpslaanMislukt {
+            pad: pad.display().to_string(),
+            reden,
+        };
+        let json =
+            serde_json::to_string(self).map_err(|e| fout(format!("Serialisatie fout: {}", e)))?;
+        let tijdelijk = pad.with_extension("tmp");
+        fs::write(&tijdelijk, json).map_err(|e| fout(e.to_string()))?;
+        fs::rename(&tijdelijk, pad).map_err(|e| fout(e.to_string()))
+    }
+
+    /// Laad een checkpoint uit een JSON bestand.
+    pub fn laad<P: AsRef<Path>>(pad: P) -> Result<Self, CheckpointFout> {
+        let pad = pad.as_ref();
+        let fout = |reden: String| CheckpointFout::LadenMislukt {
+            pad: pad.display().to_string(),
+            reden,
+        };
+        let inhoud = fs::read_to_string(pad).map_err(|e| fout(e.to_string()))?;
+        serde_json::from_str(&inhoud).map_err(|e| fout(format!("Parse fout: {}", e)))
+    }
+}
+
+/// Simuleer in blokken van `interval_uren` en roep na elk blok
+/// `bij_checkpoint` aan met het checkpoint en de tijdstappen van dat blok,
+/// zodat de aanroeper beide kan wegschrijven. Een fout uit de callback
+/// breekt de simulatie af. Geeft alle tijdstappen samen terug.
+pub fn simuleer_met_checkpoints<F>(
+    simulatie: &mut NetwerkSimulatie,
+    regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+    duration_hours: usize,
+    uitstroom_strategy: &dyn UitstroomStrategy,
+    interval_uren: usize,
+    mut bij_checkpoint: F,
+) -> Result<NetwerkSimulatieResultaat, CheckpointFout>
+where
+    F: FnMut(&Checkpoint, &NetwerkSimulatieResultaat) -> Result<(), CheckpointFout>,
+{
+    if interval_uren == 0 {
+        return Err(CheckpointFout::OngeldigCheckpoint {
+            reden: "checkpointinterval moet minstens één uur zijn".into(),
+        });
+    }
+
+    let mut tijdstappen = Vec::new();
+    let mut resterend = duration_hours;
+    while resterend > 0 {
+        let blok = interval_uren.min(resterend);
+        let resultaat = simulatie.simuleer(regen_scenario, blok, uitstroom_strategy)?;
+        bij_checkpoint(&Checkpoint::van(simulatie), &resultaat)?;
+        tijdstappen.extend(resultaat.tijdstappen);
+        resterend -= blok;
+    }
+
+    Ok(NetwerkSimulatieResultaat {
+        tijdstappen,
+        totale_kosten: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{Boezem, PeilgebiedConfig, SimpeleUitstroomStrategy, Verbinding};
+
+    fn maak_simulatie() -> NetwerkSimulatie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        for (id, max_uitstroom_debiet) in [("a", 0.0), ("b", 0.3)] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: None,
+                    oppervlakte: 100_000.0,
+                    streefpeil: -0.60,
+                    marge: 0.20,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        topologie
+            .voeg_boezem_toe(Boezem::nieuw("boezem", -0.40))
+            .unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_gemaal("gemaal".into(), "a".into(), "b".into(), 0.2, 1.0)
+                    .unwrap(),
+            )
+            .unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_gemaal("uitslag".into(), "b".into(), "boezem".into(), 0.3, 1.0)
+                    .unwrap(),
+            )
+            .unwrap();
+        NetwerkSimulatie::nieuw(topologie)
+            .unwrap()
+            .met_tijdstap(15.0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hervatten_geeft_zelfde_uitkomst() {
+        let regen = HashMap::from([(
+            "a".to_string(),
+            (0..48)
+                .map(|u| if u % 12 < 3 { 8.0 } else { 0.0 })
+                .collect(),
+        )]);
+        let strategy = SimpeleUitstroomStrategy;
+
+        let doorlopend = maak_simulatie().simuleer(&regen, 48, &strategy).unwrap();
+
+        // Onderbreek na 20 uur, sla op, laad en ga verder
+        let mut simulatie = maak_simulatie();
+        let eerste = simulatie.simuleer(&regen, 20, &strategy).unwrap();
+        let pad =
+            std::env::temp_dir().join(format!("peilbeheer_checkpoint_{}.json", std::process::id()));
+        Checkpoint::van(&simulatie)
+            .met_zaad(7)
+            .sla_op(&pad)
+            .unwrap();
+        drop(simulatie);
+
+        let geladen = Checkpoint::laad(&pad).unwrap();
+        fs::remove_file(&pad).unwrap();
+        assert_eq!(geladen.zaad, Some(7));
+        let mut hervat = geladen.hervat().unwrap();
+        assert_eq!(hervat.tijd, 20.0 * 60.0);
+        let tweede = hervat.simuleer(&regen, 28, &strategy).unwrap();
+
+        let onderbroken: Vec<_> = eerste
+            .tijdstappen
+            .iter()
+            .chain(&tweede.tijdstappen)
+            .collect();
+        assert_eq!(onderbroken.len(), doorlopend.tijdstappen.len());
+        for (a, b) in onderbroken.iter().zip(&doorlopend.tijdstappen) {
+            assert_eq!(a.tijd, b.tijd);
+            for (id, status) in &b.statussen {
+                assert!((a.statussen[id].waterstand - status.waterstand).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simuleer_met_checkpoints() {
+        let regen = HashMap::from([("a".to_string(), vec![5.0; 10])]);
+        let mut simulatie = maak_simulatie();
+        let mut tijden = Vec::new();
+        let resultaat = simuleer_met_checkpoints(
+            &mut simulatie,
+            &regen,
+            10,
+            &SimpeleUitstroomStrategy,
+            4,
+            |checkpoint, blok| {
+                assert_eq!(blok.tijdstappen.last().unwrap().tijd, checkpoint.tijd);
+                tijden.push(checkpoint.tijd / 60.0);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(tijden, vec![4.0, 8.0, 10.0]);
+        assert_eq!(resultaat.tijdstappen.len(), 40);
+        assert!(
+            simuleer_met_checkpoints(
+                &mut simulatie,
+                &regen,
+                1,
+                &SimpeleUitstroomStrategy,
+                0,
+                |_, _| Ok(()),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ongeldig_checkpoint() {
+        let mut checkpoint = Checkpoint::van(&maak_simulatie());
+        checkpoint.waterstanden.remove("b");
+        assert!(matches!(
+            checkpoint.clone().hervat(),
+            Err(CheckpointFout::OngeldigCheckpoint { .. })
+        ));
+
+        checkpoint.versie = CHECKPOINT_VERSIE + 1;
+        assert!(matches!(
+            checkpoint.hervat(),
+            Err(CheckpointFout::OngeldigCheckpoint { .. })
+        ));
+    }
+}
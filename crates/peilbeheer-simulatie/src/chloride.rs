@@ -0,0 +1,102 @@
+//! Chloridebalans van de peilgebieden in de netwerksimulatie.
+//!
+//! Chloride is een conservatieve stof: de massa in een peilgebied verandert
+//! alleen door wat met het water binnenkomt en weggaat. Per stap komt
+//! chloride binnen met de instroom over verbindingen (met de concentratie
+//! van het peilgebied of de boezem waar het water vandaan komt), met de
+//! inlaat uit de boezem en met zoute kwel. Het verdwijnt met de uitstroom,
+//! het uitslaan van het gemaal en wegzijging, tegen de eigen concentratie.
+//! Regen is zoetwater en verdamping laat het chloride achter. Binnen een
+//! peilgebied is het water volledig gemengd.
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::PeilgebiedConfig;
+
+/// Kleinste mengvolume in m³, zodat een drooggevallen peilgebied geen
+/// oneindige concentratie krijgt.
+const MIN_MENGVOLUME: f64 = 1.0;
+
+/// Chlorideparameters van een peilgebied.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChlorideParameters {
+    /// Concentratie aan het begin van de simulatie in mg/l
+    pub begin_concentratie: f64,
+    /// Concentratie van de kwel in mg/l
+    #[serde(default)]
+    pub kwel_concentratie: f64,
+    /// Gemiddelde waterdiepte bij streefpeil in m; bepaalt samen met het
+    /// bergend oppervlak het mengvolume
+    #[serde(default = "default_waterdiepte")]
+    pub waterdiepte: f64,
+}
+
+fn default_waterdiepte() -> f64 {
+    1.0
+}
+
+impl ChlorideParameters {
+    pub fn new(begin_concentratie: f64, kwel_concentratie: f64) -> Self {
+        Self {
+            begin_concentratie,
+            kwel_concentratie,
+            waterdiepte: default_waterdiepte(),
+        }
+    }
+
+    pub fn met_waterdiepte(mut self, waterdiepte: f64) -> Self {
+        self.waterdiepte = waterdiepte;
+        self
+    }
+
+    /// Controleer of de parameters bruikbaar zijn.
+    pub fn valideer(&self) -> Result<(), String> {
+        if !(self.begin_concentratie.is_finite() && self.begin_concentratie >= 0.0) {
+            return Err(format!(
+                "beginconcentratie moet >= 0 zijn, is {}",
+                self.begin_concentratie
+            ));
+        }
+        if !(self.kwel_concentratie.is_finite() && self.kwel_concentratie >= 0.0) {
+            return Err(format!(
+                "kwelconcentratie moet >= 0 zijn, is {}",
+                self.kwel_concentratie
+            ));
+        }
+        if !(self.waterdiepte.is_finite() && self.waterdiepte > 0.0) {
+            return Err(format!("waterdiepte moet > 0 zijn, is {}", self.waterdiepte));
+        }
+        Ok(())
+    }
+}
+
+/// Mengvolume van een peilgebied in m³ bij een waterstand: het volume bij
+/// streefpeil plus de berging tussen streefpeil en de waterstand.
+pub fn mengvolume(config: &PeilgebiedConfig, waterstand: f64) -> f64 {
+    let diepte = config
+        .chloride
+        .map_or_else(default_waterdiepte, |c| c.waterdiepte);
+    let bij_streefpeil = diepte * config.bergend_oppervlak(config.streefpeil);
+    let berging = match &config.bergingscurve {
+        Some(curve) => curve.volume(waterstand) - curve.volume(config.streefpeil),
+        None => config.oppervlakte * (waterstand - config.streefpeil),
+    };
+    (bij_streefpeil + berging).max(MIN_MENGVOLUME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valideer() {
+        assert!(ChlorideParameters::new(150.0, 1000.0).valideer().is_ok());
+        assert!(ChlorideParameters::new(-1.0, 0.0).valideer().is_err());
+        assert!(
+            ChlorideParameters::new(0.0, 0.0)
+                .met_waterdiepte(0.0)
+                .valideer()
+                .is_err()
+        );
+    }
+}
@@ -52,8 +52,9 @@ pub fn find_minimum_debiet(params: &SimulatieParams) -> MinimumDebietResult {
         let mut max_ov: f64 = 0.0;
 
         for stap in &tijdstappen {
+            // De overschrijding hangt niet af van het maaiveld
             let d = calculate_drooglegging(
-                params.maaiveld_niveau,
+                params.maaiveld_niveau.unwrap_or_default(),
                 stap.waterstand,
                 params.streefpeil,
                 params.marge,
@@ -0,0 +1,567 @@
+//! Verdeling van schaarse aanvoer bij droogte.
+//!
+//! Als er minder water uit de boezem beschikbaar is dan de peilgebieden
+//! vragen, wordt de aanvoer verdeeld volgens een [`Verdringingsreeks`]: de
+//! rangen krijgen op volgorde hun volledige vraag zolang er water is, en
+//! binnen een rang wordt naar rato van de vraag verdeeld. Een peilgebied
+//! hoort bij een rang omdat het er met naam in staat of omdat zijn functie
+//! (bijv. natuur, landbouw, drinkwater) erin staat; peilgebieden zonder rang
+//! komen als laatste.
+//!
+//! De balans per dag is die van de [jaarbalans](crate::jaarbalans), over het
+//! hele netwerk: wat een peilgebied vandaag tekortkomt zakt het peil, en
+//! vraagt het morgen opnieuw. De gevraagde volumes tellen dus de vraag van
+//! elke dag op. Alleen peilgebieden met een boezem vragen aanvoer; wat via
+//! verbindingen binnenkomt vermindert de vraag.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::jaarbalans::{
+    Dagweer, JaarbalansFout, JaarbalansInstellingen, JaarbalansResultaat, simuleer_dagen,
+};
+use crate::netwerk::{NetwerkTopologie, PeilgebiedId};
+
+/// Naam van de rang voor peilgebieden die in geen enkele rang vallen.
+pub const OVERIG: &str = "overig";
+
+/// Prioriteitsvolgorde voor de verdeling van aanvoer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Verdringingsreeks {
+    /// Rangen van hoogste naar laagste prioriteit
+    pub rangen: Vec<Rang>,
+    /// Functie per peilgebied
+    #[serde(default)]
+    pub functies: HashMap<PeilgebiedId, String>,
+}
+
+/// Eén rang van de verdringingsreeks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rang {
+    pub naam: String,
+    /// Peilgebieden die met naam in deze rang vallen
+    #[serde(default)]
+    pub peilgebieden: Vec<PeilgebiedId>,
+    /// Functies waarvan de peilgebieden in deze rang vallen
+    #[serde(default)]
+    pub functies: Vec<String>,
+}
+
+impl Verdringingsreeks {
+    /// Index van de rang van een peilgebied; een peilgebied dat met naam in
+    /// een rang staat gaat voor zijn functie. Zonder rang is dit het aantal
+    /// rangen.
+    pub fn rang(&self, id: &PeilgebiedId) -> usize {
+        if let Some(rang) = self.rangen.iter().position(|r| r.peilgebieden.contains(id)) {
+            return rang;
+        }
+        self.functies
+            .get(id)
+            .and_then(|functie| {
+                self.rangen
+                    .iter()
+                    .position(|r| r.functies.contains(functie))
+            })
+            .unwrap_or(self.rangen.len())
+    }
+
+    /// Verdeel `beschikbaar` over de vragen van peilgebieden met de gegeven
+    /// rangen: rang na rang de volledige vraag, en binnen de rang waar het
+    /// water opraakt naar rato.
+    pub fn verdeel(beschikbaar: f64, rangen: &[usize], vragen: &[f64]) -> Vec<f64> {
+        let mut toegewezen = vec![0.0; vragen.len()];
+        let mut rest = beschikbaar.max(0.0);
+        let mut volgorde: Vec<usize> = rangen.to_vec();
+        volgorde.sort_unstable();
+        volgorde.dedup();
+        for rang in volgorde {
+            let leden: Vec<usize> = (0..vragen.len()).filter(|&i| rangen[i] == rang).collect();
+            let vraag: f64 = leden.iter().map(|&i| vragen[i].max(0.0)).sum();
+            if vraag <= 0.0 {
+                continue;
+            }
+            let fractie = (rest / vraag).min(1.0);
+            for &i in &leden {
+                toegewezen[i] = vragen[i].max(0.0) * fractie;
+            }
+            rest = (rest - vraag).max(0.0);
+        }
+        toegewezen
+    }
+
+    fn valideer(&self, topologie: &NetwerkTopologie) -> Result<(), DroogteFout> {
+        let mut gezien = HashSet::new();
+        let mut functies = HashSet::new();
+        for rang in &self.rangen {
+            for id in &rang.peilgebieden {
+                if !topologie.peilgebieden.contains_key(id) {
+                    return Err(DroogteFout::OnbekendPeilgebied { id: id.clone() });
+                }
+                if !gezien.insert(id) {
+                    return Err(DroogteFout::DubbeleToewijzing { naam: id.clone() });
+                }
+            }
+            for functie in &rang.functies {
+                if !functies.insert(functie) {
+                    return Err(DroogteFout::DubbeleToewijzing {
+                        naam: functie.clone(),
+                    });
+                }
+            }
+        }
+        if let Some(id) = self
+            .functies
+            .keys()
+            .find(|id| !topologie.peilgebieden.contains_key(*id))
+        {
+            return Err(DroogteFout::OnbekendPeilgebied { id: id.clone() });
+        }
+        Ok(())
+    }
+
+    fn rangnaam(&self, rang: usize) -> &str {
+        self.rangen.get(rang).map_or(OVERIG, |r| r.naam.as_str())
+    }
+}
+
+/// Gevraagde en geleverde aanvoer in m³.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Levering {
+    pub gevraagd: f64,
+    pub geleverd: f64,
+    /// Gevraagd min geleverd
+    pub tekort: f64,
+    /// Geleverd gedeeld door gevraagd; 1 zonder vraag
+    pub leveringsgraad: f64,
+    /// Dagen waarop minder is geleverd dan gevraagd
+    pub dagen_met_tekort: usize,
+}
+
+impl Levering {
+    fn tel(&mut self, gevraagd: f64, geleverd: f64) {
+        self.gevraagd += gevraagd;
+        self.geleverd += geleverd;
+        self.tekort = self.gevraagd - self.geleverd;
+        self.leveringsgraad = if self.gevraagd > 0.0 {
+            self.geleverd / self.gevraagd
+        } else {
+            1.0
+        };
+        if geleverd < gevraagd {
+            self.dagen_met_tekort += 1;
+        }
+    }
+
+    fn nieuw() -> Self {
+        Self {
+            leveringsgraad: 1.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Levering aan één peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedLevering {
+    pub peilgebied_id: PeilgebiedId,
+    /// Naam van de rang
+    pub rang: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functie: Option<String>,
+    pub levering: Levering,
+}
+
+/// Levering aan de peilgebieden van één rang.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RangLevering {
+    pub naam: String,
+    pub peilgebieden: Vec<PeilgebiedId>,
+    pub levering: Levering,
+}
+
+/// Beschikbare, gevraagde en geleverde aanvoer op één dag, in m³.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DroogteDag {
+    pub datum: NaiveDate,
+    pub beschikbaar: f64,
+    pub gevraagd: f64,
+    pub geleverd: f64,
+}
+
+/// Resultaat van een droogtesimulatie.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroogteResultaat {
+    /// Waterbalans per peilgebied met de verdeelde aanvoer
+    pub balans: JaarbalansResultaat,
+    pub dagen: Vec<DroogteDag>,
+    /// Rangen op volgorde van prioriteit, met [`OVERIG`] als laatste als
+    /// er peilgebieden zonder rang zijn
+    pub rangen: Vec<RangLevering>,
+    /// Peilgebieden op volgorde van id
+    pub peilgebieden: Vec<PeilgebiedLevering>,
+    pub totaal: Levering,
+}
+
+/// Fouten bij een droogtesimulatie.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DroogteFout {
+    /// Er is geen beschikbare aanvoer opgegeven
+    GeenBeschikbaarheid,
+    /// De beschikbare aanvoer van een dag is negatief of geen getal
+    OngeldigeBeschikbaarheid { dag: usize },
+    /// De reeks noemt een onbekend peilgebied
+    OnbekendPeilgebied { id: PeilgebiedId },
+    /// Een peilgebied of functie staat in meer dan één rang
+    DubbeleToewijzing { naam: String },
+    /// De balans kon niet worden doorgerekend
+    Balans(JaarbalansFout),
+}
+
+impl fmt::Display for DroogteFout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GeenBeschikbaarheid => write!(f, "Geen beschikbare aanvoer opgegeven"),
+            Self::OngeldigeBeschikbaarheid { dag } => {
+                write!(f, "Ongeldige beschikbare aanvoer op dag {}", dag + 1)
+            }
+            Self::OnbekendPeilgebied { id } => {
+                write!(f, "Verdringingsreeks noemt onbekend peilgebied {}", id)
+            }
+            Self::DubbeleToewijzing { naam } => {
+                write!(f, "{} staat in meer dan één rang", naam)
+            }
+            Self::Balans(e) => write!(f, "Balans mislukt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DroogteFout {}
+
+impl From<JaarbalansFout> for DroogteFout {
+    fn from(e: JaarbalansFout) -> Self {
+        Self::Balans(e)
+    }
+}
+
+/// Reken een droge periode door met `beschikbaar_per_dag` m³ aanvoer per
+/// dag voor alle peilgebieden samen, verdeeld volgens `reeks`. Is de reeks
+/// beschikbaarheden korter dan het weer, dan geldt de laatste waarde voor
+/// de overige dagen.
+pub fn simuleer_droogte(
+    topologie: &NetwerkTopologie,
+    weer: &[Dagweer],
+    instellingen: &JaarbalansInstellingen,
+    reeks: &Verdringingsreeks,
+    beschikbaar_per_dag: &[f64],
+) -> Result<DroogteResultaat, DroogteFout> {
+    let laatste = *beschikbaar_per_dag
+        .last()
+        .ok_or(DroogteFout::GeenBeschikbaarheid)?;
+    if let Some(dag) = beschikbaar_per_dag
+        .iter()
+        .position(|b| !b.is_finite() || *b < 0.0)
+    {
+        return Err(DroogteFout::OngeldigeBeschikbaarheid { dag });
+    }
+    reeks.valideer(topologie)?;
+
+    let mut rangen: Vec<RangLevering> = (0..=reeks.rangen.len())
+        .map(|rang| RangLevering {
+            naam: reeks.rangnaam(rang).to_string(),
+            peilgebieden: Vec::new(),
+            levering: Levering::nieuw(),
+        })
+        .collect();
+    let mut leveringen: HashMap<PeilgebiedId, Levering> = HashMap::new();
+    let mut dagen = Vec::with_capacity(weer.len());
+    let balans = simuleer_dagen(topologie, weer, instellingen, |index, ids, vragen| {
+        let beschikbaar = beschikbaar_per_dag.get(index).copied().unwrap_or(laatste);
+        let rangen_per_id: Vec<usize> = ids.iter().map(|id| reeks.rang(id)).collect();
+        let toegewezen = Verdringingsreeks::verdeel(beschikbaar, &rangen_per_id, vragen);
+
+        let mut per_rang = vec![(0.0, 0.0); rangen.len()];
+        for (((id, rang), vraag), geleverd) in
+            ids.iter().zip(&rangen_per_id).zip(vragen).zip(&toegewezen)
+        {
+            leveringen
+                .entry((*id).clone())
+                .or_insert_with(Levering::nieuw)
+                .tel(*vraag, *geleverd);
+            per_rang[*rang].0 += vraag;
+            per_rang[*rang].1 += geleverd;
+        }
+        for (rang, (gevraagd, geleverd)) in rangen.iter_mut().zip(per_rang) {
+            rang.levering.tel(gevraagd, geleverd);
+        }
+        dagen.push(DroogteDag {
+            datum: weer[index].datum,
+            beschikbaar,
+            gevraagd: vragen.iter().sum(),
+            geleverd: toegewezen.iter().sum(),
+        });
+        toegewezen
+    })?;
+
+    let mut ids: Vec<&PeilgebiedId> = leveringen.keys().collect();
+    ids.sort();
+    let peilgebieden: Vec<PeilgebiedLevering> = ids
+        .into_iter()
+        .map(|id| {
+            let rang = reeks.rang(id);
+            rangen[rang].peilgebieden.push(id.clone());
+            PeilgebiedLevering {
+                peilgebied_id: id.clone(),
+                rang: reeks.rangnaam(rang).to_string(),
+                functie: reeks.functies.get(id).cloned(),
+                levering: leveringen[id],
+            }
+        })
+        .collect();
+    if rangen.last().is_some_and(|r| r.peilgebieden.is_empty()) {
+        rangen.pop();
+    }
+
+    let mut totaal = Levering::nieuw();
+    for dag in &dagen {
+        totaal.tel(dag.gevraagd, dag.geleverd);
+    }
+
+    Ok(DroogteResultaat {
+        balans,
+        dagen,
+        rangen,
+        peilgebieden,
+        totaal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{Boezem, PeilgebiedConfig, Verbinding};
+
+    fn peilgebied(id: &str) -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 1_000_000.0,
+            streefpeil: -0.60,
+            marge: 0.10,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet: 1.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: Some("boezem".to_string()),
+            chloride: None,
+        }
+    }
+
+    fn topologie() -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_boezem_toe(Boezem::nieuw("boezem", -0.40))
+            .unwrap();
+        for id in ["akker", "natuur", "weide"] {
+            topologie.voeg_peilgebied_toe(peilgebied(id)).unwrap();
+        }
+        topologie
+    }
+
+    /// Dertig droge zomerdagen met 5 mm verdamping: elk peilgebied vraagt
+    /// 5000 m³ per dag.
+    fn droge_maand() -> Vec<Dagweer> {
+        NaiveDate::from_ymd_opt(2025, 7, 1)
+            .unwrap()
+            .iter_days()
+            .take(30)
+            .map(|datum| Dagweer {
+                datum,
+                neerslag: 0.0,
+                referentieverdamping: 5.0,
+            })
+            .collect()
+    }
+
+    fn reeks() -> Verdringingsreeks {
+        Verdringingsreeks {
+            rangen: vec![
+                Rang {
+                    naam: "natuur".to_string(),
+                    peilgebieden: vec!["natuur".to_string()],
+                    functies: Vec::new(),
+                },
+                Rang {
+                    naam: "landbouw".to_string(),
+                    peilgebieden: Vec::new(),
+                    functies: vec!["landbouw".to_string()],
+                },
+            ],
+            functies: HashMap::from([
+                ("akker".to_string(), "landbouw".to_string()),
+                ("natuur".to_string(), "landbouw".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_verdeel() {
+        // Rang 0 krijgt alles, rang 1 deelt de rest naar rato, rang 2 niets
+        let toegewezen = Verdringingsreeks::verdeel(10.0, &[1, 0, 1, 2], &[4.0, 6.0, 4.0, 3.0]);
+        assert_eq!(toegewezen, vec![2.0, 6.0, 2.0, 0.0]);
+        // Genoeg water: iedereen zijn vraag
+        assert_eq!(
+            Verdringingsreeks::verdeel(100.0, &[1, 0], &[4.0, 6.0]),
+            vec![4.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_rang() {
+        let reeks = reeks();
+        // Met naam gaat voor de functie
+        assert_eq!(reeks.rang(&"natuur".to_string()), 0);
+        assert_eq!(reeks.rang(&"akker".to_string()), 1);
+        assert_eq!(reeks.rang(&"weide".to_string()), 2);
+    }
+
+    #[test]
+    fn test_droogte() {
+        let instellingen = JaarbalansInstellingen::default();
+        let resultaat = simuleer_droogte(
+            &topologie(),
+            &droge_maand(),
+            &instellingen,
+            &reeks(),
+            &[8000.0],
+        )
+        .unwrap();
+
+        let namen: Vec<&str> = resultaat.rangen.iter().map(|r| r.naam.as_str()).collect();
+        assert_eq!(namen, vec!["natuur", "landbouw", OVERIG]);
+        let levering = |id: &str| {
+            resultaat
+                .peilgebieden
+                .iter()
+                .find(|p| p.peilgebied_id == id)
+                .unwrap()
+        };
+        // Natuur krijgt alles, de akker de rest, de weide niets
+        assert_eq!(levering("natuur").levering.leveringsgraad, 1.0);
+        assert_eq!(levering("natuur").levering.dagen_met_tekort, 0);
+        assert!(levering("akker").levering.leveringsgraad < 1.0);
+        assert!(levering("akker").levering.geleverd > 0.0);
+        assert_eq!(levering("weide").levering.geleverd, 0.0);
+        assert_eq!(levering("weide").rang, OVERIG);
+        assert_eq!(levering("akker").functie.as_deref(), Some("landbouw"));
+
+        // Nooit meer geleverd dan beschikbaar, en de balans volgt de levering
+        assert!(
+            resultaat
+                .dagen
+                .iter()
+                .all(|d| d.geleverd <= d.beschikbaar + 1e-6)
+        );
+        assert!((resultaat.totaal.geleverd - 30.0 * 8000.0).abs() < 1e-6);
+        let weide = &resultaat.balans.peilgebieden[2];
+        assert_eq!(weide.peilgebied_id, "weide");
+        assert_eq!(weide.totaal.aanvoer, 0.0);
+        assert!(weide.totaal.dagen_onder_marge > 0);
+        let som: f64 = resultaat.rangen.iter().map(|r| r.levering.geleverd).sum();
+        assert!((som - resultaat.totaal.geleverd).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ruime_aanvoer_is_jaarbalans() {
+        let instellingen = JaarbalansInstellingen::default();
+        let resultaat = simuleer_droogte(
+            &topologie(),
+            &droge_maand(),
+            &instellingen,
+            &reeks(),
+            &[1.0e9],
+        )
+        .unwrap();
+        let balans =
+            crate::jaarbalans::simuleer_jaarbalans(&topologie(), &droge_maand(), &instellingen)
+                .unwrap();
+        assert_eq!(resultaat.balans, balans);
+        assert_eq!(resultaat.totaal.leveringsgraad, 1.0);
+    }
+
+    #[test]
+    fn test_aanvoer_via_verbinding() {
+        // De weide heeft geen boezem en krijgt 2000 m³ per dag van de akker
+        let mut topologie = topologie();
+        topologie.peilgebieden.get_mut("weide").unwrap().boezem = None;
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_gemaal(
+                    "doorvoer".to_string(),
+                    "akker".to_string(),
+                    "weide".to_string(),
+                    2000.0 / 86_400.0,
+                    0.5,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let resultaat = simuleer_droogte(
+            &topologie,
+            &droge_maand(),
+            &JaarbalansInstellingen::default(),
+            &reeks(),
+            &[1.0e9],
+        )
+        .unwrap();
+
+        let levering = |id: &str| {
+            resultaat
+                .peilgebieden
+                .iter()
+                .find(|p| p.peilgebied_id == id)
+                .unwrap()
+                .levering
+        };
+        // De akker vraagt zijn eigen verdamping plus de doorvoer
+        assert!((levering("akker").gevraagd - 30.0 * 7000.0).abs() < 1e-6);
+        assert!((levering("natuur").gevraagd - 30.0 * 5000.0).abs() < 1e-6);
+        assert_eq!(levering("weide").gevraagd, 0.0);
+        let weide = &resultaat.balans.peilgebieden[2];
+        assert!((weide.totaal.verbindingen - 30.0 * 2000.0).abs() < 1e-6);
+        assert_eq!(weide.totaal.aanvoer, 0.0);
+    }
+
+    #[test]
+    fn test_ongeldige_reeks() {
+        let instellingen = JaarbalansInstellingen::default();
+        let weer = droge_maand();
+        let mut reeks = reeks();
+        assert_eq!(
+            simuleer_droogte(&topologie(), &weer, &instellingen, &reeks, &[]),
+            Err(DroogteFout::GeenBeschikbaarheid)
+        );
+        assert_eq!(
+            simuleer_droogte(&topologie(), &weer, &instellingen, &reeks, &[1.0, -1.0]),
+            Err(DroogteFout::OngeldigeBeschikbaarheid { dag: 1 })
+        );
+
+        reeks.rangen[1].peilgebieden.push("natuur".to_string());
+        assert!(matches!(
+            simuleer_droogte(&topologie(), &weer, &instellingen, &reeks, &[1.0]),
+            Err(DroogteFout::DubbeleToewijzing { .. })
+        ));
+        reeks.rangen[1].peilgebieden = vec!["elders".to_string()];
+        assert!(matches!(
+            simuleer_droogte(&topologie(), &weer, &instellingen, &reeks, &[1.0]),
+            Err(DroogteFout::OnbekendPeilgebied { .. })
+        ));
+    }
+}
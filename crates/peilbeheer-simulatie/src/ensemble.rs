@@ -0,0 +1,355 @@
+//! Ensembleruns op meteorologische ensembleverwachtingen.
+//!
+//! Een ensembleverwachting geeft per lid een eigen neerslagreeks. Voor elk
+//! lid wordt vanaf dezelfde beginstaat vooruitgerekend (zie
+//! [`reken_vooruit`]); [`EnsembleResultaat`] bundelt de leden tot per
+//! peilgebied de kans op overschrijding van streefpeil + marge, in totaal
+//! en per uur, en percentielbanden van de waterstand.
+//!
+//! Alle leden wegen even zwaar. De leden worden over de beschikbare kernen
+//! verdeeld.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{NetwerkFout, NetwerkTopologie, PeilgebiedId};
+use crate::onzekerheid::{Percentielband, parallel, percentiel};
+use crate::realtime::{Beginstaat, Verwachting, reken_vooruit};
+use crate::scenario::StrategyType;
+
+/// Eén lid van een ensembleverwachting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ensemblelid {
+    /// Naam of nummer van het lid
+    pub lid: String,
+    /// Neerslag per uur vanaf `t0` per peilgebied in mm
+    #[serde(default)]
+    pub regen_per_uur: HashMap<PeilgebiedId, Vec<f64>>,
+}
+
+/// Uitkomst van één lid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LidUitkomst {
+    pub lid: String,
+    /// Peilgebieden die boven streefpeil + marge komen, op volgorde van id
+    pub overschreden: Vec<PeilgebiedId>,
+}
+
+/// Ensemble-uitkomst van één peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedEnsemble {
+    pub peilgebied_id: PeilgebiedId,
+    /// Fractie van de leden waarin het peil ergens in de horizon boven
+    /// streefpeil + marge komt
+    pub overschrijdingskans: f64,
+    /// Fractie van de leden met het peil boven streefpeil + marge aan het
+    /// eind van elk uur
+    pub kans_per_uur: Vec<f64>,
+    /// Waterstand (m NAP) aan het eind van elk uur per percentiel
+    pub waterstanden: Vec<Percentielband>,
+    /// Hoogste waterstand van een lid per percentiel
+    pub max_waterstanden: Vec<Percentielband>,
+}
+
+/// Gebundelde uitkomst van alle leden.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnsembleResultaat {
+    pub t0: DateTime<Utc>,
+    pub uren: usize,
+    /// Uitkomst per lid, in de volgorde van de invoer
+    pub leden: Vec<LidUitkomst>,
+    /// Uitkomst per peilgebied, op volgorde van id
+    pub peilgebieden: Vec<PeilgebiedEnsemble>,
+}
+
+/// Fout bij een ensemblerun.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnsembleFout {
+    /// Het ensemble heeft geen leden
+    GeenLeden,
+    /// Twee leden hebben dezelfde naam
+    DubbelLid { lid: String },
+    /// Een percentiel ligt niet tussen 0 en 100
+    OngeldigPercentiel { percentiel: f64 },
+    /// De simulatie van een lid is mislukt
+    Netwerk { lid: String, fout: NetwerkFout },
+}
+
+impl fmt::Display for EnsembleFout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GeenLeden => write!(f, "Het ensemble heeft geen leden"),
+            Self::DubbelLid { lid } => write!(f, "Ensemblelid '{}' komt dubbel voor", lid),
+            Self::OngeldigPercentiel { percentiel } => {
+                write!(f, "Percentiel {} ligt niet tussen 0 en 100", percentiel)
+            }
+            Self::Netwerk { lid, fout } => write!(f, "Ensemblelid '{}': {}", lid, fout),
+        }
+    }
+}
+
+impl std::error::Error for EnsembleFout {}
+
+/// Reken vanaf de gemeten waterstanden `uren` uur vooruit voor elk lid en
+/// bundel de leden met de gegeven percentielen (0–100).
+///
+/// Elk lid krijgt een eigen strategy van `strategy_type`, zodat de toestand
+/// van het ene lid het andere niet beïnvloedt.
+pub fn simuleer_ensemble(
+    topologie: &NetwerkTopologie,
+    t0: DateTime<Utc>,
+    waterstanden: &HashMap<PeilgebiedId, f64>,
+    leden: &[Ensemblelid],
+    uren: usize,
+    strategy_type: &StrategyType,
+    percentielen: &[f64],
+) -> Result<EnsembleResultaat, EnsembleFout> {
+    if leden.is_empty() {
+        return Err(EnsembleFout::GeenLeden);
+    }
+    if let Some(&percentiel) = percentielen.iter().find(|p| !(0.0..=100.0).contains(*p)) {
+        return Err(EnsembleFout::OngeldigPercentiel { percentiel });
+    }
+    for (i, lid) in leden.iter().enumerate() {
+        if leden[..i].iter().any(|l| l.lid == lid.lid) {
+            return Err(EnsembleFout::DubbelLid {
+                lid: lid.lid.clone(),
+            });
+        }
+    }
+
+    let verwachtingen = parallel(leden.len(), |i| {
+        let beginstaat = Beginstaat {
+            t0,
+            waterstanden: waterstanden.clone(),
+            regen_per_uur: leden[i].regen_per_uur.clone(),
+        };
+        reken_vooruit(topologie, &beginstaat, uren, strategy_type.strategy().as_ref())
+    })
+    .into_iter()
+    .zip(leden)
+    .map(|(verwachting, lid)| {
+        verwachting.map_err(|fout| EnsembleFout::Netwerk {
+            lid: lid.lid.clone(),
+            fout,
+        })
+    })
+    .collect::<Result<Vec<Verwachting>, EnsembleFout>>()?;
+
+    Ok(bundel(topologie, t0, uren, leden, &verwachtingen, percentielen))
+}
+
+fn bundel(
+    topologie: &NetwerkTopologie,
+    t0: DateTime<Utc>,
+    uren: usize,
+    leden: &[Ensemblelid],
+    verwachtingen: &[Verwachting],
+    percentielen: &[f64],
+) -> EnsembleResultaat {
+    let banden = |waarden: &mut Vec<Vec<f64>>| -> Vec<Percentielband> {
+        waarden.iter_mut().for_each(|w| w.sort_by(f64::total_cmp));
+        percentielen
+            .iter()
+            .map(|&p| Percentielband {
+                percentiel: p,
+                waarden: waarden.iter().map(|w| percentiel(w, p)).collect(),
+            })
+            .collect()
+    };
+    let aantal = verwachtingen.len() as f64;
+
+    // Elke verwachting heeft dezelfde peilgebieden in dezelfde volgorde
+    let mut peilgebieden = Vec::new();
+    for (i, eerste) in verwachtingen[0].peilgebieden.iter().enumerate() {
+        let config = &topologie.peilgebieden[&eerste.peilgebied_id];
+        let grens = config.streefpeil + config.marge;
+        let per_lid: Vec<_> = verwachtingen.iter().map(|v| &v.peilgebieden[i]).collect();
+
+        let mut per_uur: Vec<Vec<f64>> = (0..uren)
+            .map(|uur| per_lid.iter().map(|p| p.waarden[uur].waterstand).collect())
+            .collect();
+        let kans_per_uur = per_uur
+            .iter()
+            .map(|w| w.iter().filter(|&&w| w > grens).count() as f64 / aantal)
+            .collect();
+        let boven = per_lid
+            .iter()
+            .filter(|p| p.overschrijding_vanaf.is_some())
+            .count();
+        let mut max = vec![per_lid.iter().map(|p| p.max_waterstand).collect()];
+
+        peilgebieden.push(PeilgebiedEnsemble {
+            peilgebied_id: eerste.peilgebied_id.clone(),
+            overschrijdingskans: boven as f64 / aantal,
+            kans_per_uur,
+            waterstanden: banden(&mut per_uur),
+            max_waterstanden: banden(&mut max),
+        });
+    }
+
+    let leden = leden
+        .iter()
+        .zip(verwachtingen)
+        .map(|(lid, verwachting)| LidUitkomst {
+            lid: lid.lid.clone(),
+            overschreden: verwachting
+                .peilgebieden
+                .iter()
+                .filter(|p| p.overschrijding_vanaf.is_some())
+                .map(|p| p.peilgebied_id.clone())
+                .collect(),
+        })
+        .collect();
+
+    EnsembleResultaat {
+        t0,
+        uren,
+        leden,
+        peilgebieden,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{PeilgebiedConfig, Verbinding};
+
+    fn topologie() -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        for id in ["noord", "zuid"] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: None,
+                    oppervlakte: 100_000.0,
+                    streefpeil: -0.60,
+                    marge: 0.05,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet: 0.05,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        // Een overstort die bij deze peilen niet overstort
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_overstort(
+                    "overstort".to_string(),
+                    "noord".to_string(),
+                    "zuid".to_string(),
+                    1.0,
+                    0.0,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        topologie
+    }
+
+    fn lid(naam: &str, regen_noord: f64) -> Ensemblelid {
+        Ensemblelid {
+            lid: naam.to_string(),
+            regen_per_uur: HashMap::from([("noord".to_string(), vec![regen_noord; 2])]),
+        }
+    }
+
+    #[test]
+    fn test_overschrijdingskans() {
+        let t0 = "2025-06-01T12:00:00Z".parse().unwrap();
+        let leden = [lid("0", 0.0), lid("1", 30.0), lid("2", 0.0), lid("3", 40.0)];
+        let resultaat = simuleer_ensemble(
+            &topologie(),
+            t0,
+            &HashMap::new(),
+            &leden,
+            4,
+            &StrategyType::Simpel,
+            &[5.0, 50.0, 95.0],
+        )
+        .unwrap();
+        assert_eq!(resultaat.uren, 4);
+        assert_eq!(resultaat.leden.len(), 4);
+        assert_eq!(resultaat.leden[1].overschreden, vec!["noord".to_string()]);
+        assert!(resultaat.leden[0].overschreden.is_empty());
+
+        let noord = &resultaat.peilgebieden[0];
+        assert_eq!(noord.peilgebied_id, "noord");
+        assert_eq!(noord.overschrijdingskans, 0.5);
+        assert_eq!(noord.kans_per_uur.len(), 4);
+        assert_eq!(noord.kans_per_uur[0], 0.0);
+        assert_eq!(noord.kans_per_uur[1], 0.5);
+        assert_eq!(noord.waterstanden.len(), 3);
+        assert_eq!(noord.waterstanden[0].waarden.len(), 4);
+        // De mediaan ligt tussen de droge en de natte leden
+        let mediaan = noord.max_waterstanden[1].waarden[0];
+        assert!(mediaan > noord.max_waterstanden[0].waarden[0]);
+        assert!(mediaan < noord.max_waterstanden[2].waarden[0]);
+
+        // Zonder regen blijft zuid in elk lid op streefpeil
+        let zuid = &resultaat.peilgebieden[1];
+        assert_eq!(zuid.overschrijdingskans, 0.0);
+        assert!(zuid.kans_per_uur.iter().all(|&k| k == 0.0));
+    }
+
+    #[test]
+    fn test_ongeldig_ensemble() {
+        let t0 = Utc::now();
+        let strategy = StrategyType::Simpel;
+        let run = |leden: &[Ensemblelid]| {
+            simuleer_ensemble(
+                &topologie(),
+                t0,
+                &HashMap::new(),
+                leden,
+                2,
+                &strategy,
+                &[50.0],
+            )
+        };
+        assert_eq!(run(&[]), Err(EnsembleFout::GeenLeden));
+        assert!(matches!(
+            run(&[lid("a", 1.0), lid("a", 2.0)]),
+            Err(EnsembleFout::DubbelLid { .. })
+        ));
+        for percentiel in [-5.0, 150.0, f64::NAN] {
+            assert!(matches!(
+                simuleer_ensemble(
+                    &topologie(),
+                    t0,
+                    &HashMap::new(),
+                    &[lid("a", 1.0)],
+                    2,
+                    &strategy,
+                    &[50.0, percentiel]
+                ),
+                Err(EnsembleFout::OngeldigPercentiel { .. })
+            ));
+        }
+
+        let onbekend = HashMap::from([("elders".to_string(), -0.5)]);
+        assert!(matches!(
+            simuleer_ensemble(
+                &topologie(),
+                t0,
+                &onbekend,
+                &[lid("a", 1.0)],
+                2,
+                &strategy,
+                &[50.0]
+            ),
+            Err(EnsembleFout::Netwerk { .. })
+        ));
+    }
+}
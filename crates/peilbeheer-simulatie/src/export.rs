@@ -1,14 +1,27 @@
 //! Export functionaliteit voor simulatieresultaten.
 //!
 //! Deze module biedt functionaliteit voor het exporteren van simulatieresultaten
-//! naar verschillende formaten (CSV, JSON) met flexibele opties.
-
-use std::collections::HashMap;
-use std::path::Path;
-
+//! naar verschillende formaten (CSV, JSON, Parquet, XLSX) met flexibele opties.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::write::GzEncoder;
+use parquet::basic::{Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, DoubleType};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::types::{Type as SchemaType, TypePtr};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
 use serde::Serialize;
 
-use crate::netwerk::{NetwerkSimulatieResultaat, PeilgebiedId};
+use crate::netwerk::{NetwerkSimulatieResultaat, NetwerkTijdstap, PeilgebiedId, VerbindingId};
+use crate::vrachten::{Vracht, VrachtenResultaat};
 
 /// Export opties.
 #[derive(Debug, Clone)]
@@ -21,6 +34,8 @@ pub struct ExportOpties {
     pub csv_scheidingsteken: char,
     /// Include metadata in JSON export
     pub json_metadata: bool,
+    /// Compressie van CSV- en JSON-bestanden
+    pub compressie: Compressie,
 }
 
 impl Default for ExportOpties {
@@ -30,6 +45,58 @@ impl Default for ExportOpties {
             decimalen: 3,
             csv_scheidingsteken: ',',
             json_metadata: false,
+            compressie: Compressie::Geen,
+        }
+    }
+}
+
+impl ExportOpties {
+    /// Pad waarnaar een bestand met deze opties geschreven wordt.
+    ///
+    /// Bij compressie krijgt het pad de extensie van het formaat erbij
+    /// (`uitvoer.csv` wordt `uitvoer.csv.gz`); een bestaande
+    /// compressie-extensie wordt vervangen.
+    pub fn bestandspad<P: AsRef<Path>>(&self, pad: P) -> PathBuf {
+        let pad = pad.as_ref();
+        let zonder = match pad.extension().and_then(|e| e.to_str()) {
+            Some(extensie) if Compressie::ALLE.iter().any(|c| c.extensie() == Some(extensie)) => {
+                pad.with_extension("")
+            }
+            _ => pad.to_path_buf(),
+        };
+        match self.compressie.extensie() {
+            Some(extensie) => {
+                let mut naam = zonder.into_os_string();
+                naam.push(".");
+                naam.push(extensie);
+                PathBuf::from(naam)
+            }
+            None => zonder,
+        }
+    }
+}
+
+/// Compressie van geëxporteerde bestanden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compressie {
+    /// Ongecomprimeerd
+    #[default]
+    Geen,
+    /// gzip (`.gz`)
+    Gzip,
+    /// Zstandard (`.zst`)
+    Zstd,
+}
+
+impl Compressie {
+    const ALLE: [Compressie; 3] = [Self::Geen, Self::Gzip, Self::Zstd];
+
+    /// Bestandsextensie zonder punt, `None` zonder compressie.
+    pub fn extensie(self) -> Option<&'static str> {
+        match self {
+            Self::Geen => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
         }
     }
 }
@@ -43,6 +110,10 @@ pub enum ExportFout {
     OngeldigFormaat { formaat: String },
     /// Geen data om te exporteren
     GeenData,
+    /// Parquet schrijven mislukt
+    Parquet { reden: String },
+    /// XLSX schrijven mislukt
+    Xlsx { reden: String },
 }
 
 impl std::fmt::Display for ExportFout {
@@ -57,12 +128,34 @@ impl std::fmt::Display for ExportFout {
             Self::GeenData => {
                 write!(f, "Geen data om te exporteren")
             }
+            Self::Parquet { reden } => {
+                write!(f, "Parquet schrijven mislukt: {}", reden)
+            }
+            Self::Xlsx { reden } => {
+                write!(f, "XLSX schrijven mislukt: {}", reden)
+            }
         }
     }
 }
 
 impl std::error::Error for ExportFout {}
 
+impl From<ParquetError> for ExportFout {
+    fn from(err: ParquetError) -> Self {
+        Self::Parquet {
+            reden: err.to_string(),
+        }
+    }
+}
+
+impl From<XlsxError> for ExportFout {
+    fn from(err: XlsxError) -> Self {
+        Self::Xlsx {
+            reden: err.to_string(),
+        }
+    }
+}
+
 impl From<std::io::Error> for ExportFout {
     fn from(err: std::io::Error) -> Self {
         Self::SchrijvenMislukt {
@@ -72,6 +165,77 @@ impl From<std::io::Error> for ExportFout {
     }
 }
 
+/// Bestand dat ongecomprimeerd of gecomprimeerd geschreven wordt.
+enum Uitvoer {
+    Geen(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Uitvoer {
+    fn maak(pad: &Path, compressie: Compressie) -> std::io::Result<Self> {
+        let bestand = BufWriter::new(File::create(pad)?);
+        Ok(match compressie {
+            Compressie::Geen => Self::Geen(bestand),
+            Compressie::Gzip => Self::Gzip(GzEncoder::new(bestand, flate2::Compression::default())),
+            Compressie::Zstd => Self::Zstd(zstd::Encoder::new(bestand, 0)?),
+        })
+    }
+
+    /// Sluit de compressie af en schrijf de buffer weg.
+    fn afronden(self) -> std::io::Result<()> {
+        let mut bestand = match self {
+            Self::Geen(bestand) => bestand,
+            Self::Gzip(encoder) => encoder.finish()?,
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        bestand.flush()
+    }
+}
+
+impl Write for Uitvoer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Geen(w) => w.write(buf),
+            Self::Gzip(w) => w.write(buf),
+            Self::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Geen(w) => w.flush(),
+            Self::Gzip(w) => w.flush(),
+            Self::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+/// Schrijf een bestand met de compressie uit de opties, met het pad in
+/// schrijffouten. Geeft het werkelijke pad terug (zie
+/// [`ExportOpties::bestandspad`]).
+fn schrijf_bestand(
+    pad: &Path,
+    opties: &ExportOpties,
+    schrijf: impl FnOnce(&mut Uitvoer) -> Result<(), ExportFout>,
+) -> Result<PathBuf, ExportFout> {
+    let pad = opties.bestandspad(pad);
+    let schrijffout = |reden: String| ExportFout::SchrijvenMislukt {
+        pad: pad.display().to_string(),
+        reden,
+    };
+
+    let mut uitvoer =
+        Uitvoer::maak(&pad, opties.compressie).map_err(|e| schrijffout(e.to_string()))?;
+    schrijf(&mut uitvoer).map_err(|fout| match fout {
+        ExportFout::SchrijvenMislukt { reden, .. } => schrijffout(reden),
+        fout => fout,
+    })?;
+    uitvoer.afronden().map_err(|e| schrijffout(e.to_string()))?;
+
+    Ok(pad)
+}
+
 /// CSV export van tijdstappen.
 pub struct CsvExport {
     opties: ExportOpties,
@@ -95,42 +259,60 @@ impl CsvExport {
         &self,
         resultaat: &NetwerkSimulatieResultaat,
     ) -> Result<String, ExportFout> {
+        let mut output = Vec::new();
+        self.schrijf_naar(resultaat, &mut output)?;
+
+        // Alle velden zijn ids en geformatteerde getallen
+        Ok(String::from_utf8(output).expect("CSV is geldige UTF-8"))
+    }
+
+    /// Schrijf tijdstappen rij voor rij als CSV naar een writer.
+    ///
+    /// Er wordt nooit meer dan één rij in geheugen opgebouwd; geef bij
+    /// bestanden en sockets een gebufferde writer mee.
+    pub fn schrijf_naar<W: Write>(
+        &self,
+        resultaat: &NetwerkSimulatieResultaat,
+        mut writer: W,
+    ) -> Result<(), ExportFout> {
         if resultaat.tijdstappen.is_empty() {
             return Err(ExportFout::GeenData);
         }
 
-        let mut output = String::new();
+        // Kolomvolgorde van de eerste tijdstap, voor elke rij gelijk
+        let peilgebied_ids: Vec<&PeilgebiedId> =
+            resultaat.tijdstappen[0].statussen.keys().collect();
 
         // Header
         if self.opties.csv_header {
-            let header = self.maak_header(resultaat);
-            output.push_str(&header);
-            output.push('\n');
+            writeln!(writer, "{}", self.maak_header(&peilgebied_ids))?;
         }
 
         // Data rijen
-        for rij in self.maak_rijen(resultaat) {
-            output.push_str(&rij);
-            output.push('\n');
+        for stap in &resultaat.tijdstappen {
+            writeln!(writer, "{}", self.maak_rij(stap, &peilgebied_ids))?;
         }
 
-        Ok(output)
+        writer.flush()?;
+        Ok(())
     }
 
     /// Exporteer tijdstappen naar CSV bestand.
+    ///
+    /// Met compressie in de opties krijgt het pad de bijbehorende extensie;
+    /// geeft het geschreven pad terug.
     pub fn naar_bestand<P: AsRef<Path>>(
         &self,
         resultaat: &NetwerkSimulatieResultaat,
         pad: P,
-    ) -> Result<(), ExportFout> {
-        let inhoud = self.als_string(resultaat)?;
-
-        std::fs::write(pad.as_ref(), inhoud).map_err(|e| ExportFout::SchrijvenMislukt {
-            pad: pad.as_ref().display().to_string(),
-            reden: e.to_string(),
-        })?;
+    ) -> Result<PathBuf, ExportFout> {
+        if resultaat.tijdstappen.is_empty() {
+            return Err(ExportFout::GeenData);
+        }
 
-        Ok(())
+        schrijf_bestand(pad.as_ref(), &self.opties, |uitvoer| {
+            self.schrijf_naar(resultaat, uitvoer)
+        })
     }
 
     /// Exporteer per-peilgebied data naar afzonderlijke CSV bestanden.
@@ -157,13 +339,14 @@ impl CsvExport {
         for id in peilgebied_ids {
             let csv = self.per_peilgebied_string(resultaat, &id)?;
 
-            let bestandsnaam = format!("{}.csv", id);
-            let vol_pad = map_pad.join(&bestandsnaam);
-
-            std::fs::write(&vol_pad, &csv).map_err(|e| ExportFout::SchrijvenMislukt {
-                pad: vol_pad.display().to_string(),
-                reden: e.to_string(),
+            let pad = map_pad.join(format!("{}.csv", id));
+            let vol_pad = schrijf_bestand(&pad, &self.opties, |uitvoer| {
+                Ok(uitvoer.write_all(csv.as_bytes())?)
             })?;
+            let bestandsnaam = vol_pad
+                .file_name()
+                .map(|naam| naam.to_string_lossy().into_owned())
+                .unwrap_or_default();
 
             gegenereerd.insert(id, bestandsnaam);
         }
@@ -221,13 +404,48 @@ impl CsvExport {
         Ok(output)
     }
 
-    fn maak_header(&self, resultaat: &NetwerkSimulatieResultaat) -> String {
-        let eerste = &resultaat.tijdstappen[0];
-        let peilgebied_ids: Vec<&PeilgebiedId> = eerste.statussen.keys().collect();
+    /// Exporteer nutriëntenvrachten naar CSV: één rij per verbinding en
+    /// stroomrichting en per peilgebied voor aan- en afvoer.
+    pub fn vrachten_als_string(&self, vrachten: &VrachtenResultaat) -> Result<String, ExportFout> {
+        if vrachten.verbindingen.is_empty() && vrachten.peilgebieden.is_empty() {
+            return Err(ExportFout::GeenData);
+        }
+
+        let s = self.opties.csv_scheidingsteken;
+        let mut output = String::new();
+        if self.opties.csv_header {
+            output.push_str(&format!(
+                "soort{s}id{s}stroom{s}volume_m3{s}stikstof_kg{s}fosfor_kg\n"
+            ));
+        }
+
+        let mut rij = |soort: &str, id: &str, stroom: &str, vracht: &Vracht| {
+            output.push_str(&format!(
+                "{}{s}{}{s}{}{s}{}{s}{}{s}{}\n",
+                soort,
+                id,
+                stroom,
+                format_getal(vracht.volume, self.opties.decimalen),
+                format_getal(vracht.stikstof, self.opties.decimalen),
+                format_getal(vracht.fosfor, self.opties.decimalen),
+            ));
+        };
+        for verbinding in &vrachten.verbindingen {
+            rij("verbinding", &verbinding.verbinding_id, "naar", &verbinding.naar);
+            rij("verbinding", &verbinding.verbinding_id, "terug", &verbinding.terug);
+        }
+        for peilgebied in &vrachten.peilgebieden {
+            rij("peilgebied", &peilgebied.peilgebied_id, "aanvoer", &peilgebied.aanvoer);
+            rij("peilgebied", &peilgebied.peilgebied_id, "afvoer", &peilgebied.afvoer);
+        }
 
+        Ok(output)
+    }
+
+    fn maak_header(&self, peilgebied_ids: &[&PeilgebiedId]) -> String {
         let mut header = String::from("tijd");
 
-        for id in &peilgebied_ids {
+        for id in peilgebied_ids {
             header.push(self.opties.csv_scheidingsteken);
             header.push_str(id);
             header.push_str("_waterstand");
@@ -256,51 +474,36 @@ impl CsvExport {
         header
     }
 
-    fn maak_rijen(&self, resultaat: &NetwerkSimulatieResultaat) -> Vec<String> {
-        resultaat
-            .tijdstappen
-            .iter()
-            .map(|stap| {
-                let mut rij = format!("{:.1}", stap.tijd);
-
-                for (_id, status) in stap.statussen.iter() {
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(&format_getal(
-                        status.waterstand,
-                        self.opties.decimalen,
-                    ));
-
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(&format_getal(
-                        status.inkomend_debiet,
-                        self.opties.decimalen,
-                    ));
-
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(&format_getal(
-                        status.uitgaand_debiet,
-                        self.opties.decimalen,
-                    ));
-
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(&format_getal(
-                        status.uitstroom_debiet,
-                        self.opties.decimalen,
-                    ));
-
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(&format_getal(
-                        status.regen_intensiteit,
-                        self.opties.decimalen,
-                    ));
-
-                    rij.push(self.opties.csv_scheidingsteken);
-                    rij.push_str(if status.pomp_actief { "1" } else { "0" });
-                }
+    fn maak_rij(&self, stap: &NetwerkTijdstap, peilgebied_ids: &[&PeilgebiedId]) -> String {
+        let mut rij = format!("{:.1}", stap.tijd);
 
-                rij
-            })
-            .collect()
+        for id in peilgebied_ids {
+            // Lege velden als het peilgebied in deze stap ontbreekt
+            let Some(status) = stap.statussen.get(*id) else {
+                rij.extend(std::iter::repeat_n(self.opties.csv_scheidingsteken, 6));
+                continue;
+            };
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(&format_getal(status.waterstand, self.opties.decimalen));
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(&format_getal(status.inkomend_debiet, self.opties.decimalen));
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(&format_getal(status.uitgaand_debiet, self.opties.decimalen));
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(&format_getal(status.uitstroom_debiet, self.opties.decimalen));
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(&format_getal(status.regen_intensiteit, self.opties.decimalen));
+
+            rij.push(self.opties.csv_scheidingsteken);
+            rij.push_str(if status.pomp_actief { "1" } else { "0" });
+        }
+
+        rij
     }
 
     fn per_peilgebied_string(
@@ -403,19 +606,29 @@ impl JsonExport {
     }
 
     /// Exporteer tijdstappen naar JSON bestand.
+    ///
+    /// Met compressie in de opties krijgt het pad de bijbehorende extensie;
+    /// geeft het geschreven pad terug.
     pub fn naar_bestand<P: AsRef<Path>>(
         &self,
         resultaat: &NetwerkSimulatieResultaat,
         pad: P,
-    ) -> Result<(), ExportFout> {
-        let inhoud = self.als_pretty_string(resultaat)?;
-
-        std::fs::write(pad.as_ref(), inhoud).map_err(|e| ExportFout::SchrijvenMislukt {
-            pad: pad.as_ref().display().to_string(),
-            reden: e.to_string(),
-        })?;
+    ) -> Result<PathBuf, ExportFout> {
+        if resultaat.tijdstappen.is_empty() {
+            return Err(ExportFout::GeenData);
+        }
 
-        Ok(())
+        schrijf_bestand(pad.as_ref(), &self.opties, |uitvoer| {
+            serde_json::to_writer_pretty(uitvoer, resultaat).map_err(|e| match e.io_error_kind() {
+                Some(_) => ExportFout::SchrijvenMislukt {
+                    pad: String::new(),
+                    reden: e.to_string(),
+                },
+                None => ExportFout::OngeldigFormaat {
+                    formaat: format!("JSON serialisatie fout: {}", e),
+                },
+            })
+        })
     }
 
     /// Exporteer als gestructureerde data per peilgebied.
@@ -469,6 +682,467 @@ impl Default for JsonExport {
     }
 }
 
+/// Parquet export van tijdstappen.
+///
+/// Eén rij per tijdstap met `tijd` en `duur` in minuten, een groep
+/// `peilgebieden` met per peilgebied een groep kolommen en een groep
+/// `verbindingen` met per verbinding een groep kolommen, beide op volgorde
+/// van id. Een waarde die in een tijdstap ontbreekt, zoals de kruinhoogte
+/// van een verbinding die geen stuw is, is leeg.
+pub struct ParquetExport {
+    rijen_per_groep: usize,
+}
+
+/// Kolommen per peilgebied
+const PEILGEBIED_KOLOMMEN: [&str; 8] = [
+    "waterstand",
+    "inkomend_debiet",
+    "uitgaand_debiet",
+    "uitstroom_debiet",
+    "inlaat_debiet",
+    "regen_intensiteit",
+    "pomp_actief",
+    "chloride",
+];
+
+/// Kolommen per verbinding
+const VERBINDING_KOLOMMEN: [&str; 4] = ["debiet", "benutting", "actief", "kruinhoogte"];
+
+/// Waarden van één kolom over alle tijdstappen.
+enum Kolom {
+    Getal(Vec<f64>),
+    OptioneelGetal(Vec<Option<f64>>),
+    Vlag(Vec<Option<bool>>),
+}
+
+impl ParquetExport {
+    /// Maak een nieuwe Parquet export met rijgroepen van 100.000 tijdstappen.
+    pub fn nieuw() -> Self {
+        Self {
+            rijen_per_groep: 100_000,
+        }
+    }
+
+    /// Stel het aantal tijdstappen per rijgroep in.
+    pub fn met_rijen_per_groep(mut self, rijen: usize) -> Self {
+        self.rijen_per_groep = rijen.max(1);
+        self
+    }
+
+    /// Exporteer tijdstappen als Parquet-bestand in het geheugen.
+    pub fn als_bytes(&self, resultaat: &NetwerkSimulatieResultaat) -> Result<Vec<u8>, ExportFout> {
+        if resultaat.tijdstappen.is_empty() {
+            return Err(ExportFout::GeenData);
+        }
+
+        let peilgebied_ids: BTreeSet<&PeilgebiedId> = resultaat
+            .tijdstappen
+            .iter()
+            .flat_map(|stap| stap.statussen.keys())
+            .collect();
+        let verbinding_ids: BTreeSet<&VerbindingId> = resultaat
+            .tijdstappen
+            .iter()
+            .flat_map(|stap| {
+                stap.stromen
+                    .iter()
+                    .map(|s| &s.verbinding_id)
+                    .chain(stap.kruinhoogten.keys())
+            })
+            .collect();
+
+        let schema = schema(&peilgebied_ids, &verbinding_ids)?;
+        let kolommen = kolommen(resultaat, &peilgebied_ids, &verbinding_ids);
+
+        let mut writer = SerializedFileWriter::new(
+            Vec::new(),
+            schema,
+            Arc::new(WriterProperties::builder().build()),
+        )?;
+        let rijen = resultaat.tijdstappen.len();
+        for start in (0..rijen).step_by(self.rijen_per_groep) {
+            let bereik = start..(start + self.rijen_per_groep).min(rijen);
+            let mut groep = writer.next_row_group()?;
+            for kolom in &kolommen {
+                let Some(column) = groep.next_column()? else {
+                    break;
+                };
+                schrijf_kolom(column, kolom, bereik.clone())?;
+            }
+            groep.close()?;
+        }
+        Ok(writer.into_inner()?)
+    }
+
+    /// Exporteer tijdstappen naar een Parquet-bestand.
+    pub fn naar_bestand<P: AsRef<Path>>(
+        &self,
+        resultaat: &NetwerkSimulatieResultaat,
+        pad: P,
+    ) -> Result<(), ExportFout> {
+        let inhoud = self.als_bytes(resultaat)?;
+
+        std::fs::write(pad.as_ref(), inhoud).map_err(|e| ExportFout::SchrijvenMislukt {
+            pad: pad.as_ref().display().to_string(),
+            reden: e.to_string(),
+        })?;
+
+        Ok(())
+    }
+}
+
+impl Default for ParquetExport {
+    fn default() -> Self {
+        Self::nieuw()
+    }
+}
+
+/// Schema met `tijd`, `duur` en de groepen per peilgebied en verbinding.
+fn schema(
+    peilgebied_ids: &BTreeSet<&PeilgebiedId>,
+    verbinding_ids: &BTreeSet<&VerbindingId>,
+) -> Result<TypePtr, ParquetError> {
+    let veld = |naam: &str, repetition: Repetition| -> Result<TypePtr, ParquetError> {
+        let fysiek = match naam {
+            "pomp_actief" | "actief" => PhysicalType::BOOLEAN,
+            _ => PhysicalType::DOUBLE,
+        };
+        Ok(Arc::new(
+            SchemaType::primitive_type_builder(naam, fysiek)
+                .with_repetition(repetition)
+                .build()?,
+        ))
+    };
+    let groep = |naam: &str, velden: Vec<TypePtr>| -> Result<TypePtr, ParquetError> {
+        Ok(Arc::new(
+            SchemaType::group_type_builder(naam)
+                .with_repetition(Repetition::REQUIRED)
+                .with_fields(velden)
+                .build()?,
+        ))
+    };
+    let groepen = |ids: Vec<&String>, kolommen: &[&str]| -> Result<Vec<TypePtr>, ParquetError> {
+        ids.into_iter()
+            .map(|id| {
+                let velden = kolommen
+                    .iter()
+                    .map(|k| veld(k, Repetition::OPTIONAL))
+                    .collect::<Result<_, _>>()?;
+                groep(id, velden)
+            })
+            .collect()
+    };
+
+    let velden = vec![
+        veld("tijd", Repetition::REQUIRED)?,
+        veld("duur", Repetition::REQUIRED)?,
+        groep(
+            "peilgebieden",
+            groepen(peilgebied_ids.iter().copied().collect(), &PEILGEBIED_KOLOMMEN)?,
+        )?,
+        groep(
+            "verbindingen",
+            groepen(verbinding_ids.iter().copied().collect(), &VERBINDING_KOLOMMEN)?,
+        )?,
+    ];
+    Ok(Arc::new(
+        SchemaType::group_type_builder("netwerk_simulatie")
+            .with_fields(velden)
+            .build()?,
+    ))
+}
+
+/// Kolommen in de volgorde van het schema.
+fn kolommen(
+    resultaat: &NetwerkSimulatieResultaat,
+    peilgebied_ids: &BTreeSet<&PeilgebiedId>,
+    verbinding_ids: &BTreeSet<&VerbindingId>,
+) -> Vec<Kolom> {
+    let stappen = &resultaat.tijdstappen;
+    let mut kolommen = vec![
+        Kolom::Getal(stappen.iter().map(|s| s.tijd).collect()),
+        Kolom::Getal(stappen.iter().map(|s| s.duur).collect()),
+    ];
+
+    for id in peilgebied_ids {
+        let status = |kolom: fn(&crate::netwerk::PeilgebiedStatus) -> f64| {
+            Kolom::OptioneelGetal(
+                stappen
+                    .iter()
+                    .map(|s| s.statussen.get(*id).map(kolom))
+                    .collect(),
+            )
+        };
+        kolommen.extend([
+            status(|s| s.waterstand),
+            status(|s| s.inkomend_debiet),
+            status(|s| s.uitgaand_debiet),
+            status(|s| s.uitstroom_debiet),
+            status(|s| s.inlaat_debiet),
+            status(|s| s.regen_intensiteit),
+            Kolom::Vlag(
+                stappen
+                    .iter()
+                    .map(|s| s.statussen.get(*id).map(|s| s.pomp_actief))
+                    .collect(),
+            ),
+            Kolom::OptioneelGetal(stappen.iter().map(|s| s.chloride.get(*id).copied()).collect()),
+        ]);
+    }
+
+    for id in verbinding_ids {
+        let stromen: Vec<_> = stappen
+            .iter()
+            .map(|s| s.stromen.iter().find(|stroom| &stroom.verbinding_id == *id))
+            .collect();
+        kolommen.extend([
+            Kolom::OptioneelGetal(stromen.iter().map(|s| s.map(|s| s.debiet)).collect()),
+            Kolom::OptioneelGetal(stromen.iter().map(|s| s.map(|s| s.benutting)).collect()),
+            Kolom::Vlag(stromen.iter().map(|s| s.map(|s| s.actief)).collect()),
+            Kolom::OptioneelGetal(
+                stappen
+                    .iter()
+                    .map(|s| s.kruinhoogten.get(*id).copied())
+                    .collect(),
+            ),
+        ]);
+    }
+
+    kolommen
+}
+
+/// Schrijf de rijen `bereik` van een kolom; lege waarden krijgen
+/// definitieniveau 0.
+fn schrijf_kolom(
+    mut column: SerializedColumnWriter<'_>,
+    kolom: &Kolom,
+    bereik: Range<usize>,
+) -> Result<(), ParquetError> {
+    fn niveaus<T>(waarden: &[Option<T>]) -> Vec<i16> {
+        waarden.iter().map(|w| i16::from(w.is_some())).collect()
+    }
+
+    match kolom {
+        Kolom::Getal(waarden) => {
+            column
+                .typed::<DoubleType>()
+                .write_batch(&waarden[bereik], None, None)?;
+        }
+        Kolom::OptioneelGetal(waarden) => {
+            let waarden = &waarden[bereik];
+            let gevuld: Vec<f64> = waarden.iter().flatten().copied().collect();
+            column
+                .typed::<DoubleType>()
+                .write_batch(&gevuld, Some(&niveaus(waarden)), None)?;
+        }
+        Kolom::Vlag(waarden) => {
+            let waarden = &waarden[bereik];
+            let gevuld: Vec<bool> = waarden.iter().flatten().copied().collect();
+            column
+                .typed::<BoolType>()
+                .write_batch(&gevuld, Some(&niveaus(waarden)), None)?;
+        }
+    }
+    column.close()
+}
+
+/// XLSX export voor rapportages.
+///
+/// De werkmap heeft een blad `Statistieken`, een blad `Verbindingen` met de
+/// verbindingstromen en een blad per peilgebied met dezelfde kolommen als
+/// de CSV per peilgebied. Getallen krijgen `decimalen` decimalen uit de
+/// opties; de tijd één.
+pub struct XlsxExport {
+    opties: ExportOpties,
+}
+
+impl XlsxExport {
+    /// Maak een nieuwe XLSX export met standaard opties.
+    pub fn nieuw() -> Self {
+        Self {
+            opties: ExportOpties::default(),
+        }
+    }
+
+    /// Maak een nieuwe XLSX export met custom opties.
+    pub fn met_opties(opties: ExportOpties) -> Self {
+        Self { opties }
+    }
+
+    /// Exporteer als XLSX-werkmap in het geheugen.
+    pub fn als_bytes(&self, resultaat: &NetwerkSimulatieResultaat) -> Result<Vec<u8>, ExportFout> {
+        let statistieken = bereken_statistieken(resultaat)?;
+
+        let kop = Format::new().set_bold();
+        let getal = Format::new().set_num_format(if self.opties.decimalen == 0 {
+            "0".to_string()
+        } else {
+            format!("0.{}", "0".repeat(self.opties.decimalen))
+        });
+        let tijd = Format::new().set_num_format("0.0");
+
+        let mut werkmap = Workbook::new();
+        let mut namen = HashSet::from(["statistieken".to_string(), "verbindingen".to_string()]);
+
+        let blad = werkmap.add_worksheet().set_name("Statistieken")?;
+        schrijf_kop(
+            blad,
+            &[
+                "peilgebied",
+                "min_waterstand",
+                "max_waterstand",
+                "gem_waterstand",
+                "totale_uitstroom",
+                "pomp_uren",
+                "gem_regen",
+            ],
+            &kop,
+        )?;
+        let mut peilgebied_ids: Vec<&PeilgebiedId> = statistieken.peilgebieden.keys().collect();
+        peilgebied_ids.sort();
+        for (rij, id) in peilgebied_ids.iter().enumerate() {
+            let stat = &statistieken.peilgebieden[*id];
+            let rij = rij as u32 + 1;
+            blad.write_string(rij, 0, id.as_str())?;
+            for (kolom, waarde) in [
+                stat.min_waterstand,
+                stat.max_waterstand,
+                stat.gem_waterstand,
+                stat.totale_uitstroom,
+                stat.pomp_uren,
+                stat.gem_regen,
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                blad.write_number_with_format(rij, kolom as u16 + 1, waarde, &getal)?;
+            }
+        }
+        blad.autofit();
+
+        let blad = werkmap.add_worksheet().set_name("Verbindingen")?;
+        schrijf_kop(
+            blad,
+            &["tijd", "verbinding_id", "richting", "debiet", "benutting", "actief"],
+            &kop,
+        )?;
+        let mut rij = 1;
+        for stap in &resultaat.tijdstappen {
+            for stroom in &stap.stromen {
+                let richting = match stroom.richting {
+                    crate::netwerk::StroomRichting::Naar => "naar",
+                    crate::netwerk::StroomRichting::Terug => "terug",
+                };
+                blad.write_number_with_format(rij, 0, stap.tijd, &tijd)?;
+                blad.write_string(rij, 1, stroom.verbinding_id.as_str())?;
+                blad.write_string(rij, 2, richting)?;
+                blad.write_number_with_format(rij, 3, stroom.debiet, &getal)?;
+                blad.write_number_with_format(rij, 4, stroom.benutting, &getal)?;
+                blad.write_boolean(rij, 5, stroom.actief)?;
+                rij += 1;
+            }
+        }
+
+        for id in peilgebied_ids {
+            let blad = werkmap
+                .add_worksheet()
+                .set_name(bladnaam(id, &mut namen))?;
+            schrijf_kop(
+                blad,
+                &[
+                    "tijd",
+                    "waterstand",
+                    "inkomend_debiet",
+                    "uitgaand_debiet",
+                    "uitstroom_debiet",
+                    "regen_intensiteit",
+                    "pomp_actief",
+                ],
+                &kop,
+            )?;
+            let statussen = resultaat
+                .tijdstappen
+                .iter()
+                .filter_map(|stap| stap.statussen.get(id).map(|status| (stap.tijd, status)));
+            for (rij, (t, status)) in statussen.enumerate() {
+                let rij = rij as u32 + 1;
+                blad.write_number_with_format(rij, 0, t, &tijd)?;
+                for (kolom, waarde) in [
+                    status.waterstand,
+                    status.inkomend_debiet,
+                    status.uitgaand_debiet,
+                    status.uitstroom_debiet,
+                    status.regen_intensiteit,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    blad.write_number_with_format(rij, kolom as u16 + 1, waarde, &getal)?;
+                }
+                blad.write_boolean(rij, 6, status.pomp_actief)?;
+            }
+        }
+
+        Ok(werkmap.save_to_buffer()?)
+    }
+
+    /// Exporteer naar een XLSX-bestand.
+    pub fn naar_bestand<P: AsRef<Path>>(
+        &self,
+        resultaat: &NetwerkSimulatieResultaat,
+        pad: P,
+    ) -> Result<(), ExportFout> {
+        let inhoud = self.als_bytes(resultaat)?;
+
+        std::fs::write(pad.as_ref(), inhoud).map_err(|e| ExportFout::SchrijvenMislukt {
+            pad: pad.as_ref().display().to_string(),
+            reden: e.to_string(),
+        })?;
+
+        Ok(())
+    }
+}
+
+impl Default for XlsxExport {
+    fn default() -> Self {
+        Self::nieuw()
+    }
+}
+
+/// Vetgedrukte kopregel met bevroren eerste rij.
+fn schrijf_kop(blad: &mut Worksheet, kolommen: &[&str], kop: &Format) -> Result<(), XlsxError> {
+    for (kolom, naam) in kolommen.iter().enumerate() {
+        blad.write_string_with_format(0, kolom as u16, *naam, kop)?;
+    }
+    blad.set_freeze_panes(1, 0)?;
+    Ok(())
+}
+
+/// Geldige, unieke bladnaam voor een peilgebied: Excel staat geen
+/// `[]:*?/\` toe, maximaal 31 tekens en geen namen die alleen in
+/// hoofdletters verschillen.
+fn bladnaam(id: &str, gebruikt: &mut HashSet<String>) -> String {
+    let basis: String = id
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .collect();
+    // Een bladnaam mag niet met een apostrof beginnen of eindigen
+    let basis = match basis.trim_matches('\'') {
+        "" => "peilgebied",
+        basis => basis,
+    };
+
+    let mut naam: String = basis.chars().take(31).collect();
+    let mut volgnummer = 2;
+    while !gebruikt.insert(naam.to_lowercase()) {
+        let achtervoegsel = format!("~{}", volgnummer);
+        naam = basis.chars().take(31 - achtervoegsel.len()).collect();
+        naam.push_str(&achtervoegsel);
+        volgnummer += 1;
+    }
+    naam
+}
+
 /// Geëxporteerde data voor één peilgebied.
 #[derive(Debug, Clone, Serialize)]
 pub struct PeilgebiedExportData {
@@ -539,7 +1213,7 @@ pub fn bereken_statistieken(
             *uitstroom_totalen.entry(id.clone()).or_insert(0.0) += status.uitstroom_debiet;
 
             if status.pomp_actief {
-                *pomp_minuten.entry(id.clone()).or_insert(0.0) += 1.0;
+                *pomp_minuten.entry(id.clone()).or_insert(0.0) += stap.duur;
             }
 
             *regen_totalen
@@ -589,6 +1263,13 @@ pub fn statistieken_als_json(
     })
 }
 
+/// Exporteer nutriëntenvrachten als JSON.
+pub fn vrachten_als_json(vrachten: &VrachtenResultaat) -> Result<String, ExportFout> {
+    serde_json::to_string_pretty(vrachten).map_err(|e| ExportFout::OngeldigFormaat {
+        formaat: format!("JSON serialisatie fout: {}", e),
+    })
+}
+
 /// Helper functie om getallen te formatteren met fixed decimalen.
 fn format_getal(waarde: f64, decimalen: usize) -> String {
     format!("{:.1$}", waarde, decimalen)
@@ -610,6 +1291,7 @@ mod tests {
                     inkomend_debiet: 0.1,
                     uitgaand_debiet: 0.05,
                     uitstroom_debiet: 0.2,
+                    inlaat_debiet: 0.0,
                     regen_intensiteit: 5.0,
                     pomp_actief: true,
                 },
@@ -622,6 +1304,7 @@ mod tests {
                     inkomend_debiet: 0.05,
                     uitgaand_debiet: 0.0,
                     uitstroom_debiet: 0.1,
+                    inlaat_debiet: 0.0,
                     regen_intensiteit: 5.0,
                     pomp_actief: true,
                 },
@@ -642,13 +1325,21 @@ mod tests {
 ![
             NetwerkTijdstap {
                 tijd: 1.0,
+                duur: 1.0,
                 statussen: statussen.clone(),
                 stromen: stromen.clone(),
+                boezembelasting: HashMap::new(),
+                kruinhoogten: HashMap::new(),
+                chloride: HashMap::new(),
             },
             NetwerkTijdstap {
                 tijd: 2.0,
+                duur: 1.0,
                 statussen,
                 stromen,
+                boezembelasting: HashMap::new(),
+                kruinhoogten: HashMap::new(),
+                chloride: HashMap::new(),
             },
         ];
 
@@ -707,6 +1398,89 @@ mod tests {
         assert!(matches!(result, Err(ExportFout::GeenData)));
     }
 
+    #[test]
+    fn test_csv_schrijf_naar() {
+        let mut resultaat = maak_test_resultaat();
+        // Een tweede tijdstap met een eigen map, en dus een eigen volgorde
+        let statussen = resultaat.tijdstappen[1].statussen.drain().collect();
+        resultaat.tijdstappen[1].statussen = statussen;
+
+        let export = CsvExport::nieuw();
+        let mut gestreamd = Vec::new();
+        export.schrijf_naar(&resultaat, &mut gestreamd).unwrap();
+        let csv = String::from_utf8(gestreamd).unwrap();
+        assert_eq!(csv, export.als_string(&resultaat).unwrap());
+
+        // Elke rij volgt de kolomvolgorde van de header
+        let regels: Vec<Vec<&str>> = csv.lines().map(|r| r.split(',').collect()).collect();
+        let kolom = regels[0]
+            .iter()
+            .position(|k| *k == "polder_b_waterstand")
+            .unwrap();
+        assert_eq!(regels[1][kolom], "-0.550");
+        assert_eq!(regels[2][kolom], "-0.550");
+
+        let pad =
+            std::env::temp_dir().join(format!("peilbeheer_export_{}.csv", std::process::id()));
+        export.naar_bestand(&resultaat, &pad).unwrap();
+        let inhoud = std::fs::read_to_string(&pad).unwrap();
+        std::fs::remove_file(&pad).unwrap();
+        assert_eq!(inhoud, csv);
+    }
+
+    #[test]
+    fn test_bestandspad() {
+        let opties = |compressie| ExportOpties {
+            compressie,
+            ..Default::default()
+        };
+        let geen = opties(Compressie::Geen);
+        let gzip = opties(Compressie::Gzip);
+        let zstd = opties(Compressie::Zstd);
+
+        assert_eq!(geen.bestandspad("uit.csv"), PathBuf::from("uit.csv"));
+        assert_eq!(geen.bestandspad("uit.csv.gz"), PathBuf::from("uit.csv"));
+        assert_eq!(gzip.bestandspad("map/uit.csv"), PathBuf::from("map/uit.csv.gz"));
+        assert_eq!(gzip.bestandspad("uit.csv.gz"), PathBuf::from("uit.csv.gz"));
+        assert_eq!(zstd.bestandspad("uit.json.gz"), PathBuf::from("uit.json.zst"));
+        assert_eq!(zstd.bestandspad("uit"), PathBuf::from("uit.zst"));
+    }
+
+    #[test]
+    fn test_gecomprimeerde_bestanden() {
+        use std::io::Read;
+
+        let resultaat = maak_test_resultaat();
+        let basis = std::env::temp_dir().join(format!("peilbeheer_export_{}", std::process::id()));
+
+        let gzip = ExportOpties {
+            compressie: Compressie::Gzip,
+            ..Default::default()
+        };
+        let export = CsvExport::met_opties(gzip);
+        let pad = export.naar_bestand(&resultaat, basis.with_extension("csv")).unwrap();
+        assert_eq!(pad, basis.with_extension("csv.gz"));
+        let mut csv = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(&pad).unwrap())
+            .read_to_string(&mut csv)
+            .unwrap();
+        std::fs::remove_file(&pad).unwrap();
+        assert_eq!(csv, export.als_string(&resultaat).unwrap());
+
+        let zstd = ExportOpties {
+            compressie: Compressie::Zstd,
+            ..Default::default()
+        };
+        let pad = JsonExport::met_opties(zstd)
+            .naar_bestand(&resultaat, basis.with_extension("json"))
+            .unwrap();
+        assert_eq!(pad, basis.with_extension("json.zst"));
+        let json = zstd::decode_all(std::fs::File::open(&pad).unwrap()).unwrap();
+        std::fs::remove_file(&pad).unwrap();
+        let gelezen: NetwerkSimulatieResultaat = serde_json::from_slice(&json).unwrap();
+        assert_eq!(gelezen.tijdstappen.len(), 2);
+    }
+
     #[test]
     fn test_csv_export_verbindingen() {
         let resultaat = maak_test_resultaat();
@@ -815,6 +1589,43 @@ mod tests {
         assert!(matches!(result, Err(ExportFout::GeenData)));
     }
 
+    #[test]
+    fn test_csv_export_vrachten() {
+        use crate::vrachten::{PeilgebiedVracht, VerbindingVracht};
+
+        let vracht = Vracht {
+            volume: 1440.0,
+            stikstof: 5.76,
+            fosfor: 0.432,
+        };
+        let vrachten = VrachtenResultaat {
+            verbindingen: vec![VerbindingVracht {
+                verbinding_id: "gemaal".to_string(),
+                naar: vracht,
+                terug: Vracht::default(),
+            }],
+            peilgebieden: vec![PeilgebiedVracht {
+                peilgebied_id: "polder".to_string(),
+                aanvoer: Vracht::default(),
+                afvoer: vracht,
+            }],
+        };
+
+        let csv = CsvExport::nieuw().vrachten_als_string(&vrachten).unwrap();
+        let regels: Vec<&str> = csv.lines().collect();
+        assert_eq!(regels.len(), 5);
+        assert_eq!(regels[0], "soort,id,stroom,volume_m3,stikstof_kg,fosfor_kg");
+        assert_eq!(regels[1], "verbinding,gemaal,naar,1440.000,5.760,0.432");
+        assert_eq!(regels[4], "peilgebied,polder,afvoer,1440.000,5.760,0.432");
+
+        let json = vrachten_als_json(&vrachten).unwrap();
+        assert!(json.contains("\"stikstof\": 5.76"));
+        assert!(matches!(
+            CsvExport::nieuw().vrachten_als_string(&VrachtenResultaat::default()),
+            Err(ExportFout::GeenData)
+        ));
+    }
+
     #[test]
     fn test_format_getal() {
         assert_eq!(format_getal(1.23456, 2), "1.23");
@@ -869,6 +1680,134 @@ mod tests {
         assert!(csv.contains("-0.500"));
     }
 
+    #[test]
+    fn test_parquet_round_trip() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::{Field, RowAccessor};
+
+        let mut resultaat = maak_test_resultaat();
+        resultaat.tijdstappen[1]
+            .kruinhoogten
+            .insert("v1".to_string(), -0.4);
+        let pad = std::env::temp_dir().join(format!(
+            "peilbeheer_export_{}.parquet",
+            std::process::id()
+        ));
+        ParquetExport::nieuw()
+            .met_rijen_per_groep(1)
+            .naar_bestand(&resultaat, &pad)
+            .unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&pad).unwrap()).unwrap();
+        std::fs::remove_file(&pad).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        assert_eq!(metadata.num_row_groups(), 2);
+        // 2 + 2 peilgebieden × 8 + 1 verbinding × 4 kolommen
+        assert_eq!(metadata.file_metadata().schema_descr().num_columns(), 22);
+        assert_eq!(
+            metadata.file_metadata().schema_descr().column(2).path().string(),
+            "peilgebieden.polder_a.waterstand"
+        );
+
+        let rijen: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rijen.len(), 2);
+        assert_eq!(rijen[1].get_double(0).unwrap(), 2.0);
+        assert_eq!(rijen[1].get_double(1).unwrap(), 1.0);
+
+        let polder_b = rijen[0].get_group(2).unwrap().get_group(1).unwrap();
+        assert_eq!(polder_b.get_double(0).unwrap(), -0.55);
+        assert!(polder_b.get_bool(6).unwrap());
+        // Zonder chloride is de kolom leeg
+        assert!(matches!(polder_b.get_column_iter().nth(7), Some((_, Field::Null))));
+
+        let v1 = rijen[1].get_group(3).unwrap().get_group(0).unwrap();
+        assert_eq!(v1.get_double(0).unwrap(), 0.05);
+        assert!(v1.get_bool(2).unwrap());
+        assert_eq!(v1.get_double(3).unwrap(), -0.4);
+        let v1 = rijen[0].get_group(3).unwrap().get_group(0).unwrap();
+        assert!(matches!(v1.get_column_iter().nth(3), Some((_, Field::Null))));
+    }
+
+    #[test]
+    fn test_parquet_zonder_data() {
+        let resultaat = NetwerkSimulatieResultaat {
+            tijdstappen: vec![],
+            totale_kosten: None,
+        };
+        assert_eq!(
+            ParquetExport::nieuw().als_bytes(&resultaat),
+            Err(ExportFout::GeenData)
+        );
+    }
+
+    #[test]
+    fn test_xlsx_werkbladen() {
+        use calamine::{Data, Reader, Xlsx, open_workbook_from_rs};
+        use std::io::{Cursor, Read};
+
+        let opties = ExportOpties {
+            decimalen: 2,
+            ..Default::default()
+        };
+        let bytes = XlsxExport::met_opties(opties)
+            .als_bytes(&maak_test_resultaat())
+            .unwrap();
+
+        let mut werkmap: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(
+            werkmap.sheet_names(),
+            vec!["Statistieken", "Verbindingen", "polder_a", "polder_b"]
+        );
+
+        let statistieken = werkmap.worksheet_range("Statistieken").unwrap();
+        assert_eq!(
+            statistieken.get_value((0, 1)),
+            Some(&Data::String("min_waterstand".to_string()))
+        );
+        assert_eq!(
+            statistieken.get_value((1, 0)),
+            Some(&Data::String("polder_a".to_string()))
+        );
+        assert_eq!(statistieken.get_value((1, 3)), Some(&Data::Float(-0.5)));
+
+        let verbindingen = werkmap.worksheet_range("Verbindingen").unwrap();
+        assert_eq!(verbindingen.height(), 3);
+        assert_eq!(verbindingen.get_value((2, 0)), Some(&Data::Float(2.0)));
+        assert_eq!(verbindingen.get_value((2, 3)), Some(&Data::Float(0.05)));
+        assert_eq!(verbindingen.get_value((2, 5)), Some(&Data::Bool(true)));
+
+        let polder_b = werkmap.worksheet_range("polder_b").unwrap();
+        assert_eq!(polder_b.height(), 3);
+        assert_eq!(polder_b.get_value((1, 1)), Some(&Data::Float(-0.55)));
+        assert_eq!(polder_b.get_value((1, 6)), Some(&Data::Bool(true)));
+
+        // Nummerformaat volgt de decimalen uit de opties
+        let mut archief = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut stijlen = String::new();
+        archief
+            .by_name("xl/styles.xml")
+            .unwrap()
+            .read_to_string(&mut stijlen)
+            .unwrap();
+        assert!(stijlen.contains(r#"formatCode="0.00""#));
+    }
+
+    #[test]
+    fn test_bladnaam() {
+        let mut gebruikt = HashSet::from(["statistieken".to_string()]);
+        assert_eq!(bladnaam("NL/pg:3", &mut gebruikt), "NL_pg_3");
+        assert_eq!(bladnaam("Statistieken", &mut gebruikt), "Statistieken~2");
+        assert_eq!(bladnaam("nl_PG_3", &mut gebruikt), "nl_PG_3~2");
+        let lang = "a".repeat(40);
+        assert_eq!(bladnaam(&lang, &mut gebruikt).len(), 31);
+        assert_eq!(bladnaam(&lang, &mut gebruikt), format!("{}~2", "a".repeat(29)));
+    }
+
     #[test]
     fn test_peilgebied_export_data_serialize() {
         let data = PeilgebiedExportData {
@@ -0,0 +1,441 @@
+//! Gevoeligheidsanalyse van een scenario met een parameter sweep.
+//!
+//! Een sweep varieert één of twee parameters over een reeks waarden en
+//! simuleert het scenario voor elke combinatie; bij twee parameters ontstaat
+//! een matrix. Een tornado varieert elke parameter afzonderlijk tussen zijn
+//! laagste en hoogste waarde, met de andere op hun basiswaarde, en sorteert
+//! de parameters op de invloed op de peilstijging.
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{NetwerkSimulatieResultaat, PeilgebiedId};
+use crate::onzekerheid::parallel;
+use crate::scenario::{Scenario, ScenarioFout};
+
+/// Grootste aantal simulaties van één sweep.
+pub const MAX_SWEEP_RUNS: usize = 400;
+
+/// Parameter die in een sweep varieert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepGrootheid {
+    /// Factor op de gemaalcapaciteit naar buiten
+    Gemaalcapaciteit,
+    /// Factor op de neerslag
+    Neerslag,
+    /// Extra kwel in mm/dag
+    Kwel,
+    /// Verschuiving van het streefpeil in m
+    Streefpeil,
+    /// Factor op het oppervlak
+    Oppervlakte,
+}
+
+impl SweepGrootheid {
+    /// Waarde waarbij het scenario ongewijzigd blijft.
+    pub fn basiswaarde(&self) -> f64 {
+        match self {
+            Self::Gemaalcapaciteit | Self::Neerslag | Self::Oppervlakte => 1.0,
+            Self::Kwel | Self::Streefpeil => 0.0,
+        }
+    }
+}
+
+/// Een parameter met de waarden die de sweep doorloopt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepParameter {
+    pub grootheid: SweepGrootheid,
+    /// Alleen dit peilgebied; zonder peilgebied het hele netwerk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peilgebied: Option<PeilgebiedId>,
+    pub waarden: Vec<f64>,
+}
+
+impl SweepParameter {
+    /// `stappen` gelijk verdeelde waarden van `van` tot en met `tot`.
+    pub fn bereik(grootheid: SweepGrootheid, van: f64, tot: f64, stappen: usize) -> Self {
+        let waarden = match stappen {
+            0 => Vec::new(),
+            1 => vec![van],
+            n => (0..n)
+                .map(|i| van + (tot - van) * i as f64 / (n - 1) as f64)
+                .collect(),
+        };
+        Self {
+            grootheid,
+            peilgebied: None,
+            waarden,
+        }
+    }
+
+    pub fn voor_peilgebied(mut self, peilgebied: PeilgebiedId) -> Self {
+        self.peilgebied = Some(peilgebied);
+        self
+    }
+
+    fn valideer(&self, scenario: &Scenario) -> Result<(), ScenarioFout> {
+        let fout = |details: String| Err(ScenarioFout::OngeldigFormaat { details });
+        if self.waarden.is_empty() {
+            return fout(format!("Geen waarden voor {:?}", self.grootheid));
+        }
+        if self.waarden.iter().any(|w| !w.is_finite()) {
+            return fout(format!("Ongeldige waarde voor {:?}", self.grootheid));
+        }
+        let factor = self.grootheid.basiswaarde() == 1.0;
+        if factor && self.waarden.iter().any(|&w| w < 0.0) {
+            return fout(format!(
+                "Factor voor {:?} mag niet negatief zijn",
+                self.grootheid
+            ));
+        }
+        if let Some(id) = &self.peilgebied
+            && !scenario.topologie.peilgebieden.contains_key(id)
+        {
+            return fout(format!("Onbekend peilgebied in sweep: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Pas de waarde toe op (de peilgebieden van) het scenario.
+    fn pas_toe(&self, waarde: f64, scenario: &mut Scenario) {
+        let geldt = |id: &PeilgebiedId| self.peilgebied.as_ref().is_none_or(|p| p == id);
+        if self.grootheid == SweepGrootheid::Neerslag {
+            for (id, reeks) in scenario.regen_scenario.regen_per_uur.iter_mut() {
+                if geldt(id) {
+                    reeks.iter_mut().for_each(|r| *r *= waarde);
+                }
+            }
+            return;
+        }
+        for (id, config) in scenario.topologie.peilgebieden.iter_mut() {
+            if !geldt(id) {
+                continue;
+            }
+            match self.grootheid {
+                SweepGrootheid::Gemaalcapaciteit => config.max_uitstroom_debiet *= waarde,
+                // Kwel is negatieve infiltratie
+                SweepGrootheid::Kwel => config.infiltratie -= waarde / 24.0,
+                SweepGrootheid::Streefpeil => config.streefpeil += waarde,
+                SweepGrootheid::Oppervlakte => config.oppervlakte *= waarde,
+                SweepGrootheid::Neerslag => {}
+            }
+        }
+    }
+}
+
+/// Uitkomstmaten van één simulatie.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SweepUitkomst {
+    /// Grootste stijging boven streefpeil over alle peilgebieden (m)
+    pub max_peilstijging: f64,
+    /// Uren waarin een peilgebied boven streefpeil + marge staat
+    pub overschrijding_uren: f64,
+    /// Naar buiten afgevoerd volume (m³)
+    pub afgevoerd_volume: f64,
+}
+
+impl SweepUitkomst {
+    fn uit(scenario: &Scenario, resultaat: &NetwerkSimulatieResultaat) -> Self {
+        let peilgebieden = &scenario.topologie.peilgebieden;
+        let mut uitkomst = Self {
+            max_peilstijging: f64::NEG_INFINITY,
+            overschrijding_uren: 0.0,
+            afgevoerd_volume: 0.0,
+        };
+        for stap in &resultaat.tijdstappen {
+            let mut overschreden = false;
+            for status in stap.statussen.values() {
+                let Some(config) = peilgebieden.get(&status.id) else {
+                    continue;
+                };
+                let stijging = status.waterstand - config.streefpeil;
+                uitkomst.max_peilstijging = uitkomst.max_peilstijging.max(stijging);
+                overschreden |= stijging > config.marge;
+                uitkomst.afgevoerd_volume += status.uitstroom_debiet * stap.duur * 60.0;
+            }
+            if overschreden {
+                uitkomst.overschrijding_uren += stap.duur / 60.0;
+            }
+        }
+        uitkomst
+    }
+}
+
+/// Uitkomsten van een sweep.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepResultaat {
+    pub parameters: Vec<SweepParameter>,
+    /// Uitkomst per waarde van de eerste parameter (rij) en van de tweede
+    /// parameter (kolom); met één parameter heeft elke rij één kolom
+    pub uitkomsten: Vec<Vec<SweepUitkomst>>,
+}
+
+/// Invloed van één parameter in een tornado.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TornadoBalk {
+    pub grootheid: SweepGrootheid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peilgebied: Option<PeilgebiedId>,
+    pub laag: f64,
+    pub hoog: f64,
+    pub uitkomst_laag: SweepUitkomst,
+    pub uitkomst_hoog: SweepUitkomst,
+    /// Verschil in maximale peilstijging tussen hoog en laag (m)
+    pub uitslag: f64,
+}
+
+/// Tornado-resultaat: de basis en de balken, grootste uitslag eerst.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TornadoResultaat {
+    pub basis: SweepUitkomst,
+    pub balken: Vec<TornadoBalk>,
+}
+
+/// Simuleer het scenario met de waarden toegepast.
+fn simuleer_met(
+    scenario: &Scenario,
+    parameters: &[(&SweepParameter, f64)],
+) -> Result<SweepUitkomst, ScenarioFout> {
+    let mut variant = scenario.clone();
+    for (parameter, waarde) in parameters {
+        parameter.pas_toe(*waarde, &mut variant);
+    }
+    let resultaat = variant.simuleer()?;
+    Ok(SweepUitkomst::uit(&variant, &resultaat))
+}
+
+/// Simuleer het scenario voor elke combinatie van de waarden van één of
+/// twee parameters.
+pub fn sweep(
+    scenario: &Scenario,
+    parameters: &[SweepParameter],
+) -> Result<SweepResultaat, ScenarioFout> {
+    if !(1..=2).contains(&parameters.len()) {
+        return Err(ScenarioFout::OngeldigFormaat {
+            details: "Een sweep varieert één of twee parameters".to_string(),
+        });
+    }
+    for parameter in parameters {
+        parameter.valideer(scenario)?;
+    }
+    let rijen = parameters[0].waarden.len();
+    let kolommen = parameters.get(1).map_or(1, |p| p.waarden.len());
+    if rijen * kolommen > MAX_SWEEP_RUNS {
+        return Err(ScenarioFout::OngeldigFormaat {
+            details: format!(
+                "Sweep van {} simulaties is groter dan {}",
+                rijen * kolommen,
+                MAX_SWEEP_RUNS
+            ),
+        });
+    }
+    scenario.valideer()?;
+
+    let uitkomsten = parallel(rijen * kolommen, |i| {
+        let mut waarden = vec![(&parameters[0], parameters[0].waarden[i / kolommen])];
+        if let Some(tweede) = parameters.get(1) {
+            waarden.push((tweede, tweede.waarden[i % kolommen]));
+        }
+        simuleer_met(scenario, &waarden)
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(SweepResultaat {
+        parameters: parameters.to_vec(),
+        uitkomsten: uitkomsten.chunks(kolommen).map(<[_]>::to_vec).collect(),
+    })
+}
+
+/// Varieer elke parameter afzonderlijk tussen zijn laagste en hoogste
+/// waarde.
+pub fn tornado(
+    scenario: &Scenario,
+    parameters: &[SweepParameter],
+) -> Result<TornadoResultaat, ScenarioFout> {
+    if parameters.is_empty() || 2 * parameters.len() + 1 > MAX_SWEEP_RUNS {
+        return Err(ScenarioFout::OngeldigFormaat {
+            details: format!(
+                "Een tornado heeft 1 tot {} parameters nodig",
+                (MAX_SWEEP_RUNS - 1) / 2
+            ),
+        });
+    }
+    for parameter in parameters {
+        parameter.valideer(scenario)?;
+    }
+    scenario.valideer()?;
+
+    let grenzen: Vec<(f64, f64)> = parameters
+        .iter()
+        .map(|p| {
+            let laag = p.waarden.iter().cloned().fold(f64::INFINITY, f64::min);
+            let hoog = p.waarden.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            (laag, hoog)
+        })
+        .collect();
+    // Run 0 is de basis, daarna laag en hoog per parameter
+    let uitkomsten = parallel(2 * parameters.len() + 1, |i| {
+        if i == 0 {
+            return simuleer_met(scenario, &[]);
+        }
+        let p = (i - 1) / 2;
+        let waarde = if i % 2 == 1 {
+            grenzen[p].0
+        } else {
+            grenzen[p].1
+        };
+        simuleer_met(scenario, &[(&parameters[p], waarde)])
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let mut balken: Vec<TornadoBalk> = parameters
+        .iter()
+        .enumerate()
+        .map(|(p, parameter)| {
+            let (uitkomst_laag, uitkomst_hoog) = (uitkomsten[2 * p + 1], uitkomsten[2 * p + 2]);
+            TornadoBalk {
+                grootheid: parameter.grootheid,
+                peilgebied: parameter.peilgebied.clone(),
+                laag: grenzen[p].0,
+                hoog: grenzen[p].1,
+                uitkomst_laag,
+                uitkomst_hoog,
+                uitslag: uitkomst_hoog.max_peilstijging - uitkomst_laag.max_peilstijging,
+            }
+        })
+        .collect();
+    balken.sort_by(|a, b| b.uitslag.abs().total_cmp(&a.uitslag.abs()));
+
+    Ok(TornadoResultaat {
+        basis: uitkomsten[0],
+        balken,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{NetwerkTopologie, PeilgebiedConfig};
+
+    fn scenario() -> Scenario {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 100_000.0,
+                streefpeil: -0.60,
+                marge: 0.05,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet: 0.05,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        let mut scenario = Scenario::nieuw("sweep".to_string(), topologie);
+        scenario
+            .regen_scenario
+            .regen_per_uur
+            .insert("polder".to_string(), vec![10.0, 10.0, 0.0, 0.0]);
+        scenario.parameters.duration_hours = 4;
+        scenario
+    }
+
+    #[test]
+    fn test_bereik() {
+        let p = SweepParameter::bereik(SweepGrootheid::Neerslag, 0.5, 1.5, 3);
+        assert_eq!(p.waarden, vec![0.5, 1.0, 1.5]);
+        assert_eq!(
+            SweepParameter::bereik(SweepGrootheid::Kwel, 2.0, 9.0, 1).waarden,
+            vec![2.0]
+        );
+    }
+
+    #[test]
+    fn test_sweep_matrix() {
+        let scenario = scenario();
+        let resultaat = sweep(
+            &scenario,
+            &[
+                SweepParameter::bereik(SweepGrootheid::Neerslag, 0.5, 1.5, 3),
+                SweepParameter::bereik(SweepGrootheid::Gemaalcapaciteit, 1.0, 4.0, 2),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(resultaat.uitkomsten.len(), 3);
+        assert!(resultaat.uitkomsten.iter().all(|rij| rij.len() == 2));
+        // Meer regen: hoger peil; meer capaciteit: lager peil
+        let stijging = |r: usize, k: usize| resultaat.uitkomsten[r][k].max_peilstijging;
+        assert!(stijging(0, 0) < stijging(1, 0) && stijging(1, 0) < stijging(2, 0));
+        assert!(stijging(2, 1) < stijging(2, 0));
+
+        // De basiscombinatie is gelijk aan het scenario zelf
+        let basis = SweepUitkomst::uit(&scenario, &scenario.simuleer().unwrap());
+        assert!((stijging(1, 0) - basis.max_peilstijging).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_tornado() {
+        let scenario = scenario();
+        let resultaat = tornado(
+            &scenario,
+            &[
+                SweepParameter::bereik(SweepGrootheid::Streefpeil, -0.1, 0.1, 2),
+                SweepParameter::bereik(SweepGrootheid::Neerslag, 0.5, 1.5, 5),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(resultaat.balken.len(), 2);
+        // Neerslag bepaalt de peilstijging, een verschoven streefpeil vrijwel niet
+        assert_eq!(resultaat.balken[0].grootheid, SweepGrootheid::Neerslag);
+        assert!(resultaat.balken[0].uitslag > 0.0);
+        assert!(resultaat.balken[0].uitslag.abs() > resultaat.balken[1].uitslag.abs());
+    }
+
+    #[test]
+    fn test_sweep_valideer() {
+        let scenario = scenario();
+        assert!(sweep(&scenario, &[]).is_err());
+        assert!(
+            sweep(
+                &scenario,
+                &[SweepParameter::bereik(
+                    SweepGrootheid::Neerslag,
+                    -1.0,
+                    1.0,
+                    3
+                )]
+            )
+            .is_err()
+        );
+        assert!(
+            sweep(
+                &scenario,
+                &[SweepParameter::bereik(SweepGrootheid::Kwel, 0.0, 1.0, 2)
+                    .voor_peilgebied("onbekend".to_string())]
+            )
+            .is_err()
+        );
+        assert!(
+            sweep(
+                &scenario,
+                &[
+                    SweepParameter::bereik(SweepGrootheid::Neerslag, 0.0, 1.0, 30),
+                    SweepParameter::bereik(SweepGrootheid::Kwel, 0.0, 1.0, 30),
+                ]
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,765 @@
+//! Seizoens- en jaarbalans met dagtijdstappen.
+//!
+//! Onderbouwing van watertekort-analyses: een periode van maanden tot een
+//! jaar wordt per dag doorgerekend. Elk peilgebied houdt het zomer- of
+//! winterpeil van zijn [`Peilregime`]; het gemaal voert af wat boven dat
+//! peil komt en de inlaat vult aan wat eronder zakt, beide tot hun
+//! dagcapaciteit. Wat de inlaat niet kan aanvullen is het tekort.
+//!
+//! Het netwerk wordt doorgerekend met [`NetwerkSimulatie`] in stappen van
+//! een dag, zodat verbindingen tussen peilgebieden meetellen. Alleen een
+//! peilgebied met een boezem kan inlaten.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{
+    NetwerkFout, NetwerkSimulatie, NetwerkTopologie, PeilgebiedConfig, PeilgebiedId,
+    PeilgebiedStatus, UitstroomStrategy,
+};
+
+/// Seconden per dag.
+const SECONDEN_PER_DAG: f64 = 86_400.0;
+
+/// Minuten per dag, de tijdstap van de simulatie.
+const MINUTEN_PER_DAG: f64 = 1_440.0;
+
+/// Dag en maand waarop een seizoen begint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Dagmaand {
+    pub maand: u32,
+    pub dag: u32,
+}
+
+impl Dagmaand {
+    fn van(datum: NaiveDate) -> Self {
+        Self {
+            maand: datum.month(),
+            dag: datum.day(),
+        }
+    }
+
+    fn is_geldig(&self) -> bool {
+        NaiveDate::from_ymd_opt(2024, self.maand, self.dag).is_some()
+    }
+}
+
+/// Seizoen van een dag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Seizoen {
+    Zomer,
+    Winter,
+}
+
+/// Zomer- en winterpeil van een peilgebied (m NAP).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Peilregime {
+    pub zomerpeil: f64,
+    pub winterpeil: f64,
+}
+
+/// Instellingen van de jaarbalans.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JaarbalansInstellingen {
+    /// Eerste dag van het zomerpeil
+    #[serde(default = "default_zomer_vanaf")]
+    pub zomer_vanaf: Dagmaand,
+    /// Eerste dag van het winterpeil
+    #[serde(default = "default_winter_vanaf")]
+    pub winter_vanaf: Dagmaand,
+    /// Peilregime per peilgebied; zonder regime geldt het hele jaar het
+    /// streefpeil
+    #[serde(default)]
+    pub peilen: HashMap<PeilgebiedId, Peilregime>,
+}
+
+impl Default for JaarbalansInstellingen {
+    fn default() -> Self {
+        Self {
+            zomer_vanaf: default_zomer_vanaf(),
+            winter_vanaf: default_winter_vanaf(),
+            peilen: HashMap::new(),
+        }
+    }
+}
+
+fn default_zomer_vanaf() -> Dagmaand {
+    Dagmaand { maand: 4, dag: 1 }
+}
+
+fn default_winter_vanaf() -> Dagmaand {
+    Dagmaand { maand: 10, dag: 1 }
+}
+
+impl JaarbalansInstellingen {
+    /// Seizoen van een datum.
+    pub fn seizoen(&self, datum: NaiveDate) -> Seizoen {
+        let dag = Dagmaand::van(datum);
+        let zomer = if self.zomer_vanaf <= self.winter_vanaf {
+            self.zomer_vanaf <= dag && dag < self.winter_vanaf
+        } else {
+            dag >= self.zomer_vanaf || dag < self.winter_vanaf
+        };
+        if zomer {
+            Seizoen::Zomer
+        } else {
+            Seizoen::Winter
+        }
+    }
+
+    /// Streefpeil van een peilgebied in een seizoen.
+    fn streefpeil(&self, config: &PeilgebiedConfig, seizoen: Seizoen) -> f64 {
+        match (self.peilen.get(&config.id), seizoen) {
+            (Some(regime), Seizoen::Zomer) => regime.zomerpeil,
+            (Some(regime), Seizoen::Winter) => regime.winterpeil,
+            (None, _) => config.streefpeil,
+        }
+    }
+}
+
+/// Neerslag en referentieverdamping van één dag.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Dagweer {
+    pub datum: NaiveDate,
+    /// Neerslag in mm
+    pub neerslag: f64,
+    /// Referentieverdamping in mm, bijv. Makkink; de verdamping van een
+    /// peilgebied is deze maal zijn gewasfactor
+    pub referentieverdamping: f64,
+}
+
+/// Balans van één peilgebied op één dag. Volumes in m³; kwel is netto
+/// kwel min infiltratie en negatief bij wegzijging.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Dagbalans {
+    pub datum: NaiveDate,
+    pub seizoen: Seizoen,
+    /// Streefpeil van de dag in m NAP
+    pub streefpeil: f64,
+    /// Waterstand aan het eind van de dag in m NAP
+    pub waterstand: f64,
+    pub neerslag: f64,
+    pub verdamping: f64,
+    pub kwel: f64,
+    /// Netto instroom over verbindingen met andere peilgebieden en boezems
+    #[serde(default)]
+    pub verbindingen: f64,
+    /// Ingelaten uit de boezem
+    pub aanvoer: f64,
+    /// Uitgeslagen door het gemaal
+    pub afvoer: f64,
+    /// Volume onder streefpeil dat de inlaat aan het eind van de dag niet
+    /// heeft kunnen aanvullen
+    pub tekort: f64,
+    /// Aanvoer en afvoer vanaf de eerste dag
+    pub cumulatieve_aanvoer: f64,
+    pub cumulatieve_afvoer: f64,
+}
+
+/// Balans van een peilgebied over een seizoen of de hele periode. Volumes
+/// in m³.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Balanstotalen {
+    pub dagen: usize,
+    pub neerslag: f64,
+    pub verdamping: f64,
+    pub kwel: f64,
+    #[serde(default)]
+    pub verbindingen: f64,
+    pub aanvoer: f64,
+    pub afvoer: f64,
+    /// Toename van de berging; gelijk aan alle instroom min alle uitstroom
+    pub bergingsverandering: f64,
+    /// Grootste tekort aan het eind van een dag
+    pub max_tekort: f64,
+    /// Dagen met een tekort
+    pub dagen_met_tekort: usize,
+    /// Dagen die eindigen onder streefpeil min marge
+    pub dagen_onder_marge: usize,
+}
+
+impl Balanstotalen {
+    fn tel(&mut self, dag: &Dagbalans, berging: f64, onder_marge: bool) {
+        self.dagen += 1;
+        self.neerslag += dag.neerslag;
+        self.verdamping += dag.verdamping;
+        self.kwel += dag.kwel;
+        self.verbindingen += dag.verbindingen;
+        self.aanvoer += dag.aanvoer;
+        self.afvoer += dag.afvoer;
+        self.bergingsverandering += berging;
+        self.max_tekort = self.max_tekort.max(dag.tekort);
+        if dag.tekort > 0.0 {
+            self.dagen_met_tekort += 1;
+        }
+        if onder_marge {
+            self.dagen_onder_marge += 1;
+        }
+    }
+}
+
+/// Jaarbalans van één peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedJaarbalans {
+    pub peilgebied_id: PeilgebiedId,
+    pub dagen: Vec<Dagbalans>,
+    pub zomer: Balanstotalen,
+    pub winter: Balanstotalen,
+    pub totaal: Balanstotalen,
+}
+
+/// Jaarbalans van alle peilgebieden, op volgorde van id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JaarbalansResultaat {
+    pub peilgebieden: Vec<PeilgebiedJaarbalans>,
+}
+
+/// Fouten bij het doorrekenen van een jaarbalans.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JaarbalansFout {
+    /// Er zijn geen dagen om door te rekenen
+    GeenDagen,
+    /// De dagen volgen niet dag na dag op elkaar
+    OntbrekendeDag { na: NaiveDate },
+    /// Een seizoensgrens is geen bestaande datum
+    OngeldigeSeizoensgrens { grens: Dagmaand },
+    /// Een peilregime hoort bij een onbekend peilgebied
+    OnbekendPeilgebied { id: PeilgebiedId },
+    /// De netwerksimulatie is mislukt
+    Netwerk(NetwerkFout),
+}
+
+impl fmt::Display for JaarbalansFout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GeenDagen => write!(f, "Geen dagen om door te rekenen"),
+            Self::OntbrekendeDag { na } => write!(f, "Dag na {} ontbreekt", na),
+            Self::OngeldigeSeizoensgrens { grens } => {
+                write!(f, "Ongeldige seizoensgrens: {}-{}", grens.dag, grens.maand)
+            }
+            Self::OnbekendPeilgebied { id } => {
+                write!(f, "Peilregime voor onbekend peilgebied {}", id)
+            }
+            Self::Netwerk(e) => write!(f, "Netwerksimulatie mislukt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JaarbalansFout {}
+
+impl From<NetwerkFout> for JaarbalansFout {
+    fn from(e: NetwerkFout) -> Self {
+        Self::Netwerk(e)
+    }
+}
+
+/// Reken de peilgebieden van een topologie dag voor dag door over de dagen
+/// van `weer`, beginnend op het streefpeil van de eerste dag.
+pub fn simuleer_jaarbalans(
+    topologie: &NetwerkTopologie,
+    weer: &[Dagweer],
+    instellingen: &JaarbalansInstellingen,
+) -> Result<JaarbalansResultaat, JaarbalansFout> {
+    simuleer_dagen(topologie, weer, instellingen, |_, _, vragen| {
+        vragen.to_vec()
+    })
+}
+
+/// Reken de jaarbalans door met een eigen verdeling van de aanvoer. Per dag
+/// krijgt `verdeel` de index van de dag, de peilgebieden op volgorde van id
+/// en de aanvoer die elk vraagt (m³, al begrensd op de dagcapaciteit van de
+/// inlaat); het geeft de aanvoer terug die elk peilgebied krijgt.
+pub(crate) fn simuleer_dagen(
+    topologie: &NetwerkTopologie,
+    weer: &[Dagweer],
+    instellingen: &JaarbalansInstellingen,
+    mut verdeel: impl FnMut(usize, &[&PeilgebiedId], &[f64]) -> Vec<f64>,
+) -> Result<JaarbalansResultaat, JaarbalansFout> {
+    let eerste = weer.first().ok_or(JaarbalansFout::GeenDagen)?;
+    for paar in weer.windows(2) {
+        if paar[0].datum.succ_opt() != Some(paar[1].datum) {
+            return Err(JaarbalansFout::OntbrekendeDag { na: paar[0].datum });
+        }
+    }
+    for grens in [instellingen.zomer_vanaf, instellingen.winter_vanaf] {
+        if !grens.is_geldig() {
+            return Err(JaarbalansFout::OngeldigeSeizoensgrens { grens });
+        }
+    }
+    if let Some(id) = instellingen
+        .peilen
+        .keys()
+        .find(|id| !topologie.peilgebieden.contains_key(*id))
+    {
+        return Err(JaarbalansFout::OnbekendPeilgebied { id: id.clone() });
+    }
+
+    let mut simulatie =
+        NetwerkSimulatie::nieuw(topologie.clone())?.met_tijdstap(MINUTEN_PER_DAG)?;
+    let mut ids: Vec<&PeilgebiedId> = topologie.peilgebieden.keys().collect();
+    ids.sort();
+    for id in &ids {
+        let config = &topologie.peilgebieden[*id];
+        let start = instellingen.streefpeil(config, instellingen.seizoen(eerste.datum));
+        simulatie.waterstanden.insert((*id).clone(), start);
+        // De verdamping wordt per dag ingesteld
+        if let Some(config) = simulatie.topologie.peilgebieden.get_mut(*id) {
+            config.referentieverdamping.clear();
+        }
+    }
+    let mut staten: Vec<Balansstaat> = ids
+        .iter()
+        .map(|id| Balansstaat::nieuw(&topologie.peilgebieden[*id], weer.len()))
+        .collect();
+
+    let mut regeling = Dagregeling::default();
+    for (index, dag) in weer.iter().enumerate() {
+        let verbindingen = simulatie.netto_verbindingsdebiet()?;
+        let begins: Vec<Dagbegin> = staten
+            .iter()
+            .map(|staat| {
+                let id = &staat.config.id;
+                staat.begin_dag(
+                    dag,
+                    instellingen,
+                    simulatie.waterstanden[id],
+                    verbindingen[id] * SECONDEN_PER_DAG,
+                )
+            })
+            .collect();
+        let vragen: Vec<f64> = staten
+            .iter()
+            .zip(&begins)
+            .map(|(staat, begin)| staat.aanvoervraag(begin))
+            .collect();
+        let toegewezen: Vec<f64> = verdeel(index, &ids, &vragen)
+            .into_iter()
+            .zip(&vragen)
+            .map(|(aanvoer, vraag)| aanvoer.clamp(0.0, *vraag))
+            .collect();
+
+        let mut regen = HashMap::new();
+        for ((staat, begin), aanvoer) in staten.iter().zip(&begins).zip(&toegewezen) {
+            let id = &staat.config.id;
+            regeling.debieten.insert(
+                id.clone(),
+                Dagdebieten {
+                    afvoer: staat.afvoer(begin) / SECONDEN_PER_DAG,
+                    aanvoer: aanvoer / SECONDEN_PER_DAG,
+                },
+            );
+            // Neerslag en verdamping gelijkmatig over de dag, in mm/uur
+            regen.insert(id.clone(), dag.neerslag.max(0.0) / 24.0);
+            if let Some(config) = simulatie.topologie.peilgebieden.get_mut(id) {
+                config.verdamping = dag.referentieverdamping.max(0.0) * config.gewasfactor / 24.0;
+            }
+        }
+
+        let statussen: HashMap<PeilgebiedId, PeilgebiedStatus> = simulatie
+            .simuleer_stap(&regen, &regeling)?
+            .into_iter()
+            .map(|status| (status.id.clone(), status))
+            .collect();
+        for ((staat, begin), aanvoer) in staten.iter_mut().zip(begins).zip(toegewezen) {
+            let id = &staat.config.id;
+            staat.sluit_dag(
+                dag,
+                begin,
+                aanvoer,
+                &statussen[id],
+                simulatie.waterstanden[id],
+            );
+        }
+    }
+
+    let peilgebieden = staten.into_iter().map(Balansstaat::afronden).collect();
+    Ok(JaarbalansResultaat { peilgebieden })
+}
+
+/// Afvoer en aanvoer van een peilgebied op één dag, in m³/s.
+#[derive(Debug, Clone, Copy, Default)]
+struct Dagdebieten {
+    afvoer: f64,
+    aanvoer: f64,
+}
+
+/// Strategy die gemaal en inlaat de debieten van de dag geeft.
+#[derive(Debug, Default)]
+struct Dagregeling {
+    debieten: HashMap<PeilgebiedId, Dagdebieten>,
+}
+
+impl UitstroomStrategy for Dagregeling {
+    fn bepaal_uitstroom(
+        &self,
+        peilgebied_id: &str,
+        _waterstand: f64,
+        _config: &PeilgebiedConfig,
+        _regen_intensiteit: f64,
+        _inkomend_debiet: f64,
+    ) -> f64 {
+        self.debieten
+            .get(peilgebied_id)
+            .map_or(0.0, |debieten| debieten.afvoer)
+    }
+
+    fn bepaal_inlaat_op(
+        &self,
+        _tijd: f64,
+        peilgebied_id: &str,
+        _waterstand: f64,
+        _config: &PeilgebiedConfig,
+        _stap_minuten: f64,
+    ) -> f64 {
+        self.debieten
+            .get(peilgebied_id)
+            .map_or(0.0, |debieten| debieten.aanvoer)
+    }
+}
+
+/// Een peilgebied tijdens het doorrekenen.
+struct Balansstaat<'a> {
+    config: &'a PeilgebiedConfig,
+    cumulatieve_aanvoer: f64,
+    cumulatieve_afvoer: f64,
+    dagen: Vec<Dagbalans>,
+    zomer: Balanstotalen,
+    winter: Balanstotalen,
+    totaal: Balanstotalen,
+}
+
+/// Een dag tot en met neerslag, verdamping, kwel en verbindingen; gemaal en
+/// inlaat moeten nog.
+struct Dagbegin {
+    seizoen: Seizoen,
+    streefpeil: f64,
+    /// Volume aan het begin van de dag
+    volume: f64,
+    neerslag: f64,
+    verdamping: f64,
+    kwel: f64,
+    /// Volume boven (positief) of onder streefpeil na neerslag, verdamping,
+    /// kwel en verbindingen
+    verschil: f64,
+}
+
+impl<'a> Balansstaat<'a> {
+    fn nieuw(config: &'a PeilgebiedConfig, dagen: usize) -> Self {
+        Self {
+            config,
+            cumulatieve_aanvoer: 0.0,
+            cumulatieve_afvoer: 0.0,
+            dagen: Vec::with_capacity(dagen),
+            zomer: Balanstotalen::default(),
+            winter: Balanstotalen::default(),
+            totaal: Balanstotalen::default(),
+        }
+    }
+
+    fn mm(&self, mm: f64) -> f64 {
+        mm / 1000.0 * self.config.oppervlakte
+    }
+
+    fn volume(&self, peil: f64) -> f64 {
+        match &self.config.bergingscurve {
+            Some(curve) => curve.volume(peil),
+            None => peil * self.config.oppervlakte,
+        }
+    }
+
+    fn peil_na(&self, peil: f64, verandering: f64) -> f64 {
+        match &self.config.bergingscurve {
+            Some(curve) => curve.peil_na(peil, verandering),
+            None => peil + verandering / self.config.oppervlakte,
+        }
+    }
+
+    /// Begin een dag bij `waterstand` met `verbindingen` m³ netto instroom
+    /// over de verbindingen.
+    fn begin_dag(
+        &self,
+        dag: &Dagweer,
+        instellingen: &JaarbalansInstellingen,
+        waterstand: f64,
+        verbindingen: f64,
+    ) -> Dagbegin {
+        let config = self.config;
+        let seizoen = instellingen.seizoen(dag.datum);
+        let streefpeil = instellingen.streefpeil(config, seizoen);
+
+        // Neerslag, verdamping en kwel bij de waterstand van het begin van
+        // de dag
+        let neerslag = self.mm(dag.neerslag.max(0.0));
+        let verdamping = self.mm(dag.referentieverdamping.max(0.0) * config.gewasfactor);
+        let kwel_mm_uur =
+            config.kwel.map_or(0.0, |k| k.kwel_mm_uur(waterstand)) - config.infiltratie;
+        let kwel = self.mm(kwel_mm_uur * 24.0);
+        let peil = self.peil_na(waterstand, neerslag - verdamping + kwel + verbindingen);
+        Dagbegin {
+            seizoen,
+            streefpeil,
+            volume: self.volume(waterstand),
+            neerslag,
+            verdamping,
+            kwel,
+            verschil: self.volume(peil) - self.volume(streefpeil),
+        }
+    }
+
+    /// Aanvoer die de inlaat vandaag wil en kan inlaten; zonder boezem is
+    /// er geen inlaat.
+    fn aanvoervraag(&self, begin: &Dagbegin) -> f64 {
+        if self.config.boezem.is_none() {
+            return 0.0;
+        }
+        (-begin.verschil).clamp(0.0, self.config.max_inlaat_debiet * SECONDEN_PER_DAG)
+    }
+
+    /// Afvoer van het gemaal tot streefpeil, tot zijn dagcapaciteit.
+    fn afvoer(&self, begin: &Dagbegin) -> f64 {
+        begin
+            .verschil
+            .clamp(0.0, self.config.max_uitstroom_debiet * SECONDEN_PER_DAG)
+    }
+
+    /// Sluit de dag af met de toegewezen `aanvoer`, de gesimuleerde stap van
+    /// het peilgebied en de waterstand aan het eind van de dag.
+    fn sluit_dag(
+        &mut self,
+        dag: &Dagweer,
+        begin: Dagbegin,
+        aanvoer: f64,
+        status: &PeilgebiedStatus,
+        waterstand: f64,
+    ) {
+        let afvoer = status.uitstroom_debiet * SECONDEN_PER_DAG;
+        let verbindingen = (status.inkomend_debiet - status.uitgaand_debiet) * SECONDEN_PER_DAG;
+        let tekort = (-begin.verschil - aanvoer).max(0.0);
+
+        self.cumulatieve_aanvoer += aanvoer;
+        self.cumulatieve_afvoer += afvoer;
+        let balans = Dagbalans {
+            datum: dag.datum,
+            seizoen: begin.seizoen,
+            streefpeil: begin.streefpeil,
+            waterstand,
+            neerslag: begin.neerslag,
+            verdamping: begin.verdamping,
+            kwel: begin.kwel,
+            verbindingen,
+            aanvoer,
+            afvoer,
+            tekort,
+            cumulatieve_aanvoer: self.cumulatieve_aanvoer,
+            cumulatieve_afvoer: self.cumulatieve_afvoer,
+        };
+        let berging = self.volume(waterstand) - begin.volume;
+        let onder_marge = waterstand < begin.streefpeil - self.config.marge;
+        let seizoen_totaal = match begin.seizoen {
+            Seizoen::Zomer => &mut self.zomer,
+            Seizoen::Winter => &mut self.winter,
+        };
+        seizoen_totaal.tel(&balans, berging, onder_marge);
+        self.totaal.tel(&balans, berging, onder_marge);
+        self.dagen.push(balans);
+    }
+
+    fn afronden(self) -> PeilgebiedJaarbalans {
+        PeilgebiedJaarbalans {
+            peilgebied_id: self.config.id.clone(),
+            dagen: self.dagen,
+            zomer: self.zomer,
+            winter: self.winter,
+            totaal: self.totaal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{Boezem, Verbinding};
+
+    fn peilgebied(id: &str, max_inlaat_debiet: f64) -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 1_000_000.0,
+            streefpeil: -0.60,
+            marge: 0.10,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.02,
+            kwel: None,
+            bergingscurve: None,
+            boezem: Some("boezem".to_string()),
+            chloride: None,
+        }
+    }
+
+    fn topologie(max_inlaat_debiet: f64) -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_boezem_toe(Boezem::nieuw("boezem", -0.40))
+            .unwrap();
+        topologie
+            .voeg_peilgebied_toe(peilgebied("polder", max_inlaat_debiet))
+            .unwrap();
+        topologie
+    }
+
+    fn sluitfout(t: &Balanstotalen) -> f64 {
+        t.neerslag - t.verdamping + t.kwel + t.verbindingen + t.aanvoer
+            - t.afvoer
+            - t.bergingsverandering
+    }
+
+    /// Een jaar met natte winters en droge zomers.
+    fn jaar() -> Vec<Dagweer> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        start
+            .iter_days()
+            .take(365)
+            .map(|datum| {
+                let zomer = (4..10).contains(&datum.month());
+                Dagweer {
+                    datum,
+                    neerslag: if zomer { 1.0 } else { 3.0 },
+                    referentieverdamping: if zomer { 4.0 } else { 0.5 },
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_jaarbalans() {
+        let instellingen = JaarbalansInstellingen {
+            peilen: HashMap::from([(
+                "polder".to_string(),
+                Peilregime {
+                    zomerpeil: -0.50,
+                    winterpeil: -0.60,
+                },
+            )]),
+            ..Default::default()
+        };
+        let resultaat = simuleer_jaarbalans(&topologie(2.0), &jaar(), &instellingen).unwrap();
+        let polder = &resultaat.peilgebieden[0];
+        assert_eq!(polder.dagen.len(), 365);
+        assert_eq!(polder.zomer.dagen + polder.winter.dagen, 365);
+
+        // Zomerpeil vanaf 1 april, winterpeil vanaf 1 oktober
+        let dag = |maand, dag| {
+            polder
+                .dagen
+                .iter()
+                .find(|d| d.datum == NaiveDate::from_ymd_opt(2025, maand, dag).unwrap())
+                .unwrap()
+        };
+        assert_eq!(dag(3, 31).streefpeil, -0.60);
+        assert_eq!(dag(4, 1).streefpeil, -0.50);
+        assert_eq!(dag(4, 1).seizoen, Seizoen::Zomer);
+        assert_eq!(dag(10, 1).streefpeil, -0.60);
+
+        // 's Zomers wordt aangevoerd, 's winters afgevoerd
+        assert!(polder.zomer.aanvoer > polder.zomer.afvoer);
+        assert!(polder.winter.afvoer > polder.winter.aanvoer);
+        assert_eq!(polder.totaal.dagen_met_tekort, 0);
+        assert!(
+            (polder.dagen.last().unwrap().cumulatieve_aanvoer - polder.totaal.aanvoer).abs() < 1e-6
+        );
+
+        // De balans sluit
+        assert!(sluitfout(&polder.totaal).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_verbindingen_en_boezem() {
+        // Hoog laat in uit de boezem en slaat 0.01 m³/s door naar laag, dat
+        // geen boezem heeft en dus niet kan inlaten
+        let mut topologie = topologie(2.0);
+        let mut laag = peilgebied("laag", 2.0);
+        laag.boezem = None;
+        topologie.voeg_peilgebied_toe(laag).unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_gemaal(
+                    "doorvoer".to_string(),
+                    "polder".to_string(),
+                    "laag".to_string(),
+                    0.01,
+                    0.5,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let resultaat =
+            simuleer_jaarbalans(&topologie, &jaar(), &JaarbalansInstellingen::default()).unwrap();
+        let laag = &resultaat.peilgebieden[0];
+        let polder = &resultaat.peilgebieden[1];
+        assert_eq!(laag.peilgebied_id, "laag");
+
+        let doorvoer = 0.01 * SECONDEN_PER_DAG * 365.0;
+        assert!((laag.totaal.verbindingen - doorvoer).abs() < 1e-6);
+        assert!((polder.totaal.verbindingen + doorvoer).abs() < 1e-6);
+        assert_eq!(laag.totaal.aanvoer, 0.0);
+        assert!(laag.zomer.dagen_met_tekort > 0);
+        assert!(polder.zomer.aanvoer > 0.0);
+        assert_eq!(polder.totaal.dagen_met_tekort, 0);
+        assert!(sluitfout(&laag.totaal).abs() < 1e-6);
+        assert!(sluitfout(&polder.totaal).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tekort_zonder_inlaat() {
+        let resultaat =
+            simuleer_jaarbalans(&topologie(0.0), &jaar(), &JaarbalansInstellingen::default())
+                .unwrap();
+        let polder = &resultaat.peilgebieden[0];
+        assert_eq!(polder.totaal.aanvoer, 0.0);
+        assert!(polder.zomer.dagen_met_tekort > 0);
+        assert!(polder.zomer.dagen_onder_marge > 0);
+        assert!(polder.totaal.max_tekort > 0.0);
+    }
+
+    #[test]
+    fn test_ongeldige_invoer() {
+        let topologie = topologie(0.2);
+        let instellingen = JaarbalansInstellingen::default();
+        assert_eq!(
+            simuleer_jaarbalans(&topologie, &[], &instellingen),
+            Err(JaarbalansFout::GeenDagen)
+        );
+
+        let mut weer = jaar();
+        weer.remove(10);
+        assert!(matches!(
+            simuleer_jaarbalans(&topologie, &weer, &instellingen),
+            Err(JaarbalansFout::OntbrekendeDag { .. })
+        ));
+
+        let onbekend = JaarbalansInstellingen {
+            peilen: HashMap::from([(
+                "elders".to_string(),
+                Peilregime {
+                    zomerpeil: 0.0,
+                    winterpeil: 0.0,
+                },
+            )]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            simuleer_jaarbalans(&topologie, &jaar(), &onbekend),
+            Err(JaarbalansFout::OnbekendPeilgebied { .. })
+        ));
+    }
+}
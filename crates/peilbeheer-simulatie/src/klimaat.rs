@@ -0,0 +1,220 @@
+//! KNMI'23-klimaatscenario's voor neerslagreeksen.
+//!
+//! Een bestaande uurreeks wordt per seizoen omgezet naar een toekomstig
+//! klimaat met een machtstransformatie `y = a·x^b`: de seizoenssom groeit
+//! met de seizoensfactor en het 99e percentiel van de natte uren met de
+//! extreemfactor. Zo worden zware buien sterker dan de gemiddelde neerslag
+//! zwaarder, zoals in de KNMI'23-scenario's, en blijven droge uren droog.
+//!
+//! De factoren zijn afgeronde kerncijfers van KNMI'23 ten opzichte van de
+//! referentieperiode 1991–2020. Voor een formele onderbouwing kunnen ze
+//! vervangen worden door de waarden van de KNMI-transformatietool.
+
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// KNMI'23-klimaatscenario met zichtjaar.
+///
+/// L/H: lage of hoge uitstoot; d/n: droge of natte variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Klimaatscenario {
+    Ld2050,
+    Ln2050,
+    Hd2050,
+    Hn2050,
+    Ld2100,
+    Ln2100,
+    Hd2100,
+    Hn2100,
+}
+
+impl Klimaatscenario {
+    /// Alle scenario's, voor een doorrekening van de hele set.
+    pub fn alle() -> [Self; 8] {
+        [
+            Self::Ld2050,
+            Self::Ln2050,
+            Self::Hd2050,
+            Self::Hn2050,
+            Self::Ld2100,
+            Self::Ln2100,
+            Self::Hd2100,
+            Self::Hn2100,
+        ]
+    }
+
+    /// Transformatiefactoren van het scenario.
+    pub fn transformatie(&self) -> KlimaatTransformatie {
+        // Winter, lente, zomer, herfst; extreme uurneerslag
+        let (seizoensfactoren, extreemfactor) = match self {
+            Self::Ld2050 => ([1.03, 1.00, 0.85, 0.98], 1.10),
+            Self::Ln2050 => ([1.06, 1.04, 0.98, 1.04], 1.12),
+            Self::Hd2050 => ([1.04, 0.99, 0.80, 0.97], 1.14),
+            Self::Hn2050 => ([1.11, 1.06, 0.96, 1.06], 1.17),
+            Self::Ld2100 => ([1.03, 1.00, 0.85, 0.98], 1.10),
+            Self::Ln2100 => ([1.06, 1.05, 0.99, 1.05], 1.12),
+            Self::Hd2100 => ([1.17, 0.98, 0.63, 0.95], 1.35),
+            Self::Hn2100 => ([1.24, 1.10, 0.97, 1.10], 1.45),
+        };
+        KlimaatTransformatie {
+            seizoensfactoren,
+            extreemfactor,
+        }
+    }
+}
+
+impl std::fmt::Display for Klimaatscenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let naam = match self {
+            Self::Ld2050 => "Ld 2050",
+            Self::Ln2050 => "Ln 2050",
+            Self::Hd2050 => "Hd 2050",
+            Self::Hn2050 => "Hn 2050",
+            Self::Ld2100 => "Ld 2100",
+            Self::Ln2100 => "Ln 2100",
+            Self::Hd2100 => "Hd 2100",
+            Self::Hn2100 => "Hn 2100",
+        };
+        write!(f, "{}", naam)
+    }
+}
+
+/// Factoren waarmee een neerslagreeks naar een klimaatscenario gaat.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KlimaatTransformatie {
+    /// Factor op de neerslagsom in winter, lente, zomer en herfst
+    pub seizoensfactoren: [f64; 4],
+    /// Factor op het 99e percentiel van de natte uren
+    pub extreemfactor: f64,
+}
+
+/// Percentiel van de natte uren dat met de extreemfactor schaalt.
+const EXTREEM_PERCENTIEL: f64 = 0.99;
+
+impl KlimaatTransformatie {
+    /// Uurreeks (mm/uur) die op `start` begint omgezet naar het scenario.
+    pub fn transformeer(&self, reeks: &[f64], start: DateTime<Utc>) -> Vec<f64> {
+        let seizoenen: Vec<usize> = (0..reeks.len())
+            .map(|uur| seizoen(start + Duration::hours(uur as i64)))
+            .collect();
+
+        let mut uitkomst = reeks.to_vec();
+        for (s, &factor) in self.seizoensfactoren.iter().enumerate() {
+            let natte: Vec<f64> = reeks
+                .iter()
+                .zip(&seizoenen)
+                .filter(|&(&x, &z)| z == s && x > 0.0)
+                .map(|(&x, _)| x)
+                .collect();
+            let (a, b) = macht(&natte, factor, self.extreemfactor);
+            for (y, &z) in uitkomst.iter_mut().zip(&seizoenen) {
+                if z == s && *y > 0.0 {
+                    *y = a * y.powf(b);
+                }
+            }
+        }
+        uitkomst
+    }
+}
+
+/// Seizoen van een tijdstip: 0 winter (dec–feb) tot 3 herfst (sep–nov).
+fn seizoen(tijd: DateTime<Utc>) -> usize {
+    (tijd.month0() as usize + 1) % 12 / 3
+}
+
+/// Parameters `a` en `b` van `y = a·x^b` waarmee de som van `natte` met
+/// `factor` groeit en het extreme percentiel met `extreemfactor`.
+fn macht(natte: &[f64], factor: f64, extreemfactor: f64) -> (f64, f64) {
+    let som: f64 = natte.iter().sum();
+    if natte.len() < 2 || som <= 0.0 {
+        return (factor, 1.0);
+    }
+    let mut gesorteerd = natte.to_vec();
+    gesorteerd.sort_by(f64::total_cmp);
+    let q = gesorteerd[((gesorteerd.len() - 1) as f64 * EXTREEM_PERCENTIEL).round() as usize];
+
+    // Groei van het percentiel bij exponent b, met de som vast op factor·som;
+    // die groei neemt toe met b.
+    let a_bij = |b: f64| factor * som / natte.iter().map(|x| x.powf(b)).sum::<f64>();
+    let groei = |b: f64| a_bij(b) * q.powf(b - 1.0);
+    let (mut laag, mut hoog) = (0.2, 3.0);
+    if groei(laag) >= extreemfactor {
+        return (a_bij(laag), laag);
+    }
+    if groei(hoog) <= extreemfactor {
+        return (a_bij(hoog), hoog);
+    }
+    for _ in 0..60 {
+        let midden = 0.5 * (laag + hoog);
+        if groei(midden) < extreemfactor {
+            laag = midden;
+        } else {
+            hoog = midden;
+        }
+    }
+    let b = 0.5 * (laag + hoog);
+    (a_bij(b), b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::NeerslagGenerator;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_seizoen() {
+        let op = |maand| seizoen(Utc.with_ymd_and_hms(2023, maand, 1, 0, 0, 0).unwrap());
+        assert_eq!(op(12), 0);
+        assert_eq!(op(1), 0);
+        assert_eq!(op(3), 1);
+        assert_eq!(op(7), 2);
+        assert_eq!(op(11), 3);
+    }
+
+    #[test]
+    fn test_transformatie_seizoenssom_en_extremen() {
+        let start = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+        let reeks = NeerslagGenerator::nederland(3).genereer_jaren(2001, 10);
+        let transformatie = Klimaatscenario::Hd2100.transformatie();
+        let toekomst = transformatie.transformeer(&reeks, start);
+
+        assert_eq!(toekomst.len(), reeks.len());
+        // Droge uren blijven droog
+        assert!(
+            reeks
+                .iter()
+                .zip(&toekomst)
+                .all(|(&x, &y)| (x == 0.0) == (y == 0.0))
+        );
+
+        let zomer = |r: &[f64]| -> Vec<f64> {
+            r.iter()
+                .enumerate()
+                .filter(|(uur, _)| seizoen(start + Duration::hours(*uur as i64)) == 2)
+                .map(|(_, &x)| x)
+                .collect()
+        };
+        let (nu, straks) = (zomer(&reeks), zomer(&toekomst));
+        let verhouding = straks.iter().sum::<f64>() / nu.iter().sum::<f64>();
+        assert!((verhouding - 0.63).abs() < 1e-9);
+
+        // De zwaarste buien worden zwaarder, ook al neemt de zomersom af
+        let max = |r: &[f64]| r.iter().cloned().fold(0.0, f64::max);
+        assert!(max(&straks) > max(&nu));
+    }
+
+    #[test]
+    fn test_transformatie_zonder_extreemverschil() {
+        let start = Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap();
+        let transformatie = KlimaatTransformatie {
+            seizoensfactoren: [1.1; 4],
+            extreemfactor: 1.1,
+        };
+        let toekomst = transformatie.transformeer(&[0.0, 1.0, 4.0, 0.5, 2.0], start);
+        for (y, x) in toekomst.iter().zip([0.0, 1.0, 4.0, 0.5, 2.0]) {
+            assert!((y - 1.1 * x).abs() < 1e-6);
+        }
+    }
+}
@@ -1,32 +1,95 @@
+pub mod checkpoint;
+pub mod chloride;
 pub mod drooglegging;
+pub mod droogte;
+pub mod ensemble;
 pub mod export;
+pub mod gevoeligheid;
+pub mod jaarbalans;
+pub mod klimaat;
+pub mod mpc;
+pub mod netcdf;
 pub mod netwerk;
+pub mod ontwerpbui;
+pub mod onzekerheid;
 pub mod optimalisatie;
 pub mod pid;
+pub mod pid_tuning;
+pub mod rapport;
+pub mod realtime;
 pub mod scenario;
+pub mod vergelijking;
+pub mod topologie_bouwer;
 pub mod visualisatie;
+pub mod vrachten;
 pub mod waterbalans;
 
+pub use checkpoint::{simuleer_met_checkpoints, Checkpoint, CheckpointFout};
+pub use chloride::ChlorideParameters;
 pub use drooglegging::{calculate_drooglegging, find_minimum_debiet};
+pub use droogte::{
+    simuleer_droogte, DroogteDag, DroogteFout, DroogteResultaat, Levering, PeilgebiedLevering,
+    Rang, RangLevering, Verdringingsreeks,
+};
+pub use ensemble::{
+    simuleer_ensemble, EnsembleFout, EnsembleResultaat, Ensemblelid, LidUitkomst,
+    PeilgebiedEnsemble,
+};
 pub use export::{
-    bereken_statistieken, CsvExport, ExportFout, ExportOpties, JsonExport,
-    PeilgebiedExportData, PeilgebiedStatistieken, PeilgebiedTijdstapExport, SimulatieStatistieken,
-    statistieken_als_json,
+    bereken_statistieken, Compressie, CsvExport, ExportFout, ExportOpties, JsonExport,
+    ParquetExport, PeilgebiedExportData, PeilgebiedStatistieken, PeilgebiedTijdstapExport,
+    SimulatieStatistieken, statistieken_als_json, vrachten_als_json, XlsxExport,
+};
+pub use gevoeligheid::{
+    sweep, tornado, SweepGrootheid, SweepParameter, SweepResultaat, SweepUitkomst, TornadoBalk,
+    TornadoResultaat,
 };
+pub use jaarbalans::{
+    simuleer_jaarbalans, Balanstotalen, Dagbalans, Dagmaand, Dagweer, JaarbalansFout,
+    JaarbalansInstellingen, JaarbalansResultaat, PeilgebiedJaarbalans, Peilregime, Seizoen,
+};
+pub use klimaat::{KlimaatTransformatie, Klimaatscenario};
+pub use netcdf::NetcdfExport;
 pub use netwerk::{
-    GebalanceerdeUitstroomStrategy, NetwerkFout, NetwerkSimulatie, NetwerkSimulatieResultaat,
-    NetwerkTijdstap, NetwerkTopologie, PeilgebiedConfig, PeilgebiedId, PeilgebiedStatus,
-    SimpeleUitstroomStrategy, StroomRichting, UitstroomStrategy, Verbinding, VerbindingId,
-    VerbindingStroom, VerbindingType,
+    AdaptieveTijdstap, Annulering, Bevinding, Boezem, DuikerParameters, Ernst, GebalanceerdeUitstroomStrategy, InlaatStrategy, NetwerkFout, NetwerkSimulatie,
+    NetwerkSimulatieResultaat, NetwerkTijdstap, NetwerkTopologie, PeilgebiedConfig, PeilgebiedId,
+    PeilgebiedStatus, Schakelmoment, SimpeleUitstroomStrategy, StroomRichting, StuwParameters,
+    StuwStrategy, TrapsgewijzeUitstroomStrategy, UitstroomStrategy, Verbinding, VerbindingId, VerbindingStroom,
+    VerbindingType, ValidatieRapport, Voortgang, run_netwerksimulatie_met_voortgang,
+};
+pub use mpc::{MpcConfig, MpcRegelaar};
+pub use ontwerpbui::{Buipatroon, Ontwerpbui};
+pub use onzekerheid::{
+    KostenParameters, MonteCarloAnalyse, Onzekerheid, OnzekerheidsResultaat, Percentielband,
 };
 pub use optimalisatie::optimize_pump_schedule;
 pub use pid::PidController;
+pub use pid_tuning::{
+    doelwaarde, tune_pid, ziegler_nichols, Criterium, Doelfunctie, PidTuning, TuningFout,
+    TuningMethode,
+};
+pub use rapport::{Rapport, RapportFout, Sectie};
+pub use realtime::{
+    reken_vooruit, Beginstaat, PeilgebiedVerwachting, Verwachting, Verwachtingswaarde,
+};
 pub use scenario::{
-    constant_regen_scenario, Regenscenario, RegenscenarioType, Scenario, ScenarioBouwer,
-    ScenarioFout, ScenarioMetadata, ScenarioResultaat, SimulatieParameters, StrategyType,
+    constant_regen_scenario, run_scenarios_parallel, HistorischePeriode, MaandNeerslag, NeerslagGenerator, Regenscenario,
+    RegenscenarioType, Scenario, ScenarioBouwer, ScenarioFout, ScenarioMetadata,
+    ScenarioResultaat, SimulatieParameters, StrategyType,
+};
+pub use topologie_bouwer::{
+    stel_topologie_voor, BouwerFout, BouwerInstellingen, GrensVoorstel, KnoopVoorstel,
+    KunstwerkSoort, TopologieVoorstel, VerbindingVoorstel,
+};
+pub use vergelijking::{
+    vergelijk, Kengetallen, PeilgebiedVerschil, ScenarioVerschil, Verschil,
 };
 pub use visualisatie::{
-    genereer_alle_grafieken, Kleurenschema, PompGrafiek, RegenGrafiek, Resolutie,
-    VisualisatieFout, WaterstandGrafiek, GrafiekOpties, GrafiekType,
+    genereer_alle_grafieken, Kleurenschema, LijnGrafiek, PompGrafiek, Reeks, RegenGrafiek,
+    Resolutie, VisualisatieFout, WaterstandGrafiek, GrafiekOpties, GrafiekType,
+};
+pub use vrachten::{
+    bereken_vrachten, Nutrientconcentratie, PeilgebiedVracht, VerbindingVracht, Vracht,
+    VrachtenResultaat,
 };
 pub use waterbalans::{calculate_time_series, calculate_water_balance, mm_per_uur_to_m3_per_sec};
@@ -0,0 +1,437 @@
+//! Model Predictive Control (MPC) van het uitstroomgemaal.
+//!
+//! [`MpcRegelaar`] bepaalt per peilgebied het pompdebiet met een rolling
+//! horizon: op elk regelmoment wordt vanaf de actuele waterstand het
+//! goedkoopste pompschema over de horizon gezocht (dynamic programming
+//! over waterstand en pompfractie), met de regen- en prijsvoorspelling.
+//! Alleen de eerste stap van het schema wordt uitgevoerd; op het volgende
+//! regelmoment wordt opnieuw gepland.
+//!
+//! De regelaar implementeert [`UitstroomStrategy`] en kan zo direct in de
+//! netwerksimulatie gebruikt worden in plaats van de PID- of simpele
+//! regeling.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{PeilgebiedConfig, PeilgebiedId, UitstroomStrategy};
+use crate::optimalisatie::calculate_pump_power_kw;
+use crate::waterbalans::calculate_water_balance;
+
+/// Prijs voor uren zonder voorspelling als er helemaal geen prijzen zijn (€/kWh).
+const STANDAARD_PRIJS: f64 = 0.10;
+
+/// Discretisatie van de waterstand in de DP (m).
+const WS_STAP: f64 = 0.005;
+
+/// Beschikbare pompfracties per regelstap.
+const POMP_FRACTIES: [f64; 9] = [0.0, 0.05, 0.10, 0.25, 0.40, 0.50, 0.75, 0.90, 1.0];
+
+/// Instellingen van de MPC-regelaar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MpcConfig {
+    /// Lengte van de voorspellingshorizon in uren
+    #[serde(default = "default_horizon_uren")]
+    pub horizon_uren: usize,
+    /// Lengte van een regelstap in minuten; na elke stap wordt opnieuw gepland
+    #[serde(default = "default_stap_minuten")]
+    pub stap_minuten: usize,
+    /// Opvoerhoogte van het gemaal (m)
+    #[serde(default = "default_opvoerhoogte")]
+    pub opvoerhoogte: f64,
+    /// Rendement van het gemaal (0-1)
+    #[serde(default = "default_efficiency")]
+    pub efficiency: f64,
+    /// Strafkosten per cm per uur buiten de marge rond het streefpeil (€)
+    #[serde(default = "default_penalty_per_cm")]
+    pub penalty_per_cm: f64,
+}
+
+fn default_horizon_uren() -> usize {
+    24
+}
+
+fn default_stap_minuten() -> usize {
+    15
+}
+
+fn default_opvoerhoogte() -> f64 {
+    2.0
+}
+
+fn default_efficiency() -> f64 {
+    0.70
+}
+
+fn default_penalty_per_cm() -> f64 {
+    100.0
+}
+
+impl Default for MpcConfig {
+    fn default() -> Self {
+        Self {
+            horizon_uren: default_horizon_uren(),
+            stap_minuten: default_stap_minuten(),
+            opvoerhoogte: default_opvoerhoogte(),
+            efficiency: default_efficiency(),
+            penalty_per_cm: default_penalty_per_cm(),
+        }
+    }
+}
+
+/// Laatst berekend plan van een peilgebied.
+#[derive(Debug, Clone)]
+struct Plan {
+    /// Start van het plan in minuten
+    start: f64,
+    /// Pompfractie per regelstap
+    fracties: Vec<f64>,
+}
+
+/// MPC-regelaar voor de uitstroom van peilgebieden.
+///
+/// Tijden zijn minuten sinds de start van de voorspellingen: uur `u` van
+/// de regen- en prijsvoorspelling loopt van minuut `60 u` tot `60 (u + 1)`.
+#[derive(Debug)]
+pub struct MpcRegelaar {
+    config: MpcConfig,
+    /// Regenvoorspelling per peilgebied (mm/uur per uur)
+    regen: HashMap<PeilgebiedId, Vec<f64>>,
+    /// Prijsvoorspelling per uur (€/kWh)
+    prijzen: Vec<f64>,
+    plannen: Mutex<HashMap<PeilgebiedId, Plan>>,
+}
+
+impl MpcRegelaar {
+    /// Maak een regelaar zonder voorspellingen: geen regen, standaardprijs.
+    pub fn nieuw(config: MpcConfig) -> Self {
+        Self {
+            config,
+            regen: HashMap::new(),
+            prijzen: Vec::new(),
+            plannen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Stel de regenvoorspelling van een peilgebied in (mm/uur per uur).
+    pub fn met_regen(mut self, peilgebied_id: PeilgebiedId, regen_per_uur: Vec<f64>) -> Self {
+        self.regen.insert(peilgebied_id, regen_per_uur);
+        self
+    }
+
+    /// Stel de prijsvoorspelling in (€/kWh per uur). Na de laatste prijs
+    /// geldt die prijs.
+    pub fn met_prijzen(mut self, prijzen: Vec<f64>) -> Self {
+        self.prijzen = prijzen;
+        self
+    }
+
+    pub fn config(&self) -> &MpcConfig {
+        &self.config
+    }
+
+    /// Regenintensiteit (mm/uur) van een peilgebied op minuut `tijd`.
+    fn regen_op(&self, peilgebied_id: &str, tijd: f64) -> f64 {
+        let uur = (tijd / 60.0).floor().max(0.0) as usize;
+        self.regen
+            .get(peilgebied_id)
+            .and_then(|r| r.get(uur))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Stroomprijs (€/kWh) op minuut `tijd`.
+    fn prijs_op(&self, tijd: f64) -> f64 {
+        let uur = (tijd / 60.0).floor().max(0.0) as usize;
+        self.prijzen
+            .get(uur)
+            .or(self.prijzen.last())
+            .copied()
+            .unwrap_or(STANDAARD_PRIJS)
+    }
+
+    /// Optimaal pompschema (fractie van `max_uitstroom_debiet` per
+    /// regelstap) over de horizon vanaf minuut `tijd` en `waterstand`.
+    ///
+    /// Het inkomend debiet uit andere peilgebieden wordt over de horizon
+    /// constant verondersteld.
+    pub fn bereken_plan(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        inkomend_debiet: f64,
+    ) -> Vec<f64> {
+        let stap_minuten = self.config.stap_minuten.max(1);
+        let n_stappen = (self.config.horizon_uren * 60)
+            .div_ceil(stap_minuten)
+            .max(1);
+        let stap_uren = stap_minuten as f64 / 60.0;
+        if config.oppervlakte <= 0.0 || config.max_uitstroom_debiet <= 0.0 {
+            return vec![0.0; n_stappen];
+        }
+
+        // Peilverandering per regelstap: de waterbalans hangt alleen via de
+        // kwel en de bergingscurve af van de waterstand, die bij de huidige
+        // waterstand worden genomen, dus één minuut volstaat
+        let regen: Vec<f64> = (0..n_stappen)
+            .map(|s| self.regen_op(peilgebied_id, tijd + (s * stap_minuten) as f64))
+            .collect();
+        let verandering = |stap: usize, fractie: f64| {
+            let balans = calculate_water_balance(
+                regen[stap],
+                config.oppervlakte,
+                waterstand,
+                fractie * config.max_uitstroom_debiet,
+                config.verdamping_op(tijd + (stap * stap_minuten) as f64),
+                config.infiltratie,
+                config.kwel.as_ref(),
+            );
+            let inkomend = inkomend_debiet / config.oppervlakte * 60.0;
+            let berging = config.oppervlakte / config.bergend_oppervlak(waterstand);
+            (balans.waterstand_verandering + inkomend) * berging * stap_minuten as f64
+        };
+        let kosten: Vec<Vec<f64>> = (0..n_stappen)
+            .map(|s| {
+                let prijs = self.prijs_op(tijd + (s * stap_minuten) as f64);
+                POMP_FRACTIES
+                    .iter()
+                    .map(|&fractie| {
+                        calculate_pump_power_kw(
+                            fractie * config.max_uitstroom_debiet,
+                            self.config.opvoerhoogte,
+                            self.config.efficiency,
+                        ) * prijs
+                            * stap_uren
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Toestandsruimte: de marge plus de grootst mogelijke stijging en daling
+        let max_stijging: f64 = (0..n_stappen).map(|s| verandering(s, 0.0).max(0.0)).sum();
+        let max_daling: f64 = (0..n_stappen)
+            .map(|s| (-verandering(s, 1.0)).max(0.0))
+            .sum();
+        let ws_min = waterstand.min(config.min_peil()) - max_daling.min(5.0) - WS_STAP;
+        let ws_max = waterstand.max(config.max_peil()) + max_stijging.min(5.0) + WS_STAP;
+        let n_niveaus = ((ws_max - ws_min) / WS_STAP).ceil() as usize + 1;
+        let index =
+            |ws: f64| (((ws - ws_min) / WS_STAP).round().max(0.0) as usize).min(n_niveaus - 1);
+        let niveau = |idx: usize| ws_min + idx as f64 * WS_STAP;
+        let penalty = |ws: f64, uren: f64| {
+            let buiten_cm =
+                ((config.min_peil() - ws).max(0.0) + (ws - config.max_peil()).max(0.0)) * 100.0;
+            buiten_cm * self.config.penalty_per_cm * uren
+        };
+        // Lineair geïnterpoleerd tussen de niveaus, zodat ook een langzame
+        // stijging van minder dan een halve stap meetelt
+        let interpoleer = |waarden: &[f64], ws: f64| {
+            let positie = ((ws - ws_min) / WS_STAP).clamp(0.0, (n_niveaus - 1) as f64);
+            let onder = positie.floor() as usize;
+            let boven = (onder + 1).min(n_niveaus - 1);
+            let fractie = positie - onder as f64;
+            waarden[onder] * (1.0 - fractie) + waarden[boven] * fractie
+        };
+
+        // Backward pass: minimale resterende kosten per waterstand
+        let mut resterend: Vec<f64> = (0..n_niveaus)
+            .map(|idx| penalty(niveau(idx), 1.0))
+            .collect();
+        let mut beste: Vec<Vec<u8>> = vec![vec![0; n_niveaus]; n_stappen];
+        for stap in (0..n_stappen).rev() {
+            let veranderingen: Vec<f64> = POMP_FRACTIES
+                .iter()
+                .map(|&f| verandering(stap, f))
+                .collect();
+            let mut huidig = vec![f64::INFINITY; n_niveaus];
+            for (idx, kosten_idx) in huidig.iter_mut().enumerate() {
+                let ws = niveau(idx);
+                for (f, dws) in veranderingen.iter().enumerate() {
+                    let ws_eind = ws + dws;
+                    let totaal = kosten[stap][f]
+                        + penalty(ws_eind, stap_uren)
+                        + interpoleer(&resterend, ws_eind);
+                    if totaal < *kosten_idx {
+                        *kosten_idx = totaal;
+                        beste[stap][idx] = f as u8;
+                    }
+                }
+            }
+            resterend = huidig;
+        }
+
+        // Forward pass vanaf de actuele waterstand
+        let mut ws = waterstand;
+        (0..n_stappen)
+            .map(|stap| {
+                let f = beste[stap][index(ws)] as usize;
+                ws += verandering(stap, POMP_FRACTIES[f]);
+                POMP_FRACTIES[f]
+            })
+            .collect()
+    }
+
+    /// Pompfractie op minuut `tijd`: de eerste stap van het plan, dat
+    /// opnieuw berekend wordt zodra de regelstap voorbij is.
+    fn fractie_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        let stap_minuten = self.config.stap_minuten.max(1) as f64;
+        let mut plannen = self.plannen.lock().unwrap_or_else(|e| e.into_inner());
+        let geldig = plannen
+            .get(peilgebied_id)
+            .is_some_and(|plan| tijd >= plan.start && tijd < plan.start + stap_minuten);
+        if !geldig {
+            let fracties =
+                self.bereken_plan(tijd, peilgebied_id, waterstand, config, inkomend_debiet);
+            plannen.insert(
+                peilgebied_id.to_string(),
+                Plan {
+                    start: tijd,
+                    fracties,
+                },
+            );
+        }
+        plannen[peilgebied_id]
+            .fracties
+            .first()
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+impl UitstroomStrategy for MpcRegelaar {
+    /// Zonder tijd geldt het begin van de voorspellingen.
+    fn bepaal_uitstroom(
+        &self,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.bepaal_uitstroom_op(
+            0.0,
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_uitstroom_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        _regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.fractie_op(tijd, peilgebied_id, waterstand, config, inkomend_debiet)
+            * config.max_uitstroom_debiet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{NetwerkTopologie, SimpeleUitstroomStrategy, run_netwerksimulatie};
+
+    fn polder() -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: "polder".to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.05,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 1.0,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        }
+    }
+
+    fn mpc(prijzen: Vec<f64>, regen: Vec<f64>) -> MpcRegelaar {
+        MpcRegelaar::nieuw(MpcConfig {
+            horizon_uren: 12,
+            ..Default::default()
+        })
+        .met_prijzen(prijzen)
+        .met_regen("polder".to_string(), regen)
+    }
+
+    #[test]
+    fn test_plan_voorgemaald_voor_regen() {
+        // Bui na 4 uur die groter is dan marge plus gemaalcapaciteit: het
+        // plan pompt al vóór de bui
+        let mut regen = vec![0.0; 12];
+        regen[4] = 80.0;
+        regen[5] = 80.0;
+        let plan =
+            mpc(vec![0.10; 12], regen.clone()).bereken_plan(0.0, "polder", -0.60, &polder(), 0.0);
+        assert_eq!(plan.len(), 12 * 4);
+        assert!(plan[..16].iter().sum::<f64>() > 0.0);
+
+        // Zonder regen binnen de marge hoeft er niet gepompt te worden
+        let plan =
+            mpc(vec![0.10; 12], vec![0.0; 12]).bereken_plan(0.0, "polder", -0.60, &polder(), 0.0);
+        assert!(plan.iter().all(|&f| f == 0.0));
+    }
+
+    #[test]
+    fn test_plan_pompt_in_goedkope_uren() {
+        // Kwel duwt het peil over de bovenkant van de marge; de eerste uren
+        // zijn duur
+        let config = PeilgebiedConfig {
+            infiltratie: -2.0,
+            ..polder()
+        };
+        let mut prijzen = vec![0.50; 12];
+        prijzen[3] = 0.01;
+        let plan = mpc(prijzen, vec![0.0; 12]).bereken_plan(0.0, "polder", -0.56, &config, 0.0);
+        let duur: f64 = plan[..12].iter().sum();
+        let goedkoop: f64 = plan[12..16].iter().sum();
+        assert!(goedkoop > duur);
+    }
+
+    #[test]
+    fn test_mpc_in_netwerksimulatie() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie.voeg_peilgebied_toe(polder()).unwrap();
+        let mut regen = vec![0.0; 6];
+        regen[2] = 40.0;
+        let regen_scenario = HashMap::from([("polder".to_string(), regen.clone())]);
+
+        let regelaar = mpc(vec![0.10; 6], regen);
+        let resultaat = run_netwerksimulatie(&topologie, &regen_scenario, 6, &regelaar).unwrap();
+        let max_mpc = resultaat
+            .tijdstappen
+            .iter()
+            .map(|t| t.statussen["polder"].waterstand)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let simpel =
+            run_netwerksimulatie(&topologie, &regen_scenario, 6, &SimpeleUitstroomStrategy)
+                .unwrap();
+        assert_eq!(resultaat.tijdstappen.len(), simpel.tijdstappen.len());
+        assert!(max_mpc <= polder().max_peil() + 0.01);
+    }
+}
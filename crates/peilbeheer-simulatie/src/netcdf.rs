@@ -0,0 +1,707 @@
+//! NetCDF-export van netwerksimulaties volgens de CF-conventies.
+//!
+//! Schrijft het klassieke NetCDF-formaat met 64-bit offsets (CDF-2), dat
+//! elke NetCDF-bibliotheek kan lezen, zonder afhankelijkheid van libnetcdf.
+//! Het bestand heeft één dimensie `time` met de tijdstappen als minuten
+//! sinds de starttijd, en per peilgebied en per verbinding een variabele
+//! per grootheid, zoals `polder_a_waterstand` en `verbinding_v1_debiet`.
+//! Elke variabele heeft `units`, `long_name` en waar CF er een kent een
+//! `standard_name`; ontbrekende waarden krijgen de standaard `_FillValue`.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::export::ExportFout;
+use crate::netwerk::{NetwerkSimulatieResultaat, PeilgebiedId, PeilgebiedStatus, VerbindingId};
+
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+const NC_BYTE: u32 = 1;
+const NC_CHAR: u32 = 2;
+const NC_DOUBLE: u32 = 6;
+
+/// Standaard `_FillValue` van NetCDF voor doubles
+const FILL_DOUBLE: f64 = 9.969_209_968_386_869e36;
+/// Standaard `_FillValue` van NetCDF voor bytes
+const FILL_BYTE: i8 = -127;
+
+/// NetCDF export van tijdstappen.
+pub struct NetcdfExport {
+    starttijd: DateTime<Utc>,
+    titel: String,
+}
+
+/// Waarde van een attribuut.
+enum Attribuut {
+    Tekst(String),
+    Double(f64),
+    Bytes(Vec<i8>),
+}
+
+/// Waarden van een variabele langs `time`.
+enum Data {
+    Double(Vec<f64>),
+    Byte(Vec<i8>),
+}
+
+struct Variabele {
+    naam: String,
+    attributen: Vec<(&'static str, Attribuut)>,
+    data: Data,
+}
+
+impl NetcdfExport {
+    /// Maak een NetCDF export; `tijd` 0 van de simulatie valt op
+    /// `starttijd`.
+    pub fn nieuw(starttijd: DateTime<Utc>) -> Self {
+        Self {
+            starttijd,
+            titel: "Netwerksimulatie peilbeheer".to_string(),
+        }
+    }
+
+    /// Stel de titel (globaal attribuut `title`) in.
+    pub fn met_titel(mut self, titel: impl Into<String>) -> Self {
+        self.titel = titel.into();
+        self
+    }
+
+    /// Exporteer tijdstappen als NetCDF-bestand in het geheugen.
+    pub fn als_bytes(&self, resultaat: &NetwerkSimulatieResultaat) -> Result<Vec<u8>, ExportFout> {
+        if resultaat.tijdstappen.is_empty() {
+            return Err(ExportFout::GeenData);
+        }
+
+        let variabelen = self.variabelen(resultaat);
+        let mut namen = HashSet::new();
+        if let Some(dubbel) = variabelen.iter().find(|v| !namen.insert(&v.naam)) {
+            return Err(ExportFout::OngeldigFormaat {
+                formaat: format!("NetCDF-variabele {} komt dubbel voor", dubbel.naam),
+            });
+        }
+
+        let globaal = vec![
+            ("Conventions", Attribuut::Tekst("CF-1.8".to_string())),
+            ("title", Attribuut::Tekst(self.titel.clone())),
+            (
+                "source",
+                Attribuut::Tekst("peilbeheer-simulatie".to_string()),
+            ),
+        ];
+
+        // De header heeft een vaste lengte, dus eerst met nul-offsets
+        // schrijven om te weten waar de data begint
+        let tijdstappen = resultaat.tijdstappen.len();
+        let nul = vec![0; variabelen.len()];
+        let lengte = header(tijdstappen, &globaal, &variabelen, &nul).len() as u64;
+        let mut begin = Vec::with_capacity(variabelen.len());
+        let mut offset = lengte;
+        for variabele in &variabelen {
+            begin.push(offset);
+            offset += variabele.data.grootte() as u64;
+        }
+
+        let mut bytes = header(tijdstappen, &globaal, &variabelen, &begin);
+        for variabele in &variabelen {
+            variabele.data.schrijf(&mut bytes);
+        }
+        Ok(bytes)
+    }
+
+    /// Exporteer tijdstappen naar een NetCDF-bestand.
+    pub fn naar_bestand<P: AsRef<Path>>(
+        &self,
+        resultaat: &NetwerkSimulatieResultaat,
+        pad: P,
+    ) -> Result<(), ExportFout> {
+        let inhoud = self.als_bytes(resultaat)?;
+
+        std::fs::write(pad.as_ref(), inhoud).map_err(|e| ExportFout::SchrijvenMislukt {
+            pad: pad.as_ref().display().to_string(),
+            reden: e.to_string(),
+        })?;
+
+        Ok(())
+    }
+
+    fn variabelen(&self, resultaat: &NetwerkSimulatieResultaat) -> Vec<Variabele> {
+        let stappen = &resultaat.tijdstappen;
+        let mut variabelen = vec![Variabele {
+            naam: "time".to_string(),
+            attributen: vec![
+                ("standard_name", Attribuut::Tekst("time".to_string())),
+                ("long_name", Attribuut::Tekst("tijd".to_string())),
+                (
+                    "units",
+                    Attribuut::Tekst(format!(
+                        "minutes since {}",
+                        self.starttijd.format("%Y-%m-%d %H:%M:%S")
+                    )),
+                ),
+                ("calendar", Attribuut::Tekst("standard".to_string())),
+                ("axis", Attribuut::Tekst("T".to_string())),
+            ],
+            data: Data::Double(stappen.iter().map(|s| s.tijd).collect()),
+        }];
+
+        let peilgebied_ids: BTreeSet<&PeilgebiedId> =
+            stappen.iter().flat_map(|s| s.statussen.keys()).collect();
+        for id in peilgebied_ids {
+            let status = |waarde: fn(&PeilgebiedStatus) -> f64| {
+                stappen
+                    .iter()
+                    .map(|s| s.statussen.get(id).map(waarde))
+                    .collect::<Vec<_>>()
+            };
+            let naam = |grootheid: &str| format!("{}_{}", variabelenaam(id), grootheid);
+            let getal = |grootheid: &str,
+                         waarden: Vec<Option<f64>>,
+                         lang: &str,
+                         units: &str,
+                         cf: Option<&str>| {
+                Variabele::getal(naam(grootheid), waarden, lang, units, cf)
+                    .met_id("peilgebied_id", id)
+            };
+
+            variabelen.extend([
+                getal(
+                    "waterstand",
+                    status(|s| s.waterstand),
+                    "waterstand",
+                    "m",
+                    Some("water_surface_height_above_reference_datum"),
+                )
+                .met("comment", Attribuut::Tekst("m NAP".to_string())),
+                getal(
+                    "inkomend_debiet",
+                    status(|s| s.inkomend_debiet),
+                    "inkomend debiet uit verbindingen",
+                    "m3 s-1",
+                    None,
+                ),
+                getal(
+                    "uitgaand_debiet",
+                    status(|s| s.uitgaand_debiet),
+                    "uitgaand debiet naar verbindingen",
+                    "m3 s-1",
+                    None,
+                ),
+                getal(
+                    "uitstroom_debiet",
+                    status(|s| s.uitstroom_debiet),
+                    "uitstroom via het gemaal",
+                    "m3 s-1",
+                    None,
+                ),
+                getal(
+                    "inlaat_debiet",
+                    status(|s| s.inlaat_debiet),
+                    "inlaat van buiten het netwerk",
+                    "m3 s-1",
+                    None,
+                ),
+                getal(
+                    "regen_intensiteit",
+                    status(|s| s.regen_intensiteit),
+                    "neerslagintensiteit",
+                    "mm h-1",
+                    Some("lwe_precipitation_rate"),
+                ),
+                Variabele {
+                    naam: naam("pomp_actief"),
+                    attributen: vec![
+                        (
+                            "long_name",
+                            Attribuut::Tekst("gemaal in bedrijf".to_string()),
+                        ),
+                        ("_FillValue", Attribuut::Bytes(vec![FILL_BYTE])),
+                        ("flag_values", Attribuut::Bytes(vec![0, 1])),
+                        ("flag_meanings", Attribuut::Tekst("uit aan".to_string())),
+                    ],
+                    data: Data::Byte(
+                        stappen
+                            .iter()
+                            .map(|s| {
+                                s.statussen
+                                    .get(id)
+                                    .map_or(FILL_BYTE, |s| i8::from(s.pomp_actief))
+                            })
+                            .collect(),
+                    ),
+                }
+                .met_id("peilgebied_id", id),
+            ]);
+
+            if stappen.iter().any(|s| s.chloride.contains_key(id)) {
+                variabelen.push(getal(
+                    "chloride",
+                    stappen
+                        .iter()
+                        .map(|s| s.chloride.get(id).copied())
+                        .collect(),
+                    "chlorideconcentratie",
+                    "mg l-1",
+                    None,
+                ));
+            }
+        }
+
+        let verbinding_ids: BTreeSet<&VerbindingId> = stappen
+            .iter()
+            .flat_map(|s| {
+                s.stromen
+                    .iter()
+                    .map(|stroom| &stroom.verbinding_id)
+                    .chain(s.kruinhoogten.keys())
+            })
+            .collect();
+        for id in verbinding_ids {
+            let naam = |grootheid: &str| format!("verbinding_{}_{}", variabelenaam(id), grootheid);
+            let debiet = stappen
+                .iter()
+                .map(|s| {
+                    s.stromen
+                        .iter()
+                        .find(|stroom| &stroom.verbinding_id == id)
+                        .map(|stroom| stroom.debiet)
+                })
+                .collect();
+            variabelen.push(
+                Variabele::getal(
+                    naam("debiet"),
+                    debiet,
+                    "debiet, positief van het begin- naar het eindpeilgebied",
+                    "m3 s-1",
+                    None,
+                )
+                .met_id("verbinding_id", id),
+            );
+            if stappen.iter().any(|s| s.kruinhoogten.contains_key(id)) {
+                variabelen.push(
+                    Variabele::getal(
+                        naam("kruinhoogte"),
+                        stappen
+                            .iter()
+                            .map(|s| s.kruinhoogten.get(id).copied())
+                            .collect(),
+                        "kruinhoogte van de stuw",
+                        "m",
+                        None,
+                    )
+                    .met_id("verbinding_id", id),
+                );
+            }
+        }
+
+        variabelen
+    }
+}
+
+impl Variabele {
+    /// Double-variabele met CF-attributen; ontbrekende waarden worden
+    /// `_FillValue`.
+    fn getal(
+        naam: String,
+        waarden: Vec<Option<f64>>,
+        long_name: &str,
+        units: &str,
+        standard_name: Option<&str>,
+    ) -> Self {
+        let mut attributen = Vec::new();
+        if let Some(standard_name) = standard_name {
+            attributen.push(("standard_name", Attribuut::Tekst(standard_name.to_string())));
+        }
+        attributen.extend([
+            ("long_name", Attribuut::Tekst(long_name.to_string())),
+            ("units", Attribuut::Tekst(units.to_string())),
+            ("_FillValue", Attribuut::Double(FILL_DOUBLE)),
+        ]);
+        Self {
+            naam,
+            attributen,
+            data: Data::Double(
+                waarden
+                    .into_iter()
+                    .map(|w| w.unwrap_or(FILL_DOUBLE))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn met(mut self, naam: &'static str, waarde: Attribuut) -> Self {
+        self.attributen.push((naam, waarde));
+        self
+    }
+
+    fn met_id(self, naam: &'static str, id: &str) -> Self {
+        self.met(naam, Attribuut::Tekst(id.to_string()))
+    }
+}
+
+impl Attribuut {
+    fn schrijf(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::Tekst(tekst) => {
+                schrijf_u32(bytes, NC_CHAR);
+                schrijf_u32(bytes, tekst.len() as u32);
+                bytes.extend_from_slice(tekst.as_bytes());
+            }
+            Self::Double(waarde) => {
+                schrijf_u32(bytes, NC_DOUBLE);
+                schrijf_u32(bytes, 1);
+                bytes.extend_from_slice(&waarde.to_be_bytes());
+            }
+            Self::Bytes(waarden) => {
+                schrijf_u32(bytes, NC_BYTE);
+                schrijf_u32(bytes, waarden.len() as u32);
+                bytes.extend(waarden.iter().map(|w| *w as u8));
+            }
+        }
+        vul_aan(bytes);
+    }
+}
+
+impl Data {
+    fn nc_type(&self) -> u32 {
+        match self {
+            Self::Double(_) => NC_DOUBLE,
+            Self::Byte(_) => NC_BYTE,
+        }
+    }
+
+    /// Grootte in bytes, aangevuld tot een veelvoud van 4.
+    fn grootte(&self) -> usize {
+        match self {
+            Self::Double(waarden) => waarden.len() * 8,
+            Self::Byte(waarden) => waarden.len().next_multiple_of(4),
+        }
+    }
+
+    fn schrijf(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::Double(waarden) => {
+                for waarde in waarden {
+                    bytes.extend_from_slice(&waarde.to_be_bytes());
+                }
+            }
+            Self::Byte(waarden) => bytes.extend(waarden.iter().map(|w| *w as u8)),
+        }
+        vul_aan(bytes);
+    }
+}
+
+/// Header van een CDF-2-bestand met één vaste dimensie `time`; `begin` is
+/// de offset van de data per variabele.
+fn header(
+    tijdstappen: usize,
+    globaal: &[(&str, Attribuut)],
+    variabelen: &[Variabele],
+    begin: &[u64],
+) -> Vec<u8> {
+    let mut bytes = b"CDF\x02".to_vec();
+    // Geen recorddimensie
+    schrijf_u32(&mut bytes, 0);
+
+    schrijf_u32(&mut bytes, NC_DIMENSION);
+    schrijf_u32(&mut bytes, 1);
+    schrijf_naam(&mut bytes, "time");
+    schrijf_u32(&mut bytes, tijdstappen as u32);
+
+    schrijf_attributen(&mut bytes, globaal);
+
+    schrijf_u32(&mut bytes, NC_VARIABLE);
+    schrijf_u32(&mut bytes, variabelen.len() as u32);
+    for (variabele, begin) in variabelen.iter().zip(begin) {
+        schrijf_naam(&mut bytes, &variabele.naam);
+        // Eén dimensie: time
+        schrijf_u32(&mut bytes, 1);
+        schrijf_u32(&mut bytes, 0);
+        schrijf_attributen(&mut bytes, &variabele.attributen);
+        schrijf_u32(&mut bytes, variabele.data.nc_type());
+        schrijf_u32(
+            &mut bytes,
+            u32::try_from(variabele.data.grootte()).unwrap_or(u32::MAX),
+        );
+        bytes.extend_from_slice(&begin.to_be_bytes());
+    }
+    bytes
+}
+
+fn schrijf_attributen(bytes: &mut Vec<u8>, attributen: &[(&str, Attribuut)]) {
+    if attributen.is_empty() {
+        // ABSENT
+        schrijf_u32(bytes, 0);
+        schrijf_u32(bytes, 0);
+        return;
+    }
+    schrijf_u32(bytes, NC_ATTRIBUTE);
+    schrijf_u32(bytes, attributen.len() as u32);
+    for (naam, waarde) in attributen {
+        schrijf_naam(bytes, naam);
+        waarde.schrijf(bytes);
+    }
+}
+
+fn schrijf_naam(bytes: &mut Vec<u8>, naam: &str) {
+    schrijf_u32(bytes, naam.len() as u32);
+    bytes.extend_from_slice(naam.as_bytes());
+    vul_aan(bytes);
+}
+
+fn schrijf_u32(bytes: &mut Vec<u8>, waarde: u32) {
+    bytes.extend_from_slice(&waarde.to_be_bytes());
+}
+
+/// Vul aan met nullen tot een veelvoud van 4 bytes.
+fn vul_aan(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+}
+
+/// Geldige NetCDF-naam voor een id: tekens buiten letters, cijfers, `_`,
+/// `-` en `.` worden `_`, en een naam begint met een letter.
+fn variabelenaam(id: &str) -> String {
+    let naam: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if naam.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        naam
+    } else {
+        format!("id_{}", naam)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{NetwerkTijdstap, StroomRichting, VerbindingStroom};
+    use std::collections::HashMap;
+
+    /// Variabele zoals gelezen uit een CDF-2-bestand.
+    struct Gelezen {
+        naam: String,
+        attributen: HashMap<String, Vec<u8>>,
+        nc_type: u32,
+        begin: usize,
+    }
+
+    struct Lezer<'a> {
+        bytes: &'a [u8],
+        positie: usize,
+    }
+
+    impl Lezer<'_> {
+        fn u32(&mut self) -> u32 {
+            let waarde = u32::from_be_bytes(
+                self.bytes[self.positie..self.positie + 4]
+                    .try_into()
+                    .unwrap(),
+            );
+            self.positie += 4;
+            waarde
+        }
+
+        fn blok(&mut self, lengte: usize) -> Vec<u8> {
+            let blok = self.bytes[self.positie..self.positie + lengte].to_vec();
+            self.positie += lengte.next_multiple_of(4);
+            blok
+        }
+
+        fn naam(&mut self) -> String {
+            let lengte = self.u32() as usize;
+            String::from_utf8(self.blok(lengte)).unwrap()
+        }
+
+        fn attributen(&mut self) -> HashMap<String, Vec<u8>> {
+            let _tag = self.u32();
+            let aantal = self.u32();
+            (0..aantal)
+                .map(|_| {
+                    let naam = self.naam();
+                    let grootte = match self.u32() {
+                        NC_DOUBLE => 8,
+                        _ => 1,
+                    };
+                    let aantal = self.u32() as usize;
+                    (naam, self.blok(aantal * grootte))
+                })
+                .collect()
+        }
+    }
+
+    /// Lees de header volgens de specificatie van het klassieke formaat.
+    fn lees(bytes: &[u8]) -> (usize, HashMap<String, Vec<u8>>, Vec<Gelezen>) {
+        assert_eq!(&bytes[..4], b"CDF\x02");
+        let mut lezer = Lezer { bytes, positie: 4 };
+        assert_eq!(lezer.u32(), 0);
+        assert_eq!(lezer.u32(), NC_DIMENSION);
+        assert_eq!(lezer.u32(), 1);
+        assert_eq!(lezer.naam(), "time");
+        let tijdstappen = lezer.u32() as usize;
+        let globaal = lezer.attributen();
+        assert_eq!(lezer.u32(), NC_VARIABLE);
+        let variabelen = (0..lezer.u32())
+            .map(|_| {
+                let naam = lezer.naam();
+                assert_eq!((lezer.u32(), lezer.u32()), (1, 0));
+                let attributen = lezer.attributen();
+                let nc_type = lezer.u32();
+                let _vsize = lezer.u32();
+                let begin =
+                    u64::from_be_bytes(bytes[lezer.positie..lezer.positie + 8].try_into().unwrap());
+                lezer.positie += 8;
+                Gelezen {
+                    naam,
+                    attributen,
+                    nc_type,
+                    begin: begin as usize,
+                }
+            })
+            .collect();
+        (tijdstappen, globaal, variabelen)
+    }
+
+    fn doubles(bytes: &[u8], variabele: &Gelezen, aantal: usize) -> Vec<f64> {
+        assert_eq!(variabele.nc_type, NC_DOUBLE);
+        (0..aantal)
+            .map(|i| {
+                let start = variabele.begin + i * 8;
+                f64::from_be_bytes(bytes[start..start + 8].try_into().unwrap())
+            })
+            .collect()
+    }
+
+    fn tekst(variabele: &Gelezen, naam: &str) -> String {
+        String::from_utf8(variabele.attributen[naam].clone()).unwrap()
+    }
+
+    fn status(id: &str, waterstand: f64, pomp_actief: bool) -> PeilgebiedStatus {
+        PeilgebiedStatus {
+            id: id.to_string(),
+            waterstand,
+            inkomend_debiet: 0.0,
+            uitgaand_debiet: 0.0,
+            uitstroom_debiet: 0.2,
+            inlaat_debiet: 0.0,
+            regen_intensiteit: 5.0,
+            pomp_actief,
+        }
+    }
+
+    fn resultaat() -> NetwerkSimulatieResultaat {
+        let stap = |tijd: f64, waterstand: f64, met_b: bool| {
+            let mut statussen = HashMap::from([(
+                "polder a".to_string(),
+                status("polder a", waterstand, tijd > 1.0),
+            )]);
+            if met_b {
+                statussen.insert("polder_b".to_string(), status("polder_b", -0.7, false));
+            }
+            NetwerkTijdstap {
+                tijd,
+                duur: 1.0,
+                statussen,
+                stromen: vec![VerbindingStroom {
+                    verbinding_id: "v1".to_string(),
+                    debiet: -0.05,
+                    richting: StroomRichting::Terug,
+                    benutting: 0.1,
+                    actief: true,
+                }],
+                boezembelasting: HashMap::new(),
+                kruinhoogten: HashMap::new(),
+                chloride: HashMap::new(),
+            }
+        };
+        NetwerkSimulatieResultaat {
+            tijdstappen: vec![
+                stap(1.0, -0.5, true),
+                stap(2.0, -0.45, false),
+                stap(3.0, -0.4, true),
+            ],
+            totale_kosten: None,
+        }
+    }
+
+    #[test]
+    fn test_netcdf_cf_bestand() {
+        let starttijd = "2025-06-01T00:00:00Z".parse().unwrap();
+        let bytes = NetcdfExport::nieuw(starttijd)
+            .als_bytes(&resultaat())
+            .unwrap();
+        let (tijdstappen, globaal, variabelen) = lees(&bytes);
+        assert_eq!(tijdstappen, 3);
+        assert_eq!(globaal["Conventions"], b"CF-1.8");
+
+        let variabele = |naam: &str| variabelen.iter().find(|v| v.naam == naam).unwrap();
+        let tijd = variabele("time");
+        assert_eq!(tekst(tijd, "units"), "minutes since 2025-06-01 00:00:00");
+        assert_eq!(tekst(tijd, "standard_name"), "time");
+        assert_eq!(doubles(&bytes, tijd, 3), vec![1.0, 2.0, 3.0]);
+
+        // Id met spatie wordt een geldige naam; het id blijft als attribuut
+        let waterstand = variabele("polder_a_waterstand");
+        assert_eq!(tekst(waterstand, "peilgebied_id"), "polder a");
+        assert_eq!(tekst(waterstand, "units"), "m");
+        assert_eq!(
+            tekst(waterstand, "standard_name"),
+            "water_surface_height_above_reference_datum"
+        );
+        assert_eq!(doubles(&bytes, waterstand, 3), vec![-0.5, -0.45, -0.4]);
+
+        // Ontbrekend peilgebied in een tijdstap krijgt de _FillValue
+        let polder_b = variabele("polder_b_waterstand");
+        assert_eq!(doubles(&bytes, polder_b, 3), vec![-0.7, FILL_DOUBLE, -0.7]);
+
+        let pomp = variabele("polder_a_pomp_actief");
+        assert_eq!(pomp.nc_type, NC_BYTE);
+        assert_eq!(&bytes[pomp.begin..pomp.begin + 3], &[0, 1, 1]);
+        let pomp_b = variabele("polder_b_pomp_actief");
+        assert_eq!(bytes[pomp_b.begin + 1] as i8, FILL_BYTE);
+
+        let debiet = variabele("verbinding_v1_debiet");
+        assert_eq!(tekst(debiet, "units"), "m3 s-1");
+        assert_eq!(doubles(&bytes, debiet, 3), vec![-0.05; 3]);
+
+        // Zonder chloride en stuwen geen lege variabelen
+        assert!(variabelen.iter().all(|v| !v.naam.ends_with("chloride")));
+        assert!(variabelen.iter().all(|v| !v.naam.ends_with("kruinhoogte")));
+
+        // De data van de laatste variabele sluit het bestand af
+        let laatste = variabelen.iter().max_by_key(|v| v.begin).unwrap();
+        let grootte = if laatste.nc_type == NC_DOUBLE {
+            3 * 8
+        } else {
+            4
+        };
+        assert_eq!(laatste.begin + grootte, bytes.len());
+    }
+
+    #[test]
+    fn test_variabelenaam() {
+        assert_eq!(variabelenaam("polder_a"), "polder_a");
+        assert_eq!(variabelenaam("NL.14/pg 3"), "NL.14_pg_3");
+        assert_eq!(variabelenaam("12a"), "id_12a");
+    }
+
+    #[test]
+    fn test_zonder_data() {
+        let resultaat = NetwerkSimulatieResultaat {
+            tijdstappen: vec![],
+            totale_kosten: None,
+        };
+        assert_eq!(
+            NetcdfExport::nieuw(Utc::now()).als_bytes(&resultaat),
+            Err(ExportFout::GeenData)
+        );
+    }
+}
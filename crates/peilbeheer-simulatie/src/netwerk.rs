@@ -1,15 +1,26 @@
 //! Multi-peilgebied netwerksimulatie.
 //!
 //! Module voor het simuleren van waterstromen tussen verbonden peilgebieden.
-//! Ondersteunt verschillende connectietypen (pompen, duikers, overstorten),
-//! gecoördineerde regeling, en netwerktopologie.
+//! Ondersteunt verschillende connectietypen (pompen, duikers, overstorten,
+//! regelbare stuwen), gecoördineerde regeling, en netwerktopologie.
 
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use peilbeheer_core::bergingscurve::BergingsCurve;
+use peilbeheer_core::bodem::PeilgebiedBodem;
+use peilbeheer_core::energie::MIN_OPVOERHOOGTE;
+use peilbeheer_core::pompcurve::PompCurve;
+use peilbeheer_core::pompen::{self, Pomp};
+use peilbeheer_core::verdamping::{self, Dagwaarde};
+use peilbeheer_core::waterbalans::Kwelrelatie;
 
-use crate::waterbalans::calculate_water_balance;
+use crate::chloride::{self, ChlorideParameters};
+use crate::waterbalans::{calculate_water_balance, mm_per_uur_to_m3_per_sec};
 
 /// Unieke identificatie van een peilgebied in het netwerk.
 pub type PeilgebiedId = String;
@@ -17,6 +28,23 @@ pub type PeilgebiedId = String;
 /// Unieke identificatie van een verbinding.
 pub type VerbindingId = String;
 
+/// Maximum aantal iteraties voor consistente verbindingstromen per stap.
+const MAX_STROOM_ITERATIES: usize = 50;
+
+/// Convergentiecriterium voor de waterstanden aan het eind van een stap (m).
+const STROOM_TOLERANTIE: f64 = 1e-9;
+
+/// Debiet per peilgebied of boezem (m³/s).
+type DebietPerKnooppunt = HashMap<PeilgebiedId, f64>;
+
+/// Maalcapaciteit in mm/dag waaronder een peilgebied bij een flinke bui
+/// onder water loopt.
+const MIN_MAALCAPACITEIT: f64 = 5.0;
+
+/// Maalcapaciteit in mm/dag waarboven de capaciteit of de oppervlakte
+/// waarschijnlijk verkeerd is ingevoerd.
+const MAX_MAALCAPACITEIT: f64 = 50.0;
+
 /// Fouttype voor netwerksimulaties.
 #[derive(Debug, Clone, PartialEq)]
 pub enum NetwerkFout {
@@ -26,14 +54,32 @@ pub enum NetwerkFout {
     VerbindingNietGevonden { id: VerbindingId },
     /// Verbinding bestaat al
     VerbindingBestaatAl { id: VerbindingId },
-    /// Cyclische verbinding gedetecteerd
-    CyclischeVerbinding { van: PeilgebiedId, naar: PeilgebiedId },
     /// Ongeldige capaciteit
     OngeldigeCapaciteit { debiet: f64 },
     /// Ongeldige verbinding (van == naar)
     OngeldigeVerbinding { id: PeilgebiedId },
     /// Netwerk is niet verbonden (geïsoleerde componenten)
     NietVerbonden,
+    /// Ongeldige bergingscurve van een peilgebied
+    OngeldigeBergingscurve { id: PeilgebiedId, reden: String },
+    /// Ongeldige tijdstap of instellingen voor de adaptieve tijdstap
+    OngeldigeTijdstap { minuten: f64 },
+    /// Peilverandering ook bij de kleinste tijdstap te groot
+    InstabieleTijdstap {
+        peilgebied: PeilgebiedId,
+        tijd: f64,
+        peilverandering: f64,
+    },
+    /// Er bestaat al een peilgebied of boezem met dit id
+    KnooppuntBestaatAl { id: PeilgebiedId },
+    /// Ongeldige afmetingen of verliezen van een duiker
+    OngeldigeDuiker { id: VerbindingId, reden: String },
+    /// Ongeldig kruinhoogtebereik van een stuw
+    OngeldigeStuw { id: VerbindingId, reden: String },
+    /// Ongeldige chlorideparameters van een peilgebied
+    OngeldigeChloride { id: PeilgebiedId, reden: String },
+    /// Simulatie van buitenaf afgebroken
+    Geannuleerd { tijd: f64 },
     /// Constraint schending bij simulatie
     ConstraintSchending {
         peilgebied: PeilgebiedId,
@@ -55,9 +101,6 @@ impl fmt::Display for NetwerkFout {
             Self::VerbindingBestaatAl { id } => {
                 write!(f, "Verbinding bestaat al: {}", id)
             }
-            Self::CyclischeVerbinding { van, naar } => {
-                write!(f, "Cyclische verbinding: {} -> {}", van, naar)
-            }
             Self::OngeldigeCapaciteit { debiet } => {
                 write!(f, "Ongeldige capaciteit: {} m³/s (moet >= 0)", debiet)
             }
@@ -67,6 +110,38 @@ impl fmt::Display for NetwerkFout {
             Self::NietVerbonden => {
                 write!(f, "Netwerk is niet volledig verbonden")
             }
+            Self::OngeldigeBergingscurve { id, reden } => {
+                write!(f, "Ongeldige bergingscurve van {}: {}", id, reden)
+            }
+            Self::OngeldigeTijdstap { minuten } => {
+                write!(f, "Ongeldige tijdstap: {} minuten", minuten)
+            }
+            Self::InstabieleTijdstap {
+                peilgebied,
+                tijd,
+                peilverandering,
+            } => {
+                write!(
+                    f,
+                    "Instabiele tijdstap in {} op t={:.2} min: peilverandering {:.3} m",
+                    peilgebied, tijd, peilverandering
+                )
+            }
+            Self::KnooppuntBestaatAl { id } => {
+                write!(f, "Peilgebied of boezem bestaat al: {}", id)
+            }
+            Self::OngeldigeDuiker { id, reden } => {
+                write!(f, "Ongeldige duiker {}: {}", id, reden)
+            }
+            Self::OngeldigeStuw { id, reden } => {
+                write!(f, "Ongeldige stuw {}: {}", id, reden)
+            }
+            Self::OngeldigeChloride { id, reden } => {
+                write!(f, "Ongeldige chlorideparameters van {}: {}", id, reden)
+            }
+            Self::Geannuleerd { tijd } => {
+                write!(f, "Simulatie geannuleerd op t={:.2} min", tijd)
+            }
             Self::ConstraintSchending {
                 peilgebied,
                 waterstand,
@@ -89,7 +164,7 @@ impl std::error::Error for NetwerkFout {}
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum VerbindingType {
-    /// Gemaal/duiker: actieve watertransport met capaciteitsrestrictie
+    /// Gemaal: actieve watertransport met capaciteitsrestrictie
     Gemaal,
     /// Overstort: passieve stroming bij hoogwater boven drempel
     Overstort,
@@ -97,6 +172,12 @@ pub enum VerbindingType {
     Keerklep,
     /// Open verbinding: vrije stroming beide richtingen
     OpenVerbinding,
+    /// Duiker: passieve stroming beide richtingen met wrijvings- en
+    /// in- en uittredeverliezen
+    Duiker,
+    /// Stuw: overlaat waarvan de kruinhoogte per tijdstap door de strategy
+    /// versteld kan worden
+    Stuw,
 }
 
 impl VerbindingType {
@@ -107,12 +188,23 @@ impl VerbindingType {
 
     /// Of dit verbindingstype passieve stroming toestaat.
     pub fn is_passief(&self) -> bool {
-        matches!(self, Self::Overstort | Self::OpenVerbinding)
+        matches!(
+            self,
+            Self::Overstort | Self::OpenVerbinding | Self::Duiker | Self::Stuw
+        )
     }
 
     /// Of dit verbindingstype eenrichtingverkeer is.
     pub fn is_eenrichting(&self) -> bool {
-        matches!(self, Self::Gemaal | Self::Overstort | Self::Keerklep)
+        matches!(
+            self,
+            Self::Gemaal | Self::Overstort | Self::Keerklep | Self::Stuw
+        )
+    }
+
+    /// Of de strategy dit verbindingstype tijdens de simulatie kan regelen.
+    pub fn is_regelbaar(&self) -> bool {
+        matches!(self, Self::Gemaal | Self::Stuw)
     }
 }
 
@@ -129,24 +221,176 @@ pub struct Verbinding {
     pub naar_id: PeilgebiedId,
     /// Maximale debiet in m³/s
     pub capaciteit: f64,
-    /// Drempelpeil in m NAP voor overstort (alleen voor Overstort type)
+    /// Drempelpeil in m NAP voor overstort, of de actuele kruinhoogte van
+    /// een stuw
     #[serde(skip_serializing_if = "Option::is_none")]
     pub overstort_drempel: Option<f64>,
+    /// Kruinbreedte van een overstort in m
+    #[serde(default = "default_kruinbreedte")]
+    pub kruinbreedte: f64,
+    /// Afvoercoëfficiënt C van een overstort in m^0.5/s
+    #[serde(default = "default_afvoercoefficient")]
+    pub afvoercoefficient: f64,
     /// Hoogteverschil voor pompvermogenberekening (m)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub opvoerhoogte: Option<f64>,
     /// Efficiëntie van pompinstallatie (0-1)
     #[serde(default = "default_efficiency")]
     pub efficiency: f64,
+    /// Pompkarakteristiek van een gemaal; debiet en rendement volgen dan
+    /// uit de actuele opvoerhoogte
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pompcurve: Option<PompCurve>,
+    /// Afmetingen en verliezen van een duiker (alleen voor Duiker type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duiker: Option<DuikerParameters>,
+    /// Verstelbereik van een stuw (alleen voor Stuw type)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stuw: Option<StuwParameters>,
     /// Huidige stroomrichting (Some voor actieve regeling)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stroomrichting: Option<StroomRichting>,
 }
 
+/// Afmetingen, ruwheid en verliezen van een ronde duiker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DuikerParameters {
+    /// Binnendiameter in m
+    pub diameter: f64,
+    /// Lengte in m
+    pub lengte: f64,
+    /// Ruwheidscoëfficiënt van Manning in s/m^(1/3)
+    #[serde(default = "default_manning")]
+    pub manning: f64,
+    /// Intreeverliescoëfficiënt
+    #[serde(default = "default_intreeverlies")]
+    pub intreeverlies: f64,
+    /// Uittreeverliescoëfficiënt
+    #[serde(default = "default_uittreeverlies")]
+    pub uittreeverlies: f64,
+}
+
+/// Beton
+fn default_manning() -> f64 {
+    0.013
+}
+
+fn default_intreeverlies() -> f64 {
+    0.5
+}
+
+fn default_uittreeverlies() -> f64 {
+    1.0
+}
+
+impl DuikerParameters {
+    pub fn new(diameter: f64, lengte: f64) -> Self {
+        Self {
+            diameter,
+            lengte,
+            manning: default_manning(),
+            intreeverlies: default_intreeverlies(),
+            uittreeverlies: default_uittreeverlies(),
+        }
+    }
+
+    pub fn met_manning(mut self, manning: f64) -> Self {
+        self.manning = manning;
+        self
+    }
+
+    pub fn met_verliezen(mut self, intreeverlies: f64, uittreeverlies: f64) -> Self {
+        self.intreeverlies = intreeverlies;
+        self.uittreeverlies = uittreeverlies;
+        self
+    }
+
+    /// Controleer of de duiker bruikbaar is.
+    pub fn valideer(&self) -> Result<(), String> {
+        if !(self.diameter.is_finite() && self.diameter > 0.0) {
+            return Err(format!("diameter moet > 0 zijn, is {}", self.diameter));
+        }
+        if !(self.lengte.is_finite() && self.lengte >= 0.0) {
+            return Err(format!("lengte moet >= 0 zijn, is {}", self.lengte));
+        }
+        if !(self.manning.is_finite() && self.manning > 0.0) {
+            return Err(format!("ruwheid moet > 0 zijn, is {}", self.manning));
+        }
+        if self.intreeverlies < 0.0 || self.uittreeverlies < 0.0 {
+            return Err("in- en uittredeverlies moeten >= 0 zijn".into());
+        }
+        if self.verliescoefficient() <= 0.0 {
+            return Err("duiker zonder verliezen heeft geen begrensd debiet".into());
+        }
+        Ok(())
+    }
+
+    /// Som van de verliescoëfficiënten: intree, wrijving volgens Manning
+    /// (2g·n²·L / R^(4/3), met hydraulische straal R = D/4) en uittree.
+    pub fn verliescoefficient(&self) -> f64 {
+        let straal = self.diameter / 4.0;
+        let wrijving = 2.0 * 9.81 * self.manning.powi(2) * self.lengte / straal.powf(4.0 / 3.0);
+        self.intreeverlies + wrijving + self.uittreeverlies
+    }
+
+    /// Debiet in m³/s bij een verval in m over een volledig gevulde duiker:
+    /// Q = A·√(2g·Δh / Σξ).
+    pub fn debiet(&self, verval: f64) -> f64 {
+        let oppervlak = std::f64::consts::PI * self.diameter.powi(2) / 4.0;
+        oppervlak * (2.0 * 9.81 * verval.max(0.0) / self.verliescoefficient()).sqrt()
+    }
+}
+
+/// Verstelbereik van de kruin van een stuw.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StuwParameters {
+    /// Laagste kruinhoogte in m NAP
+    pub min_kruinhoogte: f64,
+    /// Hoogste kruinhoogte in m NAP
+    pub max_kruinhoogte: f64,
+}
+
+impl StuwParameters {
+    pub fn new(min_kruinhoogte: f64, max_kruinhoogte: f64) -> Self {
+        Self {
+            min_kruinhoogte,
+            max_kruinhoogte,
+        }
+    }
+
+    /// Controleer of het bereik bruikbaar is.
+    pub fn valideer(&self) -> Result<(), String> {
+        if !(self.min_kruinhoogte.is_finite() && self.max_kruinhoogte.is_finite()) {
+            return Err("kruinhoogtes moeten eindig zijn".into());
+        }
+        if self.min_kruinhoogte > self.max_kruinhoogte {
+            return Err(format!(
+                "laagste kruinhoogte {} ligt boven hoogste {}",
+                self.min_kruinhoogte, self.max_kruinhoogte
+            ));
+        }
+        Ok(())
+    }
+
+    /// Kruinhoogte begrensd tot het verstelbereik.
+    pub fn begrens(&self, kruinhoogte: f64) -> f64 {
+        kruinhoogte.clamp(self.min_kruinhoogte, self.max_kruinhoogte)
+    }
+}
+
 fn default_efficiency() -> f64 {
     0.70
 }
 
+fn default_kruinbreedte() -> f64 {
+    1.0
+}
+
+/// Afvoercoëfficiënt van een brede-kruinoverlaat: (2/3)·√(2g/3)
+fn default_afvoercoefficient() -> f64 {
+    1.70
+}
+
 impl Verbinding {
     /// Maak een nieuwe gemaalverbinding.
     pub fn nieuw_gemaal(
@@ -171,7 +415,12 @@ impl Verbinding {
             capaciteit,
             overstort_drempel: None,
             opvoerhoogte: Some(opvoerhoogte),
+            kruinbreedte: default_kruinbreedte(),
+            afvoercoefficient: default_afvoercoefficient(),
             efficiency: default_efficiency(),
+            pompcurve: None,
+            duiker: None,
+            stuw: None,
             stroomrichting: Some(StroomRichting::Naar),
         })
     }
@@ -199,7 +448,12 @@ impl Verbinding {
             capaciteit,
             overstort_drempel: Some(drempel),
             opvoerhoogte: None,
+            kruinbreedte: default_kruinbreedte(),
+            afvoercoefficient: default_afvoercoefficient(),
             efficiency: default_efficiency(),
+            pompcurve: None,
+            duiker: None,
+            stuw: None,
             stroomrichting: None,
         })
     }
@@ -226,22 +480,152 @@ impl Verbinding {
             capaciteit,
             overstort_drempel: None,
             opvoerhoogte: None,
+            kruinbreedte: default_kruinbreedte(),
+            afvoercoefficient: default_afvoercoefficient(),
             efficiency: default_efficiency(),
+            pompcurve: None,
+            duiker: None,
+            stuw: None,
             stroomrichting: Some(StroomRichting::Naar),
         })
     }
 
-    /// Bereken pompvermogen in kW.
-    pub fn pompvermogen_kw(&self, debiet_m3s: f64) -> Option<f64> {
+    /// Maak een nieuwe duikerverbinding.
+    pub fn nieuw_duiker(
+        id: VerbindingId,
+        van_id: PeilgebiedId,
+        naar_id: PeilgebiedId,
+        capaciteit: f64,
+        duiker: DuikerParameters,
+    ) -> Result<Self, NetwerkFout> {
+        if van_id == naar_id {
+            return Err(NetwerkFout::OngeldigeVerbinding { id: van_id });
+        }
+        if capaciteit < 0.0 {
+            return Err(NetwerkFout::OngeldigeCapaciteit { debiet: capaciteit });
+        }
+        duiker
+            .valideer()
+            .map_err(|reden| NetwerkFout::OngeldigeDuiker { id: id.clone(), reden })?;
+
+        Ok(Self {
+            id,
+            verbinding_type: VerbindingType::Duiker,
+            van_id,
+            naar_id,
+            capaciteit,
+            overstort_drempel: None,
+            kruinbreedte: default_kruinbreedte(),
+            afvoercoefficient: default_afvoercoefficient(),
+            opvoerhoogte: None,
+            efficiency: default_efficiency(),
+            pompcurve: None,
+            duiker: Some(duiker),
+            stuw: None,
+            stroomrichting: None,
+        })
+    }
+
+    /// Maak een nieuwe stuw met een begin-kruinhoogte en verstelbereik.
+    pub fn nieuw_stuw(
+        id: VerbindingId,
+        van_id: PeilgebiedId,
+        naar_id: PeilgebiedId,
+        capaciteit: f64,
+        kruinhoogte: f64,
+        stuw: StuwParameters,
+    ) -> Result<Self, NetwerkFout> {
+        if van_id == naar_id {
+            return Err(NetwerkFout::OngeldigeVerbinding { id: van_id });
+        }
+        if capaciteit < 0.0 {
+            return Err(NetwerkFout::OngeldigeCapaciteit { debiet: capaciteit });
+        }
+        stuw.valideer()
+            .map_err(|reden| NetwerkFout::OngeldigeStuw { id: id.clone(), reden })?;
+        if stuw.begrens(kruinhoogte) != kruinhoogte {
+            return Err(NetwerkFout::OngeldigeStuw {
+                id,
+                reden: format!("kruinhoogte {} buiten verstelbereik", kruinhoogte),
+            });
+        }
+
+        Ok(Self {
+            id,
+            verbinding_type: VerbindingType::Stuw,
+            van_id,
+            naar_id,
+            capaciteit,
+            overstort_drempel: Some(kruinhoogte),
+            kruinbreedte: default_kruinbreedte(),
+            afvoercoefficient: default_afvoercoefficient(),
+            opvoerhoogte: None,
+            efficiency: default_efficiency(),
+            pompcurve: None,
+            duiker: None,
+            stuw: Some(stuw),
+            stroomrichting: None,
+        })
+    }
+
+    /// Stel de kruinbreedte (m) en afvoercoëfficiënt (m^0.5/s) van een
+    /// overstort in.
+    pub fn met_overlaat(mut self, kruinbreedte: f64, afvoercoefficient: f64) -> Self {
+        self.kruinbreedte = kruinbreedte;
+        self.afvoercoefficient = afvoercoefficient;
+        self
+    }
+
+    /// Debiet over een overstort in m³/s volgens de brede-kruinoverlaat,
+    /// begrensd door de capaciteit.
+    ///
+    /// Bij volkomen afvoer is Q = C·B·h³ᐟ², met h de bovenstroomse
+    /// overstorthoogte boven de kruin. Boven een benedenstroomse
+    /// overstorthoogte van 2/3·h is de overlaat verdronken en geldt
+    /// Q = C·(3√3/2)·B·h₂·√(h − h₂), dat daar aansluit op volkomen afvoer.
+    pub fn overlaatdebiet(&self, waterstand_van: f64, waterstand_naar: f64) -> f64 {
+        let Some(drempel) = self.overstort_drempel else {
+            return 0.0;
+        };
+        let h = waterstand_van - drempel;
+        if h <= 0.0 || waterstand_naar >= waterstand_van {
+            return 0.0;
+        }
+        let h2 = (waterstand_naar - drempel).max(0.0);
+        let debiet = if h2 <= 2.0 / 3.0 * h {
+            self.afvoercoefficient * self.kruinbreedte * h.powf(1.5)
+        } else {
+            self.afvoercoefficient * 1.5 * 3f64.sqrt() * self.kruinbreedte * h2 * (h - h2).sqrt()
+        };
+        debiet.min(self.capaciteit)
+    }
+
+    /// Stel de pompkarakteristiek in.
+    pub fn met_pompcurve(mut self, pompcurve: PompCurve) -> Self {
+        self.pompcurve = Some(pompcurve);
+        self
+    }
+
+    /// Debiet van een gemaal bij volle inzet en een opvoerhoogte (m³/s):
+    /// uit de pompcurve, begrensd door de capaciteit, anders de capaciteit.
+    pub fn pompdebiet(&self, opvoerhoogte: f64) -> f64 {
+        match &self.pompcurve {
+            Some(curve) => curve.debiet(opvoerhoogte).min(self.capaciteit),
+            None => self.capaciteit,
+        }
+    }
+
+    /// Bereken pompvermogen in kW bij een actuele opvoerhoogte, met het
+    /// rendement uit de pompcurve als die het geeft.
+    pub fn pompvermogen_bij_kw(&self, debiet_m3s: f64, opvoerhoogte: f64) -> Option<f64> {
         if !self.verbinding_type.is_actief() {
             return None;
         }
 
-        let opvoerhoogte = self.opvoerhoogte?;
-        let eff = if self.efficiency > 0.0 {
-            self.efficiency
-        } else {
-            0.70
+        let eff = match self.pompcurve.as_ref().and_then(|c| c.rendement(opvoerhoogte)) {
+            Some(rendement) => rendement,
+            None if self.efficiency > 0.0 => self.efficiency,
+            None => 0.70,
         };
 
         // P = ρ × g × Q × H / η
@@ -280,18 +664,47 @@ pub struct PeilgebiedConfig {
     /// Maximale pomp capaciteit naar buiten (m³/s)
     #[serde(default)]
     pub max_uitstroom_debiet: f64,
-    /// Verdamping in mm/uur
+    /// Maximale inlaatcapaciteit vanuit de boezem (m³/s)
+    #[serde(default)]
+    pub max_inlaat_debiet: f64,
+    /// Verdamping in mm/uur als er geen `referentieverdamping` is
     #[serde(default)]
     pub verdamping: f64,
-    /// Infiltratie in mm/uur
+    /// Referentieverdamping per uur vanaf de start in mm/uur, bijv. volgens
+    /// Makkink; het laatste uur geldt daarna
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub referentieverdamping: Vec<f64>,
+    /// Gewasfactor waarmee de referentieverdamping de verdamping van het
+    /// peilgebied wordt
+    #[serde(default = "default_gewasfactor")]
+    pub gewasfactor: f64,
+    /// Infiltratie in mm/uur, negatief bij netto kwel
     #[serde(default)]
     pub infiltratie: f64,
+    /// Kwel of wegzijging afhankelijk van het peilverschil met de boezem
+    /// of het grondwater, bovenop de vaste `infiltratie`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kwel: Option<Kwelrelatie>,
+    /// Bergingscurve; zonder curve bergt de hele `oppervlakte`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bergingscurve: Option<BergingsCurve>,
+    /// Boezem waarop het gemaal uitslaat en waaruit wordt ingelaten; zonder
+    /// boezem verdwijnt de uitstroom uit het netwerk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boezem: Option<PeilgebiedId>,
+    /// Beginconcentratie en zoute kwel voor de chloridebalans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chloride: Option<ChlorideParameters>,
 }
 
 fn default_marge() -> f64 {
     0.20
 }
 
+fn default_gewasfactor() -> f64 {
+    1.0
+}
+
 impl PeilgebiedConfig {
     /// Bereken minimaal toelaatbaar peil.
     pub fn min_peil(&self) -> f64 {
@@ -307,6 +720,123 @@ impl PeilgebiedConfig {
     pub fn is_waterstand_geldig(&self, waterstand: f64) -> bool {
         waterstand >= self.min_peil() && waterstand <= self.max_peil()
     }
+
+    /// Neem de netto infiltratie over uit de bodem van het peilgebied.
+    pub fn met_bodem(mut self, bodem: &PeilgebiedBodem) -> Self {
+        self.infiltratie = bodem.parameters.netto_infiltratie();
+        self
+    }
+
+    /// Verdamping in mm/uur op `tijd` (minuten sinds de start): de
+    /// referentieverdamping maal de gewasfactor, anders de vaste `verdamping`.
+    pub fn verdamping_op(&self, tijd: f64) -> f64 {
+        let uur = (tijd.max(0.0) / 60.0) as usize;
+        match self.referentieverdamping.get(uur).or(self.referentieverdamping.last()) {
+            Some(referentie) => referentie * self.gewasfactor,
+            None => self.verdamping,
+        }
+    }
+
+    /// Neem de Makkink-verdamping uit KNMI-dagwaarden over voor `uren` uur
+    /// vanaf `start`.
+    pub fn met_makkink(mut self, dagwaarden: &[Dagwaarde], start: DateTime<Utc>, uren: usize) -> Self {
+        self.referentieverdamping = verdamping::referentieverdamping_per_uur(dagwaarden, start, uren);
+        self
+    }
+
+    /// Stel de gewasfactor in.
+    pub fn met_gewasfactor(mut self, gewasfactor: f64) -> Self {
+        self.gewasfactor = gewasfactor;
+        self
+    }
+
+    /// Stel een peilverschilafhankelijke kwel of wegzijging in.
+    pub fn met_kwel(mut self, kwel: Kwelrelatie) -> Self {
+        self.kwel = Some(kwel);
+        self
+    }
+
+    /// Stel een bergingscurve in.
+    pub fn met_bergingscurve(mut self, bergingscurve: BergingsCurve) -> Self {
+        self.bergingscurve = Some(bergingscurve);
+        self
+    }
+
+    /// Laat het gemaal uitslaan op een boezem van het netwerk.
+    pub fn met_boezem(mut self, boezem: impl Into<PeilgebiedId>) -> Self {
+        self.boezem = Some(boezem.into());
+        self
+    }
+
+    /// Neem het peilgebied op in de chloridebalans.
+    pub fn met_chloride(mut self, chloride: ChlorideParameters) -> Self {
+        self.chloride = Some(chloride);
+        self
+    }
+
+    /// Bergend oppervlak in m² bij een waterstand.
+    pub fn bergend_oppervlak(&self, waterstand: f64) -> f64 {
+        match &self.bergingscurve {
+            Some(curve) => curve.oppervlak(waterstand),
+            None => self.oppervlakte,
+        }
+    }
+}
+
+/// Boezem: randknooppunt van het netwerk met een opgelegd peil en
+/// onbeperkte berging. Het peil verandert niet door wat polders uitslaan of
+/// inlaten; die stromen tellen op in de boezembelasting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Boezem {
+    /// Unieke identificatie, naast die van de peilgebieden
+    pub id: PeilgebiedId,
+    /// Naam van de boezem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub naam: Option<String>,
+    /// Boezempeil in m NAP als er geen `peilverloop` is
+    pub peil: f64,
+    /// Boezempeil per uur vanaf de start in m NAP; het laatste uur geldt
+    /// daarna
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peilverloop: Vec<f64>,
+    /// Chlorideconcentratie van het boezemwater in mg/l
+    #[serde(default)]
+    pub chloride: f64,
+}
+
+impl Boezem {
+    /// Boezem met een vast peil.
+    pub fn nieuw(id: impl Into<PeilgebiedId>, peil: f64) -> Self {
+        Self {
+            id: id.into(),
+            naam: None,
+            peil,
+            peilverloop: Vec::new(),
+            chloride: 0.0,
+        }
+    }
+
+    /// Stel de chlorideconcentratie van het boezemwater in (mg/l).
+    pub fn met_chloride(mut self, chloride: f64) -> Self {
+        self.chloride = chloride;
+        self
+    }
+
+    /// Stel een tijdsafhankelijk peil in (m NAP per uur).
+    pub fn met_peilverloop(mut self, peilverloop: Vec<f64>) -> Self {
+        self.peilverloop = peilverloop;
+        self
+    }
+
+    /// Boezempeil op `tijd` (minuten sinds de start).
+    pub fn peil_op(&self, tijd: f64) -> f64 {
+        let uur = (tijd.max(0.0) / 60.0) as usize;
+        self.peilverloop
+            .get(uur)
+            .or(self.peilverloop.last())
+            .copied()
+            .unwrap_or(self.peil)
+    }
 }
 
 /// Status van één peilgebied op een tijdstip.
@@ -322,6 +852,9 @@ pub struct PeilgebiedStatus {
     pub uitgaand_debiet: f64,
     /// Uitstroom debiet naar boezem/externe watergang (m³/s)
     pub uitstroom_debiet: f64,
+    /// Inlaat debiet vanuit de boezem (m³/s)
+    #[serde(default)]
+    pub inlaat_debiet: f64,
     /// Regenintensiteit (mm/uur)
     pub regen_intensiteit: f64,
     /// Is de uitstroompomp actief?
@@ -350,6 +883,9 @@ pub struct NetwerkTopologie {
     pub peilgebieden: HashMap<PeilgebiedId, PeilgebiedConfig>,
     /// Alle verbindingen
     pub verbindingen: HashMap<VerbindingId, Verbinding>,
+    /// Boezems als randknooppunten
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub boezems: HashMap<PeilgebiedId, Boezem>,
 }
 
 impl NetwerkTopologie {
@@ -358,7 +894,22 @@ impl NetwerkTopologie {
         Self {
             peilgebieden: HashMap::new(),
             verbindingen: HashMap::new(),
+            boezems: HashMap::new(),
+        }
+    }
+
+    /// Voeg een boezem toe.
+    pub fn voeg_boezem_toe(&mut self, boezem: Boezem) -> Result<(), NetwerkFout> {
+        if self.peilgebieden.contains_key(&boezem.id) {
+            return Err(NetwerkFout::KnooppuntBestaatAl { id: boezem.id });
         }
+        self.boezems.insert(boezem.id.clone(), boezem);
+        Ok(())
+    }
+
+    /// Of een id een peilgebied of boezem van het netwerk is.
+    pub fn bevat_knooppunt(&self, id: &str) -> bool {
+        self.peilgebieden.contains_key(id) || self.boezems.contains_key(id)
     }
 
     /// Voeg een peilgebied toe.
@@ -368,6 +919,25 @@ impl NetwerkTopologie {
                 debiet: config.oppervlakte,
             });
         }
+        if self.boezems.contains_key(&config.id) {
+            return Err(NetwerkFout::KnooppuntBestaatAl { id: config.id });
+        }
+        if let Some(curve) = &config.bergingscurve {
+            curve
+                .valideer()
+                .map_err(|reden| NetwerkFout::OngeldigeBergingscurve {
+                    id: config.id.clone(),
+                    reden,
+                })?;
+        }
+        if let Some(chloride) = &config.chloride {
+            chloride
+                .valideer()
+                .map_err(|reden| NetwerkFout::OngeldigeChloride {
+                    id: config.id.clone(),
+                    reden,
+                })?;
+        }
 
         self.peilgebieden.insert(config.id.clone(), config);
         Ok(())
@@ -375,25 +945,20 @@ impl NetwerkTopologie {
 
     /// Voeg een verbinding toe.
     pub fn voeg_verbinding_toe(&mut self, verbinding: Verbinding) -> Result<(), NetwerkFout> {
-        // Controleer dat beide peilgebieden bestaan
-        if !self.peilgebieden.contains_key(&verbinding.van_id) {
+        // Controleer dat beide peilgebieden of boezems bestaan
+        if !self.bevat_knooppunt(&verbinding.van_id) {
             return Err(NetwerkFout::PeilgebiedNietGevonden {
                 id: verbinding.van_id,
             });
         }
-        if !self.peilgebieden.contains_key(&verbinding.naar_id) {
+        if !self.bevat_knooppunt(&verbinding.naar_id) {
             return Err(NetwerkFout::PeilgebiedNietGevonden {
                 id: verbinding.naar_id,
             });
         }
 
-        // Controleer op cyclische verbindingen (A->B en B->A)
-        if self.bestaat_verbinding_tussen(&verbinding.naar_id, &verbinding.van_id) {
-            return Err(NetwerkFout::CyclischeVerbinding {
-                van: verbinding.van_id.clone(),
-                naar: verbinding.naar_id.clone(),
-            });
-        }
+        // Cycli (A->B en B->A, maalkringen) zijn toegestaan; de stromen
+        // worden per stap iteratief consistent gemaakt
 
         self.verbindingen
             .insert(verbinding.id.clone(), verbinding);
@@ -411,6 +976,33 @@ impl NetwerkTopologie {
             .any(|v| v.van_id == van_id && v.naar_id == naar_id)
     }
 
+    /// Pompvermogen in kW van een stroom in een tijdstap, bij de opvoerhoogte
+    /// tussen de waterstanden van die stap (minstens [`MIN_OPVOERHOOGTE`]).
+    /// Zonder bekende waterstanden geldt de ingestelde opvoerhoogte van de
+    /// verbinding.
+    pub fn pompvermogen_kw(
+        &self,
+        stroom: &VerbindingStroom,
+        stap: &NetwerkTijdstap,
+    ) -> Option<f64> {
+        let verbinding = self.verbindingen.get(&stroom.verbinding_id)?;
+        let (van_id, naar_id) = match stroom.richting {
+            StroomRichting::Naar => (&verbinding.van_id, &verbinding.naar_id),
+            StroomRichting::Terug => (&verbinding.naar_id, &verbinding.van_id),
+        };
+        let waterstand = |id: &PeilgebiedId| {
+            stap.statussen
+                .get(id)
+                .map(|status| status.waterstand)
+                .or_else(|| self.boezems.get(id).map(|boezem| boezem.peil_op(stap.tijd)))
+        };
+        let opvoerhoogte = match (waterstand(van_id), waterstand(naar_id)) {
+            (Some(van), Some(naar)) => (naar - van).max(MIN_OPVOERHOOGTE),
+            _ => verbinding.opvoerhoogte?,
+        };
+        verbinding.pompvermogen_bij_kw(stroom.debiet.abs(), opvoerhoogte)
+    }
+
     /// Haal alle verbindingen op die vanuit een peilgebied vertrekken.
     pub fn verbindingen_vanuit(&self, peilgebied_id: &str) -> Vec<&Verbinding> {
         self.verbindingen
@@ -429,9 +1021,10 @@ impl NetwerkTopologie {
 
     /// Controleer of het netwerk volledig verbonden is (geen geïsoleerde componenten).
     ///
-    /// Een netwerk is verbonden als er een pad is tussen elke twee peilgebieden,
-    /// ongeacht de stroomrichting van de verbindingen (de graf is undirected voor
-    // connectivity checking).
+    /// Een netwerk is verbonden als er een pad is tussen elke twee peilgebieden
+    /// of boezems, ongeacht de stroomrichting van de verbindingen (de graf is
+    /// undirected voor connectivity checking). Een polder die op een boezem
+    /// uitslaat is met die boezem verbonden.
     pub fn is_verbonden(&self) -> bool {
         if self.peilgebieden.is_empty() {
             return true;
@@ -461,65 +1054,470 @@ impl NetwerkTopologie {
                     queue.push(verbinding.van_id.clone());
                 }
             }
+            // Polder <-> boezem waarop hij uitslaat
+            for config in self.peilgebieden.values() {
+                let Some(boezem) = &config.boezem else {
+                    continue;
+                };
+                if config.id == current && !bezocht.contains(boezem) {
+                    queue.push(boezem.clone());
+                }
+                if *boezem == current && !bezocht.contains(&config.id) {
+                    queue.push(config.id.clone());
+                }
+            }
         }
 
-        bezocht.len() == self.peilgebieden.len()
+        bezocht.len() == self.peilgebieden.len() + self.boezems.len()
     }
 
-    /// Valideer de topologie.
+    /// Valideer de topologie: alleen de fouten waardoor niet gesimuleerd
+    /// kan worden. Zie [`Self::valideer_rapport`] voor alle bevindingen.
     pub fn valideer(&self) -> Result<(), NetwerkFout> {
+        for config in self.peilgebieden.values() {
+            if let Some(boezem) = &config.boezem
+                && !self.boezems.contains_key(boezem)
+            {
+                return Err(NetwerkFout::PeilgebiedNietGevonden { id: boezem.clone() });
+            }
+        }
         if !self.is_verbonden() {
             return Err(NetwerkFout::NietVerbonden);
         }
         Ok(())
     }
-}
-
-impl Default for NetwerkTopologie {
-    fn default() -> Self {
-        Self::nieuw()
-    }
-}
 
-/// Simulatiestatus voor multi-peilgebied netwerk.
-#[derive(Debug, Clone)]
-pub struct NetwerkSimulatie {
-    /// Netwerktopologie
-    pub topologie: NetwerkTopologie,
-    /// Huidige waterstand per peilgebied
-    pub waterstanden: HashMap<PeilgebiedId, f64>,
-    /// Tijd in minuten
-    pub tijd: f64,
-}
+    /// Stel een validatierapport op met alle fouten en waarschuwingen:
+    /// ontbrekende knooppunten, peilgebieden zonder uitstroomroute,
+    /// maalcapaciteit die niet bij de oppervlakte past, gemalen zonder
+    /// opvoerhoogte en drempels die niet bij de peilen passen.
+    pub fn valideer_rapport(&self) -> ValidatieRapport {
+        let mut rapport = ValidatieRapport::default();
+
+        let mut peilgebieden: Vec<&PeilgebiedConfig> = self.peilgebieden.values().collect();
+        peilgebieden.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut verbindingen: Vec<&Verbinding> = self.verbindingen.values().collect();
+        verbindingen.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for config in &peilgebieden {
+            if let Some(boezem) = &config.boezem
+                && !self.boezems.contains_key(boezem)
+            {
+                rapport.fout(&config.id, format!("slaat uit op onbekende boezem {}", boezem));
+            }
+        }
+        for verbinding in &verbindingen {
+            self.valideer_verbinding(verbinding, &mut rapport);
+        }
+        if !self.is_verbonden() {
+            rapport.bevindingen.push(Bevinding {
+                ernst: Ernst::Fout,
+                object: None,
+                melding: "netwerk is niet volledig verbonden".to_string(),
+            });
+        }
 
-impl NetwerkSimulatie {
-    /// Maak een nieuwe simulatie vanuit topologie.
-    pub fn nieuw(topologie: NetwerkTopologie) -> Result<Self, NetwerkFout> {
-        topologie.valideer()?;
+        let afwaterend = self.afwaterende_knooppunten();
+        for config in &peilgebieden {
+            if !afwaterend.contains(config.id.as_str()) {
+                rapport.waarschuwing(
+                    &config.id,
+                    "geen uitstroomroute: water kan het peilgebied alleen via verdamping \
+                     of infiltratie verlaten",
+                );
+            }
 
-        // Initialiseer alle waterstanden op streefpeil
-        let waterstanden = topologie
-            .peilgebieden
-            .iter()
-            .map(|(id, config)| (id.clone(), config.streefpeil))
-            .collect();
+            // Uitstroomgemaal plus de gemalen die uit dit peilgebied pompen
+            let capaciteit = config.max_uitstroom_debiet
+                + verbindingen
+                    .iter()
+                    .filter(|v| v.verbinding_type == VerbindingType::Gemaal && v.van_id == config.id)
+                    .map(|v| v.capaciteit)
+                    .sum::<f64>();
+            if capaciteit > 0.0 {
+                let mm_per_dag = capaciteit * 86_400.0 / config.oppervlakte * 1000.0;
+                if mm_per_dag < MIN_MAALCAPACITEIT {
+                    rapport.waarschuwing(
+                        &config.id,
+                        format!(
+                            "maalcapaciteit {:.1} mm/dag is laag voor {:.0} ha",
+                            mm_per_dag,
+                            config.oppervlakte / 10_000.0
+                        ),
+                    );
+                } else if mm_per_dag > MAX_MAALCAPACITEIT {
+                    rapport.waarschuwing(
+                        &config.id,
+                        format!(
+                            "maalcapaciteit {:.1} mm/dag is onwaarschijnlijk hoog voor {:.0} ha",
+                            mm_per_dag,
+                            config.oppervlakte / 10_000.0
+                        ),
+                    );
+                }
+            }
+        }
 
-        Ok(Self {
-            topologie,
-            waterstanden,
-            tijd: 0.0,
-        })
+        rapport
     }
 
-    /// Stel een specifieke startwaterstand in.
-    pub fn met_start_waterstand(
-        mut self,
-        peilgebied_id: &str,
-        waterstand: f64,
-    ) -> Result<Self, NetwerkFout> {
-        if !self.waterstanden.contains_key(peilgebied_id) {
-            return Err(NetwerkFout::PeilgebiedNietGevonden {
-                id: peilgebied_id.to_string(),
+    /// Bevindingen bij één verbinding.
+    fn valideer_verbinding(&self, verbinding: &Verbinding, rapport: &mut ValidatieRapport) {
+        let id = &verbinding.id;
+        for knooppunt in [&verbinding.van_id, &verbinding.naar_id] {
+            if !self.bevat_knooppunt(knooppunt) {
+                rapport.fout(id, format!("verwijst naar onbekend knooppunt {}", knooppunt));
+            }
+        }
+        if verbinding.van_id == verbinding.naar_id {
+            rapport.fout(id, "van en naar zijn hetzelfde knooppunt");
+        }
+        if !(verbinding.capaciteit.is_finite() && verbinding.capaciteit >= 0.0) {
+            rapport.fout(id, format!("ongeldige capaciteit {} m³/s", verbinding.capaciteit));
+        }
+
+        let peil_van = self.peil_van(&verbinding.van_id);
+        let peil_naar = self.peil_van(&verbinding.naar_id);
+        match verbinding.verbinding_type {
+            VerbindingType::Gemaal => match verbinding.opvoerhoogte {
+                None => rapport.waarschuwing(
+                    id,
+                    "gemaal zonder opvoerhoogte: energie en kosten worden niet berekend",
+                ),
+                Some(opvoerhoogte) if opvoerhoogte <= 0.0 => rapport.waarschuwing(
+                    id,
+                    format!("opvoerhoogte {} m van een gemaal moet > 0 zijn", opvoerhoogte),
+                ),
+                Some(opvoerhoogte) => {
+                    if let (Some(van), Some(naar)) = (peil_van, peil_naar)
+                        && opvoerhoogte < naar - van
+                    {
+                        rapport.waarschuwing(
+                            id,
+                            format!(
+                                "opvoerhoogte {:.2} m is kleiner dan het peilverschil {:.2} m",
+                                opvoerhoogte,
+                                naar - van
+                            ),
+                        );
+                    }
+                }
+            },
+            VerbindingType::Overstort => match verbinding.overstort_drempel {
+                None => rapport.waarschuwing(id, "overstort zonder drempel voert niets af"),
+                Some(drempel) => {
+                    if let Some(van) = peil_van
+                        && drempel < van
+                    {
+                        rapport.waarschuwing(
+                            id,
+                            format!(
+                                "drempel {:.2} m NAP ligt onder het peil {:.2} m NAP van {}; \
+                                 de overstort loopt continu",
+                                drempel, van, verbinding.van_id
+                            ),
+                        );
+                    }
+                    if let Some(config) = self.peilgebieden.get(&verbinding.van_id)
+                        && config.maaiveld_niveau > config.streefpeil
+                        && drempel > config.maaiveld_niveau
+                    {
+                        rapport.waarschuwing(
+                            id,
+                            format!(
+                                "drempel {:.2} m NAP ligt boven maaiveld {:.2} m NAP van {}; \
+                                 de overstort werkt pas na inundatie",
+                                drempel, config.maaiveld_niveau, verbinding.van_id
+                            ),
+                        );
+                    }
+                }
+            },
+            VerbindingType::Duiker => match &verbinding.duiker {
+                None => rapport.fout(id, "duiker zonder afmetingen"),
+                Some(duiker) => {
+                    if let Err(reden) = duiker.valideer() {
+                        rapport.fout(id, reden);
+                    }
+                }
+            },
+            VerbindingType::Stuw => {
+                let Some(stuw) = &verbinding.stuw else {
+                    rapport.fout(id, "stuw zonder verstelbereik");
+                    return;
+                };
+                if let Err(reden) = stuw.valideer() {
+                    rapport.fout(id, reden);
+                    return;
+                }
+                match verbinding.overstort_drempel {
+                    None => rapport.fout(id, "stuw zonder kruinhoogte"),
+                    Some(kruin) if stuw.begrens(kruin) != kruin => rapport.fout(
+                        id,
+                        format!("kruinhoogte {} buiten verstelbereik", kruin),
+                    ),
+                    Some(_) => {}
+                }
+                if let Some(van) = peil_van
+                    && stuw.max_kruinhoogte < van
+                {
+                    rapport.waarschuwing(
+                        id,
+                        format!(
+                            "hoogste kruin {:.2} m NAP ligt onder het peil {:.2} m NAP van {}; \
+                             de stuw kan dat peil niet vasthouden",
+                            stuw.max_kruinhoogte, van, verbinding.van_id
+                        ),
+                    );
+                }
+                if let (Some(van), Some(naar)) = (peil_van, peil_naar)
+                    && naar > van
+                {
+                    rapport.waarschuwing(
+                        id,
+                        format!(
+                            "benedenstrooms peil {:.2} m NAP ligt boven bovenstrooms peil \
+                             {:.2} m NAP; de stuw voert niet af",
+                            naar, van
+                        ),
+                    );
+                }
+            }
+            VerbindingType::Keerklep | VerbindingType::OpenVerbinding => {}
+        }
+    }
+
+    /// Streefpeil van een peilgebied of peil van een boezem.
+    fn peil_van(&self, id: &str) -> Option<f64> {
+        self.peilgebieden
+            .get(id)
+            .map(|config| config.streefpeil)
+            .or_else(|| self.boezems.get(id).map(|boezem| boezem.peil))
+    }
+
+    /// Knooppunten vanwaar water het netwerk kan verlaten: via een eigen
+    /// uitstroomgemaal, een boezem, of een verbinding naar zo'n knooppunt.
+    fn afwaterende_knooppunten(&self) -> HashSet<&str> {
+        let mut afwaterend: HashSet<&str> = self
+            .peilgebieden
+            .values()
+            .filter(|config| config.max_uitstroom_debiet > 0.0)
+            .map(|config| config.id.as_str())
+            .chain(self.boezems.keys().map(String::as_str))
+            .collect();
+        let mut queue: Vec<&str> = afwaterend.iter().copied().collect();
+
+        // Stroomopwaarts zoeken: wie kan naar een afwaterend knooppunt?
+        while let Some(current) = queue.pop() {
+            for verbinding in self.verbindingen.values() {
+                let bovenstrooms = if verbinding.naar_id == current {
+                    verbinding.van_id.as_str()
+                } else if verbinding.van_id == current
+                    && !verbinding.verbinding_type.is_eenrichting()
+                {
+                    verbinding.naar_id.as_str()
+                } else {
+                    continue;
+                };
+                if afwaterend.insert(bovenstrooms) {
+                    queue.push(bovenstrooms);
+                }
+            }
+        }
+
+        afwaterend
+    }
+}
+
+/// Ernst van een bevinding in het validatierapport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ernst {
+    /// De topologie kan niet gesimuleerd worden
+    Fout,
+    /// Simuleerbaar, maar waarschijnlijk niet zoals bedoeld
+    Waarschuwing,
+}
+
+/// Eén bevinding uit het validatierapport.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bevinding {
+    pub ernst: Ernst,
+    /// Peilgebied, boezem of verbinding; leeg als de bevinding over het
+    /// hele netwerk gaat
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    pub melding: String,
+}
+
+/// Fouten en waarschuwingen bij een netwerktopologie.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidatieRapport {
+    pub bevindingen: Vec<Bevinding>,
+}
+
+impl ValidatieRapport {
+    /// Of de topologie zonder fouten is; waarschuwingen tellen niet mee.
+    pub fn is_geldig(&self) -> bool {
+        self.fouten().next().is_none()
+    }
+
+    /// Bevindingen die simuleren onmogelijk maken.
+    pub fn fouten(&self) -> impl Iterator<Item = &Bevinding> {
+        self.bevindingen.iter().filter(|b| b.ernst == Ernst::Fout)
+    }
+
+    /// Bevindingen die simuleren toelaten maar aandacht vragen.
+    pub fn waarschuwingen(&self) -> impl Iterator<Item = &Bevinding> {
+        self.bevindingen.iter().filter(|b| b.ernst == Ernst::Waarschuwing)
+    }
+
+    fn fout(&mut self, object: &str, melding: impl Into<String>) {
+        self.voeg_toe(Ernst::Fout, object, melding.into());
+    }
+
+    fn waarschuwing(&mut self, object: &str, melding: impl Into<String>) {
+        self.voeg_toe(Ernst::Waarschuwing, object, melding.into());
+    }
+
+    fn voeg_toe(&mut self, ernst: Ernst, object: &str, melding: String) {
+        self.bevindingen.push(Bevinding {
+            ernst,
+            object: Some(object.to_string()),
+            melding,
+        });
+    }
+}
+
+impl Default for NetwerkTopologie {
+    fn default() -> Self {
+        Self::nieuw()
+    }
+}
+
+/// Adaptieve stapverkleining: bij snelle peilveranderingen wordt een stap
+/// ingekort tot de peilverandering binnen de grens blijft.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptieveTijdstap {
+    /// Grootste peilverandering per stap in m
+    pub max_peilverandering: f64,
+    /// Kleinste tijdstap in minuten; is de peilverandering daarbij nog te
+    /// groot, dan is de simulatie instabiel
+    pub min_stap_minuten: f64,
+}
+
+impl Default for AdaptieveTijdstap {
+    fn default() -> Self {
+        Self {
+            max_peilverandering: 0.01,
+            min_stap_minuten: 0.1,
+        }
+    }
+}
+
+/// Simulatiestatus voor multi-peilgebied netwerk.
+#[derive(Debug, Clone)]
+pub struct NetwerkSimulatie {
+    /// Netwerktopologie
+    pub topologie: NetwerkTopologie,
+    /// Huidige waterstand per peilgebied en boezem
+    pub waterstanden: HashMap<PeilgebiedId, f64>,
+    /// Netto instroom per boezem in de laatste tijdstap (m³/s)
+    pub boezembelasting: HashMap<PeilgebiedId, f64>,
+    /// Tijd in minuten
+    pub tijd: f64,
+    /// Tijdstap in minuten
+    pub stap_minuten: f64,
+    /// Adaptieve stapverkleining; zonder is elke stap `stap_minuten`
+    pub adaptief: Option<AdaptieveTijdstap>,
+    /// Duur van de laatste stap in minuten
+    pub laatste_stap: f64,
+    /// Chlorideconcentratie per peilgebied in mg/l; leeg als geen
+    /// peilgebied of boezem chloride heeft
+    pub chloride: HashMap<PeilgebiedId, f64>,
+}
+
+impl NetwerkSimulatie {
+    /// Maak een nieuwe simulatie vanuit topologie.
+    pub fn nieuw(topologie: NetwerkTopologie) -> Result<Self, NetwerkFout> {
+        topologie.valideer()?;
+
+        // Initialiseer alle waterstanden op streefpeil, boezems op hun peil
+        let waterstanden = topologie
+            .peilgebieden
+            .iter()
+            .map(|(id, config)| (id.clone(), config.streefpeil))
+            .chain(
+                topologie
+                    .boezems
+                    .iter()
+                    .map(|(id, boezem)| (id.clone(), boezem.peil_op(0.0))),
+            )
+            .collect();
+        let boezembelasting = topologie.boezems.keys().map(|id| (id.clone(), 0.0)).collect();
+        let met_chloride = topologie.peilgebieden.values().any(|c| c.chloride.is_some())
+            || topologie.boezems.values().any(|b| b.chloride > 0.0);
+        let chloride = if met_chloride {
+            topologie
+                .peilgebieden
+                .iter()
+                .map(|(id, config)| {
+                    let begin = config.chloride.map_or(0.0, |c| c.begin_concentratie);
+                    (id.clone(), begin)
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            topologie,
+            waterstanden,
+            boezembelasting,
+            tijd: 0.0,
+            stap_minuten: 1.0,
+            adaptief: None,
+            laatste_stap: 0.0,
+            chloride,
+        })
+    }
+
+    /// Stel de tijdstap in minuten in; met een adaptieve tijdstap niet
+    /// kleiner dan `min_stap_minuten`.
+    pub fn met_tijdstap(mut self, minuten: f64) -> Result<Self, NetwerkFout> {
+        if !(minuten.is_finite() && minuten > 0.0) {
+            return Err(NetwerkFout::OngeldigeTijdstap { minuten });
+        }
+        if self.adaptief.is_some_and(|a| minuten < a.min_stap_minuten) {
+            return Err(NetwerkFout::OngeldigeTijdstap { minuten });
+        }
+        self.stap_minuten = minuten;
+        Ok(self)
+    }
+
+    /// Verklein stappen bij snelle peilveranderingen.
+    pub fn met_adaptieve_tijdstap(mut self, adaptief: AdaptieveTijdstap) -> Result<Self, NetwerkFout> {
+        let min = adaptief.min_stap_minuten;
+        let geldig = min.is_finite()
+            && min > 0.0
+            && min <= self.stap_minuten
+            && adaptief.max_peilverandering > 0.0;
+        if !geldig {
+            return Err(NetwerkFout::OngeldigeTijdstap { minuten: min });
+        }
+        self.adaptief = Some(adaptief);
+        Ok(self)
+    }
+
+    /// Stel een specifieke startwaterstand in.
+    pub fn met_start_waterstand(
+        mut self,
+        peilgebied_id: &str,
+        waterstand: f64,
+    ) -> Result<Self, NetwerkFout> {
+        if !self.waterstanden.contains_key(peilgebied_id) {
+            return Err(NetwerkFout::PeilgebiedNietGevonden {
+                id: peilgebied_id.to_string(),
             });
         }
 
@@ -532,18 +1530,24 @@ impl NetwerkSimulatie {
     pub fn bereken_stromen(
         &self,
         _regen_per_peilgebied: &HashMap<PeilgebiedId, f64>,
+    ) -> Result<Vec<VerbindingStroom>, NetwerkFout> {
+        self.stromen_bij(&self.waterstanden)
+    }
+
+    /// Bereken waterstromen over alle verbindingen bij gegeven waterstanden.
+    fn stromen_bij(
+        &self,
+        waterstanden: &HashMap<PeilgebiedId, f64>,
     ) -> Result<Vec<VerbindingStroom>, NetwerkFout> {
         let mut stromen = Vec::new();
 
         for verbinding in self.topologie.verbindingen.values() {
-            let waterstand_van = *self
-                .waterstanden
+            let waterstand_van = *waterstanden
                 .get(&verbinding.van_id)
                 .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
                     id: verbinding.van_id.clone(),
                 })?;
-            let waterstand_naar = *self
-                .waterstanden
+            let waterstand_naar = *waterstanden
                 .get(&verbinding.naar_id)
                 .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
                     id: verbinding.naar_id.clone(),
@@ -551,9 +1555,10 @@ impl NetwerkSimulatie {
 
             let stroom = match verbinding.verbinding_type {
                 VerbindingType::Gemaal => {
-                    // Actief transport: volledige capaciteit als richting Naar
+                    // Actief transport: volledige capaciteit als richting Naar,
+                    // met een pompcurve het debiet bij het actuele peilverschil
                     let debiet = if verbinding.stroomrichting == Some(StroomRichting::Naar) {
-                        verbinding.capaciteit
+                        verbinding.pompdebiet((waterstand_naar - waterstand_van).max(0.0))
                     } else {
                         0.0
                     };
@@ -565,8 +1570,8 @@ impl NetwerkSimulatie {
                         actief: debiet > 0.0,
                     }
                 }
-                VerbindingType::Overstort => {
-                    // Passieve stroming bij hoogwater boven drempel
+                VerbindingType::Overstort | VerbindingType::Stuw => {
+                    // Passieve stroming bij hoogwater boven drempel of kruin
                     let Some(drempel) = verbinding.overstort_drempel else {
                         // Geen drempel ingesteld, geen stroming
                         stromen.push(VerbindingStroom {
@@ -579,9 +1584,7 @@ impl NetwerkSimulatie {
                         continue;
                     };
                     let debiet = if waterstand_van > drempel {
-                        let niveauverschil = waterstand_van - waterstand_naar.max(drempel);
-                        // Debiet schaalt met niveauverschil (weir flow vereenvoudigd)
-                        (niveauverschil.sqrt() * verbinding.capaciteit).min(verbinding.capaciteit)
+                        verbinding.overlaatdebiet(waterstand_van, waterstand_naar)
                     } else {
                         0.0
                     };
@@ -627,6 +1630,26 @@ impl NetwerkSimulatie {
                         actief: debiet_beperkt > 0.0,
                     }
                 }
+                VerbindingType::Duiker => {
+                    // Tweerichtingsstroming met energieverlies over de duiker
+                    let niveauverschil = waterstand_van - waterstand_naar;
+                    let debiet = verbinding
+                        .duiker
+                        .map_or(0.0, |duiker| duiker.debiet(niveauverschil.abs()))
+                        .min(verbinding.capaciteit);
+                    let richting = if niveauverschil > 0.0 {
+                        StroomRichting::Naar
+                    } else {
+                        StroomRichting::Terug
+                    };
+                    VerbindingStroom {
+                        verbinding_id: verbinding.id.clone(),
+                        debiet,
+                        richting,
+                        benutting: debiet / verbinding.capaciteit,
+                        actief: debiet > 0.0,
+                    }
+                }
             };
 
             stromen.push(stroom);
@@ -635,25 +1658,38 @@ impl NetwerkSimulatie {
         Ok(stromen)
     }
 
-    /// Simuleer één tijdstap voor alle peilgebieden.
-    pub fn simuleer_stap(
-        &mut self,
-        regen_per_peilgebied: &HashMap<PeilgebiedId, f64>,
-        uitstroom_strategy: &dyn UitstroomStrategy,
-    ) -> Result<Vec<PeilgebiedStatus>, NetwerkFout> {
-        // Bereken verbindingstromen
-        let stromen = self.bereken_stromen(regen_per_peilgebied)?;
+    /// Netto instroom (m³/s) over de verbindingen per peilgebied in de
+    /// volgende stap, met de stromen van [`Self::bereken_consistente_stromen`].
+    pub(crate) fn netto_verbindingsdebiet(&self) -> Result<DebietPerKnooppunt, NetwerkFout> {
+        let stromen = self.bereken_consistente_stromen(self.stap_minuten)?;
+        let (inkomend, uitgaand) = self.debieten_per_knooppunt(&stromen)?;
+        Ok(self
+            .topologie
+            .peilgebieden
+            .keys()
+            .map(|id| (id.clone(), inkomend[id] - uitgaand[id]))
+            .collect())
+    }
 
-        // Bereken inkomend/uitgaand debiet per peilgebied
+    /// Inkomend en uitgaand debiet (m³/s) per peilgebied en boezem.
+    fn debieten_per_knooppunt(
+        &self,
+        stromen: &[VerbindingStroom],
+    ) -> Result<(DebietPerKnooppunt, DebietPerKnooppunt), NetwerkFout> {
         let mut inkomend_debiet: HashMap<PeilgebiedId, f64> = HashMap::new();
         let mut uitgaand_debiet: HashMap<PeilgebiedId, f64> = HashMap::new();
 
-        for id in self.topologie.peilgebieden.keys() {
+        for id in self
+            .topologie
+            .peilgebieden
+            .keys()
+            .chain(self.topologie.boezems.keys())
+        {
             inkomend_debiet.insert(id.clone(), 0.0);
             uitgaand_debiet.insert(id.clone(), 0.0);
         }
 
-        for stroom in &stromen {
+        for stroom in stromen {
             let verbinding = self
                 .topologie
                 .verbindingen
@@ -692,66 +1728,392 @@ impl NetwerkSimulatie {
             }
         }
 
-        // Update waterstanden per peilgebied
-        let mut statuses = Vec::new();
+        Ok((inkomend_debiet, uitgaand_debiet))
+    }
 
-        for (id, config) in &self.topologie.peilgebieden {
-            let huidige_ws = *self
-                .waterstanden
-                .get(id)
-                .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
-                    id: id.clone(),
-                })?;
-            let regen_intensiteit = regen_per_peilgebied.get(id).copied().unwrap_or(0.0);
-            let inkomend = *inkomend_debiet
-                .get(id)
-                .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
-                    id: id.clone(),
-                })?;
-            let uitgaand = *uitgaand_debiet
-                .get(id)
-                .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
-                    id: id.clone(),
-                })?;
+    /// Bereken verbindingstromen die consistent zijn met de waterstanden
+    /// aan het eind van een stap van `minuten`.
+    ///
+    /// De stromen bij de huidige waterstanden kunnen in een stap over het
+    /// evenwicht heen schieten, zeker als peilgebieden via meerdere wegen
+    /// of in een kring met elkaar verbonden zijn. Daarom wordt iteratief
+    /// (impliciet) gerekend met de waterstanden aan het eind van de stap,
+    /// die volgen uit de netto stroming over de verbindingen. Schiet de
+    /// iteratie heen en weer, dan wordt ze gedempt.
+    pub fn bereken_consistente_stromen(
+        &self,
+        minuten: f64,
+    ) -> Result<Vec<VerbindingStroom>, NetwerkFout> {
+        let seconden = minuten * 60.0;
+        let mut eind = self.waterstanden.clone();
+        let mut stromen = self.stromen_bij(&eind)?;
+        let mut demping = 1.0;
+        let mut vorig_verschil = f64::INFINITY;
+
+        for _ in 0..MAX_STROOM_ITERATIES {
+            let (inkomend, uitgaand) = self.debieten_per_knooppunt(&stromen)?;
+            let mut verschil: f64 = 0.0;
+            for (id, config) in &self.topologie.peilgebieden {
+                let start = self.waterstanden[id];
+                let netto = inkomend[id] - uitgaand[id];
+                let doel = start + netto * seconden / config.bergend_oppervlak(start);
+                let huidig = eind[id];
+                verschil = verschil.max((doel - huidig).abs());
+                eind.insert(id.clone(), huidig + demping * (doel - huidig));
+            }
+            if verschil < STROOM_TOLERANTIE {
+                break;
+            }
+            if verschil >= vorig_verschil {
+                demping *= 0.5;
+            }
+            vorig_verschil = verschil;
+            stromen = self.stromen_bij(&eind)?;
+        }
 
-            // Bepaal uitstroom debiet via strategy
-            let uitstroom_debiet = uitstroom_strategy.bepaal_uitstroom(
-                id,
-                huidige_ws,
-                config,
-                regen_intensiteit,
-                inkomend,
-            );
+        Ok(stromen)
+    }
+
+    /// Simuleer één tijdstap voor alle peilgebieden.
+    ///
+    /// De stap duurt `stap_minuten`, of korter met een adaptieve tijdstap;
+    /// de duur staat daarna in `laatste_stap`.
+    pub fn simuleer_stap(
+        &mut self,
+        regen_per_peilgebied: &HashMap<PeilgebiedId, f64>,
+        uitstroom_strategy: &dyn UitstroomStrategy,
+    ) -> Result<Vec<PeilgebiedStatus>, NetwerkFout> {
+        self.stap(regen_per_peilgebied, uitstroom_strategy, self.stap_minuten)
+            .map(|(statuses, _)| statuses)
+    }
+
+    /// Simuleer een stap van hoogstens `max_minuten`. Geeft naast de status
+    /// per peilgebied de verbindingstromen die in de stap zijn toegepast.
+    fn stap(
+        &mut self,
+        regen_per_peilgebied: &HashMap<PeilgebiedId, f64>,
+        uitstroom_strategy: &dyn UitstroomStrategy,
+        max_minuten: f64,
+    ) -> Result<(Vec<PeilgebiedStatus>, Vec<VerbindingStroom>), NetwerkFout> {
+        self.regel_stuwen(uitstroom_strategy);
+        let max_minuten = max_minuten.min(self.stap_minuten);
+
+        // Stapgrootte: korter bij snelle peilveranderingen. Na een verkorting
+        // worden de stromen opnieuw opgelost, zodat ze consistent zijn met
+        // de stap die echt gezet wordt
+        let mut minuten = max_minuten;
+        let mut pogingen = 0;
+        let (stromen, inkomend_debiet, uitgaand_debiet, statuses, balansen) = loop {
+            pogingen += 1;
+
+            // Bereken verbindingstromen die consistent zijn met de waterstanden
+            // tijdens de stap, ook in netwerken met cycli
+            let stromen = self.bereken_consistente_stromen(minuten)?;
+            let (mut inkomend_debiet, uitgaand_debiet) = self.debieten_per_knooppunt(&stromen)?;
+
+            // Waterbalans per peilgebied
+            let mut statuses = Vec::new();
+            let mut balansen = Vec::new();
+
+            for (id, config) in &self.topologie.peilgebieden {
+                let huidige_ws = *self
+                    .waterstanden
+                    .get(id)
+                    .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
+                        id: id.clone(),
+                    })?;
+                let regen_intensiteit = regen_per_peilgebied.get(id).copied().unwrap_or(0.0);
+                let inkomend = *inkomend_debiet
+                    .get(id)
+                    .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
+                        id: id.clone(),
+                    })?;
+                let uitgaand = *uitgaand_debiet
+                    .get(id)
+                    .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden {
+                        id: id.clone(),
+                    })?;
+
+                // Bepaal uitstroom debiet via strategy
+                let uitstroom_debiet = uitstroom_strategy.bepaal_uitstroom_op(
+                    self.tijd,
+                    id,
+                    huidige_ws,
+                    config,
+                    regen_intensiteit,
+                    inkomend,
+                );
+
+                let pomp_actief = uitstroom_debiet > 0.001;
+                let inlaat_debiet = uitstroom_strategy.bepaal_inlaat_op(
+                    self.tijd,
+                    id,
+                    huidige_ws,
+                    config,
+                    minuten,
+                );
+
+                // Bereken waterbalans
+                let balans = calculate_water_balance(
+                    regen_intensiteit,
+                    config.oppervlakte,
+                    huidige_ws,
+                    uitgaand - inkomend + uitstroom_debiet - inlaat_debiet,
+                    config.verdamping_op(self.tijd),
+                    config.infiltratie,
+                    config.kwel.as_ref(),
+                );
+
+                if let Some(boezem) = &config.boezem
+                    && let Some(belasting) = inkomend_debiet.get_mut(boezem)
+                {
+                    *belasting += uitstroom_debiet - inlaat_debiet;
+                }
 
-            let pomp_actief = uitstroom_debiet > 0.001;
+                // Peilverandering per minuut; met een bergingscurve volgt die uit
+                // het bergend oppervlak bij de huidige waterstand
+                let verandering = match &config.bergingscurve {
+                    Some(curve) => balans.water_balans * 60.0 / curve.oppervlak(huidige_ws),
+                    None => balans.waterstand_verandering,
+                };
+                balansen.push((id, huidige_ws, balans.water_balans, verandering));
 
-            // Bereken waterbalans
-            let balans = calculate_water_balance(
-                regen_intensiteit,
-                config.oppervlakte,
-                huidige_ws,
-                uitgaand + uitstroom_debiet,
-                config.verdamping,
-                config.infiltratie,
-            );
+                statuses.push(PeilgebiedStatus {
+                    id: id.clone(),
+                    waterstand: huidige_ws,
+                    inkomend_debiet: inkomend,
+                    uitgaand_debiet: uitgaand,
+                    uitstroom_debiet,
+                    inlaat_debiet,
+                    regen_intensiteit,
+                    pomp_actief,
+                });
+            }
 
-            // Update waterstand
+            let Some(adaptief) = self.adaptief else {
+                break (stromen, inkomend_debiet, uitgaand_debiet, statuses, balansen);
+            };
+            let snelste = balansen
+                .iter()
+                .map(|(_, _, _, verandering)| verandering.abs())
+                .fold(0.0, f64::max);
+            let korter = (adaptief.max_peilverandering / snelste)
+                .max(adaptief.min_stap_minuten.min(minuten));
+            if snelste * minuten <= adaptief.max_peilverandering * (1.0 + 1e-9)
+                || korter >= minuten
+                || pogingen >= MAX_STROOM_ITERATIES
+            {
+                break (stromen, inkomend_debiet, uitgaand_debiet, statuses, balansen);
+            }
+            minuten = korter;
+        };
+
+        // Stabiliteitscontrole vóór het bijwerken van de waterstanden
+        let mut nieuwe_waterstanden = Vec::with_capacity(balansen.len());
+        for (id, huidige_ws, water_balans, verandering) in balansen {
+            let config = &self.topologie.peilgebieden[id];
+            let nieuwe_waterstand = match &config.bergingscurve {
+                Some(curve) => curve.peil_na(huidige_ws, water_balans * 60.0 * minuten),
+                None => huidige_ws + verandering * minuten,
+            };
+            let te_groot = self
+                .adaptief
+                .is_some_and(|a| (verandering * minuten).abs() > a.max_peilverandering * (1.0 + 1e-9));
+            if !nieuwe_waterstand.is_finite() || te_groot {
+                return Err(NetwerkFout::InstabieleTijdstap {
+                    peilgebied: id.clone(),
+                    tijd: self.tijd,
+                    peilverandering: nieuwe_waterstand - huidige_ws,
+                });
+            }
+            nieuwe_waterstanden.push((id.clone(), nieuwe_waterstand));
+        }
+        if !self.chloride.is_empty() {
+            self.meng_chloride(&stromen, &statuses, &nieuwe_waterstanden, minuten * 60.0);
+        }
+        self.waterstanden.extend(nieuwe_waterstanden);
+
+        // Boezems bergen onbeperkt en volgen hun opgelegde peil
+        for (id, boezem) in &self.topologie.boezems {
+            let belasting = inkomend_debiet.get(id).copied().unwrap_or(0.0)
+                - uitgaand_debiet.get(id).copied().unwrap_or(0.0);
+            self.boezembelasting.insert(id.clone(), belasting);
             self.waterstanden
-                .insert(id.clone(), balans.nieuwe_waterstand);
-
-            statuses.push(PeilgebiedStatus {
-                id: id.clone(),
-                waterstand: huidige_ws,
-                inkomend_debiet: inkomend,
-                uitgaand_debiet: uitgaand,
-                uitstroom_debiet,
-                regen_intensiteit,
-                pomp_actief,
+                .insert(id.clone(), boezem.peil_op(self.tijd + minuten));
+        }
+
+        self.tijd += minuten;
+        self.laatste_stap = minuten;
+        Ok((statuses, stromen))
+    }
+
+    /// Werk de chlorideconcentraties bij over een stap van `seconden`, van
+    /// de huidige naar de nieuwe waterstanden. Zie [`crate::chloride`].
+    fn meng_chloride(
+        &mut self,
+        stromen: &[VerbindingStroom],
+        statuses: &[PeilgebiedStatus],
+        nieuwe_waterstanden: &[(PeilgebiedId, f64)],
+        seconden: f64,
+    ) {
+        let concentratie = |id: &PeilgebiedId| match self.topologie.boezems.get(id) {
+            Some(boezem) => boezem.chloride,
+            None => self.chloride.get(id).copied().unwrap_or(0.0),
+        };
+
+        // Chloridevracht per peilgebied in g/s (mg/l = g/m³)
+        let mut vracht: HashMap<PeilgebiedId, f64> = HashMap::new();
+        for stroom in stromen.iter().filter(|s| s.actief) {
+            let Some(verbinding) = self.topologie.verbindingen.get(&stroom.verbinding_id) else {
+                continue;
+            };
+            let (bron, doel) = match stroom.richting {
+                StroomRichting::Naar => (&verbinding.van_id, &verbinding.naar_id),
+                StroomRichting::Terug => (&verbinding.naar_id, &verbinding.van_id),
+            };
+            let massa = stroom.debiet * concentratie(bron);
+            *vracht.entry(bron.clone()).or_default() -= massa;
+            *vracht.entry(doel.clone()).or_default() += massa;
+        }
+        for status in statuses {
+            let config = &self.topologie.peilgebieden[&status.id];
+            let eigen = concentratie(&status.id);
+            let inlaat = config.boezem.as_ref().map_or(0.0, &concentratie);
+            // Netto kwel min infiltratie; negatief bij wegzijging
+            let kwel_mm_uur = config
+                .kwel
+                .map_or(0.0, |k| k.kwel_mm_uur(status.waterstand))
+                - config.infiltratie;
+            let kwel = mm_per_uur_to_m3_per_sec(kwel_mm_uur, config.oppervlakte);
+            let kwel_concentratie = if kwel > 0.0 {
+                config.chloride.map_or(0.0, |c| c.kwel_concentratie)
+            } else {
+                eigen
+            };
+            *vracht.entry(status.id.clone()).or_default() += status.inlaat_debiet * inlaat
+                - status.uitstroom_debiet * eigen
+                + kwel * kwel_concentratie;
+        }
+
+        let nieuwe_concentraties: Vec<(PeilgebiedId, f64)> = nieuwe_waterstanden
+            .iter()
+            .map(|(id, nieuwe_waterstand)| {
+                let config = &self.topologie.peilgebieden[id];
+                let massa = concentratie(id) * chloride::mengvolume(config, self.waterstanden[id])
+                    + vracht.get(id).copied().unwrap_or(0.0) * seconden;
+                let nieuw = massa.max(0.0) / chloride::mengvolume(config, *nieuwe_waterstand);
+                (id.clone(), nieuw)
+            })
+            .collect();
+        self.chloride.extend(nieuwe_concentraties);
+    }
+
+    /// Laat de strategy de kruinhoogte van elke stuw instellen, binnen het
+    /// verstelbereik.
+    fn regel_stuwen(&mut self, uitstroom_strategy: &dyn UitstroomStrategy) {
+        for verbinding in self.topologie.verbindingen.values_mut() {
+            if verbinding.verbinding_type != VerbindingType::Stuw {
+                continue;
+            }
+            let Some(waterstand_van) = self.waterstanden.get(&verbinding.van_id).copied() else {
+                continue;
+            };
+            let config_van = self.topologie.peilgebieden.get(&verbinding.van_id);
+            if let Some(kruinhoogte) =
+                uitstroom_strategy.bepaal_kruinhoogte_op(self.tijd, verbinding, waterstand_van, config_van)
+            {
+                let kruinhoogte = match &verbinding.stuw {
+                    Some(stuw) => stuw.begrens(kruinhoogte),
+                    None => kruinhoogte,
+                };
+                verbinding.overstort_drempel = Some(kruinhoogte);
+            }
+        }
+    }
+
+    /// Actuele kruinhoogte per stuw.
+    pub fn kruinhoogten(&self) -> HashMap<VerbindingId, f64> {
+        self.topologie
+            .verbindingen
+            .values()
+            .filter(|v| v.verbinding_type == VerbindingType::Stuw)
+            .filter_map(|v| Some((v.id.clone(), v.overstort_drempel?)))
+            .collect()
+    }
+
+    /// Simuleer `duration_hours` uur vanaf de huidige tijd met regen per uur
+    /// per peilgebied.
+    pub fn simuleer(
+        &mut self,
+        regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+        duration_hours: usize,
+        uitstroom_strategy: &dyn UitstroomStrategy,
+    ) -> Result<NetwerkSimulatieResultaat, NetwerkFout> {
+        self.simuleer_met_voortgang(
+            regen_scenario,
+            duration_hours,
+            uitstroom_strategy,
+            |_| {},
+            &Annulering::nieuw(),
+        )
+    }
+
+    /// Als [`Self::simuleer`], maar meld na elke tijdstap de voortgang en
+    /// stop met [`NetwerkFout::Geannuleerd`] zodra `annulering` is gezet.
+    /// De toestand blijft staan op het eind van de laatst berekende stap.
+    pub fn simuleer_met_voortgang(
+        &mut self,
+        regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+        duration_hours: usize,
+        uitstroom_strategy: &dyn UitstroomStrategy,
+        mut voortgang: impl FnMut(&Voortgang),
+        annulering: &Annulering,
+    ) -> Result<NetwerkSimulatieResultaat, NetwerkFout> {
+        let start = self.tijd;
+        let eind = self.tijd + duration_hours as f64 * 60.0;
+        let mut tijdstappen = Vec::new();
+        let mut stand = Voortgang::default();
+
+        while eind - self.tijd > 1e-9 {
+            if annulering.is_geannuleerd() {
+                return Err(NetwerkFout::Geannuleerd { tijd: self.tijd });
+            }
+            let uur = ((self.tijd + 1e-9) / 60.0).floor();
+            let regen_per_peilgebied: HashMap<PeilgebiedId, f64> = regen_scenario
+                .iter()
+                .map(|(id, regen_per_uur)| {
+                    (id.clone(), *regen_per_uur.get(uur as usize).unwrap_or(&0.0))
+                })
+                .collect();
+
+            // Een stap valt binnen één uur, zodat de regen per uur klopt;
+            // de laatste stap eindigt precies op het eind
+            let tot_uurgrens = (uur + 1.0) * 60.0 - self.tijd;
+            let (statussen, stromen) = self.stap(
+                &regen_per_peilgebied,
+                uitstroom_strategy,
+                (eind - self.tijd).min(tot_uurgrens),
+            )?;
+
+            tijdstappen.push(NetwerkTijdstap {
+                tijd: self.tijd,
+                duur: self.laatste_stap,
+                statussen: statussen.into_iter().map(|s| (s.id.clone(), s)).collect(),
+                stromen,
+                boezembelasting: self.boezembelasting.clone(),
+                kruinhoogten: self.kruinhoogten(),
+                chloride: self.chloride.clone(),
             });
+
+            let stap = tijdstappen.last().expect("zojuist toegevoegd");
+            stand.werk_bij(stap, (self.tijd - start) / (eind - start) * 100.0);
+            voortgang(&stand);
         }
 
-        self.tijd += 1.0;
-        Ok(statuses)
+        Ok(NetwerkSimulatieResultaat {
+            tijdstappen,
+            totale_kosten: None,
+        })
     }
 }
 
@@ -766,14 +2128,60 @@ pub trait UitstroomStrategy: Send + Sync {
         regen_intensiteit: f64,
         inkomend_debiet: f64,
     ) -> f64;
-}
-
-/// Simpele uitstroomstrategy: pomp als waterstand boven streefpeil.
-#[derive(Debug, Clone, Copy)]
-pub struct SimpeleUitstroomStrategy;
 
-impl UitstroomStrategy for SimpeleUitstroomStrategy {
-    fn bepaal_uitstroom(
+    /// Bepaal uitstroom debiet op `tijd` (minuten sinds de start).
+    ///
+    /// Standaard onafhankelijk van de tijd; strategieën met voorspellingen,
+    /// zoals [`crate::mpc::MpcRegelaar`], overschrijven deze.
+    fn bepaal_uitstroom_op(
+        &self,
+        _tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.bepaal_uitstroom(peilgebied_id, waterstand, config, regen_intensiteit, inkomend_debiet)
+    }
+
+    /// Bepaal inlaat debiet (m³/s) vanuit de boezem op `tijd`, voor een stap
+    /// van hoogstens `stap_minuten`.
+    ///
+    /// Standaard wordt niet ingelaten; zie [`InlaatStrategy`].
+    fn bepaal_inlaat_op(
+        &self,
+        _tijd: f64,
+        _peilgebied_id: &str,
+        _waterstand: f64,
+        _config: &PeilgebiedConfig,
+        _stap_minuten: f64,
+    ) -> f64 {
+        0.0
+    }
+
+    /// Bepaal de kruinhoogte (m NAP) van een stuw op `tijd`, met de
+    /// waterstand en configuratie van het bovenstroomse peilgebied.
+    ///
+    /// `None` laat de kruin staan waar hij staat; de simulatie begrenst de
+    /// waarde tot het verstelbereik. Zie [`StuwStrategy`].
+    fn bepaal_kruinhoogte_op(
+        &self,
+        _tijd: f64,
+        _stuw: &Verbinding,
+        _waterstand_van: f64,
+        _config_van: Option<&PeilgebiedConfig>,
+    ) -> Option<f64> {
+        None
+    }
+}
+
+/// Simpele uitstroomstrategy: pomp als waterstand boven streefpeil.
+#[derive(Debug, Clone, Copy)]
+pub struct SimpeleUitstroomStrategy;
+
+impl UitstroomStrategy for SimpeleUitstroomStrategy {
+    fn bepaal_uitstroom(
         &self,
         _peilgebied_id: &str,
         waterstand: f64,
@@ -824,6 +2232,324 @@ impl UitstroomStrategy for GebalanceerdeUitstroomStrategy {
     }
 }
 
+/// Uitstroomstrategy met inlaat voor droogteperiodes.
+///
+/// Zakt het peil onder `inlaatpeil` (ten opzichte van het streefpeil), dan
+/// wordt water uit de boezem ingelaten tot het streefpeil weer is bereikt,
+/// begrensd door `max_inlaat_debiet` van het peilgebied. De uitstroom volgt
+/// de onderliggende strategy.
+#[derive(Debug)]
+pub struct InlaatStrategy<S> {
+    uitstroom: S,
+    /// Afwijking van het streefpeil in m (≤ 0) waaronder de inlaat opent
+    inlaatpeil: f64,
+    /// Welke inlaten open staan, per peilgebied
+    open: Mutex<HashMap<PeilgebiedId, bool>>,
+}
+
+impl<S: UitstroomStrategy> InlaatStrategy<S> {
+    pub fn nieuw(uitstroom: S) -> Self {
+        Self {
+            uitstroom,
+            inlaatpeil: 0.0,
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Stel de afwijking van het streefpeil in waaronder de inlaat opent.
+    pub fn met_inlaatpeil(mut self, inlaatpeil: f64) -> Self {
+        self.inlaatpeil = inlaatpeil.min(0.0);
+        self
+    }
+}
+
+impl<S: UitstroomStrategy> UitstroomStrategy for InlaatStrategy<S> {
+    fn bepaal_uitstroom(
+        &self,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.uitstroom.bepaal_uitstroom(
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_uitstroom_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.uitstroom.bepaal_uitstroom_op(
+            tijd,
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_inlaat_op(
+        &self,
+        _tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        stap_minuten: f64,
+    ) -> f64 {
+        let afwijking = waterstand - config.streefpeil;
+        let mut open = self.open.lock().unwrap();
+        let open = open.entry(peilgebied_id.to_string()).or_insert(false);
+        if afwijking < self.inlaatpeil {
+            *open = true;
+        } else if afwijking >= 0.0 {
+            *open = false;
+        }
+        if !*open {
+            return 0.0;
+        }
+
+        // Niet meer inlaten dan in deze tijdstap tot het streefpeil nodig is
+        let tekort = -afwijking * config.bergend_oppervlak(waterstand) / (stap_minuten * 60.0);
+        tekort.clamp(0.0, config.max_inlaat_debiet)
+    }
+
+    fn bepaal_kruinhoogte_op(
+        &self,
+        tijd: f64,
+        stuw: &Verbinding,
+        waterstand_van: f64,
+        config_van: Option<&PeilgebiedConfig>,
+    ) -> Option<f64> {
+        self.uitstroom
+            .bepaal_kruinhoogte_op(tijd, stuw, waterstand_van, config_van)
+    }
+}
+
+/// Uitstroomstrategy die de stuwen regelt op het streefpeil bovenstrooms.
+///
+/// Staat het bovenstroomse peil boven het streefpeil, dan zakt de kruin om
+/// meer af te voeren; staat het eronder, dan komt de kruin omhoog. De
+/// verstelling per tijdstap is `versterking` maal de afwijking, begrensd tot
+/// `max_verstelling`. De uitstroom en inlaat volgen de onderliggende
+/// strategy.
+#[derive(Debug)]
+pub struct StuwStrategy<S> {
+    uitstroom: S,
+    versterking: f64,
+    /// Grootste verstelling van de kruin per tijdstap in m
+    max_verstelling: f64,
+}
+
+impl<S: UitstroomStrategy> StuwStrategy<S> {
+    pub fn nieuw(uitstroom: S) -> Self {
+        Self {
+            uitstroom,
+            versterking: 1.0,
+            max_verstelling: 0.01,
+        }
+    }
+
+    /// Stel de verstelling per m afwijking van het streefpeil in.
+    pub fn met_versterking(mut self, versterking: f64) -> Self {
+        self.versterking = versterking.max(0.0);
+        self
+    }
+
+    /// Stel de grootste verstelling van de kruin per tijdstap in (m).
+    pub fn met_max_verstelling(mut self, max_verstelling: f64) -> Self {
+        self.max_verstelling = max_verstelling.max(0.0);
+        self
+    }
+}
+
+impl<S: UitstroomStrategy> UitstroomStrategy for StuwStrategy<S> {
+    fn bepaal_uitstroom(
+        &self,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.uitstroom.bepaal_uitstroom(
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_uitstroom_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.uitstroom.bepaal_uitstroom_op(
+            tijd,
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_inlaat_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        stap_minuten: f64,
+    ) -> f64 {
+        self.uitstroom
+            .bepaal_inlaat_op(tijd, peilgebied_id, waterstand, config, stap_minuten)
+    }
+
+    fn bepaal_kruinhoogte_op(
+        &self,
+        _tijd: f64,
+        stuw: &Verbinding,
+        waterstand_van: f64,
+        config_van: Option<&PeilgebiedConfig>,
+    ) -> Option<f64> {
+        // Boezems hebben geen streefpeil om op te regelen
+        let config = config_van?;
+        let kruinhoogte = stuw.overstort_drempel?;
+        let verstelling = (self.versterking * (config.streefpeil - waterstand_van))
+            .clamp(-self.max_verstelling, self.max_verstelling);
+        Some(kruinhoogte + verstelling)
+    }
+}
+
+/// Eén in- of uitschakeling van een pomp.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schakelmoment {
+    /// Tijd in minuten
+    pub tijd: f64,
+    pub peilgebied_id: PeilgebiedId,
+    pub pomp_id: String,
+    pub aan: bool,
+}
+
+/// Trapsgewijze uitstroomstrategy: de pompen van een gemaal schakelen in
+/// volgorde op hun schakelpeilen. Peilgebieden zonder pompen pompen als
+/// [`SimpeleUitstroomStrategy`].
+#[derive(Debug, Default)]
+pub struct TrapsgewijzeUitstroomStrategy {
+    /// Pompen per peilgebied in inschakelvolgorde
+    pompen: HashMap<PeilgebiedId, Vec<Pomp>>,
+    /// Welke pompen draaien, per peilgebied
+    aan: Mutex<HashMap<PeilgebiedId, Vec<bool>>>,
+    schakelmomenten: Mutex<Vec<Schakelmoment>>,
+}
+
+impl TrapsgewijzeUitstroomStrategy {
+    pub fn nieuw() -> Self {
+        Self::default()
+    }
+
+    /// Stel de pompen van het gemaal van een peilgebied in.
+    pub fn met_pompen(
+        mut self,
+        peilgebied_id: impl Into<PeilgebiedId>,
+        pompen: &[Pomp],
+    ) -> Result<Self, String> {
+        pompen::valideer(pompen)?;
+        self.pompen
+            .insert(peilgebied_id.into(), pompen::in_volgorde(pompen));
+        Ok(self)
+    }
+
+    /// Alle in- en uitschakelingen tot nu toe, in volgorde van tijd.
+    pub fn schakelmomenten(&self) -> Vec<Schakelmoment> {
+        self.schakelmomenten.lock().unwrap().clone()
+    }
+}
+
+impl UitstroomStrategy for TrapsgewijzeUitstroomStrategy {
+    fn bepaal_uitstroom(
+        &self,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        self.bepaal_uitstroom_op(
+            0.0,
+            peilgebied_id,
+            waterstand,
+            config,
+            regen_intensiteit,
+            inkomend_debiet,
+        )
+    }
+
+    fn bepaal_uitstroom_op(
+        &self,
+        tijd: f64,
+        peilgebied_id: &str,
+        waterstand: f64,
+        config: &PeilgebiedConfig,
+        regen_intensiteit: f64,
+        inkomend_debiet: f64,
+    ) -> f64 {
+        let Some(pompen) = self.pompen.get(peilgebied_id) else {
+            return SimpeleUitstroomStrategy.bepaal_uitstroom(
+                peilgebied_id,
+                waterstand,
+                config,
+                regen_intensiteit,
+                inkomend_debiet,
+            );
+        };
+
+        let mut toestanden = self.aan.lock().unwrap();
+        let aan = toestanden
+            .entry(peilgebied_id.to_string())
+            .or_insert_with(|| vec![false; pompen.len()]);
+        let vorig = aan.clone();
+        pompen::schakel(pompen, aan, waterstand - config.streefpeil);
+
+        let mut schakelmomenten = self.schakelmomenten.lock().unwrap();
+        for ((pomp, &was), &is) in pompen.iter().zip(&vorig).zip(aan.iter()) {
+            if was != is {
+                schakelmomenten.push(Schakelmoment {
+                    tijd,
+                    peilgebied_id: peilgebied_id.to_string(),
+                    pomp_id: pomp.id.clone(),
+                    aan: is,
+                });
+            }
+        }
+
+        pompen
+            .iter()
+            .zip(aan.iter())
+            .filter(|(_, aan)| **aan)
+            .map(|(pomp, _)| pomp.debiet)
+            .sum()
+    }
+}
+
 /// Resultaat van netwerksimulatie.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetwerkSimulatieResultaat {
@@ -839,58 +2565,115 @@ pub struct NetwerkSimulatieResultaat {
 pub struct NetwerkTijdstap {
     /// Tijd in minuten
     pub tijd: f64,
+    /// Duur van de stap in minuten
+    #[serde(default = "default_duur")]
+    pub duur: f64,
     /// Status per peilgebied
     pub statussen: HashMap<PeilgebiedId, PeilgebiedStatus>,
     /// Verbindingstromen
     pub stromen: Vec<VerbindingStroom>,
+    /// Netto instroom per boezem (m³/s)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub boezembelasting: HashMap<PeilgebiedId, f64>,
+    /// Kruinhoogte per stuw tijdens de stap (m NAP)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub kruinhoogten: HashMap<VerbindingId, f64>,
+    /// Chlorideconcentratie per peilgebied aan het eind van de stap (mg/l)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub chloride: HashMap<PeilgebiedId, f64>,
 }
 
-/// Run een netwerksimulatie voor gegeven regenscenario.
-pub fn run_netwerksimulatie(
-    topologie: &NetwerkTopologie,
-    regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>, // regen per uur per peilgebied
-    duration_hours: usize,
-    uitstroom_strategy: &dyn UitstroomStrategy,
-) -> Result<NetwerkSimulatieResultaat, NetwerkFout> {
-    let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone())?;
-    let mut tijdstappen = Vec::new();
+fn default_duur() -> f64 {
+    1.0
+}
 
-    for uur in 0..duration_hours {
-        for minuut in 0..60 {
-            let _tijd_uur = uur + minuut / 60;
-            let mut regen_per_peilgebied = HashMap::new();
+/// Voortgang van een lopende netwerksimulatie met tussentijdse statistieken.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Voortgang {
+    /// Doorgerekend deel van de simulatieduur (0–100)
+    pub percentage: f64,
+    /// Aantal berekende tijdstappen
+    pub tijdstap: usize,
+    /// Simulatietijd aan het eind van de laatste stap in minuten
+    pub tijd: f64,
+    /// Hoogste waterstand per peilgebied tot nu toe (m NAP)
+    pub max_waterstand: HashMap<PeilgebiedId, f64>,
+    /// Totaal uitgeslagen volume van alle peilgebieden tot nu toe (m³)
+    pub uitgeslagen_volume: f64,
+}
 
-            for (id, regen_per_uur) in regen_scenario {
-                let regen = *regen_per_uur.get(uur).unwrap_or(&0.0);
-                regen_per_peilgebied.insert(id.clone(), regen);
-            }
+impl Voortgang {
+    fn werk_bij(&mut self, stap: &NetwerkTijdstap, percentage: f64) {
+        self.percentage = percentage.clamp(0.0, 100.0);
+        self.tijdstap += 1;
+        self.tijd = stap.tijd;
+        for (id, status) in &stap.statussen {
+            self.max_waterstand
+                .entry(id.clone())
+                .and_modify(|max| *max = max.max(status.waterstand))
+                .or_insert(status.waterstand);
+            self.uitgeslagen_volume += status.uitstroom_debiet * stap.duur * 60.0;
+        }
+    }
+}
 
-            let statussen = simulatie.simuleer_stap(&regen_per_peilgebied, uitstroom_strategy)?;
+/// Token om een lopende simulatie vanuit een andere thread af te breken.
+/// Klonen delen dezelfde vlag.
+#[derive(Debug, Clone, Default)]
+pub struct Annulering(Arc<AtomicBool>);
 
-            let stromen = simulatie.bereken_stromen(&regen_per_peilgebied)?;
+impl Annulering {
+    pub fn nieuw() -> Self {
+        Self::default()
+    }
 
-            let status_map: HashMap<PeilgebiedId, PeilgebiedStatus> = statussen
-                .into_iter()
-                .map(|s| (s.id.clone(), s))
-                .collect();
+    /// Vraag de simulatie te stoppen; die stopt voor de volgende tijdstap.
+    pub fn annuleer(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 
-            tijdstappen.push(NetwerkTijdstap {
-                tijd: simulatie.tijd,
-                statussen: status_map,
-                stromen,
-            });
-        }
+    pub fn is_geannuleerd(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
     }
+}
 
-    Ok(NetwerkSimulatieResultaat {
-        tijdstappen,
-        totale_kosten: None,
-    })
+/// Run een netwerksimulatie voor gegeven regenscenario met stappen van een
+/// minuut.
+pub fn run_netwerksimulatie(
+    topologie: &NetwerkTopologie,
+    regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>, // regen per uur per peilgebied
+    duration_hours: usize,
+    uitstroom_strategy: &dyn UitstroomStrategy,
+) -> Result<NetwerkSimulatieResultaat, NetwerkFout> {
+    NetwerkSimulatie::nieuw(topologie.clone())?.simuleer(
+        regen_scenario,
+        duration_hours,
+        uitstroom_strategy,
+    )
+}
+
+/// Als [`run_netwerksimulatie`], met voortgangsmeldingen en annulering.
+pub fn run_netwerksimulatie_met_voortgang(
+    topologie: &NetwerkTopologie,
+    regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+    duration_hours: usize,
+    uitstroom_strategy: &dyn UitstroomStrategy,
+    voortgang: impl FnMut(&Voortgang),
+    annulering: &Annulering,
+) -> Result<NetwerkSimulatieResultaat, NetwerkFout> {
+    NetwerkSimulatie::nieuw(topologie.clone())?.simuleer_met_voortgang(
+        regen_scenario,
+        duration_hours,
+        uitstroom_strategy,
+        voortgang,
+        annulering,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use peilbeheer_core::bergingscurve::BergingsPunt;
 
     fn maak_test_topologie() -> NetwerkTopologie {
         let mut topologie = NetwerkTopologie::nieuw();
@@ -905,8 +2688,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -919,8 +2709,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.4,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -967,7 +2764,7 @@ mod tests {
     }
 
     #[test]
-    fn test_cyclische_verbinding_geweigerd() {
+    fn test_cyclische_verbinding_toegestaan() {
         // Maak een topologie zonder verbindingen
         let mut topologie = NetwerkTopologie::nieuw();
 
@@ -980,8 +2777,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -994,8 +2798,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.4,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1010,6 +2821,7 @@ mod tests {
             .unwrap())
             .unwrap();
 
+        // Rondpompen: B->A naast A->B
         let result = topologie.voeg_verbinding_toe(Verbinding::nieuw_gemaal(
             "v2".to_string(),
             "polder_b".to_string(),
@@ -1017,8 +2829,178 @@ mod tests {
             0.3,
             2.0,
         ).unwrap());
+        assert!(result.is_ok());
+        assert!(topologie.valideer().is_ok());
 
-        assert!(matches!(result, Err(NetwerkFout::CyclischeVerbinding { .. })));
+        // Rondpompen verplaatst evenveel water heen als terug
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie).unwrap();
+        let regen = HashMap::new();
+        let statussen = simulatie.simuleer_stap(&regen, &SimpeleUitstroomStrategy).unwrap();
+        for status in &statussen {
+            assert!((status.inkomend_debiet - 0.3).abs() < 1e-12);
+            assert!((status.uitgaand_debiet - 0.3).abs() < 1e-12);
+        }
+        assert!((simulatie.waterstanden["polder_a"] + 0.60).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_maalkring_consistente_stromen() {
+        // Twee ruime open verbindingen in een kring tussen twee polders: de
+        // stromen bij het beginpeil zouden in één stap over het evenwicht
+        // heen schieten
+        let mut topologie = NetwerkTopologie::nieuw();
+        for id in ["a", "b"] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: None,
+                    oppervlakte: 100_000.0,
+                    streefpeil: -0.60,
+                    marge: 0.20,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet: 0.0,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        for (id, van, naar) in [("heen", "a", "b"), ("terug", "b", "a")] {
+            topologie
+                .voeg_verbinding_toe(Verbinding {
+                    verbinding_type: VerbindingType::OpenVerbinding,
+                    stroomrichting: None,
+                    ..Verbinding::nieuw_keerklep(id.to_string(), van.to_string(), naar.to_string(), 1000.0)
+                        .unwrap()
+                })
+                .unwrap();
+        }
+
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie)
+            .unwrap()
+            .met_start_waterstand("a", -0.50)
+            .unwrap()
+            .met_start_waterstand("b", -0.70)
+            .unwrap();
+        let regen = HashMap::new();
+        let expliciet: f64 = simulatie.bereken_stromen(&regen).unwrap().iter().map(|s| s.debiet).sum();
+        assert!(expliciet * 60.0 / 100_000.0 > 0.2, "zonder iteratie schiet het peil door");
+
+        simulatie.simuleer_stap(&regen, &SimpeleUitstroomStrategy).unwrap();
+        let (a, b) = (simulatie.waterstanden["a"], simulatie.waterstanden["b"]);
+        // Geen doorschieten, het verschil neemt af en er gaat geen water verloren
+        assert!(a > b, "a={} b={}", a, b);
+        assert!(a - b < 0.2);
+        assert!((a + b + 1.20).abs() < 1e-9);
+        // Impliciet: het verschil is 0.2 / (1 + k) met k = 2·1000·60·2 / 100.000
+        assert!((a - b - 0.2 / 3.4).abs() < 1e-6, "{}", a - b);
+    }
+
+    #[test]
+    fn test_opgeslagen_stromen_volgen_waterbalans() {
+        // Eén ruime open verbinding: de opgeslagen stroom maal de stapduur
+        // moet gelijk zijn aan de volumeverandering van beide polders
+        let mut topologie = NetwerkTopologie::nieuw();
+        for id in ["a", "b"] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: None,
+                    oppervlakte: 100_000.0,
+                    streefpeil: -0.60,
+                    marge: 0.20,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet: 0.0,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        topologie
+            .voeg_verbinding_toe(Verbinding {
+                verbinding_type: VerbindingType::OpenVerbinding,
+                stroomrichting: None,
+                ..Verbinding::nieuw_keerklep("ab".to_string(), "a".to_string(), "b".to_string(), 1000.0)
+                    .unwrap()
+            })
+            .unwrap();
+
+        // Ook als de adaptieve tijdstap de stap inkort
+        let adaptief = AdaptieveTijdstap {
+            max_peilverandering: 0.02,
+            min_stap_minuten: 0.01,
+        };
+        for adaptief in [None, Some(adaptief)] {
+            let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone())
+                .unwrap()
+                .met_tijdstap(10.0)
+                .unwrap()
+                .met_start_waterstand("a", -0.50)
+                .unwrap()
+                .met_start_waterstand("b", -0.70)
+                .unwrap();
+            if let Some(adaptief) = adaptief {
+                simulatie = simulatie.met_adaptieve_tijdstap(adaptief).unwrap();
+            }
+            let resultaat = simulatie
+                .simuleer(&HashMap::new(), 1, &SimpeleUitstroomStrategy)
+                .unwrap();
+            if adaptief.is_some() {
+                assert!(resultaat.tijdstappen[0].duur < 10.0);
+            }
+
+            let mut eind_b: Vec<f64> = resultaat.tijdstappen[1..]
+                .iter()
+                .map(|stap| stap.statussen["b"].waterstand)
+                .collect();
+            eind_b.push(simulatie.waterstanden["b"]);
+            for (stap, eind) in resultaat.tijdstappen.iter().zip(eind_b) {
+                let stroom = &stap.stromen[0];
+                assert_eq!(stroom.richting, StroomRichting::Naar);
+                let volume = (eind - stap.statussen["b"].waterstand) * 100_000.0;
+                assert!(
+                    (stroom.debiet * stap.duur * 60.0 - volume).abs() < 1e-6,
+                    "t={} stroom={} volume={}",
+                    stap.tijd,
+                    stroom.debiet * stap.duur * 60.0,
+                    volume
+                );
+            }
+        }
+
+        // Impliciet is de toegepaste stroom die bij de eindwaterstanden,
+        // ook als de stap is ingekort
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie)
+            .unwrap()
+            .met_tijdstap(10.0)
+            .unwrap()
+            .met_adaptieve_tijdstap(adaptief)
+            .unwrap()
+            .met_start_waterstand("a", -0.50)
+            .unwrap()
+            .met_start_waterstand("b", -0.70)
+            .unwrap();
+        simulatie
+            .simuleer_stap(&HashMap::new(), &SimpeleUitstroomStrategy)
+            .unwrap();
+        assert!(simulatie.laatste_stap < 10.0);
+        let toegepast =
+            (simulatie.waterstanden["b"] + 0.70) * 100_000.0 / (simulatie.laatste_stap * 60.0);
+        let eind = simulatie.bereken_stromen(&HashMap::new()).unwrap()[0].debiet;
+        assert!((toegepast - eind).abs() < 1e-6 * eind, "{} vs {}", toegepast, eind);
     }
 
     #[test]
@@ -1057,8 +3039,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1071,8 +3060,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.4,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1104,8 +3100,15 @@ mod tests {
             marge: 0.20,
             maaiveld_niveau: 0.0,
             max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet: 0.0,
             verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
             infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
         };
 
         assert_eq!(config.min_peil(), -0.80);
@@ -1128,6 +3131,9 @@ mod tests {
 
         assert!(VerbindingType::OpenVerbinding.is_passief());
         assert!(!VerbindingType::OpenVerbinding.is_eenrichting());
+
+        assert!(VerbindingType::Duiker.is_passief());
+        assert!(!VerbindingType::Duiker.is_eenrichting());
     }
 
     #[test]
@@ -1141,8 +3147,15 @@ mod tests {
             marge: 0.20,
             maaiveld_niveau: 0.0,
             max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet: 0.0,
             verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
             infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
         };
 
         // Onder streefpeil: geen uitstroom
@@ -1154,6 +3167,230 @@ mod tests {
         assert_eq!(debiet, 0.5);
     }
 
+    #[test]
+    fn test_inlaat_bij_droogte() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 100_000.0,
+                streefpeil: -0.60,
+                marge: 0.20,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.05,
+                verdamping: 0.5,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.5,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        let regen = HashMap::from([("polder".to_string(), vec![0.0; 24])]);
+
+        // Zonder inlaat zakt het peil 1 mm per uur
+        let zonder = run_netwerksimulatie(&topologie, &regen, 24, &SimpeleUitstroomStrategy).unwrap();
+        let eind = &zonder.tijdstappen.last().unwrap().statussen["polder"];
+        assert!(eind.waterstand < -0.62);
+        assert_eq!(eind.inlaat_debiet, 0.0);
+
+        // Met inlaat blijft het peil op het streefpeil, zonder eroverheen te gaan
+        let strategy = InlaatStrategy::nieuw(SimpeleUitstroomStrategy).met_inlaatpeil(-0.01);
+        let met = run_netwerksimulatie(&topologie, &regen, 24, &strategy).unwrap();
+        for tijdstap in &met.tijdstappen {
+            let status = &tijdstap.statussen["polder"];
+            assert!(status.waterstand > -0.611 && status.waterstand <= -0.60 + 1e-9);
+            assert!(status.inlaat_debiet <= 0.05);
+            assert!(!status.pomp_actief);
+        }
+        assert!(met.tijdstappen.iter().any(|t| t.statussen["polder"].inlaat_debiet > 0.0));
+
+        // De inlaatcapaciteit begrenst de aanvoer: 0.01 m³/s dekt ruim een
+        // derde van de verliezen van 1 mm per uur (0.028 m³/s)
+        topologie.peilgebieden.get_mut("polder").unwrap().max_inlaat_debiet = 0.01;
+        let strategy = InlaatStrategy::nieuw(SimpeleUitstroomStrategy);
+        let beperkt = run_netwerksimulatie(&topologie, &regen, 24, &strategy).unwrap();
+        let eind = &beperkt.tijdstappen.last().unwrap().statussen["polder"];
+        assert_eq!(eind.inlaat_debiet, 0.01);
+        assert!(eind.waterstand < -0.61 && eind.waterstand > -0.62);
+
+        // Ook met stappen van een uur schiet de inlaat niet over het streefpeil
+        topologie.peilgebieden.get_mut("polder").unwrap().max_inlaat_debiet = 0.5;
+        let mut per_uur = NetwerkSimulatie::nieuw(topologie).unwrap().met_tijdstap(60.0).unwrap();
+        let strategy = InlaatStrategy::nieuw(SimpeleUitstroomStrategy);
+        let resultaat = per_uur.simuleer(&regen, 24, &strategy).unwrap();
+        assert!(resultaat.tijdstappen.iter().any(|t| t.statussen["polder"].inlaat_debiet > 0.0));
+        assert!(per_uur.waterstanden["polder"] <= -0.60 + 1e-9);
+        for tijdstap in &resultaat.tijdstappen {
+            assert!(tijdstap.statussen["polder"].waterstand <= -0.60 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_makkink_verdamping() {
+        let start = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let dagwaarden = [Dagwaarde {
+            datum: start.date_naive(),
+            temperatuur: 20.0,
+            straling: 2000.0,
+        }];
+        let config = PeilgebiedConfig {
+            id: "polder".to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.0,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.5,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        };
+        assert_eq!(config.verdamping_op(0.0), 0.5);
+
+        let config = config.met_makkink(&dagwaarden, start, 24).met_gewasfactor(1.2);
+        let dag_mm = verdamping::makkink(20.0, 2000.0) * 1.2;
+        assert!((config.verdamping_op(600.0) * 24.0 - dag_mm).abs() < 1e-9);
+
+        // Een dag verdampen zonder regen verlaagt het peil met de
+        // gewasverdamping
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie.voeg_peilgebied_toe(config).unwrap();
+        let regen = HashMap::from([("polder".to_string(), vec![0.0; 24])]);
+        let resultaat = run_netwerksimulatie(&topologie, &regen, 24, &SimpeleUitstroomStrategy).unwrap();
+        let eind = resultaat.tijdstappen.last().unwrap().statussen["polder"].waterstand;
+        let daling_mm = (-0.60 - eind) * 1000.0;
+        assert!((daling_mm - dag_mm * 1439.0 / 1440.0).abs() < 1e-6, "{}", daling_mm);
+    }
+
+    #[test]
+    fn test_bergingscurve() {
+        // Open water van 10.000 m² bij streefpeil dat via de taluds
+        // groeit tot 30.000 m² op 0.3 m erboven
+        let curve = BergingsCurve::new(vec![
+            BergingsPunt::oppervlak(-0.60, 10_000.0),
+            BergingsPunt::oppervlak(-0.30, 30_000.0),
+        ])
+        .unwrap();
+        let config = PeilgebiedConfig {
+            id: "polder".to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.0,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        }
+        .met_bergingscurve(curve.clone());
+        assert!((config.bergend_oppervlak(-0.45) - 20_000.0).abs() < 1e-6);
+
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie.voeg_peilgebied_toe(config.clone()).unwrap();
+        let regen = HashMap::from([("polder".to_string(), vec![5.0; 6])]);
+        let resultaat =
+            run_netwerksimulatie(&topologie, &regen, 6, &SimpeleUitstroomStrategy).unwrap();
+        let peil = |minuut: usize| resultaat.tijdstappen[minuut].statussen["polder"].waterstand;
+
+        // Het volume sluit en de stijging vlakt af naarmate het peil stijgt
+        let volume = curve.volume(peil(359)) - curve.volume(-0.60);
+        assert!((volume - 100_000.0 * 0.005 * 359.0 / 60.0).abs() < 1e-6, "{}", volume);
+        assert!(peil(359) - peil(300) < peil(60) - peil(1));
+
+        // Een curve met één punt wordt geweigerd
+        let mut ongeldig = config;
+        ongeldig.bergingscurve = Some(BergingsCurve {
+            punten: vec![BergingsPunt::oppervlak(-0.60, 10_000.0)],
+        });
+        assert!(matches!(
+            NetwerkTopologie::nieuw().voeg_peilgebied_toe(ongeldig),
+            Err(NetwerkFout::OngeldigeBergingscurve { .. })
+        ));
+    }
+
+    #[test]
+    fn test_trapsgewijze_uitstroom_strategy() {
+        let strategy = TrapsgewijzeUitstroomStrategy::nieuw()
+            .met_pompen(
+                "test",
+                &[
+                    Pomp::new("p2", 1.0, 0.10, 0.02).met_volgorde(2),
+                    Pomp::new("p1", 0.5, 0.05, 0.0).met_volgorde(1),
+                ],
+            )
+            .unwrap();
+        let config = PeilgebiedConfig {
+            id: "test".to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 2.0,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        };
+
+        let debiet = |tijd: f64, waterstand: f64| {
+            strategy.bepaal_uitstroom_op(tijd, "test", waterstand, &config, 0.0, 0.0)
+        };
+        assert_eq!(debiet(0.0, -0.58), 0.0);
+        assert_eq!(debiet(1.0, -0.54), 0.5);
+        assert_eq!(debiet(2.0, -0.48), 1.5);
+        // Tussen de schakelpeilen blijven beide pompen draaien
+        assert_eq!(debiet(3.0, -0.57), 1.5);
+        assert_eq!(debiet(4.0, -0.59), 0.5);
+        assert_eq!(debiet(5.0, -0.61), 0.0);
+
+        let momenten: Vec<(f64, &str, bool)> = strategy
+            .schakelmomenten()
+            .iter()
+            .map(|m| (m.tijd, if m.pomp_id == "p1" { "p1" } else { "p2" }, m.aan))
+            .collect();
+        assert_eq!(
+            momenten,
+            vec![
+                (1.0, "p1", true),
+                (2.0, "p2", true),
+                (4.0, "p2", false),
+                (5.0, "p1", false)
+            ]
+        );
+
+        // Zonder pompen als de simpele strategy
+        assert_eq!(
+            strategy.bepaal_uitstroom("ander", -0.50, &config, 0.0, 0.0),
+            2.0
+        );
+    }
+
     #[test]
     fn test_netwerk_simulatie_twee_peilgebieden() {
         // Maak een netwerk van twee peilgebieden met verbinding
@@ -1168,8 +3405,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.6,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1182,8 +3426,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1233,6 +3484,78 @@ mod tests {
         assert!(noord_naar_zuid[0].debiet > 0.0);
     }
 
+    #[test]
+    fn test_gemaal_pompcurve() {
+        use peilbeheer_core::pompcurve::PompPunt;
+
+        let curve = PompCurve::new(vec![
+            PompPunt::new(0.5, 0.4).met_rendement(0.8),
+            PompPunt::new(2.5, 0.0).met_rendement(0.6),
+        ])
+        .unwrap();
+        let mut topologie = maak_test_topologie();
+        let verbinding = topologie.verbindingen.remove("verbinding_ab").unwrap();
+        let verbinding = verbinding.met_pompcurve(curve);
+
+        // Begrensd door de capaciteit, en vermogen met het rendement uit de curve
+        assert_eq!(verbinding.pompdebiet(0.0), 0.3);
+        assert!((verbinding.pompdebiet(1.5) - 0.2).abs() < 1e-12);
+        let vermogen = verbinding.pompvermogen_bij_kw(0.2, 1.5).unwrap();
+        assert!((vermogen - 9.81 * 0.2 * 1.5 / 0.7).abs() < 1e-9);
+        topologie.voeg_verbinding_toe(verbinding).unwrap();
+
+        // Het debiet volgt het actuele peilverschil tussen de peilgebieden
+        let simulatie = NetwerkSimulatie::nieuw(topologie)
+            .unwrap()
+            .met_start_waterstand("polder_b", 0.90)
+            .unwrap();
+        let stromen = simulatie.bereken_stromen(&HashMap::new()).unwrap();
+        assert!((stromen[0].debiet - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pompvermogen_bij_waterstanden() {
+        let topologie = maak_test_topologie();
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone()).unwrap();
+        let statussen = simulatie
+            .simuleer_stap(&HashMap::new(), &SimpeleUitstroomStrategy)
+            .unwrap();
+        let mut stap = NetwerkTijdstap {
+            tijd: 60.0,
+            duur: 60.0,
+            statussen: statussen.into_iter().map(|s| (s.id.clone(), s)).collect(),
+            stromen: Vec::new(),
+            boezembelasting: HashMap::new(),
+            kruinhoogten: HashMap::new(),
+            chloride: HashMap::new(),
+        };
+        let stroom = VerbindingStroom {
+            verbinding_id: "verbinding_ab".to_string(),
+            debiet: 0.2,
+            richting: StroomRichting::Naar,
+            benutting: 0.2 / 0.3,
+            actief: true,
+        };
+        let verbinding = &topologie.verbindingen["verbinding_ab"];
+
+        // Opvoerhoogte uit het peilverschil, niet de ingestelde 2.0 m
+        stap.statussen.get_mut("polder_a").unwrap().waterstand = -0.60;
+        stap.statussen.get_mut("polder_b").unwrap().waterstand = 0.90;
+        let vermogen = topologie.pompvermogen_kw(&stroom, &stap).unwrap();
+        assert!((vermogen - verbinding.pompvermogen_bij_kw(0.2, 1.5).unwrap()).abs() < 1e-9);
+
+        // Gelijke peilen: de minimale opvoerhoogte
+        stap.statussen.get_mut("polder_b").unwrap().waterstand = -0.60;
+        let vermogen = topologie.pompvermogen_kw(&stroom, &stap).unwrap();
+        let minimaal = verbinding.pompvermogen_bij_kw(0.2, MIN_OPVOERHOOGTE).unwrap();
+        assert!((vermogen - minimaal).abs() < 1e-9);
+
+        // Zonder waterstanden de ingestelde opvoerhoogte
+        stap.statussen.clear();
+        let vermogen = topologie.pompvermogen_kw(&stroom, &stap).unwrap();
+        assert!((vermogen - verbinding.pompvermogen_bij_kw(0.2, 2.0).unwrap()).abs() < 1e-9);
+    }
+
     #[test]
     fn test_overstort_passieve_stroming() {
         // Test dat overstort alleen werkt bij hoogwater
@@ -1247,8 +3570,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1261,8 +3591,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.4,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -1293,4 +3630,619 @@ mod tests {
         assert!(overstort.debiet > 0.0, "Overstort moet debiet hebben bij hoogwater");
         assert_eq!(overstort.richting, StroomRichting::Naar);
     }
+
+    fn stuw_topologie() -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 100_000.0,
+                streefpeil: -0.60,
+                marge: 0.10,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet: 0.0,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        topologie.voeg_boezem_toe(Boezem::nieuw("boezem", -1.50)).unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_stuw(
+                    "stuw".to_string(),
+                    "polder".to_string(),
+                    "boezem".to_string(),
+                    2.0,
+                    -0.55,
+                    StuwParameters::new(-0.90, -0.40),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        topologie
+    }
+
+    #[test]
+    fn test_stuw_verbinding() {
+        assert!(VerbindingType::Stuw.is_passief());
+        assert!(VerbindingType::Stuw.is_eenrichting());
+        assert!(VerbindingType::Stuw.is_regelbaar());
+        assert!(!VerbindingType::Overstort.is_regelbaar());
+
+        let bereik = StuwParameters::new(-0.90, -0.40);
+        let buiten = Verbinding::nieuw_stuw("s".into(), "a".into(), "b".into(), 1.0, -0.30, bereik);
+        assert!(matches!(buiten, Err(NetwerkFout::OngeldigeStuw { .. })));
+        let omgekeerd = StuwParameters::new(-0.40, -0.90);
+        let stuw = Verbinding::nieuw_stuw("s".into(), "a".into(), "b".into(), 1.0, -0.50, omgekeerd);
+        assert!(matches!(stuw, Err(NetwerkFout::OngeldigeStuw { .. })));
+
+        // Zonder stuwregeling werkt een stuw als overstort op de kruin
+        let simulatie = NetwerkSimulatie::nieuw(stuw_topologie())
+            .unwrap()
+            .met_start_waterstand("polder", -0.45)
+            .unwrap();
+        let stromen = simulatie.bereken_stromen(&HashMap::new()).unwrap();
+        assert!((stromen[0].debiet - 1.70 * 0.1f64.powf(1.5)).abs() < 1e-12);
+    }
+
+    fn chloride_peilgebied(id: &str, streefpeil: f64, chloride: ChlorideParameters) -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.0,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: Some(chloride),
+        }
+    }
+
+    #[test]
+    fn test_chloride_menging_behoudt_massa() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(chloride_peilgebied("zout", -0.50, ChlorideParameters::new(800.0, 0.0)))
+            .unwrap();
+        topologie
+            .voeg_peilgebied_toe(chloride_peilgebied("zoet", -0.50, ChlorideParameters::new(100.0, 0.0)))
+            .unwrap();
+        topologie
+            .voeg_verbinding_toe(Verbinding {
+                verbinding_type: VerbindingType::OpenVerbinding,
+                stroomrichting: None,
+                ..Verbinding::nieuw_keerklep("sloot".into(), "zout".into(), "zoet".into(), 1.0).unwrap()
+            })
+            .unwrap();
+
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie)
+            .unwrap()
+            .met_start_waterstand("zout", -0.30)
+            .unwrap();
+        let massa = |sim: &NetwerkSimulatie| -> f64 {
+            sim.topologie
+                .peilgebieden
+                .iter()
+                .map(|(id, config)| sim.chloride[id] * chloride::mengvolume(config, sim.waterstanden[id]))
+                .sum()
+        };
+        let begin = massa(&simulatie);
+        let resultaat = simulatie.simuleer(&HashMap::new(), 4, &SimpeleUitstroomStrategy).unwrap();
+
+        // Het zoute water stroomt naar het zoete peilgebied
+        let laatste = &resultaat.tijdstappen.last().unwrap().chloride;
+        assert!(laatste["zoet"] > 100.0);
+        assert!(laatste["zout"] <= 800.0 + 1e-9);
+        assert!((massa(&simulatie) - begin).abs() < 1e-6 * begin);
+    }
+
+    #[test]
+    fn test_chloride_zoute_kwel_en_regen() {
+        let kwel = Kwelrelatie {
+            stijghoogte: 0.0,
+            weerstand_dagen: 50.0,
+        };
+        let topologie = |regen: bool| {
+            let mut topologie = NetwerkTopologie::nieuw();
+            let config = chloride_peilgebied("polder", -1.0, ChlorideParameters::new(200.0, 2000.0))
+                .met_kwel(kwel);
+            topologie.voeg_peilgebied_toe(config).unwrap();
+            let regen = HashMap::from([("polder".to_string(), vec![if regen { 5.0 } else { 0.0 }; 24])]);
+            (topologie, regen)
+        };
+
+        let (droog, geen_regen) = topologie(false);
+        let droog = NetwerkSimulatie::nieuw(droog)
+            .unwrap()
+            .simuleer(&geen_regen, 24, &SimpeleUitstroomStrategy)
+            .unwrap();
+        let (nat, regen) = topologie(true);
+        let nat = NetwerkSimulatie::nieuw(nat)
+            .unwrap()
+            .simuleer(&regen, 24, &SimpeleUitstroomStrategy)
+            .unwrap();
+
+        let concentratie = |r: &NetwerkSimulatieResultaat| r.tijdstappen.last().unwrap().chloride["polder"];
+        // Zoute kwel verzilt het peilgebied, regen verdunt
+        assert!(concentratie(&droog) > 200.0 && concentratie(&droog) < 2000.0);
+        assert!(concentratie(&nat) < concentratie(&droog));
+
+        // Zonder chloride blijft het resultaat zonder concentraties
+        let zonder = run_netwerksimulatie(&maak_test_topologie(), &HashMap::new(), 1, &SimpeleUitstroomStrategy)
+            .unwrap();
+        assert!(zonder.tijdstappen.iter().all(|stap| stap.chloride.is_empty()));
+
+        let mut ongeldig = NetwerkTopologie::nieuw();
+        let config = chloride_peilgebied("polder", -1.0, ChlorideParameters::new(-5.0, 0.0));
+        assert!(matches!(
+            ongeldig.voeg_peilgebied_toe(config),
+            Err(NetwerkFout::OngeldigeChloride { .. })
+        ));
+    }
+
+    #[test]
+    fn test_stuw_strategy_regelt_kruin() {
+        let regen = HashMap::from([("polder".to_string(), vec![10.0; 6])]);
+        let vast = NetwerkSimulatie::nieuw(stuw_topologie())
+            .unwrap()
+            .simuleer(&regen, 6, &SimpeleUitstroomStrategy)
+            .unwrap();
+        let strategy = StuwStrategy::nieuw(SimpeleUitstroomStrategy).met_max_verstelling(0.005);
+        let geregeld = NetwerkSimulatie::nieuw(stuw_topologie())
+            .unwrap()
+            .simuleer(&regen, 6, &strategy)
+            .unwrap();
+
+        let max_peil = |resultaat: &NetwerkSimulatieResultaat| {
+            resultaat
+                .tijdstappen
+                .iter()
+                .map(|stap| stap.statussen["polder"].waterstand)
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        // De vaste kruin ligt boven streefpeil: het peil stijgt tot erboven
+        assert!(max_peil(&vast) > -0.55);
+        assert!(vast.tijdstappen.iter().all(|stap| stap.kruinhoogten["stuw"] == -0.55));
+
+        // De geregelde kruin zakt, met hoogstens de ingestelde verstelling
+        // per stap, en houdt het peil dichter bij streefpeil
+        let kruinen: Vec<f64> = geregeld
+            .tijdstappen
+            .iter()
+            .map(|stap| stap.kruinhoogten["stuw"])
+            .collect();
+        assert!(kruinen.windows(2).all(|k| (k[1] - k[0]).abs() <= 0.005 + 1e-12));
+        assert!(*kruinen.last().unwrap() < -0.60);
+        assert!(kruinen.iter().all(|&k| k >= -0.90));
+        assert!(max_peil(&geregeld) < max_peil(&vast));
+    }
+
+    #[test]
+    fn test_duikerdebiet() {
+        let duiker = DuikerParameters::new(0.8, 20.0);
+        // Wrijving: 2g·n²·L / R^(4/3) met R = 0.2 m
+        let wrijving = 2.0 * 9.81 * 0.013f64.powi(2) * 20.0 / 0.2f64.powf(4.0 / 3.0);
+        assert!((duiker.verliescoefficient() - (1.5 + wrijving)).abs() < 1e-12);
+
+        // Het debiet groeit met de wortel van het verval
+        let q = duiker.debiet(0.05);
+        assert!((duiker.debiet(0.20) - 2.0 * q).abs() < 1e-12);
+        assert_eq!(duiker.debiet(0.0), 0.0);
+        // Een langere, ruwere duiker voert minder af
+        assert!(DuikerParameters::new(0.8, 100.0).met_manning(0.025).debiet(0.05) < q);
+
+        assert!(DuikerParameters::new(0.0, 20.0).valideer().is_err());
+        assert!(
+            DuikerParameters::new(0.8, 0.0)
+                .met_verliezen(0.0, 0.0)
+                .valideer()
+                .is_err()
+        );
+        assert!(matches!(
+            Verbinding::nieuw_duiker(
+                "d".to_string(),
+                "a".to_string(),
+                "b".to_string(),
+                1.0,
+                DuikerParameters::new(-1.0, 20.0),
+            ),
+            Err(NetwerkFout::OngeldigeDuiker { .. })
+        ));
+    }
+
+    #[test]
+    fn test_duiker_tweerichting() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        for (id, streefpeil) in [("a", -0.60), ("b", -0.70)] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: None,
+                    oppervlakte: 100_000.0,
+                    streefpeil,
+                    marge: 0.20,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet: 0.0,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        let duiker = DuikerParameters::new(0.6, 15.0);
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_duiker("d".to_string(), "a".to_string(), "b".to_string(), 2.0, duiker)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let regen = HashMap::new();
+        let simulatie = NetwerkSimulatie::nieuw(topologie).unwrap();
+        let stroom = &simulatie.bereken_stromen(&regen).unwrap()[0];
+        assert_eq!(stroom.richting, StroomRichting::Naar);
+        assert!((stroom.debiet - duiker.debiet(0.10)).abs() < 1e-12);
+
+        let simulatie = simulatie.met_start_waterstand("a", -0.75).unwrap();
+        let stroom = &simulatie.bereken_stromen(&regen).unwrap()[0];
+        assert_eq!(stroom.richting, StroomRichting::Terug);
+        assert!((stroom.debiet - duiker.debiet(0.05)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_boezem_als_randknooppunt() {
+        let polder = |id: &str| PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.5,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        };
+
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(polder("polder").met_boezem("boezem"))
+            .unwrap();
+        topologie.voeg_peilgebied_toe(polder("laag")).unwrap();
+        topologie
+            .voeg_boezem_toe(Boezem::nieuw("boezem", -0.40).met_peilverloop(vec![-0.40, -0.55]))
+            .unwrap();
+        assert_eq!(
+            topologie.voeg_boezem_toe(Boezem::nieuw("laag", 0.0)),
+            Err(NetwerkFout::KnooppuntBestaatAl { id: "laag".to_string() })
+        );
+
+        // Zonder verbinding ligt "laag" los van het netwerk
+        assert_eq!(topologie.valideer(), Err(NetwerkFout::NietVerbonden));
+        let duiker = DuikerParameters::new(0.5, 10.0);
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_duiker(
+                    "duiker".to_string(),
+                    "boezem".to_string(),
+                    "laag".to_string(),
+                    1.0,
+                    duiker,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(topologie.valideer().is_ok());
+
+        // Belasting: uitslag van de polder min de stroom naar "laag"
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone()).unwrap();
+        assert_eq!(simulatie.waterstanden["boezem"], -0.40);
+        let regen = HashMap::from([("polder".to_string(), 20.0)]);
+        let mut uitgeslagen = false;
+        for _ in 0..60 {
+            let naar_laag = simulatie.bereken_consistente_stromen(simulatie.stap_minuten).unwrap()[0].debiet;
+            let statussen = simulatie.simuleer_stap(&regen, &SimpeleUitstroomStrategy).unwrap();
+            assert_eq!(statussen.len(), 2);
+            let uitslag = statussen.iter().find(|s| s.id == "polder").unwrap().uitstroom_debiet;
+            uitgeslagen |= uitslag > 0.0;
+            assert!((simulatie.boezembelasting["boezem"] - (uitslag - naar_laag)).abs() < 1e-12);
+        }
+        assert!(uitgeslagen);
+
+        // Het boezempeil volgt het opgelegde verloop, niet de belasting
+        assert_eq!(simulatie.waterstanden["boezem"], -0.55);
+
+        let resultaat =
+            run_netwerksimulatie(&topologie, &HashMap::new(), 1, &SimpeleUitstroomStrategy).unwrap();
+        assert!(resultaat.tijdstappen[0].boezembelasting.contains_key("boezem"));
+
+        // Een polder die naar een onbekende boezem uitslaat is ongeldig
+        topologie
+            .peilgebieden
+            .insert("los".to_string(), polder("los").met_boezem("elders"));
+        assert!(matches!(
+            topologie.valideer(),
+            Err(NetwerkFout::PeilgebiedNietGevonden { .. })
+        ));
+    }
+
+    #[test]
+    fn test_valideer_rapport() {
+        // 100 ha per peilgebied; 0.15 m³/s is 13 mm/dag
+        let peilgebied = |id: &str, streefpeil: f64, max_uitstroom_debiet: f64| PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 1_000_000.0,
+            streefpeil,
+            marge: 0.10,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        };
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie.voeg_peilgebied_toe(peilgebied("hoog", -0.40, 0.0)).unwrap();
+        topologie.voeg_peilgebied_toe(peilgebied("laag", -1.00, 0.15)).unwrap();
+        topologie.voeg_peilgebied_toe(peilgebied("kom", -0.80, 0.0)).unwrap();
+        topologie.voeg_peilgebied_toe(peilgebied("groot", -0.60, 2.0)).unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_overstort(
+                    "overstort".to_string(),
+                    "hoog".to_string(),
+                    "laag".to_string(),
+                    1.0,
+                    -0.50,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let mut gemaal = Verbinding::nieuw_gemaal(
+            "gemaal".to_string(),
+            "laag".to_string(),
+            "kom".to_string(),
+            0.01,
+            1.0,
+        )
+        .unwrap();
+        gemaal.opvoerhoogte = None;
+        topologie.voeg_verbinding_toe(gemaal).unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_duiker(
+                    "duiker".to_string(),
+                    "groot".to_string(),
+                    "laag".to_string(),
+                    1.0,
+                    DuikerParameters::new(0.5, 10.0),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let rapport = topologie.valideer_rapport();
+        assert!(rapport.is_geldig());
+        let objecten: Vec<&str> = rapport
+            .waarschuwingen()
+            .filter_map(|b| b.object.as_deref())
+            .collect();
+        // Gemaal zonder opvoerhoogte, drempel onder streefpeil van "hoog",
+        // "groot" maalt 173 mm/dag en "kom" krijgt alleen water binnen;
+        // "hoog" watert af via de overstort
+        assert_eq!(objecten, ["gemaal", "overstort", "groot", "kom"]);
+
+        // Een verbinding naar een onbekend knooppunt is een fout
+        let mut los = Verbinding::nieuw_keerklep(
+            "los".to_string(),
+            "laag".to_string(),
+            "nergens".to_string(),
+            1.0,
+        )
+        .unwrap();
+        los.capaciteit = f64::NAN;
+        topologie.verbindingen.insert("los".to_string(), los);
+        let rapport = topologie.valideer_rapport();
+        assert!(!rapport.is_geldig());
+        assert_eq!(rapport.fouten().count(), 3);
+    }
+
+    #[test]
+    fn test_configureerbare_tijdstap() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 100_000.0,
+                streefpeil: -0.60,
+                marge: 0.20,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet: 0.0,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        let regen = HashMap::from([("polder".to_string(), vec![6.0, 0.0])]);
+
+        let simulatie = NetwerkSimulatie::nieuw(topologie.clone()).unwrap();
+        assert!(simulatie.clone().met_tijdstap(0.0).is_err());
+        assert!(simulatie.clone().met_tijdstap(f64::NAN).is_err());
+
+        // Stappen van 7 minuten: elk uur eindigt met een stap van 4 minuten
+        let mut grof = simulatie.clone().met_tijdstap(7.0).unwrap();
+        let resultaat = grof.simuleer(&regen, 2, &SimpeleUitstroomStrategy).unwrap();
+        assert_eq!(resultaat.tijdstappen.len(), 18);
+        assert_eq!(resultaat.tijdstappen[0].duur, 7.0);
+        assert!((resultaat.tijdstappen[8].duur - 4.0).abs() < 1e-9);
+        assert!((resultaat.tijdstappen[8].tijd - 60.0).abs() < 1e-9);
+        assert!((resultaat.tijdstappen[17].duur - 4.0).abs() < 1e-9);
+        assert!((resultaat.tijdstappen[17].tijd - 120.0).abs() < 1e-9);
+        // De regen van het eerste uur valt precies in het eerste uur
+        assert!((grof.waterstanden["polder"] - (-0.60 + 0.006)).abs() < 1e-9);
+
+        // Adaptief: 6 mm/uur is 0.1 mm/min, dus stappen van 2 minuten
+        let mut adaptief = simulatie
+            .clone()
+            .met_tijdstap(10.0)
+            .unwrap()
+            .met_adaptieve_tijdstap(AdaptieveTijdstap {
+                max_peilverandering: 0.0002,
+                min_stap_minuten: 0.5,
+            })
+            .unwrap();
+        let resultaat = adaptief.simuleer(&regen, 2, &SimpeleUitstroomStrategy).unwrap();
+        assert!((resultaat.tijdstappen[0].duur - 2.0).abs() < 1e-9);
+        // Zonder regen blijft het peil gelijk en is de stap weer 10 minuten
+        assert_eq!(resultaat.tijdstappen.last().unwrap().duur, 10.0);
+        assert_eq!(resultaat.tijdstappen.len(), 30 + 6);
+        assert!((adaptief.waterstanden["polder"] - (-0.60 + 0.006)).abs() < 1e-9);
+
+        // Stabiliteitscontrole: ook bij de kleinste stap te snel
+        let mut instabiel = simulatie
+            .clone()
+            .met_adaptieve_tijdstap(AdaptieveTijdstap {
+                max_peilverandering: 0.00001,
+                min_stap_minuten: 0.5,
+            })
+            .unwrap();
+        assert!(matches!(
+            instabiel.simuleer(&regen, 1, &SimpeleUitstroomStrategy),
+            Err(NetwerkFout::InstabieleTijdstap { .. })
+        ));
+        assert!(
+            simulatie
+                .clone()
+                .met_adaptieve_tijdstap(AdaptieveTijdstap {
+                    max_peilverandering: 0.01,
+                    min_stap_minuten: 2.0,
+                })
+                .is_err()
+        );
+        // Ook andersom: een vaste stap kleiner dan de kleinste adaptieve stap
+        assert!(matches!(
+            simulatie
+                .met_adaptieve_tijdstap(AdaptieveTijdstap {
+                    max_peilverandering: 0.01,
+                    min_stap_minuten: 0.5,
+                })
+                .unwrap()
+                .met_tijdstap(0.25),
+            Err(NetwerkFout::OngeldigeTijdstap { .. })
+        ));
+    }
+
+    #[test]
+    fn test_overlaatdebiet() {
+        let overstort = Verbinding::nieuw_overstort(
+            "overstort".to_string(),
+            "hoog".to_string(),
+            "laag".to_string(),
+            5.0,
+            -0.50,
+        )
+        .unwrap()
+        .met_overlaat(2.0, 1.7);
+
+        // Volkomen afvoer: Q = C·B·h^1.5, onafhankelijk van benedenstrooms
+        let vrij = 1.7 * 2.0 * 0.3f64.powf(1.5);
+        assert!((overstort.overlaatdebiet(-0.20, -0.80) - vrij).abs() < 1e-12);
+        assert!((overstort.overlaatdebiet(-0.20, -0.40) - vrij).abs() < 1e-12);
+        // Verdronken: sluit aan bij 2/3·h en neemt daarna af tot nul
+        assert!((overstort.overlaatdebiet(-0.20, -0.30 + 1e-9) - vrij).abs() < 1e-6);
+        let verdronken = overstort.overlaatdebiet(-0.20, -0.25);
+        assert!(verdronken > 0.0 && verdronken < vrij);
+        assert_eq!(overstort.overlaatdebiet(-0.20, -0.20), 0.0);
+        // Onder de kruin en boven de capaciteit
+        assert_eq!(overstort.overlaatdebiet(-0.55, -0.80), 0.0);
+        assert_eq!(overstort.overlaatdebiet(1.0, -0.80), 5.0);
+    }
+
+    #[test]
+    fn test_voortgang_en_annulering() {
+        let topologie = maak_test_topologie();
+        let regen = HashMap::from([("polder_a".to_string(), vec![10.0; 4])]);
+
+        let mut meldingen = Vec::new();
+        let resultaat = run_netwerksimulatie_met_voortgang(
+            &topologie,
+            &regen,
+            4,
+            &SimpeleUitstroomStrategy,
+            |v| meldingen.push(v.clone()),
+            &Annulering::nieuw(),
+        )
+        .unwrap();
+        assert_eq!(meldingen.len(), resultaat.tijdstappen.len());
+        assert!(meldingen.windows(2).all(|w| w[1].percentage > w[0].percentage));
+        let laatste = meldingen.last().unwrap();
+        assert!((laatste.percentage - 100.0).abs() < 1e-9);
+        assert_eq!(laatste.tijd, 240.0);
+        let hoogste = resultaat
+            .tijdstappen
+            .iter()
+            .map(|t| t.statussen["polder_a"].waterstand)
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert_eq!(laatste.max_waterstand["polder_a"], hoogste);
+
+        // Annuleren halverwege vanuit de voortgangscallback
+        let annulering = Annulering::nieuw();
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie).unwrap();
+        let fout = simulatie
+            .simuleer_met_voortgang(
+                &regen,
+                4,
+                &SimpeleUitstroomStrategy,
+                |v| {
+                    if v.percentage >= 50.0 {
+                        annulering.annuleer();
+                    }
+                },
+                &annulering,
+            )
+            .unwrap_err();
+        assert_eq!(fout, NetwerkFout::Geannuleerd { tijd: 120.0 });
+        assert_eq!(simulatie.tijd, 120.0);
+    }
 }
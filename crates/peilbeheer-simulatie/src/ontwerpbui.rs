@@ -0,0 +1,214 @@
+//! Bibliotheek met ontwerpbuien voor toetsing aan de normen.
+//!
+//! Een ontwerpbui is de neerslagsom van een herhalingstijd en duur uit de
+//! STOWA-neerslagstatistiek, verdeeld over de uren volgens een vast
+//! patroon. Omdat de buien vastliggen, geeft een toetsing met dezelfde bui
+//! altijd dezelfde invoer.
+//!
+//! De sommen zijn afgeronde jaarrondwaarden voor het huidige klimaat,
+//! landelijk gemiddeld. Voor een klimaatscenario kan het regenscenario
+//! daarna worden omgezet met [`Regenscenario::naar_klimaat`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::PeilgebiedId;
+use crate::scenario::{Regenscenario, RegenscenarioType};
+
+/// Herhalingstijden in de bibliotheek (jaar).
+pub const HERHALINGSTIJDEN: [u32; 3] = [10, 25, 100];
+
+/// Duren in de bibliotheek (uur).
+pub const DUREN_UREN: [usize; 7] = [1, 2, 4, 8, 12, 24, 48];
+
+/// Neerslagsommen (mm) per herhalingstijd, in de volgorde van [`DUREN_UREN`].
+const NEERSLAGSOMMEN: [[f64; 7]; 3] = [
+    [25.0, 30.0, 35.0, 41.0, 45.0, 53.0, 65.0],
+    [32.0, 38.0, 44.0, 51.0, 55.0, 64.0, 77.0],
+    [45.0, 52.0, 59.0, 66.0, 71.0, 81.0, 95.0],
+];
+
+/// Verdeling van de neerslag over de duur van de bui.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Buipatroon {
+    /// Gelijkmatig over alle uren
+    Uniform,
+    /// Piek in het eerste kwart
+    Voorpiek,
+    /// Piek in het midden
+    Middenpiek,
+    /// Piek in het laatste kwart
+    Achterpiek,
+}
+
+impl Buipatroon {
+    pub fn alle() -> [Self; 4] {
+        [
+            Self::Uniform,
+            Self::Voorpiek,
+            Self::Middenpiek,
+            Self::Achterpiek,
+        ]
+    }
+
+    fn naam(&self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::Voorpiek => "voorpiek",
+            Self::Middenpiek => "middenpiek",
+            Self::Achterpiek => "achterpiek",
+        }
+    }
+
+    /// Relatieve gewichten van de uren; een driehoek met de top op de
+    /// piek en aan de randen een tiende van de top.
+    fn gewichten(&self, uren: usize) -> Vec<f64> {
+        let piek = match self {
+            Self::Uniform => return vec![1.0; uren],
+            Self::Voorpiek => 0.25,
+            Self::Middenpiek => 0.5,
+            Self::Achterpiek => 0.75,
+        };
+        let breedte = f64::max(piek, 1.0 - piek);
+        (0..uren)
+            .map(|uur| {
+                let positie = (uur as f64 + 0.5) / uren as f64;
+                1.0 - 0.9 * (positie - piek).abs() / breedte
+            })
+            .collect()
+    }
+}
+
+/// Ontwerpbui met de neerslag per uur.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ontwerpbui {
+    /// Bijvoorbeeld `T100_24u_middenpiek`
+    pub id: String,
+    /// Herhalingstijd in jaren
+    pub herhalingstijd: u32,
+    pub duur_uren: usize,
+    pub patroon: Buipatroon,
+    /// Neerslagsom van de bui (mm)
+    pub totaal_mm: f64,
+    /// Neerslag per uur (mm/uur)
+    pub regen_per_uur: Vec<f64>,
+}
+
+impl Ontwerpbui {
+    /// Bui uit de bibliotheek; `None` voor een herhalingstijd of duur die
+    /// er niet in staat.
+    pub fn nieuw(herhalingstijd: u32, duur_uren: usize, patroon: Buipatroon) -> Option<Self> {
+        let t = HERHALINGSTIJDEN.iter().position(|&h| h == herhalingstijd)?;
+        let d = DUREN_UREN.iter().position(|&u| u == duur_uren)?;
+        let totaal_mm = NEERSLAGSOMMEN[t][d];
+
+        let gewichten = patroon.gewichten(duur_uren);
+        let som: f64 = gewichten.iter().sum();
+        Some(Self {
+            id: format!("T{}_{}u_{}", herhalingstijd, duur_uren, patroon.naam()),
+            herhalingstijd,
+            duur_uren,
+            patroon,
+            totaal_mm,
+            regen_per_uur: gewichten.iter().map(|g| totaal_mm * g / som).collect(),
+        })
+    }
+
+    /// Alle buien: elke herhalingstijd, duur en elk patroon. Bij een duur
+    /// van één uur is alleen de uniforme bui opgenomen.
+    pub fn bibliotheek() -> Vec<Self> {
+        let mut buien = Vec::new();
+        for herhalingstijd in HERHALINGSTIJDEN {
+            for duur in DUREN_UREN {
+                for patroon in Buipatroon::alle() {
+                    if duur == 1 && patroon != Buipatroon::Uniform {
+                        continue;
+                    }
+                    buien.extend(Self::nieuw(herhalingstijd, duur, patroon));
+                }
+            }
+        }
+        buien
+    }
+
+    /// Bui met een id uit de bibliotheek.
+    pub fn zoek(id: &str) -> Option<Self> {
+        Self::bibliotheek().into_iter().find(|bui| bui.id == id)
+    }
+
+    /// Regenscenario met deze bui op alle peilgebieden, gevolgd door
+    /// droge uren tot `duur_uren` als die langer is dan de bui.
+    pub fn regenscenario(
+        &self,
+        peilgebied_ids: &[PeilgebiedId],
+        duur_uren: usize,
+    ) -> Regenscenario {
+        let mut reeks = self.regen_per_uur.clone();
+        if reeks.len() < duur_uren {
+            reeks.resize(duur_uren, 0.0);
+        }
+        Regenscenario {
+            regen_per_uur: peilgebied_ids
+                .iter()
+                .map(|id| (id.clone(), reeks.clone()))
+                .collect(),
+            scenario_type: RegenscenarioType::Ontworpen,
+            periode: None,
+            klimaatscenario: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ontwerpbui_som_en_patroon() {
+        let bui = Ontwerpbui::nieuw(100, 24, Buipatroon::Middenpiek).unwrap();
+        assert_eq!(bui.id, "T100_24u_middenpiek");
+        assert_eq!(bui.regen_per_uur.len(), 24);
+        assert!((bui.regen_per_uur.iter().sum::<f64>() - bui.totaal_mm).abs() < 1e-9);
+
+        let piek = |b: &Ontwerpbui| {
+            (0..b.regen_per_uur.len())
+                .max_by(|&i, &j| b.regen_per_uur[i].total_cmp(&b.regen_per_uur[j]))
+                .unwrap()
+        };
+        assert!(piek(&bui) == 11 || piek(&bui) == 12);
+        assert!(piek(&Ontwerpbui::nieuw(100, 24, Buipatroon::Voorpiek).unwrap()) < 8);
+        assert!(piek(&Ontwerpbui::nieuw(100, 24, Buipatroon::Achterpiek).unwrap()) > 16);
+
+        let uniform = Ontwerpbui::nieuw(10, 4, Buipatroon::Uniform).unwrap();
+        assert!(uniform.regen_per_uur.iter().all(|&r| r == 35.0 / 4.0));
+
+        assert!(Ontwerpbui::nieuw(50, 24, Buipatroon::Uniform).is_none());
+        assert!(Ontwerpbui::nieuw(10, 3, Buipatroon::Uniform).is_none());
+    }
+
+    #[test]
+    fn test_bibliotheek() {
+        let buien = Ontwerpbui::bibliotheek();
+        assert_eq!(buien.len(), 3 * (1 + 6 * 4));
+        // Een zeldzamere of langere bui heeft een grotere som
+        for bui in &buien {
+            for ander in &buien {
+                if ander.herhalingstijd >= bui.herhalingstijd && ander.duur_uren >= bui.duur_uren {
+                    assert!(ander.totaal_mm >= bui.totaal_mm);
+                }
+            }
+        }
+        assert_eq!(
+            Ontwerpbui::zoek("T25_8u_achterpiek").unwrap().totaal_mm,
+            51.0
+        );
+        assert!(Ontwerpbui::zoek("T25_3u_uniform").is_none());
+
+        let scenario = Ontwerpbui::zoek("T10_2u_voorpiek")
+            .unwrap()
+            .regenscenario(&["a".to_string()], 24);
+        assert_eq!(scenario.scenario_type, RegenscenarioType::Ontworpen);
+        assert_eq!(scenario.regen_per_uur["a"].len(), 24);
+        assert_eq!(scenario.regen_per_uur["a"][5], 0.0);
+    }
+}
@@ -0,0 +1,474 @@
+//! Monte Carlo-onzekerheidsanalyse over netwerksimulaties.
+//!
+//! Elke run trekt de gemaalcapaciteiten, de kwel en het neerslagvolume uit
+//! een normale verdeling rond de invoer en simuleert het netwerk. Over alle
+//! runs geeft [`OnzekerheidsResultaat`] percentielbanden van de waterstand
+//! per uur, percentielen van de pompkosten en per peilgebied de kans dat
+//! de bovengrens van de marge wordt overschreden.
+//!
+//! De runs worden over de beschikbare kernen verdeeld. Elke run heeft een
+//! eigen zaad, afgeleid van [`MonteCarloAnalyse::zaad`], zodat de uitkomst
+//! niet afhangt van het aantal threads.
+
+use std::collections::HashMap;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{
+    NetwerkFout, NetwerkSimulatie, NetwerkSimulatieResultaat, NetwerkTopologie, PeilgebiedId,
+};
+use crate::optimalisatie::calculate_pump_power_kw;
+use crate::scenario::{StrategyType, Toeval};
+
+/// Spreiding van de onzekere parameters (één standaardafwijking).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Onzekerheid {
+    /// Relatieve spreiding van de capaciteit van elk gemaal en elke
+    /// uitstroompomp
+    pub capaciteit: f64,
+    /// Spreiding van de kwel per peilgebied in mm/dag
+    pub kwel: f64,
+    /// Relatieve spreiding van het neerslagvolume, gelijk voor het hele
+    /// netwerk
+    pub neerslag: f64,
+}
+
+impl Default for Onzekerheid {
+    fn default() -> Self {
+        Self {
+            capaciteit: 0.10,
+            kwel: 0.5,
+            neerslag: 0.20,
+        }
+    }
+}
+
+/// Uitgangspunten voor de pompkosten van een run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KostenParameters {
+    /// Opvoerhoogte van de uitstroomgemalen (m)
+    pub opvoerhoogte: f64,
+    /// Rendement van de uitstroomgemalen
+    pub rendement: f64,
+    /// Energieprijs (€/kWh)
+    pub prijs_per_kwh: f64,
+}
+
+impl Default for KostenParameters {
+    fn default() -> Self {
+        Self {
+            opvoerhoogte: 2.0,
+            rendement: 0.70,
+            prijs_per_kwh: 0.25,
+        }
+    }
+}
+
+/// Instellingen van een Monte Carlo-analyse.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonteCarloAnalyse {
+    /// Aantal runs
+    pub runs: usize,
+    /// Zaad van de trekkingen
+    pub zaad: u64,
+    pub onzekerheid: Onzekerheid,
+    pub kosten: KostenParameters,
+    /// Percentielen van de banden (0–100)
+    pub percentielen: Vec<f64>,
+}
+
+/// Waarden bij één percentiel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Percentielband {
+    /// Percentiel (0–100)
+    pub percentiel: f64,
+    /// Waarde per uur, of één waarde bij een enkele grootheid
+    pub waarden: Vec<f64>,
+}
+
+/// Uitkomst van een Monte Carlo-analyse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnzekerheidsResultaat {
+    /// Aantal runs
+    pub runs: usize,
+    /// Waterstand (m NAP) aan het eind van elk uur, per peilgebied en
+    /// percentiel
+    pub waterstanden: HashMap<PeilgebiedId, Vec<Percentielband>>,
+    /// Hoogste waterstand van een run, per peilgebied en percentiel
+    pub max_waterstanden: HashMap<PeilgebiedId, Vec<Percentielband>>,
+    /// Pompkosten (€) per percentiel
+    pub kosten: Vec<Percentielband>,
+    /// Gemiddelde pompkosten (€)
+    pub gemiddelde_kosten: f64,
+    /// Fractie van de runs waarin het peil boven streefpeil + marge komt
+    pub overschrijdingskans: HashMap<PeilgebiedId, f64>,
+}
+
+/// Uitkomst van één run.
+struct Run {
+    /// Waterstand aan het eind van elk uur per peilgebied
+    waterstanden: HashMap<PeilgebiedId, Vec<f64>>,
+    max_waterstanden: HashMap<PeilgebiedId, f64>,
+    kosten: f64,
+}
+
+impl MonteCarloAnalyse {
+    /// Analyse met `runs` runs, standaardspreiding en de 5-, 50- en
+    /// 95-percentielen.
+    pub fn nieuw(runs: usize) -> Self {
+        Self {
+            runs,
+            zaad: 1,
+            onzekerheid: Onzekerheid::default(),
+            kosten: KostenParameters::default(),
+            percentielen: vec![5.0, 50.0, 95.0],
+        }
+    }
+
+    pub fn met_zaad(mut self, zaad: u64) -> Self {
+        self.zaad = zaad;
+        self
+    }
+
+    pub fn met_onzekerheid(mut self, onzekerheid: Onzekerheid) -> Self {
+        self.onzekerheid = onzekerheid;
+        self
+    }
+
+    pub fn met_kosten(mut self, kosten: KostenParameters) -> Self {
+        self.kosten = kosten;
+        self
+    }
+
+    pub fn met_percentielen(mut self, percentielen: Vec<f64>) -> Self {
+        self.percentielen = percentielen;
+        self
+    }
+
+    /// Voer alle runs uit en vat ze samen.
+    ///
+    /// Elke run krijgt een eigen strategy van `strategy_type`, zodat de
+    /// toestand van de ene run (zoals een geopende inlaat) de andere niet
+    /// beïnvloedt.
+    pub fn voer_uit(
+        &self,
+        topologie: &NetwerkTopologie,
+        regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+        duration_hours: usize,
+        strategy_type: &StrategyType,
+    ) -> Result<OnzekerheidsResultaat, NetwerkFout> {
+        topologie.valideer()?;
+        let runs = parallel(self.runs, |run| {
+            self.run(
+                run,
+                topologie,
+                regen_scenario,
+                duration_hours,
+                strategy_type,
+            )
+        })
+        .into_iter()
+        .collect::<Result<Vec<Run>, NetwerkFout>>()?;
+
+        Ok(self.vat_samen(topologie, &runs))
+    }
+
+    /// Eén run met eigen trekkingen.
+    fn run(
+        &self,
+        run: usize,
+        topologie: &NetwerkTopologie,
+        regen_scenario: &HashMap<PeilgebiedId, Vec<f64>>,
+        duration_hours: usize,
+        strategy_type: &StrategyType,
+    ) -> Result<Run, NetwerkFout> {
+        let mut toeval = Toeval(
+            self.zaad
+                .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                .wrapping_add(run as u64),
+        );
+        let spreiding = self.onzekerheid;
+        let mut topologie = topologie.clone();
+
+        // Vaste volgorde, zodat de trekkingen niet van de hashvolgorde afhangen
+        let neerslagfactor = (1.0 + spreiding.neerslag * toeval.normaal()).max(0.0);
+        let mut ids: Vec<PeilgebiedId> = topologie.peilgebieden.keys().cloned().collect();
+        ids.sort();
+        for id in &ids {
+            let config = topologie
+                .peilgebieden
+                .get_mut(id)
+                .expect("id uit de topologie");
+            config.max_uitstroom_debiet *= (1.0 + spreiding.capaciteit * toeval.normaal()).max(0.0);
+            // Kwel is negatieve infiltratie
+            config.infiltratie -= spreiding.kwel / 24.0 * toeval.normaal();
+        }
+        let mut verbindingen: Vec<_> = topologie.verbindingen.keys().cloned().collect();
+        verbindingen.sort();
+        for id in &verbindingen {
+            let verbinding = topologie
+                .verbindingen
+                .get_mut(id)
+                .expect("id uit de topologie");
+            if verbinding.verbinding_type.is_actief() {
+                verbinding.capaciteit *= (1.0 + spreiding.capaciteit * toeval.normaal()).max(0.0);
+            }
+        }
+
+        let regen: HashMap<PeilgebiedId, Vec<f64>> = regen_scenario
+            .iter()
+            .map(|(id, reeks)| {
+                (
+                    id.clone(),
+                    reeks.iter().map(|r| r * neerslagfactor).collect(),
+                )
+            })
+            .collect();
+        let resultaat = NetwerkSimulatie::nieuw(topologie.clone())?.simuleer(
+            &regen,
+            duration_hours,
+            strategy_type.strategy().as_ref(),
+        )?;
+        Ok(self.verwerk(&topologie, &resultaat, &ids))
+    }
+
+    /// Waterstanden per uur, hoogste waterstanden en kosten van een run.
+    fn verwerk(
+        &self,
+        topologie: &NetwerkTopologie,
+        resultaat: &NetwerkSimulatieResultaat,
+        ids: &[PeilgebiedId],
+    ) -> Run {
+        let mut waterstanden: HashMap<PeilgebiedId, Vec<f64>> =
+            ids.iter().map(|id| (id.clone(), Vec::new())).collect();
+        let mut max_waterstanden: HashMap<PeilgebiedId, f64> = ids
+            .iter()
+            .map(|id| (id.clone(), f64::NEG_INFINITY))
+            .collect();
+        let mut kosten = 0.0;
+        let mut volgend_uur = 60.0;
+
+        for stap in &resultaat.tijdstappen {
+            let uren = stap.duur / 60.0;
+            for status in stap.statussen.values() {
+                if let Some(max) = max_waterstanden.get_mut(&status.id) {
+                    *max = max.max(status.waterstand);
+                }
+                let vermogen = calculate_pump_power_kw(
+                    status.uitstroom_debiet,
+                    self.kosten.opvoerhoogte,
+                    self.kosten.rendement,
+                );
+                kosten += vermogen * uren * self.kosten.prijs_per_kwh;
+            }
+            for stroom in &stap.stromen {
+                let vermogen = topologie.pompvermogen_kw(stroom, stap).unwrap_or(0.0);
+                kosten += vermogen * uren * self.kosten.prijs_per_kwh;
+            }
+            if stap.tijd >= volgend_uur - 1e-6 {
+                for (id, reeks) in waterstanden.iter_mut() {
+                    if let Some(status) = stap.statussen.get(id) {
+                        reeks.push(status.waterstand);
+                    }
+                }
+                volgend_uur += 60.0;
+            }
+        }
+
+        Run {
+            waterstanden,
+            max_waterstanden,
+            kosten,
+        }
+    }
+
+    fn vat_samen(&self, topologie: &NetwerkTopologie, runs: &[Run]) -> OnzekerheidsResultaat {
+        let banden = |waarden: &mut Vec<Vec<f64>>| -> Vec<Percentielband> {
+            waarden.iter_mut().for_each(|w| w.sort_by(f64::total_cmp));
+            self.percentielen
+                .iter()
+                .map(|&p| Percentielband {
+                    percentiel: p,
+                    waarden: waarden.iter().map(|w| percentiel(w, p)).collect(),
+                })
+                .collect()
+        };
+
+        let mut waterstanden = HashMap::new();
+        let mut max_waterstanden = HashMap::new();
+        let mut overschrijdingskans = HashMap::new();
+        for (id, config) in &topologie.peilgebieden {
+            let uren = runs
+                .iter()
+                .map(|r| r.waterstanden[id].len())
+                .min()
+                .unwrap_or(0);
+            let mut per_uur: Vec<Vec<f64>> = (0..uren)
+                .map(|uur| runs.iter().map(|r| r.waterstanden[id][uur]).collect())
+                .collect();
+            waterstanden.insert(id.clone(), banden(&mut per_uur));
+
+            let mut max = vec![
+                runs.iter()
+                    .map(|r| r.max_waterstanden[id])
+                    .collect::<Vec<_>>(),
+            ];
+            let grens = config.streefpeil + config.marge;
+            let boven = max[0].iter().filter(|&&w| w > grens).count();
+            max_waterstanden.insert(id.clone(), banden(&mut max));
+            overschrijdingskans.insert(id.clone(), boven as f64 / runs.len().max(1) as f64);
+        }
+
+        let mut kosten = vec![runs.iter().map(|r| r.kosten).collect::<Vec<_>>()];
+        let gemiddelde_kosten = kosten[0].iter().sum::<f64>() / runs.len().max(1) as f64;
+
+        OnzekerheidsResultaat {
+            runs: runs.len(),
+            waterstanden,
+            max_waterstanden,
+            kosten: banden(&mut kosten),
+            gemiddelde_kosten,
+            overschrijdingskans,
+        }
+    }
+}
+
+/// `taak(0)` tot en met `taak(n - 1)`, verdeeld over de beschikbare kernen;
+/// de uitkomsten staan in de volgorde van de taken.
+pub(crate) fn parallel<T: Send>(n: usize, taak: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let threads = thread::available_parallelism()
+        .map_or(1, |k| k.get())
+        .min(n.max(1));
+    let taak = &taak;
+
+    let mut uitkomsten: Vec<Option<T>> = Vec::new();
+    uitkomsten.resize_with(n, || None);
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                scope.spawn(move || {
+                    (t..n)
+                        .step_by(threads)
+                        .map(|i| (i, taak(i)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (i, uitkomst) in handle.join().expect("Parallelle simulatie is gecrasht") {
+                uitkomsten[i] = Some(uitkomst);
+            }
+        }
+    });
+    uitkomsten.into_iter().flatten().collect()
+}
+
+/// Percentiel `p` (0–100) van gesorteerde waarden, lineair geïnterpoleerd.
+pub(crate) fn percentiel(gesorteerd: &[f64], p: f64) -> f64 {
+    match gesorteerd.len() {
+        0 => f64::NAN,
+        1 => gesorteerd[0],
+        n => {
+            let positie = (p / 100.0).clamp(0.0, 1.0) * (n - 1) as f64;
+            let onder = positie.floor() as usize;
+            let boven = (onder + 1).min(n - 1);
+            let fractie = positie - onder as f64;
+            gesorteerd[onder] + fractie * (gesorteerd[boven] - gesorteerd[onder])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{PeilgebiedConfig, SimpeleUitstroomStrategy};
+
+    fn polder() -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 100_000.0,
+                streefpeil: -0.60,
+                marge: 0.10,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet: 0.05,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        topologie
+    }
+
+    #[test]
+    fn test_percentiel() {
+        let waarden = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentiel(&waarden, 0.0), 1.0);
+        assert_eq!(percentiel(&waarden, 50.0), 3.0);
+        assert_eq!(percentiel(&waarden, 100.0), 5.0);
+        assert!((percentiel(&waarden, 90.0) - 4.6).abs() < 1e-12);
+        assert!(percentiel(&[], 50.0).is_nan());
+    }
+
+    #[test]
+    fn test_monte_carlo_banden() {
+        let topologie = polder();
+        let regen = HashMap::from([("polder".to_string(), vec![8.0, 8.0, 4.0, 0.0, 0.0, 0.0])]);
+        let analyse = MonteCarloAnalyse::nieuw(40).met_zaad(7);
+        let resultaat = analyse
+            .voer_uit(&topologie, &regen, 6, &StrategyType::Simpel)
+            .unwrap();
+
+        assert_eq!(resultaat.runs, 40);
+        let banden = &resultaat.waterstanden["polder"];
+        assert_eq!(banden.len(), 3);
+        assert_eq!(banden[0].waarden.len(), 6);
+        // Banden liggen op volgorde en hebben breedte door de onzekerheid
+        for uur in 0..6 {
+            assert!(banden[0].waarden[uur] <= banden[1].waarden[uur]);
+            assert!(banden[1].waarden[uur] <= banden[2].waarden[uur]);
+        }
+        assert!(banden[2].waarden[2] - banden[0].waarden[2] > 0.001);
+        assert!(resultaat.kosten[0].waarden[0] <= resultaat.kosten[2].waarden[0]);
+        assert!(resultaat.gemiddelde_kosten > 0.0);
+        let kans = resultaat.overschrijdingskans["polder"];
+        assert!((0.0..=1.0).contains(&kans));
+
+        // Zelfde zaad, zelfde uitkomst
+        let opnieuw = analyse
+            .voer_uit(&topologie, &regen, 6, &StrategyType::Simpel)
+            .unwrap();
+        assert_eq!(opnieuw.waterstanden["polder"], *banden);
+    }
+
+    #[test]
+    fn test_zonder_spreiding_gelijk_aan_enkele_run() {
+        let topologie = polder();
+        let regen = HashMap::from([("polder".to_string(), vec![5.0, 5.0, 0.0])]);
+        let resultaat = MonteCarloAnalyse::nieuw(5)
+            .met_onzekerheid(Onzekerheid {
+                capaciteit: 0.0,
+                kwel: 0.0,
+                neerslag: 0.0,
+            })
+            .voer_uit(&topologie, &regen, 3, &StrategyType::Simpel)
+            .unwrap();
+
+        let enkel =
+            crate::netwerk::run_netwerksimulatie(&topologie, &regen, 3, &SimpeleUitstroomStrategy)
+                .unwrap();
+        let eind = enkel.tijdstappen.last().unwrap().statussen["polder"].waterstand;
+        let banden = &resultaat.waterstanden["polder"];
+        assert!((banden[0].waarden[2] - eind).abs() < 1e-12);
+        assert!((banden[2].waarden[2] - eind).abs() < 1e-12);
+    }
+}
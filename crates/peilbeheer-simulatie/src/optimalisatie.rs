@@ -1,10 +1,25 @@
 use peilbeheer_core::energie::{
-    OptimalisatieParams, OptimalisatieResultaat, OptimalisatieUurResultaat,
-    SimulatieStapUitgebreid, UurPrijs,
+    heeft_kwartierprijzen, prijs_per_kwartier, prijs_per_uur, stroomkosten, MAX_HORIZON_UREN,
+    MIN_HORIZON_UREN, OptimalisatieKwartierResultaat, OptimalisatieParams, OptimalisatieResultaat,
+    OptimalisatieUurResultaat, Optimizer, Pompregeling, SimulatieStapUitgebreid, UurPrijs,
 };
+use peilbeheer_core::pompen::{self, Pomp};
+use microlp::{ComparisonOp, OptimizationDirection, Problem};
 
 use crate::waterbalans::calculate_water_balance;
 
+/// Prijs voor uren of kwartieren zonder opgegeven prijs (€/kWh).
+const STANDAARD_PRIJS: f64 = 0.10;
+
+/// Lengte van een optimalisatievenster in uren; een langere horizon wordt
+/// venster voor venster geoptimaliseerd.
+const VENSTER_UREN: usize = 24;
+
+/// Meeste pompen voor `Optimizer::Milp`. De solver kent geen tijdslimiet;
+/// elke pomp na de eerste voegt per interval een binaire variabele toe,
+/// dus samen met de horizon begrenst dit de omvang van het MILP.
+const MAX_MILP_POMPEN: usize = 4;
+
 /// Pompvermogen in kW: P = ρ × g × Q × H / η
 /// met ρ = 1000 kg/m³, g = 9.81 m/s²
 pub fn calculate_pump_power_kw(debiet_m3s: f64, opvoerhoogte_m: f64, efficiency: f64) -> f64 {
@@ -12,12 +27,252 @@ pub fn calculate_pump_power_kw(debiet_m3s: f64, opvoerhoogte_m: f64, efficiency:
     1000.0 * 9.81 * debiet_m3s * opvoerhoogte_m / eff / 1000.0 // delen door 1000 voor kW
 }
 
-/// Simuleer één uur (60 minuten) met vaste pompfractie.
-/// Retourneert de waterstand aan het eind van het uur.
+/// Debiet en vermogen van één pomp bij volle inzet.
+#[derive(Debug, Clone, Copy)]
+struct PompInzet {
+    /// Debiet in m³/s
+    debiet: f64,
+    /// Opgenomen vermogen in kW
+    vermogen_kw: f64,
+}
+
+/// Pompen bij volle inzet tijdens een uur, in inschakelvolgorde. Zonder
+/// pompen is het gemaal één pomp.
+fn pompen_in_uur(params: &OptimalisatieParams, pompen: &[Pomp], uur: usize) -> Vec<PompInzet> {
+    let opvoerhoogte = params.opvoerhoogte(uur);
+    if pompen.is_empty() {
+        let debiet = params.pompdebiet(uur);
+        return vec![PompInzet {
+            debiet,
+            vermogen_kw: calculate_pump_power_kw(debiet, opvoerhoogte, params.rendement(uur)),
+        }];
+    }
+    pompen
+        .iter()
+        .map(|pomp| {
+            let debiet = pomp.debiet_bij(opvoerhoogte);
+            PompInzet {
+                debiet,
+                vermogen_kw: calculate_pump_power_kw(debiet, opvoerhoogte, pomp.rendement_bij(opvoerhoogte)),
+            }
+        })
+        .collect()
+}
+
+/// Indeling van de horizon in schakelintervallen: uren, of kwartieren als
+/// er kwartierprijzen zijn.
+struct Intervallen {
+    /// Lengte van een interval in minuten
+    minuten: usize,
+    /// Uur van de horizon waarin het eerste interval valt
+    eerste_uur: usize,
+    /// Afnameprijs per interval (€/kWh)
+    prijzen: Vec<f64>,
+    /// Terugleververgoeding per interval (€/kWh)
+    vergoedingen: Vec<f64>,
+    /// Flexvergoeding per afgenomen kWh per interval (€/kWh)
+    flexvergoedingen: Vec<f64>,
+    /// Hoogste toegestane afname uit het net per interval (kW)
+    max_afname_kw: Vec<Option<f64>>,
+    /// Pompen per interval bij de opvoerhoogte van dat interval
+    pompen: Vec<Vec<PompInzet>>,
+}
+
+impl Intervallen {
+    fn new(params: &OptimalisatieParams) -> Self {
+        let uren = params.horizon_uren;
+        let mut intervallen = if heeft_kwartierprijzen(&params.prijzen) {
+            Self {
+                minuten: 15,
+                eerste_uur: 0,
+                prijzen: prijs_per_kwartier(&params.prijzen, STANDAARD_PRIJS, uren),
+                vergoedingen: Vec::new(),
+                flexvergoedingen: Vec::new(),
+                max_afname_kw: Vec::new(),
+                pompen: Vec::new(),
+            }
+        } else {
+            // Uurprijzen; zonder prijzen een uniforme prijs
+            Self {
+                minuten: 60,
+                eerste_uur: 0,
+                prijzen: prijs_per_uur(&params.prijzen, STANDAARD_PRIJS, uren),
+                vergoedingen: Vec::new(),
+                flexvergoedingen: Vec::new(),
+                max_afname_kw: Vec::new(),
+                pompen: Vec::new(),
+            }
+        };
+        // Met een contract zijn de opgegeven prijzen marktprijzen
+        let uren: Vec<usize> = (0..intervallen.aantal()).map(|i| intervallen.uur(i)).collect();
+        let markt = std::mem::take(&mut intervallen.prijzen);
+        (intervallen.prijzen, intervallen.vergoedingen) = markt
+            .iter()
+            .zip(uren.iter().copied())
+            .map(|(&prijs, uur)| match &params.contract {
+                Some(contract) => {
+                    let uur_van_dag = params.uur_van_dag(uur);
+                    (
+                        contract.afnameprijs(prijs, uur_van_dag),
+                        contract.terugleverprijs(prijs, uur_van_dag),
+                    )
+                }
+                None => (prijs, params.terugleververgoeding),
+            })
+            .unzip();
+        intervallen.flexvergoedingen = uren.iter().map(|&uur| params.flexvergoeding(uur)).collect();
+        intervallen.max_afname_kw = uren.iter().map(|&uur| params.max_afname_kw(uur)).collect();
+        let pompen = pompen::in_volgorde(&params.pompen);
+        intervallen.pompen = (0..intervallen.aantal())
+            .map(|interval| pompen_in_uur(params, &pompen, intervallen.uur(interval)))
+            .collect();
+        intervallen
+    }
+
+    /// De intervallen van uur `van` tot uur `tot`.
+    fn venster(&self, van: usize, tot: usize) -> Self {
+        let per_uur = 60 / self.minuten;
+        let bereik = van * per_uur..tot * per_uur;
+        Self {
+            minuten: self.minuten,
+            eerste_uur: self.eerste_uur + van,
+            prijzen: self.prijzen[bereik.clone()].to_vec(),
+            vergoedingen: self.vergoedingen[bereik.clone()].to_vec(),
+            flexvergoedingen: self.flexvergoedingen[bereik.clone()].to_vec(),
+            max_afname_kw: self.max_afname_kw[bereik.clone()].to_vec(),
+            pompen: self.pompen[bereik].to_vec(),
+        }
+    }
+
+    fn aantal(&self) -> usize {
+        self.prijzen.len()
+    }
+
+    fn is_kwartier(&self) -> bool {
+        self.minuten < 60
+    }
+
+    /// Uur van de horizon waarin een interval valt.
+    fn uur(&self, interval: usize) -> usize {
+        self.eerste_uur + interval * self.minuten / 60
+    }
+
+    /// Debiet bij volle inzet tijdens een interval (m³/s).
+    fn max_debiet(&self, interval: usize) -> f64 {
+        self.pompen[interval].iter().map(|p| p.debiet).sum()
+    }
+
+    /// Inzet per pomp (0-1) bij `fractie` van het maximale debiet; de
+    /// pompen worden in inschakelvolgorde volgezet.
+    fn inzet(&self, interval: usize, fractie: f64) -> Vec<f64> {
+        let capaciteiten: Vec<f64> = self.pompen[interval].iter().map(|p| p.debiet).collect();
+        pompen::verdeel(&capaciteiten, fractie * self.max_debiet(interval))
+    }
+
+    /// Regenintensiteit tijdens een interval (mm/uur).
+    fn regen(&self, params: &OptimalisatieParams, interval: usize) -> f64 {
+        *params.regen_per_uur.get(self.uur(interval)).unwrap_or(&0.0)
+    }
+
+    /// Opgenomen vermogen (kW) bij `fractie` van het maximale debiet.
+    fn vermogen_kw(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> f64 {
+        self.inzet(interval, fractie)
+            .iter()
+            .zip(&self.pompen[interval])
+            .map(|(&inzet, pomp)| params.vermogensfactor(inzet) * pomp.vermogen_kw)
+            .sum()
+    }
+
+    /// Stroomverbruik (kWh) van een interval pompen met `fractie` van het
+    /// maximale debiet.
+    fn energie_kwh(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> f64 {
+        self.vermogen_kw(params, interval, fractie) * self.minuten as f64 / 60.0
+    }
+
+    /// Zonnestroomopwek bij het gemaal tijdens een interval (kW).
+    fn opwek_kw(&self, params: &OptimalisatieParams, interval: usize) -> f64 {
+        *params.opwek_kw.get(self.uur(interval)).unwrap_or(&0.0)
+    }
+
+    /// Afnameprijs min flexvergoeding tijdens een interval (€/kWh).
+    fn netto_prijs(&self, interval: usize) -> f64 {
+        self.prijzen[interval] - self.flexvergoedingen[interval]
+    }
+
+    /// Stroomkosten (€) en eigen verbruik van zonnestroom (kWh) van een
+    /// interval pompen met `fractie` van het maximale debiet. De
+    /// flexvergoeding is met de afname verrekend.
+    fn kosten(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> (f64, f64) {
+        let power_kw = self.vermogen_kw(params, interval, fractie);
+        let opwek_kw = self.opwek_kw(params, interval);
+        let (kosten, eigen) =
+            stroomkosten(power_kw, opwek_kw, self.netto_prijs(interval), self.vergoedingen[interval]);
+        let uren = self.minuten as f64 / 60.0;
+        (kosten * uren, eigen * uren)
+    }
+
+    /// Afname uit het net (kW) tijdens een interval pompen met `fractie`
+    /// van het maximale debiet.
+    fn afname_kw(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> f64 {
+        (self.vermogen_kw(params, interval, fractie) - self.opwek_kw(params, interval).max(0.0))
+            .max(0.0)
+    }
+
+    /// Flexvergoeding (€) van een interval pompen met `fractie` van het
+    /// maximale debiet.
+    fn flexvergoeding(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> f64 {
+        self.flexvergoedingen[interval]
+            * self.afname_kw(params, interval, fractie)
+            * self.minuten as f64
+            / 60.0
+    }
+
+    /// Of de afname binnen het maximum van de netbeheerder blijft.
+    fn toegestaan(&self, params: &OptimalisatieParams, interval: usize, fractie: f64) -> bool {
+        self.max_afname_kw[interval]
+            .is_none_or(|max| self.afname_kw(params, interval, fractie) <= max + 1e-9)
+    }
+
+    /// Hoogste afname uit het net (kW) bij een schema.
+    fn piek_kw(&self, params: &OptimalisatieParams, fracties: &[f64]) -> f64 {
+        fracties
+            .iter()
+            .enumerate()
+            .map(|(interval, &fractie)| self.afname_kw(params, interval, fractie))
+            .fold(0.0, f64::max)
+    }
+
+    /// Gebruikte prijzen: per uur, of per kwartier.
+    fn uur_prijzen(&self, params: &OptimalisatieParams) -> Vec<UurPrijs> {
+        if !self.is_kwartier() && params.contract.is_none() && params.prijzen.len() == self.aantal() {
+            return params.prijzen.clone();
+        }
+        self.prijzen
+            .iter()
+            .enumerate()
+            .map(|(i, &prijs)| {
+                if self.is_kwartier() {
+                    UurPrijs::kwartier(self.uur(i) as u8, (i % 4) as u8, prijs)
+                } else {
+                    UurPrijs::uur(i as u8, prijs)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Boete van het energiecontract op een afname van `piek_kw`.
+fn piekboete(params: &OptimalisatieParams, piek_kw: f64) -> f64 {
+    params.contract.as_ref().map_or(0.0, |contract| contract.piekboete(piek_kw))
+}
+
+/// Simuleer één interval van `minuten` minuten met vaste pompfractie.
+/// Retourneert de waterstand aan het eind van het interval.
 ///
 /// `effective_regen` is de regenintensiteit geschaald met 1/berging_factor (mm/uur).
 /// `oppervlakte` is het volledige peilgebied-oppervlak (m²).
-fn simulate_one_hour(
+#[allow(clippy::too_many_arguments)]
+fn simulate_interval(
     ws_start: f64,
     pomp_fractie: f64,
     max_debiet: f64,
@@ -25,40 +280,42 @@ fn simulate_one_hour(
     oppervlakte: f64,
     verdamping: f64,
     infiltratie: f64,
+    minuten: usize,
 ) -> f64 {
     let debiet = pomp_fractie * max_debiet;
     let mut ws = ws_start;
 
-    for _ in 0..60 {
-        let balans = calculate_water_balance(effective_regen, oppervlakte, ws, debiet, verdamping, infiltratie);
+    for _ in 0..minuten {
+        let balans = calculate_water_balance(effective_regen, oppervlakte, ws, debiet, verdamping, infiltratie, None);
         ws = balans.nieuwe_waterstand;
     }
 
     ws
 }
 
-/// Simuleer 24 uur met gegeven pompfracties per uur, retourneer gedetailleerde tijdstappen.
-fn simulate_24h_detailed(
+/// Simuleer de horizon met gegeven pompfracties per interval vanaf
+/// `start_ws`, retourneer gedetailleerde tijdstappen.
+fn simulate_detailed(
     params: &OptimalisatieParams,
     pompfracties: &[f64],
-    prijzen: &[UurPrijs],
+    intervallen: &Intervallen,
+    start_ws: f64,
 ) -> (Vec<SimulatieStapUitgebreid>, f64) {
     let berging = params.berging_factor.max(0.01);
 
-    let mut stappen = Vec::with_capacity(24 * 60);
-    let mut ws = params.streefpeil;
+    let mut stappen = Vec::with_capacity(pompfracties.len() * intervallen.minuten);
+    let mut ws = start_ws;
     let mut cum_kosten = 0.0;
 
-    #[allow(clippy::needless_range_loop)]
-    for uur in 0..24_usize {
-        let fractie = pompfracties[uur];
-        let debiet = fractie * params.max_debiet;
-        let regen = *params.regen_per_uur.get(uur).unwrap_or(&0.0);
+    for (interval, &fractie) in pompfracties.iter().enumerate() {
+        let uur = intervallen.uur(interval);
+        let debiet = fractie * intervallen.max_debiet(interval);
+        let regen = intervallen.regen(params, interval);
         let effective_regen = regen / berging;
-        let prijs = prijzen.get(uur).map(|p| p.prijs_eur_kwh).unwrap_or(0.0);
-        let power_kw = calculate_pump_power_kw(debiet, params.opvoerhoogte, params.efficiency);
+        let prijs = intervallen.prijzen[interval];
+        let (kosten_interval, _) = intervallen.kosten(params, interval, fractie);
 
-        for minuut in 0..60 {
+        for minuut in 0..intervallen.minuten {
             let balans = calculate_water_balance(
                 effective_regen,
                 params.oppervlakte,
@@ -66,13 +323,14 @@ fn simulate_24h_detailed(
                 debiet,
                 params.verdamping,
                 params.infiltratie,
+                None,
             );
 
-            let kosten_deze_minuut = power_kw * prijs / 60.0; // kWh per minuut
+            let kosten_deze_minuut = kosten_interval / intervallen.minuten as f64;
             cum_kosten += kosten_deze_minuut;
 
             stappen.push(SimulatieStapUitgebreid {
-                tijd_minuten: (uur * 60 + minuut) as f64,
+                tijd_minuten: (interval * intervallen.minuten + minuut) as f64,
                 uur: uur as u8,
                 waterstand: ws,
                 water_afvoer: balans.water_afvoer,
@@ -91,27 +349,32 @@ fn simulate_24h_detailed(
 }
 
 /// Naïef pompschema: pomp 100% als waterstand > streefpeil, 0% als ≤ streefpeil.
+/// Een gemaal met meerdere pompen schakelt op de schakelpeilen van de pompen.
 fn naive_pump_fractions(
     params: &OptimalisatieParams,
+    intervallen: &Intervallen,
+    start_ws: f64,
 ) -> Vec<f64> {
+    if !params.pompen.is_empty() {
+        return trapsgewijze_fracties(params, intervallen, start_ws);
+    }
     let berging = params.berging_factor.max(0.01);
 
-    let mut fracties = vec![0.0; 24];
-    let mut ws = params.streefpeil;
+    let mut fracties = vec![0.0; intervallen.aantal()];
+    let mut ws = start_ws;
 
-    #[allow(clippy::needless_range_loop)]
-    for uur in 0..24 {
-        let regen = *params.regen_per_uur.get(uur).unwrap_or(&0.0);
+    for (interval, fractie) in fracties.iter_mut().enumerate() {
+        let regen = intervallen.regen(params, interval);
         let effective_regen = regen / berging;
 
-        // Bepaal of we moeten pompen: simuleer het uur zonder pomp, kijk of ws stijgt
-        let ws_zonder_pomp = simulate_one_hour(
-            ws, 0.0, params.max_debiet, effective_regen, params.oppervlakte,
-            params.verdamping, params.infiltratie,
+        // Bepaal of we moeten pompen: simuleer het interval zonder pomp, kijk of ws stijgt
+        let ws_zonder_pomp = simulate_interval(
+            ws, 0.0, intervallen.max_debiet(interval), effective_regen, params.oppervlakte,
+            params.verdamping, params.infiltratie, intervallen.minuten,
         );
 
         // Naïef: pomp als water boven streefpeil staat
-        fracties[uur] = if ws > params.streefpeil + 0.001 {
+        *fractie = if ws > params.streefpeil + 0.001 {
             1.0
         } else if ws_zonder_pomp > params.streefpeil + 0.001 {
             // Water gaat stijgen, begin te pompen
@@ -120,16 +383,49 @@ fn naive_pump_fractions(
             0.0
         };
 
-        // Simuleer het uur met de gekozen fractie
-        ws = simulate_one_hour(
-            ws, fracties[uur], params.max_debiet, effective_regen, params.oppervlakte,
-            params.verdamping, params.infiltratie,
+        // Simuleer het interval met de gekozen fractie
+        ws = simulate_interval(
+            ws, *fractie, intervallen.max_debiet(interval), effective_regen, params.oppervlakte,
+            params.verdamping, params.infiltratie, intervallen.minuten,
         );
     }
 
     fracties
 }
 
+/// Pompschema van de schakelpeilen: de pompen schakelen trapsgewijs op de
+/// waterstand aan het begin van elk interval.
+fn trapsgewijze_fracties(
+    params: &OptimalisatieParams,
+    intervallen: &Intervallen,
+    start_ws: f64,
+) -> Vec<f64> {
+    let berging = params.berging_factor.max(0.01);
+    let pompen = pompen::in_volgorde(&params.pompen);
+    let mut aan = vec![false; pompen.len()];
+    let mut ws = start_ws;
+
+    (0..intervallen.aantal())
+        .map(|interval| {
+            pompen::schakel(&pompen, &mut aan, ws - params.streefpeil);
+            let max_debiet = intervallen.max_debiet(interval);
+            let debiet: f64 = intervallen.pompen[interval]
+                .iter()
+                .zip(&aan)
+                .filter(|(_, aan)| **aan)
+                .map(|(pomp, _)| pomp.debiet)
+                .sum();
+            let fractie = if max_debiet > 0.0 { debiet / max_debiet } else { 0.0 };
+
+            ws = simulate_interval(
+                ws, fractie, max_debiet, intervallen.regen(params, interval) / berging,
+                params.oppervlakte, params.verdamping, params.infiltratie, intervallen.minuten,
+            );
+            fractie
+        })
+        .collect()
+}
+
 /// Beschikbare pompfracties voor DP-discretisatie.
 const PUMP_FRACTIONS: [f64; 9] = [0.0, 0.05, 0.10, 0.25, 0.40, 0.50, 0.75, 0.90, 1.0];
 
@@ -144,25 +440,102 @@ fn index_to_ws(idx: usize, ws_min: f64, stap: f64) -> f64 {
     ws_min + idx as f64 * stap
 }
 
-/// Dynamic Programming optimalisatie van het pompschema.
-pub fn optimize_pump_schedule(
-    params: &OptimalisatieParams,
-) -> Result<OptimalisatieResultaat, String> {
-    // Validatie
-    if params.oppervlakte <= 0.0 {
-        return Err("Oppervlakte moet groter zijn dan 0".into());
+/// Of het gemaal draait bij een pompfractie.
+fn is_aan(fractie: f64) -> bool {
+    fractie > 1e-6
+}
+
+/// Aantal keer dat het gemaal inschakelt; bij de start staat het uit.
+fn inschakelingen(fracties: &[f64]) -> usize {
+    let mut aan = false;
+    fracties
+        .iter()
+        .filter(|&&fractie| {
+            let ingeschakeld = !aan && is_aan(fractie);
+            aan = is_aan(fractie);
+            ingeschakeld
+        })
+        .count()
+}
+
+/// Aan/uit-toestand van het gemaal in de DP: hoeveel intervallen het al
+/// aan of uit staat, tot de minimale draai- of uittijd. Modus `k` onder
+/// `draai` is `k + 1` intervallen aan, daarboven `k - draai + 1` uit.
+struct Schakelmodi {
+    /// Minimale draaitijd in intervallen
+    draai: usize,
+    /// Minimale uittijd in intervallen
+    uit: usize,
+    /// Kosten per inschakeling (€)
+    kosten: f64,
+}
+
+impl Schakelmodi {
+    fn new(params: &OptimalisatieParams, intervallen: &Intervallen) -> Self {
+        Self {
+            draai: (params.min_draaitijd_min as usize).div_ceil(intervallen.minuten).max(1),
+            uit: (params.min_uittijd_min as usize).div_ceil(intervallen.minuten).max(1),
+            kosten: params.schakelkosten,
+        }
     }
-    if params.max_debiet <= 0.0 {
-        return Err("Max debiet moet groter zijn dan 0".into());
+
+    /// Aantal modi; zonder schakelkosten en minimale tijden is één genoeg.
+    fn aantal(&self) -> usize {
+        if self.kosten > 0.0 || self.draai > 1 || self.uit > 1 {
+            self.draai + self.uit
+        } else {
+            1
+        }
     }
-    if params.regen_per_uur.is_empty() || params.regen_per_uur.len() > 24 {
-        return Err(format!(
-            "regen_per_uur moet 1-24 waarden bevatten, maar bevat {}",
-            params.regen_per_uur.len()
-        ));
+
+    /// Modus bij de start: lang genoeg aan of uit om vrij te schakelen.
+    fn start(&self, aan: bool) -> usize {
+        match (self.aantal(), aan) {
+            (1, _) => 0,
+            (_, true) => self.draai - 1,
+            (_, false) => self.draai + self.uit - 1,
+        }
     }
 
+    /// Modus na een interval waarin het gemaal `aan` staat en de
+    /// schakelkosten daarvan; None als de minimale draai- of uittijd het
+    /// schakelen verbiedt.
+    fn volgende(&self, modus: usize, aan: bool) -> Option<(usize, f64)> {
+        if self.aantal() == 1 {
+            return Some((0, 0.0));
+        }
+        if modus < self.draai {
+            if aan {
+                Some(((modus + 1).min(self.draai - 1), 0.0))
+            } else if modus + 1 >= self.draai {
+                Some((self.draai, 0.0))
+            } else {
+                None
+            }
+        } else {
+            let uit = modus - self.draai + 1;
+            if !aan {
+                Some((self.draai + uit.min(self.uit - 1), 0.0))
+            } else if uit >= self.uit {
+                Some((0, self.kosten))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Pompfractie per interval met dynamic programming over de waterstand en
+/// de aan/uit-toestand, vanaf `start_ws` en met het gemaal `start_aan`.
+fn dp_pompfracties(
+    params: &OptimalisatieParams,
+    intervallen: &Intervallen,
+    start_ws: f64,
+    start_aan: bool,
+) -> Result<Vec<f64>, String> {
     let berging = params.berging_factor.max(0.01);
+    let n_intervallen = intervallen.aantal();
+    let uur_fractie = intervallen.minuten as f64 / 60.0;
 
     let marge_m = params.marge_cm / 100.0;
     let ws_min = params.streefpeil - marge_m; // band ondergrens
@@ -170,32 +543,30 @@ pub fn optimize_pump_schedule(
     let stap = 0.005; // 0.5 cm discretisatie
 
     // Bereken de maximale verwachte waterstandstijging om de DP-ruimte groot genoeg te maken.
-    let total_rain_mm: f64 = params.regen_per_uur.iter().sum();
+    let total_rain_mm: f64 = (0..n_intervallen)
+        .map(|interval| intervallen.regen(params, interval) * uur_fractie)
+        .sum();
     let max_rise_m = (total_rain_mm / berging / 1000.0).clamp(0.20, 5.0);
 
-    // DP-toestandsruimte: band + uitloop voor overschrijding
-    let ws_dp_min = ws_min - max_rise_m;
-    let ws_dp_max = ws_max + max_rise_m;
+    // DP-toestandsruimte: band + uitloop voor overschrijding, en de
+    // startwaterstand als die buiten de band ligt
+    let ws_dp_min = ws_min.min(start_ws) - max_rise_m;
+    let ws_dp_max = ws_max.max(start_ws) + max_rise_m;
     let n_niveaus = ((ws_dp_max - ws_dp_min) / stap).round() as usize + 1;
 
-    // Zorg dat er prijzen zijn (24 stuks)
-    let prijzen: Vec<UurPrijs> = if params.prijzen.len() == 24 {
-        params.prijzen.clone()
-    } else {
-        // Fallback: uniforme prijs
-        (0..24).map(|u| UurPrijs { uur: u, prijs_eur_kwh: 0.10 }).collect()
-    };
-
     // Strafterm: hoge kosten per cm buiten de band, zodat de DP pompen verkiest
-    // boven bandoverschrijding.
+    // boven bandoverschrijding. Per interval geschaald naar de intervallengte.
     let penalty_per_cm = 100.0; // €100 per cm per uur buiten de band
+    let penalty_per_cm_interval = penalty_per_cm * uur_fractie;
 
-    // DP arrays: kosten[uur][ws_index] = minimale resterende kosten
-    // We werken backward: van uur 23 naar uur 0
+    // DP arrays: kosten[modus][ws_index] = minimale resterende kosten
+    // We werken backward: van het laatste interval naar interval 0
     let inf = f64::INFINITY;
+    let modi = Schakelmodi::new(params, intervallen);
+    let n_modi = modi.aantal();
 
-    // Na uur 23: strafterm voor eindwaterstand buiten band
-    let mut next_cost: Vec<f64> = (0..n_niveaus)
+    // Na het laatste interval: strafterm voor eindwaterstand buiten band
+    let eind_cost: Vec<f64> = (0..n_niveaus)
         .map(|idx| {
             let ws = index_to_ws(idx, ws_dp_min, stap);
             let overschrijding_cm = if ws < ws_min {
@@ -208,24 +579,32 @@ pub fn optimize_pump_schedule(
             overschrijding_cm * penalty_per_cm
         })
         .collect();
-    let mut best_fraction: Vec<Vec<f64>> = vec![vec![0.0; n_niveaus]; 24];
+    let mut next_cost = vec![eind_cost; n_modi];
+    let mut best_fraction = vec![vec![vec![0.0; n_niveaus]; n_modi]; n_intervallen];
 
-    for uur in (0..24).rev() {
-        let mut current_cost = vec![inf; n_niveaus];
-        let regen = *params.regen_per_uur.get(uur).unwrap_or(&0.0);
+    for interval in (0..n_intervallen).rev() {
+        let mut current_cost = vec![vec![inf; n_niveaus]; n_modi];
+        let regen = intervallen.regen(params, interval);
         let effective_regen = regen / berging;
-        let prijs = prijzen[uur].prijs_eur_kwh;
+        let max_debiet = intervallen.max_debiet(interval);
 
         for ws_idx in 0..n_niveaus {
             let ws = index_to_ws(ws_idx, ws_dp_min, stap);
 
             for &fractie in &PUMP_FRACTIONS {
-                let debiet = fractie * params.max_debiet;
+                // Draaiend minstens de kleinste pompfractie
+                if is_aan(fractie) && fractie < params.min_pompfractie {
+                    continue;
+                }
+                // Het net kan minder afname toestaan
+                if !intervallen.toegestaan(params, interval, fractie) {
+                    continue;
+                }
 
-                // Simuleer dit uur
-                let ws_eind = simulate_one_hour(
-                    ws, fractie, params.max_debiet, effective_regen, params.oppervlakte,
-                    params.verdamping, params.infiltratie,
+                // Simuleer dit interval
+                let ws_eind = simulate_interval(
+                    ws, fractie, max_debiet, effective_regen, params.oppervlakte,
+                    params.verdamping, params.infiltratie, intervallen.minuten,
                 );
 
                 // Zoek eind-index in uitgebreide toestandsruimte (geen clamping)
@@ -234,9 +613,14 @@ pub fn optimize_pump_schedule(
                     _ => continue,
                 };
 
-                // Kosten dit uur: P(kW) × prijs(€/kWh) × 1 uur
-                let power_kw = calculate_pump_power_kw(debiet, params.opvoerhoogte, params.efficiency);
-                let kosten_uur = power_kw * prijs;
+                // Kosten dit interval: P(kW) × prijs(€/kWh) × duur (uur), met
+                // eigen zonnestroom tegen de terugleververgoeding
+                let (kosten_interval, _) = intervallen.kosten(params, interval, fractie);
+
+                // Afname boven het gecontracteerde vermogen kost de boete in
+                // elk interval waarin hij optreedt; zo weegt de DP hem
+                // nooit lichter dan de boete op de piek van de horizon
+                let piekboete = piekboete(params, intervallen.afname_kw(params, interval, fractie));
 
                 // Strafterm voor bandoverschrijding
                 let overschrijding_cm = if ws_eind < ws_min {
@@ -246,13 +630,25 @@ pub fn optimize_pump_schedule(
                 } else {
                     0.0
                 };
-                let penalty = overschrijding_cm * penalty_per_cm;
-
-                let totaal = kosten_uur + penalty + next_cost[eind_idx];
-
-                if totaal < current_cost[ws_idx] {
-                    current_cost[ws_idx] = totaal;
-                    best_fraction[uur][ws_idx] = fractie;
+                let penalty = overschrijding_cm * penalty_per_cm_interval;
+
+                // Per aan/uit-toestand: mag het gemaal zo schakelen, en
+                // wat kost het inschakelen
+                for modus in 0..n_modi {
+                    let Some((volgende, schakelkosten)) = modi.volgende(modus, is_aan(fractie))
+                    else {
+                        continue;
+                    };
+                    let totaal = kosten_interval
+                        + piekboete
+                        + penalty
+                        + schakelkosten
+                        + next_cost[volgende][eind_idx];
+
+                    if totaal < current_cost[modus][ws_idx] {
+                        current_cost[modus][ws_idx] = totaal;
+                        best_fraction[interval][modus][ws_idx] = fractie;
+                    }
                 }
             }
         }
@@ -261,26 +657,29 @@ pub fn optimize_pump_schedule(
     }
 
     // Forward pass: bepaal optimaal schema vanuit startconditie
-    let start_ws = params.streefpeil;
     let start_idx = ws_to_index(start_ws, ws_dp_min, stap)
-        .ok_or("Streefpeil valt buiten DP-toestandsruimte")?;
+        .ok_or("Startwaterstand valt buiten DP-toestandsruimte")?;
     if start_idx >= n_niveaus {
-        return Err("Streefpeil valt buiten DP-toestandsruimte".into());
+        return Err("Startwaterstand valt buiten DP-toestandsruimte".into());
     }
 
-    let mut opt_fracties = vec![0.0; 24];
+    let mut opt_fracties = vec![0.0; n_intervallen];
     let mut ws = start_ws;
     let mut ws_idx = start_idx;
+    let mut modus = modi.start(start_aan);
 
-    for uur in 0..24 {
-        let fractie = best_fraction[uur][ws_idx];
-        opt_fracties[uur] = fractie;
+    for interval in 0..n_intervallen {
+        let fractie = best_fraction[interval][modus][ws_idx];
+        opt_fracties[interval] = fractie;
+        modus = modi
+            .volgende(modus, is_aan(fractie))
+            .map_or(modi.start(is_aan(fractie)), |(volgende, _)| volgende);
 
-        let regen = *params.regen_per_uur.get(uur).unwrap_or(&0.0);
+        let regen = intervallen.regen(params, interval);
         let effective_regen = regen / berging;
-        let ws_eind = simulate_one_hour(
-            ws, fractie, params.max_debiet, effective_regen, params.oppervlakte,
-            params.verdamping, params.infiltratie,
+        let ws_eind = simulate_interval(
+            ws, fractie, intervallen.max_debiet(interval), effective_regen, params.oppervlakte,
+            params.verdamping, params.infiltratie, intervallen.minuten,
         );
 
         ws = ws_eind;
@@ -289,34 +688,455 @@ pub fn optimize_pump_schedule(
             .min(n_niveaus - 1);
     }
 
+    Ok(opt_fracties)
+}
+
+/// Pompfractie per interval uit een MILP, vanaf `start_ws`.
+///
+/// De waterstand is lineair in de pompfracties, zodat het schema exact
+/// optimaal is in plaats van over een discrete waterstand. Per interval
+/// beslist een binaire variabele of het gemaal draait; draaiend pompt het
+/// minstens `min_pompfractie`, na inschakelen blijft het minstens
+/// `min_draaitijd_min` aan en na uitschakelen `min_uittijd_min` uit; elke
+/// inschakeling kost `schakelkosten`. Met een gecontracteerd vermogen kost
+/// de hoogste afname daarboven de boete per kW, en een congestiesignaal
+/// begrenst de afname. Bandoverschrijding kost dezelfde
+/// strafterm als in de DP, zodat het probleem bij zware regen oplosbaar
+/// blijft.
+fn milp_pompfracties(
+    params: &OptimalisatieParams,
+    intervallen: &Intervallen,
+    start_ws: f64,
+    start_aan: bool,
+) -> Result<Vec<f64>, String> {
+    let berging = params.berging_factor.max(0.01);
+    let n_intervallen = intervallen.aantal();
+    let uur_fractie = intervallen.minuten as f64 / 60.0;
+    let seconden = intervallen.minuten as f64 * 60.0;
+
+    let marge_m = params.marge_cm / 100.0;
+    let ws_min = params.streefpeil - marge_m;
+    let ws_max = params.streefpeil + marge_m;
+
+    // Strafterm per m buiten de band, gelijk aan de DP (€100 per cm per uur)
+    let penalty_per_m = 100.0 * 100.0;
+    let penalty_interval = penalty_per_m * uur_fractie;
+
+    // Aan/uit-variabelen zijn alleen nodig voor een minimale fractie,
+    // minimale draai- of uittijd of schakelkosten
+    let draaitijd = (params.min_draaitijd_min as usize).div_ceil(intervallen.minuten);
+    let uittijd = (params.min_uittijd_min as usize).div_ceil(intervallen.minuten);
+    let met_aan_uit = params.min_pompfractie > 0.0
+        || draaitijd > 1
+        || uittijd > 1
+        || params.schakelkosten > 0.0;
+    // Draaiend pompt het gemaal minstens de kleinste DP-fractie, zodat aan
+    // ook echt pompen is
+    let min_fractie = params.min_pompfractie.max(PUMP_FRACTIONS[1]);
+
+    let segmenten_vermogen = vermogenssegmenten(params);
+    let mut problem = Problem::new(OptimizationDirection::Minimize);
+
+    // Afname boven het gecontracteerde vermogen in het zwaarste interval,
+    // met de boete per kW
+    let netcapaciteit = params.contract.as_ref().and_then(|c| c.netcapaciteit.as_ref());
+    let piek = netcapaciteit
+        .filter(|net| net.boete_eur_kw > 0.0)
+        .map(|net| (problem.add_var(net.boete_eur_kw, (0.0, f64::INFINITY)), net.gecontracteerd_kw));
+    let mut fracties = Vec::with_capacity(n_intervallen);
+    let mut aan = Vec::with_capacity(n_intervallen);
+    let mut ws_vorig = None;
+
+    for interval in 0..n_intervallen {
+        let uur = intervallen.uur(interval);
+        let prijs = intervallen.netto_prijs(interval);
+
+        // Inzet per pomp met zijn vermogen tegen de prijs; een pomp draait
+        // pas als de vorige in de inschakelvolgorde vol draait
+        let max_debiet = intervallen.max_debiet(interval);
+        let fractie = problem.add_var(0.0, (0.0, if max_debiet > 0.0 { 1.0 } else { 0.0 }));
+        let mut debiet = vec![(fractie, -max_debiet)];
+        let mut vermogen = Vec::new();
+        let mut vorige = None;
+        for pomp in &intervallen.pompen[interval] {
+            // Het vermogen per segment van de inzet; door de oplopende
+            // hellingen worden de segmenten op volgorde gevuld
+            let inzet = problem.add_var(0.0, (0.0, 1.0));
+            let mut segmenten = vec![(inzet, -1.0)];
+            for &(lengte, helling) in &segmenten_vermogen {
+                let kw = pomp.vermogen_kw * helling;
+                let segment = problem.add_var(kw * prijs * uur_fractie, (0.0, lengte));
+                segmenten.push((segment, 1.0));
+                vermogen.push((segment, -kw));
+            }
+            problem.add_constraint(segmenten.as_slice(), ComparisonOp::Eq, 0.0);
+            if let Some(vorige) = vorige {
+                let vol = problem.add_binary_var(0.0);
+                problem.add_constraint([(vorige, 1.0), (vol, -1.0)], ComparisonOp::Ge, 0.0);
+                problem.add_constraint([(inzet, 1.0), (vol, -1.0)], ComparisonOp::Le, 0.0);
+            }
+            debiet.push((inzet, pomp.debiet));
+            vorige = Some(inzet);
+        }
+        problem.add_constraint(debiet.as_slice(), ComparisonOp::Eq, 0.0);
+
+        // vermogen - opwek <= toegestane afname
+        let opwek_kw = params.opwek_kw.get(uur).copied().unwrap_or(0.0).max(0.0);
+        if let Some(max_kw) = intervallen.max_afname_kw[interval] {
+            problem.add_constraint(vermogen.as_slice(), ComparisonOp::Ge, -(max_kw + opwek_kw));
+        }
+
+        // piek >= vermogen - opwek - gecontracteerd vermogen
+        if let Some((piek, gecontracteerd_kw)) = piek {
+            let mut afname = vermogen.clone();
+            afname.push((piek, 1.0));
+            problem.add_constraint(
+                afname.as_slice(),
+                ComparisonOp::Ge,
+                -(gecontracteerd_kw + opwek_kw),
+            );
+        }
+
+        // Eigen zonnestroom (tot de opwek) tegen de terugleververgoeding
+        let vergoeding = intervallen.vergoedingen[interval];
+        if opwek_kw > 0.0 && vergoeding < prijs {
+            let eigen = problem.add_var((vergoeding - prijs) * uur_fractie, (0.0, opwek_kw));
+            vermogen.push((eigen, 1.0));
+            problem.add_constraint(vermogen.as_slice(), ComparisonOp::Le, 0.0);
+        }
+
+        // Waterstand aan het eind van het interval:
+        // ws = ws_vorig + stijging zonder pomp - daling bij vol pompen × fractie
+        let balans = calculate_water_balance(
+            intervallen.regen(params, interval) / berging,
+            params.oppervlakte,
+            0.0,
+            0.0,
+            params.verdamping,
+            params.infiltratie,
+            None,
+        );
+        let stijging = balans.water_balans / params.oppervlakte * seconden;
+        let daling = max_debiet / params.oppervlakte * seconden;
+        let ws = problem.add_var(0.0, (f64::NEG_INFINITY, f64::INFINITY));
+        match ws_vorig {
+            Some(vorig) => problem.add_constraint(
+                [(ws, 1.0), (vorig, -1.0), (fractie, daling)],
+                ComparisonOp::Eq,
+                stijging,
+            ),
+            None => problem.add_constraint(
+                [(ws, 1.0), (fractie, daling)],
+                ComparisonOp::Eq,
+                start_ws + stijging,
+            ),
+        }
+        ws_vorig = Some(ws);
+
+        // Peilrestrictie met overschrijding; de eindwaterstand telt extra
+        // mee zoals in de DP
+        let straf = if interval + 1 == n_intervallen {
+            penalty_interval + penalty_per_m
+        } else {
+            penalty_interval
+        };
+        let boven = problem.add_var(straf, (0.0, f64::INFINITY));
+        let onder = problem.add_var(straf, (0.0, f64::INFINITY));
+        problem.add_constraint([(ws, 1.0), (boven, -1.0)], ComparisonOp::Le, ws_max);
+        problem.add_constraint([(ws, 1.0), (onder, 1.0)], ComparisonOp::Ge, ws_min);
+
+        if met_aan_uit {
+            let a = problem.add_binary_var(0.0);
+            problem.add_constraint([(fractie, 1.0), (a, -1.0)], ComparisonOp::Le, 0.0);
+            problem.add_constraint(
+                [(fractie, 1.0), (a, -min_fractie)],
+                ComparisonOp::Ge,
+                0.0,
+            );
+            aan.push(a);
+        }
+        fracties.push(fractie);
+    }
+
+    // Inschakelen kost `schakelkosten`; wie inschakelt blijft de volgende
+    // `draaitijd` intervallen aan en wie uitschakelt de volgende `uittijd`
+    // intervallen uit, of tot het eind van de horizon
+    let aan_bij_start = if start_aan { 1.0 } else { 0.0 };
+    if met_aan_uit && (draaitijd > 1 || params.schakelkosten > 0.0) {
+        for interval in 0..n_intervallen {
+            let start = problem.add_var(params.schakelkosten, (0.0, 1.0));
+            let mut inschakelen = vec![(start, 1.0), (aan[interval], -1.0)];
+            let rechts = match interval.checked_sub(1) {
+                Some(vorig) => {
+                    inschakelen.push((aan[vorig], 1.0));
+                    0.0
+                }
+                None => -aan_bij_start,
+            };
+            problem.add_constraint(inschakelen.as_slice(), ComparisonOp::Ge, rechts);
+
+            if draaitijd > 1 {
+                let venster = interval..(interval + draaitijd).min(n_intervallen);
+                let mut blijft_aan: Vec<_> =
+                    aan[venster.clone()].iter().map(|&a| (a, 1.0)).collect();
+                blijft_aan.push((start, -(venster.len() as f64)));
+                problem.add_constraint(blijft_aan.as_slice(), ComparisonOp::Ge, 0.0);
+            }
+        }
+    }
+    if met_aan_uit && uittijd > 1 {
+        for interval in 0..n_intervallen {
+            let stop = problem.add_var(0.0, (0.0, 1.0));
+            let mut uitschakelen = vec![(stop, 1.0), (aan[interval], 1.0)];
+            let rechts = match interval.checked_sub(1) {
+                Some(vorig) => {
+                    uitschakelen.push((aan[vorig], -1.0));
+                    0.0
+                }
+                None => aan_bij_start,
+            };
+            problem.add_constraint(uitschakelen.as_slice(), ComparisonOp::Ge, rechts);
+
+            let venster = interval..(interval + uittijd).min(n_intervallen);
+            let mut blijft_uit: Vec<_> = aan[venster.clone()].iter().map(|&a| (a, 1.0)).collect();
+            blijft_uit.push((stop, venster.len() as f64));
+            problem.add_constraint(blijft_uit.as_slice(), ComparisonOp::Le, venster.len() as f64);
+        }
+    }
+
+    let oplossing = problem
+        .solve()
+        .map_err(|e| format!("MILP-optimalisatie mislukt: {}", e))?;
+    Ok(fracties
+        .iter()
+        .map(|&fractie| oplossing.var_value(fractie).clamp(0.0, 1.0))
+        .collect())
+}
+
+/// Pompfracties over de hele horizon met rolling-window her-optimalisatie.
+///
+/// Elk venster van `VENSTER_UREN` wordt geoptimaliseerd vanaf de waterstand
+/// aan het eind van het vorige vastgelegde deel. Van elk venster worden de
+/// eerste `herplan_uren` vastgelegd; daarna schuift het venster door. Bij
+/// een horizon van 24 uur is dat één optimalisatie. Of het gemaal aan het
+/// eind van het vastgelegde deel draait, gaat mee naar het volgende
+/// venster; bij de start staat het uit.
+fn rolling_pompfracties(
+    params: &OptimalisatieParams,
+    intervallen: &Intervallen,
+    start_ws: f64,
+) -> Result<Vec<f64>, String> {
+    let berging = params.berging_factor.max(0.01);
+    let per_uur = 60 / intervallen.minuten;
+    let horizon = intervallen.aantal() / per_uur;
+    let herplan = params.herplan_uren.clamp(1, VENSTER_UREN);
+
+    let mut fracties = Vec::with_capacity(intervallen.aantal());
+    let mut ws = start_ws;
+    let mut van = 0;
+    while van < horizon {
+        let venster = intervallen.venster(van, (van + VENSTER_UREN).min(horizon));
+        let aan = fracties.last().is_some_and(|&fractie| is_aan(fractie));
+        let venster_fracties = match params.optimizer {
+            Optimizer::Dp => dp_pompfracties(params, &venster, ws, aan)?,
+            Optimizer::Milp => milp_pompfracties(params, &venster, ws, aan)?,
+        };
+
+        // Leg het begin van het venster vast en neem de bereikte
+        // waterstand mee naar het volgende venster
+        let vast = herplan.min(horizon - van) * per_uur;
+        for (interval, &fractie) in venster_fracties[..vast].iter().enumerate() {
+            let effective_regen = venster.regen(params, interval) / berging;
+            ws = simulate_interval(
+                ws, fractie, venster.max_debiet(interval), effective_regen, params.oppervlakte,
+                params.verdamping, params.infiltratie, venster.minuten,
+            );
+        }
+        fracties.extend_from_slice(&venster_fracties[..vast]);
+        van += herplan;
+    }
+
+    Ok(fracties)
+}
+
+/// Optimalisatie van het pompschema.
+///
+/// Standaard met dynamic programming; met `Optimizer::Milp` exact als
+/// MILP met aan/uit-beslissingen en minimale draaitijden. Met alleen
+/// uurprijzen wordt per uur geschakeld; bevatten de prijzen kwartierprijzen
+/// (onbalans of intraday), dan per kwartier. Een horizon langer dan 24 uur
+/// wordt met een rolling window geoptimaliseerd. Bij toerengeregelde pompen
+/// geeft het resultaat ook de kosten van hetzelfde gemaal met aan/uit-pompen.
+pub fn optimize_pump_schedule(
+    params: &OptimalisatieParams,
+) -> Result<OptimalisatieResultaat, String> {
+    // Validatie
+    if params.oppervlakte <= 0.0 {
+        return Err("Oppervlakte moet groter zijn dan 0".into());
+    }
+    pompen::valideer(&params.pompen)?;
+    if params.optimizer == Optimizer::Milp && params.pompen.len() > MAX_MILP_POMPEN {
+        return Err(format!(
+            "MILP-optimalisatie ondersteunt hoogstens {} pompen, maar er zijn er {}",
+            MAX_MILP_POMPEN,
+            params.pompen.len()
+        ));
+    }
+    match &params.pompcurve {
+        Some(curve) => curve.valideer()?,
+        None if params.pompen.is_empty() && params.max_debiet <= 0.0 => {
+            return Err("Max debiet moet groter zijn dan 0".into());
+        }
+        None => {}
+    }
+    if !(MIN_HORIZON_UREN..=MAX_HORIZON_UREN).contains(&params.horizon_uren) {
+        return Err(format!(
+            "horizon_uren moet {}-{} zijn, maar is {}",
+            MIN_HORIZON_UREN, MAX_HORIZON_UREN, params.horizon_uren
+        ));
+    }
+    if params.regen_per_uur.is_empty() || params.regen_per_uur.len() > params.horizon_uren {
+        return Err(format!(
+            "regen_per_uur moet 1-{} waarden bevatten, maar bevat {}",
+            params.horizon_uren,
+            params.regen_per_uur.len()
+        ));
+    }
+    if !(1..=VENSTER_UREN).contains(&params.herplan_uren) {
+        return Err(format!(
+            "herplan_uren moet 1-{} zijn, maar is {}",
+            VENSTER_UREN, params.herplan_uren
+        ));
+    }
+    if !(0.0..=1.0).contains(&params.min_pompfractie) {
+        return Err("min_pompfractie moet tussen 0 en 1 liggen".into());
+    }
+    if !(0.0..1.0).contains(&params.min_toerental) {
+        return Err("min_toerental moet tussen 0 en 1 liggen".into());
+    }
+    if params.flexsignalen.iter().any(|s| s.max_vermogen_kw.is_some_and(|kw| kw < 0.0)) {
+        return Err("max_vermogen_kw van een flexsignaal mag niet negatief zijn".into());
+    }
+
+    let intervallen = Intervallen::new(params);
+    let n_intervallen = intervallen.aantal();
+    let start_ws = params.start_waterstand.unwrap_or(params.streefpeil);
+    let opt_fracties = rolling_pompfracties(params, &intervallen, start_ws)?;
+
+    // Ter vergelijking dezelfde pompen zonder toerenregeling
+    let kosten_aan_uit = match params.pompregeling {
+        Pompregeling::AanUit => None,
+        Pompregeling::Toerengeregeld => {
+            let aan_uit = OptimalisatieParams {
+                pompregeling: Pompregeling::AanUit,
+                ..params.clone()
+            };
+            let fracties = rolling_pompfracties(&aan_uit, &intervallen, start_ws)?;
+            let piekboete = piekboete(params, intervallen.piek_kw(&aan_uit, &fracties));
+            Some(simulate_detailed(&aan_uit, &fracties, &intervallen, start_ws).1 + piekboete)
+        }
+    };
+
     // Naïef schema
-    let naief_fracties = naive_pump_fractions(params);
+    let naief_fracties = naive_pump_fractions(params, &intervallen, start_ws);
+
+    // Hoogste afname en de boete van het contract daarop
+    let piek_opt_kw = intervallen.piek_kw(params, &opt_fracties);
+    let piek_naief_kw = intervallen.piek_kw(params, &naief_fracties);
+    let piekboete_opt = piekboete(params, piek_opt_kw);
+    let piekboete_naief = piekboete(params, piek_naief_kw);
+
+    // Simuleer beide schema's gedetailleerd; de piekboete telt mee in de
+    // totale kosten
+    let (stappen_opt, kosten_opt) = simulate_detailed(params, &opt_fracties, &intervallen, start_ws);
+    let (stappen_naief, kosten_naief) =
+        simulate_detailed(params, &naief_fracties, &intervallen, start_ws);
+    let kosten_opt = kosten_opt + piekboete_opt;
+    let kosten_naief = kosten_naief + piekboete_naief;
+
+    // Flexvergoeding van de netbeheerder
+    let flexvergoeding = |fracties: &[f64]| -> f64 {
+        fracties
+            .iter()
+            .enumerate()
+            .map(|(interval, &fractie)| intervallen.flexvergoeding(params, interval, fractie))
+            .sum()
+    };
 
-    // Simuleer beide schema's gedetailleerd
-    let (stappen_opt, kosten_opt) = simulate_24h_detailed(params, &opt_fracties, &prijzen);
-    let (stappen_naief, kosten_naief) = simulate_24h_detailed(params, &naief_fracties, &prijzen);
+    // Kosten per interval
+    let interval_kosten = |interval: usize, fractie: f64| intervallen.kosten(params, interval, fractie).0;
+    let eigen_verbruik = |fracties: &[f64]| -> f64 {
+        fracties
+            .iter()
+            .enumerate()
+            .map(|(interval, &fractie)| intervallen.kosten(params, interval, fractie).1)
+            .sum()
+    };
+    let per_uur = 60 / intervallen.minuten;
+
+    // Verbruik (kWh) en uitstoot van de netstroom (kg CO2) per interval;
+    // eigen verbruik van zonnestroom telt zonder uitstoot
+    let emissiefactoren = params.emissiefactoren(params.horizon_uren);
+    let interval_energie = |interval: usize, fractie: f64| -> (f64, f64) {
+        let energie = intervallen.energie_kwh(params, interval, fractie);
+        let eigen = intervallen.kosten(params, interval, fractie).1;
+        let co2 = (energie - eigen).max(0.0) * emissiefactoren[intervallen.uur(interval)];
+        (energie, co2)
+    };
+
+    // Inzet per pomp, alleen voor een gemaal met meerdere pompen
+    let pomp_inzet = |interval: usize, fractie: f64| -> Vec<f64> {
+        if params.pompen.is_empty() {
+            Vec::new()
+        } else {
+            intervallen.inzet(interval, fractie)
+        }
+    };
+    let uur_inzet = |uur: usize, fracties: &[f64]| -> Vec<f64> {
+        let mut inzet = vec![0.0; params.pompen.len()];
+        let uur_intervallen = uur * per_uur..(uur + 1) * per_uur;
+        for (interval, &fractie) in uur_intervallen.clone().zip(&fracties[uur_intervallen]) {
+            for (totaal, i) in inzet.iter_mut().zip(pomp_inzet(interval, fractie)) {
+                *totaal += i / per_uur as f64;
+            }
+        }
+        inzet
+    };
 
     // Bouw uur-resultaten
-    let mut uren = Vec::with_capacity(24);
+    let mut uren = Vec::with_capacity(params.horizon_uren);
     let mut max_afwijking_opt: f64 = 0.0;
     let mut max_afwijking_naief: f64 = 0.0;
 
-    for uur in 0..24_usize {
+    for (uur, &emissiefactor) in emissiefactoren.iter().enumerate() {
         let regen = *params.regen_per_uur.get(uur).unwrap_or(&0.0);
-        let prijs = prijzen[uur].prijs_eur_kwh;
+        let uur_intervallen = uur * per_uur..(uur + 1) * per_uur;
+        let prijs = intervallen.prijzen[uur_intervallen.clone()].iter().sum::<f64>() / per_uur as f64;
 
         // Eind waterstand = waterstand aan het einde van het uur
         let minuut_eind = (uur + 1) * 60 - 1;
         let ws_eind_opt = stappen_opt.get(minuut_eind).map(|s| s.waterstand).unwrap_or(params.streefpeil);
         let ws_eind_naief = stappen_naief.get(minuut_eind).map(|s| s.waterstand).unwrap_or(params.streefpeil);
 
-        // Kosten dit uur
-        let debiet_opt = opt_fracties[uur] * params.max_debiet;
-        let debiet_naief = naief_fracties[uur] * params.max_debiet;
-        let power_opt = calculate_pump_power_kw(debiet_opt, params.opvoerhoogte, params.efficiency);
-        let power_naief = calculate_pump_power_kw(debiet_naief, params.opvoerhoogte, params.efficiency);
-        let kosten_uur_opt = power_opt * prijs;
-        let kosten_uur_naief = power_naief * prijs;
+        // Kosten, verbruik, uitstoot en gemiddelde pompfractie dit uur
+        let mut kosten_uur_opt = 0.0;
+        let mut kosten_uur_naief = 0.0;
+        let (mut energie_opt, mut co2_opt) = (0.0, 0.0);
+        let (mut energie_naief, mut co2_naief) = (0.0, 0.0);
+        let mut fractie_opt = 0.0;
+        let mut fractie_naief = 0.0;
+        for interval in uur_intervallen {
+            kosten_uur_opt += interval_kosten(interval, opt_fracties[interval]);
+            kosten_uur_naief += interval_kosten(interval, naief_fracties[interval]);
+            let (energie, co2) = interval_energie(interval, opt_fracties[interval]);
+            energie_opt += energie;
+            co2_opt += co2;
+            let (energie, co2) = interval_energie(interval, naief_fracties[interval]);
+            energie_naief += energie;
+            co2_naief += co2;
+            fractie_opt += opt_fracties[interval] / per_uur as f64;
+            fractie_naief += naief_fracties[interval] / per_uur as f64;
+        }
 
         // Max afwijking: bereken over alle minuten van dit uur
         for m in (uur * 60)..((uur + 1) * 60) {
@@ -334,15 +1154,43 @@ pub fn optimize_pump_schedule(
             uur: uur as u8,
             prijs_eur_kwh: prijs,
             regen_mm_uur: regen,
-            pomp_fractie_optimaal: opt_fracties[uur],
-            pomp_fractie_naief: naief_fracties[uur],
+            pomp_fractie_optimaal: fractie_opt,
+            pomp_fractie_naief: fractie_naief,
             waterstand_eind_optimaal: ws_eind_opt,
             waterstand_eind_naief: ws_eind_naief,
             kosten_optimaal: kosten_uur_opt,
             kosten_naief: kosten_uur_naief,
+            energie_optimaal_kwh: energie_opt,
+            energie_naief_kwh: energie_naief,
+            emissiefactor_kg_kwh: emissiefactor,
+            co2_optimaal_kg: co2_opt,
+            co2_naief_kg: co2_naief,
+            pompen_optimaal: uur_inzet(uur, &opt_fracties),
+            pompen_naief: uur_inzet(uur, &naief_fracties),
         });
     }
 
+    let kwartieren = if intervallen.is_kwartier() {
+        (0..n_intervallen)
+            .map(|interval| {
+                let prijs = intervallen.prijzen[interval];
+                OptimalisatieKwartierResultaat {
+                    uur: intervallen.uur(interval) as u8,
+                    kwartier: (interval % 4) as u8,
+                    prijs_eur_kwh: prijs,
+                    pomp_fractie_optimaal: opt_fracties[interval],
+                    pomp_fractie_naief: naief_fracties[interval],
+                    kosten_optimaal: interval_kosten(interval, opt_fracties[interval]),
+                    kosten_naief: interval_kosten(interval, naief_fracties[interval]),
+                    pompen_optimaal: pomp_inzet(interval, opt_fracties[interval]),
+                    pompen_naief: pomp_inzet(interval, naief_fracties[interval]),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
     let besparing = kosten_naief - kosten_opt;
     let besparing_pct = if kosten_naief > 0.001 {
         (besparing / kosten_naief) * 100.0
@@ -350,6 +1198,12 @@ pub fn optimize_pump_schedule(
         0.0
     };
 
+    let totaal = |f: fn(&OptimalisatieUurResultaat) -> f64| uren.iter().map(f).sum::<f64>();
+    let (totale_energie_optimaal_kwh, totale_energie_naief_kwh) =
+        (totaal(|u| u.energie_optimaal_kwh), totaal(|u| u.energie_naief_kwh));
+    let (totale_co2_optimaal_kg, totale_co2_naief_kg) =
+        (totaal(|u| u.co2_optimaal_kg), totaal(|u| u.co2_naief_kg));
+
     Ok(OptimalisatieResultaat {
         uren,
         totale_kosten_optimaal: kosten_opt,
@@ -360,19 +1214,64 @@ pub fn optimize_pump_schedule(
         max_afwijking_naief_cm: max_afwijking_naief,
         tijdstappen_optimaal: stappen_opt,
         tijdstappen_naief: stappen_naief,
-        prijzen,
+        prijzen: intervallen.uur_prijzen(params),
+        eigen_verbruik_optimaal_kwh: eigen_verbruik(&opt_fracties),
+        eigen_verbruik_naief_kwh: eigen_verbruik(&naief_fracties),
+        totale_energie_optimaal_kwh,
+        totale_energie_naief_kwh,
+        totale_co2_optimaal_kg,
+        totale_co2_naief_kg,
+        inschakelingen_optimaal: inschakelingen(&opt_fracties),
+        inschakelingen_naief: inschakelingen(&naief_fracties),
+        piekvermogen_optimaal_kw: piek_opt_kw,
+        piekvermogen_naief_kw: piek_naief_kw,
+        piekboete_optimaal_eur: piekboete_opt,
+        piekboete_naief_eur: piekboete_naief,
+        flexvergoeding_optimaal_eur: flexvergoeding(&opt_fracties),
+        flexvergoeding_naief_eur: flexvergoeding(&naief_fracties),
+        kwartieren,
+        pompen: pompen::in_volgorde(&params.pompen).into_iter().map(|p| p.id).collect(),
+        totale_kosten_aan_uit: kosten_aan_uit,
     })
 }
 
+/// Vermogen van een pomp als stuksgewijs lineaire, convexe functie van de
+/// inzet: (lengte, helling) per segment, als deel van het volle vermogen.
+fn vermogenssegmenten(params: &OptimalisatieParams) -> Vec<(f64, f64)> {
+    const SEGMENTEN: usize = 8;
+    let grenzen: Vec<f64> = match params.pompregeling {
+        Pompregeling::AanUit => vec![0.0, 1.0],
+        Pompregeling::Toerengeregeld => {
+            let min = params.min_toerental;
+            let mut grenzen = vec![0.0, min];
+            grenzen.extend((1..=SEGMENTEN).map(|k| k as f64 / SEGMENTEN as f64).filter(|&g| g > min));
+            grenzen
+        }
+    };
+    grenzen
+        .windows(2)
+        .filter(|paar| paar[1] > paar[0])
+        .map(|paar| {
+            let lengte = paar[1] - paar[0];
+            let helling = (params.vermogensfactor(paar[1]) - params.vermogensfactor(paar[0])) / lengte;
+            (lengte, helling)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use peilbeheer_core::energie::Flexsignaal;
+    use peilbeheer_core::energiecontract::{
+        Daltarief, EnergieContract, Netcapaciteit, Tarief, Teruglevering,
+    };
 
     fn make_params(regen: Vec<f64>, prijzen: Vec<f64>) -> OptimalisatieParams {
         let prijzen_vec: Vec<UurPrijs> = prijzen
             .into_iter()
             .enumerate()
-            .map(|(i, p)| UurPrijs { uur: i as u8, prijs_eur_kwh: p })
+            .map(|(i, p)| UurPrijs::uur(i as u8, p))
             .collect();
         OptimalisatieParams {
             streefpeil: -0.60,
@@ -382,10 +1281,33 @@ mod tests {
             infiltratie: 0.0,
             opvoerhoogte: 2.0,
             efficiency: 0.70,
+            pompcurve: None,
+            pompen: Vec::new(),
+            buitenpeil_per_uur: Vec::new(),
+            buitenpeil_locatie: None,
             regen_per_uur: regen,
             prijzen: prijzen_vec,
+            prijs_signaal: Default::default(),
+            opwek_kw: Vec::new(),
+            pv_installatie: None,
+            terugleververgoeding: 0.0,
+            emissiefactor_per_uur: Vec::new(),
+            contract: None,
+            gemaal_code: None,
+            starttijd: None,
+            flexsignalen: Vec::new(),
             marge_cm: 20.0,
             berging_factor: 0.10,
+            horizon_uren: 24,
+            herplan_uren: 24,
+            start_waterstand: None,
+            optimizer: Optimizer::Dp,
+            min_draaitijd_min: 0,
+            min_uittijd_min: 0,
+            schakelkosten: 0.0,
+            min_pompfractie: 0.0,
+            pompregeling: Pompregeling::AanUit,
+            min_toerental: 0.0,
         }
     }
 
@@ -534,7 +1456,509 @@ mod tests {
         assert!(optimize_pump_schedule(&params).is_err());
 
         let mut params = make_params(vec![0.0; 24], vec![0.10; 24]);
-        params.regen_per_uur = vec![0.0; 12]; // verkeerd aantal
+        params.regen_per_uur = vec![0.0; params.horizon_uren + 1]; // langer dan de horizon
+        assert!(optimize_pump_schedule(&params).is_err());
+    }
+
+    #[test]
+    fn test_milp_max_pompen() {
+        let mut params = make_params(vec![0.0; 24], vec![0.10; 24]);
+        params.max_debiet = 0.0;
+        params.pompen = (0..=MAX_MILP_POMPEN)
+            .map(|i| Pomp::new(format!("p{}", i), 0.2, 0.01, -0.01).met_volgorde(i as u32))
+            .collect();
+        params.optimizer = Optimizer::Milp;
+        let err = optimize_pump_schedule(&params).unwrap_err();
+        assert!(err.contains("hoogstens"), "{}", err);
+
+        // De DP heeft geen binaire variabelen en accepteert ze wel
+        params.optimizer = Optimizer::Dp;
+        assert!(optimize_pump_schedule(&params).is_ok());
+    }
+
+    #[test]
+    fn test_kwartierprijzen() {
+        // Regen, day-ahead vlak; onbalans maakt het eerste kwartier van
+        // elk uur duur en het laatste goedkoop
+        let mut regen = vec![0.0; 24];
+        regen[6] = 5.0;
+        regen[7] = 10.0;
+        regen[8] = 5.0;
+        let mut params = make_params(regen, vec![0.10; 24]);
+        for uur in 0..24 {
+            params.prijzen.push(UurPrijs::kwartier(uur, 0, 0.40));
+            params.prijzen.push(UurPrijs::kwartier(uur, 3, 0.01));
+        }
+        let result = optimize_pump_schedule(&params).unwrap();
+
+        assert_eq!(result.kwartieren.len(), 96);
+        assert_eq!(result.uren.len(), 24);
+        assert_eq!(result.prijzen.len(), 96);
+        assert_eq!(result.tijdstappen_optimaal.len(), 24 * 60);
+
+        // Het optimale schema pompt meer in goedkope dan in dure kwartieren
+        let pomp = |k: u8| -> f64 {
+            result
+                .kwartieren
+                .iter()
+                .filter(|q| q.kwartier == k)
+                .map(|q| q.pomp_fractie_optimaal)
+                .sum()
+        };
+        assert!(pomp(3) > pomp(0), "goedkoop {} vs duur {}", pomp(3), pomp(0));
+        assert!(result.totale_kosten_optimaal <= result.totale_kosten_naief + 1e-9);
+
+        // Uurprijs is het gemiddelde van de kwartieren
+        assert!((result.uren[0].prijs_eur_kwh - 0.1525).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_uurprijzen_geen_kwartieren() {
+        let params = make_params(vec![0.0; 24], vec![0.10; 24]);
+        let result = optimize_pump_schedule(&params).unwrap();
+        assert!(result.kwartieren.is_empty());
+        assert_eq!(result.prijzen, params.prijzen);
+    }
+
+    #[test]
+    fn test_energie_en_co2() {
+        let mut regen = vec![0.0; 24];
+        regen[3] = 15.0;
+        regen[4] = 15.0;
+        let mut params = make_params(regen, vec![0.25; 24]);
+        // 's Nachts meer fossiele opwek dan overdag
+        params.emissiefactor_per_uur =
+            (0..24).map(|uur| if (9..17).contains(&uur) { 0.15 } else { 0.45 }).collect();
+        let result = optimize_pump_schedule(&params).unwrap();
+
+        assert!(result.totale_energie_optimaal_kwh > 0.0);
+        assert!((result.totale_energie_optimaal_kwh * 0.25 - result.totale_kosten_optimaal).abs() < 1e-6);
+        for uur in &result.uren {
+            assert_eq!(uur.emissiefactor_kg_kwh, params.emissiefactor_per_uur[uur.uur as usize]);
+            assert!((uur.co2_optimaal_kg - uur.energie_optimaal_kwh * uur.emissiefactor_kg_kwh).abs() < 1e-9);
+            assert!((uur.co2_naief_kg - uur.energie_naief_kwh * uur.emissiefactor_kg_kwh).abs() < 1e-9);
+        }
+        let co2: f64 = result.uren.iter().map(|u| u.co2_naief_kg).sum();
+        assert!((result.totale_co2_naief_kg - co2).abs() < 1e-9);
+
+        // Zonnestroom voor eigen verbruik telt zonder uitstoot
+        params.opwek_kw = vec![1_000.0; 24];
+        let zon = optimize_pump_schedule(&params).unwrap();
+        assert!(zon.totale_energie_optimaal_kwh > 0.0);
+        assert_eq!(zon.totale_co2_optimaal_kg, 0.0);
+    }
+
+    #[test]
+    fn test_zonnestroom_eigen_verbruik() {
+        // Regen in de ochtend, vlakke prijs; de panelen leveren rond het
+        // middaguur meer dan de pomp vraagt
+        let mut regen = vec![0.0; 24];
+        regen[6] = 10.0;
+        regen[7] = 20.0;
+        regen[8] = 10.0;
+        let zonder = make_params(regen.clone(), vec![0.25; 24]);
+        let mut met = zonder.clone();
+        met.opwek_kw = (0..24)
+            .map(|uur| if (10..15).contains(&uur) { 20.0 } else { 0.0 })
+            .collect();
+        met.terugleververgoeding = 0.05;
+
+        let zonder = optimize_pump_schedule(&zonder).unwrap();
+        let met = optimize_pump_schedule(&met).unwrap();
+
+        assert_eq!(zonder.eigen_verbruik_optimaal_kwh, 0.0);
+        assert!(met.eigen_verbruik_optimaal_kwh > 0.0);
+        assert!(met.totale_kosten_optimaal < zonder.totale_kosten_optimaal);
+
+        // Er wordt vooral gepompt als de zon schijnt
+        let pomp = |uren: std::ops::Range<usize>| -> f64 {
+            met.uren[uren].iter().map(|u| u.pomp_fractie_optimaal).sum()
+        };
+        assert!(pomp(10..15) > pomp(15..20));
+    }
+
+    #[test]
+    fn test_energiecontract() {
+        let mut regen = vec![0.0; 24];
+        regen[12] = 15.0;
+        regen[13] = 15.0;
+        // Met een vast tarief en daltarief doen de marktprijzen er niet toe
+        let mut params = make_params(regen, vec![0.01; 24]);
+        params.contract = Some(EnergieContract {
+            tarief: Tarief::Vast {
+                prijs_eur_kwh: 0.30,
+                dal: Some(Daltarief { prijs_eur_kwh: 0.10, van_uur: 20, tot_uur: 7 }),
+            },
+            teruglevering: Teruglevering::Geen,
+            netcapaciteit: None,
+        });
+        let result = optimize_pump_schedule(&params).unwrap();
+        assert_eq!(result.prijzen[3].prijs_eur_kwh, 0.10);
+        assert_eq!(result.prijzen[12].prijs_eur_kwh, 0.30);
+        let pomp = |uren: std::ops::Range<usize>| -> f64 {
+            result.uren[uren].iter().map(|u| u.pomp_fractie_optimaal).sum()
+        };
+        // De bui om 12 uur wordt in de daluren vooraf weggepompt
+        assert!(pomp(0..7) > pomp(7..20));
+
+        // Boven het gecontracteerde vermogen pompt de optimalisatie niet,
+        // het naïeve schema pompt vol en betaalt de boete
+        let vol_kw = calculate_pump_power_kw(params.max_debiet, params.opvoerhoogte, params.efficiency);
+        params.contract = Some(EnergieContract {
+            tarief: Tarief::Dynamisch { opslag_eur_kwh: 0.10 },
+            teruglevering: Teruglevering::Geen,
+            netcapaciteit: Some(Netcapaciteit { gecontracteerd_kw: vol_kw * 0.6, boete_eur_kw: 50.0 }),
+        });
+        for optimizer in [Optimizer::Dp, Optimizer::Milp] {
+            params.optimizer = optimizer;
+            let result = optimize_pump_schedule(&params).unwrap();
+            assert!(result.piekvermogen_optimaal_kw <= vol_kw * 0.6 + 1e-6);
+            assert!(result.piekboete_optimaal_eur < 1e-6);
+            assert!(result.piekvermogen_naief_kw > vol_kw * 0.6);
+            assert!(
+                (result.piekboete_naief_eur - (result.piekvermogen_naief_kw - vol_kw * 0.6) * 50.0)
+                    .abs()
+                    < 1e-6
+            );
+            assert!(result.totale_kosten_naief > result.piekboete_naief_eur);
+            assert!((result.uren[0].prijs_eur_kwh - 0.11).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_flexsignalen() {
+        let mut regen = vec![0.0; 24];
+        regen[9] = 15.0;
+        regen[10] = 15.0;
+        let mut params = make_params(regen, vec![0.25; 24]);
+        // Het net vraagt om af te regelen tijdens de bui en betaalt voor
+        // opregelen om 2 uur
+        params.flexsignalen = (9..13)
+            .map(|uur| Flexsignaal { uur, max_vermogen_kw: Some(0.0), vergoeding_eur_kwh: 0.0 })
+            .collect();
+        params.flexsignalen.push(Flexsignaal { uur: 2, max_vermogen_kw: None, vergoeding_eur_kwh: 0.20 });
+
+        for optimizer in [Optimizer::Dp, Optimizer::Milp] {
+            params.optimizer = optimizer;
+            let result = optimize_pump_schedule(&params).unwrap();
+            assert!(result.uren[9..13].iter().all(|u| u.pomp_fractie_optimaal < 1e-9));
+            assert!(result.uren[2].pomp_fractie_optimaal > 0.0);
+            assert!(result.flexvergoeding_optimaal_eur > 0.0);
+            assert!(
+                (result.totale_energie_optimaal_kwh * 0.25
+                    - result.flexvergoeding_optimaal_eur
+                    - result.totale_kosten_optimaal)
+                    .abs()
+                    < 1e-6
+            );
+            // Het naïeve schema houdt zich niet aan het signaal
+            assert!(result.uren[9].pomp_fractie_naief > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_buitenpeil_opvoerhoogte() {
+        // Vlakke prijs; bij hoogwater (uur 10-15) is de opvoerhoogte groot,
+        // dus pompen is goedkoper bij laagwater
+        let mut regen = vec![0.0; 24];
+        regen[6] = 10.0;
+        regen[7] = 20.0;
+        regen[8] = 10.0;
+        let mut params = make_params(regen, vec![0.25; 24]);
+        params.buitenpeil_per_uur = (0..24)
+            .map(|uur| if (10..16).contains(&uur) { 3.0 } else { 0.0 })
+            .collect();
+        let result = optimize_pump_schedule(&params).unwrap();
+
+        let pomp = |uren: std::ops::Range<usize>| -> f64 {
+            result.uren[uren].iter().map(|u| u.pomp_fractie_optimaal).sum()
+        };
+        assert!(pomp(0..10) > pomp(10..16));
+        assert!(result.totale_kosten_optimaal <= result.totale_kosten_naief + 1e-9);
+    }
+
+    #[test]
+    fn test_milp_niet_duurder_dan_dp() {
+        // Regen in dure uren: de MILP is exact en dus hooguit zo duur als de DP
+        let mut regen = vec![0.0; 24];
+        regen[10] = 8.0;
+        regen[11] = 8.0;
+        regen[12] = 5.0;
+        let mut prijzen = vec![0.05; 24];
+        prijzen[10] = 0.30;
+        prijzen[11] = 0.30;
+        prijzen[12] = 0.25;
+        let mut params = make_params(regen, prijzen);
+        params.max_debiet = 2.0;
+
+        let dp = optimize_pump_schedule(&params).unwrap();
+        params.optimizer = Optimizer::Milp;
+        let milp = optimize_pump_schedule(&params).unwrap();
+
+        assert!(milp.totale_kosten_optimaal > 0.0);
+        assert!(
+            milp.totale_kosten_optimaal <= dp.totale_kosten_optimaal + 0.01,
+            "MILP ({:.4}) duurder dan DP ({:.4})",
+            milp.totale_kosten_optimaal,
+            dp.totale_kosten_optimaal
+        );
+        assert!(milp.max_afwijking_optimaal_cm <= params.marge_cm + 0.5);
+    }
+
+    #[test]
+    fn test_milp_minimale_draaitijd_en_fractie() {
+        let mut regen = vec![0.0; 24];
+        regen[6] = 10.0;
+        regen[7] = 10.0;
+        regen[8] = 10.0;
+        let mut params = make_params(regen, vec![0.10; 24]);
+        params.oppervlakte = 1_000_000.0;
+        params.marge_cm = 10.0;
+        params.optimizer = Optimizer::Milp;
+        params.min_draaitijd_min = 180;
+        params.min_pompfractie = 0.5;
+
+        let result = optimize_pump_schedule(&params).unwrap();
+        let fracties: Vec<f64> = result.uren.iter().map(|u| u.pomp_fractie_optimaal).collect();
+        assert!(fracties.iter().any(|&f| f > 0.0));
+
+        for (uur, &fractie) in fracties.iter().enumerate() {
+            // Draaiend minstens de minimale fractie
+            assert!(!(1e-6..0.5 - 1e-6).contains(&fractie), "uur {}: {}", uur, fractie);
+            // Na inschakelen minstens drie uur aan (of tot het eind)
+            let ingeschakeld = fractie > 1e-6 && (uur == 0 || fracties[uur - 1] < 1e-6);
+            if ingeschakeld {
+                for volgend in fracties.iter().skip(uur).take(3) {
+                    assert!(*volgend > 1e-6, "uur {} te kort aan: {:?}", uur, fracties);
+                }
+            }
+        }
+    }
+
+    /// Controleer dat het gemaal na in- en uitschakelen lang genoeg aan en
+    /// uit blijft (of tot het eind van de horizon); bij de start staat het
+    /// uit.
+    fn assert_min_tijden(fracties: &[f64], draai: usize, uit: usize) {
+        for uur in 0..fracties.len() {
+            let aan = is_aan(fracties[uur]);
+            let was_aan = uur > 0 && is_aan(fracties[uur - 1]);
+            let (minimaal, blijft) = match (was_aan, aan) {
+                (false, true) => (draai, true),
+                (true, false) => (uit, false),
+                _ => continue,
+            };
+            for volgend in fracties.iter().skip(uur).take(minimaal) {
+                assert_eq!(is_aan(*volgend), blijft, "uur {}: {:?}", uur, fracties);
+            }
+        }
+    }
+
+    #[test]
+    fn test_schakelkosten_en_minimale_tijden() {
+        // Buien om de paar uur en een wisselende prijs lokken veel
+        // schakelen uit
+        let regen: Vec<f64> = (0..24).map(|uur| if uur % 4 == 1 { 8.0 } else { 0.0 }).collect();
+        let prijzen: Vec<f64> = (0..24).map(|uur| if uur % 2 == 0 { 0.05 } else { 0.30 }).collect();
+        for optimizer in [Optimizer::Dp, Optimizer::Milp] {
+            let mut params = make_params(regen.clone(), prijzen.clone());
+            params.optimizer = optimizer;
+            let vrij = optimize_pump_schedule(&params).unwrap();
+
+            params.schakelkosten = 50.0;
+            let duur = optimize_pump_schedule(&params).unwrap();
+            assert!(
+                duur.inschakelingen_optimaal < vrij.inschakelingen_optimaal,
+                "{:?}: {} vs {}",
+                optimizer,
+                duur.inschakelingen_optimaal,
+                vrij.inschakelingen_optimaal
+            );
+
+            params.schakelkosten = 0.0;
+            params.min_draaitijd_min = 120;
+            params.min_uittijd_min = 180;
+            let result = optimize_pump_schedule(&params).unwrap();
+            let fracties: Vec<f64> = result.uren.iter().map(|u| u.pomp_fractie_optimaal).collect();
+            assert!(fracties.iter().any(|&f| is_aan(f)));
+            assert_min_tijden(&fracties, 2, 3);
+        }
+    }
+
+    #[test]
+    fn test_dp_min_pompfractie() {
+        // Lichte regen zou met kleine pompfracties weggepompt worden
+        let mut params = make_params(vec![1.0; 24], vec![0.10; 24]);
+        params.min_pompfractie = 0.5;
+        let result = optimize_pump_schedule(&params).unwrap();
+        assert!(result.uren.iter().any(|u| is_aan(u.pomp_fractie_optimaal)));
+        for uur in &result.uren {
+            let fractie = uur.pomp_fractie_optimaal;
+            assert!(!is_aan(fractie) || fractie >= 0.5, "{}", fractie);
+        }
+    }
+
+    #[test]
+    fn test_milp_ongeldige_min_pompfractie() {
+        let mut params = make_params(vec![0.0; 24], vec![0.10; 24]);
+        params.optimizer = Optimizer::Milp;
+        params.min_pompfractie = 1.5;
+        assert!(optimize_pump_schedule(&params).is_err());
+    }
+
+    #[test]
+    fn test_meerdaagse_horizon() {
+        // Drie dagen met regen op dag twee; de prijzen van dag één herhalen
+        let mut regen = vec![0.0; 72];
+        regen[30] = 20.0;
+        regen[31] = 20.0;
+        let mut prijzen = vec![0.05; 24];
+        prijzen[6] = 0.30;
+        prijzen[7] = 0.30;
+        let mut params = make_params(regen, prijzen);
+        params.horizon_uren = 72;
+        params.herplan_uren = 12;
+
+        let result = optimize_pump_schedule(&params).unwrap();
+        assert_eq!(result.uren.len(), 72);
+        assert_eq!(result.tijdstappen_optimaal.len(), 72 * 60);
+        assert_eq!(result.uren[30].prijs_eur_kwh, 0.30);
+        assert!(result.totale_kosten_optimaal > 0.0);
+        assert!(result.totale_kosten_optimaal <= result.totale_kosten_naief + 0.01);
+        assert!(result.max_afwijking_optimaal_cm <= params.marge_cm + 1.0);
+
+        params.horizon_uren = 200;
+        assert!(optimize_pump_schedule(&params).is_err());
+        params.horizon_uren = 72;
+        params.herplan_uren = 0;
+        assert!(optimize_pump_schedule(&params).is_err());
+    }
+
+    #[test]
+    fn test_start_waterstand_overdracht() {
+        // Start boven de band: het eerste venster pompt het water terug en
+        // de volgende vensters gaan verder vanaf de bereikte waterstand
+        let mut params = make_params(vec![0.0; 48], vec![0.10; 24]);
+        params.horizon_uren = 48;
+        params.herplan_uren = 6;
+        params.start_waterstand = Some(params.streefpeil + 0.30);
+
+        let result = optimize_pump_schedule(&params).unwrap();
+        let stappen = &result.tijdstappen_optimaal;
+        assert_eq!(stappen[0].waterstand, params.streefpeil + 0.30);
+        assert!(result.uren[0].pomp_fractie_optimaal > 0.0);
+
+        // Geen sprong in de waterstand op de venstergrenzen
+        let max_daling = params.max_debiet / params.oppervlakte * 60.0 + 1e-9;
+        for paar in stappen.windows(2) {
+            assert!((paar[1].waterstand - paar[0].waterstand).abs() <= max_daling);
+        }
+        let eind = stappen.last().unwrap().waterstand;
+        assert!((eind - params.streefpeil).abs() <= params.marge_cm / 100.0 + 0.01);
+        for uur in &result.uren[12..] {
+            assert!((uur.waterstand_eind_optimaal - params.streefpeil).abs() <= 0.21);
+        }
+    }
+
+    #[test]
+    fn test_pompcurve() {
+        use peilbeheer_core::pompcurve::{PompCurve, PompPunt};
+
+        let mut regen = vec![0.0; 24];
+        regen[6] = 20.0;
+        regen[7] = 20.0;
+        let mut params = make_params(regen, vec![0.10; 24]);
+        let zonder = optimize_pump_schedule(&params).unwrap();
+
+        // Een curve die bij 2 m het vaste debiet en rendement geeft, verandert niets
+        params.pompcurve = Some(
+            PompCurve::new(vec![
+                PompPunt::new(2.0, 0.5).met_rendement(0.70),
+                PompPunt::new(2.5, 0.0).met_rendement(0.70),
+            ])
+            .unwrap(),
+        );
+        params.max_debiet = 0.0;
+        let met = optimize_pump_schedule(&params).unwrap();
+        assert!(met.totale_kosten_optimaal > 0.0);
+        assert!((met.totale_kosten_optimaal - zonder.totale_kosten_optimaal).abs() < 1e-9);
+
+        // Boven de nulopbrengsthoogte kan het gemaal niet pompen
+        params.buitenpeil_per_uur = vec![params.streefpeil + 3.0];
+        let hoog = optimize_pump_schedule(&params).unwrap();
+        assert_eq!(hoog.totale_kosten_optimaal, 0.0);
+        assert!(hoog.max_afwijking_optimaal_cm > params.marge_cm);
+
+        params.pompcurve = Some(PompCurve { punten: Vec::new() });
+        assert!(optimize_pump_schedule(&params).is_err());
+    }
+
+    #[test]
+    fn test_toerenregeling() {
+        let mut params = make_params(vec![2.0; 24], vec![0.10; 24]);
+        params.max_debiet = 2.0;
+
+        for optimizer in [Optimizer::Dp, Optimizer::Milp] {
+            params.optimizer = optimizer;
+            params.pompregeling = Pompregeling::AanUit;
+            let aan_uit = optimize_pump_schedule(&params).unwrap();
+            assert_eq!(aan_uit.totale_kosten_aan_uit, None);
+
+            // Langzaam pompen kost minder dan kort vol pompen
+            params.pompregeling = Pompregeling::Toerengeregeld;
+            let toeren = optimize_pump_schedule(&params).unwrap();
+            let vergelijking = toeren.totale_kosten_aan_uit.unwrap();
+            assert!((vergelijking - aan_uit.totale_kosten_optimaal).abs() < 1e-9);
+            assert!(toeren.totale_kosten_optimaal < 0.5 * vergelijking);
+            assert!(toeren.max_afwijking_optimaal_cm <= params.marge_cm + 0.5);
+        }
+
+        params.min_toerental = 1.0;
+        assert!(optimize_pump_schedule(&params).is_err());
+    }
+
+    #[test]
+    fn test_meerdere_pompen() {
+        use peilbeheer_core::pompen::Pomp;
+
+        let mut regen = vec![0.0; 24];
+        regen[6] = 20.0;
+        regen[7] = 20.0;
+        let mut params = make_params(regen, vec![0.10; 24]);
+        params.max_debiet = 0.0;
+        params.pompen = vec![
+            Pomp::new("p2", 0.3, 0.25, 0.05).met_volgorde(2),
+            Pomp::new("p1", 0.2, 0.01, -0.01).met_volgorde(1),
+        ];
+
+        for optimizer in [Optimizer::Dp, Optimizer::Milp] {
+            params.optimizer = optimizer;
+            let result = optimize_pump_schedule(&params).unwrap();
+            assert_eq!(result.pompen, vec!["p1", "p2"]);
+
+            for uur in &result.uren {
+                for (inzet, fractie) in [
+                    (&uur.pompen_optimaal, uur.pomp_fractie_optimaal),
+                    (&uur.pompen_naief, uur.pomp_fractie_naief),
+                ] {
+                    assert_eq!(inzet.len(), 2);
+                    // Samen het debiet van de fractie, p2 pas als p1 vol draait
+                    assert!((inzet[0] * 0.2 + inzet[1] * 0.3 - fractie * 0.5).abs() < 1e-6);
+                    assert!(inzet[1] < 1e-6 || inzet[0] > 1.0 - 1e-6);
+                }
+            }
+            assert!(result.uren.iter().any(|u| u.pompen_naief[1] > 0.0));
+        }
+
+        // Het naïeve schema schakelt de pompen op hun schakelpeilen
+        let result = optimize_pump_schedule(&params).unwrap();
+        assert!(
+            result
+                .uren
+                .iter()
+                .any(|u| u.pompen_naief == vec![1.0, 0.0])
+        );
+
+        params.pompen.push(Pomp::new("p1", 0.1, 0.1, 0.0));
         assert!(optimize_pump_schedule(&params).is_err());
     }
 }
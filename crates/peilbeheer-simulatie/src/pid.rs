@@ -1,13 +1,27 @@
+use peilbeheer_core::waterbalans::PidInstellingen;
+
 /// PID-regelaar voor gemaal sturing.
 ///
 /// Regelt het debiet op basis van de afwijking van het streefpeil.
 /// Kp=5.0, Ki=0.05, Kd=20.0 zijn de standaardwaarden uit de Python implementatie.
+///
+/// Voor gebruik met meetruis en pompbeperkingen kan de D-term door een
+/// laagdoorlaatfilter gaan en de verandering van de uitvoer per tijdstap
+/// begrensd worden. De integrator integreert niet verder zolang de uitvoer
+/// door de begrenzing vastzit (anti-windup) en de I-term alleen kan nooit
+/// buiten 0.0 - 1.0 komen.
 pub struct PidController {
     kp: f64,
     ki: f64,
     kd: f64,
+    /// Tijdconstante van het D-filter in minuten
+    d_filter: Option<f64>,
+    /// Grootste verandering van de uitvoer per tijdstap
+    max_verandering: Option<f64>,
     integral: f64,
     last_error: f64,
+    derivative: f64,
+    last_output: f64,
 }
 
 impl PidController {
@@ -16,9 +30,38 @@ impl PidController {
             kp,
             ki,
             kd,
+            d_filter: None,
+            max_verandering: None,
             integral: 0.0,
             last_error: 0.0,
+            derivative: 0.0,
+            last_output: 0.0,
+        }
+    }
+
+    pub fn met_instellingen(instellingen: &PidInstellingen) -> Self {
+        let mut pid = Self::new(instellingen.kp, instellingen.ki, instellingen.kd);
+        if let Some(tijdconstante) = instellingen.d_filter_minuten {
+            pid = pid.met_d_filter(tijdconstante);
+        }
+        if let Some(max) = instellingen.max_verandering {
+            pid = pid.met_max_verandering(max);
         }
+        pid
+    }
+
+    /// Filter de D-term met een eerste-orde laagdoorlaatfilter met
+    /// tijdconstante in minuten.
+    pub fn met_d_filter(mut self, tijdconstante: f64) -> Self {
+        self.d_filter = (tijdconstante > 0.0).then_some(tijdconstante);
+        self
+    }
+
+    /// Begrens de verandering van de uitvoer per tijdstap, bijvoorbeeld
+    /// voor de aanloop van een pomp of een toerengeregelde pomp.
+    pub fn met_max_verandering(mut self, max: f64) -> Self {
+        self.max_verandering = (max > 0.0).then_some(max);
+        self
     }
 
     /// Bereken PID output (0.0 - 1.0) op basis van error en tijdstap.
@@ -33,28 +76,49 @@ impl PidController {
         }
 
         // Integral alleen bijwerken bij significante error
-        if error.abs() > 0.001 {
-            self.integral += error * dt;
+        let integral_stap = if error.abs() > 0.001 { error * dt } else { 0.0 };
+        self.integral += integral_stap;
+        if self.ki != 0.0 {
+            let grens = 1.0 / self.ki.abs();
+            self.integral = self.integral.clamp(-grens, grens);
         }
 
         let derivative = (error - self.last_error) / dt;
-        let output = (self.kp * error) + (self.ki * self.integral) + (self.kd * derivative);
+        self.derivative = match self.d_filter {
+            Some(tijdconstante) => {
+                self.derivative + dt / (tijdconstante + dt) * (derivative - self.derivative)
+            }
+            None => derivative,
+        };
+        let onbegrensd =
+            (self.kp * error) + (self.ki * self.integral) + (self.kd * self.derivative);
 
-        // Clamp naar 0.0 - 1.0
-        let output = output.clamp(0.0, 1.0);
+        // Clamp naar 0.0 - 1.0 en begrens de verandering per tijdstap
+        let mut output = onbegrensd.clamp(0.0, 1.0);
+        if let Some(max) = self.max_verandering {
+            output = output.clamp(self.last_output - max, self.last_output + max);
+        }
 
-        // Anti-windup: corrigeer integral als output gesatureerd is
-        if (output == 0.0 && error < 0.0) || (output == 1.0 && error > 0.0) {
-            self.integral -= error * dt;
+        // Anti-windup: niet verder integreren als de begrenzing de uitvoer
+        // tegenhoudt in de richting van de error
+        if (output == 0.0 && error < 0.0)
+            || (output == 1.0 && error > 0.0)
+            || (onbegrensd > output && error > 0.0)
+            || (onbegrensd < output && error < 0.0)
+        {
+            self.integral -= integral_stap;
         }
 
         self.last_error = error;
+        self.last_output = output;
         output
     }
 
     pub fn reset(&mut self) {
         self.integral = 0.0;
         self.last_error = 0.0;
+        self.derivative = 0.0;
+        self.last_output = 0.0;
     }
 }
 
@@ -104,4 +168,40 @@ mod tests {
         let o2 = pid2.update(0.1, 1.0);
         assert!((o1 - o2).abs() < 0.001);
     }
+
+    #[test]
+    fn test_pid_anti_windup() {
+        // Lang verzadigd boven streefpeil: de integrator mag niet oplopen
+        let mut pid = PidController::new(5.0, 0.05, 0.0);
+        for _ in 0..1000 {
+            assert!((pid.update(0.5, 1.0) - 1.0).abs() < 0.001);
+        }
+        // Zodra het peil onder streefpeil zakt, stopt de pomp direct
+        assert!(pid.update(-0.01, 1.0) < 0.001);
+    }
+
+    #[test]
+    fn test_pid_d_filter() {
+        // Een meetpiek geeft met filter een veel kleinere D-reactie
+        let mut zonder = PidController::new(0.0, 0.0, 20.0);
+        let mut met = PidController::new(0.0, 0.0, 20.0).met_d_filter(10.0);
+        let o1 = zonder.update(0.01, 1.0);
+        let o2 = met.update(0.01, 1.0);
+        assert!((o1 - 0.2).abs() < 1e-9);
+        assert!((o2 - 0.2 / 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pid_max_verandering() {
+        let mut pid = PidController::new(5.0, 0.05, 20.0).met_max_verandering(0.1);
+        let mut vorige = 0.0;
+        for _ in 0..15 {
+            let output = pid.update(10.0, 1.0);
+            assert!(output - vorige <= 0.1 + 1e-12);
+            vorige = output;
+        }
+        assert!((vorige - 1.0).abs() < 0.001);
+        // Ook afschakelen gaat geleidelijk
+        assert!((pid.update(-10.0, 1.0) - 0.9).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,440 @@
+//! Automatische tuning van de PID-regelaar van een gemaal.
+//!
+//! Twee methoden stellen Kp/Ki/Kd voor een peilgebied voor:
+//!
+//! - **Ziegler–Nichols** (reactiekromme): de stijgsnelheid van het peil per
+//!   eenheid pompfractie wordt gemeten met één stap van de waterbalans; met
+//!   de tijdstap als dode tijd volgen de versterkingen uit de klassieke
+//!   regels voor een integrerend proces.
+//! - **Optimalisatie**: vanuit de beste van de standaard- en de
+//!   Ziegler–Nichols-instellingen zoekt Nelder–Mead (in log-ruimte) de
+//!   instellingen met de laagste [`Doelfunctie`] over de simulatie van de
+//!   bui uit de [`SimulatieParams`], tot tien keer de Ziegler–Nichols-waarden.
+
+use peilbeheer_core::waterbalans::{PidInstellingen, SimulatieParams};
+use serde::{Deserialize, Serialize};
+
+use crate::waterbalans::{calculate_time_series, calculate_water_balance};
+
+/// Grootste aantal simulaties van een optimalisatie.
+const MAX_EVALUATIES: usize = 400;
+
+/// Langste simulatie in minuten (een week).
+const MAX_DUUR_MINUTEN: f64 = 7.0 * 24.0 * 60.0;
+
+/// Grootste aantal tijdstappen van één simulatie.
+const MAX_TIJDSTAPPEN: f64 = 10_000.0;
+
+/// Kleinste versterking in de zoekruimte; log-ruimte kan nul niet bereiken.
+const MIN_VERSTERKING: f64 = 1e-6;
+
+/// Methode om de PID-instellingen te bepalen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TuningMethode {
+    ZieglerNichols,
+    #[default]
+    Optimalisatie,
+}
+
+/// Foutcriterium over de afwijking van het streefpeil.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Criterium {
+    /// Integraal van de absolute fout (m·min)
+    #[default]
+    Iae,
+    /// Integraal van de gekwadrateerde fout (m²·min)
+    Ise,
+    /// Integraal van de tijd maal de absolute fout (m·min²)
+    Itae,
+}
+
+/// Doelfunctie die de optimalisatie minimaliseert.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Doelfunctie {
+    #[serde(default)]
+    pub criterium: Criterium,
+    /// Gewicht per eenheid totale verandering van de pompfractie; voorkomt
+    /// dat de regelaar het gemaal telkens aan en uit schakelt
+    #[serde(default = "default_gewicht_schakelen")]
+    pub gewicht_schakelen: f64,
+}
+
+fn default_gewicht_schakelen() -> f64 {
+    0.01
+}
+
+impl Default for Doelfunctie {
+    fn default() -> Self {
+        Self {
+            criterium: Criterium::default(),
+            gewicht_schakelen: default_gewicht_schakelen(),
+        }
+    }
+}
+
+/// Voorgestelde PID-instellingen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidTuning {
+    pub methode: TuningMethode,
+    pub instellingen: PidInstellingen,
+    /// Doelwaarde met de voorgestelde instellingen
+    pub doelwaarde: f64,
+    /// Doelwaarde met de instellingen uit de parameters, of de
+    /// standaardinstellingen
+    pub doelwaarde_standaard: f64,
+    /// Hoogste waterstand met de voorgestelde instellingen (m NAP)
+    pub max_waterstand: f64,
+    /// Aantal simulaties
+    pub evaluaties: usize,
+}
+
+/// Fouttype voor PID-tuning.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TuningFout {
+    /// Zonder gemaaldebiet valt er niets te regelen
+    GeenGemaal,
+    /// Ongeldige simulatieparameters
+    OngeldigeParameters { details: String },
+}
+
+impl std::fmt::Display for TuningFout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GeenGemaal => write!(f, "Geen gemaaldebiet om te regelen"),
+            Self::OngeldigeParameters { details } => {
+                write!(f, "Ongeldige parameters voor PID-tuning: {}", details)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TuningFout {}
+
+fn valideer(params: &SimulatieParams, doel: &Doelfunctie) -> Result<(), TuningFout> {
+    let fout = |details: &str| {
+        Err(TuningFout::OngeldigeParameters {
+            details: details.to_string(),
+        })
+    };
+    if params.gemaal_debiet.is_nan() || params.gemaal_debiet <= 0.0 {
+        return Err(TuningFout::GeenGemaal);
+    }
+    if params.oppervlakte.is_nan() || params.oppervlakte <= 0.0 {
+        return fout("oppervlakte moet groter zijn dan 0");
+    }
+    if params.tijd_stap.is_nan() || params.tijd_stap <= 0.0 {
+        return fout("tijd_stap moet groter zijn dan 0");
+    }
+    let duur = params.regen_duur + params.na_regen_duur;
+    if duur < params.tijd_stap {
+        return fout("simulatieduur is korter dan één tijdstap");
+    }
+    // Elke evaluatie simuleert de hele bui
+    if duur > MAX_DUUR_MINUTEN {
+        return fout("simulatieduur mag hoogstens een week zijn");
+    }
+    if duur / params.tijd_stap > MAX_TIJDSTAPPEN {
+        return fout("simulatie heeft te veel tijdstappen");
+    }
+    if doel.gewicht_schakelen.is_nan() || doel.gewicht_schakelen < 0.0 {
+        return fout("gewicht_schakelen mag niet negatief zijn");
+    }
+    Ok(())
+}
+
+/// Ziegler–Nichols-instellingen volgens de reactiekromme.
+///
+/// Het peil is een integrerend proces: de pompfractie bepaalt de
+/// stijgsnelheid `R` (m/min per eenheid fractie). Met de tijdstap als dode
+/// tijd `L` geeft de klassieke regel Kp = 1.2 / (R·L), Ti = 2L en
+/// Td = L/2.
+pub fn ziegler_nichols(params: &SimulatieParams) -> Result<PidInstellingen, TuningFout> {
+    valideer(params, &Doelfunctie::default())?;
+    let stap = |debiet: f64| {
+        calculate_water_balance(
+            0.0,
+            params.oppervlakte,
+            params.streefpeil,
+            debiet,
+            params.verdamping,
+            params.infiltratie,
+            None,
+        )
+        .waterstand_verandering
+    };
+    // Verandering per minuut bij pomp uit min die bij volle capaciteit
+    let stijgsnelheid = stap(0.0) - stap(params.gemaal_debiet);
+    let dode_tijd = params.tijd_stap;
+
+    let kp = 1.2 / (stijgsnelheid * dode_tijd);
+    Ok(PidInstellingen {
+        kp,
+        ki: kp / (2.0 * dode_tijd),
+        kd: kp * dode_tijd / 2.0,
+        ..params.pid.unwrap_or_default()
+    })
+}
+
+/// Waarde van de doelfunctie en de hoogste waterstand bij een simulatie
+/// met de gegeven instellingen.
+fn evalueer(
+    params: &SimulatieParams,
+    instellingen: PidInstellingen,
+    doel: &Doelfunctie,
+) -> (f64, f64) {
+    let params = SimulatieParams {
+        smart_control: true,
+        pid: Some(instellingen),
+        ..params.clone()
+    };
+    let tijdstappen = calculate_time_series(&params);
+
+    let mut fout = 0.0;
+    let mut schakelen = 0.0;
+    let mut vorige_fractie = 0.0;
+    let mut max_waterstand = f64::NEG_INFINITY;
+    for stap in &tijdstappen {
+        let e = stap.waterstand - params.streefpeil;
+        fout += match doel.criterium {
+            Criterium::Iae => e.abs(),
+            Criterium::Ise => e * e,
+            Criterium::Itae => stap.tijd * e.abs(),
+        } * params.tijd_stap;
+        let fractie = stap.water_afvoer / params.gemaal_debiet;
+        schakelen += (fractie - vorige_fractie).abs();
+        vorige_fractie = fractie;
+        max_waterstand = max_waterstand.max(stap.waterstand);
+    }
+    (fout + doel.gewicht_schakelen * schakelen, max_waterstand)
+}
+
+/// Waarde van de doelfunctie bij een simulatie met de gegeven instellingen.
+pub fn doelwaarde(
+    params: &SimulatieParams,
+    instellingen: PidInstellingen,
+    doel: &Doelfunctie,
+) -> f64 {
+    evalueer(params, instellingen, doel).0
+}
+
+/// Stel PID-instellingen voor het peilgebied uit de parameters voor.
+pub fn tune_pid(
+    params: &SimulatieParams,
+    methode: TuningMethode,
+    doel: &Doelfunctie,
+) -> Result<PidTuning, TuningFout> {
+    valideer(params, doel)?;
+    // Filter en snelheidsbegrenzing uit de parameters blijven behouden
+    let standaard = params.pid.unwrap_or_default();
+    let zn = ziegler_nichols(params)?;
+    let doelwaarde_standaard = doelwaarde(params, standaard, doel);
+
+    let (instellingen, evaluaties) = match methode {
+        TuningMethode::ZieglerNichols => (zn, 1),
+        TuningMethode::Optimalisatie => {
+            let naar_log =
+                |p: PidInstellingen| [p.kp, p.ki, p.kd].map(|k| k.max(MIN_VERSTERKING).log10());
+            // Begrens de zoekruimte tot tien keer de Ziegler–Nichols-waarden;
+            // daarboven is de pompfractie toch verzadigd
+            let max = naar_log(zn).map(|k| k + 1.0);
+            let uit_log = |x: &[f64; 3]| {
+                let k = |i: usize| 10f64.powf(x[i].clamp(MIN_VERSTERKING.log10(), max[i]));
+                PidInstellingen {
+                    kp: k(0),
+                    ki: k(1),
+                    kd: k(2),
+                    ..standaard
+                }
+            };
+            let doelwaarde_zn = doelwaarde(params, zn, doel);
+            let start = if doelwaarde_zn < doelwaarde_standaard {
+                zn
+            } else {
+                standaard
+            };
+            let (x, evaluaties) = nelder_mead(
+                |x| doelwaarde(params, uit_log(x), doel),
+                naar_log(start),
+                1.0,
+                MAX_EVALUATIES,
+            );
+            (uit_log(&x), evaluaties + 2)
+        }
+    };
+
+    let (doelwaarde, max_waterstand) = evalueer(params, instellingen, doel);
+    Ok(PidTuning {
+        methode,
+        instellingen,
+        doelwaarde,
+        doelwaarde_standaard,
+        max_waterstand,
+        evaluaties,
+    })
+}
+
+/// Minimaliseer `f` met Nelder–Mead vanuit `start` met een simplex van
+/// zijde `stap`. Geeft het beste punt en het aantal evaluaties.
+fn nelder_mead(
+    f: impl Fn(&[f64; 3]) -> f64,
+    start: [f64; 3],
+    stap: f64,
+    max_evaluaties: usize,
+) -> ([f64; 3], usize) {
+    let mut simplex: Vec<([f64; 3], f64)> = (0..=3)
+        .map(|i| {
+            let mut x = start;
+            if i > 0 {
+                x[i - 1] += stap;
+            }
+            (x, f(&x))
+        })
+        .collect();
+    let mut evaluaties = simplex.len();
+    let punt = |a: &[f64; 3], b: &[f64; 3], t: f64| -> [f64; 3] {
+        std::array::from_fn(|j| a[j] + t * (b[j] - a[j]))
+    };
+
+    while evaluaties < max_evaluaties {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[3].1 - simplex[0].1).abs() <= 1e-9 * simplex[0].1.abs().max(1e-12) {
+            break;
+        }
+        let zwaartepunt: [f64; 3] =
+            std::array::from_fn(|j| simplex[..3].iter().map(|(x, _)| x[j]).sum::<f64>() / 3.0);
+        let slechtste = simplex[3];
+
+        let gespiegeld = punt(&zwaartepunt, &slechtste.0, -1.0);
+        let f_gespiegeld = f(&gespiegeld);
+        evaluaties += 1;
+        if f_gespiegeld < simplex[0].1 {
+            let uitgerekt = punt(&zwaartepunt, &slechtste.0, -2.0);
+            let f_uitgerekt = f(&uitgerekt);
+            evaluaties += 1;
+            simplex[3] = if f_uitgerekt < f_gespiegeld {
+                (uitgerekt, f_uitgerekt)
+            } else {
+                (gespiegeld, f_gespiegeld)
+            };
+        } else if f_gespiegeld < simplex[2].1 {
+            simplex[3] = (gespiegeld, f_gespiegeld);
+        } else {
+            let ingekrompen = punt(&zwaartepunt, &slechtste.0, 0.5);
+            let f_ingekrompen = f(&ingekrompen);
+            evaluaties += 1;
+            if f_ingekrompen < slechtste.1 {
+                simplex[3] = (ingekrompen, f_ingekrompen);
+            } else {
+                // Krimp de hele simplex naar het beste punt
+                let beste = simplex[0].0;
+                for (x, fx) in simplex.iter_mut().skip(1) {
+                    *x = punt(&beste, x, 0.5);
+                    *fx = f(x);
+                }
+                evaluaties += 3;
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (simplex[0].0, evaluaties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> SimulatieParams {
+        SimulatieParams {
+            start_waterstand: -0.60,
+            regen_intensiteit: 15.0,
+            regen_duur: 120.0,
+            oppervlakte: 100_000.0,
+            gemaal_debiet: 0.6,
+            verdamping: 0.0,
+            infiltratie: 0.0,
+            na_regen_duur: 120.0,
+            tijd_stap: 1.0,
+            smart_control: true,
+            streefpeil: -0.60,
+            marge: 5.0,
+            maaiveld_niveau: None,
+            pid: None,
+        }
+    }
+
+    #[test]
+    fn test_ziegler_nichols() {
+        let zn = ziegler_nichols(&params()).unwrap();
+        // R = 0.6 m³/s · 60 s / 100 000 m² = 3.6e-4 m/min, L = 1 min
+        assert!((zn.kp - 1.2 / 3.6e-4).abs() < 1e-6);
+        assert!((zn.ki - zn.kp / 2.0).abs() < 1e-6);
+        assert!((zn.kd - zn.kp / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_optimalisatie_verbetert_doelwaarde() {
+        let params = params();
+        let doel = Doelfunctie::default();
+        let tuning = tune_pid(&params, TuningMethode::Optimalisatie, &doel).unwrap();
+
+        assert!(tuning.evaluaties <= MAX_EVALUATIES + 2);
+        let zn = ziegler_nichols(&params).unwrap();
+        assert!(tuning.instellingen.ki <= 10.0 * zn.ki * (1.0 + 1e-9));
+        assert!(tuning.doelwaarde <= tuning.doelwaarde_standaard);
+        assert!(tuning.doelwaarde <= doelwaarde(&params, zn, &doel) + 1e-12);
+        assert!(tuning.max_waterstand > params.streefpeil);
+    }
+
+    #[test]
+    fn test_nelder_mead_kwadraat() {
+        let (x, _) = nelder_mead(
+            |x| (x[0] - 1.0).powi(2) + (x[1] + 2.0).powi(2) + x[2].powi(2),
+            [0.0; 3],
+            1.0,
+            1000,
+        );
+        assert!((x[0] - 1.0).abs() < 1e-3);
+        assert!((x[1] + 2.0).abs() < 1e-3);
+        assert!(x[2].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_zonder_gemaal() {
+        let params = SimulatieParams {
+            gemaal_debiet: 0.0,
+            ..params()
+        };
+        assert_eq!(
+            tune_pid(
+                &params,
+                TuningMethode::ZieglerNichols,
+                &Doelfunctie::default()
+            )
+            .unwrap_err(),
+            TuningFout::GeenGemaal
+        );
+    }
+
+    #[test]
+    fn test_te_lange_simulatie() {
+        let doel = Doelfunctie::default();
+        let te_lang = SimulatieParams {
+            na_regen_duur: MAX_DUUR_MINUTEN + 1.0,
+            ..params()
+        };
+        assert!(matches!(
+            tune_pid(&te_lang, TuningMethode::Optimalisatie, &doel),
+            Err(TuningFout::OngeldigeParameters { .. })
+        ));
+        let te_fijn = SimulatieParams {
+            tijd_stap: 0.001,
+            ..params()
+        };
+        assert!(matches!(
+            tune_pid(&te_fijn, TuningMethode::Optimalisatie, &doel),
+            Err(TuningFout::OngeldigeParameters { .. })
+        ));
+    }
+}
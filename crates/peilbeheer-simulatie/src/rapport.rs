@@ -0,0 +1,1105 @@
+//! Rapporten van simulatie- en optimalisatieruns.
+//!
+//! Een [`Rapport`] is een reeks secties (koppen, tekst, tabellen en
+//! grafieken), opgebouwd uit een netwerksimulatie ([`Rapport::simulatie`])
+//! of een optimalisatie ([`Rapport::optimalisatie`]): een samenvatting, de
+//! kosten, het waterstandsverloop en een overzicht van de topologie of het
+//! gemaal.
+//!
+//! [`Rapport::als_pdf`] zet het rapport op A4-pagina's. Grafieken worden met
+//! [`LijnGrafiek`] getekend en als afbeelding opgenomen; tekst staat in de
+//! standaardletters Helvetica van PDF, zodat er geen lettertype ingebed
+//! hoeft te worden.
+//!
+//! [`Rapport::als_html`] geeft één zelfstandig HTML-bestand zonder externe
+//! bronnen, te openen zonder draaiende server. De reeksen van de grafieken
+//! staan als JSON in het document en worden in de browser als SVG getekend:
+//! waarden bij de cursor, inzoomen op de tijdas en reeksen verbergen via de
+//! legenda. Daaronder staan de gebruikte parameters.
+
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use serde::Serialize;
+
+use peilbeheer_core::energie::{
+    OptimalisatieParams, OptimalisatieResultaat, OptimalisatieUurResultaat, SimulatieStapUitgebreid,
+};
+
+use crate::export::bereken_statistieken;
+use crate::netwerk::{NetwerkSimulatieResultaat, VerbindingType};
+use crate::scenario::Scenario;
+use crate::visualisatie::{GrafiekOpties, LijnGrafiek, Reeks, Resolutie, VisualisatieFout};
+
+/// Rapport van één run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rapport {
+    pub titel: String,
+    pub secties: Vec<Sectie>,
+    /// Invoer van de run (scenario of optimalisatieparameters); alleen
+    /// opgenomen in het HTML-rapport
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// Onderdeel van een rapport.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "soort", rename_all = "snake_case")]
+pub enum Sectie {
+    Kop {
+        tekst: String,
+    },
+    Tekst {
+        tekst: String,
+    },
+    Tabel {
+        kolommen: Vec<String>,
+        rijen: Vec<Vec<String>>,
+    },
+    Grafiek {
+        titel: String,
+        x_label: String,
+        y_label: String,
+        reeksen: Vec<Reeks>,
+    },
+}
+
+/// Fout bij het opstellen of schrijven van een rapport.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RapportFout {
+    /// De run heeft geen resultaten
+    GeenData,
+    /// Een grafiek kon niet getekend worden
+    Grafiek(VisualisatieFout),
+    /// Kon niet schrijven naar bestand
+    SchrijvenMislukt { pad: String, reden: String },
+}
+
+impl std::fmt::Display for RapportFout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GeenData => write!(f, "Geen resultaten om te rapporteren"),
+            Self::Grafiek(fout) => write!(f, "Grafiek tekenen mislukt: {}", fout),
+            Self::SchrijvenMislukt { pad, reden } => {
+                write!(f, "Kon niet schrijven naar {}: {}", pad, reden)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RapportFout {}
+
+impl From<VisualisatieFout> for RapportFout {
+    fn from(fout: VisualisatieFout) -> Self {
+        Self::Grafiek(fout)
+    }
+}
+
+impl Rapport {
+    /// Rapport van een netwerksimulatie van een scenario.
+    pub fn simulatie(
+        scenario: &Scenario,
+        resultaat: &NetwerkSimulatieResultaat,
+    ) -> Result<Self, RapportFout> {
+        let statistieken = bereken_statistieken(resultaat).map_err(|_| RapportFout::GeenData)?;
+        let topologie = &scenario.topologie;
+
+        let mut peilgebied_ids: Vec<_> = statistieken.peilgebieden.keys().collect();
+        peilgebied_ids.sort();
+
+        let mut secties = vec![kop("Samenvatting")];
+        if let Some(beschrijving) = &scenario.beschrijving {
+            secties.push(Sectie::Tekst {
+                tekst: beschrijving.clone(),
+            });
+        }
+        let mut samenvatting = vec![
+            vec!["Scenario".to_string(), scenario.id.clone()],
+            vec![
+                "Peilgebieden".to_string(),
+                topologie.peilgebieden.len().to_string(),
+            ],
+            vec![
+                "Verbindingen".to_string(),
+                topologie.verbindingen.len().to_string(),
+            ],
+            vec![
+                "Tijdstappen".to_string(),
+                statistieken.aantal_tijdstappen.to_string(),
+            ],
+            vec![
+                "Gesimuleerde tijd (uren)".to_string(),
+                getal(statistieken.totale_tijd / 60.0, 1),
+            ],
+        ];
+        if let Some(kosten) = resultaat.totale_kosten {
+            samenvatting.push(vec!["Totale kosten (€)".to_string(), getal(kosten, 2)]);
+        }
+        secties.push(tabel(&["Grootheid", "Waarde"], samenvatting));
+
+        secties.push(kop("Waterstanden"));
+        secties.push(tabel(
+            &[
+                "Peilgebied",
+                "Min (m NAP)",
+                "Max (m NAP)",
+                "Gemiddeld (m NAP)",
+                "Pompuren",
+                "Regen (mm/uur)",
+            ],
+            peilgebied_ids
+                .iter()
+                .map(|id| {
+                    let s = &statistieken.peilgebieden[*id];
+                    vec![
+                        id.to_string(),
+                        getal(s.min_waterstand, 3),
+                        getal(s.max_waterstand, 3),
+                        getal(s.gem_waterstand, 3),
+                        getal(s.pomp_uren, 1),
+                        getal(s.gem_regen, 2),
+                    ]
+                })
+                .collect(),
+        ));
+        secties.push(Sectie::Grafiek {
+            titel: "Waterstand per peilgebied".to_string(),
+            x_label: "Tijd (uren)".to_string(),
+            y_label: "Waterstand (m NAP)".to_string(),
+            reeksen: peilgebied_ids
+                .iter()
+                .map(|id| Reeks {
+                    naam: id.to_string(),
+                    punten: resultaat
+                        .tijdstappen
+                        .iter()
+                        .filter_map(|stap| {
+                            stap.statussen
+                                .get(*id)
+                                .map(|status| (stap.tijd / 60.0, status.waterstand))
+                        })
+                        .collect(),
+                })
+                .collect(),
+        });
+
+        secties.push(kop("Topologie"));
+        let mut peilgebieden: Vec<_> = topologie.peilgebieden.values().collect();
+        peilgebieden.sort_by(|a, b| a.id.cmp(&b.id));
+        secties.push(tabel(
+            &[
+                "Peilgebied",
+                "Naam",
+                "Oppervlakte (ha)",
+                "Streefpeil (m NAP)",
+                "Marge (m)",
+                "Uitstroom (m³/s)",
+            ],
+            peilgebieden
+                .iter()
+                .map(|p| {
+                    vec![
+                        p.id.clone(),
+                        p.naam.clone().unwrap_or_default(),
+                        getal(p.oppervlakte / 10_000.0, 1),
+                        getal(p.streefpeil, 2),
+                        getal(p.marge, 2),
+                        getal(p.max_uitstroom_debiet, 3),
+                    ]
+                })
+                .collect(),
+        ));
+        let mut verbindingen: Vec<_> = topologie.verbindingen.values().collect();
+        verbindingen.sort_by(|a, b| a.id.cmp(&b.id));
+        if !verbindingen.is_empty() {
+            secties.push(tabel(
+                &["Verbinding", "Type", "Van", "Naar", "Capaciteit (m³/s)"],
+                verbindingen
+                    .iter()
+                    .map(|v| {
+                        vec![
+                            v.id.clone(),
+                            verbindingsoort(v.verbinding_type).to_string(),
+                            v.van_id.clone(),
+                            v.naar_id.clone(),
+                            getal(v.capaciteit, 3),
+                        ]
+                    })
+                    .collect(),
+            ));
+        }
+
+        Ok(Self {
+            titel: format!(
+                "Simulatierapport {}",
+                scenario.naam.as_deref().unwrap_or(&scenario.id)
+            ),
+            secties,
+            parameters: serde_json::to_value(scenario).ok(),
+        })
+    }
+
+    /// Rapport van een optimalisatie van een gemaal, met de optimale inzet
+    /// naast de naïeve regeling.
+    pub fn optimalisatie(
+        titel: &str,
+        params: &OptimalisatieParams,
+        resultaat: &OptimalisatieResultaat,
+    ) -> Result<Self, RapportFout> {
+        if resultaat.uren.is_empty() {
+            return Err(RapportFout::GeenData);
+        }
+
+        let r = resultaat;
+        let mut secties = vec![
+            kop("Samenvatting"),
+            Sectie::Tekst {
+                tekst: format!(
+                    "De optimale inzet bespaart € {} ({} %) ten opzichte van de naïeve regeling.",
+                    getal(r.besparing_eur, 2),
+                    getal(r.besparing_pct, 1)
+                ),
+            },
+            tabel(
+                &["Grootheid", "Optimaal", "Naïef"],
+                vec![
+                    rij2(
+                        "Totale kosten (€)",
+                        r.totale_kosten_optimaal,
+                        r.totale_kosten_naief,
+                        2,
+                    ),
+                    rij2(
+                        "Energie (kWh)",
+                        r.totale_energie_optimaal_kwh,
+                        r.totale_energie_naief_kwh,
+                        1,
+                    ),
+                    rij2(
+                        "CO2 (kg)",
+                        r.totale_co2_optimaal_kg,
+                        r.totale_co2_naief_kg,
+                        1,
+                    ),
+                    rij2(
+                        "Max. afwijking streefpeil (cm)",
+                        r.max_afwijking_optimaal_cm,
+                        r.max_afwijking_naief_cm,
+                        1,
+                    ),
+                    rij2(
+                        "Piekvermogen (kW)",
+                        r.piekvermogen_optimaal_kw,
+                        r.piekvermogen_naief_kw,
+                        1,
+                    ),
+                    vec![
+                        "Inschakelingen".to_string(),
+                        r.inschakelingen_optimaal.to_string(),
+                        r.inschakelingen_naief.to_string(),
+                    ],
+                ],
+            ),
+        ];
+
+        secties.push(kop("Kosten per uur"));
+        secties.push(tabel(
+            &[
+                "Uur",
+                "Prijs (€/kWh)",
+                "Regen (mm)",
+                "Inzet optimaal",
+                "Inzet naïef",
+                "Kosten optimaal (€)",
+                "Kosten naïef (€)",
+            ],
+            r.uren
+                .iter()
+                .map(|uur| {
+                    vec![
+                        uur.uur.to_string(),
+                        getal(uur.prijs_eur_kwh, 3),
+                        getal(uur.regen_mm_uur, 1),
+                        getal(uur.pomp_fractie_optimaal, 2),
+                        getal(uur.pomp_fractie_naief, 2),
+                        getal(uur.kosten_optimaal, 2),
+                        getal(uur.kosten_naief, 2),
+                    ]
+                })
+                .collect(),
+        ));
+
+        secties.push(kop("Waterstand"));
+        secties.push(Sectie::Grafiek {
+            titel: "Waterstand optimaal en naïef".to_string(),
+            x_label: "Tijd (uren)".to_string(),
+            y_label: "Waterstand (m NAP)".to_string(),
+            reeksen: vec![
+                Reeks {
+                    naam: "Optimaal".to_string(),
+                    punten: waterstanden(&r.tijdstappen_optimaal, &r.uren, |u| {
+                        u.waterstand_eind_optimaal
+                    }),
+                },
+                Reeks {
+                    naam: "Naïef".to_string(),
+                    punten: waterstanden(&r.tijdstappen_naief, &r.uren, |u| {
+                        u.waterstand_eind_naief
+                    }),
+                },
+            ],
+        });
+
+        secties.push(kop("Gemaal"));
+        secties.push(tabel(
+            &["Grootheid", "Waarde"],
+            vec![
+                vec![
+                    "Streefpeil (m NAP)".to_string(),
+                    getal(params.streefpeil, 2),
+                ],
+                vec![
+                    "Oppervlakte (ha)".to_string(),
+                    getal(params.oppervlakte / 10_000.0, 1),
+                ],
+                vec![
+                    "Max. debiet (m³/s)".to_string(),
+                    getal(params.max_debiet, 3),
+                ],
+                vec![
+                    "Opvoerhoogte (m)".to_string(),
+                    getal(params.opvoerhoogte, 2),
+                ],
+                vec!["Rendement".to_string(), getal(params.efficiency, 2)],
+            ],
+        ));
+        if !params.pompen.is_empty() {
+            secties.push(tabel(
+                &[
+                    "Pomp",
+                    "Volgorde",
+                    "Debiet (m³/s)",
+                    "Rendement",
+                    "Inschakelpeil (m)",
+                    "Uitschakelpeil (m)",
+                ],
+                params
+                    .pompen
+                    .iter()
+                    .map(|pomp| {
+                        vec![
+                            pomp.id.clone(),
+                            pomp.volgorde.to_string(),
+                            getal(pomp.debiet, 3),
+                            getal(pomp.rendement, 2),
+                            getal(pomp.inschakelpeil, 2),
+                            getal(pomp.uitschakelpeil, 2),
+                        ]
+                    })
+                    .collect(),
+            ));
+        }
+
+        Ok(Self {
+            titel: titel.to_string(),
+            secties,
+            parameters: serde_json::to_value(params).ok(),
+        })
+    }
+
+    /// Zet het rapport op A4-pagina's.
+    pub fn als_pdf(&self) -> Result<Vec<u8>, RapportFout> {
+        let mut opmaak = PdfOpmaak::nieuw(&self.titel);
+        opmaak.tekst(&self.titel, Letter::Vet, 20.0);
+        opmaak.ruimte(8.0);
+
+        for sectie in &self.secties {
+            match sectie {
+                Sectie::Kop { tekst } => {
+                    opmaak.ruimte(12.0);
+                    opmaak.tekst(tekst, Letter::Vet, 14.0);
+                    opmaak.ruimte(4.0);
+                }
+                Sectie::Tekst { tekst } => {
+                    opmaak.tekst(tekst, Letter::Normaal, 10.0);
+                    opmaak.ruimte(6.0);
+                }
+                Sectie::Tabel { kolommen, rijen } => {
+                    opmaak.tabel(kolommen, rijen);
+                    opmaak.ruimte(10.0);
+                }
+                Sectie::Grafiek {
+                    titel,
+                    x_label,
+                    y_label,
+                    reeksen,
+                } => {
+                    let grafiek = LijnGrafiek::met_opties(GrafiekOpties {
+                        resolutie: GRAFIEK,
+                        titel: Some(titel.clone()),
+                        x_label: Some(x_label.clone()),
+                        y_label: Some(y_label.clone()),
+                        ..Default::default()
+                    });
+                    opmaak.afbeelding(&grafiek.naar_rgb(reeksen)?, GRAFIEK);
+                    opmaak.ruimte(10.0);
+                }
+            }
+        }
+
+        Ok(opmaak.afronden())
+    }
+
+    /// Schrijf het rapport als PDF naar een bestand.
+    pub fn naar_pdf<P: AsRef<Path>>(&self, pad: P) -> Result<(), RapportFout> {
+        std::fs::write(pad.as_ref(), self.als_pdf()?).map_err(|e| RapportFout::SchrijvenMislukt {
+            pad: pad.as_ref().display().to_string(),
+            reden: e.to_string(),
+        })
+    }
+
+    /// Zet het rapport in een zelfstandig HTML-document.
+    pub fn als_html(&self) -> String {
+        let titel = html_tekst(&self.titel);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"nl\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{titel}</title>\n<style>\n{HTML_STIJL}</style>\n</head>\n<body>\n\
+             <h1>{titel}</h1>\n"
+        );
+
+        for (nummer, sectie) in self.secties.iter().enumerate() {
+            match sectie {
+                Sectie::Kop { tekst } => {
+                    html.push_str(&format!("<h2>{}</h2>\n", html_tekst(tekst)));
+                }
+                Sectie::Tekst { tekst } => {
+                    html.push_str(&format!("<p>{}</p>\n", html_tekst(tekst)));
+                }
+                Sectie::Tabel { kolommen, rijen } => {
+                    html.push_str("<table>\n<tr>");
+                    for kolom in kolommen {
+                        html.push_str(&format!("<th>{}</th>", html_tekst(kolom)));
+                    }
+                    html.push_str("</tr>\n");
+                    for rij in rijen {
+                        html.push_str("<tr>");
+                        for cel in rij {
+                            let klasse = if cel.parse::<f64>().is_ok() {
+                                " class=\"getal\""
+                            } else {
+                                ""
+                            };
+                            html.push_str(&format!("<td{}>{}</td>", klasse, html_tekst(cel)));
+                        }
+                        html.push_str("</tr>\n");
+                    }
+                    html.push_str("</table>\n");
+                }
+                Sectie::Grafiek {
+                    titel,
+                    x_label,
+                    y_label,
+                    reeksen,
+                } => {
+                    let reeksen: Vec<Reeks> = reeksen
+                        .iter()
+                        .map(|reeks| Reeks {
+                            naam: reeks.naam.clone(),
+                            punten: verdun(&reeks.punten, MAX_HTML_PUNTEN),
+                        })
+                        .collect();
+                    let data = serde_json::json!({
+                        "id": nummer,
+                        "x_label": x_label,
+                        "y_label": y_label,
+                        "reeksen": reeksen,
+                    });
+                    html.push_str(&format!(
+                        "<figure class=\"grafiek\">\n<figcaption>{}</figcaption>\n\
+                         <script type=\"application/json\">{}</script>\n</figure>\n\
+                         <p class=\"hint\">Beweeg over de grafiek voor de waarden, sleep om in \
+                         te zoomen, dubbelklik om uit te zoomen en klik op de legenda om een \
+                         reeks te verbergen.</p>\n",
+                        html_tekst(titel),
+                        script_json(&data)
+                    ));
+                }
+            }
+        }
+
+        if let Some(parameters) = &self.parameters {
+            let json = serde_json::to_string_pretty(parameters).unwrap_or_default();
+            html.push_str(&format!(
+                "<h2>Gebruikte parameters</h2>\n<details>\n<summary>Invoer van de run \
+                 (JSON)</summary>\n<pre>{}</pre>\n</details>\n",
+                html_tekst(&json)
+            ));
+        }
+
+        html.push_str(&format!(
+            "<script>\n{HTML_SCRIPT}</script>\n</body>\n</html>\n"
+        ));
+        html
+    }
+
+    /// Schrijf het rapport als HTML naar een bestand.
+    pub fn naar_html<P: AsRef<Path>>(&self, pad: P) -> Result<(), RapportFout> {
+        std::fs::write(pad.as_ref(), self.als_html()).map_err(|e| RapportFout::SchrijvenMislukt {
+            pad: pad.as_ref().display().to_string(),
+            reden: e.to_string(),
+        })
+    }
+}
+
+fn kop(tekst: &str) -> Sectie {
+    Sectie::Kop {
+        tekst: tekst.to_string(),
+    }
+}
+
+fn tabel(kolommen: &[&str], rijen: Vec<Vec<String>>) -> Sectie {
+    Sectie::Tabel {
+        kolommen: kolommen.iter().map(|k| k.to_string()).collect(),
+        rijen,
+    }
+}
+
+fn rij2(naam: &str, optimaal: f64, naief: f64, decimalen: usize) -> Vec<String> {
+    vec![
+        naam.to_string(),
+        getal(optimaal, decimalen),
+        getal(naief, decimalen),
+    ]
+}
+
+/// Waterstand per minuut als de tijdstappen er zijn, anders aan het eind
+/// van elk uur.
+fn waterstanden(
+    stappen: &[SimulatieStapUitgebreid],
+    uren: &[OptimalisatieUurResultaat],
+    eind: impl Fn(&OptimalisatieUurResultaat) -> f64,
+) -> Vec<(f64, f64)> {
+    if stappen.is_empty() {
+        uren.iter()
+            .map(|uur| (uur.uur as f64 + 1.0, eind(uur)))
+            .collect()
+    } else {
+        stappen
+            .iter()
+            .map(|stap| (stap.tijd_minuten / 60.0, stap.waterstand))
+            .collect()
+    }
+}
+
+fn getal(waarde: f64, decimalen: usize) -> String {
+    format!("{:.*}", decimalen, waarde)
+}
+
+fn verbindingsoort(soort: VerbindingType) -> &'static str {
+    match soort {
+        VerbindingType::Gemaal => "gemaal",
+        VerbindingType::Overstort => "overstort",
+        VerbindingType::Keerklep => "keerklep",
+        VerbindingType::OpenVerbinding => "open verbinding",
+        VerbindingType::Duiker => "duiker",
+        VerbindingType::Stuw => "stuw",
+    }
+}
+
+/// Opmaak en script van het HTML-rapport
+const HTML_STIJL: &str = include_str!("../assets/rapport.css");
+const HTML_SCRIPT: &str = include_str!("../assets/rapport.js");
+
+fn html_tekst(tekst: &str) -> String {
+    tekst
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Grootste aantal punten per reeks in een HTML-rapport
+const MAX_HTML_PUNTEN: usize = 2000;
+
+/// Hooguit `max` punten van een reeks: per vak opeenvolgende punten het
+/// laagste en het hoogste, zodat pieken zichtbaar blijven. Punten zonder
+/// eindige waarde vallen weg.
+fn verdun(punten: &[(f64, f64)], max: usize) -> Vec<(f64, f64)> {
+    let punten: Vec<(f64, f64)> = punten.iter().copied().filter(|p| p.1.is_finite()).collect();
+    if punten.len() <= max {
+        return punten;
+    }
+    let vak = punten.len().div_ceil(max / 2);
+    let mut verdund = Vec::with_capacity(max);
+    for vak in punten.chunks(vak) {
+        let laag = vak.iter().copied().min_by(|a, b| a.1.total_cmp(&b.1));
+        let hoog = vak.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1));
+        if let (Some(laag), Some(hoog)) = (laag, hoog) {
+            let (eerst, dan) = if laag.0 <= hoog.0 { (laag, hoog) } else { (hoog, laag) };
+            verdund.push(eerst);
+            if dan != eerst {
+                verdund.push(dan);
+            }
+        }
+    }
+    verdund
+}
+
+/// JSON binnen een `<script>`-element: `<` alleen als escape, zodat
+/// `</script>` in een naam het element niet afsluit.
+fn script_json(data: &serde_json::Value) -> String {
+    data.to_string().replace('<', "\\u003c")
+}
+
+/// A4 in punten
+const PAGINA: Rect = Rect {
+    x1: 0.0,
+    y1: 0.0,
+    x2: 595.0,
+    y2: 842.0,
+};
+const MARGE: f32 = 50.0;
+const BREEDTE: f32 = PAGINA.x2 - 2.0 * MARGE;
+
+/// Pixels van een grafiek; op de pagina over de volle breedte
+const GRAFIEK: Resolutie = Resolutie {
+    breedte: 1200,
+    hoogte: 600,
+};
+
+/// Gemiddelde letterbreedte van Helvetica als deel van de lettergrootte,
+/// voor het afbreken van tekst
+const LETTERBREEDTE: f32 = 0.55;
+
+#[derive(Clone, Copy)]
+enum Letter {
+    Normaal,
+    Vet,
+}
+
+impl Letter {
+    fn naam(self) -> Name<'static> {
+        match self {
+            Self::Normaal => Name(b"F1"),
+            Self::Vet => Name(b"F2"),
+        }
+    }
+}
+
+/// Schrijft secties van boven naar beneden op pagina's en begint een
+/// nieuwe pagina als de volgende regel, rij of afbeelding niet meer past.
+struct PdfOpmaak {
+    pdf: Pdf,
+    volgende: Ref,
+    pagina_boom: Ref,
+    letters: [Ref; 2],
+    paginas: Vec<Ref>,
+    inhoud: Content,
+    afbeeldingen: Vec<(String, Ref)>,
+    /// Bovenkant van de ruimte die nog vrij is op de pagina
+    y: f32,
+}
+
+impl PdfOpmaak {
+    fn nieuw(titel: &str) -> Self {
+        let mut pdf = Pdf::new();
+        let mut volgende = Ref::new(1);
+        let catalogus = volgende.bump();
+        let pagina_boom = volgende.bump();
+        let info = volgende.bump();
+        let letters = [volgende.bump(), volgende.bump()];
+
+        pdf.catalog(catalogus).pages(pagina_boom);
+        pdf.document_info(info)
+            .title(TextStr(titel))
+            .creator(TextStr("peilbeheer"));
+        for (id, naam) in letters
+            .into_iter()
+            .zip([&b"Helvetica"[..], b"Helvetica-Bold"])
+        {
+            pdf.type1_font(id)
+                .base_font(Name(naam))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        Self {
+            pdf,
+            volgende,
+            pagina_boom,
+            letters,
+            paginas: Vec::new(),
+            inhoud: Content::new(),
+            afbeeldingen: Vec::new(),
+            y: PAGINA.y2 - MARGE,
+        }
+    }
+
+    fn ruimte(&mut self, hoogte: f32) {
+        self.y -= hoogte;
+    }
+
+    /// Zorg dat er nog `hoogte` vrij is; begin anders een nieuwe pagina.
+    fn past(&mut self, hoogte: f32) {
+        if self.y - hoogte < MARGE {
+            self.sluit_pagina();
+        }
+    }
+
+    /// Tekst over de volle breedte, afgebroken op woorden.
+    fn tekst(&mut self, tekst: &str, letter: Letter, grootte: f32) {
+        let per_regel = (BREEDTE / (grootte * LETTERBREEDTE)) as usize;
+        for regel in afbreken(tekst, per_regel) {
+            self.past(grootte * 1.4);
+            self.y -= grootte * 1.4;
+            self.schrijf(&regel, letter, grootte, MARGE, self.y + grootte * 0.3);
+        }
+    }
+
+    fn tabel(&mut self, kolommen: &[String], rijen: &[Vec<String>]) {
+        const GROOTTE: f32 = 9.0;
+        const RIJ: f32 = 14.0;
+        let breedte = BREEDTE / kolommen.len().max(1) as f32;
+        let per_cel = ((breedte - 4.0) / (GROOTTE * LETTERBREEDTE)) as usize;
+
+        let kop = |opmaak: &mut Self| {
+            opmaak.rij(kolommen, Letter::Vet, breedte, per_cel);
+            opmaak
+                .inhoud
+                .set_line_width(0.5)
+                .move_to(MARGE, opmaak.y)
+                .line_to(MARGE + BREEDTE, opmaak.y)
+                .stroke();
+        };
+
+        self.past(RIJ * 2.0);
+        kop(self);
+        for rij in rijen {
+            if self.y - RIJ < MARGE {
+                self.sluit_pagina();
+                kop(self);
+            }
+            self.rij(rij, Letter::Normaal, breedte, per_cel);
+        }
+    }
+
+    fn rij(&mut self, cellen: &[String], letter: Letter, breedte: f32, per_cel: usize) {
+        self.y -= 14.0;
+        for (i, cel) in cellen.iter().enumerate() {
+            let tekst = if cel.chars().count() > per_cel {
+                let mut ingekort: String = cel.chars().take(per_cel.saturating_sub(1)).collect();
+                ingekort.push('…');
+                ingekort
+            } else {
+                cel.clone()
+            };
+            let x = MARGE + i as f32 * breedte;
+            self.schrijf(&tekst, letter, 9.0, x, self.y + 4.0);
+        }
+    }
+
+    /// Afbeelding met RGB-pixels over de volle breedte.
+    fn afbeelding(&mut self, rgb: &[u8], resolutie: Resolutie) {
+        let hoogte = BREEDTE * resolutie.hoogte as f32 / resolutie.breedte as f32;
+        self.past(hoogte);
+        self.y -= hoogte;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        let gecomprimeerd = encoder
+            .write_all(rgb)
+            .and_then(|_| encoder.finish())
+            .expect("comprimeren in geheugen faalt niet");
+
+        let id = self.volgende.bump();
+        let mut afbeelding = self.pdf.image_xobject(id, &gecomprimeerd);
+        afbeelding.filter(Filter::FlateDecode);
+        afbeelding.width(resolutie.breedte as i32);
+        afbeelding.height(resolutie.hoogte as i32);
+        afbeelding.color_space().device_rgb();
+        afbeelding.bits_per_component(8);
+        afbeelding.finish();
+
+        let naam = format!("Im{}", self.afbeeldingen.len() + 1);
+        self.inhoud
+            .save_state()
+            .transform([BREEDTE, 0.0, 0.0, hoogte, MARGE, self.y])
+            .x_object(Name(naam.as_bytes()))
+            .restore_state();
+        self.afbeeldingen.push((naam, id));
+    }
+
+    fn schrijf(&mut self, tekst: &str, letter: Letter, grootte: f32, x: f32, y: f32) {
+        self.inhoud
+            .begin_text()
+            .set_font(letter.naam(), grootte)
+            .next_line(x, y)
+            .show(Str(&win_ansi(tekst)))
+            .end_text();
+    }
+
+    /// Schrijf de inhoud van de huidige pagina weg, met paginanummer.
+    fn sluit_pagina(&mut self) {
+        let nummer = format!("{}", self.paginas.len() + 1);
+        let x = PAGINA.x2 - MARGE - nummer.len() as f32 * 8.0 * LETTERBREEDTE;
+        self.schrijf(&nummer, Letter::Normaal, 8.0, x, MARGE / 2.0);
+
+        let pagina = self.volgende.bump();
+        let inhoud_id = self.volgende.bump();
+        let inhoud = std::mem::replace(&mut self.inhoud, Content::new()).finish();
+        self.pdf.stream(inhoud_id, &inhoud);
+
+        let mut writer = self.pdf.page(pagina);
+        writer
+            .parent(self.pagina_boom)
+            .media_box(PAGINA)
+            .contents(inhoud_id);
+        let mut bronnen = writer.resources();
+        bronnen
+            .fonts()
+            .pair(Letter::Normaal.naam(), self.letters[0])
+            .pair(Letter::Vet.naam(), self.letters[1]);
+        let mut xobjecten = bronnen.x_objects();
+        for (naam, id) in self.afbeeldingen.drain(..) {
+            xobjecten.pair(Name(naam.as_bytes()), id);
+        }
+        xobjecten.finish();
+        bronnen.finish();
+        writer.finish();
+
+        self.paginas.push(pagina);
+        self.y = PAGINA.y2 - MARGE;
+    }
+
+    fn afronden(mut self) -> Vec<u8> {
+        self.sluit_pagina();
+        self.pdf
+            .pages(self.pagina_boom)
+            .kids(self.paginas.iter().copied())
+            .count(self.paginas.len() as i32);
+        self.pdf.finish()
+    }
+}
+
+/// Breek tekst op woorden af in regels van hooguit `per_regel` tekens;
+/// langere woorden komen op een eigen regel.
+fn afbreken(tekst: &str, per_regel: usize) -> Vec<String> {
+    let mut regels = Vec::new();
+    for alinea in tekst.lines() {
+        let mut regel = String::new();
+        for woord in alinea.split_whitespace() {
+            if !regel.is_empty() && regel.chars().count() + 1 + woord.chars().count() > per_regel {
+                regels.push(std::mem::take(&mut regel));
+            }
+            if !regel.is_empty() {
+                regel.push(' ');
+            }
+            regel.push_str(woord);
+        }
+        regels.push(regel);
+    }
+    regels
+}
+
+/// Tekst in WinAnsiEncoding, de codering van de standaardletters; tekens
+/// daarbuiten worden een vraagteken.
+fn win_ansi(tekst: &str) -> Vec<u8> {
+    tekst
+        .chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '…' => 0x85,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::netwerk::{
+        NetwerkTijdstap, NetwerkTopologie, PeilgebiedConfig, PeilgebiedStatus, Verbinding,
+    };
+
+    fn scenario() -> Scenario {
+        let mut topologie = NetwerkTopologie::nieuw();
+        for id in ["noord", "zuid"] {
+            topologie
+                .voeg_peilgebied_toe(PeilgebiedConfig {
+                    id: id.to_string(),
+                    naam: Some(format!("Polder {}", id)),
+                    oppervlakte: 100_000.0,
+                    streefpeil: -0.60,
+                    marge: 0.05,
+                    maaiveld_niveau: 0.0,
+                    max_uitstroom_debiet: 0.05,
+                    max_inlaat_debiet: 0.0,
+                    verdamping: 0.0,
+                    referentieverdamping: Vec::new(),
+                    gewasfactor: 1.0,
+                    infiltratie: 0.0,
+                    kwel: None,
+                    bergingscurve: None,
+                    boezem: None,
+                    chloride: None,
+                })
+                .unwrap();
+        }
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_overstort(
+                    "overstort".to_string(),
+                    "noord".to_string(),
+                    "zuid".to_string(),
+                    1.0,
+                    0.0,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        Scenario::nieuw("s1".to_string(), topologie)
+            .met_naam("Zomerbui".to_string())
+            .met_beschrijving("Een bui van 40 mm op noord.".to_string())
+    }
+
+    fn resultaat(stappen: usize) -> NetwerkSimulatieResultaat {
+        let status = |id: &str, waterstand: f64| PeilgebiedStatus {
+            id: id.to_string(),
+            waterstand,
+            inkomend_debiet: 0.0,
+            uitgaand_debiet: 0.0,
+            uitstroom_debiet: 0.05,
+            inlaat_debiet: 0.0,
+            regen_intensiteit: 2.0,
+            pomp_actief: true,
+        };
+        NetwerkSimulatieResultaat {
+            tijdstappen: (0..stappen)
+                .map(|i| NetwerkTijdstap {
+                    tijd: i as f64 * 15.0,
+                    duur: 15.0,
+                    statussen: HashMap::from([
+                        (
+                            "noord".to_string(),
+                            status("noord", -0.60 + i as f64 * 0.01),
+                        ),
+                        ("zuid".to_string(), status("zuid", -0.60)),
+                    ]),
+                    stromen: Vec::new(),
+                    boezembelasting: HashMap::new(),
+                    kruinhoogten: HashMap::new(),
+                    chloride: HashMap::new(),
+                })
+                .collect(),
+            totale_kosten: Some(12.5),
+        }
+    }
+
+    #[test]
+    fn test_simulatierapport() {
+        let rapport = Rapport::simulatie(&scenario(), &resultaat(5)).unwrap();
+        assert_eq!(rapport.titel, "Simulatierapport Zomerbui");
+
+        let Sectie::Tabel { rijen, .. } = &rapport.secties[2] else {
+            panic!("samenvatting verwacht");
+        };
+        assert!(rijen.contains(&vec![
+            "Gesimuleerde tijd (uren)".to_string(),
+            "1.0".to_string()
+        ]));
+        assert!(rijen.contains(&vec!["Totale kosten (€)".to_string(), "12.50".to_string()]));
+
+        let grafiek = rapport
+            .secties
+            .iter()
+            .find_map(|s| match s {
+                Sectie::Grafiek { reeksen, .. } => Some(reeksen),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(grafiek[0].naam, "noord");
+        assert_eq!(grafiek[0].punten[4].0, 1.0);
+        assert!((grafiek[0].punten[4].1 + 0.56).abs() < 1e-9);
+
+        assert_eq!(
+            Rapport::simulatie(&scenario(), &resultaat(0)),
+            Err(RapportFout::GeenData)
+        );
+    }
+
+    #[test]
+    fn test_pdf() {
+        // Genoeg rijen voor een tweede pagina
+        let rapport = Rapport::simulatie(&scenario(), &resultaat(5)).unwrap();
+        let mut lang = rapport.clone();
+        lang.secties.push(tabel(
+            &["Nummer"],
+            (0..80).map(|i| vec![i.to_string()]).collect(),
+        ));
+
+        let pdf = lang.als_pdf().unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.7"));
+        assert!(pdf.ends_with(b"%%EOF"));
+        let tekst = String::from_utf8_lossy(&pdf);
+        // De grafiek past niet meer op de eerste pagina, de tabel loopt door
+        assert!(tekst.contains("/Count 3"));
+        assert!(tekst.contains("/Subtype /Image"));
+        assert!(tekst.contains("(Simulatierapport Zomerbui)"));
+    }
+
+    #[test]
+    fn test_html() {
+        let mut rapport = Rapport::simulatie(&scenario(), &resultaat(5)).unwrap();
+        rapport.titel = "Bui <noord> & zuid".to_string();
+
+        let html = rapport.als_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Bui &lt;noord&gt; &amp; zuid</title>"));
+        // Zelfstandig: geen externe scripts, stijlen of afbeeldingen
+        assert!(!html.contains("src=") && !html.contains("<link"));
+        assert!(html.contains(r#"{"naam":"noord","punten":[[0.0,-0.6],[0.25,"#));
+        assert!(html.contains("<td class=\"getal\">-0.600</td>"));
+        assert!(html.contains("Gebruikte parameters"));
+        assert!(html.contains("&quot;naam&quot;: &quot;Zomerbui&quot;"));
+
+        assert_eq!(
+            script_json(&serde_json::json!({ "naam": "</script>" })),
+            r#"{"naam":"\u003c/script>"}"#
+        );
+    }
+
+    #[test]
+    fn test_verdun() {
+        let punten: Vec<(f64, f64)> = (0..10_000)
+            .map(|i| (i as f64, if i == 5_003 { 9.0 } else { (i % 7) as f64 }))
+            .collect();
+        let verdund = verdun(&punten, MAX_HTML_PUNTEN);
+        assert!(verdund.len() <= MAX_HTML_PUNTEN);
+        // Het begin en de piek blijven, in volgorde van x
+        assert_eq!(verdund[0].0, 0.0);
+        assert!(verdund.contains(&(5_003.0, 9.0)));
+        assert!(verdund.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(verdun(&[(0.0, 1.0), (1.0, f64::NAN)], 10), vec![(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_afbreken_en_codering() {
+        assert_eq!(
+            afbreken("een twee drie vier", 9),
+            vec!["een twee", "drie vier"]
+        );
+        assert!(afbreken("", 10).is_empty());
+        assert_eq!(win_ansi("€ 3 m³ naïef"), b"\x80 3 m\xb3 na\xefef");
+        assert_eq!(win_ansi("→"), b"?");
+    }
+}
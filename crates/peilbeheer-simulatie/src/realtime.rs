@@ -0,0 +1,208 @@
+//! Verwachting vanaf de actuele toestand.
+//!
+//! In de realtime modus start de netwerksimulatie niet op streefpeil maar op
+//! de laatst gemeten waterstanden, en rekent met de neerslagverwachting
+//! enkele uren vooruit. Peilgebieden zonder meting starten op streefpeil.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{
+    NetwerkFout, NetwerkSimulatie, NetwerkTopologie, PeilgebiedId, UitstroomStrategy,
+};
+
+/// Actuele toestand waarvandaan vooruit wordt gerekend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beginstaat {
+    /// Tijdstip van de toestand; het eerste uur van de verwachting begint
+    /// hier
+    pub t0: DateTime<Utc>,
+    /// Gemeten waterstand per peilgebied in m NAP
+    #[serde(default)]
+    pub waterstanden: HashMap<PeilgebiedId, f64>,
+    /// Neerslag per uur vanaf `t0` per peilgebied in mm
+    #[serde(default)]
+    pub regen_per_uur: HashMap<PeilgebiedId, Vec<f64>>,
+}
+
+/// Verwachte waterstand aan het eind van een uur.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Verwachtingswaarde {
+    pub tijd: DateTime<Utc>,
+    /// Waterstand in m NAP
+    pub waterstand: f64,
+}
+
+/// Verwachting van één peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedVerwachting {
+    pub peilgebied_id: PeilgebiedId,
+    /// Gemeten beginwaterstand; None als het peilgebied op streefpeil is
+    /// gestart
+    pub gemeten: Option<f64>,
+    /// Eén waarde per uur na `t0`
+    pub waarden: Vec<Verwachtingswaarde>,
+    /// Hoogste verwachte waterstand in m NAP
+    pub max_waterstand: f64,
+    /// Eerste uur dat het peil boven streefpeil + marge staat
+    pub overschrijding_vanaf: Option<DateTime<Utc>>,
+}
+
+/// Verwachting van alle peilgebieden, op volgorde van id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verwachting {
+    pub t0: DateTime<Utc>,
+    pub uren: usize,
+    pub peilgebieden: Vec<PeilgebiedVerwachting>,
+}
+
+/// Reken `uren` uur vooruit vanaf de beginstaat, met stappen van een
+/// minuut.
+pub fn reken_vooruit(
+    topologie: &NetwerkTopologie,
+    beginstaat: &Beginstaat,
+    uren: usize,
+    uitstroom_strategy: &dyn UitstroomStrategy,
+) -> Result<Verwachting, NetwerkFout> {
+    let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone())?;
+    for (id, waterstand) in &beginstaat.waterstanden {
+        if !topologie.peilgebieden.contains_key(id) {
+            return Err(NetwerkFout::PeilgebiedNietGevonden { id: id.clone() });
+        }
+        simulatie = simulatie.met_start_waterstand(id, *waterstand)?;
+    }
+
+    let mut ids: Vec<&PeilgebiedId> = topologie.peilgebieden.keys().collect();
+    ids.sort();
+    let mut peilgebieden: Vec<PeilgebiedVerwachting> = ids
+        .iter()
+        .map(|id| PeilgebiedVerwachting {
+            peilgebied_id: (*id).clone(),
+            gemeten: beginstaat.waterstanden.get(*id).copied(),
+            waarden: Vec::with_capacity(uren),
+            max_waterstand: simulatie.waterstanden[*id],
+            overschrijding_vanaf: None,
+        })
+        .collect();
+
+    // Per uur doorrekenen; de simulatie telt de uren van de regen vanaf
+    // haar eigen begin
+    for uur in 1..=uren {
+        simulatie.simuleer(&beginstaat.regen_per_uur, 1, uitstroom_strategy)?;
+        let tijd = beginstaat.t0 + Duration::hours(uur as i64);
+        for verwachting in &mut peilgebieden {
+            let config = &topologie.peilgebieden[&verwachting.peilgebied_id];
+            let waterstand = simulatie.waterstanden[&verwachting.peilgebied_id];
+            verwachting
+                .waarden
+                .push(Verwachtingswaarde { tijd, waterstand });
+            verwachting.max_waterstand = verwachting.max_waterstand.max(waterstand);
+            if verwachting.overschrijding_vanaf.is_none()
+                && waterstand > config.streefpeil + config.marge
+            {
+                verwachting.overschrijding_vanaf = Some(tijd);
+            }
+        }
+    }
+
+    Ok(Verwachting {
+        t0: beginstaat.t0,
+        uren,
+        peilgebieden,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{PeilgebiedConfig, SimpeleUitstroomStrategy, Verbinding};
+
+    fn peilgebied(id: &str) -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.05,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet: 0.05,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        }
+    }
+
+    fn topologie() -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        for id in ["noord", "zuid"] {
+            topologie.voeg_peilgebied_toe(peilgebied(id)).unwrap();
+        }
+        // Een overstort die bij deze peilen niet overstort
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_overstort(
+                    "overstort".to_string(),
+                    "noord".to_string(),
+                    "zuid".to_string(),
+                    1.0,
+                    0.0,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        topologie
+    }
+
+    #[test]
+    fn test_reken_vooruit() {
+        let t0 = "2025-06-01T12:00:00Z".parse().unwrap();
+        let beginstaat = Beginstaat {
+            t0,
+            waterstanden: HashMap::from([("noord".to_string(), -0.57)]),
+            regen_per_uur: HashMap::from([("noord".to_string(), vec![30.0, 30.0])]),
+        };
+        let verwachting =
+            reken_vooruit(&topologie(), &beginstaat, 4, &SimpeleUitstroomStrategy).unwrap();
+        assert_eq!(verwachting.peilgebieden.len(), 2);
+
+        let noord = &verwachting.peilgebieden[0];
+        assert_eq!(noord.peilgebied_id, "noord");
+        assert_eq!(noord.gemeten, Some(-0.57));
+        assert_eq!(noord.waarden.len(), 4);
+        assert_eq!(noord.waarden[0].tijd, t0 + Duration::hours(1));
+        // Natte start en regen: boven de marge in het eerste uur
+        assert_eq!(noord.overschrijding_vanaf, Some(t0 + Duration::hours(1)));
+        assert!(noord.max_waterstand > -0.55);
+
+        // Zonder meting en regen blijft zuid op streefpeil
+        let zuid = &verwachting.peilgebieden[1];
+        assert_eq!(zuid.gemeten, None);
+        assert!(
+            zuid.waarden
+                .iter()
+                .all(|w| (w.waterstand + 0.60).abs() < 1e-9)
+        );
+        assert_eq!(zuid.overschrijding_vanaf, None);
+    }
+
+    #[test]
+    fn test_onbekend_peilgebied() {
+        let beginstaat = Beginstaat {
+            t0: Utc::now(),
+            waterstanden: HashMap::from([("elders".to_string(), -0.5)]),
+            regen_per_uur: HashMap::new(),
+        };
+        assert!(matches!(
+            reken_vooruit(&topologie(), &beginstaat, 2, &SimpeleUitstroomStrategy),
+            Err(NetwerkFout::PeilgebiedNietGevonden { .. })
+        ));
+    }
+}
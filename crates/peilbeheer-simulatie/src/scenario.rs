@@ -7,12 +7,17 @@ use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::klimaat::Klimaatscenario;
 use crate::netwerk::{
-    NetwerkSimulatieResultaat, NetwerkTopologie, PeilgebiedId,
+    Annulering, GebalanceerdeUitstroomStrategy, NetwerkFout, NetwerkSimulatie, NetwerkSimulatieResultaat,
+    NetwerkTopologie, PeilgebiedId, SimpeleUitstroomStrategy, StuwStrategy,
+    UitstroomStrategy, Voortgang,
 };
 
 /// Een compleet simulatiescenario.
@@ -49,6 +54,12 @@ pub struct Regenscenario {
     /// Type regenscenario
     #[serde(default)]
     pub scenario_type: RegenscenarioType,
+    /// Nagespeelde periode bij een historisch scenario
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub periode: Option<HistorischePeriode>,
+    /// Klimaatscenario waarnaar de reeksen zijn omgezet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub klimaatscenario: Option<Klimaatscenario>,
 }
 
 /// Type regenscenario.
@@ -65,8 +76,114 @@ pub enum RegenscenarioType {
     Synthetisch,
     /// Constante regenval
     Constant,
+    /// Neerslagverwachting (radar nowcast)
+    Voorspelling,
 }
 
+/// Historische periode waarvan de gemeten neerslag wordt nagespeeld.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistorischePeriode {
+    /// Begin van het eerste uur
+    pub start: DateTime<Utc>,
+    /// Einde van de periode (exclusief)
+    pub eind: DateTime<Utc>,
+}
+
+impl HistorischePeriode {
+    /// Periode van `start` tot `eind`; het einde moet na het begin liggen.
+    pub fn nieuw(start: DateTime<Utc>, eind: DateTime<Utc>) -> Result<Self, ScenarioFout> {
+        if eind <= start {
+            return Err(ScenarioFout::OngeldigFormaat {
+                details: "Einde van de historische periode ligt niet na het begin".to_string(),
+            });
+        }
+        Ok(Self { start, eind })
+    }
+
+    /// Een hele kalendermaand, bijvoorbeeld `maand(2023, 10)` voor oktober 2023.
+    pub fn maand(jaar: i32, maand: u32) -> Result<Self, ScenarioFout> {
+        let ongeldig = || ScenarioFout::OngeldigFormaat {
+            details: format!("Ongeldige maand: {}-{}", jaar, maand),
+        };
+        let start = Utc
+            .with_ymd_and_hms(jaar, maand, 1, 0, 0, 0)
+            .single()
+            .ok_or_else(ongeldig)?;
+        let (jaar, maand) = if maand == 12 { (jaar + 1, 1) } else { (jaar, maand + 1) };
+        let eind = Utc
+            .with_ymd_and_hms(jaar, maand, 1, 0, 0, 0)
+            .single()
+            .ok_or_else(ongeldig)?;
+        Self::nieuw(start, eind)
+    }
+
+    /// Aantal uren in de periode; een laatste gedeeltelijk uur telt mee.
+    pub fn uren(&self) -> usize {
+        ((self.eind - self.start).num_minutes() as usize).div_ceil(60)
+    }
+
+    /// Uursommen (mm) van neerslaghoeveelheden per tijdstap.
+    ///
+    /// Een hoeveelheid telt mee in het uur waarin haar tijdstip valt, geteld
+    /// vanaf `start`. Tijdstippen buiten de periode en ontbrekende waarden
+    /// (NaN) worden overgeslagen; uren zonder metingen krijgen 0.0.
+    pub fn uursommen(
+        &self,
+        metingen: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    ) -> Vec<f64> {
+        let mut regen = vec![0.0; self.uren()];
+        for (tijd, waarde) in metingen {
+            if tijd < self.start || tijd >= self.eind || !waarde.is_finite() {
+                continue;
+            }
+            regen[((tijd - self.start).num_minutes() / 60) as usize] += waarde.max(0.0);
+        }
+        regen
+    }
+}
+
+impl Regenscenario {
+    /// Regenscenario met de gemeten neerslag van een historische periode.
+    pub fn historisch(
+        periode: HistorischePeriode,
+        regen_per_uur: HashMap<PeilgebiedId, Vec<f64>>,
+    ) -> Self {
+        Self {
+            regen_per_uur,
+            scenario_type: RegenscenarioType::Historisch,
+            periode: Some(periode),
+            klimaatscenario: None,
+        }
+    }
+
+    /// Het scenario met alle reeksen omgezet naar een KNMI'23-klimaatscenario.
+    ///
+    /// `start` is het begin van het eerste uur, voor de seizoenen; bij een
+    /// historisch scenario is dat het begin van de periode. Een scenario
+    /// wordt maar één keer omgezet.
+    pub fn naar_klimaat(
+        &self,
+        klimaatscenario: Klimaatscenario,
+        start: DateTime<Utc>,
+    ) -> Result<Self, ScenarioFout> {
+        if let Some(huidig) = self.klimaatscenario {
+            return Err(ScenarioFout::OngeldigFormaat {
+                details: format!("Regenscenario is al omgezet naar {}", huidig),
+            });
+        }
+        let transformatie = klimaatscenario.transformatie();
+        Ok(Self {
+            regen_per_uur: self
+                .regen_per_uur
+                .iter()
+                .map(|(id, reeks)| (id.clone(), transformatie.transformeer(reeks, start)))
+                .collect(),
+            scenario_type: self.scenario_type,
+            periode: self.periode,
+            klimaatscenario: Some(klimaatscenario),
+        })
+    }
+}
 
 
 /// Simulatieparameters.
@@ -111,6 +228,31 @@ pub enum StrategyType {
     Simpel,
     /// Gebalanceerde strategy: verdeel waterlast
     Gebalanceerd { balance_factor: f64 },
+    /// Simpele strategy met stuwen geregeld op het bovenstroomse streefpeil
+    Stuwregeling {
+        versterking: f64,
+        max_verstelling: f64,
+    },
+}
+
+impl StrategyType {
+    /// De uitstroomstrategy van dit type.
+    pub fn strategy(&self) -> Box<dyn UitstroomStrategy> {
+        match *self {
+            Self::Simpel => Box::new(SimpeleUitstroomStrategy),
+            Self::Gebalanceerd { balance_factor } => {
+                Box::new(GebalanceerdeUitstroomStrategy { balance_factor })
+            }
+            Self::Stuwregeling {
+                versterking,
+                max_verstelling,
+            } => Box::new(
+                StuwStrategy::nieuw(SimpeleUitstroomStrategy)
+                    .met_versterking(versterking)
+                    .met_max_verstelling(max_verstelling),
+            ),
+        }
+    }
 }
 
 
@@ -173,6 +315,10 @@ pub enum ScenarioFout {
     OngeldigFormaat { details: String },
     /// Scenario niet gevonden
     NietGevonden { id: String },
+    /// De netwerksimulatie van het scenario is mislukt
+    SimulatieMislukt { reden: String },
+    /// De simulatie is afgebroken op `tijd` minuten
+    Geannuleerd { tijd: f64 },
 }
 
 impl std::fmt::Display for ScenarioFout {
@@ -190,12 +336,29 @@ impl std::fmt::Display for ScenarioFout {
             Self::NietGevonden { id } => {
                 write!(f, "Scenario niet gevonden: {}", id)
             }
+            Self::SimulatieMislukt { reden } => {
+                write!(f, "Simulatie van scenario mislukt: {}", reden)
+            }
+            Self::Geannuleerd { tijd } => {
+                write!(f, "Simulatie van scenario geannuleerd op t={:.2} min", tijd)
+            }
         }
     }
 }
 
 impl std::error::Error for ScenarioFout {}
 
+impl From<NetwerkFout> for ScenarioFout {
+    fn from(e: NetwerkFout) -> Self {
+        match e {
+            NetwerkFout::Geannuleerd { tijd } => Self::Geannuleerd { tijd },
+            e => Self::SimulatieMislukt {
+                reden: e.to_string(),
+            },
+        }
+    }
+}
+
 impl From<io::Error> for ScenarioFout {
     fn from(err: io::Error) -> Self {
         Self::LadenMislukt {
@@ -287,6 +450,31 @@ impl Scenario {
         Ok(())
     }
 
+    /// Simuleer het scenario met zijn regen, duur, tijdstap en strategy.
+    pub fn simuleer(&self) -> Result<NetwerkSimulatieResultaat, ScenarioFout> {
+        self.simuleer_met_voortgang(|_| {}, &Annulering::nieuw())
+    }
+
+    /// Simuleer het scenario met voortgangsmeldingen en annulering, zie
+    /// [`NetwerkSimulatie::simuleer_met_voortgang`].
+    pub fn simuleer_met_voortgang(
+        &self,
+        voortgang: impl FnMut(&Voortgang),
+        annulering: &Annulering,
+    ) -> Result<NetwerkSimulatieResultaat, ScenarioFout> {
+        self.valideer()?;
+        let strategy = self.parameters.strategy_type.strategy();
+        Ok(NetwerkSimulatie::nieuw(self.topologie.clone())?
+            .met_tijdstap(self.parameters.timestep_minutes)?
+            .simuleer_met_voortgang(
+                &self.regen_scenario.regen_per_uur,
+                self.parameters.duration_hours,
+                strategy.as_ref(),
+                voortgang,
+                annulering,
+            )?)
+    }
+
     /// Sla scenario op naar JSON bestand.
     pub fn sla_op<P: AsRef<Path>>(&self, pad: P) -> Result<(), ScenarioFout> {
         self.valideer()?;
@@ -538,6 +726,33 @@ impl ScenarioBouwer {
     }
 }
 
+/// Reken meerdere scenario's parallel door.
+///
+/// De resultaten staan in dezelfde volgorde als `scenarios`, ongeacht welke
+/// simulatie het eerst klaar is. Na elk afgerond scenario wordt `voortgang`
+/// aangeroepen met het aantal afgeronde scenario's en het totaal; die
+/// aanroepen komen uit de werkthreads en dus niet in scenariovolgorde.
+pub fn run_scenarios_parallel<F>(
+    scenarios: &[Scenario],
+    voortgang: F,
+) -> Vec<Result<ScenarioResultaat, ScenarioFout>>
+where
+    F: Fn(usize, usize) + Sync,
+{
+    let totaal = scenarios.len();
+    let afgerond = AtomicUsize::new(0);
+    scenarios
+        .par_iter()
+        .map(|scenario| {
+            let resultaat = scenario
+                .simuleer()
+                .map(|resultaat| ScenarioResultaat::nieuw(scenario.clone(), resultaat));
+            voortgang(afgerond.fetch_add(1, Ordering::Relaxed) + 1, totaal);
+            resultaat
+        })
+        .collect()
+}
+
 /// Helper functie om een constant regenscenario te maken.
 pub fn constant_regen_scenario(
     peilgebied_ids: &[String],
@@ -552,6 +767,186 @@ pub fn constant_regen_scenario(
     Regenscenario {
         regen_per_uur,
         scenario_type: RegenscenarioType::Constant,
+        periode: None,
+        klimaatscenario: None,
+    }
+}
+
+/// Parameters van de neerslaggenerator voor één kalendermaand.
+///
+/// Of een uur nat is volgt een Markovketen met twee toestanden (droog/nat);
+/// de hoeveelheid op een nat uur is exponentieel verdeeld.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MaandNeerslag {
+    /// Kans dat een uur nat is als het vorige uur droog was
+    pub kans_nat_na_droog: f64,
+    /// Kans dat een uur nat is als het vorige uur nat was
+    pub kans_nat_na_nat: f64,
+    /// Gemiddelde neerslag op een nat uur (mm/uur)
+    pub gemiddelde_intensiteit: f64,
+}
+
+impl MaandNeerslag {
+    /// Parameters uit de fractie natte uren, de kans dat een nat uur op een
+    /// nat uur volgt en de gemiddelde intensiteit op natte uren.
+    pub fn uit_natte_fractie(
+        fractie: f64,
+        persistentie: f64,
+        gemiddelde_intensiteit: f64,
+    ) -> Self {
+        Self {
+            kans_nat_na_droog: fractie * (1.0 - persistentie) / (1.0 - fractie),
+            kans_nat_na_nat: persistentie,
+            gemiddelde_intensiteit,
+        }
+    }
+
+    /// Fractie natte uren op de lange duur.
+    pub fn natte_fractie(&self) -> f64 {
+        self.kans_nat_na_droog / (1.0 - self.kans_nat_na_nat + self.kans_nat_na_droog)
+    }
+
+    /// Verwachte neerslag per uur, natte en droge uren samen (mm/uur).
+    pub fn gemiddelde_per_uur(&self) -> f64 {
+        self.natte_fractie() * self.gemiddelde_intensiteit
+    }
+
+    fn valideer(&self) -> Result<(), String> {
+        let kans = |p: f64| (0.0..=1.0).contains(&p);
+        if !kans(self.kans_nat_na_droog) || !kans(self.kans_nat_na_nat) {
+            return Err("Overgangskansen moeten tussen 0 en 1 liggen".to_string());
+        }
+        if self.kans_nat_na_droog == 0.0 && self.kans_nat_na_nat == 1.0 {
+            return Err("Markovketen zonder overgangen tussen droog en nat".to_string());
+        }
+        if !self.gemiddelde_intensiteit.is_finite() || self.gemiddelde_intensiteit <= 0.0 {
+            return Err("Gemiddelde intensiteit moet > 0 zijn".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Stochastische neerslaggenerator voor lange synthetische uurreeksen.
+///
+/// Met dezelfde parameters en hetzelfde zaad is de reeks reproduceerbaar,
+/// zodat pompuren en peiloverschrijdingen van varianten vergelijkbaar zijn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeerslagGenerator {
+    /// Parameters per kalendermaand, januari eerst
+    pub maanden: [MaandNeerslag; 12],
+    /// Zaad van de toevalsgenerator
+    pub zaad: u64,
+}
+
+impl NeerslagGenerator {
+    /// Generator met eigen maandparameters.
+    pub fn nieuw(maanden: [MaandNeerslag; 12], zaad: u64) -> Result<Self, ScenarioFout> {
+        for (i, maand) in maanden.iter().enumerate() {
+            maand
+                .valideer()
+                .map_err(|reden| ScenarioFout::OngeldigFormaat {
+                    details: format!("Maand {}: {}", i + 1, reden),
+                })?;
+        }
+        Ok(Self { maanden, zaad })
+    }
+
+    /// Generator met een Nederlands klimaat van circa 800 mm per jaar:
+    /// 's winters vaker regen, 's zomers minder vaak maar heviger.
+    pub fn nederland(zaad: u64) -> Self {
+        const FRACTIE_EN_INTENSITEIT: [(f64, f64); 12] = [
+            (0.10, 0.85),
+            (0.09, 0.85),
+            (0.08, 0.90),
+            (0.06, 1.00),
+            (0.06, 1.30),
+            (0.06, 1.60),
+            (0.06, 1.80),
+            (0.06, 1.90),
+            (0.07, 1.50),
+            (0.09, 1.20),
+            (0.10, 1.00),
+            (0.10, 0.95),
+        ];
+        Self {
+            maanden: FRACTIE_EN_INTENSITEIT.map(|(fractie, intensiteit)| {
+                MaandNeerslag::uit_natte_fractie(fractie, 0.6, intensiteit)
+            }),
+            zaad,
+        }
+    }
+
+    /// Uurreeks (mm/uur) van `uren` uur vanaf `start`.
+    pub fn genereer(&self, start: DateTime<Utc>, uren: usize) -> Vec<f64> {
+        let mut toeval = Toeval(self.zaad);
+        let mut nat = false;
+        (0..uren)
+            .map(|uur| {
+                let tijd = start + Duration::hours(uur as i64);
+                let maand = &self.maanden[tijd.month0() as usize];
+                let kans = if nat {
+                    maand.kans_nat_na_nat
+                } else {
+                    maand.kans_nat_na_droog
+                };
+                nat = toeval.uniform() < kans;
+                if nat {
+                    -maand.gemiddelde_intensiteit * (1.0 - toeval.uniform()).ln()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Uurreeks van hele kalenderjaren vanaf 1 januari van `start_jaar`.
+    pub fn genereer_jaren(&self, start_jaar: i32, jaren: u32) -> Vec<f64> {
+        let start = Utc.with_ymd_and_hms(start_jaar, 1, 1, 0, 0, 0).unwrap();
+        let eind = Utc
+            .with_ymd_and_hms(start_jaar + jaren as i32, 1, 1, 0, 0, 0)
+            .unwrap();
+        self.genereer(start, (eind - start).num_hours() as usize)
+    }
+
+    /// Regenscenario met dezelfde synthetische reeks voor alle peilgebieden.
+    pub fn regenscenario(
+        &self,
+        peilgebied_ids: &[String],
+        start: DateTime<Utc>,
+        uren: usize,
+    ) -> Regenscenario {
+        let reeks = self.genereer(start, uren);
+        Regenscenario {
+            regen_per_uur: peilgebied_ids
+                .iter()
+                .map(|id| (id.clone(), reeks.clone()))
+                .collect(),
+            scenario_type: RegenscenarioType::Synthetisch,
+            periode: None,
+            klimaatscenario: None,
+        }
+    }
+}
+
+/// Kleine reproduceerbare toevalsgenerator (SplitMix64).
+pub(crate) struct Toeval(pub(crate) u64);
+
+impl Toeval {
+    /// Getal in [0, 1).
+    pub(crate) fn uniform(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standaardnormaal verdeeld getal (Box-Muller).
+    pub(crate) fn normaal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
     }
 }
 
@@ -572,8 +967,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.5,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -586,8 +988,15 @@ mod tests {
                 marge: 0.20,
                 maaiveld_niveau: 0.0,
                 max_uitstroom_debiet: 0.4,
+                max_inlaat_debiet: 0.0,
                 verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
                 infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
             })
             .unwrap();
 
@@ -644,6 +1053,55 @@ mod tests {
         assert!(scenario.metadata.tags.contains(&"demo".to_string()));
     }
 
+    #[test]
+    fn test_run_scenarios_parallel() {
+        let topologie = maak_test_topologie();
+        let mut scenarios: Vec<Scenario> = (0..8)
+            .map(|i| {
+                ScenarioBouwer::nieuw(format!("scenario_{}", i))
+                    .met_topologie(topologie.clone())
+                    .met_regen("polder_a".to_string(), vec![i as f64; 12])
+                    .met_duur(12)
+                    .bouw()
+                    .unwrap()
+            })
+            .collect();
+        // Een ongeldig scenario mag de andere niet tegenhouden
+        scenarios[3]
+            .regen_scenario
+            .regen_per_uur
+            .insert("onbekend".to_string(), vec![1.0; 12]);
+
+        let meldingen = std::sync::Mutex::new(Vec::new());
+        let resultaten = run_scenarios_parallel(&scenarios, |afgerond, totaal| {
+            meldingen.lock().unwrap().push((afgerond, totaal));
+        });
+
+        assert_eq!(resultaten.len(), 8);
+        for (i, resultaat) in resultaten.iter().enumerate() {
+            match resultaat {
+                Ok(r) => {
+                    assert_eq!(r.scenario.id, format!("scenario_{}", i));
+                    let serieel = scenarios[i].simuleer().unwrap();
+                    assert_eq!(r.resultaat.tijdstappen.len(), serieel.tijdstappen.len());
+                    let eind = |t: &NetwerkSimulatieResultaat| {
+                        t.tijdstappen.last().unwrap().statussen["polder_a"].waterstand
+                    };
+                    assert_eq!(eind(&r.resultaat), eind(&serieel));
+                }
+                Err(fout) => {
+                    assert_eq!(i, 3);
+                    assert!(matches!(fout, ScenarioFout::OngeldigFormaat { .. }));
+                }
+            }
+        }
+
+        let mut meldingen = meldingen.into_inner().unwrap();
+        meldingen.sort();
+        let verwacht: Vec<_> = (1..=8).map(|k| (k, 8)).collect();
+        assert_eq!(meldingen, verwacht);
+    }
+
     #[test]
     fn test_scenario_builder_zonder_topologie() {
         let result = ScenarioBouwer::nieuw("test".to_string()).bouw();
@@ -726,6 +1184,123 @@ mod tests {
         assert!(regen.regen_per_uur.get("a").unwrap().iter().all(|&x| x == 5.0));
     }
 
+    #[test]
+    fn test_historische_periode() {
+        let periode = HistorischePeriode::maand(2023, 10).unwrap();
+        assert_eq!(periode.uren(), 31 * 24);
+        assert!(HistorischePeriode::maand(2023, 13).is_err());
+        assert!(HistorischePeriode::nieuw(periode.eind, periode.start).is_err());
+
+        let start = periode.start;
+        let uur = |u: i64, m: i64| start + Duration::hours(u) + Duration::minutes(m);
+        let regen = periode.uursommen([
+            (uur(0, 10), 0.5),
+            (uur(0, 40), 0.7),
+            (uur(2, 0), f64::NAN),
+            (uur(3, 0), 2.0),
+            (uur(-1, 0), 9.0),
+            (periode.eind, 9.0),
+        ]);
+        assert_eq!(regen.len(), periode.uren());
+        assert!((regen[0] - 1.2).abs() < 1e-12);
+        assert_eq!(regen[2], 0.0);
+        assert_eq!(regen[3], 2.0);
+        assert!((regen.iter().sum::<f64>() - 3.2).abs() < 1e-12);
+
+        let scenario = Regenscenario::historisch(
+            periode,
+            HashMap::from([("polder_a".to_string(), regen)]),
+        );
+        assert_eq!(scenario.scenario_type, RegenscenarioType::Historisch);
+        let json = serde_json::to_string(&scenario).unwrap();
+        let herladen: Regenscenario = serde_json::from_str(&json).unwrap();
+        assert_eq!(herladen.periode, Some(periode));
+    }
+
+    #[test]
+    fn test_regenscenario_naar_klimaat() {
+        let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        let ids = vec!["a".to_string()];
+        let regen = NeerslagGenerator::nederland(5).regenscenario(&ids, start, 24 * 365);
+
+        let toekomst = regen.naar_klimaat(Klimaatscenario::Hn2100, start).unwrap();
+        assert_eq!(toekomst.klimaatscenario, Some(Klimaatscenario::Hn2100));
+        assert_eq!(toekomst.scenario_type, RegenscenarioType::Synthetisch);
+        let som = |r: &Regenscenario| r.regen_per_uur["a"].iter().sum::<f64>();
+        assert!(som(&toekomst) > som(&regen));
+
+        // Niet twee keer omzetten
+        assert!(matches!(
+            toekomst.naar_klimaat(Klimaatscenario::Hn2100, start),
+            Err(ScenarioFout::OngeldigFormaat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_neerslaggenerator_reproduceerbaar() {
+        let start = Utc.with_ymd_and_hms(2020, 6, 1, 0, 0, 0).unwrap();
+        let a = NeerslagGenerator::nederland(42).genereer(start, 24 * 30);
+        let b = NeerslagGenerator::nederland(42).genereer(start, 24 * 30);
+        let c = NeerslagGenerator::nederland(43).genereer(start, 24 * 30);
+
+        assert_eq!(a.len(), 720);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.iter().all(|&x| x >= 0.0));
+
+        let regen =
+            NeerslagGenerator::nederland(42).regenscenario(&["a".to_string()], start, 720);
+        assert_eq!(regen.scenario_type, RegenscenarioType::Synthetisch);
+        assert_eq!(regen.regen_per_uur["a"], a);
+    }
+
+    #[test]
+    fn test_neerslaggenerator_statistiek() {
+        let generator = NeerslagGenerator::nederland(7);
+        let reeks = generator.genereer_jaren(2001, 30);
+        assert_eq!(reeks.len(), 30 * 8760 + 7 * 24);
+
+        // Jaarsom en fractie natte uren liggen dicht bij de verwachting
+        let dagen = [31.0, 28.25, 31.0, 30.0, 31.0, 30.0, 31.0, 31.0, 30.0, 31.0, 30.0, 31.0];
+        let verwacht: f64 = generator
+            .maanden
+            .iter()
+            .zip(dagen)
+            .map(|(m, dagen)| m.gemiddelde_per_uur() * dagen * 24.0)
+            .sum();
+        let jaarsom = reeks.iter().sum::<f64>() / 30.0;
+        assert!(
+            (jaarsom - verwacht).abs() / verwacht < 0.05,
+            "jaarsom {jaarsom}, verwacht {verwacht}"
+        );
+
+        let nat = reeks.iter().filter(|&&x| x > 0.0).count() as f64 / reeks.len() as f64;
+        assert!((nat - 0.077).abs() < 0.005, "fractie nat {nat}");
+
+        // Zomerse buien zijn heviger dan winterse
+        let start = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+        let max_in = |maand: u32| {
+            reeks
+                .iter()
+                .enumerate()
+                .filter(|(uur, _)| (start + Duration::hours(*uur as i64)).month() == maand)
+                .map(|(_, &x)| x)
+                .fold(0.0, f64::max)
+        };
+        assert!(max_in(8) > max_in(1));
+    }
+
+    #[test]
+    fn test_neerslaggenerator_valideer() {
+        let mut maanden = NeerslagGenerator::nederland(1).maanden;
+        assert!(NeerslagGenerator::nieuw(maanden, 1).is_ok());
+        maanden[3].kans_nat_na_nat = 1.5;
+        assert!(matches!(
+            NeerslagGenerator::nieuw(maanden, 1),
+            Err(ScenarioFout::OngeldigFormaat { .. })
+        ));
+    }
+
     #[test]
     fn test_regen_scenario_type_default() {
         let regen = Regenscenario::default();
@@ -822,6 +1397,7 @@ mod tests {
         assert_eq!(RegenscenarioType::Ontworpen, RegenscenarioType::Ontworpen);
         assert_eq!(RegenscenarioType::Synthetisch, RegenscenarioType::Synthetisch);
         assert_eq!(RegenscenarioType::Constant, RegenscenarioType::Constant);
+        assert_eq!(RegenscenarioType::Voorspelling, RegenscenarioType::Voorspelling);
     }
 
     #[test]
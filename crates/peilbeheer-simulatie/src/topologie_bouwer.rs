@@ -0,0 +1,703 @@
+//! Voorstel voor een netwerktopologie uit peilgebieden en kunstwerken.
+//!
+//! Peilgebieden die een grens delen zijn buren. Een gemaal of stuw die
+//! binnen `max_afstand` van zo'n gedeelde grens ligt, wordt een verbinding
+//! tussen de twee buren: een gemaal pompt van het laagste naar het hoogste
+//! streefpeil, over een stuw stroomt het water van hoog naar laag. Een
+//! gemaal op de buitenrand van één peilgebied is het uitslaggemaal van dat
+//! peilgebied.
+//!
+//! Het resultaat is een [`TopologieVoorstel`] dat als JSON beoordeeld en
+//! aangepast kan worden voordat het met [`TopologieVoorstel::naar_topologie`]
+//! een [`NetwerkTopologie`] wordt. Twijfelgevallen staan in de
+//! waarschuwingen van het voorstel.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use peilbeheer_core::asset::AssetRegistratie;
+use peilbeheer_core::bodem::PeilgebiedBodem;
+use peilbeheer_core::maaiveld::{omhullende, ringen_uit_geojson};
+use peilbeheer_core::projectie::{self, Crs};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::netwerk::{
+    NetwerkFout, NetwerkTopologie, PeilgebiedConfig, PeilgebiedId, StuwParameters, Verbinding,
+};
+
+type Punt = (f64, f64);
+type Segment = (Punt, Punt);
+
+/// Instellingen voor het afleiden en omzetten van een voorstel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BouwerInstellingen {
+    /// Grootste afstand van een kunstwerk tot een grens (m)
+    pub max_afstand: f64,
+    /// Kortste gedeelde grens waarmee twee peilgebieden buren zijn (m)
+    pub min_grenslengte: f64,
+    /// Afstand waarbinnen twee randen als dezelfde grens gelden (m)
+    pub tolerantie: f64,
+    /// Capaciteit van een gemaal zonder opgegeven capaciteit (m³/s)
+    pub gemaal_capaciteit: f64,
+    /// Capaciteit van een stuw (m³/s)
+    pub stuw_capaciteit: f64,
+    /// Verstelbereik van een stuw rond zijn begin-kruinhoogte (m)
+    pub stuw_verstelbereik: f64,
+    /// Marge rond streefpeil van de peilgebieden (m)
+    pub marge: f64,
+    /// Drooglegging als er geen maaiveld bekend is (m)
+    pub drooglegging: f64,
+}
+
+impl Default for BouwerInstellingen {
+    fn default() -> Self {
+        Self {
+            max_afstand: 25.0,
+            min_grenslengte: 1.0,
+            tolerantie: 0.5,
+            gemaal_capaciteit: 0.5,
+            stuw_capaciteit: 1.0,
+            stuw_verstelbereik: 0.3,
+            marge: 0.10,
+            drooglegging: 0.8,
+        }
+    }
+}
+
+/// Soort kunstwerk waaruit een verbinding is afgeleid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KunstwerkSoort {
+    Gemaal,
+    Stuw,
+}
+
+/// Voorgesteld peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnoopVoorstel {
+    pub id: PeilgebiedId,
+    #[serde(default)]
+    pub naam: Option<String>,
+    /// Oppervlakte van de polygoon (m²)
+    pub oppervlakte: f64,
+    /// Vast peil, anders zomerpeil, anders winterpeil (m NAP)
+    #[serde(default)]
+    pub streefpeil: Option<f64>,
+    /// Maaiveld uit de attributen (m NAP)
+    #[serde(default)]
+    pub maaiveld: Option<f64>,
+    /// Bodem uit de Bodemkaart; geeft de infiltratie van het peilgebied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bodem: Option<PeilgebiedBodem>,
+    /// Gemalen op de buitenrand die naar buiten uitslaan
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uitslaggemalen: Vec<String>,
+    /// Opgetelde capaciteit van de uitslaggemalen (m³/s)
+    #[serde(default)]
+    pub uitslagcapaciteit: f64,
+}
+
+/// Voorgestelde verbinding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerbindingVoorstel {
+    /// Code van het kunstwerk
+    pub id: String,
+    #[serde(default)]
+    pub naam: Option<String>,
+    pub soort: KunstwerkSoort,
+    pub van: PeilgebiedId,
+    pub naar: PeilgebiedId,
+    /// Capaciteit (m³/s)
+    pub capaciteit: f64,
+    /// Afstand van het kunstwerk tot de gedeelde grens (m)
+    pub afstand_tot_grens: f64,
+    /// Onwaar als de richting niet uit de streefpeilen volgt
+    pub richting_zeker: bool,
+}
+
+/// Gedeelde grens van twee peilgebieden.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrensVoorstel {
+    pub a: PeilgebiedId,
+    pub b: PeilgebiedId,
+    /// Lengte van de gedeelde grens (m)
+    pub lengte: f64,
+    /// Kunstwerken op deze grens
+    pub kunstwerken: Vec<String>,
+}
+
+/// Reviewbaar voorstel voor een netwerktopologie.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopologieVoorstel {
+    pub knopen: Vec<KnoopVoorstel>,
+    pub verbindingen: Vec<VerbindingVoorstel>,
+    pub grenzen: Vec<GrensVoorstel>,
+    #[serde(default)]
+    pub waarschuwingen: Vec<String>,
+}
+
+/// Fouttype voor de topologiebouwer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BouwerFout {
+    /// De peilgebieden zijn geen bruikbare GeoJSON
+    OngeldigeGeojson { reden: String },
+    /// Een peilgebied heeft geen streefpeil
+    GeenStreefpeil { id: PeilgebiedId },
+    /// De topologie is ongeldig
+    Netwerk(NetwerkFout),
+}
+
+impl fmt::Display for BouwerFout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OngeldigeGeojson { reden } => write!(f, "Ongeldige GeoJSON: {}", reden),
+            Self::GeenStreefpeil { id } => write!(f, "Peilgebied {} heeft geen streefpeil", id),
+            Self::Netwerk(e) => write!(f, "Ongeldige topologie: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BouwerFout {}
+
+impl From<NetwerkFout> for BouwerFout {
+    fn from(e: NetwerkFout) -> Self {
+        Self::Netwerk(e)
+    }
+}
+
+/// Peilgebied met zijn ringen in RD.
+struct Polygoon {
+    knoop: KnoopVoorstel,
+    ringen: Vec<Vec<Punt>>,
+    omhullende: (f64, f64, f64, f64),
+}
+
+/// Grens met de gedeelde randstukken.
+struct Grens {
+    a: usize,
+    b: usize,
+    stukken: Vec<Segment>,
+}
+
+/// Leid een topologievoorstel af uit een FeatureCollection van peilgebieden
+/// (met `CODE`, `NAAM`, `VASTPEIL`, `ZOMERPEIL`, `WINTERPEIL` en `MAAIVELD`
+/// als attributen, in RD of WGS84) en de gemaal- en stuwassets.
+pub fn stel_topologie_voor(
+    peilgebieden: &Value,
+    kunstwerken: &[AssetRegistratie],
+    instellingen: &BouwerInstellingen,
+) -> Result<TopologieVoorstel, BouwerFout> {
+    let mut voorstel = TopologieVoorstel::default();
+    let polygonen = lees_polygonen(peilgebieden, &mut voorstel.waarschuwingen)?;
+
+    let mut grenzen = Vec::new();
+    for a in 0..polygonen.len() {
+        for b in a + 1..polygonen.len() {
+            if !overlapt(
+                polygonen[a].omhullende,
+                polygonen[b].omhullende,
+                instellingen.tolerantie,
+            ) {
+                continue;
+            }
+            let stukken = gedeelde_stukken(&polygonen[a], &polygonen[b], instellingen.tolerantie);
+            let lengte: f64 = stukken.iter().map(|&(p, q)| afstand(p, q)).sum();
+            if lengte >= instellingen.min_grenslengte {
+                grenzen.push(Grens { a, b, stukken });
+            }
+        }
+    }
+
+    let mut uitslag: HashMap<usize, Vec<(String, f64)>> = HashMap::new();
+    let mut op_grens: HashMap<usize, Vec<String>> = HashMap::new();
+    for kunstwerk in kunstwerken {
+        let soort = match kunstwerk.layer_type.as_str() {
+            "gemaal" => KunstwerkSoort::Gemaal,
+            "stuw" => KunstwerkSoort::Stuw,
+            _ => continue,
+        };
+        let (Some(lat), Some(lon)) = (kunstwerk.lat, kunstwerk.lon) else {
+            voorstel
+                .waarschuwingen
+                .push(format!("{} heeft geen locatie", kunstwerk.code));
+            continue;
+        };
+        let punt = projectie::wgs84_naar_rd(lon, lat);
+
+        let dichtste_grens = grenzen
+            .iter()
+            .enumerate()
+            .map(|(i, g)| (i, afstand_tot(punt, g.stukken.iter().copied())))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .filter(|&(_, d)| d <= instellingen.max_afstand);
+
+        if let Some((i, d)) = dichtste_grens {
+            let grens = &grenzen[i];
+            let (a, b) = (&polygonen[grens.a].knoop, &polygonen[grens.b].knoop);
+            let (laag, hoog, richting_zeker) = match (a.streefpeil, b.streefpeil) {
+                (Some(pa), Some(pb)) if pa < pb => (a, b, true),
+                (Some(pa), Some(pb)) if pb < pa => (b, a, true),
+                _ => (a, b, false),
+            };
+            let (van, naar) = match soort {
+                KunstwerkSoort::Gemaal => (laag, hoog),
+                KunstwerkSoort::Stuw => (hoog, laag),
+            };
+            if !richting_zeker {
+                voorstel.waarschuwingen.push(format!(
+                    "Richting van {} tussen {} en {} volgt niet uit de streefpeilen",
+                    kunstwerk.code, a.id, b.id
+                ));
+            }
+            let capaciteit = match soort {
+                KunstwerkSoort::Gemaal => {
+                    gemaalcapaciteit(kunstwerk).unwrap_or(instellingen.gemaal_capaciteit)
+                }
+                KunstwerkSoort::Stuw => instellingen.stuw_capaciteit,
+            };
+            voorstel.verbindingen.push(VerbindingVoorstel {
+                id: kunstwerk.code.clone(),
+                naam: kunstwerk.naam.clone(),
+                soort,
+                van: van.id.clone(),
+                naar: naar.id.clone(),
+                capaciteit,
+                afstand_tot_grens: d,
+                richting_zeker,
+            });
+            op_grens.entry(i).or_default().push(kunstwerk.code.clone());
+            continue;
+        }
+
+        // Niet bij een gedeelde grens: een gemaal op de buitenrand slaat uit
+        let dichtste_rand = polygonen
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, afstand_tot(punt, randen(&p.ringen))))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .filter(|&(_, d)| d <= instellingen.max_afstand);
+        match (soort, dichtste_rand) {
+            (KunstwerkSoort::Gemaal, Some((i, _))) => {
+                let capaciteit =
+                    gemaalcapaciteit(kunstwerk).unwrap_or(instellingen.gemaal_capaciteit);
+                uitslag
+                    .entry(i)
+                    .or_default()
+                    .push((kunstwerk.code.clone(), capaciteit));
+            }
+            (KunstwerkSoort::Stuw, Some((i, _))) => voorstel.waarschuwingen.push(format!(
+                "Stuw {} ligt op de buitenrand van {} en is niet meegenomen",
+                kunstwerk.code, polygonen[i].knoop.id
+            )),
+            (_, None) => voorstel.waarschuwingen.push(format!(
+                "{} ligt niet binnen {} m van een peilgebiedgrens",
+                kunstwerk.code, instellingen.max_afstand
+            )),
+        }
+    }
+
+    for (i, grens) in grenzen.iter().enumerate() {
+        voorstel.grenzen.push(GrensVoorstel {
+            a: polygonen[grens.a].knoop.id.clone(),
+            b: polygonen[grens.b].knoop.id.clone(),
+            lengte: grens.stukken.iter().map(|&(p, q)| afstand(p, q)).sum(),
+            kunstwerken: op_grens.remove(&i).unwrap_or_default(),
+        });
+    }
+    for (i, polygoon) in polygonen.into_iter().enumerate() {
+        let mut knoop = polygoon.knoop;
+        for (code, capaciteit) in uitslag.remove(&i).unwrap_or_default() {
+            knoop.uitslaggemalen.push(code);
+            knoop.uitslagcapaciteit += capaciteit;
+        }
+        if knoop.streefpeil.is_none() {
+            voorstel
+                .waarschuwingen
+                .push(format!("Peilgebied {} heeft geen streefpeil", knoop.id));
+        }
+        voorstel.knopen.push(knoop);
+    }
+    voorstel.verbindingen.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(voorstel)
+}
+
+impl TopologieVoorstel {
+    /// Zet het (beoordeelde) voorstel om in een netwerktopologie.
+    pub fn naar_topologie(
+        &self,
+        instellingen: &BouwerInstellingen,
+    ) -> Result<NetwerkTopologie, BouwerFout> {
+        let mut topologie = NetwerkTopologie::nieuw();
+        let mut streefpeilen = HashMap::new();
+        for knoop in &self.knopen {
+            let streefpeil = knoop.streefpeil.ok_or_else(|| BouwerFout::GeenStreefpeil {
+                id: knoop.id.clone(),
+            })?;
+            streefpeilen.insert(&knoop.id, streefpeil);
+            let config = PeilgebiedConfig {
+                id: knoop.id.clone(),
+                naam: knoop.naam.clone(),
+                oppervlakte: knoop.oppervlakte,
+                streefpeil,
+                marge: instellingen.marge,
+                maaiveld_niveau: knoop
+                    .maaiveld
+                    .unwrap_or(streefpeil + instellingen.drooglegging),
+                max_uitstroom_debiet: knoop.uitslagcapaciteit,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            };
+            topologie.voeg_peilgebied_toe(match &knoop.bodem {
+                Some(bodem) => config.met_bodem(bodem),
+                None => config,
+            })?;
+        }
+
+        for voorstel in &self.verbindingen {
+            let peil = |id: &PeilgebiedId| {
+                streefpeilen
+                    .get(id)
+                    .copied()
+                    .ok_or_else(|| NetwerkFout::PeilgebiedNietGevonden { id: id.clone() })
+            };
+            let (van, naar) = (peil(&voorstel.van)?, peil(&voorstel.naar)?);
+            let verbinding = match voorstel.soort {
+                KunstwerkSoort::Gemaal => Verbinding::nieuw_gemaal(
+                    voorstel.id.clone(),
+                    voorstel.van.clone(),
+                    voorstel.naar.clone(),
+                    voorstel.capaciteit,
+                    (naar - van).max(0.0),
+                )?,
+                // De kruin houdt het bovenstroomse streefpeil vast
+                KunstwerkSoort::Stuw => Verbinding::nieuw_stuw(
+                    voorstel.id.clone(),
+                    voorstel.van.clone(),
+                    voorstel.naar.clone(),
+                    voorstel.capaciteit,
+                    van,
+                    StuwParameters::new(
+                        van - instellingen.stuw_verstelbereik,
+                        van + instellingen.stuw_verstelbereik,
+                    ),
+                )?,
+            };
+            topologie.voeg_verbinding_toe(verbinding)?;
+        }
+        Ok(topologie)
+    }
+}
+
+/// Capaciteit van een gemaal in m³/s uit `MAXIMALECAPACITEIT` (m³/min).
+fn gemaalcapaciteit(kunstwerk: &AssetRegistratie) -> Option<f64> {
+    kunstwerk
+        .extra_properties
+        .as_ref()?
+        .get("MAXIMALECAPACITEIT")?
+        .as_f64()
+        .filter(|c| *c > 0.0)
+        .map(|c| c / 60.0)
+}
+
+fn lees_polygonen(
+    peilgebieden: &Value,
+    waarschuwingen: &mut Vec<String>,
+) -> Result<Vec<Polygoon>, BouwerFout> {
+    let mut collectie = peilgebieden.clone();
+    projectie::herprojecteer(&mut collectie, Crs::RdNew).map_err(|e| {
+        BouwerFout::OngeldigeGeojson {
+            reden: e.to_string(),
+        }
+    })?;
+    let features =
+        collectie["features"]
+            .as_array()
+            .ok_or_else(|| BouwerFout::OngeldigeGeojson {
+                reden: "geen FeatureCollection".into(),
+            })?;
+
+    let mut polygonen: Vec<Polygoon> = Vec::new();
+    for feature in features {
+        let eigenschappen = &feature["properties"];
+        let Some(id) = eigenschappen["CODE"].as_str().filter(|c| !c.is_empty()) else {
+            waarschuwingen.push("Peilgebied zonder CODE overgeslagen".into());
+            continue;
+        };
+        if polygonen.iter().any(|p| p.knoop.id == id) {
+            waarschuwingen.push(format!("Dubbel peilgebied {} overgeslagen", id));
+            continue;
+        }
+        let ringen = ringen_uit_geojson(&feature["geometry"]);
+        let Some(omhullende) = omhullende(&ringen) else {
+            waarschuwingen.push(format!("Peilgebied {} heeft geen polygoon", id));
+            continue;
+        };
+        let getal = |sleutel: &str| eigenschappen[sleutel].as_f64();
+        polygonen.push(Polygoon {
+            knoop: KnoopVoorstel {
+                id: id.to_string(),
+                naam: eigenschappen["NAAM"].as_str().map(String::from),
+                oppervlakte: oppervlakte(&ringen),
+                streefpeil: getal("VASTPEIL")
+                    .or_else(|| getal("ZOMERPEIL"))
+                    .or_else(|| getal("WINTERPEIL")),
+                maaiveld: getal("MAAIVELD"),
+                bodem: None,
+                uitslaggemalen: Vec::new(),
+                uitslagcapaciteit: 0.0,
+            },
+            ringen,
+            omhullende,
+        });
+    }
+    Ok(polygonen)
+}
+
+/// Oppervlakte van de ringen; een ring die binnen een grotere ring ligt
+/// is een gat, een ring in een gat weer een eiland.
+fn oppervlakte(ringen: &[Vec<Punt>]) -> f64 {
+    let oppervlakken: Vec<f64> = ringen
+        .iter()
+        .map(|ring| {
+            let dubbel: f64 = ring
+                .iter()
+                .zip(ring.iter().cycle().skip(1))
+                .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+                .sum();
+            dubbel.abs() / 2.0
+        })
+        .collect();
+    ringen
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| {
+            let diepte = ringen
+                .iter()
+                .enumerate()
+                .filter(|&(j, ander)| {
+                    j != i && oppervlakken[j] > oppervlakken[i] && bevat(ander, ring[0])
+                })
+                .count();
+            if diepte % 2 == 0 {
+                oppervlakken[i]
+            } else {
+                -oppervlakken[i]
+            }
+        })
+        .sum()
+}
+
+fn bevat(ring: &[Punt], (x, y): Punt) -> bool {
+    let mut binnen = false;
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            binnen = !binnen;
+        }
+    }
+    binnen
+}
+
+fn overlapt(a: (f64, f64, f64, f64), b: (f64, f64, f64, f64), marge: f64) -> bool {
+    a.0 <= b.2 + marge && b.0 <= a.2 + marge && a.1 <= b.3 + marge && b.1 <= a.3 + marge
+}
+
+fn randen(ringen: &[Vec<Punt>]) -> impl Iterator<Item = Segment> + '_ {
+    ringen
+        .iter()
+        .flat_map(|ring| {
+            ring.iter()
+                .copied()
+                .zip(ring.iter().copied().cycle().skip(1))
+        })
+        .filter(|(p, q)| p != q)
+}
+
+/// Stukken van de randen van `a` die binnen `tolerantie` langs een rand
+/// van `b` lopen.
+fn gedeelde_stukken(a: &Polygoon, b: &Polygoon, tolerantie: f64) -> Vec<Segment> {
+    let mut stukken = Vec::new();
+    for (p, q) in randen(&a.ringen) {
+        let lengte = afstand(p, q);
+        let richting = ((q.0 - p.0) / lengte, (q.1 - p.1) / lengte);
+        for (r, s) in randen(&b.ringen) {
+            // Beide eindpunten van de andere rand moeten op de lijn liggen
+            let loodrecht = |t: Punt| ((t.0 - p.0) * richting.1 - (t.1 - p.1) * richting.0).abs();
+            if loodrecht(r) > tolerantie || loodrecht(s) > tolerantie {
+                continue;
+            }
+            let langs = |t: Punt| (t.0 - p.0) * richting.0 + (t.1 - p.1) * richting.1;
+            let (t1, t2) = (langs(r), langs(s));
+            let begin = t1.min(t2).max(0.0);
+            let eind = t1.max(t2).min(lengte);
+            if eind - begin > tolerantie {
+                let punt = |t: f64| (p.0 + t * richting.0, p.1 + t * richting.1);
+                stukken.push((punt(begin), punt(eind)));
+            }
+        }
+    }
+    stukken
+}
+
+fn afstand(p: Punt, q: Punt) -> f64 {
+    (q.0 - p.0).hypot(q.1 - p.1)
+}
+
+/// Kortste afstand van een punt tot een verzameling segmenten.
+fn afstand_tot(punt: Punt, segmenten: impl Iterator<Item = Segment>) -> f64 {
+    segmenten
+        .map(|(p, q)| {
+            let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+            let lengte2 = dx * dx + dy * dy;
+            let t = if lengte2 > 0.0 {
+                (((punt.0 - p.0) * dx + (punt.1 - p.1) * dy) / lengte2).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            afstand(punt, (p.0 + t * dx, p.1 + t * dy))
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use peilbeheer_core::bodem::Bodemtype;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn vierkant(x0: f64, y0: f64, grootte: f64) -> Value {
+        json!({
+            "type": "Polygon",
+            "coordinates": [[
+                [x0, y0], [x0 + grootte, y0], [x0 + grootte, y0 + grootte],
+                [x0, y0 + grootte], [x0, y0]
+            ]]
+        })
+    }
+
+    fn feature(code: &str, geometrie: Value, peil: f64) -> Value {
+        json!({
+            "type": "Feature",
+            "properties": { "CODE": code, "VASTPEIL": peil },
+            "geometry": geometrie,
+        })
+    }
+
+    fn kunstwerk(
+        layer_type: &str,
+        code: &str,
+        rd: Punt,
+        capaciteit: Option<f64>,
+    ) -> AssetRegistratie {
+        let (lon, lat) = projectie::rd_naar_wgs84(rd.0, rd.1);
+        AssetRegistratie {
+            layer_type: layer_type.to_string(),
+            code: code.to_string(),
+            naam: None,
+            lat: Some(lat),
+            lon: Some(lon),
+            extra_properties: capaciteit.map(|c| json!({ "MAXIMALECAPACITEIT": c })),
+        }
+    }
+
+    #[test]
+    fn test_stel_topologie_voor() {
+        // Drie vierkanten van 1 km naast elkaar in RD; de middelste hoger
+        let (x, y) = (100_000.0, 450_000.0);
+        let peilgebieden = json!({
+            "type": "FeatureCollection",
+            "features": [
+                feature("laag", vierkant(x, y, 1000.0), -1.20),
+                feature("midden", vierkant(x + 1000.0, y, 1000.0), -0.60),
+                feature("west", vierkant(x + 2000.0, y, 1000.0), -0.90),
+            ]
+        });
+        let kunstwerken = vec![
+            kunstwerk("gemaal", "GM1", (x + 1003.0, y + 500.0), Some(30.0)),
+            kunstwerk("stuw", "ST1", (x + 2000.0, y + 200.0), None),
+            kunstwerk("gemaal", "GM2", (x + 3000.0, y + 800.0), None),
+            kunstwerk("stuw", "ST9", (x + 500.0, y + 500.0), None),
+        ];
+
+        let instellingen = BouwerInstellingen::default();
+        let voorstel = stel_topologie_voor(&peilgebieden, &kunstwerken, &instellingen).unwrap();
+
+        assert_eq!(voorstel.knopen.len(), 3);
+        assert!((voorstel.knopen[0].oppervlakte - 1.0e6).abs() < 1e-3);
+        // Alleen de aangrenzende paren zijn buren
+        assert_eq!(voorstel.grenzen.len(), 2);
+        assert!(
+            voorstel
+                .grenzen
+                .iter()
+                .all(|g| (g.lengte - 1000.0).abs() < 1e-6)
+        );
+
+        let gemaal = &voorstel.verbindingen[0];
+        assert_eq!(
+            (gemaal.id.as_str(), gemaal.soort),
+            ("GM1", KunstwerkSoort::Gemaal)
+        );
+        assert_eq!(
+            (gemaal.van.as_str(), gemaal.naar.as_str()),
+            ("laag", "midden")
+        );
+        assert!((gemaal.capaciteit - 0.5).abs() < 1e-12);
+        assert!(gemaal.richting_zeker);
+        let stuw = &voorstel.verbindingen[1];
+        assert_eq!((stuw.van.as_str(), stuw.naar.as_str()), ("midden", "west"));
+
+        // Gemaal op de buitenrand slaat uit, stuw midden in een peilgebied niet
+        let west = voorstel.knopen.iter().find(|k| k.id == "west").unwrap();
+        assert_eq!(west.uitslaggemalen, vec!["GM2".to_string()]);
+        assert!(voorstel.waarschuwingen.iter().any(|w| w.contains("ST9")));
+
+        // Het voorstel overleeft JSON en wordt een geldige topologie
+        let json = serde_json::to_string(&voorstel).unwrap();
+        let terug: TopologieVoorstel = serde_json::from_str(&json).unwrap();
+        let topologie = terug.naar_topologie(&instellingen).unwrap();
+        assert!(topologie.valideer().is_ok());
+        assert_eq!(topologie.verbindingen.len(), 2);
+        assert_eq!(topologie.peilgebieden["west"].max_uitstroom_debiet, 0.5);
+        assert_eq!(topologie.peilgebieden["west"].infiltratie, 0.0);
+    }
+
+    #[test]
+    fn test_infiltratie_uit_bodem() {
+        let (x, y) = (100_000.0, 450_000.0);
+        let peilgebieden = json!({
+            "type": "FeatureCollection",
+            "features": [feature("zand", vierkant(x, y, 1000.0), -1.20)]
+        });
+        let instellingen = BouwerInstellingen::default();
+        let mut voorstel = stel_topologie_voor(&peilgebieden, &[], &instellingen).unwrap();
+        let oppervlakten = BTreeMap::from([(Bodemtype::Zand, 3.0), (Bodemtype::Klei, 1.0)]);
+        voorstel.knopen[0].bodem = Some(PeilgebiedBodem::uit_aandelen("zand", &oppervlakten, "test"));
+
+        // De bodem gaat mee door JSON en bepaalt de infiltratie
+        let json = serde_json::to_string(&voorstel).unwrap();
+        let terug: TopologieVoorstel = serde_json::from_str(&json).unwrap();
+        let bodem = terug.knopen[0].bodem.as_ref().unwrap();
+        assert_eq!(bodem.bodemtype, Bodemtype::Zand);
+        let topologie = terug.naar_topologie(&instellingen).unwrap();
+        let infiltratie = topologie.peilgebieden["zand"].infiltratie;
+        assert!(infiltratie > 0.0);
+        assert_eq!(infiltratie, bodem.parameters.netto_infiltratie());
+    }
+
+
+    #[test]
+    fn test_oppervlakte_met_gat() {
+        let buiten = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let gat = vec![(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)];
+        assert!((oppervlakte(&[buiten, gat]) - 96.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,263 @@
+//! Vergelijking van twee netwerksimulaties.
+//!
+//! Zet per peilgebied de kengetallen van een basisrun en een variant naast
+//! elkaar: hoogste en laagste waterstand, pompuren, pompkosten en de duur
+//! waarin het peil boven streefpeil + marge staat. Het verschil is steeds
+//! variant min basis, zodat een negatief verschil bij kosten of
+//! overschrijding een verbetering is.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{NetwerkSimulatieResultaat, NetwerkTopologie, PeilgebiedId, StroomRichting};
+use crate::onzekerheid::KostenParameters;
+use crate::optimalisatie::calculate_pump_power_kw;
+
+/// Een grootheid in de basisrun en de variant.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Verschil {
+    pub basis: f64,
+    pub variant: f64,
+    /// Variant min basis
+    pub verschil: f64,
+}
+
+impl Verschil {
+    fn new(basis: f64, variant: f64) -> Self {
+        Self {
+            basis,
+            variant,
+            verschil: variant - basis,
+        }
+    }
+}
+
+/// Kengetallen van één peilgebied in één run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Kengetallen {
+    /// Hoogste waterstand in m NAP
+    pub max_waterstand: f64,
+    /// Laagste waterstand in m NAP
+    pub min_waterstand: f64,
+    /// Uren dat het uitstroomgemaal draait
+    pub pomp_uren: f64,
+    /// Energiekosten van het uitstroomgemaal en de gemalen die uit dit
+    /// peilgebied pompen (€)
+    pub kosten: f64,
+    /// Uren boven streefpeil + marge
+    pub overschrijding_uren: f64,
+}
+
+/// Verschillen voor één peilgebied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedVerschil {
+    pub peilgebied_id: PeilgebiedId,
+    pub max_waterstand: Verschil,
+    pub min_waterstand: Verschil,
+    pub pomp_uren: Verschil,
+    pub kosten: Verschil,
+    pub overschrijding_uren: Verschil,
+}
+
+/// Vergelijking van twee simulatieresultaten.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioVerschil {
+    /// Peilgebieden die in beide runs voorkomen, op volgorde van id
+    pub peilgebieden: Vec<PeilgebiedVerschil>,
+    /// Pompuren opgeteld over alle peilgebieden
+    pub totaal_pomp_uren: Verschil,
+    /// Kosten opgeteld over alle peilgebieden
+    pub totale_kosten: Verschil,
+    /// Uren waarin minstens één peilgebied boven de marge staat
+    pub overschrijding_uren: Verschil,
+    /// Peilgebieden die alleen in de basisrun voorkomen
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alleen_in_basis: Vec<PeilgebiedId>,
+    /// Peilgebieden die alleen in de variant voorkomen
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alleen_in_variant: Vec<PeilgebiedId>,
+}
+
+/// Vergelijk een variant met een basisrun. De topologie levert de marges
+/// en de gemaalgegevens voor de kosten; beide runs worden ermee beoordeeld.
+pub fn vergelijk(
+    topologie: &NetwerkTopologie,
+    basis: &NetwerkSimulatieResultaat,
+    variant: &NetwerkSimulatieResultaat,
+    kosten: &KostenParameters,
+) -> ScenarioVerschil {
+    let (basis_getallen, basis_overschrijding) = kengetallen(topologie, basis, kosten);
+    let (variant_getallen, variant_overschrijding) = kengetallen(topologie, variant, kosten);
+
+    let ids: BTreeSet<&PeilgebiedId> = basis_getallen
+        .keys()
+        .chain(variant_getallen.keys())
+        .collect();
+    let mut peilgebieden = Vec::new();
+    let mut alleen_in_basis = Vec::new();
+    let mut alleen_in_variant = Vec::new();
+    for id in ids {
+        let (b, v) = match (basis_getallen.get(id), variant_getallen.get(id)) {
+            (Some(b), Some(v)) => (b, v),
+            (Some(_), None) => {
+                alleen_in_basis.push(id.clone());
+                continue;
+            }
+            _ => {
+                alleen_in_variant.push(id.clone());
+                continue;
+            }
+        };
+        peilgebieden.push(PeilgebiedVerschil {
+            peilgebied_id: id.clone(),
+            max_waterstand: Verschil::new(b.max_waterstand, v.max_waterstand),
+            min_waterstand: Verschil::new(b.min_waterstand, v.min_waterstand),
+            pomp_uren: Verschil::new(b.pomp_uren, v.pomp_uren),
+            kosten: Verschil::new(b.kosten, v.kosten),
+            overschrijding_uren: Verschil::new(b.overschrijding_uren, v.overschrijding_uren),
+        });
+    }
+
+    let som = |getallen: &HashMap<PeilgebiedId, Kengetallen>, f: fn(&Kengetallen) -> f64| {
+        getallen.values().map(f).sum::<f64>()
+    };
+    ScenarioVerschil {
+        peilgebieden,
+        totaal_pomp_uren: Verschil::new(
+            som(&basis_getallen, |k| k.pomp_uren),
+            som(&variant_getallen, |k| k.pomp_uren),
+        ),
+        totale_kosten: Verschil::new(
+            som(&basis_getallen, |k| k.kosten),
+            som(&variant_getallen, |k| k.kosten),
+        ),
+        overschrijding_uren: Verschil::new(basis_overschrijding, variant_overschrijding),
+        alleen_in_basis,
+        alleen_in_variant,
+    }
+}
+
+/// Kengetallen per peilgebied en de uren waarin minstens één peilgebied
+/// boven de marge staat.
+fn kengetallen(
+    topologie: &NetwerkTopologie,
+    resultaat: &NetwerkSimulatieResultaat,
+    kosten: &KostenParameters,
+) -> (HashMap<PeilgebiedId, Kengetallen>, f64) {
+    let mut getallen: HashMap<PeilgebiedId, Kengetallen> = HashMap::new();
+    let mut overschrijding_uren = 0.0;
+
+    for stap in &resultaat.tijdstappen {
+        let uren = stap.duur / 60.0;
+        let mut overschreden = false;
+        for (id, status) in &stap.statussen {
+            let k = getallen.entry(id.clone()).or_insert(Kengetallen {
+                max_waterstand: f64::NEG_INFINITY,
+                min_waterstand: f64::INFINITY,
+                ..Default::default()
+            });
+            k.max_waterstand = k.max_waterstand.max(status.waterstand);
+            k.min_waterstand = k.min_waterstand.min(status.waterstand);
+            if status.pomp_actief {
+                k.pomp_uren += uren;
+            }
+            let vermogen = calculate_pump_power_kw(
+                status.uitstroom_debiet,
+                kosten.opvoerhoogte,
+                kosten.rendement,
+            );
+            k.kosten += vermogen * uren * kosten.prijs_per_kwh;
+            if let Some(config) = topologie.peilgebieden.get(id)
+                && status.waterstand > config.streefpeil + config.marge
+            {
+                k.overschrijding_uren += uren;
+                overschreden = true;
+            }
+        }
+
+        // Gemalen tussen peilgebieden tellen bij het peilgebied waar ze uit
+        // pompen
+        for stroom in stap.stromen.iter().filter(|s| s.actief) {
+            let Some(verbinding) = topologie.verbindingen.get(&stroom.verbinding_id) else {
+                continue;
+            };
+            let Some(vermogen) = topologie.pompvermogen_kw(stroom, stap) else {
+                continue;
+            };
+            let bron = match stroom.richting {
+                StroomRichting::Naar => &verbinding.van_id,
+                StroomRichting::Terug => &verbinding.naar_id,
+            };
+            if let Some(k) = getallen.get_mut(bron) {
+                k.kosten += vermogen * uren * kosten.prijs_per_kwh;
+            }
+        }
+
+        if overschreden {
+            overschrijding_uren += uren;
+        }
+    }
+
+    (getallen, overschrijding_uren)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{PeilgebiedConfig, SimpeleUitstroomStrategy, run_netwerksimulatie};
+
+    fn topologie(max_uitstroom_debiet: f64) -> NetwerkTopologie {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(PeilgebiedConfig {
+                id: "polder".to_string(),
+                naam: None,
+                oppervlakte: 50_000.0,
+                streefpeil: -0.60,
+                marge: 0.05,
+                maaiveld_niveau: 0.0,
+                max_uitstroom_debiet,
+                max_inlaat_debiet: 0.0,
+                verdamping: 0.0,
+                referentieverdamping: Vec::new(),
+                gewasfactor: 1.0,
+                infiltratie: 0.0,
+                kwel: None,
+                bergingscurve: None,
+                boezem: None,
+                chloride: None,
+            })
+            .unwrap();
+        topologie
+    }
+
+    #[test]
+    fn test_vergelijk() {
+        let regen = HashMap::from([("polder".to_string(), vec![15.0; 6])]);
+        let klein = topologie(0.05);
+        let groot = topologie(0.5);
+        let basis = run_netwerksimulatie(&klein, &regen, 6, &SimpeleUitstroomStrategy).unwrap();
+        let variant = run_netwerksimulatie(&groot, &regen, 6, &SimpeleUitstroomStrategy).unwrap();
+
+        let verschil = vergelijk(&klein, &basis, &variant, &KostenParameters::default());
+        assert_eq!(verschil.peilgebieden.len(), 1);
+        let polder = &verschil.peilgebieden[0];
+        // Een groter gemaal houdt het peil lager
+        assert!(polder.max_waterstand.verschil < 0.0);
+        assert!(polder.overschrijding_uren.basis > 0.0);
+        assert!(polder.overschrijding_uren.verschil < 0.0);
+        assert_eq!(
+            polder.max_waterstand.verschil,
+            polder.max_waterstand.variant - polder.max_waterstand.basis
+        );
+        assert_eq!(verschil.totale_kosten, polder.kosten);
+        assert_eq!(verschil.overschrijding_uren, polder.overschrijding_uren);
+
+        // Tegen zichzelf vergeleken is er geen verschil
+        let gelijk = vergelijk(&klein, &basis, &basis, &KostenParameters::default());
+        assert_eq!(gelijk.totale_kosten.verschil, 0.0);
+        assert_eq!(gelijk.peilgebieden[0].pomp_uren.verschil, 0.0);
+        assert!(gelijk.alleen_in_basis.is_empty() && gelijk.alleen_in_variant.is_empty());
+    }
+}
@@ -6,6 +6,8 @@
 use std::collections::HashMap;
 use std::path::Path;
 
+use serde::Serialize;
+
 use crate::netwerk::{NetwerkSimulatieResultaat, PeilgebiedId};
 
 /// Grafiek type.
@@ -742,6 +744,116 @@ impl Default for PompGrafiek {
     }
 }
 
+/// Benoemde reeks punten (x, y) voor een [`LijnGrafiek`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reeks {
+    pub naam: String,
+    pub punten: Vec<(f64, f64)>,
+}
+
+/// Lijngrafiek van willekeurige reeksen, bijvoorbeeld voor rapporten.
+pub struct LijnGrafiek {
+    opties: GrafiekOpties,
+}
+
+impl LijnGrafiek {
+    /// Maak een nieuwe lijngrafiek generator.
+    pub fn nieuw() -> Self {
+        Self {
+            opties: GrafiekOpties::default(),
+        }
+    }
+
+    /// Maak met custom opties.
+    pub fn met_opties(opties: GrafiekOpties) -> Self {
+        Self { opties }
+    }
+
+    /// Teken de reeksen in een RGB-buffer van de resolutie uit de opties
+    /// (drie bytes per pixel, rij voor rij van boven naar beneden).
+    pub fn naar_rgb(&self, reeksen: &[Reeks]) -> Result<Vec<u8>, VisualisatieFout> {
+        let punten = || reeksen.iter().flat_map(|r| r.punten.iter());
+        if punten().next().is_none() {
+            return Err(VisualisatieFout::GeenData);
+        }
+
+        use plotters::prelude::*;
+
+        let bereik = |waarden: Vec<f64>| {
+            let min = waarden.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = waarden.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            // Voeg marge toe, ook als alle waarden gelijk zijn
+            let marge = ((max - min) * 0.05).max(1e-3);
+            min - marge..max + marge
+        };
+        let x_bereik = bereik(punten().map(|p| p.0).collect());
+        let y_bereik = bereik(punten().map(|p| p.1).collect());
+
+        let resolutie = self.opties.resolutie;
+        let mut buffer = vec![0u8; resolutie.breedte as usize * resolutie.hoogte as usize * 3];
+        {
+            let backend = BitMapBackend::with_buffer(
+                &mut buffer,
+                (resolutie.breedte, resolutie.hoogte),
+            )
+            .into_drawing_area();
+            let tekenfout = |e: &dyn std::fmt::Display| VisualisatieFout::OngeldigeData {
+                details: e.to_string(),
+            };
+
+            backend.fill(&WHITE).map_err(|e| tekenfout(&e))?;
+
+            let mut chart = ChartBuilder::on(&backend)
+                .margin(10u32)
+                .caption(self.opties.titel.as_deref().unwrap_or(""), ("sans-serif", 32u32))
+                .x_label_area_size(50u32)
+                .y_label_area_size(70u32)
+                .build_cartesian_2d(x_bereik, y_bereik)
+                .map_err(|e| tekenfout(&e))?;
+
+            let mut mesh = chart.configure_mesh();
+            if !self.opties.toon_grid {
+                mesh.disable_mesh();
+            }
+            if let Some(label) = &self.opties.x_label {
+                mesh.x_desc(label);
+            }
+            if let Some(label) = &self.opties.y_label {
+                mesh.y_desc(label);
+            }
+            mesh.draw().map_err(|e| tekenfout(&e))?;
+
+            for (i, reeks) in reeksen.iter().enumerate() {
+                let kleur = self.opties.kleurenschema.kleur_op_index(i);
+                chart
+                    .draw_series(LineSeries::new(reeks.punten.iter().copied(), kleur.stroke_width(2)))
+                    .map_err(|e| tekenfout(&e))?
+                    .label(reeks.naam.clone())
+                    .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], kleur));
+            }
+
+            if self.opties.toon_legenda {
+                chart
+                    .configure_series_labels()
+                    .background_style(WHITE.mix(0.8))
+                    .border_style(BLACK)
+                    .draw()
+                    .map_err(|e| tekenfout(&e))?;
+            }
+
+            backend.present().map_err(|e| tekenfout(&e))?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl Default for LijnGrafiek {
+    fn default() -> Self {
+        Self::nieuw()
+    }
+}
+
 /// Genereer alle standaard grafieken voor een simulatieresultaat.
 pub fn genereer_alle_grafieken<P: AsRef<Path>>(
     resultaat: &NetwerkSimulatieResultaat,
@@ -0,0 +1,315 @@
+//! Nutriëntenvrachten (stikstof en fosfor) over een netwerksimulatie.
+//!
+//! De vracht is debiet maal concentratie, gesommeerd over de tijdstappen
+//! van een simulatieresultaat. Water neemt de concentratie mee van het
+//! peilgebied of de boezem waar het vandaan komt; de concentraties zijn per
+//! knooppunt vast. Per verbinding wordt de vracht per stroomrichting
+//! opgeteld, per peilgebied de aanvoer (verbindingen en inlaat) en de
+//! afvoer (verbindingen en uitslag van het gemaal).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::netwerk::{
+    NetwerkSimulatieResultaat, NetwerkTopologie, PeilgebiedId, StroomRichting, VerbindingId,
+};
+
+/// Stikstof- en fosforconcentratie van het water in mg/l.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Nutrientconcentratie {
+    pub stikstof: f64,
+    pub fosfor: f64,
+}
+
+impl Nutrientconcentratie {
+    pub fn new(stikstof: f64, fosfor: f64) -> Self {
+        Self { stikstof, fosfor }
+    }
+}
+
+/// Gesommeerd watervolume met de vracht die het meevoert.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vracht {
+    /// Volume in m³
+    pub volume: f64,
+    /// Stikstof in kg
+    pub stikstof: f64,
+    /// Fosfor in kg
+    pub fosfor: f64,
+}
+
+impl Vracht {
+    /// Tel een debiet (m³/s) gedurende `seconden` met een concentratie op.
+    fn tel_op(&mut self, debiet: f64, seconden: f64, concentratie: Nutrientconcentratie) {
+        let volume = debiet * seconden;
+        self.volume += volume;
+        // mg/l = g/m³
+        self.stikstof += volume * concentratie.stikstof / 1000.0;
+        self.fosfor += volume * concentratie.fosfor / 1000.0;
+    }
+}
+
+/// Vracht over een verbinding per stroomrichting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerbindingVracht {
+    pub verbinding_id: VerbindingId,
+    /// Van `van_id` naar `naar_id`
+    pub naar: Vracht,
+    /// Van `naar_id` terug naar `van_id`
+    pub terug: Vracht,
+}
+
+/// Aan- en afgevoerde vracht van een peilgebied of boezem.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeilgebiedVracht {
+    pub peilgebied_id: PeilgebiedId,
+    /// Via verbindingen en inlaat
+    pub aanvoer: Vracht,
+    /// Via verbindingen en het gemaal
+    pub afvoer: Vracht,
+}
+
+impl PeilgebiedVracht {
+    /// Netto stikstofvracht die in het knooppunt achterblijft (kg).
+    pub fn netto_stikstof(&self) -> f64 {
+        self.aanvoer.stikstof - self.afvoer.stikstof
+    }
+
+    /// Netto fosforvracht die in het knooppunt achterblijft (kg).
+    pub fn netto_fosfor(&self) -> f64 {
+        self.aanvoer.fosfor - self.afvoer.fosfor
+    }
+}
+
+/// Vrachten per verbinding en per peilgebied, op volgorde van id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VrachtenResultaat {
+    pub verbindingen: Vec<VerbindingVracht>,
+    pub peilgebieden: Vec<PeilgebiedVracht>,
+}
+
+/// Sommeer de stikstof- en fosforvracht over de simulatie. Knooppunten
+/// zonder concentratie voeren schoon water af.
+pub fn bereken_vrachten(
+    topologie: &NetwerkTopologie,
+    resultaat: &NetwerkSimulatieResultaat,
+    concentraties: &HashMap<PeilgebiedId, Nutrientconcentratie>,
+) -> VrachtenResultaat {
+    let concentratie = |id: &PeilgebiedId| concentraties.get(id).copied().unwrap_or_default();
+    let mut verbindingen: HashMap<&VerbindingId, VerbindingVracht> = HashMap::new();
+    let mut knooppunten: HashMap<&PeilgebiedId, PeilgebiedVracht> = topologie
+        .peilgebieden
+        .keys()
+        .chain(topologie.boezems.keys())
+        .map(|id| {
+            let vracht = PeilgebiedVracht {
+                peilgebied_id: id.clone(),
+                ..Default::default()
+            };
+            (id, vracht)
+        })
+        .collect();
+
+    for stap in &resultaat.tijdstappen {
+        let seconden = stap.duur * 60.0;
+        for stroom in stap.stromen.iter().filter(|s| s.actief) {
+            let Some(verbinding) = topologie.verbindingen.get(&stroom.verbinding_id) else {
+                continue;
+            };
+            let (bron, doel) = match stroom.richting {
+                StroomRichting::Naar => (&verbinding.van_id, &verbinding.naar_id),
+                StroomRichting::Terug => (&verbinding.naar_id, &verbinding.van_id),
+            };
+            let c = concentratie(bron);
+            let vracht = verbindingen
+                .entry(&verbinding.id)
+                .or_insert_with(|| VerbindingVracht {
+                    verbinding_id: verbinding.id.clone(),
+                    ..Default::default()
+                });
+            match stroom.richting {
+                StroomRichting::Naar => vracht.naar.tel_op(stroom.debiet, seconden, c),
+                StroomRichting::Terug => vracht.terug.tel_op(stroom.debiet, seconden, c),
+            }
+            if let Some(knooppunt) = knooppunten.get_mut(bron) {
+                knooppunt.afvoer.tel_op(stroom.debiet, seconden, c);
+            }
+            if let Some(knooppunt) = knooppunten.get_mut(doel) {
+                knooppunt.aanvoer.tel_op(stroom.debiet, seconden, c);
+            }
+        }
+
+        for (id, status) in &stap.statussen {
+            let Some(config) = topologie.peilgebieden.get(id) else {
+                continue;
+            };
+            let eigen = concentratie(id);
+            if let Some(knooppunt) = knooppunten.get_mut(id) {
+                knooppunt
+                    .afvoer
+                    .tel_op(status.uitstroom_debiet, seconden, eigen);
+            }
+            let Some(boezem) = &config.boezem else {
+                continue;
+            };
+            let boezemwater = concentratie(boezem);
+            if let Some(knooppunt) = knooppunten.get_mut(id) {
+                knooppunt
+                    .aanvoer
+                    .tel_op(status.inlaat_debiet, seconden, boezemwater);
+            }
+            if let Some(knooppunt) = knooppunten.get_mut(boezem) {
+                knooppunt
+                    .aanvoer
+                    .tel_op(status.uitstroom_debiet, seconden, eigen);
+                knooppunt
+                    .afvoer
+                    .tel_op(status.inlaat_debiet, seconden, boezemwater);
+            }
+        }
+    }
+
+    let mut verbindingen: Vec<VerbindingVracht> = verbindingen.into_values().collect();
+    verbindingen.sort_by(|a, b| a.verbinding_id.cmp(&b.verbinding_id));
+    let mut peilgebieden: Vec<PeilgebiedVracht> = knooppunten.into_values().collect();
+    peilgebieden.sort_by(|a, b| a.peilgebied_id.cmp(&b.peilgebied_id));
+    VrachtenResultaat {
+        verbindingen,
+        peilgebieden,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netwerk::{
+        Boezem, DuikerParameters, NetwerkSimulatie, PeilgebiedConfig, SimpeleUitstroomStrategy,
+        Verbinding,
+    };
+
+    fn peilgebied(id: &str, max_uitstroom_debiet: f64) -> PeilgebiedConfig {
+        PeilgebiedConfig {
+            id: id.to_string(),
+            naam: None,
+            oppervlakte: 100_000.0,
+            streefpeil: -0.60,
+            marge: 0.20,
+            maaiveld_niveau: 0.0,
+            max_uitstroom_debiet,
+            max_inlaat_debiet: 0.0,
+            verdamping: 0.0,
+            referentieverdamping: Vec::new(),
+            gewasfactor: 1.0,
+            infiltratie: 0.0,
+            kwel: None,
+            bergingscurve: None,
+            boezem: None,
+            chloride: None,
+        }
+    }
+
+    #[test]
+    fn test_vrachten() {
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie
+            .voeg_peilgebied_toe(peilgebied("polder", 0.0))
+            .unwrap();
+        topologie
+            .voeg_peilgebied_toe(peilgebied("afvoer", 0.3).met_boezem("boezem"))
+            .unwrap();
+        topologie
+            .voeg_boezem_toe(Boezem::nieuw("boezem", 0.0))
+            .unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_gemaal(
+                    "gemaal".into(),
+                    "polder".into(),
+                    "afvoer".into(),
+                    0.2,
+                    1.0,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let regen = HashMap::from([("polder".to_string(), vec![10.0; 2])]);
+        let resultaat = NetwerkSimulatie::nieuw(topologie.clone())
+            .unwrap()
+            .simuleer(&regen, 2, &SimpeleUitstroomStrategy)
+            .unwrap();
+        let concentraties = HashMap::from([
+            ("polder".to_string(), Nutrientconcentratie::new(4.0, 0.3)),
+            ("afvoer".to_string(), Nutrientconcentratie::new(2.0, 0.1)),
+        ]);
+        let vrachten = bereken_vrachten(&topologie, &resultaat, &concentraties);
+
+        // Het gemaal draait continu op volle capaciteit: 0.2 m³/s · 7200 s
+        let gemaal = &vrachten.verbindingen[0];
+        assert_eq!(gemaal.verbinding_id, "gemaal");
+        assert!((gemaal.naar.volume - 1440.0).abs() < 1e-6);
+        assert!((gemaal.naar.stikstof - 1440.0 * 4.0 / 1000.0).abs() < 1e-9);
+        assert!((gemaal.naar.fosfor - 1440.0 * 0.3 / 1000.0).abs() < 1e-9);
+        assert_eq!(gemaal.terug, Vracht::default());
+
+        let per_id: HashMap<&str, &PeilgebiedVracht> = vrachten
+            .peilgebieden
+            .iter()
+            .map(|v| (v.peilgebied_id.as_str(), v))
+            .collect();
+        assert_eq!(per_id.len(), 3);
+        assert!((per_id["polder"].afvoer.stikstof - gemaal.naar.stikstof).abs() < 1e-12);
+        assert!((per_id["afvoer"].aanvoer.stikstof - gemaal.naar.stikstof).abs() < 1e-12);
+        // Wat het afvoergemaal uitslaat, komt met de eigen concentratie in de boezem
+        let uitslag = &per_id["afvoer"].afvoer;
+        assert!(uitslag.volume > 0.0);
+        assert!((uitslag.stikstof - uitslag.volume * 2.0 / 1000.0).abs() < 1e-9);
+        assert_eq!(per_id["boezem"].aanvoer, *uitslag);
+        assert!(per_id["polder"].netto_stikstof() < 0.0);
+    }
+
+    #[test]
+    fn test_vrachten_duiker() {
+        // Een duiker tussen twee polders met peilverschil: de vracht is de
+        // concentratie maal het volume dat echt is verplaatst
+        let mut topologie = NetwerkTopologie::nieuw();
+        topologie.voeg_peilgebied_toe(peilgebied("a", 0.0)).unwrap();
+        topologie.voeg_peilgebied_toe(peilgebied("b", 0.0)).unwrap();
+        topologie
+            .voeg_verbinding_toe(
+                Verbinding::nieuw_duiker(
+                    "duiker".into(),
+                    "a".into(),
+                    "b".into(),
+                    2.0,
+                    DuikerParameters::new(0.8, 20.0),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let mut simulatie = NetwerkSimulatie::nieuw(topologie.clone())
+            .unwrap()
+            .met_tijdstap(10.0)
+            .unwrap()
+            .met_start_waterstand("a", -0.50)
+            .unwrap()
+            .met_start_waterstand("b", -0.70)
+            .unwrap();
+        let resultaat = simulatie
+            .simuleer(&HashMap::new(), 2, &SimpeleUitstroomStrategy)
+            .unwrap();
+        let concentraties =
+            HashMap::from([("a".to_string(), Nutrientconcentratie::new(4.0, 0.3))]);
+        let vrachten = bereken_vrachten(&topologie, &resultaat, &concentraties);
+
+        let verplaatst = (simulatie.waterstanden["b"] + 0.70) * 100_000.0;
+        assert!(verplaatst > 0.0);
+        let duiker = &vrachten.verbindingen[0];
+        assert!((duiker.naar.volume - verplaatst).abs() < 1e-6);
+        assert!((duiker.naar.stikstof - verplaatst * 4.0 / 1000.0).abs() < 1e-9);
+        assert!((duiker.naar.fosfor - verplaatst * 0.3 / 1000.0).abs() < 1e-9);
+        assert_eq!(duiker.terug, Vracht::default());
+    }
+}
@@ -1,4 +1,4 @@
-use peilbeheer_core::waterbalans::{SimulatieParams, SimulatieStap, WaterBalance};
+use peilbeheer_core::waterbalans::{Kwelrelatie, SimulatieParams, SimulatieStap, WaterBalance};
 
 use crate::pid::PidController;
 
@@ -8,6 +8,9 @@ pub fn mm_per_uur_to_m3_per_sec(mm_per_uur: f64, oppervlakte_m2: f64) -> f64 {
 }
 
 /// Bereken waterbalans voor één tijdstap (1 minuut).
+///
+/// Naast de vaste `infiltratie` telt de `kwel` bij de huidige waterstand
+/// mee; wegzijging is negatieve kwel en komt bij het verlies.
 pub fn calculate_water_balance(
     regen_intensiteit: f64,
     oppervlakte: f64,
@@ -15,10 +18,13 @@ pub fn calculate_water_balance(
     gemaal_debiet: f64,
     verdamping: f64,
     infiltratie: f64,
+    kwel: Option<&Kwelrelatie>,
 ) -> WaterBalance {
+    let kwel_mm_uur = kwel.map_or(0.0, |k| k.kwel_mm_uur(huidige_waterstand));
     let water_toevoer = mm_per_uur_to_m3_per_sec(regen_intensiteit, oppervlakte);
     let water_afvoer = gemaal_debiet;
-    let water_verlies = mm_per_uur_to_m3_per_sec(verdamping + infiltratie, oppervlakte);
+    let water_verlies =
+        mm_per_uur_to_m3_per_sec(verdamping + infiltratie - kwel_mm_uur, oppervlakte);
     let water_balans = water_toevoer - water_afvoer - water_verlies;
     let waterstand_verandering = (water_balans / oppervlakte) * 60.0; // m per minuut
     let nieuwe_waterstand = huidige_waterstand + waterstand_verandering;
@@ -40,7 +46,7 @@ pub fn calculate_time_series(params: &SimulatieParams) -> Vec<SimulatieStap> {
     let mut tijd = 0.0;
     let totale_duur = params.regen_duur + params.na_regen_duur;
 
-    let mut pid = PidController::new(5.0, 0.05, 20.0);
+    let mut pid = PidController::met_instellingen(&params.pid.unwrap_or_default());
 
     while tijd <= totale_duur {
         let is_regen = tijd <= params.regen_duur;
@@ -66,6 +72,7 @@ pub fn calculate_time_series(params: &SimulatieParams) -> Vec<SimulatieStap> {
             actueel_debiet,
             params.verdamping,
             params.infiltratie,
+            None,
         );
 
         tijdstappen.push(SimulatieStap {
@@ -99,18 +106,38 @@ mod tests {
 
     #[test]
     fn test_water_balance_no_rain() {
-        let result = calculate_water_balance(0.0, 100_000.0, -0.5, 0.0, 0.0, 0.0);
+        let result = calculate_water_balance(0.0, 100_000.0, -0.5, 0.0, 0.0, 0.0, None);
         assert!((result.water_toevoer).abs() < 0.0001);
         assert!((result.nieuwe_waterstand - (-0.5)).abs() < 0.0001);
     }
 
     #[test]
     fn test_water_balance_with_rain() {
-        let result = calculate_water_balance(10.0, 100_000.0, -0.5, 0.0, 0.0, 0.0);
+        let result = calculate_water_balance(10.0, 100_000.0, -0.5, 0.0, 0.0, 0.0, None);
         assert!(result.water_toevoer > 0.0);
         assert!(result.nieuwe_waterstand > -0.5);
     }
 
+    #[test]
+    fn test_water_balance_kwel() {
+        // 1 m onder de boezem bij 10 dagen weerstand: 0.1 m/dag kwel
+        let kwel = Kwelrelatie {
+            stijghoogte: 0.5,
+            weerstand_dagen: 10.0,
+        };
+        assert!((kwel.kwel_mm_uur(-0.5) - 100.0 / 24.0).abs() < 1e-12);
+
+        let result = calculate_water_balance(0.0, 100_000.0, -0.5, 0.0, 0.0, 0.0, Some(&kwel));
+        assert!(result.nieuwe_waterstand > -0.5);
+        // Kwel en verdamping heffen elkaar op
+        let result = calculate_water_balance(0.0, 100_000.0, -0.5, 0.0, 100.0 / 24.0, 0.0, Some(&kwel));
+        assert!((result.nieuwe_waterstand + 0.5).abs() < 1e-12);
+        // Boven de stijghoogte zijgt het water weg
+        let result = calculate_water_balance(0.0, 100_000.0, 1.0, 0.0, 0.0, 0.0, Some(&kwel));
+        assert!(result.water_verlies > 0.0);
+        assert!(result.nieuwe_waterstand < 1.0);
+    }
+
     #[test]
     fn test_time_series_basic() {
         let params = SimulatieParams {
@@ -126,7 +153,8 @@ mod tests {
             smart_control: false,
             streefpeil: 0.0,
             marge: 0.0,
-            maaiveld_niveau: 0.0,
+            maaiveld_niveau: None,
+            pid: None,
         };
         let result = calculate_time_series(&params);
         assert!(!result.is_empty());
@@ -150,7 +178,8 @@ mod tests {
             smart_control: false,
             streefpeil: 0.0,
             marge: 0.0,
-            maaiveld_niveau: 0.0,
+            maaiveld_niveau: None,
+            pid: None,
         };
         let with_pump = calculate_time_series(&params);
 
@@ -187,7 +216,8 @@ mod tests {
             smart_control: true,
             streefpeil: -0.5,
             marge: 5.0,
-            maaiveld_niveau: 0.0,
+            maaiveld_niveau: None,
+            pid: None,
         };
         let result = calculate_time_series(&params);
         assert!(!result.is_empty());
@@ -0,0 +1,17 @@
+-- Peilbeheer HHVR: Scenario history for DuckDB
+-- DuckDB rejects the ON DELETE CASCADE foreign key of 004, so its
+-- scenarios_history table was never created. This creates it without the
+-- foreign key, so the history of a deleted scenario stays in the audit trail.
+
+CREATE TABLE IF NOT EXISTS scenarios_history (
+    id VARCHAR PRIMARY KEY,
+    scenario_id VARCHAR NOT NULL,
+    changed_at TIMESTAMP DEFAULT NOW(),
+    changed_by VARCHAR,
+    change_type VARCHAR NOT NULL, -- created, updated, deleted, executed
+    old_values JSON,
+    new_values JSON
+);
+
+CREATE INDEX IF NOT EXISTS idx_scenarios_history_scenario_id ON scenarios_history(scenario_id);
+CREATE INDEX IF NOT EXISTS idx_scenarios_history_changed_at ON scenarios_history(changed_at);
@@ -0,0 +1,88 @@
+-- Peilbeheer HHVR: Alert tables for DuckDB
+-- DuckDB rejects the ON DELETE CASCADE foreign keys and the partial index of
+-- 007, so its alerts, alert_history and alert_notifications tables were never
+-- created. This creates them without both. Deleting a rule removes its alerts
+-- and their history in AlertService instead.
+
+CREATE TABLE IF NOT EXISTS alerts (
+    id VARCHAR PRIMARY KEY,
+    rule_id VARCHAR NOT NULL,
+    rule_name VARCHAR NOT NULL,
+
+    -- Alert classification (copied from rule at trigger time)
+    category VARCHAR NOT NULL,
+    severity VARCHAR NOT NULL,
+
+    -- Alert content
+    title VARCHAR NOT NULL,
+    message TEXT,
+
+    -- Affected resources (JSON array of IDs)
+    affected_resources JSON,
+
+    -- Alert lifecycle
+    status VARCHAR NOT NULL DEFAULT 'active', -- active, acknowledged, resolved, suppressed
+    triggered_at TIMESTAMP NOT NULL,
+    acknowledged_at TIMESTAMP,
+    acknowledged_by VARCHAR,
+    resolved_at TIMESTAMP,
+
+    -- Context data at trigger time (JSON)
+    context JSON,
+
+    -- Optional metadata
+    metadata JSON
+);
+
+CREATE INDEX IF NOT EXISTS idx_alerts_rule_id ON alerts(rule_id);
+CREATE INDEX IF NOT EXISTS idx_alerts_status ON alerts(status);
+CREATE INDEX IF NOT EXISTS idx_alerts_severity ON alerts(severity);
+CREATE INDEX IF NOT EXISTS idx_alerts_category ON alerts(category);
+CREATE INDEX IF NOT EXISTS idx_alerts_triggered_at ON alerts(triggered_at);
+CREATE INDEX IF NOT EXISTS idx_alerts_acknowledged_by ON alerts(acknowledged_by);
+CREATE INDEX IF NOT EXISTS idx_alerts_active_severity ON alerts(status, severity);
+
+CREATE TABLE IF NOT EXISTS alert_history (
+    id VARCHAR PRIMARY KEY,
+    alert_id VARCHAR NOT NULL,
+    event_type VARCHAR NOT NULL, -- triggered, acknowledged, resolved, suppressed, notification_sent
+    event_data JSON,
+
+    -- Who/what caused the event
+    caused_by VARCHAR,
+    cause_type VARCHAR, -- user, system, rule_engine
+
+    -- Timestamp
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_history_alert_id ON alert_history(alert_id);
+CREATE INDEX IF NOT EXISTS idx_alert_history_event_type ON alert_history(event_type);
+CREATE INDEX IF NOT EXISTS idx_alert_history_created_at ON alert_history(created_at);
+
+CREATE TABLE IF NOT EXISTS alert_notifications (
+    id VARCHAR PRIMARY KEY,
+    alert_id VARCHAR NOT NULL,
+
+    -- Channel details
+    channel_type VARCHAR NOT NULL, -- WebSocket, Email, Webhook, Sms
+    channel_target VARCHAR, -- email address, webhook URL, phone number
+
+    -- Delivery status
+    status VARCHAR NOT NULL DEFAULT 'pending', -- pending, sent, failed, retrying
+    sent_at TIMESTAMP,
+    error_message TEXT,
+    retry_count INTEGER DEFAULT 0,
+
+    -- Request/response details
+    request_payload JSON,
+    response_payload JSON,
+
+    -- Timestamp
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_notifications_alert_id ON alert_notifications(alert_id);
+CREATE INDEX IF NOT EXISTS idx_alert_notifications_status ON alert_notifications(status);
+CREATE INDEX IF NOT EXISTS idx_alert_notifications_channel_type ON alert_notifications(channel_type);
+CREATE INDEX IF NOT EXISTS idx_alert_notifications_created_at ON alert_notifications(created_at);
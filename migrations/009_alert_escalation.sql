@@ -0,0 +1,28 @@
+-- Peilbeheer HHVR: Alert escalation
+-- Escalation policies per rule and escalation state per alert
+
+-- Escalation policy (JSON): {"min_severity": "critical", "steps": [{"after_minutes": 15, "channels": [...]}]}
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS escalation_policy JSON;
+
+-- Escalation state per alert
+CREATE TABLE IF NOT EXISTS alert_escalations (
+    alert_id VARCHAR PRIMARY KEY,
+    rule_id VARCHAR NOT NULL,
+
+    -- Number of escalation steps that have fired
+    current_step INTEGER NOT NULL DEFAULT 0,
+
+    -- Scheduling
+    next_escalation_at TIMESTAMP,
+    last_escalated_at TIMESTAMP,
+
+    -- Set once all steps fired or the alert was acknowledged/resolved
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+
+    -- Timestamps
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_escalations_due ON alert_escalations(completed, next_escalation_at);
+CREATE INDEX IF NOT EXISTS idx_alert_escalations_rule_id ON alert_escalations(rule_id);
@@ -0,0 +1,26 @@
+-- Peilbeheer HHVR: Maintenance windows
+-- Alerts matching an active window are stored as suppressed and not notified
+
+CREATE TABLE IF NOT EXISTS maintenance_windows (
+    id VARCHAR PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    description TEXT,
+
+    -- Scope (JSON arrays, empty = all)
+    rule_ids JSON, -- ["RULE_..."]
+    resources JSON, -- ["GEMAAL_001"]
+
+    -- Schedule (JSON): {"type": "once", "start": ..., "end": ...}
+    --              or {"type": "recurring", "cron": "0 7 * * Tue", "duration_minutes": 120}
+    schedule JSON NOT NULL,
+
+    -- Enable/disable
+    enabled BOOLEAN DEFAULT TRUE,
+
+    -- Audit fields
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW(),
+    created_by VARCHAR
+);
+
+CREATE INDEX IF NOT EXISTS idx_maintenance_windows_enabled ON maintenance_windows(enabled);
@@ -0,0 +1,8 @@
+-- Peilbeheer HHVR: Alert auto-resolve
+-- Rules can resolve their open alerts once the conditions no longer hold
+
+-- Consecutive non-matching evaluations before auto-resolve (NULL = disabled)
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS auto_resolve_after INTEGER;
+
+-- Annotation on how an alert was resolved
+ALTER TABLE alerts ADD COLUMN IF NOT EXISTS resolution_note TEXT;
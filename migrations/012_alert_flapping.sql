@@ -0,0 +1,18 @@
+-- Peilbeheer HHVR: Alert flapping detection
+-- Rules that keep switching between triggered and clear are damped temporarily
+
+-- Flapping policy (JSON, NULL = disabled)
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS flapping_policy TEXT;
+
+-- Rule-level events (flapping started/ended)
+CREATE TABLE IF NOT EXISTS alert_rule_events (
+    id VARCHAR PRIMARY KEY,
+    rule_id VARCHAR NOT NULL,
+    source VARCHAR,
+    event_type VARCHAR NOT NULL, -- flapping_started, flapping_ended
+    details TEXT,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_rule_events_rule_id ON alert_rule_events(rule_id);
+CREATE INDEX IF NOT EXISTS idx_alert_rule_events_created_at ON alert_rule_events(created_at);
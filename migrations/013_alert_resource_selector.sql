@@ -0,0 +1,5 @@
+-- Peilbeheer HHVR: Alert rule resource selectors
+-- A rule can be scoped to resources, e.g. "peilgebied:PG_1*" or a list of codes
+
+-- Resource selector (JSON pattern or list of patterns, NULL = every source)
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS resource_selector TEXT;
@@ -0,0 +1,16 @@
+-- Peilbeheer HHVR: Alert comments and status audit trail
+
+-- Notes by operators on what they did about an alert
+CREATE TABLE IF NOT EXISTS alert_comments (
+    id VARCHAR PRIMARY KEY,
+    alert_id VARCHAR NOT NULL,
+    user_id VARCHAR NOT NULL,
+    comment TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alert_comments_alert_id ON alert_comments(alert_id);
+
+-- Status transition of history events (NULL for events without a transition)
+ALTER TABLE alert_history ADD COLUMN IF NOT EXISTS from_status VARCHAR;
+ALTER TABLE alert_history ADD COLUMN IF NOT EXISTS to_status VARCHAR;
@@ -0,0 +1,5 @@
+-- Peilbeheer HHVR: Expected delivery interval per time series
+-- Used by no-data (heartbeat) alert rules to detect series that stopped delivering
+
+-- Expected time between deliveries (seconds, NULL = not configured)
+ALTER TABLE timeseries_catalog ADD COLUMN IF NOT EXISTS expected_interval_seconds INTEGER;
@@ -0,0 +1,11 @@
+-- Peilbeheer HHVR: Single sign-on users
+-- Users provisioned at their first OIDC (Azure AD) login are linked by the
+-- provider's stable user id. They have no usable password
+
+-- Login provider: 'local' (username/password) or 'oidc'
+ALTER TABLE users ADD COLUMN IF NOT EXISTS auth_provider VARCHAR DEFAULT 'local';
+
+-- Stable user id at the OIDC provider (Azure AD object id)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS oidc_subject VARCHAR;
+
+CREATE INDEX IF NOT EXISTS idx_users_oidc_subject ON users(oidc_subject);
@@ -0,0 +1,24 @@
+-- Peilbeheer HHVR: API keys for machine clients
+-- FEWS sync scripts and SCADA bridges authenticate with a key in the X-API-Key header
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id VARCHAR PRIMARY KEY,
+    name VARCHAR NOT NULL,
+
+    -- First characters of the key, shown in lists
+    key_prefix VARCHAR NOT NULL,
+
+    -- SHA-256 of the full key (the key itself is never stored)
+    key_hash VARCHAR NOT NULL UNIQUE,
+
+    -- Granted permissions (JSON array)
+    permissions TEXT NOT NULL,
+
+    created_by VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    last_used_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_key_hash ON api_keys(key_hash);
@@ -0,0 +1,24 @@
+-- Peilbeheer HHVR: refresh tokens
+-- Every login starts a token family, each refresh replaces the token with a
+-- successor in the same family. Presenting a used token revokes the family.
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id VARCHAR PRIMARY KEY,
+    family_id VARCHAR NOT NULL,
+    user_id VARCHAR NOT NULL,
+
+    -- SHA-256 of the token (the token itself is never stored)
+    token_hash VARCHAR NOT NULL UNIQUE,
+
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+
+    -- Successor issued when this token was used
+    replaced_by VARCHAR
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_token_hash ON refresh_tokens(token_hash);
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family ON refresh_tokens(family_id);
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user ON refresh_tokens(user_id);
@@ -0,0 +1,9 @@
+-- Peilbeheer HHVR: resource-scoped authorization
+-- A rayonbeheerder only executes scenarios and handles alerts of their own
+-- peilgebieden and assets. An empty or missing scope means no restriction.
+
+-- Resource scope of a user (JSON: {"peilgebieden": [...], "assets": [...]})
+ALTER TABLE users ADD COLUMN IF NOT EXISTS resource_scope TEXT;
+
+-- Peilgebied codes a scenario concerns (JSON array)
+ALTER TABLE scenarios ADD COLUMN IF NOT EXISTS peilgebieden TEXT;
@@ -0,0 +1,30 @@
+-- Peilbeheer HHVR: two-factor authentication (TOTP)
+
+-- TOTP secret (base32), set at enrolment and active once confirmed
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret VARCHAR;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled BOOLEAN DEFAULT FALSE;
+
+-- Time step of the last accepted code, to reject replayed codes
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
+
+-- Single-use recovery codes for when the authenticator app is lost
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+    id VARCHAR PRIMARY KEY,
+    user_id VARCHAR NOT NULL,
+
+    -- SHA-256 of the code (the code itself is never stored)
+    code_hash VARCHAR NOT NULL,
+
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_recovery_codes_user ON user_recovery_codes(user_id);
+
+-- Authentication settings changed at runtime by an admin (JSON values)
+CREATE TABLE IF NOT EXISTS auth_settings (
+    key VARCHAR PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by VARCHAR
+);
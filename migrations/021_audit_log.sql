@@ -0,0 +1,23 @@
+-- Peilbeheer HHVR: audit log of mutating API calls
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id VARCHAR PRIMARY KEY,
+    occurred_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    -- Authenticated caller, empty for anonymous calls such as a login
+    user_id VARCHAR,
+    username VARCHAR,
+
+    method VARCHAR NOT NULL,
+    path VARCHAR NOT NULL,
+
+    -- Request body with secrets redacted, truncated
+    payload_summary TEXT,
+
+    status_code INTEGER NOT NULL,
+    success BOOLEAN NOT NULL,
+    duration_ms BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_occurred_at ON audit_log(occurred_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id);
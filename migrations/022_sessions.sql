@@ -0,0 +1,23 @@
+-- Peilbeheer HHVR: login sessions
+-- A session is a refresh token family. Access tokens carry the session ID,
+-- so revoking a session also rejects its access tokens.
+
+CREATE TABLE IF NOT EXISTS sessions (
+    -- Family ID of the refresh tokens
+    id VARCHAR PRIMARY KEY,
+    user_id VARCHAR NOT NULL,
+
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    -- Expiry of the current refresh token
+    expires_at TIMESTAMP NOT NULL,
+
+    -- Client of the last login or refresh
+    user_agent VARCHAR,
+    ip_address VARCHAR,
+
+    revoked_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
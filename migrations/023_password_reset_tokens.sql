@@ -0,0 +1,17 @@
+-- Peilbeheer HHVR: self-service password reset
+-- Tokens are e-mailed to the user, single-use and valid for a short time.
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+    id VARCHAR PRIMARY KEY,
+    user_id VARCHAR NOT NULL,
+
+    -- SHA-256 of the token (the token itself is never stored)
+    token_hash VARCHAR NOT NULL UNIQUE,
+
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_token_hash ON password_reset_tokens(token_hash);
+CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user ON password_reset_tokens(user_id);
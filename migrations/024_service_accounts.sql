@@ -0,0 +1,14 @@
+-- Peilbeheer HHVR: service accounts
+-- Integrations get an account of their own that cannot log in with a
+-- password and only authenticates with API keys, until it expires.
+
+-- Kind of account: user or service
+ALTER TABLE users ADD COLUMN IF NOT EXISTS account_type VARCHAR DEFAULT 'user';
+
+-- End of validity (required for service accounts)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP;
+
+-- Service account an API key authenticates as (NULL = standalone key)
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS service_account_id VARCHAR;
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_service_account ON api_keys(service_account_id);
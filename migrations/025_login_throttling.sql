@@ -0,0 +1,27 @@
+-- Peilbeheer HHVR: brute-force protection of logins
+-- Failed password logins are recorded and lock the account or client
+-- address for an exponentially growing period.
+
+-- Every failed login attempt
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id VARCHAR PRIMARY KEY,
+    attempted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    username VARCHAR NOT NULL,
+    ip_address VARCHAR,
+
+    -- invalid_credentials, invalid_two_factor_code, inactive or locked
+    reason VARCHAR NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_attempts_attempted_at ON login_attempts(attempted_at);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_username ON login_attempts(username);
+
+-- Consecutive failures and lockout per account (lowercase username) or address
+CREATE TABLE IF NOT EXISTS login_lockouts (
+    scope VARCHAR NOT NULL,
+    subject VARCHAR NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failure_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    PRIMARY KEY (scope, subject)
+);
@@ -0,0 +1,21 @@
+-- Peilbeheer HHVR: FEWS sync runs
+-- Every manual or scheduled sync of a peilgebied is recorded with its
+-- duration, the number of points received and stored, and its errors.
+
+CREATE TABLE IF NOT EXISTS fews_sync_runs (
+    id VARCHAR PRIMARY KEY,
+    peilgebied_id VARCHAR NOT NULL,
+
+    -- scheduled or manual
+    trigger_type VARCHAR NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    duration_ms DOUBLE NOT NULL,
+    time_series_count INTEGER NOT NULL DEFAULT 0,
+    data_points_count INTEGER NOT NULL DEFAULT 0,
+    points_written INTEGER NOT NULL DEFAULT 0,
+
+    -- JSON array of error messages, empty when the run succeeded
+    errors VARCHAR NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX IF NOT EXISTS idx_fews_sync_runs_peilgebied ON fews_sync_runs(peilgebied_id, started_at);
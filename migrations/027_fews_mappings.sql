@@ -0,0 +1,16 @@
+-- Peilbeheer HHVR: FEWS id mappings
+-- Translate FEWS location and parameter ids (e.g. H.meting) to the names
+-- the time series store uses (e.g. water_level).
+
+CREATE TABLE IF NOT EXISTS fews_mappings (
+    id VARCHAR PRIMARY KEY,
+
+    -- location or parameter
+    kind VARCHAR NOT NULL,
+    fews_id VARCHAR NOT NULL,
+    local_id VARCHAR NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (kind, fews_id)
+);
@@ -0,0 +1,24 @@
+-- Peilbeheer HHVR: D-Hydro model catalog
+-- Models available on D-Hydro, fetched periodically so the scenario UI can
+-- offer a model choice without calling D-Hydro on every request.
+
+CREATE TABLE IF NOT EXISTS dhydro_models (
+    id VARCHAR PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    description TEXT,
+    model_type VARCHAR NOT NULL,
+    version VARCHAR,
+
+    -- Period the model may be used for, open-ended when NULL
+    valid_from TIMESTAMP,
+    valid_until TIMESTAMP,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+
+    -- JSON object of model parameters
+    parameters VARCHAR NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP,
+    updated_at TIMESTAMP,
+
+    -- Refresh that last returned the model
+    fetched_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Peilbeheer HHVR: EPEX day-ahead prices
+-- Hourly prices fetched from EnergyZero. Serves as cache for the price
+-- endpoints and the optimization and as history for savings reports.
+
+CREATE TABLE IF NOT EXISTS energieprijzen (
+    -- Start of the hour (UTC)
+    uur_start TIMESTAMP PRIMARY KEY,
+    -- Price including VAT (EUR/kWh)
+    prijs_eur_kwh DOUBLE NOT NULL,
+    bron VARCHAR NOT NULL DEFAULT 'energyzero',
+    opgehaald_op TIMESTAMP NOT NULL
+);
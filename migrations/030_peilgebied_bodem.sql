@@ -0,0 +1,16 @@
+-- Peilbeheer HHVR: soil type per peilgebied
+-- Determined from the BRO Bodemkaart; supplies the default infiltration
+-- and seepage of a peilgebied in simulations.
+
+CREATE TABLE IF NOT EXISTS peilgebied_bodem (
+    peilgebied_code VARCHAR PRIMARY KEY,
+    -- Soil type with the largest share: veen, moerig, klei, zand, leem, onbekend
+    bodemtype VARCHAR NOT NULL,
+    -- Area share per soil type (JSON object, 0-1)
+    aandelen JSON,
+    -- Infiltration and seepage (mm/h)
+    infiltratie DOUBLE NOT NULL,
+    kwel DOUBLE NOT NULL,
+    bron VARCHAR NOT NULL,
+    bepaald_op TIMESTAMP NOT NULL
+);
@@ -0,0 +1,9 @@
+-- Peilbeheer HHVR: ground level per peilgebied
+-- Median ground level from the AHN (PDOK), used for drooglegging and the
+-- ground level line in charts.
+
+ALTER TABLE peilgebied ADD COLUMN IF NOT EXISTS maaiveld DOUBLE;
+
+-- Source and time of determination, e.g. "ahn:dtm_05m"
+ALTER TABLE peilgebied ADD COLUMN IF NOT EXISTS maaiveld_bron VARCHAR;
+ALTER TABLE peilgebied ADD COLUMN IF NOT EXISTS maaiveld_bepaald_op TIMESTAMP;
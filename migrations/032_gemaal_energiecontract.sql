@@ -0,0 +1,10 @@
+-- Peilbeheer HHVR: energy contract per pumping station
+-- Fixed or dynamic tariff, feed-in rule and contracted grid capacity; the
+-- optimisation prices pumping with the contract of its pumping station.
+
+CREATE TABLE IF NOT EXISTS gemaal_energiecontract (
+    gemaal_code VARCHAR PRIMARY KEY,
+    -- EnergieContract (JSON): tarief, teruglevering, netcapaciteit
+    contract JSON NOT NULL,
+    bijgewerkt_op TIMESTAMP NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Peilbeheer HHVR: realtime simulation settings
+-- Topology and options of the realtime mode, which periodically computes a
+-- water level forecast from the latest measurements.
+
+CREATE TABLE IF NOT EXISTS realtime_simulatie (
+    id VARCHAR PRIMARY KEY,
+    -- RealtimeInstelling (JSON): topologie, strategy, horizon, bronnen
+    instelling JSON NOT NULL,
+    bijgewerkt_op TIMESTAMP NOT NULL
+);
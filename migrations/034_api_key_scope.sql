@@ -0,0 +1,6 @@
+-- Peilbeheer HHVR: resource scope of API keys
+-- A key carries the scope of the user that created it, so a scoped user
+-- cannot hand out access beyond their own peilgebieden and assets.
+
+-- Resource scope of the key (JSON: {"peilgebieden": [...], "assets": [...]})
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS resource_scope TEXT;